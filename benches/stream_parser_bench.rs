@@ -0,0 +1,155 @@
+//! Performance benchmarks for StreamParser under TCP fragmentation.
+//!
+//! TCP gives no message boundaries: a `read()` may return a few bytes of a
+//! frame, a frame split anywhere, or many frames coalesced into one segment.
+//! These benchmarks feed the same stream of Henry frames to the parser using
+//! the delivery patterns seen in practice and report bytes/second, so buffer
+//! management changes can be compared across patterns.
+//!
+//! Run benchmarks with:
+//! ```sh
+//! cargo bench --bench stream_parser_bench
+//! ```
+
+use bytes::BytesMut;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use tokio_util::codec::Encoder;
+use turnkey_core::DeviceId;
+use turnkey_protocol::{CommandCode, FieldData, HenryCodec, MessageBuilder, StreamParser};
+
+/// Frames per benchmarked stream.
+const FRAMES_PER_STREAM: usize = 256;
+
+/// Framed access request as sent by a turnstile.
+fn access_request_frame() -> Vec<u8> {
+    let device_id = DeviceId::new(15).unwrap();
+    let message = MessageBuilder::new(device_id, CommandCode::AccessRequest)
+        .field(FieldData::new("12345678".to_string()).unwrap())
+        .field(FieldData::new("10/05/2025 12:46:06".to_string()).unwrap())
+        .field(FieldData::new("1".to_string()).unwrap())
+        .field(FieldData::new("0".to_string()).unwrap())
+        .build()
+        .unwrap();
+
+    let mut buffer = BytesMut::new();
+    HenryCodec::new().encode(message, &mut buffer).unwrap();
+    buffer.to_vec()
+}
+
+/// Stream of back-to-back access request frames.
+fn frame_stream() -> Vec<u8> {
+    access_request_frame().repeat(FRAMES_PER_STREAM)
+}
+
+/// Feed `chunks` to a fresh parser and return the number of frames parsed.
+fn parse_chunks<'a>(chunks: impl Iterator<Item = &'a [u8]>) -> usize {
+    let mut parser = StreamParser::new();
+    let mut frames = 0;
+    for chunk in chunks {
+        parser.feed(chunk);
+        frames += parser.drain_frames().count();
+    }
+    frames
+}
+
+/// Benchmark fixed-size chunking, from byte-by-byte to large segments.
+///
+/// Small chunks stress per-call overhead and partial frame accumulation;
+/// large chunks carry many coalesced frames per call.
+fn bench_fixed_chunks(c: &mut Criterion) {
+    let stream = frame_stream();
+    let mut group = c.benchmark_group("stream_parser_fixed_chunks");
+    group.throughput(Throughput::Bytes(stream.len() as u64));
+
+    for chunk_size in [1, 7, 64, 1460, stream.len()] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(chunk_size),
+            &chunk_size,
+            |b, &size| {
+                b.iter(|| {
+                    let frames = parse_chunks(stream.chunks(size));
+                    assert_eq!(frames, FRAMES_PER_STREAM);
+                    black_box(frames)
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Benchmark a single frame split at every possible boundary.
+///
+/// Covers the split landing right after STX, inside fields and right before
+/// ETX; each iteration parses the frame once per split position.
+fn bench_every_split(c: &mut Criterion) {
+    let frame = access_request_frame();
+    let mut group = c.benchmark_group("stream_parser_every_split");
+    group.throughput(Throughput::Bytes((frame.len() * (frame.len() - 1)) as u64));
+
+    group.bench_function("access_request", |b| {
+        b.iter(|| {
+            let mut frames = 0;
+            for split in 1..frame.len() {
+                let (head, tail) = frame.split_at(split);
+                frames += parse_chunks([head, tail].into_iter());
+            }
+            black_box(frames)
+        });
+    });
+
+    group.finish();
+}
+
+/// Benchmark coalesced batches whose boundaries drift across frames.
+///
+/// Segment sizes cycle through values that are not multiples of the frame
+/// length, so every segment both ends one or more frames and starts the
+/// next one partially, as with Nagle-coalesced traffic.
+fn bench_coalesced_batches(c: &mut Criterion) {
+    let stream = frame_stream();
+    let frame_len = access_request_frame().len();
+    let mut group = c.benchmark_group("stream_parser_coalesced");
+    group.throughput(Throughput::Bytes(stream.len() as u64));
+
+    for frames_per_segment in [2, 8, 32] {
+        let sizes: Vec<usize> = (0..5)
+            .map(|offset| frames_per_segment * frame_len + offset * 3 + 1)
+            .collect();
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}_frames", frames_per_segment)),
+            &sizes,
+            |b, sizes| {
+                b.iter(|| {
+                    let mut rest = &stream[..];
+                    let mut segments = Vec::new();
+                    for size in sizes.iter().cycle() {
+                        if rest.is_empty() {
+                            break;
+                        }
+                        let (segment, tail) = rest.split_at((*size).min(rest.len()));
+                        segments.push(segment);
+                        rest = tail;
+                    }
+
+                    let frames = parse_chunks(segments.into_iter());
+                    assert_eq!(frames, FRAMES_PER_STREAM);
+                    black_box(frames)
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_fixed_chunks,
+    bench_every_split,
+    bench_coalesced_batches,
+);
+
+criterion_main!(benches);
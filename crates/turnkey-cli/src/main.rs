@@ -1,3 +1,423 @@
-fn main() {
-    println!("Hello, world!");
+//! `turnkey-cli` - operator and bench tools for Turnkey
+//!
+//! ```text
+//! turnkey-cli sensor --released arm-rotated:entry
+//! turnkey-cli sensor door-forced tamper-open tamper-closed
+//! turnkey-cli inspect '15+REON+000+0]12345678]10/05/2025 12:46:06]1]1]'
+//! turnkey-cli self-test --watch 10
+//! turnkey-cli diagnose rfid --signal 20
+//! turnkey-cli migrate ./henry-export --database turnkey.db
+//! ```
+//!
+//! `sensor` injects sensor events into a local emulator through a mock
+//! sensor, the same path a GPIO driver takes, and prints the turnstile
+//! after each one.
+//!
+//! `inspect` parses captured messages and labels each field with its name
+//! and meaning from the protocol schema, as a table or as JSON lines.
+//!
+//! `self-test` runs the health sweep a device answers to `RT` on a local
+//! emulator with mock peripherals. With `--watch` it repeats the sweep and,
+//! under systemd, sends watchdog keepalives while it passes.
+//!
+//! `diagnose` answers an `RD` request on a local emulator: each mock
+//! peripheral runs its self-test and reports firmware and, for the RFID
+//! reader, signal quality. `--signal` sets the reader's field strength to
+//! see how a weak antenna shows up.
+//!
+//! `migrate` moves a Henry export folder (`colaborador.txt`, `cartoes.txt`,
+//! `biometria.txt`) into a Turnkey database. It first prints what would be
+//! created and updated and every broken line or reference, then asks before
+//! writing everything in one transaction. `--dry-run` stops after the
+//! report; `--yes` skips the question.
+
+mod systemd;
+
+use clap::{Parser, Subcommand};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use systemd::SystemdNotify;
+use turnkey_core::DeviceId;
+use turnkey_emulator::health::probe;
+use turnkey_emulator::{EmulatorCore, HealthThresholds, TurnstileState, Watchdog};
+use turnkey_hardware::devices::{AnyKeypadDevice, AnyRfidDevice};
+use turnkey_hardware::mock::{MockKeypad, MockRfid, MockSensor};
+use turnkey_hardware::{
+    KeypadDevice, PeripheralConfig, PeripheralManager, RfidDevice, SensorDevice, SensorEvent,
+};
+use turnkey_protocol::commands::{DiagnosticsReport, HealthCheck, SelfTestReport};
+use turnkey_protocol::{MessageParser, SchemaRegistry};
+use turnkey_storage::henry_migration::{HenryExport, MigrationPlan, PlannedRows};
+use turnkey_storage::{Database, DatabaseConfig};
+
+#[derive(Debug, Parser)]
+#[command(name = "turnkey-cli", about = "Turnkey command-line tools")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Inject sensor events into an emulated turnstile
+    Sensor {
+        /// Start with a released passage waiting for the arm rotation
+        #[arg(long)]
+        released: bool,
+
+        /// Events in order: arm-rotated[:entry|:exit], door-forced,
+        /// tamper-open, tamper-closed
+        #[arg(required = true)]
+        events: Vec<SensorEvent>,
+    },
+
+    /// Label the fields of captured protocol messages
+    Inspect {
+        /// Print one JSON object per message
+        #[arg(long)]
+        json: bool,
+
+        /// Messages, without STX/ETX framing (e.g. `15+REON+00+6]5]Acesso liberado]`)
+        #[arg(required = true)]
+        messages: Vec<String>,
+    },
+
+    /// Run the device health sweep on an emulated turnstile
+    SelfTest {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Repeat the sweep every SECS seconds, notifying systemd while healthy
+        #[arg(long, value_name = "SECS")]
+        watch: Option<u64>,
+    },
+
+    /// Run peripheral diagnostics on an emulated turnstile
+    Diagnose {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Field strength of the mock RFID reader, in percent
+        #[arg(long, value_name = "PERCENT")]
+        signal: Option<u8>,
+
+        /// Peripheral to diagnose (keypad, rfid, biometric); all when omitted
+        peripheral: Option<String>,
+    },
+
+    /// Import a Henry export folder into a Turnkey database
+    Migrate {
+        /// Folder with colaborador.txt and optionally cartoes.txt and biometria.txt
+        dir: PathBuf,
+
+        /// Database file, created if missing
+        #[arg(long, value_name = "PATH")]
+        database: String,
+
+        /// Print the pre-import report without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print the pre-import report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Import without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match args.command {
+        Command::Sensor { released, events } => simulate_sensors(released, events).await,
+        Command::Inspect { json, messages } => inspect_messages(json, &messages),
+        Command::SelfTest { json, watch } => match watch {
+            Some(secs) => watch_health(Duration::from_secs(secs)).await,
+            None => self_test(json).await,
+        },
+        Command::Diagnose {
+            json,
+            signal,
+            peripheral,
+        } => diagnose(json, signal, peripheral.as_deref()).await,
+        Command::Migrate {
+            dir,
+            database,
+            dry_run,
+            json,
+            yes,
+        } => migrate(dir, database, dry_run, json, yes).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn simulate_sensors(
+    released: bool,
+    events: Vec<SensorEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut emulator = EmulatorCore::default();
+    if released {
+        for state in [
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Granted,
+            TurnstileState::WaitingRotation,
+        ] {
+            emulator.transition_to(state)?;
+        }
+    }
+    print_emulator(&emulator);
+
+    let (mut sensor, handle) = MockSensor::new();
+    for event in events {
+        handle.inject(event).await?;
+        let event = sensor.read_event().await?;
+        println!("> {}", event);
+        for transition in emulator.handle_sensor_event(event)? {
+            println!("  {:?} -> {:?}", transition.from, transition.to);
+        }
+        print_emulator(&emulator);
+    }
+
+    let counters = emulator.counters();
+    println!(
+        "rotations={} unauthorized_rotations={} doors_forced={} tamper_alarms={}",
+        counters.rotations,
+        counters.unauthorized_rotations,
+        counters.doors_forced,
+        counters.tamper_alarms
+    );
+    Ok(())
+}
+
+fn inspect_messages(json: bool, messages: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let registry = SchemaRegistry::builtin();
+    let mut all_valid = true;
+
+    for raw in messages {
+        let annotated = registry.annotate(&MessageParser::parse(raw)?);
+        all_valid &= annotated.is_valid();
+
+        if json {
+            println!("{}", serde_json::to_string(&annotated)?);
+            continue;
+        }
+
+        println!(
+            "device={} command={:?} ({})",
+            annotated.device_id, annotated.command, annotated.code
+        );
+        for field in &annotated.fields {
+            let mut line = format!("  [{}] {} = {:?}", field.index, field.name, field.value);
+            if let Some(meaning) = &field.meaning {
+                line.push_str(&format!(" ({})", meaning));
+            }
+            if let Some(error) = &field.error {
+                line.push_str(&format!("  ! {}", error));
+            }
+            println!("{}", line);
+        }
+        for name in &annotated.missing {
+            println!("  missing {}", name);
+        }
+    }
+
+    if !all_valid {
+        return Err("some messages do not match their schema".into());
+    }
+    Ok(())
+}
+
+/// Probe the mock peripherals a bench emulator runs with
+async fn probe_peripherals(thresholds: &HealthThresholds) -> Vec<HealthCheck> {
+    let (keypad, _keypad_handle) = MockKeypad::new();
+    let (rfid, _rfid_handle) = MockRfid::new();
+    vec![
+        probe("keypad", thresholds.probe_timeout, keypad.get_info()).await,
+        probe("rfid", thresholds.probe_timeout, rfid.get_reader_info()).await,
+    ]
+}
+
+async fn self_test(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let thresholds = HealthThresholds::default();
+    let emulator = EmulatorCore::default();
+    let report = emulator.self_test(&thresholds, probe_peripherals(&thresholds).await);
+
+    if json {
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if !report.passed() {
+        return Err("self-test failed".into());
+    }
+    Ok(())
+}
+
+async fn watch_health(interval: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let notify = SystemdNotify::from_env()?;
+    notify.notify("READY=1")?;
+
+    let watchdog = Watchdog::new(notify).with_interval(interval);
+    let emulator = EmulatorCore::default();
+    let mut ticks = tokio::time::interval(watchdog.interval());
+    loop {
+        ticks.tick().await;
+        let probes = probe_peripherals(watchdog.thresholds()).await;
+        let report = watchdog.check(&emulator, probes);
+        print_report(&report);
+    }
+}
+
+async fn diagnose(
+    json: bool,
+    signal: Option<u8>,
+    peripheral: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut manager = PeripheralManager::new(PeripheralConfig::default());
+    let (keypad, _keypad_handle) = MockKeypad::new();
+    manager.register_keypad(AnyKeypadDevice::Mock(keypad));
+    let (rfid, rfid_handle) = MockRfid::new();
+    if let Some(strength) = signal {
+        rfid_handle.set_signal_strength(strength);
+    }
+    manager.register_rfid(AnyRfidDevice::Mock(rfid));
+
+    let request = DiagnosticsReport::request(DeviceId::new(1)?, peripheral)?;
+    let mut emulator = EmulatorCore::default();
+    let report = emulator.handle_diagnostics(&request, &manager.diagnostics().await)?;
+
+    if json {
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        print_diagnostics(&report);
+    }
+
+    if !report.passed() {
+        return Err("diagnostics failed".into());
+    }
+    Ok(())
+}
+
+async fn migrate(
+    dir: PathBuf,
+    database: String,
+    dry_run: bool,
+    json: bool,
+    yes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let export = HenryExport::read_dir(&dir)?;
+    let db = Database::new(DatabaseConfig::new(database)).await?;
+    let plan = export.plan(db.pool()).await?;
+
+    if json {
+        println!("{}", serde_json::to_string(&plan)?);
+    } else {
+        print_plan(&plan);
+    }
+    if !plan.is_clean() {
+        return Err(format!(
+            "{} errors in the export, nothing imported",
+            plan.errors.len()
+        )
+        .into());
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !yes && !confirm("Import into the database?")? {
+        println!("import cancelled");
+        return Ok(());
+    }
+
+    export
+        .import(db.pool(), |progress| {
+            println!("  {} {}/{}", progress.stage, progress.done, progress.total);
+        })
+        .await?;
+    println!("import complete");
+    Ok(())
+}
+
+fn print_plan(plan: &MigrationPlan) {
+    let rows = |name: &str, rows: &PlannedRows| {
+        println!("  {}: {} new, {} updated", name, rows.new, rows.updated);
+    };
+    rows("users", &plan.users);
+    rows("cards", &plan.cards);
+    rows("templates", &plan.templates);
+    for warning in &plan.warnings {
+        println!(
+            "  warning {}:{}: {}",
+            warning.file, warning.line, warning.message
+        );
+    }
+    for error in &plan.errors {
+        println!("  error {}:{}: {}", error.file, error.line, error.message);
+    }
+}
+
+fn confirm(question: &str) -> std::io::Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn print_diagnostics(report: &DiagnosticsReport) {
+    for peripheral in &report.peripherals {
+        let result = if peripheral.passed { "ok" } else { "FAIL" };
+        println!("  [{}] {}: {}", result, peripheral.name, peripheral.detail);
+        if let Some(firmware) = &peripheral.firmware {
+            println!("       firmware {}", firmware);
+        }
+        if let Some(signal) = &peripheral.signal {
+            println!(
+                "       signal {}%, {} of {} reads failed",
+                signal.strength, signal.read_errors, signal.reads
+            );
+        }
+    }
+    println!(
+        "diagnostics {}",
+        if report.passed() { "passed" } else { "failed" }
+    );
+}
+
+fn print_report(report: &SelfTestReport) {
+    for check in &report.checks {
+        let result = if check.passed { "ok" } else { "FAIL" };
+        println!("  [{}] {}: {}", result, check.name, check.detail);
+    }
+    println!(
+        "self-test {}",
+        if report.passed() { "passed" } else { "failed" }
+    );
+}
+
+fn print_emulator(emulator: &EmulatorCore) {
+    println!(
+        "  state={:?} tampered={}",
+        emulator.state(),
+        emulator.is_tampered()
+    );
+    for line in emulator.display().snapshot().buffer {
+        println!("  | {} |", line);
+    }
 }
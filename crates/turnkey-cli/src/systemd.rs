@@ -0,0 +1,62 @@
+//! systemd service notifications (`sd_notify` protocol)
+//!
+//! A unit with `Type=notify` and `WatchdogSec=` restarts the service when it
+//! stops sending `WATCHDOG=1`. systemd passes the socket to write to in
+//! `$NOTIFY_SOCKET`; outside systemd the variable is unset and notifications
+//! are skipped.
+
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use turnkey_emulator::WatchdogHook;
+use turnkey_protocol::commands::SelfTestReport;
+
+/// Notification socket of the service manager
+pub struct SystemdNotify {
+    /// Socket and its path, `None` when not run by systemd
+    target: Option<(UnixDatagram, String)>,
+}
+
+impl SystemdNotify {
+    /// Use `$NOTIFY_SOCKET` if set
+    pub fn from_env() -> io::Result<Self> {
+        let target = match std::env::var_os("NOTIFY_SOCKET") {
+            Some(path) => Some((
+                UnixDatagram::unbound()?,
+                path.to_string_lossy().into_owned(),
+            )),
+            None => None,
+        };
+        Ok(Self { target })
+    }
+
+    /// Send one `KEY=value` notification
+    pub fn notify(&self, state: &str) -> io::Result<()> {
+        let Some((socket, path)) = &self.target else {
+            return Ok(());
+        };
+        // Names starting with '@' are in the Linux abstract namespace
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.strip_prefix('@') {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            return socket.send_to_addr(state.as_bytes(), &addr).map(drop);
+        }
+        socket.send_to(state.as_bytes(), path).map(drop)
+    }
+}
+
+impl WatchdogHook for SystemdNotify {
+    fn keepalive(&self) {
+        if let Err(e) = self.notify("WATCHDOG=1") {
+            eprintln!("watchdog notification failed: {}", e);
+        }
+    }
+
+    fn unhealthy(&self, report: &SelfTestReport) {
+        let failed: Vec<_> = report.failures().map(|check| check.name.as_str()).collect();
+        let status = format!("STATUS=Self-test failed: {}", failed.join(", "));
+        if let Err(e) = self.notify(&status) {
+            eprintln!("status notification failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Errors of configuration file loading
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result of loading or validating a configuration file
+pub type ConfigFileResult<T> = Result<T, ConfigFileError>;
+
+/// Configuration file that cannot be read or used
+#[derive(Debug, Error)]
+pub enum ConfigFileError {
+    /// File could not be read
+    #[error("Cannot read {}: {source}", path.display())]
+    Io {
+        /// Path of the file
+        path: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+
+    /// Not valid TOML, or a key with the wrong type or an unknown name
+    #[error("Invalid configuration file: {0}")]
+    Parse(String),
+
+    /// Server could not be started with the configured settings
+    #[error("Cannot start the server: {0}")]
+    Server(#[from] turnkey_network::TcpServerError),
+
+    /// Well-formed value outside what the component accepts
+    #[error("Invalid {field}: {reason}")]
+    Invalid {
+        /// Dotted key of the value, e.g. `timeouts.request_ms`
+        field: &'static str,
+        /// What is wrong with it
+        reason: String,
+    },
+}
+
+impl ConfigFileError {
+    pub(crate) fn invalid(field: &'static str, reason: impl ToString) -> Self {
+        Self::Invalid {
+            field,
+            reason: reason.to_string(),
+        }
+    }
+}
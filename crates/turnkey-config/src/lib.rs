@@ -0,0 +1,791 @@
+//! Single TOML configuration file for emulated devices and the server
+//!
+//! Every component takes its own configuration struct. [`TurnkeyConfig`]
+//! describes a whole installation in one file and builds those structs:
+//! [`TcpClientConfig`], [`TcpServerConfig`] and [`CommandPolicy`] for the
+//! network,
+//! [`OperatingMode`], [`Heartbeat`] and [`HeartbeatConfig`] for validation,
+//! and [`PeripheralConfig`] for the devices. The emulator builds its
+//! runtime settings and display from the same file.
+//!
+//! ```toml
+//! device_id = 15
+//! mode = "hybrid"          # online, offline or hybrid
+//! direction = "entry"      # entry or exit
+//!
+//! [server]
+//! address = "10.0.0.5:3000"   # server the device connects to
+//! bind = "0.0.0.0:3000"       # address the server listens on
+//! max_connections = 100
+//!
+//! [server.command_policy]     # commands each device may send
+//! default_role = "Turnstile"  # Turnstile or Management
+//! roles = { 99 = "Management" }
+//! permissions = { Turnstile = ["AccessRequest", "QueryStatus"] }
+//!
+//! [timeouts]
+//! request_ms = 3000           # network I/O
+//! release_secs = 5            # grants without their own timeout
+//! denied_display_ms = 3000
+//! heartbeat_secs = 30         # 0 disables keepalives
+//! missed_heartbeats = 3
+//! reconnect_secs = 5
+//!
+//! [anti_passback]
+//! window_secs = 300           # 0 disables
+//! user_overrides = true       # per-user windows from the database win
+//!
+//! [retention]
+//! max_age_days = 365          # 0 keeps access logs forever
+//! interval_mins = 60
+//! archive_dir = "/var/lib/turnkey/archive"   # archive before deleting
+//!
+//! [display]
+//! default_message = "BEM-VINDO"
+//! columns = 40
+//!
+//! [peripherals]
+//! keypad = true
+//! rfid = true
+//! biometric = false
+//! ```
+//!
+//! Every key is optional and unknown keys are rejected, so a typo does not
+//! silently fall back to a default. Without a `mode`, a device with a
+//! server `address` validates in hybrid mode and one without validates
+//! offline.
+//!
+//! # Validation
+//!
+//! [`TurnkeyConfig::from_toml()`] and [`TurnkeyConfig::load()`] check the
+//! whole file with the rules of the components it configures: device ID
+//! range, resolvable addresses, command policy device IDs, client timeout
+//! bounds, positive heartbeat
+//! settings, display sizes, a retention interval, and an archive directory
+//! without a retention age.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_config::TurnkeyConfig;
+//! use turnkey_storage::OperatingMode;
+//!
+//! let config = TurnkeyConfig::from_toml(
+//!     r#"
+//!     device_id = 15
+//!
+//!     [server]
+//!     address = "127.0.0.1:3000"
+//!     "#,
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(config.operating_mode(), OperatingMode::Hybrid);
+//! assert_eq!(config.device_id().unwrap().as_u8(), 15);
+//! assert!(config.tcp_client_config().unwrap().is_some());
+//!
+//! assert!(TurnkeyConfig::from_toml("device_id = 100").is_err());
+//! ```
+
+pub mod error;
+
+pub use error::{ConfigFileError, ConfigFileResult};
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use turnkey_core::constants::MAX_DISPLAY_MESSAGE_LENGTH;
+use turnkey_core::{AccessDirection, DeviceId};
+use turnkey_hardware::PeripheralConfig;
+use turnkey_network::{CommandPolicy, Heartbeat, TcpClientConfig, TcpServer, TcpServerConfig};
+use turnkey_storage::models::{AntiPassbackPolicy, DEFAULT_ANTI_PASSBACK_WINDOW_SECS};
+use turnkey_storage::retention::{DEFAULT_RETENTION_INTERVAL, RetentionPolicy};
+use turnkey_storage::{HeartbeatConfig, OperatingMode};
+
+/// Default release time for grants without their own timeout, in seconds
+pub const DEFAULT_RELEASE_SECS: u64 = 5;
+
+/// Default time a denial stays on the display, in milliseconds
+pub const DEFAULT_DENIED_DISPLAY_MS: u64 = 3000;
+
+/// Default time without traffic before a keepalive, in seconds
+pub const DEFAULT_HEARTBEAT_SECS: u64 = 30;
+
+/// Default wait between reconnection attempts, in seconds
+pub const DEFAULT_RECONNECT_SECS: u64 = 5;
+
+/// Complete configuration file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TurnkeyConfig {
+    /// Device ID of the emulated turnstile (1-99)
+    pub device_id: u8,
+
+    /// Validation mode (see [`operating_mode()`](Self::operating_mode))
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<OperatingMode>,
+
+    /// Direction of the passages through the emulated turnstile
+    pub direction: Direction,
+
+    /// Server address and listener
+    pub server: ServerSection,
+
+    /// Network and turnstile timeouts
+    pub timeouts: TimeoutSection,
+
+    /// Anti-passback settings
+    pub anti_passback: AntiPassbackSection,
+
+    /// Display of the emulated turnstile
+    pub display: DisplaySection,
+
+    /// Peripherals started by the emulated turnstile
+    pub peripherals: PeripheralSection,
+
+    /// Access log retention
+    pub retention: RetentionSection,
+}
+
+impl Default for TurnkeyConfig {
+    fn default() -> Self {
+        Self {
+            device_id: 1,
+            mode: None,
+            direction: Direction::Entry,
+            server: ServerSection::default(),
+            timeouts: TimeoutSection::default(),
+            anti_passback: AntiPassbackSection::default(),
+            display: DisplaySection::default(),
+            peripherals: PeripheralSection::default(),
+            retention: RetentionSection::default(),
+        }
+    }
+}
+
+/// Passage direction of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Passages enter the controlled area
+    Entry,
+    /// Passages leave the controlled area
+    Exit,
+}
+
+impl From<Direction> for AccessDirection {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Entry => AccessDirection::Entry,
+            Direction::Exit => AccessDirection::Exit,
+        }
+    }
+}
+
+/// `[server]` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    /// Server the device connects to (`host:port`); none for a device
+    /// that only validates locally
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+
+    /// Address the server listens on
+    pub bind: String,
+
+    /// Maximum number of simultaneous device connections
+    pub max_connections: usize,
+
+    /// Commands each device may send to the server (`[server.command_policy]`)
+    pub command_policy: CommandPolicy,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        let defaults = TcpServerConfig::default();
+        Self {
+            address: None,
+            bind: defaults.bind_addr.to_string(),
+            max_connections: defaults.max_connections,
+            command_policy: CommandPolicy::default(),
+        }
+    }
+}
+
+/// `[timeouts]` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutSection {
+    /// Network I/O timeout in milliseconds
+    pub request_ms: u64,
+
+    /// Release time in seconds for grants without their own timeout
+    pub release_secs: u64,
+
+    /// Time a denial stays on the display, in milliseconds
+    pub denied_display_ms: u64,
+
+    /// Time without traffic before a keepalive, in seconds (0 disables)
+    pub heartbeat_secs: u64,
+
+    /// Unanswered keepalives before the link is down
+    pub missed_heartbeats: u32,
+
+    /// Wait between reconnection attempts, in seconds
+    pub reconnect_secs: u64,
+}
+
+impl Default for TimeoutSection {
+    fn default() -> Self {
+        Self {
+            request_ms: u64::try_from(TcpClientConfig::default().timeout.as_millis())
+                .unwrap_or(u64::MAX),
+            release_secs: DEFAULT_RELEASE_SECS,
+            denied_display_ms: DEFAULT_DENIED_DISPLAY_MS,
+            heartbeat_secs: DEFAULT_HEARTBEAT_SECS,
+            missed_heartbeats: HeartbeatConfig::default().missed_threshold,
+            reconnect_secs: DEFAULT_RECONNECT_SECS,
+        }
+    }
+}
+
+impl TimeoutSection {
+    /// Network I/O timeout
+    pub fn request(&self) -> Duration {
+        Duration::from_millis(self.request_ms)
+    }
+
+    /// Release time for grants without their own timeout
+    pub fn release(&self) -> Duration {
+        Duration::from_secs(self.release_secs)
+    }
+
+    /// Time a denial stays on the display
+    pub fn denied_display(&self) -> Duration {
+        Duration::from_millis(self.denied_display_ms)
+    }
+
+    /// Wait between reconnection attempts
+    pub fn reconnect(&self) -> Duration {
+        Duration::from_secs(self.reconnect_secs)
+    }
+}
+
+/// `[anti_passback]` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AntiPassbackSection {
+    /// Seconds during which a repeated direction is denied (0 disables)
+    pub window_secs: i64,
+
+    /// Whether users with a window of their own get it instead
+    pub user_overrides: bool,
+}
+
+impl Default for AntiPassbackSection {
+    fn default() -> Self {
+        Self {
+            window_secs: DEFAULT_ANTI_PASSBACK_WINDOW_SECS,
+            user_overrides: true,
+        }
+    }
+}
+
+/// `[display]` section; unset values keep the display's defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplaySection {
+    /// Message shown while idle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_message: Option<String>,
+
+    /// Number of lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<usize>,
+
+    /// Characters per line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<usize>,
+}
+
+/// `[peripherals]` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PeripheralSection {
+    /// Start the keypad
+    pub keypad: bool,
+
+    /// Start the RFID reader
+    pub rfid: bool,
+
+    /// Start the biometric scanner
+    pub biometric: bool,
+}
+
+impl Default for PeripheralSection {
+    fn default() -> Self {
+        let defaults = PeripheralConfig::default();
+        Self {
+            keypad: defaults.keypad_enabled,
+            rfid: defaults.rfid_enabled,
+            biometric: defaults.biometric_enabled,
+        }
+    }
+}
+
+/// `[retention]` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSection {
+    /// Days access logs are kept (0 keeps them forever)
+    pub max_age_days: u32,
+
+    /// Minutes between two pruning runs
+    pub interval_mins: u64,
+
+    /// Directory logs are archived to before they are deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<PathBuf>,
+}
+
+impl Default for RetentionSection {
+    fn default() -> Self {
+        Self {
+            max_age_days: 0,
+            interval_mins: DEFAULT_RETENTION_INTERVAL.as_secs() / 60,
+            archive_dir: None,
+        }
+    }
+}
+
+impl RetentionSection {
+    /// Time between two pruning runs
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_mins * 60)
+    }
+}
+
+impl TurnkeyConfig {
+    /// Parse and validate a configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns `Parse` for malformed TOML, wrongly typed values and unknown
+    /// keys, and `Invalid` for the first value [`validate()`](Self::validate)
+    /// rejects.
+    pub fn from_toml(toml: &str) -> ConfigFileResult<Self> {
+        let config: Self =
+            toml::from_str(toml).map_err(|e| ConfigFileError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read, parse and validate a configuration file
+    ///
+    /// # Errors
+    ///
+    /// Returns `Io` if the file cannot be read, and the errors of
+    /// [`from_toml()`](Self::from_toml).
+    pub fn load(path: impl AsRef<Path>) -> ConfigFileResult<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| ConfigFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&toml)
+    }
+
+    /// Write the configuration as TOML, defaults included
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("configuration always serializes")
+    }
+
+    /// Check every value against the component it configures
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` for the first value out of range.
+    pub fn validate(&self) -> ConfigFileResult<()> {
+        self.device_id()?;
+        self.tcp_client_config()?;
+        self.tcp_server_config()?;
+        if let Some(device_id) = self
+            .server
+            .command_policy
+            .roles
+            .keys()
+            .find(|device_id| DeviceId::new(device_id.as_u8()).is_err())
+        {
+            return Err(ConfigFileError::invalid(
+                "server.command_policy.roles",
+                format!("device ID {} is not between 1 and 99", device_id.as_u8()),
+            ));
+        }
+        self.heartbeat()?;
+        HeartbeatConfig {
+            missed_threshold: self.timeouts.missed_heartbeats,
+            ..HeartbeatConfig::default()
+        }
+        .validate()
+        .map_err(|e| ConfigFileError::invalid("timeouts.missed_heartbeats", e))?;
+
+        if self.anti_passback.window_secs < 0 {
+            return Err(ConfigFileError::invalid(
+                "anti_passback.window_secs",
+                "cannot be negative",
+            ));
+        }
+
+        if self.retention.interval_mins == 0 {
+            return Err(ConfigFileError::invalid(
+                "retention.interval_mins",
+                "must be positive",
+            ));
+        }
+        if self.retention.archive_dir.is_some() && self.retention.max_age_days == 0 {
+            return Err(ConfigFileError::invalid(
+                "retention.archive_dir",
+                "needs retention.max_age_days",
+            ));
+        }
+
+        if self.display.lines == Some(0) {
+            return Err(ConfigFileError::invalid(
+                "display.lines",
+                "must be positive",
+            ));
+        }
+        if self.display.columns == Some(0) {
+            return Err(ConfigFileError::invalid(
+                "display.columns",
+                "must be positive",
+            ));
+        }
+        if let Some(message) = &self.display.default_message {
+            let limit = self.display.columns.unwrap_or(MAX_DISPLAY_MESSAGE_LENGTH);
+            if message.chars().count() > limit {
+                return Err(ConfigFileError::invalid(
+                    "display.default_message",
+                    format!("longer than {} characters", limit),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Device ID of the emulated turnstile
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` unless `device_id` is between 1 and 99.
+    pub fn device_id(&self) -> ConfigFileResult<DeviceId> {
+        DeviceId::new(self.device_id).map_err(|e| ConfigFileError::invalid("device_id", e))
+    }
+
+    /// Validation mode, hybrid with a server address and offline without
+    /// unless set
+    pub fn operating_mode(&self) -> OperatingMode {
+        match (self.mode, &self.server.address) {
+            (Some(mode), _) => mode,
+            (None, Some(_)) => OperatingMode::Hybrid,
+            (None, None) => OperatingMode::Offline,
+        }
+    }
+
+    /// Direction of the passages through the emulated turnstile
+    pub fn access_direction(&self) -> AccessDirection {
+        self.direction.into()
+    }
+
+    /// Client settings for the server address, if one is set
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` if the address does not resolve or the request
+    /// timeout is out of range.
+    pub fn tcp_client_config(&self) -> ConfigFileResult<Option<TcpClientConfig>> {
+        let Some(address) = &self.server.address else {
+            return Ok(None);
+        };
+        TcpClientConfig::builder()
+            .server(address.as_str())
+            .timeout(self.timeouts.request())
+            .build()
+            .map(Some)
+            .map_err(|e| ConfigFileError::invalid("server.address", e))
+    }
+
+    /// Listener settings of the server
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` if the bind address does not resolve or the
+    /// connection limit is out of range.
+    pub fn tcp_server_config(&self) -> ConfigFileResult<TcpServerConfig> {
+        TcpServerConfig::builder()
+            .bind(self.server.bind.as_str())
+            .max_connections(self.server.max_connections)
+            .build()
+            .map_err(|e| ConfigFileError::invalid("server.bind", e))
+    }
+
+    /// Start the server with the listener settings and command policy of
+    /// the file
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` for listener settings out of range, and `Server`
+    /// if the address cannot be bound.
+    pub async fn tcp_server(&self) -> ConfigFileResult<TcpServer> {
+        let mut server = TcpServer::bind(self.tcp_server_config()?).await?;
+        server.set_command_policy(self.server.command_policy.clone());
+        Ok(server)
+    }
+
+    /// Keepalive schedule, unless disabled with `heartbeat_secs = 0`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` if `missed_heartbeats` is zero.
+    pub fn heartbeat(&self) -> ConfigFileResult<Option<Heartbeat>> {
+        if self.timeouts.heartbeat_secs == 0 {
+            return Ok(None);
+        }
+        Heartbeat::new(
+            Duration::from_secs(self.timeouts.heartbeat_secs),
+            self.timeouts.missed_heartbeats,
+        )
+        .map(Some)
+        .map_err(|e| ConfigFileError::invalid("timeouts.missed_heartbeats", e))
+    }
+
+    /// Thresholds of the missed-heartbeat monitor
+    pub fn heartbeat_config(&self) -> HeartbeatConfig {
+        HeartbeatConfig {
+            missed_threshold: self.timeouts.missed_heartbeats,
+            ..HeartbeatConfig::default()
+        }
+    }
+
+    /// Anti-passback window of the device
+    pub fn anti_passback_policy(&self) -> AntiPassbackPolicy {
+        AntiPassbackPolicy::new(chrono::Duration::seconds(self.anti_passback.window_secs))
+            .user_overrides(self.anti_passback.user_overrides)
+    }
+
+    /// Access log retention, unless logs are kept forever
+    pub fn retention_policy(&self) -> Option<RetentionPolicy> {
+        (self.retention.max_age_days > 0)
+            .then(|| RetentionPolicy::days(i64::from(self.retention.max_age_days)))
+    }
+
+    /// Peripherals to start
+    pub fn peripheral_config(&self) -> PeripheralConfig {
+        PeripheralConfig {
+            keypad_enabled: self.peripherals.keypad,
+            rfid_enabled: self.peripherals.rfid,
+            biometric_enabled: self.peripherals.biometric,
+            ..PeripheralConfig::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turnkey_network::DeviceRole;
+    use turnkey_protocol::CommandCode;
+
+    #[test]
+    fn test_empty_file_uses_component_defaults() {
+        let config = TurnkeyConfig::from_toml("").unwrap();
+
+        assert_eq!(config, TurnkeyConfig::default());
+        assert_eq!(config.operating_mode(), OperatingMode::Offline);
+        assert_eq!(config.retention_policy(), None);
+        let server = config.tcp_server_config().unwrap();
+        let defaults = TcpServerConfig::default();
+        assert_eq!(
+            (server.bind_addr, server.max_connections),
+            (defaults.bind_addr, defaults.max_connections)
+        );
+        assert!(config.tcp_client_config().unwrap().is_none());
+        assert_eq!(config.peripheral_config(), PeripheralConfig::default());
+        assert_eq!(
+            config.heartbeat().unwrap(),
+            Some(Heartbeat::new(Duration::from_secs(30), 3).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_full_file() {
+        let config = TurnkeyConfig::from_toml(
+            r#"
+            device_id = 15
+            mode = "online"
+            direction = "exit"
+
+            [server]
+            address = "127.0.0.1:4000"
+            bind = "127.0.0.1:4000"
+            max_connections = 8
+
+            [server.command_policy]
+            roles = { 99 = "Management" }
+            permissions = { Turnstile = ["AccessRequest"] }
+
+            [timeouts]
+            request_ms = 1500
+            release_secs = 8
+            heartbeat_secs = 0
+
+            [anti_passback]
+            window_secs = 0
+
+            [display]
+            default_message = "BEM-VINDO"
+
+            [peripherals]
+            keypad = false
+            biometric = true
+
+            [retention]
+            max_age_days = 90
+            interval_mins = 30
+            archive_dir = "/var/lib/turnkey/archive"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.device_id().unwrap(), DeviceId::new(15).unwrap());
+        assert_eq!(config.operating_mode(), OperatingMode::Online);
+        assert_eq!(config.access_direction(), AccessDirection::Exit);
+        let client = config.tcp_client_config().unwrap().unwrap();
+        assert_eq!(client.server_addr.port(), 4000);
+        assert_eq!(client.timeout, Duration::from_millis(1500));
+        assert_eq!(config.tcp_server_config().unwrap().max_connections, 8);
+        let policy = &config.server.command_policy;
+        assert_eq!(
+            policy.role_of(DeviceId::new(99).unwrap()),
+            DeviceRole::Management
+        );
+        assert!(!policy.is_allowed(DeviceId::new(15).unwrap(), CommandCode::QueryStatus));
+        assert_eq!(config.timeouts.release(), Duration::from_secs(8));
+        assert!(config.heartbeat().unwrap().is_none());
+        assert_eq!(config.anti_passback_policy().window, None);
+
+        let peripherals = config.peripheral_config();
+        assert!(!peripherals.keypad_enabled);
+        assert!(peripherals.rfid_enabled);
+        assert!(peripherals.biometric_enabled);
+
+        assert_eq!(config.retention_policy(), Some(RetentionPolicy::days(90)));
+        assert_eq!(config.retention.interval(), Duration::from_secs(1800));
+        assert_eq!(
+            config.retention.archive_dir.as_deref(),
+            Some(Path::new("/var/lib/turnkey/archive"))
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_bad_values() {
+        let parse = |toml: &str| TurnkeyConfig::from_toml(toml).unwrap_err();
+
+        assert!(matches!(parse("device = 15"), ConfigFileError::Parse(_)));
+        assert!(matches!(
+            parse("mode = \"auto\""),
+            ConfigFileError::Parse(_)
+        ));
+        assert!(matches!(
+            parse("[timeouts]\nrequest = 100"),
+            ConfigFileError::Parse(_)
+        ));
+
+        for (toml, expected) in [
+            ("device_id = 0", "device_id"),
+            ("[server]\naddress = \"10.0.0.5\"", "server.address"),
+            (
+                "[server]\naddress = \"10.0.0.5:3000\"\n[timeouts]\nrequest_ms = 1",
+                "server.address",
+            ),
+            ("[server]\nmax_connections = 0", "server.bind"),
+            (
+                "[server.command_policy]\nroles = { 0 = \"Management\" }",
+                "server.command_policy.roles",
+            ),
+            (
+                "[timeouts]\nmissed_heartbeats = 0",
+                "timeouts.missed_heartbeats",
+            ),
+            (
+                "[anti_passback]\nwindow_secs = -1",
+                "anti_passback.window_secs",
+            ),
+            (
+                "[retention]\nmax_age_days = 30\ninterval_mins = 0",
+                "retention.interval_mins",
+            ),
+            (
+                "[retention]\narchive_dir = \"archive\"",
+                "retention.archive_dir",
+            ),
+            ("[display]\ncolumns = 0", "display.columns"),
+            (
+                "[display]\ncolumns = 4\ndefault_message = \"BEM-VINDO\"",
+                "display.default_message",
+            ),
+        ] {
+            match parse(toml) {
+                ConfigFileError::Invalid { field, .. } => assert_eq!(field, expected, "{}", toml),
+                other => panic!("{}: unexpected {:?}", toml, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_through_toml() {
+        let mut config = TurnkeyConfig {
+            device_id: 42,
+            mode: Some(OperatingMode::Hybrid),
+            ..TurnkeyConfig::default()
+        };
+        config.server.address = Some("127.0.0.1:3000".to_string());
+        config.server.command_policy =
+            CommandPolicy::default().with_role(DeviceId::new(99).unwrap(), DeviceRole::Management);
+        config.display.lines = Some(4);
+
+        let toml = config.to_toml();
+        assert!(toml.contains("mode = \"hybrid\""));
+        assert_eq!(TurnkeyConfig::from_toml(&toml).unwrap(), config);
+    }
+
+    #[tokio::test]
+    async fn test_server_started_with_command_policy() {
+        let config = TurnkeyConfig::from_toml(
+            r#"
+            [server]
+            bind = "127.0.0.1:0"
+
+            [server.command_policy]
+            default_role = "Management"
+            "#,
+        )
+        .unwrap();
+
+        let server = config.tcp_server().await.unwrap();
+        assert_eq!(server.command_policy(), &config.server.command_policy);
+        assert!(
+            server
+                .command_policy()
+                .is_allowed(DeviceId::new(15).unwrap(), CommandCode::SendConfig)
+        );
+    }
+
+    #[test]
+    fn test_load_reports_missing_file() {
+        let error = TurnkeyConfig::load("/nonexistent/turnkey.toml").unwrap_err();
+        assert!(matches!(error, ConfigFileError::Io { .. }));
+        assert!(error.to_string().contains("/nonexistent/turnkey.toml"));
+    }
+}
@@ -0,0 +1,158 @@
+//! Source of the current time, replaceable in tests.
+//!
+//! Anti-passback windows, schedules, validity periods and state timeouts
+//! all depend on "now". Code that reads the time through a [`Clock`]
+//! instead of calling `Utc::now()` or `Instant::now()` directly can be
+//! tested against a [`MockClock`], which stands still until the test
+//! advances it.
+//!
+//! Components take a [`SharedClock`] and default to [`SystemClock`], so
+//! production code does not need to set anything.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use chrono::{TimeZone, Utc};
+//! use turnkey_core::clock::{Clock, MockClock, SharedClock};
+//!
+//! let start = Utc.with_ymd_and_hms(2025, 5, 10, 12, 0, 0).unwrap();
+//! let mock = Arc::new(MockClock::new(start));
+//! let clock: SharedClock = mock.clone();
+//!
+//! let entered = clock.instant();
+//! mock.advance(Duration::from_secs(90));
+//!
+//! assert_eq!(clock.now(), start + chrono::Duration::seconds(90));
+//! assert_eq!(clock.instant() - entered, Duration::from_secs(90));
+//! ```
+
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Source of wall-clock and monotonic time.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Current wall-clock time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current monotonic time, for measuring elapsed durations.
+    fn instant(&self) -> Instant;
+}
+
+/// Clock shared between the components of one device or test.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// The system clock as a [`SharedClock`].
+    #[must_use]
+    pub fn shared() -> SharedClock {
+        Arc::new(Self)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Frozen clock that only moves when told to.
+///
+/// Wall-clock and monotonic time advance together through
+/// [`advance()`](Self::advance). [`set()`](Self::set) jumps the wall clock
+/// alone, like an NTP correction, and never moves the monotonic time
+/// backwards.
+#[derive(Debug)]
+pub struct MockClock {
+    time: Mutex<MockTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MockTime {
+    now: DateTime<Utc>,
+    instant: Instant,
+}
+
+impl MockClock {
+    /// Create a clock frozen at `now`.
+    #[must_use]
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            time: Mutex::new(MockTime {
+                now,
+                instant: Instant::now(),
+            }),
+        }
+    }
+
+    /// Move both wall-clock and monotonic time forward.
+    pub fn advance(&self, by: Duration) {
+        let mut time = self.lock();
+        time.now += chrono::Duration::from_std(by).unwrap_or(chrono::Duration::MAX);
+        time.instant += by;
+    }
+
+    /// Set the wall-clock time, leaving monotonic time alone.
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.lock().now = now;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockTime> {
+        // The guarded values are plain copies, so a poisoned lock is still
+        // consistent
+        self.time.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.lock().now
+    }
+
+    fn instant(&self) -> Instant {
+        self.lock().instant
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_mock_clock_stands_still_until_advanced() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let instant = clock.instant();
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.instant(), instant);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(clock.now(), start + chrono::Duration::milliseconds(1500));
+        assert_eq!(clock.instant() - instant, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_mock_clock_set_only_moves_wall_clock() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let instant = clock.instant();
+
+        clock.set(start - chrono::Duration::hours(1));
+        assert_eq!(clock.now(), start - chrono::Duration::hours(1));
+        assert_eq!(clock.instant(), instant);
+    }
+}
@@ -349,6 +349,14 @@ pub const MIN_DEVICE_ID: u8 = 1;
 /// # Value: 99
 pub const MAX_DEVICE_ID: u8 = 99;
 
+/// Maximum length of a device name, location or tag (characters).
+///
+/// Labels are operator-facing only and never sent to devices, but they end
+/// up in log lines and reports, so they are kept to a display line.
+///
+/// # Value: 40 characters
+pub const MAX_DEVICE_LABEL_LENGTH: usize = 40;
+
 // ============================================================================
 // Display Configuration
 // ============================================================================
@@ -450,6 +458,15 @@ pub const DEFAULT_GRANT_TIMEOUT_SECONDS: u8 = 5;
 /// # Value: 0 seconds (instant return to idle)
 pub const DEFAULT_DENY_TIMEOUT_SECONDS: u8 = 0;
 
+/// Default display duration for message-only responses (seconds).
+///
+/// A message-only response (e.g. "Dirija-se a recepcao") keeps the
+/// turnstile locked but carries an instruction the user has to read, so it
+/// stays on the display longer than a denial.
+///
+/// # Value: 5 seconds
+pub const DEFAULT_DISPLAY_TIMEOUT_SECONDS: u8 = 5;
+
 // ============================================================================
 // Default Display Messages (Portuguese)
 // ============================================================================
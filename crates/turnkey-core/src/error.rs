@@ -19,6 +19,9 @@ pub enum Error {
     #[error("Missing required field: {0}")]
     MissingField(String),
 
+    #[error("Invalid message signature: {reason}")]
+    InvalidSignature { reason: String },
+
     // Hardware errors
     #[error("Device not found: {device_type} at {location}")]
     DeviceNotFound {
@@ -93,6 +96,10 @@ pub enum Error {
 
     #[error("Missing configuration key: {0}")]
     MissingConfig(String),
+
+    // Secret errors
+    #[error("Secret store error: {0}")]
+    Secret(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,477 @@
+//! Latency budget of the access flow, from card tap to display update.
+//!
+//! A passage goes through four stages before the user sees the decision:
+//! the reader delivers the card, the validator decides, the decision is
+//! logged, and the display shows it. Offline, the whole path must stay under
+//! [`DEFAULT_LATENCY_BUDGET`].
+//!
+//! [`StageTimer`] measures the stages of one tap, [`LatencyTracker`] keeps
+//! the recent timings of every device, and [`LatencyReport`] breaks them
+//! down per device and stage.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use turnkey_core::DeviceId;
+//! use turnkey_core::latency::{LatencyStage, LatencyTracker, StageTimings};
+//!
+//! let mut timings = StageTimings::default();
+//! timings.set(LatencyStage::Read, Duration::from_millis(20));
+//! timings.set(LatencyStage::Validate, Duration::from_millis(120));
+//! timings.set(LatencyStage::Log, Duration::from_millis(40));
+//! timings.set(LatencyStage::Display, Duration::from_millis(5));
+//! assert_eq!(timings.total(), Duration::from_millis(185));
+//!
+//! let mut tracker = LatencyTracker::default();
+//! let device = DeviceId::new(15).unwrap();
+//! assert!(tracker.record(device, timings));
+//!
+//! let report = tracker.report();
+//! assert!(report.within_budget());
+//! assert_eq!(report.devices[0].taps, 1);
+//! ```
+
+use crate::DeviceId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Longest acceptable time from card tap to display update, offline
+pub const DEFAULT_LATENCY_BUDGET: Duration = Duration::from_millis(500);
+
+/// Taps kept per device for the percentiles of a report
+pub const DEFAULT_LATENCY_WINDOW: usize = 4096;
+
+/// Stage of the access flow between a card tap and the display update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyStage {
+    /// Reader delivering the card
+    Read,
+
+    /// Validator reaching a decision
+    Validate,
+
+    /// Decision written to the access log
+    Log,
+
+    /// Display showing the decision
+    Display,
+}
+
+impl LatencyStage {
+    /// Every stage, in flow order.
+    pub const ALL: [LatencyStage; 4] = [
+        LatencyStage::Read,
+        LatencyStage::Validate,
+        LatencyStage::Log,
+        LatencyStage::Display,
+    ];
+
+    /// Lowercase name of the stage.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Validate => "validate",
+            Self::Log => "log",
+            Self::Display => "display",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for LatencyStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Time spent in each stage by one tap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageTimings {
+    stages: [Duration; 4],
+}
+
+impl StageTimings {
+    /// Time spent in a stage.
+    pub fn get(&self, stage: LatencyStage) -> Duration {
+        self.stages[stage.index()]
+    }
+
+    /// Set the time spent in a stage.
+    pub fn set(&mut self, stage: LatencyStage, duration: Duration) {
+        self.stages[stage.index()] = duration;
+    }
+
+    /// Add time to a stage.
+    pub fn add(&mut self, stage: LatencyStage, duration: Duration) {
+        self.stages[stage.index()] += duration;
+    }
+
+    /// Move time measured as part of one stage to another.
+    ///
+    /// Used when a stage runs inside another, like the log write inside the
+    /// validator call. At most the time of `from` is moved.
+    pub fn reassign(&mut self, from: LatencyStage, to: LatencyStage, duration: Duration) {
+        let moved = duration.min(self.get(from));
+        self.stages[from.index()] -= moved;
+        self.stages[to.index()] += moved;
+    }
+
+    /// Time from tap to display update.
+    pub fn total(&self) -> Duration {
+        self.stages.iter().sum()
+    }
+
+    /// Stage that took the longest.
+    pub fn slowest(&self) -> LatencyStage {
+        LatencyStage::ALL
+            .into_iter()
+            .max_by_key(|stage| self.get(*stage))
+            .unwrap_or(LatencyStage::Read)
+    }
+}
+
+/// Measures the stages of one tap one after the other.
+///
+/// ```
+/// use turnkey_core::latency::{LatencyStage, StageTimer};
+///
+/// let mut timer = StageTimer::start();
+/// // ... read the card
+/// timer.lap(LatencyStage::Read);
+/// // ... validate, log and display
+/// timer.lap(LatencyStage::Validate);
+/// timer.lap(LatencyStage::Display);
+/// let timings = timer.finish();
+/// assert!(timings.total() < std::time::Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StageTimer {
+    last: Instant,
+    timings: StageTimings,
+}
+
+impl StageTimer {
+    /// Start timing at the card tap.
+    pub fn start() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    /// Start timing at a tap that happened at `tap`.
+    pub fn started_at(tap: Instant) -> Self {
+        Self {
+            last: tap,
+            timings: StageTimings::default(),
+        }
+    }
+
+    /// Charge the time since the previous lap to `stage`.
+    pub fn lap(&mut self, stage: LatencyStage) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.last);
+        self.last = now;
+        self.timings.add(stage, elapsed);
+        elapsed
+    }
+
+    /// Timings measured so far.
+    pub fn timings(&self) -> &StageTimings {
+        &self.timings
+    }
+
+    /// Timings measured so far, for adjustment with
+    /// [`StageTimings::reassign`].
+    pub fn timings_mut(&mut self) -> &mut StageTimings {
+        &mut self.timings
+    }
+
+    /// Stop timing.
+    pub fn finish(self) -> StageTimings {
+        self.timings
+    }
+}
+
+/// Distribution of a set of durations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Median
+    pub p50: Duration,
+
+    /// 95th percentile
+    pub p95: Duration,
+
+    /// 99th percentile
+    pub p99: Duration,
+
+    /// Slowest sample
+    pub max: Duration,
+
+    /// Arithmetic mean
+    pub mean: Duration,
+}
+
+impl LatencyStats {
+    /// Compute the distribution of `samples` (nearest-rank percentiles).
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let percentile = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+        let sum: Duration = samples.iter().sum();
+        Self {
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: samples[samples.len() - 1],
+            mean: sum / samples.len() as u32,
+        }
+    }
+}
+
+/// Latency breakdown of one device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceLatency {
+    /// Device the taps were made on
+    pub device_id: DeviceId,
+
+    /// Taps recorded
+    pub taps: u64,
+
+    /// Taps whose total exceeded the budget
+    pub over_budget: u64,
+
+    /// Tap to display, over the recent taps
+    pub total: LatencyStats,
+
+    /// Each stage, over the recent taps
+    pub stages: BTreeMap<LatencyStage, LatencyStats>,
+}
+
+/// Latency breakdown of every device, ordered by device ID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyReport {
+    /// Budget the taps were checked against
+    pub budget: Duration,
+
+    /// One entry per device with recorded taps
+    pub devices: Vec<DeviceLatency>,
+}
+
+impl LatencyReport {
+    /// Whether every recorded tap stayed within the budget.
+    pub fn within_budget(&self) -> bool {
+        self.devices.iter().all(|device| device.over_budget == 0)
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "budget {:?}", self.budget)?;
+        for device in &self.devices {
+            writeln!(
+                f,
+                "device {}: {} taps, {} over budget",
+                device.device_id, device.taps, device.over_budget
+            )?;
+            let rows = device
+                .stages
+                .iter()
+                .map(|(stage, stats)| (stage.as_str(), stats))
+                .chain([("total", &device.total)]);
+            for (name, stats) in rows {
+                writeln!(
+                    f,
+                    "  {:<8} p50 {:>10.3?}  p95 {:>10.3?}  p99 {:>10.3?}  max {:>10.3?}",
+                    name, stats.p50, stats.p95, stats.p99, stats.max
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Recent timings of one device
+#[derive(Debug, Clone, Default)]
+struct DeviceSamples {
+    recent: VecDeque<StageTimings>,
+    taps: u64,
+    over_budget: u64,
+}
+
+/// Collects tap timings per device and checks them against a budget.
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    budget: Duration,
+    window: usize,
+    devices: HashMap<DeviceId, DeviceSamples>,
+}
+
+impl LatencyTracker {
+    /// Create a tracker checking taps against `budget`.
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            window: DEFAULT_LATENCY_WINDOW,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Keep the last `window` taps of each device for percentiles (minimum 1).
+    ///
+    /// Budget overruns are counted over every tap regardless.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Budget taps are checked against.
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Record one tap; returns whether it stayed within the budget.
+    pub fn record(&mut self, device_id: DeviceId, timings: StageTimings) -> bool {
+        let within = timings.total() <= self.budget;
+        let samples = self.devices.entry(device_id).or_default();
+        samples.taps += 1;
+        if !within {
+            samples.over_budget += 1;
+        }
+        if samples.recent.len() == self.window {
+            samples.recent.pop_front();
+        }
+        samples.recent.push_back(timings);
+        within
+    }
+
+    /// Break down the recorded taps per device and stage.
+    pub fn report(&self) -> LatencyReport {
+        let mut devices: Vec<DeviceLatency> = self
+            .devices
+            .iter()
+            .map(|(device_id, samples)| {
+                let collect = |f: &dyn Fn(&StageTimings) -> Duration| {
+                    LatencyStats::from_samples(samples.recent.iter().map(f).collect())
+                };
+                DeviceLatency {
+                    device_id: *device_id,
+                    taps: samples.taps,
+                    over_budget: samples.over_budget,
+                    total: collect(&|timings| timings.total()),
+                    stages: LatencyStage::ALL
+                        .into_iter()
+                        .map(|stage| (stage, collect(&|timings| timings.get(stage))))
+                        .collect(),
+                }
+            })
+            .collect();
+        devices.sort_by_key(|device| device.device_id.as_u8());
+
+        LatencyReport {
+            budget: self.budget,
+            devices,
+        }
+    }
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn timings(read: u64, validate: u64, log: u64, display: u64) -> StageTimings {
+        let mut timings = StageTimings::default();
+        timings.set(LatencyStage::Read, ms(read));
+        timings.set(LatencyStage::Validate, ms(validate));
+        timings.set(LatencyStage::Log, ms(log));
+        timings.set(LatencyStage::Display, ms(display));
+        timings
+    }
+
+    #[test]
+    fn test_reassign_moves_nested_stage_time() {
+        let mut t = timings(10, 100, 0, 5);
+        t.reassign(LatencyStage::Validate, LatencyStage::Log, ms(30));
+        assert_eq!(t.get(LatencyStage::Validate), ms(70));
+        assert_eq!(t.get(LatencyStage::Log), ms(30));
+        assert_eq!(t.total(), ms(115));
+
+        // Never more than the source stage holds
+        t.reassign(LatencyStage::Read, LatencyStage::Log, ms(50));
+        assert_eq!(t.get(LatencyStage::Read), Duration::ZERO);
+        assert_eq!(t.get(LatencyStage::Log), ms(40));
+        assert_eq!(t.slowest(), LatencyStage::Validate);
+    }
+
+    #[test]
+    fn test_stats_percentiles() {
+        let samples = (1..=100).map(ms).collect();
+        let stats = LatencyStats::from_samples(samples);
+        assert_eq!(stats.p50, ms(50));
+        assert_eq!(stats.p95, ms(95));
+        assert_eq!(stats.p99, ms(99));
+        assert_eq!(stats.max, ms(100));
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+
+        assert_eq!(LatencyStats::from_samples(vec![ms(7)]).p50, ms(7));
+        assert_eq!(
+            LatencyStats::from_samples(Vec::new()),
+            LatencyStats::default()
+        );
+    }
+
+    #[test]
+    fn test_tracker_counts_overruns_per_device() {
+        let mut tracker = LatencyTracker::new(ms(500)).with_window(2);
+        let first = DeviceId::new(15).unwrap();
+        let second = DeviceId::new(3).unwrap();
+
+        assert!(tracker.record(first, timings(10, 400, 50, 40)));
+        assert!(!tracker.record(first, timings(10, 450, 50, 40)));
+        assert!(tracker.record(first, timings(10, 100, 20, 5)));
+        assert!(tracker.record(second, timings(5, 50, 10, 5)));
+
+        let report = tracker.report();
+        assert!(!report.within_budget());
+        assert_eq!(report.devices[0].device_id, second);
+        assert_eq!(report.devices[0].over_budget, 0);
+
+        let device = &report.devices[1];
+        assert_eq!(device.taps, 3);
+        assert_eq!(device.over_budget, 1);
+        // Only the last two taps are kept for percentiles
+        assert_eq!(device.total.max, ms(550));
+        assert_eq!(device.stages[&LatencyStage::Validate].p50, ms(100));
+        assert!(
+            report
+                .to_string()
+                .contains("device 15: 3 taps, 1 over budget")
+        );
+    }
+
+    #[test]
+    fn test_timer_charges_laps() {
+        let mut timer = StageTimer::started_at(Instant::now() - ms(20));
+        assert!(timer.lap(LatencyStage::Read) >= ms(20));
+        timer.lap(LatencyStage::Display);
+        let timings = timer.finish();
+        assert!(timings.get(LatencyStage::Read) >= ms(20));
+        assert_eq!(timings.get(LatencyStage::Validate), Duration::ZERO);
+    }
+}
@@ -1,7 +1,11 @@
+pub mod clock;
 pub mod constants;
 pub mod error;
+pub mod latency;
+pub mod secrets;
 pub mod types;
 
+pub use clock::{Clock, MockClock, SharedClock, SystemClock};
 pub use error::{Error, Result};
 pub use types::*;
 
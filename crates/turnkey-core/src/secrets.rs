@@ -0,0 +1,515 @@
+//! Secret storage outside of configuration files.
+//!
+//! Device HMAC keys, the TLS server key and the keypad code digest key
+//! should not sit in plain config files or databases next to everything
+//! else. A [`SecretProvider`] looks secrets up by
+//! name from one of several backends:
+//!
+//! - [`EnvSecrets`] - environment variables, read-only (containers, systemd
+//!   `LoadCredential=` wrappers)
+//! - [`FileSecrets`] - one file per secret under a private directory
+//! - [`KeyringSecrets`] - the OS keyring (feature `keyring`)
+//!
+//! # Names
+//!
+//! Secret names are lowercase paths such as `hmac/device-15`, built with
+//! the helpers in [`names`] so every consumer agrees on them. Each backend
+//! maps them to its own key format.
+//!
+//! # Rotation
+//!
+//! [`SecretProvider::rotate()`] stores the new value and keeps the old one
+//! under `<name>.previous`, so a verifier can accept both while devices are
+//! reconfigured. A later rotation overwrites the previous value.
+//!
+//! # Key Material in Memory
+//!
+//! Values are returned as [`SecretBytes`], which zeroes its buffer on drop
+//! and never prints its contents.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_core::DeviceId;
+//! use turnkey_core::secrets::{FileSecrets, SecretBytes, SecretProvider, names};
+//!
+//! # fn main() -> turnkey_core::Result<()> {
+//! # let dir = std::env::temp_dir().join(format!("turnkey-secrets-doc-{}", std::process::id()));
+//! let secrets = FileSecrets::new(&dir);
+//! let name = names::device_signing_key(DeviceId::new(15)?);
+//!
+//! secrets.put(&name, &SecretBytes::from("first-shared-secret"))?;
+//! secrets.rotate(&name, SecretBytes::from("second-shared-secret"))?;
+//!
+//! assert_eq!(secrets.require(&name)?.expose_secret(), b"second-shared-secret");
+//! assert!(secrets.previous(&name)?.is_some());
+//! # std::fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Error, Result};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Suffix of the name holding the value replaced by the last rotation
+pub const PREVIOUS_SUFFIX: &str = ".previous";
+
+/// Names of the secrets used by Turnkey components
+pub mod names {
+    use crate::DeviceId;
+
+    /// PEM private key of the TLS server certificate
+    pub const TLS_SERVER_KEY: &str = "tls/server-key";
+
+    /// HMAC key of the keypad code digests of a site database
+    pub const PIN_DIGEST_KEY: &str = "pin/digest-key";
+
+    /// HMAC key shared with a device for message signing
+    pub fn device_signing_key(device_id: DeviceId) -> String {
+        format!("hmac/device-{:02}", device_id.as_u8())
+    }
+}
+
+/// Key material that is zeroed when dropped
+///
+/// `Debug` only shows the length.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretBytes(Zeroizing<Vec<u8>>);
+
+impl SecretBytes {
+    /// Wrap key material
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        Self(Zeroizing::new(bytes.into()))
+    }
+
+    /// Borrow the key material
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the secret is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {} bytes])", self.len())
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<&str> for SecretBytes {
+    fn from(value: &str) -> Self {
+        Self::new(value.as_bytes())
+    }
+}
+
+/// Source of secrets looked up by name
+pub trait SecretProvider: Send + Sync {
+    /// Short backend name for logs
+    fn backend(&self) -> &'static str;
+
+    /// Fetch a secret, `None` if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Secret` for an invalid name or an unreadable store.
+    fn get(&self, name: &str) -> Result<Option<SecretBytes>>;
+
+    /// Store a secret, replacing any existing value
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Secret` if the backend is read-only or the write
+    /// fails.
+    fn put(&self, name: &str, value: &SecretBytes) -> Result<()> {
+        let _ = value;
+        Err(read_only(self.backend(), name))
+    }
+
+    /// Remove a secret, returning `false` if it did not exist
+    ///
+    /// # Errors
+    ///
+    /// Same as [`put()`](Self::put).
+    fn delete(&self, name: &str) -> Result<bool> {
+        Err(read_only(self.backend(), name))
+    }
+
+    /// Fetch a secret that must exist
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingConfig` naming the secret if it is absent.
+    fn require(&self, name: &str) -> Result<SecretBytes> {
+        self.get(name)?
+            .ok_or_else(|| Error::MissingConfig(format!("secret {} ({})", name, self.backend())))
+    }
+
+    /// Replace a secret, keeping the current value as its previous one
+    ///
+    /// Returns the value that was replaced.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`put()`](Self::put).
+    fn rotate(&self, name: &str, value: SecretBytes) -> Result<Option<SecretBytes>> {
+        let current = self.get(name)?;
+        if let Some(current) = &current {
+            self.put(&previous_name(name), current)?;
+        }
+        self.put(name, &value)?;
+        Ok(current)
+    }
+
+    /// Value replaced by the last rotation of a secret
+    ///
+    /// # Errors
+    ///
+    /// Same as [`get()`](Self::get).
+    fn previous(&self, name: &str) -> Result<Option<SecretBytes>> {
+        self.get(&previous_name(name))
+    }
+}
+
+fn previous_name(name: &str) -> String {
+    format!("{}{}", name, PREVIOUS_SUFFIX)
+}
+
+fn read_only(backend: &str, name: &str) -> Error {
+    Error::Secret(format!(
+        "cannot write {}: {} secrets are read-only",
+        name, backend
+    ))
+}
+
+/// Check that a name is a relative path of lowercase segments
+///
+/// Rejecting `..`, absolute paths and other characters keeps names usable
+/// as file paths and environment variable names alike.
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.split('/').all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('.')
+                && segment.bytes().all(|b| {
+                    b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'-' | b'_' | b'.')
+                })
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Secret(format!("invalid secret name: {:?}", name)))
+    }
+}
+
+/// Secrets read from environment variables
+///
+/// The name is upper-cased with `/`, `.` and `-` turned into `_` and the
+/// prefix prepended: `hmac/device-15` becomes
+/// `TURNKEY_SECRET_HMAC_DEVICE_15` with the default prefix. Values are
+/// taken as UTF-8 bytes.
+#[derive(Debug, Clone)]
+pub struct EnvSecrets {
+    prefix: String,
+}
+
+impl Default for EnvSecrets {
+    fn default() -> Self {
+        Self::new("TURNKEY_SECRET_")
+    }
+}
+
+impl EnvSecrets {
+    /// Read variables starting with `prefix`
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Environment variable holding a secret
+    pub fn variable(&self, name: &str) -> String {
+        let suffix: String = name
+            .chars()
+            .map(|c| match c {
+                '/' | '.' | '-' => '_',
+                c => c.to_ascii_uppercase(),
+            })
+            .collect();
+        format!("{}{}", self.prefix, suffix)
+    }
+}
+
+impl SecretProvider for EnvSecrets {
+    fn backend(&self) -> &'static str {
+        "env"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<SecretBytes>> {
+        validate_name(name)?;
+        match std::env::var_os(self.variable(name)) {
+            Some(value) => value
+                .into_string()
+                .map(|value| Some(SecretBytes::new(value)))
+                .map_err(|_| Error::Secret(format!("{} is not valid UTF-8", self.variable(name)))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Secrets stored as files under a directory
+///
+/// Each name is a path relative to the directory, and the file content is
+/// the secret as-is (no trailing newline is stripped). Files written here
+/// are created with mode `0600` on Unix and replaced atomically.
+#[derive(Debug, Clone)]
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    /// Store secrets under `dir`, created on first write
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// File holding a secret
+    pub fn path(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(self.dir.join(name))
+    }
+}
+
+impl SecretProvider for FileSecrets {
+    fn backend(&self) -> &'static str {
+        "file"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<SecretBytes>> {
+        match fs::read(self.path(name)?) {
+            Ok(bytes) => Ok(Some(SecretBytes::new(bytes))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, name: &str, value: &SecretBytes) -> Result<()> {
+        let path = self.path(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let tmp = path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&tmp)?;
+        file.write_all(value.expose_secret())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        match fs::remove_file(self.path(name)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Secrets kept in the OS keyring (feature `keyring`)
+///
+/// Entries are stored under a service name (`turnkey` by default) with the
+/// secret name as the account: the kernel keyring on Linux, the Keychain
+/// on macOS and the Credential Manager on Windows.
+#[cfg(feature = "keyring")]
+#[derive(Debug, Clone)]
+pub struct KeyringSecrets {
+    service: String,
+}
+
+#[cfg(feature = "keyring")]
+impl Default for KeyringSecrets {
+    fn default() -> Self {
+        Self::new("turnkey")
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringSecrets {
+    /// Use entries of the given keyring service
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry> {
+        validate_name(name)?;
+        keyring::Entry::new(&self.service, name).map_err(|e| keyring_error(name, e))
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_error(name: &str, error: keyring::Error) -> Error {
+    Error::Secret(format!("keyring entry {}: {}", name, error))
+}
+
+#[cfg(feature = "keyring")]
+impl SecretProvider for KeyringSecrets {
+    fn backend(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<SecretBytes>> {
+        match self.entry(name)?.get_secret() {
+            Ok(bytes) => Ok(Some(SecretBytes::new(bytes))),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(name, e)),
+        }
+    }
+
+    fn put(&self, name: &str, value: &SecretBytes) -> Result<()> {
+        self.entry(name)?
+            .set_secret(value.expose_secret())
+            .map_err(|e| keyring_error(name, e))
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(keyring_error(name, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DeviceId;
+
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("turnkey-secrets-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_secret_bytes_debug_is_redacted() {
+        let secret = SecretBytes::from("shared-secret");
+        let debug = format!("{:?}", secret);
+        assert!(!debug.contains("shared"));
+        assert!(debug.contains("13 bytes"));
+    }
+
+    #[test]
+    fn test_name_validation() {
+        assert!(validate_name("hmac/device-15").is_ok());
+        assert!(validate_name("tls/server-key.previous").is_ok());
+        for name in ["", "/etc/passwd", "hmac/../x", "hmac//x", "Hmac", "a b"] {
+            assert!(validate_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_env_variable_mapping() {
+        let env = EnvSecrets::default();
+        let name = names::device_signing_key(DeviceId::new(5).unwrap());
+        assert_eq!(env.variable(&name), "TURNKEY_SECRET_HMAC_DEVICE_05");
+        assert_eq!(
+            env.variable(&previous_name(names::TLS_SERVER_KEY)),
+            "TURNKEY_SECRET_TLS_SERVER_KEY_PREVIOUS"
+        );
+        assert!(env.put(&name, &SecretBytes::from("x")).is_err());
+    }
+
+    #[test]
+    fn test_file_secrets_roundtrip() {
+        let dir = temp_dir("roundtrip");
+        let secrets = FileSecrets::new(&dir);
+
+        assert!(secrets.get("hmac/device-15").unwrap().is_none());
+        secrets
+            .put("hmac/device-15", &SecretBytes::new(vec![0, 1, 2, 255]))
+            .unwrap();
+        assert_eq!(
+            secrets.require("hmac/device-15").unwrap().expose_secret(),
+            &[0, 1, 2, 255]
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("hmac/device-15"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(secrets.delete("hmac/device-15").unwrap());
+        assert!(!secrets.delete("hmac/device-15").unwrap());
+        assert!(matches!(
+            secrets.require("hmac/device-15"),
+            Err(Error::MissingConfig(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_value() {
+        let dir = temp_dir("rotation");
+        let secrets = FileSecrets::new(&dir);
+
+        assert_eq!(
+            secrets
+                .rotate("tls/server-key", SecretBytes::from("v1"))
+                .unwrap(),
+            None
+        );
+        assert_eq!(secrets.previous("tls/server-key").unwrap(), None);
+
+        let replaced = secrets
+            .rotate("tls/server-key", SecretBytes::from("v2"))
+            .unwrap();
+        assert_eq!(replaced, Some(SecretBytes::from("v1")));
+        assert_eq!(
+            secrets.get("tls/server-key").unwrap(),
+            Some(SecretBytes::from("v2"))
+        );
+        assert_eq!(
+            secrets.previous("tls/server-key").unwrap(),
+            Some(SecretBytes::from("v1"))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
     Result,
-    constants::{MAX_CARD_LENGTH, MAX_DEVICE_ID, MIN_CARD_LENGTH, MIN_DEVICE_ID},
+    clock::Clock,
+    constants::{
+        MAX_CARD_LENGTH, MAX_DEVICE_ID, MAX_DEVICE_LABEL_LENGTH, MIN_CARD_LENGTH, MIN_DEVICE_ID,
+    },
     error::Error,
 };
 use chrono::{DateTime, Local, TimeZone};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 use subtle::ConstantTimeEq;
 
 /// Device identifier (2 digits, zero-padded)
@@ -56,6 +60,153 @@ impl std::str::FromStr for DeviceId {
     }
 }
 
+/// Friendly identity of a device: name, location and tags
+///
+/// Labels are for people reading logs and reports; the protocol only ever
+/// carries the numeric [`DeviceId`]. A device without a name displays as
+/// its number, so a label can always be printed where an ID was before.
+///
+/// Names, locations and tags are limited to
+/// [`MAX_DEVICE_LABEL_LENGTH`] characters and may not contain control
+/// characters or protocol delimiters, so they are safe in any log line or
+/// display text.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_core::{DeviceId, DeviceLabel};
+///
+/// let id = DeviceId::new(15).unwrap();
+/// assert_eq!(DeviceLabel::unnamed(id).to_string(), "15");
+///
+/// let label = DeviceLabel::named(id, "Portaria Norte")
+///     .unwrap()
+///     .with_location("Bloco A")
+///     .unwrap();
+/// assert_eq!(label.to_string(), "Portaria Norte (15)");
+///
+/// assert!(DeviceLabel::named(id, "Portaria]Norte").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceLabel {
+    /// Device the label belongs to
+    pub id: DeviceId,
+
+    /// Friendly name (None shows the numeric ID)
+    pub name: Option<String>,
+
+    /// Where the device is installed
+    pub location: Option<String>,
+
+    /// Free-form tags for grouping (e.g. "garage", "high-security")
+    pub tags: Vec<String>,
+}
+
+impl DeviceLabel {
+    /// Label of a device that has no name
+    #[must_use]
+    pub fn unnamed(id: DeviceId) -> Self {
+        Self {
+            id,
+            name: None,
+            location: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Label with a friendly name.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidFieldFormat` if the name is not a valid label text.
+    pub fn named(id: DeviceId, name: &str) -> Result<Self> {
+        Self::check_text("Device name", name)?;
+        Ok(Self {
+            name: Some(name.trim().to_string()),
+            ..Self::unnamed(id)
+        })
+    }
+
+    /// Set the location.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidFieldFormat` if the location is not a valid label text.
+    pub fn with_location(mut self, location: &str) -> Result<Self> {
+        Self::check_text("Device location", location)?;
+        self.location = Some(location.trim().to_string());
+        Ok(self)
+    }
+
+    /// Set the tags, dropping duplicates.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidFieldFormat` if a tag is not a valid label text.
+    pub fn with_tags<I, S>(mut self, tags: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut checked: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.as_ref();
+            Self::check_text("Device tag", tag)?;
+            let tag = tag.trim().to_string();
+            if !checked.contains(&tag) {
+                checked.push(tag);
+            }
+        }
+        self.tags = checked;
+        Ok(self)
+    }
+
+    /// Whether the device carries `tag`
+    #[must_use]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Check a name, location or tag.
+    ///
+    /// Leading and trailing whitespace is ignored.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidFieldFormat` if the text is empty, longer than
+    /// [`MAX_DEVICE_LABEL_LENGTH`] characters, or contains control characters
+    /// or protocol delimiters.
+    pub fn check_text(what: &str, text: &str) -> Result<()> {
+        let text = text.trim();
+        let invalid = |reason: &str| Error::InvalidFieldFormat {
+            message: format!("{what} {reason}: {text:?}"),
+        };
+
+        if text.is_empty() {
+            return Err(invalid("is empty"));
+        }
+        if text.chars().count() > MAX_DEVICE_LABEL_LENGTH {
+            return Err(invalid(&format!(
+                "exceeds {MAX_DEVICE_LABEL_LENGTH} characters"
+            )));
+        }
+        if text
+            .chars()
+            .any(|c| c.is_control() || matches!(c, '+' | ']' | '[' | '{' | '}'))
+        {
+            return Err(invalid(
+                "contains control characters or protocol delimiters",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DeviceLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
 /// Card/badge number (3-20 characters)
 ///
 /// # Security
@@ -208,6 +359,8 @@ impl fmt::Display for AccessDirection {
 #[repr(u8)]
 pub enum ReaderType {
     Rfid = 1,
+    /// Numeric code typed on the keypad; the card field carries the code
+    Keypad = 3,
     Biometric = 5,
 }
 
@@ -217,6 +370,7 @@ impl ReaderType {
     /// Supports both legacy and modern Henry protocol encoding schemes:
     /// - Code 0: RFID (legacy devices like ACR122U)
     /// - Code 1: RFID (modern devices)
+    /// - Code 3: Keypad
     /// - Code 5: Biometric (modern devices)
     ///
     /// # Errors
@@ -234,6 +388,7 @@ impl ReaderType {
     pub fn from_u8(value: u8) -> Result<Self> {
         match value {
             0 | 1 => Ok(ReaderType::Rfid), // Accept both legacy (0) and modern (1)
+            3 => Ok(ReaderType::Keypad),
             5 => Ok(ReaderType::Biometric),
             _ => Err(Error::InvalidReaderType { code: value }),
         }
@@ -243,6 +398,7 @@ impl ReaderType {
     ///
     /// Returns codes compatible with modern Henry devices:
     /// - RFID: 1
+    /// - Keypad: 3
     /// - Biometric: 5
     #[inline]
     #[must_use]
@@ -257,6 +413,13 @@ impl ReaderType {
         matches!(self, ReaderType::Rfid)
     }
 
+    /// Returns `true` if the code was typed on the keypad.
+    #[inline]
+    #[must_use]
+    pub fn is_keypad(self) -> bool {
+        matches!(self, ReaderType::Keypad)
+    }
+
     /// Returns `true` if reader type is Biometric.
     #[inline]
     #[must_use]
@@ -276,6 +439,12 @@ impl HenryTimestamp {
         HenryTimestamp(Local::now())
     }
 
+    /// Create a timestamp from the current time of `clock`, in local time.
+    #[must_use]
+    pub fn now_from(clock: &dyn Clock) -> Self {
+        HenryTimestamp(clock.now().with_timezone(&Local))
+    }
+
     /// Create a timestamp from a DateTime instance.
     #[must_use]
     pub fn from_datetime(dt: DateTime<Local>) -> Self {
@@ -332,6 +501,52 @@ impl fmt::Display for HenryTimestamp {
     }
 }
 
+/// Identifier shared by every record of one passage
+///
+/// Generated when the credential is read and carried through the access
+/// request, the validator and the access log, so logs from the device,
+/// the server and storage can be joined for a single tap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CorrelationId(uuid::Uuid);
+
+impl CorrelationId {
+    /// Generate a new random correlation ID.
+    #[must_use]
+    pub fn new() -> Self {
+        CorrelationId(uuid::Uuid::new_v4())
+    }
+
+    /// Get the underlying UUID.
+    #[must_use]
+    pub fn as_uuid(&self) -> &uuid::Uuid {
+        &self.0
+    }
+}
+
+impl Default for CorrelationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.hyphenated())
+    }
+}
+
+impl std::str::FromStr for CorrelationId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        uuid::Uuid::parse_str(s)
+            .map(CorrelationId)
+            .map_err(|e| Error::InvalidFieldFormat {
+                message: format!("Invalid correlation ID '{s}': {e}"),
+            })
+    }
+}
+
 /// Validation mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidationMode {
@@ -374,6 +589,129 @@ impl ValidationMode {
     }
 }
 
+/// Display or release time carried in protocol messages, in whole seconds
+///
+/// The wire field holds 0 to 255 seconds. Zero is
+/// [`Timeout::PERMANENT`]: the message stays until the next action instead
+/// of expiring.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use turnkey_core::Timeout;
+///
+/// let timeout: Timeout = "5".parse().unwrap();
+/// assert_eq!(timeout.as_secs(), 5);
+/// assert_eq!(timeout.as_duration(), Duration::from_secs(5));
+///
+/// assert!("0".parse::<Timeout>().unwrap().is_permanent());
+/// assert!("256".parse::<Timeout>().is_err());
+/// assert_eq!(
+///     Timeout::try_from(Duration::from_millis(1500)).unwrap(),
+///     Timeout::from_secs(2)
+/// );
+/// ```
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timeout(u8);
+
+impl Timeout {
+    /// Message stays until the next action
+    pub const PERMANENT: Self = Self(0);
+
+    /// Longest time the wire field can carry
+    pub const MAX: Self = Self(u8::MAX);
+
+    /// Create a timeout of `secs` seconds.
+    #[must_use]
+    pub const fn from_secs(secs: u8) -> Self {
+        Self(secs)
+    }
+
+    /// Create a timeout from a wider number of seconds.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidFieldFormat` if `secs` exceeds 255.
+    pub fn try_from_secs(secs: u64) -> Result<Self> {
+        u8::try_from(secs)
+            .map(Self)
+            .map_err(|_| Error::InvalidFieldFormat {
+                message: format!("Timeout must be 0-{} seconds, got {secs}", u8::MAX),
+            })
+    }
+
+    /// Parse a timeout field, naming it in the error.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidFieldFormat` if the value is not a number of
+    /// seconds between 0 and 255.
+    pub fn parse_field(value: &str, name: &str) -> Result<Self> {
+        value
+            .parse()
+            .map(Self)
+            .map_err(|_| Error::InvalidFieldFormat {
+                message: format!("Invalid {name} '{value}': expected 0-{} seconds", u8::MAX),
+            })
+    }
+
+    /// Number of seconds.
+    #[must_use]
+    pub const fn as_secs(self) -> u8 {
+        self.0
+    }
+
+    /// Whether this is [`Timeout::PERMANENT`].
+    #[must_use]
+    pub const fn is_permanent(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Length of the timeout; zero for a permanent one.
+    #[must_use]
+    pub fn as_duration(self) -> Duration {
+        Duration::from_secs(u64::from(self.0))
+    }
+}
+
+impl From<u8> for Timeout {
+    fn from(secs: u8) -> Self {
+        Self(secs)
+    }
+}
+
+impl From<Timeout> for u8 {
+    fn from(timeout: Timeout) -> Self {
+        timeout.0
+    }
+}
+
+impl TryFrom<Duration> for Timeout {
+    type Error = Error;
+
+    /// Round up to whole seconds, so a timeout is never cut short.
+    fn try_from(duration: Duration) -> Result<Self> {
+        let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+        Self::try_from_secs(secs)
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::str::FromStr for Timeout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse_field(s, "timeout")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -389,6 +727,27 @@ mod tests {
         assert_eq!(id.to_string_padded(), format!("{:02}", expected));
     }
 
+    #[test]
+    fn test_device_label_text_rules() {
+        let id = DeviceId::new(7).unwrap();
+
+        let label = DeviceLabel::named(id, "  Garagem  ")
+            .unwrap()
+            .with_tags(["garage", "high-security", "garage"])
+            .unwrap();
+        assert_eq!(label.name.as_deref(), Some("Garagem"));
+        assert_eq!(label.tags, vec!["garage", "high-security"]);
+        assert!(label.has_tag("high-security"));
+        assert_eq!(label.to_string(), "Garagem (07)");
+
+        assert!(DeviceLabel::named(id, " ").is_err());
+        assert!(DeviceLabel::named(id, &"x".repeat(MAX_DEVICE_LABEL_LENGTH + 1)).is_err());
+        assert!(DeviceLabel::named(id, "Catraca+1").is_err());
+        assert!(DeviceLabel::named(id, "Linha\nDois").is_err());
+        assert!(DeviceLabel::named(id, "Recepção").is_ok());
+        assert!(label.with_location("Bloco{A}").is_err());
+    }
+
     #[rstest]
     #[case("00")] // 0 invalid
     #[case("100")] // > 99 invalid
@@ -463,4 +822,58 @@ mod tests {
 
         assert_eq!(ValidationMode::Online.to_char(), 'O');
     }
+
+    #[test]
+    fn test_correlation_id_roundtrip() {
+        let id = CorrelationId::new();
+        let text = id.to_string();
+        assert_eq!(text.len(), 36);
+        assert_eq!(text.parse::<CorrelationId>().unwrap(), id);
+        assert_ne!(CorrelationId::new(), id);
+    }
+
+    #[test]
+    fn test_correlation_id_invalid() {
+        assert!("not-a-uuid".parse::<CorrelationId>().is_err());
+        assert!("".parse::<CorrelationId>().is_err());
+    }
+
+    #[rstest]
+    #[case("0", 0)]
+    #[case("5", 5)]
+    #[case("255", 255)]
+    fn test_timeout_parse_valid(#[case] input: &str, #[case] expected: u8) {
+        let timeout: Timeout = input.parse().unwrap();
+        assert_eq!(timeout.as_secs(), expected);
+        assert_eq!(timeout.to_string(), input);
+    }
+
+    #[rstest]
+    #[case("256")]
+    #[case("-1")]
+    #[case("")]
+    #[case("5s")]
+    fn test_timeout_parse_invalid(#[case] input: &str) {
+        let err = Timeout::parse_field(input, "release time").unwrap_err();
+        assert!(err.to_string().contains("Invalid release time"), "{err}");
+    }
+
+    #[test]
+    fn test_timeout_conversions() {
+        assert!(Timeout::PERMANENT.is_permanent());
+        assert!(!Timeout::from_secs(1).is_permanent());
+        assert_eq!(Timeout::try_from_secs(255).unwrap(), Timeout::MAX);
+        assert!(Timeout::try_from_secs(256).is_err());
+
+        assert_eq!(
+            Timeout::try_from(Duration::from_secs(3)).unwrap(),
+            Timeout::from_secs(3)
+        );
+        assert_eq!(
+            Timeout::try_from(Duration::from_millis(1)).unwrap(),
+            Timeout::from_secs(1)
+        );
+        assert!(Timeout::try_from(Duration::from_secs(300)).is_err());
+        assert_eq!(Timeout::from_secs(7).as_duration(), Duration::from_secs(7));
+    }
 }
@@ -0,0 +1,233 @@
+//! Answer to the `RD` peripheral diagnostics command.
+//!
+//! The [`PeripheralManager`](turnkey_hardware::PeripheralManager) runs the
+//! diagnostics (self-test, firmware, signal quality) on the devices it
+//! owns; this module names the peripherals on the wire and turns the
+//! results into the [`DiagnosticsReport`] the server asked for.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_core::DeviceId;
+//! use turnkey_emulator::EmulatorCore;
+//! use turnkey_hardware::devices::AnyRfidDevice;
+//! use turnkey_hardware::mock::MockRfid;
+//! use turnkey_hardware::{PeripheralConfig, PeripheralManager};
+//! use turnkey_protocol::commands::DiagnosticsReport;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> turnkey_core::Result<()> {
+//! let mut manager = PeripheralManager::new(PeripheralConfig::default());
+//! let (rfid, _handle) = MockRfid::new();
+//! manager.register_rfid(AnyRfidDevice::Mock(rfid));
+//!
+//! let request = DiagnosticsReport::request(DeviceId::new(15).unwrap(), Some("rfid"))?;
+//! let mut emulator = EmulatorCore::default();
+//! let report = emulator.handle_diagnostics(&request, &manager.diagnostics().await)?;
+//! assert!(report.passed());
+//! assert_eq!(report.peripheral("rfid").unwrap().signal.unwrap().strength, 90);
+//! # Ok(())
+//! # }
+//! ```
+
+use turnkey_core::{Error, Result};
+use turnkey_hardware::{DeviceDiagnostics, DeviceType, PeripheralDiagnostics};
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::diagnostics::{
+    DiagnosticsReport, PeripheralDiagnostic, SignalReport,
+};
+
+use crate::emulator::EmulatorCore;
+use crate::health::field_safe;
+
+/// Detail reported for a requested peripheral that is not installed.
+pub const NOT_INSTALLED: &str = "Not installed";
+
+/// Name of a peripheral in `RD` messages.
+pub fn peripheral_name(device_type: DeviceType) -> &'static str {
+    match device_type {
+        DeviceType::Keypad => "keypad",
+        DeviceType::Rfid => "rfid",
+        DeviceType::Biometric => "biometric",
+    }
+}
+
+/// Peripheral named in an `RD` message, `None` if the name is unknown.
+pub fn peripheral_type(name: &str) -> Option<DeviceType> {
+    [DeviceType::Keypad, DeviceType::Rfid, DeviceType::Biometric]
+        .into_iter()
+        .find(|device_type| peripheral_name(*device_type) == name)
+}
+
+/// Wire form of the diagnostics of one device.
+pub fn peripheral_diagnostic(diagnostics: &DeviceDiagnostics) -> PeripheralDiagnostic {
+    let mut peripheral = PeripheralDiagnostic::new(
+        peripheral_name(diagnostics.device_type),
+        diagnostics.self_test.passed,
+        field_safe(&diagnostics.self_test.detail),
+    );
+    if let Some(firmware) = &diagnostics.firmware {
+        peripheral = peripheral.with_firmware(field_safe(&firmware.version));
+    }
+    if let Some(signal) = diagnostics.signal {
+        peripheral = peripheral.with_signal(SignalReport::new(
+            signal.strength,
+            signal.reads,
+            signal.read_errors,
+        ));
+    }
+    peripheral
+}
+
+/// Wire form of the diagnostics of every device.
+pub fn diagnostics_report(diagnostics: &PeripheralDiagnostics) -> DiagnosticsReport {
+    DiagnosticsReport::new(
+        diagnostics
+            .devices
+            .iter()
+            .map(peripheral_diagnostic)
+            .collect(),
+    )
+}
+
+impl EmulatorCore {
+    /// Answer an `RD` diagnostics request from the server.
+    ///
+    /// Reports every device in `diagnostics`, or only the one the request
+    /// names; a named peripheral that is not installed is reported as
+    /// failed. The report is queued for the server and returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` if `request` is not a diagnostics
+    /// request, `Error::InvalidFieldFormat` if it names an unknown
+    /// peripheral, and `Error::MissingField` if there is nothing to report.
+    /// Nothing is queued in any of these cases.
+    pub fn handle_diagnostics(
+        &mut self,
+        request: &Message,
+        diagnostics: &PeripheralDiagnostics,
+    ) -> Result<DiagnosticsReport> {
+        if !DiagnosticsReport::is_request(request) {
+            return Err(Error::InvalidCommandCode {
+                code: request.command.as_str().to_string(),
+            });
+        }
+
+        let report = match DiagnosticsReport::requested_peripheral(request) {
+            None => diagnostics_report(diagnostics),
+            Some(name) => {
+                let device_type =
+                    peripheral_type(name).ok_or_else(|| Error::InvalidFieldFormat {
+                        message: format!("Unknown peripheral '{}'", name),
+                    })?;
+                let peripheral = match diagnostics.device(device_type) {
+                    Some(device) => peripheral_diagnostic(device),
+                    None => PeripheralDiagnostic::new(name, false, NOT_INSTALLED),
+                };
+                DiagnosticsReport::new(vec![peripheral])
+            }
+        };
+
+        self.queue_message(report.to_message(request.device_id)?);
+        tracing::info!(
+            device_id = %request.device_id,
+            passed = report.passed(),
+            peripherals = report.peripherals.len(),
+            "diagnostics answered"
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turnkey_core::DeviceId;
+    use turnkey_hardware::{FirmwareInfo, SelfTestResult, SignalQuality};
+    use turnkey_protocol::CommandCode;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn diagnostics() -> PeripheralDiagnostics {
+        PeripheralDiagnostics {
+            devices: vec![
+                DeviceDiagnostics {
+                    device_type: DeviceType::Keypad,
+                    self_test: SelfTestResult::pass("Mock Keypad"),
+                    firmware: Some(FirmwareInfo::new("1.0.0")),
+                    signal: None,
+                },
+                DeviceDiagnostics {
+                    device_type: DeviceType::Rfid,
+                    self_test: SelfTestResult::fail("Antenna] open"),
+                    firmware: None,
+                    signal: Some(SignalQuality::new(20).with_reads(50, 7)),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_peripheral_names() {
+        for device_type in [DeviceType::Keypad, DeviceType::Rfid, DeviceType::Biometric] {
+            assert_eq!(
+                peripheral_type(peripheral_name(device_type)),
+                Some(device_type)
+            );
+        }
+        assert_eq!(peripheral_type("printer"), None);
+    }
+
+    #[test]
+    fn test_handle_diagnostics_all_peripherals() {
+        let mut emulator = EmulatorCore::default();
+        let request = DiagnosticsReport::request(device(), None).unwrap();
+        let report = emulator
+            .handle_diagnostics(&request, &diagnostics())
+            .unwrap();
+
+        assert!(!report.passed());
+        let rfid = report.peripheral("rfid").unwrap();
+        assert_eq!(rfid.detail, "Antenna  open");
+        assert_eq!(rfid.signal, Some(SignalReport::new(20, 50, 7)));
+        assert_eq!(
+            report.peripheral("keypad").unwrap().firmware.as_deref(),
+            Some("1.0.0")
+        );
+
+        let queued = emulator.next_message().unwrap();
+        assert_eq!(queued.command, CommandCode::Diagnostics);
+        assert_eq!(DiagnosticsReport::parse(&queued).unwrap(), report);
+        assert!(emulator.next_message().is_none());
+    }
+
+    #[test]
+    fn test_handle_diagnostics_one_peripheral() {
+        let mut emulator = EmulatorCore::default();
+        let request = DiagnosticsReport::request(device(), Some("keypad")).unwrap();
+        let report = emulator
+            .handle_diagnostics(&request, &diagnostics())
+            .unwrap();
+        assert_eq!(report.peripherals.len(), 1);
+        assert!(report.passed());
+
+        let request = DiagnosticsReport::request(device(), Some("biometric")).unwrap();
+        let report = emulator
+            .handle_diagnostics(&request, &diagnostics())
+            .unwrap();
+        assert_eq!(
+            report.peripherals,
+            vec![PeripheralDiagnostic::new("biometric", false, NOT_INSTALLED)]
+        );
+
+        let request = DiagnosticsReport::request(device(), Some("printer")).unwrap();
+        assert!(matches!(
+            emulator.handle_diagnostics(&request, &diagnostics()),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+        assert_eq!(emulator.pending_messages().len(), 2);
+    }
+}
@@ -4,40 +4,27 @@
 //! the physical turnstile display. It handles text rendering, alignment, temporary
 //! messages with timeouts, and automatic state machine integration.
 //!
-//! # Character Encoding - ASCII Only
+//! # Character Encoding
 //!
-//! **CRITICAL**: This display only accepts ASCII characters (0x20-0x7E).
+//! Henry LCDs show one byte per character in a fixed character set: plain
+//! ASCII on most units, code page 850 or latin-1 on firmware with an
+//! extended character ROM. The display is configured with a [`Codepage`]
+//! (ASCII by default) and shows text exactly as that hardware would:
+//! characters the codepage supports are kept, and the rest are
+//! transliterated the same way [`HenryCodec`](turnkey_protocol::HenryCodec)
+//! does when it sends them.
 //!
-//! The Henry protocol specification mandates ASCII-only encoding for all display
-//! messages. Physical turnstile hardware LCD displays do not support extended
-//! character sets or Unicode.
-//!
-//! ## Why ASCII Only?
-//!
-//! This emulator intentionally rejects non-ASCII input to ensure developers
-//! test their integrations with the same constraints as real hardware. If the
-//! emulator accepted UTF-8 and performed automatic transliteration, integration
-//! issues would only surface when connecting to physical equipment in production.
-//!
-//! ## Handling Portuguese Text
-//!
-//! If your application needs to display Portuguese messages with accents,
-//! you must transliterate them to ASCII **before** sending to the display.
-//! This matches the real-world requirement where the server/application is
-//! responsible for ASCII conversion before transmitting to the turnstile.
+//! ```
+//! use turnkey_emulator::VirtualDisplay;
+//! use turnkey_protocol::Codepage;
 //!
-//! Example:
-//! ```rust,ignore
-//! // In your application code (not in the emulator):
-//! fn to_ascii(text: &str) -> String {
-//!     text.replace("ã", "a")
-//!         .replace("ç", "c")
-//!         .replace("é", "e")
-//!         // ... other replacements
-//! }
+//! let mut ascii = VirtualDisplay::builder().build();
+//! ascii.set_line(0, "Liberação concedida").unwrap();
+//! assert_eq!(ascii.get_line(0).unwrap().trim_end(), "Liberacao concedida");
 //!
-//! let message = to_ascii("Liberação concedida");
-//! display.set_line(0, &message).unwrap();
+//! let mut cp850 = VirtualDisplay::builder().with_codepage(Codepage::Cp850).build();
+//! cp850.set_line(0, "Liberação concedida").unwrap();
+//! assert_eq!(cp850.get_line(0).unwrap().trim_end(), "Liberação concedida");
 //! ```
 //!
 //! # Examples
@@ -91,9 +78,12 @@
 //! assert_eq!(display.get_line(0).unwrap().trim(), "WELCOME");
 //! ```
 
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
+use turnkey_config::DisplaySection;
 use turnkey_core::{Error, Result};
+use turnkey_protocol::Codepage;
 
 use crate::TurnstileState;
 
@@ -147,9 +137,12 @@ pub struct VirtualDisplay {
     /// Number of columns per line.
     columns: usize,
 
-    /// Current display buffer (ASCII characters only).
+    /// Current display buffer (characters of `codepage` only).
     buffer: Vec<String>,
 
+    /// Character set of the LCD.
+    codepage: Codepage,
+
     /// Default message to show when idle.
     default_message: String,
 
@@ -164,16 +157,13 @@ impl VirtualDisplay {
     ///
     /// * `lines` - Number of lines (typically 2)
     /// * `columns` - Number of columns per line (typically 40)
-    /// * `default_message` - Default message shown when idle (ASCII only)
+    /// * `default_message` - Default message shown when idle
     ///
     /// # Returns
     ///
-    /// Returns a new `VirtualDisplay` initialized with the default message
-    /// on the first line, centered.
-    ///
-    /// # Panics
-    ///
-    /// Panics in debug builds if `default_message` contains non-ASCII characters.
+    /// Returns a new ASCII `VirtualDisplay` initialized with the default
+    /// message on the first line, centered. Use the
+    /// [builder](Self::builder) for another codepage.
     ///
     /// # Examples
     ///
@@ -184,12 +174,16 @@ impl VirtualDisplay {
     /// assert_eq!(display.get_line(0).unwrap().trim(), "DIGITE SEU CODIGO");
     /// ```
     pub fn new(lines: usize, columns: usize, default_message: String) -> Self {
-        debug_assert!(
-            default_message.is_ascii(),
-            "Default message must be ASCII only. Got: '{}'",
-            default_message
-        );
+        Self::with_config(lines, columns, &default_message, Codepage::default())
+    }
 
+    fn with_config(
+        lines: usize,
+        columns: usize,
+        default_message: &str,
+        codepage: Codepage,
+    ) -> Self {
+        let default_message = codepage.render(default_message);
         let mut buffer = vec![" ".repeat(columns); lines];
 
         // Set default message on first line, centered
@@ -201,11 +195,17 @@ impl VirtualDisplay {
             lines,
             columns,
             buffer,
+            codepage,
             default_message,
             temporary_message: None,
         }
     }
 
+    /// Get the character set of the display.
+    pub fn codepage(&self) -> Codepage {
+        self.codepage
+    }
+
     /// Create a builder for constructing a virtual display with custom configuration.
     ///
     /// # Returns
@@ -228,13 +228,14 @@ impl VirtualDisplay {
 
     /// Set text on a specific line with left alignment.
     ///
-    /// Control characters are removed, and text is truncated to fit within
+    /// Control characters are removed, characters outside the display
+    /// codepage are transliterated, and text is truncated to fit within
     /// the column width.
     ///
     /// # Arguments
     ///
     /// * `line` - Line index (0-based)
-    /// * `text` - Text to display (ASCII only)
+    /// * `text` - Text to display
     ///
     /// # Returns
     ///
@@ -245,10 +246,6 @@ impl VirtualDisplay {
     /// Returns an error if:
     /// - Line index is out of bounds
     ///
-    /// # Panics
-    ///
-    /// Panics in debug builds if `text` contains non-ASCII characters.
-    ///
     /// # Examples
     ///
     /// ```
@@ -288,12 +285,6 @@ impl VirtualDisplay {
     /// display.set_line_aligned(0, "CENTERED", Alignment::Center).unwrap();
     /// ```
     pub fn set_line_aligned(&mut self, line: usize, text: &str, align: Alignment) -> Result<()> {
-        debug_assert!(
-            text.is_ascii(),
-            "Display text must be ASCII only for protocol compatibility. Got: '{}'",
-            text
-        );
-
         if line >= self.lines {
             return Err(Error::InvalidLine {
                 line,
@@ -301,7 +292,7 @@ impl VirtualDisplay {
             });
         }
 
-        let sanitized = sanitize_text(text);
+        let sanitized = self.codepage.render(&sanitize_text(text));
         let aligned = align_text(&sanitized, self.columns, align);
 
         self.buffer[line] = aligned;
@@ -372,18 +363,12 @@ impl VirtualDisplay {
     /// assert!(!display.is_default());
     /// ```
     pub fn show_temporary(&mut self, text: &str, duration: Duration) -> Result<()> {
-        debug_assert!(
-            text.is_ascii(),
-            "Display text must be ASCII only for protocol compatibility. Got: '{}'",
-            text
-        );
-
         if duration.is_zero() {
             return Err(Error::InvalidDuration);
         }
 
         let expiration = Instant::now() + duration;
-        let sanitized = sanitize_text(text);
+        let sanitized = self.codepage.render(&sanitize_text(text));
 
         self.temporary_message = Some((sanitized.clone(), expiration));
         self.set_line_aligned(0, &sanitized, Alignment::Center)?;
@@ -478,7 +463,7 @@ impl VirtualDisplay {
     /// Update display based on state machine state.
     ///
     /// This method automatically sets appropriate messages for each state,
-    /// all using ASCII-compatible text for protocol compliance.
+    /// all using ASCII text so they read the same on every codepage.
     ///
     /// # Arguments
     ///
@@ -504,9 +489,12 @@ impl VirtualDisplay {
             TurnstileState::RotationInProgress => ("GIRANDO...".into(), String::new()),
             TurnstileState::RotationCompleted => ("OBRIGADO".into(), String::new()),
             TurnstileState::RotationTimeout => ("TEMPO ESGOTADO".into(), String::new()),
+            TurnstileState::Enrolling => ("CADASTRO DE CARTAO".into(), "Aproxime o cartao".into()),
+            TurnstileState::AwaitingBiometric => {
+                ("CONFIRME A DIGITAL".into(), "Posicione o dedo".into())
+            }
         };
 
-        // All messages are already ASCII-compatible or will be transliterated
         let _ = self.set_line_aligned(0, &line1, Alignment::Center);
         let _ = self.set_line_aligned(1, &line2, Alignment::Center);
     }
@@ -608,6 +596,7 @@ pub struct VirtualDisplayBuilder {
     lines: usize,
     columns: usize,
     default_message: String,
+    codepage: Codepage,
 }
 
 impl VirtualDisplayBuilder {
@@ -641,13 +630,62 @@ impl VirtualDisplayBuilder {
         self
     }
 
+    /// Apply the `[display]` section of a configuration file.
+    ///
+    /// Values missing from the section keep their current setting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_config::TurnkeyConfig;
+    /// use turnkey_emulator::VirtualDisplay;
+    ///
+    /// let config = TurnkeyConfig::from_toml("[display]\ndefault_message = \"BEM-VINDO\"").unwrap();
+    /// let display = VirtualDisplay::builder()
+    ///     .with_file_settings(&config.display)
+    ///     .build();
+    ///
+    /// assert_eq!(display.get_line(0).unwrap().trim(), "BEM-VINDO");
+    /// ```
+    pub fn with_file_settings(mut self, section: &DisplaySection) -> Self {
+        if let Some(lines) = section.lines {
+            self.lines = lines;
+        }
+        if let Some(columns) = section.columns {
+            self.columns = columns;
+        }
+        if let Some(message) = &section.default_message {
+            self.default_message = message.clone();
+        }
+        self
+    }
+
+    /// Set the character set of the LCD.
+    ///
+    /// # Arguments
+    ///
+    /// * `codepage` - Codepage of the emulated firmware
+    ///
+    /// # Returns
+    ///
+    /// Returns self for method chaining.
+    pub fn with_codepage(mut self, codepage: Codepage) -> Self {
+        self.codepage = codepage;
+        self
+    }
+
     /// Build the virtual display with configured parameters.
     ///
     /// # Returns
     ///
     /// Returns a new `VirtualDisplay` with the specified configuration.
     pub fn build(self) -> VirtualDisplay {
-        VirtualDisplay::new(self.lines, self.columns, self.default_message)
+        VirtualDisplay::with_config(
+            self.lines,
+            self.columns,
+            &self.default_message,
+            self.codepage,
+        )
     }
 }
 
@@ -657,15 +695,119 @@ impl Default for VirtualDisplayBuilder {
             lines: DEFAULT_LINES,
             columns: DEFAULT_COLUMNS,
             default_message: "DIGITE SEU CODIGO".to_string(),
+            codepage: Codepage::default(),
         }
     }
 }
 
-/// Truncate ASCII text to a maximum number of characters.
+/// Serializable copy of a display's content.
+///
+/// A pending temporary message keeps the time it had left when the
+/// snapshot was taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplaySnapshot {
+    /// Number of lines.
+    pub lines: usize,
+
+    /// Number of columns per line.
+    pub columns: usize,
+
+    /// Line contents, padded to `columns`.
+    pub buffer: Vec<String>,
+
+    /// Character set of the LCD.
+    #[serde(default)]
+    pub codepage: Codepage,
+
+    /// Default idle message.
+    pub default_message: String,
+
+    /// Temporary message and its remaining display time.
+    pub temporary_message: Option<(String, Duration)>,
+}
+
+impl VirtualDisplay {
+    /// Capture the complete display content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_emulator::VirtualDisplay;
+    ///
+    /// let mut display = VirtualDisplay::new(2, 40, "IDLE".to_string());
+    /// display.set_line(1, "LINHA 2").unwrap();
+    ///
+    /// let restored = VirtualDisplay::from_snapshot(display.snapshot()).unwrap();
+    /// assert_eq!(restored.get_all_lines(), display.get_all_lines());
+    /// ```
+    pub fn snapshot(&self) -> DisplaySnapshot {
+        let now = Instant::now();
+        DisplaySnapshot {
+            lines: self.lines,
+            columns: self.columns,
+            buffer: self.buffer.clone(),
+            codepage: self.codepage,
+            default_message: self.default_message.clone(),
+            temporary_message: self.temporary_message.as_ref().map(|(text, expiration)| {
+                (text.clone(), expiration.saturating_duration_since(now))
+            }),
+        }
+    }
+
+    /// Build a display from a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if the buffer does not match the declared
+    /// size or contains characters outside its codepage.
+    pub fn from_snapshot(snapshot: DisplaySnapshot) -> Result<Self> {
+        if snapshot.buffer.len() != snapshot.lines {
+            return Err(Error::Config(format!(
+                "Display snapshot has {} lines, expected {}",
+                snapshot.buffer.len(),
+                snapshot.lines
+            )));
+        }
+        if let Some(line) = snapshot.buffer.iter().position(|line| {
+            line.chars().count() != snapshot.columns
+                || !line.chars().all(|c| snapshot.codepage.supports(c))
+        }) {
+            return Err(Error::Config(format!(
+                "Display snapshot line {} is not {} {} characters",
+                line, snapshot.columns, snapshot.codepage
+            )));
+        }
+
+        let now = Instant::now();
+        Ok(Self {
+            lines: snapshot.lines,
+            columns: snapshot.columns,
+            buffer: snapshot.buffer,
+            codepage: snapshot.codepage,
+            default_message: snapshot.default_message,
+            temporary_message: snapshot
+                .temporary_message
+                .map(|(text, remaining)| (text, now + remaining)),
+        })
+    }
+
+    /// Replace the display content with a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`from_snapshot`](Self::from_snapshot). The display is left
+    /// untouched on error.
+    pub fn restore(&mut self, snapshot: DisplaySnapshot) -> Result<()> {
+        *self = Self::from_snapshot(snapshot)?;
+        Ok(())
+    }
+}
+
+/// Truncate text to a maximum number of characters.
 ///
 /// # Arguments
 ///
-/// * `text` - ASCII text to truncate
+/// * `text` - Text to truncate
 /// * `max_chars` - Maximum number of characters
 ///
 /// # Returns
@@ -685,11 +827,11 @@ pub fn truncate_text(text: &str, max_chars: usize) -> String {
     text.chars().take(max_chars).collect()
 }
 
-/// Align ASCII text within a fixed width, padding with spaces.
+/// Align text within a fixed width, padding with spaces.
 ///
 /// # Arguments
 ///
-/// * `text` - ASCII text to align
+/// * `text` - Text to align
 /// * `width` - Target width in characters
 /// * `alignment` - Alignment mode (Left, Center, or Right)
 ///
@@ -979,6 +1121,24 @@ mod tests {
         assert_eq!(display.get_line(0).unwrap().trim(), "TEMPO ESGOTADO");
     }
 
+    #[test]
+    fn test_state_machine_integration_enrolling() {
+        let mut display = VirtualDisplay::new(2, 40, "IDLE".to_string());
+        display.update_from_state(&TurnstileState::Enrolling);
+
+        assert_eq!(display.get_line(0).unwrap().trim(), "CADASTRO DE CARTAO");
+        assert_eq!(display.get_line(1).unwrap().trim(), "Aproxime o cartao");
+    }
+
+    #[test]
+    fn test_state_machine_integration_awaiting_biometric() {
+        let mut display = VirtualDisplay::new(2, 40, "IDLE".to_string());
+        display.update_from_state(&TurnstileState::AwaitingBiometric);
+
+        assert_eq!(display.get_line(0).unwrap().trim(), "CONFIRME A DIGITAL");
+        assert_eq!(display.get_line(1).unwrap().trim(), "Posicione o dedo");
+    }
+
     #[test]
     fn test_get_line_out_of_bounds() {
         let display = VirtualDisplay::new(2, 40, "IDLE".to_string());
@@ -1111,4 +1271,63 @@ mod tests {
         assert_eq!(truncate_text("Hello", 3), "Hel");
         assert_eq!(truncate_text("Hello", 10), "Hello");
     }
+
+    #[test]
+    fn test_accented_text_transliterated_on_ascii_display() {
+        let mut display = VirtualDisplay::new(2, 40, "Olá, aproxime o cartão".to_string());
+        assert_eq!(
+            display.get_line(0).unwrap().trim(),
+            "Ola, aproxime o cartao"
+        );
+
+        display
+            .set_line(1, "Atenção: prédio fechado às 22h")
+            .unwrap();
+        assert_eq!(
+            display.get_line(1).unwrap().trim_end(),
+            "Atencao: predio fechado as 22h"
+        );
+
+        display
+            .show_temporary("Liberação", Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(display.get_line(0).unwrap().trim(), "Liberacao");
+    }
+
+    #[test]
+    fn test_accented_text_kept_on_cp850_display() {
+        let mut display = VirtualDisplay::builder()
+            .with_size(2, 10)
+            .with_codepage(Codepage::Cp850)
+            .build();
+        assert_eq!(display.codepage(), Codepage::Cp850);
+
+        display.set_line(0, "Ação no prédio").unwrap();
+        let line = display.get_line(0).unwrap();
+        assert_eq!(line, "Ação no pr");
+        assert_eq!(line.chars().count(), 10);
+
+        // Outside CP850: typographic quotes are transliterated
+        display
+            .set_line_aligned(1, "“Olá”", Alignment::Right)
+            .unwrap();
+        assert_eq!(display.get_line(1).unwrap(), "     \"Olá\"");
+    }
+
+    #[test]
+    fn test_snapshot_keeps_codepage() {
+        let mut display = VirtualDisplay::builder()
+            .with_codepage(Codepage::Latin1)
+            .build();
+        display.set_line(1, "Até logo").unwrap();
+
+        let restored = VirtualDisplay::from_snapshot(display.snapshot()).unwrap();
+        assert_eq!(restored.codepage(), Codepage::Latin1);
+        assert_eq!(restored.get_all_lines(), display.get_all_lines());
+
+        // The same content is not valid on an ASCII display
+        let mut snapshot = display.snapshot();
+        snapshot.codepage = Codepage::Ascii;
+        assert!(VirtualDisplay::from_snapshot(snapshot).is_err());
+    }
 }
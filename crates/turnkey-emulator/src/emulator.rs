@@ -0,0 +1,2105 @@
+//! Emulator core combining state machine, display and outbound queue.
+//!
+//! `EmulatorCore` owns everything that makes up the observable state of one
+//! emulated turnstile:
+//!
+//! - the [`StateMachine`] driving the access flow,
+//! - the [`VirtualDisplay`] kept in sync with the current state,
+//! - the queue of protocol messages waiting to be sent to the server,
+//! - event counters (grants, denials, rotations, timeouts, alarms),
+//! - passage statistics per direction and peripheral errors,
+//! - the cards and templates synced by the server.
+//!
+//! # Sensor Events
+//!
+//! Turnstile sensors reach the emulator as [`SensorEvent`]s through the
+//! [`SensorDevice`] trait, the same path a GPIO driver will take.
+//! [`EmulatorCore::run_sensors()`] feeds every event from a sensor into
+//! [`EmulatorCore::handle_sensor_event()`]; with a
+//! [`MockSensor`](turnkey_hardware::mock::MockSensor) the events are injected
+//! by hand or from a script:
+//!
+//! - an arm rotation while waiting for one completes the passage,
+//! - a rotation at any other time, a forced door and an opened tamper
+//!   switch are counted as alarms and flashed on the display.
+//!
+//! # Server Responses
+//!
+//! [`EmulatorCore::apply_response()`] ends a validation with the server's
+//! [`AccessResponse`]. Grants and denials take the regular flow; a
+//! message-only response (`00+0`) keeps the arm locked like a denial but
+//! shows the server's text in place of "ACESSO NEGADO" for the response's
+//! timeout, then returns to `Idle`.
+//!
+//! [`EmulatorCore::handle_response()`] takes the response message as
+//! received instead, repairing or rejecting malformed ones depending on the
+//! [`Strictness`]. See the [`strictness`](crate::strictness) module.
+//!
+//! # Status Queries
+//!
+//! [`EmulatorCore::handle_status_query()`] answers the server's empty `RQ`
+//! with a [`DeviceStatusReport`] carrying the counters and the
+//! [`PassageStats`]: entries and exits counted from the arm sensor, and the
+//! read timeouts and device errors passed to
+//! [`EmulatorCore::record_peripheral_event()`], each with the time of the
+//! latest one.
+//!
+//! # Whitelist Memory
+//!
+//! Cards and templates synced by the server live in a [`DeviceMemory`] with
+//! finite capacity, set with [`EmulatorCore::with_memory()`].
+//! [`EmulatorCore::handle_sync()`] applies `ECAR`/`ED` batches and queues
+//! the answer, which reports a full memory and any evicted entries.
+//!
+//! # Request to Exit
+//!
+//! Interior doors have push-to-exit buttons that release the passage without
+//! reading or validating anything. With [`EmulatorCore::with_rex()`] and an
+//! enabled [`RexConfig`], [`EmulatorCore::handle_rex_request()`] moves an
+//! idle turnstile straight to `WaitingRotation` and queues the `000+80`
+//! notification with the exit direction and no card; the `000+81`/`000+82`
+//! that ends the passage follows when the arm turns or the release expires.
+//! [`EmulatorCore::run_rex()`] feeds it from any [`RexDevice`]. Requests are
+//! ignored while REX is disabled or another passage is in progress.
+//!
+//! # Maintenance Menu
+//!
+//! With [`EmulatorCore::with_menu()`], keypad input goes through
+//! [`EmulatorCore::handle_keypad_input()`] first: `*#` and the access code
+//! open the [`KeypadMenu`] on the display of an idle turnstile, and the keys
+//! browse it until it is closed. [`EmulatorCore::run_keypad()`] feeds it
+//! from any [`KeypadDevice`]. A transition of the access flow closes the
+//! menu, since the display then belongs to the passage.
+//!
+//! # Wait Feedback
+//!
+//! A slow server would otherwise leave "VALIDANDO..." frozen on the LCD.
+//! Once the emulator has been in `Validating` for longer than
+//! [`ValidationFeedback::delay`], [`EmulatorCore::update_validation_feedback()`]
+//! animates the first line and shows the elapsed seconds on the second.
+//! [`EmulatorCore::run_validation_feedback()`] calls it on a timer for as
+//! long as validation lasts; the response moves the emulator out of
+//! `Validating`, which overwrites the display and ends the timer.
+//!
+//! # Welcome Pre-rendering
+//!
+//! With [`EmulatorCore::with_prerender()`], known users are greeted by name
+//! as soon as their card is read; the greeting stays on a grant and is
+//! rolled back by any other decision. See the [`prerender`](crate::prerender)
+//! module.
+//!
+//! # Server Link
+//!
+//! [`EmulatorCore::set_link_online()`] takes the link state reported by the
+//! heartbeat monitor and shows "MODO OFFLINE" on the idle screen while the
+//! server is unreachable. See the [`link`](crate::link) module.
+//!
+//! # Snapshots
+//!
+//! [`EmulatorCore::snapshot()`] captures all of the above in a serializable
+//! [`EmulatorSnapshot`], and [`EmulatorCore::restore()`] puts it back. This lets
+//! integration tests stop the emulator mid-flow, compare it against a golden
+//! state stored as JSON, or list exactly what changed between two points
+//! with [`EmulatorSnapshot::diff()`].
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_emulator::{EmulatorCore, TurnstileState};
+//!
+//! let mut emulator = EmulatorCore::default();
+//! emulator.transition_to(TurnstileState::Reading).unwrap();
+//!
+//! let checkpoint = emulator.snapshot();
+//!
+//! emulator.transition_to(TurnstileState::Validating).unwrap();
+//! emulator.transition_to(TurnstileState::Denied).unwrap();
+//! assert_eq!(emulator.counters().denied, 1);
+//!
+//! emulator.restore(checkpoint).unwrap();
+//! assert_eq!(emulator.state(), TurnstileState::Reading);
+//! assert_eq!(emulator.counters().denied, 0);
+//! ```
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use chrono::{Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use turnkey_core::{
+    AccessDirection, DeviceId, Error, HenryTimestamp, ReaderType, Result, SharedClock,
+    ValidationMode,
+};
+use turnkey_hardware::{
+    HardwareError, KeypadDevice, KeypadInput, PeripheralEvent, PeripheralStats, RexDevice,
+    RexRequest, SensorDevice, SensorEvent,
+};
+use turnkey_protocol::commands::access::AccessResponse;
+use turnkey_protocol::commands::turnstile::TurnstileStatus;
+use turnkey_protocol::commands::{DeviceStatusReport, OverrideGrant, PassageStats};
+use turnkey_protocol::{CommandCode, FieldData, Message, format_message};
+
+use crate::TurnstileState;
+use crate::display::{Alignment, DisplaySnapshot, VirtualDisplay};
+use crate::memory::{DeviceMemory, MemoryConfig};
+use crate::menu::{KeypadMenu, MenuConfig, MenuScreen};
+use crate::prerender::KnownUsers;
+use crate::state_machine::{StateMachine, StateMachineSnapshot, StateTransition};
+use crate::strictness::Strictness;
+
+/// Second display line while an operator override waits for the rotation.
+const OVERRIDE_LINE: &str = "Liberado pelo operador";
+
+/// Display text for an arm rotation that was not released.
+const UNAUTHORIZED_ROTATION_MESSAGE: &str = "PASSAGEM IRREGULAR";
+
+/// Display text for a forced door.
+const DOOR_FORCED_MESSAGE: &str = "PORTA FORCADA";
+
+/// Display text for an opened tamper switch.
+const TAMPER_MESSAGE: &str = "VIOLACAO DETECTADA";
+
+/// Second display line while an exit request waits for the rotation.
+const REX_LINE: &str = "Saida liberada";
+
+/// Time an alarm stays on the display.
+const ALARM_DISPLAY_TIME: Duration = Duration::from_secs(5);
+
+/// Time a maintenance menu notice stays on the display.
+const MENU_NOTICE_TIME: Duration = Duration::from_secs(3);
+
+/// Counts of access flow outcomes since the emulator started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmulatorCounters {
+    /// Transitions into `Granted`.
+    pub granted: u64,
+
+    /// Transitions into `Denied`.
+    pub denied: u64,
+
+    /// Transitions into `RotationCompleted`.
+    pub rotations: u64,
+
+    /// Transitions into `RotationTimeout`.
+    pub rotation_timeouts: u64,
+
+    /// Denials released by an operator override.
+    #[serde(default)]
+    pub overrides: u64,
+
+    /// Arm rotations reported while no passage was released.
+    #[serde(default)]
+    pub unauthorized_rotations: u64,
+
+    /// Forced door reports.
+    #[serde(default)]
+    pub doors_forced: u64,
+
+    /// Tamper switch openings.
+    #[serde(default)]
+    pub tamper_alarms: u64,
+
+    /// Passages released by a request-to-exit input.
+    #[serde(default)]
+    pub rex_exits: u64,
+
+    /// Message-only responses; each is also counted as a denial.
+    #[serde(default)]
+    pub display_only: u64,
+
+    /// Welcomes shown to known users ahead of the decision.
+    #[serde(default)]
+    pub prerendered: u64,
+
+    /// Welcomes taken back because access was not granted.
+    #[serde(default)]
+    pub prerender_rollbacks: u64,
+
+    /// Times the server link was reported down.
+    #[serde(default)]
+    pub link_lost: u64,
+
+    /// Times the server link was reported up again.
+    #[serde(default)]
+    pub link_restored: u64,
+
+    /// Malformed responses rejected in strict mode.
+    #[serde(default)]
+    pub rejected_responses: u64,
+}
+
+impl EmulatorCounters {
+    fn record(&mut self, state: TurnstileState) {
+        match state {
+            TurnstileState::Granted => self.granted += 1,
+            TurnstileState::Denied => self.denied += 1,
+            TurnstileState::RotationCompleted => self.rotations += 1,
+            TurnstileState::RotationTimeout => self.rotation_timeouts += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Timing of the wait feedback shown while a validation is pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationFeedback {
+    /// Time in `Validating` before the display starts changing (default: 300ms).
+    pub delay: Duration,
+
+    /// Time between animation frames (default: 500ms).
+    pub frame: Duration,
+}
+
+impl Default for ValidationFeedback {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(300),
+            frame: Duration::from_millis(500),
+        }
+    }
+}
+
+impl ValidationFeedback {
+    /// Display lines after `elapsed` in `Validating`, or `None` before the delay.
+    ///
+    /// The dots cycle from one to three; the text keeps a fixed width so the
+    /// centered line does not jump between frames.
+    fn lines(&self, elapsed: Duration) -> Option<(String, String)> {
+        let waiting = elapsed.checked_sub(self.delay)?;
+        let frame = waiting.as_millis() / self.frame.as_millis().max(1);
+        let dots = (frame % 3) as usize + 1;
+
+        Some((
+            format!("VALIDANDO{:<3}", ".".repeat(dots)),
+            format!("Aguarde {}s", elapsed.as_secs()),
+        ))
+    }
+}
+
+/// Request-to-exit settings of one emulated turnstile.
+///
+/// REX is disabled by default: a device only honors exit buttons once it is
+/// explicitly enabled, since on most turnstiles both directions go through
+/// the reader.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use turnkey_core::DeviceId;
+/// use turnkey_emulator::RexConfig;
+///
+/// let rex = RexConfig::new(DeviceId::new(15).unwrap())
+///     .enabled(true)
+///     .with_release_time(Duration::from_secs(8));
+///
+/// assert!(rex.enabled);
+/// assert_eq!(rex.release_time, Duration::from_secs(8));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RexConfig {
+    /// Device ID used in the exit notifications.
+    pub device_id: DeviceId,
+
+    /// Whether exit requests release the turnstile (default: false).
+    pub enabled: bool,
+
+    /// Time the arm stays released after a request (default: 5s).
+    pub release_time: Duration,
+}
+
+impl RexConfig {
+    /// Disabled REX settings for `device_id` with the default release time.
+    pub fn new(device_id: DeviceId) -> Self {
+        Self {
+            device_id,
+            enabled: false,
+            release_time: Duration::from_secs(5),
+        }
+    }
+
+    /// Enable or disable exit requests.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set how long the arm stays released.
+    pub fn with_release_time(mut self, release_time: Duration) -> Self {
+        self.release_time = release_time;
+        self
+    }
+}
+
+/// Serializable copy of the complete emulator state.
+///
+/// Produced by [`EmulatorCore::snapshot()`] and consumed by
+/// [`EmulatorCore::restore()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmulatorSnapshot {
+    /// State machine state and history.
+    pub state_machine: StateMachineSnapshot,
+
+    /// Display content.
+    pub display: DisplaySnapshot,
+
+    /// Messages waiting to be sent, oldest first.
+    pub pending: Vec<Message>,
+
+    /// Outcome counters.
+    pub counters: EmulatorCounters,
+
+    /// Whether the tamper switch is open.
+    #[serde(default)]
+    pub tampered: bool,
+
+    /// Whether the passage in progress was released by an exit request.
+    #[serde(default)]
+    pub rex_passage: bool,
+
+    /// Welcome shown ahead of the pending decision.
+    #[serde(default)]
+    pub prerendered: Option<String>,
+
+    /// Whether the server link was down.
+    #[serde(default)]
+    pub link_offline: bool,
+}
+
+impl EmulatorSnapshot {
+    /// Describe how `other` differs from this snapshot.
+    ///
+    /// Returns one human-readable line per difference, or an empty vector if
+    /// both snapshots are equivalent. Time spent in the current state and
+    /// transition timestamps are ignored so that two runs of the same flow
+    /// compare equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_emulator::{EmulatorCore, TurnstileState};
+    ///
+    /// let mut emulator = EmulatorCore::default();
+    /// let before = emulator.snapshot();
+    ///
+    /// emulator.transition_to(TurnstileState::Reading).unwrap();
+    /// let diff = before.diff(&emulator.snapshot());
+    ///
+    /// assert!(diff.contains(&"state: Idle -> Reading".to_string()));
+    /// ```
+    pub fn diff(&self, other: &EmulatorSnapshot) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        let (a, b) = (&self.state_machine, &other.state_machine);
+        if a.state != b.state {
+            changes.push(format!("state: {:?} -> {:?}", a.state, b.state));
+        }
+        if a.timeout != b.timeout {
+            changes.push(format!("timeout: {:?} -> {:?}", a.timeout, b.timeout));
+        }
+        let path = |history: &[StateTransition]| -> Vec<(TurnstileState, TurnstileState)> {
+            history.iter().map(|t| (t.from, t.to)).collect()
+        };
+        if path(&a.history) != path(&b.history) {
+            changes.push(format!(
+                "history: {} -> {} transitions",
+                a.history.len(),
+                b.history.len()
+            ));
+        }
+
+        let (a, b) = (&self.display, &other.display);
+        if (a.lines, a.columns) != (b.lines, b.columns) {
+            changes.push(format!(
+                "display size: {}x{} -> {}x{}",
+                a.lines, a.columns, b.lines, b.columns
+            ));
+        }
+        for (index, (before, after)) in a.buffer.iter().zip(&b.buffer).enumerate() {
+            if before != after {
+                changes.push(format!(
+                    "display line {}: '{}' -> '{}'",
+                    index,
+                    before.trim(),
+                    after.trim()
+                ));
+            }
+        }
+        let temporary = |snapshot: &DisplaySnapshot| {
+            snapshot
+                .temporary_message
+                .as_ref()
+                .map(|(text, _)| text.clone())
+        };
+        if temporary(a) != temporary(b) {
+            changes.push(format!(
+                "temporary message: {:?} -> {:?}",
+                temporary(a),
+                temporary(b)
+            ));
+        }
+
+        let wire = |pending: &[Message]| pending.iter().map(format_message).collect::<Vec<_>>();
+        let (a, b) = (wire(&self.pending), wire(&other.pending));
+        if a != b {
+            changes.push(format!("pending: {} -> {} messages", a.len(), b.len()));
+        }
+
+        let (a, b) = (self.counters, other.counters);
+        for (name, before, after) in [
+            ("granted", a.granted, b.granted),
+            ("denied", a.denied, b.denied),
+            ("rotations", a.rotations, b.rotations),
+            (
+                "rotation_timeouts",
+                a.rotation_timeouts,
+                b.rotation_timeouts,
+            ),
+            ("overrides", a.overrides, b.overrides),
+            (
+                "unauthorized_rotations",
+                a.unauthorized_rotations,
+                b.unauthorized_rotations,
+            ),
+            ("doors_forced", a.doors_forced, b.doors_forced),
+            ("tamper_alarms", a.tamper_alarms, b.tamper_alarms),
+            ("rex_exits", a.rex_exits, b.rex_exits),
+            ("prerendered", a.prerendered, b.prerendered),
+            (
+                "prerender_rollbacks",
+                a.prerender_rollbacks,
+                b.prerender_rollbacks,
+            ),
+            ("link_lost", a.link_lost, b.link_lost),
+            ("link_restored", a.link_restored, b.link_restored),
+            (
+                "rejected_responses",
+                a.rejected_responses,
+                b.rejected_responses,
+            ),
+        ] {
+            if before != after {
+                changes.push(format!("counters.{}: {} -> {}", name, before, after));
+            }
+        }
+
+        if self.tampered != other.tampered {
+            changes.push(format!("tampered: {} -> {}", self.tampered, other.tampered));
+        }
+
+        if self.rex_passage != other.rex_passage {
+            changes.push(format!(
+                "rex_passage: {} -> {}",
+                self.rex_passage, other.rex_passage
+            ));
+        }
+
+        if self.prerendered != other.prerendered {
+            changes.push(format!(
+                "prerendered: {:?} -> {:?}",
+                self.prerendered, other.prerendered
+            ));
+        }
+
+        if self.link_offline != other.link_offline {
+            changes.push(format!(
+                "link_offline: {} -> {}",
+                self.link_offline, other.link_offline
+            ));
+        }
+
+        changes
+    }
+}
+
+/// Complete state of one emulated turnstile.
+///
+/// State transitions go through [`transition_to()`](Self::transition_to) so
+/// the display and counters stay consistent with the state machine.
+///
+/// # Thread Safety
+///
+/// Like its components, this struct is not thread-safe. Wrap it in a
+/// `tokio::sync::Mutex` when shared between tasks.
+pub struct EmulatorCore {
+    state_machine: StateMachine,
+    pub(crate) display: VirtualDisplay,
+    pending: VecDeque<Message>,
+    pub(crate) counters: EmulatorCounters,
+    feedback: ValidationFeedback,
+    tampered: bool,
+    rex: Option<RexConfig>,
+    rex_passage: bool,
+    menu: Option<KeypadMenu>,
+    memory: DeviceMemory,
+    peripheral_stats: PeripheralStats,
+    pub(crate) known_users: Option<KnownUsers>,
+    pub(crate) prerendered: Option<String>,
+    pub(crate) link_offline: bool,
+    pub(crate) strictness: Strictness,
+}
+
+impl EmulatorCore {
+    /// Create an idle emulator using the given display.
+    pub fn new(display: VirtualDisplay) -> Self {
+        Self {
+            state_machine: StateMachine::new(),
+            display,
+            pending: VecDeque::new(),
+            counters: EmulatorCounters::default(),
+            feedback: ValidationFeedback::default(),
+            tampered: false,
+            rex: None,
+            rex_passage: false,
+            menu: None,
+            memory: DeviceMemory::default(),
+            peripheral_stats: PeripheralStats::default(),
+            known_users: None,
+            prerendered: None,
+            link_offline: false,
+            strictness: Strictness::default(),
+        }
+    }
+
+    /// Measure state timeouts with `clock` instead of the system clock.
+    ///
+    /// See [`StateMachine::with_clock()`].
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.state_machine = self.state_machine.with_clock(clock);
+        self
+    }
+
+    /// Use custom wait feedback timing.
+    pub fn with_validation_feedback(mut self, feedback: ValidationFeedback) -> Self {
+        self.feedback = feedback;
+        self
+    }
+
+    /// Accept request-to-exit inputs with the given settings.
+    pub fn with_rex(mut self, rex: RexConfig) -> Self {
+        self.rex = Some(rex);
+        self
+    }
+
+    /// Limit the card and template memory (default: 10000 of each).
+    ///
+    /// See [`handle_sync()`](Self::handle_sync).
+    pub fn with_memory(mut self, config: MemoryConfig) -> Self {
+        self.memory = DeviceMemory::new(config);
+        self
+    }
+
+    /// Cards and templates synced to this device.
+    pub fn memory(&self) -> &DeviceMemory {
+        &self.memory
+    }
+
+    /// Mutable access to the synced cards and templates.
+    pub fn memory_mut(&mut self) -> &mut DeviceMemory {
+        &mut self.memory
+    }
+
+    /// Connected peripherals, passages per direction and peripheral errors.
+    pub fn peripheral_stats(&self) -> &PeripheralStats {
+        &self.peripheral_stats
+    }
+
+    /// Mutable access to the peripheral statistics, e.g. to copy the
+    /// connection flags from a [`PeripheralManager`](turnkey_hardware::PeripheralManager).
+    pub fn peripheral_stats_mut(&mut self) -> &mut PeripheralStats {
+        &mut self.peripheral_stats
+    }
+
+    /// Count a read timeout or device error reported by a peripheral.
+    ///
+    /// Other events are ignored.
+    pub fn record_peripheral_event(&mut self, event: &PeripheralEvent) {
+        self.peripheral_stats.record_event(event, Utc::now());
+    }
+
+    /// Request-to-exit settings, if configured.
+    pub fn rex_config(&self) -> Option<&RexConfig> {
+        self.rex.as_ref()
+    }
+
+    /// Open a maintenance menu from the keypad with the given settings.
+    pub fn with_menu(mut self, config: MenuConfig) -> Self {
+        self.menu = Some(KeypadMenu::new(config));
+        self
+    }
+
+    /// Maintenance menu, if configured.
+    pub fn menu(&self) -> Option<&KeypadMenu> {
+        self.menu.as_ref()
+    }
+
+    /// Enable or disable exit requests at runtime.
+    ///
+    /// Does nothing if REX was never configured with
+    /// [`with_rex()`](Self::with_rex).
+    pub fn set_rex_enabled(&mut self, enabled: bool) {
+        if let Some(rex) = &mut self.rex {
+            rex.enabled = enabled;
+        }
+    }
+
+    /// Current turnstile state.
+    pub fn state(&self) -> TurnstileState {
+        *self.state_machine.current_state()
+    }
+
+    /// State machine (read-only).
+    pub fn state_machine(&self) -> &StateMachine {
+        &self.state_machine
+    }
+
+    /// Display (read-only).
+    pub fn display(&self) -> &VirtualDisplay {
+        &self.display
+    }
+
+    /// Display, for direct text updates such as server display commands.
+    pub fn display_mut(&mut self) -> &mut VirtualDisplay {
+        &mut self.display
+    }
+
+    /// Outcome counters.
+    pub fn counters(&self) -> EmulatorCounters {
+        self.counters
+    }
+
+    /// Whether the tamper switch is open.
+    pub fn is_tampered(&self) -> bool {
+        self.tampered
+    }
+
+    /// Transition the state machine, then update display and counters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` if the transition is not
+    /// allowed. Nothing changes in that case.
+    pub fn transition_to(&mut self, state: TurnstileState) -> Result<StateTransition> {
+        let transition = self.state_machine.transition_to(state)?;
+        self.on_transition(&transition);
+        Ok(transition)
+    }
+
+    /// Handle an expired state timeout, if any.
+    ///
+    /// See [`StateMachine::check_and_handle_timeout()`].
+    pub fn check_timeouts(&mut self) -> Result<Option<StateTransition>> {
+        let transition = self.state_machine.check_and_handle_timeout()?;
+        if let Some(transition) = &transition {
+            self.on_transition(transition);
+        }
+        Ok(transition)
+    }
+
+    /// Refresh the wait feedback while validating.
+    ///
+    /// Does nothing outside `Validating` or before the feedback delay.
+    /// Returns `true` if the display content changed.
+    pub fn update_validation_feedback(&mut self) -> bool {
+        if self.state() != TurnstileState::Validating {
+            return false;
+        }
+        let Some((line1, line2)) = self
+            .feedback
+            .lines(self.state_machine.time_in_current_state())
+        else {
+            return false;
+        };
+
+        let before = self.display.snapshot().buffer;
+        if self.prerendered.is_none() {
+            let _ = self.display.set_line_aligned(0, &line1, Alignment::Center);
+        }
+        let _ = self.display.set_line_aligned(1, &line2, Alignment::Center);
+        self.display.snapshot().buffer != before
+    }
+
+    /// Drive the wait feedback of a shared emulator until validation ends.
+    ///
+    /// Wakes up every `tick`, refreshes the feedback and returns as soon as
+    /// the emulator is no longer `Validating`. Spawn it right after the
+    /// transition into `Validating`; dropping or aborting the task stops
+    /// it early.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use tokio::sync::Mutex;
+    /// use turnkey_emulator::{EmulatorCore, TurnstileState};
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let emulator = Arc::new(Mutex::new(EmulatorCore::default()));
+    /// {
+    ///     let mut core = emulator.lock().await;
+    ///     core.transition_to(TurnstileState::Reading).unwrap();
+    ///     core.transition_to(TurnstileState::Validating).unwrap();
+    /// }
+    ///
+    /// let feedback = tokio::spawn({
+    ///     let emulator = emulator.clone();
+    ///     async move {
+    ///         EmulatorCore::run_validation_feedback(&emulator, Duration::from_millis(100)).await
+    ///     }
+    /// });
+    ///
+    /// // Response arrives: the feedback task ends on its own
+    /// emulator.lock().await.transition_to(TurnstileState::Granted).unwrap();
+    /// feedback.await.unwrap();
+    /// # }
+    /// ```
+    pub async fn run_validation_feedback(emulator: &Mutex<Self>, tick: Duration) {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            let mut core = emulator.lock().await;
+            if core.state() != TurnstileState::Validating {
+                return;
+            }
+            core.update_validation_feedback();
+        }
+    }
+
+    /// Wait for the card owner's fingerprint after the card was accepted.
+    ///
+    /// Moves `Validating → AwaitingBiometric` with `timeout` as the time
+    /// the person has to present a finger: a finger read moves back to
+    /// `Validating`, while [`check_timeouts()`](Self::check_timeouts)
+    /// denies the passage once the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the emulator is in
+    /// `Validating`. Nothing changes in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_emulator::{EmulatorCore, TurnstileState};
+    ///
+    /// let mut emulator = EmulatorCore::default();
+    /// emulator.transition_to(TurnstileState::Reading).unwrap();
+    /// emulator.transition_to(TurnstileState::Validating).unwrap();
+    ///
+    /// emulator.await_biometric(Duration::from_secs(10)).unwrap();
+    /// assert_eq!(emulator.state(), TurnstileState::AwaitingBiometric);
+    /// ```
+    pub fn await_biometric(&mut self, timeout: Duration) -> Result<StateTransition> {
+        let transition = self.state_machine.await_biometric(timeout)?;
+        self.on_transition(&transition);
+        Ok(transition)
+    }
+
+    /// Release the arm after a grant.
+    ///
+    /// Moves `Granted → WaitingRotation` with `timeout` as the rotation
+    /// timeout: an arm rotation completes the passage (see
+    /// [`handle_sensor_event()`](Self::handle_sensor_event)), while
+    /// [`check_timeouts()`](Self::check_timeouts) abandons it once the
+    /// timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the emulator is in
+    /// `Granted`. Nothing changes in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_emulator::{EmulatorCore, TurnstileState};
+    ///
+    /// let mut emulator = EmulatorCore::default();
+    /// assert!(emulator.release_rotation(Duration::from_secs(5)).is_err());
+    ///
+    /// for state in [
+    ///     TurnstileState::Reading,
+    ///     TurnstileState::Validating,
+    ///     TurnstileState::Granted,
+    /// ] {
+    ///     emulator.transition_to(state).unwrap();
+    /// }
+    /// emulator.release_rotation(Duration::from_secs(5)).unwrap();
+    ///
+    /// assert_eq!(emulator.state(), TurnstileState::WaitingRotation);
+    /// assert!(emulator.state_machine().time_remaining().is_some());
+    /// ```
+    pub fn release_rotation(&mut self, timeout: Duration) -> Result<StateTransition> {
+        if self.state() != TurnstileState::Granted {
+            return Err(Error::InvalidStateTransition {
+                from: self.state().to_string(),
+                to: TurnstileState::WaitingRotation.to_string(),
+            });
+        }
+        let transition = self.transition_to(TurnstileState::WaitingRotation)?;
+        self.state_machine.set_timeout(timeout);
+        Ok(transition)
+    }
+
+    /// Release the turnstile for a denied credential on an operator's decision.
+    ///
+    /// Moves `Denied → WaitingRotation` with the grant's release time as the
+    /// rotation timeout, and tells the user on the second display line that
+    /// the release came from the operator. The denial stays counted; the
+    /// override is counted separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the emulator is
+    /// showing a denial. Nothing changes in that case.
+    pub fn apply_override(&mut self, grant: &OverrideGrant) -> Result<StateTransition> {
+        let timeout = grant.timeout.as_duration();
+        let transition = self.state_machine.override_denial(timeout)?;
+        self.on_transition(&transition);
+        self.counters.overrides += 1;
+        let _ = self
+            .display
+            .set_line_aligned(1, OVERRIDE_LINE, Alignment::Center);
+        Ok(transition)
+    }
+
+    /// End a validation with the server's response.
+    ///
+    /// Grants move to `Granted` and denials to `Denied`, with a non-empty
+    /// server message on the second display line. A message-only response
+    /// also moves to `Denied`, since the arm stays locked, but shows the
+    /// message on the first line and returns to `Idle` once the response's
+    /// timeout expires (see [`check_timeouts()`](Self::check_timeouts)).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the emulator is in
+    /// `Validating`. Nothing changes in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_emulator::{EmulatorCore, TurnstileState};
+    /// use turnkey_protocol::commands::access::AccessResponse;
+    ///
+    /// let mut emulator = EmulatorCore::default();
+    /// emulator.transition_to(TurnstileState::Reading).unwrap();
+    /// emulator.transition_to(TurnstileState::Validating).unwrap();
+    ///
+    /// let response = AccessResponse::display_only("Dirija-se a recepcao".to_string());
+    /// emulator.apply_response(&response).unwrap();
+    ///
+    /// assert_eq!(emulator.state(), TurnstileState::Denied);
+    /// assert_eq!(emulator.display().get_line(0).unwrap().trim(), "Dirija-se a recepcao");
+    /// assert_eq!(emulator.counters().display_only, 1);
+    /// ```
+    pub fn apply_response(&mut self, response: &AccessResponse) -> Result<StateTransition> {
+        let message = response.display_message();
+
+        if response.is_display_only() {
+            let timeout = response.timeout().as_duration();
+            let transition = self.state_machine.show_message(timeout)?;
+            self.on_transition(&transition);
+            self.counters.display_only += 1;
+            if !message.is_empty() {
+                let _ = self.display.set_line_aligned(0, message, Alignment::Center);
+            }
+            return Ok(transition);
+        }
+
+        let state = if response.is_grant() {
+            TurnstileState::Granted
+        } else {
+            TurnstileState::Denied
+        };
+        let transition = self.transition_to(state)?;
+        if !message.is_empty() {
+            let _ = self.display.set_line_aligned(1, message, Alignment::Center);
+        }
+        Ok(transition)
+    }
+
+    /// Apply an event reported by the turnstile sensors.
+    ///
+    /// An arm rotation while `WaitingRotation` completes the passage
+    /// (`RotationInProgress → RotationCompleted`), and the transitions are
+    /// returned. A rotation in any other state, a forced door and an opened
+    /// tamper switch change no state: they are counted and shown on the
+    /// display for a few seconds. The tamper switch is counted once per
+    /// opening and cleared when it closes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a state transition fails, which the checks above
+    /// rule out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_core::AccessDirection;
+    /// use turnkey_emulator::{EmulatorCore, TurnstileState};
+    /// use turnkey_hardware::SensorEvent;
+    ///
+    /// let mut emulator = EmulatorCore::default();
+    ///
+    /// // Nobody released the arm
+    /// let transitions = emulator
+    ///     .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Entry))
+    ///     .unwrap();
+    /// assert!(transitions.is_empty());
+    /// assert_eq!(emulator.counters().unauthorized_rotations, 1);
+    /// assert_eq!(emulator.state(), TurnstileState::Idle);
+    /// ```
+    pub fn handle_sensor_event(&mut self, event: SensorEvent) -> Result<Vec<StateTransition>> {
+        if let SensorEvent::ArmRotated(direction) = event {
+            self.peripheral_stats.record_passage(direction, Utc::now());
+        }
+
+        let alarm = match event {
+            SensorEvent::ArmRotated(_) if self.state() == TurnstileState::WaitingRotation => {
+                return Ok(vec![
+                    self.transition_to(TurnstileState::RotationInProgress)?,
+                    self.transition_to(TurnstileState::RotationCompleted)?,
+                ]);
+            }
+            SensorEvent::ArmRotated(_) => {
+                self.counters.unauthorized_rotations += 1;
+                Some(UNAUTHORIZED_ROTATION_MESSAGE)
+            }
+            SensorEvent::DoorForced => {
+                self.counters.doors_forced += 1;
+                Some(DOOR_FORCED_MESSAGE)
+            }
+            SensorEvent::TamperOpened if !self.tampered => {
+                self.tampered = true;
+                self.counters.tamper_alarms += 1;
+                Some(TAMPER_MESSAGE)
+            }
+            SensorEvent::TamperClosed => {
+                self.tampered = false;
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(message) = alarm {
+            let _ = self.display.show_temporary(message, ALARM_DISPLAY_TIME);
+        }
+        Ok(Vec::new())
+    }
+
+    /// Feed every event reported by `sensor` into a shared emulator.
+    ///
+    /// Runs until the sensor fails and returns its error; a
+    /// [`MockSensor`](turnkey_hardware::mock::MockSensor) fails with
+    /// `Disconnected` once every handle has been dropped and its queued
+    /// events were delivered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use tokio::sync::Mutex;
+    /// use turnkey_emulator::EmulatorCore;
+    /// use turnkey_hardware::SensorEvent;
+    /// use turnkey_hardware::mock::MockSensor;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let emulator = Arc::new(Mutex::new(EmulatorCore::default()));
+    /// let (mut sensor, handle) = MockSensor::new();
+    ///
+    /// handle.inject(SensorEvent::DoorForced).await.unwrap();
+    /// drop(handle);
+    ///
+    /// let _ = EmulatorCore::run_sensors(&emulator, &mut sensor).await;
+    /// assert_eq!(emulator.lock().await.counters().doors_forced, 1);
+    /// # }
+    /// ```
+    pub async fn run_sensors<S: SensorDevice>(
+        emulator: &Mutex<Self>,
+        sensor: &mut S,
+    ) -> turnkey_hardware::Result<()> {
+        loop {
+            let event = sensor.read_event().await?;
+            emulator
+                .lock()
+                .await
+                .handle_sensor_event(event)
+                .map_err(|e| HardwareError::other(e.to_string()))?;
+        }
+    }
+
+    /// Release the turnstile for an exit button or motion detector.
+    ///
+    /// When REX is enabled and the turnstile is idle, moves it to
+    /// `WaitingRotation` for the configured release time without reading
+    /// or validating anything, shows the release on the display and queues
+    /// the `000+80` notification (exit direction, no card). The end of the
+    /// passage is notified the same way. Returns `None` and changes nothing
+    /// when REX is disabled or another passage is in progress.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the notification cannot be built, which the
+    /// fixed field contents rule out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_core::DeviceId;
+    /// use turnkey_emulator::{EmulatorCore, RexConfig, TurnstileState};
+    /// use turnkey_hardware::RexRequest;
+    ///
+    /// let rex = RexConfig::new(DeviceId::new(15).unwrap()).enabled(true);
+    /// let mut emulator = EmulatorCore::default().with_rex(rex);
+    ///
+    /// let transition = emulator.handle_rex_request(RexRequest::Button).unwrap();
+    /// assert!(transition.is_some());
+    /// assert_eq!(emulator.state(), TurnstileState::WaitingRotation);
+    /// assert_eq!(emulator.counters().rex_exits, 1);
+    /// assert_eq!(emulator.pending_messages().len(), 1);
+    /// ```
+    pub fn handle_rex_request(&mut self, request: RexRequest) -> Result<Option<StateTransition>> {
+        let Some(rex) = self.rex.filter(|rex| rex.enabled) else {
+            tracing::debug!(%request, "exit request ignored: REX disabled");
+            return Ok(None);
+        };
+        if self.state() != TurnstileState::Idle {
+            tracing::debug!(%request, state = %self.state(), "exit request ignored: turnstile busy");
+            return Ok(None);
+        }
+
+        let notification = rex_notification(rex.device_id, TurnstileState::WaitingRotation)?;
+        let transition = self.state_machine.request_exit(rex.release_time)?;
+        self.on_transition(&transition);
+        self.rex_passage = true;
+        self.counters.rex_exits += 1;
+        let _ = self
+            .display
+            .set_line_aligned(1, REX_LINE, Alignment::Center);
+        self.queue_message(notification);
+
+        tracing::info!(
+            device_id = %rex.device_id,
+            %request,
+            release_time = ?rex.release_time,
+            "exit released without validation"
+        );
+        Ok(Some(transition))
+    }
+
+    /// Feed every request reported by an exit input into a shared emulator.
+    ///
+    /// Runs until the input fails and returns its error, like
+    /// [`run_sensors()`](Self::run_sensors).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use tokio::sync::Mutex;
+    /// use turnkey_core::DeviceId;
+    /// use turnkey_emulator::{EmulatorCore, RexConfig};
+    /// use turnkey_hardware::mock::MockRex;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let rex = RexConfig::new(DeviceId::new(15).unwrap()).enabled(true);
+    /// let emulator = Arc::new(Mutex::new(EmulatorCore::default().with_rex(rex)));
+    /// let (mut button, handle) = MockRex::new();
+    ///
+    /// handle.press().await.unwrap();
+    /// drop(handle);
+    ///
+    /// let _ = EmulatorCore::run_rex(&emulator, &mut button).await;
+    /// assert_eq!(emulator.lock().await.counters().rex_exits, 1);
+    /// # }
+    /// ```
+    pub async fn run_rex<R: RexDevice>(
+        emulator: &Mutex<Self>,
+        rex: &mut R,
+    ) -> turnkey_hardware::Result<()> {
+        loop {
+            let request = rex.read_request().await?;
+            emulator
+                .lock()
+                .await
+                .handle_rex_request(request)
+                .map_err(|e| HardwareError::other(e.to_string()))?;
+        }
+    }
+
+    /// Pass one keypad input to the maintenance menu.
+    ///
+    /// Shows the resulting menu screen on the display and returns `true`
+    /// when the menu used the key. Returns `false` for keys the menu does
+    /// not want, when no menu is configured, and for every key while the
+    /// turnstile is not idle and the menu is closed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_emulator::EmulatorCore;
+    /// use turnkey_emulator::menu::MenuConfig;
+    /// use turnkey_hardware::KeypadInput;
+    ///
+    /// let mut emulator = EmulatorCore::default().with_menu(MenuConfig::new("0"));
+    ///
+    /// assert!(!emulator.handle_keypad_input(KeypadInput::Star));
+    /// assert!(emulator.handle_keypad_input(KeypadInput::Hash));
+    /// emulator.handle_keypad_input(KeypadInput::Digit(0));
+    /// assert!(emulator.handle_keypad_input(KeypadInput::Enter));
+    /// assert_eq!(emulator.display().get_line(0).unwrap().trim(), "MENU");
+    /// ```
+    pub fn handle_keypad_input(&mut self, input: KeypadInput) -> bool {
+        let Some(mut menu) = self.menu.take() else {
+            return false;
+        };
+        let screen = if menu.is_open() || self.state() == TurnstileState::Idle {
+            menu.handle_input(input, self)
+        } else {
+            menu.close();
+            None
+        };
+        self.menu = Some(menu);
+
+        match screen {
+            Some(MenuScreen::Lines(first, second)) => {
+                let _ = self.display.set_lines(&first, &second);
+                true
+            }
+            Some(MenuScreen::Closed(notice)) => {
+                let state = self.state();
+                self.display.update_from_state(&state);
+                if let Some(notice) = notice {
+                    let _ = self.display.show_temporary(&notice, MENU_NOTICE_TIME);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Feed every key pressed on a keypad into a shared emulator's menu.
+    ///
+    /// Runs until the keypad fails and returns its error, like
+    /// [`run_sensors()`](Self::run_sensors). Keys the menu does not use are
+    /// dropped.
+    pub async fn run_keypad<K: KeypadDevice>(
+        emulator: &Mutex<Self>,
+        keypad: &mut K,
+    ) -> turnkey_hardware::Result<()> {
+        loop {
+            let input = keypad.read_input().await?;
+            emulator.lock().await.handle_keypad_input(input);
+        }
+    }
+
+    /// Force the emulator back to idle.
+    pub fn reset(&mut self) -> StateTransition {
+        let transition = self.state_machine.reset();
+        self.on_transition(&transition);
+        transition
+    }
+
+    /// Queue a message for the server.
+    pub fn queue_message(&mut self, message: Message) {
+        self.pending.push_back(message);
+    }
+
+    /// Take the oldest queued message.
+    pub fn next_message(&mut self) -> Option<Message> {
+        self.pending.pop_front()
+    }
+
+    /// Messages waiting to be sent, oldest first.
+    pub fn pending_messages(&self) -> &VecDeque<Message> {
+        &self.pending
+    }
+
+    /// Current state for a status report, sent after (re)connecting or when
+    /// the server asks.
+    ///
+    /// The emulator does not own the validation mode, so the caller passes
+    /// the one it is configured with.
+    pub fn status_report(&self, validation_mode: ValidationMode) -> DeviceStatusReport {
+        let stats = &self.peripheral_stats;
+        // Whole seconds in device local time, as on the wire
+        let local = |at: Option<chrono::DateTime<Utc>>| {
+            at.and_then(|at| at.with_timezone(&Local).naive_local().with_nanosecond(0))
+        };
+        DeviceStatusReport {
+            validation_mode,
+            granted: self.counters.granted,
+            denied: self.counters.denied,
+            rotations: self.counters.rotations,
+            timeouts: self.counters.rotation_timeouts,
+            pending_events: u32::try_from(self.pending.len()).unwrap_or(u32::MAX),
+            passages: Some(PassageStats {
+                entries: stats.entries,
+                exits: stats.exits,
+                errors: stats.errors,
+                last_entry: local(stats.last_entry_at),
+                last_exit: local(stats.last_exit_at),
+                last_error: local(stats.last_error_at),
+            }),
+        }
+    }
+
+    /// Answer an `RQ` status query from the server.
+    ///
+    /// The report is queued for the server and returned; its pending event
+    /// count does not include the report itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` if `request` is not a status
+    /// query. Nothing is queued in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_core::{AccessDirection, DeviceId, ValidationMode};
+    /// use turnkey_emulator::EmulatorCore;
+    /// use turnkey_hardware::SensorEvent;
+    /// use turnkey_protocol::commands::DeviceStatusReport;
+    ///
+    /// let mut emulator = EmulatorCore::default();
+    /// emulator
+    ///     .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Exit))
+    ///     .unwrap();
+    ///
+    /// let query = DeviceStatusReport::query(DeviceId::new(15).unwrap()).unwrap();
+    /// let report = emulator
+    ///     .handle_status_query(&query, ValidationMode::Online)
+    ///     .unwrap();
+    /// let passages = report.passages.unwrap();
+    /// assert_eq!((passages.entries, passages.exits), (0, 1));
+    /// assert!(passages.last_exit.is_some());
+    ///
+    /// let answer = emulator.next_message().unwrap();
+    /// assert_eq!(DeviceStatusReport::parse(&answer).unwrap(), report);
+    /// ```
+    pub fn handle_status_query(
+        &mut self,
+        request: &Message,
+        validation_mode: ValidationMode,
+    ) -> Result<DeviceStatusReport> {
+        if !DeviceStatusReport::is_query(request) {
+            return Err(Error::InvalidCommandCode {
+                code: request.command.as_str().to_string(),
+            });
+        }
+
+        let report = self.status_report(validation_mode);
+        self.queue_message(report.to_message(request.device_id)?);
+        Ok(report)
+    }
+
+    /// Capture the complete emulator state.
+    pub fn snapshot(&self) -> EmulatorSnapshot {
+        EmulatorSnapshot {
+            state_machine: self.state_machine.snapshot(),
+            display: self.display.snapshot(),
+            pending: self.pending.iter().cloned().collect(),
+            counters: self.counters,
+            tampered: self.tampered,
+            rex_passage: self.rex_passage,
+            prerendered: self.prerendered.clone(),
+            link_offline: self.link_offline,
+        }
+    }
+
+    /// Replace the complete emulator state with a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if the display part of the snapshot is
+    /// inconsistent. The emulator is left untouched on error.
+    pub fn restore(&mut self, snapshot: EmulatorSnapshot) -> Result<()> {
+        let display = VirtualDisplay::from_snapshot(snapshot.display)?;
+
+        self.state_machine.restore(snapshot.state_machine);
+        self.display = display;
+        self.pending = snapshot.pending.into();
+        self.counters = snapshot.counters;
+        self.tampered = snapshot.tampered;
+        self.rex_passage = snapshot.rex_passage;
+        self.prerendered = snapshot.prerendered;
+        self.link_offline = snapshot.link_offline;
+        Ok(())
+    }
+
+    fn on_transition(&mut self, transition: &StateTransition) {
+        self.display.update_from_state(&transition.to);
+        self.counters.record(transition.to);
+        self.settle_prerender(transition.to);
+        self.show_link_state(transition.to);
+        if let Some(menu) = &mut self.menu {
+            menu.close();
+        }
+
+        if self.rex_passage && transition.to != TurnstileState::RotationInProgress {
+            self.rex_passage = false;
+            self.finish_rex_passage(transition.to);
+        }
+    }
+
+    /// Notify the server how a passage released by an exit request ended.
+    fn finish_rex_passage(&mut self, state: TurnstileState) {
+        if !matches!(
+            state,
+            TurnstileState::RotationCompleted | TurnstileState::RotationTimeout
+        ) {
+            return;
+        }
+        let Some(rex) = self.rex else {
+            return;
+        };
+
+        match rex_notification(rex.device_id, state) {
+            Ok(message) => {
+                tracing::info!(device_id = %rex.device_id, %state, "exit passage ended");
+                self.queue_message(message);
+            }
+            Err(e) => tracing::warn!(device_id = %rex.device_id, "exit notification failed: {}", e),
+        }
+    }
+}
+
+/// Build the turnstile status notification for a passage released by REX.
+fn rex_notification(device_id: DeviceId, state: TurnstileState) -> Result<Message> {
+    passage_notification(
+        device_id,
+        state,
+        None,
+        AccessDirection::Exit,
+        ReaderType::Rfid,
+        None,
+    )
+}
+
+/// Build the `000+80`/`000+81`/`000+82` notification for a passage.
+///
+/// `state` selects the command: `WaitingRotation`, `RotationCompleted`,
+/// and anything else for the abandoned rotation. `nsr` is the NSR of the
+/// passage's access event, sent only when known.
+pub(crate) fn passage_notification(
+    device_id: DeviceId,
+    state: TurnstileState,
+    card_number: Option<String>,
+    direction: AccessDirection,
+    reader_type: ReaderType,
+    nsr: Option<u64>,
+) -> Result<Message> {
+    let command = match state {
+        TurnstileState::WaitingRotation => CommandCode::WaitingRotation,
+        TurnstileState::RotationCompleted => CommandCode::RotationCompleted,
+        _ => CommandCode::RotationTimeout,
+    };
+    let mut status = TurnstileStatus::new(
+        state,
+        card_number,
+        HenryTimestamp::now(),
+        direction,
+        reader_type,
+    );
+    if let Some(nsr) = nsr {
+        status = status.with_nsr(nsr);
+    }
+    let fields = status
+        .to_fields()
+        .into_iter()
+        .map(FieldData::new)
+        .collect::<Result<Vec<_>>>()?;
+    Message::new(device_id, command, fields)
+}
+
+impl Default for EmulatorCore {
+    fn default() -> Self {
+        Self::new(VirtualDisplay::builder().build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use turnkey_core::{AccessDirection, DeviceId};
+    use turnkey_hardware::mock::MockSensor;
+    use turnkey_protocol::commands::access::AccessDecision;
+    use turnkey_protocol::{CommandCode, MessageBuilder};
+
+    fn grant_and_rotate(emulator: &mut EmulatorCore) {
+        for state in [
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Granted,
+            TurnstileState::WaitingRotation,
+            TurnstileState::RotationInProgress,
+            TurnstileState::RotationCompleted,
+            TurnstileState::Idle,
+        ] {
+            emulator.transition_to(state).unwrap();
+        }
+    }
+
+    fn status_request() -> Message {
+        MessageBuilder::new(DeviceId::new(15).unwrap(), CommandCode::QueryStatus)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_transition_updates_display_and_counters() {
+        let mut emulator = EmulatorCore::default();
+        grant_and_rotate(&mut emulator);
+
+        assert_eq!(emulator.state(), TurnstileState::Idle);
+        assert_eq!(emulator.counters().granted, 1);
+        assert_eq!(emulator.counters().rotations, 1);
+
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        assert_eq!(emulator.display().get_line(0).unwrap().trim(), "AGUARDE...");
+    }
+
+    #[test]
+    fn test_status_report_reflects_counters_and_queue() {
+        let mut emulator = EmulatorCore::default();
+        grant_and_rotate(&mut emulator);
+        emulator.queue_message(status_request());
+
+        let report = emulator.status_report(ValidationMode::Online);
+        assert_eq!(report.validation_mode, ValidationMode::Online);
+        assert_eq!(report.granted, 1);
+        assert_eq!(report.rotations, 1);
+        assert_eq!(report.denied, 0);
+        assert_eq!(report.pending_events, 1);
+    }
+
+    #[test]
+    fn test_status_query_reports_passages_and_errors() {
+        let mut emulator = EmulatorCore::default();
+        for direction in [
+            AccessDirection::Entry,
+            AccessDirection::Entry,
+            AccessDirection::Exit,
+        ] {
+            emulator
+                .handle_sensor_event(SensorEvent::ArmRotated(direction))
+                .unwrap();
+        }
+        emulator.record_peripheral_event(&PeripheralEvent::ReadTimeout {
+            device_type: turnkey_hardware::DeviceType::Rfid,
+            timeout: Duration::from_secs(5),
+        });
+
+        // Answers are rejected as queries
+        let answer = emulator.status_report(ValidationMode::Online);
+        let answer = answer.to_message(DeviceId::new(15).unwrap()).unwrap();
+        assert!(
+            emulator
+                .handle_status_query(&answer, ValidationMode::Online)
+                .is_err()
+        );
+        assert!(emulator.pending_messages().is_empty());
+
+        let report = emulator
+            .handle_status_query(&status_request(), ValidationMode::Online)
+            .unwrap();
+        let passages = report.passages.unwrap();
+        assert_eq!(
+            (passages.entries, passages.exits, passages.errors),
+            (2, 1, 1)
+        );
+        assert!(passages.last_entry.is_some() && passages.last_error.is_some());
+        assert_eq!(emulator.counters().unauthorized_rotations, 3);
+        assert_eq!(report.pending_events, 0);
+        assert_eq!(emulator.pending_messages().len(), 1);
+    }
+
+    #[test]
+    fn test_override_releases_denied_turnstile() {
+        let mut emulator = EmulatorCore::default();
+        for state in [
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Denied,
+        ] {
+            emulator.transition_to(state).unwrap();
+        }
+
+        let grant = OverrideGrant::new("12345678", "guarda-07", "Cracha esquecido")
+            .unwrap()
+            .with_timeout_seconds(7);
+        emulator.apply_override(&grant).unwrap();
+
+        assert_eq!(emulator.state(), TurnstileState::WaitingRotation);
+        assert!(emulator.state_machine().time_remaining().unwrap() <= Duration::from_secs(7));
+        assert_eq!(emulator.counters().denied, 1);
+        assert_eq!(emulator.counters().overrides, 1);
+        assert_eq!(emulator.counters().granted, 0);
+        assert!(
+            emulator
+                .display()
+                .get_line(1)
+                .unwrap()
+                .contains(OVERRIDE_LINE)
+        );
+
+        for state in [
+            TurnstileState::RotationInProgress,
+            TurnstileState::RotationCompleted,
+            TurnstileState::Idle,
+        ] {
+            emulator.transition_to(state).unwrap();
+        }
+        assert_eq!(emulator.counters().rotations, 1);
+    }
+
+    #[test]
+    fn test_display_only_response_keeps_turnstile_locked() {
+        let mut emulator = EmulatorCore::default();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+
+        let response = AccessResponse::new(
+            AccessDecision::DisplayOnly,
+            1,
+            "Dirija-se a recepcao".into(),
+        );
+        emulator.apply_response(&response).unwrap();
+
+        assert_eq!(emulator.state(), TurnstileState::Denied);
+        assert_eq!(
+            emulator.display().get_line(0).unwrap().trim(),
+            "Dirija-se a recepcao"
+        );
+        assert_eq!(emulator.counters().display_only, 1);
+        assert_eq!(emulator.counters().denied, 1);
+        assert_eq!(emulator.counters().granted, 0);
+        assert!(emulator.state_machine().time_remaining().unwrap() <= Duration::from_secs(1));
+
+        // An arm turned under a message is still unauthorized
+        emulator
+            .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Entry))
+            .unwrap();
+        assert_eq!(emulator.counters().unauthorized_rotations, 1);
+
+        std::thread::sleep(Duration::from_millis(1100));
+        let transition = emulator.check_timeouts().unwrap().unwrap();
+        assert_eq!(transition.to, TurnstileState::Idle);
+    }
+
+    #[test]
+    fn test_grant_response_shows_server_message() {
+        let mut emulator = EmulatorCore::default();
+        assert!(
+            emulator
+                .apply_response(&AccessResponse::grant_entry("Bem-vindo".into()))
+                .is_err()
+        );
+
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        emulator
+            .apply_response(&AccessResponse::grant_entry("Bem-vindo".into()))
+            .unwrap();
+
+        assert_eq!(emulator.state(), TurnstileState::Granted);
+        assert_eq!(
+            emulator.display().get_line(0).unwrap().trim(),
+            "ACESSO LIBERADO"
+        );
+        assert_eq!(emulator.display().get_line(1).unwrap().trim(), "Bem-vindo");
+        assert_eq!(emulator.counters().display_only, 0);
+    }
+
+    #[test]
+    fn test_override_outside_denial_changes_nothing() {
+        let mut emulator = EmulatorCore::default();
+        let before = emulator.snapshot();
+        let grant = OverrideGrant::new("12345678", "guarda-07", "Cracha esquecido").unwrap();
+
+        assert!(emulator.apply_override(&grant).is_err());
+        assert!(before.diff(&emulator.snapshot()).is_empty());
+    }
+
+    #[test]
+    fn test_arm_rotation_completes_released_passage() {
+        let mut emulator = EmulatorCore::default();
+        for state in [
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Granted,
+            TurnstileState::WaitingRotation,
+        ] {
+            emulator.transition_to(state).unwrap();
+        }
+
+        let transitions = emulator
+            .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Entry))
+            .unwrap();
+
+        let path: Vec<_> = transitions.iter().map(|t| t.to).collect();
+        assert_eq!(
+            path,
+            vec![
+                TurnstileState::RotationInProgress,
+                TurnstileState::RotationCompleted
+            ]
+        );
+        assert_eq!(emulator.counters().rotations, 1);
+        assert_eq!(emulator.counters().unauthorized_rotations, 0);
+    }
+
+    #[test]
+    fn test_sensor_alarms_are_counted_and_shown() {
+        let mut emulator = EmulatorCore::default();
+
+        emulator
+            .handle_sensor_event(SensorEvent::DoorForced)
+            .unwrap();
+        assert_eq!(emulator.counters().doors_forced, 1);
+        assert_eq!(
+            emulator.display().get_line(0).unwrap().trim(),
+            DOOR_FORCED_MESSAGE
+        );
+
+        // Counted once per opening
+        emulator
+            .handle_sensor_event(SensorEvent::TamperOpened)
+            .unwrap();
+        emulator
+            .handle_sensor_event(SensorEvent::TamperOpened)
+            .unwrap();
+        assert!(emulator.is_tampered());
+        assert_eq!(emulator.counters().tamper_alarms, 1);
+
+        let before = emulator.snapshot();
+        emulator
+            .handle_sensor_event(SensorEvent::TamperClosed)
+            .unwrap();
+        assert!(!emulator.is_tampered());
+        assert_eq!(
+            before.diff(&emulator.snapshot()),
+            vec!["tampered: true -> false".to_string()]
+        );
+        assert_eq!(emulator.state(), TurnstileState::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_run_sensors_feeds_injected_events() {
+        let emulator = Mutex::new(EmulatorCore::default());
+        let (mut sensor, handle) = MockSensor::new();
+
+        handle
+            .inject(SensorEvent::ArmRotated(AccessDirection::Exit))
+            .await
+            .unwrap();
+        handle.inject(SensorEvent::TamperOpened).await.unwrap();
+        drop(handle);
+
+        let result = EmulatorCore::run_sensors(&emulator, &mut sensor).await;
+        assert!(matches!(result, Err(HardwareError::Disconnected { .. })));
+
+        let core = emulator.lock().await;
+        assert_eq!(core.counters().unauthorized_rotations, 1);
+        assert_eq!(core.counters().tamper_alarms, 1);
+        assert!(core.snapshot().tampered);
+    }
+
+    fn rex_emulator(enabled: bool) -> EmulatorCore {
+        let rex = RexConfig::new(DeviceId::new(15).unwrap())
+            .enabled(enabled)
+            .with_release_time(Duration::from_millis(50));
+        EmulatorCore::default().with_rex(rex)
+    }
+
+    #[test]
+    fn test_rex_ignored_unless_enabled_and_idle() {
+        let mut emulator = EmulatorCore::default();
+        assert!(
+            emulator
+                .handle_rex_request(RexRequest::Button)
+                .unwrap()
+                .is_none()
+        );
+
+        let mut emulator = rex_emulator(false);
+        assert!(
+            emulator
+                .handle_rex_request(RexRequest::Button)
+                .unwrap()
+                .is_none()
+        );
+        emulator.set_rex_enabled(true);
+        assert!(
+            emulator
+                .handle_rex_request(RexRequest::Button)
+                .unwrap()
+                .is_some()
+        );
+
+        // A passage is already released
+        assert!(
+            emulator
+                .handle_rex_request(RexRequest::Motion)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(emulator.counters().rex_exits, 1);
+        assert_eq!(emulator.counters().granted, 0);
+    }
+
+    #[test]
+    fn test_rex_passage_notifies_release_and_rotation() {
+        let mut emulator = rex_emulator(true);
+
+        emulator.handle_rex_request(RexRequest::Button).unwrap();
+        assert_eq!(emulator.state(), TurnstileState::WaitingRotation);
+        assert!(
+            emulator
+                .display()
+                .get_line(1)
+                .unwrap()
+                .contains("Saida liberada")
+        );
+
+        emulator
+            .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Exit))
+            .unwrap();
+        assert_eq!(emulator.state(), TurnstileState::RotationCompleted);
+
+        let release = emulator.next_message().unwrap();
+        assert_eq!(release.command, CommandCode::WaitingRotation);
+        assert_eq!(release.device_id, DeviceId::new(15).unwrap());
+        assert_eq!(release.field(0).unwrap(), "");
+        assert_eq!(release.field(2).unwrap(), "2");
+
+        let done = emulator.next_message().unwrap();
+        assert_eq!(done.command, CommandCode::RotationCompleted);
+        assert_eq!(done.field(2).unwrap(), "2");
+        assert!(emulator.next_message().is_none());
+        assert!(!emulator.snapshot().rex_passage);
+    }
+
+    #[test]
+    fn test_rex_passage_timeout_notified() {
+        let mut emulator = rex_emulator(true);
+        emulator.handle_rex_request(RexRequest::Motion).unwrap();
+
+        std::thread::sleep(Duration::from_millis(80));
+        emulator.check_timeouts().unwrap();
+        assert_eq!(emulator.state(), TurnstileState::RotationTimeout);
+
+        let commands = emulator
+            .pending_messages()
+            .iter()
+            .map(|message| message.command)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            vec![CommandCode::WaitingRotation, CommandCode::RotationTimeout]
+        );
+    }
+
+    #[test]
+    fn test_regular_passage_sends_no_rex_notification() {
+        let mut emulator = rex_emulator(true);
+        grant_and_rotate(&mut emulator);
+        assert!(emulator.pending_messages().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_transition_changes_nothing() {
+        let mut emulator = EmulatorCore::default();
+        let before = emulator.snapshot();
+
+        assert!(emulator.transition_to(TurnstileState::Granted).is_err());
+        assert!(before.diff(&emulator.snapshot()).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_restore_mid_flow() {
+        let mut emulator = EmulatorCore::default();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        emulator.queue_message(status_request());
+        let checkpoint = emulator.snapshot();
+
+        emulator.transition_to(TurnstileState::Denied).unwrap();
+        emulator.next_message();
+
+        emulator.restore(checkpoint.clone()).unwrap();
+
+        assert_eq!(emulator.state(), TurnstileState::Validating);
+        assert_eq!(emulator.pending_messages().len(), 1);
+        assert_eq!(emulator.counters(), EmulatorCounters::default());
+        assert!(checkpoint.diff(&emulator.snapshot()).is_empty());
+
+        // The restored emulator continues the flow normally
+        emulator.transition_to(TurnstileState::Granted).unwrap();
+        assert_eq!(emulator.counters().granted, 1);
+    }
+
+    #[test]
+    fn test_snapshot_json_roundtrip() {
+        let mut emulator = EmulatorCore::default();
+        grant_and_rotate(&mut emulator);
+        emulator.queue_message(status_request());
+        emulator
+            .display_mut()
+            .show_temporary("BEM VINDO", Duration::from_secs(30))
+            .unwrap();
+
+        let snapshot = emulator.snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: EmulatorSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut restored = EmulatorCore::default();
+        restored.restore(decoded).unwrap();
+
+        assert!(snapshot.diff(&restored.snapshot()).is_empty());
+        assert_eq!(restored.state_machine().history().len(), 7);
+        assert!(!restored.display().is_default());
+    }
+
+    #[test]
+    fn test_restore_preserves_remaining_timeout() {
+        let mut emulator = EmulatorCore::default();
+        for state in [
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Granted,
+            TurnstileState::WaitingRotation,
+        ] {
+            emulator.transition_to(state).unwrap();
+        }
+
+        let mut snapshot = emulator.snapshot();
+        snapshot.state_machine.timeout = Some(Duration::from_secs(5));
+        snapshot.state_machine.time_in_state = Duration::from_secs(6);
+
+        emulator.restore(snapshot).unwrap();
+        let timeout = emulator.check_timeouts().unwrap().unwrap();
+
+        assert_eq!(timeout.to, TurnstileState::RotationTimeout);
+        assert_eq!(emulator.counters().rotation_timeouts, 1);
+    }
+
+    #[test]
+    fn test_restore_rejects_inconsistent_display() {
+        let mut emulator = EmulatorCore::default();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+
+        let mut snapshot = emulator.snapshot();
+        snapshot.display.buffer.pop();
+
+        assert!(emulator.restore(snapshot).is_err());
+        assert_eq!(emulator.state(), TurnstileState::Reading);
+    }
+
+    fn validating_for(elapsed: Duration) -> EmulatorCore {
+        let mut emulator = EmulatorCore::default();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+
+        let mut snapshot = emulator.snapshot();
+        snapshot.state_machine.time_in_state = elapsed;
+        emulator.restore(snapshot).unwrap();
+        emulator
+    }
+
+    #[test]
+    fn test_validation_feedback_waits_for_delay() {
+        let mut emulator = validating_for(Duration::from_millis(100));
+
+        assert!(!emulator.update_validation_feedback());
+        assert_eq!(
+            emulator.display().get_line(0).unwrap().trim(),
+            "VALIDANDO..."
+        );
+    }
+
+    #[test]
+    fn test_validation_feedback_animates_and_counts_seconds() {
+        let mut emulator = validating_for(Duration::from_millis(2400));
+
+        // 2100ms past the delay: frame 4, two dots
+        assert!(emulator.update_validation_feedback());
+        assert_eq!(
+            emulator.display().get_line(0).unwrap().trim(),
+            "VALIDANDO.."
+        );
+        assert_eq!(emulator.display().get_line(1).unwrap().trim(), "Aguarde 2s");
+
+        // Same frame: nothing to redraw
+        assert!(!emulator.update_validation_feedback());
+
+        let frames: Vec<_> = [300, 800, 1300, 1800]
+            .into_iter()
+            .map(|ms| {
+                ValidationFeedback::default()
+                    .lines(Duration::from_millis(ms))
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        assert_eq!(
+            frames,
+            vec![
+                "VALIDANDO.  ",
+                "VALIDANDO.. ",
+                "VALIDANDO...",
+                "VALIDANDO.  "
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validation_feedback_ignored_outside_validating() {
+        let mut emulator = EmulatorCore::default();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+
+        let mut snapshot = emulator.snapshot();
+        snapshot.state_machine.time_in_state = Duration::from_secs(5);
+        emulator.restore(snapshot).unwrap();
+
+        assert!(!emulator.update_validation_feedback());
+        assert_eq!(emulator.display().get_line(0).unwrap().trim(), "AGUARDE...");
+    }
+
+    #[tokio::test]
+    async fn test_run_validation_feedback_stops_on_response() {
+        let emulator = std::sync::Arc::new(Mutex::new(
+            EmulatorCore::default().with_validation_feedback(ValidationFeedback {
+                delay: Duration::ZERO,
+                frame: Duration::from_millis(10),
+            }),
+        ));
+        {
+            let mut core = emulator.lock().await;
+            core.transition_to(TurnstileState::Reading).unwrap();
+            core.transition_to(TurnstileState::Validating).unwrap();
+        }
+
+        let task = tokio::spawn({
+            let emulator = emulator.clone();
+            async move {
+                EmulatorCore::run_validation_feedback(&emulator, Duration::from_millis(5)).await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(
+            emulator
+                .lock()
+                .await
+                .display()
+                .get_line(1)
+                .unwrap()
+                .contains("Aguarde 0s")
+        );
+
+        emulator
+            .lock()
+            .await
+            .transition_to(TurnstileState::Granted)
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The response replaced the feedback for good
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            emulator.lock().await.display().get_line(0).unwrap().trim(),
+            "ACESSO LIBERADO"
+        );
+    }
+
+    fn open_menu(emulator: &mut EmulatorCore) -> bool {
+        [
+            KeypadInput::Star,
+            KeypadInput::Hash,
+            KeypadInput::Digit(4),
+            KeypadInput::Digit(2),
+            KeypadInput::Enter,
+        ]
+        .into_iter()
+        .map(|key| emulator.handle_keypad_input(key))
+        .last()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_menu_shown_on_display() {
+        let mut emulator = EmulatorCore::default().with_menu(MenuConfig::new("42"));
+        assert!(!emulator.handle_keypad_input(KeypadInput::Digit(1)));
+
+        assert!(open_menu(&mut emulator));
+        assert_eq!(emulator.display().get_line(0).unwrap().trim(), "MENU");
+        assert_eq!(
+            emulator.display().get_line(1).unwrap().trim(),
+            "1 Informacoes"
+        );
+
+        assert!(emulator.handle_keypad_input(KeypadInput::Cancel));
+        assert!(!emulator.menu().unwrap().is_open());
+        assert!(emulator.display().is_default());
+    }
+
+    #[test]
+    fn test_menu_wrong_code_flashes_notice() {
+        let mut emulator = EmulatorCore::default().with_menu(MenuConfig::new("1"));
+
+        assert!(open_menu(&mut emulator));
+        assert!(!emulator.menu().unwrap().is_open());
+        assert_eq!(
+            emulator.display().get_line(0).unwrap().trim(),
+            "Codigo invalido"
+        );
+    }
+
+    #[test]
+    fn test_menu_only_opens_when_idle_and_closes_on_transition() {
+        let mut emulator = EmulatorCore::default().with_menu(MenuConfig::new("42"));
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        assert!(!open_menu(&mut emulator));
+        assert!(!emulator.menu().unwrap().is_open());
+
+        emulator.reset();
+        assert!(open_menu(&mut emulator));
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        assert!(!emulator.menu().unwrap().is_open());
+        assert_eq!(emulator.display().get_line(0).unwrap().trim(), "AGUARDE...");
+    }
+
+    #[test]
+    fn test_diff_reports_changes() {
+        let mut emulator = EmulatorCore::default();
+        let before = emulator.snapshot();
+
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        emulator.transition_to(TurnstileState::Denied).unwrap();
+        emulator.queue_message(status_request());
+
+        let diff = before.diff(&emulator.snapshot());
+
+        assert!(diff.contains(&"state: Idle -> Denied".to_string()));
+        assert!(diff.contains(&"history: 0 -> 3 transitions".to_string()));
+        assert!(diff.contains(&"pending: 0 -> 1 messages".to_string()));
+        assert!(diff.contains(&"counters.denied: 0 -> 1".to_string()));
+        assert!(diff.iter().any(|d| d.starts_with("display line 0:")));
+    }
+}
@@ -0,0 +1,335 @@
+//! Fingerprint enrollment.
+//!
+//! Enrolling a finger takes several captures: the user places the finger on
+//! the scanner [`samples`](Enrollment::with_samples) times, each capture
+//! taken with [`BiometricDevice::enroll_fingerprint()`] so that poor
+//! placements are rejected and retried. The samples are merged into one
+//! template, which is stored for the user's finger position in the
+//! `biometric_templates` table (the same layout as the Henry `biometria.txt`
+//! export: `MATRICULA|POSICAO|TEMPLATE`).
+//!
+//! Scanners merge samples with vendor algorithms that the emulator cannot
+//! reproduce, so [`merge_samples()`] keeps the template of the best sample
+//! and reports the mean quality of all of them.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_emulator::enrollment::Enrollment;
+//! use turnkey_hardware::mock::MockBiometric;
+//! use turnkey_storage::repositories::SqliteBiometricTemplateRepository;
+//!
+//! # async fn example(db: turnkey_storage::Database) -> turnkey_core::Result<()> {
+//! let (mut scanner, _handle) = MockBiometric::new();
+//! let templates = SqliteBiometricTemplateRepository::new(db.pool().clone());
+//!
+//! // Right index finger of user 12345, four samples
+//! let enrolled = Enrollment::new("12345", 1)
+//!     .with_samples(4)
+//!     .run(&mut scanner, &templates)
+//!     .await?;
+//! println!("Template {} stored, quality {}", enrolled.template_id, enrolled.quality);
+//! # Ok(())
+//! # }
+//! ```
+
+use turnkey_core::{Error, Result};
+use turnkey_hardware::traits::{BiometricData, BiometricDevice};
+use turnkey_hardware::{HardwareError, LedColor};
+use turnkey_storage::repositories::BiometricTemplateRepository;
+
+/// Default number of samples merged into a template.
+pub const DEFAULT_ENROLLMENT_SAMPLES: usize = 3;
+
+/// Default number of rejected captures tolerated per enrollment.
+pub const DEFAULT_MAX_REJECTED: usize = 3;
+
+/// Template merged from enrollment samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedTemplate {
+    /// Template of the best sample
+    pub template: Vec<u8>,
+
+    /// Mean quality of the samples (0-100)
+    pub quality: u8,
+
+    /// Number of samples merged
+    pub samples: usize,
+}
+
+/// Result of a completed enrollment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnrolledFinger {
+    /// ID of the stored template
+    pub template_id: i64,
+
+    /// Matricula of the enrolled user
+    pub matricula: String,
+
+    /// Finger position (0-9)
+    pub posicao: i64,
+
+    /// Mean quality of the samples (0-100)
+    pub quality: u8,
+
+    /// Number of samples merged
+    pub samples: usize,
+
+    /// Number of captures rejected for low quality
+    pub rejected: usize,
+}
+
+/// Merge enrollment samples into one template.
+///
+/// Keeps the template of the highest quality sample (the first one on
+/// ties) and reports the mean quality of all samples, rounded down.
+///
+/// # Errors
+///
+/// Returns `Error::HardwareError` if there are no samples.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_emulator::enrollment::merge_samples;
+/// use turnkey_hardware::traits::BiometricData;
+///
+/// let samples = vec![
+///     BiometricData::new(vec![1; 4], 70).unwrap(),
+///     BiometricData::new(vec![2; 4], 90).unwrap(),
+///     BiometricData::new(vec![3; 4], 80).unwrap(),
+/// ];
+/// let merged = merge_samples(&samples).unwrap();
+/// assert_eq!(merged.template, vec![2; 4]);
+/// assert_eq!(merged.quality, 80);
+/// assert_eq!(merged.samples, 3);
+/// ```
+pub fn merge_samples(samples: &[BiometricData]) -> Result<MergedTemplate> {
+    let best = samples
+        .iter()
+        .rev()
+        .max_by_key(|sample| sample.quality)
+        .ok_or_else(|| Error::HardwareError("No enrollment samples to merge".to_string()))?;
+    let total: usize = samples
+        .iter()
+        .map(|sample| usize::from(sample.quality))
+        .sum();
+
+    Ok(MergedTemplate {
+        template: best.template.clone(),
+        quality: (total / samples.len()) as u8,
+        samples: samples.len(),
+    })
+}
+
+/// Enrollment of one finger of a user.
+///
+/// Configured with chainable builders, then [`run`](Self::run) against a
+/// scanner and a template repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enrollment {
+    matricula: String,
+    posicao: i64,
+    samples: usize,
+    max_rejected: usize,
+}
+
+impl Enrollment {
+    /// Enroll finger `posicao` (0-9) of the user with `matricula`.
+    pub fn new(matricula: impl Into<String>, posicao: i64) -> Self {
+        Self {
+            matricula: matricula.into(),
+            posicao,
+            samples: DEFAULT_ENROLLMENT_SAMPLES,
+            max_rejected: DEFAULT_MAX_REJECTED,
+        }
+    }
+
+    /// Set the number of samples merged into the template (at least 1).
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Set how many low quality captures are retried before giving up.
+    pub fn with_max_rejected(mut self, max_rejected: usize) -> Self {
+        self.max_rejected = max_rejected;
+        self
+    }
+
+    /// Capture the samples, merge them and store the template.
+    ///
+    /// The scanner LED is green once the template is stored and red if the
+    /// enrollment fails; LED errors are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::HardwareError` if a capture fails or more than
+    /// `max_rejected` captures are rejected for low quality,
+    /// `Error::RecordNotFound` if no user has the matricula, and
+    /// `Error::Database` if the template cannot be stored (invalid finger
+    /// position or template size, or biometrics not allowed for the user).
+    pub async fn run<B, R>(&self, scanner: &mut B, templates: &R) -> Result<EnrolledFinger>
+    where
+        B: BiometricDevice,
+        R: BiometricTemplateRepository,
+    {
+        let result = self.capture_and_store(scanner, templates).await;
+        let led = if result.is_ok() {
+            LedColor::Green
+        } else {
+            LedColor::Red
+        };
+        scanner.set_led(led).await.ok();
+        result
+    }
+
+    async fn capture_and_store<B, R>(
+        &self,
+        scanner: &mut B,
+        templates: &R,
+    ) -> Result<EnrolledFinger>
+    where
+        B: BiometricDevice,
+        R: BiometricTemplateRepository,
+    {
+        let mut samples = Vec::with_capacity(self.samples);
+        let mut rejected = 0;
+        while samples.len() < self.samples {
+            match scanner.enroll_fingerprint().await {
+                Ok(sample) => samples.push(sample),
+                Err(HardwareError::BiometricCaptureError { message })
+                    if rejected < self.max_rejected =>
+                {
+                    rejected += 1;
+                    tracing::debug!(rejected, "Enrollment sample rejected: {}", message);
+                }
+                Err(e) => return Err(Error::HardwareError(e.to_string())),
+            }
+        }
+
+        let merged = merge_samples(&samples)?;
+        let template_id = templates
+            .save(&self.matricula, self.posicao, &merged.template)
+            .await
+            .map_err(|e| match e {
+                turnkey_storage::StorageError::NotFound { value, .. } => {
+                    Error::RecordNotFound(format!("User with matricula {}", value))
+                }
+                turnkey_storage::StorageError::Database(e) => Error::Database(e.to_string()),
+                e => Error::Database(e.to_string()),
+            })?;
+
+        Ok(EnrolledFinger {
+            template_id,
+            matricula: self.matricula.clone(),
+            posicao: self.posicao,
+            quality: merged.quality,
+            samples: merged.samples,
+            rejected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use turnkey_hardware::mock::MockBiometric;
+    use turnkey_storage::Database;
+    use turnkey_storage::models::User;
+    use turnkey_storage::repositories::{
+        SqliteBiometricTemplateRepository, SqliteUserRepository, UserRepository,
+    };
+
+    async fn bio_user(db: &Database, matricula: &str) {
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Bio Test".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: true,
+            allow_bio: true,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteUserRepository::new(db.pool().clone())
+            .create(&user)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_enrollment_retries_low_quality_and_stores_best_sample() {
+        let db = Database::in_memory().await.unwrap();
+        bio_user(&db, "BIO01").await;
+        let templates = SqliteBiometricTemplateRepository::new(db.pool().clone());
+        let (mut scanner, handle) = MockBiometric::new();
+
+        handle.queue_fingerprint(vec![1; 600], 70).await.unwrap();
+        handle.queue_fingerprint(vec![2; 600], 20).await.unwrap();
+        handle.queue_fingerprint(vec![3; 600], 90).await.unwrap();
+        handle.queue_fingerprint(vec![4; 600], 80).await.unwrap();
+
+        let enrolled = Enrollment::new("BIO01", 1)
+            .run(&mut scanner, &templates)
+            .await
+            .unwrap();
+        assert_eq!(enrolled.samples, 3);
+        assert_eq!(enrolled.rejected, 1);
+        assert_eq!(enrolled.quality, 80);
+        assert_eq!(scanner.led_color(), LedColor::Green);
+
+        let stored = templates.find_by_matricula("BIO01").await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, enrolled.template_id);
+        assert_eq!(stored[0].posicao, 1);
+        assert_eq!(stored[0].template_data, vec![3; 600]);
+    }
+
+    #[tokio::test]
+    async fn test_enrollment_gives_up_after_max_rejected() {
+        let db = Database::in_memory().await.unwrap();
+        bio_user(&db, "BIO02").await;
+        let templates = SqliteBiometricTemplateRepository::new(db.pool().clone());
+        let (mut scanner, handle) = MockBiometric::new();
+
+        handle.queue_fingerprint(vec![1; 600], 10).await.unwrap();
+        handle.queue_fingerprint(vec![1; 600], 10).await.unwrap();
+
+        let result = Enrollment::new("BIO02", 0)
+            .with_max_rejected(1)
+            .run(&mut scanner, &templates)
+            .await;
+        assert!(matches!(result, Err(Error::HardwareError(_))));
+        assert_eq!(scanner.led_color(), LedColor::Red);
+        assert!(
+            templates
+                .find_by_matricula("BIO02")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enrollment_unknown_user() {
+        let db = Database::in_memory().await.unwrap();
+        let templates = SqliteBiometricTemplateRepository::new(db.pool().clone());
+        let (mut scanner, handle) = MockBiometric::new();
+        handle.queue_fingerprint(vec![1; 600], 90).await.unwrap();
+
+        let result = Enrollment::new("NOBODY", 0)
+            .with_samples(1)
+            .run(&mut scanner, &templates)
+            .await;
+        assert!(matches!(result, Err(Error::RecordNotFound(_))));
+    }
+}
@@ -0,0 +1,442 @@
+//! Self-test sweep and watchdog hook.
+//!
+//! Real turnstiles have a hardware watchdog that resets the device when its
+//! firmware stops responding. The emulator offers the same two pieces:
+//!
+//! - a health sweep, answered to the `RT` self-test command with a
+//!   [`SelfTestReport`]. [`EmulatorCore::self_test()`] checks that the
+//!   access flow is not stuck, and [`probe()`] turns any async check (a
+//!   peripheral's `get_info()`, the database's `health_check()`) into a
+//!   [`HealthCheck`] bounded by a time limit;
+//! - a [`Watchdog`] that runs the sweep on a timer and feeds a
+//!   [`WatchdogHook`] only while it passes. The host binary wires the hook
+//!   to its supervisor (e.g. systemd `WATCHDOG=1` notifications), so a hung
+//!   or stuck emulator stops the keepalives and gets restarted.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use turnkey_emulator::{EmulatorCore, Watchdog};
+//!
+//! static KEEPALIVES: AtomicUsize = AtomicUsize::new(0);
+//!
+//! let watchdog = Watchdog::new(|| {
+//!     KEEPALIVES.fetch_add(1, Ordering::Relaxed);
+//! });
+//!
+//! let report = watchdog.check(&EmulatorCore::default(), Vec::new());
+//! assert!(report.passed());
+//! assert_eq!(KEEPALIVES.load(Ordering::Relaxed), 1);
+//! ```
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+use turnkey_core::constants::{DELIMITER_DEVICE, DELIMITER_FIELD, DELIMITER_SUBFIELD};
+use turnkey_core::{Error, Result};
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::self_test::{HealthCheck, SelfTestReport};
+
+use crate::TurnstileState;
+use crate::emulator::EmulatorCore;
+
+/// Name of the access flow check in a self-test report.
+pub const STATE_MACHINE_CHECK: &str = "state_machine";
+
+/// Default time the access flow may stay away from `Idle` (5 minutes).
+pub const DEFAULT_MAX_BUSY_TIME: Duration = Duration::from_secs(300);
+
+/// Default time limit of each probe (500ms).
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Default time between watchdog sweeps (10 seconds).
+pub const DEFAULT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Limits beyond which a self-test check fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthThresholds {
+    /// Time the state machine may spend outside `Idle` (default: 5 minutes).
+    ///
+    /// Every passage returns to `Idle` through its own timeouts, so a flow
+    /// busy for longer than this is stuck.
+    pub max_busy_time: Duration,
+
+    /// Time limit of each probe (default: 500ms).
+    pub probe_timeout: Duration,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_busy_time: DEFAULT_MAX_BUSY_TIME,
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+        }
+    }
+}
+
+/// Run one check of a self-test within `limit`.
+///
+/// The check passes if `check` succeeds in time; the detail then holds the
+/// response time. An error or a missed deadline fails it.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use turnkey_emulator::health::probe;
+/// use turnkey_hardware::KeypadDevice;
+/// use turnkey_hardware::mock::MockKeypad;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (keypad, _handle) = MockKeypad::new();
+/// let check = probe("keypad", Duration::from_millis(500), keypad.get_info()).await;
+/// assert!(check.passed);
+/// # }
+/// ```
+pub async fn probe<T, E: fmt::Display>(
+    name: &str,
+    limit: Duration,
+    check: impl Future<Output = std::result::Result<T, E>>,
+) -> HealthCheck {
+    let started = Instant::now();
+    match tokio::time::timeout(limit, check).await {
+        Ok(Ok(_)) => HealthCheck::pass(
+            name,
+            format!("Responded in {}ms", started.elapsed().as_millis()),
+        ),
+        Ok(Err(e)) => HealthCheck::fail(name, field_safe(&e.to_string())),
+        Err(_) => HealthCheck::fail(name, format!("No response within {}ms", limit.as_millis())),
+    }
+}
+
+/// Replace protocol delimiters so the text fits in a report field.
+pub(crate) fn field_safe(text: &str) -> String {
+    [DELIMITER_FIELD, DELIMITER_DEVICE, DELIMITER_SUBFIELD]
+        .into_iter()
+        .fold(text.to_string(), |text, delimiter| {
+            text.replace(delimiter, " ")
+        })
+}
+
+impl EmulatorCore {
+    /// Check that the access flow is not stuck.
+    ///
+    /// Fails if the state machine has been outside `Idle` for longer than
+    /// [`HealthThresholds::max_busy_time`].
+    pub fn check_state_machine(&self, thresholds: &HealthThresholds) -> HealthCheck {
+        let state = self.state();
+        let elapsed = self.state_machine().time_in_current_state();
+        let detail = format!("{:?} for {}s", state, elapsed.as_secs());
+
+        if state != TurnstileState::Idle && elapsed > thresholds.max_busy_time {
+            HealthCheck::fail(STATE_MACHINE_CHECK, detail)
+        } else {
+            HealthCheck::pass(STATE_MACHINE_CHECK, detail)
+        }
+    }
+
+    /// Run the internal health sweep.
+    ///
+    /// `probes` holds the results of checks on what the emulator does not
+    /// own (peripherals, database), usually from [`probe()`]; they come first
+    /// in the report, followed by the emulator's own checks.
+    pub fn self_test(
+        &self,
+        thresholds: &HealthThresholds,
+        probes: impl IntoIterator<Item = HealthCheck>,
+    ) -> SelfTestReport {
+        let mut report = SelfTestReport::new(probes.into_iter().collect());
+        report.push(self.check_state_machine(thresholds));
+        report
+    }
+
+    /// Answer an `RT` self-test request from the server.
+    ///
+    /// Runs the sweep and queues the report for the server, returning it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` if `request` is not a self-test
+    /// request, or an error if the report cannot be encoded. Nothing is
+    /// queued in either case.
+    pub fn handle_self_test(
+        &mut self,
+        request: &Message,
+        thresholds: &HealthThresholds,
+        probes: impl IntoIterator<Item = HealthCheck>,
+    ) -> Result<SelfTestReport> {
+        if !SelfTestReport::is_request(request) {
+            return Err(Error::InvalidCommandCode {
+                code: request.command.as_str().to_string(),
+            });
+        }
+
+        let report = self.self_test(thresholds, probes);
+        self.queue_message(report.to_message(request.device_id)?);
+        tracing::info!(
+            device_id = %request.device_id,
+            passed = report.passed(),
+            "self-test answered"
+        );
+        Ok(report)
+    }
+}
+
+/// Receiver of watchdog notifications.
+///
+/// Any `Fn()` closure is a hook that only receives keepalives.
+pub trait WatchdogHook: Send + Sync {
+    /// Called after every sweep that passed.
+    fn keepalive(&self);
+
+    /// Called after a sweep that failed, instead of [`keepalive()`](Self::keepalive).
+    fn unhealthy(&self, report: &SelfTestReport) {
+        let _ = report;
+    }
+}
+
+impl<F: Fn() + Send + Sync> WatchdogHook for F {
+    fn keepalive(&self) {
+        self()
+    }
+}
+
+/// Periodic health sweep feeding a [`WatchdogHook`].
+///
+/// The interval must be well under the supervisor's own timeout (for
+/// systemd, about half of `WatchdogSec`) so one late sweep does not cause a
+/// restart.
+#[derive(Debug, Clone)]
+pub struct Watchdog<H> {
+    hook: H,
+    interval: Duration,
+    thresholds: HealthThresholds,
+}
+
+impl<H: WatchdogHook> Watchdog<H> {
+    /// Create a watchdog with the default interval and thresholds.
+    pub fn new(hook: H) -> Self {
+        Self {
+            hook,
+            interval: DEFAULT_WATCHDOG_INTERVAL,
+            thresholds: HealthThresholds::default(),
+        }
+    }
+
+    /// Use a custom time between sweeps.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Use custom health thresholds.
+    pub fn with_thresholds(mut self, thresholds: HealthThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Time between sweeps.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Health thresholds used by the sweep.
+    pub fn thresholds(&self) -> &HealthThresholds {
+        &self.thresholds
+    }
+
+    /// Run one sweep and notify the hook of its outcome.
+    pub fn check(
+        &self,
+        emulator: &EmulatorCore,
+        probes: impl IntoIterator<Item = HealthCheck>,
+    ) -> SelfTestReport {
+        let report = emulator.self_test(&self.thresholds, probes);
+        if report.passed() {
+            self.hook.keepalive();
+        } else {
+            let failed: Vec<_> = report.failures().map(|check| check.name.as_str()).collect();
+            tracing::warn!(?failed, "self-test failed, withholding watchdog keepalive");
+            self.hook.unhealthy(&report);
+        }
+        report
+    }
+
+    /// Sweep the emulator every interval, forever.
+    ///
+    /// Spawn it next to the emulator's other tasks. A task holding the
+    /// emulator lock indefinitely also stops the keepalives, as a hung
+    /// firmware would.
+    pub async fn run(&self, emulator: &Mutex<EmulatorCore>) {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            let core = emulator.lock().await;
+            self.check(&core, Vec::new());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::VirtualDisplay;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counts {
+        keepalives: AtomicUsize,
+        failures: AtomicUsize,
+    }
+
+    struct CountingHook(Arc<Counts>);
+
+    impl WatchdogHook for CountingHook {
+        fn keepalive(&self) {
+            self.0.keepalives.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn unhealthy(&self, _report: &SelfTestReport) {
+            self.0.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn busy_emulator() -> EmulatorCore {
+        let mut emulator = EmulatorCore::new(VirtualDisplay::builder().build());
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        emulator
+    }
+
+    #[test]
+    fn test_idle_state_machine_passes() {
+        let thresholds = HealthThresholds {
+            max_busy_time: Duration::ZERO,
+            ..HealthThresholds::default()
+        };
+        let check = EmulatorCore::default().check_state_machine(&thresholds);
+        assert!(check.passed);
+        assert_eq!(check.name, STATE_MACHINE_CHECK);
+    }
+
+    #[test]
+    fn test_stuck_state_machine_fails() {
+        let emulator = busy_emulator();
+        assert!(
+            emulator
+                .check_state_machine(&HealthThresholds::default())
+                .passed
+        );
+
+        std::thread::sleep(Duration::from_millis(20));
+        let thresholds = HealthThresholds {
+            max_busy_time: Duration::from_millis(10),
+            ..HealthThresholds::default()
+        };
+        let check = emulator.check_state_machine(&thresholds);
+        assert!(!check.passed);
+        assert!(check.detail.starts_with("Validating for"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_outcomes() {
+        let limit = Duration::from_millis(50);
+
+        let check = probe("database", limit, async { Ok::<_, String>(()) }).await;
+        assert!(check.passed);
+        assert!(check.detail.starts_with("Responded in"));
+
+        let check = probe("rfid", limit, async { Err::<(), _>("bus error [0x1f]") }).await;
+        assert!(!check.passed);
+        assert_eq!(check.detail, "bus error  0x1f ");
+
+        let check = probe(
+            "keypad",
+            limit,
+            std::future::pending::<std::result::Result<(), String>>(),
+        )
+        .await;
+        assert!(!check.passed);
+        assert_eq!(check.detail, "No response within 50ms");
+    }
+
+    #[test]
+    fn test_report_puts_probes_first() {
+        let report = EmulatorCore::default().self_test(
+            &HealthThresholds::default(),
+            [HealthCheck::fail("database", "unreachable")],
+        );
+        let names: Vec<_> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["database", STATE_MACHINE_CHECK]);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_self_test_request_queues_report() {
+        let device_id = turnkey_core::DeviceId::new(15).unwrap();
+        let mut emulator = EmulatorCore::default();
+        let request = SelfTestReport::request(device_id).unwrap();
+
+        let report = emulator
+            .handle_self_test(
+                &request,
+                &HealthThresholds::default(),
+                [HealthCheck::pass("rfid", "Responded in 1ms")],
+            )
+            .unwrap();
+        assert!(report.passed());
+
+        let answer = emulator.next_message().unwrap();
+        assert_eq!(answer.device_id, device_id);
+        assert_eq!(answer.as_self_test_report().unwrap(), report);
+
+        // A report is not a request
+        assert!(
+            emulator
+                .handle_self_test(&answer, &HealthThresholds::default(), Vec::new())
+                .is_err()
+        );
+        assert!(emulator.next_message().is_none());
+    }
+
+    #[test]
+    fn test_watchdog_withholds_keepalive_when_unhealthy() {
+        let counts = Arc::new(Counts::default());
+        let watchdog =
+            Watchdog::new(CountingHook(counts.clone())).with_thresholds(HealthThresholds {
+                max_busy_time: Duration::from_millis(10),
+                ..HealthThresholds::default()
+            });
+
+        assert!(
+            watchdog
+                .check(&EmulatorCore::default(), Vec::new())
+                .passed()
+        );
+        assert_eq!(counts.keepalives.load(Ordering::Relaxed), 1);
+
+        let emulator = busy_emulator();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!watchdog.check(&emulator, Vec::new()).passed());
+        assert_eq!(counts.keepalives.load(Ordering::Relaxed), 1);
+        assert_eq!(counts.failures.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_run_feeds_hook() {
+        let counts = Arc::new(Counts::default());
+        let watchdog =
+            Watchdog::new(CountingHook(counts.clone())).with_interval(Duration::from_millis(10));
+        let emulator = Mutex::new(EmulatorCore::default());
+
+        let _ = tokio::time::timeout(Duration::from_millis(55), watchdog.run(&emulator)).await;
+        assert!(counts.keepalives.load(Ordering::Relaxed) >= 3);
+        assert_eq!(counts.failures.load(Ordering::Relaxed), 0);
+    }
+}
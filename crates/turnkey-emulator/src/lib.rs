@@ -3,11 +3,33 @@
 //! This crate contains the state machine and logic for emulating
 //! physical access control devices like turnstiles.
 
+pub mod diagnostics;
 pub mod display;
+pub mod emulator;
+pub mod enrollment;
+pub mod health;
+pub mod link;
+pub mod memory;
+pub mod menu;
+pub mod prerender;
+pub mod runtime;
 pub mod state_machine;
+pub mod strictness;
 
-pub use display::{Alignment, VirtualDisplay, VirtualDisplayBuilder, align_text, truncate_text};
-pub use state_machine::{StateMachine, StateMachineBuilder, StateTransition};
+pub use display::{
+    Alignment, DisplaySnapshot, VirtualDisplay, VirtualDisplayBuilder, align_text, truncate_text,
+};
+pub use emulator::{
+    EmulatorCore, EmulatorCounters, EmulatorSnapshot, RexConfig, ValidationFeedback,
+};
+pub use enrollment::{EnrolledFinger, Enrollment, MergedTemplate};
+pub use health::{HealthThresholds, Watchdog, WatchdogHook};
+pub use memory::{DeviceMemory, EvictionPolicy, MemoryConfig};
+pub use menu::{KeypadMenu, Language, MenuConfig, MenuEntry};
+pub use prerender::KnownUsers;
+pub use runtime::{EmulatorRuntime, RuntimeConfig};
+pub use state_machine::{StateMachine, StateMachineBuilder, StateMachineSnapshot, StateTransition};
+pub use strictness::Strictness;
 
 // Re-export TurnstileState from protocol crate (single source of truth)
 pub use turnkey_protocol::commands::turnstile::TurnstileState;
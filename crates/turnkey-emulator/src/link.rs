@@ -0,0 +1,125 @@
+//! Server link state shown on the display.
+//!
+//! When the heartbeat monitor declares the server gone, the turnstile keeps
+//! working on local validation, but people at the gate and the operator
+//! walking by should be able to tell. [`EmulatorCore::set_link_online()`]
+//! records the link state reported by the monitor: while the link is down
+//! the second line of the idle screen reads "MODO OFFLINE", and every
+//! change is counted in [`EmulatorCounters::link_lost`] and
+//! [`EmulatorCounters::link_restored`].
+//!
+//! Screens of a passage in progress are left alone; the notice comes back
+//! with the next idle screen.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_emulator::EmulatorCore;
+//!
+//! let mut emulator = EmulatorCore::default();
+//! assert!(emulator.set_link_online(false));
+//! assert_eq!(emulator.display().get_line(1).unwrap().trim(), "MODO OFFLINE");
+//!
+//! assert!(emulator.set_link_online(true));
+//! assert_eq!(emulator.display().get_line(1).unwrap().trim(), "");
+//! assert_eq!(emulator.counters().link_lost, 1);
+//! ```
+//!
+//! [`EmulatorCounters::link_lost`]: crate::EmulatorCounters::link_lost
+//! [`EmulatorCounters::link_restored`]: crate::EmulatorCounters::link_restored
+
+use crate::TurnstileState;
+use crate::display::Alignment;
+use crate::emulator::EmulatorCore;
+
+/// Second idle line while the server is unreachable.
+const OFFLINE_LINE: &str = "MODO OFFLINE";
+
+impl EmulatorCore {
+    /// Whether the server link is considered up.
+    pub fn is_link_online(&self) -> bool {
+        !self.link_offline
+    }
+
+    /// Record the server link state reported by the heartbeat monitor.
+    ///
+    /// Returns `false` if the state is unchanged.
+    pub fn set_link_online(&mut self, online: bool) -> bool {
+        if self.link_offline != online {
+            return false;
+        }
+
+        self.link_offline = !online;
+        if online {
+            tracing::info!("server link restored");
+            self.counters.link_restored += 1;
+        } else {
+            tracing::warn!("server link lost");
+            self.counters.link_lost += 1;
+        }
+
+        if self.state() == TurnstileState::Idle {
+            let line = if online { "" } else { OFFLINE_LINE };
+            let _ = self.display.set_line_aligned(1, line, Alignment::Center);
+        }
+        true
+    }
+
+    /// Add the offline notice to a freshly drawn idle screen.
+    pub(crate) fn show_link_state(&mut self, state: TurnstileState) {
+        if self.link_offline && state == TurnstileState::Idle {
+            let _ = self
+                .display
+                .set_line_aligned(1, OFFLINE_LINE, Alignment::Center);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(emulator: &EmulatorCore, index: usize) -> String {
+        emulator
+            .display()
+            .get_line(index)
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn test_notice_waits_for_idle_screen() {
+        let mut emulator = EmulatorCore::default();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+
+        assert!(emulator.set_link_online(false));
+        assert!(!emulator.set_link_online(false));
+        assert_eq!(line(&emulator, 1), "Lendo credencial");
+
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        emulator.transition_to(TurnstileState::Denied).unwrap();
+        emulator.transition_to(TurnstileState::Idle).unwrap();
+        assert_eq!(line(&emulator, 1), OFFLINE_LINE);
+        assert!(!emulator.is_link_online());
+    }
+
+    #[test]
+    fn test_link_state_survives_snapshot() {
+        let mut emulator = EmulatorCore::default();
+        let before = emulator.snapshot();
+        emulator.set_link_online(false);
+
+        let after = emulator.snapshot();
+        assert!(
+            before
+                .diff(&after)
+                .contains(&"link_offline: false -> true".to_string())
+        );
+
+        let mut restored = EmulatorCore::default();
+        restored.restore(after).unwrap();
+        assert!(!restored.is_link_online());
+        assert_eq!(restored.counters().link_lost, 1);
+    }
+}
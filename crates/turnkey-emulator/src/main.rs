@@ -0,0 +1,425 @@
+//! `turnkey-emulator` - run one emulated turnstile
+//!
+//! ```text
+//! turnkey-emulator --device-id 15 --server 10.0.0.5:3000 --mode hybrid
+//! turnkey-emulator --database turnkey.db --mode offline
+//! turnkey-emulator --config turnkey.toml --device-id 16
+//! ```
+//!
+//! With `--config` the settings are read from a TOML file (see
+//! `turnkey_config`); the options given on the command line override it.
+//!
+//! Peripherals are simulated and driven from standard input, one command
+//! per line:
+//!
+//! ```text
+//! card 034FBA9E    present the card with this UID (hex)
+//! key 1            press a keypad key (0-9, *, #, enter, cancel, clear)
+//! rotate           turn the arm in the configured direction
+//! enroll 12345 1 3 enroll finger 1 (0-9) of user 12345 from 3 samples
+//!                  (the sample count is optional, default 3)
+//! quit             shut down
+//! ```
+//!
+//! Ctrl-C or the end of the input shuts the emulator down as well.
+//!
+//! Keypad codes are looked up with the PIN digest key read from
+//! `TURNKEY_SECRET_PIN_DIGEST_KEY` (at least 32 bytes); without it they are
+//! refused.
+
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use turnkey_config::TurnkeyConfig;
+use turnkey_core::AccessDirection;
+use turnkey_core::secrets::EnvSecrets;
+use turnkey_emulator::{EmulatorCore, EmulatorRuntime, Enrollment, RuntimeConfig, VirtualDisplay};
+use turnkey_hardware::devices::{AnyKeypadDevice, AnyRfidDevice};
+use turnkey_hardware::mock::{
+    MockBiometric, MockBiometricHandle, MockKeypad, MockKeypadHandle, MockRfid, MockRfidHandle,
+    MockSensor, MockSensorHandle,
+};
+use turnkey_hardware::traits::CardType;
+use turnkey_hardware::{KeypadInput, PeripheralManager, SensorEvent};
+use turnkey_network::{CancellationToken, TcpClient};
+use turnkey_storage::outbox::Outbox;
+use turnkey_storage::pin::PinDigestKey;
+use turnkey_storage::repositories::{SqliteAccessLogRepository, SqliteBiometricTemplateRepository};
+use turnkey_storage::retention::RetentionManager;
+use turnkey_storage::sync::SyncEngine;
+use turnkey_storage::{
+    Database, DatabaseConfig, HeartbeatMonitor, OfflineValidator, OnlineValidator,
+    OnlineValidatorConfig, OperatingMode, ValidationModeController,
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "turnkey-emulator",
+    about = "Run an emulated turnstile driven from standard input"
+)]
+struct Args {
+    /// Configuration file (TOML)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Device ID (1-99, default 1)
+    #[arg(long)]
+    device_id: Option<u8>,
+
+    /// Validation server (host:port); without it the device validates locally
+    #[arg(long)]
+    server: Option<String>,
+
+    /// Local database (in-memory database with test data if omitted)
+    #[arg(long)]
+    database: Option<String>,
+
+    /// Validation mode (default: hybrid with a server, offline without)
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
+
+    /// Direction of the passages through this device (default: entry)
+    #[arg(long, value_enum)]
+    direction: Option<Direction>,
+
+    /// Seconds without traffic before a keepalive is sent (0 disables,
+    /// default: 30)
+    #[arg(long)]
+    heartbeat: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Mode {
+    Online,
+    Offline,
+    Hybrid,
+}
+
+impl From<Mode> for OperatingMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Online => OperatingMode::Online,
+            Mode::Offline => OperatingMode::Offline,
+            Mode::Hybrid => OperatingMode::Hybrid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Direction {
+    Entry,
+    Exit,
+}
+
+impl From<Direction> for turnkey_config::Direction {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Entry => turnkey_config::Direction::Entry,
+            Direction::Exit => turnkey_config::Direction::Exit,
+        }
+    }
+}
+
+/// Simulated devices fed from standard input
+struct Inputs {
+    rfid: MockRfidHandle,
+    keypad: MockKeypadHandle,
+    sensor: MockSensorHandle,
+    direction: AccessDirection,
+    scanner: MockBiometric,
+    fingers: MockBiometricHandle,
+    templates: SqliteBiometricTemplateRepository,
+}
+
+impl Inputs {
+    /// Apply one input line; returns `false` on `quit`
+    async fn apply(&mut self, line: &str) -> Result<bool, String> {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (None, _) => {}
+            (Some("quit"), None) => return Ok(false),
+            (Some("rotate"), None) => self
+                .sensor
+                .inject(SensorEvent::ArmRotated(self.direction))
+                .await
+                .map_err(|e| e.to_string())?,
+            (Some("card"), Some(uid)) => {
+                let uid = parse_uid(uid)?;
+                self.rfid
+                    .add_card(uid.clone(), CardType::MifareClassic1K)
+                    .await;
+                self.rfid
+                    .present_card(uid)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            (Some("key"), Some(key)) => {
+                let input = parse_key(key)?;
+                self.keypad
+                    .send_input(input)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            (Some("enroll"), Some(matricula)) => {
+                let (posicao, samples) = parse_enrollment(words.next(), words.next())?;
+                self.enroll(matricula, posicao, samples).await?;
+            }
+            _ => return Err(format!("unknown command '{}'", line.trim())),
+        }
+        Ok(true)
+    }
+
+    /// Enroll a finger from simulated samples of the same placement
+    async fn enroll(
+        &mut self,
+        matricula: &str,
+        posicao: i64,
+        samples: usize,
+    ) -> Result<(), String> {
+        let template = synthetic_template(matricula, posicao);
+        for _ in 0..samples {
+            self.fingers
+                .present_finger(template.clone())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        let enrolled = Enrollment::new(matricula, posicao)
+            .with_samples(samples)
+            .run(&mut self.scanner, &self.templates)
+            .await
+            .map_err(|e| e.to_string())?;
+        println!(
+            "Enrolled finger {} of {}: {} samples, quality {}",
+            enrolled.posicao, enrolled.matricula, enrolled.samples, enrolled.quality
+        );
+        Ok(())
+    }
+}
+
+fn parse_enrollment(posicao: Option<&str>, samples: Option<&str>) -> Result<(i64, usize), String> {
+    let posicao = posicao
+        .ok_or("usage: enroll <matricula> <finger> [samples]")?
+        .parse()
+        .map_err(|_| "finger position must be 0-9".to_string())?;
+    let samples = match samples {
+        Some(samples) => samples
+            .parse()
+            .ok()
+            .filter(|&samples| samples > 0)
+            .ok_or("sample count must be a positive number")?,
+        None => turnkey_emulator::enrollment::DEFAULT_ENROLLMENT_SAMPLES,
+    };
+    Ok((posicao, samples))
+}
+
+/// Template standing in for a scanned finger, stable per user and finger
+fn synthetic_template(matricula: &str, posicao: i64) -> Vec<u8> {
+    let seed: Vec<u8> = matricula.bytes().chain([posicao as u8]).collect();
+    seed.iter()
+        .cycle()
+        .take(512)
+        .enumerate()
+        .map(|(i, byte)| byte.wrapping_add(i as u8))
+        .collect()
+}
+
+fn parse_uid(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) || hex.len() > 20 {
+        return Err(format!("card UID '{}' must be 1-10 hex bytes", hex));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("card UID '{}' is not hexadecimal", hex))
+}
+
+fn parse_key(key: &str) -> Result<KeypadInput, String> {
+    match key {
+        "*" => Ok(KeypadInput::Star),
+        "#" => Ok(KeypadInput::Hash),
+        "enter" => Ok(KeypadInput::Enter),
+        "cancel" => Ok(KeypadInput::Cancel),
+        "clear" => Ok(KeypadInput::Clear),
+        digit => digit
+            .parse::<u8>()
+            .ok()
+            .and_then(|digit| KeypadInput::digit(digit).ok())
+            .ok_or_else(|| format!("unknown key '{}'", key)),
+    }
+}
+
+async fn open_database(path: Option<&str>) -> Result<Database, String> {
+    let db = match path {
+        Some(path) => Database::new(DatabaseConfig::new(path)).await,
+        None => Database::in_memory().await,
+    };
+    db.map_err(|e| format!("cannot open database: {}", e))
+}
+
+/// Load the configuration file and apply the command-line overrides
+fn configuration(args: &Args) -> Result<TurnkeyConfig, String> {
+    let mut config = match &args.config {
+        Some(path) => TurnkeyConfig::load(path).map_err(|e| e.to_string())?,
+        None => TurnkeyConfig::default(),
+    };
+    if let Some(device_id) = args.device_id {
+        config.device_id = device_id;
+    }
+    if let Some(server) = &args.server {
+        config.server.address = Some(server.clone());
+    }
+    if let Some(mode) = args.mode {
+        config.mode = Some(mode.into());
+    }
+    if let Some(direction) = args.direction {
+        config.direction = direction.into();
+    }
+    if let Some(heartbeat) = args.heartbeat {
+        config.timeouts.heartbeat_secs = heartbeat;
+    }
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+/// Build the runtime and the inputs that drive its devices
+async fn setup(args: &Args) -> Result<(EmulatorRuntime, Inputs), String> {
+    let config = configuration(args)?;
+    let runtime_config = RuntimeConfig::from_file(&config).map_err(|e| e.to_string())?;
+    let device_id = runtime_config.device_id;
+    let client_config = config.tcp_client_config().map_err(|e| e.to_string())?;
+
+    let db = open_database(args.database.as_deref()).await?;
+    let online = OnlineValidator::new(
+        TcpClient::new(client_config.clone().unwrap_or_default()),
+        device_id,
+        OnlineValidatorConfig::default(),
+    );
+    let mut offline = OfflineValidator::new(db.pool().clone())
+        .with_device_id(device_id)
+        .with_anti_passback_policy(config.anti_passback_policy());
+    // Keypad codes are only looked up with the key kept outside the database
+    match PinDigestKey::from_secrets(&EnvSecrets::default()) {
+        Ok(key) => offline = offline.with_pin_digest_key(key),
+        Err(e) => eprintln!("Warning: keypad codes are refused: {}", e),
+    }
+    // Accesses decided locally are reported once the server is reachable
+    if client_config.is_some() {
+        offline = offline.with_outbox(Outbox::new(db.pool().clone()));
+    }
+    let controller =
+        ValidationModeController::new(device_id, online, offline, config.operating_mode());
+
+    let (rfid, rfid_handle) = MockRfid::new();
+    let (keypad, keypad_handle) = MockKeypad::new();
+    let (sensor, sensor_handle) = MockSensor::new();
+    let (scanner, fingers) = MockBiometric::new();
+    let mut manager = PeripheralManager::new(config.peripheral_config());
+    manager.register_rfid(AnyRfidDevice::Mock(rfid));
+    manager.register_keypad(AnyKeypadDevice::Mock(keypad));
+
+    let display = VirtualDisplay::builder()
+        .with_file_settings(&config.display)
+        .build();
+    let mut runtime = EmulatorRuntime::new(
+        runtime_config,
+        EmulatorCore::new(display),
+        controller,
+        manager.start(),
+    )
+    .with_access_logs(SqliteAccessLogRepository::new(db.pool().clone()));
+    tokio::spawn({
+        let emulator = runtime.emulator();
+        let mut sensor = sensor;
+        async move { EmulatorCore::run_sensors(&emulator, &mut sensor).await }
+    });
+
+    if let Some(client_config) = client_config {
+        let mut notifier = TcpClient::new(client_config);
+        if let Some(heartbeat) = config.heartbeat().map_err(|e| e.to_string())? {
+            notifier.enable_heartbeat(device_id, heartbeat);
+            let monitor =
+                HeartbeatMonitor::new(config.heartbeat_config()).map_err(|e| e.to_string())?;
+            runtime = runtime.with_heartbeat(monitor);
+        }
+        runtime = runtime
+            .with_notifier(notifier)
+            .with_sync(SyncEngine::new(db.pool().clone()));
+    }
+
+    if let Some(policy) = config.retention_policy() {
+        let mut retention = RetentionManager::new(db.pool().clone(), policy);
+        if let Some(dir) = &config.retention.archive_dir {
+            retention = retention.with_archive_dir(dir);
+        }
+        runtime = runtime.with_retention(retention, config.retention.interval());
+    }
+
+    let inputs = Inputs {
+        rfid: rfid_handle,
+        keypad: keypad_handle,
+        sensor: sensor_handle,
+        direction: config.access_direction(),
+        scanner,
+        fingers,
+        templates: SqliteBiometricTemplateRepository::new(db.pool().clone()),
+    };
+    Ok((runtime, inputs))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+    let (runtime, mut inputs) = match setup(&args).await {
+        Ok(setup) => setup,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let stop = CancellationToken::new();
+    tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                stop.cancel();
+            }
+        }
+    });
+    tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            let mut lines = BufReader::new(tokio::io::stdin()).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match inputs.apply(&line).await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+            stop.cancel();
+        }
+    });
+
+    match runtime.run(stop).await {
+        Ok(counters) => {
+            println!(
+                "Stopped: {} granted, {} denied, {} rotations, {} timeouts",
+                counters.granted, counters.denied, counters.rotations, counters.rotation_timeouts
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
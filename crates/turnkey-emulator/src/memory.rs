@@ -0,0 +1,567 @@
+//! Finite card and template memory of an emulated device.
+//!
+//! Real turnstiles store their whitelist in a few megabytes of flash, so a
+//! server syncing a large database eventually hits the device's limits.
+//! [`DeviceMemory`] keeps the cards and fingerprint templates synced with
+//! `ECAR`/`ED` batches within configured capacities and decides, through an
+//! [`EvictionPolicy`], what happens when a batch does not fit:
+//!
+//! - [`EvictionPolicy::Reject`] stops the batch and answers `MemoryFull`, as
+//!   the Henry equipment does,
+//! - [`EvictionPolicy::Oldest`] drops the entries stored first,
+//! - [`EvictionPolicy::LeastRecentlyUsed`] drops the entries read least
+//!   recently, counting both syncs and local card lookups.
+//!
+//! Capacity is counted in slots: one per card and one per template, so a
+//! user with three fingerprints takes three template slots. Evicted entries
+//! are reported in the answer so the server can tell what the device no
+//! longer holds.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_core::DeviceId;
+//! use turnkey_emulator::{EmulatorCore, EvictionPolicy, MemoryConfig};
+//! use turnkey_protocol::commands::sync::{CardSync, SyncOperation, SyncStatus};
+//!
+//! let config = MemoryConfig::new(2, 10).with_eviction(EvictionPolicy::Reject);
+//! let mut emulator = EmulatorCore::default().with_memory(config);
+//!
+//! let cards = ["11111111", "22222222", "33333333"].map(String::from).to_vec();
+//! let batch = CardSync::new(SyncOperation::Insert, cards)
+//!     .to_message(DeviceId::new(15).unwrap())
+//!     .unwrap();
+//!
+//! let ack = emulator.handle_sync(&batch).unwrap();
+//! assert_eq!(ack.status, SyncStatus::MemoryFull);
+//! assert_eq!(ack.applied, 2);
+//! assert_eq!(emulator.memory().card_count(), 2);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use turnkey_core::{Error, Result};
+use turnkey_protocol::commands::sync::{
+    CardSync, SyncAck, SyncOperation, SyncStatus, TemplateRecord, TemplateSync,
+};
+use turnkey_protocol::{CommandCode, Message};
+
+use crate::emulator::EmulatorCore;
+
+/// Default card capacity, matching the Henry Primme line.
+pub const DEFAULT_MAX_CARDS: usize = 10_000;
+
+/// Default template capacity, matching the Henry Primme line.
+pub const DEFAULT_MAX_TEMPLATES: usize = 10_000;
+
+/// What a full memory does with an entry that does not fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Refuse the entry and stop the batch (device behavior)
+    #[default]
+    Reject,
+
+    /// Drop the entries stored first
+    Oldest,
+
+    /// Drop the entries read or written least recently
+    LeastRecentlyUsed,
+}
+
+/// Capacities and eviction policy of a device's memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// Maximum number of cards (default: 10000)
+    pub max_cards: usize,
+
+    /// Maximum number of templates, counted per fingerprint (default: 10000)
+    pub max_templates: usize,
+
+    /// What to do when a batch does not fit (default: reject)
+    pub eviction: EvictionPolicy,
+}
+
+impl MemoryConfig {
+    /// Capacities with the default policy.
+    pub fn new(max_cards: usize, max_templates: usize) -> Self {
+        Self {
+            max_cards,
+            max_templates,
+            eviction: EvictionPolicy::default(),
+        }
+    }
+
+    /// Set the eviction policy.
+    pub fn with_eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.eviction = eviction;
+        self
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CARDS, DEFAULT_MAX_TEMPLATES)
+    }
+}
+
+/// Entry in a [`Table`].
+#[derive(Debug)]
+struct Slot {
+    /// Templates of a user; empty for a card
+    values: Vec<String>,
+
+    /// Position in the eviction order
+    sequence: u64,
+}
+
+impl Slot {
+    /// Capacity taken: one per template, one for a card
+    fn size(&self) -> usize {
+        self.values.len().max(1)
+    }
+}
+
+/// Entries of one kind with a capacity counted in slots.
+#[derive(Debug)]
+struct Table {
+    capacity: usize,
+    used: usize,
+    next_sequence: u64,
+    slots: HashMap<String, Slot>,
+
+    /// Keys by sequence, first to evict first
+    order: BTreeMap<u64, String>,
+}
+
+/// A table could not make room for an entry.
+struct Full;
+
+impl Table {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            used: 0,
+            next_sequence: 0,
+            slots: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn sequence(&mut self) -> u64 {
+        self.next_sequence += 1;
+        self.next_sequence
+    }
+
+    /// Store `values` under `key`, replacing or extending what is there.
+    ///
+    /// Entries evicted to make room are appended to `evicted`. Nothing
+    /// changes when the entry does not fit.
+    fn store(
+        &mut self,
+        key: &str,
+        values: Vec<String>,
+        append: bool,
+        policy: EvictionPolicy,
+        evicted: &mut Vec<String>,
+    ) -> std::result::Result<(), Full> {
+        let (old_size, mut merged) = match self.slots.get(key) {
+            Some(slot) if append => (slot.size(), slot.values.clone()),
+            Some(slot) => (slot.size(), Vec::new()),
+            None => (0, Vec::new()),
+        };
+        merged.extend(values);
+        let new_size = merged.len().max(1);
+        if new_size > self.capacity {
+            return Err(Full);
+        }
+
+        while self.used - old_size + new_size > self.capacity {
+            if policy == EvictionPolicy::Reject {
+                return Err(Full);
+            }
+            let victim = self
+                .order
+                .values()
+                .find(|victim| victim.as_str() != key)
+                .cloned()
+                .ok_or(Full)?;
+            self.remove(&victim);
+            evicted.push(victim);
+        }
+
+        let keep_position = policy == EvictionPolicy::Oldest;
+        let sequence = match self.slots.get(key) {
+            Some(slot) if keep_position => slot.sequence,
+            _ => self.sequence(),
+        };
+        if let Some(old) = self.slots.insert(
+            key.to_string(),
+            Slot {
+                values: merged,
+                sequence,
+            },
+        ) {
+            self.order.remove(&old.sequence);
+            self.used -= old.size();
+        }
+        self.order.insert(sequence, key.to_string());
+        self.used += new_size;
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        let Some(slot) = self.slots.remove(key) else {
+            return false;
+        };
+        self.order.remove(&slot.sequence);
+        self.used -= slot.size();
+        true
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.order.clear();
+        self.used = 0;
+    }
+
+    /// Mark an entry as read, for least-recently-used eviction.
+    fn touch(&mut self, key: &str) -> bool {
+        let Some(old) = self.slots.get(key).map(|slot| slot.sequence) else {
+            return false;
+        };
+        let sequence = self.sequence();
+        if let Some(slot) = self.slots.get_mut(key) {
+            slot.sequence = sequence;
+        }
+        self.order.remove(&old);
+        self.order.insert(sequence, key.to_string());
+        true
+    }
+}
+
+/// Cards and templates stored on an emulated device.
+#[derive(Debug)]
+pub struct DeviceMemory {
+    config: MemoryConfig,
+    cards: Table,
+    templates: Table,
+}
+
+impl DeviceMemory {
+    /// Empty memory with the given capacities.
+    pub fn new(config: MemoryConfig) -> Self {
+        Self {
+            config,
+            cards: Table::new(config.max_cards),
+            templates: Table::new(config.max_templates),
+        }
+    }
+
+    /// Capacities and eviction policy.
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// Number of stored cards.
+    pub fn card_count(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Number of stored templates, counted per fingerprint.
+    pub fn template_count(&self) -> usize {
+        self.templates.used
+    }
+
+    /// Whether a card is stored, without counting it as a use.
+    pub fn contains_card(&self, card_number: &str) -> bool {
+        self.cards.slots.contains_key(card_number)
+    }
+
+    /// Look a card up for a local validation.
+    ///
+    /// Counts as a use under [`EvictionPolicy::LeastRecentlyUsed`].
+    pub fn lookup_card(&mut self, card_number: &str) -> bool {
+        if self.config.eviction == EvictionPolicy::LeastRecentlyUsed {
+            self.cards.touch(card_number)
+        } else {
+            self.contains_card(card_number)
+        }
+    }
+
+    /// Templates stored for a user.
+    pub fn templates(&self, matricula: &str) -> &[String] {
+        self.templates
+            .slots
+            .get(matricula)
+            .map(|slot| slot.values.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Apply a card batch, in order, until one does not fit.
+    pub fn apply_cards(&mut self, batch: &CardSync) -> SyncAck {
+        let policy = self.config.eviction;
+        let mut evicted = Vec::new();
+        let mut applied = 0;
+        let mut status = SyncStatus::Ok;
+
+        match batch.operation {
+            SyncOperation::Insert | SyncOperation::Update => {
+                for card in &batch.cards {
+                    if self
+                        .cards
+                        .store(card, Vec::new(), false, policy, &mut evicted)
+                        .is_err()
+                    {
+                        status = SyncStatus::MemoryFull;
+                        break;
+                    }
+                    applied += 1;
+                }
+            }
+            SyncOperation::Delete => {
+                for card in &batch.cards {
+                    self.cards.remove(card);
+                }
+                applied = batch.cards.len();
+            }
+            SyncOperation::Clear => {
+                self.cards.clear();
+            }
+        }
+
+        SyncAck::new(status, applied, batch.operation).with_evicted(evicted)
+    }
+
+    /// Apply a template batch, in order, until one does not fit.
+    ///
+    /// Inserts add each template to its user's; an update replaces all
+    /// templates of each user named in the batch with the batch's.
+    pub fn apply_templates(&mut self, batch: &TemplateSync) -> SyncAck {
+        let policy = self.config.eviction;
+        let mut evicted = Vec::new();
+        let mut applied = 0;
+        let mut status = SyncStatus::Ok;
+
+        match batch.operation {
+            SyncOperation::Insert | SyncOperation::Update => {
+                let mut replaced: Vec<&str> = Vec::new();
+                for TemplateRecord {
+                    matricula,
+                    template,
+                } in &batch.templates
+                {
+                    // An update replaces with the first template of a user
+                    // and appends the ones after it
+                    let append = batch.operation == SyncOperation::Insert
+                        || replaced.contains(&matricula.as_str());
+                    if self
+                        .templates
+                        .store(
+                            matricula,
+                            vec![template.clone()],
+                            append,
+                            policy,
+                            &mut evicted,
+                        )
+                        .is_err()
+                    {
+                        status = SyncStatus::MemoryFull;
+                        break;
+                    }
+                    replaced.push(matricula);
+                    applied += 1;
+                }
+            }
+            SyncOperation::Delete => {
+                for matricula in &batch.matriculas {
+                    self.templates.remove(matricula);
+                }
+                applied = batch.matriculas.len();
+            }
+            SyncOperation::Clear => {
+                self.templates.clear();
+            }
+        }
+
+        SyncAck::new(status, applied, batch.operation).with_evicted(evicted)
+    }
+}
+
+impl Default for DeviceMemory {
+    fn default() -> Self {
+        Self::new(MemoryConfig::default())
+    }
+}
+
+impl EmulatorCore {
+    /// Apply a card (`ECAR`) or template (`ED`) batch from the server.
+    ///
+    /// The answer is queued for the server and returned. A batch that does
+    /// not fit is not an error: the answer says how far it got.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands and the parse
+    /// error of a malformed batch. Nothing is stored or queued in these
+    /// cases.
+    pub fn handle_sync(&mut self, request: &Message) -> Result<SyncAck> {
+        let ack = match request.command {
+            CommandCode::SendCards => {
+                let batch = CardSync::parse(request)?;
+                self.memory_mut().apply_cards(&batch)
+            }
+            CommandCode::SendBiometrics => {
+                let batch = TemplateSync::parse(request)?;
+                self.memory_mut().apply_templates(&batch)
+            }
+            other => {
+                return Err(Error::InvalidCommandCode {
+                    code: other.as_str().to_string(),
+                });
+            }
+        };
+
+        self.queue_message(ack.to_message(request.device_id, request.command)?);
+        if ack.is_complete() {
+            tracing::info!(
+                device_id = %request.device_id,
+                command = request.command.as_str(),
+                applied = ack.applied,
+                evicted = ack.evicted.len(),
+                "sync batch applied"
+            );
+        } else {
+            tracing::warn!(
+                device_id = %request.device_id,
+                command = request.command.as_str(),
+                applied = ack.applied,
+                "device memory full"
+            );
+        }
+        Ok(ack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(numbers: &[&str]) -> CardSync {
+        CardSync::new(
+            SyncOperation::Insert,
+            numbers.iter().map(|card| card.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_reject_stops_batch_at_capacity() {
+        let mut memory = DeviceMemory::new(MemoryConfig::new(2, 2));
+
+        let ack = memory.apply_cards(&cards(&["111", "222", "333"]));
+        assert_eq!(ack.status, SyncStatus::MemoryFull);
+        assert_eq!(ack.applied, 2);
+        assert!(ack.evicted.is_empty());
+        assert!(!memory.contains_card("333"));
+
+        // Updating a stored card needs no room
+        let ack = memory.apply_cards(&CardSync::new(
+            SyncOperation::Update,
+            vec!["111".to_string()],
+        ));
+        assert!(ack.is_complete());
+
+        let ack = memory.apply_cards(&CardSync::new(
+            SyncOperation::Delete,
+            vec!["111".to_string()],
+        ));
+        assert!(ack.is_complete());
+        assert!(memory.apply_cards(&cards(&["333"])).is_complete());
+        assert_eq!(memory.card_count(), 2);
+    }
+
+    #[test]
+    fn test_oldest_and_lru_evict_different_cards() {
+        let config = MemoryConfig::new(2, 2);
+
+        let mut oldest = DeviceMemory::new(config.with_eviction(EvictionPolicy::Oldest));
+        oldest.apply_cards(&cards(&["111", "222"]));
+        assert!(oldest.lookup_card("111"));
+        let ack = oldest.apply_cards(&cards(&["333"]));
+        assert!(ack.is_complete());
+        assert_eq!(ack.evicted, vec!["111".to_string()]);
+
+        let mut lru = DeviceMemory::new(config.with_eviction(EvictionPolicy::LeastRecentlyUsed));
+        lru.apply_cards(&cards(&["111", "222"]));
+        assert!(lru.lookup_card("111"));
+        let ack = lru.apply_cards(&cards(&["333"]));
+        assert_eq!(ack.evicted, vec!["222".to_string()]);
+        assert!(lru.contains_card("111"));
+    }
+
+    #[test]
+    fn test_templates_count_per_fingerprint() {
+        let config = MemoryConfig::new(10, 3).with_eviction(EvictionPolicy::Oldest);
+        let mut memory = DeviceMemory::new(config);
+
+        let ack = memory.apply_templates(&TemplateSync::store(
+            SyncOperation::Insert,
+            vec![
+                TemplateRecord::new("1001", "AA"),
+                TemplateRecord::new("1001", "BB"),
+                TemplateRecord::new("1002", "CC"),
+            ],
+        ));
+        assert!(ack.is_complete());
+        assert_eq!(memory.template_count(), 3);
+        assert_eq!(memory.templates("1001"), ["AA", "BB"]);
+
+        // A new fingerprint evicts the whole oldest user
+        let ack = memory.apply_templates(&TemplateSync::store(
+            SyncOperation::Insert,
+            vec![TemplateRecord::new("1003", "DD")],
+        ));
+        assert_eq!(ack.evicted, vec!["1001".to_string()]);
+        assert_eq!(memory.template_count(), 2);
+
+        // An update replaces the user's templates
+        memory.apply_templates(&TemplateSync::store(
+            SyncOperation::Update,
+            vec![TemplateRecord::new("1002", "EE")],
+        ));
+        assert_eq!(memory.templates("1002"), ["EE"]);
+
+        // More fingerprints than the memory holds never fit
+        let ack = memory.apply_templates(&TemplateSync::store(
+            SyncOperation::Update,
+            (0..4)
+                .map(|i| TemplateRecord::new("1004", format!("F{i}")))
+                .collect(),
+        ));
+        assert_eq!(ack.status, SyncStatus::MemoryFull);
+        assert_eq!(ack.applied, 3);
+    }
+
+    #[test]
+    fn test_handle_sync_queues_answer() {
+        let device_id = turnkey_core::DeviceId::new(15).unwrap();
+        let mut emulator = EmulatorCore::default().with_memory(MemoryConfig::new(1, 1));
+
+        let request = cards(&["111", "222"]).to_message(device_id).unwrap();
+        let ack = emulator.handle_sync(&request).unwrap();
+        assert_eq!(ack.status, SyncStatus::MemoryFull);
+
+        let answer = emulator.next_message().unwrap();
+        assert_eq!(answer.command, CommandCode::SendCards);
+        assert_eq!(answer.as_sync_ack().unwrap(), ack);
+
+        let status = Message::new(device_id, CommandCode::QueryStatus, Vec::new()).unwrap();
+        assert!(emulator.handle_sync(&status).is_err());
+        assert!(emulator.next_message().is_none());
+    }
+}
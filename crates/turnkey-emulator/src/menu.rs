@@ -0,0 +1,718 @@
+//! Maintenance menu navigated from the turnstile keypad.
+//!
+//! Installers check a device on site without network tooling: typing `*#`,
+//! the access code and `Enter` (or `#`) on the keypad opens a menu on the
+//! LCD that shows the device ID, address, state and counters.
+//! [`KeypadMenu`] emulates it. It consumes [`KeypadInput`]s and answers with
+//! the [`MenuScreen`] to show; [`EmulatorCore::handle_keypad_input()`] wires
+//! it to the display.
+//!
+//! # Navigation
+//!
+//! | Key | In the menu |
+//! |-----|-------------|
+//! | `1`-`9` | Open entry N of the current menu |
+//! | `#` / `*` | Highlight next / previous entry |
+//! | `Enter` | Open the highlighted entry |
+//! | `Cancel` / `Clear` | Back to the parent menu, or close the menu |
+//!
+//! The first line shows the title of the current menu, the second the
+//! highlighted entry. Opening a diagnostic shows its label and value until
+//! `Enter` or `Cancel` returns to the list.
+//!
+//! # Languages
+//!
+//! Labels are [`MenuText`]s with one translation per [`Language`]; the menu
+//! is shown in the configured language and falls back to the first
+//! translation given. The standard tree has a language submenu that switches
+//! it from the keypad.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_emulator::menu::{Language, MenuConfig, MenuEntry, MenuScreen, MenuText};
+//! use turnkey_emulator::{EmulatorCore, KeypadMenu};
+//! use turnkey_hardware::KeypadInput;
+//!
+//! let config = MenuConfig::new("2580").with_entry(MenuEntry::diagnostic(
+//!     MenuText::new("Endereco IP").with(Language::English, "IP address"),
+//!     |_| "192.168.0.15".to_string(),
+//! ));
+//! let mut menu = KeypadMenu::new(config);
+//! let emulator = EmulatorCore::default();
+//!
+//! let keys = [
+//!     KeypadInput::Star,
+//!     KeypadInput::Hash,
+//!     KeypadInput::Digit(2),
+//!     KeypadInput::Digit(5),
+//!     KeypadInput::Digit(8),
+//!     KeypadInput::Digit(0),
+//!     KeypadInput::Enter,
+//! ];
+//! for key in keys {
+//!     menu.handle_input(key, &emulator);
+//! }
+//! assert!(menu.is_open());
+//!
+//! let screen = menu.handle_input(KeypadInput::Digit(3), &emulator);
+//! assert_eq!(
+//!     screen,
+//!     Some(MenuScreen::Lines("Endereco IP".to_string(), "192.168.0.15".to_string()))
+//! );
+//! ```
+
+use crate::emulator::EmulatorCore;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use turnkey_hardware::KeypadInput;
+
+/// Longest access code accepted; further digits are ignored.
+pub const MAX_ACCESS_CODE_LENGTH: usize = 8;
+
+/// Language the menu is shown in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// Brazilian Portuguese, the language of the device firmware
+    #[default]
+    Portuguese,
+
+    /// English
+    English,
+
+    /// Spanish
+    Spanish,
+}
+
+impl Language {
+    /// Every supported language, in menu order.
+    pub const ALL: [Language; 3] = [Language::Portuguese, Language::English, Language::Spanish];
+
+    /// ISO 639-1 code of the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Portuguese => "pt",
+            Self::English => "en",
+            Self::Spanish => "es",
+        }
+    }
+
+    /// Name of the language in itself, as listed in the language menu.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Self::Portuguese => "Portugues",
+            Self::English => "English",
+            Self::Spanish => "Espanol",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Menu label with its translations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuText {
+    /// Translations, the first one used when a language is missing
+    translations: Vec<(Language, String)>,
+}
+
+impl MenuText {
+    /// Create a label from its Portuguese text.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::in_language(Language::Portuguese, text)
+    }
+
+    /// Create a label from its text in one language.
+    pub fn in_language(language: Language, text: impl Into<String>) -> Self {
+        Self {
+            translations: vec![(language, text.into())],
+        }
+    }
+
+    /// Add or replace the translation for a language.
+    pub fn with(mut self, language: Language, text: impl Into<String>) -> Self {
+        let text = text.into();
+        match self.translations.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, existing)) => *existing = text,
+            None => self.translations.push((language, text)),
+        }
+        self
+    }
+
+    /// Text in the given language, or in the first language given.
+    pub fn get(&self, language: Language) -> &str {
+        self.translations
+            .iter()
+            .find(|(l, _)| *l == language)
+            .or(self.translations.first())
+            .map(|(_, text)| text.as_str())
+            .unwrap_or_default()
+    }
+
+    fn translated(pt: &str, en: &str, es: &str) -> Self {
+        Self::new(pt)
+            .with(Language::English, en)
+            .with(Language::Spanish, es)
+    }
+}
+
+impl From<&str> for MenuText {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+/// Computes the value shown by a diagnostic entry.
+pub type DiagnosticHook = Arc<dyn Fn(&EmulatorCore) -> String + Send + Sync>;
+
+/// What opening a menu entry does.
+#[derive(Clone)]
+pub enum MenuAction {
+    /// Open a nested menu
+    Submenu(Vec<MenuEntry>),
+
+    /// Show a value computed when the entry is opened
+    Diagnostic(DiagnosticHook),
+
+    /// Switch the menu to a language and return to the parent menu
+    SetLanguage(Language),
+}
+
+/// One entry of a menu.
+#[derive(Clone)]
+pub struct MenuEntry {
+    /// Label shown on the display
+    pub label: MenuText,
+
+    /// What opening the entry does
+    pub action: MenuAction,
+}
+
+impl MenuEntry {
+    /// An entry opening a nested menu.
+    pub fn submenu(label: impl Into<MenuText>, entries: Vec<MenuEntry>) -> Self {
+        Self {
+            label: label.into(),
+            action: MenuAction::Submenu(entries),
+        }
+    }
+
+    /// An entry showing the value returned by `hook`.
+    pub fn diagnostic(
+        label: impl Into<MenuText>,
+        hook: impl Fn(&EmulatorCore) -> String + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            action: MenuAction::Diagnostic(Arc::new(hook)),
+        }
+    }
+
+    /// An entry switching the menu to `language`, labelled in that language.
+    pub fn language(language: Language) -> Self {
+        Self {
+            label: MenuText::in_language(language, language.native_name()),
+            action: MenuAction::SetLanguage(language),
+        }
+    }
+}
+
+impl fmt::Debug for MenuEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let action = match &self.action {
+            MenuAction::Submenu(entries) => format!("Submenu({entries:?})"),
+            MenuAction::Diagnostic(_) => "Diagnostic".to_string(),
+            MenuAction::SetLanguage(language) => format!("SetLanguage({language:?})"),
+        };
+        f.debug_struct("MenuEntry")
+            .field("label", &self.label)
+            .field("action", &action)
+            .finish()
+    }
+}
+
+/// Menu tree, access code and initial language.
+#[derive(Debug, Clone)]
+pub struct MenuConfig {
+    access_code: String,
+    language: Language,
+    entries: Vec<MenuEntry>,
+}
+
+impl MenuConfig {
+    /// Create a configuration with the standard tree, opened by `*#`,
+    /// `access_code` and `Enter`.
+    ///
+    /// The standard tree has an information submenu with the turnstile state
+    /// and access counters, and a language submenu. Entries added with
+    /// [`with_entry()`](Self::with_entry) follow them.
+    pub fn new(access_code: impl Into<String>) -> Self {
+        Self {
+            access_code: access_code.into(),
+            language: Language::default(),
+            entries: Self::standard_entries(),
+        }
+    }
+
+    /// Show the menu in another language.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Add an entry at the end of the main menu.
+    pub fn with_entry(mut self, entry: MenuEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Replace the whole main menu.
+    pub fn with_entries(mut self, entries: Vec<MenuEntry>) -> Self {
+        self.entries = entries;
+        self
+    }
+
+    /// Language the menu opens in.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Entries of the main menu.
+    pub fn entries(&self) -> &[MenuEntry] {
+        &self.entries
+    }
+
+    /// Entries of the main menu built into the firmware.
+    pub fn standard_entries() -> Vec<MenuEntry> {
+        vec![
+            MenuEntry::submenu(
+                MenuText::translated("Informacoes", "Information", "Informacion"),
+                vec![
+                    MenuEntry::diagnostic(
+                        MenuText::translated("Estado", "State", "Estado"),
+                        |emulator| emulator.state().to_string(),
+                    ),
+                    MenuEntry::diagnostic(
+                        MenuText::translated("Acessos", "Accesses", "Accesos"),
+                        |emulator| {
+                            let counters = emulator.counters();
+                            format!(
+                                "+{} -{} giros {}",
+                                counters.granted, counters.denied, counters.rotations
+                            )
+                        },
+                    ),
+                ],
+            ),
+            MenuEntry::submenu(
+                MenuText::translated("Idioma", "Language", "Idioma"),
+                Language::ALL.into_iter().map(MenuEntry::language).collect(),
+            ),
+        ]
+    }
+}
+
+/// What the display shows after a keypad input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuScreen {
+    /// Show both lines
+    Lines(String, String),
+
+    /// The menu closed; flash a message if any
+    Closed(Option<String>),
+}
+
+/// Where the menu is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Closed,
+    /// `*` pressed, waiting for `#`
+    Armed,
+    /// Typing the access code
+    Code(String),
+    /// Browsing the menu at `path`, with entry `selected` highlighted
+    Browse {
+        path: Vec<usize>,
+        selected: usize,
+    },
+    /// Showing the diagnostic `selected` of the menu at `path`
+    View {
+        path: Vec<usize>,
+        selected: usize,
+    },
+}
+
+/// Keypad maintenance menu of one turnstile.
+#[derive(Debug, Clone)]
+pub struct KeypadMenu {
+    config: MenuConfig,
+    language: Language,
+    mode: Mode,
+}
+
+impl KeypadMenu {
+    /// Create a closed menu.
+    pub fn new(config: MenuConfig) -> Self {
+        Self {
+            language: config.language,
+            config,
+            mode: Mode::Closed,
+        }
+    }
+
+    /// Language the menu is shown in.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Whether the menu or its access code prompt is on the display.
+    pub fn is_open(&self) -> bool {
+        !matches!(self.mode, Mode::Closed | Mode::Armed)
+    }
+
+    /// Whether `*` was pressed and `#` would open the access code prompt.
+    pub fn is_armed(&self) -> bool {
+        self.mode == Mode::Armed
+    }
+
+    /// Close the menu without showing anything.
+    pub fn close(&mut self) {
+        self.mode = Mode::Closed;
+    }
+
+    /// Handle one key.
+    ///
+    /// Returns `None` when the key is not for the menu, so the caller can
+    /// use it for something else: anything typed while the menu is closed
+    /// except the `#` completing `*#`. Otherwise returns what to show.
+    /// Diagnostics are computed from `emulator` when opened.
+    pub fn handle_input(
+        &mut self,
+        input: KeypadInput,
+        emulator: &EmulatorCore,
+    ) -> Option<MenuScreen> {
+        let mode = std::mem::replace(&mut self.mode, Mode::Closed);
+        match mode {
+            Mode::Closed => {
+                if input == KeypadInput::Star {
+                    self.mode = Mode::Armed;
+                }
+                None
+            }
+            Mode::Armed => match input {
+                KeypadInput::Hash => {
+                    self.mode = Mode::Code(String::new());
+                    Some(self.code_prompt(""))
+                }
+                KeypadInput::Star => {
+                    self.mode = Mode::Armed;
+                    None
+                }
+                _ => None,
+            },
+            Mode::Code(code) => Some(self.enter_code(code, input)),
+            Mode::Browse { path, selected } => Some(self.browse(path, selected, input, emulator)),
+            Mode::View { path, selected } => match input {
+                KeypadInput::Enter | KeypadInput::Cancel | KeypadInput::Clear => {
+                    Some(self.show_list(path, selected))
+                }
+                _ => {
+                    let screen = self.show_diagnostic(&path, selected, emulator);
+                    self.mode = Mode::View { path, selected };
+                    Some(screen)
+                }
+            },
+        }
+    }
+
+    fn enter_code(&mut self, mut code: String, input: KeypadInput) -> MenuScreen {
+        match input {
+            KeypadInput::Digit(digit) => {
+                if code.len() < MAX_ACCESS_CODE_LENGTH {
+                    code.push(char::from(b'0' + digit));
+                }
+            }
+            KeypadInput::Clear => code.clear(),
+            KeypadInput::Cancel => return MenuScreen::Closed(None),
+            KeypadInput::Enter | KeypadInput::Hash => {
+                if !code.is_empty() && code == self.config.access_code {
+                    tracing::info!("maintenance menu opened");
+                    return self.show_list(Vec::new(), 0);
+                }
+                tracing::warn!("maintenance menu refused: wrong access code");
+                let notice =
+                    MenuText::translated("Codigo invalido", "Invalid code", "Codigo invalido");
+                return MenuScreen::Closed(Some(notice.get(self.language).to_string()));
+            }
+            _ => {}
+        }
+        let screen = self.code_prompt(&"*".repeat(code.len()));
+        self.mode = Mode::Code(code);
+        screen
+    }
+
+    fn browse(
+        &mut self,
+        mut path: Vec<usize>,
+        selected: usize,
+        input: KeypadInput,
+        emulator: &EmulatorCore,
+    ) -> MenuScreen {
+        let count = self.entries(&path).len();
+        match input {
+            KeypadInput::Digit(digit) if (1..=count).contains(&usize::from(digit)) => {
+                self.open(path, usize::from(digit) - 1, emulator)
+            }
+            KeypadInput::Enter => self.open(path, selected, emulator),
+            KeypadInput::Hash => self.show_list(path, (selected + 1) % count.max(1)),
+            KeypadInput::Star => self.show_list(path, (selected + count.max(1) - 1) % count.max(1)),
+            KeypadInput::Cancel | KeypadInput::Clear => match path.pop() {
+                Some(parent) => self.show_list(path, parent),
+                None => {
+                    tracing::info!("maintenance menu closed");
+                    MenuScreen::Closed(None)
+                }
+            },
+            _ => self.show_list(path, selected),
+        }
+    }
+
+    fn open(
+        &mut self,
+        mut path: Vec<usize>,
+        selected: usize,
+        emulator: &EmulatorCore,
+    ) -> MenuScreen {
+        let Some(entry) = self.entries(&path).get(selected) else {
+            return self.show_list(path, selected);
+        };
+        match &entry.action {
+            MenuAction::Submenu(_) => {
+                path.push(selected);
+                self.show_list(path, 0)
+            }
+            MenuAction::Diagnostic(_) => {
+                let screen = self.show_diagnostic(&path, selected, emulator);
+                self.mode = Mode::View { path, selected };
+                screen
+            }
+            MenuAction::SetLanguage(language) => {
+                let language = *language;
+                self.language = language;
+                tracing::info!(language = %language, "maintenance menu language changed");
+                match path.pop() {
+                    Some(parent) => self.show_list(path, parent),
+                    None => self.show_list(path, selected),
+                }
+            }
+        }
+    }
+
+    fn show_list(&mut self, path: Vec<usize>, selected: usize) -> MenuScreen {
+        let title = match self.parent_entry(&path) {
+            Some(entry) => entry.label.get(self.language).to_string(),
+            None => "MENU".to_string(),
+        };
+        let line = match self.entries(&path).get(selected) {
+            Some(entry) => format!("{} {}", selected + 1, entry.label.get(self.language)),
+            None => String::new(),
+        };
+        self.mode = Mode::Browse { path, selected };
+        MenuScreen::Lines(title, line)
+    }
+
+    fn show_diagnostic(
+        &self,
+        path: &[usize],
+        selected: usize,
+        emulator: &EmulatorCore,
+    ) -> MenuScreen {
+        let entry = &self.entries(path)[selected];
+        let value = match &entry.action {
+            MenuAction::Diagnostic(hook) => hook(emulator),
+            _ => String::new(),
+        };
+        MenuScreen::Lines(entry.label.get(self.language).to_string(), value)
+    }
+
+    fn code_prompt(&self, masked: &str) -> MenuScreen {
+        let prompt = MenuText::translated("Codigo de acesso", "Access code", "Codigo de acceso");
+        MenuScreen::Lines(prompt.get(self.language).to_string(), masked.to_string())
+    }
+
+    /// Entry whose submenu is at `path`, `None` for the main menu.
+    fn parent_entry(&self, path: &[usize]) -> Option<&MenuEntry> {
+        let (last, parents) = path.split_last()?;
+        Some(&self.entries(parents)[*last])
+    }
+
+    /// Entries of the menu at `path`.
+    fn entries(&self, path: &[usize]) -> &[MenuEntry] {
+        path.iter().fold(
+            self.config.entries.as_slice(),
+            |entries, &index| match &entries[index].action {
+                MenuAction::Submenu(children) => children,
+                _ => &[],
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(menu: &mut KeypadMenu, emulator: &EmulatorCore, code: &str) -> Option<MenuScreen> {
+        menu.handle_input(KeypadInput::Star, emulator);
+        menu.handle_input(KeypadInput::Hash, emulator);
+        for digit in code.bytes() {
+            menu.handle_input(KeypadInput::Digit(digit - b'0'), emulator);
+        }
+        menu.handle_input(KeypadInput::Enter, emulator)
+    }
+
+    fn lines(first: &str, second: &str) -> Option<MenuScreen> {
+        Some(MenuScreen::Lines(first.to_string(), second.to_string()))
+    }
+
+    #[test]
+    fn test_keys_pass_through_while_closed() {
+        let emulator = EmulatorCore::default();
+        let mut menu = KeypadMenu::new(MenuConfig::new("1234"));
+
+        assert_eq!(menu.handle_input(KeypadInput::Digit(4), &emulator), None);
+        assert_eq!(menu.handle_input(KeypadInput::Star, &emulator), None);
+        assert!(menu.is_armed());
+        assert_eq!(menu.handle_input(KeypadInput::Digit(1), &emulator), None);
+        assert!(!menu.is_armed());
+        assert!(!menu.is_open());
+    }
+
+    #[test]
+    fn test_access_code_is_masked_and_checked() {
+        let emulator = EmulatorCore::default();
+        let mut menu = KeypadMenu::new(MenuConfig::new("1234"));
+
+        menu.handle_input(KeypadInput::Star, &emulator);
+        assert_eq!(
+            menu.handle_input(KeypadInput::Hash, &emulator),
+            lines("Codigo de acesso", "")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Digit(1), &emulator),
+            lines("Codigo de acesso", "*")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Enter, &emulator),
+            Some(MenuScreen::Closed(Some("Codigo invalido".to_string())))
+        );
+        assert!(!menu.is_open());
+
+        assert_eq!(
+            open(&mut menu, &emulator, "1234"),
+            lines("MENU", "1 Informacoes")
+        );
+        assert!(menu.is_open());
+    }
+
+    #[test]
+    fn test_navigates_and_shows_diagnostics() {
+        let emulator = EmulatorCore::default();
+        let config =
+            MenuConfig::new("1234").with_entry(MenuEntry::diagnostic("ID", |_| "15".to_string()));
+        let mut menu = KeypadMenu::new(config);
+        open(&mut menu, &emulator, "1234");
+
+        assert_eq!(
+            menu.handle_input(KeypadInput::Hash, &emulator),
+            lines("MENU", "2 Idioma")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Hash, &emulator),
+            lines("MENU", "3 ID")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Hash, &emulator),
+            lines("MENU", "1 Informacoes")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Star, &emulator),
+            lines("MENU", "3 ID")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Enter, &emulator),
+            lines("ID", "15")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Cancel, &emulator),
+            lines("MENU", "3 ID")
+        );
+
+        assert_eq!(
+            menu.handle_input(KeypadInput::Digit(1), &emulator),
+            lines("Informacoes", "1 Estado")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Digit(1), &emulator),
+            lines("Estado", "Idle")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Enter, &emulator),
+            lines("Informacoes", "1 Estado")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Cancel, &emulator),
+            lines("MENU", "1 Informacoes")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Cancel, &emulator),
+            Some(MenuScreen::Closed(None))
+        );
+        assert!(!menu.is_open());
+    }
+
+    #[test]
+    fn test_language_menu_switches_labels() {
+        let emulator = EmulatorCore::default();
+        let mut menu = KeypadMenu::new(MenuConfig::new("1234"));
+        open(&mut menu, &emulator, "1234");
+
+        assert_eq!(
+            menu.handle_input(KeypadInput::Digit(2), &emulator),
+            lines("Idioma", "1 Portugues")
+        );
+        assert_eq!(
+            menu.handle_input(KeypadInput::Digit(2), &emulator),
+            lines("MENU", "2 Language")
+        );
+        assert_eq!(menu.language(), Language::English);
+        assert_eq!(
+            menu.handle_input(KeypadInput::Digit(1), &emulator),
+            lines("Information", "1 State")
+        );
+    }
+
+    #[test]
+    fn test_configured_language_and_fallback() {
+        let emulator = EmulatorCore::default();
+        let config = MenuConfig::new("1234")
+            .with_language(Language::Spanish)
+            .with_entries(vec![MenuEntry::diagnostic(
+                MenuText::new("Rede").with(Language::English, "Network"),
+                |_| String::new(),
+            )]);
+        let mut menu = KeypadMenu::new(config);
+
+        assert_eq!(
+            open(&mut menu, &emulator, "9999"),
+            Some(MenuScreen::Closed(Some("Codigo invalido".to_string())))
+        );
+        assert_eq!(open(&mut menu, &emulator, "1234"), lines("MENU", "1 Rede"));
+    }
+}
@@ -0,0 +1,304 @@
+//! Optimistic welcome for known users while a validation is pending.
+//!
+//! VIP entrances want the turnstile to greet a regular the moment the card
+//! is read, not once the server has answered. With
+//! [`EmulatorCore::with_prerender()`] and a table of [`KnownUsers`],
+//! [`EmulatorCore::prerender_card()`] shows "Bem-vindo, {nome}" on the first
+//! line as soon as a known card is read, while the second line keeps the
+//! usual wait message.
+//!
+//! The greeting is only a guess. When the validation ends:
+//!
+//! - a grant keeps the greeting and shows "ACESSO LIBERADO" below it,
+//! - any other outcome (denial, message-only response, reset) redraws the
+//!   whole display for the new state, so nothing of the greeting is left,
+//!   and counts a rollback in [`EmulatorCounters::prerender_rollbacks`].
+//!
+//! The mode is off unless configured.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_emulator::{EmulatorCore, KnownUsers, TurnstileState};
+//!
+//! let users = KnownUsers::new().with_user("12345678", "Maria");
+//! let mut emulator = EmulatorCore::default().with_prerender(users);
+//!
+//! emulator.transition_to(TurnstileState::Reading).unwrap();
+//! assert!(emulator.prerender_card("12345678"));
+//! emulator.transition_to(TurnstileState::Validating).unwrap();
+//! assert!(emulator.display().get_line(0).unwrap().contains("Bem-vindo, Maria"));
+//!
+//! // The server says no: the greeting is rolled back
+//! emulator.transition_to(TurnstileState::Denied).unwrap();
+//! assert!(emulator.display().get_line(0).unwrap().contains("ACESSO NEGADO"));
+//! assert_eq!(emulator.counters().prerender_rollbacks, 1);
+//! ```
+//!
+//! [`EmulatorCounters::prerender_rollbacks`]: crate::EmulatorCounters::prerender_rollbacks
+
+use std::collections::HashMap;
+
+use crate::TurnstileState;
+use crate::display::Alignment;
+use crate::emulator::EmulatorCore;
+
+/// Second display line under the greeting once access is granted.
+const GRANTED_LINE: &str = "ACESSO LIBERADO";
+
+/// Names of the users greeted before their validation completes, by card.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KnownUsers {
+    names: HashMap<String, String>,
+}
+
+impl KnownUsers {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a user, replacing any name already set for the card.
+    pub fn with_user(mut self, card_number: impl Into<String>, name: impl Into<String>) -> Self {
+        self.insert(card_number, name);
+        self
+    }
+
+    /// Add a user, replacing any name already set for the card.
+    pub fn insert(&mut self, card_number: impl Into<String>, name: impl Into<String>) {
+        self.names
+            .insert(normalize(&card_number.into()), name.into());
+    }
+
+    /// Remove a user, returning the name it had.
+    pub fn remove(&mut self, card_number: &str) -> Option<String> {
+        self.names.remove(&normalize(card_number))
+    }
+
+    /// Name of the user holding a card.
+    ///
+    /// Leading zeros are ignored, so `"00012345"` and `"12345"` are the
+    /// same card.
+    pub fn name(&self, card_number: &str) -> Option<&str> {
+        self.names.get(&normalize(card_number)).map(String::as_str)
+    }
+
+    /// Number of known users.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether no user is known.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Card number without padding, as readers and exports disagree on it.
+fn normalize(card_number: &str) -> String {
+    let trimmed = card_number.trim().trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0".into()
+    } else {
+        trimmed.into()
+    }
+}
+
+impl EmulatorCore {
+    /// Greet known users before their validation completes.
+    ///
+    /// See [`prerender_card()`](Self::prerender_card).
+    pub fn with_prerender(mut self, users: KnownUsers) -> Self {
+        self.known_users = Some(users);
+        self
+    }
+
+    /// Users greeted ahead of validation, if the mode is enabled.
+    pub fn known_users(&self) -> Option<&KnownUsers> {
+        self.known_users.as_ref()
+    }
+
+    /// Mutable access to the known users, if the mode is enabled.
+    pub fn known_users_mut(&mut self) -> Option<&mut KnownUsers> {
+        self.known_users.as_mut()
+    }
+
+    /// Greeting currently shown ahead of a decision, if any.
+    pub fn prerendered(&self) -> Option<&str> {
+        self.prerendered.as_deref()
+    }
+
+    /// Show the welcome for a card that was just read.
+    ///
+    /// Call it once the card number is known, in `Reading` or `Validating`;
+    /// the greeting stays through the move to `Validating` and until the
+    /// decision. Returns `false` and leaves the display alone if the mode
+    /// is disabled, the card is unknown or no validation is under way.
+    pub fn prerender_card(&mut self, card_number: &str) -> bool {
+        if !matches!(
+            self.state(),
+            TurnstileState::Reading | TurnstileState::Validating
+        ) {
+            return false;
+        }
+        let Some(name) = self
+            .known_users
+            .as_ref()
+            .and_then(|users| users.name(card_number))
+        else {
+            return false;
+        };
+
+        let welcome = format!("Bem-vindo, {}", name);
+        let _ = self
+            .display
+            .set_line_aligned(0, &welcome, Alignment::Center);
+        self.prerendered = Some(welcome);
+        self.counters.prerendered += 1;
+        true
+    }
+
+    /// Keep or roll back the greeting after a transition.
+    ///
+    /// The display has already been redrawn for `state`.
+    pub(crate) fn settle_prerender(&mut self, state: TurnstileState) {
+        let Some(welcome) = self.prerendered.take() else {
+            return;
+        };
+
+        match state {
+            TurnstileState::Reading | TurnstileState::Validating => {
+                let _ = self
+                    .display
+                    .set_line_aligned(0, &welcome, Alignment::Center);
+                self.prerendered = Some(welcome);
+            }
+            TurnstileState::Granted => {
+                let _ = self
+                    .display
+                    .set_line_aligned(0, &welcome, Alignment::Center);
+                let _ = self
+                    .display
+                    .set_line_aligned(1, GRANTED_LINE, Alignment::Center);
+            }
+            _ => {
+                tracing::debug!(%state, "rolling back prerendered welcome");
+                self.counters.prerender_rollbacks += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use turnkey_protocol::commands::access::AccessResponse;
+
+    use super::*;
+    use crate::ValidationFeedback;
+
+    fn emulator() -> EmulatorCore {
+        EmulatorCore::default().with_prerender(KnownUsers::new().with_user("22823433", "Ana"))
+    }
+
+    fn line(emulator: &EmulatorCore, index: usize) -> String {
+        emulator
+            .display()
+            .get_line(index)
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    fn validating(emulator: &mut EmulatorCore) {
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        assert!(emulator.prerender_card("00000000000022823433"));
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        assert_eq!(line(emulator, 0), "Bem-vindo, Ana");
+        assert_eq!(line(emulator, 1), "Aguarde resposta");
+    }
+
+    #[test]
+    fn test_grant_keeps_welcome() {
+        let mut emulator = emulator();
+        validating(&mut emulator);
+
+        emulator
+            .apply_response(&AccessResponse::grant_entry(String::new()))
+            .unwrap();
+
+        assert_eq!(line(&emulator, 0), "Bem-vindo, Ana");
+        assert_eq!(line(&emulator, 1), "ACESSO LIBERADO");
+        assert_eq!(emulator.prerendered(), None);
+        assert_eq!(emulator.counters().prerendered, 1);
+        assert_eq!(emulator.counters().prerender_rollbacks, 0);
+    }
+
+    #[test]
+    fn test_deny_after_prerender_rolls_back() {
+        let mut emulator = emulator();
+        validating(&mut emulator);
+
+        emulator
+            .apply_response(&AccessResponse::deny("Cartao bloqueado".into()))
+            .unwrap();
+
+        assert_eq!(line(&emulator, 0), "ACESSO NEGADO");
+        assert_eq!(line(&emulator, 1), "Cartao bloqueado");
+        assert_eq!(emulator.prerendered(), None);
+        assert_eq!(emulator.counters().prerender_rollbacks, 1);
+
+        // The next passage starts from a clean display
+        emulator.transition_to(TurnstileState::Idle).unwrap();
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        assert_eq!(line(&emulator, 0), "VALIDANDO...");
+    }
+
+    #[test]
+    fn test_display_only_and_reset_roll_back() {
+        let mut emulator = emulator();
+        validating(&mut emulator);
+        emulator
+            .apply_response(&AccessResponse::display_only("Procure o RH".into()))
+            .unwrap();
+        assert_eq!(line(&emulator, 0), "Procure o RH");
+        assert!(!line(&emulator, 1).contains("Ana"));
+
+        let mut emulator = self::emulator();
+        validating(&mut emulator);
+        emulator.reset();
+        assert!(!line(&emulator, 0).contains("Ana"));
+        assert_eq!(emulator.counters().prerender_rollbacks, 1);
+    }
+
+    #[test]
+    fn test_unknown_card_and_disabled_mode_are_ignored() {
+        let mut emulator = emulator();
+        assert!(!emulator.prerender_card("22823433"), "idle");
+
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        assert!(!emulator.prerender_card("99999999"));
+        assert_eq!(line(&emulator, 0), "AGUARDE...");
+
+        let mut plain = EmulatorCore::default();
+        plain.transition_to(TurnstileState::Reading).unwrap();
+        assert!(!plain.prerender_card("22823433"));
+        assert_eq!(plain.counters().prerendered, 0);
+    }
+
+    #[test]
+    fn test_wait_feedback_keeps_welcome() {
+        let feedback = ValidationFeedback {
+            delay: Duration::ZERO,
+            frame: Duration::from_millis(500),
+        };
+        let mut emulator = emulator().with_validation_feedback(feedback);
+        validating(&mut emulator);
+
+        emulator.update_validation_feedback();
+        assert_eq!(line(&emulator, 0), "Bem-vindo, Ana");
+        assert_ne!(line(&emulator, 1), "");
+    }
+}
@@ -0,0 +1,1276 @@
+//! Event loop tying the emulator to its peripherals, validators and server.
+//!
+//! [`EmulatorRuntime`] owns everything a running turnstile needs and drives
+//! one passage at a time:
+//!
+//! 1. A card read by a peripheral, or a code typed on the keypad and
+//!    confirmed with `Enter`, moves the idle turnstile to `Reading` and
+//!    `Validating`, with the wait feedback running on the display.
+//! 2. The [`ValidationModeController`] validates the credential on the
+//!    server or against the local database, as its mode dictates. A
+//!    validation error denies the access.
+//! 3. The response moves the turnstile to `Granted` or `Denied`. A grant
+//!    releases the arm for the response's timeout and queues `000+80`.
+//! 4. An arm rotation completes the passage and queues `000+81`; an
+//!    expired release queues `000+82`. Either way the turnstile returns to
+//!    `Idle`, as it does once a denial has been shown for
+//!    [`RuntimeConfig::denied_display`].
+//!
+//! The arm sensor feeds the shared [`emulator()`](EmulatorRuntime::emulator)
+//! directly through [`EmulatorCore::run_sensors()`], and the runtime picks
+//! the rotation up on its next tick. Keys the keypad menu does not use
+//! build the code: digits, `*` between a matricula and its PIN, `Clear` or
+//! `Cancel` to start over.
+//!
+//! # Card + biometric
+//!
+//! On a card + biometric device a card accepted by the local validator
+//! does not grant yet: the turnstile moves to `AwaitingBiometric` for
+//! [`RuntimeConfig::biometric_timeout`], and the next finger captured by
+//! the scanner is verified against the card owner's templates. A finger
+//! that does not match denies with `BIOMETRIC_MISMATCH`; no finger in time
+//! denies with `BIOMETRIC_TIMEOUT`. Fingers captured while no card waits
+//! for one are ignored.
+//!
+//! Queued messages, including those of exit requests and status reports,
+//! are sent through the notifier connection when one is set, and stay
+//! queued in the [`EmulatorCore`] while it is down. The notifier's
+//! keepalives (see [`TcpClient::enable_heartbeat()`]) feed a
+//! [`HeartbeatMonitor`], which switches the controller and the display's
+//! link state. Access events the validator buffered in the outbox while the
+//! server was unreachable are replayed by a [`SyncEngine`], set with
+//! [`with_sync()`](EmulatorRuntime::with_sync), each time the notifier
+//! connects. A [`RetentionManager`] set with
+//! [`with_retention()`](EmulatorRuntime::with_retention) prunes old access
+//! logs in a task of its own while the runtime runs.
+//!
+//! # NSR
+//!
+//! With the access log repository set through
+//! [`with_access_logs()`](EmulatorRuntime::with_access_logs), the passage
+//! notifications carry the NSR of the access log written for the passage,
+//! found by its correlation ID, so the server can detect lost events. It is
+//! the device's NSR, the event ID the access is replayed with from the
+//! outbox. Passages decided by the server have no local log and are sent
+//! without one.
+//!
+//! # Shutdown
+//!
+//! [`EmulatorRuntime::run()`] returns once its token is cancelled or its
+//! peripherals are all gone. A validation in progress is finished first;
+//! queued messages get a last chance to be sent, then the notifier is
+//! closed and the peripherals are shut down.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_core::DeviceId;
+//! use turnkey_emulator::EmulatorCore;
+//! use turnkey_emulator::runtime::{EmulatorRuntime, RuntimeConfig};
+//! use turnkey_hardware::{PeripheralConfig, PeripheralManager};
+//! use turnkey_network::{CancellationToken, TcpClient, TcpClientConfig};
+//! use turnkey_storage::{
+//!     Database, OfflineValidator, OnlineValidator, OnlineValidatorConfig, OperatingMode,
+//!     ValidationModeController,
+//! };
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let device_id = DeviceId::new(15)?;
+//! let db = Database::in_memory().await?;
+//! let online = OnlineValidator::new(
+//!     TcpClient::new(TcpClientConfig::default()),
+//!     device_id,
+//!     OnlineValidatorConfig::default(),
+//! );
+//! let offline = OfflineValidator::new(db.pool().clone()).with_device_id(device_id);
+//! let controller =
+//!     ValidationModeController::new(device_id, online, offline, OperatingMode::Hybrid);
+//! let peripherals = PeripheralManager::new(PeripheralConfig::default()).start();
+//!
+//! let runtime = EmulatorRuntime::new(
+//!     RuntimeConfig::new(device_id),
+//!     EmulatorCore::default(),
+//!     controller,
+//!     peripherals,
+//! )
+//! .with_notifier(TcpClient::new(TcpClientConfig::default()));
+//!
+//! let stop = CancellationToken::new();
+//! let counters = runtime.run(stop).await?;
+//! println!("{} granted", counters.granted);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use turnkey_config::{ConfigFileResult, TurnkeyConfig};
+use turnkey_core::constants::MAX_CARD_LENGTH;
+use turnkey_core::{
+    AccessDirection, CardNumber, CorrelationId, DeviceId, Error, HenryTimestamp, ReaderType, Result,
+};
+use turnkey_hardware::{BiometricData, KeypadInput, PeripheralEvent, PeripheralHandle};
+use turnkey_network::{CancellationToken, HealthEventKind, TcpClient};
+use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
+use turnkey_storage::repositories::{AccessLogRepository, SqliteAccessLogRepository};
+use turnkey_storage::retention::RetentionManager;
+use turnkey_storage::sync::SyncEngine;
+use turnkey_storage::{
+    CardVerification, DisplayMessages, HeartbeatMonitor, KEYPAD_MATRICULA_SEPARATOR,
+    PendingBiometric, StorageResult, TemplateMatcher, ValidationModeController,
+};
+
+use crate::TurnstileState;
+use crate::display::Alignment;
+use crate::emulator::{EmulatorCore, EmulatorCounters, passage_notification};
+
+/// Default period of the timeout checks and message flushes.
+pub const DEFAULT_TICK: Duration = Duration::from_millis(100);
+
+/// Default time the arm stays released when a grant carries no timeout.
+pub const DEFAULT_RELEASE_TIME: Duration = Duration::from_secs(5);
+
+/// Default time a denial stays on the display.
+pub const DEFAULT_DENIED_DISPLAY: Duration = Duration::from_secs(3);
+
+/// Default wait between attempts to reconnect the notifier.
+pub const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default time a card + biometric passage waits for the finger.
+pub const DEFAULT_BIOMETRIC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings of an [`EmulatorRuntime`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// Device ID used in requests and notifications.
+    pub device_id: DeviceId,
+
+    /// Direction reported for credentials read by this device
+    /// (default: entry).
+    pub direction: AccessDirection,
+
+    /// Release time for grants without a timeout.
+    pub release_time: Duration,
+
+    /// Time a denial stays on the display before returning to idle.
+    pub denied_display: Duration,
+
+    /// Period of the timeout checks and message flushes.
+    pub tick: Duration,
+
+    /// Wait between attempts to reconnect the notifier.
+    pub reconnect_interval: Duration,
+
+    /// Time an accepted card waits for the owner's finger on card +
+    /// biometric devices.
+    pub biometric_timeout: Duration,
+}
+
+impl RuntimeConfig {
+    /// Create the default settings for `device_id`.
+    pub fn new(device_id: DeviceId) -> Self {
+        Self {
+            device_id,
+            direction: AccessDirection::Entry,
+            release_time: DEFAULT_RELEASE_TIME,
+            denied_display: DEFAULT_DENIED_DISPLAY,
+            tick: DEFAULT_TICK,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            biometric_timeout: DEFAULT_BIOMETRIC_TIMEOUT,
+        }
+    }
+
+    /// Settings from a configuration file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` if the file's device ID is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_config::TurnkeyConfig;
+    /// use turnkey_emulator::RuntimeConfig;
+    ///
+    /// let file = TurnkeyConfig::from_toml("device_id = 15\n[timeouts]\nrelease_secs = 8").unwrap();
+    /// let config = RuntimeConfig::from_file(&file).unwrap();
+    ///
+    /// assert_eq!(config.device_id.as_u8(), 15);
+    /// assert_eq!(config.release_time, Duration::from_secs(8));
+    /// ```
+    pub fn from_file(config: &TurnkeyConfig) -> ConfigFileResult<Self> {
+        Ok(Self::new(config.device_id()?)
+            .with_direction(config.access_direction())
+            .with_release_time(config.timeouts.release())
+            .with_denied_display(config.timeouts.denied_display())
+            .with_reconnect_interval(config.timeouts.reconnect()))
+    }
+
+    /// Set the direction reported for credentials.
+    pub fn with_direction(mut self, direction: AccessDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the release time for grants without a timeout.
+    pub fn with_release_time(mut self, release_time: Duration) -> Self {
+        self.release_time = release_time;
+        self
+    }
+
+    /// Set the time a denial stays on the display.
+    pub fn with_denied_display(mut self, denied_display: Duration) -> Self {
+        self.denied_display = denied_display;
+        self
+    }
+
+    /// Set the period of the timeout checks and message flushes.
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Set the wait between attempts to reconnect the notifier.
+    pub fn with_reconnect_interval(mut self, reconnect_interval: Duration) -> Self {
+        self.reconnect_interval = reconnect_interval;
+        self
+    }
+
+    /// Set the time an accepted card waits for the owner's finger.
+    pub fn with_biometric_timeout(mut self, biometric_timeout: Duration) -> Self {
+        self.biometric_timeout = biometric_timeout;
+        self
+    }
+}
+
+/// Credential of the passage currently released by the runtime.
+#[derive(Debug, Clone)]
+struct Passage {
+    card_number: String,
+    reader_type: ReaderType,
+    nsr: Option<u64>,
+}
+
+/// Card accepted on a card + biometric device, waiting for the finger.
+#[derive(Debug)]
+struct PendingFinger {
+    request: AccessRequest,
+    pending: PendingBiometric,
+    correlation_id: CorrelationId,
+}
+
+/// Finger captured by the scanner, compared byte for byte with the
+/// enrolled templates like the scanner's own 1:1 verification.
+struct CapturedFinger<'a>(&'a [u8]);
+
+impl TemplateMatcher for CapturedFinger<'_> {
+    async fn matches(&mut self, template: &[u8]) -> StorageResult<bool> {
+        Ok(template == self.0)
+    }
+}
+
+/// Running turnstile: emulator, peripherals, validation and server link.
+///
+/// See the [module documentation](self) for the event flow.
+pub struct EmulatorRuntime {
+    config: RuntimeConfig,
+    emulator: Arc<Mutex<EmulatorCore>>,
+    controller: ValidationModeController,
+    peripherals: PeripheralHandle,
+    notifier: Option<TcpClient>,
+    monitor: Option<HeartbeatMonitor>,
+    sync: Option<SyncEngine>,
+    retention: Option<(RetentionManager, Duration)>,
+    access_logs: Option<SqliteAccessLogRepository>,
+    passage: Option<Passage>,
+    finger: Option<PendingFinger>,
+    keypad: String,
+    next_connect: Instant,
+}
+
+impl EmulatorRuntime {
+    /// Create a runtime around an emulator and its started peripherals.
+    pub fn new(
+        config: RuntimeConfig,
+        emulator: EmulatorCore,
+        controller: ValidationModeController,
+        peripherals: PeripheralHandle,
+    ) -> Self {
+        Self {
+            config,
+            emulator: Arc::new(Mutex::new(emulator)),
+            controller,
+            peripherals,
+            notifier: None,
+            monitor: None,
+            sync: None,
+            retention: None,
+            access_logs: None,
+            passage: None,
+            finger: None,
+            keypad: String::new(),
+            next_connect: Instant::now(),
+        }
+    }
+
+    /// Send queued messages to the server through `client`.
+    ///
+    /// The client is connected on the first flush and reconnected every
+    /// [`RuntimeConfig::reconnect_interval`] after a failure. Its
+    /// keepalives are sent when enabled on it.
+    pub fn with_notifier(mut self, client: TcpClient) -> Self {
+        self.notifier = Some(client);
+        self
+    }
+
+    /// Report the notifier's keepalives to `monitor`.
+    pub fn with_heartbeat(mut self, monitor: HeartbeatMonitor) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// Send the access events queued in the outbox through the notifier.
+    ///
+    /// The events are buffered while the notifier is down and all of them
+    /// are replayed, in NSR order, when it connects. Queue them with
+    /// [`OfflineValidator::with_outbox()`](turnkey_storage::OfflineValidator::with_outbox).
+    pub fn with_sync(mut self, engine: SyncEngine) -> Self {
+        self.sync = Some(engine);
+        self
+    }
+
+    /// Prune old access logs with `manager` every `interval`.
+    ///
+    /// The pruning task starts with [`run()`](Self::run) and is stopped
+    /// before it returns.
+    pub fn with_retention(mut self, manager: RetentionManager, interval: Duration) -> Self {
+        self.retention = Some((manager, interval));
+        self
+    }
+
+    /// Send the NSR of the passage's access log in its notifications.
+    ///
+    /// See the [module documentation](self#nsr).
+    pub fn with_access_logs(mut self, access_logs: SqliteAccessLogRepository) -> Self {
+        self.access_logs = Some(access_logs);
+        self
+    }
+
+    /// The emulator driven by this runtime.
+    ///
+    /// Feed it the turnstile sensor and exit inputs, and read its display
+    /// and counters.
+    pub fn emulator(&self) -> Arc<Mutex<EmulatorCore>> {
+        self.emulator.clone()
+    }
+
+    /// Process events until `shutdown` is cancelled.
+    ///
+    /// Returns the emulator's counters at shutdown.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::HardwareError` if the peripherals fail to shut down.
+    pub async fn run(mut self, shutdown: CancellationToken) -> Result<EmulatorCounters> {
+        let mut tick = tokio::time::interval(self.config.tick);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        tracing::info!(
+            device_id = %self.config.device_id,
+            mode = %self.controller.mode(),
+            "emulator runtime started"
+        );
+
+        let retention = self.retention.take().map(|(manager, interval)| {
+            let stop = shutdown.child_token();
+            (tokio::spawn(manager.run(interval, stop.clone())), stop)
+        });
+
+        loop {
+            let heartbeat_due = self.notifier.as_ref().and_then(TcpClient::heartbeat_due);
+            tokio::select! {
+                biased;
+                () = shutdown.cancelled() => break,
+                event = self.peripherals.recv() => match event {
+                    Some(event) => self.handle_event(event).await,
+                    None => {
+                        tracing::warn!("peripherals stopped");
+                        break;
+                    }
+                },
+                () = sleep_until_due(heartbeat_due) => self.heartbeat().await,
+                _ = tick.tick() => {
+                    self.advance().await;
+                    self.flush().await;
+                }
+            }
+        }
+
+        if let Some((task, stop)) = retention {
+            stop.cancel();
+            let _ = task.await;
+        }
+        self.shutdown().await
+    }
+
+    /// Apply an event reported by a peripheral.
+    async fn handle_event(&mut self, event: PeripheralEvent) {
+        self.emulator.lock().await.record_peripheral_event(&event);
+        match event {
+            PeripheralEvent::CardRead(card) => {
+                let card_number = CardNumber::new(&card.uid_decimal())
+                    .map(|number| number.padded())
+                    .unwrap_or_else(|_| card.uid_hex());
+                self.validate(card_number, ReaderType::Rfid, card.correlation_id)
+                    .await;
+            }
+            PeripheralEvent::KeypadInput(input) => self.handle_keypad_input(input).await,
+            PeripheralEvent::FingerprintCaptured(finger) => self.verify_finger(finger).await,
+            _ => {}
+        }
+    }
+
+    /// Feed a key to the keypad menu, or to the code being typed.
+    async fn handle_keypad_input(&mut self, input: KeypadInput) {
+        if self
+            .emulator
+            .lock()
+            .await
+            .handle_keypad_input(input.clone())
+        {
+            self.keypad.clear();
+            return;
+        }
+        match input {
+            KeypadInput::Digit(digit) if self.keypad.len() < MAX_CARD_LENGTH => {
+                self.keypad.push(char::from(b'0' + digit));
+            }
+            KeypadInput::Star if self.keypad.len() < MAX_CARD_LENGTH => {
+                self.keypad.push(KEYPAD_MATRICULA_SEPARATOR);
+            }
+            KeypadInput::Enter if !self.keypad.is_empty() => {
+                let code = std::mem::take(&mut self.keypad);
+                self.validate(code, ReaderType::Keypad, CorrelationId::new())
+                    .await;
+            }
+            KeypadInput::Clear | KeypadInput::Cancel => self.keypad.clear(),
+            _ => {}
+        }
+    }
+
+    /// Run one credential through validation and release the arm on a grant.
+    async fn validate(
+        &mut self,
+        card_number: String,
+        reader_type: ReaderType,
+        correlation_id: CorrelationId,
+    ) {
+        {
+            let mut core = self.emulator.lock().await;
+            if core.state() != TurnstileState::Idle {
+                tracing::debug!(state = %core.state(), "credential ignored: turnstile busy");
+                return;
+            }
+            if let Err(e) = core
+                .transition_to(TurnstileState::Reading)
+                .and_then(|_| core.transition_to(TurnstileState::Validating))
+            {
+                tracing::warn!("cannot start validation: {}", e);
+                return;
+            }
+        }
+
+        let request = match AccessRequest::new(
+            card_number.clone(),
+            HenryTimestamp::now(),
+            self.config.direction,
+            reader_type,
+        ) {
+            Ok(request) => request.with_correlation_id(correlation_id),
+            Err(e) => {
+                tracing::warn!(%correlation_id, "invalid credential, denying: {}", e);
+                let message = match reader_type {
+                    ReaderType::Keypad => DisplayMessages::PIN_INVALID,
+                    _ => DisplayMessages::CARD_NOT_FOUND,
+                };
+                let response = AccessResponse::deny(message.to_string());
+                self.finish(card_number, reader_type, correlation_id, &response)
+                    .await;
+                return;
+            }
+        };
+
+        let verification = tokio::select! {
+            verification = self.controller.validate_card(&request) => verification,
+            () = EmulatorCore::run_validation_feedback(&self.emulator, self.config.tick) => {
+                tracing::debug!(%correlation_id, "validation abandoned: state changed");
+                return;
+            }
+        };
+        let response = match verification {
+            Ok(CardVerification::Complete(response)) => response,
+            Ok(CardVerification::AwaitingBiometric(pending)) => {
+                let timeout = self.config.biometric_timeout;
+                if let Err(e) = self.emulator.lock().await.await_biometric(timeout) {
+                    tracing::warn!(%correlation_id, "cannot wait for the finger: {}", e);
+                    return;
+                }
+                tracing::debug!(%correlation_id, "card accepted, waiting for the finger");
+                self.finger = Some(PendingFinger {
+                    request,
+                    pending,
+                    correlation_id,
+                });
+                return;
+            }
+            Err(e) => {
+                tracing::warn!(%correlation_id, "validation failed, denying: {}", e);
+                AccessResponse::deny(DisplayMessages::DECISION_IN_PROGRESS.to_string())
+            }
+        };
+        self.finish(card_number, reader_type, correlation_id, &response)
+            .await;
+    }
+
+    /// Verify a captured finger against the card waiting for it.
+    async fn verify_finger(&mut self, finger: BiometricData) {
+        let Some(PendingFinger {
+            request,
+            pending,
+            correlation_id,
+        }) = self.finger.take()
+        else {
+            tracing::debug!("finger ignored: no card waiting for it");
+            return;
+        };
+        if let Err(e) = self
+            .emulator
+            .lock()
+            .await
+            .transition_to(TurnstileState::Validating)
+        {
+            tracing::warn!(%correlation_id, "cannot verify the finger: {}", e);
+            return;
+        }
+
+        let response = self
+            .controller
+            .verify_biometric(&request, &pending, &mut CapturedFinger(&finger.template))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(%correlation_id, "finger verification failed, denying: {}", e);
+                AccessResponse::deny(DisplayMessages::DECISION_IN_PROGRESS.to_string())
+            });
+        self.finish(
+            pending.card_number().to_string(),
+            request.reader_type(),
+            correlation_id,
+            &response,
+        )
+        .await;
+    }
+
+    /// Apply the decision on a credential and release the arm on a grant.
+    async fn finish(
+        &mut self,
+        card_number: String,
+        reader_type: ReaderType,
+        correlation_id: CorrelationId,
+        response: &AccessResponse,
+    ) {
+        let mut core = self.emulator.lock().await;
+        if let Err(e) = core.apply_response(response) {
+            tracing::warn!(%correlation_id, "cannot apply response: {}", e);
+            return;
+        }
+        if !response.is_grant() {
+            return;
+        }
+
+        let timeout = match response.timeout().as_duration() {
+            timeout if timeout.is_zero() => self.config.release_time,
+            timeout => timeout,
+        };
+        if let Err(e) = core.release_rotation(timeout) {
+            tracing::warn!(%correlation_id, "cannot release the arm: {}", e);
+            return;
+        }
+        let passage = Passage {
+            card_number,
+            reader_type,
+            nsr: self.passage_nsr(correlation_id).await,
+        };
+        self.queue_notification(&mut core, &passage, TurnstileState::WaitingRotation);
+        self.passage = Some(passage);
+    }
+
+    /// NSR of the access log written for the passage `correlation_id`.
+    async fn passage_nsr(&self, correlation_id: CorrelationId) -> Option<u64> {
+        let access_logs = self.access_logs.as_ref()?;
+        match access_logs
+            .find_by_correlation_id(&correlation_id.to_string())
+            .await
+        {
+            Ok(logs) => logs
+                .iter()
+                .filter_map(|log| log.nsr)
+                .max()
+                .and_then(|nsr| u64::try_from(nsr).ok()),
+            Err(e) => {
+                tracing::warn!(%correlation_id, "cannot look up the passage NSR: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Handle expired timeouts and end finished passages and denials.
+    async fn advance(&mut self) {
+        let mut core = self.emulator.lock().await;
+        if let Err(e) = core.check_timeouts() {
+            tracing::warn!("timeout handling failed: {}", e);
+        }
+        if core.state() != TurnstileState::AwaitingBiometric
+            && let Some(finger) = self.finger.take()
+        {
+            // Logged without holding the emulator
+            drop(core);
+            let response = self.biometric_timeout(finger).await;
+            core = self.emulator.lock().await;
+            if core.state() == TurnstileState::Denied {
+                let _ = core.display_mut().set_line_aligned(
+                    1,
+                    response.display_message(),
+                    Alignment::Center,
+                );
+            }
+        }
+
+        let state = core.state();
+        let back_to_idle = match state {
+            TurnstileState::RotationCompleted | TurnstileState::RotationTimeout => {
+                if let Some(passage) = self.passage.take() {
+                    self.queue_notification(&mut core, &passage, state);
+                }
+                true
+            }
+            // Server messages leave with their own timeout
+            TurnstileState::Denied => {
+                let machine = core.state_machine();
+                machine.time_remaining().is_none()
+                    && machine.time_in_current_state() >= self.config.denied_display
+            }
+            _ => false,
+        };
+        if back_to_idle && let Err(e) = core.transition_to(TurnstileState::Idle) {
+            tracing::warn!("cannot return to idle: {}", e);
+        }
+    }
+
+    /// Log the denial of a card whose finger never came.
+    async fn biometric_timeout(&mut self, finger: PendingFinger) -> AccessResponse {
+        let correlation_id = finger.correlation_id;
+        tracing::debug!(%correlation_id, "no finger presented in time");
+        self.controller
+            .biometric_timeout(&finger.request, &finger.pending)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(%correlation_id, "cannot log the biometric timeout: {}", e);
+                AccessResponse::deny(DisplayMessages::BIOMETRIC_TIMEOUT.to_string())
+            })
+    }
+
+    /// Queue the notification of `passage` reaching `state`.
+    fn queue_notification(
+        &self,
+        core: &mut EmulatorCore,
+        passage: &Passage,
+        state: TurnstileState,
+    ) {
+        match passage_notification(
+            self.config.device_id,
+            state,
+            Some(passage.card_number.clone()),
+            self.config.direction,
+            passage.reader_type,
+            passage.nsr,
+        ) {
+            Ok(message) => core.queue_message(message),
+            Err(e) => tracing::warn!(%state, "passage notification failed: {}", e),
+        }
+    }
+
+    /// Send queued messages through the notifier, connecting it if needed.
+    async fn flush(&mut self) {
+        let Some(client) = self.notifier.as_mut() else {
+            return;
+        };
+
+        let reconnected = !client.is_connected();
+        if reconnected {
+            if Instant::now() < self.next_connect {
+                return;
+            }
+            if let Err(e) = client.connect().await {
+                tracing::debug!("notifier connection failed: {}", e);
+                self.next_connect = Instant::now() + self.config.reconnect_interval;
+                self.record_link(false).await;
+                return;
+            }
+            tracing::info!("notifier connected");
+            let mut core = self.emulator.lock().await;
+            let report = core.status_report(self.controller.mode().validation_mode());
+            match report.to_message(self.config.device_id) {
+                Ok(message) => core.queue_message(message),
+                Err(e) => tracing::warn!("status report failed: {}", e),
+            }
+        }
+
+        loop {
+            let Some(message) = self
+                .emulator
+                .lock()
+                .await
+                .pending_messages()
+                .front()
+                .cloned()
+            else {
+                break;
+            };
+            if let Err(e) = client.send(message).await {
+                tracing::warn!("notifier send failed: {}", e);
+                let _ = client.close().await;
+                self.next_connect = Instant::now() + self.config.reconnect_interval;
+                if let Some(sync) = self.sync.as_mut() {
+                    sync.disconnected();
+                }
+                return;
+            }
+            self.emulator.lock().await.next_message();
+        }
+
+        self.sync_events(reconnected).await;
+    }
+
+    /// Send the access events of the outbox, all of them after a reconnection.
+    async fn sync_events(&mut self, reconnected: bool) {
+        let (Some(client), Some(sync)) = (self.notifier.as_mut(), self.sync.as_mut()) else {
+            return;
+        };
+        let result = if reconnected {
+            sync.reconnected(client).await
+        } else {
+            sync.sync(client).await
+        };
+        match result {
+            Ok(report) if report.failed > 0 => {
+                let _ = client.close().await;
+                self.next_connect = Instant::now() + self.config.reconnect_interval;
+            }
+            Ok(report) if report.sent > 0 => {
+                tracing::debug!(sent = report.sent, "access events sent");
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("access event sync failed: {}", e),
+        }
+    }
+
+    /// Send a keepalive if one is due and record its outcome.
+    async fn heartbeat(&mut self) {
+        let Some(client) = self.notifier.as_mut() else {
+            return;
+        };
+        let answered = match client.heartbeat().await {
+            Ok(Some(event)) => matches!(event.kind, HealthEventKind::Answered { .. }),
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("notifier connection lost: {}", e);
+                let _ = client.close().await;
+                self.next_connect = Instant::now() + self.config.reconnect_interval;
+                if let Some(sync) = self.sync.as_mut() {
+                    sync.disconnected();
+                }
+                false
+            }
+        };
+        self.record_link(answered).await;
+    }
+
+    /// Report a keepalive outcome to the heartbeat monitor.
+    async fn record_link(&mut self, answered: bool) {
+        let Some(monitor) = self.monitor.as_mut() else {
+            return;
+        };
+        let change = if answered {
+            monitor.record_heartbeat(&mut self.controller)
+        } else {
+            monitor.record_missed(&mut self.controller)
+        };
+        if let Some(change) = change {
+            tracing::info!(%change, "validation mode changed");
+        }
+        self.emulator
+            .lock()
+            .await
+            .set_link_online(monitor.is_link_up());
+    }
+
+    /// Flush what can still be sent and release the devices.
+    async fn shutdown(mut self) -> Result<EmulatorCounters> {
+        self.flush().await;
+        if let Some(client) = self.notifier.as_mut() {
+            let _ = client.close().await;
+        }
+
+        let (counters, pending) = {
+            let core = self.emulator.lock().await;
+            (core.counters(), core.pending_messages().len())
+        };
+        tracing::info!(
+            device_id = %self.config.device_id,
+            pending,
+            "emulator runtime stopped"
+        );
+
+        self.peripherals
+            .shutdown()
+            .await
+            .map_err(|e| Error::HardwareError(e.to_string()))?;
+        Ok(counters)
+    }
+}
+
+/// Wait until `due`, or forever without a deadline.
+async fn sleep_until_due(due: Option<Instant>) {
+    match due {
+        Some(due) => tokio::time::sleep_until(due).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turnkey_hardware::devices::{AnyBiometricDevice, AnyKeypadDevice, AnyRfidDevice};
+    use turnkey_hardware::mock::{
+        MockBiometric, MockBiometricHandle, MockKeypad, MockKeypadHandle, MockRfid, MockRfidHandle,
+    };
+    use turnkey_hardware::traits::CardType;
+    use turnkey_hardware::{PeripheralConfig, PeripheralManager, SensorEvent};
+    use turnkey_network::{TcpClientConfig, TcpServer, TcpServerConfig};
+    use turnkey_protocol::commands::events::EventChunk;
+    use turnkey_protocol::{CommandCode, Message};
+    use turnkey_storage::models::ReaderType as LogReaderType;
+    use turnkey_storage::outbox::Outbox;
+    use turnkey_storage::pin::PinDigestKey;
+    use turnkey_storage::repositories::{
+        AccessLogQuery, AccessLogRepository, BiometricTemplateRepository, DeviceRepository,
+        SqliteAccessLogRepository, SqliteBiometricTemplateRepository, SqliteDeviceRepository,
+        SqliteUserRepository, UserRepository,
+    };
+    use turnkey_storage::retention::RetentionPolicy;
+    use turnkey_storage::{
+        AccessLog, Database, Direction, OfflineValidator, OnlineValidator, OnlineValidatorConfig,
+        OperatingMode, VerificationMode, transaction,
+    };
+
+    /// UID whose decimal form is the seeded card of user 1005
+    const SEEDED_UID: [u8; 4] = [0x03, 0x4F, 0xBA, 0x9E];
+    const SEEDED_CARD: &str = "00000000000055556766";
+
+    /// Enrolled finger of user 1005 in the card + biometric tests
+    const FINGER: [u8; 600] = [7; 600];
+
+    fn pin_digest_key() -> PinDigestKey {
+        PinDigestKey::new([7; 32].to_vec().into()).unwrap()
+    }
+
+    fn test_config() -> RuntimeConfig {
+        RuntimeConfig::new(DeviceId::new(15).unwrap())
+            .with_tick(Duration::from_millis(10))
+            .with_denied_display(Duration::from_millis(50))
+    }
+
+    fn runtime(
+        config: RuntimeConfig,
+        offline: OfflineValidator,
+        manager: PeripheralManager,
+    ) -> EmulatorRuntime {
+        let device_id = config.device_id;
+        let online = OnlineValidator::new(
+            TcpClient::new(TcpClientConfig::default()),
+            device_id,
+            OnlineValidatorConfig::default(),
+        );
+        let controller = ValidationModeController::new(
+            device_id,
+            online,
+            offline.with_device_id(device_id),
+            OperatingMode::Offline,
+        );
+        EmulatorRuntime::new(config, EmulatorCore::default(), controller, manager.start())
+    }
+
+    async fn offline_runtime(db: &Database) -> (EmulatorRuntime, MockRfidHandle) {
+        let (rfid, mut handle) = MockRfid::new();
+        handle
+            .add_card(SEEDED_UID.to_vec(), CardType::MifareClassic1K)
+            .await;
+        handle
+            .add_card(vec![0xDE, 0xAD, 0xBE, 0xEF], CardType::MifareClassic1K)
+            .await;
+        let mut manager = PeripheralManager::new(PeripheralConfig::default());
+        manager.register_rfid(AnyRfidDevice::Mock(rfid));
+
+        let offline = OfflineValidator::new(db.pool().clone());
+        (runtime(test_config(), offline, manager), handle)
+    }
+
+    /// Runtime reading codes typed on a keypad
+    fn keypad_runtime(db: &Database) -> (EmulatorRuntime, MockKeypadHandle) {
+        let (keypad, handle) = MockKeypad::new();
+        let mut manager = PeripheralManager::new(PeripheralConfig::default());
+        manager.register_keypad(AnyKeypadDevice::Mock(keypad));
+
+        let offline =
+            OfflineValidator::new(db.pool().clone()).with_pin_digest_key(pin_digest_key());
+        (runtime(test_config(), offline, manager), handle)
+    }
+
+    /// Card + biometric device 15, where user 1005 enrolled [`FINGER`]
+    async fn card_plus_biometric_runtime(
+        db: &Database,
+        biometric_timeout: Duration,
+    ) -> (EmulatorRuntime, MockRfidHandle, MockBiometricHandle) {
+        let users = SqliteUserRepository::new(db.pool().clone()).with_digest_key(pin_digest_key());
+        let mut user = users.find_by_matricula("1005").await.unwrap().unwrap();
+        user.allow_bio = true;
+        users.update(&user).await.unwrap();
+        SqliteBiometricTemplateRepository::new(db.pool().clone())
+            .save("1005", 1, &FINGER)
+            .await
+            .unwrap();
+        SqliteDeviceRepository::new(db.pool().clone())
+            .set_verification_mode(15, VerificationMode::CardPlusBiometric)
+            .await
+            .unwrap();
+
+        let (rfid, mut rfid_handle) = MockRfid::new();
+        rfid_handle
+            .add_card(SEEDED_UID.to_vec(), CardType::MifareClassic1K)
+            .await;
+        let (scanner, scanner_handle) = MockBiometric::new();
+        let mut manager = PeripheralManager::new(PeripheralConfig {
+            biometric_enabled: true,
+            ..PeripheralConfig::default()
+        });
+        manager.register_rfid(AnyRfidDevice::Mock(rfid));
+        manager.register_biometric(AnyBiometricDevice::Mock(scanner));
+
+        let offline = OfflineValidator::new(db.pool().clone());
+        let config = test_config().with_biometric_timeout(biometric_timeout);
+        (
+            runtime(config, offline, manager),
+            rfid_handle,
+            scanner_handle,
+        )
+    }
+
+    async fn wait_for(emulator: &Mutex<EmulatorCore>, state: TurnstileState) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while emulator.lock().await.state() != state {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("emulator never reached {}", state));
+    }
+
+    fn pending_commands(core: &EmulatorCore) -> Vec<CommandCode> {
+        core.pending_messages()
+            .iter()
+            .map(|message| message.command)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_offline_grant_rotates_and_notifies() {
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, mut rfid) = offline_runtime(&db).await;
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        rfid.present_card(SEEDED_UID.to_vec()).await.unwrap();
+        wait_for(&emulator, TurnstileState::WaitingRotation).await;
+        {
+            let core = emulator.lock().await;
+            assert_eq!(pending_commands(&core), [CommandCode::WaitingRotation]);
+            let notification = core.pending_messages().front().unwrap();
+            assert_eq!(notification.field(0), Some(SEEDED_CARD));
+        }
+
+        emulator
+            .lock()
+            .await
+            .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Entry))
+            .unwrap();
+        wait_for(&emulator, TurnstileState::Idle).await;
+        assert_eq!(
+            pending_commands(&*emulator.lock().await),
+            [CommandCode::WaitingRotation, CommandCode::RotationCompleted]
+        );
+
+        stop.cancel();
+        let counters = task.await.unwrap().unwrap();
+        assert_eq!((counters.granted, counters.rotations), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_passage_notifications_carry_log_nsr() {
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, mut rfid) = offline_runtime(&db).await;
+        let runtime = runtime.with_access_logs(SqliteAccessLogRepository::new(db.pool().clone()));
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        rfid.present_card(SEEDED_UID.to_vec()).await.unwrap();
+        wait_for(&emulator, TurnstileState::WaitingRotation).await;
+        emulator
+            .lock()
+            .await
+            .handle_sensor_event(SensorEvent::ArmRotated(AccessDirection::Entry))
+            .unwrap();
+        wait_for(&emulator, TurnstileState::Idle).await;
+        stop.cancel();
+        task.await.unwrap().unwrap();
+
+        let logs = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number(SEEDED_CARD, 1)
+            .await
+            .unwrap();
+        let nsr = logs[0].nsr.unwrap().to_string();
+        let core = emulator.lock().await;
+        let fields: Vec<_> = core
+            .pending_messages()
+            .iter()
+            .map(|message| message.field(4))
+            .collect();
+        assert_eq!(fields, [Some(nsr.as_str()), Some(nsr.as_str())]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_card_is_denied_without_notification() {
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, mut rfid) = offline_runtime(&db).await;
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        rfid.present_card(vec![0xDE, 0xAD, 0xBE, 0xEF])
+            .await
+            .unwrap();
+        wait_for(&emulator, TurnstileState::Denied).await;
+        wait_for(&emulator, TurnstileState::Idle).await;
+        assert!(emulator.lock().await.pending_messages().is_empty());
+
+        stop.cancel();
+        let counters = task.await.unwrap().unwrap();
+        assert_eq!((counters.granted, counters.denied), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_keypad_code_grants_passage() {
+        let db = Database::in_memory().await.unwrap();
+        let users = SqliteUserRepository::new(db.pool().clone()).with_digest_key(pin_digest_key());
+        let mut user = users.find_by_matricula("1005").await.unwrap().unwrap();
+        user.allow_keypad = true;
+        user.codigo = Some("482916".to_string());
+        users.update(&user).await.unwrap();
+
+        let (runtime, keypad) = keypad_runtime(&db);
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        // A mistyped digit is cleared before the code
+        keypad.send_input(KeypadInput::Digit(9)).await.unwrap();
+        keypad.send_input(KeypadInput::Clear).await.unwrap();
+        keypad.send_digits(&[4, 8, 2, 9, 1, 6]).await.unwrap();
+        keypad.send_input(KeypadInput::Enter).await.unwrap();
+        wait_for(&emulator, TurnstileState::WaitingRotation).await;
+
+        stop.cancel();
+        let counters = task.await.unwrap().unwrap();
+        assert_eq!((counters.granted, counters.denied), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_card_plus_biometric_grants_after_finger() {
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, mut rfid, scanner) =
+            card_plus_biometric_runtime(&db, DEFAULT_BIOMETRIC_TIMEOUT).await;
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        rfid.present_card(SEEDED_UID.to_vec()).await.unwrap();
+        wait_for(&emulator, TurnstileState::AwaitingBiometric).await;
+        assert!(emulator.lock().await.pending_messages().is_empty());
+
+        scanner.present_finger(FINGER.to_vec()).await.unwrap();
+        wait_for(&emulator, TurnstileState::WaitingRotation).await;
+        {
+            let core = emulator.lock().await;
+            assert_eq!(pending_commands(&core), [CommandCode::WaitingRotation]);
+            let notification = core.pending_messages().front().unwrap();
+            assert_eq!(notification.field(0), Some(SEEDED_CARD));
+        }
+
+        stop.cancel();
+        let counters = task.await.unwrap().unwrap();
+        assert_eq!((counters.granted, counters.denied), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_card_plus_biometric_denies_without_finger() {
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, mut rfid, _scanner) =
+            card_plus_biometric_runtime(&db, Duration::from_millis(50)).await;
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        rfid.present_card(SEEDED_UID.to_vec()).await.unwrap();
+        wait_for(&emulator, TurnstileState::AwaitingBiometric).await;
+        wait_for(&emulator, TurnstileState::Denied).await;
+        wait_for(&emulator, TurnstileState::Idle).await;
+
+        stop.cancel();
+        let counters = task.await.unwrap().unwrap();
+        assert_eq!((counters.granted, counters.denied), (0, 1));
+        let logs = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number(SEEDED_CARD, 1)
+            .await
+            .unwrap();
+        assert!(!logs[0].granted);
+        assert_eq!(
+            logs[0].display_message.as_deref(),
+            Some(DisplayMessages::BIOMETRIC_TIMEOUT)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retention_prunes_until_shutdown() {
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, _rfid) = offline_runtime(&db).await;
+        let manager = RetentionManager::new(db.pool().clone(), RetentionPolicy::days(0));
+        let runtime = runtime.with_retention(manager, Duration::from_millis(10));
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        let logs = SqliteAccessLogRepository::new(db.pool().clone());
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while logs.count(&AccessLogQuery::new()).await.unwrap() > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("seeded access logs were never pruned");
+
+        stop.cancel();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_notifier_sends_status_and_passage_messages() {
+        let mut server = TcpServer::bind(TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            max_connections: 4,
+        })
+        .await
+        .unwrap();
+        let client_config = TcpClientConfig::builder()
+            .server_addr(server.local_addr().unwrap())
+            .build()
+            .unwrap();
+
+        let db = Database::in_memory().await.unwrap();
+        let (runtime, mut rfid) = offline_runtime(&db).await;
+        let runtime = runtime.with_notifier(TcpClient::new(client_config));
+        let emulator = runtime.emulator();
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        // Status report right after connecting
+        let (device_id, report) = server.accept().await.unwrap();
+        assert_eq!(device_id, DeviceId::new(15).unwrap());
+        assert_eq!(report.command, CommandCode::QueryStatus);
+
+        rfid.present_card(SEEDED_UID.to_vec()).await.unwrap();
+        let notification: Message = server.recv(device_id).await.unwrap().unwrap();
+        assert_eq!(notification.command, CommandCode::WaitingRotation);
+        assert_eq!(notification.field(0), Some(SEEDED_CARD));
+        assert!(emulator.lock().await.pending_messages().is_empty());
+
+        stop.cancel();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_replays_buffered_events_on_connect() {
+        let mut server = TcpServer::bind(TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            max_connections: 4,
+        })
+        .await
+        .unwrap();
+        let client_config = TcpClientConfig::builder()
+            .server_addr(server.local_addr().unwrap())
+            .build()
+            .unwrap();
+
+        // An access decided while the server was unreachable
+        let db = Database::in_memory().await.unwrap();
+        let outbox = Outbox::new(db.pool().clone());
+        let log = AccessLog::new(
+            Some(5),
+            Some("1005".to_string()),
+            SEEDED_CARD.to_string(),
+            Direction::Entry,
+            LogReaderType::Rfid,
+            true,
+            None,
+            chrono::Utc::now(),
+        );
+        let mut tx = outbox.begin().await.unwrap();
+        let log_id = transaction::create_access_log(&mut tx, &log).await.unwrap();
+        let device_id = DeviceId::new(15).unwrap();
+        Outbox::enqueue_access(&mut tx, device_id, log_id, &log)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let (runtime, _rfid) = offline_runtime(&db).await;
+        let runtime = runtime
+            .with_notifier(TcpClient::new(client_config))
+            .with_sync(SyncEngine::new(db.pool().clone()));
+        let stop = CancellationToken::new();
+        let task = tokio::spawn(runtime.run(stop.clone()));
+
+        let (device_id, report) = server.accept().await.unwrap();
+        assert_eq!(report.command, CommandCode::QueryStatus);
+        let replayed: Message = server.recv(device_id).await.unwrap().unwrap();
+        let chunk = EventChunk::parse(&replayed).unwrap();
+        assert_eq!(chunk.events[0].id, 1);
+        assert_eq!(chunk.events[0].card_number, SEEDED_CARD);
+
+        stop.cancel();
+        task.await.unwrap().unwrap();
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+    }
+}
@@ -16,6 +16,8 @@
 //! - `RotationInProgress`: Physical rotation happening
 //! - `RotationCompleted`: User passed through successfully
 //! - `RotationTimeout`: User did not pass within timeout period
+//! - `Enrolling`: Next card read is registered for a user instead of validated
+//! - `AwaitingBiometric`: Card accepted, waiting for the owner's fingerprint
 //!
 //! # Valid Transitions
 //!
@@ -23,6 +25,16 @@
 //! - Granted → WaitingRotation → RotationInProgress → RotationCompleted → Idle
 //! - WaitingRotation → RotationTimeout → Idle
 //! - Denied → Idle
+//! - Idle → Enrolling → Reading, or Enrolling → Idle when the session expires
+//! - Validating → AwaitingBiometric → Validating on card + biometric devices,
+//!   or AwaitingBiometric → Denied when no finger is presented in time (see
+//!   [`StateMachine::await_biometric()`])
+//! - Denied → WaitingRotation, only through
+//!   [`StateMachine::override_denial()`] when an operator grants a denied access
+//! - Idle → WaitingRotation, only through [`StateMachine::request_exit()`]
+//!   when an exit button releases the passage without validation
+//! - Validating → Denied → Idle when the server only shows a message (see
+//!   [`StateMachine::show_message()`]); the message stays for its timeout
 //!
 //! # Protocol Mapping
 //!
@@ -65,7 +77,7 @@ use std::time::{Duration, Instant, SystemTime};
 
 use serde::{Deserialize, Serialize};
 
-use turnkey_core::{Error, Result};
+use turnkey_core::{Error, Result, SharedClock, SystemClock};
 use turnkey_protocol::commands::turnstile::TurnstileState;
 
 /// Maximum number of state transitions to keep in history.
@@ -95,7 +107,7 @@ const MAX_HISTORY_SIZE: usize = 100;
 /// For relative timing calculations within a single process run,
 /// use the `elapsed()` method which internally uses `Instant` for
 /// monotonic time measurement.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateTransition {
     /// The state transitioned from.
     pub from: TurnstileState,
@@ -185,6 +197,9 @@ pub struct StateMachine {
 
     /// Optional timeout duration for the current state.
     current_timeout: Option<Duration>,
+
+    /// Time source for state timeouts.
+    clock: SharedClock,
 }
 
 impl StateMachine {
@@ -203,14 +218,53 @@ impl StateMachine {
     /// assert_eq!(machine.current_state(), &TurnstileState::Idle);
     /// ```
     pub fn new() -> Self {
+        let clock = SystemClock::shared();
         Self {
             current_state: TurnstileState::Idle,
-            state_entered_at: Instant::now(),
+            state_entered_at: clock.instant(),
             history: VecDeque::with_capacity(MAX_HISTORY_SIZE),
             current_timeout: None,
+            clock,
         }
     }
 
+    /// Measure state timeouts with `clock` instead of the system clock.
+    ///
+    /// The time already spent in the current state is kept, so this can
+    /// also be called on a running machine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use chrono::Utc;
+    /// use turnkey_core::MockClock;
+    /// use turnkey_emulator::{StateMachine, TurnstileState};
+    ///
+    /// let clock = Arc::new(MockClock::new(Utc::now()));
+    /// let mut machine = StateMachine::new().with_clock(clock.clone());
+    /// machine.transition_to(TurnstileState::Reading).unwrap();
+    /// machine.set_timeout(Duration::from_secs(5));
+    ///
+    /// clock.advance(Duration::from_secs(4));
+    /// assert!(!machine.has_timed_out());
+    /// clock.advance(Duration::from_secs(1));
+    /// assert!(machine.has_timed_out());
+    /// ```
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        let elapsed = self.time_in_current_state();
+        let now = clock.instant();
+        self.state_entered_at = now.checked_sub(elapsed).unwrap_or(now);
+        self.clock = clock;
+        self
+    }
+
+    /// Time source for state timeouts.
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
     /// Create a builder for constructing a state machine with custom configuration.
     ///
     /// This is useful for advanced scenarios like crash recovery where you need
@@ -252,7 +306,9 @@ impl StateMachine {
     ///
     /// Returns the duration since entering the current state.
     pub fn time_in_current_state(&self) -> Duration {
-        self.state_entered_at.elapsed()
+        self.clock
+            .instant()
+            .saturating_duration_since(self.state_entered_at)
     }
 
     /// Check if the current state has timed out.
@@ -388,6 +444,161 @@ impl StateMachine {
         Ok(transition)
     }
 
+    /// Release the turnstile after a denial, on an operator's decision.
+    ///
+    /// `Denied → WaitingRotation` is not part of the regular flow, so
+    /// [`transition_to()`](Self::transition_to) keeps rejecting it; this is
+    /// the only way to take that edge. The rotation timeout starts with the
+    /// release, so an override nobody uses closes the turnstile again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the machine is in
+    /// `Denied`. A denial that already returned to `Idle` cannot be
+    /// overridden; the person has to present the credential again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_emulator::{StateMachine, TurnstileState};
+    ///
+    /// let mut machine = StateMachine::new();
+    /// machine.transition_to(TurnstileState::Reading).unwrap();
+    /// machine.transition_to(TurnstileState::Validating).unwrap();
+    /// machine.transition_to(TurnstileState::Denied).unwrap();
+    ///
+    /// machine.override_denial(Duration::from_secs(10)).unwrap();
+    /// assert_eq!(machine.current_state(), &TurnstileState::WaitingRotation);
+    /// assert!(machine.time_remaining().is_some());
+    /// ```
+    pub fn override_denial(&mut self, timeout: Duration) -> Result<StateTransition> {
+        if self.current_state != TurnstileState::Denied {
+            return Err(Error::InvalidStateTransition {
+                from: self.current_state.to_string(),
+                to: TurnstileState::WaitingRotation.to_string(),
+            });
+        }
+
+        let transition = StateTransition::new(self.current_state, TurnstileState::WaitingRotation);
+        self.perform_state_change(TurnstileState::WaitingRotation, transition.clone());
+        self.set_timeout(timeout);
+
+        Ok(transition)
+    }
+
+    /// Release the turnstile for an exit without validation.
+    ///
+    /// Request-to-exit inputs (push buttons, motion detectors) open the
+    /// passage directly from `Idle`, skipping reading and validation. Like
+    /// [`override_denial()`](Self::override_denial), the edge is not part of
+    /// the regular flow and [`transition_to()`](Self::transition_to) keeps
+    /// rejecting it; the rotation timeout starts with the release.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the machine is in
+    /// `Idle`, so an exit request never interrupts a passage in progress.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_emulator::{StateMachine, TurnstileState};
+    ///
+    /// let mut machine = StateMachine::new();
+    /// machine.request_exit(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(machine.current_state(), &TurnstileState::WaitingRotation);
+    ///
+    /// // Already released: a second request is rejected
+    /// assert!(machine.request_exit(Duration::from_secs(5)).is_err());
+    /// ```
+    pub fn request_exit(&mut self, timeout: Duration) -> Result<StateTransition> {
+        if self.current_state != TurnstileState::Idle {
+            return Err(Error::InvalidStateTransition {
+                from: self.current_state.to_string(),
+                to: TurnstileState::WaitingRotation.to_string(),
+            });
+        }
+
+        let transition = StateTransition::new(self.current_state, TurnstileState::WaitingRotation);
+        self.perform_state_change(TurnstileState::WaitingRotation, transition.clone());
+        self.set_timeout(timeout);
+
+        Ok(transition)
+    }
+
+    /// Wait for the second factor after the card was accepted.
+    ///
+    /// Moves `Validating → AwaitingBiometric` and starts `timeout`: the
+    /// fingerprint read moves the machine back to `Validating`, while
+    /// [`check_and_handle_timeout()`](Self::check_and_handle_timeout)
+    /// denies the access once the timeout expires.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the machine is in
+    /// `Validating`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_emulator::{StateMachine, TurnstileState};
+    ///
+    /// let mut machine = StateMachine::new();
+    /// machine.transition_to(TurnstileState::Reading).unwrap();
+    /// machine.transition_to(TurnstileState::Validating).unwrap();
+    ///
+    /// machine.await_biometric(Duration::from_secs(10)).unwrap();
+    /// assert_eq!(machine.current_state(), &TurnstileState::AwaitingBiometric);
+    /// assert!(machine.time_remaining().is_some());
+    ///
+    /// // Finger read: verify it against the card owner's templates
+    /// machine.transition_to(TurnstileState::Validating).unwrap();
+    /// ```
+    pub fn await_biometric(&mut self, timeout: Duration) -> Result<StateTransition> {
+        let transition = self.transition_to(TurnstileState::AwaitingBiometric)?;
+        self.set_timeout(timeout);
+        Ok(transition)
+    }
+
+    /// Show a server message without releasing the turnstile.
+    ///
+    /// Message-only responses (`00+0`) keep the arm locked like a denial,
+    /// so the machine moves `Validating → Denied`. Unlike a regular denial,
+    /// `timeout` is started: once it expires,
+    /// [`check_and_handle_timeout()`](Self::check_and_handle_timeout) returns
+    /// the machine to `Idle`. A zero timeout keeps the message until the next
+    /// transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidStateTransition` unless the machine is in
+    /// `Validating`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_emulator::{StateMachine, TurnstileState};
+    ///
+    /// let mut machine = StateMachine::new();
+    /// machine.transition_to(TurnstileState::Reading).unwrap();
+    /// machine.transition_to(TurnstileState::Validating).unwrap();
+    ///
+    /// machine.show_message(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(machine.current_state(), &TurnstileState::Denied);
+    /// assert!(machine.time_remaining().is_some());
+    /// ```
+    pub fn show_message(&mut self, timeout: Duration) -> Result<StateTransition> {
+        let transition = self.transition_to(TurnstileState::Denied)?;
+        if !timeout.is_zero() {
+            self.set_timeout(timeout);
+        }
+        Ok(transition)
+    }
+
     /// Check for timeout and automatically transition to timeout state if needed.
     ///
     /// This is a convenience method that combines timeout checking with
@@ -429,6 +640,12 @@ impl StateMachine {
         // Determine appropriate timeout state based on current state
         let timeout_state = match self.current_state {
             TurnstileState::WaitingRotation => TurnstileState::RotationTimeout,
+            // Enrollment session expired without a card
+            TurnstileState::Enrolling => TurnstileState::Idle,
+            // Card accepted but the fingerprint never came
+            TurnstileState::AwaitingBiometric => TurnstileState::Denied,
+            // Server message shown for its timeout
+            TurnstileState::Denied => TurnstileState::Idle,
             // Future: Could add ValidationTimeout state for Validating state
             _ => return Ok(None),
         };
@@ -457,7 +674,7 @@ impl StateMachine {
     /// between transition_to and reset methods (DRY principle).
     fn perform_state_change(&mut self, new_state: TurnstileState, transition: StateTransition) {
         self.current_state = new_state;
-        self.state_entered_at = Instant::now();
+        self.state_entered_at = self.clock.instant();
         self.current_timeout = None;
 
         // Add to history with size limit enforcement
@@ -475,6 +692,83 @@ impl StateMachine {
     }
 }
 
+/// Serializable copy of a state machine's state.
+///
+/// `Instant` values cannot be serialized, so the time spent in the current
+/// state is stored as a duration and re-anchored to "now" on restore. A
+/// restored machine therefore has the same `time_remaining()` as the
+/// original had when the snapshot was taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateMachineSnapshot {
+    /// Current state.
+    pub state: TurnstileState,
+
+    /// Transition history, oldest first.
+    pub history: Vec<StateTransition>,
+
+    /// Timeout of the current state, if any.
+    pub timeout: Option<Duration>,
+
+    /// Time already spent in the current state.
+    pub time_in_state: Duration,
+}
+
+impl StateMachine {
+    /// Capture the complete machine state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_emulator::{StateMachine, TurnstileState};
+    ///
+    /// let mut machine = StateMachine::new();
+    /// machine.transition_to(TurnstileState::Reading).unwrap();
+    ///
+    /// let snapshot = machine.snapshot();
+    /// machine.reset();
+    ///
+    /// machine.restore(snapshot);
+    /// assert_eq!(machine.current_state(), &TurnstileState::Reading);
+    /// assert_eq!(machine.history().len(), 1);
+    /// ```
+    pub fn snapshot(&self) -> StateMachineSnapshot {
+        StateMachineSnapshot {
+            state: self.current_state,
+            history: self.history.iter().cloned().collect(),
+            timeout: self.current_timeout,
+            time_in_state: self.time_in_current_state(),
+        }
+    }
+
+    /// Replace the machine state with a snapshot.
+    ///
+    /// History beyond the size limit is trimmed from the oldest end.
+    pub fn restore(&mut self, snapshot: StateMachineSnapshot) {
+        *self = Self::from_snapshot_with_clock(snapshot, self.clock.clone());
+    }
+
+    /// Build a machine from a snapshot.
+    pub fn from_snapshot(snapshot: StateMachineSnapshot) -> Self {
+        Self::from_snapshot_with_clock(snapshot, SystemClock::shared())
+    }
+
+    fn from_snapshot_with_clock(snapshot: StateMachineSnapshot, clock: SharedClock) -> Self {
+        let mut history: VecDeque<StateTransition> = snapshot.history.into();
+        while history.len() > MAX_HISTORY_SIZE {
+            history.pop_front();
+        }
+
+        let now = clock.instant();
+        Self {
+            current_state: snapshot.state,
+            state_entered_at: now.checked_sub(snapshot.time_in_state).unwrap_or(now),
+            history,
+            current_timeout: snapshot.timeout,
+            clock,
+        }
+    }
+}
+
 impl Default for StateMachine {
     fn default() -> Self {
         Self::new()
@@ -505,6 +799,7 @@ pub struct StateMachineBuilder {
     initial_state: TurnstileState,
     history: VecDeque<StateTransition>,
     timeout: Option<Duration>,
+    clock: Option<SharedClock>,
 }
 
 impl StateMachineBuilder {
@@ -550,17 +845,33 @@ impl StateMachineBuilder {
         self
     }
 
+    /// Set the time source for state timeouts.
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - The clock to use instead of the system clock
+    ///
+    /// # Returns
+    ///
+    /// Returns self for method chaining.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
     /// Build the state machine with configured parameters.
     ///
     /// # Returns
     ///
     /// Returns a new `StateMachine` with the specified configuration.
     pub fn build(self) -> StateMachine {
+        let clock = self.clock.unwrap_or_else(SystemClock::shared);
         StateMachine {
             current_state: self.initial_state,
-            state_entered_at: Instant::now(),
+            state_entered_at: clock.instant(),
             history: self.history,
             current_timeout: self.timeout,
+            clock,
         }
     }
 }
@@ -571,6 +882,7 @@ impl Default for StateMachineBuilder {
             initial_state: TurnstileState::Idle,
             history: VecDeque::with_capacity(MAX_HISTORY_SIZE),
             timeout: None,
+            clock: None,
         }
     }
 }
@@ -578,7 +890,9 @@ impl Default for StateMachineBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
     use std::thread;
+    use turnkey_core::MockClock;
 
     #[test]
     fn test_new_machine_starts_idle() {
@@ -1097,6 +1411,69 @@ mod tests {
         assert!(machine.time_remaining().is_some());
     }
 
+    #[test]
+    fn test_override_denial_releases_for_rotation() {
+        let mut machine = StateMachine::new();
+        machine.transition_to(TurnstileState::Reading).unwrap();
+        machine.transition_to(TurnstileState::Validating).unwrap();
+        machine.transition_to(TurnstileState::Denied).unwrap();
+
+        // The regular transition stays forbidden
+        assert!(
+            machine
+                .transition_to(TurnstileState::WaitingRotation)
+                .is_err()
+        );
+
+        let transition = machine.override_denial(Duration::from_secs(5)).unwrap();
+        assert_eq!(transition.from, TurnstileState::Denied);
+        assert_eq!(transition.to, TurnstileState::WaitingRotation);
+        assert!(machine.time_remaining().unwrap() <= Duration::from_secs(5));
+
+        machine
+            .transition_to(TurnstileState::RotationInProgress)
+            .unwrap();
+        machine
+            .transition_to(TurnstileState::RotationCompleted)
+            .unwrap();
+        machine.transition_to(TurnstileState::Idle).unwrap();
+    }
+
+    #[test]
+    fn test_override_denial_requires_denied_state() {
+        for state in [
+            TurnstileState::Idle,
+            TurnstileState::Validating,
+            TurnstileState::Granted,
+            TurnstileState::WaitingRotation,
+        ] {
+            let mut machine = StateMachine::builder().with_initial_state(state).build();
+            assert!(machine.override_denial(Duration::from_secs(5)).is_err());
+            assert_eq!(machine.current_state(), &state);
+            assert!(machine.history().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_request_exit_releases_from_idle_only() {
+        let mut machine = StateMachine::new();
+        assert!(
+            machine
+                .transition_to(TurnstileState::WaitingRotation)
+                .is_err()
+        );
+
+        let transition = machine.request_exit(Duration::from_secs(5)).unwrap();
+        assert_eq!(transition.from, TurnstileState::Idle);
+        assert_eq!(transition.to, TurnstileState::WaitingRotation);
+        assert!(machine.time_remaining().is_some());
+
+        let mut busy = StateMachine::new();
+        busy.transition_to(TurnstileState::Reading).unwrap();
+        assert!(busy.request_exit(Duration::from_secs(5)).is_err());
+        assert_eq!(busy.current_state(), &TurnstileState::Reading);
+    }
+
     #[test]
     fn test_check_and_handle_timeout_no_timeout() {
         let mut machine = StateMachine::new();
@@ -1156,6 +1533,54 @@ mod tests {
         assert_eq!(machine.current_state(), &TurnstileState::Idle);
     }
 
+    #[test]
+    fn test_check_and_handle_timeout_enrolling() {
+        let mut machine = StateMachine::new();
+        machine.transition_to(TurnstileState::Enrolling).unwrap();
+        machine.set_timeout(Duration::from_millis(50));
+
+        thread::sleep(Duration::from_millis(100));
+
+        let transition = machine.check_and_handle_timeout().unwrap().unwrap();
+        assert_eq!(transition.from, TurnstileState::Enrolling);
+        assert_eq!(transition.to, TurnstileState::Idle);
+    }
+
+    #[test]
+    fn test_await_biometric_times_out_to_denied() {
+        let mut machine = StateMachine::new();
+        assert!(machine.await_biometric(Duration::from_millis(50)).is_err());
+
+        machine.transition_to(TurnstileState::Reading).unwrap();
+        machine.transition_to(TurnstileState::Validating).unwrap();
+        let transition = machine.await_biometric(Duration::from_millis(50)).unwrap();
+        assert_eq!(transition.from, TurnstileState::Validating);
+        assert_eq!(transition.to, TurnstileState::AwaitingBiometric);
+
+        thread::sleep(Duration::from_millis(100));
+
+        let transition = machine.check_and_handle_timeout().unwrap().unwrap();
+        assert_eq!(transition.from, TurnstileState::AwaitingBiometric);
+        assert_eq!(transition.to, TurnstileState::Denied);
+    }
+
+    #[test]
+    fn test_show_message_times_out_to_idle() {
+        let mut machine = StateMachine::new();
+        assert!(machine.show_message(Duration::from_millis(50)).is_err());
+
+        machine.transition_to(TurnstileState::Reading).unwrap();
+        machine.transition_to(TurnstileState::Validating).unwrap();
+        let transition = machine.show_message(Duration::from_millis(50)).unwrap();
+        assert_eq!(transition.to, TurnstileState::Denied);
+
+        thread::sleep(Duration::from_millis(100));
+
+        let transition = machine.check_and_handle_timeout().unwrap().unwrap();
+        assert_eq!(transition.from, TurnstileState::Denied);
+        assert_eq!(transition.to, TurnstileState::Idle);
+    }
+
     #[test]
     fn test_state_serialization() {
         let state = TurnstileState::WaitingRotation;
@@ -1181,4 +1606,56 @@ mod tests {
         assert_eq!(deserialized.from, TurnstileState::Idle);
         assert_eq!(deserialized.to, TurnstileState::Reading);
     }
+
+    #[test]
+    fn test_from_snapshot_trims_history() {
+        let transition = StateTransition::new(TurnstileState::Idle, TurnstileState::Reading);
+        let snapshot = StateMachineSnapshot {
+            state: TurnstileState::Reading,
+            history: vec![transition; MAX_HISTORY_SIZE + 5],
+            timeout: None,
+            time_in_state: Duration::from_secs(2),
+        };
+
+        let machine = StateMachine::from_snapshot(snapshot);
+
+        assert_eq!(machine.current_state(), &TurnstileState::Reading);
+        assert_eq!(machine.history().len(), MAX_HISTORY_SIZE);
+        assert!(machine.time_in_current_state() >= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_mock_clock_drives_timeouts() {
+        let clock = Arc::new(MockClock::new(chrono::Utc::now()));
+        let mut machine = StateMachine::builder()
+            .with_initial_state(TurnstileState::WaitingRotation)
+            .with_timeout(Duration::from_secs(10))
+            .with_clock(clock.clone())
+            .build();
+
+        clock.advance(Duration::from_secs(9));
+        assert_eq!(machine.time_remaining(), Some(Duration::from_secs(1)));
+        assert!(machine.check_and_handle_timeout().unwrap().is_none());
+
+        clock.advance(Duration::from_secs(1));
+        let transition = machine.check_and_handle_timeout().unwrap().unwrap();
+        assert_eq!(transition.to, TurnstileState::RotationTimeout);
+        assert_eq!(machine.time_in_current_state(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_restore_keeps_clock() {
+        let clock = Arc::new(MockClock::new(chrono::Utc::now()));
+        let mut machine = StateMachine::new().with_clock(clock.clone());
+        machine.transition_to(TurnstileState::Reading).unwrap();
+        clock.advance(Duration::from_secs(3));
+        let snapshot = machine.snapshot();
+
+        machine.reset();
+        machine.restore(snapshot);
+        assert_eq!(machine.time_in_current_state(), Duration::from_secs(3));
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(machine.time_in_current_state(), Duration::from_secs(5));
+    }
 }
@@ -0,0 +1,292 @@
+//! Checking server responses against the protocol.
+//!
+//! Real turnstiles are forgiving: a response with a missing timeout or an
+//! overlong message still releases or locks the arm. The emulator behaves
+//! the same by default, which hides server bugs until they meet a device
+//! that is not. With [`Strictness::Strict`], set through
+//! [`EmulatorCore::with_strictness()`], [`EmulatorCore::handle_response()`]
+//! rejects any response that does not follow the protocol exactly:
+//!
+//! - exactly two fields after the command: timeout and display message,
+//! - a timeout of 0 to 255 seconds, and not 0 on a grant, which would leave
+//!   the arm released until the next passage,
+//! - a display message of at most
+//!   [`MAX_DISPLAY_MESSAGE_LENGTH`](turnkey_core::constants::MAX_DISPLAY_MESSAGE_LENGTH)
+//!   characters.
+//!
+//! A rejected response changes nothing, so the emulator stays in
+//! `Validating` until a valid response or the validation timeout. In
+//! [`Strictness::Lenient`] mode the same responses are repaired: a missing
+//! or unreadable timeout takes the default for the decision, a missing
+//! message is left empty and a long one is truncated.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_emulator::{EmulatorCore, Strictness, TurnstileState};
+//! use turnkey_protocol::MessageParser;
+//!
+//! // Grant without a timeout field
+//! let response = MessageParser::parse("15+REON+00+5]Bem-vindo]").unwrap();
+//!
+//! let mut lenient = EmulatorCore::default();
+//! lenient.transition_to(TurnstileState::Reading).unwrap();
+//! lenient.transition_to(TurnstileState::Validating).unwrap();
+//! lenient.handle_response(&response).unwrap();
+//! assert_eq!(lenient.state(), TurnstileState::Granted);
+//!
+//! let mut strict = EmulatorCore::default().with_strictness(Strictness::Strict);
+//! strict.transition_to(TurnstileState::Reading).unwrap();
+//! strict.transition_to(TurnstileState::Validating).unwrap();
+//! assert!(strict.handle_response(&response).is_err());
+//! assert_eq!(strict.state(), TurnstileState::Validating);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use turnkey_core::constants::{
+    DEFAULT_DENY_TIMEOUT_SECONDS, DEFAULT_DISPLAY_TIMEOUT_SECONDS, DEFAULT_GRANT_TIMEOUT_SECONDS,
+    MAX_DISPLAY_MESSAGE_LENGTH,
+};
+use turnkey_core::{Error, Result, Timeout};
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::access::{AccessDecision, AccessResponse};
+
+use crate::emulator::EmulatorCore;
+use crate::state_machine::StateTransition;
+
+/// How closely inbound server responses must follow the protocol.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strictness {
+    /// Accept any response carrying a decision, like a real turnstile.
+    #[default]
+    Lenient,
+
+    /// Reject responses with wrong field counts, timeouts or message
+    /// lengths.
+    Strict,
+}
+
+impl EmulatorCore {
+    /// Check inbound responses with the given strictness (default: lenient).
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Strictness applied to inbound responses.
+    pub fn strictness(&self) -> Strictness {
+        self.strictness
+    }
+
+    /// Parse a server response message and apply it.
+    ///
+    /// The message is checked according to [`strictness()`](Self::strictness)
+    /// (see the [module documentation](self)), then handed to
+    /// [`apply_response()`](Self::apply_response).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` if the message is not a
+    /// response. In strict mode, returns `Error::InvalidMessageFormat` for
+    /// a wrong field count and `Error::InvalidFieldFormat` for a bad
+    /// timeout or message; nothing changes in that case. Otherwise returns
+    /// the error of [`apply_response()`](Self::apply_response).
+    pub fn handle_response(&mut self, message: &Message) -> Result<StateTransition> {
+        let response = match self.strictness {
+            Strictness::Lenient => lenient_response(message)?,
+            Strictness::Strict => strict_response(message).inspect_err(|e| {
+                self.counters.rejected_responses += 1;
+                tracing::warn!(
+                    device_id = %message.device_id,
+                    command = message.command.as_str(),
+                    error = %e,
+                    "malformed response rejected"
+                );
+            })?,
+        };
+        self.apply_response(&response)
+    }
+}
+
+fn decision(message: &Message) -> Result<AccessDecision> {
+    AccessDecision::from_command(message.command).ok_or_else(|| Error::InvalidCommandCode {
+        code: message.command.as_str().to_string(),
+    })
+}
+
+/// Response as a real turnstile would read it.
+fn lenient_response(message: &Message) -> Result<AccessResponse> {
+    let decision = decision(message)?;
+    let default_timeout = if decision.is_grant() {
+        DEFAULT_GRANT_TIMEOUT_SECONDS
+    } else if decision.is_display_only() {
+        DEFAULT_DISPLAY_TIMEOUT_SECONDS
+    } else {
+        DEFAULT_DENY_TIMEOUT_SECONDS
+    };
+
+    // A lone field is the message unless it reads as a timeout
+    let (timeout, text) = match (message.field(0), message.field(1)) {
+        (Some(first), Some(second)) => (first.parse::<Timeout>().ok(), second),
+        (Some(only), None) => match only.parse::<Timeout>() {
+            Ok(timeout) => (Some(timeout), ""),
+            Err(_) => (None, only),
+        },
+        _ => (None, ""),
+    };
+    let timeout = timeout.map_or(default_timeout, Timeout::as_secs);
+
+    // The constructor truncates the message to the display
+    Ok(AccessResponse::new(decision, timeout, text.to_string()))
+}
+
+/// Response checked field by field against the protocol.
+fn strict_response(message: &Message) -> Result<AccessResponse> {
+    let decision = decision(message)?;
+    if message.field_count() != AccessResponse::REQUIRED_FIELD_COUNT {
+        return Err(Error::InvalidMessageFormat {
+            message: format!(
+                "Access response {} requires {} fields, got {}",
+                message.command.as_str(),
+                AccessResponse::REQUIRED_FIELD_COUNT,
+                message.field_count()
+            ),
+        });
+    }
+
+    let response = message.as_access_response()?;
+    if decision.is_grant() && response.timeout().is_permanent() {
+        return Err(Error::InvalidFieldFormat {
+            message: format!("Grant {} has no release timeout", message.command.as_str()),
+        });
+    }
+    let length = response.display_message().chars().count();
+    if length > MAX_DISPLAY_MESSAGE_LENGTH {
+        return Err(Error::InvalidFieldFormat {
+            message: format!(
+                "Display message has {} characters, maximum is {}",
+                length, MAX_DISPLAY_MESSAGE_LENGTH
+            ),
+        });
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TurnstileState;
+    use turnkey_protocol::MessageParser;
+
+    fn validating(strictness: Strictness) -> EmulatorCore {
+        let mut emulator = EmulatorCore::default().with_strictness(strictness);
+        emulator.transition_to(TurnstileState::Reading).unwrap();
+        emulator.transition_to(TurnstileState::Validating).unwrap();
+        emulator
+    }
+
+    fn parse(wire: &str) -> Message {
+        MessageParser::parse(wire).unwrap()
+    }
+
+    #[test]
+    fn test_well_formed_responses_accepted_by_both() {
+        for strictness in [Strictness::Lenient, Strictness::Strict] {
+            let mut emulator = validating(strictness);
+            emulator
+                .handle_response(&parse("15+REON+00+5]5]Bem-vindo]"))
+                .unwrap();
+            assert_eq!(emulator.state(), TurnstileState::Granted);
+
+            let mut emulator = validating(strictness);
+            emulator
+                .handle_response(&parse("15+REON+00+30]0]Acesso negado]"))
+                .unwrap();
+            assert_eq!(emulator.state(), TurnstileState::Denied);
+            assert_eq!(emulator.counters().rejected_responses, 0);
+        }
+    }
+
+    #[test]
+    fn test_strict_rejects_wrong_field_count() {
+        for wire in [
+            "15+REON+00+5]Bem-vindo]",
+            "15+REON+00+5]",
+            "15+REON+00+5]5]Bem-vindo]extra]",
+        ] {
+            let mut emulator = validating(Strictness::Strict);
+            let err = emulator.handle_response(&parse(wire)).unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidMessageFormat { .. }),
+                "{wire}: {err}"
+            );
+            assert_eq!(emulator.state(), TurnstileState::Validating);
+        }
+    }
+
+    #[test]
+    fn test_strict_rejects_bad_timeouts() {
+        for wire in [
+            "15+REON+00+5]256]Bem-vindo]",
+            "15+REON+00+5]cinco]Bem-vindo]",
+            "15+REON+00+6]0]Bem-vindo]",
+        ] {
+            let mut emulator = validating(Strictness::Strict);
+            let err = emulator.handle_response(&parse(wire)).unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidFieldFormat { .. }),
+                "{wire}: {err}"
+            );
+        }
+
+        // Permanent is fine for a denial
+        let mut emulator = validating(Strictness::Strict);
+        emulator
+            .handle_response(&parse("15+REON+00+30]0]Acesso negado]"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_strict_rejects_long_message_lenient_truncates() {
+        let long = format!("15+REON+00+30]3]{}]", "X".repeat(41));
+
+        let mut strict = validating(Strictness::Strict);
+        let err = strict.handle_response(&parse(&long)).unwrap_err();
+        assert!(matches!(err, Error::InvalidFieldFormat { .. }), "{err}");
+        assert_eq!(strict.counters().rejected_responses, 1);
+
+        let mut lenient = validating(Strictness::Lenient);
+        lenient.handle_response(&parse(&long)).unwrap();
+        assert_eq!(lenient.state(), TurnstileState::Denied);
+        assert_eq!(
+            lenient.display().get_line(1).unwrap().trim().len(),
+            MAX_DISPLAY_MESSAGE_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_lenient_repairs_missing_fields() {
+        let response = lenient_response(&parse("15+REON+00+5]Bem-vindo]")).unwrap();
+        assert_eq!(response.timeout_seconds(), DEFAULT_GRANT_TIMEOUT_SECONDS);
+        assert_eq!(response.display_message(), "Bem-vindo");
+
+        let response = lenient_response(&parse("15+REON+00+0]8]")).unwrap();
+        assert_eq!(response.timeout_seconds(), 8);
+        assert_eq!(response.display_message(), "");
+
+        let response = lenient_response(&parse("15+REON+00+30]x]Negado]")).unwrap();
+        assert_eq!(response.timeout_seconds(), DEFAULT_DENY_TIMEOUT_SECONDS);
+        assert_eq!(response.display_message(), "Negado");
+    }
+
+    #[test]
+    fn test_non_response_rejected_in_both_modes() {
+        for strictness in [Strictness::Lenient, Strictness::Strict] {
+            let mut emulator = validating(strictness);
+            let err = emulator
+                .handle_response(&parse("15+REON+000+0]12345678]10/05/2025 12:46:06]1]0]"))
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidCommandCode { .. }), "{err}");
+        }
+    }
+}
@@ -29,9 +29,12 @@
 //! // Can now be used polymorphically through the KeypadDevice trait
 //! ```
 
-use crate::mock::{MockBiometric, MockKeypad, MockRfid};
-use crate::traits::{BiometricDevice, KeypadDevice, RfidDevice};
-use crate::{BiometricData, CardData, DeviceInfo, KeypadInput, LedColor, ReaderInfo, Result};
+use crate::mock::{MockBiometric, MockKeypad, MockRex, MockRfid, MockSensor};
+use crate::traits::{BiometricDevice, KeypadDevice, RexDevice, RfidDevice, SensorDevice};
+use crate::{
+    BiometricData, CardData, DeviceInfo, FirmwareInfo, KeypadInput, LedColor, ReaderInfo, Result,
+    RexRequest, SelfTestResult, SensorEvent, SignalQuality,
+};
 
 /// Enum wrapper for keypad device dispatch.
 ///
@@ -95,6 +98,18 @@ impl KeypadDevice for AnyKeypadDevice {
             Self::Mock(device) => device.get_info().await,
         }
     }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        match self {
+            Self::Mock(device) => device.self_test().await,
+        }
+    }
+
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        match self {
+            Self::Mock(device) => device.firmware_info().await,
+        }
+    }
 }
 
 /// Enum wrapper for RFID reader device dispatch.
@@ -158,6 +173,24 @@ impl RfidDevice for AnyRfidDevice {
             Self::Mock(device) => device.set_led(color).await,
         }
     }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        match self {
+            Self::Mock(device) => device.self_test().await,
+        }
+    }
+
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        match self {
+            Self::Mock(device) => device.firmware_info().await,
+        }
+    }
+
+    async fn signal_quality(&self) -> Result<SignalQuality> {
+        match self {
+            Self::Mock(device) => device.signal_quality().await,
+        }
+    }
 }
 
 /// Enum wrapper for biometric scanner device dispatch.
@@ -210,6 +243,12 @@ impl BiometricDevice for AnyBiometricDevice {
         }
     }
 
+    async fn enroll_fingerprint(&mut self) -> Result<BiometricData> {
+        match self {
+            Self::Mock(device) => device.enroll_fingerprint().await,
+        }
+    }
+
     async fn get_device_info(&self) -> Result<DeviceInfo> {
         match self {
             Self::Mock(device) => device.get_device_info().await,
@@ -221,6 +260,132 @@ impl BiometricDevice for AnyBiometricDevice {
             Self::Mock(device) => device.set_led(color).await,
         }
     }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        match self {
+            Self::Mock(device) => device.self_test().await,
+        }
+    }
+
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        match self {
+            Self::Mock(device) => device.firmware_info().await,
+        }
+    }
+}
+
+/// Enum wrapper for turnstile sensor dispatch.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_hardware::devices::AnySensorDevice;
+/// use turnkey_hardware::traits::SensorDevice;
+/// use turnkey_hardware::mock::MockSensor;
+///
+/// #[tokio::main]
+/// async fn main() -> turnkey_hardware::Result<()> {
+///     let (sensor, _handle) = MockSensor::new();
+///     let any_sensor = AnySensorDevice::Mock(sensor);
+///
+///     let info = any_sensor.get_info().await?;
+///     println!("Sensors: {}", info.name);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AnySensorDevice {
+    /// Mock sensors driven by injected events.
+    Mock(MockSensor),
+    // Planned variants:
+    // - Gpio(GpioSensors) - Linux GPIO character device inputs
+    //
+    // See issue #63 for hardware integration roadmap
+}
+
+impl SensorDevice for AnySensorDevice {
+    async fn read_event(&mut self) -> Result<SensorEvent> {
+        match self {
+            Self::Mock(device) => device.read_event().await,
+        }
+    }
+
+    async fn get_info(&self) -> Result<DeviceInfo> {
+        match self {
+            Self::Mock(device) => device.get_info().await,
+        }
+    }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        match self {
+            Self::Mock(device) => device.self_test().await,
+        }
+    }
+
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        match self {
+            Self::Mock(device) => device.firmware_info().await,
+        }
+    }
+}
+
+/// Enum wrapper for request-to-exit input dispatch.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_hardware::devices::AnyRexDevice;
+/// use turnkey_hardware::traits::RexDevice;
+/// use turnkey_hardware::mock::MockRex;
+///
+/// #[tokio::main]
+/// async fn main() -> turnkey_hardware::Result<()> {
+///     let (rex, _handle) = MockRex::new();
+///     let any_rex = AnyRexDevice::Mock(rex);
+///
+///     let info = any_rex.get_info().await?;
+///     println!("Exit input: {}", info.name);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AnyRexDevice {
+    /// Mock exit input driven by injected requests.
+    Mock(MockRex),
+    // Planned variants:
+    // - Gpio(GpioRex) - Linux GPIO character device input
+    //
+    // See issue #63 for hardware integration roadmap
+}
+
+impl RexDevice for AnyRexDevice {
+    async fn read_request(&mut self) -> Result<RexRequest> {
+        match self {
+            Self::Mock(device) => device.read_request().await,
+        }
+    }
+
+    async fn get_info(&self) -> Result<DeviceInfo> {
+        match self {
+            Self::Mock(device) => device.get_info().await,
+        }
+    }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        match self {
+            Self::Mock(device) => device.self_test().await,
+        }
+    }
+
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        match self {
+            Self::Mock(device) => device.firmware_info().await,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -253,4 +418,30 @@ mod tests {
         let info = any_scanner.get_device_info().await.unwrap();
         assert_eq!(info.name, "Mock Biometric Scanner");
     }
+
+    #[tokio::test]
+    async fn test_any_sensor_device_mock() {
+        let (sensor, handle) = crate::mock::MockSensor::new();
+        let mut any_sensor = AnySensorDevice::Mock(sensor);
+
+        handle.inject(SensorEvent::DoorForced).await.unwrap();
+        assert_eq!(
+            any_sensor.read_event().await.unwrap(),
+            SensorEvent::DoorForced
+        );
+        assert_eq!(
+            any_sensor.get_info().await.unwrap().name,
+            "Mock Turnstile Sensors"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_any_rex_device_mock() {
+        let (rex, handle) = crate::mock::MockRex::new();
+        let mut any_rex = AnyRexDevice::Mock(rex);
+
+        handle.press().await.unwrap();
+        assert_eq!(any_rex.read_request().await.unwrap(), RexRequest::Button);
+        assert_eq!(any_rex.get_info().await.unwrap().name, "Mock Exit Button");
+    }
 }
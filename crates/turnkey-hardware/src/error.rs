@@ -4,6 +4,8 @@
 //! covering various failure scenarios such as device disconnection, timeouts,
 //! protocol errors, and unsupported operations.
 
+use crate::manager::DeviceType;
+
 /// Result type alias for hardware operations.
 pub type Result<T> = std::result::Result<T, HardwareError>;
 
@@ -18,6 +20,18 @@ pub enum HardwareError {
     #[error("Operation timeout after {duration_ms}ms")]
     Timeout { duration_ms: u64 },
 
+    /// Keypad read did not complete within the configured timeout.
+    #[error("Keypad read timeout after {duration_ms}ms")]
+    KeypadTimeout { duration_ms: u64 },
+
+    /// RFID card read did not complete within the configured timeout.
+    #[error("RFID read timeout after {duration_ms}ms")]
+    RfidTimeout { duration_ms: u64 },
+
+    /// Fingerprint capture did not complete within the configured timeout.
+    #[error("Biometric read timeout after {duration_ms}ms")]
+    BiometricTimeout { duration_ms: u64 },
+
     /// Operation is not supported by this device.
     #[error("Unsupported operation: {operation}")]
     Unsupported { operation: String },
@@ -72,6 +86,28 @@ impl HardwareError {
         Self::Timeout { duration_ms }
     }
 
+    /// Create a read timeout error for the given peripheral type.
+    pub fn read_timeout(device_type: DeviceType, duration_ms: u64) -> Self {
+        match device_type {
+            DeviceType::Keypad => Self::KeypadTimeout { duration_ms },
+            DeviceType::Rfid => Self::RfidTimeout { duration_ms },
+            DeviceType::Biometric => Self::BiometricTimeout { duration_ms },
+        }
+    }
+
+    /// Peripheral whose read timed out, if this is a read timeout error.
+    ///
+    /// Lets callers show a timeout message for the peripheral that actually
+    /// timed out. The generic [`HardwareError::Timeout`] returns `None`.
+    pub fn timed_out_device(&self) -> Option<DeviceType> {
+        match self {
+            Self::KeypadTimeout { .. } => Some(DeviceType::Keypad),
+            Self::RfidTimeout { .. } => Some(DeviceType::Rfid),
+            Self::BiometricTimeout { .. } => Some(DeviceType::Biometric),
+            _ => None,
+        }
+    }
+
     /// Create a new unsupported operation error.
     pub fn unsupported(operation: impl Into<String>) -> Self {
         Self::Unsupported {
@@ -152,6 +188,22 @@ mod tests {
         assert_eq!(error.to_string(), "Operation timeout after 3000ms");
     }
 
+    #[test]
+    fn test_read_timeout_errors() {
+        let error = HardwareError::read_timeout(DeviceType::Biometric, 15000);
+        assert!(matches!(error, HardwareError::BiometricTimeout { .. }));
+        assert_eq!(error.to_string(), "Biometric read timeout after 15000ms");
+        assert_eq!(error.timed_out_device(), Some(DeviceType::Biometric));
+
+        let error = HardwareError::read_timeout(DeviceType::Rfid, 2000);
+        assert_eq!(error.timed_out_device(), Some(DeviceType::Rfid));
+
+        let error = HardwareError::read_timeout(DeviceType::Keypad, 30000);
+        assert_eq!(error.timed_out_device(), Some(DeviceType::Keypad));
+
+        assert_eq!(HardwareError::timeout(3000).timed_out_device(), None);
+    }
+
     #[test]
     fn test_unsupported_error() {
         let error = HardwareError::unsupported("set_led");
@@ -17,7 +17,7 @@
 //!
 //! # Device Traits
 //!
-//! The crate defines three main device trait families:
+//! The crate defines five main device trait families:
 //!
 //! ## Keypad Devices
 //!
@@ -83,6 +83,43 @@
 //! }
 //! ```
 //!
+//! ## Turnstile Sensors
+//!
+//! The [`SensorDevice`] trait represents the GPIO inputs of the turnstile
+//! mechanism (arm rotation, door contact, tamper switch). Each change is
+//! reported as a typed [`SensorEvent`]:
+//!
+//! ```no_run
+//! use turnkey_hardware::traits::{SensorDevice, SensorEvent};
+//! use turnkey_hardware::error::Result;
+//!
+//! async fn count_alarms<S: SensorDevice>(sensor: &mut S, events: usize) -> Result<usize> {
+//!     let mut alarms = 0;
+//!     for _ in 0..events {
+//!         if sensor.read_event().await?.is_alarm() {
+//!             alarms += 1;
+//!         }
+//!     }
+//!     Ok(alarms)
+//! }
+//! ```
+//!
+//! ## Request-to-Exit Inputs
+//!
+//! The [`RexDevice`] trait represents exit buttons and motion detectors
+//! that open the passage without a credential. Each trigger is reported as
+//! a [`RexRequest`]:
+//!
+//! ```no_run
+//! use turnkey_hardware::traits::{RexDevice, RexRequest};
+//! use turnkey_hardware::error::Result;
+//!
+//! async fn wait_for_button<R: RexDevice>(rex: &mut R) -> Result<()> {
+//!     while rex.read_request().await? != RexRequest::Button {}
+//!     Ok(())
+//! }
+//! ```
+//!
 //! # Error Handling
 //!
 //! All operations return [`Result<T>`][error::Result] which uses the
@@ -105,6 +142,10 @@
 //! [`KeypadDevice`]: traits::KeypadDevice
 //! [`RfidDevice`]: traits::RfidDevice
 //! [`BiometricDevice`]: traits::BiometricDevice
+//! [`SensorDevice`]: traits::SensorDevice
+//! [`SensorEvent`]: traits::SensorEvent
+//! [`RexDevice`]: traits::RexDevice
+//! [`RexRequest`]: traits::RexRequest
 
 pub mod devices;
 pub mod error;
@@ -131,19 +172,25 @@ pub use error::{HardwareError, Result};
 /// - [`KeypadDevice`] - Numeric keypad input devices
 /// - [`RfidDevice`] - RFID/NFC card readers
 /// - [`BiometricDevice`] - Fingerprint scanners
+/// - [`SensorDevice`] - Turnstile sensors (arm rotation, door, tamper)
+/// - [`RexDevice`] - Request-to-exit inputs (exit button, motion detector)
 pub use traits::{
     BiometricData, BiometricDevice, CardData, CardType, DEFAULT_QUALITY_THRESHOLD, KeypadDevice,
-    KeypadInput, MAX_QUALITY_SCORE, MAX_UID_LENGTH, MIN_UID_LENGTH, RfidDevice,
+    KeypadInput, MAX_QUALITY_SCORE, MAX_UID_LENGTH, MIN_UID_LENGTH, RexDevice, RexRequest,
+    RfidDevice, SensorDevice, SensorEvent,
 };
 
-/// Common hardware types (LED colors, device info, reader info).
-pub use types::{DeviceInfo, LedColor, ReaderInfo};
+/// Common hardware types (LED colors, device info, reader info, diagnostics).
+pub use types::{
+    DeviceInfo, FirmwareInfo, LedColor, MAX_SIGNAL_STRENGTH, ReaderInfo, SelfTestResult,
+    SignalQuality,
+};
 
 /// Peripheral management system for coordinating multiple devices.
 ///
 /// The [`PeripheralManager`] provides centralized device lifecycle management,
 /// event handling, and statistics tracking for all connected peripherals.
 pub use manager::{
-    DeviceType, PeripheralConfig, PeripheralEvent, PeripheralHandle, PeripheralManager,
-    PeripheralStats,
+    DeviceDiagnostics, DeviceType, PeripheralConfig, PeripheralDiagnostics, PeripheralEvent,
+    PeripheralHandle, PeripheralManager, PeripheralStats, ReadTimeouts,
 };
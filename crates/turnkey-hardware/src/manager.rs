@@ -59,9 +59,16 @@
 
 use crate::devices::{AnyBiometricDevice, AnyKeypadDevice, AnyRfidDevice};
 use crate::traits::{BiometricDevice, KeypadDevice, RfidDevice};
-use crate::{BiometricData, CardData, KeypadInput, Result};
+use crate::{
+    BiometricData, CardData, FirmwareInfo, HardwareError, KeypadInput, Result, SelfTestResult,
+    SignalQuality,
+};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::task::JoinSet;
+use turnkey_core::AccessDirection;
 
 /// Unified event from any peripheral device.
 ///
@@ -80,6 +87,19 @@ pub enum PeripheralEvent {
     /// Fingerprint captured from biometric scanner.
     FingerprintCaptured(BiometricData),
 
+    /// A read did not complete within the configured timeout.
+    ///
+    /// Sent when [`PeripheralConfig::read_timeouts`] sets a limit for the
+    /// device type and no input arrives in time. Unlike `DeviceError`, the
+    /// device task keeps running and starts a new read.
+    ReadTimeout {
+        /// Type of device whose read timed out.
+        device_type: DeviceType,
+
+        /// Timeout that elapsed.
+        timeout: Duration,
+    },
+
     /// Device error occurred.
     ///
     /// This event is sent when a device encounters an error. The device
@@ -116,9 +136,80 @@ impl std::fmt::Display for DeviceType {
     }
 }
 
+/// Recommended keypad read timeout (time to finish typing a code).
+pub const DEFAULT_KEYPAD_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Recommended RFID read timeout (a card tap is near-instant).
+pub const DEFAULT_RFID_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Recommended biometric read timeout (placement, capture and retries).
+pub const DEFAULT_BIOMETRIC_READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Per-peripheral read timeouts.
+///
+/// Each device type gets its own limit because their reads take very
+/// different amounts of time. `None` waits indefinitely, which is the
+/// default so idle readers do not produce a stream of timeout events.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_hardware::manager::{DeviceType, ReadTimeouts};
+/// use std::time::Duration;
+///
+/// let timeouts = ReadTimeouts::recommended();
+/// assert!(timeouts.for_device(DeviceType::Biometric) > timeouts.for_device(DeviceType::Rfid));
+///
+/// let timeouts = ReadTimeouts::default().with(DeviceType::Rfid, Duration::from_secs(2));
+/// assert_eq!(timeouts.for_device(DeviceType::Rfid), Some(Duration::from_secs(2)));
+/// assert_eq!(timeouts.for_device(DeviceType::Keypad), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadTimeouts {
+    /// Keypad read timeout.
+    pub keypad: Option<Duration>,
+
+    /// RFID card read timeout.
+    pub rfid: Option<Duration>,
+
+    /// Biometric capture timeout.
+    pub biometric: Option<Duration>,
+}
+
+impl ReadTimeouts {
+    /// Timeouts tuned for each peripheral type.
+    pub fn recommended() -> Self {
+        Self {
+            keypad: Some(DEFAULT_KEYPAD_READ_TIMEOUT),
+            rfid: Some(DEFAULT_RFID_READ_TIMEOUT),
+            biometric: Some(DEFAULT_BIOMETRIC_READ_TIMEOUT),
+        }
+    }
+
+    /// Set the timeout for one device type.
+    pub fn with(mut self, device_type: DeviceType, timeout: Duration) -> Self {
+        match device_type {
+            DeviceType::Keypad => self.keypad = Some(timeout),
+            DeviceType::Rfid => self.rfid = Some(timeout),
+            DeviceType::Biometric => self.biometric = Some(timeout),
+        }
+        self
+    }
+
+    /// Get the timeout for a device type.
+    pub fn for_device(&self, device_type: DeviceType) -> Option<Duration> {
+        match device_type {
+            DeviceType::Keypad => self.keypad,
+            DeviceType::Rfid => self.rfid,
+            DeviceType::Biometric => self.biometric,
+        }
+    }
+}
+
 /// Configuration for peripheral devices.
 ///
-/// Controls which devices are enabled and will be started by the manager.
+/// Controls which devices are enabled and will be started by the manager,
+/// and how long each device type may take to complete a read.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PeripheralConfig {
     /// Enable keypad device.
@@ -129,6 +220,9 @@ pub struct PeripheralConfig {
 
     /// Enable biometric scanner device.
     pub biometric_enabled: bool,
+
+    /// Read timeouts per device type.
+    pub read_timeouts: ReadTimeouts,
 }
 
 impl Default for PeripheralConfig {
@@ -137,12 +231,36 @@ impl Default for PeripheralConfig {
             keypad_enabled: true,
             rfid_enabled: true,
             biometric_enabled: false,
+            read_timeouts: ReadTimeouts::default(),
         }
     }
 }
 
-/// Statistics about connected peripherals.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Statistics about connected peripherals and turnstile passages.
+///
+/// The manager fills in which devices are connected; a
+/// [`PeripheralHandle`] also counts the errors its devices report, and the
+/// owner of the turnstile sensor records passages with
+/// [`record_passage()`](Self::record_passage).
+///
+/// # Examples
+///
+/// ```
+/// use chrono::Utc;
+/// use turnkey_core::AccessDirection;
+/// use turnkey_hardware::PeripheralStats;
+///
+/// let mut stats = PeripheralStats::default();
+/// let now = Utc::now();
+/// stats.record_passage(AccessDirection::Entry, now);
+/// stats.record_passage(AccessDirection::Entry, now);
+/// stats.record_passage(AccessDirection::Exit, now);
+///
+/// assert_eq!((stats.entries, stats.exits), (2, 1));
+/// assert_eq!(stats.last_entry_at, Some(now));
+/// assert!(stats.last_error_at.is_none());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct PeripheralStats {
     /// Keypad is connected.
     pub keypad_connected: bool,
@@ -152,6 +270,101 @@ pub struct PeripheralStats {
 
     /// Biometric scanner is connected.
     pub biometric_connected: bool,
+
+    /// Passages in the entry direction.
+    pub entries: u64,
+
+    /// Passages in the exit direction.
+    pub exits: u64,
+
+    /// Passages whose direction the sensor could not tell.
+    pub undetermined: u64,
+
+    /// Read timeouts and device errors.
+    pub errors: u64,
+
+    /// Time of the latest entry.
+    pub last_entry_at: Option<DateTime<Utc>>,
+
+    /// Time of the latest exit.
+    pub last_exit_at: Option<DateTime<Utc>>,
+
+    /// Time of the latest read timeout or device error.
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+impl PeripheralStats {
+    /// Count a passage through the turnstile.
+    pub fn record_passage(&mut self, direction: AccessDirection, at: DateTime<Utc>) {
+        match direction {
+            AccessDirection::Entry => {
+                self.entries += 1;
+                self.last_entry_at = Some(at);
+            }
+            AccessDirection::Exit => {
+                self.exits += 1;
+                self.last_exit_at = Some(at);
+            }
+            AccessDirection::Undefined => self.undetermined += 1,
+        }
+    }
+
+    /// Count an error reported by a peripheral.
+    pub fn record_error(&mut self, at: DateTime<Utc>) {
+        self.errors += 1;
+        self.last_error_at = Some(at);
+    }
+
+    /// Count the event if it reports a read timeout or a device error.
+    pub fn record_event(&mut self, event: &PeripheralEvent, at: DateTime<Utc>) {
+        if matches!(
+            event,
+            PeripheralEvent::ReadTimeout { .. } | PeripheralEvent::DeviceError { .. }
+        ) {
+            self.record_error(at);
+        }
+    }
+}
+
+/// Time a device gets to answer each diagnostic call.
+pub const DIAGNOSTIC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Diagnostics of one peripheral.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceDiagnostics {
+    /// Type of the device diagnosed.
+    pub device_type: DeviceType,
+
+    /// Outcome of the device self-test.
+    pub self_test: SelfTestResult,
+
+    /// Firmware details, if the device reports them.
+    pub firmware: Option<FirmwareInfo>,
+
+    /// Radio link quality, if the device measures it.
+    pub signal: Option<SignalQuality>,
+}
+
+/// Diagnostics of every registered peripheral, in keypad, RFID,
+/// biometric order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeripheralDiagnostics {
+    /// One entry per registered device.
+    pub devices: Vec<DeviceDiagnostics>,
+}
+
+impl PeripheralDiagnostics {
+    /// Whether every device passed its self-test.
+    pub fn passed(&self) -> bool {
+        self.devices.iter().all(|device| device.self_test.passed)
+    }
+
+    /// Diagnostics of a device type, if it is registered.
+    pub fn device(&self, device_type: DeviceType) -> Option<&DeviceDiagnostics> {
+        self.devices
+            .iter()
+            .find(|device| device.device_type == device_type)
+    }
 }
 
 /// Handle for receiving events from peripheral devices.
@@ -193,6 +406,9 @@ pub struct PeripheralHandle {
 
     /// Running device tasks.
     tasks: JoinSet<Result<()>>,
+
+    /// Connected devices and the errors received so far.
+    stats: PeripheralStats,
 }
 
 impl PeripheralHandle {
@@ -227,7 +443,15 @@ impl PeripheralHandle {
     /// # }
     /// ```
     pub async fn recv(&mut self) -> Option<PeripheralEvent> {
-        self.event_rx.recv().await
+        let event = self.event_rx.recv().await?;
+        self.stats.record_event(&event, Utc::now());
+        Some(event)
+    }
+
+    /// Devices started by the manager and the errors received through
+    /// [`recv()`](Self::recv).
+    pub fn stats(&self) -> &PeripheralStats {
+        &self.stats
     }
 
     /// Gracefully shutdown all device tasks.
@@ -326,6 +550,7 @@ enum TaskTermination {
 ///         keypad_enabled: true,
 ///         rfid_enabled: true,
 ///         biometric_enabled: false,
+///         ..PeripheralConfig::default()
 ///     };
 ///
 ///     // Create and configure manager
@@ -447,6 +672,7 @@ impl PeripheralManager {
     ///     keypad_enabled: false,
     ///     rfid_enabled: false,
     ///     biometric_enabled: true,
+    ///     ..PeripheralConfig::default()
     /// };
     ///
     /// let mut manager = PeripheralManager::new(config);
@@ -493,13 +719,15 @@ impl PeripheralManager {
     /// ```
     pub fn start(mut self) -> PeripheralHandle {
         let mut tasks = JoinSet::new();
+        let stats = self.get_stats();
 
         // Spawn keypad task
         if self.config.keypad_enabled
             && let Some(device) = self.keypad.take()
         {
             let tx = self.event_tx.clone();
-            tasks.spawn(Self::keypad_task(device, tx));
+            let timeout = self.config.read_timeouts.keypad;
+            tasks.spawn(Self::keypad_task(device, tx, timeout));
         }
 
         // Spawn RFID task
@@ -507,7 +735,8 @@ impl PeripheralManager {
             && let Some(device) = self.rfid.take()
         {
             let tx = self.event_tx.clone();
-            tasks.spawn(Self::rfid_task(device, tx));
+            let timeout = self.config.read_timeouts.rfid;
+            tasks.spawn(Self::rfid_task(device, tx, timeout));
         }
 
         // Spawn biometric task
@@ -515,12 +744,14 @@ impl PeripheralManager {
             && let Some(device) = self.biometric.take()
         {
             let tx = self.event_tx.clone();
-            tasks.spawn(Self::biometric_task(device, tx));
+            let timeout = self.config.read_timeouts.biometric;
+            tasks.spawn(Self::biometric_task(device, tx, timeout));
         }
 
         PeripheralHandle {
             event_rx: self.event_rx.take().expect("Event receiver already taken"),
             tasks,
+            stats,
         }
     }
 
@@ -569,14 +800,133 @@ impl PeripheralManager {
             keypad_connected: self.keypad.is_some(),
             rfid_connected: self.rfid.is_some(),
             biometric_connected: self.biometric.is_some(),
+            ..PeripheralStats::default()
         }
     }
 
+    /// Run diagnostics on every registered device.
+    ///
+    /// Devices are diagnosed one after the other. Diagnostics need the
+    /// devices themselves, so they run before [`start()`](Self::start) hands
+    /// the devices to their read tasks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_hardware::manager::{DeviceType, PeripheralManager, PeripheralConfig};
+    /// use turnkey_hardware::devices::AnyRfidDevice;
+    /// use turnkey_hardware::mock::MockRfid;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut manager = PeripheralManager::new(PeripheralConfig::default());
+    /// let (rfid, _handle) = MockRfid::new();
+    /// manager.register_rfid(AnyRfidDevice::Mock(rfid));
+    ///
+    /// let diagnostics = manager.diagnostics().await;
+    /// assert!(diagnostics.passed());
+    /// assert!(diagnostics.device(DeviceType::Rfid).unwrap().signal.is_some());
+    /// # }
+    /// ```
+    pub async fn diagnostics(&mut self) -> PeripheralDiagnostics {
+        let mut devices = Vec::new();
+        for device_type in [DeviceType::Keypad, DeviceType::Rfid, DeviceType::Biometric] {
+            if let Some(diagnostics) = self.diagnose(device_type).await {
+                devices.push(diagnostics);
+            }
+        }
+        PeripheralDiagnostics { devices }
+    }
+
+    /// Run diagnostics on one device, `None` if it is not registered.
+    ///
+    /// A self-test that errors or does not answer within
+    /// [`DIAGNOSTIC_TIMEOUT`] is reported as failed. Firmware and signal
+    /// quality are left out when the device does not support them.
+    pub async fn diagnose(&mut self, device_type: DeviceType) -> Option<DeviceDiagnostics> {
+        let (self_test, firmware, signal) = match device_type {
+            DeviceType::Keypad => {
+                let device = self.keypad.as_mut()?;
+                let self_test = Self::diagnostic(device_type, device.self_test()).await;
+                let firmware = Self::diagnostic(device_type, device.firmware_info()).await;
+                (self_test, firmware, None)
+            }
+            DeviceType::Rfid => {
+                let device = self.rfid.as_mut()?;
+                let self_test = Self::diagnostic(device_type, device.self_test()).await;
+                let firmware = Self::diagnostic(device_type, device.firmware_info()).await;
+                let signal = Self::diagnostic(device_type, device.signal_quality()).await;
+                (self_test, firmware, Some(signal))
+            }
+            DeviceType::Biometric => {
+                let device = self.biometric.as_mut()?;
+                let self_test = Self::diagnostic(device_type, device.self_test()).await;
+                let firmware = Self::diagnostic(device_type, device.firmware_info()).await;
+                (self_test, firmware, None)
+            }
+        };
+
+        Some(DeviceDiagnostics {
+            device_type,
+            self_test: self_test.unwrap_or_else(|e| SelfTestResult::fail(e.to_string())),
+            firmware: firmware.ok(),
+            signal: signal.and_then(Result::ok),
+        })
+    }
+
+    /// Run a diagnostic call within [`DIAGNOSTIC_TIMEOUT`].
+    async fn diagnostic<T>(
+        device_type: DeviceType,
+        call: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        Self::read_with_timeout(device_type, Some(DIAGNOSTIC_TIMEOUT), call).await
+    }
+
     // Private task functions
 
+    /// Run a device read, failing with a per-device timeout error if it takes too long.
+    ///
+    /// The read future is dropped on timeout, so device reads must be
+    /// cancel-safe (input not yet returned stays queued for the next read).
+    async fn read_with_timeout<T>(
+        device_type: DeviceType,
+        timeout: Option<Duration>,
+        read: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match timeout {
+            Some(limit) => tokio::time::timeout(limit, read).await.unwrap_or_else(|_| {
+                Err(HardwareError::read_timeout(
+                    device_type,
+                    limit.as_millis() as u64,
+                ))
+            }),
+            None => read.await,
+        }
+    }
+
+    /// Report a read timeout, returning `false` if the event channel is closed.
+    async fn send_read_timeout(
+        tx: &mpsc::Sender<PeripheralEvent>,
+        device_type: DeviceType,
+        error: &HardwareError,
+    ) -> bool {
+        let duration_ms = match error {
+            HardwareError::KeypadTimeout { duration_ms }
+            | HardwareError::RfidTimeout { duration_ms }
+            | HardwareError::BiometricTimeout { duration_ms } => *duration_ms,
+            _ => 0,
+        };
+        let event = PeripheralEvent::ReadTimeout {
+            device_type,
+            timeout: Duration::from_millis(duration_ms),
+        };
+        tx.send(event).await.is_ok()
+    }
+
     async fn keypad_task(
         mut device: AnyKeypadDevice,
         tx: mpsc::Sender<PeripheralEvent>,
+        timeout: Option<Duration>,
     ) -> Result<()> {
         // Rate limiting: minimum delay between polls to prevent busy-waiting
         const MIN_POLL_INTERVAL_MS: u64 = 10; // 100 Hz maximum
@@ -584,7 +934,7 @@ impl PeripheralManager {
         loop {
             let start = tokio::time::Instant::now();
 
-            match device.read_input().await {
+            match Self::read_with_timeout(DeviceType::Keypad, timeout, device.read_input()).await {
                 Ok(input) => {
                     // Use try_send to detect backpressure
                     match tx.try_send(PeripheralEvent::KeypadInput(input)) {
@@ -602,6 +952,11 @@ impl PeripheralManager {
                         }
                     }
                 }
+                Err(e) if e.timed_out_device().is_some() => {
+                    if !Self::send_read_timeout(&tx, DeviceType::Keypad, &e).await {
+                        break; // Channel closed
+                    }
+                }
                 Err(e) => {
                     let _ = tx
                         .send(PeripheralEvent::DeviceError {
@@ -625,14 +980,18 @@ impl PeripheralManager {
         Ok(())
     }
 
-    async fn rfid_task(mut device: AnyRfidDevice, tx: mpsc::Sender<PeripheralEvent>) -> Result<()> {
+    async fn rfid_task(
+        mut device: AnyRfidDevice,
+        tx: mpsc::Sender<PeripheralEvent>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
         // Rate limiting: minimum delay between polls to prevent busy-waiting
         const MIN_POLL_INTERVAL_MS: u64 = 10; // 100 Hz maximum
 
         loop {
             let start = tokio::time::Instant::now();
 
-            match device.read_card().await {
+            match Self::read_with_timeout(DeviceType::Rfid, timeout, device.read_card()).await {
                 Ok(card) => {
                     // Use try_send to detect backpressure
                     match tx.try_send(PeripheralEvent::CardRead(card)) {
@@ -650,6 +1009,11 @@ impl PeripheralManager {
                         }
                     }
                 }
+                Err(e) if e.timed_out_device().is_some() => {
+                    if !Self::send_read_timeout(&tx, DeviceType::Rfid, &e).await {
+                        break; // Channel closed
+                    }
+                }
                 Err(e) => {
                     let _ = tx
                         .send(PeripheralEvent::DeviceError {
@@ -676,6 +1040,7 @@ impl PeripheralManager {
     async fn biometric_task(
         mut device: AnyBiometricDevice,
         tx: mpsc::Sender<PeripheralEvent>,
+        timeout: Option<Duration>,
     ) -> Result<()> {
         // Rate limiting: minimum delay between polls to prevent busy-waiting
         const MIN_POLL_INTERVAL_MS: u64 = 10; // 100 Hz maximum
@@ -683,7 +1048,13 @@ impl PeripheralManager {
         loop {
             let start = tokio::time::Instant::now();
 
-            match device.capture_fingerprint().await {
+            match Self::read_with_timeout(
+                DeviceType::Biometric,
+                timeout,
+                device.capture_fingerprint(),
+            )
+            .await
+            {
                 Ok(data) => {
                     // Use try_send to detect backpressure
                     match tx.try_send(PeripheralEvent::FingerprintCaptured(data)) {
@@ -701,6 +1072,11 @@ impl PeripheralManager {
                         }
                     }
                 }
+                Err(e) if e.timed_out_device().is_some() => {
+                    if !Self::send_read_timeout(&tx, DeviceType::Biometric, &e).await {
+                        break; // Channel closed
+                    }
+                }
                 Err(e) => {
                     let _ = tx
                         .send(PeripheralEvent::DeviceError {
@@ -793,12 +1169,49 @@ mod tests {
         assert!(!stats.biometric_connected);
     }
 
+    #[tokio::test]
+    async fn test_manager_diagnostics() {
+        let mut manager = PeripheralManager::new(PeripheralConfig::default());
+        assert!(manager.diagnostics().await.devices.is_empty());
+
+        let (keypad, keypad_handle) = crate::mock::MockKeypad::new();
+        manager.register_keypad(AnyKeypadDevice::Mock(keypad));
+        let (rfid, rfid_handle) = crate::mock::MockRfid::new();
+        manager.register_rfid(AnyRfidDevice::Mock(rfid));
+
+        let diagnostics = manager.diagnostics().await;
+        assert!(diagnostics.passed());
+        let types: Vec<_> = diagnostics.devices.iter().map(|d| d.device_type).collect();
+        assert_eq!(types, vec![DeviceType::Keypad, DeviceType::Rfid]);
+
+        let keypad = diagnostics.device(DeviceType::Keypad).unwrap();
+        assert_eq!(keypad.firmware.as_ref().unwrap().version, "1.0.0");
+        assert!(keypad.signal.is_none());
+        assert!(
+            diagnostics
+                .device(DeviceType::Rfid)
+                .unwrap()
+                .signal
+                .is_some()
+        );
+        assert!(diagnostics.device(DeviceType::Biometric).is_none());
+
+        // A weak reader and an unplugged keypad fail their self-tests
+        rfid_handle.set_signal_strength(5);
+        drop(keypad_handle);
+        let diagnostics = manager.diagnostics().await;
+        assert!(!diagnostics.passed());
+        assert!(diagnostics.devices.iter().all(|d| !d.self_test.passed));
+        assert!(manager.diagnose(DeviceType::Biometric).await.is_none());
+    }
+
     #[test]
     fn test_manager_is_device_enabled() {
         let config = PeripheralConfig {
             keypad_enabled: true,
             rfid_enabled: false,
             biometric_enabled: true,
+            ..PeripheralConfig::default()
         };
 
         let manager = PeripheralManager::new(config);
@@ -822,6 +1235,7 @@ mod tests {
             keypad_enabled: true,
             rfid_enabled: false,
             biometric_enabled: false,
+            ..PeripheralConfig::default()
         });
 
         let (keypad, _handle) = crate::mock::MockKeypad::new();
@@ -850,6 +1264,7 @@ mod tests {
             keypad_enabled: false,
             rfid_enabled: true,
             biometric_enabled: false,
+            ..PeripheralConfig::default()
         };
 
         let mut manager = PeripheralManager::new(config);
@@ -886,6 +1301,7 @@ mod tests {
             keypad_enabled: true,
             rfid_enabled: true,
             biometric_enabled: true,
+            ..PeripheralConfig::default()
         };
 
         let mut manager = PeripheralManager::new(config);
@@ -914,6 +1330,74 @@ mod tests {
         handle.shutdown().await.unwrap();
     }
 
+    #[test]
+    fn test_read_timeouts_per_device() {
+        let timeouts = ReadTimeouts::recommended();
+        assert_eq!(
+            timeouts.for_device(DeviceType::Rfid),
+            Some(DEFAULT_RFID_READ_TIMEOUT)
+        );
+        assert_eq!(
+            timeouts.for_device(DeviceType::Biometric),
+            Some(DEFAULT_BIOMETRIC_READ_TIMEOUT)
+        );
+        assert_eq!(
+            PeripheralConfig::default().read_timeouts,
+            ReadTimeouts::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manager_rfid_read_timeout() {
+        let config = PeripheralConfig {
+            keypad_enabled: false,
+            rfid_enabled: true,
+            biometric_enabled: false,
+            read_timeouts: ReadTimeouts::default()
+                .with(DeviceType::Rfid, Duration::from_millis(20)),
+        };
+
+        let mut manager = PeripheralManager::new(config);
+        let (rfid, mut rfid_handle) = crate::mock::MockRfid::new();
+        manager.register_rfid(AnyRfidDevice::Mock(rfid));
+
+        let mut handle = manager.start();
+
+        match handle.recv().await {
+            Some(PeripheralEvent::ReadTimeout {
+                device_type,
+                timeout,
+            }) => {
+                assert_eq!(device_type, DeviceType::Rfid);
+                assert_eq!(timeout, Duration::from_millis(20));
+            }
+            other => panic!("Expected RFID read timeout, got {:?}", other),
+        }
+
+        // The task survives the timeout and keeps reading
+        let uid = vec![0x0A, 0x0B, 0x0C, 0x0D];
+        rfid_handle
+            .add_card(uid.clone(), CardType::MifareClassic1K)
+            .await;
+        rfid_handle.present_card(uid).await.unwrap();
+        let card_read = loop {
+            match handle.recv().await {
+                Some(PeripheralEvent::ReadTimeout { .. }) => continue,
+                other => break other,
+            }
+        };
+        assert!(matches!(card_read, Some(PeripheralEvent::CardRead(_))));
+
+        // Timeouts are counted as errors, card reads are not
+        let stats = handle.stats();
+        assert!(stats.rfid_connected);
+        assert!(stats.errors >= 1);
+        assert!(stats.last_error_at.is_some());
+        assert_eq!(stats.entries + stats.exits, 0);
+
+        handle.shutdown().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_manager_graceful_shutdown() {
         let config = PeripheralConfig::default();
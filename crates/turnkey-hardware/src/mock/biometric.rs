@@ -2,11 +2,16 @@
 //!
 //! This module provides a simulated fingerprint scanner that can be controlled
 //! programmatically for testing without requiring physical hardware.
+//!
+//! By default the scanner is deterministic. Attach a [`BiometricProfile`] with
+//! [`MockBiometric::with_profile()`] to simulate false rejects, false accepts,
+//! varying capture quality and capture latency.
 
+use super::profile::{BiometricProfile, SensorSimulator};
 use crate::{
     Result,
     traits::{BiometricData, BiometricDevice, DEFAULT_QUALITY_THRESHOLD},
-    types::{DeviceInfo, LedColor},
+    types::{DeviceInfo, LedColor, SelfTestResult},
 };
 use std::collections::HashMap;
 use tokio::sync::mpsc;
@@ -47,6 +52,9 @@ pub struct MockBiometric {
 
     /// Currently set LED color
     led_color: LedColor,
+
+    /// Simulated sensor behavior
+    simulator: SensorSimulator,
 }
 
 impl MockBiometric {
@@ -82,6 +90,8 @@ impl MockBiometric {
             event_rx,
             name: name.clone(),
             led_color: LedColor::Off,
+            simulator: SensorSimulator::new(BiometricProfile::ideal())
+                .expect("ideal profile is valid"),
         };
 
         let handle = MockBiometricHandle {
@@ -93,6 +103,44 @@ impl MockBiometric {
         (scanner, handle)
     }
 
+    /// Simulate a sensor with the given behavior profile.
+    ///
+    /// The profile sets the match error rates and capture latency, and the
+    /// quality of fingers queued with [`MockBiometricHandle::present_finger()`].
+    /// Fingers queued with an explicit quality keep it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error if the profile is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_hardware::mock::{BiometricProfile, MockBiometric};
+    /// use turnkey_hardware::traits::BiometricDevice;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> turnkey_hardware::Result<()> {
+    ///     let (scanner, handle) = MockBiometric::new();
+    ///     let mut scanner = scanner.with_profile(BiometricProfile::ideal().false_reject_rate(1.0))?;
+    ///
+    ///     // Genuine finger, but this sensor rejects everything
+    ///     handle.present_finger(vec![1, 2, 3]).await?;
+    ///     assert!(!scanner.verify_fingerprint(&[1, 2, 3]).await?);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_profile(mut self, profile: BiometricProfile) -> Result<Self> {
+        self.simulator = SensorSimulator::new(profile)?;
+        Ok(self)
+    }
+
+    /// Get the simulated sensor profile.
+    pub fn profile(&self) -> &BiometricProfile {
+        self.simulator.profile()
+    }
+
     /// Get the current LED color.
     ///
     /// This is useful for testing LED control.
@@ -114,17 +162,26 @@ impl BiometricDevice for MockBiometric {
                 crate::HardwareError::disconnected("Biometric event channel closed")
             })?;
 
+        let latency = self.simulator.latency();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
         match event {
             BiometricEvent::FingerprintCaptured(data) => Ok(data),
+            BiometricEvent::FingerPresented(template) => {
+                BiometricData::new(template, self.simulator.quality())
+            }
         }
     }
 
     async fn verify_fingerprint(&mut self, template: &[u8]) -> Result<bool> {
         let captured = self.capture_fingerprint().await?;
 
-        // Simple byte-by-byte comparison for mock implementation
+        // Simple byte-by-byte comparison for mock implementation, with the
+        // profile's false reject/accept rates applied on top
         // Real implementations would use sophisticated matching algorithms
-        Ok(captured.template == template)
+        Ok(self.simulator.decide(captured.template == template))
     }
 
     async fn get_device_info(&self) -> Result<DeviceInfo> {
@@ -138,12 +195,22 @@ impl BiometricDevice for MockBiometric {
         self.led_color = color;
         Ok(())
     }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        if self.event_rx.is_closed() {
+            return Ok(SelfTestResult::fail("Sensor disconnected"));
+        }
+        Ok(SelfTestResult::pass(self.name.clone()))
+    }
 }
 
 /// Internal event type for mock biometric scanner.
 #[derive(Debug, Clone)]
 enum BiometricEvent {
     FingerprintCaptured(BiometricData),
+
+    /// Finger whose capture quality is drawn from the scanner's profile
+    FingerPresented(Vec<u8>),
 }
 
 /// Handle for controlling a mock biometric scanner.
@@ -307,6 +374,36 @@ impl MockBiometricHandle {
         self.queue_fingerprint(template.clone(), quality).await
     }
 
+    /// Present a finger, with capture quality decided by the scanner.
+    ///
+    /// Unlike [`queue_fingerprint()`](Self::queue_fingerprint), the quality
+    /// is drawn from the scanner's [`BiometricProfile`], so a degraded sensor
+    /// produces low-quality captures that should trigger a retry prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scanner has been dropped and the channel is closed.
+    pub async fn present_finger(&self, template: Vec<u8>) -> Result<()> {
+        self.event_tx
+            .send(BiometricEvent::FingerPresented(template))
+            .await
+            .map_err(|_| crate::HardwareError::disconnected("Biometric event channel closed"))
+    }
+
+    /// Present a registered user's finger, with profile-driven quality.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user is not in the template database or the
+    /// scanner has been dropped.
+    pub async fn present_user_finger(&self, user_id: &str) -> Result<()> {
+        let template = self.templates.get(user_id).ok_or_else(|| {
+            crate::HardwareError::invalid_data(format!("User {} not in database", user_id))
+        })?;
+
+        self.present_finger(template.clone()).await
+    }
+
     /// Get a template from the database.
     ///
     /// # Examples
@@ -353,6 +450,8 @@ impl MockBiometricHandle {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::QualityDistribution;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_mock_biometric_capture() {
@@ -519,6 +618,59 @@ mod tests {
         assert!(captured.is_quality_acceptable());
     }
 
+    #[tokio::test]
+    async fn test_mock_biometric_profile_error_rates() {
+        let (scanner, handle) = MockBiometric::new();
+        let mut scanner = scanner
+            .with_profile(
+                BiometricProfile::ideal()
+                    .false_reject_rate(1.0)
+                    .false_accept_rate(1.0),
+            )
+            .unwrap();
+
+        handle.queue_fingerprint(vec![1, 2, 3], 80).await.unwrap();
+        assert!(!scanner.verify_fingerprint(&[1, 2, 3]).await.unwrap());
+
+        handle.queue_fingerprint(vec![4, 5, 6], 80).await.unwrap();
+        assert!(scanner.verify_fingerprint(&[1, 2, 3]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mock_biometric_profile_quality_and_latency() {
+        let profile = BiometricProfile::ideal()
+            .quality(QualityDistribution::Uniform { min: 20, max: 40 })
+            .latency(Duration::from_millis(30), Duration::from_millis(30))
+            .seed(1);
+        let (scanner, mut handle) = MockBiometric::new();
+        let mut scanner = scanner.with_profile(profile).unwrap();
+        handle
+            .add_template("user1".to_string(), vec![1, 2, 3])
+            .await;
+
+        handle.present_user_finger("user1").await.unwrap();
+        let started = std::time::Instant::now();
+        let captured = scanner.capture_fingerprint().await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        assert!((20..=40).contains(&captured.quality));
+        assert!(!captured.is_quality_acceptable());
+
+        // An explicit quality is kept
+        handle.queue_fingerprint(vec![1, 2, 3], 85).await.unwrap();
+        assert_eq!(scanner.capture_fingerprint().await.unwrap().quality, 85);
+    }
+
+    #[test]
+    fn test_mock_biometric_invalid_profile() {
+        let (scanner, _handle) = MockBiometric::new();
+        assert!(
+            scanner
+                .with_profile(BiometricProfile::ideal().false_reject_rate(2.0))
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_mock_biometric_low_quality() {
         let (mut scanner, handle) = MockBiometric::new();
@@ -532,4 +684,19 @@ mod tests {
         assert_eq!(captured.quality, 30);
         assert!(!captured.is_quality_acceptable());
     }
+
+    #[tokio::test]
+    async fn test_mock_biometric_enroll_rejects_low_quality() {
+        let (mut scanner, handle) = MockBiometric::new();
+
+        handle.queue_fingerprint(vec![1, 2, 3], 30).await.unwrap();
+        handle.queue_fingerprint(vec![4, 5, 6], 80).await.unwrap();
+
+        let err = scanner.enroll_fingerprint().await.unwrap_err();
+        assert!(err.to_string().contains("quality 30"));
+
+        let sample = scanner.enroll_fingerprint().await.unwrap();
+        assert_eq!(sample.template, vec![4, 5, 6]);
+        assert_eq!(sample.quality, 80);
+    }
 }
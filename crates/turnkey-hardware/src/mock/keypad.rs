@@ -6,7 +6,7 @@
 use crate::{
     Result,
     traits::{KeypadDevice, KeypadInput},
-    types::DeviceInfo,
+    types::{DeviceInfo, SelfTestResult},
 };
 use tokio::sync::mpsc;
 
@@ -132,6 +132,13 @@ impl KeypadDevice for MockKeypad {
     async fn get_info(&self) -> Result<DeviceInfo> {
         Ok(DeviceInfo::new(self.name.clone(), "Mock Keypad v1.0").with_firmware_version("1.0.0"))
     }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        if self.input_rx.is_closed() {
+            return Ok(SelfTestResult::fail("Input disconnected"));
+        }
+        Ok(SelfTestResult::pass(self.name.clone()))
+    }
 }
 
 /// Handle for controlling a mock keypad.
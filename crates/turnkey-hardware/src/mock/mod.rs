@@ -5,9 +5,15 @@
 
 pub mod biometric;
 pub mod keypad;
+pub mod profile;
+pub mod rex;
 pub mod rfid;
+pub mod sensor;
 
 // Re-export commonly used types
 pub use biometric::{MockBiometric, MockBiometricHandle};
 pub use keypad::{MockKeypad, MockKeypadHandle};
-pub use rfid::{MockRfid, MockRfidHandle};
+pub use profile::{BiometricProfile, QualityDistribution};
+pub use rex::{MockRex, MockRexHandle};
+pub use rfid::{MockRfid, MockRfidHandle, RfidScript, RfidScriptEvent, RfidScriptStep};
+pub use sensor::{MockSensor, MockSensorHandle};
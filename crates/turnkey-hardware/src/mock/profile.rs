@@ -0,0 +1,413 @@
+//! Sensor behavior profiles for the mock biometric scanner.
+//!
+//! Real fingerprint sensors are not deterministic: a genuine finger is
+//! sometimes rejected, an impostor is occasionally accepted, capture quality
+//! varies with skin condition and placement, and a capture takes hundreds of
+//! milliseconds. A [`BiometricProfile`] describes that behavior so acceptance
+//! tests can exercise retry prompts, quality thresholds and denial messages
+//! against a [`MockBiometric`](super::MockBiometric).
+//!
+//! Profiles are seedable; the same seed yields the same sequence of
+//! qualities, latencies and match decisions.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_hardware::mock::{BiometricProfile, QualityDistribution};
+//! use std::time::Duration;
+//!
+//! // Worn sensor in a dusty environment
+//! let profile = BiometricProfile::ideal()
+//!     .false_reject_rate(0.10)
+//!     .false_accept_rate(0.001)
+//!     .quality(QualityDistribution::Normal { mean: 55.0, std_dev: 15.0 })
+//!     .latency(Duration::from_millis(400), Duration::from_millis(1200))
+//!     .seed(42);
+//!
+//! assert!(profile.validate().is_ok());
+//! ```
+
+use crate::{HardwareError, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+/// Highest quality score a capture can have.
+const MAX_QUALITY: u8 = 100;
+
+/// Distribution of capture quality scores (0-100).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityDistribution {
+    /// Every capture has the same quality.
+    Fixed(u8),
+
+    /// Quality drawn uniformly from `min..=max`.
+    Uniform {
+        /// Lowest quality.
+        min: u8,
+        /// Highest quality.
+        max: u8,
+    },
+
+    /// Quality drawn from a normal distribution, clamped to 0-100.
+    Normal {
+        /// Mean quality.
+        mean: f64,
+        /// Standard deviation.
+        std_dev: f64,
+    },
+}
+
+impl QualityDistribution {
+    fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Fixed(quality) if quality > MAX_QUALITY => Err(HardwareError::configuration(
+                format!("Quality must be 0-100, got {}", quality),
+            )),
+            Self::Uniform { min, max } if min > max || max > MAX_QUALITY => {
+                Err(HardwareError::configuration(format!(
+                    "Quality range must be within 0-100 with min <= max, got {}-{}",
+                    min, max
+                )))
+            }
+            Self::Normal { mean, std_dev }
+                if !mean.is_finite() || !std_dev.is_finite() || std_dev < 0.0 =>
+            {
+                Err(HardwareError::configuration(format!(
+                    "Invalid quality distribution: mean {}, std dev {}",
+                    mean, std_dev
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> u8 {
+        match *self {
+            Self::Fixed(quality) => quality,
+            Self::Uniform { min, max } => rng.gen_range(min..=max),
+            Self::Normal { mean, std_dev } => {
+                // Box-Muller transform
+                let u1: f64 = 1.0 - rng.r#gen::<f64>();
+                let u2: f64 = rng.r#gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (mean + z * std_dev).round().clamp(0.0, MAX_QUALITY as f64) as u8
+            }
+        }
+    }
+}
+
+/// Simulated behavior of a fingerprint sensor.
+///
+/// The default profile is [`ideal()`](Self::ideal), which keeps the mock
+/// deterministic: no false rejects or accepts and no capture delay.
+///
+/// # Rates
+///
+/// * `false_reject_rate` - Probability that a genuine finger fails to match
+/// * `false_accept_rate` - Probability that a different finger matches
+///
+/// Both are probabilities in `0.0..=1.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiometricProfile {
+    /// Probability of rejecting a genuine finger (FRR).
+    pub false_reject_rate: f64,
+
+    /// Probability of accepting an impostor finger (FAR).
+    pub false_accept_rate: f64,
+
+    /// Quality of captures queued without an explicit quality.
+    pub quality: QualityDistribution,
+
+    /// Shortest capture time.
+    pub min_latency: Duration,
+
+    /// Longest capture time.
+    pub max_latency: Duration,
+
+    /// Random seed; `None` seeds from system entropy.
+    pub seed: Option<u64>,
+}
+
+impl Default for BiometricProfile {
+    fn default() -> Self {
+        Self::ideal()
+    }
+}
+
+impl BiometricProfile {
+    /// Perfect sensor: exact matching, quality 90, instant captures.
+    pub fn ideal() -> Self {
+        Self {
+            false_reject_rate: 0.0,
+            false_accept_rate: 0.0,
+            quality: QualityDistribution::Fixed(90),
+            min_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            seed: None,
+        }
+    }
+
+    /// Well-maintained optical sensor in an office lobby.
+    ///
+    /// 2% FRR, 0.01% FAR, quality around 75, 300-800ms per capture.
+    pub fn typical() -> Self {
+        Self {
+            false_reject_rate: 0.02,
+            false_accept_rate: 0.0001,
+            quality: QualityDistribution::Normal {
+                mean: 75.0,
+                std_dev: 10.0,
+            },
+            min_latency: Duration::from_millis(300),
+            max_latency: Duration::from_millis(800),
+            seed: None,
+        }
+    }
+
+    /// Dirty or worn sensor, or users with dry or damaged fingers.
+    ///
+    /// 15% FRR, 0.1% FAR, quality around 45 (often below the acceptance
+    /// threshold), 500-1500ms per capture.
+    pub fn degraded() -> Self {
+        Self {
+            false_reject_rate: 0.15,
+            false_accept_rate: 0.001,
+            quality: QualityDistribution::Normal {
+                mean: 45.0,
+                std_dev: 15.0,
+            },
+            min_latency: Duration::from_millis(500),
+            max_latency: Duration::from_millis(1500),
+            seed: None,
+        }
+    }
+
+    /// Set the false reject rate.
+    pub fn false_reject_rate(mut self, rate: f64) -> Self {
+        self.false_reject_rate = rate;
+        self
+    }
+
+    /// Set the false accept rate.
+    pub fn false_accept_rate(mut self, rate: f64) -> Self {
+        self.false_accept_rate = rate;
+        self
+    }
+
+    /// Set the quality distribution.
+    pub fn quality(mut self, quality: QualityDistribution) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Set the capture latency range (use the same value twice for a fixed delay).
+    pub fn latency(mut self, min: Duration, max: Duration) -> Self {
+        self.min_latency = min;
+        self.max_latency = max;
+        self
+    }
+
+    /// Set the random seed for reproducible runs.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Validate the profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigurationError` if a rate is outside `0.0..=1.0`, the
+    /// quality distribution is invalid, or `min_latency > max_latency`.
+    pub fn validate(&self) -> Result<()> {
+        for (name, rate) in [
+            ("False reject rate", self.false_reject_rate),
+            ("False accept rate", self.false_accept_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(HardwareError::configuration(format!(
+                    "{} must be 0.0-1.0, got {}",
+                    name, rate
+                )));
+            }
+        }
+
+        self.quality.validate()?;
+
+        if self.min_latency > self.max_latency {
+            return Err(HardwareError::configuration(format!(
+                "Minimum latency {:?} exceeds maximum {:?}",
+                self.min_latency, self.max_latency
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Random source driving a profile.
+#[derive(Debug)]
+pub(crate) struct SensorSimulator {
+    profile: BiometricProfile,
+    rng: StdRng,
+}
+
+impl SensorSimulator {
+    pub(crate) fn new(profile: BiometricProfile) -> Result<Self> {
+        profile.validate()?;
+
+        let rng = match profile.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Ok(Self { profile, rng })
+    }
+
+    pub(crate) fn profile(&self) -> &BiometricProfile {
+        &self.profile
+    }
+
+    /// Quality for a capture queued without one.
+    pub(crate) fn quality(&mut self) -> u8 {
+        self.profile.quality.sample(&mut self.rng)
+    }
+
+    /// Time the next capture takes.
+    pub(crate) fn latency(&mut self) -> Duration {
+        let (min, max) = (self.profile.min_latency, self.profile.max_latency);
+        if min == max {
+            return min;
+        }
+        self.rng.gen_range(min..=max)
+    }
+
+    /// Match decision for a capture, given whether the finger is genuine.
+    pub(crate) fn decide(&mut self, genuine: bool) -> bool {
+        if genuine {
+            !self.rng.gen_bool(self.profile.false_reject_rate)
+        } else {
+            self.rng.gen_bool(self.profile.false_accept_rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulator(profile: BiometricProfile) -> SensorSimulator {
+        SensorSimulator::new(profile.seed(7)).unwrap()
+    }
+
+    #[test]
+    fn test_ideal_profile_is_deterministic() {
+        let mut sim = simulator(BiometricProfile::ideal());
+
+        for _ in 0..100 {
+            assert!(sim.decide(true));
+            assert!(!sim.decide(false));
+            assert_eq!(sim.quality(), 90);
+            assert_eq!(sim.latency(), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_rates_are_respected() {
+        let mut sim = simulator(
+            BiometricProfile::ideal()
+                .false_reject_rate(0.2)
+                .false_accept_rate(0.05),
+        );
+
+        let samples = 5000;
+        let rejected = (0..samples).filter(|_| !sim.decide(true)).count();
+        let accepted = (0..samples).filter(|_| sim.decide(false)).count();
+
+        let frr = rejected as f64 / samples as f64;
+        let far = accepted as f64 / samples as f64;
+        assert!((0.17..0.23).contains(&frr), "FRR {}", frr);
+        assert!((0.035..0.065).contains(&far), "FAR {}", far);
+    }
+
+    #[test]
+    fn test_normal_quality_is_clamped_and_centered() {
+        let mut sim = simulator(
+            BiometricProfile::ideal().quality(QualityDistribution::Normal {
+                mean: 95.0,
+                std_dev: 20.0,
+            }),
+        );
+
+        let qualities: Vec<u8> = (0..2000).map(|_| sim.quality()).collect();
+        assert!(qualities.iter().all(|q| *q <= 100));
+        assert!(qualities.contains(&100));
+
+        let mut sim = simulator(
+            BiometricProfile::ideal().quality(QualityDistribution::Normal {
+                mean: 60.0,
+                std_dev: 5.0,
+            }),
+        );
+        let mean = (0..2000).map(|_| sim.quality() as f64).sum::<f64>() / 2000.0;
+        assert!((58.0..62.0).contains(&mean), "mean {}", mean);
+    }
+
+    #[test]
+    fn test_uniform_quality_and_latency_stay_in_range() {
+        let mut sim = simulator(
+            BiometricProfile::ideal()
+                .quality(QualityDistribution::Uniform { min: 30, max: 40 })
+                .latency(Duration::from_millis(100), Duration::from_millis(200)),
+        );
+
+        for _ in 0..500 {
+            assert!((30..=40).contains(&sim.quality()));
+            let latency = sim.latency();
+            assert!(latency >= Duration::from_millis(100));
+            assert!(latency <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = simulator(BiometricProfile::degraded());
+        let mut b = simulator(BiometricProfile::degraded());
+
+        for _ in 0..100 {
+            assert_eq!(a.quality(), b.quality());
+            assert_eq!(a.latency(), b.latency());
+            assert_eq!(a.decide(true), b.decide(true));
+        }
+    }
+
+    #[test]
+    fn test_invalid_profiles_rejected() {
+        let invalid = [
+            BiometricProfile::ideal().false_reject_rate(1.5),
+            BiometricProfile::ideal().false_accept_rate(-0.1),
+            BiometricProfile::ideal().false_accept_rate(f64::NAN),
+            BiometricProfile::ideal().quality(QualityDistribution::Fixed(101)),
+            BiometricProfile::ideal().quality(QualityDistribution::Uniform { min: 50, max: 40 }),
+            BiometricProfile::ideal().quality(QualityDistribution::Normal {
+                mean: 50.0,
+                std_dev: -1.0,
+            }),
+            BiometricProfile::ideal().latency(Duration::from_secs(2), Duration::from_secs(1)),
+        ];
+
+        for profile in invalid {
+            assert!(
+                matches!(
+                    SensorSimulator::new(profile.clone()),
+                    Err(HardwareError::ConfigurationError { .. })
+                ),
+                "{:?}",
+                profile
+            );
+        }
+        assert!(BiometricProfile::typical().validate().is_ok());
+        assert!(BiometricProfile::degraded().validate().is_ok());
+    }
+}
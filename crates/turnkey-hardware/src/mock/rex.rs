@@ -0,0 +1,151 @@
+//! Mock request-to-exit inputs for testing.
+//!
+//! This module provides a simulated exit button / motion detector whose
+//! requests are injected programmatically, the same way
+//! [`MockSensor`](super::MockSensor) simulates the turnstile sensors.
+
+use crate::{
+    Result,
+    traits::{RexDevice, RexRequest},
+    types::DeviceInfo,
+};
+use tokio::sync::mpsc;
+
+/// Mock request-to-exit input for testing and development.
+///
+/// Requests sent through the paired [`MockRexHandle`] are returned by
+/// [`read_request()`](RexDevice::read_request) in order.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_hardware::mock::MockRex;
+/// use turnkey_hardware::traits::{RexDevice, RexRequest};
+///
+/// #[tokio::main]
+/// async fn main() -> turnkey_hardware::Result<()> {
+///     let (mut rex, handle) = MockRex::new();
+///
+///     handle.press().await?;
+///     handle.inject(RexRequest::Motion).await?;
+///
+///     assert_eq!(rex.read_request().await?, RexRequest::Button);
+///     assert_eq!(rex.read_request().await?, RexRequest::Motion);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MockRex {
+    /// Channel receiver for injected requests
+    request_rx: mpsc::Receiver<RexRequest>,
+
+    /// Device name
+    name: String,
+}
+
+impl MockRex {
+    /// Create a new mock exit input with the default name.
+    ///
+    /// Returns a tuple of (MockRex, MockRexHandle) where the handle is used
+    /// to inject requests.
+    pub fn new() -> (Self, MockRexHandle) {
+        Self::with_name("Mock Exit Button".to_string())
+    }
+
+    /// Create a new mock exit input with a custom name.
+    pub fn with_name(name: String) -> (Self, MockRexHandle) {
+        let (request_tx, request_rx) = mpsc::channel(32);
+
+        let rex = Self {
+            request_rx,
+            name: name.clone(),
+        };
+
+        let handle = MockRexHandle { request_tx, name };
+
+        (rex, handle)
+    }
+}
+
+impl Default for MockRex {
+    fn default() -> Self {
+        Self::new().0
+    }
+}
+
+impl RexDevice for MockRex {
+    async fn read_request(&mut self) -> Result<RexRequest> {
+        self.request_rx
+            .recv()
+            .await
+            .ok_or_else(|| crate::HardwareError::disconnected("Exit request channel closed"))
+    }
+
+    async fn get_info(&self) -> Result<DeviceInfo> {
+        Ok(DeviceInfo::new(self.name.clone(), "Mock REX v1.0").with_firmware_version("1.0.0"))
+    }
+}
+
+/// Handle for injecting requests into a mock exit input.
+///
+/// It can be cloned and shared across tasks. Dropping every handle
+/// disconnects the input: pending requests are still delivered, then
+/// [`read_request()`](RexDevice::read_request) returns an error.
+#[derive(Debug, Clone)]
+pub struct MockRexHandle {
+    /// Channel sender for injected requests
+    request_tx: mpsc::Sender<RexRequest>,
+
+    /// Device name
+    name: String,
+}
+
+impl MockRexHandle {
+    /// Inject a request as if the input had been triggered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input has been dropped and the channel is closed.
+    pub async fn inject(&self, request: RexRequest) -> Result<()> {
+        self.request_tx
+            .send(request)
+            .await
+            .map_err(|_| crate::HardwareError::disconnected("Exit request channel closed"))
+    }
+
+    /// Simulate a press of the exit button.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`inject()`](Self::inject).
+    pub async fn press(&self) -> Result<()> {
+        self.inject(RexRequest::Button).await
+    }
+
+    /// Get the device name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_rex_delivers_requests_until_disconnected() {
+        let (mut rex, handle) = MockRex::with_name("Saida Norte".to_string());
+        assert_eq!(handle.name(), "Saida Norte");
+        assert_eq!(rex.get_info().await.unwrap().name, "Saida Norte");
+
+        tokio::spawn(async move {
+            handle.inject(RexRequest::Motion).await.unwrap();
+            handle.press().await.unwrap();
+        });
+
+        assert_eq!(rex.read_request().await.unwrap(), RexRequest::Motion);
+        assert_eq!(rex.read_request().await.unwrap(), RexRequest::Button);
+        assert!(rex.read_request().await.is_err());
+    }
+}
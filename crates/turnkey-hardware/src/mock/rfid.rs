@@ -2,14 +2,23 @@
 //!
 //! This module provides a simulated RFID reader that can be controlled
 //! programmatically for testing without requiring physical hardware.
+//! Realistic reader behavior (cards held in the field, failed reads,
+//! garbled UIDs) can be replayed from an [`RfidScript`].
 
 use crate::{
     Result,
     traits::{CardData, CardType, RfidDevice},
-    types::{LedColor, ReaderInfo},
+    types::{FirmwareInfo, LedColor, ReaderInfo, SelfTestResult, SignalQuality},
 };
 use std::collections::HashMap;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+/// Field strength of a new mock reader, in percent.
+const DEFAULT_SIGNAL_STRENGTH: u8 = 90;
+
+/// Field strength below which the test read fails, in percent.
+pub const MIN_TEST_READ_STRENGTH: u8 = 30;
 
 /// Mock RFID reader for testing and development.
 ///
@@ -50,6 +59,12 @@ pub struct MockRfid {
 
     /// Currently set LED color
     led_color: LedColor,
+
+    /// Simulated radio conditions, set through the handle
+    conditions: watch::Receiver<RadioConditions>,
+
+    /// Cards read successfully
+    reads: u32,
 }
 
 impl MockRfid {
@@ -80,15 +95,19 @@ impl MockRfid {
     /// ```
     pub fn with_name(name: String) -> (Self, MockRfidHandle) {
         let (event_tx, event_rx) = mpsc::channel(32);
+        let (conditions_tx, conditions) = watch::channel(RadioConditions::default());
 
         let reader = Self {
             event_rx,
             name: name.clone(),
             led_color: LedColor::Off,
+            conditions,
+            reads: 0,
         };
 
         let handle = MockRfidHandle {
             event_tx,
+            conditions: conditions_tx,
             name,
             cards: HashMap::new(),
             current_card: None,
@@ -120,7 +139,15 @@ impl RfidDevice for MockRfid {
             .ok_or_else(|| crate::HardwareError::disconnected("RFID event channel closed"))?;
 
         match event {
-            CardEvent::CardPresented(card) => Ok(card),
+            CardEvent::CardPresented(card) => {
+                self.reads = self.reads.saturating_add(1);
+                Ok(card)
+            }
+            CardEvent::ReadError(message) => Err(crate::HardwareError::card_read(message)),
+            CardEvent::MalformedUid(uid) => Err(crate::HardwareError::invalid_data(format!(
+                "Malformed card UID {:02X?}",
+                uid
+            ))),
         }
     }
 
@@ -142,12 +169,175 @@ impl RfidDevice for MockRfid {
         self.led_color = color;
         Ok(())
     }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        let conditions = self.conditions.borrow().clone();
+        if let Some(fault) = conditions.fault {
+            return Ok(SelfTestResult::fail(fault));
+        }
+        if self.event_rx.is_closed() {
+            return Ok(SelfTestResult::fail("Card feed disconnected"));
+        }
+        if conditions.strength < MIN_TEST_READ_STRENGTH {
+            return Ok(SelfTestResult::fail(format!(
+                "Test read failed, field at {}%",
+                conditions.strength
+            )));
+        }
+        Ok(SelfTestResult::pass(format!(
+            "Test read ok, field at {}%",
+            conditions.strength
+        )))
+    }
+
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        Ok(FirmwareInfo::new("1.0.0").with_build("mock"))
+    }
+
+    async fn signal_quality(&self) -> Result<SignalQuality> {
+        let conditions = self.conditions.borrow();
+        let attempts = self.reads.saturating_add(conditions.read_errors);
+        Ok(SignalQuality::new(conditions.strength).with_reads(attempts, conditions.read_errors))
+    }
 }
 
 /// Internal event type for mock RFID reader.
 #[derive(Debug, Clone)]
 enum CardEvent {
     CardPresented(CardData),
+    ReadError(String),
+    MalformedUid(Vec<u8>),
+}
+
+/// Something that happens at a mock RFID reader during a scripted scenario.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RfidScriptEvent {
+    /// A card from the handle's database enters the field and is read
+    Present(Vec<u8>),
+
+    /// The current card leaves the field
+    Remove,
+
+    /// A read fails, e.g. the card was pulled away halfway through
+    ReadError(String),
+
+    /// A read returns a UID of invalid length, as a noisy link would
+    MalformedUid(Vec<u8>),
+}
+
+/// Step of an [`RfidScript`]: an event and the pause before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RfidScriptStep {
+    /// Time to wait after the previous step
+    pub delay: Duration,
+
+    /// Event played once the delay has passed
+    pub event: RfidScriptEvent,
+}
+
+/// Timed sequence of reader events, played with
+/// [`MockRfidHandle::play_script()`].
+///
+/// Pauses are set with [`wait()`](Self::wait) and apply to the next event,
+/// so a script reads in the order things happen at the reader. Under a
+/// paused Tokio clock the playback is fully deterministic.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use turnkey_hardware::mock::RfidScript;
+///
+/// // A badge held too briefly, then presented again properly
+/// let script = RfidScript::new()
+///     .read_error("Card removed during read")
+///     .wait(Duration::from_millis(800))
+///     .present(vec![0x04, 0xAB, 0xCD, 0xEF])
+///     .wait(Duration::from_secs(2))
+///     .remove();
+///
+/// assert_eq!(script.steps().len(), 3);
+/// assert_eq!(script.duration(), Duration::from_millis(2800));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RfidScript {
+    steps: Vec<RfidScriptStep>,
+    pending_delay: Duration,
+}
+
+impl RfidScript {
+    /// Create an empty script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait before the next event; consecutive waits add up.
+    pub fn wait(mut self, delay: Duration) -> Self {
+        self.pending_delay += delay;
+        self
+    }
+
+    /// Append an event after the pending wait.
+    pub fn then(mut self, event: RfidScriptEvent) -> Self {
+        let delay = std::mem::take(&mut self.pending_delay);
+        self.steps.push(RfidScriptStep { delay, event });
+        self
+    }
+
+    /// Present a card from the handle's database.
+    pub fn present(self, uid: Vec<u8>) -> Self {
+        self.then(RfidScriptEvent::Present(uid))
+    }
+
+    /// Take the current card out of the field.
+    pub fn remove(self) -> Self {
+        self.then(RfidScriptEvent::Remove)
+    }
+
+    /// Fail a read with the given detail.
+    pub fn read_error(self, message: impl Into<String>) -> Self {
+        self.then(RfidScriptEvent::ReadError(message.into()))
+    }
+
+    /// Deliver a read with a UID of invalid length.
+    pub fn malformed_uid(self, uid: Vec<u8>) -> Self {
+        self.then(RfidScriptEvent::MalformedUid(uid))
+    }
+
+    /// Steps in playing order.
+    pub fn steps(&self) -> &[RfidScriptStep] {
+        &self.steps
+    }
+
+    /// Time from the start of playback to the last event.
+    ///
+    /// A trailing [`wait()`](Self::wait) without an event is not counted.
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.delay).sum()
+    }
+}
+
+/// Radio conditions simulated by a mock RFID reader.
+#[derive(Debug, Clone)]
+struct RadioConditions {
+    /// Field strength in percent
+    strength: u8,
+
+    /// Reads that failed
+    read_errors: u32,
+
+    /// Fault reported by the self-test
+    fault: Option<String>,
+}
+
+impl Default for RadioConditions {
+    fn default() -> Self {
+        Self {
+            strength: DEFAULT_SIGNAL_STRENGTH,
+            read_errors: 0,
+            fault: None,
+        }
+    }
 }
 
 /// Handle for controlling a mock RFID reader.
@@ -184,6 +374,9 @@ pub struct MockRfidHandle {
     /// Channel sender for card events
     event_tx: mpsc::Sender<CardEvent>,
 
+    /// Simulated radio conditions
+    conditions: watch::Sender<RadioConditions>,
+
     /// Device name
     name: String,
 
@@ -290,6 +483,100 @@ impl MockRfidHandle {
     pub fn card_count(&self) -> usize {
         self.cards.len()
     }
+
+    /// Set the field strength the reader measures, in percent.
+    ///
+    /// Below [`MIN_TEST_READ_STRENGTH`] the self-test fails its test read.
+    pub fn set_signal_strength(&self, strength: u8) {
+        self.conditions
+            .send_modify(|conditions| conditions.strength = strength.min(100));
+    }
+
+    /// Count reads that failed, as a detuned antenna would.
+    pub fn fail_reads(&self, count: u32) {
+        self.conditions.send_modify(|conditions| {
+            conditions.read_errors = conditions.read_errors.saturating_add(count)
+        });
+    }
+
+    /// Make the self-test fail with the given detail.
+    pub fn set_fault(&self, detail: impl Into<String>) {
+        let detail = detail.into();
+        self.conditions
+            .send_modify(|conditions| conditions.fault = Some(detail));
+    }
+
+    /// Clear a fault set with [`set_fault()`](Self::set_fault).
+    pub fn clear_fault(&self) {
+        self.conditions
+            .send_modify(|conditions| conditions.fault = None);
+    }
+
+    /// Play a scripted scenario, returning once its last event is delivered.
+    ///
+    /// Cards must be in the database (see [`add_card()`](Self::add_card)).
+    /// Failed and malformed reads make the reader's next
+    /// [`read_card()`](RfidDevice::read_card) return an error and count as
+    /// read errors in its signal quality.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a presented card is not in the database or the
+    /// reader has been dropped. Events before the failing one have already
+    /// been played.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use turnkey_hardware::mock::{MockRfid, RfidScript};
+    /// use turnkey_hardware::traits::{CardType, RfidDevice};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> turnkey_hardware::Result<()> {
+    ///     let (mut reader, mut handle) = MockRfid::new();
+    ///     let uid = vec![0x04, 0xAB, 0xCD, 0xEF];
+    ///     handle.add_card(uid.clone(), CardType::MifareClassic1K).await;
+    ///
+    ///     let script = RfidScript::new()
+    ///         .malformed_uid(vec![0x04])
+    ///         .wait(Duration::from_millis(10))
+    ///         .present(uid);
+    ///     handle.play_script(&script).await?;
+    ///
+    ///     assert!(reader.read_card().await.is_err());
+    ///     assert_eq!(reader.read_card().await?.uid_hex(), "04ABCDEF");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn play_script(&mut self, script: &RfidScript) -> Result<()> {
+        for step in script.steps() {
+            if !step.delay.is_zero() {
+                tokio::time::sleep(step.delay).await;
+            }
+            match &step.event {
+                RfidScriptEvent::Present(uid) => self.present_card(uid.clone()).await?,
+                RfidScriptEvent::Remove => self.remove_card(),
+                RfidScriptEvent::ReadError(message) => {
+                    self.send_failed_read(CardEvent::ReadError(message.clone()))
+                        .await?
+                }
+                RfidScriptEvent::MalformedUid(uid) => {
+                    self.send_failed_read(CardEvent::MalformedUid(uid.clone()))
+                        .await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_failed_read(&mut self, event: CardEvent) -> Result<()> {
+        self.fail_reads(1);
+        self.event_tx
+            .send(event)
+            .await
+            .map_err(|_| crate::HardwareError::disconnected("RFID event channel closed"))
+    }
 }
 
 #[cfg(test)]
@@ -430,4 +717,98 @@ mod tests {
         let card = reader.read_card().await.unwrap();
         assert_eq!(card.uid_decimal(), "16909060");
     }
+
+    #[tokio::test]
+    async fn test_mock_rfid_diagnostics() {
+        let (mut reader, mut handle) = MockRfid::new();
+        let uid = vec![0x01, 0x02, 0x03, 0x04];
+        handle
+            .add_card(uid.clone(), CardType::MifareClassic1K)
+            .await;
+        handle.present_card(uid).await.unwrap();
+        reader.read_card().await.unwrap();
+        handle.fail_reads(1);
+
+        let signal = reader.signal_quality().await.unwrap();
+        assert_eq!(signal.strength, DEFAULT_SIGNAL_STRENGTH);
+        assert_eq!((signal.reads, signal.read_errors), (2, 1));
+        assert_eq!(reader.firmware_info().await.unwrap().version, "1.0.0");
+        assert!(reader.self_test().await.unwrap().passed);
+
+        // A weak field fails the test read
+        handle.set_signal_strength(10);
+        let result = reader.self_test().await.unwrap();
+        assert!(!result.passed);
+        assert!(result.detail.contains("10%"));
+
+        // A fault wins until cleared
+        handle.set_signal_strength(80);
+        handle.set_fault("Antenna open circuit");
+        assert_eq!(
+            reader.self_test().await.unwrap(),
+            SelfTestResult::fail("Antenna open circuit")
+        );
+        handle.clear_fault();
+        assert!(reader.self_test().await.unwrap().passed);
+
+        drop(handle);
+        assert!(!reader.self_test().await.unwrap().passed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mock_rfid_script_playback() {
+        let (mut reader, mut handle) = MockRfid::new();
+        let uid = vec![0x04, 0xAB, 0xCD, 0xEF];
+        handle
+            .add_card(uid.clone(), CardType::MifareClassic1K)
+            .await;
+
+        let script = RfidScript::new()
+            .read_error("Card removed during read")
+            .wait(Duration::from_millis(500))
+            .malformed_uid(vec![0x04, 0xAB])
+            .wait(Duration::from_millis(300))
+            .present(uid.clone())
+            .wait(Duration::from_secs(2))
+            .remove();
+        assert_eq!(script.duration(), Duration::from_millis(2800));
+
+        let started = tokio::time::Instant::now();
+        let player = tokio::spawn(async move {
+            handle.play_script(&script).await.unwrap();
+            handle
+        });
+
+        assert!(matches!(
+            reader.read_card().await,
+            Err(crate::HardwareError::CardReadError { .. })
+        ));
+        assert!(matches!(
+            reader.read_card().await,
+            Err(crate::HardwareError::InvalidData { .. })
+        ));
+        assert_eq!(started.elapsed(), Duration::from_millis(500));
+        assert_eq!(reader.read_card().await.unwrap().uid, uid);
+        assert_eq!(started.elapsed(), Duration::from_millis(800));
+
+        let handle = player.await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_millis(2800));
+        assert!(!handle.is_card_presented());
+        let signal = reader.signal_quality().await.unwrap();
+        assert_eq!((signal.reads, signal.read_errors), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_mock_rfid_script_stops_at_unknown_card() {
+        let (mut reader, mut handle) = MockRfid::new();
+
+        let script = RfidScript::new()
+            .read_error("CRC error")
+            .present(vec![0xFF, 0xFF, 0xFF, 0xFF])
+            .read_error("never played");
+        assert!(handle.play_script(&script).await.is_err());
+
+        assert!(reader.read_card().await.is_err());
+        assert!(!reader.is_card_present().await.unwrap());
+    }
 }
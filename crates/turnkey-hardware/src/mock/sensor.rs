@@ -0,0 +1,162 @@
+//! Mock turnstile sensors for testing and hardware-in-the-loop preparation.
+//!
+//! This module provides simulated GPIO sensors (arm rotation, door contact,
+//! tamper switch) whose events are injected programmatically, so the
+//! emulator can be driven exactly as a real sensor driver would drive it.
+
+use crate::{
+    Result,
+    traits::{SensorDevice, SensorEvent},
+    types::DeviceInfo,
+};
+use tokio::sync::mpsc;
+
+/// Mock turnstile sensor block for testing and development.
+///
+/// Events sent through the paired [`MockSensorHandle`] are returned by
+/// [`read_event()`](SensorDevice::read_event) in order.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_core::AccessDirection;
+/// use turnkey_hardware::mock::MockSensor;
+/// use turnkey_hardware::traits::{SensorDevice, SensorEvent};
+///
+/// #[tokio::main]
+/// async fn main() -> turnkey_hardware::Result<()> {
+///     let (mut sensor, handle) = MockSensor::new();
+///
+///     handle.inject(SensorEvent::ArmRotated(AccessDirection::Entry)).await?;
+///     handle.inject(SensorEvent::TamperOpened).await?;
+///
+///     assert_eq!(
+///         sensor.read_event().await?,
+///         SensorEvent::ArmRotated(AccessDirection::Entry)
+///     );
+///     assert_eq!(sensor.read_event().await?, SensorEvent::TamperOpened);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MockSensor {
+    /// Channel receiver for injected events
+    event_rx: mpsc::Receiver<SensorEvent>,
+
+    /// Device name
+    name: String,
+}
+
+impl MockSensor {
+    /// Create a new mock sensor block with the default name.
+    ///
+    /// Returns a tuple of (MockSensor, MockSensorHandle) where the handle
+    /// is used to inject sensor events.
+    pub fn new() -> (Self, MockSensorHandle) {
+        Self::with_name("Mock Turnstile Sensors".to_string())
+    }
+
+    /// Create a new mock sensor block with a custom name.
+    pub fn with_name(name: String) -> (Self, MockSensorHandle) {
+        let (event_tx, event_rx) = mpsc::channel(32);
+
+        let sensor = Self {
+            event_rx,
+            name: name.clone(),
+        };
+
+        let handle = MockSensorHandle { event_tx, name };
+
+        (sensor, handle)
+    }
+}
+
+impl Default for MockSensor {
+    fn default() -> Self {
+        Self::new().0
+    }
+}
+
+impl SensorDevice for MockSensor {
+    async fn read_event(&mut self) -> Result<SensorEvent> {
+        self.event_rx
+            .recv()
+            .await
+            .ok_or_else(|| crate::HardwareError::disconnected("Sensor event channel closed"))
+    }
+
+    async fn get_info(&self) -> Result<DeviceInfo> {
+        Ok(DeviceInfo::new(self.name.clone(), "Mock Sensors v1.0").with_firmware_version("1.0.0"))
+    }
+}
+
+/// Handle for injecting events into a mock sensor block.
+///
+/// It can be cloned and shared across tasks. Dropping every handle
+/// disconnects the sensor: pending events are still delivered, then
+/// [`read_event()`](SensorDevice::read_event) returns an error.
+#[derive(Debug, Clone)]
+pub struct MockSensorHandle {
+    /// Channel sender for injected events
+    event_tx: mpsc::Sender<SensorEvent>,
+
+    /// Device name
+    name: String,
+}
+
+impl MockSensorHandle {
+    /// Inject a sensor event as if the GPIO input had changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sensor has been dropped and the channel is closed.
+    pub async fn inject(&self, event: SensorEvent) -> Result<()> {
+        self.event_tx
+            .send(event)
+            .await
+            .map_err(|_| crate::HardwareError::disconnected("Sensor event channel closed"))
+    }
+
+    /// Get the device name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turnkey_core::AccessDirection;
+
+    #[tokio::test]
+    async fn test_mock_sensor_delivers_events_in_order() {
+        let (mut sensor, handle) = MockSensor::new();
+
+        tokio::spawn(async move {
+            handle.inject(SensorEvent::DoorForced).await.unwrap();
+            handle
+                .inject(SensorEvent::ArmRotated(AccessDirection::Exit))
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(sensor.read_event().await.unwrap(), SensorEvent::DoorForced);
+        assert_eq!(
+            sensor.read_event().await.unwrap(),
+            SensorEvent::ArmRotated(AccessDirection::Exit)
+        );
+        // Handle dropped: the sensor is disconnected
+        assert!(sensor.read_event().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_sensor_inject_after_drop() {
+        let (sensor, handle) = MockSensor::with_name("Gate 3".to_string());
+        assert_eq!(handle.name(), "Gate 3");
+        assert_eq!(sensor.get_info().await.unwrap().name, "Gate 3");
+
+        drop(sensor);
+        assert!(handle.inject(SensorEvent::TamperOpened).await.is_err());
+    }
+}
@@ -10,8 +10,10 @@
 
 #![allow(async_fn_in_trait)]
 
-use crate::error::Result;
-use crate::types::{DeviceInfo, LedColor, ReaderInfo};
+use crate::error::{HardwareError, Result};
+use crate::types::{DeviceInfo, FirmwareInfo, LedColor, ReaderInfo, SelfTestResult, SignalQuality};
+use serde::{Deserialize, Serialize};
+use turnkey_core::{AccessDirection, CorrelationId};
 
 /// Input from a keypad device.
 ///
@@ -258,6 +260,31 @@ pub trait KeypadDevice: Send + Sync {
     /// Returns an error if a communication error occurs while querying
     /// device information.
     async fn get_info(&self) -> Result<DeviceInfo>;
+
+    /// Run the device self-test.
+    ///
+    /// The default passes when the device answers [`get_info()`](Self::get_info).
+    /// Drivers with a built-in test routine override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device cannot be reached. A device that
+    /// answers but fails its test returns a failed [`SelfTestResult`].
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        let info = self.get_info().await?;
+        Ok(SelfTestResult::pass(info.name))
+    }
+
+    /// Get firmware details.
+    ///
+    /// The default reports the firmware version from [`get_info()`](Self::get_info).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unsupported` if the keypad does not report its firmware.
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        firmware_from_info(self.get_info().await?)
+    }
 }
 
 /// RFID card type identification.
@@ -311,6 +338,8 @@ pub const MAX_UID_LENGTH: usize = 10;
 ///
 /// Contains information about a card that was read by an RFID reader,
 /// including the unique identifier (UID), card type, and timestamp.
+/// Each read gets a fresh correlation ID, to be carried in the resulting
+/// access request so the passage can be traced across components.
 #[derive(Debug, Clone)]
 pub struct CardData {
     /// Card unique identifier (4-10 bytes).
@@ -321,6 +350,9 @@ pub struct CardData {
 
     /// Timestamp when the card was read.
     pub timestamp: chrono::DateTime<chrono::Utc>,
+
+    /// Identifier of the passage started by this read.
+    pub correlation_id: CorrelationId,
 }
 
 impl CardData {
@@ -385,6 +417,7 @@ impl CardData {
             uid,
             card_type,
             timestamp: chrono::Utc::now(),
+            correlation_id: CorrelationId::new(),
         }
     }
 
@@ -454,6 +487,7 @@ pub struct CardDataBuilder {
     uid: Vec<u8>,
     card_type: CardType,
     timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    correlation_id: Option<CorrelationId>,
 }
 
 impl CardDataBuilder {
@@ -468,6 +502,7 @@ impl CardDataBuilder {
             uid,
             card_type,
             timestamp: None,
+            correlation_id: None,
         }
     }
 
@@ -483,6 +518,14 @@ impl CardDataBuilder {
         self
     }
 
+    /// Set the correlation ID instead of generating a new one.
+    ///
+    /// Useful when replaying recorded reads.
+    pub fn correlation_id(mut self, correlation_id: CorrelationId) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
     /// Build the CardData instance with validation.
     ///
     /// # Errors
@@ -519,6 +562,7 @@ impl CardDataBuilder {
             uid: self.uid,
             card_type: self.card_type,
             timestamp: self.timestamp.unwrap_or_else(chrono::Utc::now),
+            correlation_id: self.correlation_id.unwrap_or_default(),
         })
     }
 }
@@ -601,6 +645,40 @@ pub trait RfidDevice: Send + Sync {
     /// - The device does not support LED control
     /// - A communication error occurs
     async fn set_led(&mut self, color: LedColor) -> Result<()>;
+
+    /// Run the reader self-test (antenna check and test read).
+    ///
+    /// The default passes when the reader answers
+    /// [`get_reader_info()`](Self::get_reader_info).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reader cannot be reached. A reader that
+    /// answers but fails its test returns a failed [`SelfTestResult`].
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        let info = self.get_reader_info().await?;
+        Ok(SelfTestResult::pass(info.name))
+    }
+
+    /// Get firmware details.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unsupported` by default; readers that report their firmware
+    /// override it.
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        Err(HardwareError::unsupported("firmware info"))
+    }
+
+    /// Get the radio link quality.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unsupported` by default; readers that measure their field
+    /// override it.
+    async fn signal_quality(&self) -> Result<SignalQuality> {
+        Err(HardwareError::unsupported("signal quality"))
+    }
 }
 
 /// Minimum biometric quality score for acceptable captures.
@@ -636,6 +714,9 @@ pub struct BiometricData {
 
     /// Timestamp when the fingerprint was captured.
     pub timestamp: chrono::DateTime<chrono::Utc>,
+
+    /// Identifier of the passage started by this capture.
+    pub correlation_id: CorrelationId,
 }
 
 impl BiometricData {
@@ -750,6 +831,7 @@ pub struct BiometricDataBuilder {
     template: Vec<u8>,
     quality: u8,
     timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    correlation_id: Option<CorrelationId>,
 }
 
 impl BiometricDataBuilder {
@@ -764,6 +846,7 @@ impl BiometricDataBuilder {
             template,
             quality,
             timestamp: None,
+            correlation_id: None,
         }
     }
 
@@ -779,6 +862,12 @@ impl BiometricDataBuilder {
         self
     }
 
+    /// Set the correlation ID instead of generating a new one.
+    pub fn correlation_id(mut self, correlation_id: CorrelationId) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
     /// Build the BiometricData instance with validation.
     ///
     /// # Errors
@@ -805,6 +894,7 @@ impl BiometricDataBuilder {
             template: self.template,
             quality: self.quality,
             timestamp: self.timestamp.unwrap_or_else(chrono::Utc::now),
+            correlation_id: self.correlation_id.unwrap_or_default(),
         })
     }
 
@@ -844,19 +934,18 @@ impl BiometricDataBuilder {
 /// use turnkey_hardware::types::LedColor;
 /// use turnkey_hardware::error::Result;
 ///
-/// async fn enroll_fingerprint<B: BiometricDevice>(scanner: &mut B) -> Result<Vec<u8>> {
+/// async fn enroll<B: BiometricDevice>(scanner: &mut B) -> Result<Vec<u8>> {
 ///     println!("Place finger on scanner...");
 ///
-///     let biometric = scanner.capture_fingerprint().await?;
-///
-///     if biometric.is_quality_acceptable() {
-///         scanner.set_led(LedColor::Green).await.ok();
-///         Ok(biometric.template)
-///     } else {
-///         scanner.set_led(LedColor::Red).await.ok();
-///         Err(turnkey_hardware::error::HardwareError::biometric_capture(
-///             "Low quality capture",
-///         ))
+///     match scanner.enroll_fingerprint().await {
+///         Ok(sample) => {
+///             scanner.set_led(LedColor::Green).await.ok();
+///             Ok(sample.template)
+///         }
+///         Err(e) => {
+///             scanner.set_led(LedColor::Red).await.ok();
+///             Err(e)
+///         }
 ///     }
 /// }
 /// ```
@@ -890,6 +979,29 @@ pub trait BiometricDevice: Send + Sync {
     /// - The device is disconnected
     async fn verify_fingerprint(&mut self, template: &[u8]) -> Result<bool>;
 
+    /// Capture one enrollment sample.
+    ///
+    /// Returns the template with its quality score. Enrolling a finger takes
+    /// several samples (see `turnkey_emulator::enrollment`); each one must
+    /// reach [`DEFAULT_QUALITY_THRESHOLD`]. The default captures once with
+    /// [`capture_fingerprint()`](Self::capture_fingerprint); scanners with a
+    /// dedicated enrollment mode override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BiometricCapture` error if the sample quality is below the
+    /// threshold, or any error of [`capture_fingerprint()`](Self::capture_fingerprint).
+    async fn enroll_fingerprint(&mut self) -> Result<BiometricData> {
+        let sample = self.capture_fingerprint().await?;
+        if !sample.is_quality_acceptable() {
+            return Err(crate::HardwareError::biometric_capture(format!(
+                "Enrollment sample quality {} is below {}",
+                sample.quality, DEFAULT_QUALITY_THRESHOLD
+            )));
+        }
+        Ok(sample)
+    }
+
     /// Get device information.
     ///
     /// Returns metadata about the biometric scanner including name, model,
@@ -912,6 +1024,310 @@ pub trait BiometricDevice: Send + Sync {
     /// - The device does not support LED control
     /// - A communication error occurs
     async fn set_led(&mut self, color: LedColor) -> Result<()>;
+
+    /// Run the device self-test.
+    ///
+    /// The default passes when the device answers [`get_device_info()`](Self::get_device_info).
+    /// Drivers with a built-in test routine override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device cannot be reached. A device that
+    /// answers but fails its test returns a failed [`SelfTestResult`].
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        let info = self.get_device_info().await?;
+        Ok(SelfTestResult::pass(info.name))
+    }
+
+    /// Get firmware details.
+    ///
+    /// The default reports the firmware version from [`get_device_info()`](Self::get_device_info).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unsupported` if the scanner does not report its firmware.
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        firmware_from_info(self.get_device_info().await?)
+    }
+}
+
+/// Event reported by a turnstile sensor.
+///
+/// Sensors are the GPIO inputs of the turnstile mechanism: the arm
+/// rotation switch, the door contact and the enclosure tamper switch.
+/// Real drivers and [`MockSensor`](crate::mock::MockSensor) report the
+/// same events, so the emulator cannot tell them apart.
+///
+/// Events have a textual form (see [`FromStr`](std::str::FromStr)) used by
+/// command-line tools to inject them:
+///
+/// | Text               | Event                                         |
+/// |--------------------|-----------------------------------------------|
+/// | `arm-rotated`      | `ArmRotated(AccessDirection::Undefined)`      |
+/// | `arm-rotated:entry`| `ArmRotated(AccessDirection::Entry)`          |
+/// | `arm-rotated:exit` | `ArmRotated(AccessDirection::Exit)`           |
+/// | `door-forced`      | `DoorForced`                                  |
+/// | `tamper-open`      | `TamperOpened`                                |
+/// | `tamper-closed`    | `TamperClosed`                                |
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_core::AccessDirection;
+/// use turnkey_hardware::traits::SensorEvent;
+///
+/// let event: SensorEvent = "arm-rotated:entry".parse().unwrap();
+/// assert_eq!(event, SensorEvent::ArmRotated(AccessDirection::Entry));
+/// assert_eq!(event.to_string(), "arm-rotated:entry");
+///
+/// assert!("door-opened".parse::<SensorEvent>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SensorEvent {
+    /// The arm completed a rotation in the given direction.
+    ArmRotated(AccessDirection),
+
+    /// The door or arm was moved without a release.
+    DoorForced,
+
+    /// The enclosure tamper switch opened.
+    TamperOpened,
+
+    /// The enclosure tamper switch closed again.
+    TamperClosed,
+}
+
+impl SensorEvent {
+    /// Whether the event signals a security alarm.
+    pub fn is_alarm(self) -> bool {
+        matches!(self, Self::DoorForced | Self::TamperOpened)
+    }
+}
+
+impl std::fmt::Display for SensorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ArmRotated(AccessDirection::Undefined) => write!(f, "arm-rotated"),
+            Self::ArmRotated(AccessDirection::Entry) => write!(f, "arm-rotated:entry"),
+            Self::ArmRotated(AccessDirection::Exit) => write!(f, "arm-rotated:exit"),
+            Self::DoorForced => write!(f, "door-forced"),
+            Self::TamperOpened => write!(f, "tamper-open"),
+            Self::TamperClosed => write!(f, "tamper-closed"),
+        }
+    }
+}
+
+impl std::str::FromStr for SensorEvent {
+    type Err = crate::error::HardwareError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let event = match s.trim().to_ascii_lowercase().as_str() {
+            "arm-rotated" => Self::ArmRotated(AccessDirection::Undefined),
+            "arm-rotated:entry" => Self::ArmRotated(AccessDirection::Entry),
+            "arm-rotated:exit" => Self::ArmRotated(AccessDirection::Exit),
+            "door-forced" => Self::DoorForced,
+            "tamper-open" => Self::TamperOpened,
+            "tamper-closed" => Self::TamperClosed,
+            other => {
+                return Err(crate::error::HardwareError::invalid_data(format!(
+                    "Unknown sensor event '{}'",
+                    other
+                )));
+            }
+        };
+        Ok(event)
+    }
+}
+
+/// Trait for turnstile sensor inputs.
+///
+/// Implemented by GPIO drivers and by [`MockSensor`](crate::mock::MockSensor)
+/// for simulation. The emulator consumes events through this trait only.
+///
+/// # Examples
+///
+/// ```no_run
+/// use turnkey_hardware::traits::{SensorDevice, SensorEvent};
+/// use turnkey_hardware::error::Result;
+///
+/// async fn wait_for_rotation<S: SensorDevice>(sensor: &mut S) -> Result<()> {
+///     loop {
+///         if let SensorEvent::ArmRotated(_) = sensor.read_event().await? {
+///             return Ok(());
+///         }
+///     }
+/// }
+/// ```
+pub trait SensorDevice: Send + Sync {
+    /// Read the next sensor event.
+    ///
+    /// This method blocks asynchronously until a sensor changes state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device is disconnected or a communication
+    /// error occurs.
+    async fn read_event(&mut self) -> Result<SensorEvent>;
+
+    /// Get device information.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a communication error occurs while querying
+    /// device information.
+    async fn get_info(&self) -> Result<DeviceInfo>;
+
+    /// Run the device self-test.
+    ///
+    /// The default passes when the device answers [`get_info()`](Self::get_info).
+    /// Drivers with a built-in test routine override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device cannot be reached. A device that
+    /// answers but fails its test returns a failed [`SelfTestResult`].
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        let info = self.get_info().await?;
+        Ok(SelfTestResult::pass(info.name))
+    }
+
+    /// Get firmware details.
+    ///
+    /// The default reports the firmware version from [`get_info()`](Self::get_info).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unsupported` if the sensor block does not report its firmware.
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        firmware_from_info(self.get_info().await?)
+    }
+}
+
+/// Source of a request-to-exit (REX).
+///
+/// Interior doors and turnstiles on the way out often have a push button or
+/// a motion detector that opens the passage without a credential. Both are
+/// plain contact inputs; the source is kept for the audit trail only.
+///
+/// Requests have a textual form (`button`, `motion`) used by command-line
+/// tools to inject them.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_hardware::traits::RexRequest;
+///
+/// let request: RexRequest = "button".parse().unwrap();
+/// assert_eq!(request, RexRequest::Button);
+/// assert_eq!(RexRequest::Motion.to_string(), "motion");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RexRequest {
+    /// Push-to-exit button pressed.
+    Button,
+
+    /// Motion detector on the exit side triggered.
+    Motion,
+}
+
+impl std::fmt::Display for RexRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Button => write!(f, "button"),
+            Self::Motion => write!(f, "motion"),
+        }
+    }
+}
+
+impl std::str::FromStr for RexRequest {
+    type Err = crate::error::HardwareError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "button" => Ok(Self::Button),
+            "motion" => Ok(Self::Motion),
+            other => Err(crate::error::HardwareError::invalid_data(format!(
+                "Unknown request-to-exit source '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Trait for request-to-exit inputs.
+///
+/// Implemented by GPIO drivers and by [`MockRex`](crate::mock::MockRex)
+/// for simulation. A request bypasses validation entirely; whether it is
+/// honored is up to the emulator's REX configuration.
+///
+/// # Examples
+///
+/// ```no_run
+/// use turnkey_hardware::traits::{RexDevice, RexRequest};
+/// use turnkey_hardware::error::Result;
+///
+/// async fn count_button_presses<R: RexDevice>(rex: &mut R, requests: usize) -> Result<usize> {
+///     let mut presses = 0;
+///     for _ in 0..requests {
+///         if rex.read_request().await? == RexRequest::Button {
+///             presses += 1;
+///         }
+///     }
+///     Ok(presses)
+/// }
+/// ```
+pub trait RexDevice: Send + Sync {
+    /// Wait for the next request-to-exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device is disconnected or a communication
+    /// error occurs.
+    async fn read_request(&mut self) -> Result<RexRequest>;
+
+    /// Get device information.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a communication error occurs while querying
+    /// device information.
+    async fn get_info(&self) -> Result<DeviceInfo>;
+
+    /// Run the device self-test.
+    ///
+    /// The default passes when the device answers [`get_info()`](Self::get_info).
+    /// Drivers with a built-in test routine override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device cannot be reached. A device that
+    /// answers but fails its test returns a failed [`SelfTestResult`].
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        let info = self.get_info().await?;
+        Ok(SelfTestResult::pass(info.name))
+    }
+
+    /// Get firmware details.
+    ///
+    /// The default reports the firmware version from [`get_info()`](Self::get_info).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unsupported` if the input does not report its firmware.
+    async fn firmware_info(&self) -> Result<FirmwareInfo> {
+        firmware_from_info(self.get_info().await?)
+    }
+}
+
+/// Firmware details from the version a device reports in its info.
+fn firmware_from_info(info: DeviceInfo) -> Result<FirmwareInfo> {
+    info.firmware_version
+        .map(FirmwareInfo::new)
+        .ok_or_else(|| HardwareError::unsupported(format!("firmware info for {}", info.name)))
 }
 
 #[cfg(test)]
@@ -963,6 +1379,20 @@ mod tests {
         assert!(!CardType::Unknown(vec![]).is_known());
     }
 
+    #[test]
+    fn test_card_data_correlation_id() {
+        let first = CardData::new(vec![0x04, 0xAB, 0xCD, 0xEF], CardType::MifareClassic1K).unwrap();
+        let second =
+            CardData::new(vec![0x04, 0xAB, 0xCD, 0xEF], CardType::MifareClassic1K).unwrap();
+        assert_ne!(first.correlation_id, second.correlation_id);
+
+        let replayed = CardData::builder(vec![0x04, 0xAB, 0xCD, 0xEF], CardType::MifareClassic1K)
+            .correlation_id(first.correlation_id)
+            .build()
+            .unwrap();
+        assert_eq!(replayed.correlation_id, first.correlation_id);
+    }
+
     #[test]
     fn test_card_data_uid_hex() {
         let card = CardData::new(vec![0x04, 0xAB, 0xCD, 0xEF], CardType::MifareClassic1K).unwrap();
@@ -1040,6 +1470,40 @@ mod tests {
         assert!(BiometricData::new(vec![0u8; 512], 255).is_err());
     }
 
+    #[test]
+    fn test_sensor_event_text_roundtrip() {
+        for event in [
+            SensorEvent::ArmRotated(AccessDirection::Undefined),
+            SensorEvent::ArmRotated(AccessDirection::Entry),
+            SensorEvent::ArmRotated(AccessDirection::Exit),
+            SensorEvent::DoorForced,
+            SensorEvent::TamperOpened,
+            SensorEvent::TamperClosed,
+        ] {
+            assert_eq!(event.to_string().parse::<SensorEvent>().unwrap(), event);
+        }
+
+        assert_eq!(
+            " Door-Forced ".parse::<SensorEvent>().unwrap(),
+            SensorEvent::DoorForced
+        );
+        assert!("arm-rotated:sideways".parse::<SensorEvent>().is_err());
+        assert!(SensorEvent::TamperOpened.is_alarm());
+        assert!(!SensorEvent::TamperClosed.is_alarm());
+    }
+
+    #[test]
+    fn test_rex_request_text_roundtrip() {
+        for request in [RexRequest::Button, RexRequest::Motion] {
+            assert_eq!(request.to_string().parse::<RexRequest>().unwrap(), request);
+        }
+        assert_eq!(
+            " BUTTON ".parse::<RexRequest>().unwrap(),
+            RexRequest::Button
+        );
+        assert!("lever".parse::<RexRequest>().is_err());
+    }
+
     #[test]
     fn test_biometric_builder_quality_validation() {
         // Valid quality via builder
@@ -81,6 +81,117 @@ impl ReaderInfo {
     }
 }
 
+/// Firmware details reported by a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareInfo {
+    /// Firmware version string (e.g., "2.14.0").
+    pub version: String,
+
+    /// Optional build identifier or date.
+    pub build: Option<String>,
+
+    /// Optional bootloader version.
+    pub bootloader: Option<String>,
+}
+
+impl FirmwareInfo {
+    /// Create firmware info with only a version.
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            build: None,
+            bootloader: None,
+        }
+    }
+
+    /// Set the build identifier.
+    pub fn with_build(mut self, build: impl Into<String>) -> Self {
+        self.build = Some(build.into());
+        self
+    }
+
+    /// Set the bootloader version.
+    pub fn with_bootloader(mut self, bootloader: impl Into<String>) -> Self {
+        self.bootloader = Some(bootloader.into());
+        self
+    }
+}
+
+/// Maximum signal strength, in percent.
+pub const MAX_SIGNAL_STRENGTH: u8 = 100;
+
+/// Radio link quality of a reader.
+///
+/// Readers that talk to cards over the air (RFID/NFC) degrade before they
+/// fail: the antenna detunes, the field weakens and reads start to fail.
+/// Strength and the read counters let a technician see that remotely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignalQuality {
+    /// Field strength in percent (0-100).
+    pub strength: u8,
+
+    /// Card reads attempted since the counters were reset.
+    pub reads: u32,
+
+    /// Reads that failed (CRC, collision, timeout).
+    pub read_errors: u32,
+}
+
+impl SignalQuality {
+    /// Create signal quality with no reads yet, capping strength at 100.
+    pub fn new(strength: u8) -> Self {
+        Self {
+            strength: strength.min(MAX_SIGNAL_STRENGTH),
+            reads: 0,
+            read_errors: 0,
+        }
+    }
+
+    /// Set the read counters.
+    pub fn with_reads(mut self, reads: u32, read_errors: u32) -> Self {
+        self.reads = reads;
+        self.read_errors = read_errors.min(reads);
+        self
+    }
+
+    /// Fraction of reads that failed, 0.0 when nothing was read.
+    pub fn error_rate(&self) -> f64 {
+        if self.reads == 0 {
+            0.0
+        } else {
+            f64::from(self.read_errors) / f64::from(self.reads)
+        }
+    }
+}
+
+/// Outcome of a device self-test.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestResult {
+    /// Whether the device passed.
+    pub passed: bool,
+
+    /// What was found, for the technician.
+    pub detail: String,
+}
+
+impl SelfTestResult {
+    /// A passed self-test.
+    pub fn pass(detail: impl Into<String>) -> Self {
+        Self {
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    /// A failed self-test.
+    pub fn fail(detail: impl Into<String>) -> Self {
+        Self {
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
 /// LED colors for visual feedback on devices.
 ///
 /// Many hardware devices have status LEDs that can be controlled
@@ -178,6 +289,17 @@ mod tests {
         assert_eq!(info.max_baud_rate, Some(424000));
     }
 
+    #[test]
+    fn test_signal_quality() {
+        let signal = SignalQuality::new(150).with_reads(200, 5);
+        assert_eq!(signal.strength, MAX_SIGNAL_STRENGTH);
+        assert!((signal.error_rate() - 0.025).abs() < f64::EPSILON);
+
+        // More errors than reads is clamped, no reads is no error
+        assert_eq!(SignalQuality::new(80).with_reads(3, 9).read_errors, 3);
+        assert_eq!(SignalQuality::new(80).error_rate(), 0.0);
+    }
+
     #[test]
     fn test_led_color_rgb() {
         assert_eq!(LedColor::Red.as_rgb(), (255, 0, 0));
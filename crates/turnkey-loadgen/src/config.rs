@@ -0,0 +1,149 @@
+//! Load run configuration
+
+use crate::LoadError;
+use crate::mix::RequestMix;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Parameters of a load run
+///
+/// # Example
+///
+/// ```
+/// use turnkey_loadgen::LoadConfig;
+/// use std::time::Duration;
+///
+/// let config = LoadConfig {
+///     devices: 32,
+///     rate: 500.0,
+///     duration: Duration::from_secs(60),
+///     ..LoadConfig::default()
+/// };
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadConfig {
+    /// Server to load; `None` starts one in-process on a seeded database
+    pub server: Option<SocketAddr>,
+
+    /// Simulated device connections (device IDs 1..=devices)
+    pub devices: u8,
+
+    /// Target requests per second over all devices
+    pub rate: f64,
+
+    /// How long to send requests
+    pub duration: Duration,
+
+    /// How long a device waits for each answer
+    pub response_timeout: Duration,
+
+    /// Kinds of requests sent
+    pub mix: RequestMix,
+
+    /// Registered cards for [`CardKind::Valid`](crate::CardKind::Valid)
+    /// requests; read from the database of the in-process server when empty
+    pub valid_cards: Vec<String>,
+
+    /// Seed for the request mix; equal seeds send equal requests
+    pub seed: u64,
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        Self {
+            server: None,
+            devices: 16,
+            rate: 200.0,
+            duration: Duration::from_secs(30),
+            response_timeout: Duration::from_secs(1),
+            mix: RequestMix::default(),
+            valid_cards: Vec::new(),
+            seed: 0,
+        }
+    }
+}
+
+impl LoadConfig {
+    /// Check the configuration before starting
+    ///
+    /// # Errors
+    ///
+    /// Returns `LoadError::Config` describing the first invalid value.
+    pub fn validate(&self) -> Result<(), LoadError> {
+        if !(1..=99).contains(&self.devices) {
+            return Err(LoadError::Config(format!(
+                "devices must be between 1 and 99, got {}",
+                self.devices
+            )));
+        }
+        if !self.rate.is_finite() || self.rate <= 0.0 {
+            return Err(LoadError::Config(format!(
+                "rate must be a positive number of requests per second, got {}",
+                self.rate
+            )));
+        }
+        if self.duration.is_zero() || self.response_timeout.is_zero() {
+            return Err(LoadError::Config(
+                "duration and response timeout must be greater than zero".to_string(),
+            ));
+        }
+        self.mix.validate()?;
+        // An external server's cards cannot be looked up
+        if self.server.is_some() && self.valid_cards.is_empty() && self.mix.uses_valid_cards() {
+            return Err(LoadError::Config(
+                "valid cards must be listed when loading an external server".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time between two requests of one device at the target rate
+    pub(crate) fn device_interval(&self) -> Duration {
+        Duration::from_secs_f64(f64::from(self.devices) / self.rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(LoadConfig::default().validate().is_ok());
+        assert_eq!(
+            LoadConfig::default().device_interval(),
+            Duration::from_millis(80)
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_values() {
+        let invalid = [
+            LoadConfig {
+                devices: 0,
+                ..LoadConfig::default()
+            },
+            LoadConfig {
+                rate: 0.0,
+                ..LoadConfig::default()
+            },
+            LoadConfig {
+                rate: f64::NAN,
+                ..LoadConfig::default()
+            },
+            LoadConfig {
+                duration: Duration::ZERO,
+                ..LoadConfig::default()
+            },
+            LoadConfig {
+                server: Some("127.0.0.1:3000".parse().unwrap()),
+                ..LoadConfig::default()
+            },
+        ];
+        for config in invalid {
+            assert!(matches!(config.validate(), Err(LoadError::Config(_))));
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! One simulated device connection sending requests at a fixed pace
+
+use crate::mix::RequestMix;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior};
+use turnkey_core::DeviceId;
+use turnkey_network::{TcpClient, TcpClientConfig, TcpClientError};
+use turnkey_protocol::commands::access::AccessRequest;
+use turnkey_protocol::{CommandCode, FieldData, Message, MessageBuilder};
+
+/// Settings shared by every device of a run
+pub(crate) struct DeviceContext {
+    pub(crate) server_addr: SocketAddr,
+    pub(crate) devices: u8,
+    pub(crate) interval: Duration,
+    pub(crate) response_timeout: Duration,
+    pub(crate) mix: RequestMix,
+    pub(crate) valid_cards: Vec<String>,
+    pub(crate) seed: u64,
+    pub(crate) started: Instant,
+    pub(crate) deadline: Instant,
+}
+
+/// What one device saw
+#[derive(Debug, Default)]
+pub(crate) struct DeviceTally {
+    pub(crate) requests: u64,
+    pub(crate) granted: u64,
+    pub(crate) denied: u64,
+    pub(crate) timeouts: u64,
+    pub(crate) errors: u64,
+    pub(crate) latencies: Vec<Duration>,
+    /// Answers received in each second of the run
+    pub(crate) per_second: Vec<u64>,
+}
+
+impl DeviceTally {
+    fn answered(&mut self, at: Instant, started: Instant, latency: Duration) {
+        self.latencies.push(latency);
+        let second = (at - started).as_secs() as usize;
+        if self.per_second.len() <= second {
+            self.per_second.resize(second + 1, 0);
+        }
+        self.per_second[second] += 1;
+    }
+}
+
+/// Send requests from device `index` (0-based) until the deadline
+pub(crate) async fn drive(index: u8, context: Arc<DeviceContext>) -> DeviceTally {
+    let device_id = DeviceId::new(index + 1).expect("device count checked by the config");
+    let mut rng = StdRng::seed_from_u64(context.seed.wrapping_add(u64::from(index)));
+    let mut client = TcpClient::new(TcpClientConfig {
+        server_addr: context.server_addr,
+        timeout: context.response_timeout,
+    });
+    let mut tally = DeviceTally::default();
+
+    // Spread the devices over one interval so requests do not arrive in bursts
+    let offset = context
+        .interval
+        .mul_f64(f64::from(index) / f64::from(context.devices));
+    let mut pace = tokio::time::interval_at(context.started + offset, context.interval);
+    pace.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            _ = pace.tick() => {}
+            _ = tokio::time::sleep_until(context.deadline) => break,
+        }
+        tally.requests += 1;
+
+        if !client.is_connected()
+            && let Err(e) = client.connect().await
+        {
+            tally.errors += 1;
+            tracing::debug!(%device_id, error = %e, "connection failed");
+            continue;
+        }
+
+        let request = context.mix.sample(&mut rng, &context.valid_cards);
+        let Some(message) = request_message(device_id, &request) else {
+            tally.errors += 1;
+            continue;
+        };
+
+        let sent = Instant::now();
+        let result = match client.send(message).await {
+            Ok(()) => client.recv().await,
+            Err(e) => Err(e),
+        };
+        let received = Instant::now();
+
+        match result {
+            Ok(reply) => match reply.command {
+                CommandCode::GrantBoth
+                | CommandCode::GrantEntry
+                | CommandCode::GrantExit
+                | CommandCode::GrantManual
+                | CommandCode::OverrideGrant => {
+                    tally.granted += 1;
+                    tally.answered(received, context.started, received - sent);
+                }
+                CommandCode::DenyAccess | CommandCode::DisplayOnly => {
+                    tally.denied += 1;
+                    tally.answered(received, context.started, received - sent);
+                }
+                other => {
+                    tally.errors += 1;
+                    tracing::debug!(%device_id, command = other.as_str(), "unexpected reply");
+                }
+            },
+            Err(e) => {
+                if matches!(
+                    e,
+                    TcpClientError::ReadTimeout(_) | TcpClientError::ValidationTimeout(_)
+                ) {
+                    tally.timeouts += 1;
+                } else {
+                    tally.errors += 1;
+                }
+                tracing::debug!(%device_id, error = %e, "request failed");
+                // A late answer would be taken for the next request's
+                let _ = client.close().await;
+            }
+        }
+    }
+
+    let _ = client.close().await;
+    tally
+}
+
+fn request_message(device_id: DeviceId, request: &AccessRequest) -> Option<Message> {
+    let mut builder = MessageBuilder::new(device_id, CommandCode::AccessRequest);
+    for field in request.to_fields() {
+        builder = builder.field(FieldData::new(field).ok()?);
+    }
+    builder.build().ok()
+}
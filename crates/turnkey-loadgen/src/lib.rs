@@ -0,0 +1,191 @@
+//! Load generator for the Turnkey validation server
+//!
+//! Opens one connection per simulated device and sends access requests at a
+//! target rate, drawn from a configurable [`RequestMix`] of registered and
+//! unknown cards, directions and reader types. Every request waits for its
+//! answer before the device sends the next one, like a real turnstile.
+//!
+//! # Setup
+//!
+//! - **Server:** the address given in [`LoadConfig::server`], or a
+//!   [`TcpServer`](turnkey_network::TcpServer) on a loopback port answered
+//!   by a [`ServerValidationLoop`] over a SQLite file in the temp directory
+//!   (migrated and seeded).
+//! - **Devices:** one task per device ID, each pacing its requests so the
+//!   fleet together reaches [`LoadConfig::rate`]. A device that falls behind
+//!   skips ticks instead of bursting.
+//!
+//! # Report
+//!
+//! [`LoadReport`] holds latency percentiles, grant/deny counts, timeouts
+//! and errors, and the answers received in each second of the run. A
+//! timed-out device reconnects before its next request, so a late answer is
+//! never counted for the wrong request.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use turnkey_loadgen::{LoadConfig, run};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), turnkey_loadgen::LoadError> {
+//! let config = LoadConfig {
+//!     devices: 50,
+//!     rate: 1000.0,
+//!     duration: Duration::from_secs(60),
+//!     ..LoadConfig::default()
+//! };
+//! let report = run(config).await?;
+//! println!("p99 {:?}, {:.2}% errors", report.latency.p99, report.error_rate() * 100.0);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod config;
+mod device;
+pub mod mix;
+pub mod report;
+
+pub use config::LoadConfig;
+pub use mix::{CardKind, RequestMix};
+pub use report::LoadReport;
+
+use crate::device::DeviceContext;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::task::JoinSet;
+use tracing::info;
+use turnkey_network::{CancellationToken, TcpServer, TcpServerConfig, TcpServerError};
+use turnkey_storage::{Database, DatabaseConfig, ServerValidationLoop, StorageError};
+
+/// Connections allowed beyond the devices, for reconnects after timeouts
+const SPARE_CONNECTIONS: usize = 16;
+
+/// Load run errors
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Invalid load configuration: {0}")]
+    Config(String),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+
+    #[error("Server error: {0}")]
+    Server(#[from] TcpServerError),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Send requests for `config.duration` and report what came back
+///
+/// Starts an in-process server when `config.server` is `None`.
+///
+/// # Errors
+///
+/// Returns `LoadError::Config` for an invalid configuration, or the setup
+/// error if the in-process server could not start. Failed requests are
+/// counted in the report, not returned.
+pub async fn run(mut config: LoadConfig) -> Result<LoadReport, LoadError> {
+    config.validate()?;
+    if let Some(server_addr) = config.server {
+        return drive_devices(&config, server_addr).await;
+    }
+
+    let database_path = std::env::temp_dir().join(format!(
+        "turnkey-loadgen-{}-{}.db",
+        std::process::id(),
+        config.seed
+    ));
+    remove_database(&database_path);
+    let db = Database::new(DatabaseConfig::new(database_path.to_string_lossy())).await?;
+    let result = run_embedded(&mut config, &db).await;
+    db.close().await;
+    remove_database(&database_path);
+    result
+}
+
+async fn run_embedded(config: &mut LoadConfig, db: &Database) -> Result<LoadReport, LoadError> {
+    if config.valid_cards.is_empty() {
+        config.valid_cards = sqlx::query_scalar("SELECT numero_cartao FROM cards")
+            .fetch_all(db.pool())
+            .await
+            .map_err(StorageError::from)?;
+        if config.valid_cards.is_empty() && config.mix.uses_valid_cards() {
+            return Err(LoadError::Config(
+                "the database has no cards to send".to_string(),
+            ));
+        }
+    }
+
+    let server = TcpServer::bind(TcpServerConfig {
+        bind_addr: "127.0.0.1:0".parse().expect("valid loopback address"),
+        max_connections: usize::from(config.devices) + SPARE_CONNECTIONS,
+    })
+    .await?;
+    let server_addr = server.local_addr()?;
+
+    let stop = CancellationToken::new();
+    let mut validation = ServerValidationLoop::new(server, db.pool().clone());
+    let server_task = tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            validation.run(stop).await;
+            validation.stats()
+        }
+    });
+
+    let report = drive_devices(config, server_addr).await;
+    stop.cancel();
+    let stats = server_task.await.ok();
+    report.map(|report| LoadReport {
+        server: stats,
+        ..report
+    })
+}
+
+async fn drive_devices(
+    config: &LoadConfig,
+    server_addr: std::net::SocketAddr,
+) -> Result<LoadReport, LoadError> {
+    let started = tokio::time::Instant::now();
+    let context = Arc::new(DeviceContext {
+        server_addr,
+        devices: config.devices,
+        interval: config.device_interval(),
+        response_timeout: config.response_timeout,
+        mix: config.mix.clone(),
+        valid_cards: config.valid_cards.clone(),
+        seed: config.seed,
+        started,
+        deadline: started + config.duration,
+    });
+    info!(
+        devices = config.devices,
+        rate = config.rate,
+        %server_addr,
+        "Load run started"
+    );
+
+    let mut devices = JoinSet::new();
+    for index in 0..config.devices {
+        devices.spawn(device::drive(index, context.clone()));
+    }
+    let mut tallies = Vec::with_capacity(usize::from(config.devices));
+    while let Some(joined) = devices.join_next().await {
+        match joined {
+            Ok(tally) => tallies.push(tally),
+            Err(e) => return Err(LoadError::Io(std::io::Error::other(e))),
+        }
+    }
+
+    Ok(LoadReport::from_tallies(started.elapsed(), tallies))
+}
+
+fn remove_database(path: &std::path::Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
+    }
+}
@@ -0,0 +1,149 @@
+//! `turnkey-loadgen` - load the validation server with simulated devices
+//!
+//! ```text
+//! turnkey-loadgen --devices 50 --rate 1000 --duration 60s
+//! turnkey-loadgen --server 10.0.0.5:3000 --card 00000000000022823433 --cards valid=9,unknown=1
+//! ```
+//!
+//! Without `--server`, starts a server in-process on a seeded database.
+//! Exits with status 1 when the error rate exceeds `--max-error-rate`.
+
+use clap::Parser;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::time::Duration;
+use turnkey_loadgen::{LoadConfig, LoadError, RequestMix};
+use turnkey_soak::parse_duration;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "turnkey-loadgen",
+    about = "Load test the validation server with simulated devices"
+)]
+struct Args {
+    /// Server to load (in-process server if omitted)
+    #[arg(long)]
+    server: Option<SocketAddr>,
+
+    /// Number of simulated devices (1-99)
+    #[arg(long, default_value_t = 16)]
+    devices: u8,
+
+    /// Target requests per second over all devices
+    #[arg(long, default_value_t = 200.0)]
+    rate: f64,
+
+    /// Run time (e.g. 90s, 30m)
+    #[arg(long, default_value = "30s", value_parser = parse_duration)]
+    duration: Duration,
+
+    /// Time each device waits for an answer
+    #[arg(long, default_value = "1s", value_parser = parse_duration)]
+    timeout: Duration,
+
+    /// Card weights (valid=N,unknown=N)
+    #[arg(long)]
+    cards: Option<String>,
+
+    /// Direction weights (entry=N,exit=N,undefined=N)
+    #[arg(long)]
+    directions: Option<String>,
+
+    /// Reader weights (rfid=N,biometric=N,keypad=N)
+    #[arg(long)]
+    readers: Option<String>,
+
+    /// Registered card number to send (repeatable; read from the database
+    /// of the in-process server if omitted)
+    #[arg(long = "card")]
+    valid_cards: Vec<String>,
+
+    /// Seed for the request mix (random if omitted)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Highest error rate, timeouts included, that still passes (0.0-1.0)
+    #[arg(long, default_value_t = 0.01)]
+    max_error_rate: f64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+fn mix(args: &Args) -> Result<RequestMix, LoadError> {
+    let mut mix = RequestMix::default();
+    if let Some(spec) = &args.cards {
+        mix.set_cards(spec)?;
+    }
+    if let Some(spec) = &args.directions {
+        mix.set_directions(spec)?;
+    }
+    if let Some(spec) = &args.readers {
+        mix.set_readers(spec)?;
+    }
+    Ok(mix)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+    let mix = match mix(&args) {
+        Ok(mix) => mix,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let config = LoadConfig {
+        server: args.server,
+        devices: args.devices,
+        rate: args.rate,
+        duration: args.duration,
+        response_timeout: args.timeout,
+        mix,
+        valid_cards: args.valid_cards,
+        seed: args.seed.unwrap_or_else(rand::random),
+    };
+    if !args.json {
+        println!(
+            "Load: {} devices at {} requests/s for {:?}, seed {}",
+            config.devices, config.rate, config.duration, config.seed
+        );
+    }
+
+    match turnkey_loadgen::run(config).await {
+        Ok(report) => {
+            if args.json {
+                match serde_json::to_string_pretty(&report) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        return ExitCode::from(2);
+                    }
+                }
+            } else {
+                print!("{}", report);
+            }
+            if report.error_rate() > args.max_error_rate {
+                eprintln!(
+                    "FAILED: error rate {:.2}% above {:.2}%",
+                    report.error_rate() * 100.0,
+                    args.max_error_rate * 100.0
+                );
+                return ExitCode::FAILURE;
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
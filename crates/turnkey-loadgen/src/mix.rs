@@ -0,0 +1,236 @@
+//! Weighted mix of generated access requests
+
+use crate::LoadError;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
+use turnkey_protocol::commands::access::AccessRequest;
+
+/// Kind of card number put in a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardKind {
+    /// A card registered in the server database
+    Valid,
+
+    /// A random number no one registered, denied by the server
+    Unknown,
+}
+
+/// Share of each kind of request, as relative weights
+///
+/// Card kind, direction and reader type are drawn independently.
+///
+/// # Example
+///
+/// ```
+/// use turnkey_loadgen::RequestMix;
+///
+/// let mut mix = RequestMix::default();
+/// mix.set_cards("valid=90,unknown=10").unwrap();
+/// mix.set_directions("entry=1,exit=1").unwrap();
+/// mix.set_readers("rfid=8,keypad=1,biometric=1").unwrap();
+/// assert!(mix.set_readers("wiegand=1").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestMix {
+    /// Registered versus unknown cards (default: 80/20)
+    pub cards: Vec<(CardKind, u32)>,
+
+    /// Access directions (default: entry and exit, 50/50)
+    pub directions: Vec<(AccessDirection, u32)>,
+
+    /// Reader types (default: RFID only)
+    pub readers: Vec<(ReaderType, u32)>,
+}
+
+impl Default for RequestMix {
+    fn default() -> Self {
+        Self {
+            cards: vec![(CardKind::Valid, 80), (CardKind::Unknown, 20)],
+            directions: vec![(AccessDirection::Entry, 50), (AccessDirection::Exit, 50)],
+            readers: vec![(ReaderType::Rfid, 100)],
+        }
+    }
+}
+
+const CARD_NAMES: [(&str, CardKind); 2] =
+    [("valid", CardKind::Valid), ("unknown", CardKind::Unknown)];
+
+const DIRECTION_NAMES: [(&str, AccessDirection); 3] = [
+    ("entry", AccessDirection::Entry),
+    ("exit", AccessDirection::Exit),
+    ("undefined", AccessDirection::Undefined),
+];
+
+const READER_NAMES: [(&str, ReaderType); 3] = [
+    ("rfid", ReaderType::Rfid),
+    ("biometric", ReaderType::Biometric),
+    ("keypad", ReaderType::Keypad),
+];
+
+impl RequestMix {
+    /// Set the card weights from `valid=N,unknown=N`
+    ///
+    /// # Errors
+    ///
+    /// Returns `LoadError::Config` for unknown names or invalid weights.
+    pub fn set_cards(&mut self, spec: &str) -> Result<(), LoadError> {
+        self.cards = parse_weights(spec, &CARD_NAMES)?;
+        Ok(())
+    }
+
+    /// Set the direction weights from `entry=N,exit=N,undefined=N`
+    ///
+    /// # Errors
+    ///
+    /// Returns `LoadError::Config` for unknown names or invalid weights.
+    pub fn set_directions(&mut self, spec: &str) -> Result<(), LoadError> {
+        self.directions = parse_weights(spec, &DIRECTION_NAMES)?;
+        Ok(())
+    }
+
+    /// Set the reader weights from `rfid=N,biometric=N,keypad=N`
+    ///
+    /// # Errors
+    ///
+    /// Returns `LoadError::Config` for unknown names or invalid weights.
+    pub fn set_readers(&mut self, spec: &str) -> Result<(), LoadError> {
+        self.readers = parse_weights(spec, &READER_NAMES)?;
+        Ok(())
+    }
+
+    /// Whether some requests use registered cards
+    pub fn uses_valid_cards(&self) -> bool {
+        self.cards
+            .iter()
+            .any(|&(kind, weight)| kind == CardKind::Valid && weight > 0)
+    }
+
+    /// Check that every category can be drawn from
+    ///
+    /// # Errors
+    ///
+    /// Returns `LoadError::Config` if a category has no positive weight.
+    pub fn validate(&self) -> Result<(), LoadError> {
+        for (name, total) in [
+            ("card", total(&self.cards)),
+            ("direction", total(&self.directions)),
+            ("reader", total(&self.readers)),
+        ] {
+            if total == 0 {
+                return Err(LoadError::Config(format!(
+                    "{} mix needs at least one positive weight",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw one request
+    ///
+    /// `valid_cards` must not be empty when the mix uses valid cards.
+    pub(crate) fn sample(&self, rng: &mut impl Rng, valid_cards: &[String]) -> AccessRequest {
+        let card_number = match pick(&self.cards, rng) {
+            CardKind::Valid => valid_cards[rng.gen_range(0..valid_cards.len())].clone(),
+            // 12 digits starting with 9: outside the ranges sites issue
+            CardKind::Unknown => format!("9{:011}", rng.gen_range(0..100_000_000_000u64)),
+        };
+        AccessRequest::new(
+            card_number,
+            HenryTimestamp::now(),
+            pick(&self.directions, rng),
+            pick(&self.readers, rng),
+        )
+        .expect("generated card numbers are within length limits")
+    }
+}
+
+fn total<T>(weights: &[(T, u32)]) -> u64 {
+    weights.iter().map(|&(_, weight)| u64::from(weight)).sum()
+}
+
+fn pick<T: Copy>(weights: &[(T, u32)], rng: &mut impl Rng) -> T {
+    let mut point = rng.gen_range(0..total(weights));
+    for &(value, weight) in weights {
+        if point < u64::from(weight) {
+            return value;
+        }
+        point -= u64::from(weight);
+    }
+    unreachable!("point is below the total weight")
+}
+
+fn parse_weights<T: Copy>(spec: &str, names: &[(&str, T)]) -> Result<Vec<(T, u32)>, LoadError> {
+    spec.split(',')
+        .map(|entry| {
+            let (name, weight) = entry.split_once('=').ok_or_else(|| {
+                LoadError::Config(format!("expected name=weight, got '{}'", entry))
+            })?;
+            let value = names
+                .iter()
+                .find(|(known, _)| *known == name.trim())
+                .map(|&(_, value)| value)
+                .ok_or_else(|| {
+                    let known: Vec<_> = names.iter().map(|(known, _)| *known).collect();
+                    LoadError::Config(format!(
+                        "unknown name '{}' (use {})",
+                        name.trim(),
+                        known.join(", ")
+                    ))
+                })?;
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| LoadError::Config(format!("invalid weight in '{}'", entry)))?;
+            Ok((value, weight))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_parse_weights() {
+        let mut mix = RequestMix::default();
+        mix.set_directions("entry=3, exit=1").unwrap();
+        assert_eq!(
+            mix.directions,
+            vec![(AccessDirection::Entry, 3), (AccessDirection::Exit, 1)]
+        );
+
+        assert!(mix.set_cards("valid").is_err());
+        assert!(mix.set_cards("valid=-1").is_err());
+        assert!(mix.set_cards("stolen=1").is_err());
+
+        mix.set_cards("valid=0,unknown=0").unwrap();
+        assert!(matches!(mix.validate(), Err(LoadError::Config(_))));
+    }
+
+    #[test]
+    fn test_sample_follows_weights() {
+        let mut mix = RequestMix::default();
+        mix.set_cards("valid=1,unknown=0").unwrap();
+        mix.set_readers("keypad=1").unwrap();
+        let cards = vec!["12345678".to_string()];
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut entries = 0;
+        for _ in 0..1000 {
+            let request = mix.sample(&mut rng, &cards);
+            assert_eq!(request.card_number(), "12345678");
+            assert_eq!(request.reader_type(), ReaderType::Keypad);
+            entries += usize::from(request.is_entry());
+        }
+        assert!((400..600).contains(&entries), "{entries} entries");
+
+        mix.set_cards("unknown=1").unwrap();
+        let request = mix.sample(&mut rng, &[]);
+        assert_eq!(request.card_number().len(), 12);
+        assert!(!mix.uses_valid_cards());
+    }
+}
@@ -0,0 +1,159 @@
+//! Results of a load run
+
+use crate::device::DeviceTally;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use turnkey_core::latency::LatencyStats;
+use turnkey_storage::ServerLoopStats;
+
+/// Counters, latency percentiles and throughput of a load run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// Time requests were sent for
+    pub elapsed: Duration,
+
+    /// Requests attempted, including those whose connection failed
+    pub requests: u64,
+
+    /// Requests answered with a grant
+    pub granted: u64,
+
+    /// Requests answered with a denial or a message
+    pub denied: u64,
+
+    /// Requests left without an answer within the response timeout
+    pub timeouts: u64,
+
+    /// Connection, send and receive failures and unexpected replies
+    pub errors: u64,
+
+    /// Time from sending a request to receiving its answer
+    pub latency: LatencyStats,
+
+    /// Answers received in each second of the run
+    pub throughput: Vec<u64>,
+
+    /// Counters of the in-process server, when the run started one
+    pub server: Option<ServerLoopStats>,
+}
+
+impl LoadReport {
+    pub(crate) fn from_tallies(elapsed: Duration, tallies: Vec<DeviceTally>) -> Self {
+        let mut report = Self {
+            elapsed,
+            ..Self::default()
+        };
+        let mut latencies = Vec::new();
+        for tally in tallies {
+            report.requests += tally.requests;
+            report.granted += tally.granted;
+            report.denied += tally.denied;
+            report.timeouts += tally.timeouts;
+            report.errors += tally.errors;
+            latencies.extend(tally.latencies);
+            if report.throughput.len() < tally.per_second.len() {
+                report.throughput.resize(tally.per_second.len(), 0);
+            }
+            for (second, count) in tally.per_second.into_iter().enumerate() {
+                report.throughput[second] += count;
+            }
+        }
+        report.latency = LatencyStats::from_samples(latencies);
+        report
+    }
+
+    /// Requests that got an answer
+    pub fn answered(&self) -> u64 {
+        self.granted + self.denied
+    }
+
+    /// Share of requests that timed out or failed, from 0.0 to 1.0
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        (self.timeouts + self.errors) as f64 / self.requests as f64
+    }
+
+    /// Answers per second over the whole run
+    pub fn answer_rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.answered() as f64 / secs
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Ran for {:?}", self.elapsed)?;
+        writeln!(
+            f,
+            "  requests:   {} ({} granted, {} denied)",
+            self.requests, self.granted, self.denied
+        )?;
+        writeln!(
+            f,
+            "  failures:   {} timeouts, {} errors ({:.2}%)",
+            self.timeouts,
+            self.errors,
+            self.error_rate() * 100.0
+        )?;
+        writeln!(
+            f,
+            "  latency:    p50 {:?}, p95 {:?}, p99 {:?}, max {:?}",
+            self.latency.p50, self.latency.p95, self.latency.p99, self.latency.max
+        )?;
+        writeln!(f, "  throughput: {:.1} answers/s", self.answer_rate())?;
+        let seconds: Vec<String> = self.throughput.iter().map(u64::to_string).collect();
+        writeln!(f, "  per second: {}", seconds.join(" "))?;
+        if let Some(server) = &self.server {
+            writeln!(
+                f,
+                "  server:     {} requests, {} responses, {} cancelled, {} errors",
+                server.requests, server.responses, server.cancelled, server.errors
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tallies_merged() {
+        let tallies = vec![
+            DeviceTally {
+                requests: 4,
+                granted: 2,
+                denied: 1,
+                timeouts: 1,
+                latencies: vec![Duration::from_millis(10), Duration::from_millis(30)],
+                per_second: vec![2, 1],
+                ..DeviceTally::default()
+            },
+            DeviceTally {
+                requests: 2,
+                granted: 1,
+                errors: 1,
+                latencies: vec![Duration::from_millis(20)],
+                per_second: vec![0, 0, 1],
+                ..DeviceTally::default()
+            },
+        ];
+
+        let report = LoadReport::from_tallies(Duration::from_secs(3), tallies);
+
+        assert_eq!(report.requests, 6);
+        assert_eq!(report.answered(), 4);
+        assert_eq!(report.throughput, vec![2, 1, 1]);
+        assert_eq!(report.latency.p50, Duration::from_millis(20));
+        assert_eq!(report.latency.max, Duration::from_millis(30));
+        assert!((report.error_rate() - 2.0 / 6.0).abs() < 1e-9);
+        assert!(report.to_string().contains("per second: 2 1 1"));
+    }
+}
@@ -0,0 +1,67 @@
+//! Short load runs against the in-process server
+
+use std::time::Duration;
+use turnkey_loadgen::{LoadConfig, LoadError, RequestMix, run};
+
+#[tokio::test]
+async fn test_embedded_run_answers_every_request() {
+    let mut mix = RequestMix::default();
+    mix.set_cards("valid=3,unknown=1").unwrap();
+    let config = LoadConfig {
+        devices: 4,
+        rate: 40.0,
+        duration: Duration::from_millis(1500),
+        response_timeout: Duration::from_secs(2),
+        mix,
+        seed: 7,
+        ..LoadConfig::default()
+    };
+
+    let report = run(config).await.unwrap();
+
+    assert!(report.requests >= 40, "{report}");
+    assert_eq!(report.timeouts + report.errors, 0, "{report}");
+    assert_eq!(report.answered(), report.requests);
+    assert!(report.granted > 0 && report.denied > 0, "{report}");
+    assert!(report.latency.p50 > Duration::ZERO);
+    assert!(report.latency.p50 <= report.latency.p99);
+    assert_eq!(report.throughput.iter().sum::<u64>(), report.answered());
+
+    let server = report.server.expect("in-process server stats");
+    assert_eq!(server.responses, report.answered());
+}
+
+#[tokio::test]
+async fn test_unreachable_server_counts_errors() {
+    // Bind and drop a listener to get a port nobody listens on
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let mut mix = RequestMix::default();
+    mix.set_cards("unknown=1").unwrap();
+    let config = LoadConfig {
+        server: Some(addr),
+        devices: 2,
+        rate: 20.0,
+        duration: Duration::from_millis(500),
+        mix,
+        ..LoadConfig::default()
+    };
+
+    let report = run(config).await.unwrap();
+
+    assert!(report.requests > 0);
+    assert_eq!(report.answered(), 0);
+    assert_eq!(report.error_rate(), 1.0);
+    assert!(report.server.is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_config_rejected() {
+    let config = LoadConfig {
+        devices: 100,
+        ..LoadConfig::default()
+    };
+    assert!(matches!(run(config).await, Err(LoadError::Config(_))));
+}
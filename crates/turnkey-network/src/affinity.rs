@@ -0,0 +1,29 @@
+//! Device affinity between servers sharing one database.
+//!
+//! When two validation servers run side by side, a device must talk to one
+//! of them at a time: two servers deciding for the same turnstile would
+//! race on anti-passback and presence. A [`DeviceAffinity`] installed with
+//! [`TcpServer::set_device_affinity()`](crate::TcpServer::set_device_affinity)
+//! is asked to claim every device before its connection is accepted, and
+//! told when the connection goes away.
+//!
+//! A refused device is disconnected like any other rejected connection; it
+//! reconnects (possibly to the other server) on its usual retry schedule.
+//! The claim bookkeeping itself lives in the backend, e.g. the lease table
+//! of `turnkey_storage::cluster::ClusterNode`.
+
+use futures::future::BoxFuture;
+use turnkey_core::DeviceId;
+
+/// Decides which server a device may connect to
+pub trait DeviceAffinity: Send + Sync {
+    /// Claim a device for this server
+    ///
+    /// Returns `false` if another server holds the device. Implementations
+    /// should also return `false` when the claim cannot be checked, so a
+    /// device is never served by two servers.
+    fn claim(&self, device_id: DeviceId) -> BoxFuture<'_, bool>;
+
+    /// Give up a device whose connection closed
+    fn release(&self, device_id: DeviceId) -> BoxFuture<'_, ()>;
+}
@@ -55,8 +55,9 @@
 //!
 //! The TcpClient is designed as a simple transport layer:
 //! - **No automatic retry**: Caller decides retry strategy
-//! - **No connection pooling**: Single connection per turnstile
-//! - **No keepalive**: Short-lived connections
+//! - **No connection pooling**: Single connection per client; readers that
+//!   should share one connection use [`SharedTcpClient`](crate::SharedTcpClient)
+//! - **Keepalive on request**: Only when a heartbeat is enabled (see below)
 //! - **Simple error handling**: Clear errors, no recovery
 //!
 //! This keeps the client focused and testable, pushing business logic
@@ -71,23 +72,53 @@
 //!
 //! Timeout errors are returned to the caller for appropriate handling.
 //!
+//! `request()` sends a command and waits for the answer under the timeout of
+//! its [`CommandClass`](crate::CommandClass) instead (validation 3s, sync
+//! 60s, display 1s by default, see `set_command_timeouts()`). Running out
+//! of it returns `ValidationTimeout`, `SyncTimeout` or `DisplayTimeout`, so
+//! callers can tell a slow decision from a slow transfer.
+//!
+//! # Compression
+//!
+//! `set_compression()` makes the client offer zlib compression of bulk
+//! transfers on each new connection. It is used only if the server offers
+//! it back; see `turnkey_protocol::compression`.
+//!
+//! # Heartbeat
+//!
+//! `enable_heartbeat()` makes the client probe an idle connection: once
+//! nothing has been heard from the server for the heartbeat interval,
+//! `heartbeat()` sends a status query and reports whether the server
+//! answered. The caller drives the schedule, sleeping until
+//! `heartbeat_due()`; see [`heartbeat`](crate::heartbeat).
+//!
 //! # Related
 //!
 //! - Issue #65: TCP Client implementation
 //! - See `docs/tcp-client-detailed-spec.md` for complete specification
 
+use crate::config::ConfigError;
+use crate::heartbeat::{self, HealthEvent, HealthEventKind, Heartbeat};
+use crate::timeouts::{CommandClass, CommandTimeouts};
+use chrono::Utc;
 use futures::{SinkExt, StreamExt};
+use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::time::Duration;
 use thiserror::Error;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
+use tokio::time::Instant;
 use tokio_util::codec::Framed;
 use tracing::{debug, error, info, trace, warn};
-use turnkey_protocol::{HenryCodec, Message};
+use turnkey_core::DeviceId;
+use turnkey_protocol::{CompressionConfig, HenryCodec, Message};
 
 /// Configuration for TCP client
 ///
+/// Fields are public so the struct can be written as a literal; use
+/// [`TcpClientConfig::builder()`] to get bounds checking and host name resolution.
+///
 /// # Example
 ///
 /// ```
@@ -136,6 +167,18 @@ pub enum TcpClientError {
     #[error("Write timeout after {0}ms")]
     WriteTimeout(u64),
 
+    /// No answer to a validation command within its class timeout
+    #[error("Validation response timeout after {0}ms")]
+    ValidationTimeout(u64),
+
+    /// No answer to a sync transfer within its class timeout
+    #[error("Sync response timeout after {0}ms")]
+    SyncTimeout(u64),
+
+    /// No answer to a display command within its class timeout
+    #[error("Display response timeout after {0}ms")]
+    DisplayTimeout(u64),
+
     /// Connection was lost during operation
     #[error("Connection lost: {0}")]
     ConnectionLost(String),
@@ -151,6 +194,10 @@ pub enum TcpClientError {
     /// Codec error during message encoding/decoding
     #[error("Codec error: {0}")]
     Codec(String),
+
+    /// Configuration rejected by `TcpClientConfig::validate()`
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(#[from] ConfigError),
 }
 
 /// TCP client for Henry protocol communication
@@ -168,8 +215,10 @@ pub enum TcpClientError {
 ///
 /// # Thread Safety
 ///
-/// `TcpClient` is not `Send` or `Sync` by design. Each turnstile emulator
-/// should have its own client instance on a single task.
+/// `TcpClient` is not `Sync` by design: `send()` and `recv()` take `&mut self`
+/// and each client is owned by a single task. To let several readers of one
+/// turnstile use the same connection, wrap it in a
+/// [`SharedTcpClient`](crate::SharedTcpClient).
 ///
 /// # Example
 ///
@@ -203,6 +252,21 @@ pub struct TcpClient {
 
     /// Timeout for all I/O operations
     timeout: Duration,
+
+    /// Compression offered on new connections (None disables it)
+    compression: Option<CompressionConfig>,
+
+    /// Response timeouts used by `request()`
+    command_timeouts: CommandTimeouts,
+
+    /// Device the keepalives are sent for, and their schedule
+    heartbeat: Option<(DeviceId, Heartbeat)>,
+
+    /// When the server was last heard from, or the last keepalive sent
+    last_heard: Instant,
+
+    /// Messages received while waiting for a keepalive answer
+    pending: VecDeque<Message>,
 }
 
 impl TcpClient {
@@ -226,6 +290,11 @@ impl TcpClient {
             server_addr: config.server_addr,
             framed: None,
             timeout: config.timeout,
+            compression: None,
+            command_timeouts: CommandTimeouts::default(),
+            heartbeat: None,
+            last_heard: Instant::now(),
+            pending: VecDeque::new(),
         }
     }
 
@@ -240,7 +309,7 @@ impl TcpClient {
     /// - Connection times out
     /// - Server refuses connection
     /// - Network is unreachable
-    /// - Invalid address
+    /// - Configuration is invalid (see `TcpClientConfig::validate()`)
     ///
     /// # Example
     ///
@@ -257,6 +326,12 @@ impl TcpClient {
     pub async fn connect(&mut self) -> Result<(), TcpClientError> {
         info!("Connecting to server at {}", self.server_addr);
 
+        TcpClientConfig {
+            server_addr: self.server_addr,
+            timeout: self.timeout,
+        }
+        .validate()?;
+
         // Attempt connection with timeout
         let stream =
             match tokio::time::timeout(self.timeout, TcpStream::connect(self.server_addr)).await {
@@ -285,7 +360,11 @@ impl TcpClient {
         }
 
         // Wrap stream with HenryCodec for automatic framing
-        self.framed = Some(Framed::new(stream, HenryCodec::new()));
+        let mut codec = HenryCodec::new();
+        codec.set_compression(self.compression);
+        self.framed = Some(Framed::new(stream, codec));
+        self.last_heard = Instant::now();
+        self.pending.clear();
 
         debug!("Client connected and ready");
         Ok(())
@@ -327,6 +406,7 @@ impl TcpClient {
         trace!(
             device_id = %message.device_id,
             command = ?message.command,
+            correlation_id = message.correlation_id().map(tracing::field::display),
             field_count = message.fields.len(),
             "Sending message to server"
         );
@@ -379,36 +459,217 @@ impl TcpClient {
     /// # }
     /// ```
     pub async fn recv(&mut self) -> Result<Message, TcpClientError> {
+        let limit = self.timeout;
+        self.recv_within(limit).await.map_err(|e| match e {
+            RecvError::Timeout => {
+                warn!("Receive timeout after {}ms", limit.as_millis());
+                TcpClientError::ReadTimeout(limit.as_millis() as u64)
+            }
+            RecvError::Failed(e) => e,
+        })
+    }
+
+    /// Send a command and wait for its answer under the command's class timeout
+    ///
+    /// The send uses the socket-level timeout; the wait for the answer uses
+    /// the timeout of the message's [`CommandClass`] from
+    /// [`set_command_timeouts()`](Self::set_command_timeouts).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidationTimeout`, `SyncTimeout` or `DisplayTimeout` if the
+    /// answer does not arrive in time, and the errors of `send()` and
+    /// `recv()` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpClient, TcpClientConfig, TcpClientError};
+    /// use turnkey_protocol::{CommandCode, MessageBuilder};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = TcpClient::new(TcpClientConfig::default());
+    /// client.connect().await?;
+    ///
+    /// let message = MessageBuilder::new(DeviceId::new(15)?, CommandCode::AccessRequest).build()?;
+    /// match client.request(message).await {
+    ///     Ok(response) => println!("Decision: {:?}", response.command),
+    ///     Err(TcpClientError::ValidationTimeout(ms)) => println!("No decision after {}ms", ms),
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request(&mut self, message: Message) -> Result<Message, TcpClientError> {
+        let class = CommandClass::of(message.command);
+        let limit = self.command_timeouts.for_class(class);
+
+        self.send(message).await?;
+        self.recv_within(limit).await.map_err(|e| match e {
+            RecvError::Timeout => {
+                let millis = limit.as_millis() as u64;
+                warn!(class = %class, "Response timeout after {}ms", millis);
+                match class {
+                    CommandClass::Validation => TcpClientError::ValidationTimeout(millis),
+                    CommandClass::Sync => TcpClientError::SyncTimeout(millis),
+                    CommandClass::Display => TcpClientError::DisplayTimeout(millis),
+                }
+            }
+            RecvError::Failed(e) => e,
+        })
+    }
+
+    /// Set the response timeouts used by [`request()`](Self::request)
+    pub fn set_command_timeouts(&mut self, timeouts: CommandTimeouts) {
+        self.command_timeouts = timeouts;
+    }
+
+    /// Response timeouts used by [`request()`](Self::request)
+    pub fn command_timeouts(&self) -> CommandTimeouts {
+        self.command_timeouts
+    }
+
+    /// Send keepalives for `device_id` on the given schedule
+    ///
+    /// Replaces a previous schedule. Keepalives are only sent by
+    /// [`heartbeat()`](Self::heartbeat).
+    pub fn enable_heartbeat(&mut self, device_id: DeviceId, heartbeat: Heartbeat) {
+        self.heartbeat = Some((device_id, heartbeat));
+    }
+
+    /// Stop sending keepalives
+    pub fn disable_heartbeat(&mut self) {
+        self.heartbeat = None;
+    }
+
+    /// Time at which [`heartbeat()`](Self::heartbeat) will send a keepalive
+    ///
+    /// Moves forward whenever a message arrives from the server. Returns
+    /// `None` if no heartbeat is enabled or the client is not connected.
+    pub fn heartbeat_due(&self) -> Option<Instant> {
+        let (_, heartbeat) = self.heartbeat?;
+        self.framed.as_ref()?;
+        Some(self.last_heard + heartbeat.interval())
+    }
+
+    /// Probe the connection if the server has been silent for the interval
+    ///
+    /// Sends a keepalive and waits for the next message from the server, up
+    /// to the socket-level timeout. Any message counts as an answer; ones
+    /// other than the keepalive answer are kept for `recv()`. Returns
+    /// `None` if no heartbeat is enabled or none is due yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotConnected` if the client is not connected, and the
+    /// errors of `send()` and `recv()` other than timeouts, which are
+    /// reported as [`HealthEventKind::Missed`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{HealthEventKind, Heartbeat, TcpClient, TcpClientConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = TcpClient::new(TcpClientConfig::default());
+    /// client.connect().await?;
+    /// client.enable_heartbeat(DeviceId::new(15)?, Heartbeat::default());
+    ///
+    /// while let Some(due) = client.heartbeat_due() {
+    ///     tokio::time::sleep_until(due).await;
+    ///     if let Some(event) = client.heartbeat().await? {
+    ///         if event.kind == HealthEventKind::Missed {
+    ///             println!("server did not answer");
+    ///         }
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn heartbeat(&mut self) -> Result<Option<HealthEvent>, TcpClientError> {
+        if self.framed.is_none() {
+            return Err(TcpClientError::NotConnected);
+        }
+        let Some((device_id, _)) = self.heartbeat else {
+            return Ok(None);
+        };
+        if self.heartbeat_due().is_some_and(|due| due > Instant::now()) {
+            return Ok(None);
+        }
+
+        let sent = Instant::now();
+        // A miss waits a full interval before the next keepalive
+        self.last_heard = sent;
+        let answered = match self.send(heartbeat::keepalive(device_id)).await {
+            Ok(()) => match self.recv_within(self.timeout).await {
+                Ok(message) => {
+                    if !heartbeat::is_keepalive(&message) {
+                        self.pending.push_back(message);
+                    }
+                    true
+                }
+                Err(RecvError::Timeout) => false,
+                Err(RecvError::Failed(e)) => return Err(e),
+            },
+            Err(TcpClientError::WriteTimeout(_)) => false,
+            Err(e) => return Err(e),
+        };
+
+        let kind = if answered {
+            let round_trip = sent.elapsed();
+            trace!(
+                round_trip_ms = round_trip.as_millis() as u64,
+                "Keepalive answered"
+            );
+            HealthEventKind::Answered { round_trip }
+        } else {
+            warn!(
+                "Keepalive not answered after {}ms",
+                self.timeout.as_millis()
+            );
+            HealthEventKind::Missed
+        };
+        Ok(Some(HealthEvent {
+            device_id,
+            kind,
+            at: Utc::now(),
+        }))
+    }
+
+    /// Receive the next message, giving up after `limit`
+    async fn recv_within(&mut self, limit: Duration) -> Result<Message, RecvError> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(message);
+        }
         trace!("Waiting for message from server");
 
         // Check if connected
         let framed = self.framed.as_mut().ok_or(TcpClientError::NotConnected)?;
 
         // Receive message with timeout
-        match tokio::time::timeout(self.timeout, framed.next()).await {
+        match tokio::time::timeout(limit, framed.next()).await {
             Ok(Some(Ok(message))) => {
                 trace!(
                     device_id = %message.device_id,
                     command = ?message.command,
+                    correlation_id = message.correlation_id().map(tracing::field::display),
                     field_count = message.fields.len(),
                     "Received message from server"
                 );
+                self.last_heard = Instant::now();
                 Ok(message)
             }
             Ok(Some(Err(e))) => {
                 error!("Failed to decode message: {}", e);
-                Err(TcpClientError::Protocol(e))
+                Err(TcpClientError::Protocol(e).into())
             }
             Ok(None) => {
                 warn!("Connection closed by server");
-                Err(TcpClientError::ConnectionLost(
-                    "Server closed connection".to_string(),
-                ))
-            }
-            Err(_) => {
-                warn!("Receive timeout after {}ms", self.timeout.as_millis());
-                Err(TcpClientError::ReadTimeout(self.timeout.as_millis() as u64))
+                Err(TcpClientError::ConnectionLost("Server closed connection".to_string()).into())
             }
+            Err(_) => Err(RecvError::Timeout),
         }
     }
 
@@ -437,6 +698,23 @@ impl TcpClient {
         self.framed.is_some()
     }
 
+    /// Offer bulk-transfer compression on the next connection
+    ///
+    /// Takes effect on the next `connect()`; `None` (the default) disables
+    /// compression.
+    pub fn set_compression(&mut self, config: Option<CompressionConfig>) {
+        self.compression = config;
+    }
+
+    /// Whether bulk transfers on the current connection are compressed
+    ///
+    /// Becomes `true` once the server has offered compression back.
+    pub fn is_compressed(&self) -> bool {
+        self.framed
+            .as_ref()
+            .is_some_and(|framed| framed.codec().is_compression_negotiated())
+    }
+
     /// Close the connection gracefully
     ///
     /// Closes the TCP connection and cleans up resources. This method
@@ -467,6 +745,7 @@ impl TcpClient {
     /// # }
     /// ```
     pub async fn close(&mut self) -> Result<(), TcpClientError> {
+        self.pending.clear();
         if let Some(mut framed) = self.framed.take() {
             info!("Closing connection to {}", self.server_addr);
 
@@ -512,6 +791,18 @@ impl TcpClient {
     }
 }
 
+/// Failure of `recv_within()`, before the caller names the timeout
+enum RecvError {
+    Timeout,
+    Failed(TcpClientError),
+}
+
+impl From<TcpClientError> for RecvError {
+    fn from(e: TcpClientError) -> Self {
+        Self::Failed(e)
+    }
+}
+
 impl Drop for TcpClient {
     fn drop(&mut self) {
         if self.framed.is_some() {
@@ -582,6 +873,20 @@ mod tests {
         assert!(!client.is_connected());
     }
 
+    #[tokio::test]
+    async fn test_connect_rejects_invalid_config() {
+        let config = TcpClientConfig {
+            server_addr: "127.0.0.1:3000".parse().unwrap(),
+            timeout: Duration::ZERO,
+        };
+
+        let mut client = TcpClient::new(config);
+        let result = client.connect().await;
+
+        assert!(matches!(result, Err(TcpClientError::InvalidConfig(_))));
+        assert!(!client.is_connected());
+    }
+
     #[tokio::test]
     async fn test_close_when_not_connected() {
         let mut client = TcpClient::new(TcpClientConfig::default());
@@ -0,0 +1,405 @@
+//! Validated construction of client and server configurations
+//!
+//! [`TcpClientConfig`] and [`TcpServerConfig`] remain plain structs so they can
+//! be written as literals, but nothing stops a literal from carrying a zero
+//! timeout or a zero connection limit. The builders in this module check every
+//! value against the bounds below and resolve host names, so configuration
+//! mistakes surface at startup instead of as confusing timeouts later.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_network::{TcpClientConfig, TcpServerConfig};
+//! use std::time::Duration;
+//!
+//! let client = TcpClientConfig::builder()
+//!     .server("localhost:3000")
+//!     .timeout(Duration::from_millis(2000))
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(client.server_addr.port(), 3000);
+//!
+//! let server = TcpServerConfig::builder()
+//!     .bind("0.0.0.0:3000")
+//!     .max_connections(50)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(server.max_connections, 50);
+//!
+//! assert!(TcpServerConfig::builder().max_connections(0).build().is_err());
+//! ```
+
+use crate::client::TcpClientConfig;
+use crate::server::TcpServerConfig;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Shortest accepted client I/O timeout
+///
+/// Anything lower cannot survive a single round trip on a loaded LAN.
+pub const MIN_CLIENT_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Longest accepted client I/O timeout
+///
+/// Turnstiles give up on an access request after a few seconds, so waiting
+/// longer than this only hides a dead server.
+pub const MAX_CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest accepted server connection limit
+pub const MAX_SERVER_CONNECTIONS: usize = 1024;
+
+/// Invalid client or server configuration
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigError {
+    /// Timeout outside `MIN_CLIENT_TIMEOUT..=MAX_CLIENT_TIMEOUT`
+    #[error("Timeout of {actual_ms}ms is out of range ({min_ms}ms to {max_ms}ms)")]
+    TimeoutOutOfRange {
+        actual_ms: u128,
+        min_ms: u128,
+        max_ms: u128,
+    },
+
+    /// Connection limit outside `1..=MAX_SERVER_CONNECTIONS`
+    #[error("max_connections must be between 1 and {max}, got {actual}")]
+    MaxConnectionsOutOfRange { actual: usize, max: usize },
+
+    /// Client would connect to port 0
+    #[error("Server address {0} has no port (port 0)")]
+    MissingPort(SocketAddr),
+
+    /// Client would connect to an unspecified address (0.0.0.0 or ::)
+    #[error("Server address {0} is unspecified; use a concrete host")]
+    UnspecifiedServerAddress(SocketAddr),
+
+    /// Address string could not be resolved
+    #[error("Cannot resolve address '{address}': {reason}")]
+    Resolution { address: String, reason: String },
+
+    /// Address string resolved to nothing
+    #[error("Address '{0}' did not resolve to any socket address")]
+    NoAddress(String),
+
+    /// Heartbeat with a zero interval or missed threshold
+    #[error("Heartbeat interval and missed threshold must be positive")]
+    InvalidHeartbeat,
+}
+
+/// Resolve `host:port` (name or literal IP) to the first socket address
+///
+/// IPv4 results are preferred since turnstiles are IPv4-only.
+fn resolve(address: &str) -> Result<SocketAddr, ConfigError> {
+    let resolved: Vec<SocketAddr> = address
+        .to_socket_addrs()
+        .map_err(|e| ConfigError::Resolution {
+            address: address.to_string(),
+            reason: e.to_string(),
+        })?
+        .collect();
+
+    resolved
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .or_else(|| resolved.first())
+        .copied()
+        .ok_or_else(|| ConfigError::NoAddress(address.to_string()))
+}
+
+/// Where a builder takes its address from
+#[derive(Debug, Clone)]
+enum AddressSource {
+    Resolved(SocketAddr),
+    Host(String),
+}
+
+impl AddressSource {
+    fn resolve(&self) -> Result<SocketAddr, ConfigError> {
+        match self {
+            Self::Resolved(addr) => Ok(*addr),
+            Self::Host(host) => resolve(host),
+        }
+    }
+}
+
+impl TcpClientConfig {
+    /// Start building a validated client configuration
+    ///
+    /// Unset values keep their [`Default`] values.
+    pub fn builder() -> TcpClientConfigBuilder {
+        TcpClientConfigBuilder::default()
+    }
+
+    /// Check the configuration against the accepted bounds
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found: timeout out of range, port 0 or an
+    /// unspecified server address.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.timeout < MIN_CLIENT_TIMEOUT || self.timeout > MAX_CLIENT_TIMEOUT {
+            return Err(ConfigError::TimeoutOutOfRange {
+                actual_ms: self.timeout.as_millis(),
+                min_ms: MIN_CLIENT_TIMEOUT.as_millis(),
+                max_ms: MAX_CLIENT_TIMEOUT.as_millis(),
+            });
+        }
+        if self.server_addr.port() == 0 {
+            return Err(ConfigError::MissingPort(self.server_addr));
+        }
+        if self.server_addr.ip().is_unspecified() {
+            return Err(ConfigError::UnspecifiedServerAddress(self.server_addr));
+        }
+        Ok(())
+    }
+}
+
+impl TcpServerConfig {
+    /// Start building a validated server configuration
+    ///
+    /// Unset values keep their [`Default`] values.
+    pub fn builder() -> TcpServerConfigBuilder {
+        TcpServerConfigBuilder::default()
+    }
+
+    /// Check the configuration against the accepted bounds
+    ///
+    /// Port 0 is allowed on the server side and lets the OS pick a port.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MaxConnectionsOutOfRange` if `max_connections` is 0 or above
+    /// [`MAX_SERVER_CONNECTIONS`].
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_connections == 0 || self.max_connections > MAX_SERVER_CONNECTIONS {
+            return Err(ConfigError::MaxConnectionsOutOfRange {
+                actual: self.max_connections,
+                max: MAX_SERVER_CONNECTIONS,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`TcpClientConfig`]
+#[derive(Debug, Clone)]
+pub struct TcpClientConfigBuilder {
+    server: AddressSource,
+    timeout: Duration,
+}
+
+impl Default for TcpClientConfigBuilder {
+    fn default() -> Self {
+        let defaults = TcpClientConfig::default();
+        Self {
+            server: AddressSource::Resolved(defaults.server_addr),
+            timeout: defaults.timeout,
+        }
+    }
+}
+
+impl TcpClientConfigBuilder {
+    /// Connect to an already resolved socket address
+    pub fn server_addr(mut self, addr: SocketAddr) -> Self {
+        self.server = AddressSource::Resolved(addr);
+        self
+    }
+
+    /// Connect to `host:port`, resolved when [`build`](Self::build) runs
+    ///
+    /// Accepts host names (`"validator.local:3000"`) as well as literal
+    /// addresses (`"192.168.0.10:3000"`).
+    pub fn server(mut self, address: impl Into<String>) -> Self {
+        self.server = AddressSource::Host(address.into());
+        self
+    }
+
+    /// Timeout for connect, send and receive
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Resolve the server address and validate the configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the address cannot be resolved or a value
+    /// is out of bounds.
+    pub fn build(self) -> Result<TcpClientConfig, ConfigError> {
+        let config = TcpClientConfig {
+            server_addr: self.server.resolve()?,
+            timeout: self.timeout,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Builder for [`TcpServerConfig`]
+#[derive(Debug, Clone)]
+pub struct TcpServerConfigBuilder {
+    bind: AddressSource,
+    max_connections: usize,
+}
+
+impl Default for TcpServerConfigBuilder {
+    fn default() -> Self {
+        let defaults = TcpServerConfig::default();
+        Self {
+            bind: AddressSource::Resolved(defaults.bind_addr),
+            max_connections: defaults.max_connections,
+        }
+    }
+}
+
+impl TcpServerConfigBuilder {
+    /// Bind to an already resolved socket address
+    pub fn bind_addr(mut self, addr: SocketAddr) -> Self {
+        self.bind = AddressSource::Resolved(addr);
+        self
+    }
+
+    /// Bind to `host:port`, resolved when [`build`](Self::build) runs
+    pub fn bind(mut self, address: impl Into<String>) -> Self {
+        self.bind = AddressSource::Host(address.into());
+        self
+    }
+
+    /// Maximum number of simultaneous connections
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Resolve the bind address and validate the configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the address cannot be resolved or a value
+    /// is out of bounds.
+    pub fn build(self) -> Result<TcpServerConfig, ConfigError> {
+        let config = TcpServerConfig {
+            bind_addr: self.bind.resolve()?,
+            max_connections: self.max_connections,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert!(TcpClientConfig::default().validate().is_ok());
+        assert!(TcpServerConfig::default().validate().is_ok());
+
+        let client = TcpClientConfig::builder().build().unwrap();
+        assert_eq!(client.server_addr, TcpClientConfig::default().server_addr);
+        assert_eq!(client.timeout, TcpClientConfig::default().timeout);
+    }
+
+    #[test]
+    fn test_client_timeout_bounds() {
+        for timeout in [
+            Duration::ZERO,
+            Duration::from_millis(9),
+            Duration::from_secs(61),
+        ] {
+            let err = TcpClientConfig::builder()
+                .timeout(timeout)
+                .build()
+                .unwrap_err();
+            assert!(matches!(err, ConfigError::TimeoutOutOfRange { .. }));
+        }
+
+        assert!(
+            TcpClientConfig::builder()
+                .timeout(MIN_CLIENT_TIMEOUT)
+                .build()
+                .is_ok()
+        );
+        assert!(
+            TcpClientConfig::builder()
+                .timeout(MAX_CLIENT_TIMEOUT)
+                .build()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_client_rejects_unusable_address() {
+        let err = TcpClientConfig::builder()
+            .server_addr("127.0.0.1:0".parse().unwrap())
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::MissingPort(_)));
+
+        let err = TcpClientConfig::builder()
+            .server_addr("0.0.0.0:3000".parse().unwrap())
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::UnspecifiedServerAddress(_)));
+    }
+
+    #[test]
+    fn test_client_resolves_host_name() {
+        let config = TcpClientConfig::builder()
+            .server("localhost:4000")
+            .build()
+            .unwrap();
+
+        assert!(config.server_addr.ip().is_loopback());
+        assert_eq!(config.server_addr.port(), 4000);
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let err = TcpClientConfig::builder()
+            .server("no-port-here")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::Resolution { .. }));
+        assert!(err.to_string().contains("no-port-here"));
+    }
+
+    #[test]
+    fn test_server_max_connections_bounds() {
+        let err = TcpServerConfig::builder()
+            .max_connections(0)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::MaxConnectionsOutOfRange {
+                actual: 0,
+                max: MAX_SERVER_CONNECTIONS
+            }
+        );
+
+        assert!(
+            TcpServerConfig::builder()
+                .max_connections(MAX_SERVER_CONNECTIONS + 1)
+                .build()
+                .is_err()
+        );
+        assert!(
+            TcpServerConfig::builder()
+                .max_connections(1)
+                .build()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_server_allows_ephemeral_port() {
+        let config = TcpServerConfig::builder()
+            .bind("127.0.0.1:0")
+            .build()
+            .unwrap();
+
+        assert_eq!(config.bind_addr.port(), 0);
+    }
+}
@@ -0,0 +1,140 @@
+//! What the server does when a second connection claims a connected device
+//!
+//! The device ID is only known from the first message of a connection, so
+//! two connections may claim the same ID. The common cause is benign: a
+//! turnstile that lost power or crashed reconnects before the server has
+//! noticed its old socket is dead, and would otherwise be locked out until
+//! the old connection times out. The [`DuplicatePolicy`] set with
+//! [`TcpServer::set_duplicate_policy()`](crate::TcpServer::set_duplicate_policy)
+//! decides between the two connections:
+//!
+//! - [`RejectNew`](DuplicatePolicy::RejectNew) (default) keeps the
+//!   established connection and closes the new one;
+//! - [`ReplaceOld`](DuplicatePolicy::ReplaceOld) closes the established
+//!   connection in favour of the new one, which recovers crashed devices at
+//!   once but also lets a misconfigured device kick out another;
+//! - [`AllowMulti`](DuplicatePolicy::AllowMulti) keeps both, for devices
+//!   that open one connection per reader instead of multiplexing them over
+//!   one (see [`SharedTcpClient`](crate::SharedTcpClient)).
+//!
+//! With several connections per device, replies sent with
+//! [`TcpServer::send()`](crate::TcpServer::send) go to the connection that
+//! delivered the device's latest message, which matches Henry's strict
+//! request-then-response exchanges.
+//!
+//! Every rejected or replaced connection is recorded as a
+//! [`ConnectionEvent`], drained with
+//! [`TcpServer::take_connection_events()`](crate::TcpServer::take_connection_events).
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_network::DuplicatePolicy;
+//!
+//! let policy: DuplicatePolicy = "replace-old".parse().unwrap();
+//! assert_eq!(policy, DuplicatePolicy::ReplaceOld);
+//! assert_eq!(DuplicatePolicy::default(), DuplicatePolicy::RejectNew);
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use turnkey_core::DeviceId;
+
+/// Connection events kept until drained; older ones are dropped first
+pub const MAX_PENDING_CONNECTION_EVENTS: usize = 256;
+
+/// Handling of a new connection for a device that is already connected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicatePolicy {
+    /// Keep the established connection and close the new one
+    #[default]
+    RejectNew,
+
+    /// Close the established connection and keep the new one
+    ReplaceOld,
+
+    /// Keep both connections
+    AllowMulti,
+}
+
+impl fmt::Display for DuplicatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::RejectNew => "reject-new",
+            Self::ReplaceOld => "replace-old",
+            Self::AllowMulti => "allow-multi",
+        })
+    }
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject-new" => Ok(Self::RejectNew),
+            "replace-old" => Ok(Self::ReplaceOld),
+            "allow-multi" => Ok(Self::AllowMulti),
+            other => Err(format!(
+                "unknown duplicate policy '{}' (expected reject-new, replace-old or allow-multi)",
+                other
+            )),
+        }
+    }
+}
+
+/// Outcome of a duplicate connection, as decided by the policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+    /// The new connection was closed
+    Rejected,
+
+    /// The established connection was closed in favour of the new one
+    Superseded,
+
+    /// Both connections were kept
+    Added,
+}
+
+/// A second connection claimed a connected device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionEvent {
+    /// Device both connections claim
+    pub device_id: DeviceId,
+
+    /// What the server did
+    pub kind: ConnectionEventKind,
+
+    /// Address of the connection that was already established
+    pub existing_addr: SocketAddr,
+
+    /// Address of the new connection
+    pub new_addr: SocketAddr,
+
+    /// When the new connection sent its first message
+    pub at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_round_trips_through_strings() {
+        for policy in [
+            DuplicatePolicy::RejectNew,
+            DuplicatePolicy::ReplaceOld,
+            DuplicatePolicy::AllowMulti,
+        ] {
+            assert_eq!(policy.to_string().parse::<DuplicatePolicy>(), Ok(policy));
+            let json = serde_json::to_string(&policy).unwrap();
+            assert_eq!(json, format!("\"{}\"", policy));
+        }
+        assert!("replace".parse::<DuplicatePolicy>().is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! Keepalives that detect dead connections before a request does
+//!
+//! A turnstile that sits idle behind a NAT or a stateful firewall can lose
+//! its connection without either side noticing: the middlebox forgets the
+//! mapping, and the next access request waits out a full network timeout
+//! before falling back to local validation. Heartbeats keep the mapping
+//! alive and make a dead link visible while nobody is at the gate.
+//!
+//! With a [`Heartbeat`] enabled on both sides:
+//!
+//! - [`TcpClient`](crate::TcpClient) sends a status query (`RQ` without
+//!   fields) when it has heard nothing from the server for
+//!   [`Heartbeat::interval()`], and reports each outcome as
+//!   [`HealthEventKind::Answered`] or [`HealthEventKind::Missed`]. These
+//!   feed a missed-heartbeat monitor such as
+//!   `turnkey_storage::heartbeat::HeartbeatMonitor`, which moves the
+//!   device to offline validation.
+//! - [`TcpServer`](crate::TcpServer) answers those queries itself, keeps
+//!   the time each connection was last heard from, and reports a device
+//!   as [`HealthEventKind::Stale`] once it has been silent for
+//!   [`Heartbeat::stale_after()`], and as [`HealthEventKind::Recovered`]
+//!   when it speaks again.
+//!
+//! Server events are kept until drained with
+//! [`TcpServer::take_health_events()`](crate::TcpServer::take_health_events).
+//! Stale connections are not closed; the caller decides whether to
+//! [`disconnect()`](crate::TcpServer::disconnect) them.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_network::Heartbeat;
+//! use std::time::Duration;
+//!
+//! let heartbeat = Heartbeat::new(Duration::from_secs(20), 3).unwrap();
+//! assert_eq!(heartbeat.stale_after(), Duration::from_secs(60));
+//!
+//! assert!(Heartbeat::new(Duration::ZERO, 3).is_err());
+//! ```
+
+use crate::config::ConfigError;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use turnkey_core::DeviceId;
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::DeviceStatusReport;
+
+/// Default time without traffic before a keepalive is sent
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default number of silent intervals before a device is stale
+pub const DEFAULT_MISSED_HEARTBEATS: u32 = 3;
+
+/// Health events kept until drained; older ones are dropped first
+pub const MAX_PENDING_HEALTH_EVENTS: usize = 256;
+
+/// Keepalive schedule shared by client and server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    interval: Duration,
+    missed_threshold: u32,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_HEARTBEAT_INTERVAL,
+            missed_threshold: DEFAULT_MISSED_HEARTBEATS,
+        }
+    }
+}
+
+impl Heartbeat {
+    /// Create a schedule
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidHeartbeat` if `interval` or `missed_threshold` is zero.
+    pub fn new(interval: Duration, missed_threshold: u32) -> Result<Self, ConfigError> {
+        if interval.is_zero() || missed_threshold == 0 {
+            return Err(ConfigError::InvalidHeartbeat);
+        }
+        Ok(Self {
+            interval,
+            missed_threshold,
+        })
+    }
+
+    /// Time without traffic before a keepalive is sent
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Number of silent intervals before a device is stale
+    pub fn missed_threshold(&self) -> u32 {
+        self.missed_threshold
+    }
+
+    /// Silence after which the server reports a device as stale
+    pub fn stale_after(&self) -> Duration {
+        self.interval.saturating_mul(self.missed_threshold)
+    }
+}
+
+/// Change in the health of a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthEvent {
+    /// Device of the connection
+    pub device_id: DeviceId,
+
+    /// What happened
+    pub kind: HealthEventKind,
+
+    /// When it was observed
+    pub at: DateTime<Utc>,
+}
+
+/// Kind of [`HealthEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthEventKind {
+    /// Client: the server answered a keepalive
+    Answered {
+        /// Time between keepalive and answer
+        round_trip: Duration,
+    },
+
+    /// Client: a keepalive went unanswered
+    Missed,
+
+    /// Server: nothing heard from the device for the stale threshold
+    Stale {
+        /// Silence at the time of the check
+        idle: Duration,
+    },
+
+    /// Server: a stale device spoke again
+    Recovered,
+}
+
+/// Check whether a message is a keepalive (a status query without fields)
+pub(crate) fn is_keepalive(message: &Message) -> bool {
+    DeviceStatusReport::is_query(message)
+}
+
+/// Build the keepalive, which is also its answer
+pub(crate) fn keepalive(device_id: DeviceId) -> Message {
+    DeviceStatusReport::query(device_id).expect("status query has no fields to validate")
+}
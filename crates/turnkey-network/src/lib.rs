@@ -8,6 +8,15 @@
 //!
 //! - **TcpClient**: Client for connecting to validation servers (Issue #65)
 //! - **TcpServer**: Server for accepting emulator connections (Issue #66)
+//! - **SharedTcpClient**: One client connection shared by several readers of a device
+//! - **CommandPolicy**: Which commands each device may send to the server
+//! - **ProtocolTrace**: Runtime frame logging for selected devices
+//! - **CommandTimeouts**: Response timeouts per command class (validation, sync, display)
+//! - **DeviceAffinity**: One server per device when several share a backend
+//! - **DuplicatePolicy**: Handling of a second connection for a connected device
+//! - **Heartbeat**: Keepalives and connection-health events on both sides
+//! - **CancellationToken**: Cancelled when a device's connection closes, to stop work on its requests
+//! - **tls**: Client-certificate device authentication for TcpServer (feature `tls`)
 //!
 //! # Examples
 //!
@@ -18,10 +27,10 @@
 //! use std::time::Duration;
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-//! let config = TcpClientConfig {
-//!     server_addr: "127.0.0.1:3000".parse()?,
-//!     timeout: Duration::from_millis(3000),
-//! };
+//! let config = TcpClientConfig::builder()
+//!     .server("127.0.0.1:3000")
+//!     .timeout(Duration::from_millis(3000))
+//!     .build()?;
 //!
 //! let mut client = TcpClient::new(config);
 //! client.connect().await?;
@@ -44,8 +53,38 @@
 //! # }
 //! ```
 
+mod affinity;
 mod client;
+mod config;
+pub mod duplicate;
+pub mod heartbeat;
+mod policy;
 mod server;
+mod shared;
+mod timeouts;
+#[cfg(feature = "tls")]
+pub mod tls;
+mod trace;
 
+pub use affinity::DeviceAffinity;
 pub use client::{TcpClient, TcpClientConfig, TcpClientError};
-pub use server::{ConnectionInfo, TcpServer, TcpServerConfig, TcpServerError};
+pub use config::{
+    ConfigError, MAX_CLIENT_TIMEOUT, MAX_SERVER_CONNECTIONS, MIN_CLIENT_TIMEOUT,
+    TcpClientConfigBuilder, TcpServerConfigBuilder,
+};
+pub use duplicate::{ConnectionEvent, ConnectionEventKind, DuplicatePolicy};
+pub use heartbeat::{HealthEvent, HealthEventKind, Heartbeat};
+pub use policy::{CommandPolicy, DeviceRole};
+pub use server::{
+    BroadcastReport, ConnectionInfo, ConnectionRegistry, DEFAULT_HANDSHAKE_TIMEOUT, DeviceGroup,
+    DeviceState, TcpServer, TcpServerConfig, TcpServerError,
+};
+pub use shared::SharedTcpClient;
+pub use timeouts::{
+    CommandClass, CommandTimeouts, DEFAULT_DISPLAY_TIMEOUT, DEFAULT_SYNC_TIMEOUT,
+    DEFAULT_VALIDATION_TIMEOUT,
+};
+pub use trace::{DEFAULT_TRACE_DURATION, MAX_TRACE_DURATION, ProtocolTrace, TraceDirection};
+
+// Cancellation of work tied to a connection, see `TcpServer::cancellation_token()`
+pub use tokio_util::sync::CancellationToken;
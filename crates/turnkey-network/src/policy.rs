@@ -0,0 +1,218 @@
+//! Per-command authorization of messages received by the server
+//!
+//! Every connection is assigned a [`DeviceRole`], and each role may only send
+//! a fixed set of commands. A turnstile asks for access, reports rotations
+//! and answers the server's queries; it has no business sending `EC`
+//! (configuration) or a grant of its own. Management clients, such as an
+//! administration console connected to the server, may send anything.
+//!
+//! The policy is plain data and (de)serializes with serde, so it can be
+//! loaded from a configuration file:
+//!
+//! ```json
+//! {
+//!   "default_role": "Turnstile",
+//!   "roles": { "99": "Management" },
+//!   "permissions": { "Turnstile": ["AccessRequest", "QueryStatus"] }
+//! }
+//! ```
+//!
+//! `permissions` is optional; a role listed there uses exactly the given
+//! commands instead of its built-in set.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_network::{CommandPolicy, DeviceRole};
+//! use turnkey_protocol::CommandCode;
+//! use turnkey_core::DeviceId;
+//!
+//! let console = DeviceId::new(99).unwrap();
+//! let policy = CommandPolicy::default().with_role(console, DeviceRole::Management);
+//!
+//! let turnstile = DeviceId::new(15).unwrap();
+//! assert!(policy.is_allowed(turnstile, CommandCode::AccessRequest));
+//! assert!(!policy.is_allowed(turnstile, CommandCode::SendConfig));
+//! assert!(policy.is_allowed(console, CommandCode::SendConfig));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use turnkey_core::DeviceId;
+use turnkey_protocol::CommandCode;
+
+/// What a connection is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeviceRole {
+    /// Access control equipment: requests, rotation events and answers to
+    /// server queries
+    Turnstile,
+
+    /// Administration client: every command
+    Management,
+}
+
+impl DeviceRole {
+    /// Check a command against the built-in permissions of the role
+    pub fn allows(self, command: CommandCode) -> bool {
+        match self {
+            DeviceRole::Turnstile => {
+                command == CommandCode::AccessRequest
+                    || command.is_turnstile_status()
+                    || command.is_query()
+                    // Answers to the server's log and configuration reads
+                    || matches!(command, CommandCode::ReceiveLogs | CommandCode::ReceiveConfig)
+            }
+            DeviceRole::Management => true,
+        }
+    }
+}
+
+impl fmt::Display for DeviceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceRole::Turnstile => write!(f, "turnstile"),
+            DeviceRole::Management => write!(f, "management"),
+        }
+    }
+}
+
+/// Which commands each device may send to the server
+///
+/// The default policy treats every device as a [`DeviceRole::Turnstile`].
+/// Use [`CommandPolicy::permissive()`] to accept every command from every
+/// device, as the server did before policies existed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandPolicy {
+    /// Role of devices without an explicit assignment
+    pub default_role: DeviceRole,
+
+    /// Role assignments by device
+    pub roles: HashMap<DeviceId, DeviceRole>,
+
+    /// Replacement command sets, by role
+    pub permissions: HashMap<DeviceRole, HashSet<CommandCode>>,
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        Self {
+            default_role: DeviceRole::Turnstile,
+            roles: HashMap::new(),
+            permissions: HashMap::new(),
+        }
+    }
+}
+
+impl CommandPolicy {
+    /// Policy that accepts every command from every device
+    pub fn permissive() -> Self {
+        Self {
+            default_role: DeviceRole::Management,
+            ..Self::default()
+        }
+    }
+
+    /// Assign a role to a device
+    pub fn with_role(mut self, device_id: DeviceId, role: DeviceRole) -> Self {
+        self.roles.insert(device_id, role);
+        self
+    }
+
+    /// Replace the commands a role may send
+    pub fn with_permissions(
+        mut self,
+        role: DeviceRole,
+        commands: impl IntoIterator<Item = CommandCode>,
+    ) -> Self {
+        self.permissions
+            .insert(role, commands.into_iter().collect());
+        self
+    }
+
+    /// Role of a device
+    pub fn role_of(&self, device_id: DeviceId) -> DeviceRole {
+        self.roles
+            .get(&device_id)
+            .copied()
+            .unwrap_or(self.default_role)
+    }
+
+    /// Check whether a device may send a command
+    pub fn is_allowed(&self, device_id: DeviceId, command: CommandCode) -> bool {
+        let role = self.role_of(device_id);
+        match self.permissions.get(&role) {
+            Some(commands) => commands.contains(&command),
+            None => role.allows(command),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: u8) -> DeviceId {
+        DeviceId::new(id).unwrap()
+    }
+
+    #[test]
+    fn test_turnstile_role() {
+        let role = DeviceRole::Turnstile;
+        for command in [
+            CommandCode::AccessRequest,
+            CommandCode::WaitingRotation,
+            CommandCode::RotationCompleted,
+            CommandCode::RotationTimeout,
+            CommandCode::QueryStatus,
+            CommandCode::ReceiveLogs,
+        ] {
+            assert!(role.allows(command), "{command:?} should be allowed");
+        }
+        for command in [
+            CommandCode::GrantEntry,
+            CommandCode::DenyAccess,
+            CommandCode::SendConfig,
+            CommandCode::SendCards,
+            CommandCode::SendDateTime,
+        ] {
+            assert!(!role.allows(command), "{command:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_role_assignment_and_permissive_policy() {
+        let policy = CommandPolicy::default().with_role(device(99), DeviceRole::Management);
+        assert_eq!(policy.role_of(device(1)), DeviceRole::Turnstile);
+        assert!(policy.is_allowed(device(99), CommandCode::SendUsers));
+        assert!(!policy.is_allowed(device(1), CommandCode::SendUsers));
+
+        assert!(CommandPolicy::permissive().is_allowed(device(1), CommandCode::SendUsers));
+    }
+
+    #[test]
+    fn test_permission_override() {
+        let policy = CommandPolicy::default()
+            .with_permissions(DeviceRole::Turnstile, [CommandCode::AccessRequest]);
+        assert!(policy.is_allowed(device(1), CommandCode::AccessRequest));
+        assert!(!policy.is_allowed(device(1), CommandCode::RotationCompleted));
+    }
+
+    #[test]
+    fn test_policy_from_json() {
+        let policy: CommandPolicy = serde_json::from_str(
+            r#"{
+                "roles": { "99": "Management" },
+                "permissions": { "Turnstile": ["AccessRequest", "QueryStatus"] }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.default_role, DeviceRole::Turnstile);
+        assert_eq!(policy.role_of(device(99)), DeviceRole::Management);
+        assert!(policy.is_allowed(device(1), CommandCode::QueryStatus));
+        assert!(!policy.is_allowed(device(1), CommandCode::RotationCompleted));
+    }
+}
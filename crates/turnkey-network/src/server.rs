@@ -43,11 +43,12 @@
 //! # Design Principles
 //!
 //! The TcpServer is designed as a simple transport layer for emulator use:
-//! - **No authentication**: This is an emulator component
-//! - **No TLS**: Can be added later if needed
+//! - **Optional authentication**: Per-device HMAC signing (see below)
+//! - **Optional TLS**: Client-certificate device authentication (feature `tls`)
 //! - **No rate limiting**: Not needed for emulator scenarios
 //! - **Simple connection tracking**: HashMap for O(1) device lookup
-//! - **1:1 device mapping**: Each turnstile has its own connection
+//! - **1:1 device mapping**: Each turnstile has its own connection, unless
+//!   the duplicate policy allows several (see below)
 //!
 //! This keeps the server focused and testable, pushing business logic
 //! to higher layers like the Client-Emulator TUI.
@@ -60,6 +61,129 @@
 //! - Messages from any device are received via `accept()`
 //! - Responses are sent to specific devices via `send(device_id, message)`
 //! - Connection state is tracked per device
+//! - `registry()` lists the connected devices with address and uptime,
+//!   `broadcast_all()` sends a command (e.g. the clock) to every one of
+//!   them and `disconnect(device_id)` ends a session
+//!
+//! # Handshake
+//!
+//! Every new connection, whether picked up by `accept()` or `recv_any()`,
+//! goes through the same checks before it is tracked. The TLS handshake and
+//! the first message must arrive within the handshake timeout
+//! (`set_handshake_timeout()`, [`DEFAULT_HANDSHAKE_TIMEOUT`] by default);
+//! a peer that connects and stays silent is closed instead of stalling the
+//! server.
+//!
+//! # Duplicate Connections
+//!
+//! A second connection claiming a connected device ID is handled by the
+//! [`DuplicatePolicy`] set with `set_duplicate_policy()`: by default the new
+//! connection is closed, `ReplaceOld` lets a device that crashed and
+//! reconnected take over from its stale socket, and `AllowMulti` keeps
+//! every connection and answers on the one that spoke last. Rejections and
+//! replacements are kept as [`ConnectionEvent`]s until
+//! `take_connection_events()` drains them. See `turnkey_network::duplicate`.
+//!
+//! # Message Signing
+//!
+//! Devices registered with `register_signing_key()` must sign their messages
+//! with HMAC-SHA256 (see `turnkey_protocol::signing`). Signing is negotiated
+//! from the first message of each connection:
+//!
+//! - Signed first message: verified with the device key; every later message
+//!   on that connection must be signed too
+//! - Unsigned first message: rejected, unless `set_unsigned_allowed()` lets
+//!   the device connect unsigned while keys are being rolled out
+//! - Signed message from a device without a key: rejected
+//!
+//! Signatures carry a counter and the time of signing. A message signed
+//! more than `MAX_SIGNATURE_AGE` away from the server clock, or whose
+//! counter the server already accepted from that device on any connection,
+//! is rejected, so captured messages cannot be replayed.
+//!
+//! Verified signatures are stripped, so callers always receive the plain
+//! message. A message failing verification closes the connection.
+//!
+//! Keys can come from a `turnkey_core::secrets::SecretProvider` instead of
+//! configuration with `load_signing_key()`, which also accepts the key
+//! replaced by the last rotation.
+//!
+//! # Compression
+//!
+//! With `set_compression()` the server offers zlib compression of bulk
+//! transfers (card lists, users, templates, logs) to every new connection.
+//! A connection compresses once the device has offered it too; devices
+//! that never do, such as real Henry equipment, keep receiving plain
+//! messages. See `turnkey_protocol::compression` for the wire format.
+//!
+//! # Tolerant Parsing
+//!
+//! Some firmware sends device IDs without zero padding or command codes
+//! with extra spaces or lowercase letters. `set_parse_mode()` with
+//! `ParseMode::Tolerant` accepts these on new connections by normalizing
+//! the header of every received message; replies are always sent in
+//! canonical form. See `turnkey_protocol::parser` for the variants.
+//!
+//! # Command Authorization
+//!
+//! Every received message is checked against the server's [`CommandPolicy`]
+//! before it reaches the caller. By default every device is a turnstile and
+//! may only send access requests, rotation events and answers to queries.
+//! A forbidden first message rejects the connection; a forbidden message on
+//! an established connection is dropped and reported as
+//! `TcpServerError::CommandNotAllowed`, keeping the connection open. Every
+//! violation is logged at warn level under the `turnkey::audit` target.
+//!
+//! # Device State Recovery
+//!
+//! Status reports (`RQ` messages with fields, see
+//! `turnkey_protocol::commands::status`) are recorded in a device registry
+//! whenever they are received, and still returned to the caller. Devices
+//! send one after reconnecting, so a restarted server learns their mode,
+//! counters and pending events without manual intervention.
+//!
+//! With `set_state_recovery(true)` the server also sends a status query to
+//! every newly connected device it has no report for, which covers devices
+//! that do not report on their own. The registry outlives connections; a
+//! device that drops and reconnects keeps its last known state.
+//!
+//! # Protocol Trace
+//!
+//! `protocol_trace()` returns a handle that logs the frames exchanged with
+//! selected devices, in both directions, until a per-device timer expires.
+//! It can be cloned into another task to toggle tracing while the server
+//! is running; see `turnkey_network::ProtocolTrace`.
+//!
+//! # TLS
+//!
+//! With the `tls` feature, `set_tls()` makes the server require a TLS
+//! handshake with a client certificate on every new connection. The
+//! certificate must be mapped to a device in `device_certificates_mut()`,
+//! and the connection is rejected if its first message claims another
+//! device ID. Later messages claiming another device close the connection
+//! with `DeviceMismatch`. Calling `set_tls()` again swaps the server
+//! certificate for new connections; see `turnkey_network::tls` for
+//! rotating device certificates.
+//!
+//! # Heartbeat
+//!
+//! With `set_heartbeat()`, the server answers keepalives (status queries
+//! without fields) from devices itself instead of returning them from
+//! `recv()`/`recv_any()`, and `check_health()` reports devices silent for
+//! longer than the stale threshold. Stale and recovered devices are kept
+//! as [`HealthEvent`]s until `take_health_events()` drains them; the time
+//! a connection was last heard from is in [`ConnectionInfo::last_seen`].
+//! See `turnkey_network::heartbeat`.
+//!
+//! # Clustering
+//!
+//! Servers sharing one backend install a `DeviceAffinity` with
+//! `set_device_affinity()`. Each new device is claimed after signing and
+//! authorization pass; a device held by another server is disconnected.
+//! Every path that drops a connection releases the claim, so a device that
+//! moves to the other server is only refused while this one still holds
+//! it. If this server dies without releasing, the backend decides when the
+//! claim lapses.
 //!
 //! # Related
 //!
@@ -67,19 +191,45 @@
 //! - Issue #71: Client-Emulator TUI (uses this server)
 //! - Issue #65: TCP Client (counterpart for turnstiles)
 
+use crate::affinity::DeviceAffinity;
+use crate::config::ConfigError;
+use crate::duplicate::{
+    ConnectionEvent, ConnectionEventKind, DuplicatePolicy, MAX_PENDING_CONNECTION_EVENTS,
+};
+use crate::heartbeat::{self, HealthEvent, HealthEventKind, Heartbeat, MAX_PENDING_HEALTH_EVENTS};
+use crate::policy::{CommandPolicy, DeviceRole};
+#[cfg(feature = "tls")]
+use crate::tls::{DeviceCertificates, TlsServerConfig};
+use crate::trace::{ProtocolTrace, TraceDirection};
 use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_util::codec::Framed;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, trace, warn};
-use turnkey_core::DeviceId;
-use turnkey_protocol::{HenryCodec, Message};
+use turnkey_core::secrets::{SecretProvider, names};
+use turnkey_core::{DeviceId, DeviceLabel};
+use turnkey_protocol::commands::DeviceStatusReport;
+use turnkey_protocol::{
+    CommandCode, CompressionConfig, HenryCodec, Message, MessageSigner, ParseMode, ReplayGuard,
+};
+
+/// Time a new connection has to complete TLS and send its first message
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Configuration for TCP server
 ///
+/// Fields are public so the struct can be written as a literal; use
+/// [`TcpServerConfig::builder()`] to get bounds checking and host name resolution.
+///
 /// # Example
 ///
 /// ```
@@ -108,6 +258,58 @@ impl Default for TcpServerConfig {
     }
 }
 
+/// Byte stream of a connection: plain TCP or TLS over TCP
+#[derive(Debug)]
+enum Transport {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<tokio_rustls::server::TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
 /// Represents a single client connection
 ///
 /// Tracks connection metadata and provides message framing via HenryCodec.
@@ -116,14 +318,35 @@ pub struct Connection {
     /// Device ID extracted from messages
     device_id: DeviceId,
 
-    /// Framed TCP stream with HenryCodec
-    framed: Framed<TcpStream, HenryCodec>,
+    /// Framed TCP (or TLS) stream with HenryCodec
+    framed: Framed<Transport, HenryCodec>,
 
     /// Remote client address
     addr: SocketAddr,
 
     /// Connection timestamp
     connected_at: DateTime<Utc>,
+
+    /// Signer negotiated at handshake (None for unsigned connections)
+    signer: Option<SessionSigner>,
+
+    /// Device ID proven by a TLS client certificate
+    certified: bool,
+
+    /// Cancelled when the connection is dropped
+    closed: CancellationToken,
+
+    /// When the last message arrived (connection time until then)
+    last_seen: DateTime<Utc>,
+
+    /// Reported stale by `check_health()` and not heard from since
+    stale: bool,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.closed.cancel();
+    }
 }
 
 impl Connection {
@@ -147,6 +370,21 @@ impl Connection {
         Utc::now() - self.connected_at
     }
 
+    /// Whether messages on this connection are signed
+    pub fn is_signed(&self) -> bool {
+        self.signer.is_some()
+    }
+
+    /// Whether bulk transfers on this connection are compressed
+    pub fn is_compressed(&self) -> bool {
+        self.framed.codec().is_compression_negotiated()
+    }
+
+    /// Whether the device authenticated with a TLS client certificate
+    pub fn is_certified(&self) -> bool {
+        self.certified
+    }
+
     /// Send a message to this connection
     async fn send(&mut self, message: Message) -> Result<(), TcpServerError> {
         self.framed
@@ -156,9 +394,31 @@ impl Connection {
     }
 
     /// Receive a message from this connection
+    ///
+    /// On signed connections the signature is verified and stripped. On
+    /// certified connections every message must carry the certified device ID.
     async fn recv(&mut self) -> Result<Option<Message>, TcpServerError> {
         match self.framed.next().await {
-            Some(Ok(message)) => Ok(Some(message)),
+            Some(Ok(mut message)) => {
+                if self.certified && message.device_id != self.device_id {
+                    warn!(
+                        device_id = %self.device_id,
+                        claimed = %message.device_id,
+                        "Message claims another device"
+                    );
+                    return Err(TcpServerError::DeviceMismatch {
+                        device_id: self.device_id,
+                        claimed: message.device_id,
+                    });
+                }
+                if let Some(signer) = &self.signer {
+                    signer.verify_and_strip(&mut message).map_err(|e| {
+                        warn!(device_id = %self.device_id, error = %e, "Signature check failed");
+                        TcpServerError::InvalidSignature(self.device_id)
+                    })?;
+                }
+                Ok(Some(message))
+            }
             Some(Err(e)) => Err(TcpServerError::Codec(e.to_string())),
             None => Ok(None), // Connection closed
         }
@@ -174,6 +434,9 @@ pub struct ConnectionInfo {
     /// Device ID for this connection
     pub device_id: DeviceId,
 
+    /// Friendly name of the device (unnamed if none was set)
+    pub label: DeviceLabel,
+
     /// Remote client address
     pub remote_addr: SocketAddr,
 
@@ -182,6 +445,178 @@ pub struct ConnectionInfo {
 
     /// How long the connection has been active
     pub uptime: chrono::Duration,
+
+    /// When the device last sent a message on this connection
+    pub last_seen: DateTime<Utc>,
+
+    /// Whether the device signs its messages
+    pub signed: bool,
+
+    /// Whether bulk transfers to the device are compressed
+    pub compressed: bool,
+
+    /// Whether the device authenticated with a TLS client certificate
+    pub certified: bool,
+}
+
+/// Snapshot of the connections of a [`TcpServer`], ordered by device
+///
+/// Taken with [`TcpServer::registry()`] for session lists such as the one
+/// of the client-emulator TUI. A device with several connections (see
+/// [`DuplicatePolicy::AllowMulti`]) has one entry per connection, the one
+/// replies go to first.
+///
+/// # Example
+///
+/// ```no_run
+/// use turnkey_network::{TcpServer, TcpServerConfig};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let server = TcpServer::bind(TcpServerConfig::default()).await?;
+///
+/// let registry = server.registry();
+/// for info in &registry {
+///     println!("{} {} up {}s", info.device_id, info.remote_addr, info.uptime.num_seconds());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionRegistry {
+    connections: Vec<ConnectionInfo>,
+}
+
+impl ConnectionRegistry {
+    /// Number of connections, counting every connection of a device
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Check if no device is connected
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Connected devices in ascending order
+    pub fn devices(&self) -> Vec<DeviceId> {
+        let mut devices: Vec<DeviceId> = self.iter().map(|info| info.device_id).collect();
+        devices.dedup();
+        devices
+    }
+
+    /// Connection replies to the device go to
+    pub fn get(&self, device_id: DeviceId) -> Option<&ConnectionInfo> {
+        self.iter().find(|info| info.device_id == device_id)
+    }
+
+    /// Check if the device is connected
+    pub fn contains(&self, device_id: DeviceId) -> bool {
+        self.get(device_id).is_some()
+    }
+
+    /// All connections, by device
+    pub fn iter(&self) -> std::slice::Iter<'_, ConnectionInfo> {
+        self.connections.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ConnectionRegistry {
+    type Item = &'a ConnectionInfo;
+    type IntoIter = std::slice::Iter<'a, ConnectionInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for ConnectionRegistry {
+    type Item = ConnectionInfo;
+    type IntoIter = std::vec::IntoIter<ConnectionInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.connections.into_iter()
+    }
+}
+
+/// Last state reported by a device, kept in the server's device registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceState {
+    /// Device that sent the report
+    pub device_id: DeviceId,
+
+    /// Mode, counters and pending events as reported by the device
+    pub report: DeviceStatusReport,
+
+    /// When the report was received
+    pub reported_at: DateTime<Utc>,
+}
+
+/// Named set of devices addressed together (e.g. "all devices in Building A")
+///
+/// The server does not know about sites or zones; higher layers resolve a
+/// zone or site to its devices and hand the group to
+/// [`TcpServer::broadcast()`].
+///
+/// # Example
+///
+/// ```
+/// use turnkey_network::DeviceGroup;
+/// use turnkey_core::DeviceId;
+///
+/// let group = DeviceGroup::new(
+///     "Building A",
+///     [DeviceId::new(3).unwrap(), DeviceId::new(1).unwrap(), DeviceId::new(3).unwrap()],
+/// );
+/// assert_eq!(group.devices().len(), 2);
+/// assert_eq!(group.devices()[0], DeviceId::new(1).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceGroup {
+    name: String,
+    devices: Vec<DeviceId>,
+}
+
+impl DeviceGroup {
+    /// Create a group; devices are sorted and deduplicated
+    pub fn new(name: impl Into<String>, devices: impl IntoIterator<Item = DeviceId>) -> Self {
+        let mut devices: Vec<DeviceId> = devices.into_iter().collect();
+        devices.sort_by_key(|device| device.as_u8());
+        devices.dedup();
+        Self {
+            name: name.into(),
+            devices,
+        }
+    }
+
+    /// Group name, for logs and reports
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Member devices in ascending order
+    pub fn devices(&self) -> &[DeviceId] {
+        &self.devices
+    }
+}
+
+/// Outcome of a broadcast to a [`DeviceGroup`]
+#[derive(Debug, Default)]
+pub struct BroadcastReport {
+    /// Devices the message was sent to
+    pub sent: Vec<DeviceId>,
+
+    /// Group members without an active connection
+    pub not_connected: Vec<DeviceId>,
+
+    /// Devices whose send failed
+    pub failed: Vec<(DeviceId, TcpServerError)>,
+}
+
+impl BroadcastReport {
+    /// Check if every group member received the message
+    pub fn is_complete(&self) -> bool {
+        self.not_connected.is_empty() && self.failed.is_empty()
+    }
 }
 
 /// Errors that can occur during TCP server operations
@@ -207,6 +642,25 @@ pub enum TcpServerError {
     #[error("Invalid device ID in message")]
     InvalidDeviceId,
 
+    /// Message from a certified connection claims another device ID
+    #[error("Device {device_id} sent a message as device {claimed}")]
+    DeviceMismatch {
+        device_id: DeviceId,
+        claimed: DeviceId,
+    },
+
+    /// Message signature missing or not matching the device key
+    #[error("Invalid message signature from device {0}")]
+    InvalidSignature(DeviceId),
+
+    /// Command not allowed for the device's role by the command policy
+    #[error("Device {device_id} ({role}) is not allowed to send {command}")]
+    CommandNotAllowed {
+        device_id: DeviceId,
+        role: DeviceRole,
+        command: CommandCode,
+    },
+
     /// Low-level I/O error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -214,6 +668,10 @@ pub enum TcpServerError {
     /// Codec error during message encoding/decoding
     #[error("Codec error: {0}")]
     Codec(String),
+
+    /// Configuration rejected by `TcpServerConfig::validate()`
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(#[from] ConfigError),
 }
 
 /// TCP server for Henry protocol communication
@@ -261,8 +719,91 @@ pub struct TcpServer {
     /// Active connections indexed by device ID
     connections: HashMap<DeviceId, Connection>,
 
+    /// Further connections of devices under `DuplicatePolicy::AllowMulti`
+    standby: HashMap<DeviceId, Vec<Connection>>,
+
+    /// Handling of connections claiming a connected device
+    duplicate_policy: DuplicatePolicy,
+
+    /// Rejected and replaced duplicate connections, oldest first
+    connection_events: VecDeque<ConnectionEvent>,
+
     /// Server configuration
     config: TcpServerConfig,
+
+    /// Shared secrets for devices that sign their messages
+    signing_keys: HashMap<DeviceId, SigningKey>,
+
+    /// Devices with a key that may still connect unsigned
+    unsigned_allowed: HashSet<DeviceId>,
+
+    /// Commands each device may send
+    command_policy: CommandPolicy,
+
+    /// Last status report of every device seen since the server started
+    devices: HashMap<DeviceId, DeviceState>,
+
+    /// Query new connections for their state when it is unknown
+    state_recovery: bool,
+
+    /// Compression offered to new connections (None disables it)
+    compression: Option<CompressionConfig>,
+
+    /// How closely received message headers must follow the protocol
+    parse_mode: ParseMode,
+
+    /// Friendly names used in logs and connection info
+    labels: HashMap<DeviceId, DeviceLabel>,
+
+    /// Devices whose frames are logged
+    trace: ProtocolTrace,
+
+    /// Cluster-wide claim on devices (None accepts every device)
+    affinity: Option<Arc<dyn DeviceAffinity>>,
+
+    /// Keepalive answers and stale detection (None disables both)
+    heartbeat: Option<Heartbeat>,
+
+    /// Stale and recovered devices not yet drained
+    health_events: VecDeque<HealthEvent>,
+
+    /// Time a new connection has for the TLS handshake and first message
+    handshake_timeout: Duration,
+
+    /// TLS required on new connections (None accepts plain TCP)
+    #[cfg(feature = "tls")]
+    tls: Option<TlsServerConfig>,
+
+    /// Client certificates and the devices they authenticate
+    #[cfg(feature = "tls")]
+    certificates: DeviceCertificates,
+}
+
+/// Per-device signing key registered on the server
+#[derive(Debug, Clone)]
+struct SigningKey {
+    signer: MessageSigner,
+    /// Key replaced by the last rotation, still accepted at handshake
+    previous: Option<MessageSigner>,
+    /// Counters accepted from the device, shared by all its connections
+    guard: ReplayGuard,
+}
+
+/// Key and replay guard a signed connection verifies its messages with
+#[derive(Debug, Clone)]
+struct SessionSigner {
+    signer: MessageSigner,
+    guard: ReplayGuard,
+}
+
+impl SessionSigner {
+    /// Verify the signature and freshness of a message, then strip it
+    fn verify_and_strip(&self, message: &mut Message) -> turnkey_core::Result<()> {
+        let stamp = self.signer.verify(message)?;
+        self.guard.check(stamp)?;
+        MessageSigner::strip_signature(message);
+        Ok(())
+    }
 }
 
 impl TcpServer {
@@ -276,7 +817,7 @@ impl TcpServer {
     /// Returns an error if:
     /// - Address is already in use
     /// - Permission denied (e.g., binding to privileged port)
-    /// - Invalid address format
+    /// - Configuration is invalid (see `TcpServerConfig::validate()`)
     ///
     /// # Example
     ///
@@ -290,6 +831,7 @@ impl TcpServer {
     /// # }
     /// ```
     pub async fn bind(config: TcpServerConfig) -> Result<Self, TcpServerError> {
+        config.validate()?;
         info!("Binding TCP server to {}", config.bind_addr);
 
         let listener = TcpListener::bind(config.bind_addr)
@@ -304,7 +846,27 @@ impl TcpServer {
         Ok(Self {
             listener,
             connections: HashMap::new(),
+            standby: HashMap::new(),
+            duplicate_policy: DuplicatePolicy::default(),
+            connection_events: VecDeque::new(),
             config,
+            signing_keys: HashMap::new(),
+            unsigned_allowed: HashSet::new(),
+            command_policy: CommandPolicy::default(),
+            devices: HashMap::new(),
+            state_recovery: false,
+            compression: None,
+            parse_mode: ParseMode::default(),
+            labels: HashMap::new(),
+            trace: ProtocolTrace::new(),
+            affinity: None,
+            heartbeat: None,
+            health_events: VecDeque::new(),
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(feature = "tls")]
+            certificates: DeviceCertificates::new(),
         })
     }
 
@@ -355,85 +917,8 @@ impl TcpServer {
     pub async fn accept(&mut self) -> Result<(DeviceId, Message), TcpServerError> {
         loop {
             let (stream, addr) = self.listener.accept().await?;
-            debug!("Accepted new connection from {}", addr);
-
-            // Check max connections - reject this connection but keep accepting others
-            if self.connections.len() >= self.config.max_connections {
-                error!(
-                    addr = %addr,
-                    max_connections = self.config.max_connections,
-                    current_connections = self.connections.len(),
-                    "Connection rejected: maximum connections reached"
-                );
-
-                // NOTE: The Henry protocol does not define error response messages
-                // for connection rejection scenarios. The connection is closed
-                // immediately, and the client will receive a connection reset.
-                // For production deployments, consider:
-                // 1. Monitoring these rejection events
-                // 2. Alerting when rejection rate is high
-                // 3. Implementing custom error responses if protocol allows
-                drop(stream);
-                continue;
-            }
-
-            // Set TCP_NODELAY for low latency
-            if let Err(e) = stream.set_nodelay(true) {
-                warn!("Failed to set TCP_NODELAY for {}: {}", addr, e);
-            }
-
-            // Create framed connection and wait for first message to get device ID
-            let mut framed = Framed::new(stream, HenryCodec::new());
-            match framed.next().await {
-                Some(Ok(message)) => {
-                    let device_id = message.device_id;
-
-                    // Check for duplicate device ID
-                    if self.connections.contains_key(&device_id) {
-                        let existing_addr = self.connections[&device_id].addr;
-                        error!(
-                            device_id = %device_id,
-                            existing_addr = %existing_addr,
-                            duplicate_addr = %addr,
-                            "Connection rejected: device ID already connected"
-                        );
-
-                        // NOTE: The Henry protocol does not define error response messages
-                        // for duplicate device scenarios. The duplicate connection is closed
-                        // immediately to preserve the original connection.
-                        // Consider implementing device reconnection logic if needed.
-                        drop(framed);
-                        continue;
-                    }
-
-                    info!(
-                        "Device {} connected from {} (total: {})",
-                        device_id,
-                        addr,
-                        self.connections.len() + 1
-                    );
-
-                    // Create connection entry
-                    let conn = Connection {
-                        device_id,
-                        framed,
-                        addr,
-                        connected_at: Utc::now(),
-                    };
-                    self.connections.insert(device_id, conn);
-
-                    return Ok((device_id, message));
-                }
-                Some(Err(e)) => {
-                    error!("Failed to decode first message from {}: {}", addr, e);
-                    // Don't propagate error - log and continue accepting connections
-                    continue;
-                }
-                None => {
-                    warn!("Connection closed before first message from {}", addr);
-                    // Continue loop to accept next connection
-                    continue;
-                }
+            if let Some(accepted) = self.handshake(stream, addr).await {
+                return Ok(accepted);
             }
         }
     }
@@ -467,23 +952,49 @@ impl TcpServer {
     /// # }
     /// ```
     pub async fn recv(&mut self, device_id: DeviceId) -> Result<Option<Message>, TcpServerError> {
+        loop {
+            if let Some(result) = self.recv_once(device_id).await {
+                return result;
+            }
+        }
+    }
+
+    /// Receive one message from a device, or `None` for an answered keepalive
+    async fn recv_once(
+        &mut self,
+        device_id: DeviceId,
+    ) -> Option<Result<Option<Message>, TcpServerError>> {
         let Some(conn) = self.connections.get_mut(&device_id) else {
-            return Err(TcpServerError::DeviceNotConnected(device_id));
+            return Some(Err(TcpServerError::DeviceNotConnected(device_id)));
         };
 
-        match conn.recv().await {
+        let result = match conn.recv().await {
             Ok(Some(message)) => {
+                let addr = conn.addr;
+                if let Err(e) = self.authorize(device_id, &message, addr) {
+                    return Some(Err(e));
+                }
                 trace!(
                     device_id = %device_id,
                     command = ?message.command,
+                    correlation_id = message.correlation_id().map(tracing::field::display),
                     "Received message from device"
                 );
+                self.trace
+                    .record(device_id, TraceDirection::Received, &message);
+                self.record_status(device_id, &message);
+                if self.on_heard(device_id, &message).await {
+                    return None;
+                }
                 Ok(Some(message))
             }
             Ok(None) => {
                 // Connection gracefully closed by peer - this is expected
-                info!("Device {} disconnected gracefully", device_id);
-                self.connections.remove(&device_id);
+                info!(
+                    "Device {} disconnected gracefully",
+                    self.device_label(device_id)
+                );
+                self.remove_connection(device_id).await;
                 Ok(None)
             }
             Err(e) => {
@@ -499,6 +1010,15 @@ impl TcpServer {
                         );
                         Err(e)
                     }
+                    TcpServerError::InvalidSignature(_) => {
+                        // Possible spoofing - never keep a connection that failed verification
+                        error!(
+                            device_id = %device_id,
+                            "Signature verification failed (connection closed)"
+                        );
+                        self.remove_connection(device_id).await;
+                        Err(e)
+                    }
                     TcpServerError::Io(_) => {
                         // I/O error - connection is dead, remove it
                         error!(
@@ -506,7 +1026,7 @@ impl TcpServer {
                             error = %e,
                             "I/O error from device (connection closed)"
                         );
-                        self.connections.remove(&device_id);
+                        self.remove_connection(device_id).await;
                         Err(e)
                     }
                     _ => {
@@ -516,12 +1036,13 @@ impl TcpServer {
                             error = %e,
                             "Unexpected error from device (connection closed)"
                         );
-                        self.connections.remove(&device_id);
+                        self.remove_connection(device_id).await;
                         Err(e)
                     }
                 }
             }
-        }
+        };
+        Some(result)
     }
 
     /// Receive a message from any connected device (new or existing)
@@ -576,102 +1097,58 @@ impl TcpServer {
                 return self.accept().await;
             }
 
-            // Collect device IDs to avoid borrowing issues
+            // Collect device IDs to avoid borrowing issues; standby connections
+            // are polled after the primary ones, by index
             let device_ids: Vec<DeviceId> = self.connections.keys().copied().collect();
+            let standby_ids: Vec<(DeviceId, usize)> = self
+                .standby
+                .iter()
+                .flat_map(|(device_id, conns)| (0..conns.len()).map(|index| (*device_id, index)))
+                .collect();
 
             // Use tokio::select to wait for either a new connection or a message from existing ones
             tokio::select! {
                 // Wait for new connection
                 accept_result = self.listener.accept() => {
                     let (stream, addr) = accept_result?;
-                    debug!("Accepted new connection from {}", addr);
-
-                    // Check max connections
-                    if self.connections.len() >= self.config.max_connections {
-                        error!(
-                            addr = %addr,
-                            max_connections = self.config.max_connections,
-                            current_connections = self.connections.len(),
-                            "Connection rejected: maximum connections reached"
-                        );
-                        drop(stream);
-                        continue;
-                    }
-
-                    // Set TCP_NODELAY for low latency
-                    if let Err(e) = stream.set_nodelay(true) {
-                        warn!("Failed to set TCP_NODELAY for {}: {}", addr, e);
-                    }
-
-                    // Create framed connection and wait for first message
-                    let mut framed = Framed::new(stream, HenryCodec::new());
-                    match framed.next().await {
-                        Some(Ok(message)) => {
-                            let device_id = message.device_id;
-
-                            // Check for duplicate device ID
-                            if self.connections.contains_key(&device_id) {
-                                let existing_addr = self.connections[&device_id].addr;
-                                error!(
-                                    device_id = %device_id,
-                                    existing_addr = %existing_addr,
-                                    duplicate_addr = %addr,
-                                    "Connection rejected: device ID already connected"
-                                );
-                                drop(framed);
-                                continue;
-                            }
-
-                            info!(
-                                "Device {} connected from {} (total: {})",
-                                device_id,
-                                addr,
-                                self.connections.len() + 1
-                            );
-
-                            // Create connection entry
-                            let conn = Connection {
-                                device_id,
-                                framed,
-                                addr,
-                                connected_at: Utc::now(),
-                            };
-                            self.connections.insert(device_id, conn);
-
-                            return Ok((device_id, message));
-                        }
-                        Some(Err(e)) => {
-                            error!("Failed to decode first message from {}: {}", addr, e);
-                            continue;
-                        }
-                        None => {
-                            warn!("Connection closed before first message from {}", addr);
-                            continue;
-                        }
+                    if let Some(accepted) = self.handshake(stream, addr).await {
+                        return Ok(accepted);
                     }
+                    continue;
                 }
 
                 // Wait for message from any existing connection
                 // We poll each connection in round-robin fashion
                 msg_result = async {
-                    for device_id in device_ids {
-                        if let Some(conn) = self.connections.get_mut(&device_id) {
+                    let primary = device_ids.into_iter().map(|device_id| (device_id, None));
+                    let standby = standby_ids
+                        .into_iter()
+                        .map(|(device_id, index)| (device_id, Some(index)));
+                    for (device_id, index) in primary.chain(standby) {
+                        let conn = match index {
+                            None => self.connections.get_mut(&device_id),
+                            Some(index) => self
+                                .standby
+                                .get_mut(&device_id)
+                                .and_then(|conns| conns.get_mut(index)),
+                        };
+                        if let Some(conn) = conn {
                             // Try to receive without blocking
                             match tokio::time::timeout(
-                                std::time::Duration::from_millis(1),
+                                Duration::from_millis(1),
                                 conn.recv()
                             ).await {
                                 Ok(Ok(Some(message))) => {
-                                    return Some((device_id, Ok(message)));
+                                    return Some((device_id, index, Ok(message)));
                                 }
                                 Ok(Ok(None)) => {
                                     // Connection closed
-                                    return Some((device_id, Err(TcpServerError::Codec(
+                                    return Some((device_id, index, Err(TcpServerError::Codec(
                                         "Connection closed".to_string()
                                     ))));
                                 }
                                 Ok(Err(e)) => {
-                                    return Some((device_id, Err(e)));
+                                    return Some((device_id, index, Err(e)));
                                 }
                                 Err(_) => {
                                     // Timeout - try next connection
@@ -683,19 +1160,47 @@ impl TcpServer {
                     // No messages from any connection
                     None
                 } => {
-                    if let Some((device_id, result)) = msg_result {
+                    if let Some((device_id, Some(index), result)) = &msg_result {
+                        let (device_id, index) = (*device_id, *index);
+                        match result {
+                            // Replies go to the connection that spoke last
+                            Ok(_) => self.promote_standby(device_id, index),
+                            Err(e) => {
+                                info!(
+                                    "Extra connection of device {} closed: {}",
+                                    self.device_label(device_id),
+                                    e
+                                );
+                                self.remove_standby(device_id, index);
+                                continue;
+                            }
+                        }
+                    }
+                    if let Some((device_id, _, result)) = msg_result {
                         match result {
                             Ok(message) => {
+                                let addr = self.connections[&device_id].addr;
+                                self.authorize(device_id, &message, addr)?;
                                 trace!(
                                     device_id = %device_id,
                                     command = ?message.command,
+                                    correlation_id = message.correlation_id().map(tracing::field::display),
                                     "Received message from existing connection"
                                 );
+                                self.trace.record(device_id, TraceDirection::Received, &message);
+                                self.record_status(device_id, &message);
+                                if self.on_heard(device_id, &message).await {
+                                    continue;
+                                }
                                 return Ok((device_id, message));
                             }
                             Err(e) => {
-                                info!("Device {} disconnected: {}", device_id, e);
-                                self.connections.remove(&device_id);
+                                info!(
+                                    "Device {} disconnected: {}",
+                                    self.device_label(device_id),
+                                    e
+                                );
+                                self.remove_connection(device_id).await;
                                 continue;
                             }
                         }
@@ -743,6 +1248,7 @@ impl TcpServer {
         trace!(
             device_id = %device_id,
             command = ?message.command,
+            correlation_id = message.correlation_id().map(tracing::field::display),
             "Sending message to device"
         );
 
@@ -750,24 +1256,127 @@ impl TcpServer {
             return Err(TcpServerError::DeviceNotConnected(device_id));
         };
 
+        self.trace.record(device_id, TraceDirection::Sent, &message);
         conn.send(message).await
     }
 
-    /// Check if a specific device is connected
+    /// Send a command to every connected device of a group
     ///
-    /// Returns `true` if the device has an active connection.
+    /// `build` creates the message for each device, since the device ID is
+    /// part of the message header. A failed send does not stop the
+    /// broadcast; offline members and failures are listed in the report.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `build` for the first device it fails for;
+    /// messages already sent are not recalled.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_network::{DeviceGroup, TcpServer, TcpServerConfig};
+    /// use turnkey_protocol::{CommandCode, MessageBuilder};
     /// use turnkey_core::DeviceId;
     ///
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
-    /// let device_id = DeviceId::new(15)?;
-    ///
-    /// if server.is_connected(device_id) {
+    /// let group = DeviceGroup::new("Building A", [DeviceId::new(1)?, DeviceId::new(2)?]);
+    ///
+    /// let report = server
+    ///     .broadcast(&group, |device_id| {
+    ///         MessageBuilder::new(device_id, CommandCode::QueryStatus).build()
+    ///     })
+    ///     .await?;
+    /// println!("{} sent, {} offline", report.sent.len(), report.not_connected.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn broadcast<E>(
+        &mut self,
+        group: &DeviceGroup,
+        mut build: impl FnMut(DeviceId) -> Result<Message, E>,
+    ) -> Result<BroadcastReport, E> {
+        let mut report = BroadcastReport::default();
+
+        for &device_id in group.devices() {
+            let Some(conn) = self.connections.get_mut(&device_id) else {
+                report.not_connected.push(device_id);
+                continue;
+            };
+
+            match conn.send(build(device_id)?).await {
+                Ok(()) => report.sent.push(device_id),
+                Err(e) => {
+                    warn!(device_id = %device_id, group = group.name(), error = %e, "Broadcast send failed");
+                    report.failed.push((device_id, e));
+                }
+            }
+        }
+
+        debug!(
+            group = group.name(),
+            sent = report.sent.len(),
+            not_connected = report.not_connected.len(),
+            failed = report.failed.len(),
+            "Broadcast complete"
+        );
+        Ok(report)
+    }
+
+    /// Send a command to every connected device
+    ///
+    /// Same as [`broadcast()`](Self::broadcast) with a group of the devices
+    /// connected right now, e.g. to set the clock of all turnstiles. The
+    /// report never lists devices as not connected.
+    ///
+    /// # Errors
+    ///
+    /// As [`broadcast()`](Self::broadcast).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_protocol::{CommandCode, FieldData, MessageBuilder};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// let now = chrono::Local::now().format("%d/%m/%Y %H:%M:%S").to_string();
+    ///
+    /// let report = server
+    ///     .broadcast_all(|device_id| {
+    ///         MessageBuilder::new(device_id, CommandCode::SendDateTime)
+    ///             .field(FieldData::new(now.clone())?)
+    ///             .build()
+    ///     })
+    ///     .await?;
+    /// println!("clock set on {} devices", report.sent.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn broadcast_all<E>(
+        &mut self,
+        build: impl FnMut(DeviceId) -> Result<Message, E>,
+    ) -> Result<BroadcastReport, E> {
+        let group = DeviceGroup::new("all", self.connected_devices());
+        self.broadcast(&group, build).await
+    }
+
+    /// Check if a specific device is connected
+    ///
+    /// Returns `true` if the device has an active connection.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// let device_id = DeviceId::new(15)?;
+    ///
+    /// if server.is_connected(device_id) {
     ///     println!("Device {} is connected", device_id);
     /// }
     /// # Ok(())
@@ -777,6 +1386,39 @@ impl TcpServer {
         self.connections.contains_key(&device_id)
     }
 
+    /// Token cancelled when the device's current connection closes
+    ///
+    /// Work started for a request of the device (a validation, a database
+    /// query) can watch it to stop once nobody is left to answer. Closing
+    /// includes [`disconnect()`](Self::disconnect), the peer hanging up and
+    /// the connection being replaced by a duplicate. Cancelling the returned
+    /// token does not affect the connection.
+    ///
+    /// Returns `None` if the device is not connected.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// let (device_id, _request) = server.accept().await?;
+    ///
+    /// let closed = server.cancellation_token(device_id).unwrap();
+    /// tokio::spawn(async move {
+    ///     closed.cancelled().await;
+    ///     println!("Device {} went away", device_id);
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cancellation_token(&self, device_id: DeviceId) -> Option<CancellationToken> {
+        self.connections
+            .get(&device_id)
+            .map(|conn| conn.closed.child_token())
+    }
+
     /// Get list of all connected device IDs
     ///
     /// Returns a vector of device IDs for all active connections.
@@ -850,18 +1492,16 @@ impl TcpServer {
     /// # }
     /// ```
     pub fn connection_info(&self, device_id: DeviceId) -> Option<ConnectionInfo> {
-        self.connections.get(&device_id).map(|conn| ConnectionInfo {
-            device_id: conn.device_id(),
-            remote_addr: conn.remote_addr(),
-            connected_at: conn.connected_at(),
-            uptime: conn.uptime(),
-        })
+        self.connections
+            .get(&device_id)
+            .map(|conn| self.info_for(conn))
     }
 
     /// Get information about all active connections
     ///
-    /// Returns a vector of `ConnectionInfo` for all connected devices.
-    /// Useful for monitoring dashboards and status displays.
+    /// Returns a vector of `ConnectionInfo` for all connected devices, with
+    /// one entry per connection for devices that have several. Useful for
+    /// monitoring dashboards and status displays.
     ///
     /// # Example
     ///
@@ -881,19 +1521,1022 @@ impl TcpServer {
     pub fn all_connections_info(&self) -> Vec<ConnectionInfo> {
         self.connections
             .values()
-            .map(|conn| ConnectionInfo {
-                device_id: conn.device_id(),
-                remote_addr: conn.remote_addr(),
-                connected_at: conn.connected_at(),
-                uptime: conn.uptime(),
-            })
+            .chain(self.standby.values().flatten())
+            .map(|conn| self.info_for(conn))
             .collect()
     }
 
+    /// Snapshot of all connections, ordered by device
+    ///
+    /// Unlike [`all_connections_info()`](Self::all_connections_info), the
+    /// entries are sorted and can be looked up by device; see
+    /// [`ConnectionRegistry`].
+    pub fn registry(&self) -> ConnectionRegistry {
+        let mut connections: Vec<ConnectionInfo> = Vec::with_capacity(self.connection_count());
+        for (device_id, conn) in &self.connections {
+            connections.push(self.info_for(conn));
+            if let Some(extra) = self.standby.get(device_id) {
+                connections.extend(extra.iter().map(|conn| self.info_for(conn)));
+            }
+        }
+        // Stable: each device keeps its active connection first
+        connections.sort_by_key(|info| info.device_id.as_u8());
+        ConnectionRegistry { connections }
+    }
+
+    fn info_for(&self, conn: &Connection) -> ConnectionInfo {
+        ConnectionInfo {
+            device_id: conn.device_id(),
+            label: self.device_label(conn.device_id()),
+            remote_addr: conn.remote_addr(),
+            connected_at: conn.connected_at(),
+            uptime: conn.uptime(),
+            last_seen: conn.last_seen,
+            signed: conn.is_signed(),
+            compressed: conn.is_compressed(),
+            certified: conn.is_certified(),
+        }
+    }
+
+    /// Replace the command policy
+    ///
+    /// Applies to messages received from now on, including on connections
+    /// that are already open.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{CommandPolicy, DeviceRole, TcpServer, TcpServerConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.set_command_policy(
+    ///     CommandPolicy::default().with_role(DeviceId::new(99)?, DeviceRole::Management),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_command_policy(&mut self, policy: CommandPolicy) {
+        self.command_policy = policy;
+    }
+
+    /// Get the command policy in effect
+    pub fn command_policy(&self) -> &CommandPolicy {
+        &self.command_policy
+    }
+
+    /// Check a received message against the command policy
+    ///
+    /// The role is looked up by the device the connection belongs to, not by
+    /// the ID in the message header. Violations are written to the audit log.
+    fn authorize(
+        &self,
+        device_id: DeviceId,
+        message: &Message,
+        addr: SocketAddr,
+    ) -> Result<(), TcpServerError> {
+        if self.command_policy.is_allowed(device_id, message.command) {
+            return Ok(());
+        }
+
+        let role = self.command_policy.role_of(device_id);
+        warn!(
+            target: "turnkey::audit",
+            device_id = %device_id,
+            addr = %addr,
+            role = %role,
+            command = %message.command,
+            "Command rejected by policy"
+        );
+        Err(TcpServerError::CommandNotAllowed {
+            device_id,
+            role,
+            command: message.command,
+        })
+    }
+
+    /// Offer bulk-transfer compression to new connections
+    ///
+    /// Existing connections keep the setting they were opened with. `None`
+    /// (the default) disables compression.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_protocol::CompressionConfig;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.set_compression(Some(CompressionConfig::default()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_compression(&mut self, config: Option<CompressionConfig>) {
+        self.compression = config;
+    }
+
+    /// Accept non-canonical message headers on new connections
+    ///
+    /// Existing connections keep the mode they were opened with.
+    /// `ParseMode::Strict` (the default) rejects headers that do not follow
+    /// the protocol exactly.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_protocol::ParseMode;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.set_parse_mode(ParseMode::Tolerant);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.parse_mode = mode;
+    }
+
+    /// Codec for a new connection
+    fn new_codec(&self) -> HenryCodec {
+        let mut codec = HenryCodec::new().with_parse_mode(self.parse_mode);
+        codec.set_compression(self.compression);
+        codec
+    }
+
+    /// Query newly connected devices whose state is unknown
+    ///
+    /// When enabled, `accept()` and `recv_any()` send a status query to a
+    /// device that connects without the server having a report for it. The
+    /// answer arrives like any other message and updates the registry.
+    /// Disabled by default, since real Henry equipment may not answer.
+    pub fn set_state_recovery(&mut self, enabled: bool) {
+        self.state_recovery = enabled;
+    }
+
+    /// Ask a connected device to report its state
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device is not connected or the send fails.
+    pub async fn request_status(&mut self, device_id: DeviceId) -> Result<(), TcpServerError> {
+        let query = DeviceStatusReport::query(device_id)
+            .map_err(|e| TcpServerError::Codec(e.to_string()))?;
+        self.send(device_id, query).await
+    }
+
+    /// Get the last state reported by a device
+    ///
+    /// Returns `None` if the device has not reported since the server
+    /// started. The state is kept after the device disconnects.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.set_state_recovery(true);
+    ///
+    /// let (device_id, _) = server.accept().await?;
+    /// let _ = server.recv(device_id).await?; // status report
+    ///
+    /// if let Some(state) = server.device_state(device_id) {
+    ///     println!("Device {} has {} pending events",
+    ///         device_id, state.report.pending_events);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn device_state(&self, device_id: DeviceId) -> Option<&DeviceState> {
+        self.devices.get(&device_id)
+    }
+
+    /// Get the last reported state of every known device, by device ID
+    pub fn device_states(&self) -> Vec<DeviceState> {
+        let mut states: Vec<DeviceState> = self.devices.values().cloned().collect();
+        states.sort_by_key(|state| state.device_id.as_u8());
+        states
+    }
+
+    /// Set the friendly name shown for a device in logs and connection info
+    ///
+    /// Labels are display-only: messages on the wire always carry the
+    /// numeric ID. Replaces any label previously set for the same device.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_core::{DeviceId, DeviceLabel};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.set_device_label(DeviceLabel::named(DeviceId::new(15)?, "Portaria Norte")?);
+    ///
+    /// // Logs "Device Portaria Norte (15) connected from ..."
+    /// let (device_id, _) = server.accept().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_device_label(&mut self, label: DeviceLabel) {
+        self.labels.insert(label.id, label);
+    }
+
+    /// Forget the friendly name of a device
+    pub fn remove_device_label(&mut self, device_id: DeviceId) {
+        self.labels.remove(&device_id);
+    }
+
+    /// Get the label of a device
+    ///
+    /// Devices without a label get an unnamed one, which displays as the
+    /// numeric ID.
+    pub fn device_label(&self, device_id: DeviceId) -> DeviceLabel {
+        self.labels
+            .get(&device_id)
+            .cloned()
+            .unwrap_or_else(|| DeviceLabel::unnamed(device_id))
+    }
+
+    /// Get the handle controlling per-device protocol tracing
+    ///
+    /// Clones of the handle control the same server, so it can be moved to
+    /// a management task.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{DEFAULT_TRACE_DURATION, TcpServer, TcpServerConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server
+    ///     .protocol_trace()
+    ///     .enable(DeviceId::new(15)?, DEFAULT_TRACE_DURATION);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn protocol_trace(&self) -> &ProtocolTrace {
+        &self.trace
+    }
+
+    /// Share devices with other servers through a cluster-wide claim
+    ///
+    /// New connections are accepted only for devices the affinity grants to
+    /// this server. `None` accepts every device. Devices already connected
+    /// are not claimed retroactively, so install the affinity before serving.
+    pub fn set_device_affinity(&mut self, affinity: Option<Arc<dyn DeviceAffinity>>) {
+        self.affinity = affinity;
+    }
+
+    /// Claim a new device through the affinity, if one is installed
+    async fn claim_device(&self, device_id: DeviceId, addr: SocketAddr) -> bool {
+        let Some(affinity) = &self.affinity else {
+            return true;
+        };
+
+        let claimed = affinity.claim(device_id).await;
+        if !claimed {
+            error!(
+                device_id = %device_id,
+                addr = %addr,
+                "Connection rejected: device is held by another server"
+            );
+        }
+        claimed
+    }
+
+    /// Drop a connection and release its claim
+    ///
+    /// A standby connection of the device takes its place, in which case the
+    /// claim is kept.
+    async fn remove_connection(&mut self, device_id: DeviceId) -> Option<Connection> {
+        let conn = self.connections.remove(&device_id)?;
+        if let Some(next) = self.standby.get_mut(&device_id).and_then(Vec::pop) {
+            self.remove_standby_entry_if_empty(device_id);
+            self.connections.insert(device_id, next);
+        } else if let Some(affinity) = &self.affinity {
+            affinity.release(device_id).await;
+        }
+        Some(conn)
+    }
+
+    /// Choose what to do with the connections of a device that connects again
+    ///
+    /// The default keeps the first connection. See [`DuplicatePolicy`].
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicate_policy = policy;
+    }
+
+    /// Get the duplicate connection policy in effect
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
+    /// Answer keepalives and detect stale devices on this schedule
+    ///
+    /// `None` (the default) hands keepalives to the caller like any other
+    /// message and disables [`check_health()`](Self::check_health).
+    pub fn set_heartbeat(&mut self, heartbeat: Option<Heartbeat>) {
+        self.heartbeat = heartbeat;
+    }
+
+    /// Heartbeat schedule in effect
+    pub fn heartbeat(&self) -> Option<Heartbeat> {
+        self.heartbeat
+    }
+
+    /// Close new connections that take longer than this to say who they are
+    ///
+    /// Covers the TLS handshake and the first message together. Defaults to
+    /// [`DEFAULT_HANDSHAKE_TIMEOUT`].
+    pub fn set_handshake_timeout(&mut self, timeout: Duration) {
+        self.handshake_timeout = timeout;
+    }
+
+    /// Handshake timeout in effect
+    pub fn handshake_timeout(&self) -> Duration {
+        self.handshake_timeout
+    }
+
+    /// Mark devices silent for longer than the stale threshold
+    ///
+    /// Call it periodically, e.g. every heartbeat interval. Each device is
+    /// reported once as [`HealthEventKind::Stale`] until it speaks again;
+    /// its connection is left open. Returns the devices that became stale.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{Heartbeat, TcpServer, TcpServerConfig};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.set_heartbeat(Some(Heartbeat::default()));
+    ///
+    /// for device_id in server.check_health() {
+    ///     server.disconnect(device_id).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_health(&mut self) -> Vec<DeviceId> {
+        let Some(heartbeat) = self.heartbeat else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        let stale_after =
+            chrono::Duration::from_std(heartbeat.stale_after()).unwrap_or(chrono::Duration::MAX);
+
+        let mut stale = Vec::new();
+        for conn in self.connections.values_mut() {
+            let idle = now - conn.last_seen;
+            if !conn.stale && idle >= stale_after {
+                conn.stale = true;
+                stale.push((conn.device_id, idle.to_std().unwrap_or_default()));
+            }
+        }
+
+        stale.sort_by_key(|(device_id, _)| device_id.as_u8());
+        for &(device_id, idle) in &stale {
+            warn!(device_id = %device_id, idle_secs = idle.as_secs(), "Device stale");
+            self.record_health_event(device_id, HealthEventKind::Stale { idle });
+        }
+        stale.into_iter().map(|(device_id, _)| device_id).collect()
+    }
+
+    /// Take the stale and recovered device events recorded since the last call
+    ///
+    /// At most [`MAX_PENDING_HEALTH_EVENTS`] are kept; older events are
+    /// dropped when nobody drains them.
+    pub fn take_health_events(&mut self) -> Vec<HealthEvent> {
+        self.health_events.drain(..).collect()
+    }
+
+    /// Take the duplicate connection events recorded since the last call
+    ///
+    /// At most [`MAX_PENDING_CONNECTION_EVENTS`] are kept; older events are
+    /// dropped when nobody drains them.
+    pub fn take_connection_events(&mut self) -> Vec<ConnectionEvent> {
+        self.connection_events.drain(..).collect()
+    }
+
+    /// Number of open connections of a device
+    ///
+    /// Only `DuplicatePolicy::AllowMulti` yields more than one.
+    pub fn device_connection_count(&self, device_id: DeviceId) -> usize {
+        usize::from(self.connections.contains_key(&device_id))
+            + self.standby.get(&device_id).map_or(0, Vec::len)
+    }
+
+    /// Number of open connections, counting every connection of a device
+    fn connection_count(&self) -> usize {
+        self.connections.len() + self.standby.values().map(Vec::len).sum::<usize>()
+    }
+
+    /// Apply the duplicate policy to a new connection claiming `device_id`
+    ///
+    /// Returns `false` if the new connection must be closed.
+    fn admit_duplicate(&mut self, device_id: DeviceId, addr: SocketAddr) -> bool {
+        let Some(existing) = self.connections.get(&device_id) else {
+            return true;
+        };
+        if self.duplicate_policy != DuplicatePolicy::RejectNew {
+            return true;
+        }
+
+        let existing_addr = existing.addr;
+        error!(
+            device_id = %device_id,
+            existing_addr = %existing_addr,
+            duplicate_addr = %addr,
+            "Connection rejected: device ID already connected"
+        );
+        self.record_connection_event(
+            device_id,
+            ConnectionEventKind::Rejected,
+            existing_addr,
+            addr,
+        );
+        false
+    }
+
+    /// Track an accepted connection, closing or keeping the one it duplicates
+    fn insert_connection(&mut self, conn: Connection) {
+        let device_id = conn.device_id;
+        let addr = conn.addr;
+        let Some(existing) = self.connections.insert(device_id, conn) else {
+            return;
+        };
+
+        if self.duplicate_policy == DuplicatePolicy::AllowMulti {
+            info!(
+                device_id = %device_id,
+                existing_addr = %existing.addr,
+                new_addr = %addr,
+                "Additional connection for device"
+            );
+            self.record_connection_event(
+                device_id,
+                ConnectionEventKind::Added,
+                existing.addr,
+                addr,
+            );
+            self.standby.entry(device_id).or_default().push(existing);
+        } else {
+            warn!(
+                device_id = %device_id,
+                existing_addr = %existing.addr,
+                new_addr = %addr,
+                "Connection superseded by a new connection from the same device"
+            );
+            self.record_connection_event(
+                device_id,
+                ConnectionEventKind::Superseded,
+                existing.addr,
+                addr,
+            );
+            // Dropping the old connection closes its socket
+        }
+    }
+
+    /// Make a standby connection the one replies are sent to
+    fn promote_standby(&mut self, device_id: DeviceId, index: usize) {
+        let Some(conns) = self.standby.get_mut(&device_id) else {
+            return;
+        };
+        if index >= conns.len() {
+            return;
+        }
+        let conn = conns.swap_remove(index);
+        match self.connections.insert(device_id, conn) {
+            Some(previous) => conns.push(previous),
+            None => self.remove_standby_entry_if_empty(device_id),
+        }
+    }
+
+    /// Drop a standby connection
+    fn remove_standby(&mut self, device_id: DeviceId, index: usize) {
+        if let Some(conns) = self.standby.get_mut(&device_id)
+            && index < conns.len()
+        {
+            conns.swap_remove(index);
+        }
+        self.remove_standby_entry_if_empty(device_id);
+    }
+
+    fn remove_standby_entry_if_empty(&mut self, device_id: DeviceId) {
+        if self.standby.get(&device_id).is_some_and(Vec::is_empty) {
+            self.standby.remove(&device_id);
+        }
+    }
+
+    fn record_connection_event(
+        &mut self,
+        device_id: DeviceId,
+        kind: ConnectionEventKind,
+        existing_addr: SocketAddr,
+        new_addr: SocketAddr,
+    ) {
+        if self.connection_events.len() >= MAX_PENDING_CONNECTION_EVENTS {
+            self.connection_events.pop_front();
+        }
+        self.connection_events.push_back(ConnectionEvent {
+            device_id,
+            kind,
+            existing_addr,
+            new_addr,
+            at: Utc::now(),
+        });
+    }
+
+    /// Record the message in the device registry if it is a status report
+    fn record_status(&mut self, device_id: DeviceId, message: &Message) {
+        if !DeviceStatusReport::is_report(message) {
+            return;
+        }
+
+        match DeviceStatusReport::parse(message) {
+            Ok(report) => {
+                let recovered = !self.devices.contains_key(&device_id);
+                self.devices.insert(
+                    device_id,
+                    DeviceState {
+                        device_id,
+                        report,
+                        reported_at: Utc::now(),
+                    },
+                );
+                if recovered {
+                    info!(
+                        device_id = %device_id,
+                        mode = ?report.validation_mode,
+                        pending_events = report.pending_events,
+                        "Device state recovered from status report"
+                    );
+                } else {
+                    debug!(device_id = %device_id, "Device state updated");
+                }
+            }
+            Err(e) => {
+                warn!(device_id = %device_id, error = %e, "Ignoring malformed status report");
+            }
+        }
+    }
+
+    /// Heartbeat bookkeeping for a message received from a device
+    ///
+    /// Returns `true` if the message was a keepalive that has been answered
+    /// and must not be handed to the caller.
+    async fn on_heard(&mut self, device_id: DeviceId, message: &Message) -> bool {
+        let Some(conn) = self.connections.get_mut(&device_id) else {
+            return false;
+        };
+        conn.last_seen = Utc::now();
+        if std::mem::take(&mut conn.stale) {
+            info!(device_id = %device_id, "Stale device heard from again");
+            self.record_health_event(device_id, HealthEventKind::Recovered);
+        }
+
+        if self.heartbeat.is_none() || !heartbeat::is_keepalive(message) {
+            return false;
+        }
+        trace!(device_id = %device_id, "Answering keepalive");
+        if let Some(conn) = self.connections.get_mut(&device_id)
+            && let Err(e) = conn.send(heartbeat::keepalive(device_id)).await
+        {
+            warn!(device_id = %device_id, error = %e, "Failed to answer keepalive");
+        }
+        true
+    }
+
+    fn record_health_event(&mut self, device_id: DeviceId, kind: HealthEventKind) {
+        if self.health_events.len() >= MAX_PENDING_HEALTH_EVENTS {
+            self.health_events.pop_front();
+        }
+        self.health_events.push_back(HealthEvent {
+            device_id,
+            kind,
+            at: Utc::now(),
+        });
+    }
+
+    /// Registry bookkeeping for a connection that was just accepted
+    async fn on_connected(&mut self, device_id: DeviceId, first_message: &Message) {
+        self.trace
+            .record(device_id, TraceDirection::Received, first_message);
+        self.record_status(device_id, first_message);
+        // The first message identifies the device and is always returned,
+        // but a keepalive still gets its answer
+        self.on_heard(device_id, first_message).await;
+
+        if self.state_recovery && !self.devices.contains_key(&device_id) {
+            debug!(device_id = %device_id, "Device state unknown - requesting status");
+            if let Err(e) = self.request_status(device_id).await {
+                warn!(device_id = %device_id, error = %e, "Failed to request device status");
+            }
+        }
+    }
+
+    /// Register the shared secret of a device
+    ///
+    /// Once registered, the device must sign its messages: unsigned
+    /// connections from it are rejected at handshake unless
+    /// [`set_unsigned_allowed()`](Self::set_unsigned_allowed) says otherwise.
+    /// Replaces any previous key; existing connections keep the signer they
+    /// negotiated, and counters already accepted from the device stay used.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use turnkey_network::{TcpServer, TcpServerConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+    /// server.register_signing_key(DeviceId::new(15)?, b"shared-secret".to_vec());
+    ///
+    /// // Messages from device 15 are verified before being returned
+    /// let (device_id, message) = server.accept().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_signing_key(&mut self, device_id: DeviceId, key: impl Into<Vec<u8>>) {
+        self.insert_signing_key(device_id, MessageSigner::new(key), None);
+    }
+
+    /// Register the signing key of a device from a secret provider
+    ///
+    /// Reads the secret named by
+    /// [`names::device_signing_key()`](turnkey_core::secrets::names::device_signing_key).
+    /// If the secret was rotated, the previous value is still accepted from
+    /// connections that open with it, so devices can be reconfigured one by
+    /// one; the connection keeps that key until it is closed. As with
+    /// [`register_signing_key()`](Self::register_signing_key), the device
+    /// must sign its messages from then on.
+    ///
+    /// Returns `false`, leaving any registered key untouched, if the
+    /// provider has no key for the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns the provider error if the secret cannot be read.
+    pub fn load_signing_key(
+        &mut self,
+        secrets: &dyn SecretProvider,
+        device_id: DeviceId,
+    ) -> turnkey_core::Result<bool> {
+        let name = names::device_signing_key(device_id);
+        let Some(key) = secrets.get(&name)? else {
+            return Ok(false);
+        };
+        let previous = secrets.previous(&name)?;
+
+        debug!(
+            device_id = %device_id,
+            backend = secrets.backend(),
+            rotated = previous.is_some(),
+            "Signing key loaded"
+        );
+        self.insert_signing_key(
+            device_id,
+            MessageSigner::from_secret(key),
+            previous.map(MessageSigner::from_secret),
+        );
+        Ok(true)
+    }
+
+    /// Store a device key, keeping the replay guard of the key it replaces
+    fn insert_signing_key(
+        &mut self,
+        device_id: DeviceId,
+        signer: MessageSigner,
+        previous: Option<MessageSigner>,
+    ) {
+        let guard = self
+            .signing_keys
+            .get(&device_id)
+            .map(|key| key.guard.clone())
+            .unwrap_or_default();
+        self.signing_keys.insert(
+            device_id,
+            SigningKey {
+                signer,
+                previous,
+                guard,
+            },
+        );
+    }
+
+    /// Remove the shared secret of a device
+    ///
+    /// Returns `true` if a key was registered.
+    pub fn remove_signing_key(&mut self, device_id: DeviceId) -> bool {
+        self.signing_keys.remove(&device_id).is_some()
+    }
+
+    /// Let a device with a key still open unsigned connections
+    ///
+    /// Meant for rolling keys out to devices one at a time. Signed
+    /// connections from the device are verified as usual. Off by default.
+    pub fn set_unsigned_allowed(&mut self, device_id: DeviceId, allowed: bool) {
+        if allowed {
+            self.unsigned_allowed.insert(device_id);
+        } else {
+            self.unsigned_allowed.remove(&device_id);
+        }
+    }
+
+    /// Negotiate signing from the first message of a connection
+    ///
+    /// Returns `None` if the connection must be rejected, otherwise the
+    /// signer for the connection (`Some(None)` when unsigned). The signature
+    /// of an accepted first message is stripped. A first message that is
+    /// stale or replays a counter already accepted from the device is
+    /// rejected like an invalid signature.
+    fn negotiate_signing(
+        &self,
+        message: &mut Message,
+        addr: SocketAddr,
+    ) -> Option<Option<SessionSigner>> {
+        let device_id = message.device_id;
+        let signed = MessageSigner::is_signed(message);
+
+        match (self.signing_keys.get(&device_id), signed) {
+            (Some(key), true) => {
+                let verified = key
+                    .signer
+                    .verify(message)
+                    .map(|stamp| (&key.signer, stamp))
+                    .or_else(|e| {
+                        key.previous
+                            .iter()
+                            .find_map(|previous| Some((previous, previous.verify(message).ok()?)))
+                            .ok_or(e)
+                    });
+                let accepted = verified.and_then(|(signer, stamp)| {
+                    key.guard.check(stamp)?;
+                    Ok(signer)
+                });
+                match accepted {
+                    Ok(signer) => {
+                        if std::ptr::eq(signer, &key.signer) {
+                            debug!(device_id = %device_id, "Signed connection negotiated");
+                        } else {
+                            warn!(
+                                device_id = %device_id,
+                                addr = %addr,
+                                "Signed connection negotiated with the previous key"
+                            );
+                        }
+                        MessageSigner::strip_signature(message);
+                        Some(Some(SessionSigner {
+                            signer: signer.clone(),
+                            guard: key.guard.clone(),
+                        }))
+                    }
+                    Err(e) => {
+                        error!(
+                            device_id = %device_id,
+                            addr = %addr,
+                            error = %e,
+                            "Connection rejected: invalid signature"
+                        );
+                        None
+                    }
+                }
+            }
+            (Some(_), false) if !self.unsigned_allowed.contains(&device_id) => {
+                error!(
+                    device_id = %device_id,
+                    addr = %addr,
+                    "Connection rejected: device must sign its messages"
+                );
+                None
+            }
+            (None, true) => {
+                error!(
+                    device_id = %device_id,
+                    addr = %addr,
+                    "Connection rejected: signed message from device without a key"
+                );
+                None
+            }
+            _ => Some(None),
+        }
+    }
+
+    /// Require TLS with client certificates on new connections
+    ///
+    /// `None` goes back to plain TCP. Existing connections are unaffected,
+    /// so calling this again with a renewed server certificate rotates it
+    /// without dropping devices.
+    #[cfg(feature = "tls")]
+    pub fn set_tls(&mut self, config: Option<TlsServerConfig>) {
+        self.tls = config;
+    }
+
+    /// Client certificates accepted for each device
+    #[cfg(feature = "tls")]
+    pub fn device_certificates(&self) -> &DeviceCertificates {
+        &self.certificates
+    }
+
+    /// Mutable access to the client certificate mapping
+    ///
+    /// Changes apply to the next handshake; a device whose certificate is
+    /// revoked stays connected until it is disconnected.
+    #[cfg(feature = "tls")]
+    pub fn device_certificates_mut(&mut self) -> &mut DeviceCertificates {
+        &mut self.certificates
+    }
+
+    /// Admit an accepted socket as a device connection
+    ///
+    /// Runs every check a new connection goes through: connection limit,
+    /// TLS, first message, certificate claim, signing, command policy,
+    /// duplicates and cluster affinity. The TLS handshake and the first
+    /// message must complete within the handshake timeout, so a peer that
+    /// connects and stays silent cannot hold up `accept()`/`recv_any()`.
+    ///
+    /// Returns `None` if the connection was rejected (it is closed and
+    /// the reason logged), otherwise the device ID and its first message.
+    async fn handshake(
+        &mut self,
+        stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Option<(DeviceId, Message)> {
+        debug!("Accepted new connection from {}", addr);
+
+        // Check max connections - reject this connection but keep accepting others
+        if self.connection_count() >= self.config.max_connections {
+            error!(
+                addr = %addr,
+                max_connections = self.config.max_connections,
+                current_connections = self.connection_count(),
+                "Connection rejected: maximum connections reached"
+            );
+
+            // NOTE: The Henry protocol does not define error response messages
+            // for connection rejection scenarios. The connection is closed
+            // immediately, and the client will receive a connection reset.
+            // For production deployments, consider:
+            // 1. Monitoring these rejection events
+            // 2. Alerting when rejection rate is high
+            // 3. Implementing custom error responses if protocol allows
+            return None;
+        }
+
+        // Set TCP_NODELAY for low latency
+        if let Err(e) = stream.set_nodelay(true) {
+            warn!("Failed to set TCP_NODELAY for {}: {}", addr, e);
+        }
+
+        // Wait for the first message to get the device ID
+        let opened = tokio::time::timeout(self.handshake_timeout, async {
+            let (transport, certified) = self.open_transport(stream, addr).await?;
+            let mut framed = Framed::new(transport, self.new_codec());
+            let first = framed.next().await;
+            Some((framed, certified, first))
+        })
+        .await;
+        let (framed, certified, mut message) = match opened {
+            Ok(Some((framed, certified, Some(Ok(message))))) => (framed, certified, message),
+            Ok(Some((_, _, Some(Err(e))))) => {
+                error!("Failed to decode first message from {}: {}", addr, e);
+                return None;
+            }
+            Ok(Some((_, _, None))) => {
+                warn!("Connection closed before first message from {}", addr);
+                return None;
+            }
+            Ok(None) => return None,
+            Err(_) => {
+                error!(
+                    addr = %addr,
+                    timeout_ms = self.handshake_timeout.as_millis(),
+                    "Connection rejected: handshake timed out"
+                );
+                return None;
+            }
+        };
+        let device_id = message.device_id;
+
+        if !Self::claim_matches(certified, device_id, addr) {
+            return None;
+        }
+
+        let signer = self.negotiate_signing(&mut message, addr)?;
+
+        if self.authorize(device_id, &message, addr).is_err() {
+            return None;
+        }
+
+        // Check for duplicate device ID
+        // NOTE: The Henry protocol does not define error response messages
+        // for duplicate device scenarios. A rejected connection is closed
+        // immediately.
+        let duplicate = self.connections.contains_key(&device_id);
+        if !self.admit_duplicate(device_id, addr) {
+            return None;
+        }
+
+        // A connected device is already claimed by this server
+        if !duplicate && !self.claim_device(device_id, addr).await {
+            return None;
+        }
+
+        info!(
+            "Device {} connected from {} (total: {})",
+            self.device_label(device_id),
+            addr,
+            self.connection_count() + 1
+        );
+
+        // Create connection entry
+        let conn = Connection {
+            device_id,
+            framed,
+            addr,
+            connected_at: Utc::now(),
+            signer,
+            certified: certified.is_some(),
+            closed: CancellationToken::new(),
+            last_seen: Utc::now(),
+            stale: false,
+        };
+        self.insert_connection(conn);
+        self.on_connected(device_id, &message).await;
+
+        Some((device_id, message))
+    }
+
+    /// Wrap an accepted socket, performing the TLS handshake when enabled
+    ///
+    /// Returns `None` if the connection must be rejected, otherwise the
+    /// transport and the device proven by the client certificate.
+    async fn open_transport(
+        &self,
+        stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Option<(Transport, Option<DeviceId>)> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            let stream = match tls.acceptor().accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!(addr = %addr, error = %e, "Connection rejected: TLS handshake failed");
+                    return None;
+                }
+            };
+
+            let device_id = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|cert| self.certificates.device_for(cert));
+            let Some(device_id) = device_id else {
+                error!(
+                    addr = %addr,
+                    "Connection rejected: client certificate is not mapped to a device"
+                );
+                return None;
+            };
+
+            debug!(device_id = %device_id, addr = %addr, "TLS client certificate accepted");
+            return Some((Transport::Tls(Box::new(stream)), Some(device_id)));
+        }
+
+        trace!(addr = %addr, "Plain TCP connection");
+        Some((Transport::Plain(stream), None))
+    }
+
+    /// Check the device ID of a first message against the client certificate
+    fn claim_matches(certified: Option<DeviceId>, device_id: DeviceId, addr: SocketAddr) -> bool {
+        match certified {
+            Some(certified) if certified != device_id => {
+                error!(
+                    device_id = %certified,
+                    claimed = %device_id,
+                    addr = %addr,
+                    "Connection rejected: message claims another device than its certificate"
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+
     /// Disconnect a specific device
     ///
-    /// Closes the connection to the specified device and removes it from
-    /// the connection tracking. This method is idempotent.
+    /// Closes every connection of the specified device and removes them
+    /// from the connection tracking. This method is idempotent.
     ///
     /// # Errors
     ///
@@ -916,12 +2559,14 @@ impl TcpServer {
     /// # }
     /// ```
     pub async fn disconnect(&mut self, device_id: DeviceId) -> Result<(), TcpServerError> {
-        if let Some(conn) = self.connections.remove(&device_id) {
+        // Extra connections go first, so none of them is promoted
+        self.standby.remove(&device_id);
+        if let Some(conn) = self.remove_connection(device_id).await {
             info!(
                 "Disconnecting device {} from {} (total: {})",
                 device_id,
                 conn.addr,
-                self.connections.len()
+                self.connection_count()
             );
             // Connection is dropped automatically, closing the socket
             Ok(())
@@ -954,6 +2599,26 @@ mod tests {
         assert!(server.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_protocol_trace_handle_is_shared() {
+        let config = TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            max_connections: 10,
+        };
+        let server = TcpServer::bind(config).await.unwrap();
+        let device_id = DeviceId::new(15).unwrap();
+
+        let handle = server.protocol_trace().clone();
+        tokio::spawn(async move {
+            handle.enable(device_id, crate::DEFAULT_TRACE_DURATION);
+        })
+        .await
+        .unwrap();
+
+        assert!(server.protocol_trace().is_enabled(device_id));
+        assert_eq!(server.protocol_trace().active().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_server_bind_invalid_address() {
         // Try to bind to an invalid address format
@@ -968,6 +2633,17 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_server_bind_rejects_invalid_config() {
+        let config = TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            max_connections: 0,
+        };
+
+        let result = TcpServer::bind(config).await;
+        assert!(matches!(result, Err(TcpServerError::InvalidConfig(_))));
+    }
+
     #[tokio::test]
     async fn test_is_connected_empty() {
         let config = TcpServerConfig {
@@ -981,6 +2657,27 @@ mod tests {
         assert!(!server.is_connected(device_id));
     }
 
+    #[tokio::test]
+    async fn test_device_labels() {
+        let config = TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            max_connections: 10,
+        };
+
+        let mut server = TcpServer::bind(config).await.unwrap();
+        let device_id = DeviceId::new(15).unwrap();
+        assert_eq!(server.device_label(device_id).to_string(), "15");
+
+        server.set_device_label(DeviceLabel::named(device_id, "Portaria Norte").unwrap());
+        assert_eq!(
+            server.device_label(device_id).to_string(),
+            "Portaria Norte (15)"
+        );
+
+        server.remove_device_label(device_id);
+        assert_eq!(server.device_label(device_id).name, None);
+    }
+
     #[tokio::test]
     async fn test_connected_devices_empty() {
         let config = TcpServerConfig {
@@ -1006,6 +2703,31 @@ mod tests {
         assert!(matches!(result, Err(TcpServerError::DeviceNotConnected(_))));
     }
 
+    #[tokio::test]
+    async fn test_broadcast_reports_offline_devices() {
+        let config = TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            max_connections: 10,
+        };
+
+        let mut server = TcpServer::bind(config).await.unwrap();
+        let group = DeviceGroup::new(
+            "Zone",
+            [DeviceId::new(2).unwrap(), DeviceId::new(1).unwrap()],
+        );
+
+        let report = server
+            .broadcast(&group, |device_id| {
+                MessageBuilder::new(device_id, CommandCode::QueryStatus).build()
+            })
+            .await
+            .unwrap();
+
+        assert!(report.sent.is_empty());
+        assert_eq!(report.not_connected, group.devices());
+        assert!(!report.is_complete());
+    }
+
     #[tokio::test]
     async fn test_send_to_disconnected_device() {
         let config = TcpServerConfig {
@@ -0,0 +1,193 @@
+//! One server connection shared by several logical readers.
+//!
+//! A turnstile with an entry and an exit reader used to open one
+//! [`TcpClient`] per reader. [`SharedTcpClient`] lets any number of readers
+//! (typically one `OnlineValidator` each) use a single connection instead.
+//!
+//! # Multiplexing
+//!
+//! The Henry protocol has no request identifier, so responses can only be
+//! matched to requests by order. Each [`request()`](SharedTcpClient::request)
+//! is therefore a complete send-then-receive exchange performed while
+//! holding the connection; readers take turns rather than pipelining.
+//!
+//! # Fairness
+//!
+//! Exchanges are queued on a `tokio::sync::Mutex`, which grants the
+//! connection in FIFO order. A reader that keeps submitting requests cannot
+//! starve another one: every waiting reader gets its exchange before the
+//! first one gets the connection again.
+//!
+//! # Failure Handling
+//!
+//! Any error during an exchange closes the connection, because a late
+//! response would otherwise be delivered to the next reader. The same
+//! happens when a caller drops its `request()` future after sending: the
+//! next exchange finds the connection in an unknown state and reconnects.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use turnkey_network::{SharedTcpClient, TcpClientConfig};
+//! use turnkey_protocol::{CommandCode, MessageBuilder};
+//! use turnkey_core::DeviceId;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let shared = SharedTcpClient::new(TcpClientConfig::default());
+//! let entry = shared.clone();
+//! let exit = shared.clone();
+//!
+//! let device_id = DeviceId::new(15)?;
+//! let request = MessageBuilder::new(device_id, CommandCode::QueryStatus).build()?;
+//!
+//! // Both readers use the same connection, one exchange at a time
+//! let (a, b) = tokio::join!(entry.request(request.clone()), exit.request(request));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::{TcpClient, TcpClientConfig, TcpClientError};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+use turnkey_protocol::Message;
+
+/// Connection state guarded by the shared lock
+struct Connection {
+    client: TcpClient,
+
+    /// Set between send and receive; still set on lock means the previous
+    /// exchange was abandoned and its response may still arrive
+    in_exchange: bool,
+}
+
+/// TCP client shared by several logical readers over one connection
+///
+/// Cloning is cheap and every clone uses the same connection. The
+/// connection is opened on the first request and reopened after errors.
+///
+/// # Thread Safety
+///
+/// `SharedTcpClient` is `Send + Sync`; clones may be moved to different
+/// tasks.
+#[derive(Clone)]
+pub struct SharedTcpClient {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl std::fmt::Debug for SharedTcpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedTcpClient")
+            .field("handles", &self.handle_count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedTcpClient {
+    /// Create a shared client for the given server
+    ///
+    /// The connection is not opened until the first request.
+    pub fn new(config: TcpClientConfig) -> Self {
+        Self::from_client(TcpClient::new(config))
+    }
+
+    /// Share an existing client, connected or not
+    pub fn from_client(client: TcpClient) -> Self {
+        Self {
+            connection: Arc::new(Mutex::new(Connection {
+                client,
+                in_exchange: false,
+            })),
+        }
+    }
+
+    /// Send a message and wait for the server's response
+    ///
+    /// Waits for the connection if another reader is using it, connects if
+    /// needed, then sends `message` and receives the next message from the
+    /// server, each step under the client's configured timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns the underlying [`TcpClientError`]. The connection is closed
+    /// on error and reopened by the next request.
+    pub async fn request(&self, message: Message) -> Result<Message, TcpClientError> {
+        let mut connection = self.connection.lock().await;
+
+        if connection.in_exchange {
+            warn!("Previous exchange on shared connection was abandoned - reconnecting");
+            connection.client.close().await?;
+            connection.in_exchange = false;
+        }
+        if !connection.client.is_connected() {
+            connection.client.connect().await?;
+        }
+
+        connection.in_exchange = true;
+        let result = match connection.client.send(message).await {
+            Ok(()) => connection.client.recv().await,
+            Err(e) => Err(e),
+        };
+        connection.in_exchange = false;
+
+        if result.is_err() {
+            debug!("Exchange failed - closing shared connection");
+            if let Err(e) = connection.client.close().await {
+                warn!("Error closing shared connection: {}", e);
+            }
+        }
+
+        result
+    }
+
+    /// Check if the shared connection is open
+    pub async fn is_connected(&self) -> bool {
+        self.connection.lock().await.client.is_connected()
+    }
+
+    /// Close the shared connection for every reader
+    ///
+    /// Waits for the exchange in progress, if any. The next request
+    /// reconnects.
+    pub async fn close(&self) -> Result<(), TcpClientError> {
+        let mut connection = self.connection.lock().await;
+        connection.in_exchange = false;
+        connection.client.close().await
+    }
+
+    /// Number of clones currently sharing the connection
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.connection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_connection() {
+        let shared = SharedTcpClient::new(TcpClientConfig::default());
+        assert_eq!(shared.handle_count(), 1);
+
+        let entry = shared.clone();
+        let exit = shared.clone();
+        assert_eq!(entry.handle_count(), 3);
+
+        drop(exit);
+        assert_eq!(shared.handle_count(), 2);
+    }
+
+    #[test]
+    fn test_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedTcpClient>();
+    }
+
+    #[tokio::test]
+    async fn test_not_connected_initially() {
+        let shared = SharedTcpClient::new(TcpClientConfig::default());
+        assert!(!shared.is_connected().await);
+        shared.close().await.unwrap();
+    }
+}
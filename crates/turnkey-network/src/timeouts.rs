@@ -0,0 +1,181 @@
+//! Response timeouts per class of command.
+//!
+//! A turnstile holding a person at the reader cannot wait as long for an
+//! access decision as a sync job can for a card list. [`CommandTimeouts`]
+//! sets how long [`TcpClient::request()`](crate::TcpClient::request) waits
+//! for the answer to each [`CommandClass`]:
+//!
+//! | Class | Commands | Default |
+//! |-------|----------|---------|
+//! | Validation | access requests, grants, denials, rotation status | 3s |
+//! | Sync | card, user, biometric, log and configuration transfers | 60s |
+//! | Display | status queries, self-tests and date/time updates | 1s |
+//!
+//! The class timeout bounds the wait for the response only. Connecting and
+//! writing still use the socket-level `TcpClientConfig::timeout`, so a
+//! 60-second sync does not also allow a 60-second connect.
+
+use std::fmt;
+use std::time::Duration;
+use turnkey_protocol::CommandCode;
+
+/// Default response timeout for validation commands (3 seconds)
+pub const DEFAULT_VALIDATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Default response timeout for sync transfers (60 seconds)
+pub const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default response timeout for display commands (1 second)
+pub const DEFAULT_DISPLAY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Group of commands sharing a response timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandClass {
+    /// Access decisions and turnstile status, answered while someone waits
+    Validation,
+
+    /// Bulk transfers of cards, users, templates, logs and configuration,
+    /// and peripheral diagnostics, whose test reads take seconds
+    Sync,
+
+    /// Short exchanges with the device panel: status query, self-test and clock
+    Display,
+}
+
+impl CommandClass {
+    /// Class a command belongs to
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turnkey_network::CommandClass;
+    /// use turnkey_protocol::CommandCode;
+    ///
+    /// assert_eq!(CommandClass::of(CommandCode::AccessRequest), CommandClass::Validation);
+    /// assert_eq!(CommandClass::of(CommandCode::SendCards), CommandClass::Sync);
+    /// assert_eq!(CommandClass::of(CommandCode::QueryStatus), CommandClass::Display);
+    /// ```
+    pub fn of(command: CommandCode) -> Self {
+        match command {
+            CommandCode::SendConfig
+            | CommandCode::SendCards
+            | CommandCode::SendUsers
+            | CommandCode::SendBiometrics
+            | CommandCode::ReceiveLogs
+            | CommandCode::ReceiveConfig
+            | CommandCode::Diagnostics => Self::Sync,
+            CommandCode::QueryStatus | CommandCode::SelfTest | CommandCode::SendDateTime => {
+                Self::Display
+            }
+            CommandCode::AccessRequest
+            | CommandCode::GrantBoth
+            | CommandCode::GrantManual
+            | CommandCode::GrantEntry
+            | CommandCode::GrantExit
+            | CommandCode::DenyAccess
+            | CommandCode::DisplayOnly
+            | CommandCode::OverrideGrant
+            | CommandCode::WaitingRotation
+            | CommandCode::RotationCompleted
+            | CommandCode::RotationTimeout
+            | CommandCode::AlarmReport => Self::Validation,
+        }
+    }
+}
+
+impl fmt::Display for CommandClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validation => write!(f, "validation"),
+            Self::Sync => write!(f, "sync"),
+            Self::Display => write!(f, "display"),
+        }
+    }
+}
+
+/// Response timeout of each command class
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use turnkey_network::{CommandClass, CommandTimeouts};
+///
+/// let timeouts = CommandTimeouts {
+///     sync: Duration::from_secs(120),
+///     ..CommandTimeouts::default()
+/// };
+/// assert_eq!(timeouts.for_class(CommandClass::Sync), Duration::from_secs(120));
+/// assert_eq!(timeouts.for_class(CommandClass::Validation), Duration::from_secs(3));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandTimeouts {
+    /// Wait for access decisions (default: 3s)
+    pub validation: Duration,
+
+    /// Wait for sync transfers (default: 60s)
+    pub sync: Duration,
+
+    /// Wait for display commands (default: 1s)
+    pub display: Duration,
+}
+
+impl CommandTimeouts {
+    /// Timeout of a command class
+    pub fn for_class(&self, class: CommandClass) -> Duration {
+        match class {
+            CommandClass::Validation => self.validation,
+            CommandClass::Sync => self.sync,
+            CommandClass::Display => self.display,
+        }
+    }
+
+    /// Timeout of the class a command belongs to
+    pub fn for_command(&self, command: CommandCode) -> Duration {
+        self.for_class(CommandClass::of(command))
+    }
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        Self {
+            validation: DEFAULT_VALIDATION_TIMEOUT,
+            sync: DEFAULT_SYNC_TIMEOUT,
+            display: DEFAULT_DISPLAY_TIMEOUT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_timeouts() {
+        let timeouts = CommandTimeouts::default();
+        assert_eq!(timeouts.validation, Duration::from_secs(3));
+        assert_eq!(timeouts.sync, Duration::from_secs(60));
+        assert_eq!(timeouts.display, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_for_command_uses_class() {
+        let timeouts = CommandTimeouts::default();
+        assert_eq!(
+            timeouts.for_command(CommandCode::DenyAccess),
+            DEFAULT_VALIDATION_TIMEOUT
+        );
+        assert_eq!(
+            timeouts.for_command(CommandCode::RotationCompleted),
+            DEFAULT_VALIDATION_TIMEOUT
+        );
+        assert_eq!(
+            timeouts.for_command(CommandCode::ReceiveLogs),
+            DEFAULT_SYNC_TIMEOUT
+        );
+        assert_eq!(
+            timeouts.for_command(CommandCode::SendDateTime),
+            DEFAULT_DISPLAY_TIMEOUT
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! TLS client-certificate authentication for devices (feature `tls`)
+//!
+//! With TLS enabled on a [`TcpServer`](crate::TcpServer), every device must
+//! present a client certificate issued by one of the configured CAs, and
+//! the certificate must be mapped to a [`DeviceId`] in the server's
+//! [`DeviceCertificates`]. The device ID in the first message has to match
+//! the certificate, and so does every later message on the connection: a
+//! device certified as 15 cannot speak for device 16.
+//!
+//! Certificates are identified by the SHA-256 fingerprint of their DER
+//! encoding, so a re-issued certificate for the same device is a new
+//! mapping. [`DeviceCertificates::rotate()`] keeps the previous certificates
+//! of a device valid for a grace period while devices pick up the new one.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use turnkey_network::tls::TlsServerConfig;
+//! use turnkey_network::{TcpServer, TcpServerConfig};
+//! use turnkey_core::DeviceId;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let tls = TlsServerConfig::from_pem(
+//!     &std::fs::read("server.pem")?,
+//!     &std::fs::read("server.key")?,
+//!     &std::fs::read("devices-ca.pem")?,
+//! )?;
+//!
+//! let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+//! server.set_tls(Some(tls));
+//! server
+//!     .device_certificates_mut()
+//!     .register_pem(DeviceId::new(15)?, &std::fs::read("device-15.pem")?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::pki_types::pem::{self, PemObject};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::{VerifierBuilderError, WebPkiClientVerifier};
+use tokio_rustls::rustls::{self, RootCertStore, ServerConfig, crypto};
+use turnkey_core::DeviceId;
+use turnkey_core::secrets::{SecretProvider, names};
+
+/// Errors building a TLS configuration or certificate mapping
+#[derive(Debug, Error)]
+pub enum TlsConfigError {
+    /// Malformed PEM input
+    #[error("Invalid PEM data: {0}")]
+    Pem(#[from] pem::Error),
+
+    /// Certificate chain, key or protocol setup rejected by rustls
+    #[error("Invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+
+    /// Client CA roots unusable for verification
+    #[error("Invalid client CA: {0}")]
+    ClientCa(#[from] VerifierBuilderError),
+
+    /// Fingerprint is not 64 hexadecimal characters
+    #[error("Invalid certificate fingerprint: {0}")]
+    InvalidFingerprint(String),
+
+    /// Server key missing from or unreadable in the secret provider
+    #[error("Server key unavailable: {0}")]
+    Secret(#[from] turnkey_core::Error),
+}
+
+/// Server side of TLS: the server's certificate and the CAs that issue
+/// device certificates
+///
+/// Every connection must complete a TLS handshake with a client
+/// certificate issued by one of the CAs.
+#[derive(Clone)]
+pub struct TlsServerConfig {
+    acceptor: TlsAcceptor,
+}
+
+impl TlsServerConfig {
+    /// Build from DER certificates and key
+    ///
+    /// `cert_chain` starts with the server certificate; `client_roots` are
+    /// the CAs allowed to issue device certificates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key does not match the certificate or the
+    /// roots cannot be used to verify clients.
+    pub fn new(
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+        client_roots: RootCertStore,
+    ) -> Result<Self, TlsConfigError> {
+        let provider = Arc::new(crypto::ring::default_provider());
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(client_roots), provider.clone())
+                .build()?;
+
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(cert_chain, key)?;
+
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        })
+    }
+
+    /// Build from PEM-encoded server chain, server key and client CAs
+    ///
+    /// # Errors
+    ///
+    /// Same as [`new()`](Self::new), plus `Pem` for malformed input.
+    pub fn from_pem(
+        cert_chain: &[u8],
+        key: &[u8],
+        client_ca: &[u8],
+    ) -> Result<Self, TlsConfigError> {
+        let chain = CertificateDer::pem_slice_iter(cert_chain).collect::<Result<Vec<_>, _>>()?;
+        let key = PrivateKeyDer::from_pem_slice(key)?;
+
+        let mut roots = RootCertStore::empty();
+        for ca in CertificateDer::pem_slice_iter(client_ca) {
+            roots.add(ca?)?;
+        }
+
+        Self::new(chain, key, roots)
+    }
+
+    /// Build from PEM inputs, taking the server key from a secret provider
+    ///
+    /// The key is read from
+    /// [`names::TLS_SERVER_KEY`](turnkey_core::secrets::names::TLS_SERVER_KEY);
+    /// certificates are public and stay on disk. The PEM copy of the key is
+    /// zeroed once parsed.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`from_pem()`](Self::from_pem), plus `Secret` if the key is
+    /// missing.
+    pub fn from_secret_key(
+        cert_chain: &[u8],
+        secrets: &dyn SecretProvider,
+        client_ca: &[u8],
+    ) -> Result<Self, TlsConfigError> {
+        let key = secrets.require(names::TLS_SERVER_KEY)?;
+        Self::from_pem(cert_chain, key.expose_secret(), client_ca)
+    }
+
+    pub(crate) fn acceptor(&self) -> &TlsAcceptor {
+        &self.acceptor
+    }
+}
+
+impl fmt::Debug for TlsServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsServerConfig").finish_non_exhaustive()
+    }
+}
+
+/// SHA-256 fingerprint of a DER-encoded certificate
+///
+/// Displayed and parsed as 64 lowercase hex characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CertFingerprint([u8; 32]);
+
+impl CertFingerprint {
+    /// Fingerprint of a certificate
+    pub fn of(cert: &CertificateDer<'_>) -> Self {
+        Self(Sha256::digest(cert.as_ref()).into())
+    }
+}
+
+impl fmt::Display for CertFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl FromStr for CertFingerprint {
+    type Err = TlsConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TlsConfigError::InvalidFingerprint(s.to_string());
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CertEntry {
+    device_id: DeviceId,
+    /// When the certificate stops being accepted (set by rotation)
+    retire_at: Option<DateTime<Utc>>,
+}
+
+/// Mapping of client certificates to the devices they authenticate
+///
+/// A device may have several certificates at once, which is how rotation
+/// works: register the new certificate with [`rotate()`](Self::rotate) and
+/// the old ones keep working until the grace period ends.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceCertificates {
+    entries: HashMap<CertFingerprint, CertEntry>,
+}
+
+impl DeviceCertificates {
+    /// Empty mapping (no device can connect over TLS)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map a certificate fingerprint to a device
+    ///
+    /// Replaces any previous mapping of the fingerprint.
+    pub fn register_fingerprint(&mut self, device_id: DeviceId, fingerprint: CertFingerprint) {
+        self.entries.insert(
+            fingerprint,
+            CertEntry {
+                device_id,
+                retire_at: None,
+            },
+        );
+    }
+
+    /// Map a certificate to a device and return its fingerprint
+    pub fn register(&mut self, device_id: DeviceId, cert: &CertificateDer<'_>) -> CertFingerprint {
+        let fingerprint = CertFingerprint::of(cert);
+        self.register_fingerprint(device_id, fingerprint);
+        fingerprint
+    }
+
+    /// Map the first certificate of a PEM file to a device
+    ///
+    /// # Errors
+    ///
+    /// Returns `Pem` if the input holds no certificate.
+    pub fn register_pem(
+        &mut self,
+        device_id: DeviceId,
+        pem: &[u8],
+    ) -> Result<CertFingerprint, TlsConfigError> {
+        let cert = CertificateDer::from_pem_slice(pem)?;
+        Ok(self.register(device_id, &cert))
+    }
+
+    /// Replace the certificates of a device, keeping the current ones valid
+    /// for `grace`
+    ///
+    /// Certificates already retiring keep their earlier deadline. A zero
+    /// grace period retires them immediately.
+    pub fn rotate(
+        &mut self,
+        device_id: DeviceId,
+        cert: &CertificateDer<'_>,
+        grace: Duration,
+    ) -> CertFingerprint {
+        let retire_at = Utc::now() + grace;
+        for entry in self
+            .entries
+            .values_mut()
+            .filter(|entry| entry.device_id == device_id)
+        {
+            entry.retire_at = Some(entry.retire_at.map_or(retire_at, |at| at.min(retire_at)));
+        }
+        self.register(device_id, cert)
+    }
+
+    /// Stop accepting a certificate; returns `false` if it was not mapped
+    pub fn revoke(&mut self, fingerprint: &CertFingerprint) -> bool {
+        self.entries.remove(fingerprint).is_some()
+    }
+
+    /// Stop accepting every certificate of a device; returns how many were
+    /// mapped
+    pub fn revoke_device(&mut self, device_id: DeviceId) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.device_id != device_id);
+        before - self.entries.len()
+    }
+
+    /// Device a certificate authenticates, if it is mapped and not retired
+    pub fn device_for(&self, cert: &CertificateDer<'_>) -> Option<DeviceId> {
+        self.device_for_fingerprint(&CertFingerprint::of(cert), Utc::now())
+    }
+
+    fn device_for_fingerprint(
+        &self,
+        fingerprint: &CertFingerprint,
+        now: DateTime<Utc>,
+    ) -> Option<DeviceId> {
+        self.entries
+            .get(fingerprint)
+            .filter(|entry| entry.retire_at.is_none_or(|at| now < at))
+            .map(|entry| entry.device_id)
+    }
+
+    /// Fingerprints currently accepted for a device
+    pub fn fingerprints(&self, device_id: DeviceId) -> Vec<CertFingerprint> {
+        let now = Utc::now();
+        self.entries
+            .iter()
+            .filter(|(fingerprint, entry)| {
+                entry.device_id == device_id
+                    && self.device_for_fingerprint(fingerprint, now).is_some()
+            })
+            .map(|(fingerprint, _)| *fingerprint)
+            .collect()
+    }
+
+    /// Drop certificates whose grace period has ended; returns how many
+    pub fn prune(&mut self) -> usize {
+        let now = Utc::now();
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, entry| entry.retire_at.is_none_or(|at| now < at));
+        before - self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cert(byte: u8) -> CertificateDer<'static> {
+        CertificateDer::from(vec![byte; 16])
+    }
+
+    #[test]
+    fn test_fingerprint_round_trip() {
+        let fingerprint = CertFingerprint::of(&cert(1));
+        let text = fingerprint.to_string();
+        assert_eq!(text.len(), 64);
+        assert_eq!(text.parse::<CertFingerprint>().unwrap(), fingerprint);
+        assert!("abc".parse::<CertFingerprint>().is_err());
+        assert!("zz".repeat(32).parse::<CertFingerprint>().is_err());
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_certificate_for_grace_period() {
+        let device = DeviceId::new(15).unwrap();
+        let mut certificates = DeviceCertificates::new();
+
+        certificates.register(device, &cert(1));
+        assert_eq!(certificates.device_for(&cert(1)), Some(device));
+        assert_eq!(certificates.device_for(&cert(2)), None);
+
+        certificates.rotate(device, &cert(2), Duration::hours(1));
+        assert_eq!(certificates.device_for(&cert(1)), Some(device));
+        assert_eq!(certificates.device_for(&cert(2)), Some(device));
+        assert_eq!(certificates.fingerprints(device).len(), 2);
+
+        // A later rotation without grace retires both earlier certificates
+        certificates.rotate(device, &cert(3), Duration::zero());
+        assert_eq!(certificates.device_for(&cert(1)), None);
+        assert_eq!(certificates.device_for(&cert(2)), None);
+        assert_eq!(certificates.device_for(&cert(3)), Some(device));
+        assert_eq!(certificates.prune(), 2);
+
+        assert_eq!(certificates.revoke_device(device), 1);
+        assert_eq!(certificates.device_for(&cert(3)), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Per-device protocol tracing that can be switched on at runtime.
+//!
+//! Frame-level logging for every device drowns the log of a busy server,
+//! and raising the global log level needs a restart. [`ProtocolTrace`]
+//! instead logs the frames of selected devices only, at info level under
+//! the `turnkey::protocol_trace` target, so the normal log configuration
+//! shows them.
+//!
+//! # Expiry
+//!
+//! Every trace is enabled for a limited time (at most
+//! [`MAX_TRACE_DURATION`]) and switches itself off afterwards, so a trace
+//! left on after a debugging session does not keep filling the log.
+//!
+//! # Runtime Control
+//!
+//! `ProtocolTrace` is a cheap handle over shared state. Clone the one from
+//! [`TcpServer::protocol_trace()`](crate::TcpServer::protocol_trace) into a
+//! management task to toggle tracing while the server loop keeps running:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use turnkey_network::{TcpServer, TcpServerConfig};
+//! use turnkey_core::DeviceId;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+//! let trace = server.protocol_trace().clone();
+//!
+//! tokio::spawn(async move {
+//!     // e.g. on an operator command
+//!     trace.enable(DeviceId::new(15).unwrap(), Duration::from_secs(10 * 60));
+//! });
+//!
+//! loop {
+//!     let (device_id, message) = server.recv_any().await?;
+//!     // frames of device 15 are now logged in both directions
+//! }
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tracing::info;
+use turnkey_core::DeviceId;
+use turnkey_protocol::{Frame, Message};
+
+/// Trace duration for callers without a specific need (15 minutes)
+pub const DEFAULT_TRACE_DURATION: Duration = Duration::from_secs(15 * 60);
+
+/// Longest time a trace stays enabled (4 hours)
+pub const MAX_TRACE_DURATION: Duration = Duration::from_secs(4 * 60 * 60);
+
+/// Direction of a traced frame, as seen from the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    /// Frame received from the device
+    Received,
+    /// Frame sent to the device
+    Sent,
+}
+
+impl fmt::Display for TraceDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TraceDirection::Received => "rx",
+            TraceDirection::Sent => "tx",
+        })
+    }
+}
+
+/// Devices whose frames are being logged, with their expiry
+///
+/// Clones share the same set of devices.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolTrace {
+    expiries: Arc<Mutex<HashMap<DeviceId, Instant>>>,
+}
+
+impl ProtocolTrace {
+    /// Create an empty trace set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log the frames of a device for the given time
+    ///
+    /// The duration is capped at [`MAX_TRACE_DURATION`]. Enabling a device
+    /// that is already traced restarts its timer. Returns the time the
+    /// trace will stay enabled.
+    pub fn enable(&self, device_id: DeviceId, duration: Duration) -> Duration {
+        let duration = duration.min(MAX_TRACE_DURATION);
+        self.lock().insert(device_id, Instant::now() + duration);
+        info!(
+            target: "turnkey::protocol_trace",
+            device_id = %device_id,
+            minutes = duration.as_secs() / 60,
+            "Protocol trace enabled"
+        );
+        duration
+    }
+
+    /// Stop logging the frames of a device
+    ///
+    /// Returns `false` if the device was not traced.
+    pub fn disable(&self, device_id: DeviceId) -> bool {
+        let removed = self.lock().remove(&device_id).is_some();
+        if removed {
+            info!(
+                target: "turnkey::protocol_trace",
+                device_id = %device_id,
+                "Protocol trace disabled"
+            );
+        }
+        removed
+    }
+
+    /// Whether the frames of a device are being logged
+    ///
+    /// An expired trace is removed and reported as disabled.
+    pub fn is_enabled(&self, device_id: DeviceId) -> bool {
+        let mut expiries = self.lock();
+        match expiries.get(&device_id) {
+            Some(&until) if until > Instant::now() => true,
+            Some(_) => {
+                expiries.remove(&device_id);
+                info!(
+                    target: "turnkey::protocol_trace",
+                    device_id = %device_id,
+                    "Protocol trace expired"
+                );
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Time left before the trace of a device expires
+    pub fn remaining(&self, device_id: DeviceId) -> Option<Duration> {
+        self.lock()
+            .get(&device_id)
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Traced devices and the time left for each, by device ID
+    pub fn active(&self) -> Vec<(DeviceId, Duration)> {
+        let now = Instant::now();
+        let mut active: Vec<(DeviceId, Duration)> = self
+            .lock()
+            .iter()
+            .filter(|&(_, &until)| until > now)
+            .map(|(&device_id, &until)| (device_id, until - now))
+            .collect();
+        active.sort_by_key(|(device_id, _)| device_id.as_u8());
+        active
+    }
+
+    /// Log a message in wire format if its device is traced
+    pub(crate) fn record(&self, device_id: DeviceId, direction: TraceDirection, message: &Message) {
+        if !self.is_enabled(device_id) {
+            return;
+        }
+
+        let frame = Frame::from(message.clone())
+            .to_string()
+            .unwrap_or_else(|e| format!("<{}>", e));
+        info!(
+            target: "turnkey::protocol_trace",
+            device_id = %device_id,
+            direction = %direction,
+            frame = %frame,
+            "Protocol frame"
+        );
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<DeviceId, Instant>> {
+        // The map stays consistent even if a holder panicked
+        self.expiries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enable_disable_per_device() {
+        let trace = ProtocolTrace::new();
+        let traced = DeviceId::new(15).unwrap();
+        let other = DeviceId::new(2).unwrap();
+
+        trace.enable(traced, DEFAULT_TRACE_DURATION);
+        assert!(trace.is_enabled(traced));
+        assert!(!trace.is_enabled(other));
+
+        // Clones share state, so a management task can toggle it
+        let handle = trace.clone();
+        assert!(handle.disable(traced));
+        assert!(!trace.is_enabled(traced));
+        assert!(!handle.disable(traced));
+    }
+
+    #[test]
+    fn test_trace_expires() {
+        let trace = ProtocolTrace::new();
+        let device_id = DeviceId::new(15).unwrap();
+
+        trace.enable(device_id, Duration::from_millis(20));
+        assert!(trace.remaining(device_id).is_some());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(trace.active().is_empty());
+        assert_eq!(trace.remaining(device_id), None);
+        assert!(!trace.is_enabled(device_id));
+    }
+
+    #[test]
+    fn test_duration_is_capped() {
+        let trace = ProtocolTrace::new();
+        let device_id = DeviceId::new(15).unwrap();
+
+        let granted = trace.enable(device_id, Duration::from_secs(7 * 24 * 60 * 60));
+        assert_eq!(granted, MAX_TRACE_DURATION);
+
+        let active = trace.active();
+        assert_eq!(active.len(), 1);
+        assert!(active[0].1 <= MAX_TRACE_DURATION);
+    }
+}
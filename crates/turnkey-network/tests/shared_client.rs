@@ -0,0 +1,151 @@
+//! Integration tests for SharedTcpClient
+//!
+//! These tests run several logical readers over one shared connection
+//! against a mock server and check that every reader gets its own response
+//! and that the server only sees one connection.
+
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_util::codec::Framed;
+use turnkey_core::DeviceId;
+use turnkey_network::{SharedTcpClient, TcpClientConfig};
+use turnkey_protocol::{CommandCode, FieldData, HenryCodec, Message, MessageBuilder};
+
+/// Start an echo server that replies after `delay` and counts connections
+async fn echo_server(delay: Duration) -> (TcpClientConfig, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    let counter = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut framed = Framed::new(stream, HenryCodec::new());
+                while let Some(Ok(msg)) = framed.next().await {
+                    tokio::time::sleep(delay).await;
+                    if framed.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    let config = TcpClientConfig {
+        server_addr: addr,
+        timeout: Duration::from_millis(1000),
+    };
+    (config, connections)
+}
+
+fn request(card_number: &str) -> Message {
+    MessageBuilder::new(DeviceId::new(15).unwrap(), CommandCode::AccessRequest)
+        .field(FieldData::new(card_number.to_string()).unwrap())
+        .build()
+        .unwrap()
+}
+
+/// Test two readers exchanging messages concurrently over one connection
+#[tokio::test]
+async fn test_readers_share_one_connection() {
+    let (config, connections) = echo_server(Duration::ZERO).await;
+    let shared = SharedTcpClient::new(config);
+
+    let reader = |shared: SharedTcpClient, prefix: &'static str| async move {
+        for i in 0..20 {
+            let card_number = format!("{}{:04}", prefix, i);
+            let response = shared.request(request(&card_number)).await.unwrap();
+            assert_eq!(response.field(0), Some(card_number.as_str()));
+        }
+    };
+
+    let entry = tokio::spawn(reader(shared.clone(), "1000"));
+    let exit = tokio::spawn(reader(shared.clone(), "2000"));
+    entry.await.unwrap();
+    exit.await.unwrap();
+
+    assert!(shared.is_connected().await);
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+/// Test that waiting readers are served in arrival order
+#[tokio::test]
+async fn test_readers_take_turns() {
+    let (config, _) = echo_server(Duration::from_millis(20)).await;
+    let shared = SharedTcpClient::new(config);
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let mut tasks = Vec::new();
+    for reader in 0..3 {
+        let shared = shared.clone();
+        let order = order.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..2 {
+                shared.request(request("12345678")).await.unwrap();
+                order.lock().unwrap().push(reader);
+            }
+        }));
+        // Make the readers queue up in a known order
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // No reader gets a second exchange while another is waiting
+    assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 0, 1, 2]);
+}
+
+/// Test that an abandoned exchange does not leak its response to the next reader
+#[tokio::test]
+async fn test_abandoned_exchange_reconnects() {
+    let (config, connections) = echo_server(Duration::from_millis(100)).await;
+    let shared = SharedTcpClient::new(config);
+
+    let abandoned = tokio::time::timeout(
+        Duration::from_millis(20),
+        shared.request(request("11111111")),
+    )
+    .await;
+    assert!(abandoned.is_err());
+
+    let response = shared.request(request("22222222")).await.unwrap();
+    assert_eq!(response.field(0), Some("22222222"));
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+/// Test reconnection after the server closes the connection
+#[tokio::test]
+async fn test_reconnects_after_server_close() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        // First connection: close without answering
+        let (stream, _) = listener.accept().await.unwrap();
+        drop(stream);
+
+        // Second connection: echo
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut framed = Framed::new(stream, HenryCodec::new());
+        while let Some(Ok(msg)) = framed.next().await {
+            framed.send(msg).await.unwrap();
+        }
+    });
+
+    let shared = SharedTcpClient::new(TcpClientConfig {
+        server_addr: addr,
+        timeout: Duration::from_millis(1000),
+    });
+
+    assert!(shared.request(request("11111111")).await.is_err());
+    assert!(!shared.is_connected().await);
+
+    let response = shared.request(request("22222222")).await.unwrap();
+    assert_eq!(response.field(0), Some("22222222"));
+}
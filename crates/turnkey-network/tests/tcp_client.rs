@@ -8,7 +8,7 @@ use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio_util::codec::Framed;
 use turnkey_core::DeviceId;
-use turnkey_network::{TcpClient, TcpClientConfig, TcpClientError};
+use turnkey_network::{CommandTimeouts, TcpClient, TcpClientConfig, TcpClientError};
 use turnkey_protocol::{CommandCode, FieldData, HenryCodec, MessageBuilder};
 
 /// Test basic connect-send-recv-close flow with echo server
@@ -461,3 +461,84 @@ async fn test_custom_timeout() {
     let result = client.recv().await;
     assert!(matches!(result, Err(TcpClientError::ReadTimeout(_))));
 }
+
+/// Test that request() waits for each command under its class timeout
+#[tokio::test]
+async fn test_request_class_timeouts() {
+    // Server that reads requests but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut framed = Framed::new(stream, HenryCodec::new());
+        while let Some(Ok(_)) = framed.next().await {}
+    });
+
+    let mut client = TcpClient::new(TcpClientConfig {
+        server_addr: addr,
+        timeout: Duration::from_millis(1000),
+    });
+    client.set_command_timeouts(CommandTimeouts {
+        validation: Duration::from_millis(150),
+        sync: Duration::from_secs(60),
+        display: Duration::from_millis(50),
+    });
+    client.connect().await.unwrap();
+
+    let device_id = DeviceId::new(15).unwrap();
+    let access = MessageBuilder::new(device_id, CommandCode::AccessRequest)
+        .build()
+        .unwrap();
+    let start = std::time::Instant::now();
+    let result = client.request(access).await;
+    assert!(matches!(
+        result,
+        Err(TcpClientError::ValidationTimeout(150))
+    ));
+    assert!(start.elapsed() < Duration::from_millis(1000));
+
+    let status = MessageBuilder::new(device_id, CommandCode::QueryStatus)
+        .build()
+        .unwrap();
+    let result = client.request(status).await;
+    assert!(matches!(result, Err(TcpClientError::DisplayTimeout(50))));
+}
+
+/// Test that a sync transfer may take longer than the socket timeout
+#[tokio::test]
+async fn test_request_sync_outlives_socket_timeout() {
+    // Server that answers after 300ms
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut framed = Framed::new(stream, HenryCodec::new());
+        while let Some(Ok(msg)) = framed.next().await {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            framed.send(msg).await.unwrap();
+        }
+    });
+
+    let mut client = TcpClient::new(TcpClientConfig {
+        server_addr: addr,
+        timeout: Duration::from_millis(100),
+    });
+    client.connect().await.unwrap();
+
+    let device_id = DeviceId::new(15).unwrap();
+    let cards = MessageBuilder::new(device_id, CommandCode::SendCards)
+        .build()
+        .unwrap();
+    let response = client.request(cards).await.unwrap();
+    assert_eq!(response.command, CommandCode::SendCards);
+
+    // A plain recv() is still bound by the socket timeout
+    let cards = MessageBuilder::new(device_id, CommandCode::SendCards)
+        .build()
+        .unwrap();
+    client.send(cards).await.unwrap();
+    let result = client.recv().await;
+    assert!(matches!(result, Err(TcpClientError::ReadTimeout(100))));
+}
@@ -4,9 +4,17 @@
 
 use std::time::Duration;
 use tokio::time::timeout;
-use turnkey_core::DeviceId;
-use turnkey_network::{TcpClient, TcpClientConfig, TcpServer, TcpServerConfig};
-use turnkey_protocol::{CommandCode, MessageBuilder};
+use turnkey_core::secrets::{FileSecrets, SecretBytes, SecretProvider, names};
+use turnkey_core::{DeviceId, ValidationMode};
+use turnkey_network::{
+    CommandPolicy, ConnectionEventKind, DeviceRole, DuplicatePolicy, HealthEventKind, Heartbeat,
+    TcpClient, TcpClientConfig, TcpServer, TcpServerConfig, TcpServerError,
+};
+use turnkey_protocol::commands::DeviceStatusReport;
+use turnkey_protocol::{
+    CommandCode, CompressionConfig, FieldData, MessageBuilder, MessageSigner, ParseMode,
+    SignatureStamp,
+};
 
 #[tokio::test]
 async fn test_single_client_connection() {
@@ -452,6 +460,158 @@ async fn test_duplicate_device_id_rejected() {
     assert_eq!(server.connected_devices().len(), 1);
 }
 
+/// Server on `port` with the given duplicate policy
+async fn bind_with_policy(port: u16, policy: DuplicatePolicy) -> TcpServer {
+    let mut server = TcpServer::bind(TcpServerConfig {
+        bind_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+    server.set_duplicate_policy(policy);
+    server
+}
+
+/// Connect as `device_id` and send one message
+async fn connect_as(port: u16, device_id: DeviceId, command: CommandCode) -> TcpClient {
+    let mut client = TcpClient::new(TcpClientConfig {
+        server_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        timeout: Duration::from_millis(1000),
+    });
+    client.connect().await.unwrap();
+    client
+        .send(MessageBuilder::new(device_id, command).build().unwrap())
+        .await
+        .unwrap();
+    client
+}
+
+#[tokio::test]
+async fn test_duplicate_connection_rejected_by_default() {
+    let mut server = bind_with_policy(13033, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+
+    let mut first = connect_as(13033, device_id, CommandCode::QueryStatus).await;
+    server.accept().await.unwrap();
+
+    let mut second = connect_as(13033, device_id, CommandCode::AccessRequest).await;
+    // Nothing to return: the duplicate is closed and the server keeps waiting
+    assert!(
+        timeout(Duration::from_millis(500), server.recv_any())
+            .await
+            .is_err()
+    );
+    assert!(second.recv().await.is_err());
+
+    let events = server.take_connection_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ConnectionEventKind::Rejected);
+    assert_eq!(server.device_connection_count(device_id), 1);
+
+    // The original connection still gets replies
+    let reply = MessageBuilder::new(device_id, CommandCode::GrantEntry)
+        .build()
+        .unwrap();
+    server.send(device_id, reply).await.unwrap();
+    assert_eq!(first.recv().await.unwrap().command, CommandCode::GrantEntry);
+}
+
+#[tokio::test]
+async fn test_reconnect_after_crash_supersedes_stale_connection() {
+    let mut server = bind_with_policy(13034, DuplicatePolicy::ReplaceOld).await;
+    let device_id = DeviceId::new(15).unwrap();
+
+    // The device crashes without closing its socket, then reconnects
+    let mut stale = connect_as(13034, device_id, CommandCode::QueryStatus).await;
+    server.accept().await.unwrap();
+    let mut rebooted = connect_as(13034, device_id, CommandCode::AccessRequest).await;
+
+    let (received_id, message) = timeout(Duration::from_secs(5), server.recv_any())
+        .await
+        .expect("Server recv_any timeout")
+        .unwrap();
+    assert_eq!(received_id, device_id);
+    assert_eq!(message.command, CommandCode::AccessRequest);
+
+    let events = server.take_connection_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ConnectionEventKind::Superseded);
+    assert_ne!(events[0].existing_addr, events[0].new_addr);
+    assert!(server.take_connection_events().is_empty());
+    assert_eq!(server.device_connection_count(device_id), 1);
+
+    let reply = MessageBuilder::new(device_id, CommandCode::GrantEntry)
+        .build()
+        .unwrap();
+    server.send(device_id, reply).await.unwrap();
+    assert_eq!(
+        rebooted.recv().await.unwrap().command,
+        CommandCode::GrantEntry
+    );
+    assert!(stale.recv().await.is_err());
+}
+
+#[tokio::test]
+async fn test_allow_multi_replies_on_last_active_connection() {
+    let mut server = bind_with_policy(13035, DuplicatePolicy::AllowMulti).await;
+    let device_id = DeviceId::new(15).unwrap();
+    let reply = || {
+        MessageBuilder::new(device_id, CommandCode::GrantEntry)
+            .build()
+            .unwrap()
+    };
+
+    let mut entry = connect_as(13035, device_id, CommandCode::QueryStatus).await;
+    server.accept().await.unwrap();
+    let mut exit = connect_as(13035, device_id, CommandCode::QueryStatus).await;
+    timeout(Duration::from_secs(5), server.recv_any())
+        .await
+        .expect("Server recv_any timeout")
+        .unwrap();
+    assert_eq!(server.device_connection_count(device_id), 2);
+    assert_eq!(server.all_connections_info().len(), 2);
+    assert_eq!(
+        server.take_connection_events()[0].kind,
+        ConnectionEventKind::Added
+    );
+
+    // A request on the older connection is answered on that connection
+    entry
+        .send(
+            MessageBuilder::new(device_id, CommandCode::AccessRequest)
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    timeout(Duration::from_secs(5), server.recv_any())
+        .await
+        .expect("Server recv_any timeout")
+        .unwrap();
+    server.send(device_id, reply()).await.unwrap();
+    assert_eq!(entry.recv().await.unwrap().command, CommandCode::GrantEntry);
+
+    // Closing one connection keeps the device connected through the other
+    entry.close().await.unwrap();
+    let _ = timeout(Duration::from_millis(500), server.recv_any()).await;
+    assert!(server.is_connected(device_id));
+    assert_eq!(server.device_connection_count(device_id), 1);
+
+    exit.send(
+        MessageBuilder::new(device_id, CommandCode::AccessRequest)
+            .build()
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+    timeout(Duration::from_secs(5), server.recv_any())
+        .await
+        .expect("Server recv_any timeout")
+        .unwrap();
+    server.send(device_id, reply()).await.unwrap();
+    assert_eq!(exit.recv().await.unwrap().command, CommandCode::GrantEntry);
+}
+
 #[tokio::test]
 async fn test_max_connections_enforced() {
     let max_conns = 3;
@@ -893,3 +1053,827 @@ async fn test_connection_info_tracking() {
     assert!(device1_info.uptime.num_milliseconds() >= 0);
     assert!(device1_info.uptime.num_seconds() < 2);
 }
+
+fn signed_request(
+    device_id: DeviceId,
+    card: &str,
+    signer: &MessageSigner,
+) -> turnkey_protocol::Message {
+    let mut message = MessageBuilder::new(device_id, CommandCode::AccessRequest)
+        .field(FieldData::new(card.to_string()).unwrap())
+        .build()
+        .unwrap();
+    signer.sign(&mut message).unwrap();
+    message
+}
+
+#[tokio::test]
+async fn test_signed_device_messages_verified_and_stripped() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13018".parse().unwrap(),
+        max_connections: 10,
+    };
+
+    let mut server = TcpServer::bind(server_config.clone()).await.unwrap();
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(15).unwrap();
+    server.register_signing_key(device_id, b"secret".to_vec());
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+
+        let signer = MessageSigner::new(b"secret".to_vec());
+        for card in ["11111111", "22222222"] {
+            client
+                .send(signed_request(device_id, card, &signer))
+                .await
+                .unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    let (received_id, first) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(received_id, device_id);
+    assert_eq!(first.fields.len(), 1);
+    assert_eq!(first.fields[0].as_str(), "11111111");
+    assert!(server.connection_info(device_id).unwrap().signed);
+
+    let second = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout")
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.fields.len(), 1);
+    assert_eq!(second.fields[0].as_str(), "22222222");
+}
+
+#[tokio::test]
+async fn test_unsigned_device_rejected_when_key_registered() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13019".parse().unwrap(),
+        max_connections: 10,
+    };
+
+    let mut server = TcpServer::bind(server_config.clone()).await.unwrap();
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(15).unwrap();
+    server.register_signing_key(device_id, b"secret".to_vec());
+
+    // Spoofed device without the key, then the genuine one
+    for (delay, key) in [(100, None), (300, Some(b"secret".to_vec()))] {
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            let config = TcpClientConfig {
+                server_addr,
+                timeout: Duration::from_millis(1000),
+            };
+            let mut client = TcpClient::new(config);
+            client.connect().await.unwrap();
+
+            let message = match key {
+                Some(key) => signed_request(device_id, "GENUINE1", &MessageSigner::new(key)),
+                None => MessageBuilder::new(device_id, CommandCode::AccessRequest)
+                    .field(FieldData::new("SPOOFED1".to_string()).unwrap())
+                    .build()
+                    .unwrap(),
+            };
+            client.send(message).await.unwrap_or(());
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+    }
+
+    let (_, message) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(message.fields[0].as_str(), "GENUINE1");
+    assert_eq!(server.connected_devices().len(), 1);
+}
+
+#[tokio::test]
+async fn test_invalid_signature_closes_connection() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13020".parse().unwrap(),
+        max_connections: 10,
+    };
+
+    let mut server = TcpServer::bind(server_config.clone()).await.unwrap();
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(15).unwrap();
+    server.register_signing_key(device_id, b"secret".to_vec());
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+
+        let signer = MessageSigner::new(b"secret".to_vec());
+        client
+            .send(signed_request(device_id, "11111111", &signer))
+            .await
+            .unwrap();
+
+        // Signed with another key after negotiation
+        let forged = MessageSigner::new(b"guess".to_vec());
+        client
+            .send(signed_request(device_id, "22222222", &forged))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+
+    let result = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout");
+    assert!(matches!(result, Err(TcpServerError::InvalidSignature(id)) if id == device_id));
+    assert!(!server.is_connected(device_id));
+}
+
+#[tokio::test]
+async fn test_rotated_key_from_secrets_accepts_previous_key() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13028".parse().unwrap(),
+        max_connections: 10,
+    };
+
+    let dir = std::env::temp_dir().join(format!("turnkey-server-secrets-{}", std::process::id()));
+    let secrets = FileSecrets::new(&dir);
+    let device_id = DeviceId::new(15).unwrap();
+    let name = names::device_signing_key(device_id);
+    secrets
+        .put(&name, &SecretBytes::from("old-secret"))
+        .unwrap();
+    secrets
+        .rotate(&name, SecretBytes::from("new-secret"))
+        .unwrap();
+
+    let mut server = TcpServer::bind(server_config.clone()).await.unwrap();
+    let server_addr = server_config.bind_addr;
+    assert!(server.load_signing_key(&secrets, device_id).unwrap());
+    assert!(
+        !server
+            .load_signing_key(&secrets, DeviceId::new(16).unwrap())
+            .unwrap()
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Device not yet reconfigured after the rotation
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+
+        let signer = MessageSigner::new(b"old-secret".to_vec());
+        for card in ["11111111", "22222222"] {
+            client
+                .send(signed_request(device_id, card, &signer))
+                .await
+                .unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    let (_, first) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(first.fields[0].as_str(), "11111111");
+
+    let second = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout")
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.fields[0].as_str(), "22222222");
+}
+
+fn status_report(granted: u64, pending_events: u32) -> DeviceStatusReport {
+    DeviceStatusReport {
+        validation_mode: ValidationMode::Automatic,
+        granted,
+        denied: 1,
+        rotations: granted,
+        timeouts: 0,
+        pending_events,
+        passages: None,
+    }
+}
+
+#[tokio::test]
+async fn test_registry_rebuilt_after_server_restart() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13021".parse().unwrap(),
+        max_connections: 10,
+    };
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(15).unwrap();
+
+    // Device that reports its state every time it (re)connects
+    let device = tokio::spawn(async move {
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        for (granted, pending) in [(10, 0), (12, 3)] {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut client = TcpClient::new(config.clone());
+            client.connect().await.unwrap();
+            client
+                .send(
+                    status_report(granted, pending)
+                        .to_message(device_id)
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            // Wait for the server to go away
+            let _ = client.recv().await;
+        }
+    });
+
+    let mut server = TcpServer::bind(server_config.clone()).await.unwrap();
+    let (_, message) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert!(DeviceStatusReport::is_report(&message));
+    assert_eq!(server.device_state(device_id).unwrap().report.granted, 10);
+
+    // Restart: the new server starts with an empty registry
+    drop(server);
+    let mut server = TcpServer::bind(server_config).await.unwrap();
+    assert!(server.device_states().is_empty());
+
+    timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    let state = server.device_state(device_id).unwrap();
+    assert_eq!(state.report, status_report(12, 3));
+    assert_eq!(server.device_states().len(), 1);
+
+    drop(server);
+    device.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_state_recovery_queries_unknown_devices() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13022".parse().unwrap(),
+        max_connections: 10,
+    };
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(7).unwrap();
+
+    let mut server = TcpServer::bind(server_config).await.unwrap();
+    server.set_state_recovery(true);
+
+    // Device that only reports its state when asked
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+        client
+            .send(
+                MessageBuilder::new(device_id, CommandCode::AccessRequest)
+                    .field(FieldData::new("12345678".to_string()).unwrap())
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let query = client.recv().await.unwrap();
+        assert!(DeviceStatusReport::is_query(&query));
+        client
+            .send(status_report(5, 2).to_message(device_id).unwrap())
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    let (_, message) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(message.command, CommandCode::AccessRequest);
+    assert!(server.device_state(device_id).is_none());
+
+    let report = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout")
+        .unwrap()
+        .unwrap();
+    assert!(DeviceStatusReport::is_report(&report));
+    assert_eq!(
+        server.device_state(device_id).unwrap().report,
+        status_report(5, 2)
+    );
+}
+
+#[tokio::test]
+async fn test_forbidden_command_rejected_and_connection_kept() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13023".parse().unwrap(),
+        max_connections: 10,
+    };
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(15).unwrap();
+
+    let mut server = TcpServer::bind(server_config).await.unwrap();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        let mut client = TcpClient::new(config);
+        client.connect().await.unwrap();
+
+        for command in [
+            CommandCode::AccessRequest,
+            CommandCode::SendConfig,
+            CommandCode::RotationCompleted,
+        ] {
+            let message = MessageBuilder::new(device_id, command).build().unwrap();
+            client.send(message).await.unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+
+    let result = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout");
+    assert!(matches!(
+        result,
+        Err(TcpServerError::CommandNotAllowed {
+            role: DeviceRole::Turnstile,
+            command: CommandCode::SendConfig,
+            ..
+        })
+    ));
+    assert!(server.is_connected(device_id));
+
+    let message = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout")
+        .unwrap()
+        .unwrap();
+    assert_eq!(message.command, CommandCode::RotationCompleted);
+}
+
+#[tokio::test]
+async fn test_forbidden_first_message_rejects_connection() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13024".parse().unwrap(),
+        max_connections: 10,
+    };
+    let server_addr = server_config.bind_addr;
+    let turnstile = DeviceId::new(15).unwrap();
+    let console = DeviceId::new(99).unwrap();
+
+    let mut server = TcpServer::bind(server_config).await.unwrap();
+    server.set_command_policy(CommandPolicy::default().with_role(console, DeviceRole::Management));
+
+    tokio::spawn(async move {
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        };
+        for device_id in [turnstile, console] {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut client = TcpClient::new(config.clone());
+            client.connect().await.unwrap();
+            let message = MessageBuilder::new(device_id, CommandCode::SendUsers)
+                .build()
+                .unwrap();
+            client.send(message).await.unwrap();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                drop(client);
+            });
+        }
+    });
+
+    // The turnstile's connection is dropped; the console's is accepted
+    let (device_id, message) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(device_id, console);
+    assert_eq!(message.command, CommandCode::SendUsers);
+    assert!(!server.is_connected(turnstile));
+}
+
+#[tokio::test]
+async fn test_bulk_transfer_compressed_when_both_sides_offer() {
+    let server_config = TcpServerConfig {
+        bind_addr: "127.0.0.1:13025".parse().unwrap(),
+        max_connections: 10,
+    };
+    let server_addr = server_config.bind_addr;
+    let device_id = DeviceId::new(15).unwrap();
+
+    let mut server = TcpServer::bind(server_config).await.unwrap();
+    server.set_compression(Some(CompressionConfig::default()));
+
+    let cards: Vec<String> = (0..3000).map(|i| format!("{:020}", i)).collect();
+    let expected = cards.clone();
+
+    let device = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let config = TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(2000),
+        };
+        let mut client = TcpClient::new(config);
+        client.set_compression(Some(CompressionConfig::default()));
+        client.connect().await.unwrap();
+
+        let request = MessageBuilder::new(device_id, CommandCode::AccessRequest)
+            .field(FieldData::new("12345678".to_string()).unwrap())
+            .build()
+            .unwrap();
+        client.send(request).await.unwrap();
+
+        let transfer = client.recv().await.unwrap();
+        assert!(client.is_compressed());
+        transfer
+    });
+
+    let (_, request) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    // The compression offer is not visible to the caller
+    assert_eq!(request.fields.len(), 1);
+    assert!(server.connection_info(device_id).unwrap().compressed);
+
+    // About 63 KB of cards, sent in far fewer bytes
+    let transfer = MessageBuilder::new(device_id, CommandCode::SendCards)
+        .fields(
+            cards
+                .into_iter()
+                .map(|card| FieldData::new(card).unwrap())
+                .collect(),
+        )
+        .build()
+        .unwrap();
+    server.send(device_id, transfer).await.unwrap();
+
+    let received = timeout(Duration::from_secs(5), device)
+        .await
+        .expect("Device timeout")
+        .unwrap();
+    assert_eq!(received.command, CommandCode::SendCards);
+    let received: Vec<&str> = received.fields.iter().map(|f| f.as_str()).collect();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn test_tolerant_server_accepts_deviant_firmware() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut server = bind_with_policy(13038, DuplicatePolicy::RejectNew).await;
+    server.set_parse_mode(ParseMode::Tolerant);
+
+    // Unpadded device ID and a spaced, lowercase header, as sent by some firmware
+    let device = tokio::spawn(async move {
+        let mut stream = tokio::net::TcpStream::connect("127.0.0.1:13038")
+            .await
+            .unwrap();
+        stream
+            .write_all(b"\x027+reon+000 + 0]12345678]10/05/2025 12:46:06]1]0]\x03")
+            .await
+            .unwrap();
+
+        let mut reply = vec![0u8; 64];
+        let read = timeout(Duration::from_secs(5), stream.read(&mut reply))
+            .await
+            .expect("Reply timeout")
+            .unwrap();
+        reply.truncate(read);
+        reply
+    });
+
+    let (device_id, request) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(device_id, DeviceId::new(7).unwrap());
+    assert_eq!(request.command, CommandCode::AccessRequest);
+    assert_eq!(request.fields[0].as_str(), "12345678");
+
+    let grant = MessageBuilder::new(device_id, CommandCode::GrantEntry)
+        .field(FieldData::new("5".to_string()).unwrap())
+        .build()
+        .unwrap();
+    server.send(device_id, grant).await.unwrap();
+
+    let reply = device.await.unwrap();
+    assert_eq!(reply, b"\x0207+REON+00+5]5]\x03");
+}
+
+#[tokio::test]
+async fn test_cancellation_token_cancelled_when_device_hangs_up() {
+    let mut server = bind_with_policy(13036, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+    assert!(server.cancellation_token(device_id).is_none());
+
+    let mut client = connect_as(13036, device_id, CommandCode::AccessRequest).await;
+    server.accept().await.unwrap();
+    let closed = server.cancellation_token(device_id).unwrap();
+    assert!(!closed.is_cancelled());
+
+    client.close().await.unwrap();
+    // The server notices the hang-up while waiting for the next message
+    let _ = timeout(Duration::from_millis(500), server.recv_any()).await;
+
+    assert!(!server.is_connected(device_id));
+    timeout(Duration::from_secs(1), closed.cancelled())
+        .await
+        .expect("token not cancelled after disconnect");
+}
+
+#[tokio::test]
+async fn test_cancellation_token_cancelled_on_server_disconnect() {
+    let mut server = bind_with_policy(13037, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+
+    let _client = connect_as(13037, device_id, CommandCode::AccessRequest).await;
+    server.accept().await.unwrap();
+    let closed = server.cancellation_token(device_id).unwrap();
+
+    // Cancelling a handed-out token leaves the connection alone
+    server.cancellation_token(device_id).unwrap().cancel();
+    assert!(!closed.is_cancelled());
+    assert!(server.is_connected(device_id));
+
+    server.disconnect(device_id).await.unwrap();
+    assert!(closed.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_registry_broadcast_and_disconnect() {
+    let mut server = bind_with_policy(13039, DuplicatePolicy::RejectNew).await;
+    let first_id = DeviceId::new(7).unwrap();
+    let second_id = DeviceId::new(2).unwrap();
+
+    let mut first = connect_as(13039, first_id, CommandCode::QueryStatus).await;
+    server.accept().await.unwrap();
+    let mut second = connect_as(13039, second_id, CommandCode::QueryStatus).await;
+    server.accept().await.unwrap();
+
+    let registry = server.registry();
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.devices(), [second_id, first_id]);
+    let info = registry.get(first_id).unwrap();
+    assert!(info.remote_addr.ip().is_loopback());
+    assert!(info.uptime >= chrono::Duration::zero());
+
+    let report = server
+        .broadcast_all(|device_id| {
+            MessageBuilder::new(device_id, CommandCode::SendDateTime)
+                .field(FieldData::new("17/10/2026 08:00:00".to_string())?)
+                .build()
+        })
+        .await
+        .unwrap();
+    assert_eq!(report.sent, [second_id, first_id]);
+    assert!(report.is_complete());
+
+    for (client, device_id) in [(&mut first, first_id), (&mut second, second_id)] {
+        let message = client.recv().await.unwrap();
+        assert_eq!(message.command, CommandCode::SendDateTime);
+        assert_eq!(message.device_id, device_id);
+    }
+
+    server.disconnect(first_id).await.unwrap();
+    let registry = server.registry();
+    assert_eq!(registry.devices(), [second_id]);
+    assert!(!registry.contains(first_id));
+}
+
+#[tokio::test]
+async fn test_heartbeat_answered_then_device_goes_stale() {
+    let heartbeat = Heartbeat::new(Duration::from_millis(100), 5).unwrap();
+    let mut server = bind_with_policy(13040, DuplicatePolicy::RejectNew).await;
+    server.set_heartbeat(Some(heartbeat));
+    let device_id = DeviceId::new(15).unwrap();
+
+    let mut client = connect_as(13040, device_id, CommandCode::AccessRequest).await;
+    client.enable_heartbeat(device_id, heartbeat);
+    server.accept().await.unwrap();
+
+    // Nothing due right after connecting
+    assert!(client.heartbeat().await.unwrap().is_none());
+
+    // The server answers the keepalive without handing it to the caller
+    tokio::time::sleep_until(client.heartbeat_due().unwrap()).await;
+    let (event, received) = tokio::join!(
+        client.heartbeat(),
+        timeout(Duration::from_millis(150), server.recv_any())
+    );
+    let event = event.unwrap().unwrap();
+    assert_eq!(event.device_id, device_id);
+    assert!(matches!(event.kind, HealthEventKind::Answered { .. }));
+    assert!(received.is_err());
+    assert!(server.check_health().is_empty());
+
+    // Silent past the stale threshold
+    tokio::time::sleep(heartbeat.stale_after()).await;
+    assert_eq!(server.check_health(), [device_id]);
+    assert!(server.check_health().is_empty());
+    let events = server.take_health_events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0].kind, HealthEventKind::Stale { .. }));
+    assert!(server.is_connected(device_id));
+
+    client
+        .send(
+            MessageBuilder::new(device_id, CommandCode::AccessRequest)
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, message) = server.recv_any().await.unwrap();
+    assert_eq!(message.command, CommandCode::AccessRequest);
+    let events = server.take_health_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, HealthEventKind::Recovered);
+    let info = server.connection_info(device_id).unwrap();
+    assert!(chrono::Utc::now() - info.last_seen < chrono::Duration::seconds(1));
+}
+
+#[tokio::test]
+async fn test_keepalive_missed_without_server_heartbeat() {
+    let mut server = bind_with_policy(13041, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+
+    let mut client = connect_as(13041, device_id, CommandCode::AccessRequest).await;
+    client.enable_heartbeat(
+        device_id,
+        Heartbeat::new(Duration::from_millis(50), 3).unwrap(),
+    );
+    server.accept().await.unwrap();
+
+    tokio::time::sleep_until(client.heartbeat_due().unwrap()).await;
+    let (event, received) = tokio::join!(client.heartbeat(), server.recv_any());
+    assert_eq!(event.unwrap().unwrap().kind, HealthEventKind::Missed);
+
+    // Without a heartbeat the keepalive reaches the caller
+    let (_, message) = received.unwrap();
+    assert_eq!(message.command, CommandCode::QueryStatus);
+    assert_eq!(message.field_count(), 0);
+}
+
+#[tokio::test]
+async fn test_silent_connection_times_out_without_blocking_accept() {
+    use tokio::io::AsyncReadExt;
+
+    let mut server = bind_with_policy(13043, DuplicatePolicy::RejectNew).await;
+    server.set_handshake_timeout(Duration::from_millis(200));
+    let device_id = DeviceId::new(15).unwrap();
+
+    // Connects and never sends its first message
+    let mut silent = tokio::net::TcpStream::connect("127.0.0.1:13043")
+        .await
+        .unwrap();
+    let _client = connect_as(13043, device_id, CommandCode::AccessRequest).await;
+
+    let (accepted, _) = timeout(Duration::from_secs(2), server.accept())
+        .await
+        .expect("silent connection blocked accept()")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+
+    // The silent peer was closed by the server
+    let mut buf = [0u8; 1];
+    let read = timeout(Duration::from_secs(1), silent.read(&mut buf))
+        .await
+        .expect("silent connection left open");
+    assert!(matches!(read, Ok(0) | Err(_)));
+}
+
+/// Connect and send one prepared message
+async fn connect_sending(port: u16, message: turnkey_protocol::Message) -> TcpClient {
+    let mut client = TcpClient::new(TcpClientConfig {
+        server_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        timeout: Duration::from_millis(1000),
+    });
+    client.connect().await.unwrap();
+    client.send(message).await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn test_captured_signed_message_not_replayed_on_new_connection() {
+    let mut server = bind_with_policy(13044, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+    server.register_signing_key(device_id, b"secret".to_vec());
+    let signer = MessageSigner::new(b"secret".to_vec());
+
+    let captured = signed_request(device_id, "11111111", &signer);
+    let mut genuine = connect_sending(13044, captured.clone()).await;
+    server.accept().await.unwrap();
+    genuine.close().await.unwrap();
+    let _ = timeout(Duration::from_millis(500), server.recv_any()).await;
+    assert!(!server.is_connected(device_id));
+
+    // The same bytes on a new connection are refused
+    let mut replay = connect_sending(13044, captured).await;
+    assert!(
+        timeout(Duration::from_millis(500), server.accept())
+            .await
+            .is_err()
+    );
+    assert!(replay.recv().await.is_err());
+
+    // A freshly signed message from the device still gets in
+    let _genuine = connect_sending(13044, signed_request(device_id, "22222222", &signer)).await;
+    let (_, message) = timeout(Duration::from_secs(2), server.accept())
+        .await
+        .expect("fresh message rejected")
+        .unwrap();
+    assert_eq!(message.fields[0].as_str(), "22222222");
+}
+
+#[tokio::test]
+async fn test_stale_signature_rejected() {
+    let mut server = bind_with_policy(13045, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+    server.register_signing_key(device_id, b"secret".to_vec());
+    let signer = MessageSigner::new(b"secret".to_vec());
+
+    let mut stale = MessageBuilder::new(device_id, CommandCode::AccessRequest)
+        .field(FieldData::new("11111111".to_string()).unwrap())
+        .build()
+        .unwrap();
+    let stamp = SignatureStamp {
+        counter: u64::MAX,
+        timestamp: chrono::Utc::now().timestamp() - 3600,
+    };
+    signer.sign_with(&mut stale, stamp).unwrap();
+
+    let mut client = connect_sending(13045, stale).await;
+    assert!(
+        timeout(Duration::from_millis(500), server.accept())
+            .await
+            .is_err()
+    );
+    assert!(client.recv().await.is_err());
+}
+
+#[tokio::test]
+async fn test_unsigned_device_allowed_during_rollout() {
+    let mut server = bind_with_policy(13046, DuplicatePolicy::RejectNew).await;
+    let device_id = DeviceId::new(15).unwrap();
+    server.register_signing_key(device_id, b"secret".to_vec());
+    server.set_unsigned_allowed(device_id, true);
+
+    let _client = connect_as(13046, device_id, CommandCode::AccessRequest).await;
+    timeout(Duration::from_secs(2), server.accept())
+        .await
+        .expect("unsigned device refused")
+        .unwrap();
+    assert!(!server.connection_info(device_id).unwrap().signed);
+}
@@ -0,0 +1,211 @@
+//! Integration tests for TLS client-certificate authentication
+//!
+//! Certificates are generated per test with rcgen: one CA issues the
+//! server certificate and the device certificates.
+
+#![cfg(feature = "tls")]
+
+use futures::{SinkExt, StreamExt};
+use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, crypto};
+use tokio_util::codec::Framed;
+use turnkey_core::DeviceId;
+use turnkey_network::tls::TlsServerConfig;
+use turnkey_network::{TcpServer, TcpServerConfig, TcpServerError};
+use turnkey_protocol::{CommandCode, HenryCodec, MessageBuilder};
+
+struct Pki {
+    ca: CertificateDer<'static>,
+    issuer: Issuer<'static, KeyPair>,
+}
+
+struct Identity {
+    cert: CertificateDer<'static>,
+    key: Vec<u8>,
+}
+
+impl Identity {
+    fn key(&self) -> PrivateKeyDer<'static> {
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.key.clone()))
+    }
+}
+
+impl Pki {
+    fn new() -> Self {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = params.self_signed(&key).unwrap();
+        Self {
+            ca: ca.der().clone(),
+            issuer: Issuer::new(params, key),
+        }
+    }
+
+    fn issue(&self, names: &[&str], usage: ExtendedKeyUsagePurpose) -> Identity {
+        let key = KeyPair::generate().unwrap();
+        let names = names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let mut params = CertificateParams::new(names).unwrap();
+        params.extended_key_usages = vec![usage];
+        let cert = params.signed_by(&key, &self.issuer).unwrap();
+        Identity {
+            cert: cert.der().clone(),
+            key: key.serialize_der(),
+        }
+    }
+
+    fn device(&self) -> Identity {
+        self.issue(&[], ExtendedKeyUsagePurpose::ClientAuth)
+    }
+
+    fn server_config(&self) -> TlsServerConfig {
+        let server = self.issue(&["localhost"], ExtendedKeyUsagePurpose::ServerAuth);
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca.clone()).unwrap();
+        TlsServerConfig::new(vec![server.cert.clone()], server.key(), roots).unwrap()
+    }
+}
+
+async fn connect(
+    pki: &Pki,
+    addr: SocketAddr,
+    device: &Identity,
+) -> std::io::Result<Framed<TlsStream<TcpStream>, HenryCodec>> {
+    let mut roots = RootCertStore::empty();
+    roots.add(pki.ca.clone()).unwrap();
+    let config = ClientConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_client_auth_cert(vec![device.cert.clone()], device.key())
+        .unwrap();
+
+    let tcp = TcpStream::connect(addr).await?;
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect("localhost".try_into().unwrap(), tcp)
+        .await?;
+    Ok(Framed::new(stream, HenryCodec::new()))
+}
+
+fn request(device_id: u8) -> turnkey_protocol::Message {
+    MessageBuilder::new(
+        DeviceId::new(device_id).unwrap(),
+        CommandCode::AccessRequest,
+    )
+    .build()
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_tls_rejects_unmapped_certificates_and_foreign_claims() {
+    let pki = Arc::new(Pki::new());
+    let device_15 = pki.device();
+    let unmapped = pki.device();
+
+    let addr: SocketAddr = "127.0.0.1:13026".parse().unwrap();
+    let mut server = TcpServer::bind(TcpServerConfig {
+        bind_addr: addr,
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+    server.set_tls(Some(pki.server_config()));
+    server
+        .device_certificates_mut()
+        .register(DeviceId::new(15).unwrap(), &device_15.cert);
+
+    let client_pki = pki.clone();
+    let client_task = tokio::spawn(async move {
+        // Certificate of device 15 claiming to be device 16
+        let mut framed = connect(&client_pki, addr, &device_15).await.unwrap();
+        framed.send(request(16)).await.unwrap();
+        assert!(framed.next().await.is_none_or(|result| result.is_err()));
+
+        // Valid certificate not mapped to any device
+        if let Ok(mut framed) = connect(&client_pki, addr, &unmapped).await {
+            let _ = framed.send(request(15)).await;
+            assert!(framed.next().await.is_none_or(|result| result.is_err()));
+        }
+
+        let mut framed = connect(&client_pki, addr, &device_15).await.unwrap();
+        framed.send(request(15)).await.unwrap();
+        framed.send(request(16)).await.unwrap();
+        let _ = framed.next().await;
+    });
+
+    let (device_id, _) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(device_id, DeviceId::new(15).unwrap());
+    assert!(server.connection_info(device_id).unwrap().certified);
+
+    let err = timeout(Duration::from_secs(5), server.recv(device_id))
+        .await
+        .expect("Server recv timeout")
+        .unwrap_err();
+    assert!(matches!(err, TcpServerError::DeviceMismatch { .. }));
+    assert!(!server.is_connected(device_id));
+
+    client_task.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tls_certificate_rotation() {
+    let pki = Arc::new(Pki::new());
+    let old = pki.device();
+    let new = pki.device();
+    let device_id = DeviceId::new(15).unwrap();
+
+    let addr: SocketAddr = "127.0.0.1:13027".parse().unwrap();
+    let mut server = TcpServer::bind(TcpServerConfig {
+        bind_addr: addr,
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+    server.set_tls(Some(pki.server_config()));
+    server
+        .device_certificates_mut()
+        .register(device_id, &old.cert);
+    server
+        .device_certificates_mut()
+        .rotate(device_id, &new.cert, chrono::Duration::zero());
+
+    let client_pki = pki.clone();
+    let client_task = tokio::spawn(async move {
+        // The old certificate was retired without grace
+        if let Ok(mut framed) = connect(&client_pki, addr, &old).await {
+            let _ = framed.send(request(15)).await;
+            assert!(framed.next().await.is_none_or(|result| result.is_err()));
+        }
+
+        let mut framed = connect(&client_pki, addr, &new).await.unwrap();
+        framed.send(request(15)).await.unwrap();
+        let _ = framed.next().await;
+    });
+
+    let (accepted, _) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("Server accept timeout")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+
+    // A renewed server certificate only affects new handshakes
+    server.set_tls(Some(pki.server_config()));
+    assert!(server.is_connected(device_id));
+    server.disconnect(device_id).await.unwrap();
+
+    client_task.await.unwrap();
+}
@@ -0,0 +1,303 @@
+//! LCD character sets for display text.
+//!
+//! Henry turnstile LCDs do not understand UTF-8. Depending on the firmware
+//! they render plain ASCII, IBM code page 850 or ISO-8859-1 (latin-1), one
+//! byte per character. Sending "prédio" as UTF-8 shows two garbage glyphs
+//! in place of the `é`.
+//!
+//! [`Codepage`] converts text to the bytes a given LCD expects. Characters
+//! the codepage cannot represent are transliterated to ASCII (`ã` → `a`,
+//! `ç` → `c`, `“` → `"`), and anything without a transliteration becomes
+//! `?`, so the output is always exactly what the hardware will show.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_protocol::Codepage;
+//!
+//! assert_eq!(Codepage::Cp850.encode("prédio"), b"pr\x82dio");
+//! assert_eq!(Codepage::Latin1.encode("prédio"), b"pr\xe9dio");
+//! assert_eq!(Codepage::Ascii.encode("prédio"), b"predio");
+//!
+//! assert_eq!(Codepage::Cp850.decode(b"Libera\x87\xc6o"), "Liberação");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use turnkey_core::{Error, Result};
+
+/// Byte written for characters with no representation or transliteration.
+pub const REPLACEMENT_BYTE: u8 = b'?';
+
+/// Characters of code page 850 from 0x80 to 0xFF (0x00-0x7F is ASCII).
+const CP850_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤', //
+    'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀', //
+    'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´', //
+    '\u{AD}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{A0}',
+];
+
+/// Character set of a turnstile LCD.
+///
+/// The default is [`Codepage::Ascii`], which every Henry display supports.
+/// Pick [`Codepage::Cp850`] or [`Codepage::Latin1`] to show accented text
+/// on firmware that has the matching character ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Codepage {
+    /// 7-bit ASCII; every accented letter is transliterated.
+    #[default]
+    Ascii,
+    /// IBM code page 850 (DOS Latin-1).
+    Cp850,
+    /// ISO-8859-1.
+    Latin1,
+}
+
+impl Codepage {
+    /// Get the configuration name of the codepage.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Codepage::Ascii => "ascii",
+            Codepage::Cp850 => "cp850",
+            Codepage::Latin1 => "latin1",
+        }
+    }
+
+    /// Get the byte for a character, if the codepage has one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::Codepage;
+    ///
+    /// assert_eq!(Codepage::Cp850.encode_char('ç'), Some(0x87));
+    /// assert_eq!(Codepage::Latin1.encode_char('ç'), Some(0xE7));
+    /// assert_eq!(Codepage::Ascii.encode_char('ç'), None);
+    /// ```
+    pub fn encode_char(&self, c: char) -> Option<u8> {
+        if c.is_ascii() {
+            return Some(c as u8);
+        }
+        match self {
+            Codepage::Ascii => None,
+            Codepage::Cp850 => CP850_HIGH
+                .iter()
+                .position(|&high| high == c)
+                .map(|index| 0x80 + index as u8),
+            Codepage::Latin1 => u8::try_from(u32::from(c)).ok(),
+        }
+    }
+
+    /// Get the character a byte shows on the display.
+    pub fn decode_byte(&self, byte: u8) -> char {
+        if byte.is_ascii() {
+            return byte as char;
+        }
+        match self {
+            Codepage::Ascii => REPLACEMENT_BYTE as char,
+            Codepage::Cp850 => CP850_HIGH[usize::from(byte - 0x80)],
+            Codepage::Latin1 => char::from(byte),
+        }
+    }
+
+    /// Whether the codepage can show the character as is.
+    pub fn supports(&self, c: char) -> bool {
+        self.encode_char(c).is_some()
+    }
+
+    /// Encode text for the display.
+    ///
+    /// Unsupported characters are transliterated to ASCII, or replaced by
+    /// [`REPLACEMENT_BYTE`] when there is no transliteration. The result has
+    /// one byte per displayed character except for the few transliterations
+    /// that expand (`ß` → `ss`, `…` → `...`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::Codepage;
+    ///
+    /// assert_eq!(Codepage::Ascii.encode("Liberação"), b"Liberacao");
+    /// assert_eq!(Codepage::Cp850.encode("Ação ✓"), b"A\x87\xc6o ?");
+    /// ```
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            if let Some(byte) = self.encode_char(c) {
+                bytes.push(byte);
+            } else if let Some(fallback) = transliterate(c) {
+                bytes.extend_from_slice(fallback.as_bytes());
+            } else {
+                bytes.push(REPLACEMENT_BYTE);
+            }
+        }
+        bytes
+    }
+
+    /// Decode display bytes back to text.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        bytes.iter().map(|&byte| self.decode_byte(byte)).collect()
+    }
+
+    /// Get the text as the display will show it.
+    ///
+    /// Equivalent to encoding and decoding again: supported characters are
+    /// kept and everything else is transliterated or replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::Codepage;
+    ///
+    /// assert_eq!(Codepage::Cp850.render("Bem-vindo ao prédio"), "Bem-vindo ao prédio");
+    /// assert_eq!(Codepage::Ascii.render("Bem-vindo ao prédio"), "Bem-vindo ao predio");
+    /// ```
+    pub fn render(&self, text: &str) -> String {
+        if text.chars().all(|c| self.supports(c)) {
+            return text.to_string();
+        }
+        self.decode(&self.encode(text))
+    }
+}
+
+impl fmt::Display for Codepage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Codepage {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ascii" => Ok(Codepage::Ascii),
+            "cp850" | "ibm850" => Ok(Codepage::Cp850),
+            "latin1" | "iso-8859-1" => Ok(Codepage::Latin1),
+            _ => Err(Error::Config(format!("Unknown display codepage: {}", s))),
+        }
+    }
+}
+
+/// Get the ASCII approximation of a non-ASCII character.
+///
+/// Covers the Latin letters used in Portuguese and Spanish plus common
+/// typographic punctuation. Returns `None` for anything else.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_protocol::charset::transliterate;
+///
+/// assert_eq!(transliterate('ã'), Some("a"));
+/// assert_eq!(transliterate('Ç'), Some("C"));
+/// assert_eq!(transliterate('漢'), None);
+/// ```
+pub fn transliterate(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ª' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => "A",
+        'ç' => "c",
+        'Ç' => "C",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' => "E",
+        'ì' | 'í' | 'î' | 'ï' | 'ı' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+        'ñ' => "n",
+        'Ñ' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'º' | '°' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => "O",
+        'ù' | 'ú' | 'û' | 'ü' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' => "Y",
+        'ß' => "ss",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        '‘' | '’' | '´' => "'",
+        '“' | '”' | '«' | '»' => "\"",
+        '–' | '—' | '\u{AD}' => "-",
+        '…' => "...",
+        '\u{A0}' => " ",
+        '€' => "EUR",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTUGUESE: &str = "Atenção: acesso ao prédio não autorizado às áreas técnicas";
+
+    #[test]
+    fn test_cp850_round_trips_portuguese_accents() {
+        let bytes = Codepage::Cp850.encode(PORTUGUESE);
+        assert_eq!(bytes.len(), PORTUGUESE.chars().count());
+        assert!(!bytes.is_ascii());
+        assert_eq!(Codepage::Cp850.decode(&bytes), PORTUGUESE);
+    }
+
+    #[test]
+    fn test_latin1_round_trips_portuguese_accents() {
+        let bytes = Codepage::Latin1.encode(PORTUGUESE);
+        assert_eq!(bytes.len(), PORTUGUESE.chars().count());
+        assert_eq!(Codepage::Latin1.decode(&bytes), PORTUGUESE);
+    }
+
+    #[test]
+    fn test_ascii_transliterates_portuguese_accents() {
+        assert_eq!(
+            Codepage::Ascii.render(PORTUGUESE),
+            "Atencao: acesso ao predio nao autorizado as areas tecnicas"
+        );
+        assert_eq!(Codepage::Ascii.render("ÁÂÃÀÇÉÊÍÓÔÕÚÜ"), "AAAACEEIOOOUU");
+    }
+
+    #[test]
+    fn test_cp850_table_matches_known_bytes() {
+        for (c, byte) in [
+            ('Ç', 0x80),
+            ('é', 0x82),
+            ('á', 0xA0),
+            ('Á', 0xB5),
+            ('ã', 0xC6),
+            ('Ã', 0xC7),
+            ('Ê', 0xD2),
+            ('Ó', 0xE0),
+            ('õ', 0xE4),
+            ('Õ', 0xE5),
+            ('Ú', 0xE9),
+        ] {
+            assert_eq!(Codepage::Cp850.encode_char(c), Some(byte), "{}", c);
+            assert_eq!(Codepage::Cp850.decode_byte(byte), c);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_characters_fall_back() {
+        // Typographic quotes are in neither codepage
+        assert_eq!(Codepage::Latin1.encode("“ok”"), b"\"ok\"");
+        assert_eq!(Codepage::Cp850.encode("Straße"), b"Stra\xe1e");
+        assert_eq!(Codepage::Ascii.encode("Straße"), b"Strasse");
+        assert_eq!(Codepage::Latin1.encode("中"), vec![REPLACEMENT_BYTE]);
+        assert_eq!(Codepage::Ascii.decode(&[0xE9]), "?");
+    }
+
+    #[test]
+    fn test_codepage_from_str() {
+        assert_eq!("cp850".parse::<Codepage>().unwrap(), Codepage::Cp850);
+        assert_eq!("ISO-8859-1".parse::<Codepage>().unwrap(), Codepage::Latin1);
+        assert_eq!(Codepage::default().to_string(), "ascii");
+        assert!("utf8".parse::<Codepage>().is_err());
+    }
+}
@@ -58,6 +58,31 @@
 //! - Buffer size limits in [`StreamParser`]
 //! - Automatic buffer cleanup after frame extraction
 //!
+//! # Compression
+//!
+//! With [`HenryCodec::with_compression`] the codec offers payload
+//! compression to its peer and, once the peer has offered it too, compresses
+//! bulk transfers above the configured threshold. Decoded messages are
+//! always returned decompressed. See [`crate::compression`] for the wire
+//! format and limits.
+//!
+//! # Display Text
+//!
+//! Turnstile LCDs expect one byte per character in their own character
+//! set, not UTF-8. The encoder converts the text of outgoing messages,
+//! such as the display message of an access grant, to the configured
+//! [`Codepage`] (ASCII unless set with [`HenryCodec::with_codepage`]).
+//! Characters the codepage lacks are transliterated, so "Liberação" goes
+//! out as `Liberacao` to an ASCII display.
+//!
+//! # Tolerant Parsing
+//!
+//! With [`HenryCodec::with_parse_mode`] set to [`ParseMode::Tolerant`],
+//! decoded headers are normalized before parsing, so firmware that drops
+//! the zero padding of its device ID or spaces its command code is still
+//! understood. See [`crate::parser`] for the accepted variants. Encoding
+//! is unaffected and always produces the canonical form.
+//!
 //! # Performance
 //!
 //! The codec is optimized for high throughput:
@@ -78,9 +103,11 @@
 //! to handle protocol violations gracefully.
 
 use bytes::BytesMut;
+use std::borrow::Cow;
 use tokio_util::codec::{Decoder, Encoder};
 
-use crate::{Frame, Message, StreamParser};
+use crate::compression::{self, CompressionConfig};
+use crate::{Codepage, Frame, Message, MessageParser, ParseMode, StreamParser};
 use turnkey_core::{Error, Result};
 
 /// Default maximum frame size in bytes (64 KB).
@@ -129,6 +156,21 @@ pub struct HenryCodec {
     /// Frames exceeding this size will be rejected with an error
     /// to prevent denial-of-service attacks.
     max_frame_size: usize,
+
+    /// Local compression settings (None disables compression).
+    compression: Option<CompressionConfig>,
+
+    /// Whether the compression offer went out with an encoded message.
+    offer_sent: bool,
+
+    /// Whether the peer offered compression.
+    peer_offered: bool,
+
+    /// Character set of the peer's display, used for outgoing text.
+    codepage: Codepage,
+
+    /// How closely decoded message headers must follow the protocol.
+    parse_mode: ParseMode,
 }
 
 impl HenryCodec {
@@ -145,10 +187,7 @@ impl HenryCodec {
     /// let codec = HenryCodec::new();
     /// ```
     pub fn new() -> Self {
-        Self {
-            parser: StreamParser::new(),
-            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
-        }
+        Self::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
     }
 
     /// Create a new codec with custom maximum frame size.
@@ -172,9 +211,105 @@ impl HenryCodec {
         Self {
             parser: StreamParser::new(),
             max_frame_size,
+            compression: None,
+            offer_sent: false,
+            peer_offered: false,
+            codepage: Codepage::default(),
+            parse_mode: ParseMode::default(),
         }
     }
 
+    /// Set the character set used for the text of encoded messages.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turnkey_protocol::{Codepage, HenryCodec};
+    ///
+    /// let codec = HenryCodec::new().with_codepage(Codepage::Cp850);
+    /// assert_eq!(codec.codepage(), Codepage::Cp850);
+    /// ```
+    pub fn with_codepage(mut self, codepage: Codepage) -> Self {
+        self.codepage = codepage;
+        self
+    }
+
+    /// Get the character set used for the text of encoded messages.
+    pub fn codepage(&self) -> Codepage {
+        self.codepage
+    }
+
+    /// Set how closely decoded message headers must follow the protocol.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use tokio_util::codec::Decoder;
+    /// use turnkey_protocol::{HenryCodec, ParseMode};
+    ///
+    /// let mut codec = HenryCodec::new().with_parse_mode(ParseMode::Tolerant);
+    /// let mut buffer = BytesMut::from(&b"\x025+reon+rq\x03"[..]);
+    ///
+    /// let msg = codec.decode(&mut buffer).unwrap().unwrap();
+    /// assert_eq!(msg.device_id.as_u8(), 5);
+    /// ```
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Change how closely decoded message headers must follow the protocol.
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.parse_mode = mode;
+    }
+
+    /// Get how closely decoded message headers must follow the protocol.
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Enable payload compression for bulk transfers.
+    ///
+    /// Compression is only used once the peer has offered it too, so this
+    /// is safe on connections to peers without compression support.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turnkey_protocol::{CompressionConfig, HenryCodec};
+    ///
+    /// let codec = HenryCodec::new().with_compression(CompressionConfig::default());
+    /// assert!(codec.compression().is_some());
+    /// assert!(!codec.is_compression_negotiated());
+    /// ```
+    pub fn with_compression(mut self, config: CompressionConfig) -> Self {
+        self.compression = Some(config);
+        self
+    }
+
+    /// Change the compression settings.
+    ///
+    /// Disabling compression does not forget the peer's offer; enabling it
+    /// again on the same connection resumes compression.
+    pub fn set_compression(&mut self, config: Option<CompressionConfig>) {
+        self.compression = config;
+    }
+
+    /// Get the local compression settings.
+    pub fn compression(&self) -> Option<&CompressionConfig> {
+        self.compression.as_ref()
+    }
+
+    /// Whether both sides offered compression.
+    pub fn is_compression_negotiated(&self) -> bool {
+        self.negotiated_compression().is_some()
+    }
+
+    fn negotiated_compression(&self) -> Option<&CompressionConfig> {
+        self.compression.as_ref().filter(|_| self.peer_offered)
+    }
+
     /// Get the current maximum frame size.
     pub fn max_frame_size(&self) -> usize {
         self.max_frame_size
@@ -214,6 +349,8 @@ impl Decoder for HenryCodec {
     /// - The frame contains invalid UTF-8
     /// - The message format is invalid
     /// - The device ID or command code is invalid
+    /// - The message is compressed but compression was not negotiated, or
+    ///   its payload is corrupt or expands beyond the configured limit
     ///
     /// # Example
     ///
@@ -253,8 +390,31 @@ impl Decoder for HenryCodec {
                 });
             }
 
+            let frame = match self.parse_mode {
+                ParseMode::Strict => frame,
+                ParseMode::Tolerant => normalize_frame(frame)?,
+            };
+
             // Convert frame to message
-            let message = Message::try_from(frame)?;
+            let mut message = Message::try_from(frame)?;
+
+            // The offer is always stripped, even when compression is disabled
+            // locally, so callers never see it
+            if message.fields.last().is_some_and(compression::is_offer) {
+                message.fields.pop();
+                self.peer_offered = true;
+            }
+
+            if compression::is_compressed(&message) {
+                let Some(config) = self.negotiated_compression() else {
+                    return Err(Error::InvalidMessageFormat {
+                        message: "Compressed message on a connection without compression"
+                            .to_string(),
+                    });
+                };
+                config.decompress(&mut message)?;
+            }
+
             Ok(Some(message))
         } else {
             // No complete frame available yet
@@ -263,6 +423,19 @@ impl Decoder for HenryCodec {
     }
 }
 
+/// Rewrite the header of a frame to the canonical form, keeping its checksum
+fn normalize_frame(frame: Frame) -> Result<Frame> {
+    let text = frame.to_string()?;
+    let Cow::Owned(normalized) = MessageParser::normalize(&text) else {
+        return Ok(frame);
+    };
+    let mut normalized = Frame::from_string(&normalized, false);
+    if let Some(checksum) = frame.checksum() {
+        normalized.set_checksum(checksum.to_string());
+    }
+    Ok(normalized)
+}
+
 impl Encoder<Message> for HenryCodec {
     type Error = Error;
 
@@ -287,6 +460,9 @@ impl Encoder<Message> for HenryCodec {
     /// - The resulting frame exceeds `max_frame_size`
     /// - Memory allocation fails
     ///
+    /// With compression negotiated, bulk transfers are compressed before
+    /// the size check, so they may exceed `max_frame_size` uncompressed.
+    ///
     /// # Example
     ///
     /// ```
@@ -311,9 +487,17 @@ impl Encoder<Message> for HenryCodec {
     /// # Ok(())
     /// # }
     /// ```
-    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<()> {
-        // Convert message to frame
-        let frame = Frame::from(item);
+    fn encode(&mut self, mut item: Message, dst: &mut BytesMut) -> Result<()> {
+        if let Some(config) = self.negotiated_compression() {
+            config.compress(&mut item)?;
+        }
+        let offering = self.compression.is_some() && !self.offer_sent;
+        if offering {
+            item.fields.push(compression::offer_field());
+        }
+
+        // Convert message to frame, with text in the display's codepage
+        let frame = Frame::from(item).encode_text(self.codepage);
 
         // Add STX/ETX framing
         let framed = frame.with_framing();
@@ -328,6 +512,7 @@ impl Encoder<Message> for HenryCodec {
 
         // Write framed bytes to destination buffer
         dst.extend_from_slice(framed.as_bytes());
+        self.offer_sent |= offering;
 
         Ok(())
     }
@@ -374,6 +559,33 @@ mod tests {
         assert_eq!(msg.command, CommandCode::QueryStatus);
     }
 
+    #[test]
+    fn test_decode_tolerant_header() {
+        let wire = b"\x027+reon+000 + 0]12345678]10/05/2025 12:46:06]1]0]\x03";
+
+        let mut strict = HenryCodec::new();
+        assert!(strict.decode(&mut BytesMut::from(&wire[..])).is_err());
+
+        let mut codec = HenryCodec::new().with_parse_mode(ParseMode::Tolerant);
+        assert_eq!(codec.parse_mode(), ParseMode::Tolerant);
+        let msg = codec
+            .decode(&mut BytesMut::from(&wire[..]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(msg.device_id.as_u8(), 7);
+        assert_eq!(msg.command, CommandCode::AccessRequest);
+        assert_eq!(msg.field(0), Some("12345678"));
+
+        // Replies still go out in canonical form
+        let mut buffer = BytesMut::new();
+        let reply = MessageBuilder::new(msg.device_id, CommandCode::GrantEntry)
+            .field(FieldData::new("5".to_string()).unwrap())
+            .build()
+            .unwrap();
+        codec.encode(reply, &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"\x0207+REON+00+5]5]\x03");
+    }
+
     #[test]
     fn test_decode_partial_message() {
         let mut codec = HenryCodec::new();
@@ -493,6 +705,52 @@ mod tests {
         assert!(content.contains("12345678"));
     }
 
+    fn grant_with_message(text: &str) -> Message {
+        MessageBuilder::new(DeviceId::new(15).unwrap(), CommandCode::GrantEntry)
+            .field(FieldData::new("5".to_string()).unwrap())
+            .field(FieldData::new(text.to_string()).unwrap())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_encode_display_text_in_codepage() {
+        let message = "Liberação no prédio";
+
+        let mut buffer = BytesMut::new();
+        HenryCodec::new()
+            .with_codepage(Codepage::Cp850)
+            .encode(grant_with_message(message), &mut buffer)
+            .unwrap();
+        let content = &buffer[1..buffer.len() - 1];
+        assert!(content.ends_with(b"]Libera\x87\xc6o no pr\x82dio]"));
+        assert!(
+            Codepage::Cp850
+                .decode(content)
+                .ends_with("]Liberação no prédio]")
+        );
+
+        let mut buffer = BytesMut::new();
+        HenryCodec::new()
+            .with_codepage(Codepage::Latin1)
+            .encode(grant_with_message(message), &mut buffer)
+            .unwrap();
+        assert!(buffer.ends_with(b"]Libera\xe7\xe3o no pr\xe9dio]\x03"));
+    }
+
+    #[test]
+    fn test_encode_display_text_transliterated_by_default() {
+        let mut codec = HenryCodec::new();
+        assert_eq!(codec.codepage(), Codepage::Ascii);
+
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(grant_with_message("Atenção, prédio"), &mut buffer)
+            .unwrap();
+        assert!(buffer.is_ascii());
+        assert_eq!(&buffer[..], b"\x0215+REON+00+5]5]Atencao, predio]\x03");
+    }
+
     #[test]
     fn test_encode_frame_too_large() {
         let mut codec = HenryCodec::with_max_frame_size(10);
@@ -581,4 +839,112 @@ mod tests {
         let msg = message.unwrap();
         assert_eq!(msg.device_id.as_u8(), 15);
     }
+
+    fn card_list(count: usize) -> Message {
+        let fields = (0..count)
+            .map(|i| FieldData::new(format!("{:020}", i)).unwrap())
+            .collect();
+        Message::new(DeviceId::new(1).unwrap(), CommandCode::SendCards, fields).unwrap()
+    }
+
+    fn transfer(from: &mut HenryCodec, to: &mut HenryCodec, message: Message) -> (usize, Message) {
+        let mut buffer = BytesMut::new();
+        from.encode(message, &mut buffer).unwrap();
+        let size = buffer.len();
+        (size, to.decode(&mut buffer).unwrap().unwrap())
+    }
+
+    #[test]
+    fn test_compression_negotiated_after_both_offers() {
+        let mut server = HenryCodec::new().with_compression(CompressionConfig::default());
+        let mut device = HenryCodec::new().with_compression(CompressionConfig::default());
+        let status = MessageBuilder::new(DeviceId::new(1).unwrap(), CommandCode::QueryStatus)
+            .build()
+            .unwrap();
+
+        // Device offers with its first message; the offer is stripped
+        let (_, received) = transfer(&mut device, &mut server, status.clone());
+        assert_eq!(received.fields, status.fields);
+        assert!(server.is_compression_negotiated());
+        assert!(!device.is_compression_negotiated());
+
+        // Server offers back and may already compress
+        let cards = card_list(200);
+        let (size, received) = transfer(&mut server, &mut device, cards.clone());
+        assert_eq!(received.fields, cards.fields);
+        assert!(device.is_compression_negotiated());
+        assert!(size < Frame::from(cards.clone()).with_framing().size() / 4);
+
+        // Later messages carry no offer and small ones stay plain
+        let mut buffer = BytesMut::new();
+        device.encode(status.clone(), &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"\x0201+REON+RQ\x03");
+    }
+
+    #[test]
+    fn test_no_compression_without_peer_offer() {
+        let mut server = HenryCodec::new().with_compression(CompressionConfig::default());
+        let mut device = HenryCodec::new();
+
+        let status = MessageBuilder::new(DeviceId::new(1).unwrap(), CommandCode::QueryStatus)
+            .build()
+            .unwrap();
+        transfer(&mut device, &mut server, status);
+        assert!(!server.is_compression_negotiated());
+
+        // The offer goes out once, but the transfer stays plain
+        let mut buffer = BytesMut::new();
+        server.encode(card_list(200), &mut buffer).unwrap();
+        let wire = String::from_utf8(buffer.to_vec()).unwrap();
+        assert!(wire.contains("]COMP=zlib"));
+        assert!(!wire.contains(compression::COMPRESSED_FIELD_PREFIX));
+
+        let received = device.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(received.fields, card_list(200).fields);
+    }
+
+    #[test]
+    fn test_offer_stripped_when_compression_disabled() {
+        let mut server = HenryCodec::new();
+        let mut device = HenryCodec::new().with_compression(CompressionConfig::default());
+
+        let cards = card_list(3);
+        let (_, received) = transfer(&mut device, &mut server, cards.clone());
+        assert_eq!(received.fields, cards.fields);
+        assert!(!server.is_compression_negotiated());
+    }
+
+    #[test]
+    fn test_decode_rejects_unnegotiated_compression() {
+        let mut sender = HenryCodec::new().with_compression(CompressionConfig::default());
+        sender.peer_offered = true;
+        sender.offer_sent = true;
+
+        let mut buffer = BytesMut::new();
+        sender.encode(card_list(200), &mut buffer).unwrap();
+
+        let mut receiver = HenryCodec::new();
+        assert!(matches!(
+            receiver.decode(&mut buffer),
+            Err(Error::InvalidMessageFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compressed_bulk_transfer_fits_frame_limit() {
+        let config = CompressionConfig::default().with_max_decompressed_size(256 * 1024);
+        let mut server = HenryCodec::new().with_compression(config);
+        let mut device = HenryCodec::new().with_compression(config);
+        server.peer_offered = true;
+        server.offer_sent = true;
+        device.peer_offered = true;
+        device.offer_sent = true;
+
+        // About 100 KB uncompressed, well above the 64 KB frame limit
+        let cards = card_list(5000);
+        assert!(Frame::from(cards.clone()).size() > DEFAULT_MAX_FRAME_SIZE);
+
+        let (_, received) = transfer(&mut server, &mut device, cards.clone());
+        assert_eq!(received.fields, cards.fields);
+    }
 }
@@ -9,7 +9,7 @@
 //! Access request messages follow this format:
 //!
 //! ```text
-//! <ID>+REON+000+0]<CARD_NUMBER>]<TIMESTAMP>]<DIRECTION>]<READER_TYPE>]
+//! <ID>+REON+000+0]<CARD_NUMBER>]<TIMESTAMP>]<DIRECTION>]<READER_TYPE>][<CORRELATION_ID>]]
 //! ```
 //!
 //! Where:
@@ -17,6 +17,9 @@
 //! - `TIMESTAMP`: dd/mm/yyyy hh:mm:ss format
 //! - `DIRECTION`: 0=Undefined, 1=Entry, 2=Exit
 //! - `READER_TYPE`: 1=RFID, 5=Biometric
+//! - `CORRELATION_ID`: optional UUID generated when the credential was read,
+//!   used to join the logs of one passage across device, server and storage.
+//!   Real Henry equipment does not send it.
 //!
 //! # Examples
 //!
@@ -54,12 +57,15 @@
 //! assert!(AccessRequest::validate_card_number("123456789012345678901").is_err());
 //! ```
 
+use crate::commands::CommandCode;
 use serde::{Deserialize, Serialize};
 use turnkey_core::constants::{
-    DEFAULT_DENY_TIMEOUT_SECONDS, DEFAULT_GRANT_TIMEOUT_SECONDS, MAX_CARD_LENGTH,
-    MAX_DISPLAY_MESSAGE_LENGTH, MIN_CARD_LENGTH,
+    DEFAULT_DENY_TIMEOUT_SECONDS, DEFAULT_DISPLAY_TIMEOUT_SECONDS, DEFAULT_GRANT_TIMEOUT_SECONDS,
+    MAX_CARD_LENGTH, MAX_DISPLAY_MESSAGE_LENGTH, MIN_CARD_LENGTH,
+};
+use turnkey_core::{
+    AccessDirection, CorrelationId, Error, HenryTimestamp, ReaderType, Result, Timeout,
 };
-use turnkey_core::{AccessDirection, Error, HenryTimestamp, ReaderType, Result};
 
 /// Access request from a turnstile device.
 ///
@@ -72,6 +78,7 @@ use turnkey_core::{AccessDirection, Error, HenryTimestamp, ReaderType, Result};
 /// - `timestamp`: When the access attempt occurred
 /// - `direction`: Which direction the user wants to pass
 /// - `reader_type`: Which type of reader was used
+/// - `correlation_id`: Passage identifier assigned at read time, if the device sent one
 ///
 /// # Protocol Behavior
 ///
@@ -100,6 +107,8 @@ pub struct AccessRequest {
     timestamp: HenryTimestamp,
     direction: AccessDirection,
     reader_type: ReaderType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<CorrelationId>,
 }
 
 impl AccessRequest {
@@ -188,9 +197,36 @@ impl AccessRequest {
             timestamp,
             direction,
             reader_type,
+            correlation_id: None,
         })
     }
 
+    /// Attach the correlation ID generated when the credential was read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::commands::access::AccessRequest;
+    /// use turnkey_core::{AccessDirection, CorrelationId, HenryTimestamp, ReaderType};
+    ///
+    /// let correlation_id = CorrelationId::new();
+    /// let request = AccessRequest::new(
+    ///     "12345678".to_string(),
+    ///     HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+    ///     AccessDirection::Entry,
+    ///     ReaderType::Rfid,
+    /// )
+    /// .unwrap()
+    /// .with_correlation_id(correlation_id);
+    ///
+    /// let parsed = AccessRequest::parse(&request.to_fields()).unwrap();
+    /// assert_eq!(parsed.correlation_id(), Some(correlation_id));
+    /// ```
+    pub fn with_correlation_id(mut self, correlation_id: CorrelationId) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
     /// Parse access request from protocol fields.
     ///
     /// # Arguments
@@ -204,6 +240,7 @@ impl AccessRequest {
     /// 2. Timestamp (dd/mm/yyyy hh:mm:ss)
     /// 3. Direction (0, 1, or 2)
     /// 4. Reader type (1 for RFID, 5 for Biometric)
+    /// 5. Correlation ID (optional UUID; empty or absent means none)
     ///
     /// # Returns
     ///
@@ -217,6 +254,7 @@ impl AccessRequest {
     /// - Timestamp parsing fails
     /// - Direction code is invalid
     /// - Reader type code is invalid
+    /// - Correlation ID is present but not a UUID
     ///
     /// # Examples
     ///
@@ -241,12 +279,17 @@ impl AccessRequest {
         let timestamp = HenryTimestamp::parse(&fields[1])?;
         let direction = Self::parse_direction(&fields[2])?;
         let reader_type = Self::parse_reader_type(&fields[3])?;
+        let correlation_id = match fields.get(Self::REQUIRED_FIELD_COUNT) {
+            Some(field) if !field.is_empty() => Some(field.parse()?),
+            _ => None,
+        };
 
         Ok(Self {
             card_number,
             timestamp,
             direction,
             reader_type,
+            correlation_id,
         })
     }
 
@@ -365,6 +408,23 @@ impl AccessRequest {
         Ok(())
     }
 
+    /// Convert the request to protocol message fields.
+    ///
+    /// The correlation ID is appended as a fifth field only when present,
+    /// so requests without one keep the four-field Henry layout.
+    pub fn to_fields(&self) -> Vec<String> {
+        let mut fields = vec![
+            self.card_number.clone(),
+            self.timestamp.format(),
+            self.direction.to_u8().to_string(),
+            self.reader_type.to_u8().to_string(),
+        ];
+        if let Some(correlation_id) = self.correlation_id {
+            fields.push(correlation_id.to_string());
+        }
+        fields
+    }
+
     /// Get the card number.
     pub fn card_number(&self) -> &str {
         &self.card_number
@@ -385,6 +445,11 @@ impl AccessRequest {
         self.reader_type
     }
 
+    /// Get the correlation ID assigned when the credential was read.
+    pub fn correlation_id(&self) -> Option<CorrelationId> {
+        self.correlation_id
+    }
+
     /// Returns `true` if this is an entry request.
     pub fn is_entry(&self) -> bool {
         self.direction.is_entry()
@@ -423,6 +488,7 @@ impl AccessRequest {
 /// - `GrantEntry`: 00+5 (allow entry only)
 /// - `GrantExit`: 00+6 (allow exit only)
 /// - `Deny`: 00+30 (deny access)
+/// - `DisplayOnly`: 00+0 (show a message, keep the turnstile locked)
 ///
 /// # Examples
 ///
@@ -453,6 +519,12 @@ pub enum AccessDecision {
     ///
     /// The turnstile will remain locked and display a denial message.
     Deny,
+
+    /// Display a message without releasing the turnstile.
+    ///
+    /// Used when the server has an instruction rather than a refusal, e.g.
+    /// "Dirija-se a recepcao". The turnstile stays locked as for a denial.
+    DisplayOnly,
 }
 
 impl AccessDecision {
@@ -470,13 +542,54 @@ impl AccessDecision {
     /// assert_eq!(AccessDecision::GrantEntry.command_code(), "00+5");
     /// assert_eq!(AccessDecision::GrantExit.command_code(), "00+6");
     /// assert_eq!(AccessDecision::Deny.command_code(), "00+30");
+    /// assert_eq!(AccessDecision::DisplayOnly.command_code(), "00+0");
     /// ```
     pub fn command_code(&self) -> &'static str {
+        self.command().as_str()
+    }
+
+    /// Command that carries this decision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::CommandCode;
+    /// use turnkey_protocol::commands::access::AccessDecision;
+    ///
+    /// assert_eq!(AccessDecision::DisplayOnly.command(), CommandCode::DisplayOnly);
+    /// ```
+    pub fn command(&self) -> CommandCode {
         match self {
-            Self::GrantBoth => "00+1",
-            Self::GrantEntry => "00+5",
-            Self::GrantExit => "00+6",
-            Self::Deny => "00+30",
+            Self::GrantBoth => CommandCode::GrantBoth,
+            Self::GrantEntry => CommandCode::GrantEntry,
+            Self::GrantExit => CommandCode::GrantExit,
+            Self::Deny => CommandCode::DenyAccess,
+            Self::DisplayOnly => CommandCode::DisplayOnly,
+        }
+    }
+
+    /// Decision carried by a response command, or `None` for other commands.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::CommandCode;
+    /// use turnkey_protocol::commands::access::AccessDecision;
+    ///
+    /// assert_eq!(
+    ///     AccessDecision::from_command(CommandCode::GrantExit),
+    ///     Some(AccessDecision::GrantExit)
+    /// );
+    /// assert_eq!(AccessDecision::from_command(CommandCode::AccessRequest), None);
+    /// ```
+    pub fn from_command(command: CommandCode) -> Option<Self> {
+        match command {
+            CommandCode::GrantBoth => Some(Self::GrantBoth),
+            CommandCode::GrantEntry => Some(Self::GrantEntry),
+            CommandCode::GrantExit => Some(Self::GrantExit),
+            CommandCode::DenyAccess => Some(Self::Deny),
+            CommandCode::DisplayOnly => Some(Self::DisplayOnly),
+            _ => None,
         }
     }
 
@@ -509,6 +622,25 @@ impl AccessDecision {
     pub fn is_deny(&self) -> bool {
         matches!(self, Self::Deny)
     }
+
+    /// Returns `true` if this decision only displays a message.
+    ///
+    /// Such a decision is neither a grant nor a denial, but the turnstile
+    /// stays locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::commands::access::AccessDecision;
+    ///
+    /// let decision = AccessDecision::DisplayOnly;
+    /// assert!(decision.is_display_only());
+    /// assert!(!decision.is_grant());
+    /// assert!(!decision.is_deny());
+    /// ```
+    pub fn is_display_only(&self) -> bool {
+        matches!(self, Self::DisplayOnly)
+    }
 }
 
 /// Access response message sent to turnstile.
@@ -525,7 +657,7 @@ impl AccessDecision {
 /// ```
 ///
 /// Where:
-/// - `COMMAND`: Decision command code (00+1, 00+5, 00+6, or 00+30)
+/// - `COMMAND`: Decision command code (00+1, 00+5, 00+6, 00+30 or 00+0)
 /// - `TIMEOUT`: Display timeout in seconds (0 for permanent)
 /// - `MESSAGE`: Text to display on turnstile LCD (max 40 chars)
 ///
@@ -564,11 +696,15 @@ impl AccessDecision {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccessResponse {
     decision: AccessDecision,
-    timeout_seconds: u8,
+    #[serde(rename = "timeout_seconds")]
+    timeout: Timeout,
     display_message: String,
 }
 
 impl AccessResponse {
+    /// Number of fields in a response message (timeout and display message).
+    pub const REQUIRED_FIELD_COUNT: usize = 2;
+
     /// Create a new access response.
     ///
     /// # Arguments
@@ -623,11 +759,28 @@ impl AccessResponse {
 
         Self {
             decision,
-            timeout_seconds,
+            timeout: Timeout::from_secs(timeout_seconds),
             display_message: truncated_message,
         }
     }
 
+    /// Use a different display timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_core::Timeout;
+    /// use turnkey_protocol::commands::access::AccessResponse;
+    ///
+    /// let response = AccessResponse::display_only("Procure o RH".to_string())
+    ///     .with_timeout(Timeout::PERMANENT);
+    /// assert!(response.timeout().is_permanent());
+    /// ```
+    pub fn with_timeout(mut self, timeout: Timeout) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     /// Create a grant both directions response with default timeout.
     ///
     /// Uses a default timeout of 5 seconds.
@@ -708,6 +861,29 @@ impl AccessResponse {
         )
     }
 
+    /// Create a message-only response that keeps the turnstile locked.
+    ///
+    /// Uses a default timeout of 5 seconds, so the user can read the
+    /// instruction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::commands::access::AccessResponse;
+    ///
+    /// let response = AccessResponse::display_only("Dirija-se a recepcao".to_string());
+    /// assert!(response.is_display_only());
+    /// assert!(!response.is_grant());
+    /// assert_eq!(response.timeout_seconds(), 5);
+    /// ```
+    pub fn display_only(display_message: String) -> Self {
+        Self::new(
+            AccessDecision::DisplayOnly,
+            DEFAULT_DISPLAY_TIMEOUT_SECONDS,
+            display_message,
+        )
+    }
+
     /// Convert response to protocol message fields.
     ///
     /// Returns the fields in the order required by the Henry protocol:
@@ -734,19 +910,65 @@ impl AccessResponse {
     pub fn to_fields(&self) -> Vec<String> {
         vec![
             self.decision.command_code().to_string(),
-            self.timeout_seconds.to_string(),
+            self.timeout.to_string(),
             self.display_message.clone(),
         ]
     }
 
+    /// Parse the fields of a response message.
+    ///
+    /// The decision travels in the command code, so only the fields after
+    /// it are passed here: the display timeout and the display message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingField` if a field is missing and
+    /// `Error::InvalidFieldFormat` if the timeout is not a number of seconds
+    /// between 0 and 255.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::commands::access::{AccessDecision, AccessResponse};
+    ///
+    /// let fields = vec!["5".to_string(), "Acesso liberado".to_string()];
+    /// let response = AccessResponse::parse(AccessDecision::GrantEntry, &fields).unwrap();
+    ///
+    /// assert_eq!(response.timeout_seconds(), 5);
+    /// assert_eq!(response.display_message(), "Acesso liberado");
+    /// ```
+    pub fn parse(decision: AccessDecision, fields: &[String]) -> Result<Self> {
+        if fields.len() < Self::REQUIRED_FIELD_COUNT {
+            return Err(Error::MissingField(format!(
+                "Access response requires {} fields, got {}",
+                Self::REQUIRED_FIELD_COUNT,
+                fields.len()
+            )));
+        }
+
+        let timeout = Timeout::parse_field(&fields[0], "response timeout")?;
+
+        // Kept as received: the wire field was already length-checked
+        Ok(Self {
+            decision,
+            timeout,
+            display_message: fields[1].clone(),
+        })
+    }
+
     /// Get the access decision.
     pub fn decision(&self) -> AccessDecision {
         self.decision
     }
 
+    /// Get the display timeout.
+    pub fn timeout(&self) -> Timeout {
+        self.timeout
+    }
+
     /// Get the timeout in seconds.
     pub fn timeout_seconds(&self) -> u8 {
-        self.timeout_seconds
+        self.timeout.as_secs()
     }
 
     /// Get the display message.
@@ -763,6 +985,11 @@ impl AccessResponse {
     pub fn is_deny(&self) -> bool {
         self.decision.is_deny()
     }
+
+    /// Returns `true` if this response only displays a message.
+    pub fn is_display_only(&self) -> bool {
+        self.decision.is_display_only()
+    }
 }
 
 #[cfg(test)]
@@ -804,6 +1031,35 @@ mod tests {
         assert_eq!(request.reader_type(), ReaderType::Rfid);
     }
 
+    #[test]
+    fn test_parse_correlation_id() {
+        let correlation_id = CorrelationId::new();
+        let mut fields = vec![
+            "12345678".to_string(),
+            "10/05/2025 12:46:06".to_string(),
+            "1".to_string(),
+            "1".to_string(),
+        ];
+        assert_eq!(
+            AccessRequest::parse(&fields).unwrap().correlation_id(),
+            None
+        );
+
+        fields.push(String::new());
+        assert_eq!(
+            AccessRequest::parse(&fields).unwrap().correlation_id(),
+            None
+        );
+
+        fields[4] = correlation_id.to_string();
+        let request = AccessRequest::parse(&fields).unwrap();
+        assert_eq!(request.correlation_id(), Some(correlation_id));
+        assert_eq!(request.to_fields(), fields);
+
+        fields[4] = "not-a-uuid".to_string();
+        assert!(AccessRequest::parse(&fields).is_err());
+    }
+
     #[test]
     fn test_parse_exit_request() {
         let fields = vec![
@@ -1489,6 +1745,25 @@ mod tests {
         assert_eq!(response.display_message(), "");
     }
 
+    #[test]
+    fn test_access_response_parse_timeout_bounds() {
+        let fields = |timeout: &str| vec![timeout.to_string(), "Aguarde".to_string()];
+
+        let response = AccessResponse::parse(AccessDecision::DisplayOnly, &fields("0")).unwrap();
+        assert!(response.timeout().is_permanent());
+        let response = AccessResponse::parse(AccessDecision::GrantEntry, &fields("255")).unwrap();
+        assert_eq!(response.timeout(), Timeout::MAX);
+
+        for invalid in ["256", "-1", "5s", ""] {
+            let err = AccessResponse::parse(AccessDecision::GrantEntry, &fields(invalid));
+            assert!(
+                matches!(&err, Err(Error::InvalidFieldFormat { message })
+                    if message.contains("response timeout")),
+                "{invalid}: {err:?}"
+            );
+        }
+    }
+
     #[test]
     fn test_access_response_clone() {
         let response1 = AccessResponse::grant_exit("Test".to_string());
@@ -0,0 +1,272 @@
+//! Security alarm reports (command code AL).
+//!
+//! A device that detects a suspicious pattern, such as the same card being
+//! denied again and again, reports it to the server with an `AlarmReport`
+//! so the security desk sees it at once instead of in the next log pull.
+//! The server acknowledges nothing; alarms are informational and the
+//! device keeps validating.
+//!
+//! This command is a Turnkey extension and not part of the Henry
+//! specification.
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+AL]<KIND>]<CARD_NUMBER>]<COUNT>]<WINDOW>]<TIME>]<LOCKED_UNTIL>]
+//! ```
+//!
+//! Where:
+//! - `KIND`: alarm kind code, see [`AlarmKind`]
+//! - `CARD_NUMBER`: credential the alarm is about
+//! - `COUNT`: events that raised the alarm (e.g. denials)
+//! - `WINDOW`: seconds the events were counted over
+//! - `TIME`: when the alarm was raised (`dd/mm/yyyy hh:mm:ss`)
+//! - `LOCKED_UNTIL`: end of the card lockout the alarm started, `-` if none
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::commands::alarm::{AlarmKind, AlarmReport};
+//! use turnkey_core::{DeviceId, HenryTimestamp};
+//!
+//! let report = AlarmReport {
+//!     kind: AlarmKind::RepeatedDenials,
+//!     card_number: "12345678".to_string(),
+//!     count: 5,
+//!     window_secs: 60,
+//!     timestamp: HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+//!     locked_until: None,
+//! };
+//! let message = report.to_message(DeviceId::new(15).unwrap()).unwrap();
+//!
+//! assert_eq!(AlarmReport::parse(&message).unwrap(), report);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use turnkey_core::{DeviceId, Error, HenryTimestamp, Result};
+
+/// Placeholder for an absent lockout, since frames drop empty fields
+const ABSENT: &str = "-";
+
+/// Number of fields in an alarm report
+const ALARM_FIELD_COUNT: usize = 6;
+
+/// What an alarm is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AlarmKind {
+    /// The same card was denied repeatedly in a short time, as when
+    /// someone is forced to try a card that does not work
+    RepeatedDenials,
+}
+
+impl AlarmKind {
+    /// Wire code of the kind
+    pub fn code(self) -> u8 {
+        match self {
+            Self::RepeatedDenials => 1,
+        }
+    }
+
+    /// Kind with the given wire code
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` for unknown codes.
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            1 => Ok(Self::RepeatedDenials),
+            _ => Err(Error::InvalidFieldFormat {
+                message: format!("Unknown alarm kind {}", code),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for AlarmKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RepeatedDenials => write!(f, "repeated denials"),
+        }
+    }
+}
+
+/// Alarm raised by a device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlarmReport {
+    /// What the alarm is about
+    pub kind: AlarmKind,
+
+    /// Credential the alarm is about
+    pub card_number: String,
+
+    /// Events that raised the alarm
+    pub count: u32,
+
+    /// Seconds the events were counted over
+    pub window_secs: u32,
+
+    /// When the alarm was raised
+    pub timestamp: HenryTimestamp,
+
+    /// End of the card lockout started with the alarm
+    pub locked_until: Option<HenryTimestamp>,
+}
+
+impl PartialEq for AlarmReport {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.card_number == other.card_number
+            && self.count == other.count
+            && self.window_secs == other.window_secs
+            && self.timestamp.format() == other.timestamp.format()
+            && self.locked_until.as_ref().map(HenryTimestamp::format)
+                == other.locked_until.as_ref().map(HenryTimestamp::format)
+    }
+}
+
+impl Eq for AlarmReport {}
+
+impl AlarmReport {
+    /// Encode the report as the fields of an `AL` message.
+    pub fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.kind.code().to_string(),
+            self.card_number.clone(),
+            self.count.to_string(),
+            self.window_secs.to_string(),
+            self.timestamp.format(),
+            self.locked_until
+                .as_ref()
+                .map_or_else(|| ABSENT.to_string(), HenryTimestamp::format),
+        ]
+    }
+
+    /// Build the `AL` message the device sends to the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` if the card number is empty or
+    /// contains protocol delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        if self.card_number.trim().is_empty() {
+            return Err(Error::InvalidFieldFormat {
+                message: "Alarm card number is required".to_string(),
+            });
+        }
+        let fields = self
+            .to_fields()
+            .into_iter()
+            .map(FieldData::new)
+            .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::AlarmReport)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse a report from an `AL` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if fields are missing, and
+    /// `Error::InvalidFieldFormat` or `Error::InvalidTimestamp` if a value
+    /// is invalid.
+    pub fn parse(message: &Message) -> Result<Self> {
+        if message.command != CommandCode::AlarmReport {
+            return Err(Error::InvalidCommandCode {
+                code: message.command.as_str().to_string(),
+            });
+        }
+        if message.field_count() < ALARM_FIELD_COUNT {
+            return Err(Error::MissingField(format!(
+                "Alarm report requires {} fields, got {}",
+                ALARM_FIELD_COUNT,
+                message.field_count()
+            )));
+        }
+
+        let locked_until = match message.required_field(5, "locked until")? {
+            ABSENT => None,
+            value => Some(HenryTimestamp::parse(value)?),
+        };
+        Ok(Self {
+            kind: AlarmKind::from_code(parse_number(
+                message.required_field(0, "alarm kind")?,
+                "alarm kind",
+            )?)?,
+            card_number: message.required_field(1, "card number")?.to_string(),
+            count: parse_number(message.required_field(2, "count")?, "count")?,
+            window_secs: parse_number(message.required_field(3, "window")?, "window")?,
+            timestamp: HenryTimestamp::parse(message.required_field(4, "timestamp")?)?,
+            locked_until,
+        })
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T> {
+    value.parse().map_err(|_| Error::InvalidFieldFormat {
+        message: format!("Invalid {} '{}'", name, value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MessageParser;
+
+    fn report(locked_until: Option<&str>) -> AlarmReport {
+        AlarmReport {
+            kind: AlarmKind::RepeatedDenials,
+            card_number: "12345678".to_string(),
+            count: 5,
+            window_secs: 60,
+            timestamp: HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            locked_until: locked_until.map(|value| HenryTimestamp::parse(value).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for report in [report(None), report(Some("10/05/2025 12:51:06"))] {
+            let message = report.to_message(DeviceId::new(15).unwrap()).unwrap();
+            assert_eq!(message.command, CommandCode::AlarmReport);
+            assert_eq!(AlarmReport::parse(&message).unwrap(), report);
+        }
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let message = MessageParser::parse(
+            "15+REON+AL]1]12345678]5]60]10/05/2025 12:46:06]10/05/2025 12:51:06]",
+        )
+        .unwrap();
+        assert_eq!(
+            AlarmReport::parse(&message).unwrap(),
+            report(Some("10/05/2025 12:51:06"))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_messages() {
+        for raw in [
+            "15+REON+AL]1]12345678]5]60]10/05/2025 12:46:06]",
+            "15+REON+AL]9]12345678]5]60]10/05/2025 12:46:06]-]",
+            "15+REON+AL]1]12345678]cinco]60]10/05/2025 12:46:06]-]",
+            "15+REON+AL]1]12345678]5]60]ontem]-]",
+        ] {
+            let message = MessageParser::parse(raw).unwrap();
+            assert!(AlarmReport::parse(&message).is_err(), "{raw} should fail");
+        }
+
+        let message = MessageParser::parse("15+REON+00+30]0]Negado]").unwrap();
+        assert!(matches!(
+            AlarmReport::parse(&message),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+    }
+}
@@ -28,6 +28,9 @@
 //! - `GrantEntry` (00+5): Server grants entry access only
 //! - `GrantExit` (00+6): Server grants exit access only
 //! - `DenyAccess` (00+30): Server denies access
+//! - `DisplayOnly` (00+0): Server shows a message without releasing the turnstile (Turnkey extension)
+//! - `OverrideGrant` (00+40): Operator grants a denied access (Turnkey extension)
+//! - `AlarmReport` (AL): Device reports a security alarm, e.g. repeated denials (Turnkey extension)
 //!
 //! ## Turnstile Status
 //!
@@ -47,6 +50,8 @@
 //! - `ReceiveLogs` (ER): Retrieve access logs from device
 //! - `QueryStatus` (RQ): Query device status and counters
 //! - `ReceiveConfig` (RC): Request current device configuration
+//! - `SelfTest` (RT): Run a device health sweep (Turnkey extension)
+//! - `Diagnostics` (RD): Run peripheral diagnostics (Turnkey extension)
 //!
 //! # Wire Format Examples
 //!
@@ -143,7 +148,7 @@ use turnkey_core::{Error, Result};
 /// let parsed = CommandCode::parse("00+6").unwrap();
 /// assert_eq!(parsed, CommandCode::GrantExit);
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CommandCode {
     // Access control
     AccessRequest, // 000+0
@@ -152,6 +157,9 @@ pub enum CommandCode {
     GrantEntry,    // 00+5
     GrantExit,     // 00+6
     DenyAccess,    // 00+30
+    DisplayOnly,   // 00+0 (Turnkey extension)
+    OverrideGrant, // 00+40 (Turnkey extension)
+    AlarmReport,   // AL (Turnkey extension)
 
     // Turnstile status
     WaitingRotation,   // 000+80
@@ -167,6 +175,8 @@ pub enum CommandCode {
     ReceiveLogs,    // ER
     QueryStatus,    // RQ
     ReceiveConfig,  // RC
+    SelfTest,       // RT (Turnkey extension)
+    Diagnostics,    // RD (Turnkey extension)
 }
 
 impl CommandCode {
@@ -178,6 +188,9 @@ impl CommandCode {
             "00+5" => Ok(CommandCode::GrantEntry),
             "00+6" => Ok(CommandCode::GrantExit),
             "00+30" => Ok(CommandCode::DenyAccess),
+            "00+0" => Ok(CommandCode::DisplayOnly),
+            "00+40" => Ok(CommandCode::OverrideGrant),
+            "AL" => Ok(CommandCode::AlarmReport),
             "000+80" => Ok(CommandCode::WaitingRotation),
             "000+81" => Ok(CommandCode::RotationCompleted),
             "000+82" => Ok(CommandCode::RotationTimeout),
@@ -189,6 +202,8 @@ impl CommandCode {
             "ER" => Ok(CommandCode::ReceiveLogs),
             "RQ" => Ok(CommandCode::QueryStatus),
             "RC" => Ok(CommandCode::ReceiveConfig),
+            "RT" => Ok(CommandCode::SelfTest),
+            "RD" => Ok(CommandCode::Diagnostics),
             _ => Err(Error::InvalidCommandCode {
                 code: s.to_string(),
             }),
@@ -203,6 +218,9 @@ impl CommandCode {
             CommandCode::GrantEntry => "00+5",
             CommandCode::GrantExit => "00+6",
             CommandCode::DenyAccess => "00+30",
+            CommandCode::DisplayOnly => "00+0",
+            CommandCode::OverrideGrant => "00+40",
+            CommandCode::AlarmReport => "AL",
             CommandCode::WaitingRotation => "000+80",
             CommandCode::RotationCompleted => "000+81",
             CommandCode::RotationTimeout => "000+82",
@@ -214,6 +232,8 @@ impl CommandCode {
             CommandCode::ReceiveLogs => "ER",
             CommandCode::QueryStatus => "RQ",
             CommandCode::ReceiveConfig => "RC",
+            CommandCode::SelfTest => "RT",
+            CommandCode::Diagnostics => "RD",
         }
     }
 
@@ -264,6 +284,9 @@ impl CommandCode {
                 | Self::GrantEntry
                 | Self::GrantExit
                 | Self::DenyAccess
+                | Self::DisplayOnly
+                | Self::OverrideGrant
+                | Self::AlarmReport
         )
     }
 
@@ -295,6 +318,29 @@ impl CommandCode {
         )
     }
 
+    /// Returns `true` if this command carries a bulk transfer.
+    ///
+    /// Bulk transfers synchronize card lists, users, biometric templates and
+    /// access logs. Only these commands are compressed on connections that
+    /// negotiated compression (see [`crate::compression`]).
+    ///
+    /// # Example
+    /// ```
+    /// use turnkey_protocol::CommandCode;
+    ///
+    /// assert!(CommandCode::SendCards.is_bulk_transfer());
+    /// assert!(CommandCode::SendBiometrics.is_bulk_transfer());
+    /// assert!(!CommandCode::SendDateTime.is_bulk_transfer());
+    /// assert!(!CommandCode::AccessRequest.is_bulk_transfer());
+    /// ```
+    #[inline]
+    pub fn is_bulk_transfer(&self) -> bool {
+        matches!(
+            self,
+            Self::SendCards | Self::SendUsers | Self::SendBiometrics | Self::ReceiveLogs
+        )
+    }
+
     /// Returns `true` if this command is a turnstile status command.
     ///
     /// Turnstile status commands track the physical state of the turnstile rotation
@@ -326,12 +372,14 @@ impl CommandCode {
     /// use turnkey_protocol::CommandCode;
     ///
     /// assert!(CommandCode::QueryStatus.is_query());
+    /// assert!(CommandCode::SelfTest.is_query());
+    /// assert!(CommandCode::Diagnostics.is_query());
     /// assert!(!CommandCode::SendConfig.is_query());
     /// assert!(!CommandCode::AccessRequest.is_query());
     /// ```
     #[inline]
     pub fn is_query(&self) -> bool {
-        matches!(self, Self::QueryStatus)
+        matches!(self, Self::QueryStatus | Self::SelfTest | Self::Diagnostics)
     }
 }
 
@@ -358,6 +406,9 @@ mod tests {
             CommandCode::GrantEntry,
             CommandCode::GrantExit,
             CommandCode::DenyAccess,
+            CommandCode::DisplayOnly,
+            CommandCode::OverrideGrant,
+            CommandCode::AlarmReport,
             // Turnstile status commands
             CommandCode::WaitingRotation,
             CommandCode::RotationCompleted,
@@ -371,6 +422,8 @@ mod tests {
             CommandCode::ReceiveLogs,
             CommandCode::QueryStatus,
             CommandCode::ReceiveConfig,
+            CommandCode::SelfTest,
+            CommandCode::Diagnostics,
         ]
     }
 
@@ -418,6 +471,9 @@ mod tests {
         assert_eq!(format!("{}", CommandCode::GrantEntry), "00+5");
         assert_eq!(format!("{}", CommandCode::GrantExit), "00+6");
         assert_eq!(format!("{}", CommandCode::DenyAccess), "00+30");
+        assert_eq!(format!("{}", CommandCode::DisplayOnly), "00+0");
+        assert_eq!(format!("{}", CommandCode::OverrideGrant), "00+40");
+        assert_eq!(format!("{}", CommandCode::AlarmReport), "AL");
 
         // Turnstile status commands
         assert_eq!(format!("{}", CommandCode::WaitingRotation), "000+80");
@@ -432,6 +488,8 @@ mod tests {
         assert_eq!(format!("{}", CommandCode::SendDateTime), "EH");
         assert_eq!(format!("{}", CommandCode::ReceiveLogs), "ER");
         assert_eq!(format!("{}", CommandCode::QueryStatus), "RQ");
+        assert_eq!(format!("{}", CommandCode::SelfTest), "RT");
+        assert_eq!(format!("{}", CommandCode::Diagnostics), "RD");
         assert_eq!(format!("{}", CommandCode::ReceiveConfig), "RC");
     }
 
@@ -464,6 +522,7 @@ mod tests {
         assert_eq!(CommandCode::GrantEntry.len(), 4); // "00+5"
         assert_eq!(CommandCode::GrantExit.len(), 4); // "00+6"
         assert_eq!(CommandCode::DenyAccess.len(), 5); // "00+30"
+        assert_eq!(CommandCode::OverrideGrant.len(), 5); // "00+40"
         assert_eq!(CommandCode::WaitingRotation.len(), 6); // "000+80"
         assert_eq!(CommandCode::RotationCompleted.len(), 6); // "000+81"
         assert_eq!(CommandCode::RotationTimeout.len(), 6); // "000+82"
@@ -499,7 +558,7 @@ mod tests {
 
         assert_eq!(
             commands.len(),
-            17,
+            22,
             "all_command_codes() must include all CommandCode variants. \
              If you added a new command, update all_command_codes() and this assertion."
         );
@@ -524,6 +583,9 @@ mod tests {
         assert!(CommandCode::GrantEntry.is_access_control());
         assert!(CommandCode::GrantExit.is_access_control());
         assert!(CommandCode::DenyAccess.is_access_control());
+        assert!(CommandCode::DisplayOnly.is_access_control());
+        assert!(CommandCode::OverrideGrant.is_access_control());
+        assert!(CommandCode::AlarmReport.is_access_control());
 
         // Non-access control commands should return false
         assert!(!CommandCode::WaitingRotation.is_access_control());
@@ -563,6 +625,21 @@ mod tests {
         assert!(!CommandCode::QueryStatus.is_management());
     }
 
+    #[test]
+    fn test_is_bulk_transfer() {
+        assert!(CommandCode::SendCards.is_bulk_transfer());
+        assert!(CommandCode::SendUsers.is_bulk_transfer());
+        assert!(CommandCode::SendBiometrics.is_bulk_transfer());
+        assert!(CommandCode::ReceiveLogs.is_bulk_transfer());
+
+        assert!(!CommandCode::SendConfig.is_bulk_transfer());
+        assert!(!CommandCode::SendDateTime.is_bulk_transfer());
+        assert!(!CommandCode::ReceiveConfig.is_bulk_transfer());
+        assert!(!CommandCode::QueryStatus.is_bulk_transfer());
+        assert!(!CommandCode::AccessRequest.is_bulk_transfer());
+        assert!(!CommandCode::OverrideGrant.is_bulk_transfer());
+    }
+
     #[test]
     fn test_is_turnstile_status() {
         // Turnstile status commands should return true
@@ -589,8 +666,10 @@ mod tests {
 
     #[test]
     fn test_is_query() {
-        // Query command should return true
+        // Query commands should return true
         assert!(CommandCode::QueryStatus.is_query());
+        assert!(CommandCode::SelfTest.is_query());
+        assert!(CommandCode::Diagnostics.is_query());
 
         // Non-query commands should return false
         assert!(!CommandCode::AccessRequest.is_query());
@@ -0,0 +1,414 @@
+//! Peripheral diagnostics (command code RD, Turnkey extension).
+//!
+//! Maintenance technicians test readers remotely before driving to a site.
+//! The server sends an `RD` message, optionally naming one peripheral; the
+//! device runs each peripheral's self-test (a test read for RFID readers),
+//! reads its firmware details and, for readers that measure it, the radio
+//! link quality, and answers with an `RD` message carrying one entry per
+//! peripheral.
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+RD                                           (request, all peripherals)
+//! <ID>+REON+RD]<PERIPHERAL>                              (request, one peripheral)
+//! <ID>+REON+RD]<PERIPHERAL>]<RESULT>]<DETAIL>]<FIRMWARE>]<SIGNAL>]<READS>]<ERRORS>]...
+//! ```
+//!
+//! Where:
+//! - `PERIPHERAL`: `keypad`, `rfid` or `biometric`
+//! - `RESULT`: `1` if the self-test passed, `0` if it failed
+//! - `DETAIL`: what the self-test found
+//! - `FIRMWARE`: firmware version
+//! - `SIGNAL`: field strength in percent (0-100)
+//! - `READS`, `ERRORS`: card reads attempted and failed
+//!
+//! Absent values are sent as `-`, since frames drop empty fields.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::commands::diagnostics::{
+//!     DiagnosticsReport, PeripheralDiagnostic, SignalReport,
+//! };
+//! use turnkey_core::DeviceId;
+//!
+//! let report = DiagnosticsReport::new(vec![
+//!     PeripheralDiagnostic::new("rfid", true, "Test read ok, field at 90%")
+//!         .with_firmware("1.0.0")
+//!         .with_signal(SignalReport::new(90, 1200, 3)),
+//!     PeripheralDiagnostic::new("keypad", false, "Input disconnected"),
+//! ]);
+//! assert!(!report.passed());
+//!
+//! let message = report.to_message(DeviceId::new(15).unwrap()).unwrap();
+//! assert_eq!(DiagnosticsReport::parse(&message).unwrap(), report);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use turnkey_core::{DeviceId, Error, Result};
+
+/// Number of fields per peripheral in a report
+const PERIPHERAL_FIELD_COUNT: usize = 7;
+
+/// Value sent for an absent field, since frames drop empty fields
+const ABSENT: &str = "-";
+
+/// Radio link quality of a reader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignalReport {
+    /// Field strength in percent
+    pub strength: u8,
+
+    /// Card reads attempted
+    pub reads: u32,
+
+    /// Card reads that failed
+    pub read_errors: u32,
+}
+
+impl SignalReport {
+    /// Create a signal report.
+    pub fn new(strength: u8, reads: u32, read_errors: u32) -> Self {
+        Self {
+            strength,
+            reads,
+            read_errors,
+        }
+    }
+}
+
+/// Diagnostics of one peripheral.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeripheralDiagnostic {
+    /// Peripheral diagnosed (`keypad`, `rfid`, `biometric`)
+    pub name: String,
+
+    /// Whether the self-test passed
+    pub passed: bool,
+
+    /// What the self-test found
+    pub detail: String,
+
+    /// Firmware version, if reported
+    pub firmware: Option<String>,
+
+    /// Radio link quality, if measured
+    pub signal: Option<SignalReport>,
+}
+
+impl PeripheralDiagnostic {
+    /// Create diagnostics without firmware or signal details.
+    pub fn new(name: impl Into<String>, passed: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed,
+            detail: detail.into(),
+            firmware: None,
+            signal: None,
+        }
+    }
+
+    /// Set the firmware version.
+    pub fn with_firmware(mut self, firmware: impl Into<String>) -> Self {
+        self.firmware = Some(firmware.into());
+        self
+    }
+
+    /// Set the radio link quality.
+    pub fn with_signal(mut self, signal: SignalReport) -> Self {
+        self.signal = Some(signal);
+        self
+    }
+}
+
+/// Diagnostics of the peripherals of a device, in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    /// One entry per peripheral
+    pub peripherals: Vec<PeripheralDiagnostic>,
+}
+
+impl DiagnosticsReport {
+    /// Create a report from the diagnostics of its peripherals.
+    pub fn new(peripherals: Vec<PeripheralDiagnostic>) -> Self {
+        Self { peripherals }
+    }
+
+    /// Add the diagnostics of another peripheral.
+    pub fn push(&mut self, peripheral: PeripheralDiagnostic) {
+        self.peripherals.push(peripheral);
+    }
+
+    /// Whether every peripheral passed its self-test.
+    pub fn passed(&self) -> bool {
+        self.peripherals.iter().all(|peripheral| peripheral.passed)
+    }
+
+    /// Peripherals that failed their self-test.
+    pub fn failures(&self) -> impl Iterator<Item = &PeripheralDiagnostic> {
+        self.peripherals
+            .iter()
+            .filter(|peripheral| !peripheral.passed)
+    }
+
+    /// Diagnostics of a peripheral by name.
+    pub fn peripheral(&self, name: &str) -> Option<&PeripheralDiagnostic> {
+        self.peripherals
+            .iter()
+            .find(|peripheral| peripheral.name == name)
+    }
+
+    /// Build a diagnostics request for all peripherals or for one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peripheral name contains protocol delimiters.
+    pub fn request(device_id: DeviceId, peripheral: Option<&str>) -> Result<Message> {
+        let mut builder = MessageBuilder::new(device_id, CommandCode::Diagnostics);
+        if let Some(peripheral) = peripheral {
+            builder = builder.field(FieldData::new(peripheral.to_string())?);
+        }
+        builder.build()
+    }
+
+    /// Check whether a message is a diagnostics request rather than a report.
+    pub fn is_request(message: &Message) -> bool {
+        message.command == CommandCode::Diagnostics && message.field_count() <= 1
+    }
+
+    /// Peripheral named by a diagnostics request, `None` for all of them.
+    pub fn requested_peripheral(message: &Message) -> Option<&str> {
+        if Self::is_request(message) {
+            message.field(0)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether a message carries a diagnostics report.
+    pub fn is_report(message: &Message) -> bool {
+        message.command == CommandCode::Diagnostics && message.field_count() > 1
+    }
+
+    /// Encode the report as the fields of an `RD` message.
+    pub fn to_fields(&self) -> Vec<String> {
+        fn or_absent(value: Option<String>) -> String {
+            value
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| ABSENT.to_string())
+        }
+
+        self.peripherals
+            .iter()
+            .flat_map(|peripheral| {
+                let signal = peripheral.signal;
+                [
+                    peripheral.name.clone(),
+                    if peripheral.passed { "1" } else { "0" }.to_string(),
+                    or_absent(Some(peripheral.detail.clone())),
+                    or_absent(peripheral.firmware.clone()),
+                    or_absent(signal.map(|s| s.strength.to_string())),
+                    or_absent(signal.map(|s| s.reads.to_string())),
+                    or_absent(signal.map(|s| s.read_errors.to_string())),
+                ]
+            })
+            .collect()
+    }
+
+    /// Build the `RD` message a device sends with its diagnostics.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the report has no peripherals, or if a name,
+    /// detail or firmware version contains protocol delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        if self.peripherals.is_empty() {
+            return Err(Error::MissingField(
+                "Diagnostics report requires at least one peripheral".to_string(),
+            ));
+        }
+        let fields = self
+            .to_fields()
+            .into_iter()
+            .map(FieldData::new)
+            .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::Diagnostics)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse a diagnostics report from an `RD` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the message is a request or ends in the
+    /// middle of a peripheral, and `Error::InvalidFieldFormat` if a result
+    /// or a signal value is malformed.
+    pub fn parse(message: &Message) -> Result<Self> {
+        if message.command != CommandCode::Diagnostics {
+            return Err(Error::InvalidCommandCode {
+                code: message.command.as_str().to_string(),
+            });
+        }
+        let count = message.field_count();
+        if count <= 1 || !count.is_multiple_of(PERIPHERAL_FIELD_COUNT) {
+            return Err(Error::MissingField(format!(
+                "Diagnostics report requires {} fields per peripheral, got {}",
+                PERIPHERAL_FIELD_COUNT, count
+            )));
+        }
+
+        let peripherals = (0..count)
+            .step_by(PERIPHERAL_FIELD_COUNT)
+            .map(|index| {
+                let name = message.required_field(index, "peripheral")?;
+                let passed = match message.required_field(index + 1, "result")? {
+                    "1" => true,
+                    "0" => false,
+                    other => {
+                        return Err(Error::InvalidFieldFormat {
+                            message: format!("Invalid result '{}' for '{}'", other, name),
+                        });
+                    }
+                };
+                let text = |offset: usize| match message.field(index + offset) {
+                    None | Some(ABSENT) => None,
+                    Some(value) => Some(value.to_string()),
+                };
+                let strength = optional_number::<u8>(message, index + 4, "signal strength")?;
+                let reads = optional_number::<u32>(message, index + 5, "reads")?;
+                let read_errors = optional_number::<u32>(message, index + 6, "read errors")?;
+
+                Ok(PeripheralDiagnostic {
+                    name: name.to_string(),
+                    passed,
+                    detail: text(2).unwrap_or_default(),
+                    firmware: text(3),
+                    signal: strength.map(|strength| {
+                        SignalReport::new(
+                            strength,
+                            reads.unwrap_or_default(),
+                            read_errors.unwrap_or_default(),
+                        )
+                    }),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { peripherals })
+    }
+}
+
+/// Parse an optional numeric field, `-` meaning absent.
+fn optional_number<T: FromStr>(message: &Message, index: usize, name: &str) -> Result<Option<T>> {
+    match message.field(index) {
+        None | Some(ABSENT) => Ok(None),
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::InvalidFieldFormat {
+                message: format!("Invalid {} '{}'", name, value),
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::format_message;
+    use crate::parser::MessageParser;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn report() -> DiagnosticsReport {
+        DiagnosticsReport::new(vec![
+            PeripheralDiagnostic::new("keypad", true, "Mock Keypad").with_firmware("1.0.0"),
+            PeripheralDiagnostic::new("rfid", false, "Test read failed, field at 10%")
+                .with_firmware("1.0.0")
+                .with_signal(SignalReport::new(10, 40, 12)),
+            PeripheralDiagnostic::new("biometric", true, ""),
+        ])
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let message = report().to_message(device()).unwrap();
+        assert!(DiagnosticsReport::is_report(&message));
+        assert!(!DiagnosticsReport::is_request(&message));
+        assert_eq!(DiagnosticsReport::parse(&message).unwrap(), report());
+
+        // Absent values keep their place through a frame
+        let decoded = MessageParser::parse(&format_message(&message)).unwrap();
+        assert_eq!(DiagnosticsReport::parse(&decoded).unwrap(), report());
+    }
+
+    #[test]
+    fn test_report_wire_format() {
+        let message = MessageParser::parse(
+            "15+REON+RD]keypad]1]Mock Keypad]1.0.0]-]-]-]\
+             rfid]0]Test read failed, field at 10%]1.0.0]10]40]12]\
+             biometric]1]-]-]-]-]-",
+        )
+        .unwrap();
+        let parsed = DiagnosticsReport::parse(&message).unwrap();
+        assert_eq!(parsed, report());
+        assert!(!parsed.passed());
+        let failed: Vec<_> = parsed.failures().map(|p| p.name.as_str()).collect();
+        assert_eq!(failed, vec!["rfid"]);
+        assert_eq!(parsed.peripheral("rfid").unwrap().signal.unwrap().reads, 40);
+    }
+
+    #[test]
+    fn test_requests() {
+        let all = DiagnosticsReport::request(device(), None).unwrap();
+        assert!(DiagnosticsReport::is_request(&all));
+        assert_eq!(DiagnosticsReport::requested_peripheral(&all), None);
+        assert!(matches!(
+            DiagnosticsReport::parse(&all),
+            Err(Error::MissingField(_))
+        ));
+
+        let rfid = DiagnosticsReport::request(device(), Some("rfid")).unwrap();
+        assert!(DiagnosticsReport::is_request(&rfid));
+        assert!(!DiagnosticsReport::is_report(&rfid));
+        assert_eq!(DiagnosticsReport::requested_peripheral(&rfid), Some("rfid"));
+
+        assert!(DiagnosticsReport::default().to_message(device()).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_fields() {
+        let message = MessageParser::parse("15+REON+RD]rfid]1]ok]1.0]90]10").unwrap();
+        assert!(matches!(
+            DiagnosticsReport::parse(&message),
+            Err(Error::MissingField(_))
+        ));
+
+        let message = MessageParser::parse("15+REON+RD]rfid]yes]ok]1.0]90]10]0").unwrap();
+        assert!(matches!(
+            DiagnosticsReport::parse(&message),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+
+        let message = MessageParser::parse("15+REON+RD]rfid]1]ok]1.0]300]10]0").unwrap();
+        assert!(matches!(
+            DiagnosticsReport::parse(&message),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+
+        let message = MessageParser::parse("15+REON+RT]rfid]1]ok").unwrap();
+        assert!(matches!(
+            DiagnosticsReport::parse(&message),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+    }
+}
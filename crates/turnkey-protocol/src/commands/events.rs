@@ -0,0 +1,672 @@
+//! Event retrieval from devices (command code ER).
+//!
+//! Devices normally push access events as they happen, but a device that was
+//! offline keeps them in its local log. The server pulls them with an `ER`
+//! query naming a cursor: the last event ID it already has and, optionally,
+//! the oldest event time it is interested in. The device answers with one or
+//! more `ER` chunks; the last chunk of an answer says whether more events are
+//! left beyond the query limit, so the server can continue from the new
+//! cursor.
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+ER]Q]<AFTER_ID>]<SINCE>]<LIMIT>                       (query, server to device)
+//! <ID>+REON+ER]D]<CHUNK>]<LAST>]<MORE>]<NEXT_ID>]<EVENT>]...      (chunk, device to server)
+//! ```
+//!
+//! Where:
+//! - `AFTER_ID`: return events with a device event ID above this (0 = from the start)
+//! - `SINCE`: return events at or after this time (`dd/mm/yyyy hh:mm:ss`, `-` = any)
+//! - `LIMIT`: maximum number of events in the answer
+//! - `CHUNK`: position of the chunk in the answer, from 0
+//! - `LAST`: `1` on the last chunk of the answer
+//! - `MORE`: `1` if events beyond the limit are left (meaningful on the last chunk)
+//! - `NEXT_ID`: cursor for the next query, the highest event ID sent so far
+//! - `EVENT`: eight fields per event, see [`DeviceEvent`]
+//!
+//! Frames drop empty fields, so absent values (no `SINCE`, no matricula or
+//! display message) are sent as `-` to keep every field in its position.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::commands::events::{
+//!     DeviceEvent, EventAssembler, EventChunk, EventCursor, GetEvents,
+//! };
+//! use turnkey_core::{AccessDirection, DeviceId, HenryTimestamp, ReaderType};
+//!
+//! let device_id = DeviceId::new(15).unwrap();
+//! let query = GetEvents::new(EventCursor::after(40), 100).to_message(device_id).unwrap();
+//! assert!(GetEvents::is_query(&query));
+//!
+//! // Device side: answer with the stored events
+//! let event = DeviceEvent {
+//!     id: 41,
+//!     card_number: "00000000000012345678".to_string(),
+//!     timestamp: HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+//!     direction: AccessDirection::Entry,
+//!     reader_type: ReaderType::Rfid,
+//!     granted: true,
+//!     matricula: Some("EMP001".to_string()),
+//!     display_message: Some("Acesso liberado".to_string()),
+//! };
+//! let chunks = EventChunk::split(40, vec![event], 50, false);
+//!
+//! // Server side: put the answer back together
+//! let mut assembler = EventAssembler::new();
+//! for chunk in &chunks {
+//!     let message = chunk.to_message(device_id).unwrap();
+//!     assembler.push(EventChunk::parse(&message).unwrap()).unwrap();
+//! }
+//! let page = assembler.finish().unwrap();
+//! assert_eq!(page.events.len(), 1);
+//! assert_eq!(page.next_cursor.after_id, 41);
+//! assert!(!page.more);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use serde::{Deserialize, Serialize};
+use turnkey_core::{AccessDirection, DeviceId, Error, HenryTimestamp, ReaderType, Result};
+
+/// Marker of a query in the first field
+const QUERY_MARKER: &str = "Q";
+
+/// Marker of an answer chunk in the first field
+const CHUNK_MARKER: &str = "D";
+
+/// Placeholder for an absent value, since frames drop empty fields
+const ABSENT: &str = "-";
+
+/// Number of fields of a query
+const QUERY_FIELD_COUNT: usize = 4;
+
+/// Number of header fields of a chunk, marker included
+const CHUNK_HEADER_FIELD_COUNT: usize = 5;
+
+/// Number of fields per event in a chunk
+const EVENT_FIELD_COUNT: usize = 8;
+
+/// Default number of events per chunk
+///
+/// Keeps a chunk of full-length events well under the frame size limit.
+pub const DEFAULT_EVENTS_PER_CHUNK: usize = 50;
+
+/// Maximum number of events a query may ask for
+pub const MAX_EVENTS_PER_QUERY: u32 = 10_000;
+
+/// Position in a device's event log.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventCursor {
+    /// Highest device event ID already retrieved (0 = none)
+    pub after_id: i64,
+
+    /// Skip events older than this
+    pub since: Option<HenryTimestamp>,
+}
+
+impl EventCursor {
+    /// Cursor at the start of the log.
+    pub fn start() -> Self {
+        Self::default()
+    }
+
+    /// Cursor after a device event ID.
+    pub fn after(after_id: i64) -> Self {
+        Self {
+            after_id,
+            since: None,
+        }
+    }
+
+    /// Skip events older than `since`.
+    pub fn since(mut self, since: HenryTimestamp) -> Self {
+        self.since = Some(since);
+        self
+    }
+}
+
+impl PartialEq for EventCursor {
+    fn eq(&self, other: &Self) -> bool {
+        self.after_id == other.after_id
+            && self.since.as_ref().map(HenryTimestamp::format)
+                == other.since.as_ref().map(HenryTimestamp::format)
+    }
+}
+
+impl Eq for EventCursor {}
+
+/// Query for the events of a device after a cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetEvents {
+    /// Where to start
+    pub cursor: EventCursor,
+
+    /// Maximum number of events in the answer (1 to [`MAX_EVENTS_PER_QUERY`])
+    pub limit: u32,
+}
+
+impl GetEvents {
+    /// Create a query, clamping the limit to `1..=MAX_EVENTS_PER_QUERY`.
+    pub fn new(cursor: EventCursor, limit: u32) -> Self {
+        Self {
+            cursor,
+            limit: limit.clamp(1, MAX_EVENTS_PER_QUERY),
+        }
+    }
+
+    /// Check whether a message is an event query.
+    pub fn is_query(message: &Message) -> bool {
+        message.command == CommandCode::ReceiveLogs && message.field(0) == Some(QUERY_MARKER)
+    }
+
+    /// Build the `ER` query the server sends to a device.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        let since = self
+            .cursor
+            .since
+            .as_ref()
+            .map(HenryTimestamp::format)
+            .unwrap_or_else(|| ABSENT.to_string());
+        let fields = [
+            QUERY_MARKER.to_string(),
+            self.cursor.after_id.to_string(),
+            since,
+            self.limit.to_string(),
+        ]
+        .into_iter()
+        .map(FieldData::new)
+        .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::ReceiveLogs)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse an event query from an `ER` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the message is not a complete query, and
+    /// `Error::InvalidFieldFormat` if the cursor or limit cannot be decoded.
+    pub fn parse(message: &Message) -> Result<Self> {
+        expect_events_command(message)?;
+        if !Self::is_query(message) || message.field_count() < QUERY_FIELD_COUNT {
+            return Err(Error::MissingField(format!(
+                "Event query requires {} fields starting with '{}'",
+                QUERY_FIELD_COUNT, QUERY_MARKER
+            )));
+        }
+
+        let after_id = parse_number(message.required_field(1, "after id")?, "after id")?;
+        let since = optional_field(message, 2)
+            .map(HenryTimestamp::parse)
+            .transpose()?;
+        let limit = parse_number(message.required_field(3, "limit")?, "limit")?;
+
+        Ok(Self::new(EventCursor { after_id, since }, limit))
+    }
+}
+
+/// Access event stored on a device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceEvent {
+    /// Event ID in the device's local log, increasing
+    pub id: i64,
+
+    /// Card number presented
+    pub card_number: String,
+
+    /// When the event happened, by the device clock
+    pub timestamp: HenryTimestamp,
+
+    /// Direction of the passage
+    pub direction: AccessDirection,
+
+    /// Reader that captured the credential
+    pub reader_type: ReaderType,
+
+    /// Whether access was granted
+    pub granted: bool,
+
+    /// User identified by the device, if any
+    pub matricula: Option<String>,
+
+    /// Message shown on the display
+    pub display_message: Option<String>,
+}
+
+impl PartialEq for DeviceEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.card_number == other.card_number
+            && self.timestamp.format() == other.timestamp.format()
+            && self.direction == other.direction
+            && self.reader_type == other.reader_type
+            && self.granted == other.granted
+            && self.matricula == other.matricula
+            && self.display_message == other.display_message
+    }
+}
+
+impl Eq for DeviceEvent {}
+
+impl DeviceEvent {
+    fn to_fields(&self) -> [String; EVENT_FIELD_COUNT] {
+        [
+            self.id.to_string(),
+            self.card_number.clone(),
+            self.timestamp.format(),
+            self.direction.to_u8().to_string(),
+            self.reader_type.to_u8().to_string(),
+            if self.granted { "1" } else { "0" }.to_string(),
+            self.matricula.clone().unwrap_or_else(|| ABSENT.to_string()),
+            self.display_message
+                .clone()
+                .unwrap_or_else(|| ABSENT.to_string()),
+        ]
+    }
+
+    fn parse_at(message: &Message, index: usize) -> Result<Self> {
+        Ok(Self {
+            id: parse_number(message.required_field(index, "event id")?, "event id")?,
+            card_number: message
+                .required_field(index + 1, "card number")?
+                .to_string(),
+            timestamp: HenryTimestamp::parse(message.required_field(index + 2, "timestamp")?)?,
+            direction: AccessDirection::from_u8(parse_number(
+                message.required_field(index + 3, "direction")?,
+                "direction",
+            )?)?,
+            reader_type: ReaderType::from_u8(parse_number(
+                message.required_field(index + 4, "reader type")?,
+                "reader type",
+            )?)?,
+            granted: parse_flag(message.required_field(index + 5, "granted")?, "granted")?,
+            matricula: optional_field(message, index + 6).map(str::to_string),
+            display_message: optional_field(message, index + 7).map(str::to_string),
+        })
+    }
+}
+
+/// Part of a device's answer to an event query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventChunk {
+    /// Position in the answer, from 0
+    pub index: u32,
+
+    /// Whether this is the last chunk of the answer
+    pub last: bool,
+
+    /// Whether events beyond the query limit are left
+    pub more: bool,
+
+    /// Highest event ID sent so far in the answer
+    pub next_after_id: i64,
+
+    /// Events of this chunk, by increasing ID
+    pub events: Vec<DeviceEvent>,
+}
+
+impl EventChunk {
+    /// Split the answer to a query into chunks of at most `per_chunk` events.
+    ///
+    /// `after_id` is the cursor of the query, kept as `next_after_id` when
+    /// there are no events. An answer always has at least one chunk.
+    pub fn split(
+        after_id: i64,
+        events: Vec<DeviceEvent>,
+        per_chunk: usize,
+        more: bool,
+    ) -> Vec<Self> {
+        let per_chunk = per_chunk.max(1);
+        let mut next_after_id = after_id;
+        let mut chunks = Vec::with_capacity(events.len().div_ceil(per_chunk).max(1));
+        let mut events = events.into_iter().peekable();
+        loop {
+            let chunk: Vec<_> = events.by_ref().take(per_chunk).collect();
+            if let Some(event) = chunk.last() {
+                next_after_id = next_after_id.max(event.id);
+            }
+            let last = events.peek().is_none();
+            chunks.push(Self {
+                index: chunks.len() as u32,
+                last,
+                more: last && more,
+                next_after_id,
+                events: chunk,
+            });
+            if last {
+                return chunks;
+            }
+        }
+    }
+
+    /// Check whether a message is a chunk of an event answer.
+    pub fn is_chunk(message: &Message) -> bool {
+        message.command == CommandCode::ReceiveLogs && message.field(0) == Some(CHUNK_MARKER)
+    }
+
+    /// Build the `ER` message a device sends with this chunk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a card number, matricula or message contains
+    /// protocol delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        let header = [
+            CHUNK_MARKER.to_string(),
+            self.index.to_string(),
+            flag(self.last),
+            flag(self.more),
+            self.next_after_id.to_string(),
+        ];
+        let fields = header
+            .into_iter()
+            .chain(self.events.iter().flat_map(DeviceEvent::to_fields))
+            .map(FieldData::new)
+            .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::ReceiveLogs)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse a chunk from an `ER` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the message is not a chunk or ends in the
+    /// middle of an event, and `Error::InvalidFieldFormat` if a field cannot
+    /// be decoded.
+    pub fn parse(message: &Message) -> Result<Self> {
+        expect_events_command(message)?;
+        let count = message.field_count();
+        if !Self::is_chunk(message)
+            || count < CHUNK_HEADER_FIELD_COUNT
+            || !(count - CHUNK_HEADER_FIELD_COUNT).is_multiple_of(EVENT_FIELD_COUNT)
+        {
+            return Err(Error::MissingField(format!(
+                "Event chunk requires {} header fields and {} fields per event, got {}",
+                CHUNK_HEADER_FIELD_COUNT, EVENT_FIELD_COUNT, count
+            )));
+        }
+
+        let events = (CHUNK_HEADER_FIELD_COUNT..count)
+            .step_by(EVENT_FIELD_COUNT)
+            .map(|index| DeviceEvent::parse_at(message, index))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            index: parse_number(message.required_field(1, "chunk")?, "chunk")?,
+            last: parse_flag(message.required_field(2, "last")?, "last")?,
+            more: parse_flag(message.required_field(3, "more")?, "more")?,
+            next_after_id: parse_number(message.required_field(4, "next id")?, "next id")?,
+            events,
+        })
+    }
+}
+
+/// Events of one complete answer, with the cursor to continue from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventPage {
+    /// Events in device order
+    pub events: Vec<DeviceEvent>,
+
+    /// Cursor for the next query
+    pub next_cursor: EventCursor,
+
+    /// Whether events beyond the query limit are left
+    pub more: bool,
+}
+
+/// Puts the chunks of an answer back together on the server.
+#[derive(Debug, Default)]
+pub struct EventAssembler {
+    events: Vec<DeviceEvent>,
+    expected: u32,
+    finished: Option<(i64, bool)>,
+}
+
+impl EventAssembler {
+    /// Wait for the first chunk of an answer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next chunk.
+    ///
+    /// Returns `true` once the last chunk has been added.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` if the chunk is out of order or
+    /// arrives after the last one.
+    pub fn push(&mut self, chunk: EventChunk) -> Result<bool> {
+        if self.finished.is_some() || chunk.index != self.expected {
+            return Err(Error::InvalidFieldFormat {
+                message: format!(
+                    "Unexpected event chunk {} (expected {})",
+                    chunk.index,
+                    if self.finished.is_some() {
+                        "none after the last".to_string()
+                    } else {
+                        self.expected.to_string()
+                    }
+                ),
+            });
+        }
+        self.expected += 1;
+        self.events.extend(chunk.events);
+        if chunk.last {
+            self.finished = Some((chunk.next_after_id, chunk.more));
+        }
+        Ok(chunk.last)
+    }
+
+    /// Whether the last chunk has been added.
+    pub fn is_complete(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Take the assembled answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingField` if the last chunk has not arrived.
+    pub fn finish(self) -> Result<EventPage> {
+        let (next_after_id, more) = self.finished.ok_or_else(|| {
+            Error::MissingField(format!(
+                "Event answer incomplete after {} chunks",
+                self.expected
+            ))
+        })?;
+        Ok(EventPage {
+            events: self.events,
+            next_cursor: EventCursor::after(next_after_id),
+            more,
+        })
+    }
+}
+
+fn expect_events_command(message: &Message) -> Result<()> {
+    if message.command != CommandCode::ReceiveLogs {
+        return Err(Error::InvalidCommandCode {
+            code: message.command.as_str().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Value of a field that may be absent (empty or `-`)
+fn optional_field(message: &Message, index: usize) -> Option<&str> {
+    message
+        .field(index)
+        .filter(|value| !value.is_empty() && *value != ABSENT)
+}
+
+fn flag(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+fn parse_flag(value: &str, name: &str) -> Result<bool> {
+    match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        other => Err(Error::InvalidFieldFormat {
+            message: format!("Invalid {} '{}', expected 0 or 1", name, other),
+        }),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T> {
+    value.parse().map_err(|_| Error::InvalidFieldFormat {
+        message: format!("Invalid {} '{}'", name, value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::format_message;
+    use crate::parser::MessageParser;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn event(id: i64) -> DeviceEvent {
+        DeviceEvent {
+            id,
+            card_number: format!("{:020}", 12345678 + id),
+            timestamp: HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            direction: AccessDirection::Entry,
+            reader_type: ReaderType::Rfid,
+            granted: id % 2 == 0,
+            matricula: (id % 2 == 0).then(|| format!("EMP{id:03}")),
+            display_message: Some("Acesso liberado".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_query_round_trip() {
+        let since = HenryTimestamp::parse("01/05/2025 00:00:00").unwrap();
+        let query = GetEvents::new(EventCursor::after(120).since(since), 500);
+        let message = query.to_message(device()).unwrap();
+        assert!(GetEvents::is_query(&message));
+        assert!(!EventChunk::is_chunk(&message));
+        assert_eq!(GetEvents::parse(&message).unwrap(), query);
+
+        let message = MessageParser::parse("15+REON+ER]Q]0]-]100").unwrap();
+        let query = GetEvents::parse(&message).unwrap();
+        assert_eq!(query.cursor, EventCursor::start());
+        assert_eq!(query.limit, 100);
+
+        assert_eq!(GetEvents::new(EventCursor::start(), 0).limit, 1);
+        assert_eq!(
+            GetEvents::new(EventCursor::start(), u32::MAX).limit,
+            MAX_EVENTS_PER_QUERY
+        );
+    }
+
+    #[test]
+    fn test_chunk_wire_format() {
+        let message = MessageParser::parse(
+            "15+REON+ER]D]0]1]0]2]1]00000000000012345679]10/05/2025 12:46:06]1]1]0]-]Acesso liberado\
+             ]2]00000000000012345680]10/05/2025 12:46:06]1]1]1]EMP002]Acesso liberado",
+        )
+        .unwrap();
+        let chunk = EventChunk::parse(&message).unwrap();
+        assert_eq!(chunk.events, vec![event(1), event(2)]);
+        assert!(chunk.last);
+        assert!(!chunk.more);
+        assert_eq!(chunk.next_after_id, 2);
+
+        // Absent values survive a trip through a frame
+        let encoded = format_message(&chunk.to_message(device()).unwrap());
+        let decoded = MessageParser::parse(&encoded).unwrap();
+        assert_eq!(EventChunk::parse(&decoded).unwrap(), chunk);
+
+        let query = GetEvents::new(EventCursor::start(), 10);
+        let encoded = format_message(&query.to_message(device()).unwrap());
+        let decoded = MessageParser::parse(&encoded).unwrap();
+        assert_eq!(GetEvents::parse(&decoded).unwrap(), query);
+    }
+
+    #[test]
+    fn test_split_and_assemble() {
+        let events: Vec<_> = (11..=25).map(event).collect();
+        let chunks = EventChunk::split(10, events.clone(), 4, true);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[..3].iter().all(|chunk| !chunk.last && !chunk.more));
+        assert!(chunks[3].last && chunks[3].more);
+
+        let mut assembler = EventAssembler::new();
+        for chunk in chunks {
+            let message = chunk.to_message(device()).unwrap();
+            assembler
+                .push(EventChunk::parse(&message).unwrap())
+                .unwrap();
+        }
+        assert!(assembler.is_complete());
+        let page = assembler.finish().unwrap();
+        assert_eq!(page.events, events);
+        assert_eq!(page.next_cursor, EventCursor::after(25));
+        assert!(page.more);
+    }
+
+    #[test]
+    fn test_empty_answer_keeps_cursor() {
+        let chunks = EventChunk::split(42, Vec::new(), DEFAULT_EVENTS_PER_CHUNK, false);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].last);
+
+        let mut assembler = EventAssembler::new();
+        assert!(assembler.push(chunks[0].clone()).unwrap());
+        let page = assembler.finish().unwrap();
+        assert!(page.events.is_empty());
+        assert_eq!(page.next_cursor.after_id, 42);
+    }
+
+    #[test]
+    fn test_assembler_rejects_out_of_order_chunks() {
+        let chunks = EventChunk::split(0, (1..=4).map(event).collect(), 2, false);
+
+        let mut assembler = EventAssembler::new();
+        assert!(assembler.push(chunks[1].clone()).is_err());
+        assert!(!assembler.push(chunks[0].clone()).unwrap());
+        assert!(matches!(
+            EventAssembler::new().finish(),
+            Err(Error::MissingField(_))
+        ));
+        assert!(assembler.push(chunks[1].clone()).unwrap());
+        assert!(assembler.push(chunks[1].clone()).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_messages() {
+        let message = MessageParser::parse("15+REON+ER]D]0]1]0]2]1]123").unwrap();
+        assert!(matches!(
+            EventChunk::parse(&message),
+            Err(Error::MissingField(_))
+        ));
+
+        let message = MessageParser::parse("15+REON+ER]Q]abc]-]100").unwrap();
+        assert!(matches!(
+            GetEvents::parse(&message),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+
+        let message = MessageParser::parse("15+REON+ER]D]0]1]0]0").unwrap();
+        assert!(GetEvents::parse(&message).is_err());
+
+        let message = MessageParser::parse("15+REON+RQ").unwrap();
+        assert!(matches!(
+            EventChunk::parse(&message),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+    }
+}
@@ -4,12 +4,26 @@
 //! for the Henry access control protocol.
 
 pub mod access;
+pub mod alarm;
 pub mod command_code;
+pub mod diagnostics;
+pub mod events;
+pub mod operator_override;
+pub mod self_test;
+pub mod status;
+pub mod sync;
 pub mod turnstile;
 
 pub use access::AccessRequest;
+pub use alarm::{AlarmKind, AlarmReport};
 pub use command_code::CommandCode;
-pub use turnstile::{TurnstileState, TurnstileStatus, TurnstileStatusBuilder};
+pub use diagnostics::{DiagnosticsReport, PeripheralDiagnostic, SignalReport};
+pub use events::{DeviceEvent, EventAssembler, EventChunk, EventCursor, EventPage, GetEvents};
+pub use operator_override::OverrideGrant;
+pub use self_test::{HealthCheck, SelfTestReport};
+pub use status::{DeviceStatusReport, PassageStats};
+pub use sync::{CardSync, SyncAck, SyncOperation, SyncStatus, TemplateRecord, TemplateSync};
+pub use turnstile::{NsrGap, NsrTracker, TurnstileState, TurnstileStatus, TurnstileStatusBuilder};
 
 // Re-export types from turnkey-core for convenience
 pub use turnkey_core::{AccessDirection as Direction, ReaderType};
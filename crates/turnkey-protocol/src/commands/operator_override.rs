@@ -0,0 +1,250 @@
+//! Operator override of a denied access (command code 00+40).
+//!
+//! When a card is denied, a guard standing at the turnstile may decide to
+//! let the person through anyway. The server sends an `OverrideGrant` to
+//! the device still showing the denial; the device releases the turnstile
+//! for the same credential, and the server links the grant it logs to the
+//! original denial together with who overrode it and why.
+//!
+//! This command is a Turnkey extension and not part of the Henry
+//! specification. Real Henry equipment does not know it; for those devices
+//! the operator must use the manual release (`00+4`) instead.
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+00+40]<CARD_NUMBER>]<OPERATOR>]<REASON>]<SECONDS>]
+//! ```
+//!
+//! Where:
+//! - `CARD_NUMBER`: credential that was denied
+//! - `OPERATOR`: identifier of the guard granting the override
+//! - `REASON`: free text justification, kept in the audit log
+//! - `SECONDS`: time the turnstile stays released waiting for the rotation
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::commands::operator_override::OverrideGrant;
+//! use turnkey_core::DeviceId;
+//!
+//! let grant = OverrideGrant::new("12345678", "guarda-07", "Cartao esquecido em casa").unwrap();
+//! let message = grant.to_message(DeviceId::new(15).unwrap()).unwrap();
+//!
+//! assert_eq!(OverrideGrant::parse(&message).unwrap(), grant);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use serde::{Deserialize, Serialize};
+use turnkey_core::{DeviceId, Error, Result, Timeout};
+
+/// Number of fields in an override grant
+const OVERRIDE_FIELD_COUNT: usize = 4;
+
+/// Longest accepted operator identifier, in characters
+pub const MAX_OPERATOR_ID_LENGTH: usize = 20;
+
+/// Longest accepted override reason, in characters
+pub const MAX_REASON_LENGTH: usize = 100;
+
+/// Release time used when none is given (seconds)
+pub const DEFAULT_OVERRIDE_TIMEOUT_SECONDS: u8 = 10;
+
+/// Operator decision to grant a denied access.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverrideGrant {
+    /// Credential that was denied
+    pub card_number: String,
+
+    /// Guard granting the override
+    pub operator_id: String,
+
+    /// Justification recorded with the override
+    pub reason: String,
+
+    /// Time the turnstile waits for the rotation
+    #[serde(rename = "timeout_seconds")]
+    pub timeout: Timeout,
+}
+
+impl OverrideGrant {
+    /// Create an override with the default release time.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` if a value is empty or too long.
+    pub fn new(
+        card_number: impl Into<String>,
+        operator_id: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Result<Self> {
+        let grant = Self {
+            card_number: card_number.into().trim().to_string(),
+            operator_id: operator_id.into().trim().to_string(),
+            reason: reason.into().trim().to_string(),
+            timeout: Timeout::from_secs(DEFAULT_OVERRIDE_TIMEOUT_SECONDS),
+        };
+        grant.validate()?;
+        Ok(grant)
+    }
+
+    /// Use a different release time.
+    pub fn with_timeout(mut self, timeout: Timeout) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use a different release time, in seconds.
+    pub fn with_timeout_seconds(self, seconds: u8) -> Self {
+        self.with_timeout(Timeout::from_secs(seconds))
+    }
+
+    /// Check that every value can be sent and audited.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` describing the first bad value.
+    pub fn validate(&self) -> Result<()> {
+        let checks = [
+            ("card number", &self.card_number, usize::MAX),
+            ("operator", &self.operator_id, MAX_OPERATOR_ID_LENGTH),
+            ("reason", &self.reason, MAX_REASON_LENGTH),
+        ];
+        for (name, value, max) in checks {
+            if value.is_empty() {
+                return Err(Error::InvalidFieldFormat {
+                    message: format!("Override {} is required", name),
+                });
+            }
+            if value.chars().count() > max {
+                return Err(Error::InvalidFieldFormat {
+                    message: format!("Override {} exceeds {} characters", name, max),
+                });
+            }
+        }
+        if self.timeout.is_permanent() {
+            return Err(Error::InvalidFieldFormat {
+                message: "Override release time must be at least one second".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Encode the override as the fields of a `00+40` message.
+    pub fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.card_number.clone(),
+            self.operator_id.clone(),
+            self.reason.clone(),
+            self.timeout.to_string(),
+        ]
+    }
+
+    /// Build the `00+40` message the server sends to the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value is invalid or contains protocol
+    /// delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        self.validate()?;
+        let fields = self
+            .to_fields()
+            .into_iter()
+            .map(FieldData::new)
+            .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::OverrideGrant)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse an override from a `00+40` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if fields are missing, and
+    /// `Error::InvalidFieldFormat` if a value is invalid.
+    pub fn parse(message: &Message) -> Result<Self> {
+        if message.command != CommandCode::OverrideGrant {
+            return Err(Error::InvalidCommandCode {
+                code: message.command.as_str().to_string(),
+            });
+        }
+        if message.field_count() < OVERRIDE_FIELD_COUNT {
+            return Err(Error::MissingField(format!(
+                "Override grant requires {} fields, got {}",
+                OVERRIDE_FIELD_COUNT,
+                message.field_count()
+            )));
+        }
+
+        let seconds = message.required_field(3, "release time")?;
+        let grant = Self {
+            card_number: message.required_field(0, "card number")?.to_string(),
+            operator_id: message.required_field(1, "operator")?.to_string(),
+            reason: message.required_field(2, "reason")?.to_string(),
+            timeout: Timeout::parse_field(seconds, "override release time")?,
+        };
+        grant.validate()?;
+        Ok(grant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MessageParser;
+
+    fn grant() -> OverrideGrant {
+        OverrideGrant::new("12345678", "guarda-07", "Visitante autorizado")
+            .unwrap()
+            .with_timeout_seconds(8)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let message = grant().to_message(DeviceId::new(15).unwrap()).unwrap();
+        assert_eq!(message.command, CommandCode::OverrideGrant);
+        assert_eq!(OverrideGrant::parse(&message).unwrap(), grant());
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let message =
+            MessageParser::parse("15+REON+00+40]12345678]guarda-07]Visitante autorizado]8")
+                .unwrap();
+        assert_eq!(OverrideGrant::parse(&message).unwrap(), grant());
+    }
+
+    #[test]
+    fn test_new_rejects_missing_or_long_values() {
+        assert!(OverrideGrant::new("12345678", " ", "Motivo").is_err());
+        assert!(OverrideGrant::new("12345678", "guarda-07", "").is_err());
+        assert!(OverrideGrant::new("", "guarda-07", "Motivo").is_err());
+        assert!(OverrideGrant::new("12345678", "g".repeat(21), "Motivo").is_err());
+        assert!(OverrideGrant::new("12345678", "guarda-07", "m".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_messages() {
+        for raw in [
+            "15+REON+00+40]12345678]guarda-07]Motivo",
+            "15+REON+00+40]12345678]guarda-07]Motivo]0",
+            "15+REON+00+40]12345678]guarda-07]Motivo]soon",
+            "15+REON+00+40]12345678]]Motivo]5",
+        ] {
+            let message = MessageParser::parse(raw).unwrap();
+            assert!(OverrideGrant::parse(&message).is_err(), "{raw} should fail");
+        }
+
+        let message = MessageParser::parse("15+REON+00+4]5]Liberado").unwrap();
+        assert!(matches!(
+            OverrideGrant::parse(&message),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+    }
+}
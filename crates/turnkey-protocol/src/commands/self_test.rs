@@ -0,0 +1,305 @@
+//! Device self-test (command code RT, Turnkey extension).
+//!
+//! Real equipment resets itself when its firmware hangs. To see a hang
+//! coming, the server sends an empty `RT` message; the device runs a health
+//! sweep of its peripherals, storage and access flow, and answers with an
+//! `RT` message carrying one result per check.
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+RT                                         (request, server to device)
+//! <ID>+REON+RT]<CHECK>]<RESULT>]<DETAIL>]...           (report, one triple per check)
+//! ```
+//!
+//! Where:
+//! - `CHECK`: name of the component checked (e.g. `rfid`, `database`)
+//! - `RESULT`: `1` if the check passed, `0` if it failed
+//! - `DETAIL`: short description of what was found, `-` when there is none
+//!   (frames drop empty fields)
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::commands::self_test::{HealthCheck, SelfTestReport};
+//! use turnkey_core::DeviceId;
+//!
+//! let report = SelfTestReport::new(vec![
+//!     HealthCheck::pass("rfid", "Mock RFID Reader"),
+//!     HealthCheck::fail("state_machine", "Validating for 95s"),
+//! ]);
+//! assert!(!report.passed());
+//!
+//! let message = report.to_message(DeviceId::new(15).unwrap()).unwrap();
+//! assert_eq!(SelfTestReport::parse(&message).unwrap(), report);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use serde::{Deserialize, Serialize};
+use turnkey_core::{DeviceId, Error, Result};
+
+/// Number of fields per check in a report
+const CHECK_FIELD_COUNT: usize = 3;
+
+/// Detail sent for a check without one, since frames drop empty fields
+const NO_DETAIL: &str = "-";
+
+/// Outcome of one check of a self-test.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    /// Component checked
+    pub name: String,
+
+    /// Whether the component is healthy
+    pub passed: bool,
+
+    /// What was found, for the operator
+    pub detail: String,
+}
+
+impl HealthCheck {
+    /// A passed check.
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    /// A failed check.
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Results of a device self-test, in the order the checks ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    /// One entry per check
+    pub checks: Vec<HealthCheck>,
+}
+
+impl SelfTestReport {
+    /// Create a report from the results of its checks.
+    pub fn new(checks: Vec<HealthCheck>) -> Self {
+        Self { checks }
+    }
+
+    /// Add the result of another check.
+    pub fn push(&mut self, check: HealthCheck) {
+        self.checks.push(check);
+    }
+
+    /// Whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &HealthCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    /// Result of a check by name.
+    pub fn check(&self, name: &str) -> Option<&HealthCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    /// Build the self-test request the server sends to a device.
+    pub fn request(device_id: DeviceId) -> Result<Message> {
+        MessageBuilder::new(device_id, CommandCode::SelfTest).build()
+    }
+
+    /// Check whether a message is a self-test request rather than a report.
+    pub fn is_request(message: &Message) -> bool {
+        message.command == CommandCode::SelfTest && message.field_count() == 0
+    }
+
+    /// Check whether a message carries a self-test report.
+    pub fn is_report(message: &Message) -> bool {
+        message.command == CommandCode::SelfTest && message.field_count() > 0
+    }
+
+    /// Encode the report as the fields of an `RT` message.
+    pub fn to_fields(&self) -> Vec<String> {
+        self.checks
+            .iter()
+            .flat_map(|check| {
+                [
+                    check.name.clone(),
+                    if check.passed { "1" } else { "0" }.to_string(),
+                    if check.detail.is_empty() {
+                        NO_DETAIL.to_string()
+                    } else {
+                        check.detail.clone()
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// Build the `RT` message a device sends with its results.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the report has no checks, or if a name or detail
+    /// contains protocol delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        if self.checks.is_empty() {
+            return Err(Error::MissingField(
+                "Self-test report requires at least one check".to_string(),
+            ));
+        }
+        let fields = self
+            .to_fields()
+            .into_iter()
+            .map(FieldData::new)
+            .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::SelfTest)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse a self-test report from an `RT` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the message is a request or ends in the
+    /// middle of a check, and `Error::InvalidFieldFormat` if a result is
+    /// neither `0` nor `1`.
+    pub fn parse(message: &Message) -> Result<Self> {
+        if message.command != CommandCode::SelfTest {
+            return Err(Error::InvalidCommandCode {
+                code: message.command.as_str().to_string(),
+            });
+        }
+        let count = message.field_count();
+        if count == 0 || !count.is_multiple_of(CHECK_FIELD_COUNT) {
+            return Err(Error::MissingField(format!(
+                "Self-test report requires {} fields per check, got {}",
+                CHECK_FIELD_COUNT, count
+            )));
+        }
+
+        let checks = (0..count)
+            .step_by(CHECK_FIELD_COUNT)
+            .map(|index| {
+                let name = message.required_field(index, "check name")?;
+                let passed = match message.required_field(index + 1, "check result")? {
+                    "1" => true,
+                    "0" => false,
+                    other => {
+                        return Err(Error::InvalidFieldFormat {
+                            message: format!("Invalid result '{}' for check '{}'", other, name),
+                        });
+                    }
+                };
+                Ok(HealthCheck {
+                    name: name.to_string(),
+                    passed,
+                    detail: match message.field(index + 2).unwrap_or_default() {
+                        NO_DETAIL => String::new(),
+                        detail => detail.to_string(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { checks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::format_message;
+    use crate::parser::MessageParser;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn report() -> SelfTestReport {
+        SelfTestReport::new(vec![
+            HealthCheck::pass("rfid", "Mock RFID Reader"),
+            HealthCheck::pass("database", ""),
+            HealthCheck::fail("state_machine", "Validating for 95s"),
+        ])
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let message = report().to_message(device()).unwrap();
+        assert!(SelfTestReport::is_report(&message));
+        assert!(!SelfTestReport::is_request(&message));
+        assert_eq!(SelfTestReport::parse(&message).unwrap(), report());
+    }
+
+    #[test]
+    fn test_report_wire_format() {
+        let message = MessageParser::parse(
+            "15+REON+RT]rfid]1]Mock RFID Reader]database]1]-]state_machine]0]Validating for 95s",
+        )
+        .unwrap();
+        assert_eq!(SelfTestReport::parse(&message).unwrap(), report());
+
+        // An empty detail keeps its place through a frame
+        let encoded = format_message(&report().to_message(device()).unwrap());
+        let decoded = MessageParser::parse(&encoded).unwrap();
+        assert_eq!(SelfTestReport::parse(&decoded).unwrap(), report());
+    }
+
+    #[test]
+    fn test_passed_and_failures() {
+        let report = report();
+        assert!(!report.passed());
+        let failed: Vec<_> = report.failures().map(|check| check.name.as_str()).collect();
+        assert_eq!(failed, vec!["state_machine"]);
+        assert!(report.check("rfid").unwrap().passed);
+        assert!(report.check("keypad").is_none());
+
+        assert!(SelfTestReport::new(vec![HealthCheck::pass("rfid", "")]).passed());
+    }
+
+    #[test]
+    fn test_request_is_not_a_report() {
+        let request = SelfTestReport::request(device()).unwrap();
+        assert!(SelfTestReport::is_request(&request));
+        assert!(!SelfTestReport::is_report(&request));
+        assert!(matches!(
+            SelfTestReport::parse(&request),
+            Err(Error::MissingField(_))
+        ));
+        assert!(SelfTestReport::default().to_message(device()).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_fields() {
+        let message = MessageParser::parse("15+REON+RT]rfid]1]ok]database]0").unwrap();
+        assert!(matches!(
+            SelfTestReport::parse(&message),
+            Err(Error::MissingField(_))
+        ));
+
+        let message = MessageParser::parse("15+REON+RT]rfid]yes]ok").unwrap();
+        assert!(matches!(
+            SelfTestReport::parse(&message),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+
+        let message = MessageParser::parse("15+REON+RQ]A]120]4]118]2]3").unwrap();
+        assert!(matches!(
+            SelfTestReport::parse(&message),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+    }
+}
@@ -0,0 +1,377 @@
+//! Device status reports (command code RQ).
+//!
+//! The server queries a device's state with an empty `RQ` message; the
+//! device answers with an `RQ` message carrying its operating mode,
+//! counters and the number of events it has not delivered yet. A device
+//! may also send the report unprompted after reconnecting, so a server that
+//! restarted can rebuild its view of the device without operator help.
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+RQ                                   (query, server to device)
+//! <ID>+REON+RQ]<MODE>]<GRANTED>]<DENIED>]<ROTATIONS>]<TIMEOUTS>]<PENDING>]
+//! <ID>+REON+RQ]<MODE>]...]<PENDING>]<ENTRIES>]<EXITS>]<ERRORS>]<LAST_ENTRY>]<LAST_EXIT>]<LAST_ERROR>]
+//! ```
+//!
+//! Where:
+//! - `MODE`: validation mode code (F=Offline, O=Online, A=Automatic, S=Semi-automatic)
+//! - `GRANTED` / `DENIED`: access decisions since the device started
+//! - `ROTATIONS` / `TIMEOUTS`: completed and timed out rotations
+//! - `PENDING`: events queued on the device waiting to be sent
+//! - `ENTRIES` / `EXITS`: arm rotations in each direction
+//! - `ERRORS`: failed peripheral reads and device errors
+//! - `LAST_*`: time of the latest entry, exit and error (`dd/mm/yyyy hh:mm:ss`),
+//!   `-` if there was none
+//!
+//! The passage fields are optional: devices that only send the first six
+//! fields, such as older emulators, still parse, with no [`PassageStats`].
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::commands::status::DeviceStatusReport;
+//! use turnkey_core::{DeviceId, ValidationMode};
+//!
+//! let report = DeviceStatusReport {
+//!     validation_mode: ValidationMode::Automatic,
+//!     granted: 120,
+//!     denied: 4,
+//!     rotations: 118,
+//!     timeouts: 2,
+//!     pending_events: 0,
+//!     passages: None,
+//! };
+//!
+//! let message = report.to_message(DeviceId::new(15).unwrap()).unwrap();
+//! assert_eq!(DeviceStatusReport::parse(&message).unwrap(), report);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use turnkey_core::{DeviceId, Error, Result, ValidationMode};
+
+/// Number of fields in a status report
+const REPORT_FIELD_COUNT: usize = 6;
+
+/// Number of passage fields following the report fields
+const PASSAGE_FIELD_COUNT: usize = 6;
+
+/// Placeholder for an event that never happened
+const NEVER: &str = "-";
+
+/// Henry timestamp format (`dd/mm/yyyy hh:mm:ss`)
+const TIMESTAMP_FORMAT: &str = "%d/%m/%Y %H:%M:%S";
+
+/// Passage and error statistics of a turnstile, in device local time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassageStats {
+    /// Arm rotations in the entry direction
+    pub entries: u64,
+
+    /// Arm rotations in the exit direction
+    pub exits: u64,
+
+    /// Failed peripheral reads and device errors
+    pub errors: u64,
+
+    /// Latest entry
+    pub last_entry: Option<NaiveDateTime>,
+
+    /// Latest exit
+    pub last_exit: Option<NaiveDateTime>,
+
+    /// Latest error
+    pub last_error: Option<NaiveDateTime>,
+}
+
+impl PassageStats {
+    fn to_fields(self) -> Vec<String> {
+        let time = |at: Option<NaiveDateTime>| {
+            at.map_or_else(
+                || NEVER.to_string(),
+                |at| at.format(TIMESTAMP_FORMAT).to_string(),
+            )
+        };
+        vec![
+            self.entries.to_string(),
+            self.exits.to_string(),
+            self.errors.to_string(),
+            time(self.last_entry),
+            time(self.last_exit),
+            time(self.last_error),
+        ]
+    }
+
+    fn parse(message: &Message, offset: usize) -> Result<Self> {
+        Ok(Self {
+            entries: parse_counter(message, offset, "entries")?,
+            exits: parse_counter(message, offset + 1, "exits")?,
+            errors: parse_counter(message, offset + 2, "errors")?,
+            last_entry: parse_time(message, offset + 3, "last entry")?,
+            last_exit: parse_time(message, offset + 4, "last exit")?,
+            last_error: parse_time(message, offset + 5, "last error")?,
+        })
+    }
+}
+
+/// State reported by a device in answer to a status query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceStatusReport {
+    /// Validation mode the device is operating in
+    pub validation_mode: ValidationMode,
+
+    /// Accesses granted since the device started
+    pub granted: u64,
+
+    /// Accesses denied since the device started
+    pub denied: u64,
+
+    /// Rotations completed since the device started
+    pub rotations: u64,
+
+    /// Rotations that timed out since the device started
+    pub timeouts: u64,
+
+    /// Events queued on the device and not yet delivered
+    pub pending_events: u32,
+
+    /// Passage statistics, if the device reports them
+    #[serde(default)]
+    pub passages: Option<PassageStats>,
+}
+
+impl DeviceStatusReport {
+    /// Build the status query the server sends to a device.
+    pub fn query(device_id: DeviceId) -> Result<Message> {
+        MessageBuilder::new(device_id, CommandCode::QueryStatus).build()
+    }
+
+    /// Check whether a message is a status query rather than a report.
+    pub fn is_query(message: &Message) -> bool {
+        message.command == CommandCode::QueryStatus && message.field_count() == 0
+    }
+
+    /// Check whether a message carries a status report.
+    pub fn is_report(message: &Message) -> bool {
+        message.command == CommandCode::QueryStatus && message.field_count() > 0
+    }
+
+    /// Encode the report as the fields of an `RQ` message.
+    pub fn to_fields(&self) -> Vec<String> {
+        let mut fields = vec![
+            self.validation_mode.to_char().to_string(),
+            self.granted.to_string(),
+            self.denied.to_string(),
+            self.rotations.to_string(),
+            self.timeouts.to_string(),
+            self.pending_events.to_string(),
+        ];
+        if let Some(passages) = self.passages {
+            fields.extend(passages.to_fields());
+        }
+        fields
+    }
+
+    /// Build the `RQ` message a device sends to report its state.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        let fields = self
+            .to_fields()
+            .into_iter()
+            .map(FieldData::new)
+            .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, CommandCode::QueryStatus)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse a status report from an `RQ` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the message is a query or lacks fields
+    /// (including a partial set of passage fields), and
+    /// `Error::InvalidFieldFormat` if a field cannot be decoded.
+    pub fn parse(message: &Message) -> Result<Self> {
+        if message.command != CommandCode::QueryStatus {
+            return Err(Error::InvalidCommandCode {
+                code: message.command.as_str().to_string(),
+            });
+        }
+        if message.field_count() < REPORT_FIELD_COUNT {
+            return Err(Error::MissingField(format!(
+                "Status report requires {} fields, got {}",
+                REPORT_FIELD_COUNT,
+                message.field_count()
+            )));
+        }
+
+        let mode = message.required_field(0, "validation mode")?;
+        let mut chars = mode.chars();
+        let validation_mode = match (chars.next(), chars.next()) {
+            (Some(c), None) => ValidationMode::from_char(c).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| Error::InvalidFieldFormat {
+            message: format!("Invalid validation mode '{}'", mode),
+        })?;
+
+        Ok(Self {
+            validation_mode,
+            granted: parse_counter(message, 1, "granted")?,
+            denied: parse_counter(message, 2, "denied")?,
+            rotations: parse_counter(message, 3, "rotations")?,
+            timeouts: parse_counter(message, 4, "timeouts")?,
+            pending_events: parse_counter(message, 5, "pending events")?,
+            passages: match message.field_count() {
+                REPORT_FIELD_COUNT => None,
+                n if n >= REPORT_FIELD_COUNT + PASSAGE_FIELD_COUNT => {
+                    Some(PassageStats::parse(message, REPORT_FIELD_COUNT)?)
+                }
+                n => {
+                    return Err(Error::MissingField(format!(
+                        "Passage statistics require {} fields, got {}",
+                        PASSAGE_FIELD_COUNT,
+                        n - REPORT_FIELD_COUNT
+                    )));
+                }
+            },
+        })
+    }
+}
+
+fn parse_counter<T: std::str::FromStr>(message: &Message, index: usize, name: &str) -> Result<T> {
+    let field = message.required_field(index, name)?;
+    field.parse().map_err(|_| Error::InvalidFieldFormat {
+        message: format!("Invalid {} counter '{}'", name, field),
+    })
+}
+
+fn parse_time(message: &Message, index: usize, name: &str) -> Result<Option<NaiveDateTime>> {
+    let field = message.required_field(index, name)?;
+    if field == NEVER {
+        return Ok(None);
+    }
+    NaiveDateTime::parse_from_str(field, TIMESTAMP_FORMAT)
+        .map(Some)
+        .map_err(|_| Error::InvalidFieldFormat {
+            message: format!("Invalid {} time '{}'", name, field),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MessageParser;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn report() -> DeviceStatusReport {
+        DeviceStatusReport {
+            validation_mode: ValidationMode::Automatic,
+            granted: 120,
+            denied: 4,
+            rotations: 118,
+            timeouts: 2,
+            pending_events: 3,
+            passages: None,
+        }
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let message = report().to_message(device()).unwrap();
+        assert!(DeviceStatusReport::is_report(&message));
+        assert!(!DeviceStatusReport::is_query(&message));
+        assert_eq!(DeviceStatusReport::parse(&message).unwrap(), report());
+    }
+
+    #[test]
+    fn test_report_wire_format() {
+        let message = MessageParser::parse("15+REON+RQ]A]120]4]118]2]3").unwrap();
+        assert_eq!(DeviceStatusReport::parse(&message).unwrap(), report());
+        assert_eq!(report().to_fields(), vec!["A", "120", "4", "118", "2", "3"]);
+    }
+
+    #[test]
+    fn test_report_with_passage_stats() {
+        let at = |s| NaiveDateTime::parse_from_str(s, TIMESTAMP_FORMAT).unwrap();
+        let report = DeviceStatusReport {
+            passages: Some(PassageStats {
+                entries: 70,
+                exits: 48,
+                errors: 1,
+                last_entry: Some(at("10/05/2025 12:46:06")),
+                last_exit: Some(at("10/05/2025 12:40:00")),
+                last_error: None,
+            }),
+            ..report()
+        };
+
+        let message = report.to_message(device()).unwrap();
+        assert_eq!(
+            crate::builder::format_message(&message),
+            "15+REON+RQ]A]120]4]118]2]3]70]48]1]10/05/2025 12:46:06]10/05/2025 12:40:00]-]"
+        );
+        assert_eq!(DeviceStatusReport::parse(&message).unwrap(), report);
+
+        // Passage fields come as a whole set
+        let partial = MessageParser::parse("15+REON+RQ]A]120]4]118]2]3]70]48").unwrap();
+        assert!(matches!(
+            DeviceStatusReport::parse(&partial),
+            Err(Error::MissingField(_))
+        ));
+        let bad_time =
+            MessageParser::parse("15+REON+RQ]A]120]4]118]2]3]70]48]1]ontem]-]-").unwrap();
+        assert!(matches!(
+            DeviceStatusReport::parse(&bad_time),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_query_is_not_a_report() {
+        let query = DeviceStatusReport::query(device()).unwrap();
+        assert!(DeviceStatusReport::is_query(&query));
+        assert!(!DeviceStatusReport::is_report(&query));
+        assert!(matches!(
+            DeviceStatusReport::parse(&query),
+            Err(Error::MissingField(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_fields() {
+        for raw in [
+            "15+REON+RQ]X]120]4]118]2]3",
+            "15+REON+RQ]AO]120]4]118]2]3",
+            "15+REON+RQ]A]-1]4]118]2]3",
+            "15+REON+RQ]A]120]4]118]2]many",
+        ] {
+            let message = MessageParser::parse(raw).unwrap();
+            assert!(
+                matches!(
+                    DeviceStatusReport::parse(&message),
+                    Err(Error::InvalidFieldFormat { .. })
+                ),
+                "{raw} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_other_commands() {
+        let message = MessageParser::parse("15+REON+000+0]12345678").unwrap();
+        assert!(matches!(
+            DeviceStatusReport::parse(&message),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+    }
+}
@@ -0,0 +1,559 @@
+//! Card and template synchronization (command codes ECAR and ED).
+//!
+//! The server keeps each device's whitelist in step with its database by
+//! sending batches of cards (`ECAR`) and fingerprint templates (`ED`). Every
+//! batch carries one operation; the device applies the entries in order and
+//! answers with the same command, saying how many entries it applied and
+//! which stored entries it had to evict to make room. Devices have finite
+//! memory, so a batch may stop part-way with [`SyncStatus::MemoryFull`].
+//!
+//! # Message Format
+//!
+//! ```text
+//! <ID>+REON+ECAR]00]<QTY>]<OP>]<CARD>]...                      (card batch, server to device)
+//! <ID>+REON+ED]00]<QTY>]<OP>]<MATRICULA>]<TEMPLATE>]...        (template batch, server to device)
+//! <ID>+REON+ECAR]<STATUS>]<APPLIED>]<OP>]<EVICTED>]...         (answer, device to server)
+//! <ID>+REON+ED]<STATUS>]<APPLIED>]<OP>]<EVICTED>]...
+//! ```
+//!
+//! Where:
+//! - `QTY`: number of entries in the batch
+//! - `OP`: `I` insert, `A` update, `E` delete, `L` clear all
+//! - `TEMPLATE`: template data, one field per fingerprint; deletions list
+//!   only the matriculas whose templates are removed
+//! - `STATUS`: `00` when the whole batch was applied, `01` when the device
+//!   ran out of memory
+//! - `APPLIED`: number of entries applied, from the start of the batch
+//! - `EVICTED`: card numbers (`ECAR`) or matriculas (`ED`) removed to make
+//!   room, in eviction order
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_core::DeviceId;
+//! use turnkey_protocol::CommandCode;
+//! use turnkey_protocol::commands::sync::{CardSync, SyncAck, SyncOperation, SyncStatus};
+//!
+//! let device_id = DeviceId::new(15).unwrap();
+//! let batch = CardSync::new(SyncOperation::Insert, vec!["12345678".to_string()]);
+//! let message = batch.to_message(device_id).unwrap();
+//! assert_eq!(CardSync::parse(&message).unwrap(), batch);
+//!
+//! // Device side: the card did not fit
+//! let ack = SyncAck::new(SyncStatus::MemoryFull, 0, SyncOperation::Insert);
+//! let answer = ack.to_message(device_id, CommandCode::SendCards).unwrap();
+//! assert_eq!(SyncAck::parse(&answer).unwrap(), ack);
+//! ```
+
+use crate::builder::MessageBuilder;
+use crate::commands::CommandCode;
+use crate::field::FieldData;
+use crate::message::Message;
+use serde::{Deserialize, Serialize};
+use turnkey_core::{DeviceId, Error, Result};
+
+/// Status sent by the server in a batch
+const BATCH_STATUS: &str = "00";
+
+/// Number of header fields (status, quantity, operation)
+const HEADER_FIELD_COUNT: usize = 3;
+
+/// What a batch does with its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncOperation {
+    /// Store new entries
+    Insert,
+
+    /// Replace stored entries (stored as new if absent)
+    Update,
+
+    /// Remove entries
+    Delete,
+
+    /// Remove every stored entry; the batch has none
+    Clear,
+}
+
+impl SyncOperation {
+    /// Wire code of the operation.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Insert => "I",
+            Self::Update => "A",
+            Self::Delete => "E",
+            Self::Clear => "L",
+        }
+    }
+
+    /// Parse a wire code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` for an unknown code.
+    pub fn parse(code: &str) -> Result<Self> {
+        match code {
+            "I" => Ok(Self::Insert),
+            "A" => Ok(Self::Update),
+            "E" => Ok(Self::Delete),
+            "L" => Ok(Self::Clear),
+            other => Err(Error::InvalidFieldFormat {
+                message: format!("Invalid sync operation '{}', expected I, A, E or L", other),
+            }),
+        }
+    }
+}
+
+/// Outcome of a batch on the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncStatus {
+    /// Every entry was applied
+    Ok,
+
+    /// The device ran out of memory; entries after `applied` were dropped
+    MemoryFull,
+}
+
+impl SyncStatus {
+    /// Wire code of the status.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Ok => "00",
+            Self::MemoryFull => "01",
+        }
+    }
+
+    /// Parse a wire code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFieldFormat` for an unknown code.
+    pub fn parse(code: &str) -> Result<Self> {
+        match code {
+            "00" | "0" => Ok(Self::Ok),
+            "01" | "1" => Ok(Self::MemoryFull),
+            other => Err(Error::InvalidFieldFormat {
+                message: format!("Invalid sync status '{}'", other),
+            }),
+        }
+    }
+}
+
+/// Batch of cards sent to a device (`ECAR`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardSync {
+    /// What to do with the cards
+    pub operation: SyncOperation,
+
+    /// Card numbers, in the order they are applied
+    pub cards: Vec<String>,
+}
+
+impl CardSync {
+    /// Create a batch.
+    pub fn new(operation: SyncOperation, cards: Vec<String>) -> Self {
+        Self { operation, cards }
+    }
+
+    /// Batch removing every card from the device.
+    pub fn clear() -> Self {
+        Self::new(SyncOperation::Clear, Vec::new())
+    }
+
+    /// Build the `ECAR` message the server sends to a device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a card number contains protocol delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        batch_message(
+            device_id,
+            CommandCode::SendCards,
+            self.operation,
+            self.cards.len(),
+            self.cards.iter().cloned(),
+        )
+    }
+
+    /// Parse a card batch from an `ECAR` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the header is incomplete or the number of
+    /// cards does not match the quantity, and `Error::InvalidFieldFormat`
+    /// if the quantity or operation cannot be decoded.
+    pub fn parse(message: &Message) -> Result<Self> {
+        let (operation, quantity) = parse_batch_header(message, CommandCode::SendCards)?;
+        let cards = entries(message);
+        if cards.len() != quantity {
+            return Err(Error::MissingField(format!(
+                "Card batch announces {} cards, got {}",
+                quantity,
+                cards.len()
+            )));
+        }
+        Ok(Self::new(operation, cards))
+    }
+}
+
+/// Fingerprint template of a user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateRecord {
+    /// Owner of the template
+    pub matricula: String,
+
+    /// Template data as read from the sensor, without protocol delimiters
+    pub template: String,
+}
+
+impl TemplateRecord {
+    /// Create a record.
+    pub fn new(matricula: impl Into<String>, template: impl Into<String>) -> Self {
+        Self {
+            matricula: matricula.into(),
+            template: template.into(),
+        }
+    }
+}
+
+/// Batch of fingerprint templates sent to a device (`ED`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateSync {
+    /// What to do with the templates
+    pub operation: SyncOperation,
+
+    /// Templates to insert or update, one record per fingerprint
+    pub templates: Vec<TemplateRecord>,
+
+    /// Users whose templates are deleted
+    pub matriculas: Vec<String>,
+}
+
+impl TemplateSync {
+    /// Batch inserting (or, with `Update`, replacing) templates.
+    ///
+    /// An update replaces all templates of each user named in the batch.
+    pub fn store(operation: SyncOperation, templates: Vec<TemplateRecord>) -> Self {
+        Self {
+            operation,
+            templates,
+            matriculas: Vec::new(),
+        }
+    }
+
+    /// Batch deleting the templates of users.
+    pub fn delete(matriculas: Vec<String>) -> Self {
+        Self {
+            operation: SyncOperation::Delete,
+            templates: Vec::new(),
+            matriculas,
+        }
+    }
+
+    /// Batch removing every template from the device.
+    pub fn clear() -> Self {
+        Self::delete(Vec::new()).with_operation(SyncOperation::Clear)
+    }
+
+    fn with_operation(mut self, operation: SyncOperation) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    /// Number of entries on the wire: templates, or matriculas for a deletion.
+    pub fn len(&self) -> usize {
+        match self.operation {
+            SyncOperation::Insert | SyncOperation::Update => self.templates.len(),
+            SyncOperation::Delete | SyncOperation::Clear => self.matriculas.len(),
+        }
+    }
+
+    /// Whether the batch has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Build the `ED` message the server sends to a device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a matricula or template contains protocol
+    /// delimiters.
+    pub fn to_message(&self, device_id: DeviceId) -> Result<Message> {
+        let fields: Vec<String> = match self.operation {
+            SyncOperation::Insert | SyncOperation::Update => self
+                .templates
+                .iter()
+                .flat_map(|record| [record.matricula.clone(), record.template.clone()])
+                .collect(),
+            SyncOperation::Delete | SyncOperation::Clear => self.matriculas.clone(),
+        };
+        batch_message(
+            device_id,
+            CommandCode::SendBiometrics,
+            self.operation,
+            self.len(),
+            fields,
+        )
+    }
+
+    /// Parse a template batch from an `ED` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the header is incomplete or the entries do
+    /// not match the quantity, and `Error::InvalidFieldFormat` if the
+    /// quantity or operation cannot be decoded.
+    pub fn parse(message: &Message) -> Result<Self> {
+        let (operation, quantity) = parse_batch_header(message, CommandCode::SendBiometrics)?;
+        let fields = entries(message);
+        let batch = match operation {
+            SyncOperation::Insert | SyncOperation::Update => {
+                if !fields.len().is_multiple_of(2) {
+                    return Err(Error::MissingField(
+                        "Template batch ends without the last template".to_string(),
+                    ));
+                }
+                let templates = fields
+                    .chunks_exact(2)
+                    .map(|pair| TemplateRecord::new(pair[0].clone(), pair[1].clone()))
+                    .collect();
+                Self::store(operation, templates)
+            }
+            SyncOperation::Delete | SyncOperation::Clear => {
+                Self::delete(fields).with_operation(operation)
+            }
+        };
+        if batch.len() != quantity {
+            return Err(Error::MissingField(format!(
+                "Template batch announces {} entries, got {}",
+                quantity,
+                batch.len()
+            )));
+        }
+        Ok(batch)
+    }
+}
+
+/// A device's answer to a card or template batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncAck {
+    /// Whether the whole batch was applied
+    pub status: SyncStatus,
+
+    /// Number of entries applied, from the start of the batch
+    pub applied: usize,
+
+    /// Operation of the batch
+    pub operation: SyncOperation,
+
+    /// Card numbers or matriculas removed to make room, in eviction order
+    pub evicted: Vec<String>,
+}
+
+impl SyncAck {
+    /// Create an answer without evictions.
+    pub fn new(status: SyncStatus, applied: usize, operation: SyncOperation) -> Self {
+        Self {
+            status,
+            applied,
+            operation,
+            evicted: Vec::new(),
+        }
+    }
+
+    /// Report entries evicted while applying the batch.
+    pub fn with_evicted(mut self, evicted: Vec<String>) -> Self {
+        self.evicted = evicted;
+        self
+    }
+
+    /// Whether the whole batch was applied.
+    pub fn is_complete(&self) -> bool {
+        self.status == SyncStatus::Ok
+    }
+
+    /// Build the answer a device sends for a batch of `command`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` unless `command` is `ECAR` or
+    /// `ED`, or an error if an evicted entry contains protocol delimiters.
+    pub fn to_message(&self, device_id: DeviceId, command: CommandCode) -> Result<Message> {
+        expect_sync_command(command)?;
+        let fields = [
+            self.status.code().to_string(),
+            self.applied.to_string(),
+            self.operation.code().to_string(),
+        ]
+        .into_iter()
+        .chain(self.evicted.iter().cloned())
+        .map(FieldData::new)
+        .collect::<Result<Vec<_>>>()?;
+        MessageBuilder::new(device_id, command)
+            .fields(fields)
+            .build()
+    }
+
+    /// Parse a device's answer from an `ECAR` or `ED` message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands,
+    /// `Error::MissingField` if the header is incomplete, and
+    /// `Error::InvalidFieldFormat` if a header field cannot be decoded.
+    pub fn parse(message: &Message) -> Result<Self> {
+        expect_sync_command(message.command)?;
+        let status = SyncStatus::parse(message.required_field(0, "status")?)?;
+        let applied = parse_count(message.required_field(1, "applied")?, "applied")?;
+        let operation = SyncOperation::parse(message.required_field(2, "operation")?)?;
+        Ok(Self::new(status, applied, operation).with_evicted(entries(message)))
+    }
+}
+
+fn expect_sync_command(command: CommandCode) -> Result<()> {
+    if !matches!(
+        command,
+        CommandCode::SendCards | CommandCode::SendBiometrics
+    ) {
+        return Err(Error::InvalidCommandCode {
+            code: command.as_str().to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn batch_message(
+    device_id: DeviceId,
+    command: CommandCode,
+    operation: SyncOperation,
+    quantity: usize,
+    entries: impl IntoIterator<Item = String>,
+) -> Result<Message> {
+    let fields = [
+        BATCH_STATUS.to_string(),
+        quantity.to_string(),
+        operation.code().to_string(),
+    ]
+    .into_iter()
+    .chain(entries)
+    .map(FieldData::new)
+    .collect::<Result<Vec<_>>>()?;
+    MessageBuilder::new(device_id, command)
+        .fields(fields)
+        .build()
+}
+
+fn parse_batch_header(message: &Message, command: CommandCode) -> Result<(SyncOperation, usize)> {
+    if message.command != command {
+        return Err(Error::InvalidCommandCode {
+            code: message.command.as_str().to_string(),
+        });
+    }
+    if message.field_count() < HEADER_FIELD_COUNT {
+        return Err(Error::MissingField(format!(
+            "Sync batch requires {} header fields, got {}",
+            HEADER_FIELD_COUNT,
+            message.field_count()
+        )));
+    }
+    let quantity = parse_count(message.required_field(1, "quantity")?, "quantity")?;
+    let operation = SyncOperation::parse(message.required_field(2, "operation")?)?;
+    Ok((operation, quantity))
+}
+
+/// Fields after the header
+fn entries(message: &Message) -> Vec<String> {
+    (HEADER_FIELD_COUNT..message.field_count())
+        .filter_map(|index| message.field(index).map(str::to_string))
+        .collect()
+}
+
+fn parse_count(value: &str, name: &str) -> Result<usize> {
+    value.parse().map_err(|_| Error::InvalidFieldFormat {
+        message: format!("Invalid {} '{}'", name, value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::format_message;
+    use crate::parser::MessageParser;
+    use crate::validation::validate_message;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn cards(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| format!("{:020}", 12345678 + i))
+            .collect()
+    }
+
+    #[test]
+    fn test_card_batch_round_trip_over_the_wire() {
+        let batch = CardSync::new(SyncOperation::Insert, cards(3));
+        let wire = format_message(&batch.to_message(device()).unwrap());
+        assert!(wire.starts_with("15+REON+ECAR]00]3]I]"));
+
+        let message = MessageParser::parse(&wire).unwrap();
+        assert!(validate_message(&message).is_ok());
+        assert_eq!(CardSync::parse(&message).unwrap(), batch);
+    }
+
+    #[test]
+    fn test_card_batch_quantity_must_match() {
+        let mut message = CardSync::new(SyncOperation::Delete, cards(2))
+            .to_message(device())
+            .unwrap();
+        message.fields.pop();
+        assert!(matches!(
+            CardSync::parse(&message),
+            Err(Error::MissingField(_))
+        ));
+
+        let clear = CardSync::clear().to_message(device()).unwrap();
+        assert_eq!(CardSync::parse(&clear).unwrap(), CardSync::clear());
+    }
+
+    #[test]
+    fn test_template_batches_round_trip() {
+        let store = TemplateSync::store(
+            SyncOperation::Update,
+            vec![
+                TemplateRecord::new("1001", "A1B2C3"),
+                TemplateRecord::new("1001", "D4E5F6"),
+            ],
+        );
+        let message = store.to_message(device()).unwrap();
+        assert_eq!(message.field(1), Some("2"));
+        assert_eq!(TemplateSync::parse(&message).unwrap(), store);
+
+        let delete = TemplateSync::delete(vec!["1001".to_string(), "1002".to_string()]);
+        let message = delete.to_message(device()).unwrap();
+        assert_eq!(TemplateSync::parse(&message).unwrap(), delete);
+
+        assert!(CardSync::parse(&message).is_err());
+        assert_eq!(
+            TemplateSync::parse(&TemplateSync::clear().to_message(device()).unwrap()).unwrap(),
+            TemplateSync::clear()
+        );
+    }
+
+    #[test]
+    fn test_ack_round_trip() {
+        let ack =
+            SyncAck::new(SyncStatus::MemoryFull, 2, SyncOperation::Insert).with_evicted(cards(1));
+        let message = ack.to_message(device(), CommandCode::SendCards).unwrap();
+        assert!(validate_message(&message).is_ok());
+
+        let parsed = SyncAck::parse(&message).unwrap();
+        assert_eq!(parsed, ack);
+        assert!(!parsed.is_complete());
+
+        assert!(ack.to_message(device(), CommandCode::SelfTest).is_err());
+    }
+}
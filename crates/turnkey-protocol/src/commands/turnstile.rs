@@ -18,7 +18,7 @@
 //! Turnstile state messages follow this format:
 //!
 //! ```text
-//! <ID>+REON+<COMMAND>]<CARD_NUMBER>]<TIMESTAMP>]<DIRECTION>]<READER_TYPE>]
+//! <ID>+REON+<COMMAND>]<CARD_NUMBER>]<TIMESTAMP>]<DIRECTION>]<READER_TYPE>][<NSR>]
 //! ```
 //!
 //! Where:
@@ -27,6 +27,12 @@
 //! - `TIMESTAMP`: When the state transition occurred (dd/mm/yyyy hh:mm:ss)
 //! - `DIRECTION`: Direction code (0=Undefined, 1=Entry, 2=Exit)
 //! - `READER_TYPE`: Reader type code (0 or 1=RFID, 5=Biometric)
+//! - `NSR`: Optional record number (Numero Sequencial de Registro) of the
+//!   access event, the event ID of its `ER` event; every message of a
+//!   passage carries the same one
+//!
+//! Records are numbered consecutively, so a receiver can spot lost events
+//! with [`NsrTracker`].
 //!
 //! # Examples
 //!
@@ -89,6 +95,17 @@ use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType, Result};
 /// Validating → Denied → Idle
 /// ```
 ///
+/// Enrollment flow (card captured for registration, then denied):
+/// ```text
+/// Idle → Enrolling → Reading → Validating → Denied → Idle
+/// ```
+///
+/// Card + biometric flow (card accepted, then the owner's fingerprint):
+/// ```text
+/// Validating → AwaitingBiometric → Validating → Granted/Denied
+/// AwaitingBiometric → Denied (no finger within timeout)
+/// ```
+///
 /// # Examples
 ///
 /// ```
@@ -127,6 +144,13 @@ pub enum TurnstileState {
 
     /// User did not pass within timeout period (sends 000+82).
     RotationTimeout,
+
+    /// Enrollment mode: the next card read is registered instead of validated.
+    Enrolling,
+
+    /// Card accepted on a card + biometric device, waiting for the
+    /// card owner's fingerprint.
+    AwaitingBiometric,
 }
 
 impl TurnstileState {
@@ -175,6 +199,16 @@ impl TurnstileState {
         matches!(self, Self::RotationTimeout)
     }
 
+    /// Returns `true` if state is Enrolling.
+    pub fn is_enrolling(self) -> bool {
+        matches!(self, Self::Enrolling)
+    }
+
+    /// Returns `true` if state is AwaitingBiometric.
+    pub fn is_awaiting_biometric(self) -> bool {
+        matches!(self, Self::AwaitingBiometric)
+    }
+
     /// Returns `true` if this state sends a protocol message.
     ///
     /// Only WaitingRotation, RotationCompleted, and RotationTimeout
@@ -272,6 +306,9 @@ impl TurnstileState {
     /// - `WaitingRotation` → `RotationInProgress` or `RotationTimeout`
     /// - `RotationInProgress` → `RotationCompleted`
     /// - `RotationCompleted`, `Denied`, `RotationTimeout` → `Idle`
+    /// - `Idle` → `Enrolling` → `Reading` or `Idle` (cancelled or expired)
+    /// - `Validating` → `AwaitingBiometric` → `Validating` (finger read) or
+    ///   `Denied` (no finger in time)
     ///
     /// # Examples
     ///
@@ -299,6 +336,12 @@ impl TurnstileState {
             | (Self::RotationInProgress, Self::RotationCompleted)
             // Return to idle
             | (Self::RotationCompleted | Self::Denied | Self::RotationTimeout, Self::Idle)
+            // Enrollment mode
+            | (Self::Idle, Self::Enrolling)
+            | (Self::Enrolling, Self::Reading | Self::Idle)
+            // Second factor
+            | (Self::Validating, Self::AwaitingBiometric)
+            | (Self::AwaitingBiometric, Self::Validating | Self::Denied)
         )
     }
 }
@@ -315,6 +358,8 @@ impl std::fmt::Display for TurnstileState {
             Self::RotationInProgress => write!(f, "RotationInProgress"),
             Self::RotationCompleted => write!(f, "RotationCompleted"),
             Self::RotationTimeout => write!(f, "RotationTimeout"),
+            Self::Enrolling => write!(f, "Enrolling"),
+            Self::AwaitingBiometric => write!(f, "AwaitingBiometric"),
         }
     }
 }
@@ -330,7 +375,7 @@ impl std::fmt::Display for TurnstileState {
 /// Status messages follow this format:
 ///
 /// ```text
-/// <ID>+REON+<COMMAND>]<CARD_NUMBER>]<TIMESTAMP>]<DIRECTION>]<READER_TYPE>]
+/// <ID>+REON+<COMMAND>]<CARD_NUMBER>]<TIMESTAMP>]<DIRECTION>]<READER_TYPE>][<NSR>]
 /// ```
 ///
 /// # Examples
@@ -379,6 +424,8 @@ pub struct TurnstileStatus {
     timestamp: HenryTimestamp,
     direction: AccessDirection,
     reader_type: ReaderType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nsr: Option<u64>,
 }
 
 impl PartialEq for TurnstileStatus {
@@ -388,6 +435,7 @@ impl PartialEq for TurnstileStatus {
             && self.timestamp.format() == other.timestamp.format()
             && self.direction == other.direction
             && self.reader_type == other.reader_type
+            && self.nsr == other.nsr
     }
 }
 
@@ -396,12 +444,14 @@ impl Eq for TurnstileStatus {}
 impl TurnstileStatus {
     /// Number of fields required in turnstile status messages.
     ///
-    /// Turnstile status messages always contain exactly 4 fields:
+    /// Turnstile status messages always contain at least 4 fields:
     /// 1. Card number (may be empty)
     /// 2. Timestamp (dd/mm/yyyy hh:mm:ss)
     /// 3. Direction code (0, 1, or 2)
     /// 4. Reader type code (0, 1, or 5)
     ///
+    /// followed by the optional NSR of the access event.
+    ///
     /// # Use Cases
     ///
     /// This constant is exposed as public to enable:
@@ -471,9 +521,35 @@ impl TurnstileStatus {
             timestamp,
             direction,
             reader_type,
+            nsr: None,
         }
     }
 
+    /// Attach the NSR of the access event this passage belongs to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::commands::turnstile::{TurnstileStatus, TurnstileState};
+    /// use turnkey_core::{HenryTimestamp, AccessDirection, ReaderType};
+    ///
+    /// let status = TurnstileStatus::new(
+    ///     TurnstileState::RotationCompleted,
+    ///     Some("12345678".to_string()),
+    ///     HenryTimestamp::parse("10/05/2025 12:46:08").unwrap(),
+    ///     AccessDirection::Entry,
+    ///     ReaderType::Rfid,
+    /// )
+    /// .with_nsr(42);
+    ///
+    /// let parsed = TurnstileStatus::parse_rotation_completed(&status.to_fields()).unwrap();
+    /// assert_eq!(parsed.nsr(), Some(42));
+    /// ```
+    pub fn with_nsr(mut self, nsr: u64) -> Self {
+        self.nsr = Some(nsr);
+        self
+    }
+
     /// Parse a WaitingRotation (000+80) status message.
     ///
     /// # Arguments
@@ -487,6 +563,7 @@ impl TurnstileStatus {
     /// 2. Timestamp (dd/mm/yyyy hh:mm:ss)
     /// 3. Direction (0, 1, or 2)
     /// 4. Reader type (0, 1, or 5)
+    /// 5. NSR (optional, may be empty)
     ///
     /// # Returns
     ///
@@ -498,6 +575,7 @@ impl TurnstileStatus {
     /// - Insufficient fields provided
     /// - Timestamp parsing fails
     /// - Direction or reader type codes are invalid
+    /// - NSR is not a number
     ///
     /// # Examples
     ///
@@ -582,6 +660,10 @@ impl TurnstileStatus {
         let timestamp = HenryTimestamp::parse(&fields[1])?;
         let direction = Self::parse_direction(&fields[2])?;
         let reader_type = Self::parse_reader_type(&fields[3])?;
+        let nsr = match fields.get(Self::REQUIRED_FIELD_COUNT) {
+            Some(field) if !field.is_empty() => Some(Self::parse_nsr(field)?),
+            _ => None,
+        };
 
         Ok(Self {
             state,
@@ -589,6 +671,7 @@ impl TurnstileStatus {
             timestamp,
             direction,
             reader_type,
+            nsr,
         })
     }
 
@@ -647,6 +730,15 @@ impl TurnstileStatus {
         })
     }
 
+    /// Parse the NSR field.
+    fn parse_nsr(field: &str) -> Result<u64> {
+        use turnkey_core::Error;
+
+        field.parse::<u64>().map_err(|_| Error::InvalidFieldFormat {
+            message: format!("Invalid NSR: '{}' (expected a record number)", field),
+        })
+    }
+
     /// Convert status to protocol message fields.
     ///
     /// Returns the fields in the order required by the Henry protocol:
@@ -654,6 +746,7 @@ impl TurnstileStatus {
     /// 2. Timestamp
     /// 3. Direction
     /// 4. Reader type
+    /// 5. NSR, only if set
     ///
     /// # Examples
     ///
@@ -677,12 +770,16 @@ impl TurnstileStatus {
     /// assert_eq!(fields[3], "1");  // RFID (modern code)
     /// ```
     pub fn to_fields(&self) -> Vec<String> {
-        vec![
+        let mut fields = vec![
             self.card_number.clone().unwrap_or_default(),
             self.timestamp.format(),
             self.direction.to_u8().to_string(),
             self.reader_type.to_u8().to_string(),
-        ]
+        ];
+        if let Some(nsr) = self.nsr {
+            fields.push(nsr.to_string());
+        }
+        fields
     }
 
     /// Get the turnstile state.
@@ -710,6 +807,11 @@ impl TurnstileStatus {
         self.reader_type
     }
 
+    /// Get the NSR of the access event, if the device sent one.
+    pub fn nsr(&self) -> Option<u64> {
+        self.nsr
+    }
+
     /// Create a builder for TurnstileStatus.
     ///
     /// Provides a fluent API for constructing TurnstileStatus instances.
@@ -762,6 +864,7 @@ pub struct TurnstileStatusBuilder {
     timestamp: Option<HenryTimestamp>,
     direction: Option<AccessDirection>,
     reader_type: Option<ReaderType>,
+    nsr: Option<u64>,
 }
 
 impl TurnstileStatusBuilder {
@@ -795,6 +898,12 @@ impl TurnstileStatusBuilder {
         self
     }
 
+    /// Set the NSR of the access event.
+    pub fn nsr(mut self, nsr: u64) -> Self {
+        self.nsr = Some(nsr);
+        self
+    }
+
     /// Build the TurnstileStatus.
     ///
     /// # Errors
@@ -838,10 +947,87 @@ impl TurnstileStatusBuilder {
             timestamp,
             direction,
             reader_type,
+            nsr: self.nsr,
         })
     }
 }
 
+/// Gap in the NSRs received from a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NsrGap {
+    /// First missing NSR
+    pub first: u64,
+
+    /// Last missing NSR
+    pub last: u64,
+}
+
+impl NsrGap {
+    /// Number of missing records.
+    pub fn len(&self) -> u64 {
+        self.last - self.first + 1
+    }
+
+    /// Always `false`: a gap misses at least one record.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Tracks the NSRs received from one device to spot lost events.
+///
+/// The messages of a passage share their NSR, so repeated and older
+/// numbers are ignored; only a jump forward is reported.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_protocol::commands::turnstile::{NsrGap, NsrTracker};
+///
+/// let mut tracker = NsrTracker::new();
+/// assert_eq!(tracker.observe(7), None);
+/// assert_eq!(tracker.observe(7), None);
+/// assert_eq!(tracker.observe(10), Some(NsrGap { first: 8, last: 9 }));
+/// assert_eq!(tracker.last(), Some(10));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NsrTracker {
+    last: Option<u64>,
+}
+
+impl NsrTracker {
+    /// Tracker that has not seen any NSR yet.
+    ///
+    /// The first NSR observed is never reported as a gap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracker resuming after `last`, such as the last NSR stored.
+    pub fn starting_after(last: u64) -> Self {
+        Self { last: Some(last) }
+    }
+
+    /// Record `nsr`, returning the NSRs skipped since the last one.
+    pub fn observe(&mut self, nsr: u64) -> Option<NsrGap> {
+        let gap = match self.last {
+            Some(last) if nsr <= last => return None,
+            Some(last) if nsr > last + 1 => Some(NsrGap {
+                first: last + 1,
+                last: nsr - 1,
+            }),
+            _ => None,
+        };
+        self.last = Some(nsr);
+        gap
+    }
+
+    /// Highest NSR observed.
+    pub fn last(&self) -> Option<u64> {
+        self.last
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -859,6 +1045,8 @@ mod tests {
         assert!(TurnstileState::RotationInProgress.is_rotation_in_progress());
         assert!(TurnstileState::RotationCompleted.is_rotation_completed());
         assert!(TurnstileState::RotationTimeout.is_rotation_timeout());
+        assert!(TurnstileState::Enrolling.is_enrolling());
+        assert!(TurnstileState::AwaitingBiometric.is_awaiting_biometric());
     }
 
     #[test]
@@ -872,6 +1060,8 @@ mod tests {
         assert!(!TurnstileState::RotationInProgress.sends_message());
         assert!(TurnstileState::RotationCompleted.sends_message());
         assert!(TurnstileState::RotationTimeout.sends_message());
+        assert!(!TurnstileState::Enrolling.sends_message());
+        assert!(!TurnstileState::AwaitingBiometric.sends_message());
     }
 
     #[test]
@@ -1054,6 +1244,55 @@ mod tests {
         assert_eq!(fields[3], "1");
     }
 
+    #[test]
+    fn test_nsr_roundtrip() {
+        let timestamp = HenryTimestamp::parse("10/05/2025 12:46:08").unwrap();
+        let status = TurnstileStatus::new(
+            TurnstileState::RotationCompleted,
+            Some("12345678".to_string()),
+            timestamp,
+            AccessDirection::Entry,
+            ReaderType::Rfid,
+        );
+        assert_eq!(status.to_fields().len(), 4);
+
+        let status = status.with_nsr(1234);
+        let fields = status.to_fields();
+        assert_eq!(fields[4], "1234");
+        let parsed = TurnstileStatus::parse_rotation_completed(&fields).unwrap();
+        assert_eq!(parsed.nsr(), Some(1234));
+        assert_eq!(parsed, status);
+    }
+
+    #[test]
+    fn test_parse_nsr_empty_or_invalid() {
+        let mut fields = vec![
+            "".to_string(),
+            "10/05/2025 12:46:06".to_string(),
+            "0".to_string(),
+            "0".to_string(),
+            "".to_string(),
+        ];
+        let status = TurnstileStatus::parse_waiting_rotation(&fields).unwrap();
+        assert_eq!(status.nsr(), None);
+
+        fields[4] = "abc".to_string();
+        assert!(TurnstileStatus::parse_waiting_rotation(&fields).is_err());
+    }
+
+    #[test]
+    fn test_nsr_tracker_reports_gaps() {
+        let mut tracker = NsrTracker::starting_after(4);
+        assert_eq!(tracker.observe(5), None);
+        assert_eq!(tracker.observe(5), None);
+        assert_eq!(tracker.observe(3), None);
+
+        let gap = tracker.observe(9).unwrap();
+        assert_eq!(gap, NsrGap { first: 6, last: 8 });
+        assert_eq!(gap.len(), 3);
+        assert_eq!(tracker.last(), Some(9));
+    }
+
     #[test]
     fn test_new_status() {
         let timestamp = HenryTimestamp::now();
@@ -1334,6 +1573,31 @@ mod tests {
         assert!(
             !TurnstileState::RotationCompleted.can_transition_to(TurnstileState::WaitingRotation)
         );
+        assert!(!TurnstileState::Enrolling.can_transition_to(TurnstileState::Validating));
+        assert!(!TurnstileState::Denied.can_transition_to(TurnstileState::Enrolling));
+    }
+
+    #[test]
+    fn test_state_transition_enrollment() {
+        assert!(TurnstileState::Idle.can_transition_to(TurnstileState::Enrolling));
+        assert!(TurnstileState::Enrolling.can_transition_to(TurnstileState::Reading));
+        assert!(TurnstileState::Enrolling.can_transition_to(TurnstileState::Idle));
+        assert_eq!(TurnstileState::Enrolling.to_string(), "Enrolling");
+        assert_eq!(TurnstileState::Enrolling.command_code(), None);
+    }
+
+    #[test]
+    fn test_state_transition_second_factor() {
+        assert!(TurnstileState::Validating.can_transition_to(TurnstileState::AwaitingBiometric));
+        assert!(TurnstileState::AwaitingBiometric.can_transition_to(TurnstileState::Validating));
+        assert!(TurnstileState::AwaitingBiometric.can_transition_to(TurnstileState::Denied));
+        assert!(!TurnstileState::AwaitingBiometric.can_transition_to(TurnstileState::Granted));
+        assert!(!TurnstileState::Reading.can_transition_to(TurnstileState::AwaitingBiometric));
+        assert_eq!(
+            TurnstileState::AwaitingBiometric.to_string(),
+            "AwaitingBiometric"
+        );
+        assert_eq!(TurnstileState::AwaitingBiometric.command_code(), None);
     }
 
     #[test]
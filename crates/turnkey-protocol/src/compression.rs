@@ -0,0 +1,376 @@
+//! Payload compression for bulk transfers.
+//!
+//! Card lists, user tables and biometric templates are sent as very long
+//! messages, which is slow over serial bridges and 3G links. This module
+//! compresses the data fields of bulk-transfer commands (see
+//! [`CommandCode::is_bulk_transfer`]) with zlib. Other commands are always
+//! sent as plain text, so access requests and grants keep their latency and
+//! stay readable in traffic captures.
+//!
+//! # Wire Format
+//!
+//! The data fields are joined with `]`, deflated, encoded as URL-safe
+//! base64 without padding and sent as a single field:
+//!
+//! ```text
+//! 01+REON+ECAR]ZLIB=eJwzNDI2MTUzNgBxdQ...
+//!              ^^^^^^^^^^^^^^^^^^^^^^^^ Compressed payload field
+//! ```
+//!
+//! The base64 alphabet contains no protocol delimiter, so the compressed
+//! field goes through the normal framing and parsing code unchanged.
+//! Messages below [`CompressionConfig::threshold`], or that would not get
+//! smaller, are sent uncompressed.
+//!
+//! # Negotiation
+//!
+//! Compression is negotiated per connection by [`HenryCodec`]: a codec with
+//! compression enabled appends the [`COMPRESSION_OFFER`] field to the first
+//! message it sends, and compresses only once the peer has sent the offer
+//! too. The offer is stripped on receipt, so callers never see it. A
+//! compressed message received on a connection that did not negotiate
+//! compression is rejected.
+//!
+//! Real Henry equipment knows nothing about this extension; enable it only
+//! on connections between Turnkey components.
+//!
+//! # Limits
+//!
+//! Decompression stops at [`CompressionConfig::max_decompressed_size`], so a
+//! small frame cannot expand into an unbounded allocation. The decompressed
+//! fields are validated like any other field data.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::compression::CompressionConfig;
+//! use turnkey_protocol::{CommandCode, FieldData, Message};
+//! use turnkey_core::DeviceId;
+//!
+//! let fields = (0..200)
+//!     .map(|i| FieldData::new(format!("{:020}", i)).unwrap())
+//!     .collect();
+//! let original = Message::new(DeviceId::new(1).unwrap(), CommandCode::SendCards, fields).unwrap();
+//!
+//! let config = CompressionConfig::default();
+//! let mut message = original.clone();
+//! assert!(config.compress(&mut message).unwrap());
+//! assert_eq!(message.fields.len(), 1);
+//!
+//! assert!(config.decompress(&mut message).unwrap());
+//! assert_eq!(message.fields, original.fields);
+//! ```
+//!
+//! [`CommandCode::is_bulk_transfer`]: crate::CommandCode::is_bulk_transfer
+//! [`HenryCodec`]: crate::HenryCodec
+
+use crate::field::FieldData;
+use crate::message::Message;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::io::{Read, Write};
+use turnkey_core::{Error, Result, constants::DELIMITER_FIELD};
+
+/// Prefix identifying a compressed payload field
+pub const COMPRESSED_FIELD_PREFIX: &str = "ZLIB=";
+
+/// Field appended to the first message of a connection offering compression
+pub const COMPRESSION_OFFER: &str = "COMP=zlib";
+
+/// Default smallest payload worth compressing (bytes)
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 512;
+
+/// Default largest accepted decompressed payload (bytes)
+///
+/// Matches the default frame size limit of the codec.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 64 * 1024;
+
+/// Compression settings of one side of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// Payloads shorter than this many bytes are sent uncompressed
+    pub threshold: usize,
+
+    /// Largest payload a received message may decompress to
+    pub max_decompressed_size: usize,
+
+    /// zlib level, 0 (store) to 9 (best)
+    pub level: u32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            level: Compression::default().level(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Use a different compression threshold
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Use a different decompression limit
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+
+    /// Use a different zlib level (clamped to 9)
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Replace the fields of a bulk-transfer message with a compressed field
+    ///
+    /// Returns `Ok(false)` and leaves the message untouched if the command is
+    /// not a bulk transfer, the payload is below the threshold, or
+    /// compression would not make it smaller.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the encoder fails.
+    pub fn compress(&self, message: &mut Message) -> Result<bool> {
+        if !message.command.is_bulk_transfer() || is_compressed(message) {
+            return Ok(false);
+        }
+
+        let payload = join_fields(&message.fields);
+        if payload.len() < self.threshold {
+            return Ok(false);
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(self.level));
+        encoder.write_all(payload.as_bytes())?;
+        let encoded = URL_SAFE_NO_PAD.encode(encoder.finish()?);
+        if COMPRESSED_FIELD_PREFIX.len() + encoded.len() >= payload.len() {
+            return Ok(false);
+        }
+
+        // SAFETY: the prefix and the base64 URL-safe alphabet contain no
+        // protocol delimiter.
+        let field =
+            unsafe { FieldData::new_unchecked(format!("{}{}", COMPRESSED_FIELD_PREFIX, encoded)) };
+        message.fields = vec![field];
+        Ok(true)
+    }
+
+    /// Restore the fields of a compressed message
+    ///
+    /// Returns `Ok(false)` if the message is not compressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidMessageFormat` if the command is not a bulk
+    /// transfer, the payload is not valid base64 or zlib data, or it expands
+    /// beyond `max_decompressed_size`, and `Error::InvalidFieldFormat` if a
+    /// decompressed field contains a protocol delimiter.
+    pub fn decompress(&self, message: &mut Message) -> Result<bool> {
+        let Some(encoded) = compressed_payload(message) else {
+            return Ok(false);
+        };
+        if !message.command.is_bulk_transfer() {
+            return Err(invalid(format!(
+                "Compressed payload not allowed for command {}",
+                message.command
+            )));
+        }
+
+        let compressed = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|e| invalid(format!("Compressed payload is not valid base64: {}", e)))?;
+
+        let limit = self.max_decompressed_size;
+        let mut payload = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .take(limit as u64 + 1)
+            .read_to_end(&mut payload)
+            .map_err(|e| invalid(format!("Compressed payload is corrupt: {}", e)))?;
+        if payload.len() > limit {
+            return Err(invalid(format!(
+                "Compressed payload expands beyond {} bytes",
+                limit
+            )));
+        }
+
+        let payload = String::from_utf8(payload)
+            .map_err(|_| invalid("Compressed payload is not valid UTF-8".to_string()))?;
+        message.fields = payload
+            .split(DELIMITER_FIELD)
+            .map(|field| FieldData::new(field.to_string()))
+            .collect::<Result<_>>()?;
+        Ok(true)
+    }
+}
+
+/// Whether a message carries a compressed payload
+pub fn is_compressed(message: &Message) -> bool {
+    compressed_payload(message).is_some()
+}
+
+/// Whether a field is the compression offer
+pub fn is_offer(field: &FieldData) -> bool {
+    field.as_str() == COMPRESSION_OFFER
+}
+
+/// The compression offer as a field
+pub(crate) fn offer_field() -> FieldData {
+    // SAFETY: the offer contains no protocol delimiter.
+    unsafe { FieldData::new_unchecked(COMPRESSION_OFFER.to_string()) }
+}
+
+/// Base64 payload of a compressed message (its only field)
+fn compressed_payload(message: &Message) -> Option<&str> {
+    match message.fields.as_slice() {
+        [field] => field.as_str().strip_prefix(COMPRESSED_FIELD_PREFIX),
+        _ => None,
+    }
+}
+
+fn join_fields(fields: &[FieldData]) -> String {
+    fields
+        .iter()
+        .map(FieldData::as_str)
+        .collect::<Vec<_>>()
+        .join(DELIMITER_FIELD)
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidMessageFormat { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandCode;
+    use turnkey_core::DeviceId;
+
+    fn message(command: CommandCode, fields: &[&str]) -> Message {
+        Message::new(
+            DeviceId::new(1).unwrap(),
+            command,
+            fields
+                .iter()
+                .map(|f| FieldData::new(f.to_string()).unwrap())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn card_list(count: usize) -> Message {
+        let cards: Vec<String> = (0..count).map(|i| format!("{:020}", i)).collect();
+        let fields: Vec<&str> = cards.iter().map(String::as_str).collect();
+        message(CommandCode::SendCards, &fields)
+    }
+
+    fn compressed(command: CommandCode, raw: &[u8]) -> Message {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(raw).unwrap();
+        let field = format!(
+            "{}{}",
+            COMPRESSED_FIELD_PREFIX,
+            URL_SAFE_NO_PAD.encode(encoder.finish().unwrap())
+        );
+        message(command, &[&field])
+    }
+
+    #[test]
+    fn test_round_trip_keeps_fields() {
+        let original = card_list(100);
+        let config = CompressionConfig::default();
+
+        let mut message = original.clone();
+        assert!(config.compress(&mut message).unwrap());
+        assert!(is_compressed(&message));
+        assert!(message.fields[0].len() < join_fields(&original.fields).len() / 4);
+
+        assert!(config.decompress(&mut message).unwrap());
+        assert_eq!(message.fields, original.fields);
+    }
+
+    #[test]
+    fn test_round_trip_keeps_empty_fields() {
+        let original = message(CommandCode::SendUsers, &["", "1", "", "", "Ana", ""]);
+        let config = CompressionConfig::default().with_threshold(0);
+
+        let mut message = original.clone();
+        // Too short to shrink: left as is
+        assert!(!config.compress(&mut message).unwrap());
+
+        let mut message = compressed(CommandCode::SendUsers, b"]1]]]Ana]");
+        assert!(config.decompress(&mut message).unwrap());
+        assert_eq!(message.fields, original.fields);
+    }
+
+    #[test]
+    fn test_compress_skips_small_and_non_bulk_messages() {
+        let config = CompressionConfig::default();
+
+        let mut small = card_list(2);
+        assert!(!config.compress(&mut small).unwrap());
+        assert_eq!(small.fields, card_list(2).fields);
+
+        let mut grant = card_list(100);
+        grant.command = CommandCode::GrantExit;
+        let before = grant.clone();
+        assert!(!config.compress(&mut grant).unwrap());
+        assert_eq!(grant.fields, before.fields);
+    }
+
+    #[test]
+    fn test_decompress_ignores_plain_messages() {
+        let mut plain = card_list(3);
+        assert!(!CompressionConfig::default().decompress(&mut plain).unwrap());
+        assert_eq!(plain.fields, card_list(3).fields);
+    }
+
+    #[test]
+    fn test_decompress_rejects_expansion_past_limit() {
+        // 1 MiB of zeros deflates to about a kilobyte
+        let mut bomb = compressed(CommandCode::SendCards, &vec![b'0'; 1024 * 1024]);
+        assert!(bomb.fields[0].len() < 2048);
+
+        let result = CompressionConfig::default().decompress(&mut bomb);
+        assert!(matches!(result, Err(Error::InvalidMessageFormat { .. })));
+    }
+
+    #[test]
+    fn test_decompress_accepts_payload_at_limit() {
+        let config = CompressionConfig::default().with_max_decompressed_size(1000);
+        let mut message = compressed(CommandCode::SendCards, &[b'7'; 1000]);
+        assert!(config.decompress(&mut message).unwrap());
+        assert_eq!(message.fields[0].len(), 1000);
+    }
+
+    #[test]
+    fn test_decompress_rejects_delimiters_in_payload() {
+        let mut message = compressed(CommandCode::SendCards, b"123]45+6");
+        let result = CompressionConfig::default().decompress(&mut message);
+        assert!(matches!(result, Err(Error::InvalidFieldFormat { .. })));
+    }
+
+    #[test]
+    fn test_decompress_rejects_non_bulk_command() {
+        let mut message = compressed(CommandCode::GrantBoth, b"5]Liberado");
+        let result = CompressionConfig::default().decompress(&mut message);
+        assert!(matches!(result, Err(Error::InvalidMessageFormat { .. })));
+    }
+
+    #[test]
+    fn test_decompress_rejects_garbage() {
+        let config = CompressionConfig::default();
+        for field in ["ZLIB=", "ZLIB=!!!!", "ZLIB=AAAA", "ZLIB=eJw"] {
+            let mut message = message(CommandCode::SendCards, &[field]);
+            assert!(config.decompress(&mut message).is_err(), "{field}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{commands::CommandCode, field::FieldData, message::Message};
+use crate::{charset::Codepage, commands::CommandCode, field::FieldData, message::Message};
 use bytes::{BufMut, Bytes, BytesMut};
 use std::fmt;
 use turnkey_core::{DeviceId, Error, Result, constants::*};
@@ -156,6 +156,37 @@ impl Frame {
         self.checksum.as_deref()
     }
 
+    /// Re-encode the text of a frame for an LCD character set.
+    ///
+    /// Frames built from a [`Message`] hold UTF-8; this converts non-ASCII
+    /// characters to the single-byte codepage of the device. ASCII frames
+    /// and frames that are not valid UTF-8 are returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use turnkey_protocol::{Codepage, Frame};
+    ///
+    /// let frame = Frame::from_string("15+REON+00+6]5]Prédio]", false);
+    /// let encoded = frame.encode_text(Codepage::Cp850);
+    /// assert_eq!(encoded.as_bytes(), b"15+REON+00+6]5]Pr\x82dio]");
+    /// ```
+    pub fn encode_text(self, codepage: Codepage) -> Self {
+        if self.data.is_ascii() {
+            return self;
+        }
+        let Ok(text) = std::str::from_utf8(&self.data) else {
+            return self;
+        };
+
+        let data = Bytes::from(codepage.encode(text));
+        Frame {
+            size: data.len(),
+            data,
+            has_framing: self.has_framing,
+            checksum: self.checksum,
+        }
+    }
+
     /// Add STX/ETX framing bytes to the frame
     ///
     /// Returns a new Frame with framing added. If framing already exists, returns self.
@@ -1,19 +1,29 @@
 pub mod builder;
+pub mod charset;
 pub mod codec;
 pub mod commands;
+pub mod compression;
 pub mod field;
 pub mod frame;
 pub mod message;
 pub mod parser;
+pub mod schema;
+pub mod signing;
 pub mod stream_parser;
 pub mod validation;
 
 pub use builder::{MessageBuilder, format_message};
+pub use charset::Codepage;
 pub use codec::HenryCodec;
 pub use commands::CommandCode;
+pub use compression::CompressionConfig;
 pub use field::FieldData;
 pub use frame::Frame;
 pub use message::{Message, MessageType};
-pub use parser::MessageParser;
+pub use parser::{MessageParser, ParseMode};
+pub use schema::{AnnotatedMessage, CommandSchema, FieldDescriptor, FieldKind, SchemaRegistry};
+pub use signing::{MessageSigner, ReplayGuard, SignatureStamp};
 pub use stream_parser::{DrainFrames, ParserState, StreamParser};
-pub use validation::{validate_card_number, validate_field, validate_field_lengths};
+pub use validation::{
+    validate_card_number, validate_field, validate_field_lengths, validate_message,
+};
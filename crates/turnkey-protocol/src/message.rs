@@ -1,8 +1,12 @@
-use crate::commands::CommandCode;
+use crate::commands::access::{AccessDecision, AccessResponse};
+use crate::commands::{
+    AccessRequest, AlarmReport, CommandCode, DeviceStatusReport, DiagnosticsReport, EventChunk,
+    GetEvents, OverrideGrant, SelfTestReport, SyncAck, TurnstileStatus,
+};
 use crate::field::FieldData;
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use turnkey_core::{DeviceId, Error, HenryTimestamp, Result};
+use turnkey_core::{CorrelationId, DeviceId, Error, HenryTimestamp, Result};
 
 /// Parsed Henry protocol message
 ///
@@ -336,7 +340,9 @@ impl Message {
             CommandCode::GrantEntry
             | CommandCode::GrantExit
             | CommandCode::GrantBoth
-            | CommandCode::DenyAccess => MessageType::AccessResponse,
+            | CommandCode::DenyAccess
+            | CommandCode::DisplayOnly
+            | CommandCode::OverrideGrant => MessageType::AccessResponse,
             CommandCode::WaitingRotation => MessageType::WaitingForRotation,
             CommandCode::RotationCompleted => MessageType::RotationCompleted,
             CommandCode::RotationTimeout => MessageType::RotationTimeout,
@@ -345,6 +351,188 @@ impl Message {
             _ => MessageType::Other,
         }
     }
+
+    /// Correlation ID carried by an access request, if any
+    ///
+    /// Lets transport code tag its logs with the passage a message belongs
+    /// to without parsing the whole request. Returns `None` for other
+    /// commands and for requests from devices that do not send one.
+    pub fn correlation_id(&self) -> Option<CorrelationId> {
+        if self.command != CommandCode::AccessRequest {
+            return None;
+        }
+        self.field(AccessRequest::REQUIRED_FIELD_COUNT)?
+            .parse()
+            .ok()
+    }
+
+    /// Parse an access request (`000+0`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands, or the error
+    /// of [`AccessRequest::parse()`](crate::commands::AccessRequest::parse).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_core::AccessDirection;
+    /// use turnkey_protocol::MessageParser;
+    ///
+    /// let message = MessageParser::parse("15+REON+000+0]12345678]10/05/2025 12:46:06]1]1]").unwrap();
+    /// let request = message.as_access_request().unwrap();
+    ///
+    /// assert_eq!(request.card_number(), "12345678");
+    /// assert_eq!(request.direction(), AccessDirection::Entry);
+    /// assert!(message.as_access_response().is_err());
+    /// ```
+    pub fn as_access_request(&self) -> Result<AccessRequest> {
+        if self.command != CommandCode::AccessRequest {
+            return Err(self.unexpected_command());
+        }
+        AccessRequest::parse(&self.field_strings())
+    }
+
+    /// Parse an access response (`00+1`, `00+5`, `00+6` or `00+30`).
+    ///
+    /// The decision is taken from the command code; an operator override
+    /// (`00+40`) has its own fields, see
+    /// [`as_override_grant()`](Self::as_override_grant).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands, or the error
+    /// of [`AccessResponse::parse()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::MessageParser;
+    /// use turnkey_protocol::commands::access::AccessDecision;
+    ///
+    /// let message = MessageParser::parse("15+REON+00+6]5]Acesso liberado]").unwrap();
+    /// let response = message.as_access_response().unwrap();
+    ///
+    /// assert_eq!(response.decision(), AccessDecision::GrantExit);
+    /// assert_eq!(response.timeout_seconds(), 5);
+    /// assert_eq!(response.display_message(), "Acesso liberado");
+    /// ```
+    pub fn as_access_response(&self) -> Result<AccessResponse> {
+        let decision =
+            AccessDecision::from_command(self.command).ok_or_else(|| self.unexpected_command())?;
+        AccessResponse::parse(decision, &self.field_strings())
+    }
+
+    /// Parse a turnstile status notification (`000+80`, `000+81` or `000+82`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCommandCode` for other commands, or the error
+    /// of the matching [`TurnstileStatus`] parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::MessageParser;
+    /// use turnkey_protocol::commands::TurnstileState;
+    ///
+    /// let message = MessageParser::parse("15+REON+000+81]12345678]10/05/2025 12:46:08]1]1]").unwrap();
+    /// let status = message.as_turnstile_status().unwrap();
+    ///
+    /// assert_eq!(status.state(), TurnstileState::RotationCompleted);
+    /// assert_eq!(status.card_number(), Some("12345678"));
+    /// ```
+    pub fn as_turnstile_status(&self) -> Result<TurnstileStatus> {
+        let fields = self.field_strings();
+        match self.command {
+            CommandCode::WaitingRotation => TurnstileStatus::parse_waiting_rotation(&fields),
+            CommandCode::RotationCompleted => TurnstileStatus::parse_rotation_completed(&fields),
+            CommandCode::RotationTimeout => TurnstileStatus::parse_rotation_timeout(&fields),
+            _ => Err(self.unexpected_command()),
+        }
+    }
+
+    /// Parse a device status report (`RQ` with fields).
+    ///
+    /// # Errors
+    ///
+    /// See [`DeviceStatusReport::parse()`].
+    pub fn as_status_report(&self) -> Result<DeviceStatusReport> {
+        DeviceStatusReport::parse(self)
+    }
+
+    /// Parse a self-test report (`RT` with fields).
+    ///
+    /// # Errors
+    ///
+    /// See [`SelfTestReport::parse()`].
+    pub fn as_self_test_report(&self) -> Result<SelfTestReport> {
+        SelfTestReport::parse(self)
+    }
+
+    /// Parse a peripheral diagnostics report (`RD` with a report).
+    ///
+    /// # Errors
+    ///
+    /// See [`DiagnosticsReport::parse()`].
+    pub fn as_diagnostics_report(&self) -> Result<DiagnosticsReport> {
+        DiagnosticsReport::parse(self)
+    }
+
+    /// Parse an event query (`ER` from the server).
+    ///
+    /// # Errors
+    ///
+    /// See [`GetEvents::parse()`].
+    pub fn as_event_query(&self) -> Result<GetEvents> {
+        GetEvents::parse(self)
+    }
+
+    /// Parse a chunk of events (`ER` from a device).
+    ///
+    /// # Errors
+    ///
+    /// See [`EventChunk::parse()`].
+    pub fn as_event_chunk(&self) -> Result<EventChunk> {
+        EventChunk::parse(self)
+    }
+
+    /// Parse an operator override (`00+40`).
+    ///
+    /// # Errors
+    ///
+    /// See [`OverrideGrant::parse()`].
+    pub fn as_override_grant(&self) -> Result<OverrideGrant> {
+        OverrideGrant::parse(self)
+    }
+
+    /// Parse a security alarm (`AL`).
+    ///
+    /// # Errors
+    ///
+    /// See [`AlarmReport::parse()`].
+    pub fn as_alarm_report(&self) -> Result<AlarmReport> {
+        AlarmReport::parse(self)
+    }
+
+    /// Parse a device's answer to a card or template batch (`ECAR`/`ED`).
+    ///
+    /// # Errors
+    ///
+    /// See [`SyncAck::parse()`].
+    pub fn as_sync_ack(&self) -> Result<SyncAck> {
+        SyncAck::parse(self)
+    }
+
+    fn unexpected_command(&self) -> Error {
+        Error::InvalidCommandCode {
+            code: self.command.as_str().to_string(),
+        }
+    }
+
+    fn field_strings(&self) -> Vec<String> {
+        self.fields.iter().map(|f| f.as_str().to_string()).collect()
+    }
 }
 
 impl fmt::Display for Message {
@@ -421,6 +609,26 @@ mod tests {
         assert!(msg.required_field(1, "timestamp").is_err());
     }
 
+    #[test]
+    fn test_correlation_id() {
+        let device_id = DeviceId::new(15).unwrap();
+        let correlation_id = CorrelationId::new();
+        let mut fields: Vec<FieldData> = ["12345678", "10/05/2025 12:46:06", "1", "1"]
+            .iter()
+            .map(|f| FieldData::new(f.to_string()).unwrap())
+            .collect();
+
+        let msg = Message::new(device_id, CommandCode::AccessRequest, fields.clone()).unwrap();
+        assert_eq!(msg.correlation_id(), None);
+
+        fields.push(FieldData::new(correlation_id.to_string()).unwrap());
+        let msg = Message::new(device_id, CommandCode::AccessRequest, fields.clone()).unwrap();
+        assert_eq!(msg.correlation_id(), Some(correlation_id));
+
+        let msg = Message::new(device_id, CommandCode::SendConfig, fields).unwrap();
+        assert_eq!(msg.correlation_id(), None);
+    }
+
     #[test]
     fn test_message_type() {
         let device_id = DeviceId::new(1).unwrap();
@@ -701,6 +909,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_typed_accessors_check_command() {
+        let response = crate::MessageParser::parse("15+REON+00+30]0]Cartao bloqueado]").unwrap();
+        let parsed = response.as_access_response().unwrap();
+        assert!(parsed.is_deny());
+        assert_eq!(parsed.display_message(), "Cartao bloqueado");
+
+        assert!(matches!(
+            response.as_access_request(),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+        assert!(matches!(
+            response.as_turnstile_status(),
+            Err(Error::InvalidCommandCode { .. })
+        ));
+        assert!(response.as_status_report().is_err());
+        assert!(response.as_override_grant().is_err());
+    }
+
+    #[test]
+    fn test_typed_accessors_reject_bad_fields() {
+        let missing = crate::MessageParser::parse("15+REON+00+1]5]").unwrap();
+        assert!(matches!(
+            missing.as_access_response(),
+            Err(Error::MissingField(_))
+        ));
+
+        let timeout = crate::MessageParser::parse("15+REON+00+1]999]Ok]").unwrap();
+        assert!(matches!(
+            timeout.as_access_response(),
+            Err(Error::InvalidFieldFormat { .. })
+        ));
+
+        let status =
+            crate::MessageParser::parse("15+REON+000+82]]10/05/2025 12:46:08]9]1]").unwrap();
+        assert!(status.as_turnstile_status().is_err());
+    }
+
     #[test]
     fn test_capacity_constants_are_correct() {
         use turnkey_core::constants::{
@@ -82,13 +82,51 @@
 //! assert!(result.is_err());
 //! ```
 //!
+//! # Tolerant Parsing
+//!
+//! Some firmware versions deviate from the canonical header: device IDs
+//! without zero padding (`1` for `01`), spaces around the `+` of the
+//! command code (`000 + 0`) or lowercase letters (`reon`, `rq`).
+//! [`ParseMode::Tolerant`] rewrites such headers to the canonical form
+//! before parsing. Data fields are never touched, and
+//! [`MessageBuilder`](crate::MessageBuilder) always emits the canonical form.
+//!
+//! ```
+//! use turnkey_protocol::parser::{MessageParser, ParseMode};
+//! use turnkey_protocol::commands::CommandCode;
+//!
+//! let input = "1+reon+000 + 0]12345678]10/05/2025 12:46:06]1]0]";
+//! assert!(MessageParser::parse(input).is_err());
+//!
+//! let msg = MessageParser::parse_with(input, ParseMode::Tolerant).unwrap();
+//! assert_eq!(msg.device_id.as_u8(), 1);
+//! assert_eq!(msg.command, CommandCode::AccessRequest);
+//! ```
+//!
 //! # Protocol Reference
 //!
 //! See Henry protocol specification section 2.1 for message format details.
 
 use crate::{commands::CommandCode, field::FieldData, message::Message};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use turnkey_core::{DeviceId, Error, Result, constants::*};
 
+/// How closely inbound message headers must follow the protocol.
+///
+/// Only the header (device ID, protocol ID and command code) is affected;
+/// data fields are parsed the same way in both modes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParseMode {
+    /// Accept only the canonical header.
+    #[default]
+    Strict,
+
+    /// Accept headers with missing zero padding, extra spaces or lowercase
+    /// letters, normalized with [`MessageParser::normalize()`].
+    Tolerant,
+}
+
 /// Parser for Henry protocol messages.
 ///
 /// This struct provides a single static method for parsing raw protocol
@@ -261,6 +299,79 @@ impl MessageParser {
 
         Message::new(device_id, command, fields?)
     }
+
+    /// Parse a message, normalizing its header first in tolerant mode.
+    ///
+    /// With [`ParseMode::Strict`] this is the same as [`parse()`](Self::parse).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`parse()`](Self::parse), for the normalized input.
+    pub fn parse_with(input: &str, mode: ParseMode) -> Result<Message> {
+        match mode {
+            ParseMode::Strict => Self::parse(input),
+            ParseMode::Tolerant => Self::parse(&Self::normalize(input)),
+        }
+    }
+
+    /// Rewrite a message header to the canonical form.
+    ///
+    /// The header is everything before the first `]`. Each of its
+    /// `+`-separated parts is trimmed and uppercased, and a numeric device
+    /// ID is zero-padded to 2 digits. The rest of the message is returned
+    /// unchanged, so card numbers and display text keep their exact bytes.
+    /// Input that is already canonical is borrowed, not copied.
+    ///
+    /// Normalization never fails: a header that is still invalid afterwards
+    /// is left for [`parse()`](Self::parse) to reject.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_protocol::parser::MessageParser;
+    ///
+    /// assert_eq!(
+    ///     MessageParser::normalize("7+reon+00 + 6]5]Acesso liberado]"),
+    ///     "07+REON+00+6]5]Acesso liberado]"
+    /// );
+    /// assert_eq!(MessageParser::normalize("15+REON+RQ"), "15+REON+RQ");
+    /// ```
+    pub fn normalize(input: &str) -> Cow<'_, str> {
+        let input = input.trim();
+        let header_end = input.find(DELIMITER_FIELD).unwrap_or(input.len());
+        let (header, rest) = input.split_at(header_end);
+
+        let mut parts = header.split(DELIMITER_DEVICE);
+        let mut normalized = String::with_capacity(input.len() + 1);
+        if let Some(device_id) = parts.next() {
+            normalized.push_str(&normalize_device_id(device_id.trim()));
+        }
+        for part in parts {
+            normalized.push_str(DELIMITER_DEVICE);
+            normalized.push_str(&part.trim().to_ascii_uppercase());
+        }
+
+        if normalized == header {
+            Cow::Borrowed(input)
+        } else {
+            normalized.push_str(rest);
+            Cow::Owned(normalized)
+        }
+    }
+}
+
+/// Zero-pad a numeric device ID to 2 digits, dropping extra leading zeros
+///
+/// Anything that is not a plain number is returned as is.
+fn normalize_device_id(device_id: &str) -> Cow<'_, str> {
+    if device_id.is_empty() || !device_id.bytes().all(|b| b.is_ascii_digit()) {
+        return Cow::Borrowed(device_id);
+    }
+    let digits = device_id.trim_start_matches('0');
+    if digits.len() > 2 {
+        return Cow::Borrowed(device_id);
+    }
+    Cow::Owned(format!("{digits:0>2}"))
 }
 
 #[cfg(test)]
@@ -361,4 +472,72 @@ mod tests {
         let msg = MessageParser::parse(input).unwrap();
         assert_eq!(msg.device_id.as_u8(), 15);
     }
+
+    /// Headers captured from firmware in the field, with the canonical form
+    const DEVIANT_TRACES: &[(&str, &str)] = &[
+        (
+            "1+REON+000+0]00000000000011912322]10/05/2025 12:46:06]1]0]",
+            "01+REON+000+0]00000000000011912322]10/05/2025 12:46:06]1]0]",
+        ),
+        (
+            "015+REON+000+80]]10/05/2025 12:46:06]0]0]",
+            "15+REON+000+80]]10/05/2025 12:46:06]0]0]",
+        ),
+        (
+            "15+REON+000 + 0]12345678]10/05/2025 12:46:06]1]0]",
+            "15+REON+000+0]12345678]10/05/2025 12:46:06]1]0]",
+        ),
+        (
+            " 7 + REON + 000+81 ]]10/05/2025 12:46:08]1]0]",
+            "07+REON+000+81]]10/05/2025 12:46:08]1]0]",
+        ),
+        ("15+reon+rq", "15+REON+RQ"),
+        (
+            "3+Reon+ecar]00]1]I]12345678]",
+            "03+REON+ECAR]00]1]I]12345678]",
+        ),
+    ];
+
+    #[test]
+    fn test_tolerant_mode_accepts_deviant_traces() {
+        for &(deviant, canonical) in DEVIANT_TRACES {
+            assert_eq!(MessageParser::normalize(deviant), canonical, "{deviant}");
+
+            let tolerant = MessageParser::parse_with(deviant, ParseMode::Tolerant).unwrap();
+            let expected = MessageParser::parse(canonical).unwrap();
+            assert_eq!(tolerant.device_id, expected.device_id);
+            assert_eq!(tolerant.command, expected.command);
+            assert_eq!(tolerant.fields, expected.fields);
+
+            // The builder side always emits the canonical form
+            let wire = crate::Frame::from(tolerant).to_string().unwrap();
+            assert_eq!(wire, canonical);
+        }
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_deviant_headers() {
+        for input in [
+            "15+REON+000 + 0]12345678]",
+            "15+reon+RQ",
+            "15+REON+rq",
+            " 7 +REON+RQ",
+        ] {
+            assert!(MessageParser::parse(input).is_err(), "{input}");
+            assert!(MessageParser::parse_with(input, ParseMode::Strict).is_err());
+        }
+    }
+
+    #[test]
+    fn test_normalize_leaves_fields_and_bad_headers_alone() {
+        // Lowercase and spaces in fields are data, not header
+        let input = "15+REON+00+6]5]acesso + liberado]";
+        assert!(matches!(MessageParser::normalize(input), Cow::Borrowed(_)));
+
+        // Still invalid after normalization
+        for input in ["150+REON+RQ", "x1+REON+RQ", "15+XXXX+RQ", "15+REON+00+99]"] {
+            assert!(MessageParser::parse_with(input, ParseMode::Tolerant).is_err());
+        }
+        assert_eq!(MessageParser::normalize("0150+reon+rq"), "0150+REON+RQ");
+    }
 }
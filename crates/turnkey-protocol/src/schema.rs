@@ -0,0 +1,642 @@
+//! Field schemas for Henry protocol commands.
+//!
+//! The parser only splits a message into anonymous fields: field 2 of an
+//! access request is the direction, but field 2 of a grant does not exist
+//! and field 1 is a timeout. This module describes the fields of every
+//! command (name, kind, whether they are required) so tools can label and
+//! check a message without knowing the command layouts themselves.
+//!
+//! [`SchemaRegistry::builtin()`] holds the layouts of all commands in
+//! [`CommandCode`]. Equipment with vendor-specific layouts can start from
+//! [`SchemaRegistry::with_builtin()`] and [`register`](SchemaRegistry::register)
+//! its own schemas.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_protocol::MessageParser;
+//! use turnkey_protocol::schema::SchemaRegistry;
+//!
+//! let message = MessageParser::parse("15+REON+000+0]12345678]10/05/2025 12:46:06]1]1]").unwrap();
+//! let annotated = SchemaRegistry::builtin().annotate(&message);
+//!
+//! assert_eq!(annotated.fields[2].name, "direction");
+//! assert_eq!(annotated.fields[2].meaning.as_deref(), Some("Entry"));
+//! assert!(annotated.is_valid());
+//! ```
+//!
+//! A signature field appended by [`MessageSigner`](crate::MessageSigner)
+//! is recognized on any command and labeled `signature`.
+
+use crate::commands::CommandCode;
+use crate::message::Message;
+use crate::signing::{MAC_FIELD_PREFIX, MessageSigner};
+use crate::validation::validate_card_number;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use turnkey_core::constants::MAX_DISPLAY_MESSAGE_LENGTH;
+use turnkey_core::{
+    AccessDirection, CorrelationId, DeviceId, Error, HenryTimestamp, ReaderType, Result, Timeout,
+    ValidationMode,
+};
+
+/// Semantic type of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FieldKind {
+    /// Free text
+    Text,
+    /// Text shown on the device display (up to 40 characters)
+    DisplayMessage,
+    /// Card number (3-20 characters)
+    CardNumber,
+    /// `dd/mm/yyyy hh:mm:ss` timestamp
+    Timestamp,
+    /// Direction code (0=Undefined, 1=Entry, 2=Exit)
+    Direction,
+    /// Reader type code (0/1=RFID, 5=Biometric)
+    ReaderType,
+    /// Duration in seconds (0-255)
+    Seconds,
+    /// Unsigned integer
+    Integer,
+    /// Validation mode code (F, O, A or S)
+    ValidationMode,
+    /// Correlation ID (UUID)
+    CorrelationId,
+    /// HMAC signature (`MAC=<counter>:<time>:<hex>`)
+    Signature,
+}
+
+impl FieldKind {
+    /// Check a non-empty value and describe what it means
+    ///
+    /// Returns the human-readable meaning of coded values (e.g. `Entry` for
+    /// direction `1`), or `None` when the value speaks for itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidFieldFormat` (or the specific error of the value
+    /// type) if the value does not fit the kind.
+    pub fn check(self, value: &str) -> Result<Option<String>> {
+        let invalid = |expected: &str| Error::InvalidFieldFormat {
+            message: format!("'{}' is not {}", value, expected),
+        };
+
+        match self {
+            Self::Text => Ok(None),
+            Self::DisplayMessage => {
+                let len = value.chars().count();
+                if len > MAX_DISPLAY_MESSAGE_LENGTH {
+                    return Err(Error::InvalidFieldFormat {
+                        message: format!(
+                            "Display message exceeds {} characters (got {})",
+                            MAX_DISPLAY_MESSAGE_LENGTH, len
+                        ),
+                    });
+                }
+                Ok(None)
+            }
+            Self::CardNumber => validate_card_number(value).map(|_| None),
+            Self::Timestamp => HenryTimestamp::parse(value).map(|_| None),
+            Self::Direction => {
+                let code = value.parse().map_err(|_| invalid("a direction code"))?;
+                Ok(Some(format!("{:?}", AccessDirection::from_u8(code)?)))
+            }
+            Self::ReaderType => {
+                let code = value.parse().map_err(|_| invalid("a reader type code"))?;
+                Ok(Some(format!("{:?}", ReaderType::from_u8(code)?)))
+            }
+            Self::Seconds => {
+                let seconds = Timeout::parse_field(value, "seconds")?;
+                Ok(Some(format!("{}s", seconds)))
+            }
+            Self::Integer => {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| invalid("an unsigned integer"))?;
+                Ok(None)
+            }
+            Self::ValidationMode => {
+                let mut chars = value.chars();
+                let mode = match (chars.next(), chars.next()) {
+                    (Some(c), None) => ValidationMode::from_char(c)?,
+                    _ => return Err(invalid("a validation mode code")),
+                };
+                Ok(Some(format!("{:?}", mode)))
+            }
+            Self::CorrelationId => value.parse::<CorrelationId>().map(|_| None),
+            Self::Signature => {
+                value
+                    .strip_prefix(MAC_FIELD_PREFIX)
+                    .and_then(MessageSigner::split_signature)
+                    .ok_or_else(|| invalid("a signature"))?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Name and type of one field position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FieldDescriptor {
+    /// Field name, in snake_case
+    pub name: &'static str,
+
+    /// Semantic type
+    pub kind: FieldKind,
+
+    /// Whether the field must be present and non-empty
+    pub required: bool,
+}
+
+impl FieldDescriptor {
+    /// Field that must be present and non-empty
+    pub const fn required(name: &'static str, kind: FieldKind) -> Self {
+        Self {
+            name,
+            kind,
+            required: true,
+        }
+    }
+
+    /// Field that may be missing or empty
+    pub const fn optional(name: &'static str, kind: FieldKind) -> Self {
+        Self {
+            name,
+            kind,
+            required: false,
+        }
+    }
+}
+
+const SIGNATURE: FieldDescriptor = FieldDescriptor::optional("signature", FieldKind::Signature);
+
+/// Field layout of one command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandSchema {
+    /// Command described
+    pub command: CommandCode,
+
+    /// Fixed fields, in order
+    pub fields: Vec<FieldDescriptor>,
+
+    /// Layout of every field after the fixed ones (bulk commands)
+    pub repeated: Option<FieldDescriptor>,
+}
+
+impl CommandSchema {
+    /// Schema with the given fixed fields
+    pub fn new(command: CommandCode, fields: impl IntoIterator<Item = FieldDescriptor>) -> Self {
+        Self {
+            command,
+            fields: fields.into_iter().collect(),
+            repeated: None,
+        }
+    }
+
+    /// Describe every field after the fixed ones with `descriptor`
+    pub fn with_repeated(mut self, descriptor: FieldDescriptor) -> Self {
+        self.repeated = Some(descriptor);
+        self
+    }
+
+    /// Descriptor of the field at `index`, if the schema covers it
+    pub fn descriptor(&self, index: usize) -> Option<&FieldDescriptor> {
+        self.fields.get(index).or(self.repeated.as_ref())
+    }
+}
+
+/// Field of a message with its schema annotation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnnotatedField {
+    /// Position in the message (0-based)
+    pub index: usize,
+
+    /// Field name, or `field_<index>` when the schema does not cover it
+    pub name: String,
+
+    /// Semantic type, if known
+    pub kind: Option<FieldKind>,
+
+    /// Raw value
+    pub value: String,
+
+    /// Meaning of a coded value (e.g. `Entry` for direction `1`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meaning: Option<String>,
+
+    /// Why the value does not fit its kind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Message with every field labeled by its schema
+///
+/// Serializes to JSON for inspection tools and logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnnotatedMessage {
+    /// Device that sent or receives the message
+    pub device_id: DeviceId,
+
+    /// Command
+    pub command: CommandCode,
+
+    /// Wire code of the command (e.g. `000+0`)
+    pub code: &'static str,
+
+    /// Labeled fields, in order
+    pub fields: Vec<AnnotatedField>,
+
+    /// Required fields the message lacks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<&'static str>,
+}
+
+impl AnnotatedMessage {
+    /// Whether every field fits its schema and no required field is missing
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.fields.iter().all(|field| field.error.is_none())
+    }
+
+    /// Get a field by name
+    pub fn field(&self, name: &str) -> Option<&AnnotatedField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// Registry of command schemas, keyed by command code
+#[derive(Debug, Clone)]
+pub struct SchemaRegistry {
+    schemas: HashMap<CommandCode, CommandSchema>,
+}
+
+impl SchemaRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self {
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Shared registry with the schemas of every built-in command
+    pub fn builtin() -> &'static Self {
+        static BUILTIN: OnceLock<SchemaRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(Self::with_builtin)
+    }
+
+    /// Owned copy of the built-in schemas, to extend or override
+    pub fn with_builtin() -> Self {
+        use FieldDescriptor as F;
+        use FieldKind as K;
+
+        let mut registry = Self::new();
+
+        registry.register(CommandSchema::new(
+            CommandCode::AccessRequest,
+            [
+                F::required("card_number", K::CardNumber),
+                F::required("timestamp", K::Timestamp),
+                F::required("direction", K::Direction),
+                F::required("reader_type", K::ReaderType),
+                F::optional("correlation_id", K::CorrelationId),
+            ],
+        ));
+
+        for command in [
+            CommandCode::GrantBoth,
+            CommandCode::GrantManual,
+            CommandCode::GrantEntry,
+            CommandCode::GrantExit,
+            CommandCode::DenyAccess,
+            CommandCode::DisplayOnly,
+        ] {
+            registry.register(CommandSchema::new(
+                command,
+                [
+                    F::required("timeout_seconds", K::Seconds),
+                    F::optional("display_message", K::DisplayMessage),
+                ],
+            ));
+        }
+
+        registry.register(CommandSchema::new(
+            CommandCode::OverrideGrant,
+            [
+                F::required("card_number", K::CardNumber),
+                F::required("operator_id", K::Text),
+                F::required("reason", K::Text),
+                F::required("timeout_seconds", K::Seconds),
+            ],
+        ));
+
+        for command in [
+            CommandCode::WaitingRotation,
+            CommandCode::RotationCompleted,
+            CommandCode::RotationTimeout,
+        ] {
+            registry.register(CommandSchema::new(
+                command,
+                [
+                    F::optional("card_number", K::CardNumber),
+                    F::required("timestamp", K::Timestamp),
+                    F::required("direction", K::Direction),
+                    F::required("reader_type", K::ReaderType),
+                    F::optional("nsr", K::Integer),
+                ],
+            ));
+        }
+
+        // Empty for a query, filled in a report
+        registry.register(CommandSchema::new(
+            CommandCode::QueryStatus,
+            [
+                F::optional("validation_mode", K::ValidationMode),
+                F::optional("granted", K::Integer),
+                F::optional("denied", K::Integer),
+                F::optional("rotations", K::Integer),
+                F::optional("timeouts", K::Integer),
+                F::optional("pending_events", K::Integer),
+                F::optional("entries", K::Integer),
+                F::optional("exits", K::Integer),
+                F::optional("errors", K::Integer),
+                F::optional("last_entry", K::Text),
+                F::optional("last_exit", K::Text),
+                F::optional("last_error", K::Text),
+            ],
+        ));
+
+        // Q with a cursor for a query, D with a header and events for a chunk
+        registry.register(
+            CommandSchema::new(
+                CommandCode::ReceiveLogs,
+                [F::required("operation", K::Text)],
+            )
+            .with_repeated(F::optional("entry", K::Text)),
+        );
+
+        // Empty for a request, one triple per check in a report
+        registry.register(
+            CommandSchema::new(CommandCode::SelfTest, [])
+                .with_repeated(F::optional("check", K::Text)),
+        );
+
+        // Optional peripheral for a request, seven fields per peripheral in a report
+        registry.register(
+            CommandSchema::new(CommandCode::Diagnostics, [])
+                .with_repeated(F::optional("diagnostic", K::Text)),
+        );
+
+        // A lockout end of `-` reads as text, not as a timestamp
+        registry.register(CommandSchema::new(
+            CommandCode::AlarmReport,
+            [
+                F::required("kind", K::Integer),
+                F::required("card_number", K::CardNumber),
+                F::required("count", K::Integer),
+                F::required("window_seconds", K::Integer),
+                F::required("timestamp", K::Timestamp),
+                F::required("locked_until", K::Text),
+            ],
+        ));
+
+        registry.register(
+            CommandSchema::new(
+                CommandCode::SendCards,
+                [
+                    F::required("status", K::Integer),
+                    F::required("quantity", K::Integer),
+                    F::required("operation", K::Text),
+                ],
+            )
+            .with_repeated(F::optional("card_number", K::CardNumber)),
+        );
+
+        for command in [CommandCode::SendConfig, CommandCode::ReceiveConfig] {
+            registry.register(
+                CommandSchema::new(command, [F::optional("status", K::Integer)])
+                    .with_repeated(F::optional("parameter", K::Text)),
+            );
+        }
+
+        for command in [
+            CommandCode::SendUsers,
+            CommandCode::SendBiometrics,
+            CommandCode::SendDateTime,
+        ] {
+            registry.register(
+                CommandSchema::new(command, [F::optional("status", K::Integer)])
+                    .with_repeated(F::optional("entry", K::Text)),
+            );
+        }
+
+        registry
+    }
+
+    /// Add or replace the schema of a command
+    ///
+    /// Returns the schema previously registered for the command.
+    pub fn register(&mut self, schema: CommandSchema) -> Option<CommandSchema> {
+        self.schemas.insert(schema.command, schema)
+    }
+
+    /// Get the schema of a command
+    pub fn get(&self, command: CommandCode) -> Option<&CommandSchema> {
+        self.schemas.get(&command)
+    }
+
+    /// Label every field of a message
+    ///
+    /// Never fails: fields that do not fit their kind carry an `error`,
+    /// and fields beyond the schema are named `field_<index>`.
+    pub fn annotate(&self, message: &Message) -> AnnotatedMessage {
+        let schema = self.get(message.command);
+        let signed = MessageSigner::is_signed(message);
+        let last = message.fields.len().saturating_sub(1);
+
+        let fields: Vec<AnnotatedField> = message
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let value = field.as_str();
+                let descriptor = if signed && index == last {
+                    Some(&SIGNATURE)
+                } else {
+                    schema.and_then(|schema| schema.descriptor(index))
+                };
+
+                let (meaning, error) = match descriptor {
+                    Some(descriptor) if !value.is_empty() => match descriptor.kind.check(value) {
+                        Ok(meaning) => (meaning, None),
+                        Err(e) => (None, Some(e.to_string())),
+                    },
+                    Some(descriptor) if descriptor.required => {
+                        (None, Some(format!("{} is required", descriptor.name)))
+                    }
+                    _ => (None, None),
+                };
+
+                AnnotatedField {
+                    index,
+                    name: descriptor
+                        .map(|descriptor| descriptor.name.to_string())
+                        .unwrap_or_else(|| format!("field_{}", index)),
+                    kind: descriptor.map(|descriptor| descriptor.kind),
+                    value: value.to_string(),
+                    meaning,
+                    error,
+                }
+            })
+            .collect();
+
+        let present = message.fields.len() - usize::from(signed);
+        let missing = schema
+            .map(|schema| {
+                schema
+                    .fields
+                    .iter()
+                    .skip(present)
+                    .filter(|descriptor| descriptor.required)
+                    .map(|descriptor| descriptor.name)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        AnnotatedMessage {
+            device_id: message.device_id,
+            command: message.command,
+            code: message.command.as_str(),
+            fields,
+            missing,
+        }
+    }
+
+    /// Check every field of a message against its schema
+    ///
+    /// # Errors
+    ///
+    /// Returns `MissingField` for the first required field the message
+    /// lacks, or `InvalidFieldFormat` naming the first field that does not
+    /// fit its kind.
+    pub fn validate(&self, message: &Message) -> Result<()> {
+        let annotated = self.annotate(message);
+
+        if let Some(name) = annotated.missing.first() {
+            return Err(Error::MissingField(format!(
+                "{:?} requires field '{}'",
+                message.command, name
+            )));
+        }
+
+        match annotated.fields.iter().find(|field| field.error.is_some()) {
+            Some(field) => Err(Error::InvalidFieldFormat {
+                message: format!(
+                    "{:?} field '{}' ({}): {}",
+                    message.command,
+                    field.name,
+                    field.index,
+                    field.error.as_deref().unwrap_or_default()
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for SchemaRegistry {
+    fn default() -> Self {
+        Self::with_builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MessageParser;
+
+    #[test]
+    fn test_annotate_grant_and_status() {
+        let registry = SchemaRegistry::builtin();
+
+        let grant = MessageParser::parse("15+REON+00+6]5]Acesso liberado]").unwrap();
+        let annotated = registry.annotate(&grant);
+        assert!(annotated.is_valid());
+        assert_eq!(annotated.code, "00+6");
+        assert_eq!(annotated.fields[0].name, "timeout_seconds");
+        assert_eq!(annotated.fields[0].meaning.as_deref(), Some("5s"));
+        assert_eq!(
+            annotated.field("display_message").unwrap().kind,
+            Some(FieldKind::DisplayMessage)
+        );
+
+        let report = MessageParser::parse("15+REON+RQ]A]120]4]118]2]0]").unwrap();
+        let annotated = registry.annotate(&report);
+        assert_eq!(
+            annotated
+                .field("validation_mode")
+                .unwrap()
+                .meaning
+                .as_deref(),
+            Some("Automatic")
+        );
+        assert!(registry.validate(&report).is_ok());
+    }
+
+    #[test]
+    fn test_annotate_reports_bad_and_missing_fields() {
+        let registry = SchemaRegistry::builtin();
+
+        let request =
+            MessageParser::parse("15+REON+000+0]12345678]10/05/2025 12:46:06]7]").unwrap();
+        let annotated = registry.annotate(&request);
+        assert!(!annotated.is_valid());
+        assert!(annotated.fields[2].error.is_some());
+        assert_eq!(annotated.missing, vec!["reader_type"]);
+
+        let err = registry.validate(&request).unwrap_err();
+        assert!(err.to_string().contains("reader_type"));
+
+        let status = MessageParser::parse("15+REON+000+80]]10/05/2025 12:46:06]0]1]").unwrap();
+        assert!(registry.validate(&status).is_ok());
+        let status = MessageParser::parse("15+REON+000+80]12345678]]0]1]").unwrap();
+        let err = registry.validate(&status).unwrap_err();
+        assert!(err.to_string().contains("timestamp"));
+    }
+
+    #[test]
+    fn test_annotate_signature_and_repeated_fields() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut request =
+            MessageParser::parse("15+REON+000+0]12345678]10/05/2025 12:46:06]1]1]").unwrap();
+        signer.sign(&mut request).unwrap();
+
+        let annotated = SchemaRegistry::builtin().annotate(&request);
+        assert!(annotated.is_valid());
+        assert_eq!(annotated.fields[4].name, "signature");
+
+        let cards = MessageParser::parse("15+REON+ECAR]00]2]E]1111111111]2222222222]").unwrap();
+        let annotated = SchemaRegistry::builtin().annotate(&cards);
+        assert_eq!(annotated.fields[4].name, "card_number");
+        assert_eq!(annotated.fields[4].value, "2222222222");
+    }
+
+    #[test]
+    fn test_register_overrides_builtin() {
+        let mut registry = SchemaRegistry::with_builtin();
+        let previous = registry.register(CommandSchema::new(
+            CommandCode::SendDateTime,
+            [FieldDescriptor::required("date_time", FieldKind::Timestamp)],
+        ));
+        assert!(previous.is_some());
+
+        let message = MessageParser::parse("15+REON+EH]10/05/2025 12:46:06]extra]").unwrap();
+        let annotated = registry.annotate(&message);
+        assert_eq!(annotated.fields[0].name, "date_time");
+        assert_eq!(annotated.fields[1].name, "field_1");
+        assert_eq!(annotated.fields[1].kind, None);
+        assert!(annotated.is_valid());
+    }
+}
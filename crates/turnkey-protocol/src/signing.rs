@@ -0,0 +1,588 @@
+//! HMAC message signing for device authentication.
+//!
+//! The Henry protocol has no notion of device identity beyond the two-digit
+//! device ID, so any host on the LAN can impersonate a turnstile. This module
+//! adds an optional HMAC-SHA256 signature over the message payload, keyed by a
+//! secret shared between one device and the server.
+//!
+//! # Wire Format
+//!
+//! The MAC travels as an extra trailing data field, so unsigned peers and
+//! existing parsers keep working:
+//!
+//! ```text
+//! 15+REON+000+0]12345678]10/05/2025 12:46:06]1]0]MAC=<counter>:<time>:<64 hex digits>]
+//!                                                ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ Signature field
+//! ```
+//!
+//! The signed payload is the message without the signature field, in its
+//! canonical form `<ID>+REON+<COMMAND>]<FIELD>]...`, followed by the stamp
+//! `]<counter>:<time>`. Checksum metadata is not covered.
+//!
+//! # Replay Protection
+//!
+//! Every signature carries a [`SignatureStamp`]: a counter that grows with
+//! each message a signer sends, and the Unix time of signing. Counters start
+//! from the current time in milliseconds, so a device that restarts keeps
+//! counting above the values it used before. A [`ReplayGuard`] on the
+//! receiving side rejects stamps outside [`MAX_SIGNATURE_AGE`] of its own
+//! clock and counters it has already accepted, so a captured message cannot
+//! be sent again on this or any later connection.
+//!
+//! # Negotiation
+//!
+//! Signing is negotiated per connection: a device that signs its first
+//! message is expected to sign every following message. See `TcpServer`
+//! in `turnkey-network` for the server-side rules.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_protocol::{CommandCode, FieldData, Message, MessageSigner};
+//! use turnkey_core::DeviceId;
+//!
+//! let signer = MessageSigner::new(b"shared-secret".to_vec());
+//! let mut message = Message::new(
+//!     DeviceId::new(15).unwrap(),
+//!     CommandCode::AccessRequest,
+//!     vec![FieldData::new("12345678".to_string()).unwrap()],
+//! )
+//! .unwrap();
+//!
+//! signer.sign(&mut message).unwrap();
+//! assert!(MessageSigner::is_signed(&message));
+//! assert!(signer.verify(&message).is_ok());
+//! ```
+
+use crate::field::FieldData;
+use crate::message::Message;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use turnkey_core::secrets::SecretBytes;
+use turnkey_core::{Error, Result, constants::PROTOCOL_ID};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Prefix identifying the signature field
+pub const MAC_FIELD_PREFIX: &str = "MAC=";
+
+/// Length of the hex-encoded HMAC-SHA256 signature
+pub const MAC_HEX_LENGTH: usize = 64;
+
+/// How far a signature time may be from the receiver's clock
+pub const MAX_SIGNATURE_AGE: Duration = Duration::from_secs(60);
+
+/// Counter and time covered by a signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureStamp {
+    /// Grows with every message signed by the sender
+    pub counter: u64,
+    /// Unix time of signing, in seconds
+    pub timestamp: i64,
+}
+
+impl SignatureStamp {
+    /// Parse the `<counter>:<time>` form
+    fn parse(value: &str) -> Option<Self> {
+        let (counter, timestamp) = value.split_once(':')?;
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if !digits(counter) || !digits(timestamp) {
+            return None;
+        }
+        Some(Self {
+            counter: counter.parse().ok()?,
+            timestamp: timestamp.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for SignatureStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.counter, self.timestamp)
+    }
+}
+
+/// Signs and verifies messages with a per-device shared secret
+///
+/// The key is never printed; `Debug` output only shows its length. It is
+/// zeroed when the last copy of the signer is dropped. Clones share the
+/// signing counter.
+#[derive(Clone)]
+pub struct MessageSigner {
+    key: SecretBytes,
+    /// Counter of the last message signed
+    counter: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for MessageSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageSigner")
+            .field("key_len", &self.key.len())
+            .finish()
+    }
+}
+
+impl MessageSigner {
+    /// Create a signer from a shared secret
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self::from_secret(SecretBytes::new(key))
+    }
+
+    /// Create a signer from a key fetched from a secret provider
+    ///
+    /// See `turnkey_core::secrets::names::device_signing_key()`.
+    pub fn from_secret(key: SecretBytes) -> Self {
+        Self {
+            key,
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Compute the hex-encoded MAC of a message under a stamp
+    ///
+    /// Any signature field already present is ignored, so the result is the
+    /// same for a message before and after signing.
+    pub fn compute_mac(&self, message: &Message, stamp: SignatureStamp) -> String {
+        let mut mac = self.hmac();
+        mac.update(Self::canonical_payload(message, stamp).as_bytes());
+        Self::to_hex(&mac.finalize().into_bytes())
+    }
+
+    /// Append (or replace) the signature field of a message
+    ///
+    /// The message is stamped with the next counter and the current time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the signature field cannot be represented as
+    /// field data, which cannot happen for hex output.
+    pub fn sign(&self, message: &mut Message) -> Result<()> {
+        self.sign_with(message, self.next_stamp())
+    }
+
+    /// Append (or replace) the signature field of a message with a given stamp
+    ///
+    /// # Errors
+    ///
+    /// Same as [`sign`](Self::sign).
+    pub fn sign_with(&self, message: &mut Message, stamp: SignatureStamp) -> Result<()> {
+        let mac = self.compute_mac(message, stamp);
+        Self::strip_signature(message);
+        message.fields.push(FieldData::new(format!(
+            "{}{}:{}",
+            MAC_FIELD_PREFIX, stamp, mac
+        ))?);
+        Ok(())
+    }
+
+    /// Verify the signature field of a message
+    ///
+    /// The comparison is constant-time. Only the MAC is checked; pass the
+    /// returned stamp to a [`ReplayGuard`] to reject stale or replayed
+    /// messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidSignature` if the message is unsigned, the
+    /// signature is malformed, or it does not match.
+    pub fn verify(&self, message: &Message) -> Result<SignatureStamp> {
+        let signature = Self::signature(message).ok_or_else(|| Error::InvalidSignature {
+            reason: format!("message from device {} is not signed", message.device_id),
+        })?;
+
+        let malformed = || Error::InvalidSignature {
+            reason: format!("malformed signature from device {}", message.device_id),
+        };
+        let (stamp, hex) = Self::split_signature(signature).ok_or_else(malformed)?;
+        let expected = Self::from_hex(hex).ok_or_else(malformed)?;
+
+        let mut mac = self.hmac();
+        mac.update(Self::canonical_payload(message, stamp).as_bytes());
+        mac.verify_slice(&expected)
+            .map_err(|_| Error::InvalidSignature {
+                reason: format!("signature mismatch for device {}", message.device_id),
+            })?;
+        Ok(stamp)
+    }
+
+    /// Verify a message and remove its signature field
+    ///
+    /// Convenience for receivers that hand the message to code unaware of
+    /// signing.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`verify`](Self::verify). The message is left untouched on error.
+    pub fn verify_and_strip(&self, message: &mut Message) -> Result<SignatureStamp> {
+        let stamp = self.verify(message)?;
+        Self::strip_signature(message);
+        Ok(stamp)
+    }
+
+    /// Check whether a message carries a signature field
+    pub fn is_signed(message: &Message) -> bool {
+        Self::signature(message).is_some()
+    }
+
+    /// Signature carried by a message (`<counter>:<time>:<hex>`), if any
+    ///
+    /// Only the last field is considered.
+    pub fn signature(message: &Message) -> Option<&str> {
+        message
+            .fields
+            .last()
+            .and_then(|field| field.as_str().strip_prefix(MAC_FIELD_PREFIX))
+    }
+
+    /// Split a signature into its stamp and hex MAC
+    ///
+    /// Returns `None` unless the value has the `<counter>:<time>:<hex>` form
+    /// with a MAC of [`MAC_HEX_LENGTH`] hex digits.
+    pub fn split_signature(signature: &str) -> Option<(SignatureStamp, &str)> {
+        let (stamp, hex) = signature.rsplit_once(':')?;
+        if hex.len() != MAC_HEX_LENGTH || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some((SignatureStamp::parse(stamp)?, hex))
+    }
+
+    /// Remove the signature field, returning its value
+    pub fn strip_signature(message: &mut Message) -> Option<String> {
+        let signature = Self::signature(message)?.to_string();
+        message.fields.pop();
+        Some(signature)
+    }
+
+    fn hmac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(self.key.expose_secret())
+            .expect("HMAC accepts keys of any length")
+    }
+
+    /// Stamp for the next message: above the last counter and the current time
+    fn next_stamp(&self) -> SignatureStamp {
+        let now = Utc::now();
+        let floor = u64::try_from(now.timestamp_millis()).unwrap_or(0);
+        let next = |last: u64| (last + 1).max(floor);
+        let last = self
+            .counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(next(last)))
+            .unwrap_or_else(|last| last);
+        SignatureStamp {
+            counter: next(last),
+            timestamp: now.timestamp(),
+        }
+    }
+
+    /// Canonical signed form: `<ID>+REON+<COMMAND>]<FIELD>]...]<counter>:<time>`
+    fn canonical_payload(message: &Message, stamp: SignatureStamp) -> String {
+        let mut fields = message.fields.as_slice();
+        if Self::signature(message).is_some() {
+            fields = &fields[..fields.len() - 1];
+        }
+
+        let mut payload = format!(
+            "{}+{}+{}",
+            message.device_id,
+            PROTOCOL_ID,
+            message.command.as_str()
+        );
+        for field in fields {
+            payload.push(']');
+            payload.push_str(field.as_str());
+        }
+        payload.push(']');
+        payload.push_str(&stamp.to_string());
+        payload
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    fn from_hex(hex: &str) -> Option<Vec<u8>> {
+        if hex.len() != MAC_HEX_LENGTH || !hex.is_ascii() {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect()
+    }
+}
+
+/// Rejects signed messages that are stale or were already accepted
+///
+/// Clones share the last accepted counter, so a guard kept per device covers
+/// every connection of that device, including ones opened later. The
+/// counter lives in memory: after a restart only the time window applies.
+#[derive(Debug, Clone)]
+pub struct ReplayGuard {
+    /// Highest counter accepted so far
+    last: Arc<AtomicU64>,
+    max_age: Duration,
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new(MAX_SIGNATURE_AGE)
+    }
+}
+
+impl ReplayGuard {
+    /// Create a guard accepting signature times within `max_age` of now
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            last: Arc::new(AtomicU64::new(0)),
+            max_age,
+        }
+    }
+
+    /// Accept a verified stamp, recording its counter
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidSignature` if the stamp is outside the time
+    /// window or its counter is not above every counter accepted before.
+    pub fn check(&self, stamp: SignatureStamp) -> Result<()> {
+        self.check_at(stamp, Utc::now().timestamp())
+    }
+
+    /// Same as [`check`](Self::check) against a given Unix time
+    pub fn check_at(&self, stamp: SignatureStamp, now: i64) -> Result<()> {
+        let skew = stamp.timestamp.abs_diff(now);
+        if skew > self.max_age.as_secs() {
+            return Err(Error::InvalidSignature {
+                reason: format!("stale signature, signed {}s away from now", skew),
+            });
+        }
+
+        self.last
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                (stamp.counter > last).then_some(stamp.counter)
+            })
+            .map(|_| ())
+            .map_err(|last| Error::InvalidSignature {
+                reason: format!(
+                    "replayed signature, counter {} not above {}",
+                    stamp.counter, last
+                ),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandCode;
+    use turnkey_core::DeviceId;
+
+    fn access_request() -> Message {
+        Message::new(
+            DeviceId::new(15).unwrap(),
+            CommandCode::AccessRequest,
+            vec![
+                FieldData::new("12345678".to_string()).unwrap(),
+                FieldData::new("10/05/2025 12:46:06".to_string()).unwrap(),
+                FieldData::new("1".to_string()).unwrap(),
+                FieldData::new("0".to_string()).unwrap(),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sign_appends_mac_field() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+
+        signer.sign(&mut message).unwrap();
+
+        assert_eq!(message.fields.len(), 5);
+        let signature = MessageSigner::signature(&message).unwrap();
+        let (stamp, hex) = MessageSigner::split_signature(signature).unwrap();
+        assert_eq!(hex.len(), MAC_HEX_LENGTH);
+        assert!((stamp.timestamp - Utc::now().timestamp()).abs() <= 1);
+    }
+
+    #[test]
+    fn test_known_vector() {
+        // HMAC-SHA256("key", "15+REON+RQ]1:1700000000")
+        let signer = MessageSigner::new(b"key".to_vec());
+        let message =
+            Message::new(DeviceId::new(15).unwrap(), CommandCode::QueryStatus, vec![]).unwrap();
+        let stamp = SignatureStamp {
+            counter: 1,
+            timestamp: 1_700_000_000,
+        };
+
+        let mut mac = HmacSha256::new_from_slice(b"key").unwrap();
+        mac.update(b"15+REON+RQ]1:1700000000");
+        let expected = MessageSigner::to_hex(&mac.finalize().into_bytes());
+
+        assert_eq!(signer.compute_mac(&message, stamp), expected);
+    }
+
+    #[test]
+    fn test_sign_with_same_stamp_is_idempotent() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+        let stamp = SignatureStamp {
+            counter: 7,
+            timestamp: 1_700_000_000,
+        };
+
+        signer.sign_with(&mut message, stamp).unwrap();
+        let first = MessageSigner::signature(&message).unwrap().to_string();
+        signer.sign_with(&mut message, stamp).unwrap();
+
+        assert_eq!(message.fields.len(), 5);
+        assert_eq!(MessageSigner::signature(&message).unwrap(), first);
+    }
+
+    #[test]
+    fn test_counter_grows_across_messages_and_clones() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let clone = signer.clone();
+        let mut message = access_request();
+
+        let mut counters = Vec::new();
+        for signer in [&signer, &clone, &signer] {
+            signer.sign(&mut message).unwrap();
+            counters.push(signer.verify(&message).unwrap().counter);
+        }
+
+        assert!(counters.windows(2).all(|pair| pair[0] < pair[1]));
+        // Seeded from the clock, so a restarted device keeps counting upwards
+        assert!(counters[0] >= 1_700_000_000_000);
+    }
+
+    #[test]
+    fn test_verify_roundtrip_through_wire_format() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+        signer.sign(&mut message).unwrap();
+
+        let wire = crate::format_message(&message);
+        let parsed = crate::MessageParser::parse(&wire).unwrap();
+
+        assert!(signer.verify(&parsed).is_ok());
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_field() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+        signer.sign(&mut message).unwrap();
+
+        message.fields[0] = FieldData::new("87654321".to_string()).unwrap();
+
+        assert!(matches!(
+            signer.verify(&message),
+            Err(Error::InvalidSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_other_device_id() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+        signer.sign(&mut message).unwrap();
+
+        message.device_id = DeviceId::new(16).unwrap();
+
+        assert!(signer.verify(&message).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_stamp() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+        signer.sign(&mut message).unwrap();
+
+        let signature = MessageSigner::strip_signature(&mut message).unwrap();
+        let (stamp, hex) = MessageSigner::split_signature(&signature).unwrap();
+        let forged = format!(
+            "{}{}:{}:{}",
+            MAC_FIELD_PREFIX,
+            stamp.counter + 1,
+            stamp.timestamp,
+            hex
+        );
+        message.fields.push(FieldData::new(forged).unwrap());
+
+        assert!(signer.verify(&message).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_key() {
+        let mut message = access_request();
+        MessageSigner::new(b"secret".to_vec())
+            .sign(&mut message)
+            .unwrap();
+
+        assert!(
+            MessageSigner::new(b"other".to_vec())
+                .verify(&message)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_unsigned_and_malformed() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let mut message = access_request();
+        assert!(signer.verify(&message).is_err());
+
+        message
+            .fields
+            .push(FieldData::new("MAC=XYZ".to_string()).unwrap());
+        assert!(signer.verify(&message).is_err());
+    }
+
+    #[test]
+    fn test_verify_and_strip() {
+        let signer = MessageSigner::new(b"secret".to_vec());
+        let original = access_request();
+        let mut message = original.clone();
+        signer.sign(&mut message).unwrap();
+
+        signer.verify_and_strip(&mut message).unwrap();
+
+        assert!(!MessageSigner::is_signed(&message));
+        assert_eq!(message.fields, original.fields);
+    }
+
+    #[test]
+    fn test_replay_guard_rejects_stale_and_replayed_stamps() {
+        let guard = ReplayGuard::new(Duration::from_secs(60));
+        let other_connection = guard.clone();
+        let now = 1_700_000_000;
+        let stamp = |counter, timestamp| SignatureStamp { counter, timestamp };
+
+        assert!(guard.check_at(stamp(10, now - 5), now).is_ok());
+        // Same message again, on any connection sharing the guard
+        assert!(guard.check_at(stamp(10, now - 5), now).is_err());
+        assert!(other_connection.check_at(stamp(10, now), now).is_err());
+        assert!(other_connection.check_at(stamp(9, now), now).is_err());
+        assert!(other_connection.check_at(stamp(11, now), now).is_ok());
+
+        // Outside the window either way, even with a fresh counter
+        assert!(guard.check_at(stamp(20, now - 61), now).is_err());
+        assert!(guard.check_at(stamp(21, now + 61), now).is_err());
+        assert!(guard.check_at(stamp(22, now + 60), now).is_ok());
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        let signer = MessageSigner::new(b"top-secret".to_vec());
+        let debug = format!("{:?}", signer);
+
+        assert!(!debug.contains("top-secret"));
+        assert!(debug.contains("key_len"));
+    }
+}
@@ -34,13 +34,21 @@
 //! }
 //! ```
 //!
+//! # Fragmentation
+//!
+//! Each call to [`StreamParser::feed`] scans only the bytes it is given, so
+//! the cost of a stream does not depend on how TCP split it. Bytes of a
+//! frame that arrives whole in one chunk are copied once, straight into the
+//! [`Frame`]; only frames spanning several chunks are assembled in a reused
+//! payload buffer. See `benches/stream_parser_bench.rs` for throughput under
+//! byte-by-byte, split and coalesced delivery.
+//!
 //! # ASCII Encoding
 //!
 //! The Henry protocol uses ASCII encoding (7-bit, 0x00-0x7F). All characters
 //! in the protocol messages must be valid ASCII. Bytes outside this range
 //! indicate protocol violation or data corruption.
 
-use bytes::BytesMut;
 use std::collections::VecDeque;
 use turnkey_core::constants::{END_BYTE, START_BYTE};
 
@@ -48,15 +56,11 @@ use crate::frame::Frame;
 
 /// Maximum buffer size to prevent memory exhaustion from malformed streams.
 ///
-/// If buffer grows beyond this size without finding a complete frame,
-/// it indicates either a very large message or a protocol violation.
+/// If a payload grows beyond this size without finding ETX, it indicates
+/// either a very large message or a protocol violation, and the partial
+/// frame is discarded.
 const MAX_BUFFER_SIZE: usize = 64 * 1024; // 64 KB
 
-/// Initial buffer capacity for incoming TCP data.
-///
-/// This value is optimized for typical TCP packet sizes.
-const INITIAL_BUFFER_CAPACITY: usize = 4 * 1024; // 4 KB
-
 /// Initial payload capacity for frame assembly.
 ///
 /// This value accommodates most Henry protocol messages without reallocation.
@@ -100,7 +104,7 @@ pub enum ParserState {
 /// │WaitingStart │─────────────>│ReadingPayload │────────────>│Frame ready  │
 /// └─────────────┘              └───────────────┘             └─────────────┘
 ///       ^  │                          │                              │
-///       │  │ Non-STX bytes            │ Buffer > MAX_SIZE            │
+///       │  │ Non-STX bytes            │ Payload > MAX_SIZE           │
 ///       │  │ (discarded)              │ (reset to prevent DoS)       │
 ///       │  └──────────────────────────┘                              │
 ///       │                                                            │
@@ -110,7 +114,7 @@ pub enum ParserState {
 /// State transitions:
 /// - WaitingStart → ReadingPayload: When STX (0x02) byte is found
 /// - ReadingPayload → WaitingStart: When ETX (0x03) byte is found and frame is extracted
-/// - ReadingPayload → WaitingStart: When buffer exceeds MAX_BUFFER_SIZE (DoS protection)
+/// - ReadingPayload → WaitingStart: When payload exceeds MAX_BUFFER_SIZE (DoS protection)
 /// - WaitingStart → WaitingStart: When non-STX bytes are encountered (garbage discarded)
 /// ```
 ///
@@ -147,13 +151,12 @@ pub enum ParserState {
 /// ```
 #[derive(Debug)]
 pub struct StreamParser {
-    /// Internal buffer for accumulating incoming bytes.
-    buffer: BytesMut,
-
     /// Current state of the parser state machine.
     state: ParserState,
 
-    /// Temporary buffer for current frame payload (between STX and ETX).
+    /// Partial payload of a frame spanning several `feed()` calls.
+    ///
+    /// Cleared, never shrunk, between frames so its capacity is reused.
     payload: Vec<u8>,
 
     /// Queue of complete frames ready for extraction.
@@ -164,7 +167,6 @@ impl StreamParser {
     /// Create a new stream parser with optimized initial capacity.
     ///
     /// Preallocates buffers to avoid reallocation during typical usage:
-    /// - 4 KB for incoming TCP data (standard packet size)
     /// - 1 KB for frame payload assembly
     /// - 4 slots for frame queue (handles burst traffic)
     ///
@@ -177,7 +179,6 @@ impl StreamParser {
     /// ```
     pub fn new() -> Self {
         Self {
-            buffer: BytesMut::with_capacity(INITIAL_BUFFER_CAPACITY),
             state: ParserState::WaitingStart,
             payload: Vec::with_capacity(INITIAL_PAYLOAD_CAPACITY),
             frames: VecDeque::with_capacity(INITIAL_FRAME_QUEUE_CAPACITY),
@@ -186,10 +187,10 @@ impl StreamParser {
 
     /// Feed bytes from TCP stream into the parser.
     ///
-    /// This method appends new bytes to the internal buffer and attempts
-    /// to extract complete frames using the state machine. Multiple frames
-    /// may be extracted from a single `feed()` call if the data contains
-    /// multiple complete messages.
+    /// This method runs the state machine over the new bytes and queues
+    /// every frame they complete. Multiple frames may be extracted from a
+    /// single `feed()` call if the data contains multiple complete messages;
+    /// bytes of an unfinished frame are kept for the next call.
     ///
     /// # Arguments
     ///
@@ -210,11 +211,12 @@ impl StreamParser {
     /// assert!(parser.next_frame().is_some());
     /// ```
     pub fn feed(&mut self, bytes: &[u8]) {
-        self.buffer.extend_from_slice(bytes);
-
-        // Try to extract frames from buffer
-        while self.try_extract_frame() {
-            // Continue extracting frames while possible
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            rest = match self.state {
+                ParserState::WaitingStart => self.handle_waiting_start(rest),
+                ParserState::ReadingPayload => self.handle_reading_payload(rest),
+            };
         }
     }
 
@@ -282,7 +284,6 @@ impl StreamParser {
     ///
     /// This method is useful for error recovery or when resetting the
     /// connection. It performs a complete cleanup:
-    /// - Discards all buffered bytes
     /// - Clears accumulated payload
     /// - Removes all queued frames
     /// - Resets state machine to initial state
@@ -300,7 +301,6 @@ impl StreamParser {
     /// assert_eq!(parser.frames_available(), 0);
     /// ```
     pub fn clear(&mut self) {
-        self.discard_buffer();
         self.payload.clear();
         self.frames.clear();
         self.state = ParserState::WaitingStart;
@@ -334,63 +334,20 @@ impl StreamParser {
         DrainFrames { parser: self }
     }
 
-    /// Try to extract one complete frame from the buffer.
-    ///
-    /// Returns `true` if a frame was extracted, `false` otherwise.
-    /// This method implements the state machine logic for frame extraction.
-    fn try_extract_frame(&mut self) -> bool {
-        // Check buffer size limit
-        if self.buffer.len() > MAX_BUFFER_SIZE {
-            // Buffer too large, discard everything and reset
-            self.clear();
-            return false;
-        }
-
-        loop {
-            match self.state {
-                ParserState::WaitingStart => {
-                    if !self.handle_waiting_start() {
-                        return false;
-                    }
-                }
-                ParserState::ReadingPayload => {
-                    return self.handle_reading_payload();
-                }
-            }
-        }
-    }
-
     /// Handle WaitingStart state: look for STX byte.
     ///
-    /// Returns `true` if STX was found and state transitioned, `false` otherwise.
-    fn handle_waiting_start(&mut self) -> bool {
-        if let Some(stx_pos) = self.buffer.iter().position(|&b| b == START_BYTE) {
-            self.discard_garbage_before_stx(stx_pos);
-            self.consume_stx_byte();
-            self.transition_to_reading_payload();
-            true
-        } else {
-            self.discard_buffer();
-            false
+    /// Bytes before STX are garbage and dropped. Returns the bytes after
+    /// STX, or an empty slice if there is none.
+    fn handle_waiting_start<'a>(&mut self, bytes: &'a [u8]) -> &'a [u8] {
+        match memchr::memchr(START_BYTE, bytes) {
+            Some(stx_pos) => {
+                self.transition_to_reading_payload();
+                &bytes[stx_pos + 1..]
+            }
+            None => &[],
         }
     }
 
-    /// Discard garbage bytes that appear before STX marker.
-    ///
-    /// The Henry protocol only recognizes data between STX and ETX markers.
-    /// Any bytes before STX are considered noise and must be discarded.
-    fn discard_garbage_before_stx(&mut self, stx_pos: usize) {
-        let _ = self.buffer.split_to(stx_pos);
-    }
-
-    /// Consume the STX (Start of Text) byte from buffer.
-    ///
-    /// After locating the STX marker, this method removes it from
-    /// the buffer so subsequent bytes become part of the payload.
-    fn consume_stx_byte(&mut self) {
-        let _ = self.buffer.split_to(1);
-    }
-
     /// Transition state machine to ReadingPayload state.
     ///
     /// Prepares the parser to accumulate payload bytes by clearing
@@ -400,59 +357,54 @@ impl StreamParser {
         self.payload.clear();
     }
 
-    /// Discard all bytes from internal buffer.
-    ///
-    /// Used when no STX marker is found - all buffered bytes
-    /// are considered garbage and discarded.
-    fn discard_buffer(&mut self) {
-        self.buffer.clear();
-    }
-
     /// Handle ReadingPayload state: look for ETX byte and extract frame.
     ///
-    /// Returns `true` if frame was extracted, `false` if need more data.
-    fn handle_reading_payload(&mut self) -> bool {
-        if let Some(etx_pos) = self.buffer.iter().position(|&b| b == END_BYTE) {
-            self.extract_payload_from_buffer(etx_pos);
-
-            if self.is_valid_ascii_payload() {
-                self.enqueue_frame_from_payload();
+    /// Returns the bytes after ETX, or an empty slice if the frame is not
+    /// complete yet and the bytes were kept as partial payload.
+    fn handle_reading_payload<'a>(&mut self, bytes: &'a [u8]) -> &'a [u8] {
+        match memchr::memchr(END_BYTE, bytes) {
+            Some(etx_pos) => {
+                self.complete_frame(&bytes[..etx_pos]);
+                &bytes[etx_pos + 1..]
+            }
+            None => {
+                self.accumulate_payload(bytes);
+                &[]
             }
-            // Note: Non-ASCII frames are silently discarded as protocol violations
-
-            self.reset_for_next_frame();
-            true
-        } else {
-            self.accumulate_available_bytes();
-            false
         }
     }
 
-    /// Extract payload bytes from buffer until ETX marker.
+    /// Queue the frame ending with `tail` and wait for the next STX.
     ///
-    /// This method removes bytes from the buffer up to the ETX position,
-    /// appends them to the payload, and consumes the ETX byte itself.
-    fn extract_payload_from_buffer(&mut self, etx_pos: usize) {
-        let payload_bytes = self.buffer.split_to(etx_pos);
-        self.payload.extend_from_slice(&payload_bytes);
-        let _ = self.buffer.split_to(1); // Consume ETX byte
-    }
+    /// A frame received whole in one chunk is built straight from the
+    /// input; otherwise `tail` completes the buffered payload first.
+    /// Non-ASCII frames are silently discarded as protocol violations, and
+    /// so are frames larger than `MAX_BUFFER_SIZE`.
+    fn complete_frame(&mut self, tail: &[u8]) {
+        let payload = if self.payload.is_empty() {
+            tail
+        } else {
+            self.payload.extend_from_slice(tail);
+            &self.payload[..]
+        };
 
-    /// Validate that payload contains only ASCII bytes.
-    ///
-    /// Henry protocol requires ASCII encoding (7-bit, 0x00-0x7F).
-    /// Non-ASCII bytes indicate protocol violation or data corruption.
-    fn is_valid_ascii_payload(&self) -> bool {
-        self.payload.iter().all(|&b| b.is_ascii())
+        if payload.len() <= MAX_BUFFER_SIZE && payload.is_ascii() {
+            self.frames.push_back(Frame::from_bytes(payload, false));
+        }
+
+        self.reset_for_next_frame();
     }
 
-    /// Create frame from current payload and enqueue it.
+    /// Keep bytes of an unfinished frame until ETX arrives.
     ///
-    /// Constructs a Frame from the accumulated payload bytes and
-    /// adds it to the queue of frames ready for extraction.
-    fn enqueue_frame_from_payload(&mut self) {
-        let frame = Frame::from_bytes(&self.payload, false);
-        self.frames.push_back(frame);
+    /// Abandons the frame once it exceeds `MAX_BUFFER_SIZE`; its remaining
+    /// bytes are then skipped as garbage while looking for the next STX.
+    fn accumulate_payload(&mut self, bytes: &[u8]) {
+        if self.payload.len() + bytes.len() > MAX_BUFFER_SIZE {
+            self.reset_for_next_frame();
+        } else {
+            self.payload.extend_from_slice(bytes);
+        }
     }
 
     /// Reset parser state for next frame.
@@ -463,19 +415,6 @@ impl StreamParser {
         self.state = ParserState::WaitingStart;
         self.payload.clear();
     }
-
-    /// Accumulate available bytes into payload buffer.
-    ///
-    /// When ETX is not yet found, this method moves all available
-    /// bytes from the internal buffer to the payload buffer while
-    /// preserving buffer capacity for efficiency.
-    fn accumulate_available_bytes(&mut self) {
-        let available_len = self.buffer.len();
-        if available_len > 0 {
-            self.payload.extend_from_slice(&self.buffer[..]);
-            self.buffer.clear();
-        }
-    }
 }
 
 impl Default for StreamParser {
@@ -875,6 +814,91 @@ mod tests {
         assert_eq!(parser.frames_available(), 1);
     }
 
+    #[test]
+    fn test_oversized_payload_discarded_across_small_feeds() {
+        let mut parser = StreamParser::new();
+
+        // Payload grows past MAX_BUFFER_SIZE one small chunk at a time
+        parser.feed(&[0x02]);
+        let chunk = vec![b'X'; 512];
+        for _ in 0..(MAX_BUFFER_SIZE / chunk.len() + 1) {
+            parser.feed(&chunk);
+        }
+        assert_eq!(parser.state(), ParserState::WaitingStart);
+
+        // ETX of the abandoned frame must not produce a frame
+        parser.feed(&[b'X', 0x03]);
+        assert_eq!(parser.frames_available(), 0);
+
+        parser.feed(&make_frame(b"01+REON+RQ"));
+        assert_eq!(parser.frames_available(), 1);
+    }
+
+    #[test]
+    fn test_split_at_every_boundary() {
+        let data = make_frames(&[
+            b"15+REON+000+0]12345678]10/05/2025 12:46:06]1]0]",
+            b"02+REON+EC",
+        ]);
+
+        for split in 0..=data.len() {
+            let mut parser = StreamParser::new();
+            parser.feed(&data[..split]);
+            parser.feed(&data[split..]);
+
+            let frames: Vec<_> = parser
+                .drain_frames()
+                .map(|f| f.to_string().unwrap())
+                .collect();
+            assert_eq!(
+                frames,
+                vec![
+                    "15+REON+000+0]12345678]10/05/2025 12:46:06]1]0]",
+                    "02+REON+EC"
+                ],
+                "split at byte {}",
+                split
+            );
+            assert_eq!(parser.state(), ParserState::WaitingStart);
+        }
+    }
+
+    #[test]
+    fn test_three_way_splits() {
+        let data = make_frame(b"01+REON+RQ");
+
+        for first in 0..=data.len() {
+            for second in first..=data.len() {
+                let mut parser = StreamParser::new();
+                parser.feed(&data[..first]);
+                parser.feed(&data[first..second]);
+                parser.feed(&data[second..]);
+
+                let frame = parser.next_frame().unwrap();
+                assert_eq!(frame.to_string().unwrap(), "01+REON+RQ");
+                assert!(parser.next_frame().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_coalesced_batches_with_drifting_boundaries() {
+        let payloads: Vec<String> = (1..=40).map(|id| format!("{:02}+REON+RQ", id)).collect();
+        let refs: Vec<&[u8]> = payloads.iter().map(|p| p.as_bytes()).collect();
+        let data = make_frames(&refs);
+
+        // Segments of 2-3 frames plus a few bytes, never frame-aligned
+        for segment in [25, 29, 31, 37] {
+            let mut parser = StreamParser::new();
+            let mut frames = Vec::new();
+            for chunk in data.chunks(segment) {
+                parser.feed(chunk);
+                frames.extend(parser.drain_frames().map(|f| f.to_string().unwrap()));
+            }
+            assert_eq!(frames, payloads, "segment of {} bytes", segment);
+        }
+    }
+
     #[test]
     fn test_multiple_clear_calls() {
         let mut parser = StreamParser::new();
@@ -254,6 +254,27 @@ pub fn validate_card_number(card: &str) -> Result<&str> {
     Ok(card)
 }
 
+/// Validate the fields of a message against its command schema
+///
+/// Shorthand for [`SchemaRegistry::builtin().validate()`](crate::schema::SchemaRegistry::validate):
+/// checks field types (card numbers, timestamps, direction codes, ...) and
+/// that required fields are present.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_protocol::{MessageParser, validation::validate_message};
+///
+/// let grant = MessageParser::parse("15+REON+00+6]5]Acesso liberado]").unwrap();
+/// assert!(validate_message(&grant).is_ok());
+///
+/// let grant = MessageParser::parse("15+REON+00+6]five]Acesso liberado]").unwrap();
+/// assert!(validate_message(&grant).is_err());
+/// ```
+pub fn validate_message(message: &crate::Message) -> Result<()> {
+    crate::schema::SchemaRegistry::builtin().validate(message)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
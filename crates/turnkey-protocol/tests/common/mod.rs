@@ -281,13 +281,8 @@ pub fn create_turnstile_status(
 ///
 /// Panics if the message fields cannot be parsed as a valid AccessRequest.
 pub fn parse_access_request(message: &Message) -> AccessRequest {
-    let fields: Vec<String> = message
-        .fields
-        .iter()
-        .map(|f| f.as_str().to_string())
-        .collect();
-
-    AccessRequest::parse(&fields)
+    message
+        .as_access_request()
         .expect("Test helper: failed to parse AccessRequest from message fields")
 }
 
@@ -301,31 +296,15 @@ pub fn parse_access_request(message: &Message) -> AccessRequest {
 ///
 /// Returns the parsed access decision and response details.
 pub fn parse_access_response(message: &Message) -> (AccessDecision, u8, String) {
-    // Message command contains the decision
-    let decision = match message.command {
-        CommandCode::GrantBoth => AccessDecision::GrantBoth,
-        CommandCode::GrantEntry => AccessDecision::GrantEntry,
-        CommandCode::GrantExit => AccessDecision::GrantExit,
-        CommandCode::DenyAccess => AccessDecision::Deny,
-        _ => panic!("Not an access response command: {:?}", message.command),
-    };
-
-    assert!(
-        message.field_count() >= 2,
-        "Access response requires at least 2 fields"
-    );
-
-    let timeout = message
-        .field(0)
-        .expect("Test helper: access response missing timeout field (field 0)")
-        .parse::<u8>()
-        .expect("Test helper: failed to parse timeout as u8");
-    let msg = message
-        .field(1)
-        .expect("Test helper: access response missing message field (field 1)")
-        .to_string();
-
-    (decision, timeout, msg)
+    let response = message
+        .as_access_response()
+        .expect("Test helper: failed to parse AccessResponse from message");
+
+    (
+        response.decision(),
+        response.timeout_seconds(),
+        response.display_message().to_string(),
+    )
 }
 
 /// Parse a turnstile status message.
@@ -338,24 +317,9 @@ pub fn parse_access_response(message: &Message) -> (AccessDecision, u8, String)
 ///
 /// Returns the parsed turnstile status.
 pub fn parse_turnstile_status(message: &Message) -> TurnstileStatus {
-    let fields: Vec<String> = message
-        .fields
-        .iter()
-        .map(|f| f.as_str().to_string())
-        .collect();
-
-    match message.command {
-        CommandCode::WaitingRotation => TurnstileStatus::parse_waiting_rotation(&fields)
-            .expect("Test helper: failed to parse WaitingRotation status"),
-        CommandCode::RotationCompleted => TurnstileStatus::parse_rotation_completed(&fields)
-            .expect("Test helper: failed to parse RotationCompleted status"),
-        CommandCode::RotationTimeout => TurnstileStatus::parse_rotation_timeout(&fields)
-            .expect("Test helper: failed to parse RotationTimeout status"),
-        _ => panic!(
-            "Test helper: not a turnstile status command: {:?}",
-            message.command
-        ),
-    }
+    message
+        .as_turnstile_status()
+        .expect("Test helper: failed to parse TurnstileStatus from message")
 }
 
 /// Assert that a message is an access request.
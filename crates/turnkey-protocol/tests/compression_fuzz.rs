@@ -0,0 +1,174 @@
+//! Fuzz tests for the decompression path.
+//!
+//! Compressed payloads come straight from the network, so decompression
+//! must never panic, never allocate past its limit and never hand back
+//! fields containing protocol delimiters, whatever the input.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use bytes::BytesMut;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use proptest::prelude::*;
+use std::io::Write;
+use tokio_util::codec::{Decoder, Encoder};
+use turnkey_core::DeviceId;
+use turnkey_protocol::compression::{COMPRESSED_FIELD_PREFIX, COMPRESSION_OFFER};
+use turnkey_protocol::{CommandCode, CompressionConfig, FieldData, HenryCodec, Message};
+
+/// Small limit so that oversized payloads are cheap to generate
+const LIMIT: usize = 4096;
+
+fn config() -> CompressionConfig {
+    CompressionConfig::default()
+        .with_threshold(0)
+        .with_max_decompressed_size(LIMIT)
+}
+
+fn deflate(raw: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(raw).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn compressed_message(encoded: String) -> Message {
+    Message::new(
+        DeviceId::new(1).unwrap(),
+        CommandCode::SendCards,
+        vec![FieldData::new(format!("{}{}", COMPRESSED_FIELD_PREFIX, encoded)).unwrap()],
+    )
+    .unwrap()
+}
+
+/// Check the invariants of a decompression result
+fn check(result: turnkey_core::Result<bool>, message: &Message) -> Result<(), TestCaseError> {
+    if let Ok(decompressed) = result {
+        prop_assert!(decompressed);
+        let size: usize = message.fields.iter().map(|f| f.len() + 1).sum();
+        prop_assert!(size <= LIMIT + 1);
+        for field in &message.fields {
+            prop_assert!(FieldData::new(field.as_str().to_string()).is_ok());
+        }
+    }
+    Ok(())
+}
+
+/// Codec pair that already negotiated compression
+fn negotiated() -> (HenryCodec, HenryCodec) {
+    let mut a = HenryCodec::new().with_compression(config());
+    let mut b = HenryCodec::new().with_compression(config());
+    let hello = Message::new(DeviceId::new(1).unwrap(), CommandCode::QueryStatus, vec![]).unwrap();
+    let exchange = |from: &mut HenryCodec, to: &mut HenryCodec| {
+        let mut buffer = BytesMut::new();
+        from.encode(hello.clone(), &mut buffer).unwrap();
+        to.decode(&mut buffer).unwrap().unwrap();
+    };
+    exchange(&mut a, &mut b);
+    exchange(&mut b, &mut a);
+    assert!(a.is_compression_negotiated() && b.is_compression_negotiated());
+    (a, b)
+}
+
+proptest! {
+    /// Arbitrary base64 text as the payload.
+    #[test]
+    fn fuzz_arbitrary_encoded_payload(encoded in "[A-Za-z0-9_-]{0,512}") {
+        let mut message = compressed_message(encoded);
+        let result = config().decompress(&mut message);
+        check(result, &message)?;
+    }
+
+    /// Arbitrary bytes, base64 encoded but not zlib data.
+    #[test]
+    fn fuzz_arbitrary_compressed_bytes(bytes in prop::collection::vec(any::<u8>(), 0..1024)) {
+        let mut message = compressed_message(URL_SAFE_NO_PAD.encode(&bytes));
+        let result = config().decompress(&mut message);
+        check(result, &message)?;
+    }
+
+    /// Valid zlib streams of arbitrary content, including delimiters,
+    /// control bytes, invalid UTF-8 and payloads past the limit.
+    #[test]
+    fn fuzz_arbitrary_decompressed_content(
+        raw in prop::collection::vec(any::<u8>(), 0..(2 * LIMIT)),
+    ) {
+        let mut message = compressed_message(URL_SAFE_NO_PAD.encode(deflate(&raw)));
+        let result = config().decompress(&mut message);
+        if raw.len() > LIMIT {
+            prop_assert!(result.is_err());
+        }
+        check(result, &message)?;
+    }
+
+    /// Highly repetitive payloads expanding far past the limit.
+    #[test]
+    fn fuzz_expansion_is_bounded(byte in b'0'..=b'9', size in LIMIT + 1..(64 * LIMIT)) {
+        let mut message = compressed_message(URL_SAFE_NO_PAD.encode(deflate(&vec![byte; size])));
+        prop_assert!(config().decompress(&mut message).is_err());
+    }
+
+    /// Valid zlib streams truncated or corrupted at an arbitrary position.
+    #[test]
+    fn fuzz_corrupted_stream(
+        cards in prop::collection::vec("[0-9]{8,20}", 1..200),
+        position in any::<prop::sample::Index>(),
+        flip in any::<u8>(),
+        truncate in any::<bool>(),
+    ) {
+        let mut compressed = deflate(cards.join("]").as_bytes());
+        let index = position.index(compressed.len());
+        if truncate {
+            compressed.truncate(index);
+        } else {
+            compressed[index] ^= flip;
+        }
+        let mut message = compressed_message(URL_SAFE_NO_PAD.encode(&compressed));
+        let result = config().decompress(&mut message);
+        check(result, &message)?;
+    }
+
+    /// Arbitrary frames through a codec that negotiated compression.
+    #[test]
+    fn fuzz_codec_decode(body in "[ -~]{0,600}") {
+        let (_, mut decoder) = negotiated();
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(b"\x02");
+        buffer.extend_from_slice(body.as_bytes());
+        buffer.extend_from_slice(b"\x03");
+        let _ = decoder.decode(&mut buffer);
+    }
+
+    /// Bulk transfers survive compression through the codec unchanged.
+    #[test]
+    fn prop_codec_round_trip(cards in prop::collection::vec("[0-9A-Za-z]{1,20}", 1..150)) {
+        let (mut encoder, mut decoder) = negotiated();
+        let fields: Vec<FieldData> = cards
+            .iter()
+            .map(|c| FieldData::new(c.clone()).unwrap())
+            .collect();
+        let message = Message::new(DeviceId::new(7).unwrap(), CommandCode::SendCards, fields.clone())
+            .unwrap();
+
+        let mut buffer = BytesMut::new();
+        encoder.encode(message, &mut buffer).unwrap();
+        let received = decoder.decode(&mut buffer).unwrap().unwrap();
+        prop_assert_eq!(received.command, CommandCode::SendCards);
+        prop_assert_eq!(received.fields, fields);
+    }
+}
+
+#[test]
+fn test_offer_inside_compressed_payload_is_kept() {
+    // Only a trailing plain field is an offer; inside a payload it is data
+    let (_, mut decoder) = negotiated();
+    let payload = format!("12345678]{}", COMPRESSION_OFFER);
+    let frame = format!(
+        "\x0201+REON+ECAR]{}{}\x03",
+        COMPRESSED_FIELD_PREFIX,
+        URL_SAFE_NO_PAD.encode(deflate(payload.as_bytes()))
+    );
+    let mut buffer = BytesMut::from(frame.as_bytes());
+    let message = decoder.decode(&mut buffer).unwrap().unwrap();
+    assert_eq!(message.fields.len(), 2);
+    assert_eq!(message.fields[1].as_str(), COMPRESSION_OFFER);
+}
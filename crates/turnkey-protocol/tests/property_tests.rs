@@ -7,7 +7,7 @@ mod common;
 
 use proptest::prelude::*;
 use turnkey_core::{AccessDirection, ReaderType};
-use turnkey_protocol::{CommandCode, FieldData, MessageBuilder};
+use turnkey_protocol::{CommandCode, FieldData, MessageBuilder, StreamParser};
 
 /// Strategy for generating valid card numbers (3-20 chars, no delimiters).
 ///
@@ -254,6 +254,45 @@ proptest! {
         prop_assert_eq!(parsed_request.direction(), direction, "Parsed direction must match");
         prop_assert_eq!(parsed_request.reader_type(), reader_type, "Parsed reader type must match");
     }
+
+    /// Property: StreamParser output does not depend on TCP fragmentation.
+    ///
+    /// The same stream of frames, cut into arbitrary chunks (byte-by-byte,
+    /// mid-field, several frames coalesced), must yield the same frames in
+    /// the same order as feeding it in one piece.
+    #[test]
+    fn prop_stream_parser_fragmentation_invariant(
+        card_numbers in prop::collection::vec(valid_card_number(), 1..8),
+        cuts in prop::collection::vec(1usize..64, 0..32),
+    ) {
+        let payloads: Vec<String> = card_numbers
+            .iter()
+            .map(|card| format!("15+REON+000+0]{}]10/05/2025 12:46:06]1]0]", card))
+            .collect();
+        let mut stream = Vec::new();
+        for payload in &payloads {
+            stream.push(0x02);
+            stream.extend_from_slice(payload.as_bytes());
+            stream.push(0x03);
+        }
+
+        let mut parser = StreamParser::new();
+        let mut frames = Vec::new();
+        let mut rest = &stream[..];
+        for cut in cuts.iter().cycle().take(stream.len()) {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at((*cut).min(rest.len()));
+            parser.feed(chunk);
+            frames.extend(parser.drain_frames().map(|f| f.to_string().unwrap()));
+            rest = tail;
+        }
+        parser.feed(rest);
+        frames.extend(parser.drain_frames().map(|f| f.to_string().unwrap()));
+
+        prop_assert_eq!(frames, payloads);
+    }
 }
 
 #[cfg(test)]
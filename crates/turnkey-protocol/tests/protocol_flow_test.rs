@@ -120,6 +120,35 @@ fn test_access_denied_flow_online() {
     assert!(decision.is_deny());
 }
 
+#[test]
+fn test_display_only_flow_online() {
+    use test_data::*;
+
+    let request_msg = common::create_access_request(
+        TEST_DEVICE_ID,
+        VALID_CARD_1,
+        AccessDirection::Entry,
+        ReaderType::Rfid,
+    );
+    common::assert_access_request(&request_msg);
+
+    // Server answers with an instruction and keeps the turnstile locked
+    let response_msg = common::create_access_response(
+        TEST_DEVICE_ID,
+        AccessDecision::DisplayOnly,
+        5,
+        "Dirija-se a recepcao",
+    );
+    assert_eq!(response_msg.command, CommandCode::DisplayOnly);
+
+    let (decision, timeout, message) = common::parse_access_response(&response_msg);
+    assert!(decision.is_display_only());
+    assert!(!decision.is_grant());
+    assert!(!decision.is_deny());
+    assert_eq!(timeout, 5);
+    assert_eq!(message, "Dirija-se a recepcao");
+}
+
 #[test]
 fn test_rotation_timeout_flow() {
     let device_id = 15;
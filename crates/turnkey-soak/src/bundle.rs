@@ -0,0 +1,111 @@
+//! Diagnostic bundle written when an invariant fails
+
+use crate::SoakConfig;
+use crate::fleet::DeviceReport;
+use crate::invariants::{MemorySample, Violation};
+use crate::server::ServerReport;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Access logs copied into the bundle, most recent first
+const RECENT_LOG_LIMIT: i64 = 200;
+
+/// Access log row as stored, for the bundle
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoggedAccess {
+    pub id: i64,
+    pub card_number: String,
+    pub direction: i64,
+    pub granted: bool,
+    pub timestamp: String,
+    pub device_id: Option<i64>,
+}
+
+/// Everything needed to investigate a failed soak run
+///
+/// Written as a directory so each part can be opened on its own:
+///
+/// | File               | Contents                                        |
+/// |--------------------|-------------------------------------------------|
+/// | `manifest.json`    | config (with seed), violations, server counters |
+/// | `devices.json`     | state, errors and emulator snapshot per device  |
+/// | `memory.json`      | memory samples taken by the checker             |
+/// | `access_logs.json` | most recent access logs                         |
+/// | `events.log`       | faults, reconnects and errors, oldest first     |
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticBundle {
+    pub created_at: DateTime<Utc>,
+    pub elapsed: Duration,
+    pub config: SoakConfig,
+    pub violations: Vec<Violation>,
+    pub server: ServerReport,
+    pub devices: Vec<DeviceReport>,
+    pub memory: Vec<MemorySample>,
+    pub access_logs: Vec<LoggedAccess>,
+    pub events: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    created_at: DateTime<Utc>,
+    elapsed: Duration,
+    config: &'a SoakConfig,
+    violations: &'a [Violation],
+    server: ServerReport,
+}
+
+impl DiagnosticBundle {
+    /// Read the most recent access logs for the bundle
+    ///
+    /// Errors are swallowed: a bundle without logs is still worth writing.
+    pub(crate) async fn recent_access_logs(pool: &SqlitePool) -> Vec<LoggedAccess> {
+        sqlx::query_as(
+            "SELECT id, card_number, direction, granted, timestamp, device_id
+             FROM access_logs ORDER BY id DESC LIMIT ?",
+        )
+        .bind(RECENT_LOG_LIMIT)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Write the bundle into a new `soak-<timestamp>` directory under `dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the directory or a file cannot be written.
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = dir.join(format!(
+            "soak-{}",
+            self.created_at.format("%Y%m%dT%H%M%S%.3fZ")
+        ));
+        std::fs::create_dir_all(&path)?;
+
+        let manifest = Manifest {
+            created_at: self.created_at,
+            elapsed: self.elapsed,
+            config: &self.config,
+            violations: &self.violations,
+            server: self.server,
+        };
+        write_json(&path.join("manifest.json"), &manifest)?;
+        write_json(&path.join("devices.json"), &self.devices)?;
+        write_json(&path.join("memory.json"), &self.memory)?;
+        write_json(&path.join("access_logs.json"), &self.access_logs)?;
+
+        let mut events = self.events.join("\n");
+        events.push('\n');
+        std::fs::write(path.join("events.log"), events)?;
+
+        Ok(path)
+    }
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    std::fs::write(path, json)
+}
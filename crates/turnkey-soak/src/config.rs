@@ -0,0 +1,198 @@
+//! Soak run configuration
+
+use crate::SoakError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Parameters of a soak run
+///
+/// # Example
+///
+/// ```
+/// use turnkey_soak::SoakConfig;
+/// use std::time::Duration;
+///
+/// let config = SoakConfig {
+///     devices: 4,
+///     duration: Duration::from_secs(30),
+///     ..SoakConfig::default()
+/// };
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakConfig {
+    /// Number of emulated turnstiles (device IDs 1..=devices)
+    pub devices: u8,
+
+    /// How long to run
+    pub duration: Duration,
+
+    /// Seed for card choice, timing and faults; equal seeds give equal
+    /// fault schedules
+    pub seed: u64,
+
+    /// Mean time between injected faults, `None` disables fault injection
+    pub fault_interval: Option<Duration>,
+
+    /// Time between invariant checks
+    pub check_interval: Duration,
+
+    /// A device outside `Idle` for longer than this is stuck
+    pub stuck_after: Duration,
+
+    /// How long a device waits for the server's answer
+    pub response_timeout: Duration,
+
+    /// Time before the memory baseline is taken, so caches and pools can fill
+    pub memory_warmup: Duration,
+
+    /// Largest accepted ratio of allocated memory over the baseline
+    pub max_memory_growth: f64,
+
+    /// Directory that receives diagnostic bundles
+    pub bundle_dir: PathBuf,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            devices: 8,
+            duration: Duration::from_secs(60 * 60),
+            seed: 0,
+            fault_interval: Some(Duration::from_secs(2)),
+            check_interval: Duration::from_secs(5),
+            stuck_after: Duration::from_secs(30),
+            response_timeout: Duration::from_secs(1),
+            memory_warmup: Duration::from_secs(60),
+            max_memory_growth: 2.0,
+            bundle_dir: PathBuf::from("soak-failures"),
+        }
+    }
+}
+
+impl SoakConfig {
+    /// Check the configuration before starting
+    ///
+    /// # Errors
+    ///
+    /// Returns `SoakError::Config` describing the first invalid value.
+    pub fn validate(&self) -> Result<(), SoakError> {
+        if !(1..=99).contains(&self.devices) {
+            return Err(SoakError::Config(format!(
+                "devices must be between 1 and 99, got {}",
+                self.devices
+            )));
+        }
+        if self.duration.is_zero() || self.check_interval.is_zero() {
+            return Err(SoakError::Config(
+                "duration and check interval must be greater than zero".to_string(),
+            ));
+        }
+        if self
+            .fault_interval
+            .is_some_and(|interval| interval.is_zero())
+        {
+            return Err(SoakError::Config(
+                "fault interval must be greater than zero".to_string(),
+            ));
+        }
+        // A device waiting for a lost response must not look stuck
+        if self.stuck_after <= self.response_timeout {
+            return Err(SoakError::Config(format!(
+                "stuck threshold ({:?}) must exceed the response timeout ({:?})",
+                self.stuck_after, self.response_timeout
+            )));
+        }
+        if self.max_memory_growth.is_nan() || self.max_memory_growth < 1.0 {
+            return Err(SoakError::Config(format!(
+                "max memory growth must be at least 1.0, got {}",
+                self.max_memory_growth
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a duration such as `90s`, `15m`, `4h` or a plain number of seconds
+///
+/// # Example
+///
+/// ```
+/// use turnkey_soak::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("4h").unwrap(), Duration::from_secs(4 * 3600));
+/// assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number * 60)),
+        "h" => Ok(Duration::from_secs(number * 3600)),
+        _ => Err(format!(
+            "invalid duration unit '{}' (use ms, s, m or h)",
+            unit
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(SoakConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_values() {
+        let invalid = [
+            SoakConfig {
+                devices: 0,
+                ..SoakConfig::default()
+            },
+            SoakConfig {
+                devices: 100,
+                ..SoakConfig::default()
+            },
+            SoakConfig {
+                fault_interval: Some(Duration::ZERO),
+                ..SoakConfig::default()
+            },
+            SoakConfig {
+                stuck_after: Duration::from_millis(500),
+                ..SoakConfig::default()
+            },
+            SoakConfig {
+                max_memory_growth: 0.5,
+                ..SoakConfig::default()
+            },
+        ];
+        for config in invalid {
+            assert!(matches!(config.validate(), Err(SoakError::Config(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("4d").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+}
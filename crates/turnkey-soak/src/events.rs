@@ -0,0 +1,32 @@
+//! Bounded log of notable harness events, kept for diagnostic bundles
+
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Events kept in memory; older ones are dropped
+const EVENT_CAPACITY: usize = 1000;
+
+/// Recent faults, reconnects and errors, oldest first
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    entries: Mutex<VecDeque<String>>,
+}
+
+impl EventLog {
+    /// Append an event stamped with the current time
+    pub(crate) fn record(&self, event: impl AsRef<str>) {
+        let line = format!("{} {}", Utc::now().format("%H:%M:%S%.3f"), event.as_ref());
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == EVENT_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(line);
+    }
+
+    /// Copy of the retained events
+    pub(crate) fn entries(&self) -> Vec<String> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+}
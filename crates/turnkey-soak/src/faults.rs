@@ -0,0 +1,121 @@
+//! Random fault schedule
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Disturbance injected into a running soak
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fault {
+    /// A device loses its server connection and reconnects
+    DropConnection { device_index: usize },
+
+    /// The server swallows its next responses; devices time out
+    DropResponses { count: u32 },
+
+    /// The server pauses before its next response
+    StallServer { millis: u64 },
+
+    /// A stranger connects and sends bytes that are not a Henry frame
+    GarbageClient,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::DropConnection { device_index } => {
+                write!(f, "drop connection of device #{}", device_index + 1)
+            }
+            Fault::DropResponses { count } => write!(f, "drop next {} responses", count),
+            Fault::StallServer { millis } => write!(f, "stall server for {}ms", millis),
+            Fault::GarbageClient => write!(f, "garbage client"),
+        }
+    }
+}
+
+/// Draws faults and the delays between them from a seeded generator
+pub(crate) struct FaultInjector {
+    rng: StdRng,
+    devices: usize,
+    mean_interval: Duration,
+    response_timeout: Duration,
+}
+
+impl FaultInjector {
+    pub(crate) fn new(
+        seed: u64,
+        devices: usize,
+        mean_interval: Duration,
+        response_timeout: Duration,
+    ) -> Self {
+        Self {
+            // Separate stream from the devices, which use seed + device ID
+            rng: StdRng::seed_from_u64(seed ^ 0x5eed_fa17),
+            devices,
+            mean_interval,
+            response_timeout,
+        }
+    }
+
+    /// Time until the next fault, between half and one and a half means
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        self.mean_interval.mul_f64(self.rng.gen_range(0.5..1.5))
+    }
+
+    /// Pick the next fault
+    pub(crate) fn next_fault(&mut self) -> Fault {
+        match self.rng.gen_range(0..4) {
+            0 => Fault::DropConnection {
+                device_index: self.rng.gen_range(0..self.devices),
+            },
+            1 => Fault::DropResponses {
+                count: self.rng.gen_range(1..=3),
+            },
+            2 => {
+                // Sometimes shorter, sometimes longer than the device timeout
+                let timeout = self.response_timeout.as_millis() as u64;
+                Fault::StallServer {
+                    millis: self.rng.gen_range(timeout / 2..=timeout * 2),
+                }
+            }
+            _ => Fault::GarbageClient,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_schedule() {
+        let schedule = |seed| {
+            let mut injector =
+                FaultInjector::new(seed, 4, Duration::from_secs(2), Duration::from_secs(1));
+            (0..20)
+                .map(|_| (injector.next_delay(), injector.next_fault()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(schedule(7), schedule(7));
+        assert_ne!(schedule(7), schedule(8));
+    }
+
+    #[test]
+    fn test_faults_stay_in_range() {
+        let mut injector =
+            FaultInjector::new(1, 3, Duration::from_secs(2), Duration::from_millis(400));
+        for _ in 0..200 {
+            let delay = injector.next_delay();
+            assert!(delay >= Duration::from_secs(1) && delay < Duration::from_secs(3));
+            match injector.next_fault() {
+                Fault::DropConnection { device_index } => assert!(device_index < 3),
+                Fault::DropResponses { count } => assert!((1..=3).contains(&count)),
+                Fault::StallServer { millis } => assert!((200..=800).contains(&millis)),
+                Fault::GarbageClient => {}
+            }
+        }
+    }
+}
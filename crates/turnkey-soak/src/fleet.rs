@@ -0,0 +1,290 @@
+//! Emulated turnstiles driving access flows against the server
+
+use crate::SoakConfig;
+use crate::events::EventLog;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use turnkey_core::{AccessDirection, DeviceId, HenryTimestamp, ReaderType};
+use turnkey_emulator::{EmulatorCore, EmulatorSnapshot, TurnstileState};
+use turnkey_network::{TcpClient, TcpClientConfig};
+use turnkey_protocol::commands::access::AccessRequest;
+use turnkey_storage::{AccessValidator, OnlineValidator, OnlineValidatorConfig};
+
+/// State machine errors kept per device
+const ERROR_CAPACITY: usize = 20;
+
+/// Probability that a granted user actually walks through
+const ROTATION_PROBABILITY: f64 = 0.9;
+
+/// What the harness knows about one emulated device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceReport {
+    /// Henry device ID
+    pub device_id: u8,
+
+    /// Current state
+    pub state: TurnstileState,
+
+    /// Time since the last state change
+    pub in_state_for: Duration,
+
+    /// Completed access flows
+    pub cycles: u64,
+
+    /// Rejected state transitions, most recent last
+    pub errors: Vec<String>,
+
+    /// Emulator state at the end of the last completed flow
+    pub snapshot: EmulatorSnapshot,
+}
+
+/// Shared between a device task and the harness
+struct DeviceProbe {
+    state: TurnstileState,
+    last_change: Instant,
+    cycles: u64,
+    errors: Vec<String>,
+    snapshot: EmulatorSnapshot,
+}
+
+struct DeviceHandle {
+    device_id: DeviceId,
+    probe: Arc<Mutex<DeviceProbe>>,
+    disconnect: Arc<AtomicBool>,
+}
+
+/// Running set of emulated devices
+pub(crate) struct Fleet {
+    devices: Vec<DeviceHandle>,
+    tasks: JoinSet<()>,
+}
+
+impl Fleet {
+    /// Start one task per device
+    pub(crate) fn spawn(
+        config: &SoakConfig,
+        server_addr: SocketAddr,
+        cards: Arc<[String]>,
+        stop: watch::Receiver<bool>,
+        events: Arc<EventLog>,
+    ) -> Self {
+        let mut devices = Vec::new();
+        let mut tasks = JoinSet::new();
+
+        for id in 1..=config.devices {
+            let device_id = DeviceId::new(id).expect("device count validated");
+            let emulator = EmulatorCore::default();
+            let probe = Arc::new(Mutex::new(DeviceProbe {
+                state: emulator.state(),
+                last_change: Instant::now(),
+                cycles: 0,
+                errors: Vec::new(),
+                snapshot: emulator.snapshot(),
+            }));
+            let disconnect = Arc::new(AtomicBool::new(false));
+
+            let device = Device {
+                device_id,
+                emulator,
+                client_config: TcpClientConfig {
+                    server_addr,
+                    timeout: config.response_timeout,
+                },
+                rng: StdRng::seed_from_u64(config.seed.wrapping_add(u64::from(id))),
+                cards: cards.clone(),
+                probe: probe.clone(),
+                disconnect: disconnect.clone(),
+                events: events.clone(),
+            };
+            tasks.spawn(device.run(stop.clone()));
+
+            devices.push(DeviceHandle {
+                device_id,
+                probe,
+                disconnect,
+            });
+        }
+
+        Self { devices, tasks }
+    }
+
+    /// Number of devices
+    pub(crate) fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Close the server connection of a device before its next flow
+    pub(crate) fn request_disconnect(&self, index: usize) -> Option<DeviceId> {
+        let device = self.devices.get(index)?;
+        device.disconnect.store(true, Ordering::SeqCst);
+        Some(device.device_id)
+    }
+
+    /// Current view of every device
+    pub(crate) fn reports(&self) -> Vec<DeviceReport> {
+        self.devices
+            .iter()
+            .map(|device| {
+                let probe = lock(&device.probe);
+                DeviceReport {
+                    device_id: device.device_id.as_u8(),
+                    state: probe.state,
+                    in_state_for: probe.last_change.elapsed(),
+                    cycles: probe.cycles,
+                    errors: probe.errors.clone(),
+                    snapshot: probe.snapshot.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Wait for every device task to notice the stop signal
+    pub(crate) async fn join(mut self) {
+        while self.tasks.join_next().await.is_some() {}
+    }
+}
+
+fn lock(probe: &Mutex<DeviceProbe>) -> std::sync::MutexGuard<'_, DeviceProbe> {
+    probe.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// One emulated turnstile with an online validator
+struct Device {
+    device_id: DeviceId,
+    emulator: EmulatorCore,
+    client_config: TcpClientConfig,
+    rng: StdRng,
+    cards: Arc<[String]>,
+    probe: Arc<Mutex<DeviceProbe>>,
+    disconnect: Arc<AtomicBool>,
+    events: Arc<EventLog>,
+}
+
+impl Device {
+    fn validator(&self) -> OnlineValidator {
+        let config = OnlineValidatorConfig {
+            max_retries: 0,
+            retry_delay: Duration::ZERO,
+            fallback_to_offline: false,
+        };
+        OnlineValidator::new(
+            TcpClient::new(self.client_config.clone()),
+            self.device_id,
+            config,
+        )
+    }
+
+    async fn run(mut self, mut stop: watch::Receiver<bool>) {
+        let mut validator = self.validator();
+
+        while !*stop.borrow() {
+            if self.disconnect.swap(false, Ordering::SeqCst) {
+                // Dropping the validator closes its connection
+                validator = self.validator();
+                self.events
+                    .record(format!("device {}: connection dropped", self.device_id));
+            }
+
+            self.access_flow(&mut validator).await;
+            {
+                let mut probe = lock(&self.probe);
+                probe.cycles += 1;
+                probe.snapshot = self.emulator.snapshot();
+            }
+
+            let pause = Duration::from_millis(self.rng.gen_range(10..100));
+            tokio::select! {
+                _ = tokio::time::sleep(pause) => {}
+                _ = stop.changed() => {}
+            }
+        }
+    }
+
+    /// Present a card and follow the server's decision back to `Idle`
+    async fn access_flow(&mut self, validator: &mut OnlineValidator) {
+        let card_number = self
+            .cards
+            .choose(&mut self.rng)
+            .cloned()
+            .unwrap_or_default();
+        let direction = if self.rng.gen_bool(0.5) {
+            AccessDirection::Entry
+        } else {
+            AccessDirection::Exit
+        };
+
+        if !self.step(TurnstileState::Reading) || !self.step(TurnstileState::Validating) {
+            return;
+        }
+
+        let granted = match AccessRequest::new(
+            card_number,
+            HenryTimestamp::now(),
+            direction,
+            ReaderType::Rfid,
+        ) {
+            Ok(request) => match validator.validate(&request).await {
+                Ok(response) => response.is_grant(),
+                // Lost responses and dropped connections end in a denial,
+                // as on a real turnstile without offline fallback
+                Err(_) => false,
+            },
+            Err(_) => false,
+        };
+
+        let path: &[TurnstileState] = if !granted {
+            &[TurnstileState::Denied, TurnstileState::Idle]
+        } else if self.rng.gen_bool(ROTATION_PROBABILITY) {
+            &[
+                TurnstileState::Granted,
+                TurnstileState::WaitingRotation,
+                TurnstileState::RotationInProgress,
+                TurnstileState::RotationCompleted,
+                TurnstileState::Idle,
+            ]
+        } else {
+            &[
+                TurnstileState::Granted,
+                TurnstileState::WaitingRotation,
+                TurnstileState::RotationTimeout,
+                TurnstileState::Idle,
+            ]
+        };
+        for &state in path {
+            if !self.step(state) {
+                return;
+            }
+        }
+    }
+
+    /// Transition and publish the new state
+    ///
+    /// A rejected transition is recorded and the emulator is reset, so the
+    /// fleet keeps running and the invariant check reports the error.
+    fn step(&mut self, state: TurnstileState) -> bool {
+        let from = self.emulator.state();
+        let result = self.emulator.transition_to(state);
+
+        let mut probe = lock(&self.probe);
+        if let Err(e) = &result {
+            if probe.errors.len() == ERROR_CAPACITY {
+                probe.errors.remove(0);
+            }
+            probe
+                .errors
+                .push(format!("{:?} -> {:?}: {}", from, state, e));
+            self.emulator.reset();
+        }
+        probe.state = self.emulator.state();
+        probe.last_change = Instant::now();
+        result.is_ok()
+    }
+}
@@ -0,0 +1,408 @@
+//! Invariants checked continuously during a soak run
+//!
+//! | Invariant                 | Holds when                                                   |
+//! |---------------------------|--------------------------------------------------------------|
+//! | `StuckStateMachine`       | every device returns to `Idle` within `stuck_after`          |
+//! | `StateMachineError`       | no device attempted a transition its state machine rejects   |
+//! | `LogsNotMonotonic`        | access logs are only appended, in server time order          |
+//! | `OccupancyInconsistent`   | the presence projection matches a replay of the access logs  |
+//! | `MemoryGrowth`            | allocated memory stays within `max_memory_growth` × baseline |
+
+use crate::SoakConfig;
+use crate::fleet::DeviceReport;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+use turnkey_emulator::TurnstileState;
+use turnkey_storage::StorageResult;
+
+/// Memory samples kept for the diagnostic bundle
+const MEMORY_SAMPLE_CAPACITY: usize = 2048;
+
+/// Invariant that can be violated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Invariant {
+    StuckStateMachine,
+    StateMachineError,
+    LogsNotMonotonic,
+    OccupancyInconsistent,
+    MemoryGrowth,
+}
+
+/// A failed invariant check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Which invariant failed
+    pub invariant: Invariant,
+
+    /// What was observed
+    pub detail: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.invariant, self.detail)
+    }
+}
+
+/// Allocated memory at a point of the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemorySample {
+    /// Time since the checker started
+    pub elapsed: Duration,
+
+    /// Bytes allocated
+    pub bytes: u64,
+}
+
+/// Access log position after the previous check
+#[derive(Debug, Clone, Copy)]
+struct LogWatermark {
+    count: i64,
+    max_id: i64,
+    last_timestamp: Option<DateTime<Utc>>,
+}
+
+/// Checks the invariants against the fleet and the database
+pub struct InvariantChecker {
+    pool: SqlitePool,
+    stuck_after: Duration,
+    memory_warmup: Duration,
+    max_memory_growth: f64,
+    started: Instant,
+    logs: Option<LogWatermark>,
+    memory_baseline: Option<u64>,
+    memory: VecDeque<MemorySample>,
+}
+
+impl InvariantChecker {
+    /// Create a checker
+    ///
+    /// The first check only records the access logs already present (seed
+    /// data, earlier runs); later checks verify what was appended since.
+    pub fn new(pool: SqlitePool, config: &SoakConfig) -> Self {
+        Self {
+            pool,
+            stuck_after: config.stuck_after,
+            memory_warmup: config.memory_warmup,
+            max_memory_growth: config.max_memory_growth,
+            started: Instant::now(),
+            logs: None,
+            memory_baseline: None,
+            memory: VecDeque::new(),
+        }
+    }
+
+    /// Run every check
+    ///
+    /// `allocated` is the current allocation size, `None` if unknown.
+    ///
+    /// # Errors
+    ///
+    /// Returns the storage error if the database cannot be queried.
+    pub async fn check(
+        &mut self,
+        devices: &[DeviceReport],
+        allocated: Option<u64>,
+    ) -> StorageResult<Vec<Violation>> {
+        let mut violations = self.check_devices(devices);
+        violations.extend(self.check_logs().await?);
+        violations.extend(self.check_occupancy().await?);
+        if let Some(bytes) = allocated {
+            violations.extend(self.check_memory(self.started.elapsed(), bytes));
+        }
+        Ok(violations)
+    }
+
+    /// Memory samples taken so far, oldest first
+    pub fn memory_samples(&self) -> Vec<MemorySample> {
+        self.memory.iter().copied().collect()
+    }
+
+    /// Devices that are stuck or hit a state machine error
+    pub fn check_devices(&self, devices: &[DeviceReport]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for device in devices {
+            if device.state != TurnstileState::Idle && device.in_state_for > self.stuck_after {
+                violations.push(Violation {
+                    invariant: Invariant::StuckStateMachine,
+                    detail: format!(
+                        "device {} in {:?} for {:?}",
+                        device.device_id, device.state, device.in_state_for
+                    ),
+                });
+            }
+            if let Some(error) = device.errors.last() {
+                violations.push(Violation {
+                    invariant: Invariant::StateMachineError,
+                    detail: format!(
+                        "device {}: {} ({} errors)",
+                        device.device_id,
+                        error,
+                        device.errors.len()
+                    ),
+                });
+            }
+        }
+        violations
+    }
+
+    /// Logs written since the last check come after the previous ones, in
+    /// time order, and none disappeared
+    async fn check_logs(&mut self) -> StorageResult<Vec<Violation>> {
+        let Some(previous) = self.logs else {
+            let (count, max_id): (i64, Option<i64>) =
+                sqlx::query_as("SELECT COUNT(*), MAX(id) FROM access_logs")
+                    .fetch_one(&self.pool)
+                    .await?;
+            let last_timestamp: Option<DateTime<Utc>> =
+                sqlx::query_scalar("SELECT timestamp FROM access_logs WHERE id = ?")
+                    .bind(max_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+            self.logs = Some(LogWatermark {
+                count,
+                max_id: max_id.unwrap_or(0),
+                last_timestamp,
+            });
+            return Ok(Vec::new());
+        };
+        let mut violations = Vec::new();
+
+        // One read transaction, so both queries see the same snapshot while
+        // the server keeps writing
+        let mut tx = self.pool.begin().await?;
+        let new_logs: Vec<(i64, DateTime<Utc>)> =
+            sqlx::query_as("SELECT id, timestamp FROM access_logs WHERE id > ? ORDER BY id")
+                .bind(previous.max_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM access_logs")
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        let expected = previous.count + new_logs.len() as i64;
+        if count != expected {
+            violations.push(Violation {
+                invariant: Invariant::LogsNotMonotonic,
+                detail: format!(
+                    "{} access logs, expected {} ({} before, {} appended after ID {})",
+                    count,
+                    expected,
+                    previous.count,
+                    new_logs.len(),
+                    previous.max_id
+                ),
+            });
+        }
+
+        let mut last_timestamp = previous.last_timestamp;
+        for &(id, timestamp) in &new_logs {
+            if let Some(last) = last_timestamp
+                && timestamp < last
+            {
+                violations.push(Violation {
+                    invariant: Invariant::LogsNotMonotonic,
+                    detail: format!(
+                        "access log {} at {} is older than its predecessor at {}",
+                        id, timestamp, last
+                    ),
+                });
+                break;
+            }
+            last_timestamp = Some(timestamp);
+        }
+
+        self.logs = Some(LogWatermark {
+            count,
+            max_id: new_logs.last().map_or(previous.max_id, |&(id, _)| id),
+            last_timestamp,
+        });
+        Ok(violations)
+    }
+
+    /// Occupancy from the projection equals a replay of the granted logs
+    async fn check_occupancy(&self) -> StorageResult<Vec<Violation>> {
+        // The projection is updated by trigger in the writing transaction,
+        // so a single snapshot always sees both sides agree
+        let mut tx = self.pool.begin().await?;
+        let (projected,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM access_state WHERE inside = 1")
+                .fetch_one(&mut *tx)
+                .await?;
+        let (replayed,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM access_logs l
+             WHERE l.granted AND l.direction = 1
+               AND l.id = (SELECT MAX(id) FROM access_logs
+                           WHERE card_number = l.card_number AND granted)",
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        if projected == replayed {
+            return Ok(Vec::new());
+        }
+        Ok(vec![Violation {
+            invariant: Invariant::OccupancyInconsistent,
+            detail: format!(
+                "presence projection counts {} inside, access logs replay to {}",
+                projected, replayed
+            ),
+        }])
+    }
+
+    /// Allocation stays within the allowed growth over the post-warmup baseline
+    pub fn check_memory(&mut self, elapsed: Duration, bytes: u64) -> Vec<Violation> {
+        if self.memory.len() == MEMORY_SAMPLE_CAPACITY {
+            self.memory.pop_front();
+        }
+        self.memory.push_back(MemorySample { elapsed, bytes });
+
+        if elapsed < self.memory_warmup {
+            return Vec::new();
+        }
+        let baseline = *self.memory_baseline.get_or_insert(bytes);
+
+        let limit = (baseline as f64 * self.max_memory_growth) as u64;
+        if bytes <= limit {
+            return Vec::new();
+        }
+        vec![Violation {
+            invariant: Invariant::MemoryGrowth,
+            detail: format!(
+                "{} bytes allocated, baseline {} bytes after warmup (limit {:.1}x)",
+                bytes, baseline, self.max_memory_growth
+            ),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turnkey_emulator::EmulatorCore;
+    use turnkey_storage::Database;
+
+    fn device(state: TurnstileState, in_state_for: Duration) -> DeviceReport {
+        DeviceReport {
+            device_id: 1,
+            state,
+            in_state_for,
+            cycles: 0,
+            errors: Vec::new(),
+            snapshot: EmulatorCore::default().snapshot(),
+        }
+    }
+
+    async fn checker() -> (Database, InvariantChecker) {
+        let db = Database::in_memory().await.unwrap();
+        let config = SoakConfig {
+            memory_warmup: Duration::from_secs(10),
+            ..SoakConfig::default()
+        };
+        let checker = InvariantChecker::new(db.pool().clone(), &config);
+        (db, checker)
+    }
+
+    async fn insert_log(db: &Database, card_number: &str, direction: i32, timestamp: &str) {
+        sqlx::query(
+            "INSERT INTO access_logs (card_number, direction, reader_type, granted, timestamp)
+             VALUES (?, ?, 1, 1, ?)",
+        )
+        .bind(card_number)
+        .bind(direction)
+        .bind(timestamp)
+        .execute(db.pool())
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stuck_and_failed_devices() {
+        let (_db, checker) = checker().await;
+
+        let idle = device(TurnstileState::Idle, Duration::from_secs(600));
+        let busy = device(TurnstileState::Validating, Duration::from_secs(1));
+        let stuck = device(TurnstileState::WaitingRotation, Duration::from_secs(60));
+        let mut failed = device(TurnstileState::Idle, Duration::ZERO);
+        failed.errors.push("Idle -> Granted: invalid".to_string());
+
+        assert!(checker.check_devices(&[idle, busy]).is_empty());
+        let violations = checker.check_devices(&[stuck, failed]);
+        let invariants: Vec<_> = violations.iter().map(|v| v.invariant).collect();
+        assert_eq!(
+            invariants,
+            vec![Invariant::StuckStateMachine, Invariant::StateMachineError]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logs_must_only_grow_in_time_order() {
+        let (db, mut checker) = checker().await;
+        assert!(checker.check_logs().await.unwrap().is_empty());
+
+        insert_log(&db, "12345678", 1, "2026-01-01T10:00:00Z").await;
+        insert_log(&db, "12345678", 2, "2026-01-01T10:00:01Z").await;
+        assert!(checker.check_logs().await.unwrap().is_empty());
+
+        // Appended out of order
+        insert_log(&db, "87654321", 1, "2026-01-01T09:00:00Z").await;
+        let violations = checker.check_logs().await.unwrap();
+        assert_eq!(violations[0].invariant, Invariant::LogsNotMonotonic);
+
+        // Deleted behind the watermark
+        sqlx::query("DELETE FROM access_logs WHERE id = (SELECT MIN(id) FROM access_logs)")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let violations = checker.check_logs().await.unwrap();
+        assert_eq!(violations[0].invariant, Invariant::LogsNotMonotonic);
+    }
+
+    #[tokio::test]
+    async fn test_occupancy_matches_replay() {
+        let (db, checker) = checker().await;
+        insert_log(&db, "12345678", 1, "2026-01-01T10:00:00Z").await;
+        insert_log(&db, "87654321", 1, "2026-01-01T10:00:01Z").await;
+        insert_log(&db, "87654321", 2, "2026-01-01T10:00:02Z").await;
+        assert!(checker.check_occupancy().await.unwrap().is_empty());
+
+        // Projection drifting from the logs
+        sqlx::query("UPDATE access_state SET inside = 1 WHERE card_number = '87654321'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let violations = checker.check_occupancy().await.unwrap();
+        assert_eq!(violations[0].invariant, Invariant::OccupancyInconsistent);
+    }
+
+    #[tokio::test]
+    async fn test_memory_growth_after_warmup() {
+        let (_db, mut checker) = checker().await;
+
+        // Growth during warmup is expected
+        assert!(
+            checker
+                .check_memory(Duration::from_secs(1), 10_000_000)
+                .is_empty()
+        );
+        assert!(
+            checker
+                .check_memory(Duration::from_secs(10), 40_000_000)
+                .is_empty()
+        );
+        assert!(
+            checker
+                .check_memory(Duration::from_secs(20), 70_000_000)
+                .is_empty()
+        );
+        let violations = checker.check_memory(Duration::from_secs(30), 90_000_000);
+        assert_eq!(violations[0].invariant, Invariant::MemoryGrowth);
+        assert_eq!(checker.memory_samples().len(), 4);
+    }
+}
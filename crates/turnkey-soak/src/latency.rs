@@ -0,0 +1,163 @@
+//! Offline tap-to-display pipeline with latency tracking
+//!
+//! [`OfflinePipeline`] runs the access flow of one turnstile without a
+//! server: a reader delivers the card, the [`OfflineValidator`] decides and
+//! logs, and the [`EmulatorCore`] display shows the decision. Every tap is
+//! timed per [`LatencyStage`] and recorded in a [`LatencyTracker`], whose
+//! report tells which stage eats the budget.
+//!
+//! With a mock reader and a SQLite file this is the stack the latency
+//! budget test runs in CI (`tests/latency_budget.rs`).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use turnkey_core::{AccessDirection, DeviceId};
+//! use turnkey_hardware::mock::MockRfid;
+//! use turnkey_hardware::traits::CardType;
+//! use turnkey_soak::latency::OfflinePipeline;
+//! use turnkey_storage::{Database, DatabaseConfig, OfflineValidator};
+//!
+//! # async fn example() -> Result<(), turnkey_soak::SoakError> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let device_id = DeviceId::new(15).unwrap();
+//! let (reader, mut handle) = MockRfid::new();
+//! let validator = OfflineValidator::new(db.pool().clone()).with_device_id(device_id);
+//! let mut pipeline = OfflinePipeline::new(device_id, reader, validator);
+//!
+//! let uid = vec![0x01, 0x5C, 0x42, 0x09];
+//! handle.add_card(uid.clone(), CardType::MifareClassic1K).await;
+//! handle.present_card(uid).await?;
+//! let tap = pipeline.tap(AccessDirection::Entry).await?;
+//! println!("granted={} in {:?}", tap.granted, tap.timings.total());
+//! println!("{}", pipeline.report());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::SoakError;
+use std::time::Duration;
+use turnkey_core::latency::{
+    LatencyReport, LatencyStage, LatencyTracker, StageTimer, StageTimings,
+};
+use turnkey_core::{AccessDirection, CardNumber, DeviceId, HenryTimestamp, ReaderType};
+use turnkey_emulator::{EmulatorCore, TurnstileState};
+use turnkey_hardware::RfidDevice;
+use turnkey_protocol::commands::access::AccessRequest;
+use turnkey_storage::{AccessValidator, OfflineValidator};
+
+/// Outcome of one tap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tap {
+    /// Card number as sent to the validator
+    pub card_number: String,
+
+    /// Whether access was granted
+    pub granted: bool,
+
+    /// Time spent in each stage
+    pub timings: StageTimings,
+
+    /// Whether the tap stayed within the budget
+    pub within_budget: bool,
+}
+
+/// One turnstile validating offline, timed from tap to display
+pub struct OfflinePipeline<R: RfidDevice> {
+    device_id: DeviceId,
+    reader: R,
+    validator: OfflineValidator,
+    emulator: EmulatorCore,
+    tracker: LatencyTracker,
+}
+
+impl<R: RfidDevice> OfflinePipeline<R> {
+    /// Create an idle pipeline checked against the default budget
+    pub fn new(device_id: DeviceId, reader: R, validator: OfflineValidator) -> Self {
+        Self {
+            device_id,
+            reader,
+            validator,
+            emulator: EmulatorCore::default(),
+            tracker: LatencyTracker::default(),
+        }
+    }
+
+    /// Check taps against another budget
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.tracker = LatencyTracker::new(budget);
+        self
+    }
+
+    /// Emulated turnstile showing the decisions
+    pub fn emulator(&self) -> &EmulatorCore {
+        &self.emulator
+    }
+
+    /// Wait for the next card and follow it to the display
+    ///
+    /// The clock starts when the reader is asked for the card, so present
+    /// the card first to time the tap itself. The turnstile is back in
+    /// `Idle` afterwards; the passage itself is not part of the budget.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Hardware` if the reader fails, `Emulator` if the card number
+    /// or request is invalid, and `Storage` if validation fails.
+    pub async fn tap(&mut self, direction: AccessDirection) -> Result<Tap, SoakError> {
+        let mut timer = StageTimer::start();
+        let card = self.reader.read_card().await?;
+        let card_number = CardNumber::new(&card.uid_decimal())?.padded();
+        self.emulator.transition_to(TurnstileState::Reading)?;
+        timer.lap(LatencyStage::Read);
+
+        let request = AccessRequest::new(
+            card_number.clone(),
+            HenryTimestamp::now(),
+            direction,
+            ReaderType::Rfid,
+        )?;
+        self.emulator.transition_to(TurnstileState::Validating)?;
+        let response = self.validator.validate(&request).await?;
+        timer.lap(LatencyStage::Validate);
+        let log_time = self.validator.take_log_time();
+        timer
+            .timings_mut()
+            .reassign(LatencyStage::Validate, LatencyStage::Log, log_time);
+
+        let granted = response.is_grant();
+        self.emulator.transition_to(if granted {
+            TurnstileState::Granted
+        } else {
+            TurnstileState::Denied
+        })?;
+        self.emulator
+            .display_mut()
+            .set_line(1, response.display_message())?;
+        timer.lap(LatencyStage::Display);
+
+        let timings = timer.finish();
+        let within_budget = self.tracker.record(self.device_id, timings);
+        if !within_budget {
+            tracing::warn!(
+                device_id = %self.device_id,
+                total = ?timings.total(),
+                slowest = %timings.slowest(),
+                "tap exceeded the latency budget"
+            );
+        }
+        self.emulator.reset();
+
+        Ok(Tap {
+            card_number,
+            granted,
+            timings,
+            within_budget,
+        })
+    }
+
+    /// Latency breakdown of the taps so far
+    pub fn report(&self) -> LatencyReport {
+        self.tracker.report()
+    }
+}
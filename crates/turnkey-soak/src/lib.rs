@@ -0,0 +1,403 @@
+//! Long-running soak test for the Turnkey stack
+//!
+//! Runs a fleet of emulated turnstiles against a validation server for hours,
+//! injects faults at random and checks invariants while it runs. The first
+//! violation stops the run and writes a diagnostic bundle.
+//!
+//! # Setup
+//!
+//! - **Server:** a [`TcpServer`](turnkey_network::TcpServer) on a loopback
+//!   port answering access requests with the offline validator, backed by a
+//!   SQLite file in the temp directory (migrated and seeded).
+//! - **Fleet:** one task per device, each an `EmulatorCore` walking the
+//!   turnstile state machine and an `OnlineValidator` asking the server.
+//!   Cards are drawn from the seeded cards plus a few unknown numbers.
+//!
+//! # Faults
+//!
+//! See [`Fault`]: dropped device connections, lost responses, stalled
+//! server, and clients sending garbage. The schedule depends only on the
+//! seed, so a failing run can be repeated with `--seed`.
+//!
+//! # Invariants
+//!
+//! See [`Invariant`]: no stuck or failing state machines, access logs only
+//! appended and in time order, occupancy matching a replay of the logs, and
+//! no unbounded memory growth (jemalloc `stats.allocated` in the binary).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use turnkey_soak::{SoakConfig, run};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), turnkey_soak::SoakError> {
+//! let config = SoakConfig {
+//!     duration: Duration::from_secs(4 * 3600),
+//!     seed: 42,
+//!     ..SoakConfig::default()
+//! };
+//! let report = run(config).await?;
+//! println!("{} access flows", report.cycles);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod bundle;
+pub mod config;
+mod events;
+pub mod faults;
+pub mod fleet;
+pub mod invariants;
+pub mod latency;
+pub mod memory;
+pub mod server;
+
+pub use bundle::DiagnosticBundle;
+pub use config::{SoakConfig, parse_duration};
+pub use faults::Fault;
+pub use fleet::DeviceReport;
+pub use invariants::{Invariant, InvariantChecker, MemorySample, Violation};
+pub use server::ServerReport;
+
+use crate::events::EventLog;
+use crate::faults::FaultInjector;
+use crate::fleet::Fleet;
+use crate::server::{ServerFaults, ServerStats};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tracing::{info, warn};
+use turnkey_network::{TcpServer, TcpServerConfig, TcpServerError};
+use turnkey_storage::{Database, DatabaseConfig, StorageError};
+
+/// Card numbers with no registration, so denials are exercised too
+const UNKNOWN_CARDS: [&str; 3] = ["99999999", "00000000000000000001", "ABCDEF123456"];
+
+/// Connections allowed beyond the fleet, for garbage clients and reconnects
+const SPARE_CONNECTIONS: usize = 16;
+
+/// Soak run errors
+#[derive(Debug, Error)]
+pub enum SoakError {
+    #[error("Invalid soak configuration: {0}")]
+    Config(String),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+
+    #[error("Server error: {0}")]
+    Server(#[from] TcpServerError),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Hardware error: {0}")]
+    Hardware(#[from] turnkey_hardware::HardwareError),
+
+    #[error("Emulator error: {0}")]
+    Emulator(#[from] turnkey_core::Error),
+
+    #[error(
+        "{} invariant violation(s), diagnostic bundle at {}",
+        violations.len(),
+        bundle.display()
+    )]
+    InvariantViolated {
+        violations: Vec<Violation>,
+        bundle: PathBuf,
+    },
+}
+
+/// Summary of a soak run that held every invariant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakReport {
+    /// Time the fleet ran
+    pub elapsed: Duration,
+
+    /// Access flows completed by all devices
+    pub cycles: u64,
+
+    /// Server counters
+    pub server: ServerReport,
+
+    /// Faults injected
+    pub faults_injected: u64,
+
+    /// Invariant checks performed
+    pub checks: u64,
+
+    /// Highest memory sample, if memory could be measured
+    pub peak_memory: Option<u64>,
+}
+
+/// Run a soak test until `config.duration` elapses or an invariant fails
+///
+/// # Errors
+///
+/// Returns `SoakError::InvariantViolated` with the bundle path when an
+/// invariant fails, or the setup error if the run could not start.
+pub async fn run(config: SoakConfig) -> Result<SoakReport, SoakError> {
+    config.validate()?;
+
+    // A file database, so log growth is not counted as heap growth
+    let database_path = std::env::temp_dir().join(format!(
+        "turnkey-soak-{}-{}.db",
+        std::process::id(),
+        config.seed
+    ));
+    remove_database(&database_path);
+    let db = Database::new(DatabaseConfig::new(database_path.to_string_lossy())).await?;
+    let pool = db.pool().clone();
+
+    let result = Soak::start(config, pool).await?.run().await;
+    db.close().await;
+    if result.is_ok() {
+        remove_database(&database_path);
+    }
+    result
+}
+
+fn remove_database(path: &std::path::Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+/// Running soak and its shared state
+struct Soak {
+    config: SoakConfig,
+    pool: SqlitePool,
+    server_addr: std::net::SocketAddr,
+    fleet: Fleet,
+    server_task: tokio::task::JoinHandle<()>,
+    faults: Arc<ServerFaults>,
+    stats: Arc<ServerStats>,
+    events: Arc<EventLog>,
+    stop: watch::Sender<bool>,
+}
+
+impl Soak {
+    async fn start(config: SoakConfig, pool: SqlitePool) -> Result<Self, SoakError> {
+        let mut cards: Vec<String> = sqlx::query_scalar("SELECT numero_cartao FROM cards")
+            .fetch_all(&pool)
+            .await
+            .map_err(StorageError::from)?;
+        cards.extend(UNKNOWN_CARDS.iter().map(|card| card.to_string()));
+
+        let server = TcpServer::bind(TcpServerConfig {
+            bind_addr: "127.0.0.1:0".parse().expect("valid loopback address"),
+            max_connections: usize::from(config.devices) + SPARE_CONNECTIONS,
+        })
+        .await?;
+        let server_addr = server.local_addr()?;
+
+        let (stop, stop_rx) = watch::channel(false);
+        let faults = Arc::new(ServerFaults::default());
+        let stats = Arc::new(ServerStats::default());
+        let events = Arc::new(EventLog::default());
+
+        let server_task = tokio::spawn(server::serve(
+            server,
+            pool.clone(),
+            faults.clone(),
+            stats.clone(),
+            events.clone(),
+            stop_rx.clone(),
+        ));
+        let fleet = Fleet::spawn(&config, server_addr, cards.into(), stop_rx, events.clone());
+
+        info!(
+            devices = config.devices,
+            seed = config.seed,
+            %server_addr,
+            "Soak run started"
+        );
+        events.record(format!(
+            "started: {} devices, seed {}, server {}",
+            config.devices, config.seed, server_addr
+        ));
+
+        Ok(Self {
+            config,
+            pool,
+            server_addr,
+            fleet,
+            server_task,
+            faults,
+            stats,
+            events,
+            stop,
+        })
+    }
+
+    async fn run(self) -> Result<SoakReport, SoakError> {
+        let started = Instant::now();
+        let deadline = tokio::time::Instant::now() + self.config.duration;
+        let mut checker = InvariantChecker::new(self.pool.clone(), &self.config);
+        let mut injector = self.config.fault_interval.map(|interval| {
+            FaultInjector::new(
+                self.config.seed,
+                self.fleet.len(),
+                interval,
+                self.config.response_timeout,
+            )
+        });
+
+        let mut check_timer = tokio::time::interval(self.config.check_interval);
+        check_timer.tick().await;
+        let mut next_fault = tokio::time::Instant::now()
+            + injector
+                .as_mut()
+                .map_or(self.config.duration, FaultInjector::next_delay);
+
+        let mut checks = 0;
+        let mut faults_injected = 0;
+        let mut violations = Vec::new();
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => break,
+                _ = check_timer.tick() => {
+                    checks += 1;
+                    violations = checker
+                        .check(&self.fleet.reports(), memory::allocated_bytes())
+                        .await?;
+                    if !violations.is_empty() {
+                        break;
+                    }
+                }
+                _ = tokio::time::sleep_until(next_fault), if injector.is_some() => {
+                    let injector = injector.as_mut().expect("guarded by select");
+                    let fault = injector.next_fault();
+                    self.inject(fault);
+                    faults_injected += 1;
+                    next_fault = tokio::time::Instant::now() + injector.next_delay();
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            // Let in-flight flows and lost responses settle before the
+            // final check, so a device mid-flow is not reported as stuck
+            tokio::time::sleep(self.config.response_timeout).await;
+            checks += 1;
+            violations = checker
+                .check(&self.fleet.reports(), memory::allocated_bytes())
+                .await?;
+        }
+        let elapsed = started.elapsed();
+
+        if !violations.is_empty() {
+            for violation in &violations {
+                warn!(%violation, "Soak invariant violated");
+                self.events.record(format!("violation: {}", violation));
+            }
+            let bundle = self.write_bundle(elapsed, &violations, &checker).await?;
+            self.shutdown().await;
+            return Err(SoakError::InvariantViolated { violations, bundle });
+        }
+
+        let report = SoakReport {
+            elapsed,
+            cycles: self
+                .fleet
+                .reports()
+                .iter()
+                .map(|device| device.cycles)
+                .sum(),
+            server: self.stats.report(),
+            faults_injected,
+            checks,
+            peak_memory: checker
+                .memory_samples()
+                .iter()
+                .map(|sample| sample.bytes)
+                .max(),
+        };
+        self.shutdown().await;
+        info!(
+            cycles = report.cycles,
+            faults = report.faults_injected,
+            "Soak run finished"
+        );
+        Ok(report)
+    }
+
+    fn inject(&self, fault: Fault) {
+        self.events.record(format!("fault: {}", fault));
+        match fault {
+            Fault::DropConnection { device_index } => {
+                self.fleet.request_disconnect(device_index);
+            }
+            Fault::DropResponses { count } => {
+                self.faults
+                    .drop_responses
+                    .fetch_add(count, Ordering::SeqCst);
+            }
+            Fault::StallServer { millis } => {
+                self.faults.stall_millis.store(millis, Ordering::SeqCst);
+            }
+            Fault::GarbageClient => {
+                let addr = self.server_addr;
+                let events = self.events.clone();
+                tokio::spawn(async move {
+                    match TcpStream::connect(addr).await {
+                        Ok(mut stream) => {
+                            let _ = stream.write_all(b"\x00\xffGET / HTTP/1.1\r\n\r\n").await;
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                        }
+                        Err(e) => events.record(format!("garbage client: connect failed: {}", e)),
+                    }
+                });
+            }
+        }
+    }
+
+    async fn write_bundle(
+        &self,
+        elapsed: Duration,
+        violations: &[Violation],
+        checker: &InvariantChecker,
+    ) -> Result<PathBuf, SoakError> {
+        let bundle = DiagnosticBundle {
+            created_at: Utc::now(),
+            elapsed,
+            config: self.config.clone(),
+            violations: violations.to_vec(),
+            server: self.stats.report(),
+            devices: self.fleet.reports(),
+            memory: checker.memory_samples(),
+            access_logs: DiagnosticBundle::recent_access_logs(&self.pool).await,
+            events: self.events.entries(),
+        };
+        let path = bundle.write_to(&self.config.bundle_dir)?;
+
+        // Full database copy, consistent even while the server writes
+        let copy = path.join("turnkey.db");
+        if let Err(e) = sqlx::query("VACUUM INTO ?")
+            .bind(copy.to_string_lossy())
+            .execute(&self.pool)
+            .await
+        {
+            warn!(error = %e, "Could not copy the soak database into the bundle");
+        }
+        Ok(path)
+    }
+
+    async fn shutdown(self) {
+        let _ = self.stop.send(true);
+        self.fleet.join().await;
+        let _ = self.server_task.await;
+    }
+}
@@ -0,0 +1,126 @@
+//! `turnkey-soak` - run the Turnkey stack under faults for hours
+//!
+//! ```text
+//! turnkey-soak --duration 4h --devices 16 --seed 42
+//! ```
+//!
+//! Exits with status 1 and prints the diagnostic bundle path when an
+//! invariant fails; rerun with the printed seed to repeat the fault schedule.
+
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use turnkey_soak::{SoakConfig, SoakError, parse_duration};
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "turnkey-soak",
+    about = "Soak test with fault injection and invariant checks"
+)]
+struct Args {
+    /// Number of emulated turnstiles
+    #[arg(long, default_value_t = 8)]
+    devices: u8,
+
+    /// Run time (e.g. 90s, 30m, 4h)
+    #[arg(long, default_value = "1h", value_parser = parse_duration)]
+    duration: Duration,
+
+    /// Seed for cards, timing and faults (random if omitted)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Mean time between injected faults
+    #[arg(long, default_value = "2s", value_parser = parse_duration)]
+    fault_interval: Duration,
+
+    /// Disable fault injection
+    #[arg(long)]
+    no_faults: bool,
+
+    /// Time between invariant checks
+    #[arg(long, default_value = "5s", value_parser = parse_duration)]
+    check_interval: Duration,
+
+    /// Time outside Idle after which a device counts as stuck
+    #[arg(long, default_value = "30s", value_parser = parse_duration)]
+    stuck_after: Duration,
+
+    /// Time before the memory baseline is taken
+    #[arg(long, default_value = "60s", value_parser = parse_duration)]
+    memory_warmup: Duration,
+
+    /// Largest accepted memory growth over the baseline
+    #[arg(long, default_value_t = 2.0)]
+    max_memory_growth: f64,
+
+    /// Directory for diagnostic bundles
+    #[arg(long, default_value = "soak-failures")]
+    bundle_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "info,turnkey::audit=off".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+    let config = SoakConfig {
+        devices: args.devices,
+        duration: args.duration,
+        seed: args.seed.unwrap_or_else(rand::random),
+        fault_interval: (!args.no_faults).then_some(args.fault_interval),
+        check_interval: args.check_interval,
+        stuck_after: args.stuck_after,
+        memory_warmup: args.memory_warmup,
+        max_memory_growth: args.max_memory_growth,
+        bundle_dir: args.bundle_dir,
+        ..SoakConfig::default()
+    };
+    let seed = config.seed;
+    println!(
+        "Soak: {} devices for {:?}, seed {}",
+        config.devices, config.duration, seed
+    );
+
+    match turnkey_soak::run(config).await {
+        Ok(report) => {
+            println!("Passed after {:?}", report.elapsed);
+            println!("  access flows:   {}", report.cycles);
+            println!(
+                "  server:         {} requests, {} responses, {} dropped, {} errors",
+                report.server.requests,
+                report.server.responses,
+                report.server.dropped,
+                report.server.errors
+            );
+            println!("  faults:         {}", report.faults_injected);
+            println!("  checks:         {}", report.checks);
+            if let Some(bytes) = report.peak_memory {
+                println!("  peak memory:    {} KiB", bytes / 1024);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(SoakError::InvariantViolated { violations, bundle }) => {
+            eprintln!("FAILED (seed {})", seed);
+            for violation in &violations {
+                eprintln!("  {}", violation);
+            }
+            eprintln!("Diagnostic bundle: {}", bundle.display());
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
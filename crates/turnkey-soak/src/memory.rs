@@ -0,0 +1,35 @@
+//! Process memory sampling
+
+/// Bytes currently allocated by the process, if the platform can tell
+///
+/// With the `jemalloc` feature this is jemalloc's `stats.allocated`, which
+/// counts live allocations only and is not inflated by fragmentation or
+/// memory the allocator keeps cached. It is only meaningful when jemalloc
+/// is the global allocator, as in the `turnkey-soak` binary. Without the
+/// feature the resident set size from `/proc/self/statm` is used, which is
+/// noisier but still shows unbounded growth over a long run.
+pub fn allocated_bytes() -> Option<u64> {
+    #[cfg(feature = "jemalloc")]
+    {
+        use tikv_jemalloc_ctl::{epoch, stats};
+
+        // Statistics are cached until the epoch advances
+        epoch::advance().ok()?;
+        stats::allocated::read().ok().map(|bytes| bytes as u64)
+    }
+
+    #[cfg(not(feature = "jemalloc"))]
+    {
+        resident_bytes()
+    }
+}
+
+#[cfg(not(feature = "jemalloc"))]
+fn resident_bytes() -> Option<u64> {
+    // Fields are in pages: size resident shared text lib data dt
+    const PAGE_SIZE: u64 = 4096;
+
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(resident * PAGE_SIZE)
+}
@@ -0,0 +1,162 @@
+//! Validation server side of the soak run
+
+use crate::events::EventLog;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use turnkey_core::DeviceId;
+use turnkey_network::TcpServer;
+use turnkey_protocol::commands::access::AccessResponse;
+use turnkey_protocol::{CommandCode, FieldData, Message, MessageBuilder};
+use turnkey_storage::{AccessValidator, OfflineValidator};
+
+/// Server misbehaviour requested by the fault injector
+#[derive(Debug, Default)]
+pub(crate) struct ServerFaults {
+    /// Responses still to be swallowed
+    pub(crate) drop_responses: AtomicU32,
+
+    /// Pause before the next response, in milliseconds
+    pub(crate) stall_millis: AtomicU64,
+}
+
+/// Request counters of the server loop
+#[derive(Debug, Default)]
+pub(crate) struct ServerStats {
+    requests: AtomicU64,
+    responses: AtomicU64,
+    dropped: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// Copy of the server counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerReport {
+    /// Access requests received
+    pub requests: u64,
+
+    /// Responses sent
+    pub responses: u64,
+
+    /// Responses swallowed by injected faults
+    pub dropped: u64,
+
+    /// Receive, validation or send errors
+    pub errors: u64,
+}
+
+impl ServerStats {
+    pub(crate) fn report(&self) -> ServerReport {
+        ServerReport {
+            requests: self.requests.load(Ordering::Relaxed),
+            responses: self.responses.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Answer access requests with the offline validator until stopped
+///
+/// Every request goes through the full validation flow, so access logs and
+/// the presence projection grow exactly as on a production server.
+pub(crate) async fn serve(
+    mut server: TcpServer,
+    pool: SqlitePool,
+    faults: Arc<ServerFaults>,
+    stats: Arc<ServerStats>,
+    events: Arc<EventLog>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut validators: HashMap<DeviceId, OfflineValidator> = HashMap::new();
+
+    loop {
+        let (device_id, message) = tokio::select! {
+            _ = stop.changed() => break,
+            received = server.recv_any() => match received {
+                Ok(received) => received,
+                Err(e) => {
+                    stats.errors.fetch_add(1, Ordering::Relaxed);
+                    events.record(format!("server: receive failed: {}", e));
+                    continue;
+                }
+            },
+        };
+        if message.command != CommandCode::AccessRequest {
+            continue;
+        }
+        stats.requests.fetch_add(1, Ordering::Relaxed);
+
+        let validator = validators
+            .entry(device_id)
+            .or_insert_with(|| OfflineValidator::new(pool.clone()).with_device_id(device_id));
+        let response = match validate(validator, &message).await {
+            Ok(response) => response,
+            Err(e) => {
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+                events.record(format!("server: device {}: {}", device_id, e));
+                continue;
+            }
+        };
+
+        let stall = faults.stall_millis.swap(0, Ordering::SeqCst);
+        if stall > 0 {
+            tokio::time::sleep(Duration::from_millis(stall)).await;
+        }
+        let dropped = faults
+            .drop_responses
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if dropped {
+            stats.dropped.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+
+        let sent = match response_message(device_id, &response) {
+            Ok(reply) => server
+                .send(device_id, reply)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(()) => {
+                stats.responses.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+                events.record(format!(
+                    "server: reply to device {} failed: {}",
+                    device_id, e
+                ));
+            }
+        }
+    }
+}
+
+async fn validate(
+    validator: &mut OfflineValidator,
+    message: &Message,
+) -> Result<AccessResponse, String> {
+    let request = message.as_access_request().map_err(|e| e.to_string())?;
+    validator
+        .validate(&request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Encode a decision the way `OnlineValidator` expects it: `seconds]message`
+fn response_message(device_id: DeviceId, response: &AccessResponse) -> Result<Message, String> {
+    let build = || -> turnkey_core::Result<Message> {
+        let command = CommandCode::parse(response.decision().command_code())?;
+        MessageBuilder::new(device_id, command)
+            .field(FieldData::new(response.timeout_seconds().to_string())?)
+            .field(FieldData::new(response.display_message().to_string())?)
+            .build()
+    };
+    build().map_err(|e| e.to_string())
+}
@@ -0,0 +1,74 @@
+//! Tap-to-display latency budget on the mock stack, offline
+//!
+//! Runs a few hundred taps through a mock reader, the offline validator on a
+//! SQLite file and the emulator display, and fails if any tap takes longer
+//! than the product budget.
+
+use turnkey_core::latency::{DEFAULT_LATENCY_BUDGET, LatencyStage};
+use turnkey_core::{AccessDirection, DeviceId};
+use turnkey_hardware::mock::MockRfid;
+use turnkey_hardware::traits::CardType;
+use turnkey_soak::latency::OfflinePipeline;
+use turnkey_storage::{Database, DatabaseConfig, OfflineValidator};
+
+/// Taps per run; enough for a meaningful p99
+const TAPS: usize = 300;
+
+/// Seeded card of user 1002 (card number 22823433)
+const REGISTERED_UID: [u8; 4] = [0x01, 0x5C, 0x42, 0x09];
+
+/// Card with no registration
+const UNKNOWN_UID: [u8; 4] = [0x7F, 0x00, 0x00, 0x01];
+
+#[tokio::test]
+async fn test_offline_tap_to_display_within_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("latency.db");
+    let db = Database::new(DatabaseConfig::new(path.to_string_lossy()))
+        .await
+        .unwrap();
+
+    let device_id = DeviceId::new(15).unwrap();
+    let (reader, mut handle) = MockRfid::new();
+    handle
+        .add_card(REGISTERED_UID.to_vec(), CardType::MifareClassic1K)
+        .await;
+    handle
+        .add_card(UNKNOWN_UID.to_vec(), CardType::MifareClassic1K)
+        .await;
+    let validator = OfflineValidator::new(db.pool().clone()).with_device_id(device_id);
+    let mut pipeline = OfflinePipeline::new(device_id, reader, validator);
+
+    let mut granted = 0;
+    for i in 0..TAPS {
+        let (uid, direction) = match i % 3 {
+            0 => (REGISTERED_UID, AccessDirection::Entry),
+            1 => (REGISTERED_UID, AccessDirection::Exit),
+            _ => (UNKNOWN_UID, AccessDirection::Entry),
+        };
+        handle.present_card(uid.to_vec()).await.unwrap();
+        let tap = pipeline.tap(direction).await.unwrap();
+        if tap.granted {
+            granted += 1;
+        }
+    }
+
+    let report = pipeline.report();
+    eprintln!("{report}");
+
+    assert_eq!(report.devices.len(), 1);
+    let device = &report.devices[0];
+    assert_eq!(device.taps, TAPS as u64);
+    assert!(granted > 0 && granted < TAPS, "granted {granted} of {TAPS}");
+    assert!(device.stages[&LatencyStage::Log].max > std::time::Duration::ZERO);
+    assert!(
+        report.within_budget(),
+        "{} taps over the {:?} budget, p99 {:?}, max {:?}",
+        device.over_budget,
+        DEFAULT_LATENCY_BUDGET,
+        device.total.p99,
+        device.total.max
+    );
+
+    db.close().await;
+}
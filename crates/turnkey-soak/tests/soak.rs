@@ -0,0 +1,88 @@
+//! Short soak runs exercising the whole harness
+
+use std::time::Duration;
+use turnkey_soak::{
+    DiagnosticBundle, Invariant, ServerReport, SoakConfig, SoakError, Violation, run,
+};
+
+fn short_config(bundle_dir: &std::path::Path) -> SoakConfig {
+    SoakConfig {
+        devices: 3,
+        duration: Duration::from_secs(3),
+        seed: 7,
+        fault_interval: Some(Duration::from_millis(300)),
+        check_interval: Duration::from_millis(500),
+        stuck_after: Duration::from_secs(5),
+        response_timeout: Duration::from_millis(300),
+        memory_warmup: Duration::from_secs(1),
+        // The test binary does not use jemalloc; growth is not meaningful
+        max_memory_growth: 1000.0,
+        bundle_dir: bundle_dir.to_path_buf(),
+    }
+}
+
+#[tokio::test]
+async fn test_short_soak_with_faults_holds_invariants() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let report = match run(short_config(dir.path())).await {
+        Ok(report) => report,
+        Err(SoakError::InvariantViolated { violations, bundle }) => {
+            panic!("violations {:?}, bundle {}", violations, bundle.display())
+        }
+        Err(e) => panic!("soak failed: {}", e),
+    };
+
+    assert!(report.cycles > 0);
+    assert!(report.server.requests > 0);
+    assert!(report.faults_injected > 0);
+    assert!(report.checks >= 2);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_invalid_config_is_rejected_before_starting() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SoakConfig {
+        devices: 0,
+        ..short_config(dir.path())
+    };
+
+    assert!(matches!(run(config).await, Err(SoakError::Config(_))));
+}
+
+#[test]
+fn test_bundle_is_written_as_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = DiagnosticBundle {
+        created_at: chrono::Utc::now(),
+        elapsed: Duration::from_secs(90),
+        config: short_config(dir.path()),
+        violations: vec![Violation {
+            invariant: Invariant::StuckStateMachine,
+            detail: "device 2 in WaitingRotation for 45s".to_string(),
+        }],
+        server: ServerReport::default(),
+        devices: Vec::new(),
+        memory: Vec::new(),
+        access_logs: Vec::new(),
+        events: vec!["fault: garbage client".to_string()],
+    };
+
+    let path = bundle.write_to(dir.path()).unwrap();
+
+    for file in [
+        "manifest.json",
+        "devices.json",
+        "memory.json",
+        "access_logs.json",
+        "events.log",
+    ] {
+        assert!(path.join(file).exists(), "missing {}", file);
+    }
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path.join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["config"]["seed"], 7);
+    assert_eq!(manifest["violations"][0]["invariant"], "StuckStateMachine");
+}
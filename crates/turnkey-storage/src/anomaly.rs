@@ -0,0 +1,557 @@
+//! Entry-rate analytics with anomaly detection.
+//!
+//! [`RateAnalyzer`] follows the access logs published on an
+//! [`EventBus`](crate::events::EventBus) and keeps, per device, the number of
+//! granted entries in a rolling window. It raises a [`SecurityAnomaly`] when
+//! that rate crosses a fixed limit, or when it is far above what the device
+//! usually sees at that hour of the day (a simple z-score against a per-hour
+//! baseline). Fifty entries in a minute is a normal shift change at 8am and an
+//! incident at 3am; the baseline tells them apart.
+//!
+//! Anomalies are published on their own bus, serialized as JSON with a `type`
+//! tag, so webhook or MQTT forwarders subscribe to it like any other consumer.
+//! The analyzer is optional: nothing runs unless it is started.
+//!
+//! # Baseline
+//!
+//! Time is cut into periods of one window. Every finished period adds its
+//! entry count to the baseline of its hour of day, and periods without
+//! entries count as zero, so a quiet night has a baseline near zero. Silent
+//! gaps longer than a day count as one day. The z-score is only used once
+//! the hour has [`min_baseline_periods`](RateAnalyzerConfig::min_baseline_periods)
+//! periods, and the standard deviation is floored at
+//! [`min_std_dev`](RateAnalyzerConfig::min_std_dev) so a flat baseline does
+//! not turn every extra entry into an alert.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use turnkey_storage::anomaly::{RateAnalyzer, RateAnalyzerConfig};
+//! use turnkey_storage::events::{EventBus, SubscriptionOptions};
+//! use turnkey_storage::models::AccessLog;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let logs: EventBus<AccessLog> = EventBus::new(0);
+//! let anomalies = EventBus::new(16);
+//! let mut webhook = anomalies
+//!     .subscribe(SubscriptionOptions::new("webhook"))
+//!     .await;
+//!
+//! let config = RateAnalyzerConfig::new(Duration::from_secs(60)).max_entries(Some(30));
+//! let subscription = logs.subscribe(SubscriptionOptions::new("rate-analyzer")).await;
+//! tokio::spawn(RateAnalyzer::new(config).run(subscription, anomalies));
+//!
+//! // ... validators publish their logs on `logs`
+//! # drop(logs);
+//! while let Some(anomaly) = webhook.recv().await {
+//!     println!("{}", serde_json::to_string(&anomaly).unwrap());
+//! }
+//! # }
+//! ```
+
+use crate::events::{EventBus, Subscription};
+use crate::models::{AccessLog, Direction};
+use chrono::{DateTime, FixedOffset, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::Duration;
+
+/// Default rolling window for entry rates
+pub const DEFAULT_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Default z-score above which a rate is an outlier
+pub const DEFAULT_Z_SCORE_THRESHOLD: f64 = 4.0;
+
+/// Hours in the per-hour baseline
+const HOURS_PER_DAY: usize = 24;
+
+/// Seconds in a day, the longest silent gap added to the baseline
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Settings of a [`RateAnalyzer`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateAnalyzerConfig {
+    /// Rolling window the entry rate is counted over (at least 1s)
+    pub window: Duration,
+
+    /// Raise an anomaly when a window holds this many entries (None = off)
+    pub max_entries: Option<u32>,
+
+    /// Raise an anomaly when the rate is this many standard deviations
+    /// above the baseline of the hour (None = off)
+    pub z_score: Option<f64>,
+
+    /// Finished periods an hour needs before its baseline is trusted
+    pub min_baseline_periods: u64,
+
+    /// Entries a window needs before it can be an outlier
+    pub min_entries: u32,
+
+    /// Floor of the baseline standard deviation
+    pub min_std_dev: f64,
+
+    /// Quiet time per device after an anomaly
+    pub cooldown: Duration,
+
+    /// Offset of the site's local time, for the hour of day
+    pub utc_offset: FixedOffset,
+}
+
+impl RateAnalyzerConfig {
+    /// Z-score detection over `window`, with a cooldown of one window
+    pub fn new(window: Duration) -> Self {
+        let window = window.max(Duration::from_secs(1));
+        Self {
+            window,
+            max_entries: None,
+            z_score: Some(DEFAULT_Z_SCORE_THRESHOLD),
+            min_baseline_periods: 60,
+            min_entries: 10,
+            min_std_dev: 1.0,
+            cooldown: window,
+            utc_offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+        }
+    }
+
+    /// Set the fixed entry limit per window
+    pub fn max_entries(mut self, max_entries: Option<u32>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set the z-score threshold
+    pub fn z_score(mut self, z_score: Option<f64>) -> Self {
+        self.z_score = z_score;
+        self
+    }
+
+    /// Set the finished periods an hour needs before its baseline is used
+    pub fn min_baseline_periods(mut self, periods: u64) -> Self {
+        self.min_baseline_periods = periods;
+        self
+    }
+
+    /// Set the entries a window needs before it can be an outlier
+    pub fn min_entries(mut self, entries: u32) -> Self {
+        self.min_entries = entries;
+        self
+    }
+
+    /// Set the floor of the baseline standard deviation
+    pub fn min_std_dev(mut self, std_dev: f64) -> Self {
+        self.min_std_dev = std_dev;
+        self
+    }
+
+    /// Set the quiet time per device after an anomaly
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Set the offset of the site's local time
+    pub fn utc_offset(mut self, offset: FixedOffset) -> Self {
+        self.utc_offset = offset;
+        self
+    }
+}
+
+impl Default for RateAnalyzerConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_RATE_WINDOW)
+    }
+}
+
+/// Why an entry rate was flagged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnomalyKind {
+    /// The window reached the fixed limit
+    EntryRateLimit {
+        /// Configured limit
+        limit: u32,
+    },
+
+    /// The window is far above the baseline of the hour
+    EntryRateOutlier {
+        /// Usual entries per window at this hour
+        baseline_mean: f64,
+
+        /// Standard deviation used, after the floor
+        baseline_std_dev: f64,
+
+        /// Standard deviations above the baseline
+        z_score: f64,
+    },
+}
+
+/// Unusual entry rate on a device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityAnomaly {
+    /// Device the entries were made on
+    pub device_id: i64,
+
+    /// Time of the entry that raised the anomaly
+    pub detected_at: DateTime<Utc>,
+
+    /// Entries in the window ending at `detected_at`
+    pub entries: u32,
+
+    /// Length of the window in seconds
+    pub window_secs: u64,
+
+    /// Rule that flagged the rate
+    #[serde(flatten)]
+    pub kind: AnomalyKind,
+}
+
+impl fmt::Display for SecurityAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "device {}: {} entries in {}s at {}",
+            self.device_id, self.entries, self.window_secs, self.detected_at
+        )?;
+        match &self.kind {
+            AnomalyKind::EntryRateLimit { limit } => write!(f, " (limit {})", limit),
+            AnomalyKind::EntryRateOutlier {
+                baseline_mean,
+                z_score,
+                ..
+            } => write!(f, " (usually {:.1}, z-score {:.1})", baseline_mean, z_score),
+        }
+    }
+}
+
+/// Entry counts of finished periods in one hour of day
+#[derive(Debug, Clone, Copy, Default)]
+struct HourBaseline {
+    periods: u64,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl HourBaseline {
+    fn add(&mut self, count: u32, periods: u64) {
+        let count = f64::from(count);
+        self.periods += periods;
+        self.sum += count * periods as f64;
+        self.sum_sq += count * count * periods as f64;
+    }
+
+    fn mean_and_std_dev(&self) -> (f64, f64) {
+        let n = self.periods as f64;
+        let mean = self.sum / n;
+        let variance = (self.sum_sq / n - mean * mean).max(0.0);
+        (mean, variance.sqrt())
+    }
+}
+
+/// Rolling rate and baseline of one device
+#[derive(Debug)]
+struct DeviceRate {
+    recent: VecDeque<DateTime<Utc>>,
+    period: i64,
+    period_entries: u32,
+    baseline: [HourBaseline; HOURS_PER_DAY],
+    last_anomaly: Option<DateTime<Utc>>,
+}
+
+/// Per-device entry rates with anomaly detection
+#[derive(Debug)]
+pub struct RateAnalyzer {
+    config: RateAnalyzerConfig,
+    devices: HashMap<i64, DeviceRate>,
+}
+
+impl RateAnalyzer {
+    /// Create an analyzer without history
+    pub fn new(config: RateAnalyzerConfig) -> Self {
+        Self {
+            config,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Settings in use
+    pub fn config(&self) -> &RateAnalyzerConfig {
+        &self.config
+    }
+
+    /// Entries of a device in the window ending at its latest entry
+    pub fn current_rate(&self, device_id: i64) -> u32 {
+        self.devices
+            .get(&device_id)
+            .map_or(0, |device| device.recent.len() as u32)
+    }
+
+    /// Account for an access log
+    ///
+    /// Only granted entries with a device count; everything else is
+    /// ignored. Returns the anomaly the entry raised, if any.
+    pub fn observe(&mut self, log: &AccessLog) -> Option<SecurityAnomaly> {
+        let device_id = log.device_id?;
+        if !log.granted || log.get_direction() != Some(Direction::Entry) {
+            return None;
+        }
+        self.observe_entry(device_id, log.timestamp)
+    }
+
+    /// Account for a granted entry on a device at `at`
+    pub fn observe_entry(&mut self, device_id: i64, at: DateTime<Utc>) -> Option<SecurityAnomaly> {
+        let window_secs = self.config.window.as_secs() as i64;
+        let period = at.timestamp().div_euclid(window_secs);
+        let device = self.devices.entry(device_id).or_insert_with(|| DeviceRate {
+            recent: VecDeque::new(),
+            period,
+            period_entries: 0,
+            baseline: [HourBaseline::default(); HOURS_PER_DAY],
+            last_anomaly: None,
+        });
+
+        if period > device.period {
+            let finished = device.period;
+            let count = device.period_entries;
+            Self::close_periods(&self.config, device, finished, count, period);
+            device.period = period;
+            device.period_entries = 0;
+        }
+        device.period_entries += 1;
+
+        // Late entries count toward the window they belong to
+        let position = device.recent.partition_point(|t| *t <= at);
+        device.recent.insert(position, at);
+        let latest = *device.recent.back().expect("an entry was just added");
+        let window = chrono::Duration::seconds(window_secs);
+        while device.recent.front().is_some_and(|t| *t <= latest - window) {
+            device.recent.pop_front();
+        }
+        let entries = device.recent.len() as u32;
+
+        let cooling_down = device.last_anomaly.is_some_and(|last| {
+            at.signed_duration_since(last).to_std().unwrap_or_default() < self.config.cooldown
+        });
+        if cooling_down {
+            return None;
+        }
+
+        let kind = Self::check(&self.config, device, entries, at)?;
+        device.last_anomaly = Some(at);
+        Some(SecurityAnomaly {
+            device_id,
+            detected_at: at,
+            entries,
+            window_secs: window_secs as u64,
+            kind,
+        })
+    }
+
+    /// Follow logs from a bus and publish anomalies on another
+    ///
+    /// Returns the analyzer when the log bus closes.
+    pub async fn run(
+        mut self,
+        mut logs: Subscription<AccessLog>,
+        anomalies: EventBus<SecurityAnomaly>,
+    ) -> Self {
+        while let Some(log) = logs.recv().await {
+            if let Some(anomaly) = self.observe(&log) {
+                tracing::warn!(
+                    device_id = anomaly.device_id,
+                    entries = anomaly.entries,
+                    "{}",
+                    anomaly
+                );
+                anomalies.publish(anomaly).await;
+            }
+        }
+        self
+    }
+
+    fn check(
+        config: &RateAnalyzerConfig,
+        device: &DeviceRate,
+        entries: u32,
+        at: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        if let Some(limit) = config.max_entries
+            && entries >= limit
+        {
+            return Some(AnomalyKind::EntryRateLimit { limit });
+        }
+
+        let threshold = config.z_score?;
+        if entries < config.min_entries {
+            return None;
+        }
+        let baseline = &device.baseline[Self::hour(config, at.timestamp())];
+        if baseline.periods < config.min_baseline_periods.max(1) {
+            return None;
+        }
+        let (mean, std_dev) = baseline.mean_and_std_dev();
+        let std_dev = std_dev.max(config.min_std_dev).max(f64::EPSILON);
+        let z_score = (f64::from(entries) - mean) / std_dev;
+        (z_score >= threshold).then_some(AnomalyKind::EntryRateOutlier {
+            baseline_mean: mean,
+            baseline_std_dev: std_dev,
+            z_score,
+        })
+    }
+
+    /// Add a finished period and the silent ones up to `next` to the baseline
+    fn close_periods(
+        config: &RateAnalyzerConfig,
+        device: &mut DeviceRate,
+        finished: i64,
+        entries: u32,
+        next: i64,
+    ) {
+        let window_secs = config.window.as_secs() as i64;
+        let hour_of = |period: i64| Self::hour(config, period * window_secs);
+        device.baseline[hour_of(finished)].add(entries, 1);
+
+        let max_silent = (SECONDS_PER_DAY / window_secs).max(1);
+        let silent_start = (finished + 1).max(next - max_silent);
+        for period in silent_start..next {
+            device.baseline[hour_of(period)].add(0, 1);
+        }
+    }
+
+    fn hour(config: &RateAnalyzerConfig, timestamp: i64) -> usize {
+        DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .with_timezone(&config.utc_offset)
+            .hour() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SubscriptionOptions;
+    use crate::models::ReaderType;
+    use chrono::TimeZone;
+
+    fn entry(device_id: i64, at: DateTime<Utc>) -> AccessLog {
+        AccessLog::new(
+            None,
+            None,
+            "00000000000022823433".to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            true,
+            None,
+            at,
+        )
+        .with_device_clock(device_id, at)
+    }
+
+    fn at(day: u32, hour: u32, minute: u32, millis: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 5, day, hour, minute, 0).unwrap()
+            + chrono::Duration::milliseconds(millis)
+    }
+
+    #[test]
+    fn test_fixed_limit_with_cooldown() {
+        let config = RateAnalyzerConfig::new(Duration::from_secs(60))
+            .max_entries(Some(10))
+            .z_score(None);
+        let mut analyzer = RateAnalyzer::new(config);
+
+        let anomalies: Vec<_> = (0..30)
+            .filter_map(|i| analyzer.observe(&entry(15, at(10, 12, 0, i * 1000))))
+            .collect();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].entries, 10);
+        assert_eq!(anomalies[0].kind, AnomalyKind::EntryRateLimit { limit: 10 });
+        assert_eq!(analyzer.current_rate(15), 30);
+
+        // Another device has its own rate, exits and denials do not count
+        let mut exit = entry(16, at(10, 12, 0, 0));
+        exit.direction = Direction::Exit.into();
+        assert!(analyzer.observe(&exit).is_none());
+        assert_eq!(analyzer.current_rate(16), 0);
+
+        // After the cooldown the device can alert again
+        let anomaly = (0..10)
+            .filter_map(|i| analyzer.observe(&entry(15, at(10, 12, 2, i * 1000))))
+            .next();
+        assert!(anomaly.is_some());
+    }
+
+    #[test]
+    fn test_outlier_against_hourly_baseline() {
+        let mut analyzer = RateAnalyzer::new(RateAnalyzerConfig::default());
+
+        // Three days of a busy 9am hour: 15 to 24 entries a minute
+        for day in 1..=3 {
+            for minute in 0..60 {
+                let count = 15 + (minute % 10) as i64;
+                for i in 0..count {
+                    let log = entry(15, at(day, 9, minute, i * 60_000 / count));
+                    assert!(analyzer.observe(&log).is_none(), "day {day} 9:{minute}");
+                }
+            }
+        }
+
+        // A busy minute at 9am is normal
+        for i in 0..24 {
+            assert!(
+                analyzer
+                    .observe(&entry(15, at(4, 9, 30, i * 2500)))
+                    .is_none()
+            );
+        }
+
+        // The same burst at 3am is not
+        let anomalies: Vec<_> = (0..50)
+            .filter_map(|i| analyzer.observe(&entry(15, at(5, 3, 0, i * 500))))
+            .collect();
+        assert_eq!(anomalies.len(), 1);
+        let anomaly = &anomalies[0];
+        assert_eq!(anomaly.entries, 10);
+        match anomaly.kind {
+            AnomalyKind::EntryRateOutlier {
+                baseline_mean,
+                z_score,
+                ..
+            } => {
+                assert!(baseline_mean < 0.1, "mean {baseline_mean}");
+                assert!(z_score >= DEFAULT_Z_SCORE_THRESHOLD);
+            }
+            ref other => panic!("unexpected {other:?}"),
+        }
+
+        let json = serde_json::to_value(anomaly).unwrap();
+        assert_eq!(json["type"], "entry_rate_outlier");
+        assert_eq!(json["device_id"], 15);
+    }
+
+    #[tokio::test]
+    async fn test_run_publishes_anomalies() {
+        let logs = EventBus::new(0);
+        let anomalies = EventBus::new(0);
+        let mut webhook = anomalies
+            .subscribe(SubscriptionOptions::new("webhook"))
+            .await;
+        let subscription = logs
+            .subscribe(SubscriptionOptions::new("rate-analyzer"))
+            .await;
+        let config = RateAnalyzerConfig::default().max_entries(Some(5));
+        let analyzer = tokio::spawn(RateAnalyzer::new(config).run(subscription, anomalies));
+
+        for i in 0..8 {
+            logs.publish(entry(15, at(10, 3, 0, i * 1000))).await;
+        }
+        drop(logs);
+        let analyzer = analyzer.await.unwrap();
+
+        let anomaly = webhook.recv().await.unwrap();
+        assert_eq!(anomaly.entries, 5);
+        assert_eq!(analyzer.current_rate(15), 8);
+        assert!(webhook.recv().await.is_none());
+    }
+}
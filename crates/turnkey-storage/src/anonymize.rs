@@ -0,0 +1,497 @@
+//! Anonymization of a person's access history for erasure requests.
+//!
+//! LGPD and GDPR let a person ask for their data to be erased, but deleting
+//! their access logs would change occupancy figures, daily statistics and
+//! every report built on them. [`Anonymizer`] keeps the rows and replaces
+//! what identifies the person instead:
+//!
+//! - `access_logs`: the card number becomes a pseudonym; `user_id` and
+//!   `matricula` are cleared
+//! - `access_state`: the credential row is moved to the same pseudonym and
+//!   unlinked from the user
+//! - `card_block_events`: card number and matricula become pseudonyms and
+//!   the card link is cleared
+//!
+//! Each credential gets its own pseudonym, so per-credential counters and
+//! the derived state stay consistent with the logs. `user_id` and
+//! `matricula` in `access_logs` reference `users` and cannot hold a
+//! pseudonym, so they are cleared.
+//!
+//! # Irreversibility
+//!
+//! Pseudonyms are `ANON` followed by 16 hex digits of a SHA-256 over the
+//! original value and a random key generated for the run. The key is never
+//! stored, so a pseudonym cannot be traced back to the card or matricula,
+//! not even by hashing candidate values.
+//!
+//! # Audit
+//!
+//! Every run writes a row to `anonymizations` with the operator, the reason
+//! and how many rows changed per table. The subject is recorded by its
+//! pseudonym only.
+//!
+//! # Usage Pattern
+//!
+//! Run the job while the user still exists: deleting the user first clears
+//! the links the job follows to find the history.
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::anonymize::Anonymizer;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let anonymizer = Anonymizer::new(db.pool().clone());
+//!
+//! let record = anonymizer
+//!     .anonymize_user("EMP001", "dpo", "Erasure request #2025-113")
+//!     .await?;
+//! println!(
+//!     "{}: {} logs anonymized across {} credentials",
+//!     record.subject, record.access_logs, record.credentials
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+/// Prefix of every pseudonym, so anonymized values are recognizable
+pub const PSEUDONYM_PREFIX: &str = "ANON";
+
+/// Audit record of one anonymization run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AnonymizationRecord {
+    /// Record ID
+    pub id: i64,
+
+    /// Pseudonym that replaced the subject's matricula
+    pub subject: String,
+
+    /// Operator who ran the job
+    pub requested_by: String,
+
+    /// Reason, e.g. the erasure request reference
+    pub reason: String,
+
+    /// Distinct credentials (card numbers) anonymized
+    pub credentials: i64,
+
+    /// `access_logs` rows rewritten
+    pub access_logs: i64,
+
+    /// `access_state` rows rewritten
+    pub access_states: i64,
+
+    /// `card_block_events` rows rewritten
+    pub card_block_events: i64,
+
+    /// When the job ran
+    pub performed_at: DateTime<Utc>,
+}
+
+/// Replaces a person's identifying fields across the access history
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    pool: SqlitePool,
+}
+
+impl Anonymizer {
+    /// Create an anonymizer for the given database pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Anonymize the access history of the user with `matricula`
+    ///
+    /// Credentials are every card registered to the user plus every card
+    /// number found in their logs and block history. Logs of those
+    /// credentials are rewritten unless they are identified as another
+    /// user's. Everything, including the audit record, is written in one
+    /// transaction. Running it again for the same user finds nothing left
+    /// to rewrite and records a run with zero counts.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::Validation` if the operator or reason is
+    /// empty or too long, `StorageError::NotFound` if no user has this
+    /// matricula, or a database error; nothing is changed in those cases.
+    pub async fn anonymize_user(
+        &self,
+        matricula: &str,
+        requested_by: &str,
+        reason: &str,
+    ) -> StorageResult<AnonymizationRecord> {
+        let requested_by = requested_by.trim();
+        let reason = reason.trim();
+        if requested_by.is_empty() || requested_by.chars().count() > 100 {
+            return Err(StorageError::Validation(
+                "Operator must be 1-100 characters".to_string(),
+            ));
+        }
+        if reason.is_empty() || reason.chars().count() > 200 {
+            return Err(StorageError::Validation(
+                "Reason must be 1-200 characters".to_string(),
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let user_id = sqlx::query_scalar!(
+            r#"SELECT id AS "id!" FROM users WHERE matricula = ?"#,
+            matricula
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| StorageError::NotFound {
+            entity_type: "User".to_string(),
+            field: "matricula".to_string(),
+            value: matricula.to_string(),
+        })?;
+
+        let credentials = sqlx::query_scalar!(
+            r#"
+            SELECT numero_cartao AS "numero_cartao!" FROM cards WHERE user_id = ?1
+            UNION
+            SELECT card_number FROM access_logs WHERE user_id = ?1 OR matricula = ?2
+            UNION
+            SELECT numero_cartao FROM card_block_events WHERE matricula = ?2
+            "#,
+            user_id,
+            matricula
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let key = Self::generate_key();
+        let subject = pseudonym(&key, matricula);
+        let mut counts = (0u64, 0u64, 0u64);
+
+        for card_number in &credentials {
+            let alias = pseudonym(&key, card_number);
+
+            counts.0 += sqlx::query!(
+                r#"
+                UPDATE access_logs
+                SET card_number = ?, user_id = NULL, matricula = NULL
+                WHERE card_number = ?
+                  AND (user_id = ? OR matricula = ? OR (user_id IS NULL AND matricula IS NULL))
+                "#,
+                alias,
+                card_number,
+                user_id,
+                matricula
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            // The derived row follows its logs; it stays in place if the
+            // credential still has logs identified as someone else's
+            counts.1 += sqlx::query!(
+                r#"
+                UPDATE access_state
+                SET card_number = ?, user_id = NULL
+                WHERE card_number = ?
+                  AND NOT EXISTS (SELECT 1 FROM access_logs WHERE card_number = ?)
+                "#,
+                alias,
+                card_number,
+                card_number
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            counts.2 += sqlx::query!(
+                r#"
+                UPDATE card_block_events
+                SET numero_cartao = ?, matricula = ?, card_id = NULL
+                WHERE numero_cartao = ? AND matricula = ?
+                "#,
+                alias,
+                subject,
+                card_number,
+                matricula
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        let credential_count = credentials.len() as i64;
+        let (access_logs, access_states, card_block_events) =
+            (counts.0 as i64, counts.1 as i64, counts.2 as i64);
+        let performed_at = Utc::now();
+        let record = sqlx::query_as!(
+            AnonymizationRecord,
+            r#"
+            INSERT INTO anonymizations (
+                subject, requested_by, reason, credentials,
+                access_logs, access_states, card_block_events, performed_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING id AS "id!", subject, requested_by, reason, credentials,
+                      access_logs, access_states, card_block_events,
+                      performed_at AS "performed_at: _"
+            "#,
+            subject,
+            requested_by,
+            reason,
+            credential_count,
+            access_logs,
+            access_states,
+            card_block_events,
+            performed_at
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(
+            subject = %record.subject,
+            requested_by = %record.requested_by,
+            credentials = record.credentials,
+            access_logs = record.access_logs,
+            "access history anonymized"
+        );
+        Ok(record)
+    }
+
+    /// Audit records of past runs, most recent first
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the query fails.
+    pub async fn history(&self) -> StorageResult<Vec<AnonymizationRecord>> {
+        let records = sqlx::query_as!(
+            AnonymizationRecord,
+            r#"
+            SELECT id AS "id!", subject, requested_by, reason, credentials,
+                   access_logs, access_states, card_block_events,
+                   performed_at AS "performed_at: _"
+            FROM anonymizations
+            ORDER BY performed_at DESC, id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Random key for one run, dropped when the run ends
+    fn generate_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        key
+    }
+}
+
+/// Pseudonym of `value` under `key` (20 characters, valid as a card number)
+fn pseudonym(key: &[u8; 32], value: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(key)
+        .chain_update(value.as_bytes())
+        .finalize();
+    format!("{}{}", PSEUDONYM_PREFIX, hex::encode_upper(&digest[..8]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{AccessLog, Card, Direction, ReaderType, User};
+    use crate::replay::AccessStateReplay;
+    use crate::repositories::{
+        AccessLogRepository, CardBlockRepository, CardRepository, SqliteAccessLogRepository,
+        SqliteCardBlockRepository, SqliteCardRepository, SqliteUserRepository, UserRepository,
+    };
+    use crate::{BlockReason, CardSelector};
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn create_user_with_card(db: &Database, matricula: &str, numero: &str) -> i64 {
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Erasure Test".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: true,
+            allow_bio: false,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let user_id = SqliteUserRepository::new(db.pool().clone())
+            .create(&user)
+            .await
+            .unwrap();
+
+        let card = Card {
+            id: 0,
+            numero_cartao: numero.to_string(),
+            matricula: matricula.to_string(),
+            user_id,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteCardRepository::new(db.pool().clone())
+            .create(&card)
+            .await
+            .unwrap();
+        user_id
+    }
+
+    async fn insert_log(db: &Database, user: Option<(i64, &str)>, card_number: &str) {
+        let log = AccessLog::new(
+            user.map(|(id, _)| id),
+            user.map(|(_, matricula)| matricula.to_string()),
+            card_number.to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            true,
+            None,
+            Utc::now(),
+        );
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create(&log)
+            .await
+            .unwrap();
+    }
+
+    async fn logs_of_card(db: &Database, card_number: &str) -> i64 {
+        sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM access_logs WHERE card_number = ?",
+            card_number
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap()
+    }
+
+    async fn logs_of_matricula(db: &Database, matricula: &str) -> i64 {
+        sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM access_logs WHERE matricula = ?",
+            matricula
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap()
+    }
+
+    async fn block_events_of_matricula(db: &Database, matricula: &str) -> i64 {
+        sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM card_block_events WHERE matricula = ?",
+            matricula
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap()
+    }
+
+    async fn total_logs(db: &Database) -> i64 {
+        sqlx::query_scalar!("SELECT COUNT(*) FROM access_logs")
+            .fetch_one(db.pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_anonymize_user_keeps_counts() {
+        let db = setup_test_db().await;
+        let user_id = create_user_with_card(&db, "ERA001", "8100001").await;
+        let other_id = create_user_with_card(&db, "ERA002", "8100002").await;
+
+        insert_log(&db, Some((user_id, "ERA001")), "8100001").await;
+        insert_log(&db, Some((user_id, "ERA001")), "8100001").await;
+        insert_log(&db, None, "8100001").await;
+        insert_log(&db, Some((other_id, "ERA002")), "8100002").await;
+        SqliteCardBlockRepository::new(db.pool().clone())
+            .block(
+                &CardSelector::CardNumbers(vec!["8100001".to_string()]),
+                BlockReason::Lost,
+                "security",
+            )
+            .await
+            .unwrap();
+        let total_before = total_logs(&db).await;
+
+        let record = Anonymizer::new(db.pool().clone())
+            .anonymize_user("ERA001", "dpo", "Erasure request 113")
+            .await
+            .unwrap();
+        assert!(record.subject.starts_with(PSEUDONYM_PREFIX));
+        assert_eq!(record.subject.len(), 20);
+        assert_eq!(record.credentials, 1);
+        assert_eq!(record.access_logs, 3);
+        assert_eq!(record.access_states, 1);
+        assert_eq!(record.card_block_events, 1);
+
+        // Nothing identifying is left, and no row was lost
+        assert_eq!(logs_of_card(&db, "8100001").await, 0);
+        assert_eq!(logs_of_matricula(&db, "ERA001").await, 0);
+        assert_eq!(block_events_of_matricula(&db, "ERA001").await, 0);
+        assert_eq!(total_logs(&db).await, total_before);
+
+        let alias = sqlx::query_scalar!(
+            "SELECT card_number FROM access_logs WHERE card_number LIKE 'ANON%' LIMIT 1"
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap();
+        assert_eq!(logs_of_card(&db, &alias).await, 3);
+
+        // Other users are untouched and the derived state still matches
+        assert_eq!(logs_of_card(&db, "8100002").await, 1);
+        let report = AccessStateReplay::new(db.pool().clone())
+            .verify()
+            .await
+            .unwrap();
+        assert!(report.is_consistent(), "{:?}", report.mismatches);
+
+        let history = Anonymizer::new(db.pool().clone()).history().await.unwrap();
+        assert_eq!(history, vec![record]);
+    }
+
+    #[tokio::test]
+    async fn test_anonymize_user_validation() {
+        let db = setup_test_db().await;
+        let anonymizer = Anonymizer::new(db.pool().clone());
+
+        assert!(matches!(
+            anonymizer.anonymize_user("NOPE01", "dpo", "request").await,
+            Err(StorageError::NotFound { .. })
+        ));
+        assert!(matches!(
+            anonymizer.anonymize_user("NOPE01", " ", "request").await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(anonymizer.history().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pseudonym_depends_on_key() {
+        let key = [7u8; 32];
+        assert_eq!(pseudonym(&key, "8100001"), pseudonym(&key, "8100001"));
+        assert_ne!(pseudonym(&key, "8100001"), pseudonym(&key, "8100002"));
+        assert_ne!(pseudonym(&key, "8100001"), pseudonym(&[8u8; 32], "8100001"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Archive stored as files under a local directory
+//!
+//! Object keys map to paths below the root directory, so an archive with
+//! the default prefix looks like:
+//!
+//! ```text
+//! <root>/access-logs/2025/05/10/segment-0001.jsonl.gz
+//! <root>/access-logs/2025/05/10/manifest.json
+//! ```
+//!
+//! Segments are gzip-compressed JSON Lines and can be read back with
+//! standard tools (`zcat segment-0001.jsonl.gz | jq .`).
+
+use super::ObjectStore;
+use crate::error::{StorageError, StorageResult};
+use std::path::{Component, Path, PathBuf};
+
+/// Object store backed by a local directory
+///
+/// Files are written to a temporary name and renamed into place, so a crash
+/// during an upload never leaves a truncated segment or manifest behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStore {
+    root: PathBuf,
+}
+
+impl FileStore {
+    /// Create a store under `root`; directories are created on first write
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Directory the objects are stored under
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of an object, rejecting keys that would leave the root
+    fn path(&self, key: &str) -> StorageResult<PathBuf> {
+        let relative = Path::new(key);
+        let inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if key.is_empty() || !inside {
+            return Err(StorageError::Validation(format!(
+                "Invalid archive object key '{}'",
+                key
+            )));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+fn file_error(path: &Path, e: std::io::Error) -> StorageError {
+    StorageError::Internal(format!("Archive file '{}': {}", path.display(), e))
+}
+
+impl ObjectStore for FileStore {
+    async fn put(&self, key: &str, body: Vec<u8>) -> StorageResult<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| file_error(parent, e))?;
+        }
+
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        tokio::fs::write(&partial, body)
+            .await
+            .map_err(|e| file_error(&partial, e))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .map_err(|e| file_error(&path, e))
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        let path = self.path(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(body) => Ok(Some(body)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(file_error(&path, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_and_reject_escaping_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path());
+
+        store
+            .put("logs/2025/05/10/manifest.json", b"{}".to_vec())
+            .await
+            .unwrap();
+        store
+            .put("logs/2025/05/10/manifest.json", b"[]".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get("logs/2025/05/10/manifest.json").await.unwrap(),
+            Some(b"[]".to_vec())
+        );
+        assert!(dir.path().join("logs/2025/05/10/manifest.json").is_file());
+        assert_eq!(store.get("logs/missing.json").await.unwrap(), None);
+
+        for key in ["", "../outside", "/etc/passwd", "logs/../../outside"] {
+            assert!(matches!(
+                store.put(key, Vec::new()).await,
+                Err(StorageError::Validation(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,845 @@
+//! Incremental backup of access logs to object storage
+//!
+//! Access logs grow without bound, but only recent ones are needed online.
+//! [`LogArchiver`] copies them, one UTC day at a time, to an
+//! [`ObjectStore`] such as a local directory ([`FileStore`]) or an
+//! S3-compatible bucket (`S3Store`), and can restore an archived day into
+//! the database later.
+//!
+//! Only available with the `archive` feature; `S3Store` also needs the
+//! `s3` feature.
+//!
+//! # Layout
+//!
+//! ```text
+//! <prefix>/2025/05/10/segment-0001.jsonl.gz
+//! <prefix>/2025/05/10/segment-0002.jsonl.gz
+//! <prefix>/2025/05/10/manifest.json
+//! ```
+//!
+//! - **Segments** hold up to [`ArchiveConfig::segment_rows`] logs each, as
+//!   gzip-compressed JSON Lines of [`AccessLog`], in ID order.
+//! - **The manifest** ([`ArchiveManifest`]) lists the segments of the day
+//!   with their row counts, ID ranges, sizes and SHA-256 checksums. It is
+//!   uploaded after the segments, so a manifest never names a segment that
+//!   is missing.
+//!
+//! # Incremental and Resumable
+//!
+//! Every uploaded segment is recorded in `log_archive_segments` before the
+//! next one is built. A run that fails part-way keeps what was uploaded and
+//! the next run continues after the last recorded segment; the manifest of
+//! a day is rewritten whenever it lists fewer segments than were uploaded.
+//! Logs of an archived day that arrive later (events pulled from a device
+//! that was offline) have higher IDs and go into new segments of that day.
+//!
+//! The archiver never deletes logs from the database; the
+//! [`retention`](crate::retention) manager does, once they are archived.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::archive::{ArchiveConfig, FileStore, LogArchiver};
+//! use chrono::{Days, Utc};
+//!
+//! # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let store = FileStore::new("/var/lib/turnkey/archive");
+//! let archiver = LogArchiver::new(pool, store, ArchiveConfig::new().prefix("site-a/logs"));
+//!
+//! // Every complete day up to yesterday
+//! let report = archiver.archive(Utc::now().date_naive()).await?;
+//! println!("{} segments, {} logs uploaded", report.segments, report.rows);
+//!
+//! // Bring a day back after it was purged from the database
+//! let day = Utc::now().date_naive() - Days::new(400);
+//! let restored = archiver.restore_day(day).await?;
+//! println!("{} logs restored", restored.inserted);
+//! # Ok(())
+//! # }
+//! ```
+
+#![allow(async_fn_in_trait)]
+
+mod file;
+#[cfg(feature = "s3")]
+mod s3;
+
+pub use file::FileStore;
+#[cfg(feature = "s3")]
+pub use s3::{DEFAULT_S3_REGION, S3Config, S3Store};
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::AccessLog;
+use crate::repositories::{
+    AccessLogRepository, BulkInsertOptions, OnDuplicate, SqliteAccessLogRepository,
+};
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use tracing::info;
+
+/// Default number of logs per segment
+pub const DEFAULT_SEGMENT_ROWS: usize = 10_000;
+
+/// Version written to new manifests
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Storage the archive is written to
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait ObjectStore: Send + Sync {
+    /// Store an object, replacing any object with the same key
+    async fn put(&self, key: &str, body: Vec<u8>) -> StorageResult<()>;
+
+    /// Read an object, `None` if there is no object with this key
+    async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>>;
+}
+
+/// Object key prefix and segment size of a [`LogArchiver`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveConfig {
+    /// Prefix of every object key, without trailing slash (default:
+    /// `access-logs`)
+    pub prefix: String,
+
+    /// Maximum logs per segment (default: [`DEFAULT_SEGMENT_ROWS`])
+    pub segment_rows: usize,
+}
+
+impl ArchiveConfig {
+    /// Default prefix and segment size
+    pub fn new() -> Self {
+        Self {
+            prefix: "access-logs".to_string(),
+            segment_rows: DEFAULT_SEGMENT_ROWS,
+        }
+    }
+
+    /// Set the object key prefix
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into().trim_matches('/').to_string();
+        self
+    }
+
+    /// Set the maximum logs per segment (at least 1)
+    pub fn segment_rows(mut self, rows: usize) -> Self {
+        self.segment_rows = rows.max(1);
+        self
+    }
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One archived segment, as listed in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveSegment {
+    /// Object key
+    pub key: String,
+
+    /// Logs in the segment
+    pub rows: u64,
+
+    /// Lowest log ID in the segment
+    pub first_id: i64,
+
+    /// Highest log ID in the segment
+    pub last_id: i64,
+
+    /// Compressed size in bytes
+    pub bytes: u64,
+
+    /// Hex SHA-256 of the compressed object
+    pub sha256: String,
+}
+
+/// Index of the archived segments of one day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// Manifest format version
+    pub version: u32,
+
+    /// UTC day the logs belong to
+    pub day: NaiveDate,
+
+    /// Logs in all segments
+    pub rows: u64,
+
+    /// Segments in upload order
+    pub segments: Vec<ArchiveSegment>,
+
+    /// When the manifest was written
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Outcome of [`LogArchiver::archive`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveReport {
+    /// Days whose manifest was written, in order
+    pub days: Vec<NaiveDate>,
+
+    /// Segments uploaded
+    pub segments: u64,
+
+    /// Logs uploaded
+    pub rows: u64,
+
+    /// Compressed bytes uploaded, manifests excluded
+    pub bytes: u64,
+}
+
+/// Outcome of [`LogArchiver::restore_day`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreReport {
+    /// Segments read
+    pub segments: u64,
+
+    /// Logs read from the segments
+    pub rows: u64,
+
+    /// Logs written to the database
+    pub inserted: u64,
+
+    /// Logs already in the database
+    pub duplicates: u64,
+}
+
+/// Archiver of access logs to an [`ObjectStore`]
+pub struct LogArchiver<S> {
+    pool: SqlitePool,
+    store: S,
+    config: ArchiveConfig,
+}
+
+impl<S: ObjectStore> LogArchiver<S> {
+    /// Create an archiver writing to `store`
+    pub fn new(pool: SqlitePool, store: S, config: ArchiveConfig) -> Self {
+        Self {
+            pool,
+            store,
+            config,
+        }
+    }
+
+    /// Object store the archive is written to
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Key of the manifest of a day
+    pub fn manifest_key(&self, day: NaiveDate) -> String {
+        format!("{}/manifest.json", self.day_prefix(day))
+    }
+
+    fn day_prefix(&self, day: NaiveDate) -> String {
+        let path = format!("{:04}/{:02}/{:02}", day.year(), day.month(), day.day());
+        if self.config.prefix.is_empty() {
+            path
+        } else {
+            format!("{}/{}", self.config.prefix, path)
+        }
+    }
+
+    /// Archive the logs of every day before `until` not archived yet
+    ///
+    /// Days are processed oldest first. Only logs that are not in a
+    /// segment yet are uploaded, so running this daily uploads one new day
+    /// plus any late logs of earlier days.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first upload or database error. Segments uploaded
+    /// before it are kept and skipped by the next run.
+    pub async fn archive(&self, until: NaiveDate) -> StorageResult<ArchiveReport> {
+        let until = until.to_string();
+        let days: Vec<String> = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT substr(l.timestamp, 1, 10) AS "day!: String"
+            FROM access_logs l
+            WHERE l.timestamp < ?1
+              AND l.id > COALESCE(
+                  (SELECT MAX(s.last_id) FROM log_archive_segments s
+                   WHERE s.day = substr(l.timestamp, 1, 10)),
+                  0)
+            UNION
+            SELECT s.day
+            FROM log_archive_segments s
+            WHERE s.day < ?1
+            GROUP BY s.day
+            HAVING COUNT(*) != COALESCE(
+                (SELECT d.segments FROM log_archive_days d WHERE d.day = s.day), 0)
+            ORDER BY 1
+            "#,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut report = ArchiveReport::default();
+        for day in days {
+            let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .map_err(|e| StorageError::DateTime(format!("Invalid log day '{}': {}", day, e)))?;
+            self.archive_into(day, &mut report).await?;
+        }
+
+        info!(
+            days = report.days.len(),
+            segments = report.segments,
+            rows = report.rows,
+            "Access logs archived"
+        );
+        Ok(report)
+    }
+
+    /// Archive the logs of one day that are not in a segment yet
+    ///
+    /// # Errors
+    ///
+    /// Returns the first upload or database error; see
+    /// [`archive()`](Self::archive).
+    pub async fn archive_day(&self, day: NaiveDate) -> StorageResult<ArchiveReport> {
+        let mut report = ArchiveReport::default();
+        self.archive_into(day, &mut report).await?;
+        Ok(report)
+    }
+
+    async fn archive_into(&self, day: NaiveDate, report: &mut ArchiveReport) -> StorageResult<()> {
+        let mut segments = self.recorded_segments(day).await?;
+        let (start, end) = (day.to_string(), next_day(day)?.to_string());
+        let limit = self.config.segment_rows as i64;
+
+        loop {
+            let after_id = segments.last().map_or(0, |segment| segment.last_id);
+            let logs = sqlx::query_as!(
+                AccessLog,
+                r#"
+                SELECT id AS "id!", user_id, matricula, card_number,
+                       direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                       display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                       device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+                FROM access_logs
+                WHERE timestamp >= ? AND timestamp < ? AND id > ?
+                ORDER BY id
+                LIMIT ?
+                "#,
+                start,
+                end,
+                after_id,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let (Some(first), Some(last)) = (logs.first(), logs.last()) else {
+                break;
+            };
+
+            let body = encode_segment(&logs)?;
+            let segment = ArchiveSegment {
+                key: format!(
+                    "{}/segment-{:04}.jsonl.gz",
+                    self.day_prefix(day),
+                    segments.len() + 1
+                ),
+                rows: logs.len() as u64,
+                first_id: first.id,
+                last_id: last.id,
+                bytes: body.len() as u64,
+                sha256: hex::encode(Sha256::digest(&body)),
+            };
+            self.store.put(&segment.key, body).await?;
+            self.record_segment(day, segments.len() + 1, &segment)
+                .await?;
+
+            report.segments += 1;
+            report.rows += segment.rows;
+            report.bytes += segment.bytes;
+            segments.push(segment);
+            if logs.len() < self.config.segment_rows {
+                break;
+            }
+        }
+
+        let day_key = day.to_string();
+        let listed = sqlx::query_scalar!(
+            "SELECT segments FROM log_archive_days WHERE day = ?",
+            day_key
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if segments.is_empty() || listed == Some(segments.len() as i64) {
+            return Ok(());
+        }
+
+        let manifest = ArchiveManifest {
+            version: MANIFEST_VERSION,
+            day,
+            rows: segments.iter().map(|segment| segment.rows).sum(),
+            segments,
+            updated_at: Utc::now(),
+        };
+        let key = self.manifest_key(day);
+        let body = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| StorageError::Internal(format!("Manifest encoding: {}", e)))?;
+        self.store.put(&key, body).await?;
+        let day_key = day.to_string();
+        let segments = manifest.segments.len() as i64;
+        let rows = manifest.rows as i64;
+        sqlx::query!(
+            r#"
+            INSERT INTO log_archive_days (day, manifest_key, segments, rows, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (day) DO UPDATE SET
+                manifest_key = excluded.manifest_key,
+                segments = excluded.segments,
+                rows = excluded.rows,
+                updated_at = excluded.updated_at
+            "#,
+            day_key,
+            key,
+            segments,
+            rows,
+            manifest.updated_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        report.days.push(day);
+        Ok(())
+    }
+
+    async fn recorded_segments(&self, day: NaiveDate) -> StorageResult<Vec<ArchiveSegment>> {
+        let day_key = day.to_string();
+        let rows = sqlx::query!(
+            r#"
+            SELECT object_key, rows, first_id, last_id, bytes, sha256
+            FROM log_archive_segments
+            WHERE day = ?
+            ORDER BY seq
+            "#,
+            day_key
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ArchiveSegment {
+                key: row.object_key,
+                rows: row.rows as u64,
+                first_id: row.first_id,
+                last_id: row.last_id,
+                bytes: row.bytes as u64,
+                sha256: row.sha256,
+            })
+            .collect())
+    }
+
+    async fn record_segment(
+        &self,
+        day: NaiveDate,
+        seq: usize,
+        segment: &ArchiveSegment,
+    ) -> StorageResult<()> {
+        let day_key = day.to_string();
+        let seq = seq as i64;
+        let rows = segment.rows as i64;
+        let bytes = segment.bytes as i64;
+        sqlx::query!(
+            r#"
+            INSERT INTO log_archive_segments (
+                day, seq, object_key, rows, first_id, last_id, bytes, sha256
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            day_key,
+            seq,
+            segment.key,
+            rows,
+            segment.first_id,
+            segment.last_id,
+            bytes,
+            segment.sha256
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Read the manifest of an archived day, `None` if it was never archived
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetworkError` if the store cannot be read and `Validation`
+    /// if the manifest is not valid JSON.
+    pub async fn manifest(&self, day: NaiveDate) -> StorageResult<Option<ArchiveManifest>> {
+        let key = self.manifest_key(day);
+        let Some(body) = self.store.get(&key).await? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| StorageError::Validation(format!("Invalid manifest '{}': {}", key, e)))
+    }
+
+    /// Write the archived logs of a day back to the database
+    ///
+    /// Every segment is checked against the size and checksum in the
+    /// manifest before any of its logs are written. Logs already in the
+    /// database are skipped, so a day can be restored more than once.
+    /// Users are matched by matricula; users deleted since are restored as
+    /// unidentified.
+    ///
+    /// The derived `access_state` is updated in insertion order, so run
+    /// [`AccessStateReplay::rebuild`](crate::replay::AccessStateReplay::rebuild)
+    /// afterwards if restored logs are to count for anti-passback.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the day has no manifest, `NetworkError` if the
+    /// store cannot be read, and `Validation` if a segment is missing,
+    /// corrupted or does not match the manifest. Segments restored before
+    /// the error stay written.
+    pub async fn restore_day(&self, day: NaiveDate) -> StorageResult<RestoreReport> {
+        let manifest = self
+            .manifest(day)
+            .await?
+            .ok_or_else(|| StorageError::NotFound {
+                entity_type: "ArchiveManifest".to_string(),
+                field: "day".to_string(),
+                value: day.to_string(),
+            })?;
+
+        let repository = SqliteAccessLogRepository::new(self.pool.clone());
+        let mut users: HashMap<String, Option<i64>> = HashMap::new();
+        let mut report = RestoreReport::default();
+        for segment in &manifest.segments {
+            let body = self.store.get(&segment.key).await?.ok_or_else(|| {
+                StorageError::Validation(format!("Archived segment '{}' is missing", segment.key))
+            })?;
+            let mut logs = decode_segment(segment, &body)?;
+
+            for log in &mut logs {
+                let user_id = match &log.matricula {
+                    Some(matricula) => match users.get(matricula) {
+                        Some(user_id) => *user_id,
+                        None => {
+                            let user_id = sqlx::query_scalar!(
+                                r#"SELECT id AS "id!" FROM users WHERE matricula = ?"#,
+                                matricula
+                            )
+                            .fetch_optional(&self.pool)
+                            .await?;
+                            users.insert(matricula.clone(), user_id);
+                            user_id
+                        }
+                    },
+                    None => None,
+                };
+                log.user_id = user_id;
+                if user_id.is_none() {
+                    log.matricula = None;
+                }
+            }
+
+            let stored = repository
+                .create_many_with(
+                    &logs,
+                    BulkInsertOptions::default().on_duplicate(OnDuplicate::Skip),
+                )
+                .await?;
+            report.segments += 1;
+            report.rows += logs.len() as u64;
+            report.inserted += stored.inserted;
+            report.duplicates += stored.duplicates;
+        }
+
+        info!(
+            %day,
+            rows = report.rows,
+            inserted = report.inserted,
+            "Archived access logs restored"
+        );
+        Ok(report)
+    }
+}
+
+fn next_day(day: NaiveDate) -> StorageResult<NaiveDate> {
+    day.checked_add_days(Days::new(1))
+        .ok_or_else(|| StorageError::DateTime(format!("No day after {}", day)))
+}
+
+fn encode_segment(logs: &[AccessLog]) -> StorageResult<Vec<u8>> {
+    let encoding_error = |e: &dyn std::fmt::Display| {
+        StorageError::Internal(format!("Archive segment encoding: {}", e))
+    };
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for log in logs {
+        serde_json::to_writer(&mut encoder, log).map_err(|e| encoding_error(&e))?;
+        encoder.write_all(b"\n").map_err(|e| encoding_error(&e))?;
+    }
+    encoder.finish().map_err(|e| encoding_error(&e))
+}
+
+fn decode_segment(segment: &ArchiveSegment, body: &[u8]) -> StorageResult<Vec<AccessLog>> {
+    let corrupted = |detail: String| {
+        StorageError::Validation(format!("Archived segment '{}' {}", segment.key, detail))
+    };
+    let sha256 = hex::encode(Sha256::digest(body));
+    if body.len() as u64 != segment.bytes || sha256 != segment.sha256 {
+        return Err(corrupted(format!(
+            "does not match its checksum ({} bytes, SHA-256 {})",
+            body.len(),
+            sha256
+        )));
+    }
+
+    let mut logs = Vec::with_capacity(segment.rows as usize);
+    for line in BufReader::new(GzDecoder::new(body)).lines() {
+        let line = line.map_err(|e| corrupted(format!("cannot be decompressed: {}", e)))?;
+        if line.is_empty() {
+            continue;
+        }
+        let log = serde_json::from_str(&line)
+            .map_err(|e| corrupted(format!("holds an invalid log: {}", e)))?;
+        logs.push(log);
+    }
+    if logs.len() as u64 != segment.rows {
+        return Err(corrupted(format!(
+            "holds {} logs, the manifest lists {}",
+            logs.len(),
+            segment.rows
+        )));
+    }
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Direction, ReaderType};
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CARD: &str = "00000000000022823433";
+
+    /// In-memory store failing every upload after the first `fail_after`
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: Mutex<HashMap<String, Vec<u8>>>,
+        puts: AtomicUsize,
+        fail_after: Option<usize>,
+    }
+
+    impl ObjectStore for MemoryStore {
+        async fn put(&self, key: &str, body: Vec<u8>) -> StorageResult<()> {
+            let put = self.puts.fetch_add(1, Ordering::SeqCst);
+            if self.fail_after.is_some_and(|limit| put >= limit) {
+                return Err(StorageError::NetworkError("store unavailable".to_string()));
+            }
+            self.objects.lock().unwrap().insert(key.to_string(), body);
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+            Ok(self.objects.lock().unwrap().get(key).cloned())
+        }
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 5, d).unwrap()
+    }
+
+    /// Replace the seeded logs with `per_day` logs on each of May 10 and 11
+    async fn seed_logs(db: &Database, per_day: i64) {
+        sqlx::query!("DELETE FROM access_logs")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let logs: Vec<_> = [10, 11]
+            .into_iter()
+            .flat_map(|d| {
+                let start = Utc.with_ymd_and_hms(2025, 5, d, 8, 0, 0).unwrap();
+                (0..per_day).map(move |i| {
+                    AccessLog::new(
+                        Some(2),
+                        Some("1002".to_string()),
+                        CARD.to_string(),
+                        Direction::Entry,
+                        ReaderType::Rfid,
+                        true,
+                        None,
+                        start + chrono::Duration::minutes(i),
+                    )
+                    .with_device_clock(15, start + chrono::Duration::minutes(i))
+                })
+            })
+            .collect();
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create_many(&logs)
+            .await
+            .unwrap();
+    }
+
+    async fn count_logs(db: &Database) -> i64 {
+        sqlx::query_scalar!("SELECT COUNT(*) FROM access_logs")
+            .fetch_one(db.pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_restore_round_trip() {
+        let db = Database::in_memory().await.unwrap();
+        seed_logs(&db, 5).await;
+        let archiver = LogArchiver::new(
+            db.pool().clone(),
+            MemoryStore::default(),
+            ArchiveConfig::new().segment_rows(2),
+        );
+
+        // Only May 10 is complete before May 11
+        let report = archiver.archive(day(11)).await.unwrap();
+        assert_eq!(report.days, [day(10)]);
+        assert_eq!((report.segments, report.rows), (3, 5));
+
+        let manifest = archiver.manifest(day(10)).await.unwrap().unwrap();
+        assert_eq!(manifest.rows, 5);
+        assert_eq!(
+            manifest.segments[0].key,
+            "access-logs/2025/05/10/segment-0001.jsonl.gz"
+        );
+
+        // Nothing new: nothing uploaded
+        let report = archiver.archive(day(12)).await.unwrap();
+        assert_eq!(report.days, [day(11)]);
+        assert_eq!(report.rows, 5);
+        assert_eq!(
+            archiver.archive(day(12)).await.unwrap(),
+            ArchiveReport::default()
+        );
+
+        sqlx::query!("DELETE FROM access_logs WHERE substr(timestamp, 1, 10) = '2025-05-10'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(count_logs(&db).await, 5);
+
+        let restored = archiver.restore_day(day(10)).await.unwrap();
+        assert_eq!(restored.segments, 3);
+        assert_eq!((restored.inserted, restored.duplicates), (5, 0));
+        assert_eq!(count_logs(&db).await, 10);
+
+        // Restoring again only finds duplicates
+        let restored = archiver.restore_day(day(10)).await.unwrap();
+        assert_eq!((restored.inserted, restored.duplicates), (0, 5));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_archive_resumes() {
+        let db = Database::in_memory().await.unwrap();
+        seed_logs(&db, 5).await;
+        let failing = LogArchiver::new(
+            db.pool().clone(),
+            MemoryStore {
+                fail_after: Some(2),
+                ..MemoryStore::default()
+            },
+            ArchiveConfig::new().segment_rows(2),
+        );
+        assert!(failing.archive(day(11)).await.is_err());
+        assert!(failing.manifest(day(10)).await.unwrap().is_none());
+
+        // A new run uploads only the missing segment, then the manifest
+        let archiver = LogArchiver::new(
+            db.pool().clone(),
+            MemoryStore::default(),
+            ArchiveConfig::new().segment_rows(2),
+        );
+        let report = archiver.archive(day(11)).await.unwrap();
+        assert_eq!(report.days, [day(10)]);
+        assert_eq!((report.segments, report.rows), (1, 1));
+        let manifest = archiver.manifest(day(10)).await.unwrap().unwrap();
+        assert_eq!(manifest.segments.len(), 3);
+        assert_eq!(manifest.rows, 5);
+    }
+
+    #[tokio::test]
+    async fn test_late_logs_go_into_new_segments() {
+        let db = Database::in_memory().await.unwrap();
+        seed_logs(&db, 2).await;
+        let archiver = LogArchiver::new(
+            db.pool().clone(),
+            MemoryStore::default(),
+            ArchiveConfig::new(),
+        );
+        archiver.archive(day(11)).await.unwrap();
+
+        let late = AccessLog::new(
+            None,
+            None,
+            CARD.to_string(),
+            Direction::Exit,
+            ReaderType::Rfid,
+            false,
+            None,
+            Utc.with_ymd_and_hms(2025, 5, 10, 23, 0, 0).unwrap(),
+        );
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create(&late)
+            .await
+            .unwrap();
+
+        let report = archiver.archive(day(11)).await.unwrap();
+        assert_eq!((report.segments, report.rows), (1, 1));
+        let manifest = archiver.manifest(day(10)).await.unwrap().unwrap();
+        assert_eq!(manifest.segments.len(), 2);
+        assert_eq!(manifest.rows, 3);
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_corrupted_segment() {
+        let db = Database::in_memory().await.unwrap();
+        seed_logs(&db, 3).await;
+        let archiver = LogArchiver::new(
+            db.pool().clone(),
+            MemoryStore::default(),
+            ArchiveConfig::new(),
+        );
+        archiver.archive(day(11)).await.unwrap();
+
+        let key = archiver.manifest(day(10)).await.unwrap().unwrap().segments[0]
+            .key
+            .clone();
+        archiver
+            .store()
+            .objects
+            .lock()
+            .unwrap()
+            .get_mut(&key)
+            .unwrap()[12] ^= 0xFF;
+
+        assert!(matches!(
+            archiver.restore_day(day(10)).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            archiver.restore_day(day(9)).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+}
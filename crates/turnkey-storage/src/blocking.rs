@@ -0,0 +1,253 @@
+//! Blocking facade for offline validation.
+//!
+//! [`OfflineValidatorBlocking`] wraps an [`OfflineValidator`] together with a
+//! private single-threaded Tokio runtime, so synchronous callers (CLI tools,
+//! FFI bindings, plugins embedded in non-async hosts) can validate access
+//! requests without setting up async plumbing themselves.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{DatabaseConfig, OfflineValidatorBlocking};
+//! use turnkey_protocol::commands::access::AccessRequest;
+//! use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut validator = OfflineValidatorBlocking::open(DatabaseConfig::new("turnkey.db"))?;
+//!
+//! let request = AccessRequest::new(
+//!     "1234567890".to_string(),
+//!     HenryTimestamp::now(),
+//!     AccessDirection::Entry,
+//!     ReaderType::Rfid,
+//! )?;
+//!
+//! let response = validator.validate_blocking(&request)?;
+//! println!("Access {}", if response.is_grant() { "granted" } else { "denied" });
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Runtime Restrictions
+//!
+//! The facade drives its own runtime with `block_on`, which panics when
+//! called from inside another Tokio runtime. Async code should use
+//! [`OfflineValidator`] directly instead.
+
+use crate::connection::{Database, DatabaseConfig};
+use crate::error::{StorageError, StorageResult};
+use crate::validator::{AccessValidator, OfflineValidator};
+use tokio::runtime::{Builder, Runtime};
+use turnkey_core::DeviceId;
+use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
+
+/// Synchronous wrapper around [`OfflineValidator`]
+///
+/// Owns a current-thread runtime that only runs while a blocking call is in
+/// progress, so it adds no background threads beyond those used by SQLite.
+pub struct OfflineValidatorBlocking {
+    runtime: Runtime,
+    database: Database,
+    validator: OfflineValidator,
+}
+
+impl std::fmt::Debug for OfflineValidatorBlocking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OfflineValidatorBlocking")
+            .field("validator", &self.validator)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OfflineValidatorBlocking {
+    /// Open the database described by `config` and create a validator for it
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the runtime cannot be created, the database cannot
+    /// be opened, or migrations fail.
+    pub fn open(config: DatabaseConfig) -> StorageResult<Self> {
+        let runtime = Self::build_runtime()?;
+        let database = runtime.block_on(Database::new(config))?;
+        Ok(Self::from_parts(runtime, database))
+    }
+
+    /// Create a validator backed by a fresh in-memory database
+    ///
+    /// Mainly useful for tests and demos; data is lost when dropped.
+    pub fn open_in_memory() -> StorageResult<Self> {
+        let runtime = Self::build_runtime()?;
+        let database = runtime.block_on(Database::in_memory())?;
+        Ok(Self::from_parts(runtime, database))
+    }
+
+    /// Tag access logs with the device this validator serves
+    ///
+    /// See [`OfflineValidator::with_device_id`].
+    pub fn with_device_id(mut self, device_id: DeviceId) -> Self {
+        self.validator = self.validator.with_device_id(device_id);
+        self
+    }
+
+    /// Record unknown card reads for later assignment
+    ///
+    /// See [`OfflineValidator::with_learning_mode`].
+    pub fn with_learning_mode(mut self, enabled: bool) -> Self {
+        self.validator = self.validator.with_learning_mode(enabled);
+        self
+    }
+
+    /// Validate an access request, blocking the current thread until done
+    ///
+    /// # Errors
+    ///
+    /// Returns error if database operations fail. Validation failures
+    /// (e.g., card not found) return `Ok(AccessResponse::deny)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within an asynchronous execution context.
+    pub fn validate_blocking(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        self.runtime.block_on(self.validator.validate(request))
+    }
+
+    /// Run an arbitrary async storage operation on the internal runtime
+    ///
+    /// Lets synchronous callers reuse the same database for setup or
+    /// reporting (e.g., inserting cards before validating).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within an asynchronous execution context.
+    pub fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Get the database used by this validator
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    fn build_runtime() -> StorageResult<Runtime> {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| StorageError::Internal(format!("Failed to create runtime: {}", e)))
+    }
+
+    fn from_parts(runtime: Runtime, database: Database) -> Self {
+        let validator = OfflineValidator::new(database.pool().clone());
+        Self {
+            runtime,
+            database,
+            validator,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Card, User};
+    use crate::repositories::{
+        AccessLogRepository, CardRepository, SqliteAccessLogRepository, SqliteCardRepository,
+        SqliteUserRepository, UserRepository,
+    };
+    use chrono::Utc;
+    use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
+
+    fn create_access_request(card_number: &str) -> AccessRequest {
+        AccessRequest::new(
+            card_number.to_string(),
+            HenryTimestamp::now(),
+            AccessDirection::Entry,
+            ReaderType::Rfid,
+        )
+        .unwrap()
+    }
+
+    fn create_test_user_and_card(validator: &OfflineValidatorBlocking, numero: &str) {
+        let pool = validator.database().pool().clone();
+        validator.block_on(async move {
+            let user = User {
+                id: 0,
+                pis: None,
+                nome: "Blocking User".to_string(),
+                matricula: "BLK001".to_string(),
+                cpf: None,
+                validade_inicio: None,
+                validade_fim: None,
+                ativo: true,
+                allow_card: true,
+                allow_bio: false,
+                allow_keypad: false,
+                codigo: None,
+                empresa: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+            let user_id = SqliteUserRepository::new(pool.clone())
+                .create(&user)
+                .await
+                .unwrap();
+
+            let card = Card {
+                id: 0,
+                numero_cartao: numero.to_string(),
+                matricula: "BLK001".to_string(),
+                user_id,
+                validade_inicio: None,
+                validade_fim: None,
+                ativo: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+            SqliteCardRepository::new(pool).create(&card).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_validate_blocking_grant() {
+        let mut validator = OfflineValidatorBlocking::open_in_memory()
+            .unwrap()
+            .with_device_id(DeviceId::new(4).unwrap());
+        create_test_user_and_card(&validator, "4040404040");
+
+        let response = validator
+            .validate_blocking(&create_access_request("4040404040"))
+            .unwrap();
+        assert!(response.is_grant());
+
+        let pool = validator.database().pool().clone();
+        let logs = validator
+            .block_on(SqliteAccessLogRepository::new(pool).find_by_card_number("4040404040", 10))
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].device_id, Some(4));
+    }
+
+    #[test]
+    fn test_validate_blocking_deny() {
+        let mut validator = OfflineValidatorBlocking::open_in_memory().unwrap();
+
+        let response = validator
+            .validate_blocking(&create_access_request("4141414141"))
+            .unwrap();
+        assert!(response.is_deny());
+    }
+
+    #[test]
+    fn test_open_file_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocking.db");
+
+        let mut validator =
+            OfflineValidatorBlocking::open(DatabaseConfig::new(path.to_string_lossy())).unwrap();
+        let response = validator
+            .validate_blocking(&create_access_request("4242424242"))
+            .unwrap();
+
+        assert!(response.is_deny());
+        assert!(path.exists());
+    }
+}
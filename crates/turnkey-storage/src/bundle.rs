@@ -0,0 +1,1564 @@
+//! Portable site configuration bundles
+//!
+//! A bundle is an uncompressed tar archive holding one JSON document per
+//! section, so a whole site (users, cards, biometric templates, sites and
+//! zones, devices and access exceptions) can be moved between installations or kept as a
+//! reviewable snapshot:
+//!
+//! | Entry                      | Content                                   |
+//! |----------------------------|-------------------------------------------|
+//! | `manifest.json`            | [`BundleManifest`] (versions and counts)  |
+//! | `config.json`              | Free-form site settings (string map)      |
+//! | `users.json`               | [`UserRecord`] list                       |
+//! | `cards.json`               | [`CardRecord`] list                       |
+//! | `biometric_templates.json` | [`TemplateRecord`] list (hex data)        |
+//! | `sites.json`               | [`SiteRecord`] list (with their zones)    |
+//! | `devices.json`             | [`DeviceRecord`] list                     |
+//! | `access_exceptions.json`   | [`ExceptionRecord`] list (time windows)   |
+//!
+//! Records use natural keys (matricula, card number, device ID, site and
+//! zone names) instead of database IDs, so a bundle can be imported into a database whose
+//! auto-increment counters differ from the source.
+//!
+//! # Versioning
+//!
+//! `format_version` describes the archive layout and must match
+//! [`BUNDLE_FORMAT_VERSION`]. `schema_version` is the latest migration known
+//! to the exporting build; bundles from a newer schema are rejected because
+//! they may carry data this build would silently drop. Bundles from an older
+//! schema are accepted and missing fields take their column defaults;
+//! `sites.json` may be absent in bundles written before zones existed.
+//!
+//! # Device keys
+//!
+//! HMAC keys are secrets and are left out unless
+//! [`ExportOptions::include_device_keys`] is set. On import a device without
+//! a key in the bundle keeps the key already provisioned in the target
+//! database.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::bundle::{self, ExportOptions, ImportMode};
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use std::fs::File;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let source = Database::new(DatabaseConfig::new("site-a.db")).await?;
+//! let manifest = bundle::export_bundle(
+//!     source.pool(),
+//!     File::create("site-a.tar")?,
+//!     &ExportOptions::default().setting("site.name", "Site A"),
+//! )
+//! .await?;
+//! println!("Exported {} users", manifest.counts.users);
+//!
+//! let target = Database::new(DatabaseConfig::new("site-b.db")).await?;
+//! let report =
+//!     bundle::import_bundle(target.pool(), File::open("site-a.tar")?, ImportMode::Replace)
+//!         .await?;
+//! println!("Imported bundle created at {}", report.manifest.created_at);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
+use turnkey_core::DeviceLabel;
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::VerificationMode;
+use crate::repositories::sync_state::mark_synced;
+
+/// Archive layout version written by this build
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const CONFIG_ENTRY: &str = "config.json";
+const USERS_ENTRY: &str = "users.json";
+const CARDS_ENTRY: &str = "cards.json";
+const TEMPLATES_ENTRY: &str = "biometric_templates.json";
+const SITES_ENTRY: &str = "sites.json";
+const DEVICES_ENTRY: &str = "devices.json";
+const EXCEPTIONS_ENTRY: &str = "access_exceptions.json";
+
+/// Latest migration version known to this build
+pub fn current_schema_version() -> i64 {
+    sqlx::migrate!("../../migrations")
+        .migrations
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Number of records in each bundle section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleCounts {
+    pub users: usize,
+    pub cards: usize,
+    pub biometric_templates: usize,
+    #[serde(default)]
+    pub sites: usize,
+    pub devices: usize,
+    pub access_exceptions: usize,
+}
+
+/// Bundle header stored in `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// Archive layout version (see [`BUNDLE_FORMAT_VERSION`])
+    pub format_version: u32,
+
+    /// Latest migration known to the exporting build
+    pub schema_version: i64,
+
+    /// When the bundle was exported
+    pub created_at: DateTime<Utc>,
+
+    /// Whether `devices.json` carries HMAC keys
+    pub includes_device_keys: bool,
+
+    /// Records per section, checked on import
+    pub counts: BundleCounts,
+}
+
+impl BundleManifest {
+    /// Check that this build can import the bundle
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` for a different format version or a newer
+    /// schema version.
+    pub fn check_compatibility(&self) -> StorageResult<()> {
+        if self.format_version != BUNDLE_FORMAT_VERSION {
+            return Err(StorageError::Validation(format!(
+                "Unsupported bundle format version {} (expected {})",
+                self.format_version, BUNDLE_FORMAT_VERSION
+            )));
+        }
+
+        let current = current_schema_version();
+        if self.schema_version > current {
+            return Err(StorageError::Validation(format!(
+                "Bundle schema version {} is newer than this build ({})",
+                self.schema_version, current
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// User entry of `users.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserRecord {
+    pub matricula: String,
+    pub nome: String,
+    pub pis: Option<String>,
+    pub cpf: Option<String>,
+    pub validade_inicio: Option<DateTime<Utc>>,
+    pub validade_fim: Option<DateTime<Utc>>,
+    pub ativo: bool,
+    pub allow_card: bool,
+    pub allow_bio: bool,
+    pub allow_keypad: bool,
+    pub codigo: Option<String>,
+    #[serde(default)]
+    pub empresa: Option<String>,
+}
+
+/// Card entry of `cards.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CardRecord {
+    pub numero_cartao: String,
+    pub matricula: String,
+    pub validade_inicio: Option<DateTime<Utc>>,
+    pub validade_fim: Option<DateTime<Utc>>,
+    pub ativo: bool,
+}
+
+/// Biometric template entry of `biometric_templates.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateRecord {
+    pub matricula: String,
+    pub posicao: i64,
+    /// Template bytes, hex encoded
+    pub template_data: String,
+}
+
+/// Site entry of `sites.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteRecord {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub zones: Vec<ZoneRecord>,
+}
+
+/// Zone of a [`SiteRecord`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneRecord {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Reference to a zone by site and zone name
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ZoneRef {
+    pub site: String,
+    pub zone: String,
+}
+
+/// Device entry of `devices.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceRecord {
+    pub device_id: i64,
+    pub signing_required: bool,
+    #[serde(default = "default_true")]
+    pub allow_card: bool,
+    #[serde(default = "default_true")]
+    pub allow_bio: bool,
+    #[serde(default = "default_true")]
+    pub allow_keypad: bool,
+    /// Deny offline requests while the local data is stale
+    #[serde(default)]
+    pub deny_when_stale: bool,
+    /// Credentials required for a passage
+    #[serde(default)]
+    pub verification_mode: VerificationMode,
+    /// Show the remaining daily quota on grants
+    #[serde(default)]
+    pub show_balance: bool,
+    /// Friendly name shown next to the device ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Where the device is installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// HMAC key, hex encoded; only present when exported with keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hmac_key: Option<String>,
+    /// Zone the device is assigned to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<ZoneRef>,
+}
+
+/// Access exception entry of `access_exceptions.json`
+///
+/// Membership and device/zone restrictions are inlined so the entry does
+/// not depend on exception or zone IDs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExceptionRecord {
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub active: bool,
+    #[serde(default)]
+    pub cards: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub devices: Vec<i64>,
+    #[serde(default)]
+    pub zones: Vec<ZoneRef>,
+}
+
+/// Export settings
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Write device HMAC keys into the bundle
+    pub include_device_keys: bool,
+
+    /// Site settings stored in `config.json`
+    pub config: BTreeMap<String, String>,
+}
+
+impl ExportOptions {
+    /// Include device HMAC keys
+    pub fn include_device_keys(mut self, include: bool) -> Self {
+        self.include_device_keys = include;
+        self
+    }
+
+    /// Add a site setting to `config.json`
+    pub fn setting(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// How an import treats data already in the target database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Insert new records and update existing ones by natural key
+    ///
+    /// Records missing from the bundle are kept. Exceptions with the same
+    /// name as a bundled one are replaced.
+    Merge,
+
+    /// Make the site data match the bundle exactly
+    ///
+    /// Users, cards, templates, sites (with their zones) and exceptions not
+    /// in the bundle are deleted. Devices not in the bundle are deleted; bundled devices are
+    /// updated in place so their provisioned keys survive.
+    Replace,
+}
+
+/// Outcome of a successful import
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    /// Header of the imported bundle
+    pub manifest: BundleManifest,
+
+    /// Site settings from `config.json`
+    pub config: BTreeMap<String, String>,
+}
+
+/// In-memory site bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteBundle {
+    pub manifest: BundleManifest,
+    pub config: BTreeMap<String, String>,
+    pub users: Vec<UserRecord>,
+    pub cards: Vec<CardRecord>,
+    pub biometric_templates: Vec<TemplateRecord>,
+    pub sites: Vec<SiteRecord>,
+    pub devices: Vec<DeviceRecord>,
+    pub access_exceptions: Vec<ExceptionRecord>,
+}
+
+impl SiteBundle {
+    /// Read the full site configuration from the database
+    ///
+    /// All sections are read inside one transaction so the bundle is a
+    /// consistent snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Database` on query failures.
+    pub async fn collect(pool: &SqlitePool, options: &ExportOptions) -> StorageResult<Self> {
+        let mut tx = pool.begin().await?;
+
+        let users = sqlx::query_as!(
+            UserRecord,
+            r#"
+            SELECT matricula, nome, pis, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa
+            FROM users
+            ORDER BY matricula
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let cards = sqlx::query_as!(
+            CardRecord,
+            r#"
+            SELECT numero_cartao, matricula, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo
+            FROM cards
+            ORDER BY numero_cartao
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let biometric_templates = sqlx::query!(
+            "SELECT matricula, posicao, template_data FROM biometric_templates ORDER BY matricula, posicao"
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| TemplateRecord {
+            matricula: row.matricula,
+            posicao: row.posicao,
+            template_data: hex::encode(row.template_data),
+        })
+        .collect();
+
+        let mut sites: Vec<SiteRecord> = Vec::new();
+        let zones = sqlx::query!(
+            r#"
+            SELECT s.name AS site, s.description AS site_description,
+                   z.name AS "zone?", z.description AS "zone_description?"
+            FROM sites s
+            LEFT JOIN zones z ON z.site_id = s.id
+            ORDER BY s.name, z.name
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for row in zones {
+            if sites.last().is_none_or(|last| last.name != row.site) {
+                sites.push(SiteRecord {
+                    name: row.site,
+                    description: row.site_description,
+                    zones: Vec::new(),
+                });
+            }
+            if let (Some(last), Some(name)) = (sites.last_mut(), row.zone) {
+                last.zones.push(ZoneRecord {
+                    name,
+                    description: row.zone_description,
+                });
+            }
+        }
+
+        let mut tags: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+        for row in sqlx::query!("SELECT device_id, tag FROM device_tags ORDER BY device_id, tag")
+            .fetch_all(&mut *tx)
+            .await?
+        {
+            tags.entry(row.device_id).or_default().push(row.tag);
+        }
+
+        let devices = sqlx::query_as!(
+            DeviceRow,
+            r#"
+            SELECT d.device_id, d.signing_required, d.allow_card, d.allow_bio, d.allow_keypad,
+                   d.deny_when_stale, d.verification_mode AS "verification_mode: _",
+                   d.show_balance AS "show_balance: _", d.name, d.location, d.hmac_key,
+                   s.name AS "site_name?", z.name AS "zone_name?"
+            FROM devices d
+            LEFT JOIN zones z ON z.id = d.zone_id
+            LEFT JOIN sites s ON s.id = z.site_id
+            ORDER BY d.device_id
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| DeviceRecord {
+            tags: tags.remove(&row.device_id).unwrap_or_default(),
+            device_id: row.device_id,
+            signing_required: row.signing_required,
+            allow_card: row.allow_card,
+            allow_bio: row.allow_bio,
+            allow_keypad: row.allow_keypad,
+            deny_when_stale: row.deny_when_stale,
+            verification_mode: row.verification_mode,
+            show_balance: row.show_balance,
+            name: row.name,
+            location: row.location,
+            hmac_key: row
+                .hmac_key
+                .filter(|_| options.include_device_keys)
+                .map(hex::encode),
+            zone: zone_ref(row.site_name, row.zone_name),
+        })
+        .collect();
+
+        let windows = sqlx::query!(
+            r#"
+            SELECT id AS "id!", name, starts_at AS "starts_at: DateTime<Utc>",
+                   ends_at AS "ends_at: DateTime<Utc>", active
+            FROM access_exceptions
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut access_exceptions = Vec::with_capacity(windows.len());
+        for window in windows {
+            let id = window.id;
+            let members = sqlx::query!(
+                "SELECT card_number, matricula FROM access_exception_members WHERE exception_id = ? ORDER BY id",
+                id
+            )
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|row| (row.card_number, row.matricula));
+
+            let devices = sqlx::query_scalar!(
+                "SELECT device_id FROM access_exception_devices WHERE exception_id = ? ORDER BY device_id",
+                id
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let zones = sqlx::query!(
+                r#"
+                SELECT s.name AS site, z.name AS zone
+                FROM access_exception_zones x
+                JOIN zones z ON z.id = x.zone_id
+                JOIN sites s ON s.id = z.site_id
+                WHERE x.exception_id = ?
+                ORDER BY s.name, z.name
+                "#,
+                id
+            )
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|row| ZoneRef {
+                site: row.site,
+                zone: row.zone,
+            })
+            .collect();
+
+            let (cards, users): (Vec<_>, Vec<_>) = members.partition(|(card, _)| card.is_some());
+
+            access_exceptions.push(ExceptionRecord {
+                name: window.name,
+                starts_at: window.starts_at,
+                ends_at: window.ends_at,
+                active: window.active,
+                cards: cards.into_iter().filter_map(|(card, _)| card).collect(),
+                users: users.into_iter().filter_map(|(_, user)| user).collect(),
+                devices,
+                zones,
+            });
+        }
+
+        tx.commit().await?;
+
+        let mut bundle = Self {
+            manifest: BundleManifest {
+                format_version: BUNDLE_FORMAT_VERSION,
+                schema_version: current_schema_version(),
+                created_at: Utc::now(),
+                includes_device_keys: options.include_device_keys,
+                counts: BundleCounts::default(),
+            },
+            config: options.config.clone(),
+            users,
+            cards,
+            biometric_templates,
+            sites,
+            devices,
+            access_exceptions,
+        };
+        bundle.manifest.counts = bundle.counts();
+        Ok(bundle)
+    }
+
+    /// Actual number of records in each section
+    pub fn counts(&self) -> BundleCounts {
+        BundleCounts {
+            users: self.users.len(),
+            cards: self.cards.len(),
+            biometric_templates: self.biometric_templates.len(),
+            sites: self.sites.len(),
+            devices: self.devices.len(),
+            access_exceptions: self.access_exceptions.len(),
+        }
+    }
+
+    /// Check versions, counts and references inside the bundle
+    ///
+    /// Cards and templates must belong to a user in the bundle, zone
+    /// references must name a zone in `sites.json`, and binary fields must
+    /// be valid hex.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` describing the first problem found.
+    pub fn validate(&self) -> StorageResult<()> {
+        self.manifest.check_compatibility()?;
+
+        if self.manifest.counts != self.counts() {
+            return Err(StorageError::Validation(format!(
+                "Bundle manifest counts {:?} do not match content {:?}",
+                self.manifest.counts,
+                self.counts()
+            )));
+        }
+
+        let matriculas: HashSet<&str> = self.users.iter().map(|u| u.matricula.as_str()).collect();
+        if matriculas.len() != self.users.len() {
+            return Err(StorageError::Validation(
+                "Bundle contains duplicate matriculas".to_string(),
+            ));
+        }
+
+        if let Some(card) = self
+            .cards
+            .iter()
+            .find(|card| !matriculas.contains(card.matricula.as_str()))
+        {
+            return Err(StorageError::Validation(format!(
+                "Card {} references unknown matricula {}",
+                card.numero_cartao, card.matricula
+            )));
+        }
+
+        for template in &self.biometric_templates {
+            if !matriculas.contains(template.matricula.as_str()) {
+                return Err(StorageError::Validation(format!(
+                    "Biometric template references unknown matricula {}",
+                    template.matricula
+                )));
+            }
+            decode_hex("template_data", &template.template_data)?;
+        }
+
+        let mut zones = HashSet::new();
+        let mut site_names = HashSet::new();
+        for site in &self.sites {
+            if !site_names.insert(site.name.as_str()) {
+                return Err(StorageError::Validation(format!(
+                    "Bundle contains duplicate site {}",
+                    site.name
+                )));
+            }
+            for zone in &site.zones {
+                if !zones.insert((site.name.as_str(), zone.name.as_str())) {
+                    return Err(StorageError::Validation(format!(
+                        "Site {} contains duplicate zone {}",
+                        site.name, zone.name
+                    )));
+                }
+            }
+        }
+        let check_zone = |zone: &ZoneRef, owner: String| {
+            if zones.contains(&(zone.site.as_str(), zone.zone.as_str())) {
+                Ok(())
+            } else {
+                Err(StorageError::Validation(format!(
+                    "{} references unknown zone {}/{}",
+                    owner, zone.site, zone.zone
+                )))
+            }
+        };
+
+        for device in &self.devices {
+            if let Some(key) = &device.hmac_key {
+                decode_hex("hmac_key", key)?;
+            }
+            if let Some(zone) = &device.zone {
+                check_zone(zone, format!("Device {}", device.device_id))?;
+            }
+            let label_texts = [
+                ("name", device.name.as_deref()),
+                ("location", device.location.as_deref()),
+            ]
+            .into_iter()
+            .filter_map(|(what, text)| Some((what, text?)))
+            .chain(device.tags.iter().map(|tag| ("tag", tag.as_str())));
+            for (what, text) in label_texts {
+                DeviceLabel::check_text(&format!("Device {} {}", device.device_id, what), text)
+                    .map_err(|e| StorageError::Validation(e.to_string()))?;
+            }
+        }
+
+        for exception in &self.access_exceptions {
+            for zone in &exception.zones {
+                check_zone(zone, format!("Exception {}", exception.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the bundle as a tar archive
+    ///
+    /// # Errors
+    ///
+    /// Returns `Internal` on serialization or I/O failures.
+    pub fn write_to<W: Write>(&self, writer: W) -> StorageResult<()> {
+        let mut builder = tar::Builder::new(writer);
+        let mtime = self.manifest.created_at.timestamp().max(0) as u64;
+
+        append_json(&mut builder, MANIFEST_ENTRY, &self.manifest, mtime)?;
+        append_json(&mut builder, CONFIG_ENTRY, &self.config, mtime)?;
+        append_json(&mut builder, USERS_ENTRY, &self.users, mtime)?;
+        append_json(&mut builder, CARDS_ENTRY, &self.cards, mtime)?;
+        append_json(
+            &mut builder,
+            TEMPLATES_ENTRY,
+            &self.biometric_templates,
+            mtime,
+        )?;
+        append_json(&mut builder, SITES_ENTRY, &self.sites, mtime)?;
+        append_json(&mut builder, DEVICES_ENTRY, &self.devices, mtime)?;
+        append_json(
+            &mut builder,
+            EXCEPTIONS_ENTRY,
+            &self.access_exceptions,
+            mtime,
+        )?;
+
+        builder
+            .into_inner()
+            .and_then(|mut writer| writer.flush())
+            .map_err(|e| StorageError::Internal(format!("Failed to write bundle: {}", e)))
+    }
+
+    /// Read a bundle from a tar archive
+    ///
+    /// Unknown entries are ignored and a missing `sites.json` reads as no
+    /// sites. The bundle is not validated; call
+    /// [`validate()`](Self::validate) before using it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the archive is malformed, an entry is missing
+    /// or a document cannot be parsed.
+    pub fn read_from<R: Read>(reader: R) -> StorageResult<Self> {
+        let invalid =
+            |e: std::io::Error| StorageError::Validation(format!("Invalid bundle: {}", e));
+
+        let mut entries = BTreeMap::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(invalid)? {
+            let mut entry = entry.map_err(invalid)?;
+            let path = entry
+                .path()
+                .map_err(invalid)?
+                .to_string_lossy()
+                .into_owned();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).map_err(invalid)?;
+            entries.insert(path, content);
+        }
+
+        Ok(Self {
+            manifest: parse_entry(&entries, MANIFEST_ENTRY)?,
+            config: parse_entry(&entries, CONFIG_ENTRY)?,
+            users: parse_entry(&entries, USERS_ENTRY)?,
+            cards: parse_entry(&entries, CARDS_ENTRY)?,
+            biometric_templates: parse_entry(&entries, TEMPLATES_ENTRY)?,
+            sites: if entries.contains_key(SITES_ENTRY) {
+                parse_entry(&entries, SITES_ENTRY)?
+            } else {
+                Vec::new()
+            },
+            devices: parse_entry(&entries, DEVICES_ENTRY)?,
+            access_exceptions: parse_entry(&entries, EXCEPTIONS_ENTRY)?,
+        })
+    }
+
+    /// Validate the bundle and write it to the database in one transaction
+    ///
+    /// Nothing is changed if any record fails; the transaction is rolled
+    /// back and the error returned. A successful import marks the local
+    /// data as synced (source `"bundle"`, see
+    /// [`SyncState`](crate::models::SyncState)).
+    ///
+    /// Hashed keypad codes are imported without a lookup digest (see
+    /// [`crate::pin`]), so their users are found by PIN alone only after
+    /// entering matricula and PIN once.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the bundle is invalid or a signing device has
+    /// no key in either the bundle or the target, and `Database` if a record
+    /// violates a database constraint.
+    pub async fn apply(&self, pool: &SqlitePool, mode: ImportMode) -> StorageResult<()> {
+        self.validate()?;
+
+        let mut tx = pool.begin().await?;
+
+        if mode == ImportMode::Replace {
+            self.clear(&mut tx).await?;
+        }
+
+        for user in &self.users {
+            sqlx::query!(
+                r#"
+                INSERT INTO users (matricula, nome, pis, cpf, validade_inicio, validade_fim,
+                                   ativo, allow_card, allow_bio, allow_keypad, codigo, empresa)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(matricula) DO UPDATE SET
+                    nome = excluded.nome,
+                    pis = excluded.pis,
+                    cpf = excluded.cpf,
+                    validade_inicio = excluded.validade_inicio,
+                    validade_fim = excluded.validade_fim,
+                    ativo = excluded.ativo,
+                    allow_card = excluded.allow_card,
+                    allow_bio = excluded.allow_bio,
+                    allow_keypad = excluded.allow_keypad,
+                    codigo = excluded.codigo,
+                    codigo_digest = CASE WHEN codigo IS excluded.codigo THEN codigo_digest END,
+                    empresa = excluded.empresa
+                "#,
+                user.matricula,
+                user.nome,
+                user.pis,
+                user.cpf,
+                user.validade_inicio,
+                user.validade_fim,
+                user.ativo,
+                user.allow_card,
+                user.allow_bio,
+                user.allow_keypad,
+                user.codigo,
+                user.empresa
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for card in &self.cards {
+            sqlx::query!(
+                r#"
+                INSERT INTO cards (numero_cartao, matricula, user_id, validade_inicio, validade_fim, ativo)
+                VALUES (?, ?, (SELECT id FROM users WHERE matricula = ?), ?, ?, ?)
+                ON CONFLICT(numero_cartao) DO UPDATE SET
+                    matricula = excluded.matricula,
+                    user_id = excluded.user_id,
+                    validade_inicio = excluded.validade_inicio,
+                    validade_fim = excluded.validade_fim,
+                    ativo = excluded.ativo
+                "#,
+                card.numero_cartao,
+                card.matricula,
+                card.matricula,
+                card.validade_inicio,
+                card.validade_fim,
+                card.ativo
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for template in &self.biometric_templates {
+            let template_data = decode_hex("template_data", &template.template_data)?;
+            sqlx::query!(
+                r#"
+                INSERT INTO biometric_templates (matricula, user_id, posicao, template_data)
+                VALUES (?, (SELECT id FROM users WHERE matricula = ?), ?, ?)
+                ON CONFLICT(matricula, posicao) DO UPDATE SET
+                    template_data = excluded.template_data
+                "#,
+                template.matricula,
+                template.matricula,
+                template.posicao,
+                template_data
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        self.apply_sites(&mut tx).await?;
+        self.apply_devices(&mut tx).await?;
+        self.apply_exceptions(&mut tx).await?;
+        mark_synced(&mut tx, "bundle", Utc::now()).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Delete site data not covered by the bundle (replace mode)
+    async fn clear(&self, tx: &mut Transaction<'_, Sqlite>) -> StorageResult<()> {
+        // Exceptions go first: their zone restrictions block zone deletion
+        for table in [
+            "access_exceptions",
+            "biometric_templates",
+            "cards",
+            "users",
+            "sites",
+        ] {
+            sqlx::query(&format!("DELETE FROM {}", table))
+                .execute(&mut **tx)
+                .await?;
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM devices");
+        if !self.devices.is_empty() {
+            query.push(" WHERE device_id NOT IN (");
+            let mut ids = query.separated(", ");
+            for device in &self.devices {
+                ids.push_bind(device.device_id);
+            }
+            query.push(")");
+        }
+        query.build().execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    async fn apply_sites(&self, tx: &mut Transaction<'_, Sqlite>) -> StorageResult<()> {
+        for site in &self.sites {
+            let site_id: i64 = sqlx::query_scalar!(
+                r#"
+                INSERT INTO sites (name, description) VALUES (?, ?)
+                ON CONFLICT(name) DO UPDATE SET description = excluded.description
+                RETURNING id
+                "#,
+                site.name,
+                site.description
+            )
+            .fetch_one(&mut **tx)
+            .await?;
+
+            for zone in &site.zones {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO zones (site_id, name, description) VALUES (?, ?, ?)
+                    ON CONFLICT(site_id, name) DO UPDATE SET description = excluded.description
+                    "#,
+                    site_id,
+                    zone.name,
+                    zone.description
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_devices(&self, tx: &mut Transaction<'_, Sqlite>) -> StorageResult<()> {
+        for device in &self.devices {
+            let key = device
+                .hmac_key
+                .as_deref()
+                .map(|key| decode_hex("hmac_key", key))
+                .transpose()?;
+
+            if device.signing_required && key.is_none() {
+                let provisioned = sqlx::query_scalar!(
+                    r#"SELECT hmac_key IS NOT NULL AS "provisioned!: bool" FROM devices WHERE device_id = ?"#,
+                    device.device_id
+                )
+                .fetch_optional(&mut **tx)
+                .await?;
+
+                if provisioned != Some(true) {
+                    return Err(StorageError::Validation(format!(
+                        "Device {} requires signing but has no HMAC key in the bundle or the database",
+                        device.device_id
+                    )));
+                }
+            }
+
+            // The existing key is resolved in VALUES because CHECK constraints
+            // run on the inserted row before ON CONFLICT takes over.
+            let name = device.name.as_deref().map(str::trim);
+            let location = device.location.as_deref().map(str::trim);
+            let site = device.zone.as_ref().map(|zone| &zone.site);
+            let zone = device.zone.as_ref().map(|zone| &zone.zone);
+            let now = Utc::now();
+            sqlx::query!(
+                r#"
+                INSERT INTO devices (device_id, hmac_key, signing_required,
+                                     allow_card, allow_bio, allow_keypad, deny_when_stale,
+                                     verification_mode, show_balance, name, location,
+                                     zone_id, created_at, updated_at)
+                VALUES (?, COALESCE(?, (SELECT hmac_key FROM devices WHERE device_id = ?)),
+                        ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                        (SELECT z.id FROM zones z JOIN sites s ON s.id = z.site_id
+                         WHERE s.name = ? AND z.name = ?),
+                        ?, ?)
+                ON CONFLICT(device_id) DO UPDATE SET
+                    hmac_key = excluded.hmac_key,
+                    signing_required = excluded.signing_required,
+                    allow_card = excluded.allow_card,
+                    allow_bio = excluded.allow_bio,
+                    allow_keypad = excluded.allow_keypad,
+                    deny_when_stale = excluded.deny_when_stale,
+                    verification_mode = excluded.verification_mode,
+                    show_balance = excluded.show_balance,
+                    name = excluded.name,
+                    location = excluded.location,
+                    zone_id = excluded.zone_id,
+                    updated_at = excluded.updated_at
+                "#,
+                device.device_id,
+                key,
+                device.device_id,
+                device.signing_required,
+                device.allow_card,
+                device.allow_bio,
+                device.allow_keypad,
+                device.deny_when_stale,
+                device.verification_mode,
+                device.show_balance,
+                name,
+                location,
+                site,
+                zone,
+                now,
+                now
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            sqlx::query!(
+                "DELETE FROM device_tags WHERE device_id = ?",
+                device.device_id
+            )
+            .execute(&mut **tx)
+            .await?;
+            for tag in &device.tags {
+                let tag = tag.trim();
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO device_tags (device_id, tag) VALUES (?, ?)",
+                    device.device_id,
+                    tag
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_exceptions(&self, tx: &mut Transaction<'_, Sqlite>) -> StorageResult<()> {
+        for exception in &self.access_exceptions {
+            sqlx::query!(
+                "DELETE FROM access_exceptions WHERE name = ?",
+                exception.name
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            let id = sqlx::query!(
+                "INSERT INTO access_exceptions (name, starts_at, ends_at, active) VALUES (?, ?, ?, ?)",
+                exception.name,
+                exception.starts_at,
+                exception.ends_at,
+                exception.active
+            )
+            .execute(&mut **tx)
+            .await?
+            .last_insert_rowid();
+
+            for card in &exception.cards {
+                sqlx::query!(
+                    "INSERT INTO access_exception_members (exception_id, card_number) VALUES (?, ?)",
+                    id,
+                    card
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            for matricula in &exception.users {
+                sqlx::query!(
+                    "INSERT INTO access_exception_members (exception_id, matricula) VALUES (?, ?)",
+                    id,
+                    matricula
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            for device_id in &exception.devices {
+                sqlx::query!(
+                    "INSERT INTO access_exception_devices (exception_id, device_id) VALUES (?, ?)",
+                    id,
+                    device_id
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            for zone in &exception.zones {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO access_exception_zones (exception_id, zone_id)
+                    SELECT ?, z.id FROM zones z JOIN sites s ON s.id = z.site_id
+                    WHERE s.name = ? AND z.name = ?
+                    "#,
+                    id,
+                    zone.site,
+                    zone.zone
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Export the site configuration of `pool` as a tar bundle
+///
+/// # Errors
+///
+/// Returns `Database` on query failures and `Internal` on write failures.
+pub async fn export_bundle<W: Write>(
+    pool: &SqlitePool,
+    writer: W,
+    options: &ExportOptions,
+) -> StorageResult<BundleManifest> {
+    let bundle = SiteBundle::collect(pool, options).await?;
+    bundle.write_to(writer)?;
+    Ok(bundle.manifest)
+}
+
+/// Import a tar bundle into `pool` in a single transaction
+///
+/// # Errors
+///
+/// Returns `Validation` for malformed or incompatible bundles and
+/// `Database` if a record is rejected by the database. The target database
+/// is unchanged on error.
+pub async fn import_bundle<R: Read>(
+    pool: &SqlitePool,
+    reader: R,
+    mode: ImportMode,
+) -> StorageResult<ImportReport> {
+    let bundle = SiteBundle::read_from(reader)?;
+    bundle.apply(pool, mode).await?;
+    Ok(ImportReport {
+        manifest: bundle.manifest,
+        config: bundle.config,
+    })
+}
+
+/// Device row joined with its zone and site names
+struct DeviceRow {
+    device_id: i64,
+    signing_required: bool,
+    allow_card: bool,
+    allow_bio: bool,
+    allow_keypad: bool,
+    deny_when_stale: bool,
+    verification_mode: VerificationMode,
+    show_balance: bool,
+    name: Option<String>,
+    location: Option<String>,
+    hmac_key: Option<Vec<u8>>,
+    site_name: Option<String>,
+    zone_name: Option<String>,
+}
+
+fn zone_ref(site: Option<String>, zone: Option<String>) -> Option<ZoneRef> {
+    Some(ZoneRef {
+        site: site?,
+        zone: zone?,
+    })
+}
+
+fn append_json<W: Write, T: Serialize>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    value: &T,
+    mtime: u64,
+) -> StorageResult<()> {
+    let data = serde_json::to_vec_pretty(value)
+        .map_err(|e| StorageError::Internal(format!("Failed to serialize {}: {}", name, e)))?;
+
+    let mut header = tar::Header::new_ustar();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+
+    builder
+        .append_data(&mut header, name, data.as_slice())
+        .map_err(|e| StorageError::Internal(format!("Failed to write {}: {}", name, e)))
+}
+
+fn parse_entry<T: DeserializeOwned>(
+    entries: &BTreeMap<String, Vec<u8>>,
+    name: &str,
+) -> StorageResult<T> {
+    let data = entries
+        .get(name)
+        .ok_or_else(|| StorageError::Validation(format!("Bundle is missing {}", name)))?;
+
+    serde_json::from_slice(data)
+        .map_err(|e| StorageError::Validation(format!("Invalid {}: {}", name, e)))
+}
+
+fn decode_hex(field: &str, value: &str) -> StorageResult<Vec<u8>> {
+    hex::decode(value)
+        .map_err(|e| StorageError::Validation(format!("Invalid hex in {}: {}", field, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Site, Zone};
+    use crate::repositories::{
+        DeviceRepository, SiteRepository, SqliteDeviceRepository, SqliteSiteRepository,
+        SqliteSyncStateRepository, SyncStateRepository,
+    };
+
+    async fn export(db: &Database, options: &ExportOptions) -> Vec<u8> {
+        let mut archive = Vec::new();
+        export_bundle(db.pool(), &mut archive, options)
+            .await
+            .unwrap();
+        archive
+    }
+
+    async fn count(db: &Database, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(db.pool())
+            .await
+            .unwrap()
+    }
+
+    async fn seed_extras(db: &Database) {
+        let template = vec![0xABu8; 600];
+        sqlx::query!(
+            "INSERT INTO biometric_templates (matricula, user_id, posicao, template_data) VALUES ('1001', 1, 1, ?)",
+            template
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let devices = SqliteDeviceRepository::new(db.pool().clone());
+        devices.set_signing_key(15, &[7u8; 32], true).await.unwrap();
+        devices.set_deny_when_stale(15, true).await.unwrap();
+        devices
+            .set_verification_mode(15, VerificationMode::CardPlusBiometric)
+            .await
+            .unwrap();
+        devices.set_show_balance(15, true).await.unwrap();
+        devices
+            .set_label(15, Some("Portaria Norte"), Some("Bloco A"))
+            .await
+            .unwrap();
+        devices.set_tags(15, &["entrance"]).await.unwrap();
+
+        let id = sqlx::query!("INSERT INTO access_exceptions (name, starts_at, ends_at) VALUES ('Feira', '2025-11-01T08:00:00Z', '2025-11-01T18:00:00Z')")
+        .execute(db.pool())
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        sqlx::query!(
+            "INSERT INTO access_exception_members (exception_id, card_number) VALUES (?, 'VISITOR01')",
+            id
+        )
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query!(
+            "INSERT INTO access_exception_devices (exception_id, device_id) VALUES (?, 15)",
+            id
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let sites = SqliteSiteRepository::new(db.pool().clone());
+        let site_id = sites.create_site(&Site::new("Matriz")).await.unwrap();
+        let lobby = sites
+            .create_zone(&Zone::new(site_id, "Lobby").with_description("Terreo"))
+            .await
+            .unwrap();
+        sites
+            .create_zone(&Zone::new(site_id, "Garagem"))
+            .await
+            .unwrap();
+        sites.assign_device(15, Some(lobby)).await.unwrap();
+        sqlx::query!(
+            "INSERT INTO access_exception_zones (exception_id, zone_id) VALUES (?, ?)",
+            id,
+            lobby
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_into_empty_database() {
+        let source = Database::in_memory().await.unwrap();
+        seed_extras(&source).await;
+        let archive = export(
+            &source,
+            &ExportOptions::default()
+                .include_device_keys(true)
+                .setting("site.name", "Matriz"),
+        )
+        .await;
+
+        let target = Database::in_memory().await.unwrap();
+        sqlx::query!("DELETE FROM users")
+            .execute(target.pool())
+            .await
+            .unwrap();
+        let report = import_bundle(target.pool(), archive.as_slice(), ImportMode::Replace)
+            .await
+            .unwrap();
+
+        assert_eq!(report.config["site.name"], "Matriz");
+        assert_eq!(report.manifest.schema_version, current_schema_version());
+        for table in [
+            "users",
+            "cards",
+            "biometric_templates",
+            "devices",
+            "access_exceptions",
+            "access_exception_members",
+            "access_exception_devices",
+            "sites",
+            "zones",
+            "access_exception_zones",
+        ] {
+            assert_eq!(count(&source, table).await, count(&target, table).await);
+        }
+
+        let device = SqliteDeviceRepository::new(target.pool().clone())
+            .find_by_id(15)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(device.hmac_key, Some(vec![7u8; 32]));
+        assert!(device.signing_required);
+        assert!(device.deny_when_stale);
+        assert_eq!(
+            device.verification_mode,
+            VerificationMode::CardPlusBiometric
+        );
+        assert!(device.show_balance);
+        assert_eq!(device.name.as_deref(), Some("Portaria Norte"));
+        assert_eq!(device.location.as_deref(), Some("Bloco A"));
+        assert_eq!(
+            SqliteDeviceRepository::new(target.pool().clone())
+                .find_by_tag("entrance")
+                .await
+                .unwrap(),
+            vec![15]
+        );
+        let sync = SqliteSyncStateRepository::new(target.pool().clone())
+            .get()
+            .await
+            .unwrap();
+        assert!(sync.last_success_at.is_some());
+        assert_eq!(sync.last_source.as_deref(), Some("bundle"));
+        let lobby = SqliteSiteRepository::new(target.pool().clone())
+            .find_zone_by_id(device.zone_id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lobby.name, "Lobby");
+        assert_eq!(lobby.description.as_deref(), Some("Terreo"));
+
+        let source_bundle = SiteBundle::collect(source.pool(), &ExportOptions::default())
+            .await
+            .unwrap();
+        let target_bundle = SiteBundle::collect(target.pool(), &ExportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(source_bundle.users, target_bundle.users);
+        assert_eq!(source_bundle.cards, target_bundle.cards);
+        assert_eq!(source_bundle.sites, target_bundle.sites);
+        assert_eq!(source_bundle.devices, target_bundle.devices);
+        assert_eq!(
+            source_bundle.access_exceptions,
+            target_bundle.access_exceptions
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keys_excluded_by_default() {
+        let source = Database::in_memory().await.unwrap();
+        seed_extras(&source).await;
+        let archive = export(&source, &ExportOptions::default()).await;
+
+        let bundle = SiteBundle::read_from(archive.as_slice()).unwrap();
+        assert!(!bundle.manifest.includes_device_keys);
+        assert!(bundle.devices.iter().all(|d| d.hmac_key.is_none()));
+
+        // Signing device without a key anywhere cannot be imported
+        let target = Database::in_memory().await.unwrap();
+        let err = bundle
+            .apply(target.pool(), ImportMode::Merge)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Validation(_)));
+        assert_eq!(count(&target, "devices").await, 0);
+        assert_eq!(count(&target, "biometric_templates").await, 0);
+
+        // A target that already has the key keeps it
+        SqliteDeviceRepository::new(target.pool().clone())
+            .set_signing_key(15, &[9u8; 16], false)
+            .await
+            .unwrap();
+        bundle
+            .apply(target.pool(), ImportMode::Merge)
+            .await
+            .unwrap();
+        let device = SqliteDeviceRepository::new(target.pool().clone())
+            .find_by_id(15)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(device.hmac_key, Some(vec![9u8; 16]));
+        assert!(device.signing_required);
+    }
+
+    #[tokio::test]
+    async fn test_merge_keeps_unlisted_records() {
+        let source = Database::in_memory().await.unwrap();
+        let mut bundle = SiteBundle::collect(source.pool(), &ExportOptions::default())
+            .await
+            .unwrap();
+        bundle.users.retain(|u| u.matricula == "1009");
+        bundle.users[0].nome = "Nome Atualizado".to_string();
+        bundle.cards.retain(|c| c.matricula == "1009");
+        bundle.manifest.counts = bundle.counts();
+
+        let target = Database::in_memory().await.unwrap();
+        let users_before = count(&target, "users").await;
+        bundle
+            .apply(target.pool(), ImportMode::Merge)
+            .await
+            .unwrap();
+
+        assert_eq!(count(&target, "users").await, users_before);
+        let nome: String = sqlx::query_scalar!("SELECT nome FROM users WHERE matricula = '1009'")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(nome, "Nome Atualizado");
+
+        bundle
+            .apply(target.pool(), ImportMode::Replace)
+            .await
+            .unwrap();
+        assert_eq!(count(&target, "users").await, 1);
+        assert_eq!(count(&target, "cards").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_incompatible_versions() {
+        let db = Database::in_memory().await.unwrap();
+        let mut bundle = SiteBundle::collect(db.pool(), &ExportOptions::default())
+            .await
+            .unwrap();
+
+        bundle.manifest.schema_version = current_schema_version() + 1;
+        let mut archive = Vec::new();
+        bundle.write_to(&mut archive).unwrap();
+        let err = import_bundle(db.pool(), archive.as_slice(), ImportMode::Merge)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("newer"));
+
+        bundle.manifest.schema_version = current_schema_version();
+        bundle.manifest.format_version = BUNDLE_FORMAT_VERSION + 1;
+        assert!(bundle.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_inconsistent_bundle() {
+        let db = Database::in_memory().await.unwrap();
+        let mut bundle = SiteBundle::collect(db.pool(), &ExportOptions::default())
+            .await
+            .unwrap();
+
+        bundle.users.pop();
+        let err = bundle.validate().unwrap_err();
+        assert!(err.to_string().contains("counts"));
+
+        bundle.manifest.counts = bundle.counts();
+        bundle.cards[0].matricula = "GHOST".to_string();
+        let err = bundle.validate().unwrap_err();
+        assert!(err.to_string().contains("GHOST"));
+
+        bundle.cards.remove(0);
+        bundle.manifest.counts = bundle.counts();
+        bundle.devices.push(DeviceRecord {
+            device_id: 20,
+            signing_required: false,
+            allow_card: true,
+            allow_bio: true,
+            allow_keypad: true,
+            deny_when_stale: false,
+            verification_mode: VerificationMode::SingleFactor,
+            show_balance: false,
+            name: None,
+            location: None,
+            tags: Vec::new(),
+            hmac_key: None,
+            zone: Some(ZoneRef {
+                site: "Filial".to_string(),
+                zone: "Portaria".to_string(),
+            }),
+        });
+        bundle.manifest.counts = bundle.counts();
+        let err = bundle.validate().unwrap_err();
+        assert!(err.to_string().contains("Filial/Portaria"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_import_rolls_back() {
+        let db = Database::in_memory().await.unwrap();
+        let mut bundle = SiteBundle::collect(db.pool(), &ExportOptions::default())
+            .await
+            .unwrap();
+        // Violates CHECK (LENGTH(nome) <= 100)
+        bundle.users.last_mut().unwrap().nome = "x".repeat(101);
+
+        let users_before = count(&db, "users").await;
+        let err = bundle
+            .apply(db.pool(), ImportMode::Replace)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::Database(_)));
+        assert_eq!(count(&db, "users").await, users_before);
+        let sync = SqliteSyncStateRepository::new(db.pool().clone())
+            .get()
+            .await
+            .unwrap();
+        assert_eq!(sync.last_success_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_reads_bundle_without_sites() {
+        let db = Database::in_memory().await.unwrap();
+        let mut archive = Vec::new();
+        SiteBundle::collect(db.pool(), &ExportOptions::default())
+            .await
+            .unwrap()
+            .write_to(&mut archive)
+            .unwrap();
+
+        // Rebuild the archive the way bundles were written before zones
+        let mut legacy = Vec::new();
+        {
+            let mut builder = tar::Builder::new(&mut legacy);
+            let mut source = tar::Archive::new(archive.as_slice());
+            for entry in source.entries().unwrap() {
+                let mut entry = entry.unwrap();
+                if entry.path().unwrap().to_str() == Some(SITES_ENTRY) {
+                    continue;
+                }
+                let mut header = entry.header().clone();
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                builder
+                    .append_data(&mut header, entry.path().unwrap(), data.as_slice())
+                    .unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let bundle = SiteBundle::read_from(legacy.as_slice()).unwrap();
+        assert!(bundle.sites.is_empty());
+        bundle.validate().unwrap();
+    }
+
+    #[test]
+    fn test_read_rejects_missing_entries() {
+        let mut archive = Vec::new();
+        {
+            let mut builder = tar::Builder::new(&mut archive);
+            append_json(
+                &mut builder,
+                MANIFEST_ENTRY,
+                &BTreeMap::<String, String>::new(),
+                0,
+            )
+            .unwrap();
+            builder.finish().unwrap();
+        }
+
+        let err = SiteBundle::read_from(archive.as_slice()).unwrap_err();
+        assert!(matches!(err, StorageError::Validation(_)));
+    }
+}
@@ -0,0 +1,620 @@
+//! Bloom filter over registered card numbers for fast unknown-card denials.
+//!
+//! At large sites most invalid taps are cards that were never registered.
+//! [`CardFilter`] holds every card number of the `cards` table in a bloom
+//! filter: when it answers "not registered" the validator skips the card
+//! query. A bloom filter has no false negatives, so a card the filter holds
+//! is always looked up; a small share of unknown cards (the false positive
+//! rate, see [`CardFilterConfig`]) still reach the database and are denied
+//! there. Exceptions and pending-card capture for unregistered cards run as
+//! before.
+//!
+//! # Keeping Up With Changes
+//!
+//! The filter only knows the cards it was told about. Cards written through
+//! a [`SqliteCardRepository`](crate::repositories::SqliteCardRepository)
+//! set up with
+//! [`with_card_filter()`](crate::repositories::SqliteCardRepository::with_card_filter)
+//! are added as they are written. Cards written any other way (transactions,
+//! bundle and Henry imports, another process) reach it through the
+//! [`changes`](crate::changes) outbox: the filter remembers the last change
+//! event it has seen, and before it rules a card out,
+//! [`rules_out()`](CardFilter::rules_out) reads any newer events first. A
+//! card is therefore never denied as unregistered just because the filter
+//! has not heard of it yet. The check costs one query on the outbox's
+//! primary key, which finds nothing while the filter is current; calling
+//! [`sync()`](CardFilter::sync) from a change feed keeps it that way.
+//!
+//! A bloom filter cannot forget a card, so a deleted card, or a filter
+//! holding more cards than it was sized for, triggers a
+//! [`rebuild()`](CardFilter::rebuild) from the database.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use turnkey_storage::card_filter::{CardFilter, CardFilterConfig};
+//! use turnkey_storage::changes::ChangeFeed;
+//! use turnkey_storage::repositories::SqliteCardRepository;
+//! use turnkey_storage::{Database, DatabaseConfig, OfflineValidator};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! let config = CardFilterConfig::new(500_000, 0.001)?;
+//! let filter = Arc::new(CardFilter::build(db.pool().clone(), config).await?);
+//! let validator = OfflineValidator::new(db.pool().clone()).with_card_filter(filter.clone());
+//!
+//! // Cards registered here are recognized right away
+//! let cards = SqliteCardRepository::new(db.pool().clone()).with_card_filter(filter.clone());
+//!
+//! let feed = ChangeFeed::new(db.pool().clone(), "card-filter")?;
+//! loop {
+//!     if filter.sync(&feed).await? == 0 {
+//!         tokio::time::sleep(Duration::from_secs(1)).await;
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::changes::{ChangeEntity, ChangeEvent, ChangeFeed, ChangeOperation};
+use crate::error::{StorageError, StorageResult};
+use sqlx::SqlitePool;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+/// Cards a filter is sized for unless configured otherwise
+pub const DEFAULT_EXPECTED_CARDS: usize = 100_000;
+
+/// Share of unknown cards still looked up unless configured otherwise
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Most hash functions used per card
+const MAX_HASHES: u32 = 16;
+
+/// Size and accuracy of a [`CardFilter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardFilterConfig {
+    expected_cards: usize,
+    false_positive_rate: f64,
+}
+
+impl Default for CardFilterConfig {
+    fn default() -> Self {
+        Self {
+            expected_cards: DEFAULT_EXPECTED_CARDS,
+            false_positive_rate: DEFAULT_FALSE_POSITIVE_RATE,
+        }
+    }
+}
+
+impl CardFilterConfig {
+    /// Size a filter for `expected_cards` with the given false positive rate
+    ///
+    /// The filter grows to the number of cards in the database when it is
+    /// built, so `expected_cards` only needs to cover the site's size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if `expected_cards` is 0 or the rate is not
+    /// strictly between 0 and 1.
+    pub fn new(expected_cards: usize, false_positive_rate: f64) -> StorageResult<Self> {
+        if expected_cards == 0 {
+            return Err(StorageError::Validation(
+                "Card filter must expect at least one card".to_string(),
+            ));
+        }
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(StorageError::Validation(format!(
+                "False positive rate must be between 0 and 1, got {}",
+                false_positive_rate
+            )));
+        }
+        Ok(Self {
+            expected_cards,
+            false_positive_rate,
+        })
+    }
+
+    /// Cards the filter is sized for
+    pub fn expected_cards(&self) -> usize {
+        self.expected_cards
+    }
+
+    /// Share of unknown cards still looked up
+    pub fn false_positive_rate(&self) -> f64 {
+        self.false_positive_rate
+    }
+
+    /// Bits and hash functions for `cards` at the configured rate
+    fn dimensions(&self, cards: usize) -> (usize, u32) {
+        let cards = cards.max(self.expected_cards) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-cards * self.false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let bits = (bits as usize).max(64).next_multiple_of(64);
+        let hashes = ((bits as f64 / cards) * ln2).round() as u32;
+        (bits, hashes.clamp(1, MAX_HASHES))
+    }
+}
+
+/// Hit rates of a [`CardFilter`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardFilterMetrics {
+    /// Card numbers in the filter, deleted ones included until a rebuild
+    pub cards: u64,
+
+    /// Size of the filter in bits
+    pub bits: u64,
+
+    /// Card numbers checked
+    pub lookups: u64,
+
+    /// Lookups answered "not registered" without a database query
+    pub rejected: u64,
+
+    /// Lookups passed to the database that found no card
+    pub false_positives: u64,
+
+    /// Full reloads from the database, the initial build included
+    pub rebuilds: u64,
+}
+
+impl CardFilterMetrics {
+    /// Share of lookups answered without a database query
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+        self.rejected as f64 / self.lookups as f64
+    }
+
+    /// Share of unknown cards that still reached the database
+    pub fn observed_false_positive_rate(&self) -> f64 {
+        let unknown = self.rejected + self.false_positives;
+        if unknown == 0 {
+            return 0.0;
+        }
+        self.false_positives as f64 / unknown as f64
+    }
+}
+
+/// Bit array and hash count of the filter
+#[derive(Debug)]
+struct Bloom {
+    bits: Vec<u64>,
+    hashes: u32,
+    cards: u64,
+    capacity: usize,
+}
+
+impl Bloom {
+    fn new(config: &CardFilterConfig, cards: usize) -> Self {
+        let (bits, hashes) = config.dimensions(cards);
+        Self {
+            bits: vec![0; bits / 64],
+            hashes,
+            cards: 0,
+            capacity: cards.max(config.expected_cards),
+        }
+    }
+
+    /// Bit positions of a card number (double hashing)
+    fn positions(&self, card_number: &str) -> impl Iterator<Item = usize> + use<> {
+        let mut hasher = DefaultHasher::new();
+        card_number.hash(&mut hasher);
+        let first = hasher.finish();
+        0xA5u8.hash(&mut hasher);
+        let second = hasher.finish() | 1;
+
+        let len = self.bits.len() as u64 * 64;
+        (0..u64::from(self.hashes))
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % len) as usize)
+    }
+
+    fn insert(&mut self, card_number: &str) {
+        for position in self.positions(card_number) {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+        self.cards += 1;
+    }
+
+    fn contains(&self, card_number: &str) -> bool {
+        self.positions(card_number)
+            .all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    fn is_full(&self) -> bool {
+        self.cards > self.capacity as u64
+    }
+}
+
+/// Bloom filter of the card numbers in the `cards` table
+///
+/// Shared between validators and the task following the change feed. See
+/// the [module documentation](self) for how it stays up to date.
+#[derive(Debug)]
+pub struct CardFilter {
+    pool: SqlitePool,
+    config: CardFilterConfig,
+    bloom: RwLock<Bloom>,
+    /// Last `change_events` ID the bloom reflects
+    seen_through: AtomicI64,
+    /// Held while reloading or catching up, so neither loses the other's cards
+    refresh: Mutex<()>,
+    lookups: AtomicU64,
+    rejected: AtomicU64,
+    false_positives: AtomicU64,
+    rebuilds: AtomicU64,
+}
+
+impl CardFilter {
+    /// Build the filter from every card in the database
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the cards cannot be read.
+    pub async fn build(pool: SqlitePool, config: CardFilterConfig) -> StorageResult<Self> {
+        let filter = Self {
+            pool,
+            config,
+            bloom: RwLock::new(Bloom::new(&config, 0)),
+            seen_through: AtomicI64::new(0),
+            refresh: Mutex::new(()),
+            lookups: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            false_positives: AtomicU64::new(0),
+            rebuilds: AtomicU64::new(0),
+        };
+        filter.rebuild().await?;
+        Ok(filter)
+    }
+
+    /// Configured size and accuracy
+    pub fn config(&self) -> CardFilterConfig {
+        self.config
+    }
+
+    /// Reload every card number from the database
+    ///
+    /// The filter is resized when the site has outgrown the configured
+    /// number of cards. Returns the number of cards loaded.
+    pub async fn rebuild(&self) -> StorageResult<u64> {
+        let _refresh = self.refresh.lock().await;
+        self.reload().await
+    }
+
+    /// Rebuild with `refresh` held
+    async fn reload(&self) -> StorageResult<u64> {
+        // Read before the cards: a change committed in between is read
+        // again by the next catch-up, which is harmless
+        let seen_through = latest_change(&self.pool).await?;
+        let cards = sqlx::query_scalar!("SELECT numero_cartao FROM cards")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut bloom = Bloom::new(&self.config, cards.len());
+        for card_number in &cards {
+            bloom.insert(card_number);
+        }
+        let count = bloom.cards;
+        *self.bloom.write().unwrap_or_else(|e| e.into_inner()) = bloom;
+        self.seen_through.store(seen_through, Ordering::Release);
+        self.rebuilds.fetch_add(1, Ordering::Relaxed);
+
+        tracing::debug!(cards = count, "card filter rebuilt");
+        Ok(count)
+    }
+
+    /// Add a card number, e.g. right after registering it
+    pub fn insert(&self, card_number: &str) {
+        self.bloom
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(card_number);
+    }
+
+    /// Check whether a card number may be registered
+    ///
+    /// `false` means the card is certainly not in the `cards` table. The
+    /// number is compared as stored, so pass it normalized like the card
+    /// query would.
+    pub fn might_contain(&self, card_number: &str) -> bool {
+        let found = self.contains(card_number);
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        if !found {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        found
+    }
+
+    /// Check whether a card number is certainly not registered
+    ///
+    /// Like [`might_contain()`](Self::might_contain), but before answering
+    /// `true` the filter [catches up](Self::catch_up) with cards written
+    /// since it last looked, however they were written.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the change outbox cannot be read.
+    pub async fn rules_out(&self, card_number: &str) -> StorageResult<bool> {
+        if self.contains(card_number) || (self.catch_up().await? && self.contains(card_number)) {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            return Ok(false);
+        }
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    /// Add the cards changed since the filter last looked at the outbox
+    ///
+    /// Rebuilds instead when events it has not seen were already purged.
+    /// Returns whether anything changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the outbox or the cards cannot be read.
+    pub async fn catch_up(&self) -> StorageResult<bool> {
+        let _refresh = self.refresh.lock().await;
+        let seen_through = self.seen_through.load(Ordering::Acquire);
+        let latest = latest_change(&self.pool).await?;
+        if latest == seen_through {
+            return Ok(false);
+        }
+
+        let events = sqlx::query_as!(
+            ChangeEvent,
+            r#"
+            SELECT id AS "id!", entity AS "entity: ChangeEntity", entity_key,
+                   matricula, operation AS "operation: ChangeOperation",
+                   changed_at AS "changed_at: _"
+            FROM change_events
+            WHERE id > ?
+            ORDER BY id
+            "#,
+            seen_through
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let purged = latest < seen_through
+            || events
+                .first()
+                .is_none_or(|event| event.id != seen_through + 1);
+        if purged || self.insert_changes(&events) {
+            self.reload().await?;
+        } else if let Some(last) = events.last() {
+            self.seen_through.store(last.id, Ordering::Release);
+        }
+        Ok(true)
+    }
+
+    fn contains(&self, card_number: &str) -> bool {
+        self.bloom
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(card_number)
+    }
+
+    /// Count a card that passed the filter but was not found in the database
+    pub fn record_false_positive(&self) {
+        self.false_positives.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Apply card changes from the change feed
+    ///
+    /// Inserted and renamed cards are added; a deleted card, or more cards
+    /// than the filter was sized for, triggers one rebuild. Changes to
+    /// other entities are ignored.
+    pub async fn apply_changes(&self, events: &[ChangeEvent]) -> StorageResult<()> {
+        if self.insert_changes(events) {
+            self.rebuild().await?;
+        }
+        Ok(())
+    }
+
+    /// Add inserted and renamed cards, returning whether a rebuild is due
+    fn insert_changes(&self, events: &[ChangeEvent]) -> bool {
+        let mut rebuild = false;
+        let mut bloom = self.bloom.write().unwrap_or_else(|e| e.into_inner());
+        for event in events.iter().filter(|e| e.entity == ChangeEntity::Card) {
+            match event.operation {
+                ChangeOperation::Insert | ChangeOperation::Update => {
+                    bloom.insert(&event.entity_key)
+                }
+                ChangeOperation::Delete => rebuild = true,
+            }
+        }
+        rebuild || bloom.is_full()
+    }
+
+    /// Apply the next batch of the change feed and acknowledge it
+    ///
+    /// Returns the number of events read, 0 when the feed is drained.
+    pub async fn sync(&self, feed: &ChangeFeed) -> StorageResult<usize> {
+        let events = feed.poll().await?;
+        self.apply_changes(&events).await?;
+        if let Some(last) = events.last() {
+            feed.acknowledge(last.id).await?;
+        }
+        Ok(events.len())
+    }
+
+    /// Current size and hit rates
+    pub fn metrics(&self) -> CardFilterMetrics {
+        let bloom = self.bloom.read().unwrap_or_else(|e| e.into_inner());
+        CardFilterMetrics {
+            cards: bloom.cards,
+            bits: bloom.bits.len() as u64 * 64,
+            lookups: self.lookups.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            false_positives: self.false_positives.load(Ordering::Relaxed),
+            rebuilds: self.rebuilds.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// ID of the newest event in the change outbox (0 if it is empty)
+async fn latest_change(pool: &SqlitePool) -> StorageResult<i64> {
+    let latest =
+        sqlx::query_scalar!(r#"SELECT COALESCE(MAX(id), 0) AS "id!: i64" FROM change_events"#)
+            .fetch_one(pool)
+            .await?;
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::Card;
+    use crate::repositories::{CardRepository, SqliteCardRepository};
+    use chrono::Utc;
+    use std::sync::Arc;
+
+    const SEEDED_CARD: &str = "00000000000022823433";
+
+    fn card(numero: &str) -> Card {
+        Card {
+            id: 0,
+            numero_cartao: numero.to_string(),
+            matricula: "1002".to_string(),
+            user_id: 2,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_config_validation_and_sizing() {
+        assert!(CardFilterConfig::new(0, 0.01).is_err());
+        assert!(CardFilterConfig::new(1000, 0.0).is_err());
+        assert!(CardFilterConfig::new(1000, 1.0).is_err());
+        assert!(CardFilterConfig::new(1000, f64::NAN).is_err());
+
+        // About 9.6 bits and 7 hashes per card at 1%
+        let (bits, hashes) = CardFilterConfig::new(1000, 0.01).unwrap().dimensions(0);
+        assert_eq!(bits, 9600);
+        assert_eq!(hashes, 7);
+
+        // Sized for the actual number of cards when it is larger
+        let (grown, _) = CardFilterConfig::new(1000, 0.01).unwrap().dimensions(2000);
+        assert_eq!(grown, 2 * bits);
+    }
+
+    #[test]
+    fn test_false_positive_rate_within_config() {
+        let config = CardFilterConfig::new(10_000, 0.01).unwrap();
+        let mut bloom = Bloom::new(&config, 0);
+        for i in 0..10_000 {
+            bloom.insert(&format!("{:020}", i));
+        }
+        assert!((0..10_000).all(|i| bloom.contains(&format!("{:020}", i))));
+
+        let false_positives = (10_000..60_000)
+            .filter(|i| bloom.contains(&format!("{:020}", i)))
+            .count();
+        assert!(
+            false_positives < 1000,
+            "{} false positives",
+            false_positives
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_built_from_cards() {
+        let db = Database::in_memory().await.unwrap();
+        let filter = CardFilter::build(db.pool().clone(), CardFilterConfig::default())
+            .await
+            .unwrap();
+
+        assert!(filter.might_contain(SEEDED_CARD));
+        assert!(!filter.might_contain("99999999999999999999"));
+        filter.record_false_positive();
+
+        let metrics = filter.metrics();
+        assert!(metrics.cards >= 1);
+        assert_eq!(metrics.lookups, 2);
+        assert_eq!(metrics.rejected, 1);
+        assert_eq!(metrics.rebuilds, 1);
+        assert_eq!(metrics.hit_rate(), 0.5);
+        assert_eq!(metrics.observed_false_positive_rate(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_sync_follows_card_changes() {
+        let db = Database::in_memory().await.unwrap();
+        let feed = ChangeFeed::new(db.pool().clone(), "card-filter").unwrap();
+        let filter = CardFilter::build(db.pool().clone(), CardFilterConfig::default())
+            .await
+            .unwrap();
+        while filter.sync(&feed).await.unwrap() > 0 {}
+        let rebuilds = filter.metrics().rebuilds;
+
+        let repo = SqliteCardRepository::new(db.pool().clone());
+        let id = repo.create(&card("12345678")).await.unwrap();
+        assert!(!filter.might_contain("12345678"));
+
+        filter.sync(&feed).await.unwrap();
+        assert!(filter.might_contain("12345678"));
+        assert_eq!(filter.metrics().rebuilds, rebuilds);
+
+        // Deletes cannot be removed from the filter, so it is rebuilt
+        repo.delete(id).await.unwrap();
+        filter.sync(&feed).await.unwrap();
+        assert!(!filter.might_contain("12345678"));
+        assert_eq!(filter.metrics().rebuilds, rebuilds + 1);
+    }
+
+    #[tokio::test]
+    async fn test_rules_out_catches_up_with_outbox() {
+        let db = Database::in_memory().await.unwrap();
+        let filter = CardFilter::build(db.pool().clone(), CardFilterConfig::default())
+            .await
+            .unwrap();
+        assert!(filter.rules_out("12345678").await.unwrap());
+        assert!(!filter.catch_up().await.unwrap());
+
+        // Written behind the filter's back
+        let repo = SqliteCardRepository::new(db.pool().clone());
+        repo.create(&card("12345678")).await.unwrap();
+        assert!(!filter.rules_out("12345678").await.unwrap());
+        assert_eq!(filter.metrics().rebuilds, 1);
+
+        // Events purged before the filter read them force a rebuild
+        repo.create(&card("23456789")).await.unwrap();
+        sqlx::query!("DELETE FROM change_events")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(!filter.rules_out("23456789").await.unwrap());
+        assert_eq!(filter.metrics().rebuilds, 2);
+
+        let metrics = filter.metrics();
+        assert_eq!((metrics.lookups, metrics.rejected), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_repository_adds_cards_as_written() {
+        let db = Database::in_memory().await.unwrap();
+        let filter = Arc::new(
+            CardFilter::build(db.pool().clone(), CardFilterConfig::default())
+                .await
+                .unwrap(),
+        );
+        let repo = SqliteCardRepository::new(db.pool().clone()).with_card_filter(filter.clone());
+
+        let mut registered = card("12345678");
+        registered.id = repo.create(&registered).await.unwrap();
+        assert!(filter.might_contain("12345678"));
+
+        registered.numero_cartao = "87654321".to_string();
+        repo.update(&registered).await.unwrap();
+        assert!(filter.might_contain("87654321"));
+    }
+}
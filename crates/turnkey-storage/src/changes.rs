@@ -0,0 +1,464 @@
+//! Change data capture for users, cards and fingerprint templates.
+//!
+//! Validator caches, template caches and device sync all have to learn when
+//! the people and credentials they hold change. Database triggers append a
+//! [`ChangeEvent`] to the `change_events` outbox in the same transaction as
+//! every insert, update or delete on `users`, `cards` and
+//! `biometric_templates`. Repositories, imports, bundles and manual SQL are
+//! all covered, and an event exists exactly when its change committed.
+//!
+//! # Delivery
+//!
+//! Each consumer reads the outbox through a [`ChangeFeed`] with its own
+//! cursor in `change_cursors`. [`poll()`](ChangeFeed::poll) returns the
+//! events past the cursor and [`acknowledge()`](ChangeFeed::acknowledge)
+//! moves it forward once they are handled. An event read but not
+//! acknowledged before a crash or restart is read again, so delivery is
+//! at-least-once and consumers must tolerate duplicates. Invalidating a
+//! cache entry twice is harmless.
+//!
+//! [`relay()`](ChangeFeed::relay) forwards the feed into a channel for
+//! in-process consumers, acknowledging each batch once it is queued. The
+//! guarantee then ends at the channel, which suits in-memory caches that
+//! start empty after a restart anyway. Consumers that persist what they
+//! derive, such as device sync, should poll and acknowledge themselves.
+//!
+//! Old events are removed with [`purge()`](ChangeFeed::purge), which keeps
+//! anything a registered consumer has not acknowledged yet.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tokio::sync::mpsc;
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::changes::{ChangeEntity, ChangeFeed};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let feed = ChangeFeed::new(db.pool().clone(), "device-sync")?;
+//!
+//! loop {
+//!     let events = feed.poll().await?;
+//!     for event in &events {
+//!         if event.entity == ChangeEntity::Card {
+//!             println!("resend card {} ({:?})", event.entity_key, event.operation);
+//!         }
+//!     }
+//!     match events.last() {
+//!         Some(last) => feed.acknowledge(last.id).await?,
+//!         None => tokio::time::sleep(Duration::from_secs(1)).await,
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Events returned by one [`ChangeFeed::poll()`] unless configured otherwise
+pub const DEFAULT_CHANGE_BATCH_SIZE: i64 = 500;
+
+/// Longest consumer name accepted by `change_cursors`
+pub const MAX_CONSUMER_NAME_LENGTH: usize = 64;
+
+/// Table a change happened in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum ChangeEntity {
+    /// `users`, keyed by matricula
+    User,
+    /// `cards`, keyed by card number
+    Card,
+    /// `biometric_templates`, keyed by `matricula:posicao`
+    BiometricTemplate,
+}
+
+/// Kind of change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum ChangeOperation {
+    /// Row created
+    Insert,
+    /// Row modified
+    Update,
+    /// Row removed, or its key renamed (the new key gets an update)
+    Delete,
+}
+
+/// One captured change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ChangeEvent {
+    /// Outbox sequence number, increasing in commit order
+    pub id: i64,
+
+    /// Table the change happened in
+    pub entity: ChangeEntity,
+
+    /// Matricula, card number or `matricula:posicao`
+    pub entity_key: String,
+
+    /// User the changed row belongs to
+    pub matricula: Option<String>,
+
+    /// Kind of change
+    pub operation: ChangeOperation,
+
+    /// When the change was committed
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Cursor of one consumer over the `change_events` outbox
+///
+/// See the [module documentation](self) for the delivery guarantees.
+#[derive(Debug, Clone)]
+pub struct ChangeFeed {
+    pool: SqlitePool,
+    consumer: String,
+    batch_size: i64,
+}
+
+impl ChangeFeed {
+    /// Open the feed of `consumer`
+    ///
+    /// A consumer seen for the first time starts at the beginning of the
+    /// outbox. Its cursor is stored on the first acknowledgement.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the name is empty or longer than
+    /// [`MAX_CONSUMER_NAME_LENGTH`].
+    pub fn new(pool: SqlitePool, consumer: impl Into<String>) -> StorageResult<Self> {
+        let consumer = consumer.into();
+        if consumer.is_empty() || consumer.len() > MAX_CONSUMER_NAME_LENGTH {
+            return Err(StorageError::Validation(format!(
+                "Consumer name must be 1 to {} characters, got {}",
+                MAX_CONSUMER_NAME_LENGTH,
+                consumer.len()
+            )));
+        }
+
+        Ok(Self {
+            pool,
+            consumer,
+            batch_size: DEFAULT_CHANGE_BATCH_SIZE,
+        })
+    }
+
+    /// Set how many events [`poll()`](Self::poll) returns at most
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Name of the consumer
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    /// ID of the last acknowledged event (0 if none)
+    pub async fn cursor(&self) -> StorageResult<i64> {
+        let cursor = sqlx::query_scalar!(
+            "SELECT last_event_id FROM change_cursors WHERE consumer = ?",
+            self.consumer
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(cursor.unwrap_or(0))
+    }
+
+    /// Get the next events past the cursor, oldest first
+    ///
+    /// The cursor does not move: polling again returns the same events
+    /// until they are acknowledged.
+    pub async fn poll(&self) -> StorageResult<Vec<ChangeEvent>> {
+        let events = sqlx::query_as!(
+            ChangeEvent,
+            r#"
+            SELECT id AS "id!", entity AS "entity: _", entity_key, matricula,
+                   operation AS "operation: _", changed_at AS "changed_at: _"
+            FROM change_events
+            WHERE id > COALESCE(
+                (SELECT last_event_id FROM change_cursors WHERE consumer = ?), 0
+            )
+            ORDER BY id
+            LIMIT ?
+            "#,
+            self.consumer,
+            self.batch_size
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Mark every event up to `event_id` as handled
+    ///
+    /// The cursor never moves back, so acknowledging an older event again
+    /// is a no-op.
+    pub async fn acknowledge(&self, event_id: i64) -> StorageResult<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO change_cursors (consumer, last_event_id, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (consumer) DO UPDATE SET
+                last_event_id = MAX(last_event_id, excluded.last_event_id),
+                updated_at = excluded.updated_at
+            "#,
+            self.consumer,
+            event_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forward the feed into `events` until the receiver is dropped
+    ///
+    /// Each batch is acknowledged once it is queued in the channel; when
+    /// the outbox is drained the relay waits `interval` before polling
+    /// again. Returns `Ok(())` when the receiver is gone, after
+    /// acknowledging what it accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a database operation fails; events not yet
+    /// acknowledged are delivered again by the next relay.
+    pub async fn relay(
+        &self,
+        events: mpsc::Sender<ChangeEvent>,
+        interval: Duration,
+    ) -> StorageResult<()> {
+        loop {
+            let batch = self.poll().await?;
+            let drained = (batch.len() as i64) < self.batch_size;
+
+            let mut delivered = None;
+            for event in batch {
+                let id = event.id;
+                if events.send(event).await.is_err() {
+                    if let Some(id) = delivered {
+                        self.acknowledge(id).await?;
+                    }
+                    return Ok(());
+                }
+                delivered = Some(id);
+            }
+            if let Some(id) = delivered {
+                self.acknowledge(id).await?;
+            }
+
+            if drained {
+                tokio::select! {
+                    _ = events.closed() => return Ok(()),
+                    _ = tokio::time::sleep(interval) => {}
+                }
+            }
+        }
+    }
+
+    /// Delete events older than `before` that every consumer has acknowledged
+    ///
+    /// Returns the number of events removed. With no registered consumer,
+    /// every event older than `before` is removed.
+    pub async fn purge(pool: &SqlitePool, before: DateTime<Utc>) -> StorageResult<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM change_events
+            WHERE changed_at < strftime('%Y-%m-%dT%H:%M:%fZ', ?)
+              AND id <= COALESCE((SELECT MIN(last_event_id) FROM change_cursors), id)
+            "#,
+            before
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Card, User};
+    use crate::repositories::{
+        BiometricTemplateRepository, CardRepository, SqliteBiometricTemplateRepository,
+        SqliteCardRepository, SqliteUserRepository, UserRepository,
+    };
+
+    fn user(matricula: &str) -> User {
+        User {
+            id: 0,
+            pis: None,
+            nome: "Change Test".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: true,
+            allow_bio: true,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    async fn changes(feed: &ChangeFeed) -> Vec<(ChangeEntity, String, ChangeOperation)> {
+        feed.poll()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.entity, event.entity_key, event.operation))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_captured_in_commit_order() {
+        let db = Database::in_memory().await.unwrap();
+        let feed = ChangeFeed::new(db.pool().clone(), "test").unwrap();
+        // Seed data from the migrations is already in the outbox
+        if let Some(last) = feed.poll().await.unwrap().last() {
+            feed.acknowledge(last.id).await.unwrap();
+        }
+
+        let users = SqliteUserRepository::new(db.pool().clone());
+        let cards = SqliteCardRepository::new(db.pool().clone());
+        let templates = SqliteBiometricTemplateRepository::new(db.pool().clone());
+
+        let user_id = users.create(&user("CDC001")).await.unwrap();
+        let mut card = Card {
+            id: 0,
+            numero_cartao: "4040404040".to_string(),
+            matricula: "CDC001".to_string(),
+            user_id,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        card.id = cards.create(&card).await.unwrap();
+        templates.save("CDC001", 2, &[1u8; 600]).await.unwrap();
+        card.ativo = false;
+        cards.update(&card).await.unwrap();
+        cards.delete(card.id).await.unwrap();
+
+        let key = |s: &str| s.to_string();
+        assert_eq!(
+            changes(&feed).await,
+            vec![
+                (ChangeEntity::User, key("CDC001"), ChangeOperation::Insert),
+                (
+                    ChangeEntity::Card,
+                    key("4040404040"),
+                    ChangeOperation::Insert
+                ),
+                (
+                    ChangeEntity::BiometricTemplate,
+                    key("CDC001:2"),
+                    ChangeOperation::Insert
+                ),
+                (
+                    ChangeEntity::Card,
+                    key("4040404040"),
+                    ChangeOperation::Update
+                ),
+                (
+                    ChangeEntity::Card,
+                    key("4040404040"),
+                    ChangeOperation::Delete
+                ),
+            ]
+        );
+
+        let events = feed.poll().await.unwrap();
+        assert_eq!(events[1].matricula.as_deref(), Some("CDC001"));
+    }
+
+    #[tokio::test]
+    async fn test_cursors_are_independent_and_survive_reopening() {
+        let db = Database::in_memory().await.unwrap();
+        let users = SqliteUserRepository::new(db.pool().clone());
+        users.create(&user("CDC002")).await.unwrap();
+        users.create(&user("CDC003")).await.unwrap();
+
+        let cache = ChangeFeed::new(db.pool().clone(), "cache")
+            .unwrap()
+            .with_batch_size(1);
+        let sync = ChangeFeed::new(db.pool().clone(), "sync").unwrap();
+        let all = sync.poll().await.unwrap();
+        let first = cache.poll().await.unwrap();
+        assert_eq!(first.len(), 1);
+
+        // Not acknowledged: read again
+        assert_eq!(cache.poll().await.unwrap(), first);
+        cache.acknowledge(first[0].id).await.unwrap();
+        cache.acknowledge(0).await.unwrap();
+        assert_eq!(cache.cursor().await.unwrap(), first[0].id);
+
+        // Reopened after a restart, the cache resumes; sync is untouched
+        let reopened = ChangeFeed::new(db.pool().clone(), "cache").unwrap();
+        assert_eq!(reopened.poll().await.unwrap(), all[1..].to_vec());
+        assert_eq!(sync.poll().await.unwrap(), all);
+
+        assert!(matches!(
+            ChangeFeed::new(db.pool().clone(), ""),
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_relay_and_purge_respect_slowest_consumer() {
+        let db = Database::in_memory().await.unwrap();
+        let users = SqliteUserRepository::new(db.pool().clone());
+        users.create(&user("CDC004")).await.unwrap();
+
+        let sync = ChangeFeed::new(db.pool().clone(), "sync").unwrap();
+        sync.acknowledge(0).await.unwrap();
+
+        let relay = ChangeFeed::new(db.pool().clone(), "relay").unwrap();
+        let total = relay.poll().await.unwrap().len();
+        let (tx, mut rx) = mpsc::channel(total);
+        let task = tokio::spawn({
+            let relay = relay.clone();
+            async move { relay.relay(tx, Duration::from_millis(10)).await }
+        });
+
+        let mut received = Vec::new();
+        for _ in 0..total {
+            received.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(received.last().unwrap().entity_key, "CDC004");
+        drop(rx);
+        task.await.unwrap().unwrap();
+        assert_eq!(relay.cursor().await.unwrap(), received.last().unwrap().id);
+
+        // "sync" has acknowledged nothing, so nothing can go
+        let later = Utc::now() + chrono::Duration::minutes(1);
+        assert_eq!(ChangeFeed::purge(db.pool(), later).await.unwrap(), 0);
+
+        sync.acknowledge(received.last().unwrap().id).await.unwrap();
+        let earlier = Utc::now() - chrono::Duration::minutes(1);
+        assert_eq!(ChangeFeed::purge(db.pool(), earlier).await.unwrap(), 0);
+        assert_eq!(
+            ChangeFeed::purge(db.pool(), later).await.unwrap(),
+            total as u64
+        );
+        assert!(sync.poll().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,677 @@
+//! Cluster mode: several validation server processes sharing one SQLite
+//! database on the same host.
+//!
+//! Running two server processes against the same database file lets one
+//! take over the devices of the other when it stops or crashes, e.g.
+//! during an upgrade. [`ClusterNode`] is one server's handle on the shared
+//! state, and covers the two things that go wrong when servers decide side
+//! by side.
+//!
+//! # Scope
+//!
+//! This is process failover, not high availability. The nodes share one
+//! SQLite file, which must sit on a local disk of a single host: SQLite
+//! locking is not reliable over network filesystems, so nodes on other
+//! hosts must not open the file. The host and its disk remain single
+//! points of failure. Servers on separate hosts would need a shared
+//! database server such as PostgreSQL, which this crate does not
+//! implement.
+//!
+//! # Device Affinity
+//!
+//! A device is leased to one node at a time in `device_leases`.
+//! `ClusterNode` implements [`DeviceAffinity`], so installing it with
+//! `TcpServer::set_device_affinity()` makes the server claim each device
+//! on connect and release it on disconnect. Leases last
+//! [`DEFAULT_LEASE_TTL`] and are renewed by
+//! [`spawn_lease_renewal()`](ClusterNode::spawn_lease_renewal); a device
+//! knocking on the other node is refused until its lease is released or
+//! lapses.
+//!
+//! # Decision Idempotency
+//!
+//! A device that gets no answer resends the same request, possibly to the
+//! other node after failover. With [`OfflineValidator::with_cluster()`],
+//! requests carrying a correlation ID are claimed in `access_decisions`
+//! before validation and the answer is stored after it:
+//!
+//! - A request already decided gets the stored answer, without a second
+//!   validation or access log entry
+//! - A request another node is deciding is denied with
+//!   [`DisplayMessages::DECISION_IN_PROGRESS`]; the device retries
+//! - A claim left behind by a node that died mid-decision is taken over
+//!   after [`DECISION_CLAIM_TIMEOUT`]. If that node already wrote the access
+//!   log, the answer is rebuilt from the log instead of validating again,
+//!   so the passage is never logged twice
+//!
+//! Requests without a correlation ID are validated as usual.
+//!
+//! # Failover
+//!
+//! When a node stops, its devices lose their connection and reconnect on
+//! their own schedule. A node that shut down cleanly released its leases,
+//! so the devices are accepted by the survivor at once. A node that
+//! crashed keeps its leases until they expire, so its devices are refused
+//! for up to the lease TTL; keep the TTL short against the device retry
+//! interval. Decisions recorded by the dead node stay valid for retries.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use turnkey_network::{TcpServer, TcpServerConfig};
+//! use turnkey_storage::cluster::ClusterNode;
+//! use turnkey_storage::{Database, DatabaseConfig, OfflineValidator};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("/srv/turnkey/shared.db")).await?;
+//! let node = ClusterNode::new(db.pool().clone(), "server-a")?;
+//! let _renewal = node.spawn_lease_renewal(Duration::from_secs(5));
+//!
+//! let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+//! server.set_device_affinity(Some(Arc::new(node.clone())));
+//!
+//! let (device_id, _request) = server.accept().await?;
+//! let validator = OfflineValidator::new(db.pool().clone())
+//!     .with_device_id(device_id)
+//!     .with_cluster(node);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`OfflineValidator::with_cluster()`]: crate::OfflineValidator::with_cluster
+//! [`DisplayMessages::DECISION_IN_PROGRESS`]: crate::messages::DisplayMessages::DECISION_IN_PROGRESS
+
+use crate::error::{StorageError, StorageResult};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use turnkey_core::{CorrelationId, DeviceId};
+use turnkey_network::DeviceAffinity;
+use turnkey_protocol::commands::access::{AccessDecision, AccessResponse};
+
+/// Lease length unless set otherwise (30 seconds)
+pub const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// Age after which an undecided claim is taken over (10 seconds)
+///
+/// Far longer than a validation takes, so only claims of a node that died
+/// mid-decision are affected.
+pub const DECISION_CLAIM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum length of a node ID
+pub const MAX_NODE_ID_LENGTH: usize = 64;
+
+/// Device held by a node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DeviceLease {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// Node holding the device
+    pub node_id: String,
+
+    /// When the node first claimed the device
+    pub acquired_at: DateTime<Utc>,
+
+    /// When the lease lapses unless renewed
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Outcome of claiming a decision
+#[derive(Debug, Clone)]
+pub(crate) enum DecisionClaim {
+    /// This node decides; `takeover` if a stale claim was replaced
+    Claimed { takeover: bool },
+
+    /// Already decided, by this or another node
+    Decided(AccessResponse),
+
+    /// Another node is deciding right now
+    InFlight,
+}
+
+/// One server's handle on the state shared by the cluster
+///
+/// Clones share the same node ID.
+#[derive(Debug, Clone)]
+pub struct ClusterNode {
+    pool: SqlitePool,
+    node_id: String,
+    lease_ttl: Duration,
+}
+
+impl ClusterNode {
+    /// Join the cluster as `node_id`
+    ///
+    /// The ID must be unique among running nodes and stable across
+    /// restarts, so a restarted node recognizes its own leases.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the ID is empty or longer than
+    /// [`MAX_NODE_ID_LENGTH`].
+    pub fn new(pool: SqlitePool, node_id: impl Into<String>) -> StorageResult<Self> {
+        let node_id = node_id.into();
+        if node_id.is_empty() || node_id.len() > MAX_NODE_ID_LENGTH {
+            return Err(StorageError::Validation(format!(
+                "Node ID must be 1 to {} characters, got {}",
+                MAX_NODE_ID_LENGTH,
+                node_id.len()
+            )));
+        }
+
+        Ok(Self {
+            pool,
+            node_id,
+            lease_ttl: DEFAULT_LEASE_TTL,
+        })
+    }
+
+    /// Set how long a lease lasts without renewal
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.lease_ttl = ttl;
+        self
+    }
+
+    /// ID of this node
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Lease length of this node
+    pub fn lease_ttl(&self) -> Duration {
+        self.lease_ttl
+    }
+
+    /// Lease a device to this node
+    ///
+    /// Succeeds if the device is free, already held by this node (which
+    /// renews the lease) or its lease has lapsed. Returns `false` if
+    /// another node holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails.
+    pub async fn acquire_device(&self, device_id: DeviceId) -> StorageResult<bool> {
+        let device = i64::from(device_id.as_u8());
+        let now = Utc::now();
+        let expires_at = self.expiry(now);
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO device_leases (device_id, node_id, acquired_at, expires_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                acquired_at = CASE
+                    WHEN device_leases.node_id = excluded.node_id THEN device_leases.acquired_at
+                    ELSE excluded.acquired_at
+                END,
+                node_id = excluded.node_id,
+                expires_at = excluded.expires_at
+            WHERE device_leases.node_id = excluded.node_id
+               OR device_leases.expires_at <= excluded.acquired_at
+            "#,
+            device,
+            self.node_id,
+            now,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Give up a device held by this node
+    ///
+    /// Returns `false` if this node did not hold it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails.
+    pub async fn release_device(&self, device_id: DeviceId) -> StorageResult<bool> {
+        let device = i64::from(device_id.as_u8());
+        let result = sqlx::query!(
+            "DELETE FROM device_leases WHERE device_id = ? AND node_id = ?",
+            device,
+            self.node_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Extend every lease held by this node
+    ///
+    /// Returns the number of leases renewed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails.
+    pub async fn renew_leases(&self) -> StorageResult<u64> {
+        let expires_at = self.expiry(Utc::now());
+        let result = sqlx::query!(
+            "UPDATE device_leases SET expires_at = ? WHERE node_id = ?",
+            expires_at,
+            self.node_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Renew this node's leases every `interval` in a background task
+    ///
+    /// The interval should be well below the lease TTL. Failed renewals are
+    /// logged and retried on the next tick. Abort the returned handle to
+    /// stop renewing, e.g. before a clean shutdown.
+    pub fn spawn_lease_renewal(&self, interval: Duration) -> JoinHandle<()> {
+        let node = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = node.renew_leases().await {
+                    warn!(node_id = %node.node_id, error = %e, "Failed to renew device leases");
+                }
+            }
+        })
+    }
+
+    /// Current lease of a device, if it is held and not lapsed
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails.
+    pub async fn device_owner(&self, device_id: DeviceId) -> StorageResult<Option<DeviceLease>> {
+        let device = i64::from(device_id.as_u8());
+        let now = Utc::now();
+        let lease = sqlx::query_as!(
+            DeviceLease,
+            r#"
+            SELECT device_id, node_id, acquired_at AS "acquired_at: _", expires_at AS "expires_at: _"
+            FROM device_leases
+            WHERE device_id = ? AND expires_at > ?
+            "#,
+            device,
+            now
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(lease)
+    }
+
+    /// Live leases of the whole cluster, by device ID
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails.
+    pub async fn leases(&self) -> StorageResult<Vec<DeviceLease>> {
+        let now = Utc::now();
+        let leases = sqlx::query_as!(
+            DeviceLease,
+            r#"
+            SELECT device_id, node_id, acquired_at AS "acquired_at: _", expires_at AS "expires_at: _"
+            FROM device_leases
+            WHERE expires_at > ?
+            ORDER BY device_id
+            "#,
+            now
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(leases)
+    }
+
+    /// Delete recorded decisions claimed before `before`
+    ///
+    /// Devices retry within seconds, so decisions older than a few minutes
+    /// are no longer needed. Returns the number of decisions removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the database operation fails.
+    pub async fn purge_decisions(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        let result = sqlx::query!("DELETE FROM access_decisions WHERE claimed_at < ?", before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Claim the decision of a passage for this node
+    pub(crate) async fn claim_decision(
+        &self,
+        device_id: DeviceId,
+        correlation_id: CorrelationId,
+    ) -> StorageResult<DecisionClaim> {
+        let device = i64::from(device_id.as_u8());
+        let correlation = correlation_id.to_string();
+        let now = Utc::now();
+
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO access_decisions (device_id, correlation_id, node_id, claimed_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id, correlation_id) DO NOTHING
+            "#,
+            device,
+            correlation,
+            self.node_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if claimed == 1 {
+            return Ok(DecisionClaim::Claimed { takeover: false });
+        }
+
+        let row = sqlx::query_as!(
+            DecisionRow,
+            r#"
+            SELECT decision, timeout_seconds, display_message
+            FROM access_decisions
+            WHERE device_id = ? AND correlation_id = ?
+            "#,
+            device,
+            correlation
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(response) = row.and_then(DecisionRow::into_response) {
+            return Ok(DecisionClaim::Decided(response));
+        }
+
+        // Undecided: take over only a claim that has been pending too long
+        let stale_before =
+            now - chrono::Duration::from_std(DECISION_CLAIM_TIMEOUT).unwrap_or_default();
+        let taken = sqlx::query!(
+            r#"
+            UPDATE access_decisions SET node_id = ?, claimed_at = ?
+            WHERE device_id = ? AND correlation_id = ?
+              AND decision IS NULL AND claimed_at < ?
+            "#,
+            self.node_id,
+            now,
+            device,
+            correlation,
+            stale_before
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if taken == 1 {
+            warn!(
+                node_id = %self.node_id,
+                device_id = %device_id,
+                correlation_id = %correlation_id,
+                "Took over a stale decision claim"
+            );
+            Ok(DecisionClaim::Claimed { takeover: true })
+        } else {
+            Ok(DecisionClaim::InFlight)
+        }
+    }
+
+    /// Store the answer of a claimed decision
+    pub(crate) async fn complete_decision(
+        &self,
+        device_id: DeviceId,
+        correlation_id: CorrelationId,
+        response: &AccessResponse,
+    ) -> StorageResult<()> {
+        let decision = decision_name(response.decision());
+        let timeout_seconds = i64::from(response.timeout_seconds());
+        let display_message = response.display_message();
+        let decided_at = Utc::now();
+        let device = i64::from(device_id.as_u8());
+        let correlation = correlation_id.to_string();
+        sqlx::query!(
+            r#"
+            UPDATE access_decisions
+            SET decision = ?, timeout_seconds = ?, display_message = ?, decided_at = ?
+            WHERE device_id = ? AND correlation_id = ? AND node_id = ?
+            "#,
+            decision,
+            timeout_seconds,
+            display_message,
+            decided_at,
+            device,
+            correlation,
+            self.node_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drop a claim whose validation failed, so a retry can decide
+    pub(crate) async fn abandon_decision(
+        &self,
+        device_id: DeviceId,
+        correlation_id: CorrelationId,
+    ) -> StorageResult<()> {
+        let device = i64::from(device_id.as_u8());
+        let correlation = correlation_id.to_string();
+        sqlx::query!(
+            r#"
+            DELETE FROM access_decisions
+            WHERE device_id = ? AND correlation_id = ? AND node_id = ? AND decision IS NULL
+            "#,
+            device,
+            correlation,
+            self.node_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn expiry(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + chrono::Duration::from_std(self.lease_ttl).unwrap_or(chrono::Duration::MAX)
+    }
+}
+
+impl DeviceAffinity for ClusterNode {
+    fn claim(&self, device_id: DeviceId) -> BoxFuture<'_, bool> {
+        Box::pin(async move {
+            match self.acquire_device(device_id).await {
+                Ok(acquired) => acquired,
+                Err(e) => {
+                    // Unknown owner: refusing is safer than serving it twice
+                    warn!(node_id = %self.node_id, device_id = %device_id, error = %e, "Failed to lease device");
+                    false
+                }
+            }
+        })
+    }
+
+    fn release(&self, device_id: DeviceId) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            match self.release_device(device_id).await {
+                Ok(released) => {
+                    debug!(node_id = %self.node_id, device_id = %device_id, released, "Device lease released")
+                }
+                Err(e) => {
+                    warn!(node_id = %self.node_id, device_id = %device_id, error = %e, "Failed to release device lease")
+                }
+            }
+        })
+    }
+}
+
+/// Stored answer of a decision, NULL while undecided
+struct DecisionRow {
+    decision: Option<String>,
+    timeout_seconds: Option<i64>,
+    display_message: Option<String>,
+}
+
+impl DecisionRow {
+    fn into_response(self) -> Option<AccessResponse> {
+        let decision = match self.decision?.as_str() {
+            "grant_both" => AccessDecision::GrantBoth,
+            "grant_entry" => AccessDecision::GrantEntry,
+            "grant_exit" => AccessDecision::GrantExit,
+            "display_only" => AccessDecision::DisplayOnly,
+            _ => AccessDecision::Deny,
+        };
+        Some(AccessResponse::new(
+            decision,
+            u8::try_from(self.timeout_seconds?).ok()?,
+            self.display_message.unwrap_or_default(),
+        ))
+    }
+}
+
+fn decision_name(decision: AccessDecision) -> &'static str {
+    match decision {
+        AccessDecision::GrantBoth => "grant_both",
+        AccessDecision::GrantEntry => "grant_entry",
+        AccessDecision::GrantExit => "grant_exit",
+        AccessDecision::Deny => "deny",
+        AccessDecision::DisplayOnly => "display_only",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    fn device(id: u8) -> DeviceId {
+        DeviceId::new(id).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_device_lease_is_exclusive() {
+        let db = Database::in_memory().await.unwrap();
+        let a = ClusterNode::new(db.pool().clone(), "a").unwrap();
+        let b = ClusterNode::new(db.pool().clone(), "b").unwrap();
+
+        assert!(a.acquire_device(device(15)).await.unwrap());
+        assert!(!b.acquire_device(device(15)).await.unwrap());
+        assert!(b.acquire_device(device(16)).await.unwrap());
+
+        // Reconnecting to the owner renews its lease
+        assert!(a.acquire_device(device(15)).await.unwrap());
+        assert_eq!(a.renew_leases().await.unwrap(), 1);
+        assert_eq!(
+            b.device_owner(device(15)).await.unwrap().unwrap().node_id,
+            "a"
+        );
+
+        assert!(!b.release_device(device(15)).await.unwrap());
+        assert!(a.release_device(device(15)).await.unwrap());
+        assert!(b.acquire_device(device(15)).await.unwrap());
+        assert_eq!(a.leases().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_lease_moves_to_other_node() {
+        let db = Database::in_memory().await.unwrap();
+        let a = ClusterNode::new(db.pool().clone(), "a")
+            .unwrap()
+            .with_lease_ttl(Duration::from_millis(50));
+        let b = ClusterNode::new(db.pool().clone(), "b").unwrap();
+
+        assert!(a.acquire_device(device(15)).await.unwrap());
+        assert!(!b.acquire_device(device(15)).await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(a.device_owner(device(15)).await.unwrap().is_none());
+        assert!(b.acquire_device(device(15)).await.unwrap());
+        assert!(!a.acquire_device(device(15)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_decision_claimed_once() {
+        let db = Database::in_memory().await.unwrap();
+        let a = ClusterNode::new(db.pool().clone(), "a").unwrap();
+        let b = ClusterNode::new(db.pool().clone(), "b").unwrap();
+        let correlation_id = CorrelationId::new();
+
+        assert!(matches!(
+            a.claim_decision(device(15), correlation_id).await.unwrap(),
+            DecisionClaim::Claimed { takeover: false }
+        ));
+        assert!(matches!(
+            b.claim_decision(device(15), correlation_id).await.unwrap(),
+            DecisionClaim::InFlight
+        ));
+
+        let response = AccessResponse::grant_entry("Acesso liberado".to_string());
+        a.complete_decision(device(15), correlation_id, &response)
+            .await
+            .unwrap();
+        match b.claim_decision(device(15), correlation_id).await.unwrap() {
+            DecisionClaim::Decided(stored) => assert_eq!(stored, response),
+            other => panic!("expected stored decision, got {:?}", other),
+        }
+
+        // Same correlation ID on another device is another passage
+        assert!(matches!(
+            b.claim_decision(device(16), correlation_id).await.unwrap(),
+            DecisionClaim::Claimed { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_display_only_decision_recorded() {
+        let db = Database::in_memory().await.unwrap();
+        let a = ClusterNode::new(db.pool().clone(), "a").unwrap();
+        let correlation_id = CorrelationId::new();
+
+        a.claim_decision(device(15), correlation_id).await.unwrap();
+        let response = AccessResponse::display_only("Dirija-se a recepcao".to_string());
+        a.complete_decision(device(15), correlation_id, &response)
+            .await
+            .unwrap();
+        match a.claim_decision(device(15), correlation_id).await.unwrap() {
+            DecisionClaim::Decided(stored) => assert_eq!(stored, response),
+            other => panic!("expected stored decision, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_claim_taken_over() {
+        let db = Database::in_memory().await.unwrap();
+        let a = ClusterNode::new(db.pool().clone(), "a").unwrap();
+        let b = ClusterNode::new(db.pool().clone(), "b").unwrap();
+        let correlation_id = CorrelationId::new();
+
+        a.claim_decision(device(15), correlation_id).await.unwrap();
+        let stale = Utc::now() - chrono::Duration::minutes(1);
+        sqlx::query!("UPDATE access_decisions SET claimed_at = ?", stale)
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            b.claim_decision(device(15), correlation_id).await.unwrap(),
+            DecisionClaim::Claimed { takeover: true }
+        ));
+        // The node that lost the claim can no longer store its answer
+        let late = AccessResponse::deny("late".to_string());
+        a.complete_decision(device(15), correlation_id, &late)
+            .await
+            .unwrap();
+        assert!(matches!(
+            a.claim_decision(device(15), correlation_id).await.unwrap(),
+            DecisionClaim::InFlight
+        ));
+    }
+}
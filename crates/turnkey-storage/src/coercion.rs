@@ -0,0 +1,549 @@
+//! Alarms on repeated denials of the same card.
+//!
+//! A card denied five times in a minute at the same turnstile is rarely a
+//! confused employee: it is someone trying a card that is not theirs, or
+//! someone being forced to try theirs. [`CoercionDetector`] counts denials
+//! per card (and per device, unless configured across devices) in a
+//! sliding window and raises a [`CoercionAlarm`] when the count reaches the
+//! threshold. Alarms are published on an [`EventBus`], serialized as JSON
+//! like the [`anomaly`](crate::anomaly) alerts, and convert to an
+//! [`AlarmReport`] for devices that report them to a server.
+//!
+//! # Lockout
+//!
+//! With [`lockout`](CoercionConfig::lockout) set, the alarm also locks the
+//! card for that long. An [`OfflineValidator`](crate::OfflineValidator)
+//! sharing the detector (see
+//! [`with_coercion_detector`](crate::OfflineValidator::with_coercion_detector))
+//! denies a locked card with `CARD_LOCKED` before looking it up, and the
+//! lockout ends on its own once the time has passed. An operator can end
+//! it early with [`release()`](CoercionDetector::release). Denials while a
+//! card is locked are not counted again, so a lockout does not extend
+//! itself.
+//!
+//! Windows and lockouts are kept in memory: a restart forgets them, and
+//! validators only share them when they share the detector.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use turnkey_storage::coercion::{CoercionConfig, CoercionDetector};
+//! use turnkey_storage::events::{EventBus, SubscriptionOptions};
+//! use turnkey_storage::{Database, OfflineValidator};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::in_memory().await?;
+//! let alarms = EventBus::new(16);
+//! let mut security_desk = alarms.subscribe(SubscriptionOptions::new("desk")).await;
+//!
+//! let config = CoercionConfig::default().lockout(Some(Duration::from_secs(300)));
+//! let detector = Arc::new(CoercionDetector::new(config).with_alarm_bus(alarms));
+//! let validator =
+//!     OfflineValidator::new(db.pool().clone()).with_coercion_detector(detector.clone());
+//!
+//! // ... validate requests; the fifth denial of a card in a minute alarms
+//! # drop(validator);
+//! # drop(detector);
+//! while let Some(alarm) = security_desk.recv().await {
+//!     println!("{}", alarm);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::events::{EventBus, Subscription};
+use crate::models::{AccessLog, ReaderType};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+use turnkey_core::HenryTimestamp;
+use turnkey_protocol::commands::alarm::{AlarmKind, AlarmReport};
+
+/// Default denials of one card that raise an alarm
+pub const DEFAULT_MAX_DENIALS: u32 = 5;
+
+/// Default window the denials are counted over
+pub const DEFAULT_DENIAL_WINDOW: Duration = Duration::from_secs(60);
+
+/// Card windows kept before quiet ones are dropped
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Settings of a [`CoercionDetector`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoercionConfig {
+    /// Denials within the window that raise an alarm (at least 1)
+    pub max_denials: u32,
+
+    /// Sliding window the denials are counted over (at least 1s)
+    pub window: Duration,
+
+    /// Count denials of a card on all devices together instead of per device
+    pub across_devices: bool,
+
+    /// Lock the card for this long when it alarms (None = alarm only)
+    pub lockout: Option<Duration>,
+
+    /// Quiet time per card after an alarm
+    pub cooldown: Duration,
+}
+
+impl CoercionConfig {
+    /// Alarm on `max_denials` denials within `window`, with a cooldown of
+    /// one window and no lockout
+    pub fn new(max_denials: u32, window: Duration) -> Self {
+        let window = window.max(Duration::from_secs(1));
+        Self {
+            max_denials: max_denials.max(1),
+            window,
+            across_devices: false,
+            lockout: None,
+            cooldown: window,
+        }
+    }
+
+    /// Count denials of a card on all devices together
+    pub fn across_devices(mut self, across_devices: bool) -> Self {
+        self.across_devices = across_devices;
+        self
+    }
+
+    /// Set how long an alarming card is locked
+    pub fn lockout(mut self, lockout: Option<Duration>) -> Self {
+        self.lockout = lockout;
+        self
+    }
+
+    /// Set the quiet time per card after an alarm
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+}
+
+impl Default for CoercionConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DENIALS, DEFAULT_DENIAL_WINDOW)
+    }
+}
+
+/// Card denied too often in a short time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoercionAlarm {
+    /// Card that was denied
+    pub card_number: String,
+
+    /// Device the denials happened on (None when counted across devices)
+    pub device_id: Option<i64>,
+
+    /// Denials in the window ending at `detected_at`
+    pub denials: u32,
+
+    /// Length of the window in seconds
+    pub window_secs: u64,
+
+    /// Time of the denial that raised the alarm
+    pub detected_at: DateTime<Utc>,
+
+    /// End of the lockout started by the alarm, if locking is enabled
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl CoercionAlarm {
+    /// Protocol report of the alarm, for devices that send it to a server
+    pub fn to_report(&self) -> AlarmReport {
+        let henry = |at: DateTime<Utc>| HenryTimestamp::from_datetime(at.with_timezone(&Local));
+        AlarmReport {
+            kind: AlarmKind::RepeatedDenials,
+            card_number: self.card_number.clone(),
+            count: self.denials,
+            window_secs: u32::try_from(self.window_secs).unwrap_or(u32::MAX),
+            timestamp: henry(self.detected_at),
+            locked_until: self.locked_until.map(henry),
+        }
+    }
+}
+
+impl fmt::Display for CoercionAlarm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "card {} denied {} times in {}s",
+            self.card_number, self.denials, self.window_secs
+        )?;
+        if let Some(device_id) = self.device_id {
+            write!(f, " on device {}", device_id)?;
+        }
+        write!(f, " at {}", self.detected_at)?;
+        if let Some(until) = self.locked_until {
+            write!(f, ", locked until {}", until)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recent denials of one card (on one device, unless counted across devices)
+#[derive(Debug, Default)]
+struct DenialWindow {
+    recent: VecDeque<DateTime<Utc>>,
+    last_alarm: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct DetectorState {
+    windows: HashMap<(String, Option<i64>), DenialWindow>,
+    lockouts: HashMap<String, DateTime<Utc>>,
+}
+
+/// Sliding-window detector of repeated denials, with optional card lockout
+///
+/// Shared between validators and the code watching the alarms; every
+/// method takes `&self`.
+#[derive(Debug)]
+pub struct CoercionDetector {
+    config: CoercionConfig,
+    state: Mutex<DetectorState>,
+    alarms: Option<EventBus<CoercionAlarm>>,
+}
+
+impl CoercionDetector {
+    /// Create a detector without history
+    pub fn new(config: CoercionConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(DetectorState::default()),
+            alarms: None,
+        }
+    }
+
+    /// Publish alarms on `bus` (see [`publish()`](Self::publish))
+    pub fn with_alarm_bus(mut self, bus: EventBus<CoercionAlarm>) -> Self {
+        self.alarms = Some(bus);
+        self
+    }
+
+    /// Settings in use
+    pub fn config(&self) -> &CoercionConfig {
+        &self.config
+    }
+
+    /// Account for an access log
+    ///
+    /// Only card denials count; grants and keypad entries, which have a
+    /// lockout of their own, are ignored. Returns the alarm the denial
+    /// raised, if any.
+    pub fn observe(&self, log: &AccessLog) -> Option<CoercionAlarm> {
+        if log.granted
+            || log.card_number.is_empty()
+            || log.get_reader_type() == Some(ReaderType::Keypad)
+        {
+            return None;
+        }
+        self.observe_denial(&log.card_number, log.device_id, log.timestamp)
+    }
+
+    /// Account for a denial of `card_number` on a device at `at`
+    ///
+    /// Returns the alarm when the denial brings the window to the
+    /// threshold, outside the cooldown; with a lockout configured the card
+    /// is locked from then on.
+    pub fn observe_denial(
+        &self,
+        card_number: &str,
+        device_id: Option<i64>,
+        at: DateTime<Utc>,
+    ) -> Option<CoercionAlarm> {
+        let mut state = self.state.lock().expect("coercion detector lock poisoned");
+        if Self::active_lockout(&mut state, card_number, at).is_some() {
+            return None;
+        }
+
+        if state.windows.len() >= PRUNE_THRESHOLD {
+            self.prune(&mut state, at);
+        }
+
+        let device_id = if self.config.across_devices {
+            None
+        } else {
+            device_id
+        };
+        let window = state
+            .windows
+            .entry((card_number.to_string(), device_id))
+            .or_default();
+
+        // Late denials count toward the window they belong to
+        let position = window.recent.partition_point(|t| *t <= at);
+        window.recent.insert(position, at);
+        let latest = *window.recent.back().expect("a denial was just added");
+        let length =
+            chrono::Duration::from_std(self.config.window).unwrap_or(chrono::Duration::MAX);
+        while window.recent.front().is_some_and(|t| *t <= latest - length) {
+            window.recent.pop_front();
+        }
+        let denials = window.recent.len() as u32;
+
+        let cooling_down = window.last_alarm.is_some_and(|last| {
+            at.signed_duration_since(last).to_std().unwrap_or_default() < self.config.cooldown
+        });
+        if cooling_down || denials < self.config.max_denials {
+            return None;
+        }
+        window.last_alarm = Some(at);
+
+        let locked_until = self
+            .config
+            .lockout
+            .and_then(|lockout| chrono::Duration::from_std(lockout).ok())
+            .map(|lockout| at + lockout);
+        if let Some(until) = locked_until {
+            state.lockouts.insert(card_number.to_string(), until);
+        }
+        Some(CoercionAlarm {
+            card_number: card_number.to_string(),
+            device_id,
+            denials,
+            window_secs: self.config.window.as_secs(),
+            detected_at: at,
+            locked_until,
+        })
+    }
+
+    /// End of the lockout of `card_number`, if it is locked at `now`
+    ///
+    /// Expired lockouts are released on the way.
+    pub fn locked_until(&self, card_number: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut state = self.state.lock().expect("coercion detector lock poisoned");
+        Self::active_lockout(&mut state, card_number, now)
+    }
+
+    /// Cards locked at `now` with the end of their lockout
+    pub fn locked_cards(&self, now: DateTime<Utc>) -> Vec<(String, DateTime<Utc>)> {
+        let mut state = self.state.lock().expect("coercion detector lock poisoned");
+        state.lockouts.retain(|_, until| now < *until);
+        let mut cards: Vec<_> = state
+            .lockouts
+            .iter()
+            .map(|(card, until)| (card.clone(), *until))
+            .collect();
+        cards.sort();
+        cards
+    }
+
+    /// End the lockout of `card_number` early and forget its denials
+    ///
+    /// Returns whether the card was locked.
+    pub fn release(&self, card_number: &str) -> bool {
+        let mut state = self.state.lock().expect("coercion detector lock poisoned");
+        state.windows.retain(|(card, _), _| card != card_number);
+        state.lockouts.remove(card_number).is_some()
+    }
+
+    /// Publish an alarm on the bus given to [`with_alarm_bus()`](Self::with_alarm_bus)
+    pub async fn publish(&self, alarm: CoercionAlarm) {
+        tracing::warn!(
+            card_number = %alarm.card_number,
+            device_id = ?alarm.device_id,
+            denials = alarm.denials,
+            "{}",
+            alarm
+        );
+        if let Some(alarms) = &self.alarms {
+            alarms.publish(alarm).await;
+        }
+    }
+
+    /// Follow logs from a bus and publish the alarms they raise
+    ///
+    /// For denials that do not go through a validator sharing the
+    /// detector, such as logs pulled from devices. Returns when the log
+    /// bus closes.
+    pub async fn run(&self, mut logs: Subscription<AccessLog>) {
+        while let Some(log) = logs.recv().await {
+            if let Some(alarm) = self.observe(&log) {
+                self.publish(alarm).await;
+            }
+        }
+    }
+
+    /// Drop windows with no denial or alarm that still matters at `now`
+    fn prune(&self, state: &mut DetectorState, now: DateTime<Utc>) {
+        let keep = chrono::Duration::from_std(self.config.window.max(self.config.cooldown))
+            .unwrap_or(chrono::Duration::MAX);
+        state.windows.retain(|_, window| {
+            let last = window.recent.back().copied().max(window.last_alarm);
+            last.is_some_and(|last| last > now - keep)
+        });
+    }
+
+    fn active_lockout(
+        state: &mut DetectorState,
+        card_number: &str,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let until = *state.lockouts.get(card_number)?;
+        if now < until {
+            return Some(until);
+        }
+        state.lockouts.remove(card_number);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SubscriptionOptions;
+    use crate::models::Direction;
+    use chrono::TimeZone;
+
+    const CARD: &str = "00000000000022823433";
+
+    fn at(second: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 5, 10, 12, 0, 0).unwrap() + chrono::Duration::seconds(second)
+    }
+
+    fn denial(device_id: i64, second: i64) -> AccessLog {
+        AccessLog::new(
+            None,
+            None,
+            CARD.to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            false,
+            None,
+            at(second),
+        )
+        .with_device_clock(device_id, at(second))
+    }
+
+    #[test]
+    fn test_alarm_on_fifth_denial_in_window() {
+        let detector = CoercionDetector::new(CoercionConfig::default());
+
+        // Four denials a minute apart never fill the window
+        for i in 0..4 {
+            assert!(detector.observe(&denial(15, i * 61)).is_none());
+        }
+
+        let alarms: Vec<_> = (0..10)
+            .filter_map(|i| detector.observe(&denial(15, 300 + i * 5)))
+            .collect();
+        assert_eq!(alarms.len(), 1, "cooldown holds back the rest");
+        let alarm = &alarms[0];
+        assert_eq!(alarm.denials, 5);
+        assert_eq!(alarm.device_id, Some(15));
+        assert_eq!(alarm.detected_at, at(320));
+        assert_eq!(alarm.locked_until, None);
+
+        // Grants do not count
+        let mut grant = denial(16, 400);
+        grant.granted = true;
+        assert!(detector.observe(&grant).is_none());
+    }
+
+    #[test]
+    fn test_devices_counted_apart_unless_configured() {
+        let per_device = CoercionDetector::new(CoercionConfig::new(3, DEFAULT_DENIAL_WINDOW));
+        let across = CoercionDetector::new(
+            CoercionConfig::new(3, DEFAULT_DENIAL_WINDOW).across_devices(true),
+        );
+
+        let mut per_device_alarms = 0;
+        let mut across_alarms = Vec::new();
+        for (i, device_id) in [15, 16, 17].into_iter().enumerate() {
+            let log = denial(device_id, i as i64);
+            per_device_alarms += usize::from(per_device.observe(&log).is_some());
+            across_alarms.extend(across.observe(&log));
+        }
+
+        assert_eq!(per_device_alarms, 0);
+        assert_eq!(across_alarms.len(), 1);
+        assert_eq!(across_alarms[0].device_id, None);
+    }
+
+    #[test]
+    fn test_lockout_released_after_time_or_by_operator() {
+        let config =
+            CoercionConfig::new(2, DEFAULT_DENIAL_WINDOW).lockout(Some(Duration::from_secs(300)));
+        let detector = CoercionDetector::new(config);
+
+        assert!(detector.observe(&denial(15, 0)).is_none());
+        let alarm = detector.observe(&denial(15, 1)).unwrap();
+        assert_eq!(alarm.locked_until, Some(at(301)));
+        assert_eq!(detector.locked_until(CARD, at(100)), Some(at(301)));
+        assert_eq!(
+            detector.locked_cards(at(100)),
+            vec![(CARD.to_string(), at(301))]
+        );
+
+        // Denials while locked neither count nor extend the lockout
+        for i in 0..10 {
+            assert!(detector.observe(&denial(15, 2 + i)).is_none());
+        }
+        assert_eq!(detector.locked_until(CARD, at(300)), Some(at(301)));
+        assert_eq!(detector.locked_until(CARD, at(301)), None);
+        assert!(detector.locked_cards(at(301)).is_empty());
+
+        // Locked again, then released early
+        detector.observe(&denial(15, 400));
+        assert!(detector.observe(&denial(15, 401)).is_some());
+        assert!(detector.release(CARD));
+        assert_eq!(detector.locked_until(CARD, at(402)), None);
+        assert!(!detector.release(CARD));
+    }
+
+    #[test]
+    fn test_alarm_converts_to_protocol_report() {
+        let config =
+            CoercionConfig::new(1, DEFAULT_DENIAL_WINDOW).lockout(Some(Duration::from_secs(60)));
+        let alarm = CoercionDetector::new(config)
+            .observe(&denial(15, 0))
+            .unwrap();
+
+        let report = alarm.to_report();
+        assert_eq!(report.kind, AlarmKind::RepeatedDenials);
+        assert_eq!(report.card_number, CARD);
+        assert_eq!(report.count, 1);
+        assert_eq!(report.window_secs, 60);
+        assert_eq!(
+            report.locked_until.unwrap().inner().with_timezone(&Utc),
+            at(60)
+        );
+
+        let json = serde_json::to_value(&alarm).unwrap();
+        assert_eq!(json["card_number"], CARD);
+        assert_eq!(json["device_id"], 15);
+    }
+
+    #[tokio::test]
+    async fn test_run_publishes_alarms_from_log_bus() {
+        let logs: EventBus<AccessLog> = EventBus::new(0);
+        let alarms = EventBus::new(4);
+        let mut desk = alarms.subscribe(SubscriptionOptions::new("desk")).await;
+        let detector = std::sync::Arc::new(
+            CoercionDetector::new(CoercionConfig::new(2, DEFAULT_DENIAL_WINDOW))
+                .with_alarm_bus(alarms),
+        );
+
+        let subscription = logs.subscribe(SubscriptionOptions::new("coercion")).await;
+        let task = tokio::spawn({
+            let detector = detector.clone();
+            async move { detector.run(subscription).await }
+        });
+        for i in 0..2 {
+            logs.publish(denial(15, i)).await;
+        }
+        drop(logs);
+        task.await.unwrap();
+
+        let alarm = desk.recv().await.unwrap();
+        assert_eq!(alarm.denials, 2);
+        assert_eq!(alarm.card_number, CARD);
+    }
+}
@@ -0,0 +1,729 @@
+//! Bulk import of users from `colaborador.txt` exports.
+//!
+//! Henry equipment and the configuration tool exchange users as pipe
+//! separated lines:
+//!
+//! ```text
+//! PIS|NOME|MATRICULA|CPF|VALIDADE_INICIO|VALIDADE_FIM|ATIVO|ALLOW_CARD|ALLOW_BIO|ALLOW_KEYPAD|CODIGO
+//! ```
+//!
+//! Large sites export hundreds of thousands of lines, so [`ColaboradorImporter`]
+//! runs the import as a pipeline connected by bounded channels:
+//!
+//! 1. a reader thread splits the file into chunks of
+//!    [`batch_size`](ColaboradorImportOptions::batch_size) lines,
+//! 2. [`parallelism`](ColaboradorImportOptions::parallelism) parser tasks
+//!    turn chunks into validated [`User`] rows,
+//! 3. one writer puts chunks back in file order and upserts each one in a
+//!    single transaction.
+//!
+//! SQLite accepts one writer at a time, so parsing is what runs in
+//! parallel; the writer only issues multi-row statements. The bounded
+//! channels keep memory flat whatever the file size.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::colaborador::{ColaboradorImportOptions, ColaboradorImporter};
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! let options = ColaboradorImportOptions::default()
+//!     .batch_size(2000)
+//!     .parallelism(4);
+//! let importer = ColaboradorImporter::new(db.pool().clone(), options);
+//!
+//! let file = BufReader::new(File::open("colaborador.txt")?);
+//! let report = importer
+//!     .import(file, |progress| println!("{} lines", progress.lines_read))
+//!     .await?;
+//! println!(
+//!     "{} new, {} updated, {} rejected at {:.0} rows/s",
+//!     report.inserted,
+//!     report.updated,
+//!     report.rejected,
+//!     report.rows_per_second()
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Row Handling
+//!
+//! - **Comments and blank lines** (`#` prefix) are skipped and not counted.
+//! - **Existing users** (same matricula) are updated; `empresa`, which the
+//!   format does not carry, is left as is.
+//! - **Repeated matriculas** within the file: the first line wins and the
+//!   later ones are rejected, as the configuration tool does.
+//! - **Rejected lines** never abort the import. They are reported in line
+//!   order, whatever order the parsers finished in.
+//! - **Keypad codes** are written as they appear in the file. Like codes
+//!   from databases created before hashing, they are replaced by a hash
+//!   the first time they are used (see [`pin`](crate::pin)); hashing half a
+//!   million codes up front would take hours.
+
+use crate::error::{StorageError, StorageResult};
+use crate::ingest::{MAX_REPORTED_ROW_ERRORS, RowError};
+use crate::models::User;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::BufRead;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, mpsc};
+
+/// Default number of lines per chunk and per transaction
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Default number of parser tasks
+pub const DEFAULT_IMPORT_PARALLELISM: usize = 4;
+
+/// Number of fields in a `colaborador.txt` line
+const FIELD_COUNT: usize = 11;
+
+/// Rows per INSERT statement, under SQLite's bound parameter limit
+const ROWS_PER_STATEMENT: usize = 500;
+
+/// Pipeline settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColaboradorImportOptions {
+    /// Lines per chunk, which is also the rows per transaction
+    /// (default [`DEFAULT_IMPORT_BATCH_SIZE`])
+    pub batch_size: usize,
+
+    /// Parser tasks running at once (default [`DEFAULT_IMPORT_PARALLELISM`])
+    pub parallelism: usize,
+}
+
+impl Default for ColaboradorImportOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_IMPORT_BATCH_SIZE,
+            parallelism: DEFAULT_IMPORT_PARALLELISM,
+        }
+    }
+}
+
+impl ColaboradorImportOptions {
+    /// Set the number of lines per chunk (minimum 1)
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set the number of parser tasks (minimum 1)
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+}
+
+/// Progress of a running import, reported after every committed chunk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColaboradorImportProgress {
+    /// Data lines processed so far (comments and blank lines excluded)
+    pub lines_read: u64,
+
+    /// Users created
+    pub inserted: u64,
+
+    /// Existing users updated
+    pub updated: u64,
+
+    /// Lines rejected
+    pub rejected: u64,
+}
+
+/// Summary of a finished import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColaboradorImportReport {
+    /// Data lines read (comments and blank lines excluded)
+    pub lines_read: u64,
+
+    /// Users created
+    pub inserted: u64,
+
+    /// Existing users updated
+    pub updated: u64,
+
+    /// Lines rejected
+    pub rejected: u64,
+
+    /// First [`MAX_REPORTED_ROW_ERRORS`] rejected lines, in file order
+    pub errors: Vec<RowError>,
+
+    /// Wall-clock time of the whole import
+    pub elapsed: Duration,
+}
+
+impl ColaboradorImportReport {
+    /// Check if every line was imported
+    pub fn is_clean(&self) -> bool {
+        self.rejected == 0
+    }
+
+    /// Data lines processed per second
+    pub fn rows_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.lines_read as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    fn progress(&self) -> ColaboradorImportProgress {
+        ColaboradorImportProgress {
+            lines_read: self.lines_read,
+            inserted: self.inserted,
+            updated: self.updated,
+            rejected: self.rejected,
+        }
+    }
+
+    fn reject(&mut self, error: RowError) {
+        self.rejected += 1;
+        if self.errors.len() < MAX_REPORTED_ROW_ERRORS {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// Lines handed from the reader to the parsers
+struct RawChunk {
+    seq: u64,
+    lines: Vec<(u64, Result<String, String>)>,
+}
+
+/// Parsed lines handed from a parser to the writer
+struct ParsedChunk {
+    seq: u64,
+    lines_read: u64,
+    users: Vec<(u64, User)>,
+    errors: Vec<RowError>,
+}
+
+/// Imports users from `colaborador.txt`
+#[derive(Debug, Clone)]
+pub struct ColaboradorImporter {
+    pool: SqlitePool,
+    options: ColaboradorImportOptions,
+}
+
+impl ColaboradorImporter {
+    /// Create an importer for the given database pool
+    pub fn new(pool: SqlitePool, options: ColaboradorImportOptions) -> Self {
+        Self { pool, options }
+    }
+
+    /// Import every line of `reader`
+    ///
+    /// Calls `on_progress` after every committed chunk. Chunks already
+    /// committed stay in the database if a later one fails; running the
+    /// import again updates them in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Internal` if the file cannot be read and `Database` if a
+    /// chunk cannot be written.
+    pub async fn import<R: BufRead + Send + 'static>(
+        &self,
+        reader: R,
+        mut on_progress: impl FnMut(ColaboradorImportProgress),
+    ) -> StorageResult<ColaboradorImportReport> {
+        let started = Instant::now();
+        let capacity = self.options.parallelism * 2;
+        let (raw_tx, raw_rx) = mpsc::channel::<RawChunk>(capacity);
+        let (parsed_tx, mut parsed_rx) = mpsc::channel::<ParsedChunk>(capacity);
+
+        let batch_size = self.options.batch_size;
+        let reader_task =
+            tokio::task::spawn_blocking(move || read_chunks(reader, batch_size, raw_tx));
+
+        let raw_rx = Arc::new(Mutex::new(raw_rx));
+        for _ in 0..self.options.parallelism {
+            let raw_rx = raw_rx.clone();
+            let parsed_tx = parsed_tx.clone();
+            tokio::spawn(async move {
+                loop {
+                    let Some(chunk) = raw_rx.lock().await.recv().await else {
+                        return;
+                    };
+                    if parsed_tx.send(parse_chunk(chunk)).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(parsed_tx);
+
+        let mut report = ColaboradorImportReport::default();
+        let mut waiting = BTreeMap::new();
+        let mut next_seq = 0;
+        let mut seen = HashMap::new();
+
+        while let Some(chunk) = parsed_rx.recv().await {
+            waiting.insert(chunk.seq, chunk);
+            while let Some(chunk) = waiting.remove(&next_seq) {
+                next_seq += 1;
+                self.write_chunk(chunk, &mut seen, &mut report).await?;
+                on_progress(report.progress());
+            }
+        }
+
+        reader_task
+            .await
+            .map_err(|e| StorageError::Internal(format!("Import reader failed: {}", e)))?
+            .map_err(|e| StorageError::Internal(format!("Failed to read file: {}", e)))?;
+
+        report.elapsed = started.elapsed();
+        tracing::info!(
+            lines = report.lines_read,
+            inserted = report.inserted,
+            updated = report.updated,
+            rejected = report.rejected,
+            rows_per_second = report.rows_per_second() as u64,
+            "colaborador import finished"
+        );
+        Ok(report)
+    }
+
+    /// Drop repeated matriculas, then upsert the rest in one transaction
+    async fn write_chunk(
+        &self,
+        chunk: ParsedChunk,
+        seen: &mut HashMap<String, u64>,
+        report: &mut ColaboradorImportReport,
+    ) -> StorageResult<()> {
+        let mut errors = chunk.errors;
+        let mut users = Vec::with_capacity(chunk.users.len());
+        for (line, user) in chunk.users {
+            match seen.get(&user.matricula) {
+                Some(first) => errors.push(RowError {
+                    line,
+                    message: format!(
+                        "MATRICULA '{}' duplicated (first on line {})",
+                        user.matricula, first
+                    ),
+                }),
+                None => {
+                    seen.insert(user.matricula.clone(), line);
+                    users.push(user);
+                }
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let existing = upsert_users(&mut tx, &users).await?;
+        tx.commit().await?;
+
+        let updated = existing;
+        report.lines_read += chunk.lines_read;
+        report.updated += updated;
+        report.inserted += users.len() as u64 - updated;
+        errors.sort_by_key(|error| error.line);
+        for error in errors {
+            report.reject(error);
+        }
+        Ok(())
+    }
+}
+
+/// Insert or update users by matricula
+///
+/// Returns how many of them already existed.
+pub(crate) async fn upsert_users(
+    tx: &mut Transaction<'_, Sqlite>,
+    users: &[User],
+) -> StorageResult<u64> {
+    let mut existing = HashSet::new();
+    for part in users.chunks(ROWS_PER_STATEMENT) {
+        let mut query =
+            QueryBuilder::<Sqlite>::new("SELECT matricula FROM users WHERE matricula IN (");
+        let mut separated = query.separated(", ");
+        for user in part {
+            separated.push_bind(&user.matricula);
+        }
+        separated.push_unseparated(")");
+        let found: Vec<String> = query.build_query_scalar().fetch_all(&mut **tx).await?;
+        existing.extend(found);
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO users (pis, nome, matricula, cpf, validade_inicio, validade_fim, \
+             ativo, allow_card, allow_bio, allow_keypad, codigo) ",
+        );
+        query.push_values(part, |mut row, user| {
+            row.push_bind(&user.pis)
+                .push_bind(&user.nome)
+                .push_bind(&user.matricula)
+                .push_bind(&user.cpf)
+                .push_bind(user.validade_inicio)
+                .push_bind(user.validade_fim)
+                .push_bind(user.ativo)
+                .push_bind(user.allow_card)
+                .push_bind(user.allow_bio)
+                .push_bind(user.allow_keypad)
+                .push_bind(&user.codigo);
+        });
+        query.push(
+            " ON CONFLICT (matricula) DO UPDATE SET \
+             pis = excluded.pis, nome = excluded.nome, cpf = excluded.cpf, \
+             validade_inicio = excluded.validade_inicio, validade_fim = excluded.validade_fim, \
+             ativo = excluded.ativo, allow_card = excluded.allow_card, \
+             allow_bio = excluded.allow_bio, allow_keypad = excluded.allow_keypad, \
+             codigo = excluded.codigo, \
+             codigo_digest = CASE WHEN codigo IS excluded.codigo THEN codigo_digest END",
+        );
+        query.build().execute(&mut **tx).await?;
+    }
+    Ok(existing.len() as u64)
+}
+
+/// Parse one `colaborador.txt` line into a user (ID and timestamps unset)
+///
+/// # Errors
+///
+/// Returns a description of the first rule the line breaks.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::colaborador::parse_colaborador_line;
+///
+/// let user = parse_colaborador_line(
+///     "12345678901|João da Silva|1001|12345678901|01/01/2025|31/12/2025|1|1|1|1|1234",
+/// )
+/// .unwrap();
+/// assert_eq!(user.matricula, "1001");
+/// assert!(user.allow_keypad);
+///
+/// // Keypad enabled without a code
+/// assert!(parse_colaborador_line("|Ana Costa|1004||||1|0|0|1|").is_err());
+/// ```
+pub fn parse_colaborador_line(line: &str) -> Result<User, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+    if fields.len() != FIELD_COUNT {
+        return Err(format!(
+            "Expected {} fields, found {}",
+            FIELD_COUNT,
+            fields.len()
+        ));
+    }
+
+    let pis = document(fields[0], "PIS")?;
+    let nome = fields[1];
+    if nome.is_empty() {
+        return Err("Missing required field NOME".to_string());
+    }
+    if nome.chars().count() > 100 {
+        return Err("NOME longer than 100 characters".to_string());
+    }
+    let matricula = fields[2];
+    if !(3..=20).contains(&matricula.chars().count()) {
+        return Err(format!("MATRICULA '{}' must be 3-20 characters", matricula));
+    }
+    let cpf = document(fields[3], "CPF")?;
+
+    let validade_inicio = date(fields[4], "VALIDADE_INICIO", (0, 0, 0))?;
+    let validade_fim = date(fields[5], "VALIDADE_FIM", (23, 59, 59))?;
+    if let (Some(inicio), Some(fim)) = (validade_inicio, validade_fim)
+        && fim < inicio
+    {
+        return Err("VALIDADE_FIM is before VALIDADE_INICIO".to_string());
+    }
+
+    let ativo = flag(fields[6], "ATIVO")?;
+    let allow_card = flag(fields[7], "ALLOW_CARD")?;
+    let allow_bio = flag(fields[8], "ALLOW_BIO")?;
+    let allow_keypad = flag(fields[9], "ALLOW_KEYPAD")?;
+    if !(allow_card || allow_bio || allow_keypad) {
+        return Err("At least one access method must be enabled".to_string());
+    }
+
+    let codigo = Some(fields[10]).filter(|code| !code.is_empty());
+    if codigo.is_some_and(|code| code.chars().count() > 20) {
+        return Err("CODIGO longer than 20 characters".to_string());
+    }
+    if allow_keypad && codigo.is_none() {
+        return Err("ALLOW_KEYPAD requires CODIGO".to_string());
+    }
+
+    let now = Utc::now();
+    Ok(User {
+        id: 0,
+        pis,
+        nome: nome.to_string(),
+        matricula: matricula.to_string(),
+        cpf,
+        validade_inicio,
+        validade_fim,
+        ativo,
+        allow_card,
+        allow_bio,
+        allow_keypad,
+        codigo: codigo.map(str::to_string),
+        empresa: None,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+/// Optional 11-digit document number (PIS, CPF)
+fn document(value: &str, name: &str) -> Result<Option<String>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if value.len() != 11 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Invalid {} number '{}'", name, value));
+    }
+    Ok(Some(value.to_string()))
+}
+
+/// Optional `dd/mm/yyyy` date at the given time of day (UTC)
+pub(crate) fn date(
+    value: &str,
+    name: &str,
+    (h, m, s): (u32, u32, u32),
+) -> Result<Option<DateTime<Utc>>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(value, "%d/%m/%Y")
+        .ok()
+        .and_then(|date| date.and_hms_opt(h, m, s))
+        .map(|datetime| Some(datetime.and_utc()))
+        .ok_or_else(|| format!("Invalid {} date '{}' (expected dd/mm/yyyy)", name, value))
+}
+
+/// Required `0`/`1` flag
+pub(crate) fn flag(value: &str, name: &str) -> Result<bool, String> {
+    match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(format!(
+            "Invalid {} value '{}' (expected 0 or 1)",
+            name, value
+        )),
+    }
+}
+
+/// Reader stage: split data lines into numbered chunks
+///
+/// Returns when the file ends or every parser is gone.
+fn read_chunks<R: BufRead>(
+    mut reader: R,
+    batch_size: usize,
+    raw_tx: mpsc::Sender<RawChunk>,
+) -> std::io::Result<()> {
+    let mut seq = 0;
+    let mut lines = Vec::with_capacity(batch_size);
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let line = String::from_utf8(std::mem::take(&mut buffer))
+            .map_err(|_| "Line is not valid UTF-8".to_string());
+        if let Ok(text) = &line {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+        }
+        lines.push((line_number, line));
+
+        if lines.len() >= batch_size {
+            let chunk = RawChunk {
+                seq,
+                lines: std::mem::replace(&mut lines, Vec::with_capacity(batch_size)),
+            };
+            if raw_tx.blocking_send(chunk).is_err() {
+                return Ok(());
+            }
+            seq += 1;
+        }
+    }
+
+    if !lines.is_empty() {
+        let _ = raw_tx.blocking_send(RawChunk { seq, lines });
+    }
+    Ok(())
+}
+
+/// Parser stage: validate every line of a chunk
+fn parse_chunk(chunk: RawChunk) -> ParsedChunk {
+    let mut parsed = ParsedChunk {
+        seq: chunk.seq,
+        lines_read: chunk.lines.len() as u64,
+        users: Vec::with_capacity(chunk.lines.len()),
+        errors: Vec::new(),
+    };
+
+    for (line, text) in chunk.lines {
+        match text.and_then(|text| parse_colaborador_line(text.trim_end_matches(['\r', '\n']))) {
+            Ok(user) => parsed.users.push((line, user)),
+            Err(message) => parsed.errors.push(RowError { line, message }),
+        }
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::repositories::{SqliteUserRepository, UserRepository};
+    use std::fmt::Write;
+    use std::io::Cursor;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    fn line(matricula: &str, nome: &str) -> String {
+        format!("|{}|{}||01/01/2025||1|1|0|0|\n", nome, matricula)
+    }
+
+    #[test]
+    fn test_parse_line_rules() {
+        let user = parse_colaborador_line(
+            "98765432101|Maria Santos|1002|98765432101|01/01/2025||1|1|0|1|5678",
+        )
+        .unwrap();
+        assert_eq!(user.pis.as_deref(), Some("98765432101"));
+        assert_eq!(user.codigo.as_deref(), Some("5678"));
+        assert!(user.validade_fim.is_none());
+        assert_eq!(
+            user.validade_inicio.unwrap().to_rfc3339(),
+            "2025-01-01T00:00:00+00:00"
+        );
+
+        for (line, expected) in [
+            ("123|Ana|1004||||1|1|0|0|", "Invalid PIS"),
+            ("|Ana|1004|||||1|0|0|", "Invalid ATIVO"),
+            ("||1004||||1|1|0|0|", "Missing required field NOME"),
+            ("|Ana|10||||1|1|0|0|", "must be 3-20"),
+            ("|Ana|1004||||1|0|0|0|", "At least one access method"),
+            (
+                "|Ana|1004||02/01/2025|01/01/2025|1|1|0|0|",
+                "before VALIDADE_INICIO",
+            ),
+            ("|Ana|1004||31/02/2025||1|1|0|0|", "Invalid VALIDADE_INICIO"),
+            ("|Ana|1004||||1|1|0|0", "Expected 11 fields"),
+        ] {
+            let error = parse_colaborador_line(line).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", line, error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_inserts_updates_and_reports_in_order() {
+        let db = setup_test_db().await;
+        let repo = SqliteUserRepository::new(db.pool().clone());
+        let mut existing = parse_colaborador_line(&line("IMP0001", "Old Name")).unwrap();
+        existing.empresa = Some("Acme".to_string());
+        repo.create(&existing).await.unwrap();
+
+        let mut file = String::from("# Arquivo de colaboradores\n\n");
+        for i in 0..250 {
+            file.push_str(&line(&format!("IMP{:04}", i), &format!("Pessoa {}", i)));
+        }
+        file.push_str("|Broken|X1||||1|1|0|0|\n");
+        file.push_str(&line("IMP0003", "Repeated"));
+        file.push_str("|No Code|IMP9999||||1|0|0|1|\r\n");
+
+        let options = ColaboradorImportOptions::default()
+            .batch_size(16)
+            .parallelism(3);
+        let mut progress = Vec::new();
+        let report = ColaboradorImporter::new(db.pool().clone(), options)
+            .import(Cursor::new(file), |p| progress.push(p))
+            .await
+            .unwrap();
+
+        assert_eq!(report.lines_read, 253);
+        assert_eq!(report.inserted, 249);
+        assert_eq!(report.updated, 1);
+        assert_eq!(report.rejected, 3);
+        let lines: Vec<u64> = report.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![253, 254, 255]);
+        assert!(report.errors[1].message.contains("first on line 6"));
+        assert_eq!(progress.len(), 16);
+        assert_eq!(progress.last().unwrap().lines_read, 253);
+
+        let updated = repo.find_by_matricula("IMP0001").await.unwrap().unwrap();
+        assert_eq!(updated.nome, "Pessoa 1");
+        assert_eq!(updated.empresa.as_deref(), Some("Acme"));
+        let first = repo.find_by_matricula("IMP0003").await.unwrap().unwrap();
+        assert_eq!(first.nome, "Pessoa 3");
+    }
+
+    #[tokio::test]
+    async fn test_import_is_repeatable() {
+        let db = setup_test_db().await;
+        let file = (0..40).fold(String::new(), |mut file, i| {
+            let _ = write!(file, "{}", line(&format!("RPT{:03}", i), "Pessoa"));
+            file
+        });
+        let importer = ColaboradorImporter::new(
+            db.pool().clone(),
+            ColaboradorImportOptions::default().batch_size(7),
+        );
+
+        let first = importer
+            .import(Cursor::new(file.clone()), |_| {})
+            .await
+            .unwrap();
+        let second = importer.import(Cursor::new(file), |_| {}).await.unwrap();
+
+        assert_eq!((first.inserted, first.updated), (40, 0));
+        assert_eq!((second.inserted, second.updated), (0, 40));
+        assert!(second.is_clean());
+    }
+
+    /// Throughput check on a 500k-line file:
+    /// `cargo test -p turnkey-storage --release -- --ignored colaborador --nocapture`
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn test_import_throughput() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("colaborador.db");
+        let db = Database::new(crate::DatabaseConfig::new(path.to_str().unwrap()))
+            .await
+            .unwrap();
+
+        let mut file = String::with_capacity(500_000 * 64);
+        for i in 0..500_000 {
+            let _ = writeln!(
+                file,
+                "12345678901|Pessoa {}|M{:07}|12345678901|01/01/2025|31/12/2026|1|1|0|1|{:04}",
+                i,
+                i,
+                i % 10_000
+            );
+        }
+
+        let report = ColaboradorImporter::new(db.pool().clone(), Default::default())
+            .import(Cursor::new(file), |_| {})
+            .await
+            .unwrap();
+        println!(
+            "{} rows in {:?} ({:.0} rows/s)",
+            report.inserted,
+            report.elapsed,
+            report.rows_per_second()
+        );
+        assert_eq!(report.inserted, 500_000);
+    }
+}
@@ -0,0 +1,131 @@
+//! Sending profile-based configuration to devices
+//!
+//! Device settings are managed centrally as
+//! [`ConfigProfile`](crate::models::ConfigProfile)s with per-device
+//! overrides (see [`ConfigProfileRepository`]). The functions here resolve
+//! a device's effective settings and send them as one `SendConfig` (EC)
+//! message per parameter, so a device always receives its full
+//! configuration and never depends on what it was sent before.
+//!
+//! # Wire Format
+//!
+//! | Parameter     | Setting                                          |
+//! |---------------|--------------------------------------------------|
+//! | `TIMEOUT_ON`  | Online timeout in milliseconds                   |
+//! | `TIPO_VALIDA` | Validation mode (`O`, `F`, `A` or `S`)           |
+//! | `MSG_ENTRADA` | Entry message                                    |
+//! | `MSG_SAIDA`   | Exit message                                     |
+//! | `TEMPO_MSG`   | Message display time in seconds                  |
+//! | `VOLUME`      | Buzzer volume, 0-9                               |
+//! | `SINAL`       | Feedback, see [`FeedbackPattern::code()`](crate::models::FeedbackPattern::code) |
+//!
+//! Each is sent as `<ID>+REON+EC]00]<PARAMETER>]<VALUE>]`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::config_sync;
+//! use turnkey_storage::models::{ConfigProfile, ConfigSettings};
+//! use turnkey_storage::repositories::{ConfigProfileRepository, SqliteConfigProfileRepository};
+//! use turnkey_network::{TcpServer, TcpServerConfig};
+//!
+//! # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let profiles = SqliteConfigProfileRepository::new(pool);
+//! let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+//!
+//! let mut profile = profiles.find_by_name("Portaria").await?.expect("profile exists");
+//! profile.settings.volume = Some(9);
+//! profiles.update(&profile).await?;
+//!
+//! // Every device using the profile, directly or through a child profile
+//! let report = config_sync::sync_profile(&profiles, &mut server, profile.id).await?;
+//! println!("{} synced, {} offline", report.sent.len(), report.not_connected.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::ResolvedConfig;
+use crate::repositories::ConfigProfileRepository;
+use tracing::{debug, warn};
+use turnkey_core::DeviceId;
+use turnkey_network::{BroadcastReport, TcpServer};
+
+/// Send the effective configuration of a connected device
+///
+/// Returns the configuration that was sent.
+///
+/// # Errors
+///
+/// Returns the repository error if the configuration cannot be resolved,
+/// and `NetworkError` if the device is not connected or a send fails;
+/// parameters already sent stay applied.
+pub async fn sync_device<R: ConfigProfileRepository>(
+    profiles: &R,
+    server: &mut TcpServer,
+    device_id: DeviceId,
+) -> StorageResult<ResolvedConfig> {
+    let config = profiles.resolve(i64::from(device_id.as_u8())).await?;
+    for message in config.to_messages(device_id)? {
+        server
+            .send(device_id, message)
+            .await
+            .map_err(|e| StorageError::NetworkError(e.to_string()))?;
+    }
+
+    debug!(
+        device_id = %device_id,
+        profiles = ?config.profiles,
+        "Device configuration sent"
+    );
+    Ok(config)
+}
+
+/// Send the effective configuration to every device using a profile
+///
+/// Covers devices assigned to the profile or to a profile inheriting from
+/// it. Devices that are not connected are listed in the report and should
+/// be synced when they connect; a failed send does not stop the others.
+///
+/// # Errors
+///
+/// Returns the repository error if the devices or a configuration cannot
+/// be read.
+pub async fn sync_profile<R: ConfigProfileRepository>(
+    profiles: &R,
+    server: &mut TcpServer,
+    profile_id: i64,
+) -> StorageResult<BroadcastReport> {
+    let mut report = BroadcastReport::default();
+
+    for device_id in profiles.find_devices_using(profile_id).await? {
+        let device_id = u8::try_from(device_id)
+            .ok()
+            .and_then(|id| DeviceId::new(id).ok())
+            .ok_or_else(|| {
+                StorageError::Validation(format!("Invalid device ID {} in profile", device_id))
+            })?;
+        if !server.is_connected(device_id) {
+            report.not_connected.push(device_id);
+            continue;
+        }
+
+        let config = profiles.resolve(i64::from(device_id.as_u8())).await?;
+        let mut failure = None;
+        for message in config.to_messages(device_id)? {
+            if let Err(e) = server.send(device_id, message).await {
+                failure = Some(e);
+                break;
+            }
+        }
+        match failure {
+            None => report.sent.push(device_id),
+            Some(e) => {
+                warn!(device_id = %device_id, error = %e, "Configuration sync failed");
+                report.failed.push((device_id, e));
+            }
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,156 @@
+//! Cached device names for logs, events and reports.
+//!
+//! Device labels change rarely but are looked up on every log line that
+//! mentions a device, so [`DeviceDirectory`] keeps them in memory and
+//! answers synchronously. Lookups never fail: a device that is unknown,
+//! unnamed or not yet loaded is shown by its numeric ID, which is also the
+//! only form that ever goes on the wire.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_core::DeviceId;
+//! use turnkey_storage::{Database, DatabaseConfig, DeviceDirectory};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! let directory = DeviceDirectory::new(db.pool().clone());
+//! directory.refresh().await?;
+//!
+//! let device = DeviceId::new(15)?;
+//! tracing::info!(device = %directory.label(device), "Turnstile online");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::StorageResult;
+use crate::repositories::{DeviceRepository, SqliteDeviceRepository};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use turnkey_core::{DeviceId, DeviceLabel};
+
+/// In-memory cache of [`DeviceLabel`]s backed by the `devices` table
+///
+/// The cache is filled by [`refresh()`](Self::refresh) and by
+/// [`load()`](Self::load) on a miss; call `refresh()` again (or
+/// [`invalidate()`](Self::invalidate)) after renaming devices.
+pub struct DeviceDirectory {
+    repo: SqliteDeviceRepository,
+    labels: RwLock<HashMap<DeviceId, DeviceLabel>>,
+}
+
+impl DeviceDirectory {
+    /// Create an empty directory
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            repo: SqliteDeviceRepository::new(pool),
+            labels: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Reload every label from the database
+    ///
+    /// Returns the number of devices loaded.
+    pub async fn refresh(&self) -> StorageResult<usize> {
+        let labels = self.repo.find_labels().await?;
+        let count = labels.len();
+
+        let mut cache = self.labels.write().unwrap_or_else(|e| e.into_inner());
+        *cache = labels.into_iter().map(|label| (label.id, label)).collect();
+        Ok(count)
+    }
+
+    /// Get the cached label of a device
+    ///
+    /// Falls back to an unnamed label, displayed as the numeric ID, when
+    /// the device is not cached.
+    pub fn label(&self, id: DeviceId) -> DeviceLabel {
+        self.labels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| DeviceLabel::unnamed(id))
+    }
+
+    /// Get the label of a device, querying the database on a cache miss
+    pub async fn load(&self, id: DeviceId) -> StorageResult<DeviceLabel> {
+        if let Some(label) = self
+            .labels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+        {
+            return Ok(label.clone());
+        }
+
+        let label = self.repo.find_label(i64::from(id.as_u8())).await?;
+        self.labels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, label.clone());
+        Ok(label)
+    }
+
+    /// Get every cached label, ordered by device ID
+    pub fn labels(&self) -> Vec<DeviceLabel> {
+        let mut labels: Vec<_> = self
+            .labels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        labels.sort_by_key(|label| label.id.as_u8());
+        labels
+    }
+
+    /// Drop the cached label of a device, so the next [`load()`](Self::load)
+    /// reads it again
+    pub fn invalidate(&self, id: DeviceId) {
+        self.labels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[tokio::test]
+    async fn test_directory_caches_and_falls_back() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+        repo.set_label(15, Some("Portaria Norte"), None)
+            .await
+            .unwrap();
+
+        let directory = DeviceDirectory::new(db.pool().clone());
+        let id = DeviceId::new(15).unwrap();
+        assert_eq!(directory.label(id).to_string(), "15");
+
+        assert_eq!(directory.refresh().await.unwrap(), 1);
+        assert_eq!(directory.label(id).to_string(), "Portaria Norte (15)");
+
+        // Renames are not seen until the entry is invalidated
+        repo.set_label(15, Some("Garagem"), None).await.unwrap();
+        assert_eq!(
+            directory.load(id).await.unwrap().to_string(),
+            "Portaria Norte (15)"
+        );
+        directory.invalidate(id);
+        assert_eq!(
+            directory.load(id).await.unwrap().to_string(),
+            "Garagem (15)"
+        );
+
+        let unknown = DeviceId::new(99).unwrap();
+        assert_eq!(directory.load(unknown).await.unwrap().to_string(), "99");
+        assert_eq!(directory.labels().len(), 2);
+    }
+}
@@ -0,0 +1,196 @@
+//! Card enrollment via reader
+//!
+//! Administrators register new cards by tapping them on a designated
+//! device. The server sends a `MODO_CADASTRO` configuration command carrying
+//! the matricula of the user the card is for; the device opens an
+//! [`EnrollmentSession`](crate::models::EnrollmentSession), and the
+//! [`OfflineValidator`](crate::OfflineValidator) captures the next card read
+//! there as a [`PendingCard`] bound to that user instead of validating it.
+//! The capture is reported back to the server with
+//! [`confirmation()`], and the registration is completed with
+//! [`PendingCardRepository::assign`](crate::repositories::PendingCardRepository::assign).
+//!
+//! # Wire Format
+//!
+//! | Direction        | Message                                         |
+//! |------------------|-------------------------------------------------|
+//! | Server → device  | `<ID>+REON+EC]00]MODO_CADASTRO]<MATRICULA>]`    |
+//! | Server → device  | `<ID>+REON+EC]00]MODO_CADASTRO]]` (cancel)      |
+//! | Device → server  | `<ID>+REON+RC]00]MODO_CADASTRO]<CARD_NUMBER>]`  |
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::enrollment::{EnrollmentCommand, DEFAULT_ENROLLMENT_TTL};
+//! use turnkey_storage::repositories::{EnrollmentSessionRepository, SqliteEnrollmentSessionRepository};
+//! use turnkey_protocol::Message;
+//!
+//! # async fn example(pool: sqlx::SqlitePool, message: Message) -> Result<(), Box<dyn std::error::Error>> {
+//! let sessions = SqliteEnrollmentSessionRepository::new(pool);
+//! let device_id = i64::from(message.device_id.as_u8());
+//!
+//! match EnrollmentCommand::from_message(&message) {
+//!     Some(EnrollmentCommand::Start { matricula }) => {
+//!         let expires_at = chrono::Utc::now() + DEFAULT_ENROLLMENT_TTL;
+//!         sessions.start(device_id, &matricula, expires_at).await?;
+//!     }
+//!     Some(EnrollmentCommand::Cancel) => {
+//!         sessions.cancel(device_id).await?;
+//!     }
+//!     None => {}
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::PendingCard;
+use turnkey_core::DeviceId;
+use turnkey_protocol::{CommandCode, FieldData, Message};
+
+/// Configuration parameter carrying enrollment commands in EC/RC messages
+pub const ENROLLMENT_PARAMETER: &str = "MODO_CADASTRO";
+
+/// How long a device waits for a card after entering enrollment mode
+pub const DEFAULT_ENROLLMENT_TTL: chrono::Duration = chrono::Duration::minutes(2);
+
+/// Enrollment command sent by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnrollmentCommand {
+    /// Capture the next card read for the user with this matricula
+    Start {
+        /// Matricula of the user the card is for
+        matricula: String,
+    },
+    /// Leave enrollment mode without capturing a card
+    Cancel,
+}
+
+impl EnrollmentCommand {
+    /// Parse an enrollment command from a `SendConfig` (EC) message
+    ///
+    /// The field after `MODO_CADASTRO` holds the matricula; an empty or
+    /// missing value cancels. Returns `None` for other messages.
+    pub fn from_message(message: &Message) -> Option<Self> {
+        if message.command != CommandCode::SendConfig {
+            return None;
+        }
+        let position = message
+            .fields
+            .iter()
+            .position(|field| field.as_str() == ENROLLMENT_PARAMETER)?;
+
+        let matricula = message
+            .fields
+            .get(position + 1)
+            .map(|field| field.as_str().trim())
+            .unwrap_or_default();
+
+        Some(if matricula.is_empty() {
+            Self::Cancel
+        } else {
+            Self::Start {
+                matricula: matricula.to_string(),
+            }
+        })
+    }
+
+    /// Build the EC message sending this command to a device
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if the matricula is not a valid field.
+    pub fn to_message(&self, device_id: DeviceId) -> StorageResult<Message> {
+        let value = match self {
+            Self::Start { matricula } => matricula.as_str(),
+            Self::Cancel => "",
+        };
+        config_message(device_id, CommandCode::SendConfig, value)
+    }
+}
+
+/// Notification telling the server a card was captured for enrollment
+///
+/// Uses the layout of a configuration answer (`RC`):
+/// `<ID>+REON+RC]00]MODO_CADASTRO]<CARD_NUMBER>]` with the normalized card
+/// number.
+///
+/// # Errors
+///
+/// Returns `ProtocolError` if the message cannot be built.
+pub fn confirmation(device_id: DeviceId, pending: &PendingCard) -> StorageResult<Message> {
+    config_message(device_id, CommandCode::ReceiveConfig, &pending.card_number)
+}
+
+fn config_message(
+    device_id: DeviceId,
+    command: CommandCode,
+    value: &str,
+) -> StorageResult<Message> {
+    let fields = ["00", ENROLLMENT_PARAMETER, value]
+        .into_iter()
+        .map(|field| FieldData::new(field.to_string()))
+        .collect::<turnkey_core::Result<Vec<_>>>()
+        .map_err(|e| StorageError::ProtocolError(format!("Enrollment message: {}", e)))?;
+
+    Message::new(device_id, command, fields)
+        .map_err(|e| StorageError::ProtocolError(format!("Enrollment message: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    #[test]
+    fn test_command_roundtrip() {
+        let start = EnrollmentCommand::Start {
+            matricula: "1001".to_string(),
+        };
+        let message = start.to_message(device()).unwrap();
+        assert_eq!(message.command, CommandCode::SendConfig);
+        assert_eq!(EnrollmentCommand::from_message(&message), Some(start));
+
+        let cancel = EnrollmentCommand::Cancel.to_message(device()).unwrap();
+        assert_eq!(
+            EnrollmentCommand::from_message(&cancel),
+            Some(EnrollmentCommand::Cancel)
+        );
+    }
+
+    #[test]
+    fn test_other_messages_ignored() {
+        let status = Message::new(device(), CommandCode::QueryStatus, vec![]).unwrap();
+        assert_eq!(EnrollmentCommand::from_message(&status), None);
+
+        let fields = ["00", "TIPO_VALIDA", "F"]
+            .into_iter()
+            .map(|value| FieldData::new(value.to_string()).unwrap())
+            .collect();
+        let mode = Message::new(device(), CommandCode::SendConfig, fields).unwrap();
+        assert_eq!(EnrollmentCommand::from_message(&mode), None);
+    }
+
+    #[test]
+    fn test_confirmation_carries_card_number() {
+        let pending = PendingCard {
+            id: 1,
+            raw_value: " ab12cd34".to_string(),
+            card_number: "AB12CD34".to_string(),
+            device_id: Some(15),
+            read_count: 1,
+            first_seen_at: Utc::now(),
+            last_seen_at: Utc::now(),
+            matricula: Some("1001".to_string()),
+        };
+
+        let message = confirmation(device(), &pending).unwrap();
+        assert_eq!(message.command, CommandCode::ReceiveConfig);
+        assert_eq!(message.fields[1].as_str(), ENROLLMENT_PARAMETER);
+        assert_eq!(message.fields[2].as_str(), "AB12CD34");
+    }
+}
@@ -50,6 +50,10 @@ pub enum StorageError {
     #[error("Validation failed after {0} retries: {1}")]
     ValidationFailed(usize, String),
 
+    /// Work abandoned because the requester went away
+    #[error("Operation cancelled")]
+    Cancelled,
+
     /// Generic internal error
     #[error("Internal error: {0}")]
     Internal(String),
@@ -0,0 +1,544 @@
+//! In-process event bus with bounded, per-subscriber buffers.
+//!
+//! Validators, monitors and device handlers publish events that several
+//! consumers want: the TUI, audit writers, sync jobs. A plain channel per
+//! consumer ties the publisher to the slowest of them. [`EventBus`] gives
+//! every [`Subscription`] its own bounded queue and an [`OverflowPolicy`]
+//! deciding what happens when that queue is full:
+//!
+//! - [`DropOldest`](OverflowPolicy::DropOldest) and
+//!   [`DropNewest`](OverflowPolicy::DropNewest) never wait, so a stalled
+//!   display loses events instead of stalling validation
+//! - [`Block`](OverflowPolicy::Block) makes `publish()` wait for room, for
+//!   consumers that must see every event and may slow the publisher down
+//!
+//! # Ordering
+//!
+//! Publishing is serialized: every subscription receives events in the
+//! order they were published, minus any its policy dropped. A `Block`
+//! subscription that stops reading therefore holds up every publisher,
+//! which is why it should be reserved for consumers that keep up.
+//!
+//! # Lag Metrics
+//!
+//! [`EventBus::stats()`] reports, per subscription, the events waiting in
+//! its queue (its lag), how many it received, how many were dropped for it
+//! and the deepest its queue has been.
+//!
+//! # Replay
+//!
+//! The bus keeps the last events published (see [`EventBus::new`]). A
+//! subscription created with [`SubscriptionOptions::replay`] starts with
+//! those events in its queue, so a TUI attached late still shows recent
+//! activity. Replayed events are not counted as drops.
+//!
+//! # Example
+//!
+//! ```
+//! use turnkey_storage::events::{EventBus, OverflowPolicy, SubscriptionOptions};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let bus = EventBus::new(16);
+//! let mut tui = bus
+//!     .subscribe(SubscriptionOptions::new("tui").capacity(2).replay(true))
+//!     .await;
+//!
+//! for n in 0..5 {
+//!     bus.publish(n).await;
+//! }
+//!
+//! // The TUI only kept the latest events and reports what it lost
+//! assert_eq!(tui.recv().await, Some(3));
+//! assert_eq!(tui.recv().await, Some(4));
+//! assert_eq!(tui.stats().dropped, 3);
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+
+/// Queue size of a subscription unless set otherwise
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 256;
+
+/// What a subscription does with an event when its queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued event to make room
+    #[default]
+    DropOldest,
+    /// Discard the event being published
+    DropNewest,
+    /// Wait until the subscriber makes room
+    Block,
+}
+
+/// Settings of a new subscription
+#[derive(Debug, Clone)]
+pub struct SubscriptionOptions {
+    name: String,
+    capacity: usize,
+    policy: OverflowPolicy,
+    replay: bool,
+}
+
+impl SubscriptionOptions {
+    /// Options for a subscription named in metrics
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            capacity: DEFAULT_SUBSCRIPTION_CAPACITY,
+            policy: OverflowPolicy::default(),
+            replay: false,
+        }
+    }
+
+    /// Set the queue size (at least 1)
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Set what happens when the queue is full
+    pub fn policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Start with the events in the bus replay buffer
+    ///
+    /// At most `capacity` of the most recent ones are queued.
+    pub fn replay(mut self, replay: bool) -> Self {
+        self.replay = replay;
+        self
+    }
+}
+
+/// Counters of one subscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionStats {
+    /// Name given at subscription
+    pub name: String,
+
+    /// Overflow policy of the subscription
+    pub policy: OverflowPolicy,
+
+    /// Queue size
+    pub capacity: usize,
+
+    /// Events waiting to be received (current lag)
+    pub queued: usize,
+
+    /// Largest number of events ever waiting
+    pub max_queued: usize,
+
+    /// Events received by the subscriber
+    pub received: u64,
+
+    /// Events discarded because the queue was full
+    pub dropped: u64,
+}
+
+/// Counters of the whole bus
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusStats {
+    /// Events published since the bus was created
+    pub published: u64,
+
+    /// Live subscriptions, in subscription order
+    pub subscriptions: Vec<SubscriptionStats>,
+}
+
+/// State shared between a subscription and the bus
+#[derive(Debug)]
+struct Slot<T> {
+    name: String,
+    policy: OverflowPolicy,
+    capacity: usize,
+    queue: std::sync::Mutex<VecDeque<T>>,
+    /// Signalled when an event is queued or the bus closes
+    ready: Notify,
+    /// Signalled when an event is taken or the subscription closes
+    space: Notify,
+    /// The subscription was dropped
+    unsubscribed: AtomicBool,
+    /// Every bus handle was dropped
+    bus_closed: AtomicBool,
+    max_queued: AtomicUsize,
+    received: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl<T> Slot<T> {
+    fn queue(&self) -> MutexGuard<'_, VecDeque<T>> {
+        // A queue is left consistent even if a holder panicked
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue an event, handing it back if the queue is full
+    fn try_push(&self, event: T) -> Result<(), T> {
+        let mut queue = self.queue();
+        if queue.len() >= self.capacity {
+            return Err(event);
+        }
+        queue.push_back(event);
+        self.max_queued.fetch_max(queue.len(), Ordering::Relaxed);
+        drop(queue);
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// Queue an event according to the overflow policy
+    async fn deliver(&self, event: T) {
+        let event = match self.try_push(event) {
+            Ok(()) => return,
+            Err(event) => event,
+        };
+
+        match self.policy {
+            OverflowPolicy::DropNewest => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            OverflowPolicy::DropOldest => {
+                let mut queue = self.queue();
+                if queue.len() >= self.capacity {
+                    queue.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                queue.push_back(event);
+                drop(queue);
+                self.ready.notify_one();
+            }
+            OverflowPolicy::Block => {
+                let mut event = event;
+                loop {
+                    // Receivers leave a permit when nobody waits yet, so a
+                    // wake-up between try_push and here is not lost
+                    self.space.notified().await;
+                    if self.unsubscribed.load(Ordering::Acquire) {
+                        return;
+                    }
+                    match self.try_push(event) {
+                        Ok(()) => return,
+                        Err(back) => event = back,
+                    }
+                }
+            }
+        }
+    }
+
+    fn stats(&self) -> SubscriptionStats {
+        SubscriptionStats {
+            name: self.name.clone(),
+            policy: self.policy,
+            capacity: self.capacity,
+            queued: self.queue().len(),
+            max_queued: self.max_queued.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BusState<T> {
+    slots: Vec<Arc<Slot<T>>>,
+    replay: VecDeque<T>,
+    published: u64,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    state: Mutex<BusState<T>>,
+    replay_capacity: usize,
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        for slot in &self.state.get_mut().slots {
+            slot.bus_closed.store(true, Ordering::Release);
+            slot.ready.notify_one();
+        }
+    }
+}
+
+/// Publisher side of the bus
+///
+/// Clones publish to the same subscriptions. Subscriptions see the end of
+/// the stream once every clone is dropped and their queue is drained.
+#[derive(Debug)]
+pub struct EventBus<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for EventBus<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: Clone> EventBus<T> {
+    /// Create a bus remembering the last `replay_capacity` events
+    ///
+    /// Zero disables replay.
+    pub fn new(replay_capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(BusState {
+                    slots: Vec::new(),
+                    replay: VecDeque::with_capacity(replay_capacity),
+                    published: 0,
+                }),
+                replay_capacity,
+            }),
+        }
+    }
+
+    /// Add a subscription
+    pub async fn subscribe(&self, options: SubscriptionOptions) -> Subscription<T> {
+        let mut state = self.inner.state.lock().await;
+
+        let queue: VecDeque<T> = if options.replay {
+            let skip = state.replay.len().saturating_sub(options.capacity);
+            state.replay.iter().skip(skip).cloned().collect()
+        } else {
+            VecDeque::new()
+        };
+        let slot = Arc::new(Slot {
+            name: options.name,
+            policy: options.policy,
+            capacity: options.capacity,
+            max_queued: AtomicUsize::new(queue.len()),
+            queue: std::sync::Mutex::new(queue),
+            ready: Notify::new(),
+            space: Notify::new(),
+            unsubscribed: AtomicBool::new(false),
+            bus_closed: AtomicBool::new(false),
+            received: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+        state.slots.push(Arc::clone(&slot));
+
+        Subscription { slot }
+    }
+
+    /// Deliver an event to every subscription
+    ///
+    /// Returns once the event is queued for every subscription, or dropped
+    /// for those whose policy drops. Only `Block` subscriptions with a full
+    /// queue make this wait.
+    pub async fn publish(&self, event: T) {
+        let mut state = self.inner.state.lock().await;
+        state.published += 1;
+        state
+            .slots
+            .retain(|slot| !slot.unsubscribed.load(Ordering::Acquire));
+
+        if self.inner.replay_capacity > 0 {
+            if state.replay.len() == self.inner.replay_capacity {
+                state.replay.pop_front();
+            }
+            state.replay.push_back(event.clone());
+        }
+
+        for slot in &state.slots {
+            slot.deliver(event.clone()).await;
+        }
+    }
+
+    /// Number of live subscriptions
+    pub async fn subscriber_count(&self) -> usize {
+        let state = self.inner.state.lock().await;
+        state
+            .slots
+            .iter()
+            .filter(|slot| !slot.unsubscribed.load(Ordering::Acquire))
+            .count()
+    }
+
+    /// Counters of the bus and of every live subscription
+    pub async fn stats(&self) -> BusStats {
+        let state = self.inner.state.lock().await;
+        BusStats {
+            published: state.published,
+            subscriptions: state
+                .slots
+                .iter()
+                .filter(|slot| !slot.unsubscribed.load(Ordering::Acquire))
+                .map(|slot| slot.stats())
+                .collect(),
+        }
+    }
+}
+
+/// Receiving side of one subscription
+///
+/// Dropping it unsubscribes and releases any publisher waiting for room.
+#[derive(Debug)]
+pub struct Subscription<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Subscription<T> {
+    /// Wait for the next event
+    ///
+    /// Returns `None` once every bus handle is dropped and the queue is
+    /// empty.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            if let Some(event) = self.try_recv() {
+                return Some(event);
+            }
+            if self.slot.bus_closed.load(Ordering::Acquire) {
+                // Events queued just before the bus closed
+                return self.try_recv();
+            }
+            self.slot.ready.notified().await;
+        }
+    }
+
+    /// Take the next event if one is queued
+    pub fn try_recv(&mut self) -> Option<T> {
+        let event = self.slot.queue().pop_front()?;
+        self.slot.received.fetch_add(1, Ordering::Relaxed);
+        self.slot.space.notify_one();
+        Some(event)
+    }
+
+    /// Name given at subscription
+    pub fn name(&self) -> &str {
+        &self.slot.name
+    }
+
+    /// Counters of this subscription
+    pub fn stats(&self) -> SubscriptionStats {
+        self.slot.stats()
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        self.slot.unsubscribed.store(true, Ordering::Release);
+        self.slot.space.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_slow_subscriber_does_not_stall_publisher() {
+        let bus = EventBus::new(0);
+        let mut fast = bus
+            .subscribe(SubscriptionOptions::new("audit").capacity(100))
+            .await;
+        let slow = bus
+            .subscribe(
+                SubscriptionOptions::new("tui")
+                    .capacity(4)
+                    .policy(OverflowPolicy::DropNewest),
+            )
+            .await;
+
+        timeout(Duration::from_secs(1), async {
+            for n in 0..50 {
+                bus.publish(n).await;
+            }
+        })
+        .await
+        .expect("dropping subscribers never block");
+
+        assert_eq!(fast.recv().await, Some(0));
+        let stats = bus.stats().await;
+        assert_eq!(stats.published, 50);
+        assert_eq!(stats.subscriptions[0].queued, 49);
+        assert_eq!(stats.subscriptions[0].received, 1);
+        assert_eq!(stats.subscriptions[1].queued, 4);
+        assert_eq!(stats.subscriptions[1].dropped, 46);
+        assert_eq!(slow.stats().max_queued, 4);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_subscriber_applies_backpressure() {
+        let bus = EventBus::new(0);
+        let mut sub = bus
+            .subscribe(
+                SubscriptionOptions::new("writer")
+                    .capacity(1)
+                    .policy(OverflowPolicy::Block),
+            )
+            .await;
+
+        bus.publish(1).await;
+        let publisher = bus.clone();
+        let pending = tokio::spawn(async move { publisher.publish(2).await });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pending.is_finished());
+
+        assert_eq!(sub.recv().await, Some(1));
+        timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sub.recv().await, Some(2));
+        assert_eq!(sub.stats().dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_subscriber_releases_publisher() {
+        let bus = EventBus::new(0);
+        let sub = bus
+            .subscribe(
+                SubscriptionOptions::new("gone")
+                    .capacity(1)
+                    .policy(OverflowPolicy::Block),
+            )
+            .await;
+
+        bus.publish(1).await;
+        let publisher = bus.clone();
+        let pending = tokio::spawn(async move { publisher.publish(2).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        drop(sub);
+        timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(bus.subscriber_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_late_subscriber_replays_recent_events() {
+        let bus = EventBus::new(3);
+        for n in 0..10 {
+            bus.publish(n).await;
+        }
+
+        let mut late = bus
+            .subscribe(SubscriptionOptions::new("late").replay(true))
+            .await;
+        let mut live_only = bus.subscribe(SubscriptionOptions::new("live")).await;
+        bus.publish(10).await;
+        drop(bus);
+
+        let mut replayed = Vec::new();
+        while let Some(n) = late.recv().await {
+            replayed.push(n);
+        }
+        assert_eq!(replayed, vec![7, 8, 9, 10]);
+        assert_eq!(live_only.recv().await, Some(10));
+        assert_eq!(live_only.recv().await, None);
+    }
+}
@@ -0,0 +1,515 @@
+//! Export of users, cards and fingerprints as Henry data files.
+//!
+//! The reverse of [`henry_migration`](crate::henry_migration): the
+//! database is written back as `colaborador.txt`, `cartoes.txt` and
+//! `biometria.txt`, line for line in the formats the import reads, so
+//! data can go back to Henry management software or be kept as a plain
+//! text snapshot.
+//!
+//! ```text
+//! colaborador.txt  PIS|NOME|MATRICULA|CPF|VALIDADE_INICIO|VALIDADE_FIM|ATIVO|ALLOW_CARD|ALLOW_BIO|ALLOW_KEYPAD|CODIGO
+//! cartoes.txt      NUMERO_CARTAO|MATRICULA|VALIDADE_INICIO|VALIDADE_FIM|ATIVO
+//! biometria.txt    MATRICULA|POSICAO|TEMPLATE_BASE64
+//! ```
+//!
+//! # Options
+//!
+//! [`HenryExportOptions`] sets the field delimiter (`|` by default) and the
+//! text encoding. Henry software on Windows reads Latin-1; characters
+//! Latin-1 lacks are transliterated as on turnstile displays (see
+//! [`Codepage`]). A value containing the delimiter or a line break fails
+//! the export instead of shifting the fields of its line.
+//!
+//! # Keypad Codes
+//!
+//! Codes are only written while still stored in plain text, as imported.
+//! Hashed codes (see [`pin`](crate::pin)) cannot be turned back into the
+//! code, so their users are exported without keypad access and listed in
+//! [`HenryExportReport::withheld_codes`].
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::export::{self, FileEncoding, HenryExportOptions};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! let options = HenryExportOptions::default().encoding(FileEncoding::Latin1);
+//! let report = export::export_dir(db.pool(), "./henry-export", &options).await?;
+//! println!(
+//!     "{} users, {} cards, {} fingerprints",
+//!     report.users, report.cards, report.templates
+//! );
+//! for matricula in &report.withheld_codes {
+//!     println!("{} needs a new keypad code", matricula);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::henry_migration::{BIOMETRIA_FILE, CARTOES_FILE, COLABORADOR_FILE};
+use crate::models::User;
+use crate::pin::is_hashed;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use turnkey_protocol::Codepage;
+
+/// Field delimiter of Henry data files
+pub const DEFAULT_DELIMITER: char = '|';
+
+/// Text encoding of exported files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileEncoding {
+    /// UTF-8, as read by [`henry_migration`](crate::henry_migration)
+    #[default]
+    Utf8,
+
+    /// ISO-8859-1, as read by Henry software on Windows
+    Latin1,
+}
+
+impl FileEncoding {
+    fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Latin1 => Codepage::Latin1.encode(text),
+        }
+    }
+}
+
+/// Format of exported files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HenryExportOptions {
+    /// Character between fields (default [`DEFAULT_DELIMITER`])
+    pub delimiter: char,
+
+    /// Text encoding (default UTF-8)
+    pub encoding: FileEncoding,
+}
+
+impl Default for HenryExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: DEFAULT_DELIMITER,
+            encoding: FileEncoding::default(),
+        }
+    }
+}
+
+impl HenryExportOptions {
+    /// Set the character between fields
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set the text encoding
+    pub fn encoding(mut self, encoding: FileEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Check that the delimiter can separate fields
+    fn validate(&self) -> StorageResult<()> {
+        if self.delimiter.is_alphanumeric() || self.delimiter.is_whitespace() {
+            return Err(StorageError::Validation(format!(
+                "Delimiter {:?} cannot separate fields",
+                self.delimiter
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// What an export wrote
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HenryExportReport {
+    /// Lines written to `colaborador.txt`
+    pub users: u64,
+
+    /// Lines written to `cartoes.txt`
+    pub cards: u64,
+
+    /// Lines written to `biometria.txt`
+    pub templates: u64,
+
+    /// Matriculas exported without keypad access because their code is hashed
+    pub withheld_codes: Vec<String>,
+}
+
+/// Write all users as `colaborador.txt` lines, in matricula order
+///
+/// Returns the number of lines written and the matriculas whose keypad
+/// code was withheld.
+///
+/// # Errors
+///
+/// Returns `Validation` if the delimiter is unusable or a value contains
+/// it, `Database` if the users cannot be read and `Internal` if the writer
+/// fails.
+pub async fn export_users<W: Write>(
+    pool: &SqlitePool,
+    mut writer: W,
+    options: &HenryExportOptions,
+) -> StorageResult<(u64, Vec<String>)> {
+    options.validate()?;
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+               validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+               codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
+        FROM users
+        ORDER BY matricula
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut withheld = Vec::new();
+    for user in &users {
+        let code = user.codigo.as_deref().filter(|code| !is_hashed(code));
+        let allow_keypad = user.allow_keypad && code.is_some();
+        if user.allow_keypad && !allow_keypad {
+            withheld.push(user.matricula.clone());
+        }
+
+        let line = [
+            user.pis.as_deref().unwrap_or_default(),
+            &user.nome,
+            &user.matricula,
+            user.cpf.as_deref().unwrap_or_default(),
+            &date(user.validade_inicio),
+            &date(user.validade_fim),
+            flag(user.ativo),
+            flag(user.allow_card),
+            flag(user.allow_bio),
+            flag(allow_keypad),
+            code.filter(|_| allow_keypad).unwrap_or_default(),
+        ];
+        write_line(&mut writer, COLABORADOR_FILE, &line, options)?;
+    }
+    flush(&mut writer, COLABORADOR_FILE)?;
+
+    if !withheld.is_empty() {
+        tracing::warn!(
+            users = withheld.len(),
+            "hashed keypad codes left out of {}",
+            COLABORADOR_FILE
+        );
+    }
+    Ok((users.len() as u64, withheld))
+}
+
+/// Write all cards as `cartoes.txt` lines, in card number order
+///
+/// Returns the number of lines written.
+///
+/// # Errors
+///
+/// As [`export_users()`].
+pub async fn export_cards<W: Write>(
+    pool: &SqlitePool,
+    mut writer: W,
+    options: &HenryExportOptions,
+) -> StorageResult<u64> {
+    options.validate()?;
+    let cards = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+            bool,
+        ),
+    >(
+        r#"
+        SELECT numero_cartao, matricula, validade_inicio, validade_fim, ativo
+        FROM cards
+        ORDER BY numero_cartao
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (numero_cartao, matricula, validade_inicio, validade_fim, ativo) in &cards {
+        let line = [
+            numero_cartao.as_str(),
+            matricula,
+            &date(*validade_inicio),
+            &date(*validade_fim),
+            flag(*ativo),
+        ];
+        write_line(&mut writer, CARTOES_FILE, &line, options)?;
+    }
+    flush(&mut writer, CARTOES_FILE)?;
+    Ok(cards.len() as u64)
+}
+
+/// Write all fingerprints as `biometria.txt` lines, by matricula and finger
+///
+/// Returns the number of lines written.
+///
+/// # Errors
+///
+/// As [`export_users()`].
+pub async fn export_templates<W: Write>(
+    pool: &SqlitePool,
+    mut writer: W,
+    options: &HenryExportOptions,
+) -> StorageResult<u64> {
+    options.validate()?;
+    let templates = sqlx::query!(
+        r#"
+        SELECT matricula, posicao, template_data
+        FROM biometric_templates
+        ORDER BY matricula, posicao
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for template in &templates {
+        let line = [
+            template.matricula.as_str(),
+            &template.posicao.to_string(),
+            &BASE64.encode(&template.template_data),
+        ];
+        write_line(&mut writer, BIOMETRIA_FILE, &line, options)?;
+    }
+    flush(&mut writer, BIOMETRIA_FILE)?;
+    Ok(templates.len() as u64)
+}
+
+/// Write the three Henry files into `dir`, replacing existing ones
+///
+/// The directory is created if needed.
+///
+/// # Errors
+///
+/// As [`export_users()`], plus `Internal` if a file cannot be created.
+pub async fn export_dir(
+    pool: &SqlitePool,
+    dir: impl AsRef<Path>,
+    options: &HenryExportOptions,
+) -> StorageResult<HenryExportReport> {
+    options.validate()?;
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir).map_err(|e| {
+        StorageError::Internal(format!("Failed to create {}: {}", dir.display(), e))
+    })?;
+    let create = |name: &str| -> StorageResult<BufWriter<File>> {
+        let path = dir.join(name);
+        File::create(&path).map(BufWriter::new).map_err(|e| {
+            StorageError::Internal(format!("Failed to create {}: {}", path.display(), e))
+        })
+    };
+
+    let (users, withheld_codes) = export_users(pool, create(COLABORADOR_FILE)?, options).await?;
+    let cards = export_cards(pool, create(CARTOES_FILE)?, options).await?;
+    let templates = export_templates(pool, create(BIOMETRIA_FILE)?, options).await?;
+
+    tracing::info!(users, cards, templates, dir = %dir.display(), "henry files exported");
+    Ok(HenryExportReport {
+        users,
+        cards,
+        templates,
+        withheld_codes,
+    })
+}
+
+/// Join `fields` with the delimiter and write them as one encoded line
+fn write_line<W: Write>(
+    writer: &mut W,
+    file: &str,
+    fields: &[&str],
+    options: &HenryExportOptions,
+) -> StorageResult<()> {
+    let mut line = String::new();
+    for (index, field) in fields.iter().enumerate() {
+        if field.contains(options.delimiter) || field.contains(['\r', '\n']) {
+            return Err(StorageError::Validation(format!(
+                "{}: field {} of '{}' contains the delimiter or a line break",
+                file,
+                index + 1,
+                fields.iter().find(|f| !f.is_empty()).unwrap_or(&"")
+            )));
+        }
+        if index > 0 {
+            line.push(options.delimiter);
+        }
+        line.push_str(field);
+    }
+    line.push('\n');
+
+    writer
+        .write_all(&options.encoding.encode(&line))
+        .map_err(|e| StorageError::Internal(format!("Failed to write {}: {}", file, e)))
+}
+
+fn flush<W: Write>(writer: &mut W, file: &str) -> StorageResult<()> {
+    writer
+        .flush()
+        .map_err(|e| StorageError::Internal(format!("Failed to write {}: {}", file, e)))
+}
+
+/// `dd/mm/yyyy` (UTC), empty when unset
+fn date(value: Option<DateTime<Utc>>) -> String {
+    value
+        .map(|value| value.format("%d/%m/%Y").to_string())
+        .unwrap_or_default()
+}
+
+fn flag(value: bool) -> &'static str {
+    if value { "1" } else { "0" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::henry_migration::HenryExport;
+    use crate::repositories::{SqliteUserRepository, UserRepository};
+    use std::io::Cursor;
+
+    const COLABORADOR: &str = "\
+12345678901|José Araújo|EXP001|98765432100|01/01/2025|31/12/2025|1|1|1|1|482916
+|Bruno Lima|EXP002||||0|1|0|0|
+";
+    const CARTOES: &str = "\
+00000000000011912322|EXP001|01/01/2025||1
+ABCDEF123456|EXP002|||0
+";
+
+    async fn seeded() -> (Database, String) {
+        let db = Database::in_memory().await.unwrap();
+        // Start from an empty database, without the migration seed data
+        sqlx::query!("DELETE FROM users")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let biometria = format!("EXP001|3|{}\n", BASE64.encode([7u8; 600]));
+        HenryExport::read(
+            Cursor::new(COLABORADOR),
+            Some(Cursor::new(CARTOES)),
+            Some(Cursor::new(biometria.clone())),
+        )
+        .unwrap()
+        .import(db.pool(), |_| {})
+        .await
+        .unwrap();
+        (db, biometria)
+    }
+
+    async fn export_to_strings(
+        pool: &SqlitePool,
+        options: &HenryExportOptions,
+    ) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut users = Vec::new();
+        let mut cards = Vec::new();
+        let mut templates = Vec::new();
+        export_users(pool, &mut users, options).await.unwrap();
+        export_cards(pool, &mut cards, options).await.unwrap();
+        export_templates(pool, &mut templates, options)
+            .await
+            .unwrap();
+        (users, cards, templates)
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let (db, biometria) = seeded().await;
+        let (users, cards, templates) =
+            export_to_strings(db.pool(), &HenryExportOptions::default()).await;
+
+        assert_eq!(String::from_utf8(users.clone()).unwrap(), COLABORADOR);
+        assert_eq!(String::from_utf8(cards.clone()).unwrap(), CARTOES);
+        assert_eq!(String::from_utf8(templates.clone()).unwrap(), biometria);
+
+        // The files import again into an empty database
+        let target = Database::in_memory().await.unwrap();
+        sqlx::query!("DELETE FROM users")
+            .execute(target.pool())
+            .await
+            .unwrap();
+        let plan = HenryExport::read(
+            Cursor::new(users),
+            Some(Cursor::new(cards)),
+            Some(Cursor::new(templates)),
+        )
+        .unwrap()
+        .import(target.pool(), |_| {})
+        .await
+        .unwrap();
+        assert_eq!(
+            (plan.users.new, plan.cards.new, plan.templates.new),
+            (2, 2, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delimiter_and_latin1() {
+        let (db, _) = seeded().await;
+        let options = HenryExportOptions::default()
+            .delimiter(';')
+            .encoding(FileEncoding::Latin1);
+        let (users, _, _) = export_to_strings(db.pool(), &options).await;
+
+        let first = users.split(|&b| b == b'\n').next().unwrap();
+        assert_eq!(
+            first,
+            b"12345678901;Jos\xe9 Ara\xfajo;EXP001;98765432100;01/01/2025;31/12/2025;1;1;1;1;482916"
+        );
+
+        assert!(
+            export_users(db.pool(), Vec::new(), &options.delimiter('A'))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_value_with_delimiter_fails() {
+        let (db, _) = seeded().await;
+        let options = HenryExportOptions::default().delimiter(' ');
+        assert!(export_users(db.pool(), Vec::new(), &options).await.is_err());
+
+        // Dates are written with '/'
+        let err = export_users(db.pool(), Vec::new(), &options.delimiter('/'))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(COLABORADOR_FILE));
+    }
+
+    #[tokio::test]
+    async fn test_hashed_codes_are_withheld() {
+        let (db, _) = seeded().await;
+        let key = crate::pin::PinDigestKey::new([7; 32].to_vec().into()).unwrap();
+        let repo = SqliteUserRepository::new(db.pool().clone()).with_digest_key(key);
+        let user = repo.find_by_matricula("EXP001").await.unwrap().unwrap();
+        repo.set_code(user.id, "482916").await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let report = export_dir(db.pool(), dir.path(), &HenryExportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!((report.users, report.cards, report.templates), (2, 2, 1));
+        assert_eq!(report.withheld_codes, ["EXP001"]);
+
+        let users = std::fs::read_to_string(dir.path().join(COLABORADOR_FILE)).unwrap();
+        assert!(users.starts_with(
+            "12345678901|José Araújo|EXP001|98765432100|01/01/2025|31/12/2025|1|1|1|0|\n"
+        ));
+    }
+}
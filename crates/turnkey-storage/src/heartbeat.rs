@@ -0,0 +1,289 @@
+//! Missed-heartbeat detection driving the validation mode
+//!
+//! A hybrid [`ValidationModeController`] normally notices that the server is
+//! gone only when a validation fails, so the first user after an outage
+//! waits for a full network timeout. [`HeartbeatMonitor`] watches the
+//! keepalives exchanged with the server instead: after
+//! [`HeartbeatConfig::missed_threshold`] consecutive keepalives go
+//! unanswered, it moves the controller to local validation before anyone
+//! presents a card.
+//!
+//! A single answered keepalive is not enough to go back. The link has to
+//! answer [`HeartbeatConfig::stable_heartbeats`] keepalives in a row, and
+//! any miss in between starts the count over, so a flapping link keeps the
+//! device offline.
+//!
+//! Switches go through the controller and therefore produce the usual
+//! [`ModeChange`] on its event channel, with reason
+//! [`HeartbeatMissed`](ModeChangeReason::HeartbeatMissed) or
+//! [`HeartbeatRestored`](ModeChangeReason::HeartbeatRestored). As with
+//! [`ValidationModeController::on_connection_lost()`], only hybrid devices
+//! switch; the monitor still tracks the link for the others.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::heartbeat::{HeartbeatConfig, HeartbeatMonitor};
+//! use turnkey_storage::mode::{OperatingMode, ValidationModeController};
+//! use turnkey_storage::{OfflineValidator, OnlineValidator, OnlineValidatorConfig};
+//! use turnkey_network::{TcpClient, TcpClientConfig};
+//! use turnkey_core::DeviceId;
+//!
+//! # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let device_id = DeviceId::new(15)?;
+//! let online = OnlineValidator::new(
+//!     TcpClient::new(TcpClientConfig::default()),
+//!     device_id,
+//!     OnlineValidatorConfig::default(),
+//! );
+//! let offline = OfflineValidator::new(pool).with_device_id(device_id);
+//! let mut controller =
+//!     ValidationModeController::new(device_id, online, offline, OperatingMode::Hybrid);
+//!
+//! let mut monitor = HeartbeatMonitor::new(HeartbeatConfig::default())?;
+//!
+//! // Keepalive timer: report each outcome
+//! for _ in 0..3 {
+//!     monitor.record_missed(&mut controller);
+//! }
+//! assert!(!monitor.is_link_up());
+//! assert!(!controller.is_online());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::mode::{ModeChange, ModeChangeReason, ValidationModeController};
+
+/// Thresholds of a [`HeartbeatMonitor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    /// Consecutive unanswered keepalives that mark the link down
+    /// (default: 3)
+    pub missed_threshold: u32,
+
+    /// Consecutive answered keepalives that mark the link up again
+    /// (default: 3)
+    pub stable_heartbeats: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            missed_threshold: 3,
+            stable_heartbeats: 3,
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    /// Check that both thresholds are positive
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if a threshold is zero.
+    pub fn validate(&self) -> StorageResult<()> {
+        if self.missed_threshold == 0 || self.stable_heartbeats == 0 {
+            return Err(StorageError::Configuration(
+                "Heartbeat missed_threshold and stable_heartbeats must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Tracks keepalive outcomes and switches a controller on link changes
+///
+/// See the [module documentation](self) for the rules.
+#[derive(Debug, Clone)]
+pub struct HeartbeatMonitor {
+    config: HeartbeatConfig,
+    link_up: bool,
+    missed: u32,
+    answered: u32,
+}
+
+impl HeartbeatMonitor {
+    /// Create a monitor that assumes the link is up
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if `config` is invalid.
+    pub fn new(config: HeartbeatConfig) -> StorageResult<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            link_up: true,
+            missed: 0,
+            answered: 0,
+        })
+    }
+
+    /// Whether the link is currently considered up
+    pub fn is_link_up(&self) -> bool {
+        self.link_up
+    }
+
+    /// Unanswered keepalives since the last answered one
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    /// Record an answered keepalive
+    ///
+    /// Returns the change if this answer completed a stable reconnection
+    /// and the controller went back to the server.
+    pub fn record_heartbeat(
+        &mut self,
+        controller: &mut ValidationModeController,
+    ) -> Option<ModeChange> {
+        self.missed = 0;
+        if self.link_up {
+            return None;
+        }
+
+        self.answered += 1;
+        if self.answered < self.config.stable_heartbeats {
+            return None;
+        }
+
+        self.answered = 0;
+        self.link_up = true;
+        tracing::info!("heartbeat restored");
+        controller.switch_link(true, ModeChangeReason::HeartbeatRestored)
+    }
+
+    /// Record a keepalive that went unanswered
+    ///
+    /// Returns the change if this miss reached the threshold and the
+    /// controller switched to local validation.
+    pub fn record_missed(
+        &mut self,
+        controller: &mut ValidationModeController,
+    ) -> Option<ModeChange> {
+        self.answered = 0;
+        self.missed = self.missed.saturating_add(1);
+        if !self.link_up || self.missed < self.config.missed_threshold {
+            return None;
+        }
+
+        self.link_up = false;
+        tracing::warn!(missed = self.missed, "heartbeat lost");
+        controller.switch_link(false, ModeChangeReason::HeartbeatMissed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::mode::OperatingMode;
+    use crate::validator::{OfflineValidator, OnlineValidator, OnlineValidatorConfig};
+    use tokio::sync::mpsc;
+    use turnkey_core::DeviceId;
+    use turnkey_network::{TcpClient, TcpClientConfig};
+
+    async fn controller(db: &Database, mode: OperatingMode) -> ValidationModeController {
+        let device_id = DeviceId::new(15).unwrap();
+        let online = OnlineValidator::new(
+            TcpClient::new(TcpClientConfig::default()),
+            device_id,
+            OnlineValidatorConfig::default(),
+        );
+        let offline = OfflineValidator::new(db.pool().clone());
+        ValidationModeController::new(device_id, online, offline, mode)
+    }
+
+    fn monitor() -> HeartbeatMonitor {
+        HeartbeatMonitor::new(HeartbeatConfig {
+            missed_threshold: 3,
+            stable_heartbeats: 2,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_config_rejects_zero_thresholds() {
+        let config = HeartbeatConfig {
+            missed_threshold: 0,
+            ..Default::default()
+        };
+        assert!(HeartbeatMonitor::new(config).is_err());
+        assert!(HeartbeatConfig::default().validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeats_switch_offline_and_back() {
+        let db = Database::in_memory().await.unwrap();
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut controller = controller(&db, OperatingMode::Hybrid)
+            .await
+            .with_mode_events(tx);
+        let mut monitor = monitor();
+
+        assert!(monitor.record_missed(&mut controller).is_none());
+        assert!(monitor.record_missed(&mut controller).is_none());
+        let lost = monitor.record_missed(&mut controller).unwrap();
+        assert_eq!(lost.reason, ModeChangeReason::HeartbeatMissed);
+        assert!(!lost.online);
+        assert!(!controller.is_online());
+        assert!(!monitor.is_link_up());
+
+        // Further misses do not emit again
+        assert!(monitor.record_missed(&mut controller).is_none());
+
+        assert!(monitor.record_heartbeat(&mut controller).is_none());
+        let restored = monitor.record_heartbeat(&mut controller).unwrap();
+        assert_eq!(restored.reason, ModeChangeReason::HeartbeatRestored);
+        assert!(controller.is_online());
+        assert!(monitor.is_link_up());
+
+        assert_eq!(rx.try_recv().unwrap(), lost);
+        assert_eq!(rx.try_recv().unwrap(), restored);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_answer_between_misses_resets_count() {
+        let db = Database::in_memory().await.unwrap();
+        let mut controller = controller(&db, OperatingMode::Hybrid).await;
+        let mut monitor = monitor();
+
+        monitor.record_missed(&mut controller);
+        monitor.record_missed(&mut controller);
+        assert!(monitor.record_heartbeat(&mut controller).is_none());
+        assert_eq!(monitor.missed(), 0);
+        assert!(monitor.record_missed(&mut controller).is_none());
+        assert!(controller.is_online());
+    }
+
+    #[tokio::test]
+    async fn test_flapping_link_stays_offline() {
+        let db = Database::in_memory().await.unwrap();
+        let mut controller = controller(&db, OperatingMode::Hybrid).await;
+        let mut monitor = monitor();
+        for _ in 0..3 {
+            monitor.record_missed(&mut controller);
+        }
+
+        for _ in 0..3 {
+            assert!(monitor.record_heartbeat(&mut controller).is_none());
+            monitor.record_missed(&mut controller);
+        }
+        assert!(!controller.is_online());
+        assert!(!monitor.is_link_up());
+    }
+
+    #[tokio::test]
+    async fn test_only_hybrid_devices_switch() {
+        let db = Database::in_memory().await.unwrap();
+        let mut controller = controller(&db, OperatingMode::Online).await;
+        let mut monitor = monitor();
+
+        for _ in 0..3 {
+            assert!(monitor.record_missed(&mut controller).is_none());
+        }
+        assert!(!monitor.is_link_up());
+        assert!(controller.is_online());
+    }
+}
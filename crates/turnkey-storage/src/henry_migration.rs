@@ -0,0 +1,865 @@
+//! Migration of a Henry equipment export folder into the database.
+//!
+//! Sites replacing Henry software bring its exports: `colaborador.txt`
+//! (users), `cartoes.txt` (cards) and `biometria.txt` (fingerprints). The
+//! three files reference each other by matricula, and a card pointing at a
+//! user that is in neither the file nor the database would only fail in the
+//! middle of an import. [`HenryExport`] therefore runs in two steps:
+//!
+//! 1. [`HenryExport::plan()`] checks every line and every cross-file
+//!    reference against the files and the database, and counts what would
+//!    be created or updated, without writing anything;
+//! 2. [`HenryExport::import()`] writes users, cards and templates in that
+//!    order in one transaction, and refuses to start while the plan has
+//!    errors. A failure leaves the database as it was.
+//!
+//! # File Formats
+//!
+//! ```text
+//! colaborador.txt  PIS|NOME|MATRICULA|CPF|VALIDADE_INICIO|VALIDADE_FIM|ATIVO|ALLOW_CARD|ALLOW_BIO|ALLOW_KEYPAD|CODIGO
+//! cartoes.txt      NUMERO_CARTAO|MATRICULA|VALIDADE_INICIO|VALIDADE_FIM|ATIVO
+//! biometria.txt    MATRICULA|POSICAO|TEMPLATE_BASE64
+//! ```
+//!
+//! Only `colaborador.txt` is required. Comments (`#`) and blank lines are
+//! skipped, as in [`colaborador`](crate::colaborador).
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::henry_migration::HenryExport;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let export = HenryExport::read_dir("./henry-export")?;
+//!
+//! let plan = export.plan(db.pool()).await?;
+//! for error in &plan.errors {
+//!     println!("{}:{}: {}", error.file, error.line, error.message);
+//! }
+//! if plan.is_clean() {
+//!     export
+//!         .import(db.pool(), |p| println!("{} {}/{}", p.stage, p.done, p.total))
+//!         .await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::colaborador::{date, flag, parse_colaborador_line, upsert_users};
+use crate::error::{StorageError, StorageResult};
+use crate::models::{Card, MAX_TEMPLATE_SIZE, MIN_TEMPLATE_SIZE, User};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// User file of a Henry export
+pub const COLABORADOR_FILE: &str = "colaborador.txt";
+
+/// Card file of a Henry export
+pub const CARTOES_FILE: &str = "cartoes.txt";
+
+/// Fingerprint file of a Henry export
+pub const BIOMETRIA_FILE: &str = "biometria.txt";
+
+/// Rows written between two progress reports
+const PROGRESS_INTERVAL: usize = 500;
+
+/// Number of fields in a `cartoes.txt` line
+const CARTAO_FIELD_COUNT: usize = 5;
+
+/// Number of fields in a `biometria.txt` line
+const BIOMETRIA_FIELD_COUNT: usize = 3;
+
+/// Card read from `cartoes.txt`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardRow {
+    /// Card number, normalized (see [`Card::normalize_card_number`])
+    pub numero_cartao: String,
+
+    /// Owner's matricula
+    pub matricula: String,
+
+    /// Valid from (UTC, start of day)
+    pub validade_inicio: Option<DateTime<Utc>>,
+
+    /// Valid until (UTC, end of day)
+    pub validade_fim: Option<DateTime<Utc>>,
+
+    /// Whether the card is active
+    pub ativo: bool,
+}
+
+/// Fingerprint read from `biometria.txt`
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateRow {
+    /// Owner's matricula
+    pub matricula: String,
+
+    /// Finger position (0-9)
+    pub posicao: i64,
+
+    /// Decoded template
+    pub template_data: Vec<u8>,
+}
+
+impl fmt::Debug for TemplateRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TemplateRow")
+            .field("matricula", &self.matricula)
+            .field("posicao", &self.posicao)
+            .field("template_len", &self.template_data.len())
+            .finish()
+    }
+}
+
+/// Problem found on a line of an export file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIssue {
+    /// File name (e.g. `cartoes.txt`)
+    pub file: String,
+
+    /// One-based line number
+    pub line: u64,
+
+    /// Description of the problem
+    pub message: String,
+}
+
+impl FileIssue {
+    fn new(file: &str, line: u64, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Rows of one kind that an import creates and updates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedRows {
+    /// Rows not in the database yet
+    pub new: u64,
+
+    /// Rows that replace existing ones
+    pub updated: u64,
+}
+
+impl PlannedRows {
+    /// Rows written in total
+    pub fn total(&self) -> u64 {
+        self.new + self.updated
+    }
+
+    fn count(&mut self, exists: bool) {
+        if exists {
+            self.updated += 1;
+        } else {
+            self.new += 1;
+        }
+    }
+}
+
+/// Pre-import report: what an import would write and what blocks it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    /// Users from `colaborador.txt`
+    pub users: PlannedRows,
+
+    /// Cards from `cartoes.txt`
+    pub cards: PlannedRows,
+
+    /// Fingerprints from `biometria.txt`
+    pub templates: PlannedRows,
+
+    /// Problems that block the import, by file and line
+    pub errors: Vec<FileIssue>,
+
+    /// Changes worth a look that do not block the import
+    pub warnings: Vec<FileIssue>,
+}
+
+impl MigrationPlan {
+    /// Check if nothing blocks the import
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Part of the import being written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStage {
+    /// `colaborador.txt`
+    Users,
+
+    /// `cartoes.txt`
+    Cards,
+
+    /// `biometria.txt`
+    Templates,
+}
+
+impl fmt::Display for MigrationStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Users => "users",
+            Self::Cards => "cards",
+            Self::Templates => "templates",
+        })
+    }
+}
+
+/// Progress of a running import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationProgress {
+    /// Part being written
+    pub stage: MigrationStage,
+
+    /// Rows of the stage written so far
+    pub done: usize,
+
+    /// Rows of the stage
+    pub total: usize,
+}
+
+/// Parsed contents of a Henry export folder
+///
+/// Lines that do not parse are kept as errors and reported by
+/// [`plan()`](Self::plan) with the cross-file checks.
+#[derive(Debug, Clone, Default)]
+pub struct HenryExport {
+    users: Vec<(u64, User)>,
+    cards: Vec<(u64, CardRow)>,
+    templates: Vec<(u64, TemplateRow)>,
+    parse_errors: Vec<FileIssue>,
+}
+
+impl HenryExport {
+    /// Read the export files in `dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if `colaborador.txt` is missing and `Internal`
+    /// if a file cannot be read.
+    pub fn read_dir(dir: impl AsRef<Path>) -> StorageResult<Self> {
+        let dir = dir.as_ref();
+        let open = |name: &str| -> StorageResult<Option<BufReader<File>>> {
+            let path = dir.join(name);
+            if !path.exists() {
+                return Ok(None);
+            }
+            File::open(&path)
+                .map(BufReader::new)
+                .map(Some)
+                .map_err(|e| {
+                    StorageError::Internal(format!("Failed to open {}: {}", path.display(), e))
+                })
+        };
+
+        let colaborador = open(COLABORADOR_FILE)?.ok_or_else(|| {
+            StorageError::Validation(format!(
+                "{} not found in {}",
+                COLABORADOR_FILE,
+                dir.display()
+            ))
+        })?;
+        Self::read(colaborador, open(CARTOES_FILE)?, open(BIOMETRIA_FILE)?)
+    }
+
+    /// Read the export files from readers
+    ///
+    /// # Errors
+    ///
+    /// Returns `Internal` if a reader fails.
+    pub fn read(
+        colaborador: impl BufRead,
+        cartoes: Option<impl BufRead>,
+        biometria: Option<impl BufRead>,
+    ) -> StorageResult<Self> {
+        let mut export = Self::default();
+        for (line, text) in data_lines(colaborador, COLABORADOR_FILE)? {
+            match parse_colaborador_line(&text) {
+                Ok(user) => export.users.push((line, user)),
+                Err(message) => {
+                    export
+                        .parse_errors
+                        .push(FileIssue::new(COLABORADOR_FILE, line, message))
+                }
+            }
+        }
+        if let Some(cartoes) = cartoes {
+            for (line, text) in data_lines(cartoes, CARTOES_FILE)? {
+                match parse_cartao_line(&text) {
+                    Ok(card) => export.cards.push((line, card)),
+                    Err(message) => {
+                        export
+                            .parse_errors
+                            .push(FileIssue::new(CARTOES_FILE, line, message))
+                    }
+                }
+            }
+        }
+        if let Some(biometria) = biometria {
+            for (line, text) in data_lines(biometria, BIOMETRIA_FILE)? {
+                match parse_biometria_line(&text) {
+                    Ok(template) => export.templates.push((line, template)),
+                    Err(message) => {
+                        export
+                            .parse_errors
+                            .push(FileIssue::new(BIOMETRIA_FILE, line, message))
+                    }
+                }
+            }
+        }
+        Ok(export)
+    }
+
+    /// Number of data lines parsed from each file: users, cards, templates
+    pub fn counts(&self) -> (usize, usize, usize) {
+        (self.users.len(), self.cards.len(), self.templates.len())
+    }
+
+    /// Check the export against itself and the database
+    ///
+    /// Nothing is written. Blocking errors:
+    /// - lines that do not parse,
+    /// - matriculas, card numbers or finger positions repeated in a file,
+    /// - cards and fingerprints of users found neither in `colaborador.txt`
+    ///   nor in the database,
+    /// - fingerprints of users without `ALLOW_BIO`.
+    ///
+    /// A card already registered to another user is a warning: the import
+    /// moves it to the user named in `cartoes.txt`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Database` if the existing rows cannot be read.
+    pub async fn plan(&self, pool: &SqlitePool) -> StorageResult<MigrationPlan> {
+        let stored_users: HashMap<String, bool> =
+            sqlx::query!("SELECT matricula, allow_bio FROM users")
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|row| (row.matricula, row.allow_bio))
+                .collect();
+        let stored_cards: HashMap<String, String> =
+            sqlx::query!("SELECT numero_cartao, matricula FROM cards")
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|row| {
+                    (
+                        Card::normalize_card_number(&row.numero_cartao),
+                        row.matricula,
+                    )
+                })
+                .collect();
+        let stored_templates: HashSet<(String, i64)> =
+            sqlx::query!("SELECT matricula, posicao FROM biometric_templates")
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|row| (row.matricula, row.posicao))
+                .collect();
+
+        let mut plan = MigrationPlan {
+            errors: self.parse_errors.clone(),
+            ..MigrationPlan::default()
+        };
+
+        // matricula -> (first line, allow_bio) for users in the file
+        let mut file_users: HashMap<&str, (u64, bool)> = HashMap::new();
+        for (line, user) in &self.users {
+            if let Some((first, _)) = file_users.get(user.matricula.as_str()) {
+                plan.errors.push(FileIssue::new(
+                    COLABORADOR_FILE,
+                    *line,
+                    format!(
+                        "MATRICULA '{}' duplicated (first on line {})",
+                        user.matricula, first
+                    ),
+                ));
+                continue;
+            }
+            file_users.insert(&user.matricula, (*line, user.allow_bio));
+            plan.users.count(stored_users.contains_key(&user.matricula));
+        }
+        let allow_bio = |matricula: &str| {
+            file_users
+                .get(matricula)
+                .map(|(_, allow_bio)| *allow_bio)
+                .or_else(|| stored_users.get(matricula).copied())
+        };
+
+        let mut file_cards: HashMap<&str, u64> = HashMap::new();
+        for (line, card) in &self.cards {
+            if let Some(first) = file_cards.get(card.numero_cartao.as_str()) {
+                plan.errors.push(FileIssue::new(
+                    CARTOES_FILE,
+                    *line,
+                    format!(
+                        "NUMERO_CARTAO '{}' duplicated (first on line {})",
+                        card.numero_cartao, first
+                    ),
+                ));
+                continue;
+            }
+            file_cards.insert(&card.numero_cartao, *line);
+            if allow_bio(&card.matricula).is_none() {
+                plan.errors.push(FileIssue::new(
+                    CARTOES_FILE,
+                    *line,
+                    format!(
+                        "Card '{}' references missing user '{}'",
+                        card.numero_cartao, card.matricula
+                    ),
+                ));
+                continue;
+            }
+            let owner = stored_cards.get(&card.numero_cartao);
+            if let Some(owner) = owner
+                && *owner != card.matricula
+            {
+                plan.warnings.push(FileIssue::new(
+                    CARTOES_FILE,
+                    *line,
+                    format!(
+                        "Card '{}' moves from user '{}' to '{}'",
+                        card.numero_cartao, owner, card.matricula
+                    ),
+                ));
+            }
+            plan.cards.count(owner.is_some());
+        }
+
+        let mut file_templates: HashMap<(&str, i64), u64> = HashMap::new();
+        for (line, template) in &self.templates {
+            let key = (template.matricula.as_str(), template.posicao);
+            if let Some(first) = file_templates.get(&key) {
+                plan.errors.push(FileIssue::new(
+                    BIOMETRIA_FILE,
+                    *line,
+                    format!(
+                        "Finger {} of '{}' duplicated (first on line {})",
+                        template.posicao, template.matricula, first
+                    ),
+                ));
+                continue;
+            }
+            file_templates.insert(key, *line);
+            match allow_bio(&template.matricula) {
+                None => plan.errors.push(FileIssue::new(
+                    BIOMETRIA_FILE,
+                    *line,
+                    format!(
+                        "Fingerprint references missing user '{}'",
+                        template.matricula
+                    ),
+                )),
+                Some(false) => plan.errors.push(FileIssue::new(
+                    BIOMETRIA_FILE,
+                    *line,
+                    format!(
+                        "User '{}' does not have ALLOW_BIO enabled",
+                        template.matricula
+                    ),
+                )),
+                Some(true) => plan.templates.count(
+                    stored_templates.contains(&(template.matricula.clone(), template.posicao)),
+                ),
+            }
+        }
+
+        plan.errors
+            .sort_by(|a, b| (file_order(&a.file), a.line).cmp(&(file_order(&b.file), b.line)));
+        Ok(plan)
+    }
+
+    /// Check the export, then write it in one transaction
+    ///
+    /// Calls `on_progress` every few hundred rows and at the end of each
+    /// stage. Returns the plan that was carried out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the plan has errors, in which case nothing
+    /// is written, and `Database` if a row cannot be written, in which case
+    /// the whole import is rolled back.
+    pub async fn import(
+        &self,
+        pool: &SqlitePool,
+        mut on_progress: impl FnMut(MigrationProgress),
+    ) -> StorageResult<MigrationPlan> {
+        let plan = self.plan(pool).await?;
+        if !plan.is_clean() {
+            return Err(StorageError::Validation(format!(
+                "Export has {} errors; fix them before importing",
+                plan.errors.len()
+            )));
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let users: Vec<User> = self.users.iter().map(|(_, user)| user.clone()).collect();
+        let total = users.len();
+        for (index, part) in users.chunks(PROGRESS_INTERVAL).enumerate() {
+            upsert_users(&mut tx, part).await?;
+            on_progress(MigrationProgress {
+                stage: MigrationStage::Users,
+                done: (index * PROGRESS_INTERVAL + part.len()).min(total),
+                total,
+            });
+        }
+
+        let total = self.cards.len();
+        for (index, (_, card)) in self.cards.iter().enumerate() {
+            sqlx::query!(
+                r#"
+                INSERT INTO cards (
+                    numero_cartao, matricula, user_id,
+                    validade_inicio, validade_fim, ativo
+                )
+                VALUES (?, ?, (SELECT id FROM users WHERE matricula = ?), ?, ?, ?)
+                ON CONFLICT (numero_cartao) DO UPDATE SET
+                    matricula = excluded.matricula,
+                    user_id = excluded.user_id,
+                    validade_inicio = excluded.validade_inicio,
+                    validade_fim = excluded.validade_fim,
+                    ativo = excluded.ativo
+                "#,
+                card.numero_cartao,
+                card.matricula,
+                card.matricula,
+                card.validade_inicio,
+                card.validade_fim,
+                card.ativo
+            )
+            .execute(&mut *tx)
+            .await?;
+            report_progress(&mut on_progress, MigrationStage::Cards, index + 1, total);
+        }
+
+        let total = self.templates.len();
+        for (index, (_, template)) in self.templates.iter().enumerate() {
+            let now = Utc::now();
+            sqlx::query!(
+                r#"
+                INSERT INTO biometric_templates (
+                    matricula, user_id, posicao, template_data, created_at, updated_at
+                )
+                VALUES (?, (SELECT id FROM users WHERE matricula = ?), ?, ?, ?, ?)
+                ON CONFLICT (matricula, posicao) DO UPDATE SET
+                    template_data = excluded.template_data,
+                    updated_at = excluded.updated_at
+                "#,
+                template.matricula,
+                template.matricula,
+                template.posicao,
+                template.template_data,
+                now,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+            report_progress(
+                &mut on_progress,
+                MigrationStage::Templates,
+                index + 1,
+                total,
+            );
+        }
+
+        tx.commit().await?;
+        tracing::info!(
+            users = plan.users.total(),
+            cards = plan.cards.total(),
+            templates = plan.templates.total(),
+            warnings = plan.warnings.len(),
+            "henry export imported"
+        );
+        Ok(plan)
+    }
+}
+
+/// Parse one `cartoes.txt` line
+///
+/// # Errors
+///
+/// Returns a description of the first rule the line breaks.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::henry_migration::parse_cartao_line;
+///
+/// let card = parse_cartao_line("abcdef123456|1003|||1").unwrap();
+/// assert_eq!(card.numero_cartao, "ABCDEF123456");
+/// assert!(card.validade_fim.is_none());
+///
+/// assert!(parse_cartao_line("12|1003|||1").is_err());
+/// ```
+pub fn parse_cartao_line(line: &str) -> Result<CardRow, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+    if fields.len() != CARTAO_FIELD_COUNT {
+        return Err(format!(
+            "Expected {} fields, found {}",
+            CARTAO_FIELD_COUNT,
+            fields.len()
+        ));
+    }
+
+    let numero_cartao = Card::normalize_card_number(fields[0]);
+    if !(3..=20).contains(&numero_cartao.chars().count()) {
+        return Err(format!(
+            "NUMERO_CARTAO '{}' must be 3-20 characters",
+            numero_cartao
+        ));
+    }
+    let matricula = fields[1];
+    if matricula.is_empty() {
+        return Err("Missing required field MATRICULA".to_string());
+    }
+
+    let validade_inicio = date(fields[2], "VALIDADE_INICIO", (0, 0, 0))?;
+    let validade_fim = date(fields[3], "VALIDADE_FIM", (23, 59, 59))?;
+    if let (Some(inicio), Some(fim)) = (validade_inicio, validade_fim)
+        && fim < inicio
+    {
+        return Err("VALIDADE_FIM is before VALIDADE_INICIO".to_string());
+    }
+
+    Ok(CardRow {
+        numero_cartao,
+        matricula: matricula.to_string(),
+        validade_inicio,
+        validade_fim,
+        ativo: flag(fields[4], "ATIVO")?,
+    })
+}
+
+/// Parse one `biometria.txt` line
+///
+/// # Errors
+///
+/// Returns a description of the first rule the line breaks, including
+/// templates outside [`MIN_TEMPLATE_SIZE`]..=[`MAX_TEMPLATE_SIZE`] bytes.
+pub fn parse_biometria_line(line: &str) -> Result<TemplateRow, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+    if fields.len() != BIOMETRIA_FIELD_COUNT {
+        return Err(format!(
+            "Expected {} fields, found {}",
+            BIOMETRIA_FIELD_COUNT,
+            fields.len()
+        ));
+    }
+
+    let matricula = fields[0];
+    if matricula.is_empty() {
+        return Err("Missing required field MATRICULA".to_string());
+    }
+    let posicao = fields[1]
+        .parse::<i64>()
+        .ok()
+        .filter(|posicao| (0..=9).contains(posicao))
+        .ok_or_else(|| format!("Invalid POSICAO '{}' (expected 0-9)", fields[1]))?;
+    let template_data = BASE64
+        .decode(fields[2])
+        .map_err(|e| format!("Invalid TEMPLATE_BASE64: {}", e))?;
+    if !(MIN_TEMPLATE_SIZE..=MAX_TEMPLATE_SIZE).contains(&template_data.len()) {
+        return Err(format!(
+            "Template has {} bytes (expected {}-{})",
+            template_data.len(),
+            MIN_TEMPLATE_SIZE,
+            MAX_TEMPLATE_SIZE
+        ));
+    }
+
+    Ok(TemplateRow {
+        matricula: matricula.to_string(),
+        posicao,
+        template_data,
+    })
+}
+
+/// Numbered data lines of a file, without comments and blank lines
+fn data_lines(reader: impl BufRead, file: &str) -> StorageResult<Vec<(u64, String)>> {
+    let mut lines = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line =
+            line.map_err(|e| StorageError::Internal(format!("Failed to read {}: {}", file, e)))?;
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        lines.push((index as u64 + 1, text.to_string()));
+    }
+    Ok(lines)
+}
+
+/// Position of a file in the import order, for sorting issues
+fn file_order(file: &str) -> usize {
+    [COLABORADOR_FILE, CARTOES_FILE, BIOMETRIA_FILE]
+        .iter()
+        .position(|name| *name == file)
+        .unwrap_or(usize::MAX)
+}
+
+fn report_progress(
+    on_progress: &mut impl FnMut(MigrationProgress),
+    stage: MigrationStage,
+    done: usize,
+    total: usize,
+) {
+    if done.is_multiple_of(PROGRESS_INTERVAL) || done == total {
+        on_progress(MigrationProgress { stage, done, total });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::repositories::{CardRepository, SqliteCardRepository};
+    use std::io::Cursor;
+
+    const COLABORADOR: &str = "\
+# Arquivo de colaboradores
+|Ana Costa|MIG001||01/01/2025||1|1|1|0|
+|Bruno Lima|MIG002||||1|1|0|0|
+";
+
+    fn template(matricula: &str, posicao: u8) -> String {
+        format!(
+            "{}|{}|{}\n",
+            matricula,
+            posicao,
+            BASE64.encode([posicao; 600])
+        )
+    }
+
+    fn export(cartoes: &str, biometria: &str) -> HenryExport {
+        HenryExport::read(
+            Cursor::new(COLABORADOR),
+            Some(Cursor::new(cartoes.to_string())),
+            Some(Cursor::new(biometria.to_string())),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let card = parse_cartao_line("00000000000011912322|1001|01/01/2025|31/12/2025|1").unwrap();
+        assert_eq!(card.matricula, "1001");
+        assert_eq!(
+            card.validade_fim.unwrap().to_rfc3339(),
+            "2025-12-31T23:59:59+00:00"
+        );
+        assert!(parse_cartao_line("123456|1001|||2").is_err());
+
+        let row = parse_biometria_line(template("1001", 6).trim()).unwrap();
+        assert_eq!((row.posicao, row.template_data.len()), (6, 600));
+        for (line, expected) in [
+            ("1001|10|AAAA", "Invalid POSICAO"),
+            ("1001|1|not base64!", "Invalid TEMPLATE_BASE64"),
+            ("1001|1|AAAA", "Template has 3 bytes"),
+        ] {
+            let error = parse_biometria_line(line).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", line, error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_reports_cross_file_errors() {
+        let db = Database::in_memory().await.unwrap();
+        let cartoes = "\
+11111111|MIG001|||1
+11111111|MIG002|||1
+22222222|NOBODY|||1
+00000000000022823433|MIG002|||1
+";
+        let biometria = format!(
+            "{}{}{}",
+            template("MIG001", 1),
+            template("MIG002", 1),
+            template("MIG001", 1)
+        );
+        let plan = export(cartoes, &biometria).plan(db.pool()).await.unwrap();
+
+        let errors: Vec<(&str, u64)> = plan
+            .errors
+            .iter()
+            .map(|e| (e.file.as_str(), e.line))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (CARTOES_FILE, 2),
+                (CARTOES_FILE, 3),
+                (BIOMETRIA_FILE, 2),
+                (BIOMETRIA_FILE, 3)
+            ]
+        );
+        assert!(plan.errors[1].message.contains("missing user 'NOBODY'"));
+        assert!(plan.errors[2].message.contains("ALLOW_BIO"));
+
+        // Seeded card of user 1002, moved to a new user
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].message.contains("from user '1002'"));
+        assert_eq!(plan.users, PlannedRows { new: 2, updated: 0 });
+        assert_eq!(plan.cards, PlannedRows { new: 1, updated: 1 });
+
+        let result = export(cartoes, &biometria).import(db.pool(), |_| {}).await;
+        assert!(matches!(result, Err(StorageError::Validation(_))));
+        let cards = SqliteCardRepository::new(db.pool().clone());
+        assert!(cards.find_by_number("11111111").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_writes_everything_in_order() {
+        let db = Database::in_memory().await.unwrap();
+        let cartoes = "11111111|MIG001|||1\n33333333|1001|||0\n";
+        let export = export(cartoes, &template("MIG001", 0));
+
+        let mut progress = Vec::new();
+        let plan = export
+            .import(db.pool(), |p| progress.push((p.stage, p.done, p.total)))
+            .await
+            .unwrap();
+        assert!(plan.is_clean());
+        assert_eq!(
+            progress,
+            vec![
+                (MigrationStage::Users, 2, 2),
+                (MigrationStage::Cards, 2, 2),
+                (MigrationStage::Templates, 1, 1)
+            ]
+        );
+
+        let cards = SqliteCardRepository::new(db.pool().clone());
+        let card = cards.find_by_number("11111111").await.unwrap().unwrap();
+        assert_eq!(card.matricula, "MIG001");
+        assert!(
+            !cards
+                .find_by_number("33333333")
+                .await
+                .unwrap()
+                .unwrap()
+                .ativo
+        );
+
+        // Running it again only updates
+        let again = export.plan(db.pool()).await.unwrap();
+        assert_eq!(again.users, PlannedRows { new: 0, updated: 2 });
+        assert_eq!(again.templates, PlannedRows { new: 0, updated: 1 });
+    }
+}
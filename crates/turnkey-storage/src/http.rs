@@ -0,0 +1,502 @@
+//! Validation against a REST back-end
+//!
+//! Some access control back-ends expose an HTTP API instead of the Henry
+//! TCP protocol. [`HttpValidator`] sends each access request to such an
+//! endpoint and turns the reply into an [`AccessResponse`], so it can stand
+//! in for an [`OnlineValidator`](crate::OnlineValidator) anywhere an
+//! [`AccessValidator`] is expected.
+//!
+//! # Wire Format
+//!
+//! Each request is a `POST` to [`HttpValidatorConfig::endpoint`] with the
+//! serde JSON of the [`AccessRequest`] plus the device ID:
+//!
+//! ```json
+//! {
+//!   "device_id": 15,
+//!   "card_number": "00000000000022823433",
+//!   "timestamp": "2025-10-27T14:30:00-03:00",
+//!   "direction": "Entry",
+//!   "reader_type": "Rfid"
+//! }
+//! ```
+//!
+//! A `2xx` reply carries the serde JSON of the [`AccessResponse`]:
+//!
+//! ```json
+//! { "decision": "GrantEntry", "timeout_seconds": 5, "display_message": "Bem-vindo" }
+//! ```
+//!
+//! Display messages longer than the display are truncated, as for
+//! responses received over TCP.
+//!
+//! # Errors and Retries
+//!
+//! | Failure | Error | Retried |
+//! |---------|-------|---------|
+//! | Connection refused, timeout, broken transfer | `NetworkError` | yes |
+//! | HTTP `5xx` or `429` | `NetworkError` | yes |
+//! | Other non-`2xx` status | `ProtocolError` | no |
+//! | Reply is not a valid response JSON | `ProtocolError` | no |
+//!
+//! Retries wait [`HttpValidatorConfig::retry_delay`], doubled after each
+//! attempt up to [`HttpValidatorConfig::max_retry_delay`]. When the request
+//! still fails and `fallback_to_offline` is set, the offline validator
+//! decides, exactly as with [`OnlineValidator`](crate::OnlineValidator).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::http::{HttpValidator, HttpValidatorConfig};
+//! use turnkey_storage::OfflineValidator;
+//! use turnkey_core::DeviceId;
+//!
+//! # fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let config = HttpValidatorConfig {
+//!     fallback_to_offline: true,
+//!     ..HttpValidatorConfig::new("http://192.168.0.100:8080/api/access")
+//! };
+//! let validator = HttpValidator::new(DeviceId::new(15)?, config)?
+//!     .with_offline_fallback(OfflineValidator::new(pool));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::validator::{AccessValidator, OfflineValidator, validation_span};
+use reqwest::{Client, StatusCode, Url};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::Instrument;
+use turnkey_core::DeviceId;
+use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
+
+/// Endpoint, timeout and retry policy of an [`HttpValidator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpValidatorConfig {
+    /// URL the requests are posted to (`http` or `https`)
+    pub endpoint: String,
+
+    /// Time allowed for one attempt, connection included (default: 3s)
+    pub timeout: Duration,
+
+    /// Retry attempts after the first failure (default: 2)
+    pub max_retries: usize,
+
+    /// Wait before the first retry, doubled for each next one (default: 200ms)
+    pub retry_delay: Duration,
+
+    /// Upper bound of the wait between retries (default: 2s)
+    pub max_retry_delay: Duration,
+
+    /// Validate offline when the endpoint cannot answer (default: false)
+    pub fallback_to_offline: bool,
+}
+
+impl HttpValidatorConfig {
+    /// Default policy for `endpoint`
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            timeout: Duration::from_secs(3),
+            max_retries: 2,
+            retry_delay: Duration::from_millis(200),
+            max_retry_delay: Duration::from_secs(2),
+            fallback_to_offline: false,
+        }
+    }
+
+    /// Wait before retry number `retry` (0-based)
+    fn backoff(&self, retry: usize) -> Duration {
+        let factor = 1u32.checked_shl(retry as u32).unwrap_or(u32::MAX);
+        self.retry_delay
+            .saturating_mul(factor)
+            .min(self.max_retry_delay)
+    }
+}
+
+/// Body of the request posted to the endpoint
+#[derive(Serialize)]
+struct HttpAccessRequest<'a> {
+    device_id: u8,
+    #[serde(flatten)]
+    request: &'a AccessRequest,
+}
+
+/// Validator posting access requests to a REST endpoint
+///
+/// See the [module documentation](self) for the wire format and retry
+/// policy.
+pub struct HttpValidator {
+    client: Client,
+    endpoint: Url,
+    device_id: DeviceId,
+    config: HttpValidatorConfig,
+    offline_fallback: Option<OfflineValidator>,
+}
+
+impl std::fmt::Debug for HttpValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpValidator")
+            .field("device_id", &self.device_id)
+            .field("config", &self.config)
+            .field("has_offline_fallback", &self.offline_fallback.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl HttpValidator {
+    /// Create a validator for `device_id` without offline fallback
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if the endpoint is not an `http` or `https`
+    /// URL, or the HTTP client cannot be created.
+    pub fn new(device_id: DeviceId, config: HttpValidatorConfig) -> StorageResult<Self> {
+        let endpoint = Url::parse(&config.endpoint).map_err(|e| {
+            StorageError::Configuration(format!(
+                "Invalid HTTP validator endpoint '{}': {}",
+                config.endpoint, e
+            ))
+        })?;
+        if !matches!(endpoint.scheme(), "http" | "https") {
+            return Err(StorageError::Configuration(format!(
+                "HTTP validator endpoint must use http or https: {}",
+                config.endpoint
+            )));
+        }
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| {
+                StorageError::Configuration(format!("Failed to create HTTP client: {}", e))
+            })?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            device_id,
+            config,
+            offline_fallback: None,
+        })
+    }
+
+    /// Fall back to an offline validator when the endpoint cannot answer
+    ///
+    /// Only used when `config.fallback_to_offline` is set, as for
+    /// [`OnlineValidator::with_offline_fallback()`](crate::OnlineValidator::with_offline_fallback).
+    pub fn with_offline_fallback(mut self, offline_validator: OfflineValidator) -> Self {
+        self.offline_fallback = Some(offline_validator);
+        self
+    }
+
+    /// Attempt validation with exponential backoff between retries
+    ///
+    /// Only network failures are retried; a reply the validator cannot use
+    /// will not improve by asking again.
+    async fn validate_with_retry(
+        &mut self,
+        request: &AccessRequest,
+    ) -> StorageResult<AccessResponse> {
+        let mut retry = 0;
+        let error = loop {
+            match self.validate_once(request).await {
+                Ok(response) => return Ok(response),
+                Err(e @ StorageError::NetworkError(_)) if retry < self.config.max_retries => {
+                    tracing::debug!(error = %e, retry, "HTTP validation failed, retrying");
+                    tokio::time::sleep(self.config.backoff(retry)).await;
+                    retry += 1;
+                }
+                Err(e) => break e,
+            }
+        };
+
+        if self.config.fallback_to_offline
+            && let Some(ref mut offline) = self.offline_fallback
+        {
+            tracing::warn!(error = %error, "HTTP validation failed, validating offline");
+            return offline.validate(request).await;
+        }
+
+        match error {
+            StorageError::NetworkError(e) => Err(StorageError::ValidationFailed(retry, e)),
+            other => Err(other),
+        }
+    }
+
+    /// Single request to the endpoint
+    async fn validate_once(&self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        let body = HttpAccessRequest {
+            device_id: self.device_id.as_u8(),
+            request,
+        };
+        let reply = self
+            .client
+            .post(self.endpoint.clone())
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| StorageError::NetworkError(format!("HTTP request failed: {}", e)))?;
+
+        let status = reply.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(StorageError::NetworkError(format!(
+                "HTTP validator endpoint answered {}",
+                status
+            )));
+        }
+        if !status.is_success() {
+            return Err(StorageError::ProtocolError(format!(
+                "HTTP validator endpoint answered {}",
+                status
+            )));
+        }
+
+        let bytes = reply
+            .bytes()
+            .await
+            .map_err(|e| StorageError::NetworkError(format!("HTTP reply failed: {}", e)))?;
+        let response: AccessResponse = serde_json::from_slice(&bytes)
+            .map_err(|e| StorageError::ProtocolError(format!("Invalid HTTP reply: {}", e)))?;
+
+        // Deserializing bypasses the constructor, which enforces the display limit
+        Ok(AccessResponse::new(
+            response.decision(),
+            response.timeout_seconds(),
+            response.display_message().to_string(),
+        ))
+    }
+}
+
+impl AccessValidator for HttpValidator {
+    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        self.validate_with_retry(request)
+            .instrument(validation_span("http", request))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
+    use turnkey_protocol::commands::access::AccessDecision;
+
+    /// Seeded card of user 1002
+    const CARD: &str = "00000000000022823433";
+
+    fn request(card: &str) -> AccessRequest {
+        AccessRequest::new(
+            card.to_string(),
+            HenryTimestamp::now(),
+            AccessDirection::Entry,
+            ReaderType::Rfid,
+        )
+        .unwrap()
+    }
+
+    fn config(endpoint: String) -> HttpValidatorConfig {
+        HttpValidatorConfig {
+            timeout: Duration::from_millis(500),
+            retry_delay: Duration::from_millis(10),
+            ..HttpValidatorConfig::new(endpoint)
+        }
+    }
+
+    fn validator(endpoint: String) -> HttpValidator {
+        HttpValidator::new(DeviceId::new(15).unwrap(), config(endpoint)).unwrap()
+    }
+
+    /// Endpoint answering with `replies` in turn (the last one repeats)
+    ///
+    /// Returns the endpoint URL, the number of requests received and the
+    /// body of the last one.
+    async fn endpoint(
+        replies: Vec<(u16, &'static str)>,
+    ) -> (String, Arc<AtomicUsize>, Arc<std::sync::Mutex<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/access", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let last_body = Arc::new(std::sync::Mutex::new(String::new()));
+
+        let (counter, body_slot) = (hits.clone(), last_body.clone());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let hit = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, reply) = replies[hit.min(replies.len() - 1)];
+
+                let mut raw = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    raw.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&raw);
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|l| {
+                                l.to_ascii_lowercase()
+                                    .strip_prefix("content-length: ")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if raw.len() >= end + 4 + length {
+                            *body_slot.lock().unwrap() = text[end + 4..].to_string();
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+
+                let answer = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+                let _ = stream.write_all(answer.as_bytes()).await;
+            }
+        });
+        (url, hits, last_body)
+    }
+
+    #[tokio::test]
+    async fn test_posts_request_and_parses_response() {
+        let (url, hits, body) = endpoint(vec![(
+            200,
+            r#"{"decision":"GrantEntry","timeout_seconds":5,"display_message":"Bem-vindo"}"#,
+        )])
+        .await;
+
+        let response = validator(url).validate(&request(CARD)).await.unwrap();
+
+        assert_eq!(response.decision(), AccessDecision::GrantEntry);
+        assert_eq!(response.timeout_seconds(), 5);
+        assert_eq!(response.display_message(), "Bem-vindo");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let sent: serde_json::Value = serde_json::from_str(&body.lock().unwrap()).unwrap();
+        assert_eq!(sent["device_id"], 15);
+        assert_eq!(sent["card_number"], CARD);
+        assert_eq!(sent["direction"], "Entry");
+        assert_eq!(sent["reader_type"], "Rfid");
+    }
+
+    #[tokio::test]
+    async fn test_long_message_truncated() {
+        let (url, _, _) = endpoint(vec![(
+            200,
+            r#"{"decision":"Deny","timeout_seconds":0,"display_message":"Dirija-se a recepcao do bloco B para regularizar"}"#,
+        )])
+        .await;
+
+        let response = validator(url).validate(&request(CARD)).await.unwrap();
+
+        assert!(response.is_deny());
+        assert_eq!(response.display_message().chars().count(), 40);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_retried_then_answered() {
+        let (url, hits, _) = endpoint(vec![
+            (503, ""),
+            (500, ""),
+            (
+                200,
+                r#"{"decision":"GrantBoth","timeout_seconds":3,"display_message":"Ok"}"#,
+            ),
+        ])
+        .await;
+
+        let response = validator(url).validate(&request(CARD)).await.unwrap();
+
+        assert!(response.is_grant());
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_exhaust_retries() {
+        let (url, hits, _) = endpoint(vec![(503, "")]).await;
+
+        let err = validator(url).validate(&request(CARD)).await.unwrap_err();
+
+        assert!(matches!(err, StorageError::ValidationFailed(2, _)), "{err}");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_error_and_bad_json_not_retried() {
+        let (url, hits, _) = endpoint(vec![(400, "")]).await;
+        let err = validator(url).validate(&request(CARD)).await.unwrap_err();
+        assert!(matches!(err, StorageError::ProtocolError(_)), "{err}");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let (url, hits, _) = endpoint(vec![(200, r#"{"decision":"Maybe"}"#)]).await;
+        let err = validator(url).validate(&request(CARD)).await.unwrap_err();
+        assert!(matches!(err, StorageError::ProtocolError(_)), "{err}");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_endpoint_falls_back_offline() {
+        // Bind then drop, so the port refuses connections
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/access", listener.local_addr().unwrap());
+        drop(listener);
+
+        let err = validator(url.clone())
+            .validate(&request(CARD))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::ValidationFailed(..)), "{err}");
+
+        let db = Database::in_memory().await.unwrap();
+        let config = HttpValidatorConfig {
+            fallback_to_offline: true,
+            ..config(url)
+        };
+        let mut validator = HttpValidator::new(DeviceId::new(15).unwrap(), config)
+            .unwrap()
+            .with_offline_fallback(OfflineValidator::new(db.pool().clone()));
+
+        assert!(validator.validate(&request(CARD)).await.unwrap().is_grant());
+        assert!(
+            validator
+                .validate(&request("99999999"))
+                .await
+                .unwrap()
+                .is_deny()
+        );
+    }
+
+    #[test]
+    fn test_invalid_endpoint_rejected() {
+        let device_id = DeviceId::new(15).unwrap();
+        for endpoint in ["not a url", "ftp://example.com/access"] {
+            let err =
+                HttpValidator::new(device_id, HttpValidatorConfig::new(endpoint)).unwrap_err();
+            assert!(matches!(err, StorageError::Configuration(_)), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_limit() {
+        let config = HttpValidatorConfig {
+            retry_delay: Duration::from_millis(200),
+            max_retry_delay: Duration::from_millis(700),
+            ..HttpValidatorConfig::new("http://localhost/access")
+        };
+
+        assert_eq!(config.backoff(0), Duration::from_millis(200));
+        assert_eq!(config.backoff(1), Duration::from_millis(400));
+        assert_eq!(config.backoff(2), Duration::from_millis(700));
+        assert_eq!(config.backoff(64), Duration::from_millis(700));
+    }
+}
@@ -0,0 +1,601 @@
+//! Latency-aware switching between server and local validation
+//!
+//! [`ValidationModeController`](crate::mode::ValidationModeController)
+//! only leaves the server when it is unreachable. A server that answers
+//! slowly is just as harmful at a busy turnstile: every passage waits for
+//! the slowest reply. [`HybridValidator`] measures each online validation
+//! and keeps a rolling window of latencies. When the 95th percentile rises
+//! above [`HybridConfig::degrade_threshold`], requests go to the local
+//! database instead.
+//!
+//! While local, one request every [`HybridConfig::probe_interval`] is still
+//! sent to the server as a probe. The validator returns online after
+//! [`HybridConfig::recovery_probes`] consecutive probes answered below
+//! [`HybridConfig::recover_threshold`]. The gap between the two thresholds
+//! keeps the device from flapping around a single value.
+//!
+//! Network failures fall back to local validation for the failing request
+//! and switch the device offline right away; recovery goes through the same
+//! probes.
+//!
+//! Every switch emits a [`ModeChange`] (mode stays `Hybrid`, only `online`
+//! changes), and [`HybridValidator::metrics()`] reports counters and the
+//! current p95.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::hybrid::{HybridConfig, HybridValidator};
+//! use turnkey_storage::{OfflineValidator, OnlineValidator, OnlineValidatorConfig};
+//! use turnkey_network::{TcpClient, TcpClientConfig};
+//! use turnkey_core::DeviceId;
+//! use std::time::Duration;
+//!
+//! # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let device_id = DeviceId::new(15)?;
+//! let online = OnlineValidator::new(
+//!     TcpClient::new(TcpClientConfig::default()),
+//!     device_id,
+//!     OnlineValidatorConfig::default(),
+//! );
+//! let offline = OfflineValidator::new(pool).with_device_id(device_id);
+//!
+//! let config = HybridConfig {
+//!     degrade_threshold: Duration::from_millis(500),
+//!     recover_threshold: Duration::from_millis(200),
+//!     ..Default::default()
+//! };
+//! let validator = HybridValidator::new(device_id, online, offline, config);
+//!
+//! let metrics = validator.metrics();
+//! println!("online: {}, p95: {:?}", metrics.online, metrics.p95_latency);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::mode::{ModeChange, ModeChangeReason, OperatingMode};
+use crate::validator::{AccessValidator, OfflineValidator, OnlineValidator};
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use turnkey_core::DeviceId;
+use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
+
+/// Thresholds and window sizes of a [`HybridValidator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HybridConfig {
+    /// Number of online latencies kept in the rolling window (default: 50)
+    pub window: usize,
+
+    /// Samples required before the p95 is trusted (default: 20)
+    pub min_samples: usize,
+
+    /// Switch offline when the p95 rises above this (default: 800ms)
+    pub degrade_threshold: Duration,
+
+    /// Probe latency that counts as healthy while offline (default: 400ms)
+    ///
+    /// Must not exceed `degrade_threshold`.
+    pub recover_threshold: Duration,
+
+    /// Minimum time between probes while offline (default: 30s)
+    pub probe_interval: Duration,
+
+    /// Consecutive healthy probes needed to return online (default: 3)
+    pub recovery_probes: u32,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        Self {
+            window: 50,
+            min_samples: 20,
+            degrade_threshold: Duration::from_millis(800),
+            recover_threshold: Duration::from_millis(400),
+            probe_interval: Duration::from_secs(30),
+            recovery_probes: 3,
+        }
+    }
+}
+
+impl HybridConfig {
+    /// Check that the thresholds leave room for hysteresis
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if a window or probe count is zero or
+    /// `recover_threshold` is above `degrade_threshold`.
+    pub fn validate(&self) -> StorageResult<()> {
+        if self.window == 0 || self.min_samples == 0 || self.recovery_probes == 0 {
+            return Err(StorageError::Configuration(
+                "Hybrid window, min_samples and recovery_probes must be positive".to_string(),
+            ));
+        }
+        if self.min_samples > self.window {
+            return Err(StorageError::Configuration(format!(
+                "Hybrid min_samples ({}) exceeds window ({})",
+                self.min_samples, self.window
+            )));
+        }
+        if self.recover_threshold > self.degrade_threshold {
+            return Err(StorageError::Configuration(format!(
+                "Hybrid recover_threshold ({:?}) exceeds degrade_threshold ({:?})",
+                self.recover_threshold, self.degrade_threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Counters and latency of a [`HybridValidator`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HybridMetrics {
+    /// Whether requests currently go to the server
+    pub online: bool,
+
+    /// Requests answered by the server (probes included)
+    pub online_requests: u64,
+
+    /// Requests answered by the local database
+    pub offline_requests: u64,
+
+    /// Probes sent while offline
+    pub probes: u64,
+
+    /// Requests whose online validation failed and were answered locally
+    pub fallbacks: u64,
+
+    /// Switches from server to local validation
+    pub switches_to_offline: u64,
+
+    /// Switches from local back to server validation
+    pub switches_to_online: u64,
+
+    /// 95th percentile of the latency window (`None` below `min_samples`)
+    pub p95_latency: Option<Duration>,
+}
+
+/// Rolling window of online validation latencies
+#[derive(Debug)]
+struct LatencyWindow {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl LatencyWindow {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn record(&mut self, latency: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Nearest-rank 95th percentile
+    fn p95(&self, min_samples: usize) -> Option<Duration> {
+        if self.samples.len() < min_samples.max(1) {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (sorted.len() * 95).div_ceil(100);
+        Some(sorted[rank - 1])
+    }
+}
+
+/// Hybrid validator that leaves the server when it is slow or down
+///
+/// Generic over both validators so tests and embedders can plug in their
+/// own; the defaults are the stock [`OnlineValidator`] and
+/// [`OfflineValidator`]. See the [module documentation](self) for the
+/// switching rules.
+pub struct HybridValidator<On = OnlineValidator, Off = OfflineValidator> {
+    device_id: DeviceId,
+    online: On,
+    offline: Off,
+    config: HybridConfig,
+    is_online: bool,
+    latencies: LatencyWindow,
+    last_probe: Option<Instant>,
+    healthy_probes: u32,
+    offline_reason: Option<ModeChangeReason>,
+    metrics: HybridMetrics,
+    events: Option<mpsc::Sender<ModeChange>>,
+}
+
+impl<On, Off> fmt::Debug for HybridValidator<On, Off> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HybridValidator")
+            .field("device_id", &self.device_id)
+            .field("online", &self.is_online)
+            .field("config", &self.config)
+            .field("metrics", &self.metrics)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<On: AccessValidator, Off: AccessValidator> HybridValidator<On, Off> {
+    /// Create a hybrid validator starting online
+    ///
+    /// Call [`HybridConfig::validate()`] first for configurations coming
+    /// from user input; inconsistent thresholds are not rejected here.
+    pub fn new(device_id: DeviceId, online: On, offline: Off, config: HybridConfig) -> Self {
+        Self {
+            device_id,
+            online,
+            offline,
+            latencies: LatencyWindow::new(config.window.max(1)),
+            config,
+            is_online: true,
+            last_probe: None,
+            healthy_probes: 0,
+            offline_reason: None,
+            metrics: HybridMetrics {
+                online: true,
+                ..Default::default()
+            },
+            events: None,
+        }
+    }
+
+    /// Send every [`ModeChange`] to `events`
+    ///
+    /// Delivery is best-effort: a full or closed channel drops the event
+    /// rather than blocking validation.
+    pub fn with_mode_events(mut self, events: mpsc::Sender<ModeChange>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Whether requests currently go to the server
+    pub fn is_online(&self) -> bool {
+        self.is_online
+    }
+
+    /// Active configuration
+    pub fn config(&self) -> &HybridConfig {
+        &self.config
+    }
+
+    /// Snapshot of counters and the current p95
+    pub fn metrics(&self) -> HybridMetrics {
+        HybridMetrics {
+            p95_latency: self.latencies.p95(self.config.min_samples),
+            ..self.metrics.clone()
+        }
+    }
+
+    /// Validate on the server, measuring latency, and fall back locally on
+    /// network failures
+    async fn validate_online(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        let started = Instant::now();
+        match self.online.validate(request).await {
+            Ok(response) => {
+                self.latencies.record(started.elapsed());
+                self.metrics.online_requests += 1;
+
+                if let Some(p95) = self.latencies.p95(self.config.min_samples)
+                    && p95 > self.config.degrade_threshold
+                {
+                    self.switch(false, ModeChangeReason::LatencyDegraded);
+                }
+                Ok(response)
+            }
+            Err(StorageError::NetworkError(_) | StorageError::ValidationFailed(..)) => {
+                self.metrics.fallbacks += 1;
+                self.switch(false, ModeChangeReason::ConnectionLost);
+                self.validate_offline(request).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send a probe to the server; answer locally if it fails
+    async fn validate_probe(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        self.last_probe = Some(Instant::now());
+        self.metrics.probes += 1;
+
+        let started = Instant::now();
+        match self.online.validate(request).await {
+            Ok(response) => {
+                let latency = started.elapsed();
+                self.metrics.online_requests += 1;
+
+                if latency <= self.config.recover_threshold {
+                    self.healthy_probes += 1;
+                } else {
+                    self.healthy_probes = 0;
+                }
+                if self.healthy_probes >= self.config.recovery_probes {
+                    let reason = match self.offline_reason {
+                        Some(ModeChangeReason::ConnectionLost) => {
+                            ModeChangeReason::ConnectionRestored
+                        }
+                        _ => ModeChangeReason::LatencyRecovered,
+                    };
+                    self.latencies.record(latency);
+                    self.switch(true, reason);
+                }
+                Ok(response)
+            }
+            Err(StorageError::NetworkError(_) | StorageError::ValidationFailed(..)) => {
+                self.healthy_probes = 0;
+                self.metrics.fallbacks += 1;
+                self.validate_offline(request).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn validate_offline(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        let response = self.offline.validate(request).await?;
+        self.metrics.offline_requests += 1;
+        Ok(response)
+    }
+
+    fn probe_due(&self) -> bool {
+        self.last_probe
+            .is_none_or(|last| last.elapsed() >= self.config.probe_interval)
+    }
+
+    fn switch(&mut self, online: bool, reason: ModeChangeReason) {
+        if self.is_online == online {
+            return;
+        }
+
+        self.is_online = online;
+        self.metrics.online = online;
+        self.healthy_probes = 0;
+        self.offline_reason = (!online).then_some(reason);
+        if online {
+            self.metrics.switches_to_online += 1;
+        } else {
+            // Old samples describe the server before it degraded; the next
+            // probe is due after a full interval.
+            self.latencies.clear();
+            self.last_probe = Some(Instant::now());
+            self.metrics.switches_to_offline += 1;
+        }
+
+        let change = ModeChange {
+            device_id: self.device_id,
+            from: OperatingMode::Hybrid,
+            to: OperatingMode::Hybrid,
+            online,
+            reason,
+            changed_at: Utc::now(),
+        };
+        if let Some(events) = &self.events {
+            let _ = events.try_send(change);
+        }
+    }
+}
+
+impl<On: AccessValidator, Off: AccessValidator> AccessValidator for HybridValidator<On, Off> {
+    /// Validate on the server or locally, depending on measured health
+    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        if self.is_online {
+            self.validate_online(request).await
+        } else if self.probe_due() {
+            self.validate_probe(request).await
+        } else {
+            self.validate_offline(request).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
+
+    /// Server stand-in with adjustable delay and availability
+    struct FakeServer {
+        delay: Duration,
+        down: bool,
+    }
+
+    impl AccessValidator for FakeServer {
+        async fn validate(&mut self, _request: &AccessRequest) -> StorageResult<AccessResponse> {
+            if self.down {
+                return Err(StorageError::NetworkError("connection refused".to_string()));
+            }
+            tokio::time::sleep(self.delay).await;
+            Ok(AccessResponse::grant_entry("SERVER".to_string()))
+        }
+    }
+
+    struct FakeLocal;
+
+    impl AccessValidator for FakeLocal {
+        async fn validate(&mut self, _request: &AccessRequest) -> StorageResult<AccessResponse> {
+            Ok(AccessResponse::grant_entry("LOCAL".to_string()))
+        }
+    }
+
+    fn request() -> AccessRequest {
+        AccessRequest::new(
+            "12345678".to_string(),
+            HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            AccessDirection::Entry,
+            ReaderType::Rfid,
+        )
+        .unwrap()
+    }
+
+    fn config() -> HybridConfig {
+        HybridConfig {
+            window: 10,
+            min_samples: 5,
+            degrade_threshold: Duration::from_millis(20),
+            recover_threshold: Duration::from_millis(5),
+            probe_interval: Duration::ZERO,
+            recovery_probes: 2,
+        }
+    }
+
+    fn validator(delay_ms: u64) -> HybridValidator<FakeServer, FakeLocal> {
+        let server = FakeServer {
+            delay: Duration::from_millis(delay_ms),
+            down: false,
+        };
+        HybridValidator::new(DeviceId::new(15).unwrap(), server, FakeLocal, config())
+    }
+
+    #[test]
+    fn test_p95_nearest_rank() {
+        let mut window = LatencyWindow::new(20);
+        for ms in 1..=20 {
+            window.record(Duration::from_millis(ms));
+        }
+        assert_eq!(window.p95(5), Some(Duration::from_millis(19)));
+        assert_eq!(window.p95(21), None);
+
+        // Oldest samples are evicted
+        for _ in 0..20 {
+            window.record(Duration::from_millis(1));
+        }
+        assert_eq!(window.p95(5), Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(HybridConfig::default().validate().is_ok());
+
+        let inverted = HybridConfig {
+            recover_threshold: Duration::from_secs(2),
+            ..Default::default()
+        };
+        assert!(matches!(
+            inverted.validate(),
+            Err(StorageError::Configuration(_))
+        ));
+
+        let too_few = HybridConfig {
+            window: 5,
+            min_samples: 10,
+            ..Default::default()
+        };
+        assert!(too_few.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fast_server_stays_online() {
+        let mut validator = validator(0);
+        for _ in 0..10 {
+            let response = validator.validate(&request()).await.unwrap();
+            assert_eq!(response.display_message(), "SERVER");
+        }
+
+        let metrics = validator.metrics();
+        assert!(metrics.online);
+        assert_eq!(metrics.online_requests, 10);
+        assert_eq!(metrics.switches_to_offline, 0);
+        assert!(metrics.p95_latency.unwrap() < Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_slow_server_switches_offline_and_recovers() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut validator = validator(30).with_mode_events(tx);
+
+        // p95 is only trusted after min_samples requests
+        for _ in 0..4 {
+            validator.validate(&request()).await.unwrap();
+            assert!(validator.is_online());
+        }
+        validator.validate(&request()).await.unwrap();
+        assert!(!validator.is_online());
+
+        let degraded = rx.try_recv().unwrap();
+        assert_eq!(degraded.reason, ModeChangeReason::LatencyDegraded);
+        assert_eq!(degraded.to, OperatingMode::Hybrid);
+        assert!(!degraded.online);
+
+        // Slow probes keep the device offline
+        validator.validate(&request()).await.unwrap();
+        assert!(!validator.is_online());
+
+        // Server is healthy again: two fast probes bring it back
+        validator.online.delay = Duration::ZERO;
+        validator.validate(&request()).await.unwrap();
+        assert!(!validator.is_online());
+        validator.validate(&request()).await.unwrap();
+        assert!(validator.is_online());
+
+        let recovered = rx.try_recv().unwrap();
+        assert_eq!(recovered.reason, ModeChangeReason::LatencyRecovered);
+        assert!(recovered.online);
+
+        let metrics = validator.metrics();
+        assert_eq!(metrics.switches_to_offline, 1);
+        assert_eq!(metrics.switches_to_online, 1);
+        assert_eq!(metrics.probes, 3);
+    }
+
+    #[tokio::test]
+    async fn test_probes_respect_interval() {
+        let mut validator = validator(30);
+        validator.config.probe_interval = Duration::from_secs(3600);
+        for _ in 0..5 {
+            validator.validate(&request()).await.unwrap();
+        }
+        assert!(!validator.is_online());
+
+        // No probe is due yet: everything is answered locally
+        validator.online.delay = Duration::ZERO;
+        for _ in 0..5 {
+            let response = validator.validate(&request()).await.unwrap();
+            assert_eq!(response.display_message(), "LOCAL");
+        }
+
+        let metrics = validator.metrics();
+        assert_eq!(metrics.probes, 0);
+        assert_eq!(metrics.offline_requests, 5);
+    }
+
+    #[tokio::test]
+    async fn test_network_failure_falls_back_immediately() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut validator = validator(0).with_mode_events(tx);
+        validator.online.down = true;
+
+        let response = validator.validate(&request()).await.unwrap();
+        assert_eq!(response.display_message(), "LOCAL");
+        assert!(!validator.is_online());
+        assert_eq!(
+            rx.try_recv().unwrap().reason,
+            ModeChangeReason::ConnectionLost
+        );
+
+        // Failed probes are answered locally and do not count as healthy
+        let response = validator.validate(&request()).await.unwrap();
+        assert_eq!(response.display_message(), "LOCAL");
+
+        validator.online.down = false;
+        validator.validate(&request()).await.unwrap();
+        validator.validate(&request()).await.unwrap();
+        assert!(validator.is_online());
+        assert_eq!(
+            rx.try_recv().unwrap().reason,
+            ModeChangeReason::ConnectionRestored
+        );
+
+        let metrics = validator.metrics();
+        assert_eq!(metrics.fallbacks, 2);
+        assert_eq!(metrics.offline_requests, 2);
+    }
+}
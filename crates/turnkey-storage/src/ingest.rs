@@ -0,0 +1,833 @@
+//! Streaming ingestion of historical access logs from CSV files.
+//!
+//! Sites migrating from legacy controllers usually bring years of access
+//! history exported as CSV, with column names and value conventions that
+//! differ from vendor to vendor. [`CsvLogIngester`] reads such a file record
+//! by record, maps the configured columns onto [`AccessLog`] fields and
+//! writes them in batches, one transaction per batch, so arbitrarily large
+//! files are ingested with bounded memory.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::ingest::{CsvIngestOptions, CsvLogIngester, CsvLogMapping};
+//! use std::fs::File;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! let mapping = CsvLogMapping::new("Cartao", "DataHora")
+//!     .direction("Sentido")
+//!     .granted("Resultado")
+//!     .device_id("Catraca");
+//! let options = CsvIngestOptions::new(mapping)
+//!     .delimiter(b';')
+//!     .timestamp_format("%d/%m/%Y %H:%M:%S");
+//!
+//! let ingester = CsvLogIngester::new(db.pool().clone(), options);
+//! let report = ingester
+//!     .ingest(File::open("legacy.csv")?, |progress| {
+//!         println!("{} rows, {} inserted", progress.rows_read, progress.inserted);
+//!     })
+//!     .await?;
+//! println!(
+//!     "{} inserted, {} duplicates, {} rejected",
+//!     report.inserted, report.duplicates, report.rejected
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Row Handling
+//!
+//! - **Users**: resolved by the matricula column when mapped, otherwise by
+//!   the card number. Unknown users are stored as unidentified (NULL).
+//! - **Duplicates**: a row with the same card number, timestamp and device
+//!   as an existing log (or an earlier row of the file) is skipped, so an
+//!   interrupted import can simply be run again.
+//! - **Rejected rows**: rows with a missing card number or a value that does
+//!   not parse are skipped and listed in the report; they never abort the
+//!   import.
+//!
+//! # Derived State
+//!
+//! The `access_state` trigger folds logs in insertion order. Historical logs
+//! are usually older than the live ones already stored, so run
+//! [`AccessStateReplay::rebuild`](crate::replay::AccessStateReplay::rebuild)
+//! after ingesting into a database that is already in use.
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{AccessLog, Card, Direction, ReaderType};
+use crate::transaction;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use std::io::Read;
+
+/// Default number of rows written per transaction
+pub const DEFAULT_INGEST_BATCH_SIZE: usize = 500;
+
+/// Maximum number of row errors kept in the report
+///
+/// Further errors are still counted in `rejected`.
+pub const MAX_REPORTED_ROW_ERRORS: usize = 100;
+
+/// Naive timestamp formats tried when no explicit format is configured
+const FALLBACK_TIMESTAMP_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%d/%m/%Y %H:%M:%S",
+    "%d/%m/%Y %H:%M",
+];
+
+/// Column reference, by header name or zero-based position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    /// Header name (exact match after trimming)
+    Name(String),
+    /// Zero-based column position
+    Index(usize),
+}
+
+impl From<&str> for CsvColumn {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_string())
+    }
+}
+
+impl From<String> for CsvColumn {
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl From<usize> for CsvColumn {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+/// Mapping of CSV columns to access log fields
+///
+/// Card number and timestamp are required; every other field is optional:
+///
+/// | Field             | Accepted values (case-insensitive)                 | Unmapped  |
+/// |-------------------|----------------------------------------------------|-----------|
+/// | `direction`       | `1`/`E`/`ENTRADA`/`ENTRY`/`IN`, `2`/`S`/`SAIDA`/`EXIT`/`OUT`, `0` | Undefined |
+/// | `granted`         | `1`/`S`/`SIM`/`TRUE`/`LIBERADO`, `0`/`N`/`NAO`/`FALSE`/`NEGADO` | granted   |
+/// | `reader_type`     | `1`/`RFID`/`CARTAO`, `5`/`BIO`/`BIOMETRIA`         | RFID      |
+/// | `device_id`       | `1`-`99`                                           | NULL      |
+/// | `matricula`       | Any; used to resolve the user                      | by card   |
+/// | `display_message` | Any                                                | NULL      |
+///
+/// Empty optional cells are treated as unmapped.
+#[derive(Debug, Clone)]
+pub struct CsvLogMapping {
+    card_number: CsvColumn,
+    timestamp: CsvColumn,
+    direction: Option<CsvColumn>,
+    granted: Option<CsvColumn>,
+    reader_type: Option<CsvColumn>,
+    device_id: Option<CsvColumn>,
+    matricula: Option<CsvColumn>,
+    display_message: Option<CsvColumn>,
+}
+
+impl CsvLogMapping {
+    /// Map the two required columns
+    pub fn new(card_number: impl Into<CsvColumn>, timestamp: impl Into<CsvColumn>) -> Self {
+        Self {
+            card_number: card_number.into(),
+            timestamp: timestamp.into(),
+            direction: None,
+            granted: None,
+            reader_type: None,
+            device_id: None,
+            matricula: None,
+            display_message: None,
+        }
+    }
+
+    /// Column holding the access direction
+    pub fn direction(mut self, column: impl Into<CsvColumn>) -> Self {
+        self.direction = Some(column.into());
+        self
+    }
+
+    /// Column holding the grant/deny outcome
+    pub fn granted(mut self, column: impl Into<CsvColumn>) -> Self {
+        self.granted = Some(column.into());
+        self
+    }
+
+    /// Column holding the reader type
+    pub fn reader_type(mut self, column: impl Into<CsvColumn>) -> Self {
+        self.reader_type = Some(column.into());
+        self
+    }
+
+    /// Column holding the Henry device ID
+    pub fn device_id(mut self, column: impl Into<CsvColumn>) -> Self {
+        self.device_id = Some(column.into());
+        self
+    }
+
+    /// Column holding the user's matricula
+    pub fn matricula(mut self, column: impl Into<CsvColumn>) -> Self {
+        self.matricula = Some(column.into());
+        self
+    }
+
+    /// Column holding the message shown on the device
+    pub fn display_message(mut self, column: impl Into<CsvColumn>) -> Self {
+        self.display_message = Some(column.into());
+        self
+    }
+}
+
+/// CSV dialect and ingestion settings
+#[derive(Debug, Clone)]
+pub struct CsvIngestOptions {
+    /// Column mapping
+    pub mapping: CsvLogMapping,
+
+    /// Field delimiter (default `,`)
+    pub delimiter: u8,
+
+    /// Whether the first record is a header row (default `true`)
+    pub has_headers: bool,
+
+    /// chrono format for timestamps without an offset
+    ///
+    /// When unset, RFC 3339 and a few common formats are tried.
+    pub timestamp_format: Option<String>,
+
+    /// Offset of timestamps that carry none (default UTC)
+    pub utc_offset: FixedOffset,
+
+    /// Rows written per transaction (default [`DEFAULT_INGEST_BATCH_SIZE`])
+    pub batch_size: usize,
+}
+
+impl CsvIngestOptions {
+    /// Default options for the given mapping
+    pub fn new(mapping: CsvLogMapping) -> Self {
+        Self {
+            mapping,
+            delimiter: b',',
+            has_headers: true,
+            timestamp_format: None,
+            utc_offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+            batch_size: DEFAULT_INGEST_BATCH_SIZE,
+        }
+    }
+
+    /// Set the field delimiter
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set whether the file starts with a header row
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Parse timestamps with a fixed chrono format
+    pub fn timestamp_format(mut self, format: impl Into<String>) -> Self {
+        self.timestamp_format = Some(format.into());
+        self
+    }
+
+    /// Interpret timestamps without an offset in this offset
+    pub fn utc_offset(mut self, offset: FixedOffset) -> Self {
+        self.utc_offset = offset;
+        self
+    }
+
+    /// Set the number of rows per transaction (minimum 1)
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+/// Progress of a running ingestion, reported after every batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestProgress {
+    /// Data rows read so far (header excluded)
+    pub rows_read: u64,
+
+    /// Rows written to `access_logs`
+    pub inserted: u64,
+
+    /// Rows skipped as duplicates
+    pub duplicates: u64,
+
+    /// Rows skipped because they could not be parsed
+    pub rejected: u64,
+}
+
+/// Why a row was rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowError {
+    /// One-based line number in the file
+    pub line: u64,
+
+    /// Description of the problem
+    pub message: String,
+}
+
+/// Summary of a finished ingestion
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestReport {
+    /// Data rows read (header excluded)
+    pub rows_read: u64,
+
+    /// Rows written to `access_logs`
+    pub inserted: u64,
+
+    /// Rows skipped as duplicates
+    pub duplicates: u64,
+
+    /// Rows skipped because they could not be parsed
+    pub rejected: u64,
+
+    /// First [`MAX_REPORTED_ROW_ERRORS`] rejected rows
+    pub errors: Vec<RowError>,
+
+    /// Earliest timestamp among inserted rows
+    pub first_timestamp: Option<DateTime<Utc>>,
+
+    /// Latest timestamp among inserted rows
+    pub last_timestamp: Option<DateTime<Utc>>,
+}
+
+impl IngestReport {
+    /// Check if every row was inserted or recognized as a duplicate
+    pub fn is_clean(&self) -> bool {
+        self.rejected == 0
+    }
+
+    fn progress(&self) -> IngestProgress {
+        IngestProgress {
+            rows_read: self.rows_read,
+            inserted: self.inserted,
+            duplicates: self.duplicates,
+            rejected: self.rejected,
+        }
+    }
+
+    fn reject(&mut self, line: u64, message: String) {
+        self.rejected += 1;
+        if self.errors.len() < MAX_REPORTED_ROW_ERRORS {
+            self.errors.push(RowError { line, message });
+        }
+    }
+}
+
+/// Column positions resolved against the header row
+struct ResolvedMapping {
+    card_number: usize,
+    timestamp: usize,
+    direction: Option<usize>,
+    granted: Option<usize>,
+    reader_type: Option<usize>,
+    device_id: Option<usize>,
+    matricula: Option<usize>,
+    display_message: Option<usize>,
+}
+
+/// Parsed row waiting to be written
+struct PendingRow {
+    line: u64,
+    log: AccessLog,
+    matricula: Option<String>,
+}
+
+/// Imports access logs from legacy CSV exports
+#[derive(Debug, Clone)]
+pub struct CsvLogIngester {
+    pool: SqlitePool,
+    options: CsvIngestOptions,
+}
+
+impl CsvLogIngester {
+    /// Create an ingester for the given database pool
+    pub fn new(pool: SqlitePool, options: CsvIngestOptions) -> Self {
+        Self { pool, options }
+    }
+
+    /// Stream `reader` into `access_logs`
+    ///
+    /// Calls `on_progress` after every committed batch. Batches already
+    /// committed stay in the database if a later batch fails; running the
+    /// import again skips them as duplicates.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if a mapped column is missing from the header,
+    /// `Internal` on I/O errors and `Database` if a batch cannot be written.
+    pub async fn ingest<R: Read>(
+        &self,
+        reader: R,
+        mut on_progress: impl FnMut(IngestProgress),
+    ) -> StorageResult<IngestReport> {
+        let mut csv = csv::ReaderBuilder::new()
+            .delimiter(self.options.delimiter)
+            .has_headers(self.options.has_headers)
+            .flexible(true)
+            .from_reader(reader);
+
+        let headers = if self.options.has_headers {
+            Some(
+                csv.headers()
+                    .map_err(|e| StorageError::Validation(format!("Invalid CSV header: {}", e)))?
+                    .clone(),
+            )
+        } else {
+            None
+        };
+        let mapping = self.resolve(headers.as_ref())?;
+
+        let mut report = IngestReport::default();
+        let mut batch = Vec::with_capacity(self.options.batch_size);
+
+        for record in csv.records() {
+            report.rows_read += 1;
+            let record = match record {
+                Ok(record) => record,
+                Err(e) if e.is_io_error() => {
+                    return Err(StorageError::Internal(format!("Failed to read CSV: {}", e)));
+                }
+                Err(e) => {
+                    let line = e.position().map_or(0, |p| p.line());
+                    report.reject(line, e.to_string());
+                    continue;
+                }
+            };
+
+            let line = record.position().map_or(0, |p| p.line());
+            match self.parse_row(&mapping, &record) {
+                Ok((log, matricula)) => batch.push(PendingRow {
+                    line,
+                    log,
+                    matricula,
+                }),
+                Err(message) => report.reject(line, message),
+            }
+
+            if batch.len() >= self.options.batch_size {
+                self.write_batch(&mut batch, &mut report).await?;
+                on_progress(report.progress());
+            }
+        }
+
+        if !batch.is_empty() {
+            self.write_batch(&mut batch, &mut report).await?;
+        }
+        on_progress(report.progress());
+
+        Ok(report)
+    }
+
+    /// Turn column references into positions
+    fn resolve(&self, headers: Option<&csv::StringRecord>) -> StorageResult<ResolvedMapping> {
+        let position = |column: &CsvColumn| -> StorageResult<usize> {
+            match (column, headers) {
+                (CsvColumn::Index(index), _) => Ok(*index),
+                (CsvColumn::Name(name), Some(headers)) => headers
+                    .iter()
+                    .position(|header| header.trim() == name)
+                    .ok_or_else(|| {
+                        StorageError::Validation(format!("CSV has no column named '{}'", name))
+                    }),
+                (CsvColumn::Name(name), None) => Err(StorageError::Validation(format!(
+                    "Column '{}' referenced by name but the CSV has no header row",
+                    name
+                ))),
+            }
+        };
+        let optional = |column: &Option<CsvColumn>| column.as_ref().map(position).transpose();
+
+        let mapping = &self.options.mapping;
+        Ok(ResolvedMapping {
+            card_number: position(&mapping.card_number)?,
+            timestamp: position(&mapping.timestamp)?,
+            direction: optional(&mapping.direction)?,
+            granted: optional(&mapping.granted)?,
+            reader_type: optional(&mapping.reader_type)?,
+            device_id: optional(&mapping.device_id)?,
+            matricula: optional(&mapping.matricula)?,
+            display_message: optional(&mapping.display_message)?,
+        })
+    }
+
+    /// Parse one record into a log (user not resolved yet) and its matricula
+    fn parse_row(
+        &self,
+        mapping: &ResolvedMapping,
+        record: &csv::StringRecord,
+    ) -> Result<(AccessLog, Option<String>), String> {
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let card_number = cell(Some(mapping.card_number))
+            .map(Card::normalize_card_number)
+            .ok_or("missing card number")?;
+        let raw_timestamp = cell(Some(mapping.timestamp)).ok_or("missing timestamp")?;
+        let timestamp = self
+            .parse_timestamp(raw_timestamp)
+            .ok_or_else(|| format!("invalid timestamp '{}'", raw_timestamp))?;
+
+        let direction = match cell(mapping.direction) {
+            Some(value) => {
+                parse_direction(value).ok_or_else(|| format!("invalid direction '{}'", value))?
+            }
+            None => Direction::Undefined,
+        };
+        let granted = match cell(mapping.granted) {
+            Some(value) => {
+                parse_granted(value).ok_or_else(|| format!("invalid result '{}'", value))?
+            }
+            None => true,
+        };
+        let reader_type = match cell(mapping.reader_type) {
+            Some(value) => parse_reader_type(value)
+                .ok_or_else(|| format!("invalid reader type '{}'", value))?,
+            None => ReaderType::Rfid,
+        };
+        let device_id = match cell(mapping.device_id) {
+            Some(value) => Some(
+                value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|id| (1..=99).contains(id))
+                    .ok_or_else(|| format!("invalid device ID '{}'", value))?,
+            ),
+            None => None,
+        };
+
+        let mut log = AccessLog::new(
+            None,
+            None,
+            card_number,
+            direction,
+            reader_type,
+            granted,
+            cell(mapping.display_message).map(str::to_string),
+            timestamp,
+        );
+        log.device_id = device_id;
+
+        Ok((log, cell(mapping.matricula).map(str::to_string)))
+    }
+
+    fn parse_timestamp(&self, value: &str) -> Option<DateTime<Utc>> {
+        let naive = |format: &str| NaiveDateTime::parse_from_str(value, format).ok();
+
+        let parsed = match &self.options.timestamp_format {
+            Some(format) => naive(format),
+            None => {
+                if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+                    return Some(datetime.with_timezone(&Utc));
+                }
+                FALLBACK_TIMESTAMP_FORMATS.iter().find_map(|f| naive(f))
+            }
+        }?;
+
+        self.options
+            .utc_offset
+            .from_local_datetime(&parsed)
+            .single()
+            .map(|datetime| datetime.with_timezone(&Utc))
+    }
+
+    /// Resolve users, drop duplicates and insert one batch atomically
+    async fn write_batch(
+        &self,
+        batch: &mut Vec<PendingRow>,
+        report: &mut IngestReport,
+    ) -> StorageResult<()> {
+        let mut tx = self.pool.begin().await?;
+        let mut inserted = Vec::with_capacity(batch.len());
+
+        for row in batch.drain(..) {
+            let PendingRow {
+                line,
+                mut log,
+                matricula,
+            } = row;
+
+            if Self::is_duplicate(&mut tx, &log).await? {
+                report.duplicates += 1;
+                continue;
+            }
+
+            let user = match &matricula {
+                Some(matricula) => sqlx::query!(
+                    r#"SELECT id AS "id!", matricula FROM users WHERE matricula = ?"#,
+                    matricula
+                )
+                .fetch_optional(&mut *tx)
+                .await?
+                .map(|row| (row.id, row.matricula)),
+                None => sqlx::query!(
+                    "SELECT user_id, matricula FROM cards WHERE numero_cartao = ?",
+                    log.card_number
+                )
+                .fetch_optional(&mut *tx)
+                .await?
+                .map(|row| (row.user_id, row.matricula)),
+            };
+            if let Some((user_id, matricula)) = user {
+                log.user_id = Some(user_id);
+                log.matricula = Some(matricula);
+            }
+
+            transaction::create_access_log(&mut tx, &log)
+                .await
+                .map_err(|e| match e {
+                    StorageError::Database(e) => StorageError::Validation(format!(
+                        "Line {}: cannot insert access log: {}",
+                        line, e
+                    )),
+                    other => other,
+                })?;
+            inserted.push(log.timestamp);
+        }
+
+        tx.commit().await?;
+
+        report.inserted += inserted.len() as u64;
+        for timestamp in inserted {
+            report.first_timestamp = Some(
+                report
+                    .first_timestamp
+                    .map_or(timestamp, |t| t.min(timestamp)),
+            );
+            report.last_timestamp = Some(
+                report
+                    .last_timestamp
+                    .map_or(timestamp, |t| t.max(timestamp)),
+            );
+        }
+        Ok(())
+    }
+
+    /// Same card, timestamp and device as a stored log
+    async fn is_duplicate(
+        tx: &mut Transaction<'_, Sqlite>,
+        log: &AccessLog,
+    ) -> StorageResult<bool> {
+        let exists = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM access_logs
+                WHERE card_number = ? AND timestamp = ? AND device_id IS ?
+            ) AS "exists!: bool"
+            "#,
+            log.card_number,
+            log.timestamp,
+            log.device_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(exists)
+    }
+}
+
+fn parse_direction(value: &str) -> Option<Direction> {
+    match value.to_uppercase().as_str() {
+        "0" | "U" | "INDEFINIDO" | "UNDEFINED" => Some(Direction::Undefined),
+        "1" | "E" | "ENTRADA" | "ENTRY" | "IN" => Some(Direction::Entry),
+        "2" | "S" | "SAIDA" | "SAÍDA" | "EXIT" | "OUT" => Some(Direction::Exit),
+        _ => None,
+    }
+}
+
+fn parse_granted(value: &str) -> Option<bool> {
+    match value.to_uppercase().as_str() {
+        "1" | "S" | "SIM" | "Y" | "YES" | "TRUE" | "LIBERADO" | "GRANTED" => Some(true),
+        "0" | "N" | "NAO" | "NÃO" | "NO" | "FALSE" | "NEGADO" | "DENIED" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_reader_type(value: &str) -> Option<ReaderType> {
+    match value.to_uppercase().as_str() {
+        "1" | "RFID" | "CARD" | "CARTAO" | "CARTÃO" => Some(ReaderType::Rfid),
+        "5" | "BIO" | "BIOMETRIC" | "BIOMETRIA" => Some(ReaderType::Biometric),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    const LEGACY_CSV: &str = "\
+Cartao;DataHora;Sentido;Resultado;Catraca
+00000000000011912322;03/02/2020 08:00:00;E;LIBERADO;3
+00000000000011912322;03/02/2020 12:00:00;S;LIBERADO;3
+99999999999999999999;03/02/2020 12:05:00;E;NEGADO;4
+00000000000022823433;31/02/2020 08:00:00;E;LIBERADO;3
+00000000000022823433;04/02/2020 08:00:00;X;LIBERADO;3
+;04/02/2020 09:00:00;E;LIBERADO;3
+";
+
+    fn legacy_options() -> CsvIngestOptions {
+        let mapping = CsvLogMapping::new("Cartao", "DataHora")
+            .direction("Sentido")
+            .granted("Resultado")
+            .device_id("Catraca");
+        CsvIngestOptions::new(mapping)
+            .delimiter(b';')
+            .timestamp_format("%d/%m/%Y %H:%M:%S")
+            .utc_offset(FixedOffset::west_opt(3 * 3600).unwrap())
+            .batch_size(2)
+    }
+
+    async fn ingest(db: &Database, options: CsvIngestOptions, csv: &str) -> IngestReport {
+        CsvLogIngester::new(db.pool().clone(), options)
+            .ingest(csv.as_bytes(), |_| {})
+            .await
+            .unwrap()
+    }
+
+    async fn legacy_logs(db: &Database) -> Vec<AccessLog> {
+        sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE timestamp < '2021'
+            ORDER BY timestamp
+            "#
+        )
+        .fetch_all(db.pool())
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ingest_maps_columns() {
+        let db = Database::in_memory().await.unwrap();
+        let mut progress = Vec::new();
+        let report = CsvLogIngester::new(db.pool().clone(), legacy_options())
+            .ingest(LEGACY_CSV.as_bytes(), |p| progress.push(p))
+            .await
+            .unwrap();
+
+        assert_eq!(report.rows_read, 6);
+        assert_eq!(report.inserted, 3);
+        assert_eq!(report.rejected, 3);
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+            vec![5, 6, 7]
+        );
+        assert!(report.errors[1].message.contains("direction"));
+
+        // -03:00 local time
+        assert_eq!(
+            report.first_timestamp.unwrap().to_rfc3339(),
+            "2020-02-03T11:00:00+00:00"
+        );
+        assert_eq!(progress.last().unwrap().inserted, 3);
+        assert!(progress.len() >= 2);
+
+        let logs = legacy_logs(&db).await;
+        assert_eq!(logs.len(), 3);
+        assert_eq!(logs[0].matricula.as_deref(), Some("1001"));
+        assert_eq!(logs[0].direction, Direction::Entry as i32);
+        assert_eq!(logs[0].device_id, Some(3));
+        assert_eq!(logs[1].direction, Direction::Exit as i32);
+        assert!(!logs[2].granted);
+        assert_eq!(logs[2].user_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_reingest_skips_duplicates() {
+        let db = Database::in_memory().await.unwrap();
+        ingest(&db, legacy_options(), LEGACY_CSV).await;
+        let report = ingest(&db, legacy_options(), LEGACY_CSV).await;
+
+        assert_eq!(report.inserted, 0);
+        assert_eq!(report.duplicates, 3);
+        assert_eq!(legacy_logs(&db).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_within_file_and_devices() {
+        let db = Database::in_memory().await.unwrap();
+        let csv = "\
+card,time,device
+ABC123,2020-01-01T10:00:00Z,1
+ABC123,2020-01-01T10:00:00Z,1
+ABC123,2020-01-01T10:00:00Z,2
+ABC123,2020-01-01T10:00:00Z,
+ABC123,2020-01-01T10:00:00Z,
+";
+        let options = CsvIngestOptions::new(CsvLogMapping::new("card", "time").device_id("device"));
+        let report = ingest(&db, options, csv).await;
+
+        assert_eq!(report.inserted, 3);
+        assert_eq!(report.duplicates, 2);
+        assert!(report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_positional_columns_and_matricula() {
+        let db = Database::in_memory().await.unwrap();
+        let csv = "1002,CARD-NOT-REGISTERED,2020-05-01 07:30:00,1\n";
+        let options = CsvIngestOptions::new(CsvLogMapping::new(1, 2).matricula(0).direction(3))
+            .has_headers(false);
+        let report = ingest(&db, options, csv).await;
+
+        assert_eq!(report.inserted, 1);
+        let logs = legacy_logs(&db).await;
+        assert_eq!(logs[0].matricula.as_deref(), Some("1002"));
+        assert_eq!(logs[0].user_id, Some(2));
+        assert_eq!(logs[0].card_number, "CARD-NOT-REGISTERED");
+    }
+
+    #[tokio::test]
+    async fn test_missing_column_is_fatal() {
+        let db = Database::in_memory().await.unwrap();
+        let err = CsvLogIngester::new(
+            db.pool().clone(),
+            CsvIngestOptions::new(CsvLogMapping::new("card", "when")),
+        )
+        .ingest(
+            "card,time\nABC123,2020-01-01T10:00:00Z\n".as_bytes(),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, StorageError::Validation(_)));
+        assert!(err.to_string().contains("when"));
+    }
+
+    #[test]
+    fn test_value_parsers() {
+        assert_eq!(parse_direction("saída"), Some(Direction::Exit));
+        assert_eq!(parse_direction("in"), Some(Direction::Entry));
+        assert_eq!(parse_direction("?"), None);
+        assert_eq!(parse_granted("Não"), Some(false));
+        assert_eq!(parse_granted("sim"), Some(true));
+        assert_eq!(parse_reader_type("Biometria"), Some(ReaderType::Biometric));
+    }
+}
@@ -10,8 +10,40 @@
 //!
 //! - [`Database`] - Connection pool manager with automatic migrations
 //! - [`UserRepository`], [`CardRepository`], [`AccessLogRepository`] - Data access traits
+//! - [`SiteRepository`] - Site/zone/device hierarchy, broadcast groups and zone occupancy
+//! - [`DeviceDirectory`] - Cached device names and locations for logs and reports
+//! - [`PresenceRepository`] - "Who is inside" report and corrections for missed exits
+//! - [`QuotaRepository`] - Daily passage quotas per user or company
+//! - [`ScheduleRepository`] - Weekly access schedules with overnight intervals and date exceptions
+//! - [`ConfigProfileRepository`] - Device configuration profiles with inheritance and per-device overrides
 //! - [`OfflineValidator`] - 9-step validation flow implementation
+//! - [`ValidationModeController`] - Runtime switching between online, offline and hybrid
+//! - `HttpValidator` - Validation against a REST back-end, with retries and offline fallback (feature `http`, on by default)
+//! - [`HybridValidator`] - Leaves the server when its p95 latency degrades, returns when healthy
+//! - [`HeartbeatMonitor`] - Goes offline after missed keepalives, back online once they are steady
+//! - [`ServerValidationLoop`] - Answers device requests on the server, dropping work for disconnected devices
 //! - [`transaction`] - Transaction-aware operations for atomic multi-step operations
+//! - [`bundle`] - Export/import of the full site configuration as a portable archive
+//! - [`ingest`] - Streaming import of historical access logs from legacy CSV exports
+//! - [`log_pull`] - Pulling events stored on devices into the central log, with cursors and dedup
+//! - [`outbox`] - Access notifications written with their logs and sent with retries
+//! - [`sync`] - Events buffered while offline, replayed on reconnection and deduplicated by NSR
+//! - `archive` - Daily access log segments backed up to files or S3-compatible storage (features `archive`, `s3`)
+//! - [`retention`] - Pruning of old access logs, optionally archived first, as a scheduled task
+//! - [`config_sync`] - Sending the resolved configuration of profiles to devices
+//! - [`colaborador`] - Parallel bulk import of users from `colaborador.txt`
+//! - [`henry_migration`] - Checked, transactional migration of a Henry export folder (users, cards, fingerprints)
+//! - [`export`] - Writing users, cards and fingerprints back as Henry data files (delimiter, Latin-1/UTF-8)
+//! - [`enrollment`] - Registering new cards by tapping them on a device in enrollment mode
+//! - [`card_filter`] - Bloom filter of registered cards, so unknown cards skip the card query
+//! - [`pin`] - Argon2id hashing of keypad codes, with migration of plaintext codes on use
+//! - [`anonymize`] - Pseudonymization of a person's access history for erasure requests
+//! - [`changes`] - Change events for users, cards and templates, for cache invalidation and sync
+//! - [`rules`] - Export/import of anti-passback, schedule and quota rules as reviewable JSON
+//! - [`cluster`] - Device leases and a shared decision ledger for several servers on one host's database
+//! - [`events`] - Event bus with bounded per-subscriber queues, lag metrics and replay
+//! - [`anomaly`] - Per-device entry rates on the event bus, with alerts on unusual bursts
+//! - [`coercion`] - Alarms on repeated denials of a card, with optional temporary lockout
 //!
 //! # Core Concepts
 //!
@@ -31,6 +63,10 @@
 //! - Separation of business logic from persistence
 //! - Transaction support for atomic operations
 //!
+//! Repository statements use the `sqlx::query!` family of macros, checked at
+//! compile time against the schema recorded in `.sqlx/` at the workspace
+//! root; run `make sqlx-prepare` after changing a migration or a query.
+//!
 //! # Examples
 //!
 //! ## Basic Setup and Offline Validation
@@ -123,6 +159,7 @@
 //!     allow_bio: false,
 //!     allow_keypad: false,
 //!     codigo: None,
+//!     empresa: None,
 //!     created_at: Utc::now(),
 //!     updated_at: Utc::now(),
 //! };
@@ -184,22 +221,84 @@
 //!
 //! This ensures future import features can be implemented without schema migrations.
 
+pub mod anomaly;
+pub mod anonymize;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod blocking;
+pub mod bundle;
+pub mod card_filter;
+pub mod changes;
+pub mod cluster;
+pub mod coercion;
+pub mod colaborador;
+pub mod config_sync;
 pub mod connection;
+pub mod directory;
+pub mod enrollment;
 pub mod error;
+pub mod events;
+pub mod export;
+pub mod heartbeat;
+pub mod henry_migration;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod hybrid;
+pub mod ingest;
+pub mod log_pull;
 pub mod messages;
+pub mod mode;
 pub mod models;
+pub mod monitoring;
+pub mod outbox;
+pub mod pagination;
+pub mod pin;
+pub mod replay;
 pub mod repositories;
+pub mod retention;
+pub mod rules;
+pub mod server_loop;
+pub mod sync;
 pub mod transaction;
 pub mod validator;
 
+pub use anonymize::{AnonymizationRecord, Anonymizer};
+pub use blocking::OfflineValidatorBlocking;
+pub use bundle::{BundleManifest, ExportOptions, ImportMode, ImportReport, SiteBundle};
 pub use connection::{Database, DatabaseConfig};
+pub use directory::DeviceDirectory;
 pub use error::{StorageError, StorageResult};
-pub use messages::DisplayMessages;
-pub use models::{AccessLog, Card, Direction, ReaderType, User};
+pub use heartbeat::{HeartbeatConfig, HeartbeatMonitor};
+#[cfg(feature = "http")]
+pub use http::{HttpValidator, HttpValidatorConfig};
+pub use hybrid::{HybridConfig, HybridMetrics, HybridValidator};
+pub use messages::{DisplayMessages, Locale, MessageCatalog, TemplateValue};
+pub use mode::{ModeChange, ModeChangeReason, OperatingMode, ValidationModeController};
+pub use models::{
+    AccessException, AccessLog, AccessMethod, AccessOverride, AccessOverrideOutcome, AccessState,
+    BiometricTemplate, BlockReason, Card, CardSelector, ClockDriftAlert, DailyQuota, Device,
+    DeviceClockDrift, Direction, EnrollmentSession, KeypadLockout, KeypadLockoutPolicy, Occupant,
+    PendingCard, QuotaDay, ReaderType, Site, StaleDataWarning, SyncState, TimeInterval, User,
+    VerificationMode, WeeklySchedule, Zone, ZoneOccupancy,
+};
+pub use monitoring::{StorageAlert, StorageMonitor, StorageThresholds};
+pub use pagination::{Page, PageRequest};
 pub use repositories::{
-    AccessLogRepository, CardRepository, SqliteAccessLogRepository, SqliteCardRepository,
-    SqliteUserRepository, UserRepository,
+    AccessExceptionRepository, AccessLogOrder, AccessLogQuery, AccessLogRepository,
+    AccessOverrideRepository, BiometricTemplateRepository, BulkInsertOptions, BulkInsertReport,
+    CardBlockRepository, CardRepository, ConfigProfileRepository, DeviceRepository,
+    EnrollmentSessionRepository, KeypadLockoutRepository, OnDuplicate, PendingCardRepository,
+    PresenceRepository, QuotaRepository, ScheduleRepository, SiteRepository,
+    SqliteAccessExceptionRepository, SqliteAccessLogRepository, SqliteAccessOverrideRepository,
+    SqliteBiometricTemplateRepository, SqliteCardBlockRepository, SqliteCardRepository,
+    SqliteConfigProfileRepository, SqliteDeviceRepository, SqliteEnrollmentSessionRepository,
+    SqliteKeypadLockoutRepository, SqlitePendingCardRepository, SqlitePresenceRepository,
+    SqliteQuotaRepository, SqliteScheduleRepository, SqliteSiteRepository,
+    SqliteSyncStateRepository, SqliteUserRepository, SyncStateRepository, UserRepository,
 };
+pub use server_loop::{ServerLoopStats, ServerValidationLoop};
+pub use sync::{EventReceiver, ReceiveReport, SyncEngine};
 pub use validator::{
-    AccessValidator, OfflineValidator, OnlineValidator, OnlineValidatorConfig, Validator,
+    AccessValidator, CardVerification, KEYPAD_MATRICULA_SEPARATOR, OfflineValidator,
+    OnlineValidator, OnlineValidatorConfig, PendingBiometric, TemplateMatcher, Validator,
 };
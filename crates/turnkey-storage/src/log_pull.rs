@@ -0,0 +1,519 @@
+//! Pulling stored events from devices into the central log.
+//!
+//! A device that validated offline keeps its decisions in its own
+//! `access_logs`. Instead of waiting for the device to push them, the server
+//! can pull them with `ER` event queries (see
+//! [`turnkey_protocol::commands::events`]):
+//!
+//! - On the device, [`DeviceLogSource`] answers a query from the local
+//!   `access_logs`, after the cursor and in chunks that fit a frame.
+//! - On the server, [`LogPuller`] sends queries, assembles the chunks and
+//!   writes the events to the central log, page after page, until the device
+//!   has nothing left.
+//!
+//! # Cursor and Duplicates
+//!
+//! The cursor is the device's own log ID, so the server keeps one cursor per
+//! device and passes it to the next pull. Losing it is harmless: events are
+//! stored with [`OnDuplicate::Skip`] on card number, event time and device,
+//! so pulling again from the start only adds what is missing.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_core::DeviceId;
+//! use turnkey_network::{TcpServer, TcpServerConfig};
+//! use turnkey_protocol::commands::events::EventCursor;
+//! use turnkey_storage::log_pull::LogPuller;
+//! use turnkey_storage::{Database, DatabaseConfig};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let mut server = TcpServer::bind(TcpServerConfig::default()).await?;
+//! let (device_id, _) = server.accept().await?;
+//!
+//! let puller = LogPuller::new(db.pool().clone());
+//! let report = puller.pull(&mut server, device_id, EventCursor::start()).await?;
+//! println!(
+//!     "{} events, {} new, continue after {}",
+//!     report.events, report.inserted, report.cursor.after_id
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{AccessLog, Direction, ReaderType as LogReaderType};
+use crate::repositories::{
+    AccessLogRepository, BulkInsertOptions, OnDuplicate, SqliteAccessLogRepository,
+};
+use chrono::{Local, Utc};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::Duration;
+use turnkey_core::{AccessDirection, DeviceId, HenryTimestamp, ReaderType};
+use turnkey_network::TcpServer;
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::events::{
+    DEFAULT_EVENTS_PER_CHUNK, DeviceEvent, EventAssembler, EventChunk, EventCursor, GetEvents,
+};
+
+/// Default number of events asked for per query
+pub const DEFAULT_PULL_PAGE_SIZE: u32 = 500;
+
+/// Default time to wait for each chunk of an answer
+pub const DEFAULT_CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answers event queries from the device's local access log
+#[derive(Debug, Clone)]
+pub struct DeviceLogSource {
+    pool: SqlitePool,
+    events_per_chunk: usize,
+}
+
+impl DeviceLogSource {
+    /// Read events from the local database
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            events_per_chunk: DEFAULT_EVENTS_PER_CHUNK,
+        }
+    }
+
+    /// Send at most `events_per_chunk` events per chunk
+    pub fn with_events_per_chunk(mut self, events_per_chunk: usize) -> Self {
+        self.events_per_chunk = events_per_chunk.max(1);
+        self
+    }
+
+    /// Read the events matching a query
+    ///
+    /// Returns the events and whether more are left beyond the limit.
+    pub async fn read(&self, query: &GetEvents) -> StorageResult<(Vec<DeviceEvent>, bool)> {
+        let since = query
+            .cursor
+            .since
+            .as_ref()
+            .map(|since| since.inner().with_timezone(&Utc));
+        let limit = i64::from(query.limit) + 1;
+        let mut logs = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE id > ?1 AND (?2 IS NULL OR timestamp >= ?2)
+            ORDER BY id
+            LIMIT ?3
+            "#,
+            query.cursor.after_id,
+            since,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let more = logs.len() > query.limit as usize;
+        logs.truncate(query.limit as usize);
+        let events = logs
+            .into_iter()
+            .map(Self::to_event)
+            .collect::<StorageResult<Vec<_>>>()?;
+        Ok((events, more))
+    }
+
+    /// Answer a query with the chunks to send back
+    pub async fn answer(&self, query: &GetEvents) -> StorageResult<Vec<EventChunk>> {
+        let (events, more) = self.read(query).await?;
+        Ok(EventChunk::split(
+            query.cursor.after_id,
+            events,
+            self.events_per_chunk,
+            more,
+        ))
+    }
+
+    /// Answer an `ER` query message with the messages to send back
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if the message is not a valid event query or
+    /// a stored event cannot be encoded.
+    pub async fn answer_message(&self, message: &Message) -> StorageResult<Vec<Message>> {
+        let query = GetEvents::parse(message).map_err(protocol_error)?;
+        self.answer(&query)
+            .await?
+            .iter()
+            .map(|chunk| chunk.to_message(message.device_id).map_err(protocol_error))
+            .collect()
+    }
+
+    pub(crate) fn to_event(log: AccessLog) -> StorageResult<DeviceEvent> {
+        let direction = u8::try_from(log.direction)
+            .ok()
+            .and_then(|code| AccessDirection::from_u8(code).ok());
+        let reader_type = u8::try_from(log.reader_type)
+            .ok()
+            .and_then(|code| ReaderType::from_u8(code).ok());
+        let (Some(direction), Some(reader_type)) = (direction, reader_type) else {
+            return Err(StorageError::Validation(format!(
+                "Access log {} has direction {} and reader type {}",
+                log.id, log.direction, log.reader_type
+            )));
+        };
+        Ok(DeviceEvent {
+            id: log.id,
+            card_number: log.card_number,
+            timestamp: HenryTimestamp::from_datetime(log.timestamp.with_timezone(&Local)),
+            direction,
+            reader_type,
+            granted: log.granted,
+            matricula: log.matricula,
+            display_message: log.display_message,
+        })
+    }
+}
+
+/// Outcome of a pull from one device
+#[derive(Debug, Clone, Default)]
+pub struct PullReport {
+    /// Queries sent
+    pub queries: u32,
+
+    /// Events received
+    pub events: u64,
+
+    /// Events written to the central log
+    pub inserted: u64,
+
+    /// Events already in the central log
+    pub duplicates: u64,
+
+    /// Cursor to pass to the next pull from this device
+    pub cursor: EventCursor,
+
+    /// Other messages the device sent during the pull, in arrival order
+    pub deferred: Vec<Message>,
+}
+
+/// Pulls events from devices into the central access log
+#[derive(Debug, Clone)]
+pub struct LogPuller {
+    pool: SqlitePool,
+    page_size: u32,
+    chunk_timeout: Duration,
+}
+
+impl LogPuller {
+    /// Write pulled events to the central database
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            page_size: DEFAULT_PULL_PAGE_SIZE,
+            chunk_timeout: DEFAULT_CHUNK_TIMEOUT,
+        }
+    }
+
+    /// Ask for at most `page_size` events per query
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Give up when a chunk takes longer than `timeout` to arrive
+    pub fn with_chunk_timeout(mut self, timeout: Duration) -> Self {
+        self.chunk_timeout = timeout;
+        self
+    }
+
+    /// Query for the page after `cursor`
+    pub fn query(&self, cursor: EventCursor) -> GetEvents {
+        GetEvents::new(cursor, self.page_size)
+    }
+
+    /// Pull every event after `cursor` from a connected device
+    ///
+    /// Each page is written as it arrives, so an interrupted pull keeps the
+    /// pages already stored; continue from the cursor of the last report or
+    /// simply start over.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetworkError` if the device disconnects or a chunk does not
+    /// arrive in time, and `ProtocolError` if a chunk is invalid or out of
+    /// order.
+    pub async fn pull(
+        &self,
+        server: &mut TcpServer,
+        device_id: DeviceId,
+        cursor: EventCursor,
+    ) -> StorageResult<PullReport> {
+        let mut report = PullReport {
+            cursor,
+            ..PullReport::default()
+        };
+
+        loop {
+            let query = self
+                .query(report.cursor.clone())
+                .to_message(device_id)
+                .map_err(protocol_error)?;
+            server
+                .send(device_id, query)
+                .await
+                .map_err(|e| StorageError::NetworkError(e.to_string()))?;
+            report.queries += 1;
+
+            let mut assembler = EventAssembler::new();
+            while !assembler.is_complete() {
+                let message = tokio::time::timeout(self.chunk_timeout, server.recv(device_id))
+                    .await
+                    .map_err(|_| {
+                        StorageError::NetworkError(format!(
+                            "Device {} sent no events for {:?}",
+                            device_id, self.chunk_timeout
+                        ))
+                    })?
+                    .map_err(|e| StorageError::NetworkError(e.to_string()))?
+                    .ok_or_else(|| {
+                        StorageError::NetworkError(format!(
+                            "Device {} disconnected during the pull",
+                            device_id
+                        ))
+                    })?;
+                if !EventChunk::is_chunk(&message) {
+                    report.deferred.push(message);
+                    continue;
+                }
+                let chunk = EventChunk::parse(&message).map_err(protocol_error)?;
+                assembler.push(chunk).map_err(protocol_error)?;
+            }
+
+            let page = assembler.finish().map_err(protocol_error)?;
+            let stored = self.store(device_id, &page.events).await?;
+            report.events += page.events.len() as u64;
+            report.inserted += stored.inserted;
+            report.duplicates += stored.duplicates;
+            report.cursor = EventCursor {
+                since: report.cursor.since,
+                ..page.next_cursor
+            };
+            if !page.more {
+                return Ok(report);
+            }
+        }
+    }
+
+    /// Write events of a device to the central log, skipping duplicates
+    ///
+    /// Users are resolved by matricula, or by card number when the device
+    /// did not identify one; unknown users are stored as unidentified. The
+    /// device clock is both the event time and the device timestamp.
+    pub async fn store(
+        &self,
+        device_id: DeviceId,
+        events: &[DeviceEvent],
+    ) -> StorageResult<crate::repositories::BulkInsertReport> {
+        self.store_events(device_id, events, false).await
+    }
+
+    /// Write events like [`store()`](Self::store), keeping each event ID as
+    /// the NSR of its log
+    ///
+    /// For events a device numbered with its NSR, like those it pushes from
+    /// its outbox.
+    pub(crate) async fn store_numbered(
+        &self,
+        device_id: DeviceId,
+        events: &[DeviceEvent],
+    ) -> StorageResult<crate::repositories::BulkInsertReport> {
+        self.store_events(device_id, events, true).await
+    }
+
+    async fn store_events(
+        &self,
+        device_id: DeviceId,
+        events: &[DeviceEvent],
+        numbered: bool,
+    ) -> StorageResult<crate::repositories::BulkInsertReport> {
+        let mut users: HashMap<(bool, String), Option<(i64, String)>> = HashMap::new();
+        let mut logs = Vec::with_capacity(events.len());
+        for event in events {
+            let key = match &event.matricula {
+                Some(matricula) => (true, matricula.clone()),
+                None => (false, event.card_number.clone()),
+            };
+            let user = match users.get(&key) {
+                Some(user) => user.clone(),
+                None => {
+                    let user = self.resolve_user(&key).await?;
+                    users.insert(key, user.clone());
+                    user
+                }
+            };
+            let (user_id, matricula) = user.unzip();
+            let timestamp = event.timestamp.inner().with_timezone(&Utc);
+            let log = AccessLog::new(
+                user_id,
+                matricula,
+                event.card_number.clone(),
+                Direction::from_i32(i32::from(event.direction.to_u8()))
+                    .unwrap_or(Direction::Undefined),
+                LogReaderType::from_i32(i32::from(event.reader_type.to_u8()))
+                    .unwrap_or(LogReaderType::Rfid),
+                event.granted,
+                event.display_message.clone(),
+                timestamp,
+            )
+            .with_device_clock(i64::from(device_id.as_u8()), timestamp);
+            logs.push(if numbered {
+                log.with_nsr(event.id)
+            } else {
+                log
+            });
+        }
+
+        SqliteAccessLogRepository::new(self.pool.clone())
+            .create_many_with(
+                &logs,
+                BulkInsertOptions::default().on_duplicate(OnDuplicate::Skip),
+            )
+            .await
+    }
+
+    async fn resolve_user(
+        &self,
+        (by_matricula, key): &(bool, String),
+    ) -> StorageResult<Option<(i64, String)>> {
+        let user = if *by_matricula {
+            sqlx::query!(
+                r#"SELECT id AS "id!", matricula FROM users WHERE matricula = ?"#,
+                key
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| (row.id, row.matricula))
+        } else {
+            sqlx::query!(
+                "SELECT user_id, matricula FROM cards WHERE numero_cartao = ?",
+                key
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| (row.user_id, row.matricula))
+        };
+        Ok(user)
+    }
+}
+
+pub(crate) fn protocol_error(error: turnkey_core::Error) -> StorageError {
+    StorageError::ProtocolError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use chrono::TimeZone;
+
+    const CARD: &str = "00000000000022823433";
+
+    /// Replace the seeded logs with `count` logs of one card, a minute apart
+    async fn seed_logs(db: &Database, count: i64) {
+        sqlx::query!("DELETE FROM access_logs")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 5, 10, 12, 0, 0).unwrap();
+        let logs: Vec<_> = (0..count)
+            .map(|i| {
+                AccessLog::new(
+                    None,
+                    None,
+                    CARD.to_string(),
+                    Direction::Entry,
+                    LogReaderType::Rfid,
+                    i % 4 != 0,
+                    Some("Acesso liberado".to_string()),
+                    start + chrono::Duration::minutes(i),
+                )
+            })
+            .collect();
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create_many(&logs)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_answer_pages_and_chunks() {
+        let db = Database::in_memory().await.unwrap();
+        seed_logs(&db, 12).await;
+        let source = DeviceLogSource::new(db.pool().clone()).with_events_per_chunk(3);
+
+        let chunks = source
+            .answer(&GetEvents::new(EventCursor::start(), 8))
+            .await
+            .unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[2].last && chunks[2].more);
+        let next = chunks[2].next_after_id;
+
+        let chunks = source
+            .answer(&GetEvents::new(EventCursor::after(next), 8))
+            .await
+            .unwrap();
+        let events: Vec<_> = chunks.iter().flat_map(|chunk| &chunk.events).collect();
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|event| event.id > next));
+        assert!(!chunks.last().unwrap().more);
+
+        let since = HenryTimestamp::from_datetime(
+            Utc.with_ymd_and_hms(2025, 5, 10, 12, 10, 0)
+                .unwrap()
+                .with_timezone(&Local),
+        );
+        let (events, more) = source
+            .read(&GetEvents::new(EventCursor::start().since(since), 100))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(!more);
+    }
+
+    #[tokio::test]
+    async fn test_store_resolves_users_and_skips_duplicates() {
+        let device = Database::in_memory().await.unwrap();
+        seed_logs(&device, 5).await;
+        let central = Database::in_memory().await.unwrap();
+        seed_logs(&central, 0).await;
+        let device_id = DeviceId::new(15).unwrap();
+
+        let (events, _) = DeviceLogSource::new(device.pool().clone())
+            .read(&GetEvents::new(EventCursor::start(), 100))
+            .await
+            .unwrap();
+        let puller = LogPuller::new(central.pool().clone());
+
+        let report = puller.store(device_id, &events).await.unwrap();
+        assert_eq!(report.inserted, 5);
+        let report = puller.store(device_id, &events).await.unwrap();
+        assert_eq!(report.inserted, 0);
+        assert_eq!(report.duplicates, 5);
+
+        let stored = SqliteAccessLogRepository::new(central.pool().clone())
+            .find_by_card_number(CARD, 10)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 5);
+        assert!(stored.iter().all(|log| log.user_id == Some(2)));
+        assert!(
+            stored
+                .iter()
+                .all(|log| log.matricula.as_deref() == Some("1002"))
+        );
+        assert!(stored.iter().all(|log| log.device_id == Some(15)));
+    }
+}
@@ -6,8 +6,10 @@
 //!
 //! # Internationalization
 //!
-//! Future versions may support multiple languages through a message catalog
-//! or i18n framework. For now, all messages are compile-time constants.
+//! The message texts are compile-time constants. Values substituted into
+//! their `{name}` variables are formatted by a [`MessageCatalog`], whose
+//! [`Locale`] decides how dates, times and numbers are written: pt-BR on
+//! the turnstile display, ISO 8601 in logs and exports.
 //!
 //! # Usage
 //!
@@ -18,6 +20,9 @@
 //! println!("{}", message); // "Acesso liberado"
 //! ```
 
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use turnkey_core::constants::MAX_DISPLAY_MESSAGE_LENGTH;
+
 /// Display messages for access control validation (Portuguese/Brazilian)
 ///
 /// This struct provides constants for all user-facing messages in the system.
@@ -44,6 +49,36 @@ impl DisplayMessages {
     /// Returned when `numero_cartao` does not exist in cards table.
     pub const CARD_NOT_FOUND: &'static str = "Cartao nao cadastrado";
 
+    /// Unknown card recorded for assignment (learning mode)
+    ///
+    /// Returned instead of `CARD_NOT_FOUND` while the validator is in
+    /// learning mode, confirming to the operator that the read was captured.
+    pub const CARD_PENDING: &'static str = "Cartao aguardando cadastro";
+
+    /// Card captured by an enrollment session
+    ///
+    /// The device is in enrollment mode; the card is bound to the chosen
+    /// user and waits for the administrator to confirm the registration.
+    pub const CARD_ENROLLED: &'static str = "Cartao capturado p/ cadastro";
+
+    /// Card presented in enrollment mode is already registered
+    ///
+    /// The enrollment session stays open for another card.
+    pub const CARD_ALREADY_REGISTERED: &'static str = "Cartao ja cadastrado";
+
+    /// Exit recorded by an operator for a missed passage
+    ///
+    /// Message of the access log written by
+    /// [`PresenceRepository::mark_exited`](crate::repositories::PresenceRepository::mark_exited);
+    /// never shown on a turnstile.
+    pub const MANUAL_EXIT: &'static str = "Saida registrada pelo operador";
+
+    /// Denied access released by an operator
+    ///
+    /// Message of the granted access log written by
+    /// [`AccessOverrideRepository::create`](crate::repositories::AccessOverrideRepository::create).
+    pub const OPERATOR_OVERRIDE: &'static str = "Liberado pelo operador";
+
     /// Card is inactive (ativo = false)
     ///
     /// Returned when card exists but `ativo` field is false.
@@ -81,16 +116,411 @@ impl DisplayMessages {
     /// Returned when user's `allow_bio` field is false.
     pub const BIO_ACCESS_DENIED: &'static str = "Acesso biometrico nao permitido";
 
+    /// User does not have permission to use keypad (PIN) access
+    ///
+    /// Returned when user's `allow_keypad` field is false or no code is set.
+    pub const KEYPAD_ACCESS_DENIED: &'static str = "Acesso por senha nao permitido";
+
+    /// Card reader disabled on this device
+    ///
+    /// Returned when the device's `allow_card` setting is false, regardless
+    /// of the user's own permissions.
+    pub const DEVICE_CARD_DISABLED: &'static str = "Cartao desabilitado neste acesso";
+
+    /// Biometric reader disabled on this device
+    ///
+    /// Returned when the device's `allow_bio` setting is false.
+    pub const DEVICE_BIO_DISABLED: &'static str = "Biometria desabilitada neste acesso";
+
+    /// Keypad disabled on this device
+    ///
+    /// Returned when the device's `allow_keypad` setting is false.
+    pub const DEVICE_KEYPAD_DISABLED: &'static str = "Teclado desabilitado neste acesso";
+
+    /// Code typed on the keypad belongs to no keypad user
+    pub const PIN_INVALID: &'static str = "Senha invalida";
+
+    /// Too many wrong codes on this device, keypad refused for a while
+    ///
+    /// Validation shows [`PIN_RETRY_IN`](Self::PIN_RETRY_IN) instead, which
+    /// tells how long the lockout lasts.
+    pub const KEYPAD_LOCKED: &'static str = "Teclado bloqueado";
+
+    /// Keypad or user PIN locked after wrong codes, with the time left
+    ///
+    /// Returned for every keypad entry while the lockout lasts, including
+    /// the wrong code that started it. `{minutos}` is the number of
+    /// minutes left, rounded up; see [`retry_in()`](Self::retry_in).
+    pub const PIN_RETRY_IN: &'static str = "Tente novamente em {minutos} min";
+
+    /// Card denied too often in a short time, refused for a while
+    ///
+    /// Returned while a lockout started by the
+    /// [`coercion`](crate::coercion) detector lasts.
+    pub const CARD_LOCKED: &'static str = "Cartao bloqueado temporariamente";
+
     /// Access granted successfully
     ///
     /// Returned when all validation checks pass.
     pub const ACCESS_GRANTED: &'static str = "Acesso liberado";
 
+    /// Access granted through a temporary access exception
+    ///
+    /// Returned when the regular validity check would deny but the card or
+    /// user belongs to an event/visitor group whose window is open.
+    pub const ACCESS_GRANTED_EXCEPTION: &'static str = "Acesso liberado - evento";
+
     /// Anti-passback violation detected
     ///
     /// Returned when user attempts entry-after-entry or exit-after-exit
     /// within the anti-passback time window (default: 5 minutes).
     pub const ANTI_PASSBACK: &'static str = "Bloqueio por anti-dupla";
+
+    /// Local data too old for this device to decide offline
+    ///
+    /// Returned on devices marked `deny_when_stale` when the last successful
+    /// sync is older than the validator's staleness threshold.
+    pub const DATA_STALE: &'static str = "Cadastro desatualizado";
+
+    /// User already passed as many times today as their quota allows
+    ///
+    /// Returned for entries once the user's granted passages since the
+    /// start of the quota day reach their daily quota (user or company).
+    pub const DAILY_QUOTA_EXCEEDED: &'static str = "Limite diario atingido";
+
+    /// User has a schedule and the passage falls outside it
+    ///
+    /// Returned when the site local time is not covered by the intervals
+    /// of the user's weekly schedule, or the day is closed by an exception.
+    pub const OUTSIDE_SCHEDULE: &'static str = "Fora do horario permitido";
+
+    /// Site closed for a holiday
+    ///
+    /// Returned on holidays when the validator closes the site and the user
+    /// is neither exempt nor given an exception for the date.
+    pub const HOLIDAY_CLOSED: &'static str = "Acesso fechado: feriado";
+
+    /// Card accepted, but the device also requires the owner's fingerprint
+    ///
+    /// Returned when a card read on a card + biometric device goes through
+    /// single-step validation, which cannot collect the second factor.
+    pub const BIOMETRIC_REQUIRED: &'static str = "Confirme com a digital";
+
+    /// Fingerprint or PIN presented alone on a card + biometric device
+    pub const CARD_REQUIRED: &'static str = "Apresente o cartao";
+
+    /// Card owner has no fingerprint template to verify against
+    pub const BIOMETRIC_NOT_ENROLLED: &'static str = "Digital nao cadastrada";
+
+    /// Fingerprint does not match any template of the card owner
+    pub const BIOMETRIC_MISMATCH: &'static str = "Digital nao confere com o cartao";
+
+    /// No fingerprint presented in time after the card was accepted
+    pub const BIOMETRIC_TIMEOUT: &'static str = "Digital nao apresentada";
+
+    /// No decision could be reached yet; the credential is presented again
+    ///
+    /// Returned in cluster mode when a retried request reaches a node
+    /// while the first answer is pending; the device retries. Also shown
+    /// by the device itself when its validation fails with an error.
+    pub const DECISION_IN_PROGRESS: &'static str = "Tente novamente";
+
+    /// Passages left in the daily quota, appended to grant messages
+    ///
+    /// Shown on devices with balance display enabled. `{saldo}` is the
+    /// number of entries the user may still make today.
+    pub const BALANCE: &'static str = "Saldo: {saldo}";
+
+    /// Replace `{name}` variables of a message template
+    ///
+    /// Variables without a value are left as written, so a typo shows up on
+    /// the display instead of silently disappearing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_storage::messages::DisplayMessages;
+    ///
+    /// let text = DisplayMessages::render(DisplayMessages::BALANCE, &[("saldo", "12")]);
+    /// assert_eq!(text, "Saldo: 12");
+    /// ```
+    pub fn render(template: &str, variables: &[(&str, &str)]) -> String {
+        let mut text = template.to_string();
+        for (name, value) in variables {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    /// Lockout message for the time left before the keypad is accepted again
+    ///
+    /// Minutes are rounded up, so the display never promises a retry
+    /// before the lockout has ended.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_storage::messages::DisplayMessages;
+    /// use chrono::Duration;
+    ///
+    /// assert_eq!(
+    ///     DisplayMessages::retry_in(Duration::seconds(90)),
+    ///     "Tente novamente em 2 min"
+    /// );
+    /// ```
+    pub fn retry_in(remaining: chrono::Duration) -> String {
+        let minutes = (remaining.num_seconds() + 59).div_euclid(60).max(1);
+        Self::render(Self::PIN_RETRY_IN, &[("minutos", &minutes.to_string())])
+    }
+
+    /// Append the balance to a message, within the display limit
+    ///
+    /// When the result would exceed
+    /// [`MAX_DISPLAY_MESSAGE_LENGTH`](turnkey_core::constants::MAX_DISPLAY_MESSAGE_LENGTH),
+    /// the message is shortened instead of the balance, so the number is
+    /// never cut off by the truncation in `AccessResponse`. A negative
+    /// balance shows as zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_storage::messages::DisplayMessages;
+    ///
+    /// assert_eq!(
+    ///     DisplayMessages::with_balance(DisplayMessages::ACCESS_GRANTED, 12),
+    ///     "Acesso liberado Saldo: 12"
+    /// );
+    /// ```
+    pub fn with_balance(message: &str, balance: i64) -> String {
+        let balance = Self::render(Self::BALANCE, &[("saldo", &balance.max(0).to_string())]);
+        let room = MAX_DISPLAY_MESSAGE_LENGTH.saturating_sub(balance.chars().count() + 1);
+        let message: String = message.chars().take(room).collect();
+        format!("{} {}", message.trim_end(), balance)
+    }
+}
+
+/// Conventions used to format template values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    /// Brazilian Portuguese, for the turnstile display
+    ///
+    /// `27/10/2025`, `14:30`, `1.234`
+    #[default]
+    PtBr,
+
+    /// ISO 8601, for logs and exports
+    ///
+    /// `2025-10-27`, `14:30:00`, `1234`
+    Iso,
+}
+
+/// Value of a `{name}` template variable
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateValue<'a> {
+    /// Inserted as written
+    Text(&'a str),
+
+    /// Calendar date
+    Date(NaiveDate),
+
+    /// Time of day
+    Time(NaiveTime),
+
+    /// Date and time of day
+    DateTime(NaiveDateTime),
+
+    /// Count or balance, grouped by thousands where the locale does
+    Number(i64),
+}
+
+/// Formats and renders display messages for one [`Locale`]
+///
+/// The same template gives `Valido ate 27/10/2025` on the display and
+/// `Valido ate 2025-10-27` in a log line.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use turnkey_storage::messages::{Locale, MessageCatalog, TemplateValue};
+///
+/// let date = NaiveDate::from_ymd_opt(2025, 10, 27).unwrap();
+/// let vars = [("data", TemplateValue::Date(date))];
+///
+/// let display = MessageCatalog::display();
+/// assert_eq!(display.render("Valido ate {data}", &vars), "Valido ate 27/10/2025");
+///
+/// let log = MessageCatalog::new(Locale::Iso);
+/// assert_eq!(log.render("Valido ate {data}", &vars), "Valido ate 2025-10-27");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MessageCatalog {
+    locale: Locale,
+}
+
+impl MessageCatalog {
+    /// Catalog formatting values for `locale`
+    pub fn new(locale: Locale) -> Self {
+        Self { locale }
+    }
+
+    /// Catalog for turnstile displays (pt-BR)
+    pub fn display() -> Self {
+        Self::new(Locale::PtBr)
+    }
+
+    /// Catalog for logs and exports (ISO 8601)
+    pub fn log() -> Self {
+        Self::new(Locale::Iso)
+    }
+
+    /// Locale of this catalog
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Format a calendar date
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        match self.locale {
+            Locale::PtBr => date.format("%d/%m/%Y").to_string(),
+            Locale::Iso => date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Format a time of day
+    ///
+    /// The display shows minutes only; seconds are kept in ISO.
+    pub fn format_time(&self, time: NaiveTime) -> String {
+        match self.locale {
+            Locale::PtBr => time.format("%H:%M").to_string(),
+            Locale::Iso => time.format("%H:%M:%S").to_string(),
+        }
+    }
+
+    /// Format a date and time of day
+    pub fn format_datetime(&self, datetime: NaiveDateTime) -> String {
+        match self.locale {
+            Locale::PtBr => datetime.format("%d/%m/%Y %H:%M").to_string(),
+            Locale::Iso => datetime.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        }
+    }
+
+    /// Format a count or balance
+    ///
+    /// pt-BR groups thousands with a dot (`1.234`); ISO writes the plain
+    /// number.
+    pub fn format_number(&self, value: i64) -> String {
+        let digits = value.unsigned_abs().to_string();
+        let grouped = match self.locale {
+            Locale::Iso => digits,
+            Locale::PtBr => {
+                let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+                for (i, digit) in digits.chars().enumerate() {
+                    if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                        grouped.push('.');
+                    }
+                    grouped.push(digit);
+                }
+                grouped
+            }
+        };
+        if value < 0 {
+            format!("-{}", grouped)
+        } else {
+            grouped
+        }
+    }
+
+    /// Format one template value
+    pub fn format(&self, value: &TemplateValue<'_>) -> String {
+        match value {
+            TemplateValue::Text(text) => text.to_string(),
+            TemplateValue::Date(date) => self.format_date(*date),
+            TemplateValue::Time(time) => self.format_time(*time),
+            TemplateValue::DateTime(datetime) => self.format_datetime(*datetime),
+            TemplateValue::Number(value) => self.format_number(*value),
+        }
+    }
+
+    /// Replace `{name}` variables with values formatted for this locale
+    ///
+    /// Variables without a value are left as written, as in
+    /// [`DisplayMessages::render`].
+    pub fn render(&self, template: &str, variables: &[(&str, TemplateValue<'_>)]) -> String {
+        self.segments(template, variables)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    /// Render a message for the display, within the display limit
+    ///
+    /// Like [`render`](Self::render), but the result never exceeds
+    /// [`MAX_DISPLAY_MESSAGE_LENGTH`](turnkey_core::constants::MAX_DISPLAY_MESSAGE_LENGTH).
+    /// Template text is cut where the limit falls, but a formatted value
+    /// is kept whole or dropped whole: a date cut to `27/10/20` would be
+    /// read as another date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use turnkey_storage::messages::{MessageCatalog, TemplateValue};
+    ///
+    /// let date = NaiveDate::from_ymd_opt(2025, 10, 27).unwrap();
+    /// let text = MessageCatalog::display().render_display(
+    ///     "Cartao do Centro de Distribuicao valido ate {data}",
+    ///     &[("data", TemplateValue::Date(date))],
+    /// );
+    /// assert_eq!(text, "Cartao do Centro de Distribuicao valido");
+    /// ```
+    pub fn render_display(
+        &self,
+        template: &str,
+        variables: &[(&str, TemplateValue<'_>)],
+    ) -> String {
+        let mut text = String::new();
+        let mut room = MAX_DISPLAY_MESSAGE_LENGTH;
+        for (segment, is_value) in self.segments(template, variables) {
+            let len = segment.chars().count();
+            if len <= room {
+                text.push_str(&segment);
+                room -= len;
+                continue;
+            }
+            if !is_value {
+                text.extend(segment.chars().take(room));
+            }
+            break;
+        }
+        text.trim_end().to_string()
+    }
+
+    /// Split a rendered template into literal text and formatted values
+    fn segments(
+        &self,
+        template: &str,
+        variables: &[(&str, TemplateValue<'_>)],
+    ) -> Vec<(String, bool)> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 1..start + len];
+            match variables.iter().find(|(var, _)| *var == name) {
+                Some((_, value)) => {
+                    segments.push((rest[..start].to_string(), false));
+                    segments.push((self.format(value), true));
+                }
+                None => segments.push((rest[..start + len + 1].to_string(), false)),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        segments.push((rest.to_string(), false));
+        segments
+    }
 }
 
 #[cfg(test)]
@@ -105,6 +535,11 @@ mod tests {
     #[allow(clippy::const_is_empty)]
     fn test_messages_are_non_empty() {
         assert!(!DisplayMessages::CARD_NOT_FOUND.is_empty());
+        assert!(!DisplayMessages::CARD_PENDING.is_empty());
+        assert!(!DisplayMessages::CARD_ENROLLED.is_empty());
+        assert!(!DisplayMessages::CARD_ALREADY_REGISTERED.is_empty());
+        assert!(!DisplayMessages::MANUAL_EXIT.is_empty());
+        assert!(!DisplayMessages::OPERATOR_OVERRIDE.is_empty());
         assert!(!DisplayMessages::CARD_INACTIVE.is_empty());
         assert!(!DisplayMessages::CARD_EXPIRED.is_empty());
         assert!(!DisplayMessages::USER_NOT_FOUND.is_empty());
@@ -112,8 +547,116 @@ mod tests {
         assert!(!DisplayMessages::USER_EXPIRED.is_empty());
         assert!(!DisplayMessages::CARD_ACCESS_DENIED.is_empty());
         assert!(!DisplayMessages::BIO_ACCESS_DENIED.is_empty());
+        assert!(!DisplayMessages::KEYPAD_ACCESS_DENIED.is_empty());
+        assert!(!DisplayMessages::DEVICE_CARD_DISABLED.is_empty());
+        assert!(!DisplayMessages::DEVICE_BIO_DISABLED.is_empty());
+        assert!(!DisplayMessages::DEVICE_KEYPAD_DISABLED.is_empty());
+        assert!(!DisplayMessages::PIN_INVALID.is_empty());
+        assert!(!DisplayMessages::KEYPAD_LOCKED.is_empty());
+        assert!(!DisplayMessages::PIN_RETRY_IN.is_empty());
+        assert!(!DisplayMessages::CARD_LOCKED.is_empty());
         assert!(!DisplayMessages::ACCESS_GRANTED.is_empty());
+        assert!(!DisplayMessages::ACCESS_GRANTED_EXCEPTION.is_empty());
         assert!(!DisplayMessages::ANTI_PASSBACK.is_empty());
+        assert!(!DisplayMessages::DATA_STALE.is_empty());
+        assert!(!DisplayMessages::DAILY_QUOTA_EXCEEDED.is_empty());
+        assert!(!DisplayMessages::OUTSIDE_SCHEDULE.is_empty());
+        assert!(!DisplayMessages::HOLIDAY_CLOSED.is_empty());
+        assert!(!DisplayMessages::BIOMETRIC_REQUIRED.is_empty());
+        assert!(!DisplayMessages::CARD_REQUIRED.is_empty());
+        assert!(!DisplayMessages::BIOMETRIC_NOT_ENROLLED.is_empty());
+        assert!(!DisplayMessages::BIOMETRIC_MISMATCH.is_empty());
+        assert!(!DisplayMessages::BIOMETRIC_TIMEOUT.is_empty());
+        assert!(!DisplayMessages::DECISION_IN_PROGRESS.is_empty());
+        assert!(!DisplayMessages::BALANCE.is_empty());
+    }
+
+    #[test]
+    fn test_with_balance_keeps_number_within_limit() {
+        let long = "Bem-vindo ao Centro de Distribuicao Sul";
+        let text = DisplayMessages::with_balance(long, 1234);
+        assert_eq!(text.len(), MAX_DISPLAY_MESSAGE_LENGTH);
+        assert!(text.ends_with(" Saldo: 1234"));
+
+        assert_eq!(
+            DisplayMessages::with_balance(DisplayMessages::ACCESS_GRANTED, -3),
+            "Acesso liberado Saldo: 0"
+        );
+        assert_eq!(
+            DisplayMessages::render("{nome} {saldo}", &[("saldo", "1")]),
+            "{nome} 1"
+        );
+    }
+
+    fn sample_datetime() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 10, 27)
+            .unwrap()
+            .and_hms_opt(14, 30, 5)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_catalog_formats_by_locale() {
+        let datetime = sample_datetime();
+        let display = MessageCatalog::display();
+        let log = MessageCatalog::log();
+
+        assert_eq!(display.format_date(datetime.date()), "27/10/2025");
+        assert_eq!(log.format_date(datetime.date()), "2025-10-27");
+        assert_eq!(display.format_time(datetime.time()), "14:30");
+        assert_eq!(log.format_time(datetime.time()), "14:30:05");
+        assert_eq!(display.format_datetime(datetime), "27/10/2025 14:30");
+        assert_eq!(log.format_datetime(datetime), "2025-10-27T14:30:05");
+
+        assert_eq!(display.format_number(0), "0");
+        assert_eq!(display.format_number(999), "999");
+        assert_eq!(display.format_number(1234), "1.234");
+        assert_eq!(display.format_number(-1234567), "-1.234.567");
+        assert_eq!(log.format_number(-1234567), "-1234567");
+        assert_eq!(MessageCatalog::default().locale(), Locale::PtBr);
+    }
+
+    #[test]
+    fn test_catalog_render_substitutes_values() {
+        let datetime = sample_datetime();
+        let vars = [
+            ("nome", TemplateValue::Text("Maria")),
+            ("hora", TemplateValue::Time(datetime.time())),
+            ("saldo", TemplateValue::Number(1500)),
+        ];
+
+        assert_eq!(
+            MessageCatalog::display().render("{nome} {hora} Saldo: {saldo} {x}", &vars),
+            "Maria 14:30 Saldo: 1.500 {x}"
+        );
+        assert_eq!(
+            MessageCatalog::log().render("{nome} {hora} Saldo: {saldo} {x}", &vars),
+            "Maria 14:30:05 Saldo: 1500 {x}"
+        );
+    }
+
+    #[test]
+    fn test_render_display_truncates_text_not_values() {
+        let display = MessageCatalog::display();
+        let vars = [("quando", TemplateValue::DateTime(sample_datetime()))];
+
+        // Fits exactly: 24 chars of text + 16 of date and time
+        let text = display.render_display("Ultimo acesso registrado{quando}", &vars);
+        assert_eq!(text, "Ultimo acesso registrado27/10/2025 14:30");
+        assert_eq!(text.len(), MAX_DISPLAY_MESSAGE_LENGTH);
+
+        // One more char: the value is dropped rather than cut
+        let text = display.render_display("Ultimo acesso registrado: {quando}", &vars);
+        assert_eq!(text, "Ultimo acesso registrado:");
+
+        // Text after the value is cut at the limit
+        let text = display.render_display("{quando} - Bem-vindo ao Centro de Distribuicao", &vars);
+        assert_eq!(text, "27/10/2025 14:30 - Bem-vindo ao Centro d");
+        assert_eq!(text.len(), MAX_DISPLAY_MESSAGE_LENGTH);
+
+        // ISO values are longer and drop sooner
+        let text = MessageCatalog::log().render_display("Ultimo acesso em {quando}", &vars);
+        assert_eq!(text, "Ultimo acesso em 2025-10-27T14:30:05");
     }
 
     /// Verifies messages are in Portuguese (Brazilian market requirement)
@@ -0,0 +1,698 @@
+//! Runtime switching between online, offline and hybrid validation
+//!
+//! A [`Validator`] is picked once at construction time. Real turnstiles,
+//! however, change mode while running: the server can push a new
+//! `TIPO_VALIDA` setting, and a hybrid device drops to local validation when
+//! the link goes down and returns to the server when it comes back.
+//!
+//! [`ValidationModeController`] owns both validators and swaps the active
+//! one between requests. Since [`AccessValidator::validate`] takes
+//! `&mut self`, a switch can never happen while a request is in flight.
+//! Every switch produces a [`ModeChange`], which is sent to an optional
+//! event channel and can be turned into a protocol notification for the
+//! server with [`ModeChange::notification()`].
+//!
+//! # Modes
+//!
+//! | Mode      | Validator used                                        |
+//! |-----------|-------------------------------------------------------|
+//! | `Online`  | Server only; network errors are returned to the caller |
+//! | `Offline` | Local database only                                   |
+//! | `Hybrid`  | Server while reachable, local database while not      |
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::mode::{ModeChangeReason, OperatingMode, ValidationModeController};
+//! use turnkey_storage::{AccessValidator, OfflineValidator, OnlineValidator, OnlineValidatorConfig};
+//! use turnkey_network::{TcpClient, TcpClientConfig};
+//! use turnkey_core::DeviceId;
+//!
+//! # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let device_id = DeviceId::new(15)?;
+//! let online = OnlineValidator::new(
+//!     TcpClient::new(TcpClientConfig::default()),
+//!     device_id,
+//!     OnlineValidatorConfig::default(),
+//! );
+//! let offline = OfflineValidator::new(pool).with_device_id(device_id);
+//!
+//! let mut controller =
+//!     ValidationModeController::new(device_id, online, offline, OperatingMode::Hybrid);
+//!
+//! // Link monitor reports an outage: hybrid devices switch to local validation
+//! if let Some(change) = controller.on_connection_lost() {
+//!     println!("{}", change);
+//! }
+//!
+//! // Operator forces offline operation
+//! controller.set_mode(OperatingMode::Offline, ModeChangeReason::Requested);
+//! assert!(!controller.is_online());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::validator::{
+    AccessValidator, CardVerification, OfflineValidator, OnlineValidator, PendingBiometric,
+    TemplateMatcher, Validator,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tokio::sync::mpsc;
+use turnkey_core::{DeviceId, ValidationMode};
+use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
+use turnkey_protocol::{CommandCode, FieldData, Message};
+
+/// Configuration parameter carrying the validation mode in EC/RC commands
+pub const VALIDATION_MODE_PARAMETER: &str = "TIPO_VALIDA";
+
+/// Operational mode of a device
+///
+/// Serialized in lowercase (`"online"`, `"offline"`, `"hybrid"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperatingMode {
+    /// Validate every request on the server
+    Online,
+    /// Validate every request against the local database
+    Offline,
+    /// Validate on the server, falling back to the local database when the
+    /// server is unreachable
+    Hybrid,
+}
+
+impl OperatingMode {
+    /// Uppercase name used in logs and events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Online => "ONLINE",
+            Self::Offline => "OFFLINE",
+            Self::Hybrid => "HYBRID",
+        }
+    }
+
+    /// Henry `TIPO_VALIDA` code for this mode
+    ///
+    /// Hybrid maps to automatic (`A`), the Henry mode with offline fallback.
+    pub fn validation_mode(&self) -> ValidationMode {
+        match self {
+            Self::Online => ValidationMode::Online,
+            Self::Offline => ValidationMode::Offline,
+            Self::Hybrid => ValidationMode::Automatic,
+        }
+    }
+}
+
+impl From<ValidationMode> for OperatingMode {
+    /// Automatic and semi-automatic both fall back to local validation, so
+    /// both map to `Hybrid`.
+    fn from(mode: ValidationMode) -> Self {
+        match mode {
+            ValidationMode::Online => Self::Online,
+            ValidationMode::Offline => Self::Offline,
+            ValidationMode::Automatic | ValidationMode::SemiAutomatic => Self::Hybrid,
+        }
+    }
+}
+
+impl fmt::Display for OperatingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a mode change happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeChangeReason {
+    /// Changed through [`ValidationModeController::set_mode`]
+    Requested,
+    /// Server sent a `TIPO_VALIDA` configuration command
+    ServerCommand,
+    /// Server became unreachable (hybrid mode only)
+    ConnectionLost,
+    /// Server became reachable again (hybrid mode only)
+    ConnectionRestored,
+    /// Server latency rose above the degrade threshold
+    /// (see [`HybridValidator`](crate::hybrid::HybridValidator))
+    LatencyDegraded,
+    /// Server latency is back below the recover threshold
+    LatencyRecovered,
+    /// Too many consecutive keepalives went unanswered
+    /// (see [`HeartbeatMonitor`](crate::heartbeat::HeartbeatMonitor))
+    HeartbeatMissed,
+    /// Keepalives have been answered steadily again after an outage
+    HeartbeatRestored,
+}
+
+/// Record of one switch of mode or active validator
+///
+/// In hybrid mode `from` and `to` are both `Hybrid` and only `online`
+/// changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeChange {
+    /// Device whose mode changed
+    pub device_id: DeviceId,
+
+    /// Mode before the change
+    pub from: OperatingMode,
+
+    /// Mode after the change
+    pub to: OperatingMode,
+
+    /// Whether the server validator is active after the change
+    pub online: bool,
+
+    /// What triggered the change
+    pub reason: ModeChangeReason,
+
+    /// When the change happened
+    pub changed_at: DateTime<Utc>,
+}
+
+impl ModeChange {
+    /// Notification telling the server about the new mode
+    ///
+    /// Uses the layout of a configuration answer (`RC`):
+    /// `<ID>+REON+RC]00]TIPO_VALIDA]<CODE>]`, where the code reflects the
+    /// validator actually in use (`F` while a hybrid device is cut off).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if the message cannot be built.
+    pub fn notification(&self) -> StorageResult<Message> {
+        let effective = match (self.to, self.online) {
+            (OperatingMode::Hybrid, false) => ValidationMode::Offline,
+            (mode, _) => mode.validation_mode(),
+        };
+
+        let fields = [
+            "00".to_string(),
+            VALIDATION_MODE_PARAMETER.to_string(),
+            effective.to_char().to_string(),
+        ]
+        .into_iter()
+        .map(FieldData::new)
+        .collect::<turnkey_core::Result<Vec<_>>>()
+        .map_err(|e| StorageError::ProtocolError(format!("Mode notification: {}", e)))?;
+
+        Message::new(self.device_id, CommandCode::ReceiveConfig, fields)
+            .map_err(|e| StorageError::ProtocolError(format!("Mode notification: {}", e)))
+    }
+}
+
+impl fmt::Display for ModeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "device {}: {} -> {} ({}, {:?})",
+            self.device_id,
+            self.from,
+            self.to,
+            if self.online { "online" } else { "offline" },
+            self.reason
+        )
+    }
+}
+
+/// Owns the online and offline validators of a device and switches
+/// between them at runtime
+///
+/// See the [module documentation](self) for the mode rules.
+pub struct ValidationModeController {
+    device_id: DeviceId,
+    mode: OperatingMode,
+    active: Validator,
+    standby: Validator,
+    events: Option<mpsc::Sender<ModeChange>>,
+}
+
+impl fmt::Debug for ValidationModeController {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidationModeController")
+            .field("device_id", &self.device_id)
+            .field("mode", &self.mode)
+            .field("online", &self.is_online())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ValidationModeController {
+    /// Create a controller starting in `mode`
+    ///
+    /// Hybrid mode starts online.
+    pub fn new(
+        device_id: DeviceId,
+        online: OnlineValidator,
+        offline: OfflineValidator,
+        mode: OperatingMode,
+    ) -> Self {
+        let online = Validator::Online(Box::new(online));
+        let offline = Validator::Offline(Box::new(offline));
+        let (active, standby) = match mode {
+            OperatingMode::Offline => (offline, online),
+            OperatingMode::Online | OperatingMode::Hybrid => (online, offline),
+        };
+
+        Self {
+            device_id,
+            mode,
+            active,
+            standby,
+            events: None,
+        }
+    }
+
+    /// Send every [`ModeChange`] to `events`
+    ///
+    /// Delivery is best-effort: a full or closed channel drops the event
+    /// rather than blocking validation.
+    pub fn with_mode_events(mut self, events: mpsc::Sender<ModeChange>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Configured mode
+    pub fn mode(&self) -> OperatingMode {
+        self.mode
+    }
+
+    /// Whether requests currently go to the server
+    pub fn is_online(&self) -> bool {
+        matches!(self.active, Validator::Online(_))
+    }
+
+    /// Change the configured mode
+    ///
+    /// Switching to hybrid activates the server validator; the next failed
+    /// request or [`on_connection_lost`](Self::on_connection_lost) moves it
+    /// offline if the server is down.
+    ///
+    /// Returns `None` if the mode is unchanged.
+    pub fn set_mode(
+        &mut self,
+        mode: OperatingMode,
+        reason: ModeChangeReason,
+    ) -> Option<ModeChange> {
+        if mode == self.mode {
+            return None;
+        }
+
+        let from = self.mode;
+        self.mode = mode;
+        self.activate(mode != OperatingMode::Offline);
+        Some(self.emit(from, reason))
+    }
+
+    /// Apply a `TIPO_VALIDA` configuration command from the server
+    ///
+    /// Expects a `SendConfig` (EC) message whose fields contain
+    /// `TIPO_VALIDA` followed by the mode code (`O`, `F`, `A` or `S`). Other
+    /// messages are ignored and return `Ok(None)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the mode code is missing or unknown.
+    pub fn apply_server_command(&mut self, message: &Message) -> StorageResult<Option<ModeChange>> {
+        if message.command != CommandCode::SendConfig {
+            return Ok(None);
+        }
+        let Some(position) = message
+            .fields
+            .iter()
+            .position(|field| field.as_str() == VALIDATION_MODE_PARAMETER)
+        else {
+            return Ok(None);
+        };
+
+        let code = message
+            .fields
+            .get(position + 1)
+            .map(|field| field.as_str())
+            .unwrap_or_default();
+        let mut chars = code.chars();
+        let mode = match (chars.next(), chars.next()) {
+            (Some(c), None) => ValidationMode::from_char(c).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            StorageError::Validation(format!(
+                "Invalid {} value '{}'",
+                VALIDATION_MODE_PARAMETER, code
+            ))
+        })?;
+
+        Ok(self.set_mode(mode.into(), ModeChangeReason::ServerCommand))
+    }
+
+    /// Report that the server became unreachable
+    ///
+    /// Hybrid devices switch to local validation. Online devices keep
+    /// trying the server, and offline devices are unaffected.
+    pub fn on_connection_lost(&mut self) -> Option<ModeChange> {
+        self.switch_link(false, ModeChangeReason::ConnectionLost)
+    }
+
+    /// Report that the server is reachable again
+    ///
+    /// Hybrid devices running locally switch back to the server.
+    pub fn on_connection_restored(&mut self) -> Option<ModeChange> {
+        self.switch_link(true, ModeChangeReason::ConnectionRestored)
+    }
+
+    /// Move a hybrid device to or from local validation
+    ///
+    /// Returns `None` outside hybrid mode or if the device is already there.
+    pub(crate) fn switch_link(
+        &mut self,
+        online: bool,
+        reason: ModeChangeReason,
+    ) -> Option<ModeChange> {
+        if self.mode != OperatingMode::Hybrid || self.is_online() == online {
+            return None;
+        }
+        self.activate(online);
+        Some(self.emit(self.mode, reason))
+    }
+
+    /// Validate the card step of a passage
+    ///
+    /// Server validators decide in one step, with the same hybrid fallback
+    /// as [`validate()`](AccessValidator::validate). Locally the card goes
+    /// through [`OfflineValidator::validate_card()`], so a card + biometric
+    /// device gets the card owner's templates back instead of a
+    /// `BIOMETRIC_REQUIRED` denial, and finishes the passage with
+    /// [`verify_biometric()`](Self::verify_biometric) or
+    /// [`biometric_timeout()`](Self::biometric_timeout).
+    ///
+    /// # Errors
+    ///
+    /// As [`validate()`](AccessValidator::validate).
+    pub async fn validate_card(
+        &mut self,
+        request: &AccessRequest,
+    ) -> StorageResult<CardVerification> {
+        if self.is_online() {
+            match self.active.validate(request).await {
+                Err(StorageError::NetworkError(_) | StorageError::ValidationFailed(..))
+                    if self.mode == OperatingMode::Hybrid =>
+                {
+                    self.on_connection_lost();
+                }
+                result => return result.map(CardVerification::Complete),
+            }
+        }
+        self.offline().validate_card(request).await
+    }
+
+    /// Verify the fingerprint of a card accepted by [`validate_card()`](Self::validate_card)
+    ///
+    /// Runs on the local validator whatever the mode, since only it hands
+    /// out pending passages.
+    ///
+    /// # Errors
+    ///
+    /// As [`OfflineValidator::verify_biometric()`].
+    pub async fn verify_biometric<M: TemplateMatcher>(
+        &self,
+        request: &AccessRequest,
+        pending: &PendingBiometric,
+        matcher: &mut M,
+    ) -> StorageResult<AccessResponse> {
+        self.offline()
+            .verify_biometric(request, pending, matcher)
+            .await
+    }
+
+    /// Log a card accepted by [`validate_card()`](Self::validate_card)
+    /// whose fingerprint never came
+    ///
+    /// # Errors
+    ///
+    /// As [`OfflineValidator::biometric_timeout()`].
+    pub async fn biometric_timeout(
+        &self,
+        request: &AccessRequest,
+        pending: &PendingBiometric,
+    ) -> StorageResult<AccessResponse> {
+        self.offline().biometric_timeout(request, pending).await
+    }
+
+    /// The local validator, active or on standby
+    fn offline(&self) -> &OfflineValidator {
+        match (&self.active, &self.standby) {
+            (Validator::Offline(offline), _) | (_, Validator::Offline(offline)) => offline,
+            _ => unreachable!("the controller always owns an offline validator"),
+        }
+    }
+
+    /// Swap validators if the active one is not the requested one
+    fn activate(&mut self, online: bool) {
+        if self.is_online() != online {
+            std::mem::swap(&mut self.active, &mut self.standby);
+        }
+    }
+
+    fn emit(&self, from: OperatingMode, reason: ModeChangeReason) -> ModeChange {
+        let change = ModeChange {
+            device_id: self.device_id,
+            from,
+            to: self.mode,
+            online: self.is_online(),
+            reason,
+            changed_at: Utc::now(),
+        };
+
+        if let Some(events) = &self.events {
+            let _ = events.try_send(change.clone());
+        }
+        change
+    }
+}
+
+impl AccessValidator for ValidationModeController {
+    /// Validate with the active validator
+    ///
+    /// In hybrid mode a network failure of the server validator switches
+    /// the device offline and the same request is validated locally.
+    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        match self.active.validate(request).await {
+            Err(StorageError::NetworkError(_) | StorageError::ValidationFailed(..))
+                if self.mode == OperatingMode::Hybrid && self.is_online() =>
+            {
+                self.on_connection_lost();
+                self.active.validate(request).await
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::validator::OnlineValidatorConfig;
+    use std::time::Duration;
+    use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
+    use turnkey_network::{TcpClient, TcpClientConfig};
+
+    /// Controller whose server validator points at a closed local port
+    async fn controller(db: &Database, mode: OperatingMode) -> ValidationModeController {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let device_id = DeviceId::new(15).unwrap();
+        let online = OnlineValidator::new(
+            TcpClient::new(TcpClientConfig {
+                server_addr,
+                timeout: Duration::from_millis(200),
+            }),
+            device_id,
+            OnlineValidatorConfig {
+                max_retries: 0,
+                ..Default::default()
+            },
+        );
+        let offline = OfflineValidator::new(db.pool().clone());
+
+        ValidationModeController::new(device_id, online, offline, mode)
+    }
+
+    fn request() -> AccessRequest {
+        AccessRequest::new(
+            "99999999".to_string(),
+            HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            AccessDirection::Entry,
+            ReaderType::Rfid,
+        )
+        .unwrap()
+    }
+
+    fn config_message(code: &str) -> Message {
+        let fields = ["00", VALIDATION_MODE_PARAMETER, code]
+            .into_iter()
+            .map(|value| FieldData::new(value.to_string()).unwrap())
+            .collect();
+        Message::new(DeviceId::new(15).unwrap(), CommandCode::SendConfig, fields).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_initial_validator_follows_mode() {
+        let db = Database::in_memory().await.unwrap();
+
+        assert!(controller(&db, OperatingMode::Online).await.is_online());
+        assert!(controller(&db, OperatingMode::Hybrid).await.is_online());
+        assert!(!controller(&db, OperatingMode::Offline).await.is_online());
+    }
+
+    #[tokio::test]
+    async fn test_online_mode_returns_network_errors() {
+        let db = Database::in_memory().await.unwrap();
+        let mut controller = controller(&db, OperatingMode::Online).await;
+
+        assert!(controller.validate(&request()).await.is_err());
+        assert!(controller.is_online());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_falls_back_and_recovers() {
+        let db = Database::in_memory().await.unwrap();
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut controller = controller(&db, OperatingMode::Hybrid)
+            .await
+            .with_mode_events(tx);
+
+        // Server down: request is answered locally (unknown card is denied)
+        let response = controller.validate(&request()).await.unwrap();
+        assert!(!response.is_grant());
+        assert!(!controller.is_online());
+
+        let lost = rx.try_recv().unwrap();
+        assert_eq!(lost.reason, ModeChangeReason::ConnectionLost);
+        assert_eq!(lost.to, OperatingMode::Hybrid);
+        assert!(!lost.online);
+
+        let restored = controller.on_connection_restored().unwrap();
+        assert!(restored.online);
+        assert!(controller.is_online());
+        assert!(controller.on_connection_restored().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_card_step_falls_back_to_local_validation() {
+        let db = Database::in_memory().await.unwrap();
+        let mut online = controller(&db, OperatingMode::Online).await;
+        let mut hybrid = controller(&db, OperatingMode::Hybrid).await;
+
+        assert!(online.validate_card(&request()).await.is_err());
+
+        let verification = hybrid.validate_card(&request()).await.unwrap();
+        let CardVerification::Complete(response) = verification else {
+            panic!("an unknown card needs no fingerprint");
+        };
+        assert!(response.is_deny());
+        assert!(!hybrid.is_online());
+    }
+
+    #[tokio::test]
+    async fn test_connectivity_events_ignored_outside_hybrid() {
+        let db = Database::in_memory().await.unwrap();
+        let mut online = controller(&db, OperatingMode::Online).await;
+        let mut offline = controller(&db, OperatingMode::Offline).await;
+
+        assert!(online.on_connection_lost().is_none());
+        assert!(online.is_online());
+        assert!(offline.on_connection_restored().is_none());
+        assert!(!offline.is_online());
+    }
+
+    #[tokio::test]
+    async fn test_set_mode_swaps_validator() {
+        let db = Database::in_memory().await.unwrap();
+        let mut controller = controller(&db, OperatingMode::Online).await;
+
+        let change = controller
+            .set_mode(OperatingMode::Offline, ModeChangeReason::Requested)
+            .unwrap();
+        assert_eq!(change.from, OperatingMode::Online);
+        assert_eq!(change.to, OperatingMode::Offline);
+        assert!(!controller.is_online());
+
+        // Offline validation works without a server
+        assert!(controller.validate(&request()).await.is_ok());
+
+        assert!(
+            controller
+                .set_mode(OperatingMode::Offline, ModeChangeReason::Requested)
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_server_command() {
+        let db = Database::in_memory().await.unwrap();
+        let mut controller = controller(&db, OperatingMode::Online).await;
+
+        let change = controller
+            .apply_server_command(&config_message("A"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.reason, ModeChangeReason::ServerCommand);
+        assert_eq!(controller.mode(), OperatingMode::Hybrid);
+
+        controller
+            .apply_server_command(&config_message("F"))
+            .unwrap();
+        assert_eq!(controller.mode(), OperatingMode::Offline);
+
+        assert!(matches!(
+            controller.apply_server_command(&config_message("X")),
+            Err(StorageError::Validation(_))
+        ));
+
+        let status =
+            Message::new(DeviceId::new(15).unwrap(), CommandCode::QueryStatus, vec![]).unwrap();
+        assert!(controller.apply_server_command(&status).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_notification_reports_effective_mode() {
+        let change = ModeChange {
+            device_id: DeviceId::new(15).unwrap(),
+            from: OperatingMode::Hybrid,
+            to: OperatingMode::Hybrid,
+            online: false,
+            reason: ModeChangeReason::ConnectionLost,
+            changed_at: Utc::now(),
+        };
+
+        let message = change.notification().unwrap();
+        assert_eq!(message.command, CommandCode::ReceiveConfig);
+        assert_eq!(message.fields[1].as_str(), VALIDATION_MODE_PARAMETER);
+        assert_eq!(message.fields[2].as_str(), "F");
+
+        let online = ModeChange {
+            online: true,
+            ..change
+        };
+        assert_eq!(online.notification().unwrap().fields[2].as_str(), "A");
+    }
+
+    #[test]
+    fn test_validation_mode_mapping() {
+        assert_eq!(
+            OperatingMode::from(ValidationMode::SemiAutomatic),
+            OperatingMode::Hybrid
+        );
+        for mode in [
+            OperatingMode::Online,
+            OperatingMode::Offline,
+            OperatingMode::Hybrid,
+        ] {
+            assert_eq!(OperatingMode::from(mode.validation_mode()), mode);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::TemporalValidity;
+
+/// Temporary access window for an event or visitor group
+///
+/// An exception grants access to a group of cards and/or users between
+/// `starts_at` and `ends_at`, optionally limited to specific devices. It
+/// takes precedence over the regular validity period of the card or user,
+/// so visitor badges that are not registered (or already expired) still
+/// work during the event.
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `name` - Event or group description (1-100 chars)
+/// * `starts_at` - When the window opens
+/// * `ends_at` - When the window closes
+/// * `active` - Whether the exception is enabled
+/// * `created_at` - Record creation timestamp
+/// * `updated_at` - Record last modification timestamp
+///
+/// # Database Schema
+///
+/// Maps to the `access_exceptions` table. Group membership is stored in
+/// `access_exception_members` (one card number or matricula per row) and
+/// device restrictions in `access_exception_devices` and
+/// `access_exception_zones`. An exception with neither applies to every
+/// device.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{AccessException, TemporalValidity};
+/// use chrono::{Duration, Utc};
+///
+/// let event = AccessException::new(
+///     "Feira de Tecnologia",
+///     Utc::now() - Duration::hours(1),
+///     Utc::now() + Duration::hours(8),
+/// );
+///
+/// assert!(event.is_valid());
+/// assert!(!event.is_open_at(Utc::now() + Duration::days(1)));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AccessException {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Event or group description
+    pub name: String,
+
+    /// Start of the access window
+    pub starts_at: DateTime<Utc>,
+
+    /// End of the access window
+    pub ends_at: DateTime<Utc>,
+
+    /// Whether the exception is enabled
+    ///
+    /// Disabled exceptions are ignored during validation.
+    pub active: bool,
+
+    /// Record creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Record last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AccessException {
+    /// Create a new active exception for the given window
+    pub fn new(name: impl Into<String>, starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            name: name.into(),
+            starts_at,
+            ends_at,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Check if the exception is active and `at` falls inside the window
+    pub fn is_open_at(&self, at: DateTime<Utc>) -> bool {
+        self.active && self.starts_at <= at && at <= self.ends_at
+    }
+}
+
+impl TemporalValidity for AccessException {
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn validity_start(&self) -> Option<DateTime<Utc>> {
+        Some(self.starts_at)
+    }
+
+    fn validity_end(&self) -> Option<DateTime<Utc>> {
+        Some(self.ends_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn create_test_exception() -> AccessException {
+        AccessException::new(
+            "Evento",
+            Utc::now() - Duration::hours(1),
+            Utc::now() + Duration::hours(1),
+        )
+    }
+
+    #[test]
+    fn test_exception_open_inside_window() {
+        let exception = create_test_exception();
+        assert!(exception.is_open_at(Utc::now()));
+        assert!(exception.is_valid());
+    }
+
+    #[test]
+    fn test_exception_closed_outside_window() {
+        let exception = create_test_exception();
+        assert!(!exception.is_open_at(Utc::now() - Duration::hours(2)));
+        assert!(!exception.is_open_at(Utc::now() + Duration::hours(2)));
+    }
+
+    #[test]
+    fn test_exception_inactive() {
+        let mut exception = create_test_exception();
+        exception.active = false;
+        assert!(!exception.is_open_at(Utc::now()));
+        assert!(!exception.is_valid());
+    }
+}
@@ -17,8 +17,12 @@ use serde::{Deserialize, Serialize};
 /// * `reader_type` - Type of reader used (1=RFID, 5=biometric)
 /// * `granted` - Whether access was granted (true) or denied (false)
 /// * `display_message` - Message shown to user (e.g., "Acesso liberado", "Acesso negado")
-/// * `timestamp` - When the access attempt occurred (from device/request)
+/// * `timestamp` - When the access attempt was received (server clock)
 /// * `created_at` - When the log was written to database
+/// * `device_id` - Henry device ID that reported the event (NULL if unknown)
+/// * `device_timestamp` - Event time as reported by the device clock (NULL if unknown)
+/// * `correlation_id` - Passage identifier assigned at read time (NULL if the device sent none)
+/// * `nsr` - Sequential record number of the event on its device (NULL if not numbered)
 ///
 /// # Database Schema
 ///
@@ -27,6 +31,8 @@ use serde::{Deserialize, Serialize};
 /// - Required card number: Always present, even for denied or unregistered attempts
 /// - Indexed columns: `user_id`, `card_number`, `timestamp`, `granted` for query performance
 /// - Dual timestamp strategy: `timestamp` (event time) vs `created_at` (log time)
+/// - Clock reconciliation: `device_timestamp` (device clock) vs `timestamp` (server clock)
+/// - Event numbering: `nsr` increases by one with every event of a device and is never reused
 ///
 /// # Security and Compliance
 ///
@@ -123,15 +129,45 @@ pub struct AccessLog {
     /// Optional but recommended for audit trail clarity.
     pub display_message: Option<String>,
 
-    /// Timestamp when the access attempt occurred (from device/request)
+    /// Timestamp when the access attempt was received (server clock)
     ///
-    /// This is the event time from the access control device, not the database write time.
+    /// This is the authoritative event time used for ordering and anti-passback.
+    /// The time reported by the device itself is kept in `device_timestamp`.
     pub timestamp: DateTime<Utc>,
 
     /// Record creation timestamp (when logged to database)
     ///
     /// This may differ from `timestamp` due to network delays or offline queueing.
     pub created_at: DateTime<Utc>,
+
+    /// Henry device ID (1-99) that reported the event
+    ///
+    /// NULL for logs written before device tracking or by unidentified devices.
+    pub device_id: Option<i64>,
+
+    /// Event time as reported by the device clock (from the protocol message)
+    ///
+    /// Compared against `timestamp` to measure device clock drift.
+    /// See [`AccessLog::clock_drift`].
+    pub device_timestamp: Option<DateTime<Utc>>,
+
+    /// Correlation ID of the passage, as a hyphenated UUID
+    ///
+    /// Generated when the credential was read and carried in the access
+    /// request, so this entry can be joined with device and server logs.
+    pub correlation_id: Option<String>,
+
+    /// NSR (Numero Sequencial de Registro) of the entry on its device
+    ///
+    /// Like the event numbers of Henry equipment, it increases by one with
+    /// every event of the device and is never reused. A device numbers the
+    /// logs it writes from the counter of its queued events (see
+    /// [`Outbox::number_access`](crate::outbox::Outbox::number_access)), so
+    /// the log, its `ER` event and its turnstile notifications carry the
+    /// same NSR; a server keeps the NSR of pushed events. NULL for entries
+    /// that were not numbered. See
+    /// [`find_by_nsr_range`](crate::repositories::AccessLogRepository::find_by_nsr_range).
+    pub nsr: Option<i64>,
 }
 
 /// Direction of access (entry or exit)
@@ -232,12 +268,13 @@ impl From<Direction> for i32 {
 /// # Protocol Mapping
 ///
 /// - `1` - RFID: RFID/NFC card reader (proximity cards, Mifare, etc.)
+/// - `3` - Keypad: Numeric code typed on the device
 /// - `5` - Biometric: Fingerprint biometric reader
 ///
 /// # Note
 ///
-/// The protocol reserves additional codes for other reader types (keypad=0, barcode=2, etc.),
-/// but this implementation currently supports only RFID and biometric readers.
+/// The protocol reserves additional codes for other reader types (barcode=2, etc.),
+/// but this implementation currently supports only RFID, keypad and biometric readers.
 ///
 /// # Examples
 ///
@@ -256,6 +293,8 @@ impl From<Direction> for i32 {
 pub enum ReaderType {
     /// RFID/NFC card reader (proximity cards, Mifare, etc.)
     Rfid = 1,
+    /// Numeric code typed on the device keypad
+    Keypad = 3,
     /// Fingerprint biometric reader
     Biometric = 5,
 }
@@ -269,7 +308,7 @@ impl ReaderType {
     ///
     /// # Returns
     ///
-    /// Returns `Some(ReaderType)` if the value is valid (1 or 5), `None` otherwise.
+    /// Returns `Some(ReaderType)` if the value is valid (1, 3 or 5), `None` otherwise.
     ///
     /// # Examples
     ///
@@ -283,6 +322,7 @@ impl ReaderType {
     pub fn from_i32(value: i32) -> Option<Self> {
         match value {
             1 => Some(Self::Rfid),
+            3 => Some(Self::Keypad),
             5 => Some(Self::Biometric),
             _ => None,
         }
@@ -303,6 +343,7 @@ impl ReaderType {
     pub fn display_name(&self) -> &'static str {
         match self {
             Self::Rfid => "RFID",
+            Self::Keypad => "Teclado",
             Self::Biometric => "Biométrico",
         }
     }
@@ -338,9 +379,65 @@ impl AccessLog {
             display_message,
             timestamp,
             created_at: Utc::now(),
+            device_id: None,
+            device_timestamp: None,
+            correlation_id: None,
+            nsr: None,
         }
     }
 
+    /// Attach the reporting device and its clock reading to this entry
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_storage::models::{AccessLog, Direction, ReaderType};
+    /// use chrono::{Duration, Utc};
+    ///
+    /// let received = Utc::now();
+    /// let log = AccessLog::new(
+    ///     None,
+    ///     None,
+    ///     "1234567890".to_string(),
+    ///     Direction::Entry,
+    ///     ReaderType::Rfid,
+    ///     false,
+    ///     None,
+    ///     received,
+    /// )
+    /// .with_device_clock(15, received - Duration::seconds(90));
+    ///
+    /// assert_eq!(log.device_id, Some(15));
+    /// assert_eq!(log.clock_drift(), Some(Duration::seconds(-90)));
+    /// ```
+    pub fn with_device_clock(mut self, device_id: i64, device_timestamp: DateTime<Utc>) -> Self {
+        self.device_id = Some(device_id);
+        self.device_timestamp = Some(device_timestamp);
+        self
+    }
+
+    /// Attach the correlation ID of the passage this entry records
+    pub fn with_correlation_id(mut self, correlation_id: turnkey_core::CorrelationId) -> Self {
+        self.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+
+    /// Attach the NSR the reporting device gave this entry
+    pub fn with_nsr(mut self, nsr: i64) -> Self {
+        self.nsr = Some(nsr);
+        self
+    }
+
+    /// Get the device clock drift for this event
+    ///
+    /// Returns `device_timestamp - timestamp`: positive when the device clock
+    /// is ahead of the server, negative when it is behind. Returns `None` if
+    /// the device did not report a timestamp.
+    pub fn clock_drift(&self) -> Option<chrono::Duration> {
+        self.device_timestamp
+            .map(|device_ts| device_ts.signed_duration_since(self.timestamp))
+    }
+
     /// Get the direction as an enum
     pub fn get_direction(&self) -> Option<Direction> {
         Direction::from_i32(self.direction)
@@ -384,6 +481,7 @@ mod tests {
     #[test]
     fn test_reader_type_from_i32() {
         assert_eq!(ReaderType::from_i32(1), Some(ReaderType::Rfid));
+        assert_eq!(ReaderType::from_i32(3), Some(ReaderType::Keypad));
         assert_eq!(ReaderType::from_i32(5), Some(ReaderType::Biometric));
         assert_eq!(ReaderType::from_i32(2), None);
     }
@@ -434,4 +532,26 @@ mod tests {
         assert!(log.was_denied());
         assert!(!log.was_granted());
     }
+
+    #[test]
+    fn test_access_log_clock_drift() {
+        let received = Utc::now();
+        let log = AccessLog::new(
+            None,
+            None,
+            "1234567890".to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            true,
+            None,
+            received,
+        );
+
+        assert_eq!(log.device_id, None);
+        assert_eq!(log.clock_drift(), None);
+
+        let log = log.with_device_clock(15, received + chrono::Duration::seconds(42));
+        assert_eq!(log.device_id, Some(15));
+        assert_eq!(log.clock_drift(), Some(chrono::Duration::seconds(42)));
+    }
 }
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use turnkey_core::DeviceId;
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::OverrideGrant;
+
+use super::AccessLog;
+use crate::error::{StorageError, StorageResult};
+
+/// Operator override of a denied access
+///
+/// Links the granted access log written for the override to the denial it
+/// overrides, together with the guard who decided and the reason given.
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `denied_log_id` - Access log of the original denial
+/// * `granted_log_id` - Access log of the grant written for the override
+/// * `operator_id` - Guard who granted the override
+/// * `reason` - Justification given by the guard
+/// * `device_id` - Device released (copied from the denial, NULL if unknown)
+/// * `created_at` - When the override was granted
+///
+/// # Database Schema
+///
+/// Maps to the `access_overrides` table. Both log IDs are unique: a denial
+/// can be overridden once, and every override has its own grant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AccessOverride {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Access log of the denial
+    pub denied_log_id: i64,
+
+    /// Access log of the override grant
+    pub granted_log_id: i64,
+
+    /// Guard who granted the override
+    pub operator_id: String,
+
+    /// Justification given by the guard
+    pub reason: String,
+
+    /// Device released, if the denial recorded one
+    pub device_id: Option<i64>,
+
+    /// When the override was granted
+    pub created_at: DateTime<Utc>,
+}
+
+/// Result of granting an override
+///
+/// Holds the audit record and the granted access log, which carries the
+/// card, direction and correlation ID of the denied passage.
+#[derive(Debug, Clone)]
+pub struct AccessOverrideOutcome {
+    /// Audit record linking the grant to the denial
+    pub record: AccessOverride,
+
+    /// Granted access log written for the override
+    pub granted_log: AccessLog,
+}
+
+impl AccessOverrideOutcome {
+    /// Command telling the device to release the turnstile
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if a stored value cannot be encoded.
+    pub fn grant(&self) -> StorageResult<OverrideGrant> {
+        OverrideGrant::new(
+            self.granted_log.card_number.clone(),
+            self.record.operator_id.clone(),
+            self.record.reason.clone(),
+        )
+        .map_err(|e| StorageError::ProtocolError(format!("Override grant: {}", e)))
+    }
+
+    /// `00+40` message for the device, addressed to `device_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if a stored value cannot be encoded.
+    pub fn grant_message(&self, device_id: DeviceId) -> StorageResult<Message> {
+        self.grant()?
+            .to_message(device_id)
+            .map_err(|e| StorageError::ProtocolError(format!("Override grant: {}", e)))
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{AccessLog, Direction};
+
+/// Derived access state for a single credential
+///
+/// Summarizes everything `access_logs` says about one card number:
+/// counters, the last access, and whether the holder is currently inside.
+/// The table is maintained by a trigger on `access_logs` and can be rebuilt
+/// from scratch with [`AccessStateReplay`](crate::replay::AccessStateReplay).
+///
+/// # Fields
+///
+/// * `card_number` - Credential identifier (primary key)
+/// * `user_id` - Most recent identified user (NULL if never identified)
+/// * `granted_count` - Number of granted attempts
+/// * `denied_count` - Number of denied attempts
+/// * `last_access_at` - Time of the most recent attempt (any outcome)
+/// * `last_granted_at` - Time of the most recent granted attempt
+/// * `last_direction` - Direction of the most recent granted attempt (0/1/2)
+/// * `inside` - Whether the last granted attempt was an entry
+/// * `last_log_id` - Highest `access_logs.id` folded into this row
+///
+/// # Database Schema
+///
+/// Maps to the `access_state` table. "Most recent" follows log insertion
+/// order (`access_logs.id`), matching the order in which the trigger sees
+/// events.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{AccessLog, AccessState, Direction, ReaderType};
+/// use chrono::Utc;
+///
+/// let log = AccessLog::new(
+///     Some(1),
+///     Some("EMP001".to_string()),
+///     "1234567890".to_string(),
+///     Direction::Entry,
+///     ReaderType::Rfid,
+///     true,
+///     None,
+///     Utc::now(),
+/// );
+///
+/// let state = AccessState::from_log(&log);
+/// assert!(state.inside);
+/// assert_eq!(state.granted_count, 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AccessState {
+    /// Credential identifier
+    pub card_number: String,
+
+    /// Most recent identified user for this credential
+    pub user_id: Option<i64>,
+
+    /// Number of granted attempts
+    pub granted_count: i64,
+
+    /// Number of denied attempts
+    pub denied_count: i64,
+
+    /// Time of the most recent attempt (server clock)
+    pub last_access_at: DateTime<Utc>,
+
+    /// Time of the most recent granted attempt
+    pub last_granted_at: Option<DateTime<Utc>>,
+
+    /// Direction code of the most recent granted attempt
+    ///
+    /// Use `Direction::from_i32()` to convert.
+    pub last_direction: Option<i32>,
+
+    /// Occupancy flag: the last granted attempt was an entry
+    pub inside: bool,
+
+    /// Highest access log ID included in this state
+    pub last_log_id: i64,
+}
+
+impl AccessState {
+    /// Start a new state from the first log of a credential
+    pub fn from_log(log: &AccessLog) -> Self {
+        let mut state = Self {
+            card_number: log.card_number.clone(),
+            user_id: None,
+            granted_count: 0,
+            denied_count: 0,
+            last_access_at: log.timestamp,
+            last_granted_at: None,
+            last_direction: None,
+            inside: false,
+            last_log_id: log.id,
+        };
+        state.apply(log);
+        state
+    }
+
+    /// Fold the next log (in ID order) into this state
+    ///
+    /// Mirrors the `update_access_state_on_log` trigger.
+    pub fn apply(&mut self, log: &AccessLog) {
+        if log.user_id.is_some() {
+            self.user_id = log.user_id;
+        }
+
+        if log.granted {
+            self.granted_count += 1;
+            self.last_granted_at = Some(log.timestamp);
+            self.last_direction = Some(log.direction);
+            self.inside = log.direction == Direction::Entry as i32;
+        } else {
+            self.denied_count += 1;
+        }
+
+        self.last_access_at = log.timestamp;
+        self.last_log_id = log.id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ReaderType;
+    use chrono::Duration;
+
+    fn log(id: i64, user_id: Option<i64>, direction: Direction, granted: bool) -> AccessLog {
+        let mut log = AccessLog::new(
+            user_id,
+            None,
+            "1234567890".to_string(),
+            direction,
+            ReaderType::Rfid,
+            granted,
+            None,
+            Utc::now() + Duration::seconds(id),
+        );
+        log.id = id;
+        log
+    }
+
+    #[test]
+    fn test_apply_entry_then_exit() {
+        let mut state = AccessState::from_log(&log(1, Some(7), Direction::Entry, true));
+        assert!(state.inside);
+
+        state.apply(&log(2, Some(7), Direction::Exit, true));
+        assert!(!state.inside);
+        assert_eq!(state.granted_count, 2);
+        assert_eq!(state.last_direction, Some(Direction::Exit as i32));
+        assert_eq!(state.last_log_id, 2);
+    }
+
+    #[test]
+    fn test_apply_denied_keeps_occupancy() {
+        let entry = log(1, Some(7), Direction::Entry, true);
+        let mut state = AccessState::from_log(&entry);
+
+        let denied = log(2, None, Direction::Exit, false);
+        state.apply(&denied);
+
+        assert!(state.inside);
+        assert_eq!(state.denied_count, 1);
+        assert_eq!(state.user_id, Some(7));
+        assert_eq!(state.last_granted_at, Some(entry.timestamp));
+        assert_eq!(state.last_access_at, denied.timestamp);
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Default anti-passback window in seconds (5 minutes)
+pub const DEFAULT_ANTI_PASSBACK_WINDOW_SECS: i64 = 300;
+
+/// Anti-passback window of one user, replacing the window of every device
+///
+/// # Fields
+///
+/// * `user_id` - User the window applies to
+/// * `window_seconds` - Seconds a repeated direction is denied (0 disables
+///   anti-passback for the user)
+/// * `created_at` / `updated_at` - Audit timestamps
+///
+/// # Database Schema
+///
+/// Maps to the `user_anti_passback` table. Users without a row get the
+/// window of the device they pass through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserAntiPassback {
+    /// User the window applies to
+    pub user_id: i64,
+
+    /// Seconds a repeated direction is denied (0 disables)
+    pub window_seconds: i64,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UserAntiPassback {
+    /// Window applied to the user, `None` if anti-passback is disabled
+    pub fn window(&self) -> Option<Duration> {
+        (self.window_seconds > 0).then(|| Duration::seconds(self.window_seconds))
+    }
+}
+
+/// How long a repeated direction is denied on a device
+///
+/// After a granted entry, another entry of the same user is denied until
+/// the window has passed or the user has exited (and the same for exits).
+/// `window` applies to every user of the device, unless the user has a
+/// [`UserAntiPassback`] of their own and `user_overrides` is set.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::AntiPassbackPolicy;
+/// use chrono::Duration;
+///
+/// let policy = AntiPassbackPolicy::new(Duration::minutes(10));
+/// assert_eq!(policy.window, Some(Duration::minutes(10)));
+/// assert_eq!(AntiPassbackPolicy::default().window, Some(Duration::minutes(5)));
+///
+/// // A user override replaces the device window, 0 seconds disables it
+/// assert_eq!(policy.window_for(Some(0)), None);
+/// assert_eq!(policy.window_for(Some(60)), Some(Duration::minutes(1)));
+/// assert_eq!(policy.window_for(None), Some(Duration::minutes(10)));
+///
+/// // Unless the device ignores overrides
+/// let strict = policy.user_overrides(false);
+/// assert_eq!(strict.window_for(Some(0)), Some(Duration::minutes(10)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AntiPassbackPolicy {
+    /// Window of users without an override (`None` disables anti-passback)
+    pub window: Option<Duration>,
+
+    /// Whether per-user windows replace `window`
+    pub user_overrides: bool,
+}
+
+impl AntiPassbackPolicy {
+    /// Deny a repeated direction for `window` (zero or less disables)
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: (window > Duration::zero()).then_some(window),
+            user_overrides: true,
+        }
+    }
+
+    /// Never deny a repeated direction, unless a user has a window of their own
+    pub fn disabled() -> Self {
+        Self {
+            window: None,
+            user_overrides: true,
+        }
+    }
+
+    /// Set whether per-user windows replace the device window
+    pub fn user_overrides(mut self, enabled: bool) -> Self {
+        self.user_overrides = enabled;
+        self
+    }
+
+    /// Window applied to a user with the override `user_window_seconds`
+    pub fn window_for(&self, user_window_seconds: Option<i64>) -> Option<Duration> {
+        match user_window_seconds {
+            Some(seconds) if self.user_overrides => {
+                (seconds > 0).then(|| Duration::seconds(seconds))
+            }
+            _ => self.window,
+        }
+    }
+}
+
+impl Default for AntiPassbackPolicy {
+    fn default() -> Self {
+        Self::new(Duration::seconds(DEFAULT_ANTI_PASSBACK_WINDOW_SECS))
+    }
+}
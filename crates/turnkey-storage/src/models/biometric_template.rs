@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Smallest fingerprint template accepted by `biometric_templates`, in bytes
+pub const MIN_TEMPLATE_SIZE: usize = 500;
+
+/// Largest fingerprint template accepted by `biometric_templates`, in bytes
+pub const MAX_TEMPLATE_SIZE: usize = 2000;
+
+/// Enrolled fingerprint of a user
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `matricula` - Owner's matricula
+/// * `user_id` - Owner's user ID
+/// * `posicao` - Finger position (0 = right thumb ... 9 = left pinky)
+/// * `template_data` - Scanner-specific template, 500-2000 bytes
+/// * `created_at` - Creation timestamp
+/// * `updated_at` - Last update timestamp
+///
+/// # Database Schema
+///
+/// Maps to the `biometric_templates` table, unique by `(matricula, posicao)`.
+/// Templates can only be stored for users with `allow_bio` enabled.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BiometricTemplate {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Owner's matricula
+    pub matricula: String,
+
+    /// Owner's user ID
+    pub user_id: i64,
+
+    /// Finger position (0-9)
+    pub posicao: i64,
+
+    /// Template data (format is scanner-specific)
+    pub template_data: Vec<u8>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for BiometricTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BiometricTemplate")
+            .field("id", &self.id)
+            .field("matricula", &self.matricula)
+            .field("user_id", &self.user_id)
+            .field("posicao", &self.posicao)
+            .field("template_len", &self.template_data.len())
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .finish()
+    }
+}
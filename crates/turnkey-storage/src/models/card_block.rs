@@ -0,0 +1,350 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use turnkey_core::DeviceId;
+use turnkey_protocol::{CommandCode, FieldData, Message};
+
+use super::Card;
+use crate::error::{StorageError, StorageResult};
+
+/// Maximum number of cards carried by one card sync message
+///
+/// Keeps each ECAR frame well below the codec frame size limit.
+pub const MAX_CARDS_PER_SYNC_MESSAGE: usize = 20;
+
+/// Bulk card operation kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockAction {
+    /// Deactivate cards
+    Block,
+    /// Reactivate cards
+    Unblock,
+}
+
+impl BlockAction {
+    /// Code stored in `card_block_batches.action`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "BLOCK",
+            Self::Unblock => "UNBLOCK",
+        }
+    }
+
+    /// Parse a stored action code
+    pub fn parse(code: &str) -> Option<Self> {
+        match code {
+            "BLOCK" => Some(Self::Block),
+            "UNBLOCK" => Some(Self::Unblock),
+            _ => None,
+        }
+    }
+
+    /// ECAR operation sent to devices: delete on block, insert on unblock
+    pub fn sync_operation(&self) -> &'static str {
+        match self {
+            Self::Block => "E",
+            Self::Unblock => "I",
+        }
+    }
+}
+
+/// Reason recorded for a bulk block or unblock
+///
+/// Stored as a stable uppercase code so reports do not depend on the enum
+/// order.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::BlockReason;
+///
+/// assert_eq!(BlockReason::Terminated.as_str(), "TERMINATED");
+/// assert_eq!(BlockReason::parse("LOST"), Some(BlockReason::Lost));
+/// assert_eq!(BlockReason::parse("UNKNOWN"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockReason {
+    /// Employment or contract terminated
+    Terminated,
+    /// Contractor company's contract ended
+    ContractEnded,
+    /// Card reported lost
+    Lost,
+    /// Card reported stolen
+    Stolen,
+    /// Temporary suspension (disciplinary, pending documents, ...)
+    Suspended,
+    /// Blocked after a security incident
+    SecurityIncident,
+    /// Access restored after a previous block
+    Reinstated,
+    /// Any other reason
+    Other,
+}
+
+impl BlockReason {
+    /// Code stored in `card_block_batches.reason_code`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Terminated => "TERMINATED",
+            Self::ContractEnded => "CONTRACT_ENDED",
+            Self::Lost => "LOST",
+            Self::Stolen => "STOLEN",
+            Self::Suspended => "SUSPENDED",
+            Self::SecurityIncident => "SECURITY_INCIDENT",
+            Self::Reinstated => "REINSTATED",
+            Self::Other => "OTHER",
+        }
+    }
+
+    /// Parse a stored reason code
+    pub fn parse(code: &str) -> Option<Self> {
+        match code {
+            "TERMINATED" => Some(Self::Terminated),
+            "CONTRACT_ENDED" => Some(Self::ContractEnded),
+            "LOST" => Some(Self::Lost),
+            "STOLEN" => Some(Self::Stolen),
+            "SUSPENDED" => Some(Self::Suspended),
+            "SECURITY_INCIDENT" => Some(Self::SecurityIncident),
+            "REINSTATED" => Some(Self::Reinstated),
+            "OTHER" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Set of cards targeted by a bulk operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardSelector {
+    /// Cards whose matricula starts with the prefix (case-sensitive)
+    MatriculaPrefix(String),
+    /// Cards of users whose `empresa` matches (case-insensitive)
+    Company(String),
+    /// Explicit card numbers (normalized before matching)
+    CardNumbers(Vec<String>),
+}
+
+impl CardSelector {
+    /// Reject selectors that would match nothing or everything
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` for an empty prefix, company or card list.
+    pub fn validate(&self) -> StorageResult<()> {
+        let empty = match self {
+            Self::MatriculaPrefix(prefix) => prefix.trim().is_empty(),
+            Self::Company(company) => company.trim().is_empty(),
+            Self::CardNumbers(numbers) => numbers.iter().all(|n| n.trim().is_empty()),
+        };
+
+        if empty {
+            return Err(StorageError::Validation(format!(
+                "Card selector must not be empty: {}",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CardSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MatriculaPrefix(prefix) => write!(f, "matricula prefix '{}'", prefix),
+            Self::Company(company) => write!(f, "company '{}'", company),
+            Self::CardNumbers(numbers) => write!(f, "{} card number(s)", numbers.len()),
+        }
+    }
+}
+
+/// Audit record of one bulk block/unblock operation
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `action` - `BLOCK` or `UNBLOCK` (see [`BlockAction`])
+/// * `reason_code` - Reason code (see [`BlockReason`])
+/// * `selector` - Human-readable description of the targeted cards
+/// * `operator` - Who ran the operation
+/// * `card_count` - Number of cards actually changed
+/// * `created_at` - When the operation ran
+///
+/// # Database Schema
+///
+/// Maps to the `card_block_batches` table. Affected cards are listed in
+/// `card_block_events`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CardBlockBatch {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Action code
+    ///
+    /// Use `BlockAction::parse()` to convert.
+    pub action: String,
+
+    /// Reason code
+    ///
+    /// Use `BlockReason::parse()` to convert.
+    pub reason_code: String,
+
+    /// Description of the targeted cards
+    pub selector: String,
+
+    /// Operator attribution
+    pub operator: String,
+
+    /// Number of cards changed by this batch
+    pub card_count: i64,
+
+    /// When the operation ran
+    pub created_at: DateTime<Utc>,
+}
+
+/// Result of a bulk block/unblock
+///
+/// Holds the audit batch and the cards that changed state (already in
+/// their new state). Cards that were already blocked (or already active,
+/// when unblocking) are not included.
+#[derive(Debug, Clone)]
+pub struct BulkBlockOutcome {
+    /// Audit record of the operation
+    pub batch: CardBlockBatch,
+
+    /// Cards changed by the operation
+    pub cards: Vec<Card>,
+}
+
+impl BulkBlockOutcome {
+    /// Card list updates to push to a device
+    ///
+    /// Builds ECAR (`SendCards`) messages removing blocked cards from the
+    /// device list, or inserting unblocked ones. Each message carries at most
+    /// [`MAX_CARDS_PER_SYNC_MESSAGE`] cards with the field layout
+    /// `00]<QTY>]<OPERATION>]<CARD>]...`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if a stored card number cannot be encoded.
+    pub fn sync_messages(&self, device_id: DeviceId) -> StorageResult<Vec<Message>> {
+        let action = BlockAction::parse(&self.batch.action).ok_or_else(|| {
+            StorageError::Internal(format!("Unknown block action '{}'", self.batch.action))
+        })?;
+
+        self.cards
+            .chunks(MAX_CARDS_PER_SYNC_MESSAGE)
+            .map(|chunk| {
+                let mut values = vec![
+                    "00".to_string(),
+                    chunk.len().to_string(),
+                    action.sync_operation().to_string(),
+                ];
+                values.extend(chunk.iter().map(|card| card.numero_cartao.clone()));
+
+                let fields = values
+                    .into_iter()
+                    .map(FieldData::new)
+                    .collect::<turnkey_core::Result<Vec<_>>>()
+                    .map_err(|e| StorageError::ProtocolError(format!("Card sync: {}", e)))?;
+
+                Message::new(device_id, CommandCode::SendCards, fields)
+                    .map_err(|e| StorageError::ProtocolError(format!("Card sync: {}", e)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(numero: &str) -> Card {
+        Card {
+            id: 0,
+            numero_cartao: numero.to_string(),
+            matricula: "CTR001".to_string(),
+            user_id: 1,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn outcome(action: BlockAction, count: usize) -> BulkBlockOutcome {
+        BulkBlockOutcome {
+            batch: CardBlockBatch {
+                id: 1,
+                action: action.as_str().to_string(),
+                reason_code: BlockReason::Terminated.as_str().to_string(),
+                selector: "company 'ACME'".to_string(),
+                operator: "security".to_string(),
+                card_count: count as i64,
+                created_at: Utc::now(),
+            },
+            cards: (0..count).map(|i| card(&format!("{:08}", i))).collect(),
+        }
+    }
+
+    #[test]
+    fn test_reason_codes_roundtrip() {
+        for reason in [
+            BlockReason::Terminated,
+            BlockReason::ContractEnded,
+            BlockReason::Lost,
+            BlockReason::Stolen,
+            BlockReason::Suspended,
+            BlockReason::SecurityIncident,
+            BlockReason::Reinstated,
+            BlockReason::Other,
+        ] {
+            assert_eq!(BlockReason::parse(reason.as_str()), Some(reason));
+        }
+    }
+
+    #[test]
+    fn test_selector_validation() {
+        assert!(
+            CardSelector::MatriculaPrefix("CTR".to_string())
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            CardSelector::MatriculaPrefix(" ".to_string())
+                .validate()
+                .is_err()
+        );
+        assert!(CardSelector::Company(String::new()).validate().is_err());
+        assert!(CardSelector::CardNumbers(vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn test_sync_messages_chunked() {
+        let device_id = DeviceId::new(15).unwrap();
+        let messages = outcome(BlockAction::Block, 45)
+            .sync_messages(device_id)
+            .unwrap();
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.command == CommandCode::SendCards));
+
+        let last = &messages[2];
+        assert_eq!(last.fields[0].as_str(), "00");
+        assert_eq!(last.fields[1].as_str(), "5");
+        assert_eq!(last.fields[2].as_str(), "E");
+        assert_eq!(last.fields.len(), 3 + 5);
+    }
+
+    #[test]
+    fn test_sync_messages_unblock_inserts() {
+        let device_id = DeviceId::new(1).unwrap();
+        let messages = outcome(BlockAction::Unblock, 1)
+            .sync_messages(device_id)
+            .unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].fields[2].as_str(), "I");
+        assert_eq!(messages[0].fields[3].as_str(), "00000000");
+    }
+}
@@ -0,0 +1,201 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Default drift threshold in seconds before a device clock is reported
+///
+/// Henry timestamps have one-second resolution and requests spend a few
+/// hundred milliseconds in transit, so anything under a minute is treated
+/// as normal jitter rather than a misconfigured clock.
+pub const DEFAULT_CLOCK_DRIFT_THRESHOLD_SECS: i64 = 60;
+
+/// Aggregated clock drift for a single device
+///
+/// Health data computed from `access_logs` rows that carry both the
+/// device-reported timestamp and the server receive time. Drift values
+/// are `device_timestamp - timestamp` in milliseconds: positive means the
+/// device clock is ahead of the server, negative means it is behind.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::DeviceClockDrift;
+/// use chrono::{Duration, Utc};
+///
+/// let drift = DeviceClockDrift {
+///     device_id: 15,
+///     device_name: Some("Portaria Norte".to_string()),
+///     sample_count: 12,
+///     avg_drift_ms: -95_000,
+///     max_abs_drift_ms: 101_000,
+///     last_drift_ms: -98_000,
+///     last_seen: Utc::now(),
+/// };
+///
+/// assert!(drift.exceeds(Duration::seconds(60)));
+/// assert!(!drift.exceeds(Duration::seconds(120)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DeviceClockDrift {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// Friendly name of the device, if one was set
+    #[serde(default)]
+    pub device_name: Option<String>,
+
+    /// Number of events used to compute the drift
+    pub sample_count: i64,
+
+    /// Average drift across all samples (milliseconds)
+    pub avg_drift_ms: i64,
+
+    /// Largest absolute drift observed (milliseconds)
+    pub max_abs_drift_ms: i64,
+
+    /// Drift of the most recent event (milliseconds)
+    pub last_drift_ms: i64,
+
+    /// Server time of the most recent event from this device
+    pub last_seen: DateTime<Utc>,
+}
+
+impl DeviceClockDrift {
+    /// Get the average drift as a duration
+    pub fn avg_drift(&self) -> Duration {
+        Duration::milliseconds(self.avg_drift_ms)
+    }
+
+    /// Get the drift of the most recent event as a duration
+    pub fn last_drift(&self) -> Duration {
+        Duration::milliseconds(self.last_drift_ms)
+    }
+
+    /// Check if the device clock is off by more than `threshold`
+    ///
+    /// Uses the most recent drift so a device whose clock was corrected
+    /// stops being reported immediately, instead of waiting for the
+    /// average to settle.
+    pub fn exceeds(&self, threshold: Duration) -> bool {
+        self.last_drift().abs() > threshold
+    }
+}
+
+/// Alert raised when a device reports a timestamp too far from the server clock
+///
+/// Emitted by [`OfflineValidator`](crate::validator::OfflineValidator) when
+/// drift alerts are enabled. Consumers typically surface these in the
+/// operator UI so the device clock can be resynchronized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockDriftAlert {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// Observed drift in milliseconds (`device_timestamp - server_timestamp`)
+    pub drift_ms: i64,
+
+    /// Threshold that was exceeded (milliseconds)
+    pub threshold_ms: i64,
+
+    /// Time reported by the device
+    pub device_timestamp: DateTime<Utc>,
+
+    /// Server time when the event was received
+    pub server_timestamp: DateTime<Utc>,
+}
+
+impl ClockDriftAlert {
+    /// Build an alert if `device_timestamp` is further than `threshold` from `server_timestamp`
+    ///
+    /// Returns `None` when the drift is within the threshold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_storage::models::ClockDriftAlert;
+    /// use chrono::{Duration, Utc};
+    ///
+    /// let now = Utc::now();
+    /// let threshold = Duration::seconds(60);
+    ///
+    /// assert!(ClockDriftAlert::check(15, now + Duration::seconds(5), now, threshold).is_none());
+    ///
+    /// let alert = ClockDriftAlert::check(15, now - Duration::minutes(3), now, threshold).unwrap();
+    /// assert_eq!(alert.drift(), Duration::minutes(-3));
+    /// ```
+    pub fn check(
+        device_id: i64,
+        device_timestamp: DateTime<Utc>,
+        server_timestamp: DateTime<Utc>,
+        threshold: Duration,
+    ) -> Option<Self> {
+        let drift = device_timestamp.signed_duration_since(server_timestamp);
+
+        (drift.abs() > threshold).then_some(Self {
+            device_id,
+            drift_ms: drift.num_milliseconds(),
+            threshold_ms: threshold.num_milliseconds(),
+            device_timestamp,
+            server_timestamp,
+        })
+    }
+
+    /// Get the observed drift as a duration
+    pub fn drift(&self) -> Duration {
+        Duration::milliseconds(self.drift_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drift_with_last(last_drift_ms: i64) -> DeviceClockDrift {
+        DeviceClockDrift {
+            device_id: 1,
+            device_name: None,
+            sample_count: 3,
+            avg_drift_ms: 120_000,
+            max_abs_drift_ms: 180_000,
+            last_drift_ms,
+            last_seen: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_device_clock_drift_uses_last_sample() {
+        let threshold = Duration::seconds(DEFAULT_CLOCK_DRIFT_THRESHOLD_SECS);
+
+        assert!(drift_with_last(90_000).exceeds(threshold));
+        assert!(drift_with_last(-90_000).exceeds(threshold));
+        // Clock was corrected: average is still high but the device is fine now
+        assert!(!drift_with_last(500).exceeds(threshold));
+    }
+
+    #[test]
+    fn test_device_clock_drift_durations() {
+        let drift = drift_with_last(-1_500);
+        assert_eq!(drift.avg_drift(), Duration::minutes(2));
+        assert_eq!(drift.last_drift(), Duration::milliseconds(-1_500));
+    }
+
+    #[test]
+    fn test_clock_drift_alert_within_threshold() {
+        let now = Utc::now();
+        let threshold = Duration::seconds(60);
+
+        assert!(ClockDriftAlert::check(1, now, now, threshold).is_none());
+        assert!(ClockDriftAlert::check(1, now + threshold, now, threshold).is_none());
+    }
+
+    #[test]
+    fn test_clock_drift_alert_exceeded() {
+        let now = Utc::now();
+        let threshold = Duration::seconds(60);
+
+        let alert = ClockDriftAlert::check(7, now + Duration::seconds(61), now, threshold).unwrap();
+        assert_eq!(alert.device_id, 7);
+        assert_eq!(alert.drift(), Duration::seconds(61));
+        assert_eq!(alert.threshold_ms, 60_000);
+        assert_eq!(alert.server_timestamp, now);
+    }
+}
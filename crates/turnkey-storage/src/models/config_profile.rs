@@ -0,0 +1,470 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use turnkey_core::constants::{
+    DEFAULT_DISPLAY_TIMEOUT_SECONDS, DEFAULT_ONLINE_TIMEOUT, MAX_DISPLAY_MESSAGE_LENGTH,
+    MAX_ONLINE_TIMEOUT, MIN_ONLINE_TIMEOUT,
+};
+use turnkey_core::{DeviceId, ValidationMode};
+use turnkey_protocol::{CommandCode, FieldData, Message};
+
+use crate::error::{StorageError, StorageResult};
+use crate::mode::VALIDATION_MODE_PARAMETER;
+
+/// Entry message shown when no profile sets one
+pub const DEFAULT_ENTRY_MESSAGE: &str = "Bem vindo";
+
+/// Exit message shown when no profile sets one
+pub const DEFAULT_EXIT_MESSAGE: &str = "Ate logo";
+
+/// Buzzer volume when no profile sets one (0-9)
+pub const DEFAULT_VOLUME: u8 = 5;
+
+/// Highest buzzer volume
+pub const MAX_VOLUME: u8 = 9;
+
+/// Profiles followed up the parent chain before resolution gives up
+pub const MAX_PROFILE_DEPTH: usize = 16;
+
+/// How a device signals a decision to the person at the turnstile
+///
+/// Sent as the `SINAL` configuration parameter, one letter per pattern.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum FeedbackPattern {
+    /// No buzzer and no light
+    Silent,
+    /// Buzzer only
+    Beep,
+    /// Status light only
+    Light,
+    /// Buzzer and status light
+    #[default]
+    BeepAndLight,
+}
+
+impl FeedbackPattern {
+    /// Letter sent in the `SINAL` parameter
+    pub fn code(self) -> char {
+        match self {
+            Self::Silent => 'N',
+            Self::Beep => 'S',
+            Self::Light => 'L',
+            Self::BeepAndLight => 'A',
+        }
+    }
+}
+
+/// Device settings of a profile or of a device override
+///
+/// Every setting is optional: `None` inherits the value from the level
+/// below (parent profile, then built-in defaults). The parameter each
+/// setting is sent as is given in brackets.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{ConfigSettings, FeedbackPattern};
+///
+/// let base = ConfigSettings {
+///     volume: Some(3),
+///     feedback: Some(FeedbackPattern::Beep),
+///     ..ConfigSettings::default()
+/// };
+/// let device = ConfigSettings {
+///     volume: Some(9),
+///     ..ConfigSettings::default()
+/// };
+///
+/// let merged = base.overlay(&device);
+/// assert_eq!(merged.volume, Some(9));
+/// assert_eq!(merged.feedback, Some(FeedbackPattern::Beep));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigSettings {
+    /// Time to wait for the server, in milliseconds (`TIMEOUT_ON`)
+    pub online_timeout_ms: Option<u64>,
+
+    /// Where requests are decided (`TIPO_VALIDA`)
+    pub validation_mode: Option<ValidationMode>,
+
+    /// Message shown on entry (`MSG_ENTRADA`)
+    pub entry_message: Option<String>,
+
+    /// Message shown on exit (`MSG_SAIDA`)
+    pub exit_message: Option<String>,
+
+    /// How long messages stay on the display, in seconds (`TEMPO_MSG`)
+    pub message_secs: Option<u8>,
+
+    /// Buzzer volume, 0-9 (`VOLUME`)
+    pub volume: Option<u8>,
+
+    /// How decisions are signalled (`SINAL`)
+    pub feedback: Option<FeedbackPattern>,
+}
+
+impl ConfigSettings {
+    /// Whether no setting is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Settings of `self` with every setting of `other` applied on top
+    pub fn overlay(&self, other: &Self) -> Self {
+        Self {
+            online_timeout_ms: other.online_timeout_ms.or(self.online_timeout_ms),
+            validation_mode: other.validation_mode.or(self.validation_mode),
+            entry_message: other
+                .entry_message
+                .clone()
+                .or_else(|| self.entry_message.clone()),
+            exit_message: other
+                .exit_message
+                .clone()
+                .or_else(|| self.exit_message.clone()),
+            message_secs: other.message_secs.or(self.message_secs),
+            volume: other.volume.or(self.volume),
+            feedback: other.feedback.or(self.feedback),
+        }
+    }
+
+    /// Check every set value is in range and can be sent to a device
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` naming the first invalid setting.
+    pub fn validate(&self) -> StorageResult<()> {
+        if let Some(timeout) = self.online_timeout_ms
+            && !(MIN_ONLINE_TIMEOUT..=MAX_ONLINE_TIMEOUT).contains(&timeout)
+        {
+            return Err(StorageError::Validation(format!(
+                "Online timeout must be {}-{} ms, got {}",
+                MIN_ONLINE_TIMEOUT, MAX_ONLINE_TIMEOUT, timeout
+            )));
+        }
+        for (what, message) in [
+            ("Entry message", &self.entry_message),
+            ("Exit message", &self.exit_message),
+        ] {
+            if let Some(message) = message {
+                check_message(what, message)?;
+            }
+        }
+        if let Some(volume) = self.volume
+            && volume > MAX_VOLUME
+        {
+            return Err(StorageError::Validation(format!(
+                "Volume must be 0-{}, got {}",
+                MAX_VOLUME, volume
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn check_message(what: &str, message: &str) -> StorageResult<()> {
+    if message.trim().is_empty() || message.chars().count() > MAX_DISPLAY_MESSAGE_LENGTH {
+        return Err(StorageError::Validation(format!(
+            "{} must be 1-{} characters",
+            what, MAX_DISPLAY_MESSAGE_LENGTH
+        )));
+    }
+    FieldData::new(message.to_string())
+        .map(|_| ())
+        .map_err(|e| StorageError::Validation(format!("{}: {}", what, e)))
+}
+
+/// Named set of device settings shared by many devices
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `name` - Unique profile name (1-100 chars)
+/// * `parent_id` - Profile whose settings this one inherits (None for
+///   built-in defaults)
+/// * `settings` - Settings set by this profile
+/// * `created_at` - Record creation timestamp
+/// * `updated_at` - Record last modification timestamp
+///
+/// # Database Schema
+///
+/// Maps to the `config_profiles` table, one nullable column per setting.
+/// Devices are assigned to profiles through `device_configs`.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{ConfigProfile, ConfigSettings};
+///
+/// let profile = ConfigProfile::new("Portaria").with_settings(ConfigSettings {
+///     entry_message: Some("Bem vindo a portaria".to_string()),
+///     ..ConfigSettings::default()
+/// });
+///
+/// assert_eq!(profile.parent_id, None);
+/// assert!(profile.settings.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Unique profile name
+    pub name: String,
+
+    /// Profile inherited from
+    pub parent_id: Option<i64>,
+
+    /// Settings set by this profile
+    pub settings: ConfigSettings,
+
+    /// Record creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Record last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ConfigProfile {
+    /// Create a new root profile without settings
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            name: name.into(),
+            parent_id: None,
+            settings: ConfigSettings::default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Inherit from another profile
+    pub fn with_parent(mut self, parent_id: i64) -> Self {
+        self.parent_id = Some(parent_id);
+        self
+    }
+
+    /// Set the settings
+    pub fn with_settings(mut self, settings: ConfigSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+/// Profile assignment and overrides of one device
+///
+/// # Database Schema
+///
+/// Maps to the `device_configs` table. A device without a row uses the
+/// built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// Assigned profile (None for built-in defaults)
+    pub profile_id: Option<i64>,
+
+    /// Settings overriding the profile on this device
+    pub overrides: ConfigSettings,
+}
+
+/// Effective settings of a device after inheritance
+///
+/// Built from the built-in defaults, then each profile of the chain from
+/// the root down to the assigned profile, then the device overrides; the
+/// last level that sets a value wins.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{ConfigSettings, ResolvedConfig};
+/// use turnkey_core::{DeviceId, ValidationMode};
+///
+/// let profile = ConfigSettings {
+///     validation_mode: Some(ValidationMode::Automatic),
+///     ..ConfigSettings::default()
+/// };
+/// let config = ResolvedConfig::resolve(15, vec!["Portaria".to_string()], [&profile]);
+///
+/// assert_eq!(config.validation_mode, ValidationMode::Automatic);
+/// assert_eq!(config.volume, 5);
+///
+/// let messages = config.to_messages(DeviceId::new(15).unwrap()).unwrap();
+/// assert_eq!(messages.len(), 7);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedConfig {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// Names of the profiles applied, from the root down
+    pub profiles: Vec<String>,
+
+    /// Time to wait for the server, in milliseconds
+    pub online_timeout_ms: u64,
+
+    /// Where requests are decided
+    pub validation_mode: ValidationMode,
+
+    /// Message shown on entry
+    pub entry_message: String,
+
+    /// Message shown on exit
+    pub exit_message: String,
+
+    /// How long messages stay on the display, in seconds
+    pub message_secs: u8,
+
+    /// Buzzer volume, 0-9
+    pub volume: u8,
+
+    /// How decisions are signalled
+    pub feedback: FeedbackPattern,
+}
+
+impl ResolvedConfig {
+    /// Apply `layers` in order over the built-in defaults
+    pub fn resolve<'a>(
+        device_id: i64,
+        profiles: Vec<String>,
+        layers: impl IntoIterator<Item = &'a ConfigSettings>,
+    ) -> Self {
+        let merged = layers
+            .into_iter()
+            .fold(ConfigSettings::default(), |merged, layer| {
+                merged.overlay(layer)
+            });
+
+        Self {
+            device_id,
+            profiles,
+            online_timeout_ms: merged.online_timeout_ms.unwrap_or(DEFAULT_ONLINE_TIMEOUT),
+            validation_mode: merged.validation_mode.unwrap_or(ValidationMode::Online),
+            entry_message: merged
+                .entry_message
+                .unwrap_or_else(|| DEFAULT_ENTRY_MESSAGE.to_string()),
+            exit_message: merged
+                .exit_message
+                .unwrap_or_else(|| DEFAULT_EXIT_MESSAGE.to_string()),
+            message_secs: merged
+                .message_secs
+                .unwrap_or(DEFAULT_DISPLAY_TIMEOUT_SECONDS),
+            volume: merged.volume.unwrap_or(DEFAULT_VOLUME),
+            feedback: merged.feedback.unwrap_or_default(),
+        }
+    }
+
+    /// Configuration parameters and their values, in sending order
+    pub fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("TIMEOUT_ON", self.online_timeout_ms.to_string()),
+            (
+                VALIDATION_MODE_PARAMETER,
+                self.validation_mode.to_char().to_string(),
+            ),
+            ("MSG_ENTRADA", self.entry_message.clone()),
+            ("MSG_SAIDA", self.exit_message.clone()),
+            ("TEMPO_MSG", self.message_secs.to_string()),
+            ("VOLUME", self.volume.to_string()),
+            ("SINAL", self.feedback.code().to_string()),
+        ]
+    }
+
+    /// Build the `SendConfig` (EC) messages applying this configuration
+    ///
+    /// One message per parameter, `<ID>+REON+EC]00]<PARAMETER>]<VALUE>]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if a value is not a valid field.
+    pub fn to_messages(&self, device_id: DeviceId) -> StorageResult<Vec<Message>> {
+        self.parameters()
+            .into_iter()
+            .map(|(parameter, value)| {
+                ["00", parameter, &value]
+                    .into_iter()
+                    .map(|field| FieldData::new(field.to_string()))
+                    .collect::<turnkey_core::Result<Vec<_>>>()
+                    .and_then(|fields| Message::new(device_id, CommandCode::SendConfig, fields))
+                    .map_err(|e| StorageError::ProtocolError(format!("{}: {}", parameter, e)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_applies_layers_in_order() {
+        let root = ConfigSettings {
+            online_timeout_ms: Some(2000),
+            volume: Some(2),
+            entry_message: Some("Bem vindo".to_string()),
+            ..ConfigSettings::default()
+        };
+        let child = ConfigSettings {
+            volume: Some(7),
+            feedback: Some(FeedbackPattern::Light),
+            ..ConfigSettings::default()
+        };
+        let device = ConfigSettings {
+            entry_message: Some("Entrada B".to_string()),
+            ..ConfigSettings::default()
+        };
+
+        let config = ResolvedConfig::resolve(3, vec![], [&root, &child, &device]);
+        assert_eq!(config.online_timeout_ms, 2000);
+        assert_eq!(config.volume, 7);
+        assert_eq!(config.feedback, FeedbackPattern::Light);
+        assert_eq!(config.entry_message, "Entrada B");
+        assert_eq!(config.exit_message, DEFAULT_EXIT_MESSAGE);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_values() {
+        for settings in [
+            ConfigSettings {
+                online_timeout_ms: Some(100),
+                ..ConfigSettings::default()
+            },
+            ConfigSettings {
+                volume: Some(10),
+                ..ConfigSettings::default()
+            },
+            ConfigSettings {
+                entry_message: Some(" ".to_string()),
+                ..ConfigSettings::default()
+            },
+            ConfigSettings {
+                exit_message: Some("Saida]B".to_string()),
+                ..ConfigSettings::default()
+            },
+        ] {
+            assert!(settings.validate().is_err(), "{:?} should fail", settings);
+        }
+        assert!(ConfigSettings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_to_messages_wire_format() {
+        let config = ResolvedConfig::resolve(15, vec![], []);
+        let messages = config.to_messages(DeviceId::new(15).unwrap()).unwrap();
+
+        let fields = |index: usize| -> Vec<&str> {
+            messages[index].fields.iter().map(|f| f.as_str()).collect()
+        };
+        assert!(
+            messages
+                .iter()
+                .all(|m| m.command == CommandCode::SendConfig)
+        );
+        assert_eq!(fields(0), ["00", "TIMEOUT_ON", "3000"]);
+        assert_eq!(fields(1), ["00", "TIPO_VALIDA", "O"]);
+        assert_eq!(fields(2), ["00", "MSG_ENTRADA", DEFAULT_ENTRY_MESSAGE]);
+        assert_eq!(fields(6), ["00", "SINAL", "A"]);
+    }
+}
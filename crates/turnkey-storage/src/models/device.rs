@@ -0,0 +1,290 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use turnkey_protocol::MessageSigner;
+
+use super::User;
+use crate::messages::DisplayMessages;
+
+/// Minimum length of a device HMAC key in bytes
+pub const MIN_DEVICE_KEY_LENGTH: usize = 16;
+
+/// Server-side settings of a turnstile
+///
+/// # Fields
+///
+/// * `device_id` - Henry device ID (1-99), primary key
+/// * `hmac_key` - Shared secret for message signing (never serialized)
+/// * `signing_required` - Whether unsigned connections are rejected
+/// * `allow_card` - Whether the card reader may be used on this device
+/// * `allow_bio` - Whether the biometric reader may be used on this device
+/// * `allow_keypad` - Whether PIN entry may be used on this device
+/// * `zone_id` - Zone the device is installed in (see [`Zone`](super::Zone))
+/// * `deny_when_stale` - Whether offline requests are denied while the local
+///   data is stale (see [`SyncState`](super::SyncState))
+/// * `verification_mode` - Credentials required for a passage
+/// * `show_balance` - Whether grants show the passages left today
+/// * `name` - Friendly name shown next to the ID (see
+///   [`DeviceLabel`](turnkey_core::DeviceLabel))
+/// * `location` - Where the device is installed
+/// * `created_at` - Creation timestamp
+/// * `updated_at` - Last update timestamp
+///
+/// # Database Schema
+///
+/// Maps to the `devices` table. A missing row means the device is unknown
+/// to the server: it may connect unsigned and every access method is
+/// allowed.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{AccessMethod, Device, VerificationMode};
+/// use chrono::Utc;
+///
+/// let device = Device {
+///     device_id: 15,
+///     hmac_key: Some(b"0123456789abcdef".to_vec()),
+///     signing_required: true,
+///     allow_card: true,
+///     allow_bio: true,
+///     allow_keypad: false,
+///     zone_id: None,
+///     deny_when_stale: false,
+///     verification_mode: VerificationMode::SingleFactor,
+///     show_balance: false,
+///     name: Some("Portaria Norte".to_string()),
+///     location: None,
+///     created_at: Utc::now(),
+///     updated_at: Utc::now(),
+/// };
+///
+/// assert!(device.signer().is_some());
+/// assert!(!device.allows(AccessMethod::Keypad));
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Device {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// HMAC-SHA256 shared secret
+    #[serde(skip)]
+    pub hmac_key: Option<Vec<u8>>,
+
+    /// Reject unsigned connections from this device
+    ///
+    /// The server requires signatures from every device with a key; a
+    /// device with a key and this unset maps to
+    /// `TcpServer::set_unsigned_allowed()` while keys are rolled out.
+    pub signing_required: bool,
+
+    /// Card reader enabled on this device
+    pub allow_card: bool,
+
+    /// Biometric reader enabled on this device
+    pub allow_bio: bool,
+
+    /// PIN entry enabled on this device
+    pub allow_keypad: bool,
+
+    /// Zone the device belongs to (NULL if not assigned)
+    pub zone_id: Option<i64>,
+
+    /// Deny offline requests while the local data is stale
+    pub deny_when_stale: bool,
+
+    /// Credentials required for a passage
+    pub verification_mode: VerificationMode,
+
+    /// Show the passages left in the daily quota on grants
+    pub show_balance: bool,
+
+    /// Friendly name (NULL shows the numeric ID)
+    pub name: Option<String>,
+
+    /// Where the device is installed
+    pub location: Option<String>,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Device")
+            .field("device_id", &self.device_id)
+            .field("has_hmac_key", &self.hmac_key.is_some())
+            .field("signing_required", &self.signing_required)
+            .field("allow_card", &self.allow_card)
+            .field("allow_bio", &self.allow_bio)
+            .field("allow_keypad", &self.allow_keypad)
+            .field("zone_id", &self.zone_id)
+            .field("deny_when_stale", &self.deny_when_stale)
+            .field("verification_mode", &self.verification_mode)
+            .field("show_balance", &self.show_balance)
+            .field("name", &self.name)
+            .field("location", &self.location)
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .finish()
+    }
+}
+
+impl Device {
+    /// Message signer for this device, if it has a key
+    pub fn signer(&self) -> Option<MessageSigner> {
+        self.hmac_key.clone().map(MessageSigner::new)
+    }
+
+    /// Check whether an access method is enabled on this device
+    pub fn allows(&self, method: AccessMethod) -> bool {
+        match method {
+            AccessMethod::Card => self.allow_card,
+            AccessMethod::Biometric => self.allow_bio,
+            AccessMethod::Keypad => self.allow_keypad,
+        }
+    }
+}
+
+/// Credentials a device requires for a passage
+///
+/// Stored in `devices.verification_mode`. Unlike
+/// [`ValidationMode`](turnkey_core::ValidationMode), which says where a
+/// request is decided (online or offline), this says what the person has to
+/// present.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum VerificationMode {
+    /// Any single allowed credential (card, fingerprint or PIN)
+    #[default]
+    SingleFactor,
+    /// Card followed by the fingerprint of the card's owner
+    CardPlusBiometric,
+}
+
+impl VerificationMode {
+    /// Whether a card read must be confirmed by the owner's fingerprint
+    pub fn requires_biometric(self) -> bool {
+        matches!(self, Self::CardPlusBiometric)
+    }
+}
+
+/// Way a credential was presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccessMethod {
+    /// RFID/NFC card
+    Card,
+    /// Fingerprint
+    Biometric,
+    /// PIN code
+    Keypad,
+}
+
+impl AccessMethod {
+    /// Access method of a protocol reader type
+    pub fn from_reader_type(reader: turnkey_core::ReaderType) -> Self {
+        match reader {
+            turnkey_core::ReaderType::Rfid => Self::Card,
+            turnkey_core::ReaderType::Keypad => Self::Keypad,
+            turnkey_core::ReaderType::Biometric => Self::Biometric,
+        }
+    }
+
+    /// Combined device and user permission check
+    ///
+    /// The device setting is checked first, since a disabled reader denies
+    /// every user. A missing device (no row in `devices`) allows every
+    /// method. Returns the deny message, or `None` if the method is allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use turnkey_storage::models::AccessMethod;
+    /// use turnkey_storage::messages::DisplayMessages;
+    /// # use turnkey_storage::models::{Device, User};
+    /// # use chrono::Utc;
+    /// # let user = User {
+    /// #     id: 1, pis: None, nome: "Test".to_string(), matricula: "001".to_string(),
+    /// #     cpf: None, validade_inicio: None, validade_fim: None, ativo: true,
+    /// #     allow_card: true, allow_bio: false, allow_keypad: false, codigo: None,
+    /// #     empresa: None, created_at: Utc::now(), updated_at: Utc::now(),
+    /// # };
+    /// # let gate = Device {
+    /// #     device_id: 1, hmac_key: None, signing_required: false,
+    /// #     allow_card: false, allow_bio: true, allow_keypad: false, zone_id: None,
+    /// #     deny_when_stale: false, verification_mode: Default::default(), show_balance: false, name: None, location: None, created_at: Utc::now(), updated_at: Utc::now(),
+    /// # };
+    ///
+    /// // Card reader disabled at the gate, even though the user may use cards
+    /// assert_eq!(
+    ///     AccessMethod::Card.denial(Some(&gate), &user),
+    ///     Some(DisplayMessages::DEVICE_CARD_DISABLED)
+    /// );
+    /// // Biometrics enabled at the gate but not for this user
+    /// assert_eq!(
+    ///     AccessMethod::Biometric.denial(Some(&gate), &user),
+    ///     Some(DisplayMessages::BIO_ACCESS_DENIED)
+    /// );
+    /// assert_eq!(AccessMethod::Card.denial(None, &user), None);
+    /// ```
+    pub fn denial(self, device: Option<&Device>, user: &User) -> Option<&'static str> {
+        if let Some(message) = self.device_denial(device) {
+            return Some(message);
+        }
+
+        let (allowed, message) = match self {
+            Self::Card => (user.allow_card, DisplayMessages::CARD_ACCESS_DENIED),
+            Self::Biometric => (user.allow_bio, DisplayMessages::BIO_ACCESS_DENIED),
+            Self::Keypad => (
+                user.allow_keypad && user.codigo.is_some(),
+                DisplayMessages::KEYPAD_ACCESS_DENIED,
+            ),
+        };
+        (!allowed).then_some(message)
+    }
+
+    /// Message denying this method at a device that has it disabled
+    ///
+    /// The device half of [`denial()`](Self::denial), for reads that have
+    /// no user, such as unregistered cards admitted by an access exception.
+    pub fn device_denial(self, device: Option<&Device>) -> Option<&'static str> {
+        let device = device?;
+        (!device.allows(self)).then_some(match self {
+            Self::Card => DisplayMessages::DEVICE_CARD_DISABLED,
+            Self::Biometric => DisplayMessages::DEVICE_BIO_DISABLED,
+            Self::Keypad => DisplayMessages::DEVICE_KEYPAD_DISABLED,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_hides_key() {
+        let device = Device {
+            device_id: 15,
+            hmac_key: Some(b"super-secret-key".to_vec()),
+            signing_required: false,
+            allow_card: true,
+            allow_bio: true,
+            allow_keypad: true,
+            zone_id: None,
+            deny_when_stale: false,
+            verification_mode: VerificationMode::SingleFactor,
+            show_balance: false,
+            name: None,
+            location: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let debug = format!("{:?}", device);
+        assert!(debug.contains("has_hmac_key: true"));
+        assert!(!debug.contains("115, 117"));
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Open card enrollment session on a device
+///
+/// While a session is open, the next card read on the device is not
+/// validated: it is stored as a [`PendingCard`](super::PendingCard) bound to
+/// the session's matricula and the session ends. The administrator then
+/// confirms the registration with
+/// [`PendingCardRepository::assign`](crate::repositories::PendingCardRepository::assign).
+///
+/// # Fields
+///
+/// * `device_id` - Henry device ID in enrollment mode (1-99)
+/// * `matricula` - User the captured card will be registered for
+/// * `started_at` - When enrollment mode was entered
+/// * `expires_at` - When the session stops capturing reads
+///
+/// # Database Schema
+///
+/// Maps to the `enrollment_sessions` table, one row per device. Sessions are
+/// deleted when a card is captured, when cancelled, or when the user is
+/// deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EnrollmentSession {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// User the captured card will be registered for
+    pub matricula: String,
+
+    /// When enrollment mode was entered
+    pub started_at: DateTime<Utc>,
+
+    /// When the session stops capturing reads
+    pub expires_at: DateTime<Utc>,
+}
+
+impl EnrollmentSession {
+    /// Whether the session still captures reads at `at`
+    pub fn is_open_at(&self, at: DateTime<Utc>) -> bool {
+        at < self.expires_at
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Date on which the site may be closed
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `date` - Site local date
+/// * `description` - Name of the holiday (1-100 chars)
+/// * `created_at` - Audit timestamp
+///
+/// Whether holidays close the site is decided by the validator's
+/// [`HolidayPolicy`].
+///
+/// # Database Schema
+///
+/// Maps to the `holidays` table, one row per date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Holiday {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Site local date
+    pub date: NaiveDate,
+
+    /// Name of the holiday
+    pub description: String,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+impl Holiday {
+    /// Create a holiday to be stored
+    pub fn new(date: NaiveDate, description: impl Into<String>) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            date,
+            description: description.into(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// How the validator treats holidays
+///
+/// # Examples
+///
+/// ```no_run
+/// use turnkey_storage::OfflineValidator;
+/// use turnkey_storage::models::HolidayPolicy;
+///
+/// # fn example(pool: sqlx::SqlitePool) {
+/// let validator = OfflineValidator::new(pool).with_holiday_policy(HolidayPolicy::Closed);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HolidayPolicy {
+    /// Holidays are ordinary days of the week
+    #[default]
+    Open,
+
+    /// The site is closed on holidays
+    ///
+    /// Users are denied unless they are exempt or their schedule has an
+    /// exception for the date. Exempt users still follow their schedule.
+    Closed,
+}
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Wrong codes allowed on a device before its keypad is locked (5)
+pub const DEFAULT_KEYPAD_MAX_ATTEMPTS: u32 = 5;
+
+/// How long a locked keypad stays locked the first time, in seconds (5 minutes)
+pub const DEFAULT_KEYPAD_COOLDOWN_SECS: i64 = 5 * 60;
+
+/// Factor each further lockout multiplies the cooldown by (2)
+pub const DEFAULT_KEYPAD_BACKOFF: u32 = 2;
+
+/// Longest a keypad or user stays locked, in seconds (1 hour)
+pub const DEFAULT_KEYPAD_MAX_COOLDOWN_SECS: i64 = 60 * 60;
+
+/// Failed keypad codes on one device
+///
+/// # Fields
+///
+/// * `device_id` - Henry device ID (1-99)
+/// * `failed_attempts` - Wrong codes since the last success or lockout
+/// * `lockouts` - Lockouts since the last success, for escalation
+/// * `last_failed_at` - Time of the last wrong code
+/// * `locked_until` - Keypad refused until this time (None if not locked)
+///
+/// # Database Schema
+///
+/// Maps to the `keypad_lockouts` table. Devices without a row have no
+/// failed attempts.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::KeypadLockout;
+/// use chrono::{Duration, Utc};
+///
+/// let now = Utc::now();
+/// let lockout = KeypadLockout {
+///     device_id: 15,
+///     failed_attempts: 0,
+///     lockouts: 1,
+///     last_failed_at: Some(now),
+///     locked_until: Some(now + Duration::minutes(5)),
+/// };
+///
+/// assert!(lockout.is_locked(now));
+/// assert_eq!(lockout.remaining(now), Some(Duration::minutes(5)));
+/// assert!(!lockout.is_locked(now + Duration::minutes(5)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct KeypadLockout {
+    /// Henry device ID (1-99)
+    pub device_id: i64,
+
+    /// Wrong codes since the last success or lockout
+    pub failed_attempts: i64,
+
+    /// Lockouts since the last success
+    pub lockouts: i64,
+
+    /// Time of the last wrong code
+    pub last_failed_at: Option<DateTime<Utc>>,
+
+    /// Keypad refused until this time
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl KeypadLockout {
+    /// Whether the keypad is refused at `now`
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| now < until)
+    }
+
+    /// Time left before the keypad is accepted again, `None` if not locked
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.locked_until
+            .filter(|until| now < *until)
+            .map(|until| until - now)
+    }
+}
+
+/// Failed PINs typed for one user, on any device
+///
+/// Counted when the user is known and the PIN is wrong (matricula + PIN
+/// entry), so guessing one user's PIN is limited across turnstiles.
+///
+/// # Database Schema
+///
+/// Maps to the `user_pin_lockouts` table. Users without a row have no
+/// failed attempts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserPinLockout {
+    /// Internal user ID
+    pub user_id: i64,
+
+    /// Wrong PINs since the last success or lockout
+    pub failed_attempts: i64,
+
+    /// Lockouts since the last success
+    pub lockouts: i64,
+
+    /// Time of the last wrong PIN
+    pub last_failed_at: Option<DateTime<Utc>>,
+
+    /// PIN refused until this time
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl UserPinLockout {
+    /// Whether the user's PIN is refused at `now`
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until.is_some_and(|until| now < until)
+    }
+
+    /// Time left before the PIN is accepted again, `None` if not locked
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.locked_until
+            .filter(|until| now < *until)
+            .map(|until| until - now)
+    }
+}
+
+/// When a keypad, or a user's PIN, is locked after wrong codes
+///
+/// After `max_attempts` wrong codes the keypad is refused for `cooldown`,
+/// after which the count starts over. Every further lockout lasts
+/// `backoff` times longer than the previous one, up to `max_cooldown`. A
+/// correct code resets both the count and the escalation; `cooldown`
+/// without a wrong code forgives the count, and `max_cooldown` without one
+/// forgives the escalation.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::KeypadLockoutPolicy;
+/// use chrono::Duration;
+///
+/// let policy = KeypadLockoutPolicy::new(3, Duration::minutes(10));
+/// assert_eq!(policy.max_attempts, 3);
+/// assert_eq!(KeypadLockoutPolicy::default().max_attempts, 5);
+///
+/// // 10, 20, 40 minutes, then capped at an hour
+/// assert_eq!(policy.cooldown_after(0), Duration::minutes(10));
+/// assert_eq!(policy.cooldown_after(2), Duration::minutes(40));
+/// assert_eq!(policy.cooldown_after(3), Duration::hours(1));
+///
+/// // Without escalation every lockout lasts the same
+/// assert_eq!(policy.backoff(1).cooldown_after(5), Duration::minutes(10));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeypadLockoutPolicy {
+    /// Wrong codes that lock the keypad
+    pub max_attempts: u32,
+
+    /// How long the first lockout lasts
+    pub cooldown: Duration,
+
+    /// Factor each further lockout multiplies the cooldown by (1 disables
+    /// escalation)
+    pub backoff: u32,
+
+    /// Longest a lockout lasts
+    pub max_cooldown: Duration,
+}
+
+impl KeypadLockoutPolicy {
+    /// Lock after `max_attempts` wrong codes, first for `cooldown`
+    ///
+    /// Further lockouts escalate by [`DEFAULT_KEYPAD_BACKOFF`] up to an
+    /// hour, or up to `cooldown` if that is longer.
+    pub fn new(max_attempts: u32, cooldown: Duration) -> Self {
+        Self {
+            max_attempts,
+            cooldown,
+            backoff: DEFAULT_KEYPAD_BACKOFF,
+            max_cooldown: cooldown.max(Duration::seconds(DEFAULT_KEYPAD_MAX_COOLDOWN_SECS)),
+        }
+    }
+
+    /// Set the factor further lockouts are multiplied by (at least 1)
+    pub fn backoff(mut self, backoff: u32) -> Self {
+        self.backoff = backoff.max(1);
+        self
+    }
+
+    /// Set the longest lockout (at least `cooldown`)
+    pub fn max_cooldown(mut self, max_cooldown: Duration) -> Self {
+        self.max_cooldown = max_cooldown.max(self.cooldown);
+        self
+    }
+
+    /// Length of the next lockout after `lockouts` earlier ones
+    pub fn cooldown_after(&self, lockouts: i64) -> Duration {
+        let mut cooldown = self.cooldown;
+        for _ in 0..lockouts.max(0) {
+            if cooldown >= self.max_cooldown || self.backoff == 1 {
+                break;
+            }
+            cooldown = i32::try_from(self.backoff)
+                .ok()
+                .and_then(|backoff| cooldown.checked_mul(backoff))
+                .unwrap_or(self.max_cooldown);
+        }
+        cooldown.min(self.max_cooldown)
+    }
+}
+
+impl Default for KeypadLockoutPolicy {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_KEYPAD_MAX_ATTEMPTS,
+            Duration::seconds(DEFAULT_KEYPAD_COOLDOWN_SECS),
+        )
+    }
+}
+
+/// What a PIN lockout applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PinLockoutSubject {
+    /// The keypad of a device, for every code typed on it
+    Device {
+        /// Henry device ID (1-99)
+        device_id: i64,
+    },
+
+    /// The PIN of one user, on every device
+    User {
+        /// Internal user ID
+        user_id: i64,
+    },
+}
+
+/// Security event raised when a keypad or a user's PIN gets locked
+///
+/// Published by [`OfflineValidator`](crate::OfflineValidator) on the bus
+/// given to
+/// [`with_pin_lockout_events`](crate::OfflineValidator::with_pin_lockout_events),
+/// and serialized as JSON for audit trails. Wrong codes typed while a lock
+/// is on do not raise further events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinLockoutEvent {
+    /// Device keypad or user that was locked
+    pub subject: PinLockoutSubject,
+
+    /// Device the last wrong code was typed on, if known
+    pub device_id: Option<i64>,
+
+    /// Wrong codes that led to the lockout
+    pub failed_attempts: i64,
+
+    /// Lockouts since the last correct code, this one included
+    pub lockouts: i64,
+
+    /// Time of the wrong code that locked
+    pub locked_at: DateTime<Utc>,
+
+    /// End of the lockout
+    pub locked_until: DateTime<Utc>,
+}
+
+impl fmt::Display for PinLockoutEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.subject {
+            PinLockoutSubject::Device { device_id } => write!(f, "keypad of device {}", device_id)?,
+            PinLockoutSubject::User { user_id } => write!(f, "PIN of user {}", user_id)?,
+        }
+        write!(
+            f,
+            " locked for {}s after {} wrong codes (lockout {})",
+            (self.locked_until - self.locked_at).num_seconds(),
+            self.failed_attempts,
+            self.lockouts
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cooldown_escalates_up_to_cap() {
+        let policy =
+            KeypadLockoutPolicy::new(5, Duration::minutes(5)).max_cooldown(Duration::minutes(30));
+
+        let cooldowns: Vec<_> = (0..5)
+            .map(|lockouts| policy.cooldown_after(lockouts).num_minutes())
+            .collect();
+        assert_eq!(cooldowns, [5, 10, 20, 30, 30]);
+
+        // Huge lockout counts neither overflow nor loop for long
+        assert_eq!(policy.cooldown_after(i64::MAX), Duration::minutes(30));
+
+        // A cap below the cooldown is raised to it
+        let policy = policy.max_cooldown(Duration::minutes(1));
+        assert_eq!(policy.cooldown_after(3), Duration::minutes(5));
+    }
+
+    #[test]
+    fn test_lockout_event_display() {
+        let at = Utc::now();
+        let event = PinLockoutEvent {
+            subject: PinLockoutSubject::User { user_id: 7 },
+            device_id: Some(15),
+            failed_attempts: 5,
+            lockouts: 2,
+            locked_at: at,
+            locked_until: at + Duration::minutes(10),
+        };
+        assert_eq!(
+            event.to_string(),
+            "PIN of user 7 locked for 600s after 5 wrong codes (lockout 2)"
+        );
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["subject"]["kind"], "user");
+        assert_eq!(json["subject"]["user_id"], 7);
+    }
+}
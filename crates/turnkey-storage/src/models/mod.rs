@@ -1,9 +1,55 @@
+pub mod access_exception;
 pub mod access_log;
+pub mod access_override;
+pub mod access_state;
+pub mod anti_passback;
+pub mod biometric_template;
 pub mod card;
+pub mod card_block;
+pub mod clock_drift;
+pub mod config_profile;
+pub mod device;
+pub mod enrollment_session;
+pub mod holiday;
+pub mod keypad_lockout;
+pub mod pending_card;
+pub mod presence;
+pub mod quota;
+pub mod schedule;
+pub mod site;
+pub mod sync_state;
 pub mod temporal_validity;
 pub mod user;
 
+pub use access_exception::AccessException;
 pub use access_log::{AccessLog, Direction, ReaderType};
+pub use access_override::{AccessOverride, AccessOverrideOutcome};
+pub use access_state::AccessState;
+pub use anti_passback::{AntiPassbackPolicy, DEFAULT_ANTI_PASSBACK_WINDOW_SECS, UserAntiPassback};
+pub use biometric_template::{BiometricTemplate, MAX_TEMPLATE_SIZE, MIN_TEMPLATE_SIZE};
 pub use card::Card;
+pub use card_block::{
+    BlockAction, BlockReason, BulkBlockOutcome, CardBlockBatch, CardSelector,
+    MAX_CARDS_PER_SYNC_MESSAGE,
+};
+pub use clock_drift::{ClockDriftAlert, DEFAULT_CLOCK_DRIFT_THRESHOLD_SECS, DeviceClockDrift};
+pub use config_profile::{
+    ConfigProfile, ConfigSettings, DEFAULT_ENTRY_MESSAGE, DEFAULT_EXIT_MESSAGE, DEFAULT_VOLUME,
+    DeviceConfig, FeedbackPattern, MAX_PROFILE_DEPTH, MAX_VOLUME, ResolvedConfig,
+};
+pub use device::{AccessMethod, Device, MIN_DEVICE_KEY_LENGTH, VerificationMode};
+pub use enrollment_session::EnrollmentSession;
+pub use holiday::{Holiday, HolidayPolicy};
+pub use keypad_lockout::{
+    DEFAULT_KEYPAD_BACKOFF, DEFAULT_KEYPAD_COOLDOWN_SECS, DEFAULT_KEYPAD_MAX_ATTEMPTS,
+    DEFAULT_KEYPAD_MAX_COOLDOWN_SECS, KeypadLockout, KeypadLockoutPolicy, PinLockoutEvent,
+    PinLockoutSubject, UserPinLockout,
+};
+pub use pending_card::PendingCard;
+pub use presence::Occupant;
+pub use quota::{DailyQuota, QuotaDay};
+pub use schedule::{CompiledSchedule, MINUTES_PER_DAY, TimeInterval, WeeklySchedule};
+pub use site::{Site, Zone, ZoneOccupancy};
+pub use sync_state::{DEFAULT_STALE_DATA_THRESHOLD_SECS, StaleDataWarning, SyncState};
 pub use temporal_validity::TemporalValidity;
 pub use user::User;
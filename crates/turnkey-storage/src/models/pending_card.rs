@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::Card;
+
+/// Unknown card read captured in learning mode
+///
+/// When learning mode is enabled on the offline validator, every card read
+/// that matches no registered card is stored here. Operators integrating a
+/// new reader tap a badge, look up the pending entry, and either assign it
+/// to a user or pick one of the stored cards proposed as a match.
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `raw_value` - Card number exactly as reported by the reader
+/// * `card_number` - Normalized card number (trimmed, uppercase)
+/// * `device_id` - Device of the most recent read (1-99), if known
+/// * `read_count` - Number of unknown reads of this card
+/// * `first_seen_at` - Time of the first unknown read
+/// * `last_seen_at` - Time of the most recent unknown read
+/// * `matricula` - User the card was captured for in enrollment mode, if any
+///
+/// # Database Schema
+///
+/// Maps to the `pending_cards` table, unique by `card_number`. Rows are
+/// removed when the card is assigned to a user.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::PendingCard;
+///
+/// let forms = PendingCard::candidate_forms("000255");
+/// assert_eq!(forms, vec!["255", "FF", "597"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PendingCard {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Card number as reported by the reader
+    pub raw_value: String,
+
+    /// Normalized card number, same form as `Card::numero_cartao`
+    pub card_number: String,
+
+    /// Henry device ID of the most recent read (1-99)
+    pub device_id: Option<i64>,
+
+    /// Number of unknown reads recorded for this card
+    pub read_count: i64,
+
+    /// First time the card was read while unknown
+    pub first_seen_at: DateTime<Utc>,
+
+    /// Most recent time the card was read while unknown
+    pub last_seen_at: DateTime<Utc>,
+
+    /// User chosen when the card was captured by an enrollment session
+    pub matricula: Option<String>,
+}
+
+impl PendingCard {
+    /// Alternative spellings of a card number used to propose stored matches
+    ///
+    /// Readers from different vendors often report the same badge with or
+    /// without leading zeros, or in decimal instead of hexadecimal. Returns
+    /// the normalized number without leading zeros, followed by its
+    /// decimal-to-hex and hex-to-decimal conversions when they parse.
+    /// Duplicates are removed while keeping that order.
+    pub fn candidate_forms(card_number: &str) -> Vec<String> {
+        let normalized = Card::normalize_card_number(card_number);
+        let stripped = Self::strip_leading_zeros(&normalized);
+
+        let mut forms = vec![stripped.clone()];
+
+        if let Ok(decimal) = stripped.parse::<u64>() {
+            forms.push(format!("{:X}", decimal));
+        }
+        if let Ok(hex) = u64::from_str_radix(&stripped, 16) {
+            forms.push(hex.to_string());
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        forms.retain(|form| seen.insert(form.clone()));
+        forms
+    }
+
+    /// Remove leading zeros, keeping a single "0" for all-zero numbers
+    fn strip_leading_zeros(card_number: &str) -> String {
+        let stripped = card_number.trim_start_matches('0');
+        if stripped.is_empty() && !card_number.is_empty() {
+            "0".to_string()
+        } else {
+            stripped.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_forms_decimal() {
+        assert_eq!(
+            PendingCard::candidate_forms("  0011912322 "),
+            vec!["11912322", "B5C482", "294724386"]
+        );
+    }
+
+    #[test]
+    fn test_candidate_forms_hex_only() {
+        assert_eq!(
+            PendingCard::candidate_forms("00abcdef"),
+            vec!["ABCDEF", "11259375"]
+        );
+    }
+
+    #[test]
+    fn test_candidate_forms_not_numeric() {
+        assert_eq!(PendingCard::candidate_forms("XYZ-123"), vec!["XYZ-123"]);
+    }
+
+    #[test]
+    fn test_candidate_forms_deduplicates() {
+        // "1" is the same in decimal and hexadecimal
+        assert_eq!(PendingCard::candidate_forms("0001"), vec!["1"]);
+        assert_eq!(PendingCard::candidate_forms("000"), vec!["0"]);
+    }
+}
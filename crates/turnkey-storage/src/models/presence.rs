@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Person currently inside the building, for the "who is inside" report
+///
+/// Read from the occupancy projection in `access_state`, which the
+/// `update_access_state_on_log` trigger updates on every validated passage:
+/// a credential is inside when its last granted passage was an entry.
+///
+/// # Fields
+///
+/// * `card_number` - Credential used for the entry
+/// * `user_id` - Identified user (NULL for credentials never identified)
+/// * `matricula` - User's matricula, if identified
+/// * `nome` - User's name, if identified
+/// * `entered_at` - Time of the entry (server clock)
+/// * `device_id` - Device that granted the entry, if known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Occupant {
+    /// Credential used for the entry
+    pub card_number: String,
+
+    /// Identified user
+    pub user_id: Option<i64>,
+
+    /// User's matricula
+    pub matricula: Option<String>,
+
+    /// User's name
+    pub nome: Option<String>,
+
+    /// Time of the entry
+    pub entered_at: DateTime<Utc>,
+
+    /// Henry device ID that granted the entry (1-99)
+    pub device_id: Option<i64>,
+}
+
+impl Occupant {
+    /// Time spent inside as of `now`
+    pub fn time_inside(&self, now: DateTime<Utc>) -> chrono::Duration {
+        now - self.entered_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_time_inside() {
+        let now = Utc::now();
+        let occupant = Occupant {
+            card_number: "1234567890".to_string(),
+            user_id: Some(1),
+            matricula: Some("1001".to_string()),
+            nome: Some("Test".to_string()),
+            entered_at: now - Duration::minutes(90),
+            device_id: Some(3),
+        };
+
+        assert_eq!(occupant.time_inside(now), Duration::minutes(90));
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Cap on granted passages per day for a user or a company
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `user_id` - User the quota applies to (None for a company quota)
+/// * `empresa` - Company whose users each get the limit (None for a user quota)
+/// * `max_passages` - Granted passages allowed per day
+/// * `created_at` / `updated_at` - Audit timestamps
+///
+/// Each user of a company gets `max_passages` of their own; the company
+/// does not share one pool. A user quota takes precedence over the quota of
+/// the user's company, so one person can be given more (or fewer) passages
+/// than the rest.
+///
+/// # Database Schema
+///
+/// Maps to the `daily_quotas` table. Exactly one of `user_id` and `empresa`
+/// is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DailyQuota {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// User the quota applies to
+    pub user_id: Option<i64>,
+
+    /// Company the quota applies to
+    pub empresa: Option<String>,
+
+    /// Granted passages allowed per day
+    pub max_passages: i64,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Day boundary used to count passages against a [`DailyQuota`]
+///
+/// Quotas reset when the day starts, at `day_start` in the site's UTC
+/// offset. The default is midnight UTC; a cafeteria serving night shifts
+/// might start its day at 04:00 local time instead.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::QuotaDay;
+/// use chrono::{FixedOffset, NaiveTime, TimeZone, Utc};
+///
+/// // São Paulo (UTC-3), day starts at 04:00
+/// let day = QuotaDay::new(
+///     NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+///     FixedOffset::west_opt(3 * 3600).unwrap(),
+/// );
+///
+/// // 02:30 local on the 11th still counts towards the 10th
+/// let now = Utc.with_ymd_and_hms(2025, 11, 11, 5, 30, 0).unwrap();
+/// let (start, end) = day.window(now);
+/// assert_eq!(start, Utc.with_ymd_and_hms(2025, 11, 10, 7, 0, 0).unwrap());
+/// assert_eq!(end, Utc.with_ymd_and_hms(2025, 11, 11, 7, 0, 0).unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaDay {
+    /// Local time at which the day starts
+    pub day_start: NaiveTime,
+
+    /// Offset of the site's local time
+    pub utc_offset: FixedOffset,
+}
+
+impl QuotaDay {
+    /// Day starting at `day_start` in `utc_offset`
+    pub fn new(day_start: NaiveTime, utc_offset: FixedOffset) -> Self {
+        Self {
+            day_start,
+            utc_offset,
+        }
+    }
+
+    /// Start (inclusive) and end (exclusive) of the day containing `now`
+    pub fn window(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let local = now.with_timezone(&self.utc_offset);
+        let mut date = local.date_naive();
+        if local.time() < self.day_start {
+            date -= Duration::days(1);
+        }
+
+        let start = self
+            .utc_offset
+            .from_local_datetime(&date.and_time(self.day_start))
+            .single()
+            .expect("fixed offsets map local times unambiguously")
+            .with_timezone(&Utc);
+        (start, start + Duration::days(1))
+    }
+}
+
+impl Default for QuotaDay {
+    fn default() -> Self {
+        Self::new(
+            NaiveTime::MIN,
+            FixedOffset::east_opt(0).expect("zero offset is valid"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_day_window_boundaries() {
+        let day = QuotaDay::default();
+        let midnight = Utc.with_ymd_and_hms(2025, 11, 10, 0, 0, 0).unwrap();
+
+        // The boundary itself opens the new day
+        assert_eq!(
+            day.window(midnight),
+            (midnight, midnight + Duration::days(1))
+        );
+        assert_eq!(
+            day.window(midnight - Duration::seconds(1)).0,
+            midnight - Duration::days(1)
+        );
+
+        let shifted = QuotaDay::new(
+            NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+            FixedOffset::east_opt(0).unwrap(),
+        );
+        let (start, _) = shifted.window(midnight + Duration::hours(5));
+        assert_eq!(start, midnight + Duration::hours(4));
+        let (start, _) = shifted.window(midnight + Duration::hours(3));
+        assert_eq!(start, midnight - Duration::hours(20));
+    }
+}
@@ -0,0 +1,568 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{StorageError, StorageResult};
+
+/// Minutes in a day; interval bounds are minutes since midnight
+pub const MINUTES_PER_DAY: u16 = 24 * 60;
+
+const DAY_WORDS: usize = (MINUTES_PER_DAY as usize).div_ceil(64);
+const WEEK_WORDS: usize = (7 * MINUTES_PER_DAY as usize).div_ceil(64);
+
+/// Daily time interval, minute resolution
+///
+/// `start_minute` is in `0..1440` and `end_minute` in `1..=1440`, so
+/// `00:00-24:00` covers a whole day. An interval whose end is not after its
+/// start crosses midnight: `23:00-02:00` belongs to the day it starts on and
+/// continues until 02:00 of the following day.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::TimeInterval;
+///
+/// let night = TimeInterval::parse("23:00-02:00").unwrap();
+/// assert!(night.is_overnight());
+/// assert_eq!(night.duration_minutes(), 180);
+/// assert_eq!(night.to_string(), "23:00-02:00");
+///
+/// assert!(TimeInterval::parse("08:00-08:00").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TimeInterval {
+    /// Start, in minutes since midnight (inclusive)
+    pub start_minute: u16,
+
+    /// End, in minutes since midnight (exclusive); on the next day if overnight
+    pub end_minute: u16,
+}
+
+impl TimeInterval {
+    /// Create an interval from minutes since midnight
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if a bound is out of range or both bounds are
+    /// equal (which would be ambiguous between empty and 24 hours).
+    pub fn new(start_minute: u16, end_minute: u16) -> StorageResult<Self> {
+        if start_minute >= MINUTES_PER_DAY {
+            return Err(StorageError::Validation(format!(
+                "Interval start must be before 24:00, got minute {}",
+                start_minute
+            )));
+        }
+        if end_minute == 0 || end_minute > MINUTES_PER_DAY {
+            return Err(StorageError::Validation(format!(
+                "Interval end must be 00:01-24:00, got minute {}",
+                end_minute
+            )));
+        }
+        if start_minute == end_minute {
+            return Err(StorageError::Validation(format!(
+                "Interval {} starts and ends at the same time",
+                format_minute(start_minute)
+            )));
+        }
+
+        Ok(Self {
+            start_minute,
+            end_minute,
+        })
+    }
+
+    /// Parse an interval written as `HH:MM-HH:MM` (`24:00` allowed as end)
+    pub fn parse(s: &str) -> StorageResult<Self> {
+        let (start, end) = s.split_once('-').ok_or_else(|| {
+            StorageError::Validation(format!("Invalid interval '{}': expected HH:MM-HH:MM", s))
+        })?;
+        Self::new(parse_minute(start.trim())?, parse_minute(end.trim())?)
+    }
+
+    /// Whether the interval continues past midnight into the next day
+    pub fn is_overnight(&self) -> bool {
+        self.end_minute < self.start_minute
+    }
+
+    /// Length of the interval in minutes
+    pub fn duration_minutes(&self) -> u16 {
+        if self.is_overnight() {
+            MINUTES_PER_DAY - self.start_minute + self.end_minute
+        } else {
+            self.end_minute - self.start_minute
+        }
+    }
+
+    /// Minutes covered on the starting day
+    fn same_day(&self) -> (u16, u16) {
+        if self.is_overnight() {
+            (self.start_minute, MINUTES_PER_DAY)
+        } else {
+            (self.start_minute, self.end_minute)
+        }
+    }
+
+    /// Minutes covered on the next day, for overnight intervals
+    fn next_day(&self) -> Option<(u16, u16)> {
+        self.is_overnight().then_some((0, self.end_minute))
+    }
+}
+
+impl fmt::Display for TimeInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            format_minute(self.start_minute),
+            format_minute(self.end_minute)
+        )
+    }
+}
+
+fn format_minute(minute: u16) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+fn parse_minute(s: &str) -> StorageResult<u16> {
+    let invalid = || StorageError::Validation(format!("Invalid time '{}': expected HH:MM", s));
+    let (hours, minutes) = s.split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+    if minutes >= 60 || hours > 24 || (hours == 24 && minutes > 0) {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// Weekly access schedule with per-date exceptions
+///
+/// Each weekday holds any number of non-overlapping intervals. An exception
+/// replaces the intervals that start on its date (an empty list closes the
+/// day); overnight intervals from the previous day still run into it.
+/// Schedules are expressed in the site's local time.
+///
+/// Use [`compile()`](Self::compile) to validate the schedule and build the
+/// bitmaps that answer "is time T allowed?" in constant time.
+///
+/// # Database Schema
+///
+/// Maps to `schedules`, with intervals in `schedule_intervals` (weekday
+/// 1=Monday..7=Sunday) and exceptions in `schedule_exceptions` and
+/// `schedule_exception_intervals`.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{TimeInterval, WeeklySchedule};
+/// use chrono::{NaiveDate, Weekday};
+///
+/// let schedule = WeeklySchedule::new("Vigilancia")
+///     .with_interval(Weekday::Fri, TimeInterval::parse("23:00-02:00").unwrap())
+///     .with_exception(NaiveDate::from_ymd_opt(2025, 12, 26).unwrap(), vec![]);
+///
+/// let compiled = schedule.compile().unwrap();
+/// let friday = NaiveDate::from_ymd_opt(2025, 12, 19).unwrap();
+/// assert!(compiled.is_allowed(friday.and_hms_opt(23, 30, 0).unwrap()));
+/// assert!(compiled.is_allowed(friday.succ_opt().unwrap().and_hms_opt(1, 59, 0).unwrap()));
+///
+/// // Closed on the 26th (a Friday) by exception
+/// let closed = NaiveDate::from_ymd_opt(2025, 12, 26).unwrap();
+/// assert!(!compiled.is_allowed(closed.and_hms_opt(23, 30, 0).unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeeklySchedule {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Unique schedule name (1-100 chars)
+    pub name: String,
+
+    /// Intervals per weekday, Monday first
+    pub days: [Vec<TimeInterval>; 7],
+
+    /// Intervals replacing the weekly ones on specific dates
+    pub exceptions: BTreeMap<NaiveDate, Vec<TimeInterval>>,
+}
+
+impl WeeklySchedule {
+    /// Create an empty schedule (no access at any time)
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            name: name.into(),
+            days: Default::default(),
+            exceptions: BTreeMap::new(),
+        }
+    }
+
+    /// Add an interval starting on a weekday
+    pub fn with_interval(mut self, weekday: Weekday, interval: TimeInterval) -> Self {
+        self.days[weekday.num_days_from_monday() as usize].push(interval);
+        self
+    }
+
+    /// Add the same interval to several weekdays
+    pub fn with_interval_on(mut self, weekdays: &[Weekday], interval: TimeInterval) -> Self {
+        for weekday in weekdays {
+            self = self.with_interval(*weekday, interval);
+        }
+        self
+    }
+
+    /// Replace the intervals starting on a date (empty closes the day)
+    pub fn with_exception(mut self, date: NaiveDate, intervals: Vec<TimeInterval>) -> Self {
+        self.exceptions.insert(date, intervals);
+        self
+    }
+
+    /// Intervals starting on a weekday
+    pub fn intervals(&self, weekday: Weekday) -> &[TimeInterval] {
+        &self.days[weekday.num_days_from_monday() as usize]
+    }
+
+    /// Intervals starting on a date, after applying exceptions
+    pub fn intervals_on(&self, date: NaiveDate) -> &[TimeInterval] {
+        self.exceptions
+            .get(&date)
+            .map(Vec::as_slice)
+            .unwrap_or_else(|| self.intervals(date.weekday()))
+    }
+
+    /// Check the name and that no intervals overlap
+    ///
+    /// Overnight intervals are checked against the intervals of the day they
+    /// run into, including Sunday into Monday.
+    pub fn validate(&self) -> StorageResult<()> {
+        self.compile().map(|_| ())
+    }
+
+    /// Validate the schedule and precompute its minute bitmaps
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the name is empty or too long, or if two
+    /// intervals overlap on a weekday or on a date affected by an exception.
+    pub fn compile(&self) -> StorageResult<CompiledSchedule> {
+        if self.name.is_empty() || self.name.len() > 100 {
+            return Err(StorageError::Validation(
+                "Schedule name must be 1-100 characters".to_string(),
+            ));
+        }
+
+        let mut week = MinuteBitmap::<WEEK_WORDS>::new();
+        for (day, intervals) in self.days.iter().enumerate() {
+            let weekday = Weekday::try_from(day as u8).expect("day index is 0-6");
+            let today = day * MINUTES_PER_DAY as usize;
+            let tomorrow = (day + 1) % 7 * MINUTES_PER_DAY as usize;
+
+            for interval in intervals {
+                let overlap = || {
+                    StorageError::Validation(format!(
+                        "Schedule '{}': {} on {} overlaps another interval",
+                        self.name, interval, weekday
+                    ))
+                };
+                let (start, end) = interval.same_day();
+                week.insert(today + start as usize, today + end as usize)
+                    .map_err(|_| overlap())?;
+                if let Some((start, end)) = interval.next_day() {
+                    week.insert(tomorrow + start as usize, tomorrow + end as usize)
+                        .map_err(|_| overlap())?;
+                }
+            }
+        }
+
+        // Exceptions change the date itself and, through overnight
+        // intervals, the day after
+        let mut overrides = HashMap::new();
+        for date in self.exceptions.keys() {
+            for date in [*date, *date + Duration::days(1)] {
+                if let Entry::Vacant(entry) = overrides.entry(date) {
+                    entry.insert(self.compile_date(date)?);
+                }
+            }
+        }
+
+        Ok(CompiledSchedule { week, overrides })
+    }
+
+    /// Bitmap of one date, from its own intervals and the previous day's spill
+    fn compile_date(&self, date: NaiveDate) -> StorageResult<MinuteBitmap<DAY_WORDS>> {
+        let mut day = MinuteBitmap::<DAY_WORDS>::new();
+        let previous = date - Duration::days(1);
+
+        for interval in self.intervals_on(previous) {
+            if let Some((start, end)) = interval.next_day() {
+                day.insert(start as usize, end as usize)
+                    .map_err(|_| self.date_overlap(previous, interval, date))?;
+            }
+        }
+        for interval in self.intervals_on(date) {
+            let (start, end) = interval.same_day();
+            day.insert(start as usize, end as usize)
+                .map_err(|_| self.date_overlap(date, interval, date))?;
+        }
+
+        Ok(day)
+    }
+
+    fn date_overlap(
+        &self,
+        start: NaiveDate,
+        interval: &TimeInterval,
+        on: NaiveDate,
+    ) -> StorageError {
+        StorageError::Validation(format!(
+            "Schedule '{}': {} starting {} overlaps another interval on {}",
+            self.name, interval, start, on
+        ))
+    }
+}
+
+/// Validated schedule with precomputed minute bitmaps
+///
+/// One bit per minute of the week, plus one bitmap per date touched by an
+/// exception, so [`is_allowed()`](Self::is_allowed) is a lookup rather than a
+/// scan over intervals.
+#[derive(Debug, Clone)]
+pub struct CompiledSchedule {
+    week: MinuteBitmap<WEEK_WORDS>,
+    overrides: HashMap<NaiveDate, MinuteBitmap<DAY_WORDS>>,
+}
+
+impl CompiledSchedule {
+    /// Whether access is allowed at a local date and time
+    pub fn is_allowed(&self, at: NaiveDateTime) -> bool {
+        let minute = (at.hour() * 60 + at.minute()) as usize;
+
+        match self.overrides.get(&at.date()) {
+            Some(day) => day.contains(minute),
+            None => {
+                let day = at.weekday().num_days_from_monday() as usize;
+                self.week.contains(day * MINUTES_PER_DAY as usize + minute)
+            }
+        }
+    }
+
+    /// Whether an exception replaces the weekly intervals on `date`
+    pub fn has_exception(&self, date: NaiveDate) -> bool {
+        self.overrides.contains_key(&date)
+    }
+}
+
+/// Fixed-size bitset over minutes
+#[derive(Debug, Clone)]
+struct MinuteBitmap<const WORDS: usize>([u64; WORDS]);
+
+impl<const WORDS: usize> MinuteBitmap<WORDS> {
+    fn new() -> Self {
+        Self([0; WORDS])
+    }
+
+    fn contains(&self, minute: usize) -> bool {
+        self.0[minute / 64] & (1 << (minute % 64)) != 0
+    }
+
+    /// Set minutes `start..end`, failing if any of them is already set
+    fn insert(&mut self, start: usize, end: usize) -> Result<(), ()> {
+        if (start..end).any(|minute| self.contains(minute)) {
+            return Err(());
+        }
+        for minute in start..end {
+            self.0[minute / 64] |= 1 << (minute % 64);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(s: &str) -> TimeInterval {
+        TimeInterval::parse(s).unwrap()
+    }
+
+    fn at(date: NaiveDate, hour: u32, minute: u32) -> NaiveDateTime {
+        date.and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    /// A Monday
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 10, 27).unwrap()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(
+            interval("08:00-12:30"),
+            TimeInterval::new(480, 750).unwrap()
+        );
+        assert_eq!(interval("00:00-24:00").duration_minutes(), 1440);
+        assert!(!interval("00:00-24:00").is_overnight());
+
+        for invalid in [
+            "",
+            "08:00",
+            "8-12",
+            "08:60-09:00",
+            "24:01-01:00",
+            "24:00-01:00",
+            "08:00-08:00",
+        ] {
+            assert!(TimeInterval::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_end_of_day_written_as_24() {
+        // Midnight as an end bound is 24:00, never 00:00
+        assert!(TimeInterval::parse("22:00-00:00").is_err());
+        assert!(!interval("22:00-24:00").is_overnight());
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let compiled = WeeklySchedule::new("Noturno")
+            .with_interval(Weekday::Mon, interval("23:00-02:00"))
+            .compile()
+            .unwrap();
+        let tuesday = monday().succ_opt().unwrap();
+
+        assert!(!compiled.is_allowed(at(monday(), 22, 59)));
+        assert!(compiled.is_allowed(at(monday(), 23, 0)));
+        assert!(compiled.is_allowed(at(monday(), 23, 59)));
+        assert!(compiled.is_allowed(at(tuesday, 0, 0)));
+        assert!(compiled.is_allowed(at(tuesday, 1, 59)));
+        assert!(!compiled.is_allowed(at(tuesday, 2, 0)));
+        assert!(!compiled.is_allowed(at(tuesday, 23, 30)));
+    }
+
+    #[test]
+    fn test_sunday_overnight_wraps_to_monday() {
+        let compiled = WeeklySchedule::new("Domingo")
+            .with_interval(Weekday::Sun, interval("22:00-06:00"))
+            .compile()
+            .unwrap();
+        let sunday = monday() - Duration::days(1);
+
+        assert!(compiled.is_allowed(at(sunday, 23, 0)));
+        assert!(compiled.is_allowed(at(monday(), 5, 59)));
+        assert!(!compiled.is_allowed(at(monday(), 6, 0)));
+    }
+
+    #[test]
+    fn test_adjacent_intervals_do_not_overlap() {
+        let compiled = WeeklySchedule::new("Comercial")
+            .with_interval(Weekday::Mon, interval("08:00-12:00"))
+            .with_interval(Weekday::Mon, interval("12:00-18:00"))
+            .with_interval(Weekday::Mon, interval("18:00-08:00"))
+            .compile()
+            .unwrap();
+
+        assert!(compiled.is_allowed(at(monday(), 12, 0)));
+        assert!(compiled.is_allowed(at(monday(), 17, 59)));
+        assert!(compiled.is_allowed(at(monday().succ_opt().unwrap(), 7, 59)));
+    }
+
+    #[test]
+    fn test_overlapping_intervals_rejected() {
+        let same_day = WeeklySchedule::new("Sobreposto")
+            .with_interval(Weekday::Wed, interval("08:00-12:00"))
+            .with_interval(Weekday::Wed, interval("11:59-13:00"));
+        assert!(matches!(
+            same_day.validate(),
+            Err(StorageError::Validation(msg)) if msg.contains("Wed")
+        ));
+
+        // Monday's overnight interval runs into Tuesday's first interval
+        let across_midnight = WeeklySchedule::new("Sobreposto")
+            .with_interval(Weekday::Mon, interval("23:00-02:00"))
+            .with_interval(Weekday::Tue, interval("01:00-03:00"));
+        assert!(across_midnight.validate().is_err());
+
+        let across_week = WeeklySchedule::new("Sobreposto")
+            .with_interval(Weekday::Sun, interval("23:00-02:00"))
+            .with_interval(Weekday::Mon, interval("00:00-01:00"));
+        assert!(across_week.validate().is_err());
+    }
+
+    #[test]
+    fn test_exception_replaces_day() {
+        let weekdays = [Weekday::Mon, Weekday::Tue, Weekday::Wed];
+        let holiday = monday().succ_opt().unwrap();
+        let compiled = WeeklySchedule::new("Comercial")
+            .with_interval_on(&weekdays, interval("08:00-18:00"))
+            .with_exception(holiday, vec![interval("10:00-12:00")])
+            .compile()
+            .unwrap();
+
+        assert!(compiled.is_allowed(at(monday(), 9, 0)));
+        assert!(!compiled.is_allowed(at(holiday, 9, 0)));
+        assert!(compiled.is_allowed(at(holiday, 11, 0)));
+        assert!(compiled.is_allowed(at(holiday.succ_opt().unwrap(), 9, 0)));
+    }
+
+    #[test]
+    fn test_exception_and_overnight_spill() {
+        let tuesday = monday().succ_opt().unwrap();
+
+        // Closing Tuesday keeps Monday night's interval running into it...
+        let compiled = WeeklySchedule::new("Noturno")
+            .with_interval_on(&[Weekday::Mon, Weekday::Tue], interval("23:00-02:00"))
+            .with_exception(tuesday, vec![])
+            .compile()
+            .unwrap();
+        assert!(compiled.is_allowed(at(tuesday, 1, 0)));
+        assert!(!compiled.is_allowed(at(tuesday, 23, 30)));
+        assert!(!compiled.is_allowed(at(tuesday.succ_opt().unwrap(), 1, 0)));
+
+        // ...and an overnight exception runs into the next day
+        let compiled = WeeklySchedule::new("Evento")
+            .with_exception(monday(), vec![interval("20:00-03:00")])
+            .compile()
+            .unwrap();
+        assert!(compiled.is_allowed(at(tuesday, 2, 59)));
+        assert!(!compiled.is_allowed(at(tuesday, 3, 0)));
+
+        // An exception may not collide with the previous night's spill
+        let colliding = WeeklySchedule::new("Noturno")
+            .with_interval(Weekday::Mon, interval("23:00-02:00"))
+            .with_exception(tuesday, vec![interval("01:00-05:00")]);
+        assert!(matches!(
+            colliding.validate(),
+            Err(StorageError::Validation(msg)) if msg.contains("2025-10-28")
+        ));
+    }
+
+    #[test]
+    fn test_full_day_and_empty_schedule() {
+        let always = WeeklySchedule::new("24h")
+            .with_interval_on(
+                &[
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                    Weekday::Sat,
+                    Weekday::Sun,
+                ],
+                interval("00:00-24:00"),
+            )
+            .compile()
+            .unwrap();
+        let never = WeeklySchedule::new("Nunca").compile().unwrap();
+
+        for day in 0..7 {
+            let date = monday() + Duration::days(day);
+            assert!(always.is_allowed(at(date, 0, 0)));
+            assert!(always.is_allowed(at(date, 23, 59)));
+            assert!(!never.is_allowed(at(date, 12, 0)));
+        }
+        assert!(WeeklySchedule::new("").validate().is_err());
+    }
+}
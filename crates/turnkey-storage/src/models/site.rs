@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Physical installation grouping zones (a campus, a building complex)
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `name` - Unique site name (1-100 chars)
+/// * `description` - Optional free-form description
+/// * `created_at` - Record creation timestamp
+/// * `updated_at` - Record last modification timestamp
+///
+/// # Database Schema
+///
+/// Maps to the `sites` table. Deleting a site deletes its zones; devices in
+/// those zones become unassigned.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::{Site, Zone};
+///
+/// let site = Site::new("Sede").with_description("Av. Paulista, 1000");
+/// let zone = Zone::new(site.id, "Building A");
+///
+/// assert_eq!(site.description.as_deref(), Some("Av. Paulista, 1000"));
+/// assert_eq!(zone.name, "Building A");
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Site {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Unique site name
+    pub name: String,
+
+    /// Optional description
+    pub description: Option<String>,
+
+    /// Record creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Record last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Site {
+    /// Create a new site without description
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            name: name.into(),
+            description: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Area of a site served by a set of devices (a building, a floor, a gate)
+///
+/// # Fields
+///
+/// * `id` - Auto-increment primary key
+/// * `site_id` - Site the zone belongs to
+/// * `name` - Zone name, unique within the site (1-100 chars)
+/// * `description` - Optional free-form description
+/// * `created_at` - Record creation timestamp
+/// * `updated_at` - Record last modification timestamp
+///
+/// # Database Schema
+///
+/// Maps to the `zones` table. Devices reference their zone through
+/// `devices.zone_id`; access exceptions may be restricted to zones through
+/// `access_exception_zones`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Zone {
+    /// Auto-increment primary key
+    pub id: i64,
+
+    /// Site the zone belongs to
+    pub site_id: i64,
+
+    /// Zone name (unique within the site)
+    pub name: String,
+
+    /// Optional description
+    pub description: Option<String>,
+
+    /// Record creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Record last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Zone {
+    /// Create a new zone in the given site
+    pub fn new(site_id: i64, name: impl Into<String>) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            site_id,
+            name: name.into(),
+            description: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Number of people currently inside a zone
+///
+/// A credential counts towards the zone of the device that granted its last
+/// passage, provided that passage was an entry (see
+/// [`AccessState::inside`](super::AccessState::inside)).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ZoneOccupancy {
+    /// Zone ID
+    pub zone_id: i64,
+
+    /// Zone name
+    pub zone_name: String,
+
+    /// Credentials inside
+    pub inside: i64,
+}
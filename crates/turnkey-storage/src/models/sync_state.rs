@@ -0,0 +1,136 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Default age in seconds after which local data counts as stale (3 days)
+///
+/// Card lists are normally pushed at least daily; three days without a
+/// sync survives a long weekend while still catching a broken link.
+pub const DEFAULT_STALE_DATA_THRESHOLD_SECS: i64 = 3 * 24 * 60 * 60;
+
+/// Freshness of the local database
+///
+/// # Fields
+///
+/// * `last_success_at` - Last time data was synced successfully (None if never)
+/// * `last_source` - What delivered that data (e.g. `"bundle"`)
+/// * `last_attempt_at` - Last sync attempt, successful or not
+/// * `last_error` - Error of the last failed attempt, cleared on success
+///
+/// # Database Schema
+///
+/// Maps to the single row of the `sync_state` table.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::models::SyncState;
+/// use chrono::{Duration, Utc};
+///
+/// let now = Utc::now();
+/// let state = SyncState {
+///     last_success_at: Some(now - Duration::days(4)),
+///     ..SyncState::default()
+/// };
+///
+/// assert_eq!(state.age(now), Some(Duration::days(4)));
+/// assert!(state.is_stale(Duration::days(3), now));
+/// assert!(SyncState::default().is_stale(Duration::days(3), now));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncState {
+    /// Last successful sync
+    pub last_success_at: Option<DateTime<Utc>>,
+
+    /// What delivered the data of the last successful sync
+    pub last_source: Option<String>,
+
+    /// Last sync attempt
+    pub last_attempt_at: Option<DateTime<Utc>>,
+
+    /// Error of the last failed attempt
+    pub last_error: Option<String>,
+}
+
+impl SyncState {
+    /// Time since the last successful sync, `None` if never synced
+    pub fn age(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.last_success_at.map(|at| now - at)
+    }
+
+    /// Whether the data is older than `threshold`
+    ///
+    /// A database that was never synced is stale: nothing is known about
+    /// where its data came from.
+    pub fn is_stale(&self, threshold: Duration, now: DateTime<Utc>) -> bool {
+        self.age(now).is_none_or(|age| age > threshold)
+    }
+}
+
+/// Warning raised when an offline decision is taken on stale data
+///
+/// Emitted by [`OfflineValidator`](crate::validator::OfflineValidator) for
+/// every request validated while the last sync is older than its staleness
+/// threshold, whether the request ends up granted or denied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaleDataWarning {
+    /// Henry device ID (None if the validator has no device)
+    pub device_id: Option<i64>,
+
+    /// Card presented
+    pub card_number: String,
+
+    /// Last successful sync (None if never synced)
+    pub last_success_at: Option<DateTime<Utc>>,
+
+    /// Age of the data in seconds (None if never synced)
+    pub age_secs: Option<i64>,
+
+    /// Threshold that was exceeded (seconds)
+    pub threshold_secs: i64,
+
+    /// Whether the request was denied because of the stale data
+    pub denied: bool,
+
+    /// Server time of the decision
+    pub timestamp: DateTime<Utc>,
+}
+
+impl StaleDataWarning {
+    /// Age of the data, `None` if never synced
+    pub fn age(&self) -> Option<Duration> {
+        self.age_secs.map(Duration::seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_data_is_not_stale() {
+        let now = Utc::now();
+        let state = SyncState {
+            last_success_at: Some(now - Duration::hours(2)),
+            ..SyncState::default()
+        };
+        assert!(!state.is_stale(Duration::days(1), now));
+    }
+
+    #[test]
+    fn test_data_at_threshold_is_not_stale() {
+        let now = Utc::now();
+        let state = SyncState {
+            last_success_at: Some(now - Duration::days(1)),
+            ..SyncState::default()
+        };
+        assert!(!state.is_stale(Duration::days(1), now));
+        assert!(state.is_stale(Duration::days(1), now + Duration::seconds(1)));
+    }
+
+    #[test]
+    fn test_never_synced_is_stale() {
+        let state = SyncState::default();
+        assert_eq!(state.age(Utc::now()), None);
+        assert!(state.is_stale(Duration::days(365), Utc::now()));
+    }
+}
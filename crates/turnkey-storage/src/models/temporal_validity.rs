@@ -115,13 +115,19 @@ pub trait TemporalValidity {
     /// # }
     /// ```
     fn is_valid(&self) -> bool {
+        self.is_valid_at(Utc::now())
+    }
+
+    /// Check if the entity is valid at a given time
+    ///
+    /// Same rules as [`is_valid()`](Self::is_valid), for callers that take
+    /// the time from a [`Clock`](turnkey_core::Clock).
+    fn is_valid_at(&self, now: DateTime<Utc>) -> bool {
         // Entity must be active
         if !self.is_active() {
             return false;
         }
 
-        let now = Utc::now();
-
         // Check validity start
         if let Some(start) = self.validity_start()
             && now < start
@@ -21,7 +21,8 @@ use super::TemporalValidity;
 /// * `allow_card` - Whether RFID/NFC card access is permitted
 /// * `allow_bio` - Whether biometric (fingerprint) access is permitted
 /// * `allow_keypad` - Whether keypad (PIN code) access is permitted
-/// * `codigo` - Numeric access code (required if allow_keypad is true)
+/// * `codigo` - Argon2id hash of the numeric access code (required if allow_keypad is true)
+/// * `empresa` - Employer or contractor company, optional
 /// * `created_at` - Record creation timestamp
 /// * `updated_at` - Record last modification timestamp
 ///
@@ -52,6 +53,7 @@ use super::TemporalValidity;
 ///     allow_bio: false,
 ///     allow_keypad: true,
 ///     codigo: Some("1234".to_string()),
+///     empresa: None,
 ///     created_at: Utc::now(),
 ///     updated_at: Utc::now(),
 /// };
@@ -101,8 +103,14 @@ pub struct User {
     pub allow_keypad: bool,
 
     /// Numeric access code (required if allow_keypad is true)
+    ///
+    /// Stored as an argon2id hash; see [`pin`](crate::pin). Plaintext
+    /// values are accepted on write and hashed by the repository.
     pub codigo: Option<String>,
 
+    /// Employer or contractor company (used for bulk card operations)
+    pub empresa: Option<String>,
+
     /// Record creation timestamp
     pub created_at: DateTime<Utc>,
 
@@ -142,12 +150,13 @@ impl User {
 
     /// Check if the provided PIN code matches the user's code
     ///
-    /// This method uses constant-time comparison to prevent timing attacks
-    /// that could be used to determine valid PIN codes through response time analysis.
+    /// `codigo` holds either an argon2id hash or, for records not yet
+    /// migrated, the plaintext code; both are handled by
+    /// [`pin::verify_stored`](crate::pin::verify_stored).
     ///
     /// # Security
     ///
-    /// CRITICAL: This implementation uses constant-time comparison via the `subtle` crate
+    /// CRITICAL: Plaintext codes are compared in constant time via the `subtle` crate
     /// to prevent timing side-channel attacks. Do not replace with standard string comparison.
     ///
     /// # Examples
@@ -159,20 +168,18 @@ impl User {
     /// #     id: 1, pis: None, nome: "Test".to_string(), matricula: "001".to_string(),
     /// #     cpf: None, validade_inicio: None, validade_fim: None, ativo: true,
     /// #     allow_card: false, allow_bio: false, allow_keypad: true,
-    /// #     codigo: Some("1234".to_string()), created_at: Utc::now(), updated_at: Utc::now(),
+    /// #     codigo: Some("1234".to_string()), empresa: None, created_at: Utc::now(), updated_at: Utc::now(),
     /// # };
     /// assert!(user.verify_code("1234"));
     /// assert!(!user.verify_code("9999"));
     /// ```
     pub fn verify_code(&self, code: &str) -> bool {
-        use subtle::ConstantTimeEq;
-
         if !self.can_use_keypad() {
             return false;
         }
 
         match &self.codigo {
-            Some(stored_code) => stored_code.as_bytes().ct_eq(code.as_bytes()).into(),
+            Some(stored_code) => crate::pin::verify_stored(code, stored_code),
             None => false,
         }
     }
@@ -197,6 +204,7 @@ mod tests {
             allow_bio: false,
             allow_keypad: true,
             codigo: Some("1234".to_string()),
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -259,6 +267,19 @@ mod tests {
         assert!(!user.verify_code("9999"));
     }
 
+    #[test]
+    fn test_verify_code_hashed() {
+        use crate::pin::{Argon2PinHasher, PinHashParams, PinHasher};
+
+        let hasher =
+            Argon2PinHasher::new(PinHashParams::default().memory_kib(64).iterations(1)).unwrap();
+        let mut user = create_test_user();
+        user.codigo = Some(hasher.hash("1234").unwrap());
+
+        assert!(user.verify_code("1234"));
+        assert!(!user.verify_code("9999"));
+    }
+
     #[test]
     fn test_verify_code_no_code() {
         let mut user = create_test_user();
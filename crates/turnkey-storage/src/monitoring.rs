@@ -0,0 +1,440 @@
+//! Storage growth monitoring with soft limits.
+//!
+//! Access logs are append-only, so on a busy site the SQLite file grows
+//! steadily until someone notices the disk filling up. [`StorageMonitor`]
+//! measures table row counts and the database size, compares them with
+//! configurable warning/critical thresholds, and publishes a
+//! [`StorageAlert`] for each limit that is crossed.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::monitoring::{StorageMonitor, StorageThresholds};
+//! use std::time::Duration;
+//! use tokio::sync::mpsc;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! let (alert_tx, mut alert_rx) = mpsc::channel(16);
+//! let (retention_tx, mut retention_rx) = mpsc::channel(1);
+//!
+//! let monitor = StorageMonitor::new(db.pool().clone(), StorageThresholds::default())
+//!     .with_alerts(alert_tx)
+//!     .with_retention_trigger(retention_tx);
+//! let _handle = monitor.spawn(Duration::from_secs(15 * 60));
+//!
+//! while let Some(alert) = alert_rx.recv().await {
+//!     println!("{:?} {:?}: {} >= {}", alert.level, alert.metric, alert.value, alert.threshold);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Delivery
+//!
+//! Alerts are re-sent on every check while a metric stays above its limit,
+//! so the check interval also bounds how often operators are notified.
+//! Delivery is best-effort, like clock drift alerts: a full or closed
+//! channel drops the alert instead of stalling the monitor.
+
+use crate::error::StorageResult;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Warning and critical levels for a single metric
+///
+/// A metric at or above `critical` raises a critical alert; at or above
+/// `warning` (but below `critical`) a warning. `None` disables that level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SoftLimit {
+    /// Value that raises a warning
+    pub warning: Option<u64>,
+
+    /// Value that raises a critical alert
+    pub critical: Option<u64>,
+}
+
+impl SoftLimit {
+    /// Create a limit with both levels set
+    pub fn new(warning: u64, critical: u64) -> Self {
+        Self {
+            warning: Some(warning),
+            critical: Some(critical),
+        }
+    }
+
+    /// A limit that never alerts
+    pub fn disabled() -> Self {
+        Self {
+            warning: None,
+            critical: None,
+        }
+    }
+
+    /// Classify a value against this limit
+    ///
+    /// Returns the alert level and the threshold that was reached, or `None`
+    /// when the value is below every configured level.
+    pub fn evaluate(&self, value: u64) -> Option<(StorageAlertLevel, u64)> {
+        if let Some(critical) = self.critical
+            && value >= critical
+        {
+            return Some((StorageAlertLevel::Critical, critical));
+        }
+        if let Some(warning) = self.warning
+            && value >= warning
+        {
+            return Some((StorageAlertLevel::Warning, warning));
+        }
+        None
+    }
+}
+
+/// Thresholds checked by [`StorageMonitor`]
+///
+/// Defaults are sized for the embedded controllers this project targets:
+/// about a year of logs for a busy turnstile before the warning fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageThresholds {
+    /// Row count limits for the `access_logs` table
+    pub access_log_rows: SoftLimit,
+
+    /// Size limits for the database file, in bytes
+    pub database_bytes: SoftLimit,
+}
+
+impl Default for StorageThresholds {
+    fn default() -> Self {
+        Self {
+            access_log_rows: SoftLimit::new(1_000_000, 5_000_000),
+            database_bytes: SoftLimit::new(512 * 1024 * 1024, 2 * 1024 * 1024 * 1024),
+        }
+    }
+}
+
+/// Severity of a storage alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum StorageAlertLevel {
+    /// Approaching the limit; plan an archival
+    Warning,
+
+    /// Limit reached; archival should run now
+    Critical,
+}
+
+/// Metric that crossed a soft limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageMetric {
+    /// Number of rows in `access_logs`
+    AccessLogRows,
+
+    /// Size of the database file in bytes
+    DatabaseBytes,
+}
+
+/// Alert published when a metric reaches a soft limit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageAlert {
+    /// Alert severity
+    pub level: StorageAlertLevel,
+
+    /// Metric that crossed the limit
+    pub metric: StorageMetric,
+
+    /// Measured value
+    pub value: u64,
+
+    /// Threshold that was reached
+    pub threshold: u64,
+}
+
+/// Snapshot of table sizes and database file size
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// Row count per application table, keyed by table name
+    pub table_rows: BTreeMap<String, u64>,
+
+    /// Size of the main database file in bytes (`page_count * page_size`)
+    ///
+    /// Excludes the WAL file, which is bounded by checkpointing.
+    pub database_bytes: u64,
+}
+
+impl StorageUsage {
+    /// Get the row count of a table (0 if the table is unknown)
+    pub fn rows(&self, table: &str) -> u64 {
+        self.table_rows.get(table).copied().unwrap_or(0)
+    }
+}
+
+/// Periodic checker for table row counts and database size
+pub struct StorageMonitor {
+    pool: SqlitePool,
+    thresholds: StorageThresholds,
+    alerts: Option<mpsc::Sender<StorageAlert>>,
+    retention_trigger: Option<mpsc::Sender<StorageAlert>>,
+}
+
+impl std::fmt::Debug for StorageMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StorageMonitor")
+            .field("thresholds", &self.thresholds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StorageMonitor {
+    /// Create a monitor for the given database pool
+    pub fn new(pool: SqlitePool, thresholds: StorageThresholds) -> Self {
+        Self {
+            pool,
+            thresholds,
+            alerts: None,
+            retention_trigger: None,
+        }
+    }
+
+    /// Publish every warning and critical alert on `alerts`
+    pub fn with_alerts(mut self, alerts: mpsc::Sender<StorageAlert>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Notify a retention/archival task when a critical limit is reached
+    ///
+    /// The task receives the critical alert that caused the trigger and is
+    /// expected to archive or prune old access logs. Warnings never trigger
+    /// retention.
+    pub fn with_retention_trigger(mut self, trigger: mpsc::Sender<StorageAlert>) -> Self {
+        self.retention_trigger = Some(trigger);
+        self
+    }
+
+    /// Get the configured thresholds
+    pub fn thresholds(&self) -> &StorageThresholds {
+        &self.thresholds
+    }
+
+    /// Measure row counts of all application tables and the database size
+    pub async fn usage(&self) -> StorageResult<StorageUsage> {
+        let tables: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT name FROM sqlite_master
+            WHERE type = 'table'
+              AND name NOT LIKE 'sqlite_%'
+              AND name NOT LIKE '_sqlx_%'
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut table_rows = BTreeMap::new();
+        for (table,) in tables {
+            // Table names come from sqlite_master, not user input; quoting
+            // only guards against unusual identifiers
+            let sql = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+            let (count,): (i64,) = sqlx::query_as(&sql).fetch_one(&self.pool).await?;
+            table_rows.insert(table, count.max(0) as u64);
+        }
+
+        let (database_bytes,): (i64,) = sqlx::query_as(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(StorageUsage {
+            table_rows,
+            database_bytes: database_bytes.max(0) as u64,
+        })
+    }
+
+    /// Compare a usage snapshot against the thresholds
+    pub fn evaluate(&self, usage: &StorageUsage) -> Vec<StorageAlert> {
+        let metrics = [
+            (
+                StorageMetric::AccessLogRows,
+                usage.rows("access_logs"),
+                self.thresholds.access_log_rows,
+            ),
+            (
+                StorageMetric::DatabaseBytes,
+                usage.database_bytes,
+                self.thresholds.database_bytes,
+            ),
+        ];
+
+        metrics
+            .into_iter()
+            .filter_map(|(metric, value, limit)| {
+                limit
+                    .evaluate(value)
+                    .map(|(level, threshold)| StorageAlert {
+                        level,
+                        metric,
+                        value,
+                        threshold,
+                    })
+            })
+            .collect()
+    }
+
+    /// Measure usage once, publish alerts and trigger retention if needed
+    ///
+    /// Returns the alerts raised by this check (empty when all metrics are
+    /// below their limits).
+    pub async fn check(&self) -> StorageResult<Vec<StorageAlert>> {
+        let usage = self.usage().await?;
+        let alerts = self.evaluate(&usage);
+
+        for alert in &alerts {
+            if let Some(tx) = &self.alerts {
+                let _ = tx.try_send(alert.clone());
+            }
+        }
+
+        // One trigger per check is enough, even if several metrics are critical
+        if let Some(trigger) = &self.retention_trigger
+            && let Some(critical) = alerts
+                .iter()
+                .find(|alert| alert.level == StorageAlertLevel::Critical)
+        {
+            let _ = trigger.try_send(critical.clone());
+        }
+
+        Ok(alerts)
+    }
+
+    /// Run [`check`](Self::check) every `interval` on a background task
+    ///
+    /// The first check runs immediately. Failed checks are skipped and
+    /// retried at the next tick. Abort the returned handle to stop.
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let _ = self.check().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn insert_logs(db: &Database, count: usize) {
+        for _ in 0..count {
+            sqlx::query!(r#"
+                INSERT INTO access_logs (card_number, direction, reader_type, granted, display_message, timestamp)
+                VALUES ('9999999999', 1, 1, 0, 'Cartao nao cadastrado', datetime('now'))
+                "#)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        }
+    }
+
+    fn row_thresholds(warning: u64, critical: u64) -> StorageThresholds {
+        StorageThresholds {
+            access_log_rows: SoftLimit::new(warning, critical),
+            database_bytes: SoftLimit::disabled(),
+        }
+    }
+
+    #[test]
+    fn test_soft_limit_evaluate() {
+        let limit = SoftLimit::new(10, 20);
+        assert_eq!(limit.evaluate(9), None);
+        assert_eq!(limit.evaluate(10), Some((StorageAlertLevel::Warning, 10)));
+        assert_eq!(limit.evaluate(25), Some((StorageAlertLevel::Critical, 20)));
+        assert_eq!(SoftLimit::disabled().evaluate(u64::MAX), None);
+    }
+
+    #[tokio::test]
+    async fn test_usage_counts_tables() {
+        let db = setup_test_db().await;
+        let monitor = StorageMonitor::new(db.pool().clone(), StorageThresholds::default());
+        let seeded = monitor.usage().await.unwrap().rows("access_logs");
+
+        insert_logs(&db, 3).await;
+        let usage = monitor.usage().await.unwrap();
+
+        assert_eq!(usage.rows("access_logs"), seeded + 3);
+        assert!(usage.rows("users") > 0);
+        assert!(!usage.table_rows.contains_key("_sqlx_migrations"));
+        assert!(usage.database_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_below_limits() {
+        let db = setup_test_db().await;
+        let monitor =
+            StorageMonitor::new(db.pool().clone(), row_thresholds(u64::MAX - 1, u64::MAX));
+
+        assert!(monitor.check().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_publishes_warning_without_retention() {
+        let db = setup_test_db().await;
+        let seeded = StorageMonitor::new(db.pool().clone(), StorageThresholds::default())
+            .usage()
+            .await
+            .unwrap()
+            .rows("access_logs");
+        insert_logs(&db, 3).await;
+
+        let (alert_tx, mut alert_rx) = mpsc::channel(4);
+        let (retention_tx, mut retention_rx) = mpsc::channel(1);
+        let monitor =
+            StorageMonitor::new(db.pool().clone(), row_thresholds(seeded + 2, seeded + 5))
+                .with_alerts(alert_tx)
+                .with_retention_trigger(retention_tx);
+
+        monitor.check().await.unwrap();
+
+        let alert = alert_rx.try_recv().unwrap();
+        assert_eq!(alert.level, StorageAlertLevel::Warning);
+        assert_eq!(alert.metric, StorageMetric::AccessLogRows);
+        assert_eq!(alert.value, seeded + 3);
+        assert_eq!(alert.threshold, seeded + 2);
+        assert!(retention_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_critical_triggers_retention() {
+        let db = setup_test_db().await;
+        insert_logs(&db, 3).await;
+
+        let (retention_tx, mut retention_rx) = mpsc::channel(1);
+        let thresholds = StorageThresholds {
+            access_log_rows: SoftLimit::new(1, 2),
+            database_bytes: SoftLimit::new(1, 2),
+        };
+        let monitor =
+            StorageMonitor::new(db.pool().clone(), thresholds).with_retention_trigger(retention_tx);
+
+        let alerts = monitor.check().await.unwrap();
+        assert_eq!(alerts.len(), 2);
+
+        let trigger = retention_rx.try_recv().unwrap();
+        assert_eq!(trigger.level, StorageAlertLevel::Critical);
+        assert_eq!(trigger.metric, StorageMetric::AccessLogRows);
+        assert!(retention_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,830 @@
+#![allow(async_fn_in_trait)]
+
+//! Transactional outbox for notifications to the server.
+//!
+//! Every access decided locally is reported to the server as an `ER` event
+//! (see [`access_notification()`]). If the process stopped between writing
+//! the access log and sending that message, the server would never learn
+//! about the access. [`Outbox::enqueue()`] stores the message in the same
+//! transaction as the log, so both commit or neither does, and an
+//! [`OutboxDispatcher`] sends what is pending.
+//!
+//! # NSR
+//!
+//! Each queued message gets an NSR (Numero Sequencial de Registro), the
+//! sequential record number of Henry event logs: per device, it increases
+//! by one with every message and is never reused, even once sent messages
+//! are purged. Access events queued with [`Outbox::enqueue_access()`] carry
+//! it as their event ID, and the access log they report is stamped with the
+//! same number (see [`Outbox::number_access()`]).
+//!
+//! # Delivery
+//!
+//! Pending messages are sent in the order they were queued and marked sent
+//! once the [`NotificationSink`] accepts them. A crash in between sends the
+//! message again, so delivery is at-least-once. The server skips events
+//! whose NSR it already stored (see [`EventReceiver`](crate::sync::EventReceiver)),
+//! so it can take duplicates.
+//!
+//! A failed send is retried with exponential backoff. The dispatcher stops
+//! at the failed message until it goes through, so later messages never
+//! overtake it. For a [`TcpClient`] the guarantee ends once the message is
+//! written to the connection.
+//!
+//! Sent messages are removed with [`purge_sent()`](Outbox::purge_sent).
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use turnkey_core::DeviceId;
+//! use turnkey_network::{TcpClient, TcpClientConfig};
+//! use turnkey_storage::outbox::{Outbox, OutboxDispatcher};
+//! use turnkey_storage::{Database, DatabaseConfig, OfflineValidator};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//!
+//! // Access logs and their notifications now commit together
+//! let validator = OfflineValidator::new(db.pool().clone())
+//!     .with_device_id(DeviceId::new(15)?)
+//!     .with_outbox(Outbox::new(db.pool().clone()));
+//!
+//! let client = TcpClient::new(TcpClientConfig::default());
+//! OutboxDispatcher::new(db.pool().clone())
+//!     .run(client, Duration::from_secs(1))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::log_pull::{DeviceLogSource, protocol_error};
+use crate::models::AccessLog;
+use chrono::{DateTime, Utc};
+use sqlx::{Sqlite, SqliteConnection, SqlitePool, Transaction};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use turnkey_core::DeviceId;
+use turnkey_network::TcpClient;
+use turnkey_protocol::commands::events::EventChunk;
+use turnkey_protocol::{Message, MessageParser, format_message};
+
+/// Messages sent by one [`OutboxDispatcher::dispatch()`] unless configured otherwise
+pub const DEFAULT_OUTBOX_BATCH_SIZE: i64 = 100;
+
+/// Wait before the first retry of a failed send
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between retries of a failed send
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Notification of an access, as the `ER` event a device sends
+///
+/// The event carries `event_id` as its ID in a single chunk: the NSR for
+/// events queued with [`Outbox::enqueue_access()`].
+///
+/// # Errors
+///
+/// Returns `Validation` if the log has an unknown direction or reader type,
+/// and `ProtocolError` if a field contains protocol delimiters.
+pub fn access_notification(
+    device_id: DeviceId,
+    event_id: i64,
+    log: &AccessLog,
+) -> StorageResult<Message> {
+    let event = DeviceLogSource::to_event(AccessLog {
+        id: event_id,
+        ..log.clone()
+    })?;
+    EventChunk {
+        index: 0,
+        last: true,
+        more: false,
+        next_after_id: event_id,
+        events: vec![event],
+    }
+    .to_message(device_id)
+    .map_err(protocol_error)
+}
+
+/// One queued message
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /// Queue position, increasing in commit order
+    pub id: i64,
+
+    /// Sequential record number, per device
+    pub nsr: i64,
+
+    /// Message to send
+    pub message: Message,
+
+    /// Access log the message is about
+    pub access_log_id: Option<i64>,
+
+    /// Failed sends so far
+    pub attempts: u32,
+
+    /// Error of the last failed send
+    pub last_error: Option<String>,
+
+    /// Not sent before this time
+    pub next_attempt_at: DateTime<Utc>,
+
+    /// When the message was queued
+    pub created_at: DateTime<Utc>,
+}
+
+struct OutboxRow {
+    id: i64,
+    nsr: i64,
+    message: String,
+    access_log_id: Option<i64>,
+    attempts: i64,
+    last_error: Option<String>,
+    next_attempt_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<OutboxRow> for OutboxEntry {
+    type Error = StorageError;
+
+    fn try_from(row: OutboxRow) -> StorageResult<Self> {
+        Ok(Self {
+            id: row.id,
+            nsr: row.nsr,
+            message: MessageParser::parse(&row.message).map_err(protocol_error)?,
+            access_log_id: row.access_log_id,
+            attempts: u32::try_from(row.attempts).unwrap_or(u32::MAX),
+            last_error: row.last_error,
+            next_attempt_at: row.next_attempt_at,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Queue of messages waiting to be sent to the server
+///
+/// See the [module documentation](self) for the delivery guarantees.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    pool: SqlitePool,
+}
+
+impl Outbox {
+    /// Create an outbox on the given database pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Begin a transaction to write messages with the rows they are about
+    pub async fn begin(&self) -> StorageResult<Transaction<'static, Sqlite>> {
+        Ok(self.pool.begin().await?)
+    }
+
+    /// Queue `message` within a transaction
+    ///
+    /// The message is sent only if the transaction commits.
+    ///
+    /// # Returns
+    ///
+    /// Returns the queue position of the message
+    pub async fn enqueue(
+        tx: &mut Transaction<'_, Sqlite>,
+        message: &Message,
+        access_log_id: Option<i64>,
+    ) -> StorageResult<i64> {
+        let nsr = Self::next_nsr(tx, i64::from(message.device_id.as_u8())).await?;
+        Self::insert(tx, message, access_log_id, nsr).await
+    }
+
+    /// Queue the `ER` event of an access log within a transaction
+    ///
+    /// The log is numbered with the next NSR of `device_id` (see
+    /// [`number_access()`](Self::number_access)), which the event carries
+    /// as its event ID (see [`access_notification()`]).
+    ///
+    /// # Returns
+    ///
+    /// Returns the NSR of the event
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` or `ProtocolError` if the log cannot be encoded.
+    pub async fn enqueue_access(
+        tx: &mut Transaction<'_, Sqlite>,
+        device_id: DeviceId,
+        access_log_id: i64,
+        log: &AccessLog,
+    ) -> StorageResult<i64> {
+        let nsr = Self::number_access(tx, device_id, access_log_id).await?;
+        let message = access_notification(device_id, nsr, log)?;
+        Self::insert(tx, &message, Some(access_log_id), nsr).await?;
+        Ok(nsr)
+    }
+
+    /// Number an access log with the next NSR of `device_id`
+    ///
+    /// Access logs and queued messages share the counter, so the NSR
+    /// stored in the log is the one its `ER` event is sent with. A log that
+    /// is already numbered keeps its NSR.
+    ///
+    /// # Returns
+    ///
+    /// Returns the NSR of the log
+    pub async fn number_access(
+        tx: &mut Transaction<'_, Sqlite>,
+        device_id: DeviceId,
+        access_log_id: i64,
+    ) -> StorageResult<i64> {
+        if let Some(nsr) = Self::number_log(tx, access_log_id).await? {
+            return Ok(nsr);
+        }
+
+        let nsr = Self::next_nsr(tx, i64::from(device_id.as_u8())).await?;
+        sqlx::query!(
+            "UPDATE access_logs SET nsr = ? WHERE id = ?",
+            nsr,
+            access_log_id
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(nsr)
+    }
+
+    /// Number an access log with the next NSR of its own device
+    ///
+    /// Every access log repository insert calls this in its transaction, so
+    /// a log a device writes is numbered however it is written. A log that
+    /// is already numbered keeps its NSR; one without a device has no
+    /// counter to take a number from and stays unnumbered.
+    ///
+    /// # Returns
+    ///
+    /// Returns the NSR of the log, `None` if it has no device
+    pub(crate) async fn number_log(
+        conn: &mut SqliteConnection,
+        access_log_id: i64,
+    ) -> StorageResult<Option<i64>> {
+        let log = sqlx::query!(
+            "SELECT device_id, nsr FROM access_logs WHERE id = ?",
+            access_log_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(log) = log else {
+            return Err(StorageError::NotFound {
+                entity_type: "AccessLog".to_string(),
+                field: "id".to_string(),
+                value: access_log_id.to_string(),
+            });
+        };
+
+        match (log.nsr, log.device_id) {
+            (Some(nsr), _) => Ok(Some(nsr)),
+            (None, Some(device_id)) => {
+                let nsr = Self::next_nsr(conn, device_id).await?;
+                sqlx::query!(
+                    "UPDATE access_logs SET nsr = ? WHERE id = ?",
+                    nsr,
+                    access_log_id
+                )
+                .execute(&mut *conn)
+                .await?;
+                Ok(Some(nsr))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Take the next NSR of `device_id`
+    async fn next_nsr(conn: &mut SqliteConnection, device_id: i64) -> StorageResult<i64> {
+        let nsr = sqlx::query_scalar!(
+            r#"
+            INSERT INTO outbox_sequences (device_id, last_nsr) VALUES (?, 1)
+            ON CONFLICT(device_id) DO UPDATE SET last_nsr = last_nsr + 1
+            RETURNING last_nsr
+            "#,
+            device_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(nsr)
+    }
+
+    async fn insert(
+        tx: &mut Transaction<'_, Sqlite>,
+        message: &Message,
+        access_log_id: Option<i64>,
+        nsr: i64,
+    ) -> StorageResult<i64> {
+        let device_id = i64::from(message.device_id.as_u8());
+        let text = format_message(message);
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO outbox_messages (device_id, nsr, message, access_log_id, next_attempt_at)
+            VALUES (?, ?, ?, ?, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            "#,
+            device_id,
+            nsr,
+            text,
+            access_log_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Count messages not sent yet
+    pub async fn pending_count(&self) -> StorageResult<i64> {
+        let count =
+            sqlx::query_scalar!("SELECT COUNT(*) FROM outbox_messages WHERE sent_at IS NULL")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count)
+    }
+
+    /// Get the oldest messages not sent yet, in sending order
+    ///
+    /// Includes messages waiting for a retry; see
+    /// [`next_attempt_at`](OutboxEntry::next_attempt_at).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if a stored message no longer parses.
+    pub async fn pending(&self, limit: i64) -> StorageResult<Vec<OutboxEntry>> {
+        let rows = sqlx::query_as!(
+            OutboxRow,
+            r#"
+            SELECT id AS "id!", nsr, message, access_log_id, attempts, last_error,
+                   next_attempt_at AS "next_attempt_at: _", created_at AS "created_at: _"
+            FROM outbox_messages
+            WHERE sent_at IS NULL
+            ORDER BY id
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(OutboxEntry::try_from).collect()
+    }
+
+    /// Mark a message as delivered
+    pub async fn mark_sent(&self, id: i64) -> StorageResult<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            UPDATE outbox_messages
+            SET sent_at = strftime('%Y-%m-%dT%H:%M:%fZ', ?)
+            WHERE id = ? AND sent_at IS NULL
+            "#,
+            now,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed send, to be retried at `retry_at`
+    pub async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outbox_messages
+            SET attempts = attempts + 1,
+                last_error = ?,
+                next_attempt_at = strftime('%Y-%m-%dT%H:%M:%fZ', ?)
+            WHERE id = ? AND sent_at IS NULL
+            "#,
+            error,
+            retry_at,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Make every pending message due now, cancelling retry waits
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of pending messages
+    pub async fn retry_now(&self) -> StorageResult<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE outbox_messages
+            SET next_attempt_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            WHERE sent_at IS NULL
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete messages sent before `before`
+    pub async fn purge_sent(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM outbox_messages
+            WHERE sent_at IS NOT NULL
+              AND sent_at < strftime('%Y-%m-%dT%H:%M:%fZ', ?)
+            "#,
+            before
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Destination of the messages in the outbox
+pub trait NotificationSink {
+    /// Deliver one message
+    ///
+    /// An error leaves the message in the outbox for a later retry.
+    async fn send(&mut self, message: Message) -> StorageResult<()>;
+
+    /// Whether the sink can no longer take messages, which stops
+    /// [`OutboxDispatcher::run()`]
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl NotificationSink for TcpClient {
+    async fn send(&mut self, message: Message) -> StorageResult<()> {
+        if !self.is_connected() {
+            self.connect()
+                .await
+                .map_err(|e| StorageError::NetworkError(format!("Connection failed: {}", e)))?;
+        }
+        TcpClient::send(self, message)
+            .await
+            .map_err(|e| StorageError::NetworkError(format!("Send failed: {}", e)))
+    }
+}
+
+impl NotificationSink for mpsc::Sender<Message> {
+    async fn send(&mut self, message: Message) -> StorageResult<()> {
+        mpsc::Sender::send(self, message)
+            .await
+            .map_err(|_| StorageError::NetworkError("Notification receiver closed".to_string()))
+    }
+
+    fn is_closed(&self) -> bool {
+        mpsc::Sender::is_closed(self)
+    }
+}
+
+/// Outcome of one [`OutboxDispatcher::dispatch()`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DispatchReport {
+    /// Messages delivered
+    pub sent: u64,
+
+    /// Messages that failed and wait for a retry
+    pub failed: u64,
+}
+
+/// Sends the messages of the outbox, retrying failures with backoff
+#[derive(Debug, Clone)]
+pub struct OutboxDispatcher {
+    outbox: Outbox,
+    batch_size: i64,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+}
+
+impl OutboxDispatcher {
+    /// Create a dispatcher for the outbox on the given database pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            outbox: Outbox::new(pool),
+            batch_size: DEFAULT_OUTBOX_BATCH_SIZE,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            max_retry_delay: DEFAULT_MAX_RETRY_DELAY,
+        }
+    }
+
+    /// Set how many messages [`dispatch()`](Self::dispatch) sends at most
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set the wait before the first retry and the longest wait between retries
+    ///
+    /// The wait doubles after each failure of the same message.
+    pub fn with_retry_delay(mut self, initial: Duration, max: Duration) -> Self {
+        self.retry_delay = initial;
+        self.max_retry_delay = max.max(initial);
+        self
+    }
+
+    /// The outbox this dispatcher sends from
+    pub fn outbox(&self) -> &Outbox {
+        &self.outbox
+    }
+
+    /// Wait before retrying a message that failed `attempts` times
+    pub fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32 << attempts.saturating_sub(1).min(16);
+        self.retry_delay
+            .saturating_mul(factor)
+            .min(self.max_retry_delay)
+    }
+
+    /// Send the pending messages that are due, in order
+    ///
+    /// Stops at the first message that is waiting for a retry or fails to
+    /// send, so messages are never delivered out of order.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a database operation fails. A sink error is not an
+    /// error here: it is recorded on the message and counted in the report.
+    pub async fn dispatch<S: NotificationSink>(
+        &self,
+        sink: &mut S,
+    ) -> StorageResult<DispatchReport> {
+        let mut report = DispatchReport::default();
+        for entry in self.outbox.pending(self.batch_size).await? {
+            if entry.next_attempt_at > Utc::now() {
+                break;
+            }
+            match sink.send(entry.message).await {
+                Ok(()) => {
+                    self.outbox.mark_sent(entry.id).await?;
+                    report.sent += 1;
+                }
+                Err(error) => {
+                    let attempts = entry.attempts.saturating_add(1);
+                    let delay = self.retry_delay(attempts);
+                    let retry_at = Utc::now()
+                        + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX);
+                    tracing::warn!(
+                        id = entry.id,
+                        attempts,
+                        retry_in = ?delay,
+                        error = %error,
+                        "outbox send failed"
+                    );
+                    self.outbox
+                        .mark_failed(entry.id, &error.to_string(), retry_at)
+                        .await?;
+                    report.failed += 1;
+                    break;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Keep sending until the sink is closed
+    ///
+    /// When nothing more is due the dispatcher waits `interval` before
+    /// looking again.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a database operation fails; unsent messages stay in
+    /// the outbox for the next run.
+    pub async fn run<S: NotificationSink>(
+        &self,
+        mut sink: S,
+        interval: Duration,
+    ) -> StorageResult<()> {
+        loop {
+            let report = self.dispatch(&mut sink).await?;
+            if sink.is_closed() {
+                return Ok(());
+            }
+            if report.failed > 0 || (report.sent as i64) < self.batch_size {
+                tokio::time::sleep(interval).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Direction, ReaderType};
+    use crate::transaction;
+    use turnkey_protocol::CommandCode;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    fn log() -> AccessLog {
+        AccessLog::new(
+            Some(2),
+            Some("1002".to_string()),
+            "00000000000022823433".to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            true,
+            Some("Acesso liberado".to_string()),
+            Utc::now(),
+        )
+    }
+
+    async fn enqueue_access(db: &Database) -> i64 {
+        let log = log();
+        let mut tx = db.pool().begin().await.unwrap();
+        let log_id = transaction::create_access_log(&mut tx, &log).await.unwrap();
+        let message = access_notification(device(), log_id, &log).unwrap();
+        Outbox::enqueue(&mut tx, &message, Some(log_id))
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        log_id
+    }
+
+    /// Sink failing a number of times before it accepts messages
+    struct FlakySink {
+        failures: u32,
+        received: Vec<Message>,
+    }
+
+    impl NotificationSink for FlakySink {
+        async fn send(&mut self, message: Message) -> StorageResult<()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(StorageError::NetworkError("Connection refused".to_string()));
+            }
+            self.received.push(message);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_access_notification() {
+        let message = access_notification(device(), 42, &log()).unwrap();
+        assert_eq!(message.command, CommandCode::ReceiveLogs);
+        assert!(EventChunk::is_chunk(&message));
+
+        let chunk = EventChunk::parse(&message).unwrap();
+        assert_eq!(chunk.next_after_id, 42);
+        assert_eq!(chunk.events.len(), 1);
+        assert_eq!(chunk.events[0].id, 42);
+        assert_eq!(chunk.events[0].matricula.as_deref(), Some("1002"));
+        assert!(chunk.events[0].granted);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_access_numbers_events_per_device() {
+        let db = Database::in_memory().await.unwrap();
+        let outbox = Outbox::new(db.pool().clone());
+        let log = log();
+
+        let mut nsrs = Vec::new();
+        for device_id in [15, 15, 16, 15] {
+            let mut tx = outbox.begin().await.unwrap();
+            let log_id = transaction::create_access_log(&mut tx, &log).await.unwrap();
+            let device_id = DeviceId::new(device_id).unwrap();
+            nsrs.push(
+                Outbox::enqueue_access(&mut tx, device_id, log_id, &log)
+                    .await
+                    .unwrap(),
+            );
+            tx.commit().await.unwrap();
+        }
+        assert_eq!(nsrs, vec![1, 2, 1, 3]);
+
+        let pending = outbox.pending(10).await.unwrap();
+        for (entry, nsr) in pending.iter().zip(&nsrs) {
+            assert_eq!(entry.nsr, *nsr);
+            let chunk = EventChunk::parse(&entry.message).unwrap();
+            assert_eq!(chunk.events[0].id, *nsr);
+            assert_eq!(chunk.next_after_id, *nsr);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rolled_back_transaction_queues_nothing() {
+        let db = Database::in_memory().await.unwrap();
+        let outbox = Outbox::new(db.pool().clone());
+
+        let log = log();
+        let mut tx = outbox.begin().await.unwrap();
+        let log_id = transaction::create_access_log(&mut tx, &log).await.unwrap();
+        let message = access_notification(device(), log_id, &log).unwrap();
+        Outbox::enqueue(&mut tx, &message, Some(log_id))
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_retries_with_backoff() {
+        let db = Database::in_memory().await.unwrap();
+        let log_id = enqueue_access(&db).await;
+        let dispatcher = OutboxDispatcher::new(db.pool().clone())
+            .with_retry_delay(Duration::from_millis(20), Duration::from_millis(50));
+        let mut sink = FlakySink {
+            failures: 1,
+            received: Vec::new(),
+        };
+
+        let report = dispatcher.dispatch(&mut sink).await.unwrap();
+        assert_eq!(report, DispatchReport { sent: 0, failed: 1 });
+        let pending = dispatcher.outbox().pending(10).await.unwrap();
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(
+            pending[0].last_error.as_deref(),
+            Some("Network error: Connection refused")
+        );
+
+        // Not due yet
+        let report = dispatcher.dispatch(&mut sink).await.unwrap();
+        assert_eq!(report, DispatchReport::default());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let report = dispatcher.dispatch(&mut sink).await.unwrap();
+        assert_eq!(report, DispatchReport { sent: 1, failed: 0 });
+        assert_eq!(dispatcher.outbox().pending_count().await.unwrap(), 0);
+
+        let chunk = EventChunk::parse(&sink.received[0]).unwrap();
+        assert_eq!(chunk.events[0].id, log_id);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_keeps_order_after_failure() {
+        let db = Database::in_memory().await.unwrap();
+        let first = enqueue_access(&db).await;
+        let second = enqueue_access(&db).await;
+        let dispatcher = OutboxDispatcher::new(db.pool().clone())
+            .with_retry_delay(Duration::ZERO, Duration::ZERO);
+        let mut sink = FlakySink {
+            failures: 1,
+            received: Vec::new(),
+        };
+
+        let report = dispatcher.dispatch(&mut sink).await.unwrap();
+        assert_eq!(report, DispatchReport { sent: 0, failed: 1 });
+        let report = dispatcher.dispatch(&mut sink).await.unwrap();
+        assert_eq!(report, DispatchReport { sent: 2, failed: 0 });
+
+        let ids: Vec<i64> = sink
+            .received
+            .iter()
+            .map(|message| EventChunk::parse(message).unwrap().next_after_id)
+            .collect();
+        assert_eq!(ids, vec![first, second]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_sent() {
+        let db = Database::in_memory().await.unwrap();
+        enqueue_access(&db).await;
+        enqueue_access(&db).await;
+        let dispatcher = OutboxDispatcher::new(db.pool().clone()).with_batch_size(1);
+        let (mut sender, mut receiver) = mpsc::channel(4);
+
+        dispatcher.dispatch(&mut sender).await.unwrap();
+        assert!(receiver.try_recv().is_ok());
+
+        let outbox = dispatcher.outbox();
+        let purged = outbox
+            .purge_sent(Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(purged, 1);
+        assert_eq!(outbox.pending_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_delay_doubles_up_to_max() {
+        let dispatcher =
+            OutboxDispatcher::new(SqlitePool::connect_lazy("sqlite::memory:").unwrap())
+                .with_retry_delay(Duration::from_secs(1), Duration::from_secs(10));
+        let delays: Vec<u64> = (1..=5)
+            .map(|attempts| dispatcher.retry_delay(attempts).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 10]);
+        assert_eq!(dispatcher.retry_delay(u32::MAX), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,186 @@
+//! Keyset pagination for large tables.
+//!
+//! Repositories expose `find_page()` for cursor-style traversal and
+//! `stream_all()` for a single pass over every row. Both avoid `OFFSET`,
+//! whose cost grows with the number of skipped rows, and instead seek
+//! directly to `id > after_id` using the primary key index.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig, SqliteCardRepository, CardRepository};
+//! use turnkey_storage::pagination::PageRequest;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let repo = SqliteCardRepository::new(db.pool().clone());
+//!
+//! let mut request = Some(PageRequest::first(500));
+//! while let Some(page_request) = request {
+//!     let page = repo.find_page(page_request).await?;
+//!     for card in &page.items {
+//!         println!("{}", card.numero_cartao);
+//!     }
+//!     request = page.next_page();
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Consistency
+//!
+//! Keyset pagination is stable under concurrent inserts: new rows get
+//! higher IDs and appear on later pages, and rows already returned are
+//! never repeated. Deleted rows simply disappear from later pages.
+
+/// Default number of rows per page
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Maximum number of rows per page
+///
+/// Larger requests are clamped to keep a single page bounded in memory.
+pub const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Cursor describing which page to fetch
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::pagination::{PageRequest, MAX_PAGE_SIZE};
+///
+/// let first = PageRequest::first(50);
+/// assert_eq!(first.after_id, None);
+///
+/// let next = first.after(120);
+/// assert_eq!(next.after_id, Some(120));
+/// assert_eq!(next.page_size, 50);
+///
+/// // Page size is clamped to 1..=MAX_PAGE_SIZE
+/// assert_eq!(PageRequest::first(0).page_size, 1);
+/// assert_eq!(PageRequest::first(1_000_000).page_size, MAX_PAGE_SIZE);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Return rows with `id` strictly greater than this value (None = from the start)
+    pub after_id: Option<i64>,
+
+    /// Maximum number of rows to return
+    pub page_size: i64,
+}
+
+impl PageRequest {
+    /// Request the first page with the given size
+    pub fn first(page_size: i64) -> Self {
+        Self {
+            after_id: None,
+            page_size: page_size.clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+
+    /// Continue after the given row ID, keeping the page size
+    pub fn after(self, after_id: i64) -> Self {
+        Self {
+            after_id: Some(after_id),
+            ..self
+        }
+    }
+
+    /// Lower bound for the `id > ?` predicate
+    ///
+    /// Row IDs start at 1, so 0 selects everything.
+    pub(crate) fn id_lower_bound(&self) -> i64 {
+        self.after_id.unwrap_or(0)
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::first(DEFAULT_PAGE_SIZE)
+    }
+}
+
+/// A single page of results ordered by ascending ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    /// Rows in this page
+    pub items: Vec<T>,
+
+    /// ID of the last row, to pass as `after_id` for the next page
+    ///
+    /// `None` when this is the last page.
+    pub next_after_id: Option<i64>,
+
+    /// Page size that produced this page
+    pub page_size: i64,
+}
+
+impl<T> Page<T> {
+    /// Build a page from rows, using `id_of` to extract the cursor
+    ///
+    /// A page shorter than `page_size` is the last one. A full page may be
+    /// followed by an empty page when the row count is an exact multiple.
+    pub(crate) fn from_rows(
+        items: Vec<T>,
+        request: PageRequest,
+        id_of: impl Fn(&T) -> i64,
+    ) -> Self {
+        let next_after_id = if items.len() as i64 == request.page_size {
+            items.last().map(id_of)
+        } else {
+            None
+        };
+
+        Self {
+            items,
+            next_after_id,
+            page_size: request.page_size,
+        }
+    }
+
+    /// Request for the following page, if there is one
+    pub fn next_page(&self) -> Option<PageRequest> {
+        self.next_after_id
+            .map(|after_id| PageRequest::first(self.page_size).after(after_id))
+    }
+
+    /// Check if there are more pages after this one
+    pub fn has_more(&self) -> bool {
+        self.next_after_id.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_request_default() {
+        let request = PageRequest::default();
+        assert_eq!(request.after_id, None);
+        assert_eq!(request.page_size, DEFAULT_PAGE_SIZE);
+        assert_eq!(request.id_lower_bound(), 0);
+    }
+
+    #[test]
+    fn test_page_request_clamps_size() {
+        assert_eq!(PageRequest::first(-5).page_size, 1);
+        assert_eq!(
+            PageRequest::first(MAX_PAGE_SIZE + 1).page_size,
+            MAX_PAGE_SIZE
+        );
+    }
+
+    #[test]
+    fn test_page_full_has_next() {
+        let page = Page::from_rows(vec![3, 7, 9], PageRequest::first(3), |id| *id);
+        assert!(page.has_more());
+        assert_eq!(page.next_page(), Some(PageRequest::first(3).after(9)));
+    }
+
+    #[test]
+    fn test_page_short_is_last() {
+        let page = Page::from_rows(vec![3, 7], PageRequest::first(3), |id| *id);
+        assert!(!page.has_more());
+        assert_eq!(page.next_page(), None);
+    }
+}
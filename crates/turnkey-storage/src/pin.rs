@@ -0,0 +1,371 @@
+//! Keypad PIN hashing
+//!
+//! Keypad codes are stored as argon2id hashes in PHC string format
+//! (`$argon2id$v=19$m=...,t=...,p=...$<salt>$<hash>`), so a copy of the
+//! database or a site bundle does not reveal them. The hashing strategy is
+//! pluggable through [`PinHasher`]; [`Argon2PinHasher`] is the default used
+//! by [`SqliteUserRepository`](crate::repositories::SqliteUserRepository).
+//!
+//! # Migration
+//!
+//! Databases created before hashing was introduced (and seed data) hold
+//! plaintext codes. These keep working: verification falls back to a
+//! constant-time plaintext comparison for values that are not PHC strings,
+//! and [`PinHasher::needs_rehash`] flags them so the repository replaces
+//! them with a hash the next time the code is used successfully. The same
+//! happens to hashes made with parameters other than the configured ones,
+//! so raising the cost takes effect as users badge in.
+//!
+//! # Lookup
+//!
+//! Salted hashes cannot be searched, so each code is also stored as a
+//! [`code_digest`]: an HMAC-SHA256 keyed with the site's [`PinDigestKey`].
+//! Codes are looked up by their digest through a unique index, which also
+//! keeps two users from sharing a code, and the argon2 hash of the matching
+//! row confirms them. The key is a secret kept outside the database (see
+//! [`turnkey_core::secrets`]): stored next to the digests, it would let a
+//! copy of the database give up every code by digesting all candidates.
+//! Without the key, nothing that digests a code works; there is no
+//! fallback to an unkeyed digest. Argon2 runs with
+//! [`hash_blocking`] and [`verify_blocking`] off the async executor.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey_storage::pin::{Argon2PinHasher, PinHashParams, PinHasher};
+//!
+//! let hasher = Argon2PinHasher::new(PinHashParams::default().memory_kib(8 * 1024)).unwrap();
+//! let stored = hasher.hash("1234").unwrap();
+//!
+//! assert!(stored.starts_with("$argon2id$"));
+//! assert!(hasher.verify("1234", &stored));
+//! assert!(!hasher.verify("4321", &stored));
+//!
+//! // Legacy plaintext codes still verify, but are due for a rehash
+//! assert!(hasher.verify("1234", "1234"));
+//! assert!(hasher.needs_rehash("1234"));
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use turnkey_core::secrets::{SecretBytes, SecretProvider, names};
+
+/// Prefix of PHC strings produced by any argon2 variant
+const ARGON2_PREFIX: &str = "$argon2";
+
+/// Strategy for hashing and verifying keypad codes
+///
+/// Implementations must be able to verify every stored format they may
+/// find in the `users.codigo` column, including legacy plaintext codes.
+pub trait PinHasher: Send + Sync {
+    /// Hash a code for storage
+    fn hash(&self, code: &str) -> StorageResult<String>;
+
+    /// Check a code entered on the keypad against a stored value
+    fn verify(&self, code: &str, stored: &str) -> bool;
+
+    /// Whether a stored value should be replaced by a fresh [`hash`](Self::hash)
+    fn needs_rehash(&self, stored: &str) -> bool;
+}
+
+/// Argon2id cost parameters
+///
+/// Defaults follow the OWASP recommendation for argon2id
+/// (19 MiB of memory, 2 iterations, 1 lane).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinHashParams {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+
+    /// Number of passes over memory
+    pub iterations: u32,
+
+    /// Degree of parallelism (lanes)
+    pub parallelism: u32,
+}
+
+impl Default for PinHashParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl PinHashParams {
+    /// Set the memory cost in KiB
+    pub fn memory_kib(mut self, memory_kib: u32) -> Self {
+        self.memory_kib = memory_kib;
+        self
+    }
+
+    /// Set the number of iterations
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Set the degree of parallelism
+    pub fn parallelism(mut self, parallelism: u32) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+}
+
+/// Default [`PinHasher`]: argon2id with configurable cost
+#[derive(Debug, Clone)]
+pub struct Argon2PinHasher {
+    params: Params,
+}
+
+impl Argon2PinHasher {
+    /// Create a hasher with the given cost parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if argon2 rejects the parameters (e.g. memory
+    /// below 8 KiB per lane, or zero iterations).
+    pub fn new(params: PinHashParams) -> StorageResult<Self> {
+        let params = Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            None,
+        )
+        .map_err(|e| StorageError::Configuration(format!("Invalid PIN hash parameters: {}", e)))?;
+
+        Ok(Self { params })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl Default for Argon2PinHasher {
+    fn default() -> Self {
+        Self::new(PinHashParams::default()).expect("default PIN hash parameters are valid")
+    }
+}
+
+impl PinHasher for Argon2PinHasher {
+    fn hash(&self, code: &str) -> StorageResult<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = self
+            .argon2()
+            .hash_password(code.as_bytes(), &salt)
+            .map_err(|e| StorageError::Internal(format!("PIN hashing failed: {}", e)))?;
+
+        Ok(hash.to_string())
+    }
+
+    fn verify(&self, code: &str, stored: &str) -> bool {
+        verify_stored(code, stored)
+    }
+
+    fn needs_rehash(&self, stored: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(stored) else {
+            return true;
+        };
+        if hash.algorithm != Algorithm::Argon2id.ident() {
+            return true;
+        }
+
+        match Params::try_from(&hash) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// Whether a stored code is an argon2 hash rather than legacy plaintext
+pub fn is_hashed(stored: &str) -> bool {
+    stored.starts_with(ARGON2_PREFIX)
+}
+
+/// Check a code against a stored argon2 hash or legacy plaintext code
+///
+/// Argon2 hashes carry their own parameters, so no hasher configuration is
+/// needed to verify them. Plaintext codes are compared in constant time.
+pub fn verify_stored(code: &str, stored: &str) -> bool {
+    use subtle::ConstantTimeEq;
+
+    if !is_hashed(stored) {
+        return stored.as_bytes().ct_eq(code.as_bytes()).into();
+    }
+
+    match PasswordHash::new(stored) {
+        Ok(hash) => Argon2::default()
+            .verify_password(code.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Deterministic digest of a code, for indexed lookups
+///
+/// `key` is the site's [`PinDigestKey`].
+pub fn code_digest(key: &[u8], code: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(code.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Key of the keypad code digests
+///
+/// Every process that writes or looks up the codes of a database must use
+/// the same key. `Debug` does not print it.
+#[derive(Debug, Clone)]
+pub struct PinDigestKey(SecretBytes);
+
+impl PinDigestKey {
+    /// Shortest key accepted, in bytes
+    pub const MIN_LEN: usize = 32;
+
+    /// Use `key` as the digest key
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if the key is shorter than
+    /// [`MIN_LEN`](Self::MIN_LEN) bytes.
+    pub fn new(key: SecretBytes) -> StorageResult<Self> {
+        if key.len() < Self::MIN_LEN {
+            return Err(StorageError::Configuration(format!(
+                "PIN digest key must be at least {} bytes, got {}",
+                Self::MIN_LEN,
+                key.len()
+            )));
+        }
+        Ok(Self(key))
+    }
+
+    /// Load the key from the secret named [`names::PIN_DIGEST_KEY`]
+    ///
+    /// # Errors
+    ///
+    /// Returns `Configuration` if the secret is missing, cannot be read or
+    /// is too short.
+    pub fn from_secrets(secrets: &dyn SecretProvider) -> StorageResult<Self> {
+        let key = secrets
+            .require(names::PIN_DIGEST_KEY)
+            .map_err(|e| StorageError::Configuration(e.to_string()))?;
+        Self::new(key)
+    }
+
+    /// Digest of a code under this key (see [`code_digest`])
+    pub fn digest(&self, code: &str) -> String {
+        code_digest(self.0.expose_secret(), code)
+    }
+}
+
+/// Hash a code on the blocking thread pool
+pub async fn hash_blocking(hasher: Arc<dyn PinHasher>, code: &str) -> StorageResult<String> {
+    let code = code.to_string();
+    tokio::task::spawn_blocking(move || hasher.hash(&code))
+        .await
+        .map_err(|e| StorageError::Internal(format!("PIN hashing task failed: {}", e)))?
+}
+
+/// Verify a code on the blocking thread pool
+///
+/// A verification task that panics counts as a mismatch.
+pub async fn verify_blocking(hasher: Arc<dyn PinHasher>, code: &str, stored: &str) -> bool {
+    let (code, stored) = (code.to_string(), stored.to_string());
+    tokio::task::spawn_blocking(move || hasher.verify(&code, &stored))
+        .await
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters to keep tests fast
+    fn test_hasher() -> Argon2PinHasher {
+        Argon2PinHasher::new(PinHashParams::default().memory_kib(64).iterations(1)).unwrap()
+    }
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hasher = test_hasher();
+        let stored = hasher.hash("1234").unwrap();
+
+        assert!(is_hashed(&stored));
+        assert!(stored.starts_with("$argon2id$"));
+        assert!(hasher.verify("1234", &stored));
+        assert!(!hasher.verify("12345", &stored));
+        assert!(!hasher.needs_rehash(&stored));
+
+        // Salted: the same code never hashes to the same value
+        assert_ne!(stored, hasher.hash("1234").unwrap());
+    }
+
+    #[test]
+    fn test_legacy_plaintext() {
+        let hasher = test_hasher();
+
+        assert!(!is_hashed("1234"));
+        assert!(hasher.verify("1234", "1234"));
+        assert!(!hasher.verify("1235", "1234"));
+        assert!(hasher.needs_rehash("1234"));
+    }
+
+    #[test]
+    fn test_needs_rehash_on_parameter_change() {
+        let stored = test_hasher().hash("1234").unwrap();
+        let stronger =
+            Argon2PinHasher::new(PinHashParams::default().memory_kib(128).iterations(1)).unwrap();
+
+        assert!(stronger.needs_rehash(&stored));
+        assert!(stronger.verify("1234", &stored));
+    }
+
+    #[test]
+    fn test_digest_key_from_secrets() {
+        let dir = std::env::temp_dir().join(format!("turnkey-pin-key-{}", std::process::id()));
+        let secrets = turnkey_core::secrets::FileSecrets::new(&dir);
+        assert!(matches!(
+            PinDigestKey::from_secrets(&secrets),
+            Err(StorageError::Configuration(_))
+        ));
+
+        secrets
+            .put(names::PIN_DIGEST_KEY, &SecretBytes::from("too short"))
+            .unwrap();
+        assert!(PinDigestKey::from_secrets(&secrets).is_err());
+
+        secrets
+            .put(names::PIN_DIGEST_KEY, &SecretBytes::new([1; 32]))
+            .unwrap();
+        let key = PinDigestKey::from_secrets(&secrets).unwrap();
+        assert_eq!(key.digest("1234"), code_digest(&[1; 32], "1234"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_code_digest_is_keyed() {
+        let digest = code_digest(&[1; 32], "1234");
+
+        assert_eq!(digest, code_digest(&[1; 32], "1234"));
+        assert_ne!(digest, code_digest(&[1; 32], "1235"));
+        assert_ne!(digest, code_digest(&[2; 32], "1234"));
+        assert_eq!(digest.len(), 64);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let result = Argon2PinHasher::new(PinHashParams::default().iterations(0));
+        assert!(matches!(result, Err(StorageError::Configuration(_))));
+    }
+}
@@ -0,0 +1,426 @@
+//! Event replay for rebuilding derived state from access logs.
+//!
+//! `access_logs` is the source of truth; `access_state` (counters,
+//! occupancy, last access per credential) is derived from it by a trigger.
+//! After a crash, a restored backup or manual edits, the derived table may
+//! disagree with the logs. [`AccessStateReplay`] streams every log in ID
+//! order, recomputes the state in memory and either rewrites the table
+//! ([`rebuild`](AccessStateReplay::rebuild)) or reports the differences
+//! ([`verify`](AccessStateReplay::verify)).
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_storage::{Database, DatabaseConfig};
+//! use turnkey_storage::replay::AccessStateReplay;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let replay = AccessStateReplay::new(db.pool().clone());
+//!
+//! let report = replay.verify().await?;
+//! if !report.is_consistent() {
+//!     println!("{} credentials out of sync, rebuilding", report.mismatches.len());
+//!     let summary = replay
+//!         .rebuild(|progress| println!("{}/{} logs", progress.processed, progress.total))
+//!         .await?;
+//!     println!("Rebuilt {} credentials", summary.states_written);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Idempotence and Concurrency
+//!
+//! A rebuild replaces the whole table inside one transaction, so running it
+//! twice yields the same result and readers never see a half-written table.
+//! If another connection writes a log while the rebuild is reading, SQLite
+//! rejects the rebuild's write with a busy/snapshot error; retry it.
+
+use crate::error::StorageResult;
+use crate::models::{AccessLog, AccessState};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use std::collections::BTreeMap;
+
+/// Default number of logs between progress reports
+pub const DEFAULT_PROGRESS_INTERVAL: u64 = 1000;
+
+/// Progress of a running rebuild
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayProgress {
+    /// Logs processed so far
+    pub processed: u64,
+
+    /// Total logs to process
+    pub total: u64,
+
+    /// ID of the last processed log
+    pub last_log_id: i64,
+}
+
+/// Outcome of a completed rebuild
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaySummary {
+    /// Number of logs replayed
+    pub logs_processed: u64,
+
+    /// Number of `access_state` rows written
+    pub states_written: u64,
+}
+
+/// Difference between stored and recomputed state for one credential
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateMismatch {
+    /// Credential identifier
+    pub card_number: String,
+
+    /// Row currently in `access_state` (None if missing)
+    pub stored: Option<AccessState>,
+
+    /// State recomputed from `access_logs` (None if the row should not exist)
+    pub recomputed: Option<AccessState>,
+}
+
+/// Result of comparing stored and recomputed state
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// Number of credentials compared (union of stored and recomputed)
+    pub checked: usize,
+
+    /// Credentials whose stored state differs from the logs
+    pub mismatches: Vec<StateMismatch>,
+}
+
+impl ConsistencyReport {
+    /// Check if stored state matches the logs exactly
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Recomputes `access_state` from `access_logs`
+#[derive(Debug, Clone)]
+pub struct AccessStateReplay {
+    pool: SqlitePool,
+    progress_interval: u64,
+}
+
+impl AccessStateReplay {
+    /// Create a replay tool for the given database pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Report progress every `interval` logs (minimum 1)
+    pub fn with_progress_interval(mut self, interval: u64) -> Self {
+        self.progress_interval = interval.max(1);
+        self
+    }
+
+    /// Rebuild `access_state` from scratch
+    ///
+    /// Calls `on_progress` every progress interval and once at the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if reading logs or writing state fails; the previous
+    /// state is left untouched in that case.
+    pub async fn rebuild(
+        &self,
+        mut on_progress: impl FnMut(ReplayProgress),
+    ) -> StorageResult<ReplaySummary> {
+        let mut tx = self.pool.begin().await?;
+
+        let (states, logs_processed) = self.recompute(&mut tx, &mut on_progress).await?;
+
+        sqlx::query!("DELETE FROM access_state")
+            .execute(&mut *tx)
+            .await?;
+
+        for state in states.values() {
+            sqlx::query!(
+                r#"
+                INSERT INTO access_state (
+                    card_number, user_id, granted_count, denied_count,
+                    last_access_at, last_granted_at, last_direction, inside, last_log_id
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                state.card_number,
+                state.user_id,
+                state.granted_count,
+                state.denied_count,
+                state.last_access_at,
+                state.last_granted_at,
+                state.last_direction,
+                state.inside,
+                state.last_log_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(ReplaySummary {
+            logs_processed,
+            states_written: states.len() as u64,
+        })
+    }
+
+    /// Compare stored `access_state` with state recomputed from the logs
+    ///
+    /// Read-only; both sides are read from the same snapshot.
+    pub async fn verify(&self) -> StorageResult<ConsistencyReport> {
+        let mut tx = self.pool.begin().await?;
+
+        let (mut recomputed, _) = self.recompute(&mut tx, &mut |_| {}).await?;
+
+        let stored = sqlx::query_as!(
+            AccessState,
+            r#"
+            SELECT card_number AS "card_number!", user_id, granted_count, denied_count,
+                   last_access_at AS "last_access_at: _", last_granted_at AS "last_granted_at: _",
+                   last_direction AS "last_direction: _", inside, last_log_id
+            FROM access_state
+            ORDER BY card_number
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.rollback().await?;
+
+        let mut report = ConsistencyReport::default();
+
+        for stored in stored {
+            report.checked += 1;
+            let expected = recomputed.remove(&stored.card_number);
+            if expected.as_ref() != Some(&stored) {
+                report.mismatches.push(StateMismatch {
+                    card_number: stored.card_number.clone(),
+                    stored: Some(stored),
+                    recomputed: expected,
+                });
+            }
+        }
+
+        // Whatever is left has logs but no stored row
+        for (card_number, expected) in recomputed {
+            report.checked += 1;
+            report.mismatches.push(StateMismatch {
+                card_number,
+                stored: None,
+                recomputed: Some(expected),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Stream all logs in ID order and fold them into per-credential state
+    async fn recompute(
+        &self,
+        tx: &mut Transaction<'_, Sqlite>,
+        on_progress: &mut impl FnMut(ReplayProgress),
+    ) -> StorageResult<(BTreeMap<String, AccessState>, u64)> {
+        let total = sqlx::query_scalar!("SELECT COUNT(*) FROM access_logs")
+            .fetch_one(&mut **tx)
+            .await?;
+        let total = total.max(0) as u64;
+
+        let mut states: BTreeMap<String, AccessState> = BTreeMap::new();
+        let mut progress = ReplayProgress {
+            processed: 0,
+            total,
+            last_log_id: 0,
+        };
+
+        let mut logs = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            ORDER BY id
+            "#
+        )
+        .fetch(&mut **tx);
+
+        while let Some(log) = logs.try_next().await? {
+            match states.get_mut(&log.card_number) {
+                Some(state) => state.apply(&log),
+                None => {
+                    states.insert(log.card_number.clone(), AccessState::from_log(&log));
+                }
+            }
+
+            progress.processed += 1;
+            progress.last_log_id = log.id;
+            if progress.processed.is_multiple_of(self.progress_interval) {
+                on_progress(progress);
+            }
+        }
+
+        // Always finish with a final report, unless it was just sent
+        if progress.processed == 0 || !progress.processed.is_multiple_of(self.progress_interval) {
+            on_progress(progress);
+        }
+
+        Ok((states, progress.processed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Direction, ReaderType};
+    use crate::repositories::{AccessLogRepository, SqliteAccessLogRepository};
+    use chrono::{Duration, Utc};
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn insert_log(db: &Database, card_number: &str, direction: Direction, granted: bool) {
+        let log = AccessLog::new(
+            None,
+            None,
+            card_number.to_string(),
+            direction,
+            ReaderType::Rfid,
+            granted,
+            None,
+            Utc::now(),
+        );
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create(&log)
+            .await
+            .unwrap();
+    }
+
+    async fn stored_state(db: &Database, card_number: &str) -> Option<AccessState> {
+        sqlx::query_as!(
+            AccessState,
+            r#"
+            SELECT card_number AS "card_number!", user_id, granted_count, denied_count,
+                   last_access_at AS "last_access_at: _", last_granted_at AS "last_granted_at: _",
+                   last_direction AS "last_direction: _", inside, last_log_id
+            FROM access_state
+            WHERE card_number = ?
+            "#,
+            card_number
+        )
+        .fetch_optional(db.pool())
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_trigger_state_is_consistent() {
+        let db = setup_test_db().await;
+        insert_log(&db, "5555555555", Direction::Entry, true).await;
+        insert_log(&db, "5555555555", Direction::Exit, false).await;
+
+        let state = stored_state(&db, "5555555555").await.unwrap();
+        assert!(state.inside);
+        assert_eq!(state.granted_count, 1);
+        assert_eq!(state.denied_count, 1);
+
+        // Seeded logs were backfilled by the migration
+        let report = AccessStateReplay::new(db.pool().clone())
+            .verify()
+            .await
+            .unwrap();
+        assert!(report.is_consistent(), "{:?}", report.mismatches);
+        assert!(report.checked > 1);
+    }
+
+    #[tokio::test]
+    async fn test_verify_detects_and_rebuild_repairs() {
+        let db = setup_test_db().await;
+        insert_log(&db, "6666666666", Direction::Entry, true).await;
+
+        // Simulate corruption: wrong counter, lost row, stale row
+        sqlx::query!("UPDATE access_state SET granted_count = 42 WHERE card_number = '6666666666'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query!("DELETE FROM access_state WHERE card_number = '99999999999999999999'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let yesterday = Utc::now() - Duration::days(1);
+        sqlx::query!(
+            r#"
+            INSERT INTO access_state (card_number, last_access_at, last_log_id)
+            VALUES ('GHOST123', ?, 999)
+            "#,
+            yesterday
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let replay = AccessStateReplay::new(db.pool().clone());
+        let report = replay.verify().await.unwrap();
+
+        let mut broken: Vec<_> = report
+            .mismatches
+            .iter()
+            .map(|m| m.card_number.as_str())
+            .collect();
+        broken.sort();
+        assert_eq!(
+            broken,
+            vec!["6666666666", "99999999999999999999", "GHOST123"]
+        );
+
+        let ghost = report
+            .mismatches
+            .iter()
+            .find(|m| m.card_number == "GHOST123")
+            .unwrap();
+        assert!(ghost.recomputed.is_none());
+
+        replay.rebuild(|_| {}).await.unwrap();
+
+        assert!(replay.verify().await.unwrap().is_consistent());
+        let state = stored_state(&db, "6666666666").await.unwrap();
+        assert_eq!(state.granted_count, 1);
+        assert!(stored_state(&db, "GHOST123").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_is_idempotent_and_reports_progress() {
+        let db = setup_test_db().await;
+        for _ in 0..5 {
+            insert_log(&db, "7777777777", Direction::Entry, true).await;
+        }
+
+        let replay = AccessStateReplay::new(db.pool().clone()).with_progress_interval(4);
+
+        let mut reports = Vec::new();
+        let first = replay.rebuild(|p| reports.push(p)).await.unwrap();
+        let second = replay.rebuild(|_| {}).await.unwrap();
+        assert_eq!(first, second);
+
+        // Interval reports plus a final one, ending at the total
+        let last = reports.last().unwrap();
+        assert_eq!(last.processed, first.logs_processed);
+        assert_eq!(last.total, first.logs_processed);
+        assert!(reports.len() >= 2);
+        assert!(reports.windows(2).all(|w| w[0].processed < w[1].processed));
+
+        let state = stored_state(&db, "7777777777").await.unwrap();
+        assert_eq!(state.granted_count, 5);
+    }
+}
@@ -0,0 +1,497 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{AccessException, Card};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository trait for AccessException entity operations
+///
+/// Manages temporary access windows together with their member groups
+/// (cards and users) and device restrictions.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait AccessExceptionRepository: Send + Sync {
+    /// Find an exception by its ID
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<AccessException>>;
+
+    /// Get all active exceptions, ordered by window start
+    async fn find_all_active(&self) -> StorageResult<Vec<AccessException>>;
+
+    /// Find open exceptions covering a credential on a device at a given time
+    ///
+    /// Matches exceptions where the card number or the user's matricula is a
+    /// member, the window contains `at`, and the device is either listed,
+    /// belongs to a listed zone, or the exception has no device or zone
+    /// restriction. When `device_id` is `None`, only unrestricted exceptions
+    /// match.
+    async fn find_matching(
+        &self,
+        card_number: &str,
+        matricula: Option<&str>,
+        device_id: Option<i64>,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Vec<AccessException>>;
+
+    /// Create a new exception
+    async fn create(&self, exception: &AccessException) -> StorageResult<i64>;
+
+    /// Update an existing exception
+    async fn update(&self, exception: &AccessException) -> StorageResult<()>;
+
+    /// Delete an exception and its members and device restrictions
+    async fn delete(&self, id: i64) -> StorageResult<()>;
+
+    /// Add a card to the exception group (card number is normalized)
+    async fn add_card(&self, exception_id: i64, card_number: &str) -> StorageResult<()>;
+
+    /// Add a user (by matricula) to the exception group
+    async fn add_user(&self, exception_id: i64, matricula: &str) -> StorageResult<()>;
+
+    /// Restrict the exception to a device (may be called multiple times)
+    async fn add_device(&self, exception_id: i64, device_id: i64) -> StorageResult<()>;
+
+    /// List the devices an exception is restricted to (empty = all devices)
+    async fn find_devices(&self, exception_id: i64) -> StorageResult<Vec<i64>>;
+
+    /// Restrict the exception to every device of a zone
+    ///
+    /// Zone and device restrictions add up: the exception applies on listed
+    /// devices and on devices currently assigned to listed zones.
+    async fn add_zone(&self, exception_id: i64, zone_id: i64) -> StorageResult<()>;
+
+    /// List the zones an exception is restricted to
+    async fn find_zones(&self, exception_id: i64) -> StorageResult<Vec<i64>>;
+}
+
+/// SQLite implementation of AccessExceptionRepository
+pub struct SqliteAccessExceptionRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteAccessExceptionRepository {
+    /// Create a new SQLite access exception repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn not_found(id: i64) -> StorageError {
+        StorageError::NotFound {
+            entity_type: "AccessException".to_string(),
+            field: "id".to_string(),
+            value: id.to_string(),
+        }
+    }
+}
+
+impl AccessExceptionRepository for SqliteAccessExceptionRepository {
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<AccessException>> {
+        let exception = sqlx::query_as!(
+            AccessException,
+            r#"
+            SELECT id AS "id!", name, starts_at AS "starts_at: _", ends_at AS "ends_at: _", active,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM access_exceptions
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(exception)
+    }
+
+    async fn find_all_active(&self) -> StorageResult<Vec<AccessException>> {
+        let exceptions = sqlx::query_as!(
+            AccessException,
+            r#"
+            SELECT id AS "id!", name, starts_at AS "starts_at: _", ends_at AS "ends_at: _", active,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM access_exceptions
+            WHERE active = 1
+            ORDER BY starts_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(exceptions)
+    }
+
+    async fn find_matching(
+        &self,
+        card_number: &str,
+        matricula: Option<&str>,
+        device_id: Option<i64>,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Vec<AccessException>> {
+        let card_number = Card::normalize_card_number(card_number);
+        let exceptions = sqlx::query_as!(
+            AccessException,
+            r#"
+            SELECT e.id AS "id!", e.name, e.starts_at AS "starts_at: _", e.ends_at AS "ends_at: _",
+                   e.active, e.created_at AS "created_at: _", e.updated_at AS "updated_at: _"
+            FROM access_exceptions e
+            WHERE e.active = 1
+              AND e.starts_at <= ? AND e.ends_at >= ?
+              AND EXISTS (
+                  SELECT 1 FROM access_exception_members m
+                  WHERE m.exception_id = e.id
+                    AND (m.card_number = ? OR m.matricula = ?)
+              )
+              AND (
+                  (
+                      NOT EXISTS (
+                          SELECT 1 FROM access_exception_devices d WHERE d.exception_id = e.id
+                      )
+                      AND NOT EXISTS (
+                          SELECT 1 FROM access_exception_zones z WHERE z.exception_id = e.id
+                      )
+                  )
+                  OR EXISTS (
+                      SELECT 1 FROM access_exception_devices d
+                      WHERE d.exception_id = e.id AND d.device_id = ?
+                  )
+                  OR EXISTS (
+                      SELECT 1 FROM access_exception_zones z
+                      JOIN devices dev ON dev.zone_id = z.zone_id
+                      WHERE z.exception_id = e.id AND dev.device_id = ?
+                  )
+              )
+            ORDER BY e.ends_at DESC
+            "#,
+            at,
+            at,
+            card_number,
+            matricula,
+            device_id,
+            device_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(exceptions)
+    }
+
+    async fn create(&self, exception: &AccessException) -> StorageResult<i64> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO access_exceptions (name, starts_at, ends_at, active)
+            VALUES (?, ?, ?, ?)
+            "#,
+            exception.name,
+            exception.starts_at,
+            exception.ends_at,
+            exception.active
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn update(&self, exception: &AccessException) -> StorageResult<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE access_exceptions
+            SET name = ?, starts_at = ?, ends_at = ?, active = ?,
+                updated_at = datetime('now')
+            WHERE id = ?
+            "#,
+            exception.name,
+            exception.starts_at,
+            exception.ends_at,
+            exception.active,
+            exception.id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(exception.id));
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i64) -> StorageResult<()> {
+        let result = sqlx::query!("DELETE FROM access_exceptions WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(id));
+        }
+
+        Ok(())
+    }
+
+    async fn add_card(&self, exception_id: i64, card_number: &str) -> StorageResult<()> {
+        let card_number = Card::normalize_card_number(card_number);
+        sqlx::query!(
+            r#"
+            INSERT INTO access_exception_members (exception_id, card_number)
+            VALUES (?, ?)
+            ON CONFLICT DO NOTHING
+            "#,
+            exception_id,
+            card_number
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn add_user(&self, exception_id: i64, matricula: &str) -> StorageResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO access_exception_members (exception_id, matricula)
+            VALUES (?, ?)
+            ON CONFLICT DO NOTHING
+            "#,
+            exception_id,
+            matricula
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn add_device(&self, exception_id: i64, device_id: i64) -> StorageResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO access_exception_devices (exception_id, device_id)
+            VALUES (?, ?)
+            ON CONFLICT DO NOTHING
+            "#,
+            exception_id,
+            device_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_devices(&self, exception_id: i64) -> StorageResult<Vec<i64>> {
+        let devices = sqlx::query_scalar!(
+            r#"
+            SELECT device_id FROM access_exception_devices
+            WHERE exception_id = ?
+            ORDER BY device_id
+            "#,
+            exception_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    async fn add_zone(&self, exception_id: i64, zone_id: i64) -> StorageResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO access_exception_zones (exception_id, zone_id)
+            VALUES (?, ?)
+            ON CONFLICT DO NOTHING
+            "#,
+            exception_id,
+            zone_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_zones(&self, exception_id: i64) -> StorageResult<Vec<i64>> {
+        let zones = sqlx::query_scalar!(
+            "SELECT zone_id FROM access_exception_zones WHERE exception_id = ? ORDER BY zone_id",
+            exception_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(zones)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Site, Zone};
+    use crate::repositories::{SiteRepository, SqliteSiteRepository};
+    use chrono::Duration;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    fn create_open_exception(name: &str) -> AccessException {
+        AccessException::new(
+            name,
+            Utc::now() - Duration::hours(1),
+            Utc::now() + Duration::hours(4),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_exception() {
+        let db = setup_test_db().await;
+        let repo = SqliteAccessExceptionRepository::new(db.pool().clone());
+
+        let id = repo
+            .create(&create_open_exception("Congresso"))
+            .await
+            .unwrap();
+        assert!(id > 0);
+
+        let found = repo.find_by_id(id).await.unwrap().unwrap();
+        assert_eq!(found.name, "Congresso");
+        assert!(found.active);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_exception() {
+        let db = setup_test_db().await;
+        let repo = SqliteAccessExceptionRepository::new(db.pool().clone());
+
+        let id = repo.create(&create_open_exception("Show")).await.unwrap();
+        let mut exception = repo.find_by_id(id).await.unwrap().unwrap();
+        exception.active = false;
+        repo.update(&exception).await.unwrap();
+
+        assert!(repo.find_all_active().await.unwrap().is_empty());
+
+        repo.delete(id).await.unwrap();
+        assert!(repo.find_by_id(id).await.unwrap().is_none());
+        assert!(matches!(
+            repo.delete(id).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_by_card_and_user() {
+        let db = setup_test_db().await;
+        let repo = SqliteAccessExceptionRepository::new(db.pool().clone());
+
+        let id = repo
+            .create(&create_open_exception("Visitantes"))
+            .await
+            .unwrap();
+        repo.add_card(id, "abc123def").await.unwrap();
+        repo.add_user(id, "EMP050").await.unwrap();
+
+        let now = Utc::now();
+        let by_card = repo
+            .find_matching("ABC123DEF", None, None, now)
+            .await
+            .unwrap();
+        assert_eq!(by_card.len(), 1);
+
+        let by_user = repo
+            .find_matching("0000000000", Some("EMP050"), None, now)
+            .await
+            .unwrap();
+        assert_eq!(by_user.len(), 1);
+
+        let outsider = repo
+            .find_matching("0000000000", Some("EMP051"), None, now)
+            .await
+            .unwrap();
+        assert!(outsider.is_empty());
+
+        let after_window = repo
+            .find_matching("ABC123DEF", None, None, now + Duration::hours(5))
+            .await
+            .unwrap();
+        assert!(after_window.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_respects_devices() {
+        let db = setup_test_db().await;
+        let repo = SqliteAccessExceptionRepository::new(db.pool().clone());
+
+        let id = repo.create(&create_open_exception("Palco")).await.unwrap();
+        repo.add_card(id, "5050505050").await.unwrap();
+        repo.add_device(id, 3).await.unwrap();
+        repo.add_device(id, 4).await.unwrap();
+
+        assert_eq!(repo.find_devices(id).await.unwrap(), vec![3, 4]);
+
+        let now = Utc::now();
+        let on_device = repo
+            .find_matching("5050505050", None, Some(3), now)
+            .await
+            .unwrap();
+        assert_eq!(on_device.len(), 1);
+
+        let other_device = repo
+            .find_matching("5050505050", None, Some(9), now)
+            .await
+            .unwrap();
+        assert!(other_device.is_empty());
+
+        // Adding the same device twice is a no-op, out-of-range IDs are rejected
+        repo.add_device(id, 3).await.unwrap();
+        assert!(repo.add_device(id, 100).await.is_err());
+
+        // Restricted exceptions never match an unidentified device
+        let unknown_device = repo
+            .find_matching("5050505050", None, None, now)
+            .await
+            .unwrap();
+        assert!(unknown_device.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_respects_zones() {
+        let db = setup_test_db().await;
+        let repo = SqliteAccessExceptionRepository::new(db.pool().clone());
+        let sites = SqliteSiteRepository::new(db.pool().clone());
+
+        let site_id = sites.create_site(&Site::new("Pavilhao")).await.unwrap();
+        let hall = sites
+            .create_zone(&Zone::new(site_id, "Hall"))
+            .await
+            .unwrap();
+        sites.assign_device(5, Some(hall)).await.unwrap();
+        sites.assign_device(6, None).await.unwrap();
+
+        let id = repo.create(&create_open_exception("Expo")).await.unwrap();
+        repo.add_card(id, "7070707070").await.unwrap();
+        repo.add_zone(id, hall).await.unwrap();
+        repo.add_device(id, 8).await.unwrap();
+        assert_eq!(repo.find_zones(id).await.unwrap(), vec![hall]);
+
+        let now = Utc::now();
+        for (device_id, expected) in [(5, 1), (8, 1), (6, 0)] {
+            let matches = repo
+                .find_matching("7070707070", None, Some(device_id), now)
+                .await
+                .unwrap();
+            assert_eq!(matches.len(), expected, "device {}", device_id);
+        }
+
+        // A device moved into the zone is covered from then on
+        sites.assign_device(6, Some(hall)).await.unwrap();
+        let moved = repo
+            .find_matching("7070707070", None, Some(6), now)
+            .await
+            .unwrap();
+        assert_eq!(moved.len(), 1);
+
+        // Zones referenced by an exception cannot be deleted
+        assert!(sites.delete_zone(hall).await.is_err());
+    }
+}
@@ -1,9 +1,322 @@
 #![allow(async_fn_in_trait)]
 
-use crate::error::StorageResult;
-use crate::models::AccessLog;
+use crate::error::{StorageError, StorageResult};
+use crate::models::{AccessLog, DeviceClockDrift, Direction, ReaderType};
+use crate::outbox::Outbox;
+use crate::pagination::{Page, PageRequest};
 use chrono::{DateTime, Utc};
-use sqlx::SqlitePool;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::collections::HashSet;
+
+/// Default number of logs written per `INSERT` statement by `create_many`
+pub const DEFAULT_BULK_CHUNK_SIZE: usize = 500;
+
+/// Largest chunk that fits SQLite's limit of 32766 bound parameters
+pub const MAX_BULK_CHUNK_SIZE: usize = 32766 / INSERT_COLUMNS;
+
+/// Number of columns bound per log by the bulk insert
+const INSERT_COLUMNS: usize = 11;
+
+/// What a bulk insert does with a log that is already stored
+///
+/// Two logs are duplicates when they have the same card number, timestamp
+/// and device, the key [`CsvLogIngester`](crate::ingest::CsvLogIngester)
+/// uses as well.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// Skip logs already stored or repeated earlier in the same call, so an
+    /// interrupted upload or import can simply be run again
+    #[default]
+    Skip,
+
+    /// Insert every log without looking for duplicates (fastest)
+    Insert,
+}
+
+/// Settings of [`AccessLogRepository::create_many_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkInsertOptions {
+    /// Logs per `INSERT` statement, between 1 and [`MAX_BULK_CHUNK_SIZE`]
+    pub chunk_size: usize,
+
+    /// Handling of logs already stored
+    pub on_duplicate: OnDuplicate,
+}
+
+impl BulkInsertOptions {
+    /// Set the number of logs per statement (clamped to the valid range)
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.clamp(1, MAX_BULK_CHUNK_SIZE);
+        self
+    }
+
+    /// Set the handling of logs already stored
+    pub fn on_duplicate(mut self, on_duplicate: OnDuplicate) -> Self {
+        self.on_duplicate = on_duplicate;
+        self
+    }
+}
+
+impl Default for BulkInsertOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_BULK_CHUNK_SIZE,
+            on_duplicate: OnDuplicate::default(),
+        }
+    }
+}
+
+/// Outcome of a bulk insert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkInsertReport {
+    /// Logs written
+    pub inserted: u64,
+
+    /// Logs skipped as duplicates
+    pub duplicates: u64,
+}
+
+/// Order of the logs returned by [`AccessLogRepository::query`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessLogOrder {
+    /// Order of recording, oldest first
+    #[default]
+    IdAscending,
+
+    /// Order of recording, newest first
+    IdDescending,
+
+    /// Event time, earliest first (logs uploaded late fall in place)
+    TimestampAscending,
+
+    /// Event time, latest first
+    TimestampDescending,
+}
+
+impl AccessLogOrder {
+    fn descending(self) -> bool {
+        matches!(self, Self::IdDescending | Self::TimestampDescending)
+    }
+
+    fn by_timestamp(self) -> bool {
+        matches!(self, Self::TimestampAscending | Self::TimestampDescending)
+    }
+}
+
+/// Filters and order of an access log search
+///
+/// Every filter left unset matches all logs. Built with chainable setters
+/// and passed to [`AccessLogRepository::query`] for a page of logs or
+/// [`AccessLogRepository::count`] for the number of matches.
+///
+/// # Examples
+///
+/// ```no_run
+/// use turnkey_storage::models::Direction;
+/// use turnkey_storage::pagination::PageRequest;
+/// use turnkey_storage::repositories::{
+///     AccessLogOrder, AccessLogQuery, AccessLogRepository, SqliteAccessLogRepository,
+/// };
+/// use chrono::{Duration, Utc};
+///
+/// # async fn example(repo: SqliteAccessLogRepository) -> turnkey_storage::StorageResult<()> {
+/// // Denied entries at device 3 in the last day, newest first
+/// let query = AccessLogQuery::new()
+///     .since(Utc::now() - Duration::days(1))
+///     .granted(false)
+///     .direction(Direction::Entry)
+///     .device_id(3)
+///     .order(AccessLogOrder::TimestampDescending);
+///
+/// let total = repo.count(&query).await?;
+/// let mut request = Some(PageRequest::first(50));
+/// while let Some(page_request) = request {
+///     let page = repo.query(&query, page_request).await?;
+///     request = page.next_page();
+///     # let _ = (total, page.items);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessLogQuery {
+    /// Earliest event time, inclusive
+    pub since: Option<DateTime<Utc>>,
+
+    /// Latest event time, inclusive
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only granted (`true`) or denied (`false`) attempts
+    pub granted: Option<bool>,
+
+    /// Only attempts in this direction
+    pub direction: Option<Direction>,
+
+    /// Only attempts on this kind of reader
+    pub reader_type: Option<ReaderType>,
+
+    /// Only attempts reported by this device
+    pub device_id: Option<i64>,
+
+    /// Only attempts of this user
+    pub user_id: Option<i64>,
+
+    /// Only attempts with this card number
+    pub card_number: Option<String>,
+
+    /// Only attempts whose display message contains this text (ASCII
+    /// letters match regardless of case)
+    pub message: Option<String>,
+
+    /// Order of the results
+    pub order: AccessLogOrder,
+}
+
+impl AccessLogQuery {
+    /// Match every log, oldest first
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match logs from `start` to `end` inclusive
+    pub fn between(self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.since(start).until(end)
+    }
+
+    /// Match logs at or after `start`
+    pub fn since(mut self, start: DateTime<Utc>) -> Self {
+        self.since = Some(start);
+        self
+    }
+
+    /// Match logs at or before `end`
+    pub fn until(mut self, end: DateTime<Utc>) -> Self {
+        self.until = Some(end);
+        self
+    }
+
+    /// Match only granted (`true`) or denied (`false`) attempts
+    pub fn granted(mut self, granted: bool) -> Self {
+        self.granted = Some(granted);
+        self
+    }
+
+    /// Match only attempts in a direction
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Match only attempts on a kind of reader
+    pub fn reader_type(mut self, reader_type: ReaderType) -> Self {
+        self.reader_type = Some(reader_type);
+        self
+    }
+
+    /// Match only attempts reported by a device
+    pub fn device_id(mut self, device_id: i64) -> Self {
+        self.device_id = Some(device_id);
+        self
+    }
+
+    /// Match only attempts of a user
+    pub fn user_id(mut self, user_id: i64) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// Match only attempts with a card number
+    pub fn card_number(mut self, card_number: impl Into<String>) -> Self {
+        self.card_number = Some(card_number.into());
+        self
+    }
+
+    /// Match only attempts whose display message contains `text`
+    pub fn message_contains(mut self, text: impl Into<String>) -> Self {
+        self.message = Some(text.into());
+        self
+    }
+
+    /// Set the order of the results
+    pub fn order(mut self, order: AccessLogOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Append ` WHERE ...` with every filter set (always at least `1 = 1`)
+    fn push_filters(&self, sql: &mut QueryBuilder<'_, Sqlite>) {
+        sql.push(" WHERE 1 = 1");
+        if let Some(since) = self.since {
+            sql.push(" AND timestamp >= ").push_bind(since);
+        }
+        if let Some(until) = self.until {
+            sql.push(" AND timestamp <= ").push_bind(until);
+        }
+        if let Some(granted) = self.granted {
+            sql.push(" AND granted = ").push_bind(granted);
+        }
+        if let Some(direction) = self.direction {
+            sql.push(" AND direction = ").push_bind(direction as i32);
+        }
+        if let Some(reader_type) = self.reader_type {
+            sql.push(" AND reader_type = ")
+                .push_bind(reader_type as i32);
+        }
+        if let Some(device_id) = self.device_id {
+            sql.push(" AND device_id = ").push_bind(device_id);
+        }
+        if let Some(user_id) = self.user_id {
+            sql.push(" AND user_id = ").push_bind(user_id);
+        }
+        if let Some(card_number) = &self.card_number {
+            sql.push(" AND card_number = ")
+                .push_bind(card_number.clone());
+        }
+        if let Some(message) = &self.message {
+            let escaped = message
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            sql.push(" AND display_message LIKE ")
+                .push_bind(format!("%{}%", escaped))
+                .push(" ESCAPE '\\'");
+        }
+    }
+
+    /// Append the keyset predicate, order and limit of a page
+    ///
+    /// The cursor is the ID of the last row of the previous page; with a
+    /// timestamp order the row's timestamp is looked up to seek past it.
+    fn push_page(&self, sql: &mut QueryBuilder<'_, Sqlite>, page: PageRequest) {
+        let (seek, direction) = if self.order.descending() {
+            ("<", "DESC")
+        } else {
+            (">", "ASC")
+        };
+        if let Some(after_id) = page.after_id {
+            if self.order.by_timestamp() {
+                sql.push(format!(
+                    " AND (timestamp, id) {} ((SELECT timestamp FROM access_logs WHERE id = ",
+                    seek
+                ))
+                .push_bind(after_id)
+                .push("), ")
+                .push_bind(after_id)
+                .push(")");
+            } else {
+                sql.push(format!(" AND id {} ", seek)).push_bind(after_id);
+            }
+        }
+        if self.order.by_timestamp() {
+            sql.push(format!(" ORDER BY timestamp {0}, id {0}", direction));
+        } else {
+            sql.push(format!(" ORDER BY id {}", direction));
+        }
+        sql.push(" LIMIT ").push_bind(page.page_size);
+    }
+}
 
 /// Repository trait for AccessLog entity operations
 ///
@@ -17,8 +330,51 @@ use sqlx::SqlitePool;
 /// full async/await support in trait methods.
 pub trait AccessLogRepository: Send + Sync {
     /// Create a new access log entry
+    ///
+    /// A log with a device and no NSR is numbered with the next NSR of its
+    /// device, in the same transaction as the insert.
     async fn create(&self, log: &AccessLog) -> StorageResult<i64>;
 
+    /// Create many entries at once, skipping duplicates
+    ///
+    /// Same as [`create_many_with`](Self::create_many_with) with the default
+    /// [`BulkInsertOptions`].
+    async fn create_many(&self, logs: &[AccessLog]) -> StorageResult<BulkInsertReport> {
+        self.create_many_with(logs, BulkInsertOptions::default())
+            .await
+    }
+
+    /// Create many entries at once with multi-row inserts
+    ///
+    /// All logs are written in one transaction, `options.chunk_size` rows
+    /// per statement: either every log is stored or, on error, none is.
+    /// The `id` and `created_at` of the given logs are ignored. Logs keep
+    /// the NSR they carry and are not numbered: bulk inserts store copies,
+    /// such as events pulled from a device, whose numbers belong to the
+    /// device that wrote them.
+    async fn create_many_with(
+        &self,
+        logs: &[AccessLog],
+        options: BulkInsertOptions,
+    ) -> StorageResult<BulkInsertReport>;
+
+    /// Create a granted entry unless it would violate anti-passback
+    ///
+    /// The entry is skipped when the user's most recent granted access is in
+    /// the same direction and at or after `window_start`. Denied attempts in
+    /// between do not reset the check. The check and
+    /// the insert run as a single statement, so two validators racing on the
+    /// same user (e.g. one card presented at two turnstiles at once) cannot
+    /// both be granted. Entries without a user or with an undefined
+    /// direction are always created.
+    ///
+    /// Returns the new log ID, or `None` if anti-passback blocked it.
+    async fn create_unless_passback(
+        &self,
+        log: &AccessLog,
+        window_start: DateTime<Utc>,
+    ) -> StorageResult<Option<i64>>;
+
     /// Find access logs by user ID
     async fn find_by_user_id(&self, user_id: i64, limit: i64) -> StorageResult<Vec<AccessLog>>;
 
@@ -29,6 +385,21 @@ pub trait AccessLogRepository: Send + Sync {
         limit: i64,
     ) -> StorageResult<Vec<AccessLog>>;
 
+    /// Find every entry recorded for one passage, oldest first
+    async fn find_by_correlation_id(&self, correlation_id: &str) -> StorageResult<Vec<AccessLog>>;
+
+    /// Find the logs of a device numbered `first` to `last` (NSR, inclusive),
+    /// in NSR order
+    ///
+    /// Missing numbers in the result are logs that were pruned, or events
+    /// that were not access logs.
+    async fn find_by_nsr_range(
+        &self,
+        device_id: i64,
+        first: i64,
+        last: i64,
+    ) -> StorageResult<Vec<AccessLog>>;
+
     /// Find recent denied accesses (security monitoring)
     async fn find_recent_denied(&self, limit: i64) -> StorageResult<Vec<AccessLog>>;
 
@@ -55,6 +426,36 @@ pub trait AccessLogRepository: Send + Sync {
         card_number: &str,
         since: DateTime<Utc>,
     ) -> StorageResult<i64>;
+
+    /// Compute clock drift per device from logs received since `since`
+    ///
+    /// Only entries that recorded both a device ID and a device timestamp
+    /// are considered. Results are ordered by device ID.
+    async fn clock_drift_by_device(
+        &self,
+        since: DateTime<Utc>,
+    ) -> StorageResult<Vec<DeviceClockDrift>>;
+
+    /// Fetch one page of access logs ordered by ID (keyset pagination)
+    async fn find_page(&self, page: PageRequest) -> StorageResult<Page<AccessLog>>;
+
+    /// Fetch one page of the logs matching a query (keyset pagination)
+    ///
+    /// Pages follow `query.order`; `page.after_id` is the ID of the last
+    /// log of the previous page, as returned by [`Page::next_page`]. A
+    /// timestamp-ordered search ends early if that log has been archived
+    /// in the meantime.
+    async fn query(
+        &self,
+        query: &AccessLogQuery,
+        page: PageRequest,
+    ) -> StorageResult<Page<AccessLog>>;
+
+    /// Count the logs matching a query
+    async fn count(&self, query: &AccessLogQuery) -> StorageResult<i64>;
+
+    /// Stream all access logs ordered by ID without loading them into memory
+    fn stream_all(&self) -> BoxStream<'_, StorageResult<AccessLog>>;
 }
 
 /// SQLite implementation of AccessLogRepository
@@ -71,43 +472,137 @@ impl SqliteAccessLogRepository {
 
 impl AccessLogRepository for SqliteAccessLogRepository {
     async fn create(&self, log: &AccessLog) -> StorageResult<i64> {
-        let result = sqlx::query(
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query!(
             r#"
             INSERT INTO access_logs (
                 user_id, matricula, card_number, direction,
-                reader_type, granted, display_message, timestamp
+                reader_type, granted, display_message, timestamp,
+                device_id, device_timestamp, correlation_id, nsr
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
+            log.user_id,
+            log.matricula,
+            log.card_number,
+            log.direction,
+            log.reader_type,
+            log.granted,
+            log.display_message,
+            log.timestamp,
+            log.device_id,
+            log.device_timestamp,
+            log.correlation_id,
+            log.nsr
         )
-        .bind(log.user_id)
-        .bind(&log.matricula)
-        .bind(&log.card_number)
-        .bind(log.direction)
-        .bind(log.reader_type)
-        .bind(log.granted)
-        .bind(&log.display_message)
-        .bind(log.timestamp)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
+        let id = result.last_insert_rowid();
+        Outbox::number_log(&mut tx, id).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
 
-        Ok(result.last_insert_rowid())
+    async fn create_many_with(
+        &self,
+        logs: &[AccessLog],
+        options: BulkInsertOptions,
+    ) -> StorageResult<BulkInsertReport> {
+        let chunk_size = options.chunk_size.clamp(1, MAX_BULK_CHUNK_SIZE);
+        let skip = options.on_duplicate == OnDuplicate::Skip;
+
+        // Repeats within the call are dropped here; the statement below only
+        // sees logs stored before it runs
+        let mut seen = HashSet::new();
+        let unique: Vec<&AccessLog> = logs
+            .iter()
+            .filter(|log| !skip || seen.insert((&log.card_number, log.timestamp, log.device_id)))
+            .collect();
+        let mut report = BulkInsertReport {
+            inserted: 0,
+            duplicates: (logs.len() - unique.len()) as u64,
+        };
+
+        let mut tx = self.pool.begin().await?;
+        for chunk in unique.chunks(chunk_size) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "WITH batch (user_id, matricula, card_number, direction, \
+                 reader_type, granted, display_message, timestamp, \
+                 device_id, device_timestamp, correlation_id, nsr) AS (",
+            );
+            query.push_values(chunk, |mut row, log| {
+                row.push_bind(log.user_id)
+                    .push_bind(&log.matricula)
+                    .push_bind(&log.card_number)
+                    .push_bind(log.direction)
+                    .push_bind(log.reader_type)
+                    .push_bind(log.granted)
+                    .push_bind(&log.display_message)
+                    .push_bind(log.timestamp)
+                    .push_bind(log.device_id)
+                    .push_bind(log.device_timestamp)
+                    .push_bind(&log.correlation_id)
+                    .push_bind(log.nsr);
+            });
+            query.push(
+                ") INSERT INTO access_logs (user_id, matricula, card_number, direction, \
+                 reader_type, granted, display_message, timestamp, \
+                 device_id, device_timestamp, correlation_id, nsr) \
+                 SELECT * FROM batch",
+            );
+            if skip {
+                query.push(
+                    " WHERE NOT EXISTS (\
+                     SELECT 1 FROM access_logs a \
+                     WHERE a.card_number = batch.card_number \
+                       AND a.timestamp = batch.timestamp \
+                       AND a.device_id IS batch.device_id)",
+                );
+            }
+
+            let inserted = query.build().execute(&mut *tx).await?.rows_affected();
+            report.inserted += inserted;
+            report.duplicates += chunk.len() as u64 - inserted;
+        }
+        tx.commit().await?;
+
+        Ok(report)
+    }
+
+    async fn create_unless_passback(
+        &self,
+        log: &AccessLog,
+        window_start: DateTime<Utc>,
+    ) -> StorageResult<Option<i64>> {
+        if !passback_applies(log) {
+            return self.create(log).await.map(Some);
+        }
+        let mut tx = self.pool.begin().await?;
+        let id = insert_unless_passback(&mut *tx, log, window_start).await?;
+        if let Some(id) = id {
+            Outbox::number_log(&mut tx, id).await?;
+        }
+        tx.commit().await?;
+        Ok(id)
     }
 
     async fn find_by_user_id(&self, user_id: i64, limit: i64) -> StorageResult<Vec<AccessLog>> {
-        let logs = sqlx::query_as::<_, AccessLog>(
+        let logs = sqlx::query_as!(
+            AccessLog,
             r#"
-            SELECT id, user_id, matricula, card_number,
-                   direction, reader_type, granted,
-                   display_message, timestamp, created_at
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
             FROM access_logs
             WHERE user_id = ?
             ORDER BY timestamp DESC
             LIMIT ?
             "#,
+            user_id,
+            limit
         )
-        .bind(user_id)
-        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
@@ -119,19 +614,68 @@ impl AccessLogRepository for SqliteAccessLogRepository {
         card_number: &str,
         limit: i64,
     ) -> StorageResult<Vec<AccessLog>> {
-        let logs = sqlx::query_as::<_, AccessLog>(
+        let logs = sqlx::query_as!(
+            AccessLog,
             r#"
-            SELECT id, user_id, matricula, card_number,
-                   direction, reader_type, granted,
-                   display_message, timestamp, created_at
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
             FROM access_logs
             WHERE card_number = ?
             ORDER BY timestamp DESC
             LIMIT ?
             "#,
+            card_number,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(logs)
+    }
+
+    async fn find_by_correlation_id(&self, correlation_id: &str) -> StorageResult<Vec<AccessLog>> {
+        let logs = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE correlation_id = ?
+            ORDER BY id
+            "#,
+            correlation_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(logs)
+    }
+
+    async fn find_by_nsr_range(
+        &self,
+        device_id: i64,
+        first: i64,
+        last: i64,
+    ) -> StorageResult<Vec<AccessLog>> {
+        let logs = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE device_id = ? AND nsr BETWEEN ? AND ?
+            ORDER BY nsr
+            "#,
+            device_id,
+            first,
+            last
         )
-        .bind(card_number)
-        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
@@ -139,18 +683,20 @@ impl AccessLogRepository for SqliteAccessLogRepository {
     }
 
     async fn find_recent_denied(&self, limit: i64) -> StorageResult<Vec<AccessLog>> {
-        let logs = sqlx::query_as::<_, AccessLog>(
+        let logs = sqlx::query_as!(
+            AccessLog,
             r#"
-            SELECT id, user_id, matricula, card_number,
-                   direction, reader_type, granted,
-                   display_message, timestamp, created_at
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
             FROM access_logs
             WHERE granted = 0
             ORDER BY timestamp DESC
             LIMIT ?
             "#,
+            limit
         )
-        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
@@ -158,18 +704,20 @@ impl AccessLogRepository for SqliteAccessLogRepository {
     }
 
     async fn find_recent_granted(&self, limit: i64) -> StorageResult<Vec<AccessLog>> {
-        let logs = sqlx::query_as::<_, AccessLog>(
+        let logs = sqlx::query_as!(
+            AccessLog,
             r#"
-            SELECT id, user_id, matricula, card_number,
-                   direction, reader_type, granted,
-                   display_message, timestamp, created_at
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
             FROM access_logs
             WHERE granted = 1
             ORDER BY timestamp DESC
             LIMIT ?
             "#,
+            limit
         )
-        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
@@ -181,18 +729,20 @@ impl AccessLogRepository for SqliteAccessLogRepository {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> StorageResult<Vec<AccessLog>> {
-        let logs = sqlx::query_as::<_, AccessLog>(
+        let logs = sqlx::query_as!(
+            AccessLog,
             r#"
-            SELECT id, user_id, matricula, card_number,
-                   direction, reader_type, granted,
-                   display_message, timestamp, created_at
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
             FROM access_logs
             WHERE timestamp >= ? AND timestamp <= ?
             ORDER BY timestamp DESC
             "#,
+            start,
+            end
         )
-        .bind(start)
-        .bind(end)
         .fetch_all(&self.pool)
         .await?;
 
@@ -204,15 +754,15 @@ impl AccessLogRepository for SqliteAccessLogRepository {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> StorageResult<i64> {
-        let result: (i64,) = sqlx::query_as(
+        let count = sqlx::query_scalar!(
             "SELECT COUNT(*) FROM access_logs WHERE timestamp >= ? AND timestamp <= ?",
+            start,
+            end
         )
-        .bind(start)
-        .bind(end)
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(result.0)
+        Ok(count)
     }
 
     async fn count_denied_by_card(
@@ -220,24 +770,205 @@ impl AccessLogRepository for SqliteAccessLogRepository {
         card_number: &str,
         since: DateTime<Utc>,
     ) -> StorageResult<i64> {
-        let result: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM access_logs WHERE card_number = ? AND granted = 0 AND timestamp >= ?",
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM access_logs
+            WHERE card_number = ? AND granted = 0 AND timestamp >= ?
+            "#,
+            card_number,
+            since
         )
-        .bind(card_number)
-        .bind(since)
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(result.0)
+        Ok(count)
+    }
+
+    async fn clock_drift_by_device(
+        &self,
+        since: DateTime<Utc>,
+    ) -> StorageResult<Vec<DeviceClockDrift>> {
+        let drifts = sqlx::query_as!(
+            DeviceClockDrift,
+            r#"
+            WITH samples AS (
+                SELECT device_id, timestamp,
+                       CAST(ROUND((julianday(device_timestamp) - julianday(timestamp))
+                            * 86400000.0) AS INTEGER) AS drift_ms,
+                       ROW_NUMBER() OVER (
+                           PARTITION BY device_id ORDER BY timestamp DESC, id DESC
+                       ) AS recency
+                FROM access_logs
+                WHERE device_id IS NOT NULL
+                  AND device_timestamp IS NOT NULL
+                  AND timestamp >= ?
+            )
+            SELECT s.device_id AS "device_id!",
+                   MAX(d.name) AS device_name,
+                   COUNT(*) AS sample_count,
+                   CAST(ROUND(AVG(s.drift_ms)) AS INTEGER) AS "avg_drift_ms!: i64",
+                   MAX(ABS(s.drift_ms)) AS "max_abs_drift_ms!: i64",
+                   MAX(CASE WHEN s.recency = 1 THEN s.drift_ms END) AS "last_drift_ms!: i64",
+                   MAX(s.timestamp) AS "last_seen!: _"
+            FROM samples s
+            LEFT JOIN devices d ON d.device_id = s.device_id
+            GROUP BY s.device_id
+            ORDER BY s.device_id
+            "#,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(drifts)
+    }
+
+    async fn find_page(&self, page: PageRequest) -> StorageResult<Page<AccessLog>> {
+        let after_id = page.id_lower_bound();
+        let rows = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE id > ?
+            ORDER BY id
+            LIMIT ?
+            "#,
+            after_id,
+            page.page_size
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Page::from_rows(rows, page, |row| row.id))
+    }
+
+    async fn query(
+        &self,
+        query: &AccessLogQuery,
+        page: PageRequest,
+    ) -> StorageResult<Page<AccessLog>> {
+        let mut sql = QueryBuilder::<Sqlite>::new(
+            "SELECT id, user_id, matricula, card_number, direction, reader_type, granted, \
+             display_message, timestamp, created_at, device_id, device_timestamp, \
+             correlation_id, nsr FROM access_logs",
+        );
+        query.push_filters(&mut sql);
+        query.push_page(&mut sql, page);
+
+        let rows = sql
+            .build_query_as::<AccessLog>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(Page::from_rows(rows, page, |row| row.id))
+    }
+
+    async fn count(&self, query: &AccessLogQuery) -> StorageResult<i64> {
+        let mut sql = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM access_logs");
+        query.push_filters(&mut sql);
+
+        let count: i64 = sql.build_query_scalar().fetch_one(&self.pool).await?;
+
+        Ok(count)
+    }
+
+    fn stream_all(&self) -> BoxStream<'_, StorageResult<AccessLog>> {
+        sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            ORDER BY id
+            "#
+        )
+        .fetch(&self.pool)
+        .map_err(StorageError::from)
+        .boxed()
     }
 }
 
+/// Whether anti-passback checks this log: it has a user and a direction
+pub(crate) fn passback_applies(log: &AccessLog) -> bool {
+    let directional =
+        log.direction == Direction::Entry as i32 || log.direction == Direction::Exit as i32;
+    log.user_id.is_some() && directional
+}
+
+/// Insert a log unless it violates anti-passback, on any executor (pool or
+/// transaction)
+///
+/// The caller checks [`passback_applies()`] first; see
+/// [`AccessLogRepository::create_unless_passback`].
+pub(crate) async fn insert_unless_passback<'e, E>(
+    executor: E,
+    log: &AccessLog,
+    window_start: DateTime<Utc>,
+) -> StorageResult<Option<i64>>
+where
+    E: sqlx::SqliteExecutor<'e>,
+{
+    // INSERT ... SELECT holds the write lock from the check to the insert,
+    // which SQLite offers in place of SELECT ... FOR UPDATE
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO access_logs (
+            user_id, matricula, card_number, direction,
+            reader_type, granted, display_message, timestamp,
+            device_id, device_timestamp, correlation_id, nsr
+        )
+        SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM (
+                SELECT direction, timestamp
+                FROM access_logs
+                WHERE user_id = ? AND granted = 1
+                ORDER BY timestamp DESC, id DESC
+                LIMIT 1
+            ) AS last
+            WHERE last.direction = ?
+              AND last.timestamp >= ?
+        )
+        "#,
+        log.user_id,
+        log.matricula,
+        log.card_number,
+        log.direction,
+        log.reader_type,
+        log.granted,
+        log.display_message,
+        log.timestamp,
+        log.device_id,
+        log.device_timestamp,
+        log.correlation_id,
+        log.nsr,
+        log.user_id,
+        log.direction,
+        window_start
+    )
+    .execute(executor)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(result.last_insert_rowid()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::connection::Database;
     use crate::models::{Card, Direction, ReaderType, User};
     use crate::repositories::card::{CardRepository, SqliteCardRepository};
+    use crate::repositories::device::{DeviceRepository, SqliteDeviceRepository};
     use crate::repositories::user::{SqliteUserRepository, UserRepository};
     use chrono::Duration;
 
@@ -259,6 +990,7 @@ mod tests {
             allow_bio: false,
             allow_keypad: false,
             codigo: None,
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -319,6 +1051,89 @@ mod tests {
         assert!(id > 0);
     }
 
+    #[tokio::test]
+    async fn test_create_many_skips_duplicates() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP012").await;
+        create_test_card(&db, "1212121212", "EMP012", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let start = Utc::now() - Duration::hours(1);
+        let mut logs: Vec<AccessLog> = (0..5)
+            .map(|i| {
+                let mut log = create_test_log(user_id, "EMP012", "1212121212", i % 2 == 0);
+                log.timestamp = start + Duration::seconds(i);
+                log.with_device_clock(15, start + Duration::seconds(i))
+            })
+            .collect();
+        // Same card and time, but another device or none at all
+        logs.push(logs[0].clone().with_device_clock(16, start));
+        let mut unidentified = logs[0].clone();
+        unidentified.device_id = None;
+        logs.push(unidentified);
+
+        let options = BulkInsertOptions::default().chunk_size(2);
+        let report = repo.create_many_with(&logs, options).await.unwrap();
+        assert_eq!(
+            report,
+            BulkInsertReport {
+                inserted: 7,
+                duplicates: 0
+            }
+        );
+
+        // Re-running, with a repeat inside the batch, inserts nothing
+        logs.push(logs[2].clone());
+        let report = repo.create_many_with(&logs, options).await.unwrap();
+        assert_eq!(report.inserted, 0);
+        assert_eq!(report.duplicates, 8);
+
+        let stored = repo.find_by_card_number("1212121212", 100).await.unwrap();
+        assert_eq!(stored.len(), 7);
+        assert_eq!(stored.iter().filter(|log| log.granted).count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_create_many_insert_policy_is_atomic() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP013").await;
+        create_test_card(&db, "1313131313", "EMP013", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let log = create_test_log(user_id, "EMP013", "1313131313", true);
+        let options = BulkInsertOptions::default().on_duplicate(OnDuplicate::Insert);
+
+        let report = repo
+            .create_many_with(&[log.clone(), log.clone()], options)
+            .await
+            .unwrap();
+        assert_eq!(report.inserted, 2);
+        assert_eq!(
+            repo.create_many(std::slice::from_ref(&log))
+                .await
+                .unwrap()
+                .duplicates,
+            1
+        );
+
+        // A row breaking a constraint rolls back the rows before it
+        let mut invalid = log.clone();
+        invalid.reader_type = 9;
+        let mut fresh = log.clone();
+        fresh.timestamp = log.timestamp + Duration::seconds(1);
+        let result = repo
+            .create_many_with(&[fresh, invalid], options.chunk_size(1))
+            .await;
+        assert!(result.is_err());
+        let stored = repo.find_by_card_number("1313131313", 100).await.unwrap();
+        assert_eq!(stored.len(), 2);
+
+        assert_eq!(
+            repo.create_many(&[]).await.unwrap(),
+            BulkInsertReport::default()
+        );
+    }
+
     #[tokio::test]
     async fn test_find_by_user_id() {
         let db = setup_test_db().await;
@@ -436,4 +1251,417 @@ mod tests {
             .unwrap();
         assert_eq!(count, 1);
     }
+
+    #[tokio::test]
+    async fn test_create_unless_passback() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP010").await;
+        create_test_card(&db, "1010101010", "EMP010", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let window_start = Utc::now() - Duration::minutes(5);
+        let entry = create_test_log(user_id, "EMP010", "1010101010", true);
+
+        // Both requests passed their read-only check; only one may be stored
+        assert!(
+            repo.create_unless_passback(&entry, window_start)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            repo.create_unless_passback(&entry, window_start)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // A denied attempt in between does not reset anti-passback
+        repo.create(&create_test_log(user_id, "EMP010", "1010101010", false))
+            .await
+            .unwrap();
+        assert!(
+            repo.create_unless_passback(&entry, window_start)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let mut exit = entry.clone();
+        exit.direction = Direction::Exit as i32;
+        assert!(
+            repo.create_unless_passback(&exit, window_start)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Outside the window the same direction is allowed again
+        assert!(
+            repo.create_unless_passback(&exit, Utc::now() + Duration::seconds(1))
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let mut undefined = entry.clone();
+        undefined.direction = Direction::Undefined as i32;
+        for _ in 0..2 {
+            assert!(
+                repo.create_unless_passback(&undefined, window_start)
+                    .await
+                    .unwrap()
+                    .is_some()
+            );
+        }
+        assert_eq!(repo.find_by_user_id(user_id, 10).await.unwrap().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_correlation_id() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP010").await;
+        create_test_card(&db, "1010101010", "EMP010", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let correlation_id = turnkey_core::CorrelationId::new();
+        let log = create_test_log(user_id, "EMP010", "1010101010", true)
+            .with_correlation_id(correlation_id);
+        let id = repo
+            .create_unless_passback(&log, Utc::now() - Duration::minutes(5))
+            .await
+            .unwrap()
+            .unwrap();
+        repo.create(&create_test_log(user_id, "EMP010", "1010101010", false))
+            .await
+            .unwrap();
+
+        let logs = repo
+            .find_by_correlation_id(&correlation_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, id);
+        assert_eq!(logs[0].correlation_id, Some(correlation_id.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_nsr_range_per_device() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP011").await;
+        create_test_card(&db, "1111111110", "EMP011", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let log = |device_id: i64, nsr: i64| {
+            create_test_log(user_id, "EMP011", "1111111110", true)
+                .with_device_clock(device_id, Utc::now())
+                .with_nsr(nsr)
+        };
+        let mut ids = Vec::new();
+        for nsr in [1, 2, 4] {
+            ids.push(repo.create(&log(15, nsr)).await.unwrap());
+        }
+        repo.create(&log(16, 3)).await.unwrap();
+        repo.create(&create_test_log(user_id, "EMP011", "1111111110", false))
+            .await
+            .unwrap();
+
+        let logs = repo.find_by_nsr_range(15, 1, 4).await.unwrap();
+        let found: Vec<i64> = logs.iter().map(|log| log.id).collect();
+        assert_eq!(found, ids);
+        assert_eq!(repo.find_by_nsr_range(16, 1, 10).await.unwrap().len(), 1);
+        assert!(repo.find_by_nsr_range(15, 5, 10).await.unwrap().is_empty());
+
+        // A device never has two logs with one NSR
+        assert!(repo.create(&log(15, 2)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_numbers_device_logs() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP012").await;
+        create_test_card(&db, "1212121210", "EMP012", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let log = |device_id: i64| {
+            create_test_log(user_id, "EMP012", "1212121210", true)
+                .with_device_clock(device_id, Utc::now())
+        };
+        for _ in 0..2 {
+            repo.create(&log(15)).await.unwrap();
+        }
+        // A window starting in the future never blocks
+        let id = repo
+            .create_unless_passback(&log(16), Utc::now() + Duration::minutes(5))
+            .await
+            .unwrap()
+            .unwrap();
+        let unnumbered = repo
+            .create(&create_test_log(user_id, "EMP012", "1212121210", false))
+            .await
+            .unwrap();
+
+        // Each device counts on its own; a log without a device has no NSR
+        let nsrs: Vec<_> = repo
+            .find_by_nsr_range(15, 1, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|log| log.nsr)
+            .collect();
+        assert_eq!(nsrs, [Some(1), Some(2)]);
+        let numbered = sqlx::query_scalar!("SELECT nsr FROM access_logs WHERE id = ?", id)
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        let unnumbered =
+            sqlx::query_scalar!("SELECT nsr FROM access_logs WHERE id = ?", unnumbered)
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(numbered, Some(1));
+        assert_eq!(unnumbered, None);
+    }
+
+    #[tokio::test]
+    async fn test_device_clock_roundtrip() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP009").await;
+        create_test_card(&db, "9999999999", "EMP009", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let log = create_test_log(user_id, "EMP009", "9999999999", true);
+        let device_ts = log.timestamp - Duration::seconds(30);
+        repo.create(&log.with_device_clock(15, device_ts))
+            .await
+            .unwrap();
+
+        let logs = repo.find_by_card_number("9999999999", 10).await.unwrap();
+        assert_eq!(logs[0].device_id, Some(15));
+        assert_eq!(logs[0].device_timestamp, Some(device_ts));
+        assert_eq!(logs[0].clock_drift(), Some(Duration::seconds(-30)));
+    }
+
+    #[tokio::test]
+    async fn test_clock_drift_by_device() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP010").await;
+        create_test_card(&db, "1010101010", "EMP010", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let base = Utc::now() - Duration::minutes(10);
+
+        // Device 3 drifts further ahead over time; device 7 runs behind
+        for (device_id, offset_secs, drift_secs) in
+            [(3, 0, 10), (3, 60, 30), (3, 120, 50), (7, 30, -120)]
+        {
+            let mut log = create_test_log(user_id, "EMP010", "1010101010", true);
+            log.timestamp = base + Duration::seconds(offset_secs);
+            let device_ts = log.timestamp + Duration::seconds(drift_secs);
+            repo.create(&log.with_device_clock(device_id, device_ts))
+                .await
+                .unwrap();
+        }
+
+        // Logs without device clock data are ignored
+        repo.create(&create_test_log(user_id, "EMP010", "1010101010", true))
+            .await
+            .unwrap();
+
+        SqliteDeviceRepository::new(db.pool().clone())
+            .set_label(3, Some("Portaria Norte"), None)
+            .await
+            .unwrap();
+
+        let drifts = repo
+            .clock_drift_by_device(base - Duration::minutes(1))
+            .await
+            .unwrap();
+
+        assert_eq!(drifts.len(), 2);
+
+        assert_eq!(drifts[0].device_id, 3);
+        assert_eq!(drifts[0].device_name.as_deref(), Some("Portaria Norte"));
+        assert_eq!(drifts[0].sample_count, 3);
+        assert_eq!(drifts[0].avg_drift_ms, 30_000);
+        assert_eq!(drifts[0].max_abs_drift_ms, 50_000);
+        assert_eq!(drifts[0].last_drift_ms, 50_000);
+        assert_eq!(drifts[0].last_seen, base + Duration::seconds(120));
+
+        assert_eq!(drifts[1].device_id, 7);
+        assert_eq!(drifts[1].device_name, None);
+        assert_eq!(drifts[1].last_drift_ms, -120_000);
+        assert_eq!(drifts[1].max_abs_drift_ms, 120_000);
+
+        // Window excludes older samples
+        let recent = repo
+            .clock_drift_by_device(base + Duration::seconds(90))
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].sample_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_find_page_and_stream_all_logs() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP011").await;
+        create_test_card(&db, "1111111111", "EMP011", user_id).await;
+
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        for granted in [true, false, true] {
+            repo.create(&create_test_log(user_id, "EMP011", "1111111111", granted))
+                .await
+                .unwrap();
+        }
+
+        let streamed: Vec<AccessLog> = repo.stream_all().try_collect().await.unwrap();
+
+        let mut paged_ids = Vec::new();
+        let mut request = Some(PageRequest::first(2));
+        while let Some(page_request) = request {
+            let page = repo.find_page(page_request).await.unwrap();
+            request = page.next_page();
+            paged_ids.extend(page.items.iter().map(|log| log.id));
+        }
+
+        let streamed_ids: Vec<i64> = streamed.iter().map(|log| log.id).collect();
+        assert_eq!(streamed_ids, paged_ids);
+        assert!(streamed_ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            streamed
+                .iter()
+                .filter(|log| log.card_number == "1111111111")
+                .count(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_and_count() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP012").await;
+        create_test_card(&db, "1212121212", "EMP012", user_id).await;
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+
+        let now = Utc::now();
+        let mut logs = Vec::new();
+        for (minutes, granted, direction, device_id, message) in [
+            (50, true, Direction::Entry, 1, "Acesso liberado"),
+            (40, false, Direction::Entry, 1, "Cartao bloqueado"),
+            (30, false, Direction::Exit, 2, "100% negado"),
+            (20, false, Direction::Entry, 2, "Fora do horario permitido"),
+            (10, true, Direction::Exit, 2, "Acesso liberado"),
+        ] {
+            let mut log = create_test_log(user_id, "EMP012", "1212121212", granted);
+            log.direction = direction as i32;
+            log.device_id = Some(device_id);
+            log.display_message = Some(message.to_string());
+            log.timestamp = now - Duration::minutes(minutes);
+            logs.push(log);
+        }
+        repo.create_many(&logs).await.unwrap();
+
+        // The seeded database has logs of its own
+        let mine = AccessLogQuery::new().user_id(user_id);
+        assert_eq!(repo.count(&mine).await.unwrap(), 5);
+
+        let denied_entries = mine.clone().granted(false).direction(Direction::Entry);
+        assert_eq!(repo.count(&denied_entries).await.unwrap(), 2);
+
+        let recent_at_device = mine
+            .clone()
+            .since(now - Duration::minutes(35))
+            .device_id(2)
+            .reader_type(ReaderType::Rfid)
+            .card_number("1212121212");
+        assert_eq!(repo.count(&recent_at_device).await.unwrap(), 3);
+        assert_eq!(
+            repo.count(&recent_at_device.clone().until(now - Duration::minutes(25)))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            repo.count(&mine.clone().reader_type(ReaderType::Biometric))
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Message match ignores ASCII case and treats wildcards literally
+        let page = repo
+            .query(
+                &mine.clone().message_contains("LIBERADO"),
+                PageRequest::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(
+            repo.count(&mine.clone().message_contains("100%"))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            repo.count(&mine.clone().message_contains("%"))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            repo.count(&mine.clone().message_contains("_"))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_pages_in_every_order() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP013").await;
+        create_test_card(&db, "1313131313", "EMP013", user_id).await;
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+
+        // Recorded out of event order, as after an offline upload; two
+        // logs share a timestamp
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for minutes in [30, 10, 50, 20, 20] {
+            let mut log = create_test_log(user_id, "EMP013", "1313131313", true);
+            log.timestamp = now - Duration::minutes(minutes);
+            ids.push((repo.create(&log).await.unwrap(), minutes));
+        }
+
+        async fn collect(repo: &SqliteAccessLogRepository, query: &AccessLogQuery) -> Vec<i64> {
+            let mut found = Vec::new();
+            let mut request = Some(PageRequest::first(2));
+            while let Some(page_request) = request {
+                let page = repo.query(query, page_request).await.unwrap();
+                request = page.next_page();
+                found.extend(page.items.iter().map(|log| log.id));
+            }
+            found
+        }
+
+        let by_id: Vec<i64> = ids.iter().map(|(id, _)| *id).collect();
+        let query = AccessLogQuery::new().user_id(user_id);
+        assert_eq!(collect(&repo, &query).await, by_id);
+
+        let newest_first: Vec<i64> = by_id.iter().rev().copied().collect();
+        let query = query.order(AccessLogOrder::IdDescending);
+        assert_eq!(collect(&repo, &query).await, newest_first);
+
+        let mut by_time = ids.clone();
+        by_time.sort_by_key(|(id, minutes)| (std::cmp::Reverse(*minutes), *id));
+        let earliest_first: Vec<i64> = by_time.iter().map(|(id, _)| *id).collect();
+        let query = query.order(AccessLogOrder::TimestampAscending);
+        assert_eq!(collect(&repo, &query).await, earliest_first);
+
+        let latest_first: Vec<i64> = earliest_first.iter().rev().copied().collect();
+        let query = query.order(AccessLogOrder::TimestampDescending);
+        assert_eq!(collect(&repo, &query).await, latest_first);
+    }
 }
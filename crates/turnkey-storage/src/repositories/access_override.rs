@@ -0,0 +1,393 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::messages::DisplayMessages;
+use crate::models::{AccessLog, AccessOverride, AccessOverrideOutcome};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use turnkey_protocol::commands::operator_override::{MAX_OPERATOR_ID_LENGTH, MAX_REASON_LENGTH};
+
+/// Repository trait for operator overrides of denied accesses
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait AccessOverrideRepository: Send + Sync {
+    /// Grant a denied access on an operator's decision
+    ///
+    /// In one transaction, writes a granted access log copying the card,
+    /// user, direction, reader, device and correlation ID of the denial,
+    /// with `DisplayMessages::OPERATOR_OVERRIDE` as message, and records the
+    /// override linking both logs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the denial does not exist, and `Validation`
+    /// for an empty or too long operator or reason, a log that was granted,
+    /// or a denial that was already overridden.
+    async fn create(
+        &self,
+        denied_log_id: i64,
+        operator_id: &str,
+        reason: &str,
+    ) -> StorageResult<AccessOverrideOutcome>;
+
+    /// Find the override of a denial, if any
+    async fn find_by_denied_log(&self, denied_log_id: i64)
+    -> StorageResult<Option<AccessOverride>>;
+
+    /// Overrides granted by an operator since `since`, oldest first
+    async fn find_by_operator(
+        &self,
+        operator_id: &str,
+        since: DateTime<Utc>,
+    ) -> StorageResult<Vec<AccessOverride>>;
+
+    /// Most recent overrides, newest first
+    async fn find_recent(&self, limit: i64) -> StorageResult<Vec<AccessOverride>>;
+}
+
+/// SQLite implementation of AccessOverrideRepository
+pub struct SqliteAccessOverrideRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteAccessOverrideRepository {
+    /// Create a new SQLite access override repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+fn required(name: &str, value: &str, max: usize) -> StorageResult<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(StorageError::Validation(format!(
+            "Override {} is required",
+            name
+        )));
+    }
+    if value.chars().count() > max {
+        return Err(StorageError::Validation(format!(
+            "Override {} exceeds {} characters",
+            name, max
+        )));
+    }
+    Ok(value.to_string())
+}
+
+impl AccessOverrideRepository for SqliteAccessOverrideRepository {
+    async fn create(
+        &self,
+        denied_log_id: i64,
+        operator_id: &str,
+        reason: &str,
+    ) -> StorageResult<AccessOverrideOutcome> {
+        let operator_id = required("operator", operator_id, MAX_OPERATOR_ID_LENGTH)?;
+        let reason = required("reason", reason, MAX_REASON_LENGTH)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let denial = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE id = ?
+            "#,
+            denied_log_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| StorageError::NotFound {
+            entity_type: "AccessLog".to_string(),
+            field: "id".to_string(),
+            value: denied_log_id.to_string(),
+        })?;
+        if denial.granted {
+            return Err(StorageError::Validation(format!(
+                "Access log {} was granted; only denials can be overridden",
+                denied_log_id
+            )));
+        }
+
+        let existing: Option<i64> = sqlx::query_scalar!(
+            r#"SELECT id AS "id!" FROM access_overrides WHERE denied_log_id = ?"#,
+            denied_log_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(existing) = existing {
+            return Err(StorageError::Validation(format!(
+                "Denial {} was already overridden (override {})",
+                denied_log_id, existing
+            )));
+        }
+
+        let now = Utc::now();
+        let granted_log_id = sqlx::query!(
+            r#"
+            INSERT INTO access_logs (
+                user_id, matricula, card_number, direction,
+                reader_type, granted, display_message, timestamp,
+                device_id, device_timestamp, correlation_id
+            )
+            VALUES (?, ?, ?, ?, ?, 1, ?, ?, ?, NULL, ?)
+            "#,
+            denial.user_id,
+            denial.matricula,
+            denial.card_number,
+            denial.direction,
+            denial.reader_type,
+            DisplayMessages::OPERATOR_OVERRIDE,
+            now,
+            denial.device_id,
+            denial.correlation_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        let record = sqlx::query_as!(
+            AccessOverride,
+            r#"
+            INSERT INTO access_overrides (
+                denied_log_id, granted_log_id, operator_id, reason, device_id, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id AS "id!", denied_log_id, granted_log_id, operator_id, reason, device_id,
+                      created_at AS "created_at: _"
+            "#,
+            denied_log_id,
+            granted_log_id,
+            operator_id,
+            reason,
+            denial.device_id,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let granted_log = sqlx::query_as!(
+            AccessLog,
+            r#"
+            SELECT id AS "id!", user_id, matricula, card_number,
+                   direction AS "direction: _", reader_type AS "reader_type: _", granted,
+                   display_message, timestamp AS "timestamp: _", created_at AS "created_at: _",
+                   device_id, device_timestamp AS "device_timestamp: _", correlation_id, nsr
+            FROM access_logs
+            WHERE id = ?
+            "#,
+            granted_log_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(AccessOverrideOutcome {
+            record,
+            granted_log,
+        })
+    }
+
+    async fn find_by_denied_log(
+        &self,
+        denied_log_id: i64,
+    ) -> StorageResult<Option<AccessOverride>> {
+        let record = sqlx::query_as!(
+            AccessOverride,
+            r#"
+            SELECT id AS "id!", denied_log_id, granted_log_id, operator_id, reason, device_id,
+                   created_at AS "created_at: _"
+            FROM access_overrides
+            WHERE denied_log_id = ?
+            "#,
+            denied_log_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    async fn find_by_operator(
+        &self,
+        operator_id: &str,
+        since: DateTime<Utc>,
+    ) -> StorageResult<Vec<AccessOverride>> {
+        let operator_id = operator_id.trim();
+        let records = sqlx::query_as!(
+            AccessOverride,
+            r#"
+            SELECT id AS "id!", denied_log_id, granted_log_id, operator_id, reason, device_id,
+                   created_at AS "created_at: _"
+            FROM access_overrides
+            WHERE operator_id = ? AND created_at >= ?
+            ORDER BY created_at, id
+            "#,
+            operator_id,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn find_recent(&self, limit: i64) -> StorageResult<Vec<AccessOverride>> {
+        let records = sqlx::query_as!(
+            AccessOverride,
+            r#"
+            SELECT id AS "id!", denied_log_id, granted_log_id, operator_id, reason, device_id,
+                   created_at AS "created_at: _"
+            FROM access_overrides
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{Direction, ReaderType};
+    use crate::repositories::{
+        AccessLogRepository, PresenceRepository, SqliteAccessLogRepository,
+        SqlitePresenceRepository,
+    };
+    use turnkey_core::{CorrelationId, DeviceId};
+    use turnkey_protocol::CommandCode;
+    use turnkey_protocol::commands::OverrideGrant;
+
+    async fn setup() -> (Database, SqliteAccessOverrideRepository) {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteAccessOverrideRepository::new(db.pool().clone());
+        (db, repo)
+    }
+
+    async fn log(db: &Database, granted: bool, correlation_id: Option<CorrelationId>) -> i64 {
+        let mut log = AccessLog::new(
+            Some(3),
+            Some("1003".to_string()),
+            "00000000000033734544".to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            granted,
+            Some(DisplayMessages::CARD_EXPIRED.to_string()),
+            Utc::now(),
+        )
+        .with_device_clock(15, Utc::now());
+        if let Some(correlation_id) = correlation_id {
+            log = log.with_correlation_id(correlation_id);
+        }
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create(&log)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_override_grants_and_links_denial() {
+        let (db, repo) = setup().await;
+        let correlation_id = CorrelationId::new();
+        let denied = log(&db, false, Some(correlation_id)).await;
+        let presence = SqlitePresenceRepository::new(db.pool().clone());
+        let inside_before = presence.count_inside().await.unwrap();
+
+        let outcome = repo
+            .create(denied, " guarda-07 ", "Cracha vencido, RH confirmou")
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.record.denied_log_id, denied);
+        assert_eq!(outcome.record.granted_log_id, outcome.granted_log.id);
+        assert_eq!(outcome.record.operator_id, "guarda-07");
+        assert_eq!(outcome.record.device_id, Some(15));
+
+        let granted = &outcome.granted_log;
+        assert!(granted.granted);
+        assert_eq!(granted.card_number, "00000000000033734544");
+        assert_eq!(granted.user_id, Some(3));
+        assert_eq!(granted.get_direction(), Some(Direction::Entry));
+        assert_eq!(granted.device_id, Some(15));
+        assert_eq!(granted.correlation_id, Some(correlation_id.to_string()));
+        assert_eq!(
+            granted.display_message.as_deref(),
+            Some(DisplayMessages::OPERATOR_OVERRIDE)
+        );
+
+        // The override grant counts as a passage
+        assert_eq!(presence.count_inside().await.unwrap(), inside_before + 1);
+
+        assert_eq!(
+            repo.find_by_denied_log(denied).await.unwrap(),
+            Some(outcome.record.clone())
+        );
+        assert_eq!(repo.find_recent(10).await.unwrap(), vec![outcome.record]);
+    }
+
+    #[tokio::test]
+    async fn test_override_rejects_invalid_targets() {
+        let (db, repo) = setup().await;
+        let granted = log(&db, true, None).await;
+        let denied = log(&db, false, None).await;
+
+        assert!(matches!(
+            repo.create(9999, "guarda-07", "Motivo").await,
+            Err(StorageError::NotFound { .. })
+        ));
+        assert!(matches!(
+            repo.create(granted, "guarda-07", "Motivo").await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.create(denied, "  ", "Motivo").await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.create(denied, "guarda-07", &"m".repeat(101)).await,
+            Err(StorageError::Validation(_))
+        ));
+
+        repo.create(denied, "guarda-07", "Motivo").await.unwrap();
+        assert!(matches!(
+            repo.create(denied, "guarda-08", "Outro motivo").await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_operator_and_grant_message() {
+        let (db, repo) = setup().await;
+        let since = Utc::now() - chrono::Duration::minutes(1);
+        let first = log(&db, false, None).await;
+        let second = log(&db, false, None).await;
+
+        let outcome = repo.create(first, "guarda-07", "Motivo A").await.unwrap();
+        repo.create(second, "guarda-08", "Motivo B").await.unwrap();
+
+        let records = repo.find_by_operator("guarda-07", since).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].denied_log_id, first);
+
+        let message = outcome.grant_message(DeviceId::new(15).unwrap()).unwrap();
+        assert_eq!(message.command, CommandCode::OverrideGrant);
+        let grant = OverrideGrant::parse(&message).unwrap();
+        assert_eq!(grant.card_number, "00000000000033734544");
+        assert_eq!(grant.operator_id, "guarda-07");
+        assert_eq!(grant.reason, "Motivo A");
+    }
+}
@@ -0,0 +1,139 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::UserAntiPassback;
+use sqlx::SqlitePool;
+
+/// Repository trait for per-user anti-passback windows
+///
+/// A user's window replaces the window of every device the user passes
+/// through (see [`AntiPassbackPolicy`](crate::models::AntiPassbackPolicy)).
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait AntiPassbackRepository: Send + Sync {
+    /// Set the window of a user, replacing any previous one
+    ///
+    /// A window of 0 seconds disables anti-passback for the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if `window_seconds` is negative.
+    async fn set_user_window(&self, user_id: i64, window_seconds: i64) -> StorageResult<()>;
+
+    /// Remove the window of a user; returns `false` if there was none
+    async fn remove_user_window(&self, user_id: i64) -> StorageResult<bool>;
+
+    /// Get the window of a user in seconds, `None` if the user has none
+    async fn find_user_window(&self, user_id: i64) -> StorageResult<Option<i64>>;
+
+    /// Get all user windows, by user ID
+    async fn find_all(&self) -> StorageResult<Vec<UserAntiPassback>>;
+}
+
+/// SQLite implementation of AntiPassbackRepository
+pub struct SqliteAntiPassbackRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteAntiPassbackRepository {
+    /// Create a new SQLite anti-passback repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl AntiPassbackRepository for SqliteAntiPassbackRepository {
+    async fn set_user_window(&self, user_id: i64, window_seconds: i64) -> StorageResult<()> {
+        if window_seconds < 0 {
+            return Err(StorageError::Validation(format!(
+                "Anti-passback window cannot be negative, got {} seconds",
+                window_seconds
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_anti_passback (user_id, window_seconds) VALUES (?, ?)
+            ON CONFLICT (user_id) DO UPDATE SET
+                window_seconds = excluded.window_seconds,
+                updated_at = datetime('now')
+            "#,
+            user_id,
+            window_seconds
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_user_window(&self, user_id: i64) -> StorageResult<bool> {
+        let result = sqlx::query!("DELETE FROM user_anti_passback WHERE user_id = ?", user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_user_window(&self, user_id: i64) -> StorageResult<Option<i64>> {
+        let window: Option<i64> = sqlx::query_scalar!(
+            "SELECT window_seconds FROM user_anti_passback WHERE user_id = ?",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(window)
+    }
+
+    async fn find_all(&self) -> StorageResult<Vec<UserAntiPassback>> {
+        let windows = sqlx::query_as!(
+            UserAntiPassback,
+            r#"
+            SELECT user_id AS "user_id!", window_seconds, created_at AS "created_at: _",
+                   updated_at AS "updated_at: _"
+            FROM user_anti_passback
+            ORDER BY user_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(windows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    #[tokio::test]
+    async fn test_user_window_set_replace_remove() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteAntiPassbackRepository::new(db.pool().clone());
+
+        assert_eq!(repo.find_user_window(1).await.unwrap(), None);
+
+        repo.set_user_window(1, 600).await.unwrap();
+        repo.set_user_window(1, 0).await.unwrap();
+        assert_eq!(repo.find_user_window(1).await.unwrap(), Some(0));
+
+        let all = repo.find_all().await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].window(), None);
+
+        assert!(repo.remove_user_window(1).await.unwrap());
+        assert!(!repo.remove_user_window(1).await.unwrap());
+        assert_eq!(repo.find_user_window(1).await.unwrap(), None);
+
+        assert!(matches!(
+            repo.set_user_window(1, -1).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{BiometricTemplate, MAX_TEMPLATE_SIZE, MIN_TEMPLATE_SIZE};
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+/// Repository trait for BiometricTemplate entity operations
+///
+/// Holds the enrolled fingerprints used to confirm the card owner on
+/// card + biometric devices (see
+/// [`VerificationMode`](crate::models::VerificationMode)).
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait BiometricTemplateRepository: Send + Sync {
+    /// Store the template of one finger of a user, replacing any template
+    /// already enrolled for that finger. Returns the template ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the position is outside 0-9 or the template
+    /// size is outside [`MIN_TEMPLATE_SIZE`]..=[`MAX_TEMPLATE_SIZE`],
+    /// `NotFound` if no user has the matricula, and `Database` if the user
+    /// does not have `allow_bio` enabled.
+    async fn save(&self, matricula: &str, posicao: i64, template: &[u8]) -> StorageResult<i64>;
+
+    /// Get the templates of a user, ordered by finger position
+    async fn find_by_matricula(&self, matricula: &str) -> StorageResult<Vec<BiometricTemplate>>;
+
+    /// Remove every template of a user, returning how many were removed
+    async fn delete_by_matricula(&self, matricula: &str) -> StorageResult<u64>;
+}
+
+/// SQLite implementation of BiometricTemplateRepository
+pub struct SqliteBiometricTemplateRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteBiometricTemplateRepository {
+    /// Create a new SQLite biometric template repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl BiometricTemplateRepository for SqliteBiometricTemplateRepository {
+    async fn save(&self, matricula: &str, posicao: i64, template: &[u8]) -> StorageResult<i64> {
+        if !(0..=9).contains(&posicao) {
+            return Err(StorageError::Validation(format!(
+                "Finger position must be between 0 and 9, got {}",
+                posicao
+            )));
+        }
+        if !(MIN_TEMPLATE_SIZE..=MAX_TEMPLATE_SIZE).contains(&template.len()) {
+            return Err(StorageError::Validation(format!(
+                "Template must be {} to {} bytes, got {}",
+                MIN_TEMPLATE_SIZE,
+                MAX_TEMPLATE_SIZE,
+                template.len()
+            )));
+        }
+
+        let user_id = sqlx::query_scalar!(
+            r#"SELECT id AS "id!" FROM users WHERE matricula = ?"#,
+            matricula
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(user_id) = user_id else {
+            return Err(StorageError::NotFound {
+                entity_type: "User".to_string(),
+                field: "matricula".to_string(),
+                value: matricula.to_string(),
+            });
+        };
+
+        let now = Utc::now();
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO biometric_templates (
+                matricula, user_id, posicao, template_data, created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (matricula, posicao) DO UPDATE SET
+                template_data = excluded.template_data,
+                updated_at = excluded.updated_at
+            RETURNING id AS "id!"
+            "#,
+            matricula,
+            user_id,
+            posicao,
+            template,
+            now,
+            now
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn find_by_matricula(&self, matricula: &str) -> StorageResult<Vec<BiometricTemplate>> {
+        let templates = sqlx::query_as!(
+            BiometricTemplate,
+            r#"
+            SELECT id AS "id!", matricula, user_id, posicao, template_data AS "template_data: _",
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM biometric_templates
+            WHERE matricula = ?
+            ORDER BY posicao
+            "#,
+            matricula
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(templates)
+    }
+
+    async fn delete_by_matricula(&self, matricula: &str) -> StorageResult<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM biometric_templates WHERE matricula = ?",
+            matricula
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::User;
+    use crate::repositories::{SqliteUserRepository, UserRepository};
+
+    async fn create_test_user(db: &Database, matricula: &str, allow_bio: bool) {
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Bio Test".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: true,
+            allow_bio,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteUserRepository::new(db.pool().clone())
+            .create(&user)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_replaces_finger_and_lists_by_position() {
+        let db = Database::in_memory().await.unwrap();
+        create_test_user(&db, "BIO001", true).await;
+        let repo = SqliteBiometricTemplateRepository::new(db.pool().clone());
+
+        repo.save("BIO001", 6, &[6u8; 600]).await.unwrap();
+        let first = repo.save("BIO001", 1, &[1u8; 600]).await.unwrap();
+        let replaced = repo.save("BIO001", 1, &[9u8; 800]).await.unwrap();
+        assert_eq!(first, replaced);
+
+        let templates = repo.find_by_matricula("BIO001").await.unwrap();
+        let positions: Vec<i64> = templates.iter().map(|t| t.posicao).collect();
+        assert_eq!(positions, vec![1, 6]);
+        assert_eq!(templates[0].template_data, vec![9u8; 800]);
+
+        assert_eq!(repo.delete_by_matricula("BIO001").await.unwrap(), 2);
+        assert!(repo.find_by_matricula("BIO001").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_rejects_invalid_templates() {
+        let db = Database::in_memory().await.unwrap();
+        create_test_user(&db, "BIO002", false).await;
+        let repo = SqliteBiometricTemplateRepository::new(db.pool().clone());
+
+        assert!(matches!(
+            repo.save("BIO002", 10, &[0u8; 600]).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.save("BIO002", 0, &[0u8; 100]).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.save("NOBODY", 0, &[0u8; 600]).await,
+            Err(StorageError::NotFound { .. })
+        ));
+        // allow_bio is disabled for this user
+        assert!(repo.save("BIO002", 0, &[0u8; 600]).await.is_err());
+    }
+}
@@ -1,8 +1,13 @@
 #![allow(async_fn_in_trait)]
 
+use crate::card_filter::CardFilter;
 use crate::error::{StorageError, StorageResult};
 use crate::models::Card;
+use crate::pagination::{Page, PageRequest};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use sqlx::SqlitePool;
+use std::sync::Arc;
 
 /// Repository trait for Card entity operations
 ///
@@ -38,32 +43,58 @@ pub trait CardRepository: Send + Sync {
 
     /// Check if a card number already exists
     async fn exists_by_number(&self, numero_cartao: &str) -> StorageResult<bool>;
+
+    /// Fetch one page of cards ordered by ID (keyset pagination)
+    async fn find_page(&self, page: PageRequest) -> StorageResult<Page<Card>>;
+
+    /// Stream all cards ordered by ID without loading them into memory
+    fn stream_all(&self) -> BoxStream<'_, StorageResult<Card>>;
 }
 
 /// SQLite implementation of CardRepository
 pub struct SqliteCardRepository {
     pool: SqlitePool,
+    card_filter: Option<Arc<CardFilter>>,
 }
 
 impl SqliteCardRepository {
     /// Create a new SQLite card repository
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            card_filter: None,
+        }
+    }
+
+    /// Add the cards this repository creates or renumbers to `filter`
+    ///
+    /// The validator sharing the filter recognizes them right away instead
+    /// of after the filter's next [`sync()`](CardFilter::sync).
+    pub fn with_card_filter(mut self, filter: Arc<CardFilter>) -> Self {
+        self.card_filter = Some(filter);
+        self
+    }
+
+    fn register_in_filter(&self, card: &Card) {
+        if let Some(filter) = &self.card_filter {
+            filter.insert(&card.numero_cartao);
+        }
     }
 }
 
 impl CardRepository for SqliteCardRepository {
     async fn find_by_number(&self, numero_cartao: &str) -> StorageResult<Option<Card>> {
-        let card = sqlx::query_as::<_, Card>(
+        let card = sqlx::query_as!(
+            Card,
             r#"
-            SELECT id, numero_cartao, matricula, user_id,
-                   validade_inicio, validade_fim, ativo,
-                   created_at, updated_at
+            SELECT id AS "id!", numero_cartao, matricula, user_id,
+                   validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                   ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM cards
             WHERE numero_cartao = ?
             "#,
+            numero_cartao
         )
-        .bind(numero_cartao)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -71,17 +102,18 @@ impl CardRepository for SqliteCardRepository {
     }
 
     async fn find_by_matricula(&self, matricula: &str) -> StorageResult<Vec<Card>> {
-        let cards = sqlx::query_as::<_, Card>(
+        let cards = sqlx::query_as!(
+            Card,
             r#"
-            SELECT id, numero_cartao, matricula, user_id,
-                   validade_inicio, validade_fim, ativo,
-                   created_at, updated_at
+            SELECT id AS "id!", numero_cartao, matricula, user_id,
+                   validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                   ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM cards
             WHERE matricula = ?
             ORDER BY created_at DESC
             "#,
+            matricula
         )
-        .bind(matricula)
         .fetch_all(&self.pool)
         .await?;
 
@@ -89,17 +121,18 @@ impl CardRepository for SqliteCardRepository {
     }
 
     async fn find_by_user_id(&self, user_id: i64) -> StorageResult<Vec<Card>> {
-        let cards = sqlx::query_as::<_, Card>(
+        let cards = sqlx::query_as!(
+            Card,
             r#"
-            SELECT id, numero_cartao, matricula, user_id,
-                   validade_inicio, validade_fim, ativo,
-                   created_at, updated_at
+            SELECT id AS "id!", numero_cartao, matricula, user_id,
+                   validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                   ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM cards
             WHERE user_id = ?
             ORDER BY created_at DESC
             "#,
+            user_id
         )
-        .bind(user_id)
         .fetch_all(&self.pool)
         .await?;
 
@@ -107,15 +140,16 @@ impl CardRepository for SqliteCardRepository {
     }
 
     async fn find_all_active(&self) -> StorageResult<Vec<Card>> {
-        let cards = sqlx::query_as::<_, Card>(
+        let cards = sqlx::query_as!(
+            Card,
             r#"
-            SELECT id, numero_cartao, matricula, user_id,
-                   validade_inicio, validade_fim, ativo,
-                   created_at, updated_at
+            SELECT id AS "id!", numero_cartao, matricula, user_id,
+                   validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                   ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM cards
             WHERE ativo = 1
             ORDER BY created_at DESC
-            "#,
+            "#
         )
         .fetch_all(&self.pool)
         .await?;
@@ -124,7 +158,7 @@ impl CardRepository for SqliteCardRepository {
     }
 
     async fn create(&self, card: &Card) -> StorageResult<i64> {
-        let result = sqlx::query(
+        let result = sqlx::query!(
             r#"
             INSERT INTO cards (
                 numero_cartao, matricula, user_id,
@@ -132,21 +166,22 @@ impl CardRepository for SqliteCardRepository {
             )
             VALUES (?, ?, ?, ?, ?, ?)
             "#,
+            card.numero_cartao,
+            card.matricula,
+            card.user_id,
+            card.validade_inicio,
+            card.validade_fim,
+            card.ativo
         )
-        .bind(&card.numero_cartao)
-        .bind(&card.matricula)
-        .bind(card.user_id)
-        .bind(card.validade_inicio)
-        .bind(card.validade_fim)
-        .bind(card.ativo)
         .execute(&self.pool)
         .await?;
+        self.register_in_filter(card);
 
         Ok(result.last_insert_rowid())
     }
 
     async fn update(&self, card: &Card) -> StorageResult<()> {
-        let result = sqlx::query(
+        let result = sqlx::query!(
             r#"
             UPDATE cards
             SET numero_cartao = ?, matricula = ?, user_id = ?,
@@ -154,14 +189,14 @@ impl CardRepository for SqliteCardRepository {
                 updated_at = datetime('now')
             WHERE id = ?
             "#,
+            card.numero_cartao,
+            card.matricula,
+            card.user_id,
+            card.validade_inicio,
+            card.validade_fim,
+            card.ativo,
+            card.id
         )
-        .bind(&card.numero_cartao)
-        .bind(&card.matricula)
-        .bind(card.user_id)
-        .bind(card.validade_inicio)
-        .bind(card.validade_fim)
-        .bind(card.ativo)
-        .bind(card.id)
         .execute(&self.pool)
         .await?;
 
@@ -172,13 +207,13 @@ impl CardRepository for SqliteCardRepository {
                 value: card.id.to_string(),
             });
         }
+        self.register_in_filter(card);
 
         Ok(())
     }
 
     async fn delete(&self, id: i64) -> StorageResult<()> {
-        let result = sqlx::query("DELETE FROM cards WHERE id = ?")
-            .bind(id)
+        let result = sqlx::query!("DELETE FROM cards WHERE id = ?", id)
             .execute(&self.pool)
             .await?;
 
@@ -194,12 +229,52 @@ impl CardRepository for SqliteCardRepository {
     }
 
     async fn exists_by_number(&self, numero_cartao: &str) -> StorageResult<bool> {
-        let result: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM cards WHERE numero_cartao = ?")
-            .bind(numero_cartao)
-            .fetch_one(&self.pool)
-            .await?;
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM cards WHERE numero_cartao = ?",
+            numero_cartao
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    async fn find_page(&self, page: PageRequest) -> StorageResult<Page<Card>> {
+        let after_id = page.id_lower_bound();
+        let rows = sqlx::query_as!(
+            Card,
+            r#"
+            SELECT id AS "id!", numero_cartao, matricula, user_id,
+                   validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                   ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM cards
+            WHERE id > ?
+            ORDER BY id
+            LIMIT ?
+            "#,
+            after_id,
+            page.page_size
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
-        Ok(result.0 > 0)
+        Ok(Page::from_rows(rows, page, |row| row.id))
+    }
+
+    fn stream_all(&self) -> BoxStream<'_, StorageResult<Card>> {
+        sqlx::query_as!(
+            Card,
+            r#"
+            SELECT id AS "id!", numero_cartao, matricula, user_id,
+                   validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                   ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM cards
+            ORDER BY id
+            "#
+        )
+        .fetch(&self.pool)
+        .map_err(StorageError::from)
+        .boxed()
     }
 }
 
@@ -229,6 +304,7 @@ mod tests {
             allow_bio: false,
             allow_keypad: false,
             codigo: None,
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -357,4 +433,39 @@ mod tests {
         assert!(repo.exists_by_number("7777777777").await.unwrap());
         assert!(!repo.exists_by_number("9999999999").await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_find_page_and_stream_all_cards() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP008").await;
+
+        let repo = SqliteCardRepository::new(db.pool().clone());
+        for i in 0..4 {
+            let card = create_test_card(&format!("880000000{i}"), "EMP008", user_id);
+            repo.create(&card).await.unwrap();
+        }
+
+        let first = repo.find_page(PageRequest::first(3)).await.unwrap();
+        assert_eq!(first.items.len(), 3);
+        assert!(first.has_more());
+
+        let second = repo.find_page(first.next_page().unwrap()).await.unwrap();
+        assert!(second.items[0].id > first.items[2].id);
+
+        let streamed: Vec<Card> = repo.stream_all().try_collect().await.unwrap();
+        let mut paged_ids = Vec::new();
+        let mut request = Some(PageRequest::first(3));
+        while let Some(page_request) = request {
+            let page = repo.find_page(page_request).await.unwrap();
+            request = page.next_page();
+            paged_ids.extend(page.items.iter().map(|c| c.id));
+        }
+
+        let streamed_ids: Vec<i64> = streamed.iter().map(|c| c.id).collect();
+        assert_eq!(streamed_ids, paged_ids);
+        assert_eq!(
+            streamed.iter().filter(|c| c.matricula == "EMP008").count(),
+            4
+        );
+    }
 }
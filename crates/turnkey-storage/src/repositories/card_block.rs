@@ -0,0 +1,473 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{
+    BlockAction, BlockReason, BulkBlockOutcome, Card, CardBlockBatch, CardSelector,
+};
+use chrono::Utc;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
+
+/// Repository trait for bulk card block/unblock operations
+///
+/// Each operation runs in a single transaction: the matching cards change
+/// state, and an audit batch with reason code and operator is recorded
+/// together with the list of affected cards.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait CardBlockRepository: Send + Sync {
+    /// Deactivate every active card matching the selector
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` for an empty selector or operator.
+    async fn block(
+        &self,
+        selector: &CardSelector,
+        reason: BlockReason,
+        operator: &str,
+    ) -> StorageResult<BulkBlockOutcome>;
+
+    /// Reactivate every inactive card matching the selector
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` for an empty selector or operator.
+    async fn unblock(
+        &self,
+        selector: &CardSelector,
+        reason: BlockReason,
+        operator: &str,
+    ) -> StorageResult<BulkBlockOutcome>;
+
+    /// Find an audit batch by ID
+    async fn find_batch(&self, id: i64) -> StorageResult<Option<CardBlockBatch>>;
+
+    /// Card numbers changed by a batch
+    async fn find_batch_cards(&self, batch_id: i64) -> StorageResult<Vec<String>>;
+
+    /// Block/unblock history of a card, oldest first
+    async fn find_history(&self, numero_cartao: &str) -> StorageResult<Vec<CardBlockBatch>>;
+}
+
+/// SQLite implementation of CardBlockRepository
+pub struct SqliteCardBlockRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteCardBlockRepository {
+    /// Create a new SQLite card block repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn apply(
+        &self,
+        action: BlockAction,
+        selector: &CardSelector,
+        reason: BlockReason,
+        operator: &str,
+    ) -> StorageResult<BulkBlockOutcome> {
+        selector.validate()?;
+        let operator = operator.trim();
+        if operator.is_empty() {
+            return Err(StorageError::Validation(
+                "Operator is required for bulk card operations".to_string(),
+            ));
+        }
+
+        let target_state = action == BlockAction::Unblock;
+        let mut tx = self.pool.begin().await?;
+
+        let mut cards = Self::select_cards(&mut tx, selector, !target_state).await?;
+        let now = Utc::now();
+        let (action_code, reason_code) = (action.as_str(), reason.as_str());
+        let selector_text = selector.to_string();
+        let card_count = cards.len() as i64;
+
+        let batch_id: i64 = sqlx::query_scalar!(
+            r#"
+            INSERT INTO card_block_batches (
+                action, reason_code, selector, operator, card_count, created_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id
+            "#,
+            action_code,
+            reason_code,
+            selector_text,
+            operator,
+            card_count,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for card in &mut cards {
+            sqlx::query!(
+                "UPDATE cards SET ativo = ? WHERE id = ?",
+                target_state,
+                card.id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO card_block_events (batch_id, card_id, numero_cartao, matricula)
+                VALUES (?, ?, ?, ?)
+                "#,
+                batch_id,
+                card.id,
+                card.numero_cartao,
+                card.matricula
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            card.ativo = target_state;
+            card.updated_at = now;
+        }
+
+        let batch = sqlx::query_as!(
+            CardBlockBatch,
+            r#"
+            SELECT id AS "id!", action, reason_code, selector, operator, card_count,
+                   created_at AS "created_at: _"
+            FROM card_block_batches
+            WHERE id = ?
+            "#,
+            batch_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(BulkBlockOutcome { batch, cards })
+    }
+
+    /// Cards matching the selector that are currently in `ativo` state
+    async fn select_cards(
+        tx: &mut Transaction<'_, Sqlite>,
+        selector: &CardSelector,
+        ativo: bool,
+    ) -> StorageResult<Vec<Card>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT c.id, c.numero_cartao, c.matricula, c.user_id,
+                   c.validade_inicio, c.validade_fim, c.ativo,
+                   c.created_at, c.updated_at
+            FROM cards c
+            "#,
+        );
+
+        match selector {
+            CardSelector::MatriculaPrefix(prefix) => {
+                let prefix = prefix.trim();
+                query
+                    .push("WHERE substr(c.matricula, 1, ")
+                    .push_bind(prefix.chars().count() as i64)
+                    .push(") = ")
+                    .push_bind(prefix.to_string());
+            }
+            CardSelector::Company(company) => {
+                query
+                    .push("JOIN users u ON u.id = c.user_id WHERE u.empresa = ")
+                    .push_bind(company.trim().to_string())
+                    .push(" COLLATE NOCASE");
+            }
+            CardSelector::CardNumbers(numbers) => {
+                query.push("WHERE c.numero_cartao IN (");
+                let mut separated = query.separated(", ");
+                for number in numbers.iter().filter(|n| !n.trim().is_empty()) {
+                    separated.push_bind(Card::normalize_card_number(number));
+                }
+                separated.push_unseparated(")");
+            }
+        }
+
+        query
+            .push(" AND c.ativo = ")
+            .push_bind(ativo)
+            .push(" ORDER BY c.id");
+
+        let cards = query.build_query_as::<Card>().fetch_all(&mut **tx).await?;
+
+        Ok(cards)
+    }
+}
+
+impl CardBlockRepository for SqliteCardBlockRepository {
+    async fn block(
+        &self,
+        selector: &CardSelector,
+        reason: BlockReason,
+        operator: &str,
+    ) -> StorageResult<BulkBlockOutcome> {
+        self.apply(BlockAction::Block, selector, reason, operator)
+            .await
+    }
+
+    async fn unblock(
+        &self,
+        selector: &CardSelector,
+        reason: BlockReason,
+        operator: &str,
+    ) -> StorageResult<BulkBlockOutcome> {
+        self.apply(BlockAction::Unblock, selector, reason, operator)
+            .await
+    }
+
+    async fn find_batch(&self, id: i64) -> StorageResult<Option<CardBlockBatch>> {
+        let batch = sqlx::query_as!(
+            CardBlockBatch,
+            r#"
+            SELECT id AS "id!", action, reason_code, selector, operator, card_count,
+                   created_at AS "created_at: _"
+            FROM card_block_batches
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(batch)
+    }
+
+    async fn find_batch_cards(&self, batch_id: i64) -> StorageResult<Vec<String>> {
+        let numbers = sqlx::query_scalar!(
+            r#"
+            SELECT numero_cartao
+            FROM card_block_events
+            WHERE batch_id = ?
+            ORDER BY id
+            "#,
+            batch_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(numbers)
+    }
+
+    async fn find_history(&self, numero_cartao: &str) -> StorageResult<Vec<CardBlockBatch>> {
+        let numero_cartao = Card::normalize_card_number(numero_cartao);
+        let batches = sqlx::query_as!(
+            CardBlockBatch,
+            r#"
+            SELECT b.id AS "id!", b.action, b.reason_code, b.selector, b.operator,
+                   b.card_count, b.created_at AS "created_at: _"
+            FROM card_block_batches b
+            JOIN card_block_events e ON e.batch_id = b.id
+            WHERE e.numero_cartao = ?
+            ORDER BY b.id
+            "#,
+            numero_cartao
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(batches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::User;
+    use crate::repositories::{
+        CardRepository, SqliteCardRepository, SqliteUserRepository, UserRepository,
+    };
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn create_user_with_cards(
+        db: &Database,
+        matricula: &str,
+        empresa: Option<&str>,
+        cards: &[&str],
+    ) {
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Bulk Test".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: true,
+            allow_bio: false,
+            allow_keypad: false,
+            codigo: None,
+            empresa: empresa.map(str::to_string),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let user_id = SqliteUserRepository::new(db.pool().clone())
+            .create(&user)
+            .await
+            .unwrap();
+
+        let card_repo = SqliteCardRepository::new(db.pool().clone());
+        for numero in cards {
+            let card = Card {
+                id: 0,
+                numero_cartao: numero.to_string(),
+                matricula: matricula.to_string(),
+                user_id,
+                validade_inicio: None,
+                validade_fim: None,
+                ativo: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+            card_repo.create(&card).await.unwrap();
+        }
+    }
+
+    async fn is_active(db: &Database, numero: &str) -> bool {
+        SqliteCardRepository::new(db.pool().clone())
+            .find_by_number(numero)
+            .await
+            .unwrap()
+            .unwrap()
+            .ativo
+    }
+
+    #[tokio::test]
+    async fn test_block_by_company() {
+        let db = setup_test_db().await;
+        create_user_with_cards(
+            &db,
+            "CTR001",
+            Some("Acme Servicos"),
+            &["7000001", "7000002"],
+        )
+        .await;
+        create_user_with_cards(&db, "CTR002", Some("ACME SERVICOS"), &["7000003"]).await;
+        create_user_with_cards(&db, "EMP900", Some("Outra"), &["7000004"]).await;
+
+        let repo = SqliteCardBlockRepository::new(db.pool().clone());
+        let outcome = repo
+            .block(
+                &CardSelector::Company("acme servicos".to_string()),
+                BlockReason::ContractEnded,
+                "security.ana",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.cards.len(), 3);
+        assert!(outcome.cards.iter().all(|c| !c.ativo));
+        assert_eq!(outcome.batch.card_count, 3);
+        assert_eq!(outcome.batch.operator, "security.ana");
+        assert_eq!(
+            BlockReason::parse(&outcome.batch.reason_code),
+            Some(BlockReason::ContractEnded)
+        );
+
+        assert!(!is_active(&db, "7000001").await);
+        assert!(!is_active(&db, "7000003").await);
+        assert!(is_active(&db, "7000004").await);
+
+        let numbers = repo.find_batch_cards(outcome.batch.id).await.unwrap();
+        assert_eq!(numbers, vec!["7000001", "7000002", "7000003"]);
+    }
+
+    #[tokio::test]
+    async fn test_block_by_matricula_prefix_skips_already_blocked() {
+        let db = setup_test_db().await;
+        create_user_with_cards(&db, "TMP001", None, &["7100001"]).await;
+        create_user_with_cards(&db, "TMP002", None, &["7100002"]).await;
+        create_user_with_cards(&db, "XTMP03", None, &["7100003"]).await;
+
+        let repo = SqliteCardBlockRepository::new(db.pool().clone());
+        let selector = CardSelector::MatriculaPrefix("TMP".to_string());
+
+        let first = repo
+            .block(&selector, BlockReason::Suspended, "op")
+            .await
+            .unwrap();
+        assert_eq!(first.cards.len(), 2);
+        assert!(is_active(&db, "7100003").await);
+
+        let second = repo
+            .block(&selector, BlockReason::Suspended, "op")
+            .await
+            .unwrap();
+        assert!(second.cards.is_empty());
+        assert_eq!(second.batch.card_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unblock_card_list_and_history() {
+        let db = setup_test_db().await;
+        create_user_with_cards(&db, "LST001", None, &["AB7200001", "AB7200002"]).await;
+
+        let repo = SqliteCardBlockRepository::new(db.pool().clone());
+        let selector =
+            CardSelector::CardNumbers(vec!["ab7200001".to_string(), " AB7200002 ".to_string()]);
+
+        repo.block(&selector, BlockReason::Lost, "op.block")
+            .await
+            .unwrap();
+        let outcome = repo
+            .unblock(
+                &CardSelector::CardNumbers(vec!["AB7200001".to_string()]),
+                BlockReason::Reinstated,
+                "op.unblock",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.cards.len(), 1);
+        assert!(outcome.cards[0].ativo);
+        assert!(is_active(&db, "AB7200001").await);
+        assert!(!is_active(&db, "AB7200002").await);
+
+        let history = repo.find_history("ab7200001").await.unwrap();
+        let actions: Vec<_> = history
+            .iter()
+            .map(|b| BlockAction::parse(&b.action).unwrap())
+            .collect();
+        assert_eq!(actions, vec![BlockAction::Block, BlockAction::Unblock]);
+        assert_eq!(history[1].operator, "op.unblock");
+
+        let found = repo.find_batch(outcome.batch.id).await.unwrap().unwrap();
+        assert_eq!(found, outcome.batch);
+    }
+
+    #[tokio::test]
+    async fn test_validation_rejects_empty_selector_and_operator() {
+        let db = setup_test_db().await;
+        let repo = SqliteCardBlockRepository::new(db.pool().clone());
+
+        assert!(matches!(
+            repo.block(
+                &CardSelector::MatriculaPrefix(String::new()),
+                BlockReason::Other,
+                "op"
+            )
+            .await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.block(
+                &CardSelector::Company("Acme".to_string()),
+                BlockReason::Other,
+                "  "
+            )
+            .await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,774 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{
+    ConfigProfile, ConfigSettings, DeviceConfig, FeedbackPattern, MAX_PROFILE_DEPTH, ResolvedConfig,
+};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use turnkey_core::ValidationMode;
+
+/// Repository trait for device configuration profiles
+///
+/// Profiles hold shared device settings and may inherit from a parent
+/// profile; devices are assigned to a profile and may override single
+/// settings. [`resolve()`](Self::resolve) merges the levels into the
+/// [`ResolvedConfig`] sent to the device (see
+/// [`config_sync`](crate::config_sync)).
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait ConfigProfileRepository: Send + Sync {
+    /// Create a profile
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the name or a setting is invalid and
+    /// `NotFound` if the parent profile does not exist.
+    async fn create(&self, profile: &ConfigProfile) -> StorageResult<i64>;
+
+    /// Find a profile by its ID
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<ConfigProfile>>;
+
+    /// Find a profile by its unique name
+    async fn find_by_name(&self, name: &str) -> StorageResult<Option<ConfigProfile>>;
+
+    /// Get all profiles, ordered by name
+    async fn find_all(&self) -> StorageResult<Vec<ConfigProfile>>;
+
+    /// Replace the name, parent and settings of a profile
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if a value is invalid or the new parent would
+    /// make the profile inherit from itself, and `NotFound` if the profile
+    /// or its parent does not exist.
+    async fn update(&self, profile: &ConfigProfile) -> StorageResult<()>;
+
+    /// Delete a profile
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReferentialIntegrity` while other profiles inherit from it
+    /// or devices are assigned to it, and `NotFound` if it does not exist.
+    async fn delete(&self, id: i64) -> StorageResult<()>;
+
+    /// Assign a device to a profile (or back to the defaults with `None`)
+    ///
+    /// The device is created with default settings if it is not known yet.
+    /// Its overrides are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range and `NotFound`
+    /// if the profile does not exist.
+    async fn assign_device(&self, device_id: i64, profile_id: Option<i64>) -> StorageResult<()>;
+
+    /// Replace the settings a device overrides on top of its profile
+    ///
+    /// Empty settings remove every override.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID or a setting is invalid.
+    async fn set_overrides(&self, device_id: i64, overrides: &ConfigSettings) -> StorageResult<()>;
+
+    /// Get the profile assignment and overrides of a device
+    async fn find_device_config(&self, device_id: i64) -> StorageResult<Option<DeviceConfig>>;
+
+    /// Profiles a profile inherits from, from the root down to itself
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the profile does not exist and `Validation` if
+    /// the chain is longer than [`MAX_PROFILE_DEPTH`].
+    async fn chain(&self, id: i64) -> StorageResult<Vec<ConfigProfile>>;
+
+    /// Effective settings of a device
+    ///
+    /// Devices without an assignment get the built-in defaults.
+    async fn resolve(&self, device_id: i64) -> StorageResult<ResolvedConfig>;
+
+    /// Devices whose settings depend on a profile, ordered by device ID
+    ///
+    /// Lists devices assigned to the profile or to any profile inheriting
+    /// from it: the devices to sync after the profile changes.
+    async fn find_devices_using(&self, id: i64) -> StorageResult<Vec<i64>>;
+}
+
+/// Settings columns shared by `config_profiles` and `device_configs`
+struct SettingsColumns {
+    online_timeout_ms: Option<i64>,
+    validation_mode: Option<String>,
+    entry_message: Option<String>,
+    exit_message: Option<String>,
+    message_secs: Option<i64>,
+    volume: Option<i64>,
+    feedback: Option<FeedbackPattern>,
+}
+
+impl SettingsColumns {
+    fn from_settings(settings: &ConfigSettings) -> Self {
+        Self {
+            online_timeout_ms: settings.online_timeout_ms.map(|ms| ms as i64),
+            validation_mode: settings
+                .validation_mode
+                .map(|mode| mode.to_char().to_string()),
+            entry_message: settings.entry_message.clone(),
+            exit_message: settings.exit_message.clone(),
+            message_secs: settings.message_secs.map(i64::from),
+            volume: settings.volume.map(i64::from),
+            feedback: settings.feedback,
+        }
+    }
+
+    fn into_settings(self) -> StorageResult<ConfigSettings> {
+        let invalid = |what: &str, value: &dyn std::fmt::Display| {
+            StorageError::Validation(format!("Stored {} '{}' is invalid", what, value))
+        };
+        let validation_mode = match self.validation_mode {
+            Some(code) => {
+                let mut chars = code.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(
+                        ValidationMode::from_char(c)
+                            .map_err(|_| invalid("validation mode", &code))?,
+                    ),
+                    _ => return Err(invalid("validation mode", &code)),
+                }
+            }
+            None => None,
+        };
+
+        Ok(ConfigSettings {
+            online_timeout_ms: self
+                .online_timeout_ms
+                .map(|ms| u64::try_from(ms).map_err(|_| invalid("online timeout", &ms)))
+                .transpose()?,
+            validation_mode,
+            entry_message: self.entry_message,
+            exit_message: self.exit_message,
+            message_secs: self
+                .message_secs
+                .map(|secs| u8::try_from(secs).map_err(|_| invalid("message time", &secs)))
+                .transpose()?,
+            volume: self
+                .volume
+                .map(|volume| u8::try_from(volume).map_err(|_| invalid("volume", &volume)))
+                .transpose()?,
+            feedback: self.feedback,
+        })
+    }
+}
+
+struct ProfileRow {
+    id: i64,
+    name: String,
+    parent_id: Option<i64>,
+    online_timeout_ms: Option<i64>,
+    validation_mode: Option<String>,
+    entry_message: Option<String>,
+    exit_message: Option<String>,
+    message_secs: Option<i64>,
+    volume: Option<i64>,
+    feedback: Option<FeedbackPattern>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl ProfileRow {
+    fn into_profile(self) -> StorageResult<ConfigProfile> {
+        let settings = SettingsColumns {
+            online_timeout_ms: self.online_timeout_ms,
+            validation_mode: self.validation_mode,
+            entry_message: self.entry_message,
+            exit_message: self.exit_message,
+            message_secs: self.message_secs,
+            volume: self.volume,
+            feedback: self.feedback,
+        }
+        .into_settings()?;
+
+        Ok(ConfigProfile {
+            id: self.id,
+            name: self.name,
+            parent_id: self.parent_id,
+            settings,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+struct DeviceConfigRow {
+    device_id: i64,
+    profile_id: Option<i64>,
+    online_timeout_ms: Option<i64>,
+    validation_mode: Option<String>,
+    entry_message: Option<String>,
+    exit_message: Option<String>,
+    message_secs: Option<i64>,
+    volume: Option<i64>,
+    feedback: Option<FeedbackPattern>,
+}
+
+impl DeviceConfigRow {
+    fn into_config(self) -> StorageResult<DeviceConfig> {
+        let overrides = SettingsColumns {
+            online_timeout_ms: self.online_timeout_ms,
+            validation_mode: self.validation_mode,
+            entry_message: self.entry_message,
+            exit_message: self.exit_message,
+            message_secs: self.message_secs,
+            volume: self.volume,
+            feedback: self.feedback,
+        }
+        .into_settings()?;
+
+        Ok(DeviceConfig {
+            device_id: self.device_id,
+            profile_id: self.profile_id,
+            overrides,
+        })
+    }
+}
+
+/// SQLite implementation of ConfigProfileRepository
+pub struct SqliteConfigProfileRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteConfigProfileRepository {
+    /// Create a new SQLite configuration profile repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn not_found(id: i64) -> StorageError {
+        StorageError::NotFound {
+            entity_type: "ConfigProfile".to_string(),
+            field: "id".to_string(),
+            value: id.to_string(),
+        }
+    }
+
+    fn check_device_id(device_id: i64) -> StorageResult<()> {
+        if !(1..=99).contains(&device_id) {
+            return Err(StorageError::Validation(format!(
+                "Device ID must be 1-99, got {}",
+                device_id
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_profile(profile: &ConfigProfile) -> StorageResult<()> {
+        let length = profile.name.chars().count();
+        if profile.name.trim().is_empty() || length > 100 {
+            return Err(StorageError::Validation(
+                "Profile name must be 1-100 characters".to_string(),
+            ));
+        }
+        profile.settings.validate()
+    }
+
+    /// Fail if `parent_id` is missing, or is `id` or inherits from it
+    async fn check_parent(&self, id: Option<i64>, parent_id: i64) -> StorageResult<()> {
+        let chain = self.chain(parent_id).await?;
+        if let Some(id) = id
+            && chain.iter().any(|profile| profile.id == id)
+        {
+            return Err(StorageError::Validation(format!(
+                "Profile {} cannot inherit from profile {}, which inherits from it",
+                id, parent_id
+            )));
+        }
+        if chain.len() >= MAX_PROFILE_DEPTH {
+            return Err(StorageError::Validation(format!(
+                "Profiles may inherit at most {} levels deep",
+                MAX_PROFILE_DEPTH
+            )));
+        }
+        Ok(())
+    }
+
+    async fn ensure_device(&self, device_id: i64, now: DateTime<Utc>) -> StorageResult<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, created_at, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (device_id) DO NOTHING
+            "#,
+            device_id,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl ConfigProfileRepository for SqliteConfigProfileRepository {
+    async fn create(&self, profile: &ConfigProfile) -> StorageResult<i64> {
+        Self::check_profile(profile)?;
+        if let Some(parent_id) = profile.parent_id {
+            self.check_parent(None, parent_id).await?;
+        }
+
+        let columns = SettingsColumns::from_settings(&profile.settings);
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO config_profiles (
+                name, parent_id, online_timeout_ms, validation_mode, entry_message,
+                exit_message, message_secs, volume, feedback, created_at, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            profile.name,
+            profile.parent_id,
+            columns.online_timeout_ms,
+            columns.validation_mode,
+            columns.entry_message,
+            columns.exit_message,
+            columns.message_secs,
+            columns.volume,
+            columns.feedback,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<ConfigProfile>> {
+        let row = sqlx::query_as!(
+            ProfileRow,
+            r#"
+            SELECT id AS "id!", name, parent_id, online_timeout_ms, validation_mode,
+                   entry_message, exit_message, message_secs, volume,
+                   feedback AS "feedback: _",
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM config_profiles
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(ProfileRow::into_profile).transpose()
+    }
+
+    async fn find_by_name(&self, name: &str) -> StorageResult<Option<ConfigProfile>> {
+        let row = sqlx::query_as!(
+            ProfileRow,
+            r#"
+            SELECT id AS "id!", name, parent_id, online_timeout_ms, validation_mode,
+                   entry_message, exit_message, message_secs, volume,
+                   feedback AS "feedback: _",
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM config_profiles
+            WHERE name = ?
+            "#,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(ProfileRow::into_profile).transpose()
+    }
+
+    async fn find_all(&self) -> StorageResult<Vec<ConfigProfile>> {
+        let rows = sqlx::query_as!(
+            ProfileRow,
+            r#"
+            SELECT id AS "id!", name, parent_id, online_timeout_ms, validation_mode,
+                   entry_message, exit_message, message_secs, volume,
+                   feedback AS "feedback: _",
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM config_profiles
+            ORDER BY name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(ProfileRow::into_profile).collect()
+    }
+
+    async fn update(&self, profile: &ConfigProfile) -> StorageResult<()> {
+        Self::check_profile(profile)?;
+        if self.find_by_id(profile.id).await?.is_none() {
+            return Err(Self::not_found(profile.id));
+        }
+        if let Some(parent_id) = profile.parent_id {
+            self.check_parent(Some(profile.id), parent_id).await?;
+        }
+
+        let columns = SettingsColumns::from_settings(&profile.settings);
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE config_profiles
+            SET name = ?, parent_id = ?, online_timeout_ms = ?, validation_mode = ?,
+                entry_message = ?, exit_message = ?, message_secs = ?, volume = ?,
+                feedback = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+            profile.name,
+            profile.parent_id,
+            columns.online_timeout_ms,
+            columns.validation_mode,
+            columns.entry_message,
+            columns.exit_message,
+            columns.message_secs,
+            columns.volume,
+            columns.feedback,
+            now,
+            profile.id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(profile.id));
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i64) -> StorageResult<()> {
+        let children = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM config_profiles WHERE parent_id = ?",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let devices = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM device_configs WHERE profile_id = ?",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if children > 0 || devices > 0 {
+            return Err(StorageError::ReferentialIntegrity(format!(
+                "Profile {} is used by {} profiles and {} devices",
+                id, children, devices
+            )));
+        }
+
+        let result = sqlx::query!("DELETE FROM config_profiles WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(id));
+        }
+
+        Ok(())
+    }
+
+    async fn assign_device(&self, device_id: i64, profile_id: Option<i64>) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+        if let Some(profile_id) = profile_id
+            && self.find_by_id(profile_id).await?.is_none()
+        {
+            return Err(Self::not_found(profile_id));
+        }
+
+        let now = Utc::now();
+        self.ensure_device(device_id, now).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO device_configs (device_id, profile_id, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                profile_id = excluded.profile_id,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            profile_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_overrides(&self, device_id: i64, overrides: &ConfigSettings) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+        overrides.validate()?;
+
+        let columns = SettingsColumns::from_settings(overrides);
+        let now = Utc::now();
+        self.ensure_device(device_id, now).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO device_configs (
+                device_id, online_timeout_ms, validation_mode, entry_message, exit_message,
+                message_secs, volume, feedback, updated_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                online_timeout_ms = excluded.online_timeout_ms,
+                validation_mode = excluded.validation_mode,
+                entry_message = excluded.entry_message,
+                exit_message = excluded.exit_message,
+                message_secs = excluded.message_secs,
+                volume = excluded.volume,
+                feedback = excluded.feedback,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            columns.online_timeout_ms,
+            columns.validation_mode,
+            columns.entry_message,
+            columns.exit_message,
+            columns.message_secs,
+            columns.volume,
+            columns.feedback,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_device_config(&self, device_id: i64) -> StorageResult<Option<DeviceConfig>> {
+        let row = sqlx::query_as!(
+            DeviceConfigRow,
+            r#"
+            SELECT device_id AS "device_id!", profile_id, online_timeout_ms, validation_mode,
+                   entry_message, exit_message, message_secs, volume,
+                   feedback AS "feedback: _"
+            FROM device_configs
+            WHERE device_id = ?
+            "#,
+            device_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(DeviceConfigRow::into_config).transpose()
+    }
+
+    async fn chain(&self, id: i64) -> StorageResult<Vec<ConfigProfile>> {
+        let mut chain: Vec<ConfigProfile> = Vec::new();
+        let mut next = Some(id);
+        while let Some(id) = next {
+            if chain.len() == MAX_PROFILE_DEPTH {
+                return Err(StorageError::Validation(format!(
+                    "Profile {} inherits more than {} levels deep",
+                    chain[0].id, MAX_PROFILE_DEPTH
+                )));
+            }
+            let profile = self
+                .find_by_id(id)
+                .await?
+                .ok_or_else(|| Self::not_found(id))?;
+            next = profile.parent_id;
+            chain.push(profile);
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
+    async fn resolve(&self, device_id: i64) -> StorageResult<ResolvedConfig> {
+        let Some(config) = self.find_device_config(device_id).await? else {
+            return Ok(ResolvedConfig::resolve(device_id, Vec::new(), []));
+        };
+        let chain = match config.profile_id {
+            Some(profile_id) => self.chain(profile_id).await?,
+            None => Vec::new(),
+        };
+
+        let names = chain.iter().map(|profile| profile.name.clone()).collect();
+        let layers = chain
+            .iter()
+            .map(|profile| &profile.settings)
+            .chain([&config.overrides]);
+        Ok(ResolvedConfig::resolve(device_id, names, layers))
+    }
+
+    async fn find_devices_using(&self, id: i64) -> StorageResult<Vec<i64>> {
+        let devices = sqlx::query_scalar!(
+            r#"
+            WITH RECURSIVE descendants (id) AS (
+                SELECT ?
+                UNION
+                SELECT config_profiles.id
+                FROM config_profiles
+                JOIN descendants ON config_profiles.parent_id = descendants.id
+            )
+            SELECT device_id AS "device_id!"
+            FROM device_configs
+            WHERE profile_id IN (SELECT id FROM descendants)
+            ORDER BY device_id
+            "#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    async fn setup() -> (Database, SqliteConfigProfileRepository) {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteConfigProfileRepository::new(db.pool().clone());
+        (db, repo)
+    }
+
+    #[tokio::test]
+    async fn test_resolve_inherits_and_overrides() {
+        let (_db, repo) = setup().await;
+
+        let site = repo
+            .create(&ConfigProfile::new("Sede").with_settings(ConfigSettings {
+                online_timeout_ms: Some(2000),
+                validation_mode: Some(ValidationMode::Automatic),
+                volume: Some(3),
+                ..ConfigSettings::default()
+            }))
+            .await
+            .unwrap();
+        let gate = repo
+            .create(
+                &ConfigProfile::new("Portaria")
+                    .with_parent(site)
+                    .with_settings(ConfigSettings {
+                        entry_message: Some("Bem vindo a portaria".to_string()),
+                        volume: Some(8),
+                        ..ConfigSettings::default()
+                    }),
+            )
+            .await
+            .unwrap();
+
+        repo.assign_device(15, Some(gate)).await.unwrap();
+        repo.set_overrides(
+            15,
+            &ConfigSettings {
+                feedback: Some(FeedbackPattern::Light),
+                ..ConfigSettings::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let config = repo.resolve(15).await.unwrap();
+        assert_eq!(config.profiles, ["Sede", "Portaria"]);
+        assert_eq!(config.online_timeout_ms, 2000);
+        assert_eq!(config.validation_mode, ValidationMode::Automatic);
+        assert_eq!(config.entry_message, "Bem vindo a portaria");
+        assert_eq!(config.volume, 8);
+        assert_eq!(config.feedback, FeedbackPattern::Light);
+
+        // Reassigning keeps the overrides
+        repo.assign_device(15, Some(site)).await.unwrap();
+        let config = repo.resolve(15).await.unwrap();
+        assert_eq!(config.volume, 3);
+        assert_eq!(config.feedback, FeedbackPattern::Light);
+
+        let defaults = repo.resolve(16).await.unwrap();
+        assert!(defaults.profiles.is_empty());
+        assert_eq!(defaults.validation_mode, ValidationMode::Online);
+    }
+
+    #[tokio::test]
+    async fn test_update_rejects_inheritance_cycles() {
+        let (_db, repo) = setup().await;
+        let root = repo.create(&ConfigProfile::new("A")).await.unwrap();
+        let child = repo
+            .create(&ConfigProfile::new("B").with_parent(root))
+            .await
+            .unwrap();
+
+        let mut profile = repo.find_by_id(root).await.unwrap().unwrap();
+        profile.parent_id = Some(child);
+        assert!(matches!(
+            repo.update(&profile).await,
+            Err(StorageError::Validation(_))
+        ));
+
+        profile.parent_id = Some(root);
+        assert!(repo.update(&profile).await.is_err());
+
+        profile.parent_id = None;
+        profile.settings.volume = Some(1);
+        repo.update(&profile).await.unwrap();
+        assert_eq!(
+            repo.find_by_id(root)
+                .await
+                .unwrap()
+                .unwrap()
+                .settings
+                .volume,
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_devices_using_follows_children() {
+        let (_db, repo) = setup().await;
+        let root = repo.create(&ConfigProfile::new("Base")).await.unwrap();
+        let child = repo
+            .create(&ConfigProfile::new("Refeitorio").with_parent(root))
+            .await
+            .unwrap();
+        let other = repo.create(&ConfigProfile::new("Garagem")).await.unwrap();
+
+        repo.assign_device(3, Some(child)).await.unwrap();
+        repo.assign_device(1, Some(root)).await.unwrap();
+        repo.assign_device(7, Some(other)).await.unwrap();
+
+        assert_eq!(repo.find_devices_using(root).await.unwrap(), [1, 3]);
+        assert_eq!(repo.find_devices_using(child).await.unwrap(), [3]);
+
+        assert!(matches!(
+            repo.delete(root).await,
+            Err(StorageError::ReferentialIntegrity(_))
+        ));
+        repo.assign_device(7, None).await.unwrap();
+        repo.delete(other).await.unwrap();
+        assert!(repo.find_by_name("Garagem").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_settings_rejected() {
+        let (_db, repo) = setup().await;
+        let profile = ConfigProfile::new("Alto").with_settings(ConfigSettings {
+            volume: Some(12),
+            ..ConfigSettings::default()
+        });
+        assert!(matches!(
+            repo.create(&profile).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.create(&ConfigProfile::new("Filho").with_parent(99))
+                .await,
+            Err(StorageError::NotFound { .. })
+        ));
+        assert!(
+            repo.assign_device(100, None).await.is_err(),
+            "device ID out of range"
+        );
+    }
+}
@@ -0,0 +1,797 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{Device, MIN_DEVICE_KEY_LENGTH, VerificationMode};
+use chrono::Utc;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use turnkey_core::{DeviceId, DeviceLabel};
+
+/// Repository trait for Device entity operations
+///
+/// Holds per-device server settings: the HMAC shared secrets loaded into
+/// `TcpServer::register_signing_key()` at startup, the access methods
+/// enabled on each device, and the friendly names, locations and tags
+/// shown next to device IDs (see [`DeviceLabel`]).
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait DeviceRepository: Send + Sync {
+    /// Find a device by its Henry device ID
+    async fn find_by_id(&self, device_id: i64) -> StorageResult<Option<Device>>;
+
+    /// Get all known devices, ordered by device ID
+    async fn find_all(&self) -> StorageResult<Vec<Device>>;
+
+    /// Get devices that have a signing key, ordered by device ID
+    async fn find_signing_devices(&self) -> StorageResult<Vec<Device>>;
+
+    /// Set the HMAC key of a device, creating the device if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the key is shorter than
+    /// [`MIN_DEVICE_KEY_LENGTH`] bytes or the device ID is out of range.
+    async fn set_signing_key(
+        &self,
+        device_id: i64,
+        key: &[u8],
+        required: bool,
+    ) -> StorageResult<()>;
+
+    /// Remove the HMAC key of a device
+    ///
+    /// The device goes back to unsigned operation.
+    async fn clear_signing_key(&self, device_id: i64) -> StorageResult<()>;
+
+    /// Enable or disable access methods on a device, creating it if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if every method is disabled or the device ID is
+    /// out of range.
+    async fn set_access_methods(
+        &self,
+        device_id: i64,
+        allow_card: bool,
+        allow_bio: bool,
+        allow_keypad: bool,
+    ) -> StorageResult<()>;
+
+    /// Set whether a device denies offline requests on stale data,
+    /// creating the device if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range.
+    async fn set_deny_when_stale(&self, device_id: i64, deny: bool) -> StorageResult<()>;
+
+    /// Set the credentials a device requires, creating the device if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range.
+    async fn set_verification_mode(
+        &self,
+        device_id: i64,
+        mode: VerificationMode,
+    ) -> StorageResult<()>;
+
+    /// Set whether grants on a device show the remaining daily quota,
+    /// creating the device if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range.
+    async fn set_show_balance(&self, device_id: i64, show: bool) -> StorageResult<()>;
+
+    /// Set the name and location of a device, creating it if needed
+    ///
+    /// `None` clears the value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range or a value is
+    /// not a valid label text (see [`DeviceLabel::check_text`]).
+    async fn set_label(
+        &self,
+        device_id: i64,
+        name: Option<&str>,
+        location: Option<&str>,
+    ) -> StorageResult<()>;
+
+    /// Replace the tags of a device, creating it if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range or a tag is
+    /// not a valid label text.
+    async fn set_tags(&self, device_id: i64, tags: &[&str]) -> StorageResult<()>;
+
+    /// Get the label of a device
+    ///
+    /// Devices that are unknown or have no name get an unnamed label, which
+    /// displays as the numeric ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range.
+    async fn find_label(&self, device_id: i64) -> StorageResult<DeviceLabel>;
+
+    /// Get the labels of all known devices, ordered by device ID
+    async fn find_labels(&self) -> StorageResult<Vec<DeviceLabel>>;
+
+    /// Get the devices carrying `tag`, ordered by device ID
+    async fn find_by_tag(&self, tag: &str) -> StorageResult<Vec<i64>>;
+}
+
+/// SQLite implementation of DeviceRepository
+pub struct SqliteDeviceRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteDeviceRepository {
+    /// Create a new SQLite device repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn check_device_id(device_id: i64) -> StorageResult<()> {
+        if !(1..=99).contains(&device_id) {
+            return Err(StorageError::Validation(format!(
+                "Device ID must be 1-99, got {}",
+                device_id
+            )));
+        }
+        Ok(())
+    }
+
+    fn label_id(device_id: i64) -> StorageResult<DeviceId> {
+        Self::check_device_id(device_id)?;
+        DeviceId::new(device_id as u8).map_err(|e| StorageError::Validation(e.to_string()))
+    }
+
+    fn check_label_text(what: &str, text: &str) -> StorageResult<()> {
+        DeviceLabel::check_text(what, text).map_err(|e| StorageError::Validation(e.to_string()))
+    }
+
+    async fn ensure_device(conn: &mut sqlx::SqliteConnection, device_id: i64) -> StorageResult<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, created_at, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (device_id) DO NOTHING
+            "#,
+            device_id,
+            now,
+            now
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    fn not_found(device_id: i64) -> StorageError {
+        StorageError::NotFound {
+            entity_type: "Device".to_string(),
+            field: "device_id".to_string(),
+            value: device_id.to_string(),
+        }
+    }
+}
+
+impl DeviceRepository for SqliteDeviceRepository {
+    async fn find_by_id(&self, device_id: i64) -> StorageResult<Option<Device>> {
+        let device = sqlx::query_as!(
+            Device,
+            r#"
+            SELECT device_id, hmac_key AS "hmac_key: _", signing_required, allow_card, allow_bio,
+                   allow_keypad, zone_id, deny_when_stale,
+                   verification_mode AS "verification_mode: _",
+                   show_balance AS "show_balance: _", name, location,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM devices
+            WHERE device_id = ?
+            "#,
+            device_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(device)
+    }
+
+    async fn find_all(&self) -> StorageResult<Vec<Device>> {
+        let devices = sqlx::query_as!(
+            Device,
+            r#"
+            SELECT device_id, hmac_key AS "hmac_key: _", signing_required, allow_card, allow_bio,
+                   allow_keypad, zone_id, deny_when_stale,
+                   verification_mode AS "verification_mode: _",
+                   show_balance AS "show_balance: _", name, location,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM devices
+            ORDER BY device_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    async fn find_signing_devices(&self) -> StorageResult<Vec<Device>> {
+        let devices = sqlx::query_as!(
+            Device,
+            r#"
+            SELECT device_id, hmac_key AS "hmac_key: _", signing_required, allow_card, allow_bio,
+                   allow_keypad, zone_id, deny_when_stale,
+                   verification_mode AS "verification_mode: _",
+                   show_balance AS "show_balance: _", name, location,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM devices
+            WHERE hmac_key IS NOT NULL
+            ORDER BY device_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    async fn set_signing_key(
+        &self,
+        device_id: i64,
+        key: &[u8],
+        required: bool,
+    ) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+        if key.len() < MIN_DEVICE_KEY_LENGTH {
+            return Err(StorageError::Validation(format!(
+                "Signing key must be at least {} bytes, got {}",
+                MIN_DEVICE_KEY_LENGTH,
+                key.len()
+            )));
+        }
+
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, hmac_key, signing_required, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                hmac_key = excluded.hmac_key,
+                signing_required = excluded.signing_required,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            key,
+            required,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn clear_signing_key(&self, device_id: i64) -> StorageResult<()> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE devices
+            SET hmac_key = NULL, signing_required = 0, updated_at = ?
+            WHERE device_id = ?
+            "#,
+            now,
+            device_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(device_id));
+        }
+
+        Ok(())
+    }
+
+    async fn set_access_methods(
+        &self,
+        device_id: i64,
+        allow_card: bool,
+        allow_bio: bool,
+        allow_keypad: bool,
+    ) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+        if !(allow_card || allow_bio || allow_keypad) {
+            return Err(StorageError::Validation(format!(
+                "Device {} must allow at least one access method",
+                device_id
+            )));
+        }
+
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, allow_card, allow_bio, allow_keypad,
+                                 created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                allow_card = excluded.allow_card,
+                allow_bio = excluded.allow_bio,
+                allow_keypad = excluded.allow_keypad,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            allow_card,
+            allow_bio,
+            allow_keypad,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_deny_when_stale(&self, device_id: i64, deny: bool) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, deny_when_stale, created_at, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                deny_when_stale = excluded.deny_when_stale,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            deny,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_verification_mode(
+        &self,
+        device_id: i64,
+        mode: VerificationMode,
+    ) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, verification_mode, created_at, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                verification_mode = excluded.verification_mode,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            mode,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_show_balance(&self, device_id: i64, show: bool) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, show_balance, created_at, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                show_balance = excluded.show_balance,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            show,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_label(
+        &self,
+        device_id: i64,
+        name: Option<&str>,
+        location: Option<&str>,
+    ) -> StorageResult<()> {
+        Self::check_device_id(device_id)?;
+        if let Some(name) = name {
+            Self::check_label_text("Device name", name)?;
+        }
+        if let Some(location) = location {
+            Self::check_label_text("Device location", location)?;
+        }
+
+        let name = name.map(str::trim);
+        let location = location.map(str::trim);
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, name, location, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                name = excluded.name,
+                location = excluded.location,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            name,
+            location,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_tags(&self, device_id: i64, tags: &[&str]) -> StorageResult<()> {
+        let id = Self::label_id(device_id)?;
+        let label = DeviceLabel::unnamed(id)
+            .with_tags(tags)
+            .map_err(|e| StorageError::Validation(e.to_string()))?;
+
+        let mut tx = self.pool.begin().await?;
+        Self::ensure_device(&mut tx, device_id).await?;
+        sqlx::query!("DELETE FROM device_tags WHERE device_id = ?", device_id)
+            .execute(&mut *tx)
+            .await?;
+        for tag in &label.tags {
+            sqlx::query!(
+                "INSERT INTO device_tags (device_id, tag) VALUES (?, ?)",
+                device_id,
+                tag
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn find_label(&self, device_id: i64) -> StorageResult<DeviceLabel> {
+        let id = Self::label_id(device_id)?;
+
+        let row = sqlx::query!(
+            "SELECT name, location FROM devices WHERE device_id = ?",
+            device_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let tags = sqlx::query_scalar!(
+            "SELECT tag FROM device_tags WHERE device_id = ? ORDER BY tag",
+            device_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let (name, location) = row.map(|row| (row.name, row.location)).unwrap_or_default();
+        Ok(DeviceLabel {
+            name,
+            location,
+            tags,
+            ..DeviceLabel::unnamed(id)
+        })
+    }
+
+    async fn find_labels(&self) -> StorageResult<Vec<DeviceLabel>> {
+        let rows = sqlx::query!(
+            r#"SELECT device_id AS "device_id!", name, location FROM devices ORDER BY device_id"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let tags = sqlx::query!("SELECT device_id, tag FROM device_tags ORDER BY device_id, tag")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut labels = BTreeMap::new();
+        for row in rows {
+            let id = Self::label_id(row.device_id)?;
+            labels.insert(
+                row.device_id,
+                DeviceLabel {
+                    name: row.name,
+                    location: row.location,
+                    ..DeviceLabel::unnamed(id)
+                },
+            );
+        }
+        for row in tags {
+            if let Some(label) = labels.get_mut(&row.device_id) {
+                label.tags.push(row.tag);
+            }
+        }
+
+        Ok(labels.into_values().collect())
+    }
+
+    async fn find_by_tag(&self, tag: &str) -> StorageResult<Vec<i64>> {
+        let tag = tag.trim();
+        let devices = sqlx::query_scalar!(
+            "SELECT device_id FROM device_tags WHERE tag = ? ORDER BY device_id",
+            tag
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    const KEY: &[u8] = b"0123456789abcdef";
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_set_and_find_signing_key() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_signing_key(15, KEY, true).await.unwrap();
+
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert_eq!(device.hmac_key.as_deref(), Some(KEY));
+        assert!(device.signing_required);
+        assert!(device.signer().is_some());
+        assert!(repo.find_by_id(16).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_set_signing_key_replaces_existing() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_signing_key(15, KEY, true).await.unwrap();
+        repo.set_signing_key(15, b"fedcba9876543210", false)
+            .await
+            .unwrap();
+
+        let devices = repo.find_all().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(
+            devices[0].hmac_key.as_deref(),
+            Some(&b"fedcba9876543210"[..])
+        );
+        assert!(!devices[0].signing_required);
+    }
+
+    #[tokio::test]
+    async fn test_set_signing_key_validation() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        assert!(matches!(
+            repo.set_signing_key(15, b"short", false).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.set_signing_key(100, KEY, false).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_clear_signing_key() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_signing_key(15, KEY, true).await.unwrap();
+        repo.set_signing_key(20, KEY, false).await.unwrap();
+        repo.clear_signing_key(15).await.unwrap();
+
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert!(device.hmac_key.is_none());
+        assert!(!device.signing_required);
+
+        let signing: Vec<i64> = repo
+            .find_signing_devices()
+            .await
+            .unwrap()
+            .iter()
+            .map(|d| d.device_id)
+            .collect();
+        assert_eq!(signing, vec![20]);
+
+        assert!(matches!(
+            repo.clear_signing_key(42).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_access_methods() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_signing_key(15, KEY, true).await.unwrap();
+        repo.set_access_methods(15, true, true, false)
+            .await
+            .unwrap();
+        repo.set_access_methods(16, false, true, false)
+            .await
+            .unwrap();
+
+        // Existing settings are kept
+        let gate = repo.find_by_id(15).await.unwrap().unwrap();
+        assert!(gate.signing_required);
+        assert!(gate.allow_card && gate.allow_bio && !gate.allow_keypad);
+
+        let new = repo.find_by_id(16).await.unwrap().unwrap();
+        assert!(new.hmac_key.is_none());
+        assert!(!new.allow_card && new.allow_bio);
+
+        assert!(matches!(
+            repo.set_access_methods(15, false, false, false).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_new_devices_allow_every_method() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_signing_key(15, KEY, false).await.unwrap();
+
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert!(device.allow_card && device.allow_bio && device.allow_keypad);
+    }
+
+    #[tokio::test]
+    async fn test_set_verification_mode() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_deny_when_stale(15, true).await.unwrap();
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert_eq!(device.verification_mode, VerificationMode::SingleFactor);
+
+        repo.set_verification_mode(15, VerificationMode::CardPlusBiometric)
+            .await
+            .unwrap();
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert_eq!(
+            device.verification_mode,
+            VerificationMode::CardPlusBiometric
+        );
+        assert!(device.deny_when_stale);
+
+        assert!(matches!(
+            repo.set_verification_mode(100, VerificationMode::SingleFactor)
+                .await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_show_balance() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_verification_mode(15, VerificationMode::CardPlusBiometric)
+            .await
+            .unwrap();
+        assert!(!repo.find_by_id(15).await.unwrap().unwrap().show_balance);
+
+        repo.set_show_balance(15, true).await.unwrap();
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert!(device.show_balance);
+        assert_eq!(
+            device.verification_mode,
+            VerificationMode::CardPlusBiometric
+        );
+
+        assert!(matches!(
+            repo.set_show_balance(0, true).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_deny_when_stale() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_access_methods(15, true, false, false)
+            .await
+            .unwrap();
+        assert!(!repo.find_by_id(15).await.unwrap().unwrap().deny_when_stale);
+
+        repo.set_deny_when_stale(15, true).await.unwrap();
+        let device = repo.find_by_id(15).await.unwrap().unwrap();
+        assert!(device.deny_when_stale);
+        assert!(device.allow_card && !device.allow_bio);
+
+        assert!(matches!(
+            repo.set_deny_when_stale(100, true).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_device_labels() {
+        let db = setup_test_db().await;
+        let repo = SqliteDeviceRepository::new(db.pool().clone());
+
+        repo.set_signing_key(15, KEY, true).await.unwrap();
+        repo.set_label(15, Some(" Portaria Norte "), Some("Bloco A"))
+            .await
+            .unwrap();
+        repo.set_tags(15, &["high-security", "entrance"])
+            .await
+            .unwrap();
+        repo.set_tags(16, &["garage"]).await.unwrap();
+
+        let label = repo.find_label(15).await.unwrap();
+        assert_eq!(label.to_string(), "Portaria Norte (15)");
+        assert_eq!(label.location.as_deref(), Some("Bloco A"));
+        assert_eq!(label.tags, vec!["entrance", "high-security"]);
+        // Other settings are kept
+        assert!(repo.find_by_id(15).await.unwrap().unwrap().signing_required);
+
+        // Unknown and unnamed devices fall back to the number
+        assert_eq!(repo.find_label(42).await.unwrap().to_string(), "42");
+        assert_eq!(repo.find_label(16).await.unwrap().to_string(), "16");
+
+        let labels = repo.find_labels().await.unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[1].tags, vec!["garage"]);
+        assert_eq!(repo.find_by_tag("garage").await.unwrap(), vec![16]);
+
+        repo.set_tags(15, &[]).await.unwrap();
+        repo.set_label(15, None, None).await.unwrap();
+        assert_eq!(
+            repo.find_label(15).await.unwrap(),
+            DeviceLabel::unnamed(DeviceId::new(15).unwrap())
+        );
+
+        assert!(matches!(
+            repo.set_label(15, Some("Catraca]1"), None).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.set_tags(15, &[""]).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.find_label(0).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+}
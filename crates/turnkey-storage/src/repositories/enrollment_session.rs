@@ -0,0 +1,315 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{Card, EnrollmentSession, PendingCard};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository trait for card enrollment sessions
+///
+/// Backs enrollment via reader: an administrator opens a session on a
+/// device for a user, and the next card read there is captured as a pending
+/// card bound to that user.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait EnrollmentSessionRepository: Send + Sync {
+    /// Put a device into enrollment mode for a user until `expires_at`
+    ///
+    /// Replaces any session already open on the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is not 1-99 or `expires_at` is
+    /// not in the future, and `NotFound` if no user has the matricula.
+    async fn start(
+        &self,
+        device_id: i64,
+        matricula: &str,
+        expires_at: DateTime<Utc>,
+    ) -> StorageResult<EnrollmentSession>;
+
+    /// Find the session of a device that is still open at `at`
+    async fn find_open(
+        &self,
+        device_id: i64,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<EnrollmentSession>>;
+
+    /// Leave enrollment mode
+    ///
+    /// Returns `true` if the device had a session (open or expired).
+    async fn cancel(&self, device_id: i64) -> StorageResult<bool>;
+
+    /// Capture a card read into the device's open session
+    ///
+    /// In one transaction, stores the card as pending bound to the session's
+    /// matricula and closes the session. A card already pending is rebound
+    /// and its read statistics updated. Returns `None`, leaving the read
+    /// untouched, if the device has no session open at `at`.
+    async fn capture(
+        &self,
+        device_id: i64,
+        raw_value: &str,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<PendingCard>>;
+}
+
+/// SQLite implementation of EnrollmentSessionRepository
+pub struct SqliteEnrollmentSessionRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteEnrollmentSessionRepository {
+    /// Create a new SQLite enrollment session repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl EnrollmentSessionRepository for SqliteEnrollmentSessionRepository {
+    async fn start(
+        &self,
+        device_id: i64,
+        matricula: &str,
+        expires_at: DateTime<Utc>,
+    ) -> StorageResult<EnrollmentSession> {
+        if !(1..=99).contains(&device_id) {
+            return Err(StorageError::Validation(format!(
+                "Device ID must be 1-99, got {}",
+                device_id
+            )));
+        }
+
+        let started_at = Utc::now();
+        if expires_at <= started_at {
+            return Err(StorageError::Validation(
+                "Enrollment session must expire in the future".to_string(),
+            ));
+        }
+
+        let user = sqlx::query_scalar!("SELECT id FROM users WHERE matricula = ?", matricula)
+            .fetch_optional(&self.pool)
+            .await?;
+        if user.is_none() {
+            return Err(StorageError::NotFound {
+                entity_type: "User".to_string(),
+                field: "matricula".to_string(),
+                value: matricula.to_string(),
+            });
+        }
+
+        let session = sqlx::query_as!(
+            EnrollmentSession,
+            r#"
+            INSERT INTO enrollment_sessions (device_id, matricula, started_at, expires_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                matricula = excluded.matricula,
+                started_at = excluded.started_at,
+                expires_at = excluded.expires_at
+            RETURNING device_id AS "device_id!", matricula, started_at AS "started_at: _",
+                      expires_at AS "expires_at: _"
+            "#,
+            device_id,
+            matricula,
+            started_at,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn find_open(
+        &self,
+        device_id: i64,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<EnrollmentSession>> {
+        let session = sqlx::query_as!(
+            EnrollmentSession,
+            r#"
+            SELECT device_id, matricula, started_at AS "started_at: _",
+                   expires_at AS "expires_at: _"
+            FROM enrollment_sessions
+            WHERE device_id = ? AND expires_at > ?
+            "#,
+            device_id,
+            at
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn cancel(&self, device_id: i64) -> StorageResult<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM enrollment_sessions WHERE device_id = ?",
+            device_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn capture(
+        &self,
+        device_id: i64,
+        raw_value: &str,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<PendingCard>> {
+        let mut tx = self.pool.begin().await?;
+
+        let session = sqlx::query_scalar!(
+            "SELECT matricula FROM enrollment_sessions WHERE device_id = ? AND expires_at > ?",
+            device_id,
+            at
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(matricula) = session else {
+            return Ok(None);
+        };
+        let card_number = Card::normalize_card_number(raw_value);
+
+        let pending = sqlx::query_as!(
+            PendingCard,
+            r#"
+            INSERT INTO pending_cards (
+                raw_value, card_number, device_id, first_seen_at, last_seen_at, matricula
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (card_number) DO UPDATE SET
+                raw_value = excluded.raw_value,
+                device_id = excluded.device_id,
+                read_count = read_count + 1,
+                last_seen_at = excluded.last_seen_at,
+                matricula = excluded.matricula
+            RETURNING id AS "id!", raw_value, card_number, device_id, read_count,
+                      first_seen_at AS "first_seen_at: _", last_seen_at AS "last_seen_at: _",
+                      matricula
+            "#,
+            raw_value,
+            card_number,
+            device_id,
+            at,
+            at,
+            matricula
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM enrollment_sessions WHERE device_id = ?",
+            device_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::repositories::{PendingCardRepository, SqlitePendingCardRepository};
+    use chrono::Duration;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_start_validates_device_and_user() {
+        let db = setup_test_db().await;
+        let repo = SqliteEnrollmentSessionRepository::new(db.pool().clone());
+        let expires_at = Utc::now() + Duration::minutes(2);
+
+        assert!(matches!(
+            repo.start(0, "1001", expires_at).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.start(5, "1001", Utc::now() - Duration::seconds(1))
+                .await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.start(5, "NOBODY", expires_at).await,
+            Err(StorageError::NotFound { .. })
+        ));
+
+        let session = repo.start(5, "1001", expires_at).await.unwrap();
+        assert_eq!(session.matricula, "1001");
+        assert!(session.is_open_at(Utc::now()));
+
+        // Starting again replaces the session
+        let session = repo.start(5, "1002", expires_at).await.unwrap();
+        assert_eq!(session.matricula, "1002");
+        let open = repo.find_open(5, Utc::now()).await.unwrap().unwrap();
+        assert_eq!(open.matricula, "1002");
+    }
+
+    #[tokio::test]
+    async fn test_capture_binds_pending_card_and_closes_session() {
+        let db = setup_test_db().await;
+        let repo = SqliteEnrollmentSessionRepository::new(db.pool().clone());
+        let pending_repo = SqlitePendingCardRepository::new(db.pool().clone());
+
+        // Card already pending from a learning mode read
+        pending_repo
+            .record("ab12cd34", Some(9), Utc::now() - Duration::minutes(10))
+            .await
+            .unwrap();
+
+        repo.start(5, "1003", Utc::now() + Duration::minutes(2))
+            .await
+            .unwrap();
+        let pending = repo
+            .capture(5, " ab12cd34", Utc::now())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.card_number, "AB12CD34");
+        assert_eq!(pending.device_id, Some(5));
+        assert_eq!(pending.read_count, 2);
+        assert_eq!(pending.matricula.as_deref(), Some("1003"));
+
+        assert!(repo.find_open(5, Utc::now()).await.unwrap().is_none());
+        assert!(
+            repo.capture(5, "99887766", Utc::now())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let card_id = pending_repo.assign(pending.id, "1003").await.unwrap();
+        assert!(card_id > 0);
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_does_not_capture() {
+        let db = setup_test_db().await;
+        let repo = SqliteEnrollmentSessionRepository::new(db.pool().clone());
+
+        let expires_at = Utc::now() + Duration::minutes(1);
+        repo.start(7, "1004", expires_at).await.unwrap();
+
+        let later = expires_at + Duration::seconds(1);
+        assert!(repo.find_open(7, later).await.unwrap().is_none());
+        assert!(repo.capture(7, "11223344", later).await.unwrap().is_none());
+
+        assert!(repo.cancel(7).await.unwrap());
+        assert!(!repo.cancel(7).await.unwrap());
+    }
+}
@@ -0,0 +1,264 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::Holiday;
+use chrono::NaiveDate;
+use sqlx::SqlitePool;
+
+/// Repository trait for the holiday calendar
+///
+/// Holidays are keyed by date: storing a holiday on a date that already
+/// has one replaces its description. Users exempt from holiday closures
+/// are kept here as well.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait HolidayRepository: Send + Sync {
+    /// Store a holiday, replacing the description of one on the same date
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the description is empty or longer than 100
+    /// characters.
+    async fn upsert(&self, holiday: &Holiday) -> StorageResult<i64>;
+
+    /// Store many holidays in one transaction; returns how many were stored
+    ///
+    /// Meant for loading a national or municipal calendar. Nothing is
+    /// stored if any holiday is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` naming the first invalid holiday.
+    async fn import(&self, holidays: &[Holiday]) -> StorageResult<usize>;
+
+    /// Find the holiday on a date
+    async fn find_by_date(&self, date: NaiveDate) -> StorageResult<Option<Holiday>>;
+
+    /// Get the holidays from `start` to `end` inclusive, by date
+    async fn find_between(&self, start: NaiveDate, end: NaiveDate) -> StorageResult<Vec<Holiday>>;
+
+    /// Remove the holiday on a date; returns `false` if there was none
+    async fn delete(&self, date: NaiveDate) -> StorageResult<bool>;
+
+    /// Let a user through on holidays, or stop doing so
+    async fn set_exempt(&self, user_id: i64, exempt: bool) -> StorageResult<()>;
+
+    /// Whether a user is let through on holidays
+    async fn is_exempt(&self, user_id: i64) -> StorageResult<bool>;
+}
+
+/// SQLite implementation of HolidayRepository
+pub struct SqliteHolidayRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteHolidayRepository {
+    /// Create a new SQLite holiday repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn check(holiday: &Holiday) -> StorageResult<String> {
+        let description = holiday.description.trim();
+        if description.is_empty() || description.chars().count() > 100 {
+            return Err(StorageError::Validation(format!(
+                "Description of the holiday on {} must be 1-100 characters",
+                holiday.date
+            )));
+        }
+        Ok(description.to_string())
+    }
+}
+
+impl HolidayRepository for SqliteHolidayRepository {
+    async fn upsert(&self, holiday: &Holiday) -> StorageResult<i64> {
+        let description = Self::check(holiday)?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO holidays (date, description) VALUES (?, ?)
+            ON CONFLICT (date) DO UPDATE SET description = excluded.description
+            RETURNING id AS "id!"
+            "#,
+            holiday.date,
+            description
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn import(&self, holidays: &[Holiday]) -> StorageResult<usize> {
+        let descriptions = holidays
+            .iter()
+            .map(Self::check)
+            .collect::<StorageResult<Vec<_>>>()?;
+
+        let mut tx = self.pool.begin().await?;
+        for (holiday, description) in holidays.iter().zip(&descriptions) {
+            sqlx::query!(
+                r#"
+                INSERT INTO holidays (date, description) VALUES (?, ?)
+                ON CONFLICT (date) DO UPDATE SET description = excluded.description
+                "#,
+                holiday.date,
+                description
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(holidays.len())
+    }
+
+    async fn find_by_date(&self, date: NaiveDate) -> StorageResult<Option<Holiday>> {
+        let holiday = sqlx::query_as!(
+            Holiday,
+            r#"
+            SELECT id AS "id!", date AS "date: NaiveDate", description,
+                   created_at AS "created_at: _"
+            FROM holidays
+            WHERE date = ?
+            "#,
+            date
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(holiday)
+    }
+
+    async fn find_between(&self, start: NaiveDate, end: NaiveDate) -> StorageResult<Vec<Holiday>> {
+        let holidays = sqlx::query_as!(
+            Holiday,
+            r#"
+            SELECT id AS "id!", date AS "date: NaiveDate", description,
+                   created_at AS "created_at: _"
+            FROM holidays
+            WHERE date >= ? AND date <= ?
+            ORDER BY date
+            "#,
+            start,
+            end
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(holidays)
+    }
+
+    async fn delete(&self, date: NaiveDate) -> StorageResult<bool> {
+        let result = sqlx::query!("DELETE FROM holidays WHERE date = ?", date)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_exempt(&self, user_id: i64, exempt: bool) -> StorageResult<()> {
+        if exempt {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO holiday_exemptions (user_id) VALUES (?)",
+                user_id
+            )
+            .execute(&self.pool)
+            .await?;
+        } else {
+            sqlx::query!("DELETE FROM holiday_exemptions WHERE user_id = ?", user_id)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn is_exempt(&self, user_id: i64) -> StorageResult<bool> {
+        let exempt = sqlx::query_scalar!(
+            "SELECT user_id FROM holiday_exemptions WHERE user_id = ?",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(exempt.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_import_upserts_by_date() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteHolidayRepository::new(db.pool().clone());
+
+        let imported = repo
+            .import(&[
+                Holiday::new(date(12, 25), "Natal"),
+                Holiday::new(date(11, 20), "Consciencia Negra"),
+                Holiday::new(date(1, 1), "Confraternizacao Universal"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(imported, 3);
+
+        repo.upsert(&Holiday::new(date(12, 25), " Natal de Jesus "))
+            .await
+            .unwrap();
+        let christmas = repo.find_by_date(date(12, 25)).await.unwrap().unwrap();
+        assert_eq!(christmas.description, "Natal de Jesus");
+
+        let late: Vec<_> = repo
+            .find_between(date(11, 1), date(12, 25))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|h| h.date)
+            .collect();
+        assert_eq!(late, vec![date(11, 20), date(12, 25)]);
+
+        assert!(repo.delete(date(1, 1)).await.unwrap());
+        assert!(!repo.delete(date(1, 1)).await.unwrap());
+        assert!(repo.find_by_date(date(1, 1)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_import_stores_nothing() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteHolidayRepository::new(db.pool().clone());
+
+        let result = repo
+            .import(&[
+                Holiday::new(date(4, 21), "Tiradentes"),
+                Holiday::new(date(5, 1), " "),
+            ])
+            .await;
+        assert!(matches!(result, Err(StorageError::Validation(_))));
+        assert!(repo.find_by_date(date(4, 21)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exemptions() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteHolidayRepository::new(db.pool().clone());
+
+        assert!(!repo.is_exempt(1).await.unwrap());
+        repo.set_exempt(1, true).await.unwrap();
+        repo.set_exempt(1, true).await.unwrap();
+        assert!(repo.is_exempt(1).await.unwrap());
+        repo.set_exempt(1, false).await.unwrap();
+        assert!(!repo.is_exempt(1).await.unwrap());
+    }
+}
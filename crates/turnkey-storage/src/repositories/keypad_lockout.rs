@@ -0,0 +1,411 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::StorageResult;
+use crate::models::{KeypadLockout, KeypadLockoutPolicy, UserPinLockout};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use turnkey_core::DeviceId;
+
+/// Repository trait for keypad lockouts after wrong codes
+///
+/// Offline validation records every wrong PIN-only code against the device
+/// it was typed on, and refuses the keypad while the device is locked.
+/// Wrong PINs typed for a known user also count against that user, on
+/// every device. Both escalate per [`KeypadLockoutPolicy`].
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait KeypadLockoutRepository: Send + Sync {
+    /// Get the failed attempts of a device, `None` if it has none recorded
+    async fn find(&self, device_id: DeviceId) -> StorageResult<Option<KeypadLockout>>;
+
+    /// Record a wrong code typed at `now`, locking the keypad per `policy`
+    ///
+    /// Returns the updated record; check
+    /// [`is_locked()`](KeypadLockout::is_locked) to see whether this
+    /// attempt locked the keypad.
+    async fn record_failure(
+        &self,
+        device_id: DeviceId,
+        policy: &KeypadLockoutPolicy,
+        now: DateTime<Utc>,
+    ) -> StorageResult<KeypadLockout>;
+
+    /// Clear the failed attempts and lockouts of a device after a correct code
+    async fn reset(&self, device_id: DeviceId) -> StorageResult<()>;
+
+    /// Get the failed PINs of a user, `None` if they have none recorded
+    async fn find_user(&self, user_id: i64) -> StorageResult<Option<UserPinLockout>>;
+
+    /// Record a wrong PIN for a user at `now`, locking their PIN per `policy`
+    async fn record_user_failure(
+        &self,
+        user_id: i64,
+        policy: &KeypadLockoutPolicy,
+        now: DateTime<Utc>,
+    ) -> StorageResult<UserPinLockout>;
+
+    /// Clear the failed PINs and lockouts of a user after a correct PIN
+    async fn reset_user(&self, user_id: i64) -> StorageResult<()>;
+}
+
+/// SQLite implementation of KeypadLockoutRepository
+pub struct SqliteKeypadLockoutRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteKeypadLockoutRepository {
+    /// Create a new SQLite keypad lockout repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Escalation level a new lockout would start from
+///
+/// Lockouts are forgotten after `max_cooldown` without a wrong code.
+fn escalation(
+    lockouts: i64,
+    last_failed_at: Option<DateTime<Utc>>,
+    policy: &KeypadLockoutPolicy,
+    now: DateTime<Utc>,
+) -> i64 {
+    match last_failed_at {
+        Some(at) if at > now - policy.max_cooldown => lockouts,
+        _ => 0,
+    }
+}
+
+impl KeypadLockoutRepository for SqliteKeypadLockoutRepository {
+    async fn find(&self, device_id: DeviceId) -> StorageResult<Option<KeypadLockout>> {
+        let device_id = i64::from(device_id.as_u8());
+        let lockout = sqlx::query_as!(
+            KeypadLockout,
+            r#"
+            SELECT device_id, failed_attempts, lockouts,
+                   last_failed_at AS "last_failed_at: _",
+                   locked_until AS "locked_until: _"
+            FROM keypad_lockouts
+            WHERE device_id = ?
+            "#,
+            device_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    async fn record_failure(
+        &self,
+        device_id: DeviceId,
+        policy: &KeypadLockoutPolicy,
+        now: DateTime<Utc>,
+    ) -> StorageResult<KeypadLockout> {
+        // The length of a new lockout depends on the earlier ones. Reading
+        // them first is safe: if another wrong code locks the keypad in
+        // between, the statement keeps that lockout and ignores ours.
+        let level = match self.find(device_id).await? {
+            Some(current) => escalation(current.lockouts, current.last_failed_at, policy, now),
+            None => 0,
+        };
+
+        // One statement, so concurrent wrong codes cannot both slip under
+        // the limit. The count starts over once a lockout has ended, or
+        // after a cooldown without wrong codes.
+        let device_id = i64::from(device_id.as_u8());
+        let max_attempts = i64::from(policy.max_attempts);
+        let locked_until = now + policy.cooldown_after(level);
+        let idle_since = now - policy.cooldown;
+        let forgiven_since = now - policy.max_cooldown;
+        let lockout = sqlx::query_as!(
+            KeypadLockout,
+            r#"
+            INSERT INTO keypad_lockouts (device_id, failed_attempts, lockouts, last_failed_at, locked_until)
+            VALUES (?1, 1, CASE WHEN 1 >= ?3 THEN 1 ELSE 0 END, ?2, CASE WHEN 1 >= ?3 THEN ?4 END)
+            ON CONFLICT (device_id) DO UPDATE SET
+                failed_attempts = CASE
+                    WHEN keypad_lockouts.locked_until <= ?2
+                      OR (keypad_lockouts.locked_until IS NULL AND keypad_lockouts.last_failed_at <= ?5)
+                    THEN 1
+                    ELSE keypad_lockouts.failed_attempts + 1
+                END,
+                lockouts = CASE
+                    WHEN keypad_lockouts.last_failed_at <= ?6 THEN 0
+                    ELSE keypad_lockouts.lockouts
+                END + CASE
+                    WHEN keypad_lockouts.locked_until > ?2 THEN 0
+                    WHEN (CASE
+                        WHEN keypad_lockouts.locked_until <= ?2
+                          OR (keypad_lockouts.locked_until IS NULL AND keypad_lockouts.last_failed_at <= ?5)
+                        THEN 1
+                        ELSE keypad_lockouts.failed_attempts + 1
+                    END) >= ?3 THEN 1
+                    ELSE 0
+                END,
+                locked_until = CASE
+                    WHEN keypad_lockouts.locked_until > ?2 THEN keypad_lockouts.locked_until
+                    WHEN (CASE
+                        WHEN keypad_lockouts.locked_until <= ?2
+                          OR (keypad_lockouts.locked_until IS NULL AND keypad_lockouts.last_failed_at <= ?5)
+                        THEN 1
+                        ELSE keypad_lockouts.failed_attempts + 1
+                    END) >= ?3 THEN ?4
+                END,
+                last_failed_at = ?2
+            RETURNING device_id AS "device_id!", failed_attempts, lockouts,
+                      last_failed_at AS "last_failed_at: _", locked_until AS "locked_until: _"
+            "#,
+            device_id,
+            now,
+            max_attempts,
+            locked_until,
+            idle_since,
+            forgiven_since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    async fn reset(&self, device_id: DeviceId) -> StorageResult<()> {
+        let device_id = i64::from(device_id.as_u8());
+        sqlx::query!("DELETE FROM keypad_lockouts WHERE device_id = ?", device_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn find_user(&self, user_id: i64) -> StorageResult<Option<UserPinLockout>> {
+        let lockout = sqlx::query_as!(
+            UserPinLockout,
+            r#"
+            SELECT user_id, failed_attempts, lockouts,
+                   last_failed_at AS "last_failed_at: _",
+                   locked_until AS "locked_until: _"
+            FROM user_pin_lockouts
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    async fn record_user_failure(
+        &self,
+        user_id: i64,
+        policy: &KeypadLockoutPolicy,
+        now: DateTime<Utc>,
+    ) -> StorageResult<UserPinLockout> {
+        // Same rules as record_failure(), per user instead of per device
+        let level = match self.find_user(user_id).await? {
+            Some(current) => escalation(current.lockouts, current.last_failed_at, policy, now),
+            None => 0,
+        };
+
+        let max_attempts = i64::from(policy.max_attempts);
+        let locked_until = now + policy.cooldown_after(level);
+        let idle_since = now - policy.cooldown;
+        let forgiven_since = now - policy.max_cooldown;
+        let lockout = sqlx::query_as!(
+            UserPinLockout,
+            r#"
+            INSERT INTO user_pin_lockouts (user_id, failed_attempts, lockouts, last_failed_at, locked_until)
+            VALUES (?1, 1, CASE WHEN 1 >= ?3 THEN 1 ELSE 0 END, ?2, CASE WHEN 1 >= ?3 THEN ?4 END)
+            ON CONFLICT (user_id) DO UPDATE SET
+                failed_attempts = CASE
+                    WHEN user_pin_lockouts.locked_until <= ?2
+                      OR (user_pin_lockouts.locked_until IS NULL AND user_pin_lockouts.last_failed_at <= ?5)
+                    THEN 1
+                    ELSE user_pin_lockouts.failed_attempts + 1
+                END,
+                lockouts = CASE
+                    WHEN user_pin_lockouts.last_failed_at <= ?6 THEN 0
+                    ELSE user_pin_lockouts.lockouts
+                END + CASE
+                    WHEN user_pin_lockouts.locked_until > ?2 THEN 0
+                    WHEN (CASE
+                        WHEN user_pin_lockouts.locked_until <= ?2
+                          OR (user_pin_lockouts.locked_until IS NULL AND user_pin_lockouts.last_failed_at <= ?5)
+                        THEN 1
+                        ELSE user_pin_lockouts.failed_attempts + 1
+                    END) >= ?3 THEN 1
+                    ELSE 0
+                END,
+                locked_until = CASE
+                    WHEN user_pin_lockouts.locked_until > ?2 THEN user_pin_lockouts.locked_until
+                    WHEN (CASE
+                        WHEN user_pin_lockouts.locked_until <= ?2
+                          OR (user_pin_lockouts.locked_until IS NULL AND user_pin_lockouts.last_failed_at <= ?5)
+                        THEN 1
+                        ELSE user_pin_lockouts.failed_attempts + 1
+                    END) >= ?3 THEN ?4
+                END,
+                last_failed_at = ?2
+            RETURNING user_id AS "user_id!", failed_attempts, lockouts,
+                      last_failed_at AS "last_failed_at: _", locked_until AS "locked_until: _"
+            "#,
+            user_id,
+            now,
+            max_attempts,
+            locked_until,
+            idle_since,
+            forgiven_since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    async fn reset_user(&self, user_id: i64) -> StorageResult<()> {
+        sqlx::query!("DELETE FROM user_pin_lockouts WHERE user_id = ?", user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_locks_after_max_attempts() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        let device = DeviceId::new(15).unwrap();
+        let policy = KeypadLockoutPolicy::new(3, Duration::minutes(5));
+        let now = Utc::now();
+
+        assert!(repo.find(device).await.unwrap().is_none());
+        for attempt in 1..=2 {
+            let lockout = repo.record_failure(device, &policy, now).await.unwrap();
+            assert_eq!(lockout.failed_attempts, attempt);
+            assert!(!lockout.is_locked(now));
+        }
+
+        let lockout = repo.record_failure(device, &policy, now).await.unwrap();
+        assert!(lockout.is_locked(now));
+        assert!(lockout.is_locked(now + Duration::minutes(4)));
+        assert!(!lockout.is_locked(now + Duration::minutes(5)));
+
+        // Other devices are not affected
+        let other = DeviceId::new(16).unwrap();
+        assert!(repo.find(other).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_count_starts_over_after_lockout_and_cooldown() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        let device = DeviceId::new(15).unwrap();
+        let policy = KeypadLockoutPolicy::new(2, Duration::minutes(5));
+        let start = Utc::now();
+
+        repo.record_failure(device, &policy, start).await.unwrap();
+        let locked = repo.record_failure(device, &policy, start).await.unwrap();
+        assert!(locked.is_locked(start));
+
+        // Lockout over: the next wrong code counts as the first
+        let after = start + Duration::minutes(6);
+        let lockout = repo.record_failure(device, &policy, after).await.unwrap();
+        assert_eq!(lockout.failed_attempts, 1);
+        assert!(!lockout.is_locked(after));
+
+        // A quiet cooldown also forgives earlier wrong codes
+        let later = after + Duration::minutes(6);
+        let lockout = repo.record_failure(device, &policy, later).await.unwrap();
+        assert_eq!(lockout.failed_attempts, 1);
+        assert!(!lockout.is_locked(later));
+    }
+
+    #[tokio::test]
+    async fn test_reset_clears_attempts() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        let device = DeviceId::new(15).unwrap();
+        let policy = KeypadLockoutPolicy::default();
+
+        repo.record_failure(device, &policy, Utc::now())
+            .await
+            .unwrap();
+        repo.reset(device).await.unwrap();
+        assert!(repo.find(device).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lockouts_escalate_until_reset() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        let device = DeviceId::new(15).unwrap();
+        let policy = KeypadLockoutPolicy::new(2, Duration::minutes(5));
+        let mut now = Utc::now();
+
+        // Each lockout lasts twice as long as the one before
+        for (lockouts, minutes) in [(1, 5), (2, 10), (3, 20)] {
+            repo.record_failure(device, &policy, now).await.unwrap();
+            let locked = repo.record_failure(device, &policy, now).await.unwrap();
+            assert_eq!(locked.lockouts, lockouts);
+            assert_eq!(locked.remaining(now), Some(Duration::minutes(minutes)));
+
+            // Wrong codes while locked neither extend nor escalate
+            let during = repo
+                .record_failure(device, &policy, now + Duration::minutes(1))
+                .await
+                .unwrap();
+            assert_eq!(during.lockouts, lockouts);
+            assert_eq!(during.locked_until, locked.locked_until);
+
+            now = locked.locked_until.unwrap();
+        }
+
+        // A correct code starts over at the base cooldown
+        repo.reset(device).await.unwrap();
+        repo.record_failure(device, &policy, now).await.unwrap();
+        let locked = repo.record_failure(device, &policy, now).await.unwrap();
+        assert_eq!(locked.remaining(now), Some(Duration::minutes(5)));
+
+        // So does an hour without wrong codes
+        let later = locked.locked_until.unwrap() + Duration::hours(1);
+        repo.record_failure(device, &policy, later).await.unwrap();
+        let locked = repo.record_failure(device, &policy, later).await.unwrap();
+        assert_eq!(locked.lockouts, 1);
+        assert_eq!(locked.remaining(later), Some(Duration::minutes(5)));
+    }
+
+    #[tokio::test]
+    async fn test_user_lockout_is_separate_from_devices() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        let policy = KeypadLockoutPolicy::new(2, Duration::minutes(5));
+        let now = Utc::now();
+
+        assert!(repo.find_user(2).await.unwrap().is_none());
+        let first = repo.record_user_failure(2, &policy, now).await.unwrap();
+        assert!(!first.is_locked(now));
+        let locked = repo.record_user_failure(2, &policy, now).await.unwrap();
+        assert!(locked.is_locked(now));
+        assert_eq!(locked.lockouts, 1);
+        assert!(
+            repo.find(DeviceId::new(15).unwrap())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        repo.reset_user(2).await.unwrap();
+        assert!(repo.find_user(2).await.unwrap().is_none());
+    }
+}
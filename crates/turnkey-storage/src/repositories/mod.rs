@@ -1,7 +1,42 @@
+pub mod access_exception;
 pub mod access_log;
+pub mod access_override;
+pub mod anti_passback;
+pub mod biometric_template;
 pub mod card;
+pub mod card_block;
+pub mod config_profile;
+pub mod device;
+pub mod enrollment_session;
+pub mod holiday;
+pub mod keypad_lockout;
+pub mod pending_card;
+pub mod presence;
+pub mod quota;
+pub mod schedule;
+pub mod site;
+pub mod sync_state;
 pub mod user;
 
-pub use access_log::{AccessLogRepository, SqliteAccessLogRepository};
+pub use access_exception::{AccessExceptionRepository, SqliteAccessExceptionRepository};
+pub use access_log::{
+    AccessLogOrder, AccessLogQuery, AccessLogRepository, BulkInsertOptions, BulkInsertReport,
+    OnDuplicate, SqliteAccessLogRepository,
+};
+pub use access_override::{AccessOverrideRepository, SqliteAccessOverrideRepository};
+pub use anti_passback::{AntiPassbackRepository, SqliteAntiPassbackRepository};
+pub use biometric_template::{BiometricTemplateRepository, SqliteBiometricTemplateRepository};
 pub use card::{CardRepository, SqliteCardRepository};
+pub use card_block::{CardBlockRepository, SqliteCardBlockRepository};
+pub use config_profile::{ConfigProfileRepository, SqliteConfigProfileRepository};
+pub use device::{DeviceRepository, SqliteDeviceRepository};
+pub use enrollment_session::{EnrollmentSessionRepository, SqliteEnrollmentSessionRepository};
+pub use holiday::{HolidayRepository, SqliteHolidayRepository};
+pub use keypad_lockout::{KeypadLockoutRepository, SqliteKeypadLockoutRepository};
+pub use pending_card::{PendingCardRepository, SqlitePendingCardRepository};
+pub use presence::{PresenceRepository, SqlitePresenceRepository};
+pub use quota::{QuotaRepository, SqliteQuotaRepository};
+pub use schedule::{ScheduleRepository, SqliteScheduleRepository};
+pub use site::{SiteRepository, SqliteSiteRepository};
+pub use sync_state::{SqliteSyncStateRepository, SyncStateRepository};
 pub use user::{SqliteUserRepository, UserRepository};
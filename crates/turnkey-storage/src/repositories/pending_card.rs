@@ -0,0 +1,402 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{Card, PendingCard};
+use crate::transaction;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository trait for PendingCard entity operations
+///
+/// Backs the learning mode workflow: unknown reads are recorded, stored
+/// cards that look like the same badge are proposed, and the operator
+/// assigns the pending card to a user.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait PendingCardRepository: Send + Sync {
+    /// Record an unknown card read
+    ///
+    /// Inserts a new pending card or, if the normalized number is already
+    /// pending, increments its read count and updates the raw value,
+    /// device and last seen time. Returns the pending card ID.
+    async fn record(
+        &self,
+        raw_value: &str,
+        device_id: Option<i64>,
+        seen_at: DateTime<Utc>,
+    ) -> StorageResult<i64>;
+
+    /// Find a pending card by its ID
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<PendingCard>>;
+
+    /// Find a pending card by number (normalized before lookup)
+    async fn find_by_card_number(&self, card_number: &str) -> StorageResult<Option<PendingCard>>;
+
+    /// Get all pending cards, most recently read first
+    async fn find_all(&self) -> StorageResult<Vec<PendingCard>>;
+
+    /// Propose stored cards that may be the same badge in another format
+    ///
+    /// Compares the pending number against registered cards ignoring
+    /// leading zeros and accepting decimal/hexadecimal conversions (see
+    /// [`PendingCard::candidate_forms`]). Exact matches come first.
+    async fn find_candidates(&self, id: i64) -> StorageResult<Vec<Card>>;
+
+    /// Register the pending card for a user and remove it from the queue
+    ///
+    /// Creates an active card with the normalized number, without validity
+    /// limits, owned by the user with the given matricula. Returns the new
+    /// card ID.
+    async fn assign(&self, id: i64, matricula: &str) -> StorageResult<i64>;
+
+    /// Discard a pending card
+    async fn delete(&self, id: i64) -> StorageResult<()>;
+}
+
+/// SQLite implementation of PendingCardRepository
+pub struct SqlitePendingCardRepository {
+    pool: SqlitePool,
+}
+
+impl SqlitePendingCardRepository {
+    /// Create a new SQLite pending card repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn not_found(id: i64) -> StorageError {
+        StorageError::NotFound {
+            entity_type: "PendingCard".to_string(),
+            field: "id".to_string(),
+            value: id.to_string(),
+        }
+    }
+}
+
+impl PendingCardRepository for SqlitePendingCardRepository {
+    async fn record(
+        &self,
+        raw_value: &str,
+        device_id: Option<i64>,
+        seen_at: DateTime<Utc>,
+    ) -> StorageResult<i64> {
+        let card_number = Card::normalize_card_number(raw_value);
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO pending_cards (
+                raw_value, card_number, device_id, first_seen_at, last_seen_at
+            )
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (card_number) DO UPDATE SET
+                raw_value = excluded.raw_value,
+                device_id = excluded.device_id,
+                read_count = read_count + 1,
+                last_seen_at = excluded.last_seen_at
+            RETURNING id AS "id!"
+            "#,
+            raw_value,
+            card_number,
+            device_id,
+            seen_at,
+            seen_at
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<PendingCard>> {
+        let pending = sqlx::query_as!(
+            PendingCard,
+            r#"
+            SELECT id AS "id!", raw_value, card_number, device_id, read_count,
+                   first_seen_at AS "first_seen_at: _", last_seen_at AS "last_seen_at: _", matricula
+            FROM pending_cards
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(pending)
+    }
+
+    async fn find_by_card_number(&self, card_number: &str) -> StorageResult<Option<PendingCard>> {
+        let card_number = Card::normalize_card_number(card_number);
+        let pending = sqlx::query_as!(
+            PendingCard,
+            r#"
+            SELECT id AS "id!", raw_value, card_number, device_id, read_count,
+                   first_seen_at AS "first_seen_at: _", last_seen_at AS "last_seen_at: _", matricula
+            FROM pending_cards
+            WHERE card_number = ?
+            "#,
+            card_number
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(pending)
+    }
+
+    async fn find_all(&self) -> StorageResult<Vec<PendingCard>> {
+        let pending = sqlx::query_as!(
+            PendingCard,
+            r#"
+            SELECT id AS "id!", raw_value, card_number, device_id, read_count,
+                   first_seen_at AS "first_seen_at: _", last_seen_at AS "last_seen_at: _", matricula
+            FROM pending_cards
+            ORDER BY last_seen_at DESC, id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(pending)
+    }
+
+    async fn find_candidates(&self, id: i64) -> StorageResult<Vec<Card>> {
+        let pending = self
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| Self::not_found(id))?;
+
+        // ltrim() defeats the numero_cartao index; acceptable for an
+        // operator-driven lookup, never used on the validation path
+        let mut candidates: Vec<Card> = Vec::new();
+        for form in PendingCard::candidate_forms(&pending.card_number) {
+            let cards = sqlx::query_as!(
+                Card,
+                r#"
+                SELECT id AS "id!", numero_cartao, matricula, user_id,
+                       validade_inicio AS "validade_inicio: _", validade_fim AS "validade_fim: _",
+                       ativo, created_at AS "created_at: _", updated_at AS "updated_at: _"
+                FROM cards
+                WHERE ltrim(numero_cartao, '0') = ?
+                ORDER BY id
+                "#,
+                form
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            for card in cards {
+                if !candidates.iter().any(|c| c.id == card.id) {
+                    candidates.push(card);
+                }
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    async fn assign(&self, id: i64, matricula: &str) -> StorageResult<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let card_number =
+            sqlx::query_scalar!("SELECT card_number FROM pending_cards WHERE id = ?", id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| Self::not_found(id))?;
+
+        let user_id = sqlx::query_scalar!(
+            r#"SELECT id AS "id!" FROM users WHERE matricula = ?"#,
+            matricula
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| StorageError::NotFound {
+            entity_type: "User".to_string(),
+            field: "matricula".to_string(),
+            value: matricula.to_string(),
+        })?;
+
+        let now = Utc::now();
+        let card = Card {
+            id: 0,
+            numero_cartao: card_number,
+            matricula: matricula.to_string(),
+            user_id,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let card_id = transaction::create_card(&mut tx, &card).await?;
+
+        sqlx::query!("DELETE FROM pending_cards WHERE id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(card_id)
+    }
+
+    async fn delete(&self, id: i64) -> StorageResult<()> {
+        let result = sqlx::query!("DELETE FROM pending_cards WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(id));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::User;
+    use crate::repositories::{
+        CardRepository, SqliteCardRepository, SqliteUserRepository, UserRepository,
+    };
+    use chrono::Duration;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn create_test_user(db: &Database, matricula: &str) -> i64 {
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Pending Test".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: true,
+            allow_bio: false,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteUserRepository::new(db.pool().clone())
+            .create(&user)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_new_and_repeated_reads() {
+        let db = setup_test_db().await;
+        let repo = SqlitePendingCardRepository::new(db.pool().clone());
+
+        let first_seen = Utc::now() - Duration::minutes(5);
+        let id = repo.record(" ab12cd34", Some(3), first_seen).await.unwrap();
+        let again = repo.record("AB12CD34", Some(7), Utc::now()).await.unwrap();
+        assert_eq!(id, again);
+
+        let pending = repo.find_by_card_number("ab12cd34").await.unwrap().unwrap();
+        assert_eq!(pending.card_number, "AB12CD34");
+        assert_eq!(pending.raw_value, "AB12CD34");
+        assert_eq!(pending.device_id, Some(7));
+        assert_eq!(pending.read_count, 2);
+        assert_eq!(pending.first_seen_at, first_seen);
+        assert!(pending.last_seen_at > first_seen);
+
+        assert_eq!(repo.find_all().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_find_candidates_across_formats() {
+        let db = setup_test_db().await;
+        let repo = SqlitePendingCardRepository::new(db.pool().clone());
+        let card_repo = SqliteCardRepository::new(db.pool().clone());
+
+        let user_id = create_test_user(&db, "PND001").await;
+        for numero in ["00002E9D599", "48879001", "77777777"] {
+            let card = Card {
+                id: 0,
+                numero_cartao: numero.to_string(),
+                matricula: "PND001".to_string(),
+                user_id,
+                validade_inicio: None,
+                validade_fim: None,
+                ativo: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+            card_repo.create(&card).await.unwrap();
+        }
+
+        // Reader reports the decimal number with extra leading zeros
+        let id = repo.record("0048879001", None, Utc::now()).await.unwrap();
+        let candidates = repo.find_candidates(id).await.unwrap();
+
+        let numbers: Vec<_> = candidates
+            .iter()
+            .map(|c| c.numero_cartao.as_str())
+            .collect();
+        assert_eq!(numbers, vec!["48879001", "00002E9D599"]);
+
+        assert!(matches!(
+            repo.find_candidates(9999).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assign_creates_card_and_clears_pending() {
+        let db = setup_test_db().await;
+        let repo = SqlitePendingCardRepository::new(db.pool().clone());
+        let user_id = create_test_user(&db, "PND002").await;
+
+        let id = repo.record("fe12dc34", Some(1), Utc::now()).await.unwrap();
+        let card_id = repo.assign(id, "PND002").await.unwrap();
+        assert!(card_id > 0);
+
+        let card = SqliteCardRepository::new(db.pool().clone())
+            .find_by_number("FE12DC34")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(card.id, card_id);
+        assert_eq!(card.user_id, user_id);
+        assert!(card.ativo);
+
+        assert!(repo.find_by_id(id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_assign_unknown_user_keeps_pending() {
+        let db = setup_test_db().await;
+        let repo = SqlitePendingCardRepository::new(db.pool().clone());
+
+        let id = repo.record("1357913579", None, Utc::now()).await.unwrap();
+        assert!(matches!(
+            repo.assign(id, "NOBODY").await,
+            Err(StorageError::NotFound { .. })
+        ));
+        assert!(repo.find_by_id(id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_pending_card() {
+        let db = setup_test_db().await;
+        let repo = SqlitePendingCardRepository::new(db.pool().clone());
+
+        let id = repo.record("2468024680", None, Utc::now()).await.unwrap();
+        repo.delete(id).await.unwrap();
+
+        assert!(repo.find_all().await.unwrap().is_empty());
+        assert!(matches!(
+            repo.delete(id).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::messages::DisplayMessages;
+use crate::models::{Card, Direction, Occupant};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository trait for the "who is inside" presence report
+///
+/// Occupancy comes from the `access_state` projection, so the report costs
+/// one indexed scan regardless of log volume. Corrections for missed exits
+/// are written as access logs rather than patched into the projection, so
+/// they survive an [`AccessStateReplay`](crate::replay::AccessStateReplay),
+/// show up in the audit trail, and release anti-passback for the next entry.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait PresenceRepository: Send + Sync {
+    /// Everyone currently inside, longest stay first
+    async fn find_occupants(&self) -> StorageResult<Vec<Occupant>>;
+
+    /// The occupant holding a credential, if it is inside
+    async fn find_occupant(&self, card_number: &str) -> StorageResult<Option<Occupant>>;
+
+    /// Number of credentials currently inside
+    async fn count_inside(&self) -> StorageResult<i64>;
+
+    /// Record a missed exit for a credential
+    ///
+    /// Writes a granted exit log at `at`, copying user and reader type from
+    /// the entry and leaving the device empty, with
+    /// `DisplayMessages::MANUAL_EXIT` as message. Returns the log ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the credential is not inside.
+    async fn mark_exited(&self, card_number: &str, at: DateTime<Utc>) -> StorageResult<i64>;
+
+    /// Record a missed exit for everyone still inside (end-of-day sweep)
+    ///
+    /// Returns the number of credentials marked as exited.
+    async fn mark_all_exited(&self, at: DateTime<Utc>) -> StorageResult<u64>;
+}
+
+/// SQLite implementation of PresenceRepository
+pub struct SqlitePresenceRepository {
+    pool: SqlitePool,
+}
+
+impl SqlitePresenceRepository {
+    /// Create a new SQLite presence repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl PresenceRepository for SqlitePresenceRepository {
+    async fn find_occupants(&self) -> StorageResult<Vec<Occupant>> {
+        // Occupants with their user and the device of their last granted passage
+        let occupants = sqlx::query_as!(
+            Occupant,
+            r#"
+            SELECT s.card_number AS "card_number!", s.user_id, u.matricula, u.nome,
+                   s.last_granted_at AS "entered_at!: _",
+                   (SELECT l.device_id FROM access_logs l
+                    WHERE l.card_number = s.card_number AND l.granted = 1
+                    ORDER BY l.id DESC LIMIT 1) AS device_id
+            FROM access_state s
+            LEFT JOIN users u ON u.id = s.user_id
+            WHERE s.inside = 1
+            ORDER BY s.last_granted_at, s.card_number
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(occupants)
+    }
+
+    async fn find_occupant(&self, card_number: &str) -> StorageResult<Option<Occupant>> {
+        let card_number = Card::normalize_card_number(card_number);
+        let occupant = sqlx::query_as!(
+            Occupant,
+            r#"
+            SELECT s.card_number AS "card_number!", s.user_id, u.matricula, u.nome,
+                   s.last_granted_at AS "entered_at!: _",
+                   (SELECT l.device_id FROM access_logs l
+                    WHERE l.card_number = s.card_number AND l.granted = 1
+                    ORDER BY l.id DESC LIMIT 1) AS device_id
+            FROM access_state s
+            LEFT JOIN users u ON u.id = s.user_id
+            WHERE s.inside = 1
+              AND s.card_number = ?
+            "#,
+            card_number
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(occupant)
+    }
+
+    async fn count_inside(&self) -> StorageResult<i64> {
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM access_state WHERE inside = 1")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn mark_exited(&self, card_number: &str, at: DateTime<Utc>) -> StorageResult<i64> {
+        let card_number = Card::normalize_card_number(card_number);
+        let direction = Direction::Exit as i32;
+        // Exit log built from the credential's entry log
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO access_logs (
+                user_id, matricula, card_number, direction,
+                reader_type, granted, display_message, timestamp
+            )
+            SELECT l.user_id, l.matricula, l.card_number, ?,
+                   l.reader_type, 1, ?, ?
+            FROM access_state s
+            JOIN access_logs l ON l.id = (
+                SELECT g.id FROM access_logs g
+                WHERE g.card_number = s.card_number AND g.granted = 1
+                ORDER BY g.id DESC LIMIT 1
+            )
+            WHERE s.inside = 1
+              AND s.card_number = ?
+            "#,
+            direction,
+            DisplayMessages::MANUAL_EXIT,
+            at,
+            card_number
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound {
+                entity_type: "Occupant".to_string(),
+                field: "card_number".to_string(),
+                value: card_number,
+            });
+        }
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn mark_all_exited(&self, at: DateTime<Utc>) -> StorageResult<u64> {
+        let direction = Direction::Exit as i32;
+        // One exit log per inside credential, built from its entry log
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO access_logs (
+                user_id, matricula, card_number, direction,
+                reader_type, granted, display_message, timestamp
+            )
+            SELECT l.user_id, l.matricula, l.card_number, ?,
+                   l.reader_type, 1, ?, ?
+            FROM access_state s
+            JOIN access_logs l ON l.id = (
+                SELECT g.id FROM access_logs g
+                WHERE g.card_number = s.card_number AND g.granted = 1
+                ORDER BY g.id DESC LIMIT 1
+            )
+            WHERE s.inside = 1
+            "#,
+            direction,
+            DisplayMessages::MANUAL_EXIT,
+            at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{AccessLog, ReaderType};
+    use crate::replay::AccessStateReplay;
+    use crate::repositories::{
+        AccessLogRepository, SqliteAccessLogRepository, SqliteUserRepository, UserRepository,
+    };
+    use chrono::Duration;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    /// Log a granted passage of a seeded user
+    async fn pass(
+        db: &Database,
+        matricula: &str,
+        card_number: &str,
+        direction: Direction,
+        at: DateTime<Utc>,
+    ) {
+        let user = SqliteUserRepository::new(db.pool().clone())
+            .find_by_matricula(matricula)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut log = AccessLog::new(
+            Some(user.id),
+            Some(matricula.to_string()),
+            card_number.to_string(),
+            direction,
+            ReaderType::Biometric,
+            true,
+            None,
+            at,
+        );
+        log.device_id = Some(4);
+        SqliteAccessLogRepository::new(db.pool().clone())
+            .create(&log)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_occupants_follows_passages() {
+        let db = setup_test_db().await;
+        let repo = SqlitePresenceRepository::new(db.pool().clone());
+        let start = Utc::now() - Duration::hours(3);
+        let seeded = repo.count_inside().await.unwrap();
+
+        pass(
+            &db,
+            "1002",
+            "2000000002",
+            Direction::Entry,
+            start + Duration::hours(1),
+        )
+        .await;
+        pass(&db, "1001", "2000000001", Direction::Entry, start).await;
+        pass(&db, "1003", "2000000003", Direction::Entry, start).await;
+        pass(
+            &db,
+            "1003",
+            "2000000003",
+            Direction::Exit,
+            start + Duration::hours(2),
+        )
+        .await;
+
+        // Seed data has occupants of its own
+        let occupants: Vec<_> = repo
+            .find_occupants()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|o| o.card_number.starts_with("20000000"))
+            .collect();
+        let matriculas: Vec<_> = occupants
+            .iter()
+            .map(|o| o.matricula.as_deref().unwrap())
+            .collect();
+        assert_eq!(matriculas, vec!["1001", "1002"]);
+        assert_eq!(occupants[0].entered_at, start);
+        assert_eq!(occupants[0].device_id, Some(4));
+        assert!(occupants[0].nome.is_some());
+
+        assert_eq!(repo.count_inside().await.unwrap(), seeded + 2);
+        assert!(repo.find_occupant("2000000003").await.unwrap().is_none());
+        assert!(repo.find_occupant("2000000002").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_mark_exited_writes_replayable_log() {
+        let db = setup_test_db().await;
+        let repo = SqlitePresenceRepository::new(db.pool().clone());
+        let seeded = repo.count_inside().await.unwrap();
+
+        pass(&db, "1004", "2000000004", Direction::Entry, Utc::now()).await;
+
+        let log_id = repo.mark_exited("2000000004", Utc::now()).await.unwrap();
+        assert!(repo.find_occupant("2000000004").await.unwrap().is_none());
+        assert!(matches!(
+            repo.mark_exited("2000000004", Utc::now()).await,
+            Err(StorageError::NotFound { .. })
+        ));
+
+        let log = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number("2000000004", 1)
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(log.id, log_id);
+        assert_eq!(log.get_direction(), Some(Direction::Exit));
+        assert_eq!(log.reader_type, ReaderType::Biometric as i32);
+        assert_eq!(log.matricula.as_deref(), Some("1004"));
+        assert_eq!(log.device_id, None);
+        assert_eq!(
+            log.display_message.as_deref(),
+            Some(DisplayMessages::MANUAL_EXIT)
+        );
+
+        // The correction is part of the log, so a rebuild keeps it
+        AccessStateReplay::new(db.pool().clone())
+            .rebuild(|_| {})
+            .await
+            .unwrap();
+        assert_eq!(repo.count_inside().await.unwrap(), seeded);
+    }
+
+    #[tokio::test]
+    async fn test_mark_all_exited() {
+        let db = setup_test_db().await;
+        let repo = SqlitePresenceRepository::new(db.pool().clone());
+        let seeded = repo.count_inside().await.unwrap() as u64;
+
+        pass(&db, "1005", "2000000005", Direction::Entry, Utc::now()).await;
+        pass(&db, "1006", "2000000006", Direction::Entry, Utc::now()).await;
+
+        assert_eq!(repo.mark_all_exited(Utc::now()).await.unwrap(), seeded + 2);
+        assert!(repo.find_occupants().await.unwrap().is_empty());
+        assert_eq!(repo.mark_all_exited(Utc::now()).await.unwrap(), 0);
+    }
+}
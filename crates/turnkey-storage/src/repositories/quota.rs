@@ -0,0 +1,282 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{DailyQuota, Direction};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository trait for daily passage quotas
+///
+/// Quotas are set per user or per company (`users.empresa`). Passages are
+/// counted straight from `access_logs`: granted entries and passages
+/// without a direction count, exits do not, so leaving the gym never uses
+/// up a visit.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait QuotaRepository: Send + Sync {
+    /// Set the daily quota of a user, replacing any previous one
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if `max_passages` is below 1.
+    async fn set_user_quota(&self, user_id: i64, max_passages: i64) -> StorageResult<()>;
+
+    /// Set the daily quota of every user of a company
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if `max_passages` is below 1 or the company name
+    /// is empty or longer than 100 characters.
+    async fn set_company_quota(&self, empresa: &str, max_passages: i64) -> StorageResult<()>;
+
+    /// Remove the quota of a user; returns `false` if there was none
+    async fn remove_user_quota(&self, user_id: i64) -> StorageResult<bool>;
+
+    /// Remove the quota of a company; returns `false` if there was none
+    async fn remove_company_quota(&self, empresa: &str) -> StorageResult<bool>;
+
+    /// Get all quotas, user quotas first
+    async fn find_all(&self) -> StorageResult<Vec<DailyQuota>>;
+
+    /// Get the passages per day allowed to a user, `None` if unlimited
+    ///
+    /// The user's own quota wins over the quota of their company.
+    async fn find_effective(
+        &self,
+        user_id: i64,
+        empresa: Option<&str>,
+    ) -> StorageResult<Option<i64>>;
+
+    /// Count the granted passages of a user in `[start, end)`
+    async fn count_passages(
+        &self,
+        user_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> StorageResult<i64>;
+}
+
+/// SQLite implementation of QuotaRepository
+pub struct SqliteQuotaRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteQuotaRepository {
+    /// Create a new SQLite quota repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn check_max_passages(max_passages: i64) -> StorageResult<()> {
+        if max_passages < 1 {
+            return Err(StorageError::Validation(format!(
+                "Daily quota must allow at least 1 passage, got {}",
+                max_passages
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl QuotaRepository for SqliteQuotaRepository {
+    async fn set_user_quota(&self, user_id: i64, max_passages: i64) -> StorageResult<()> {
+        Self::check_max_passages(max_passages)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_quotas (user_id, max_passages) VALUES (?, ?)
+            ON CONFLICT (user_id) DO UPDATE SET
+                max_passages = excluded.max_passages,
+                updated_at = datetime('now')
+            "#,
+            user_id,
+            max_passages
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_company_quota(&self, empresa: &str, max_passages: i64) -> StorageResult<()> {
+        Self::check_max_passages(max_passages)?;
+        let empresa = empresa.trim();
+        if empresa.is_empty() || empresa.chars().count() > 100 {
+            return Err(StorageError::Validation(
+                "Company name must be 1-100 characters".to_string(),
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO daily_quotas (empresa, max_passages) VALUES (?, ?)
+            ON CONFLICT (empresa) DO UPDATE SET
+                max_passages = excluded.max_passages,
+                updated_at = datetime('now')
+            "#,
+            empresa,
+            max_passages
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_user_quota(&self, user_id: i64) -> StorageResult<bool> {
+        let result = sqlx::query!("DELETE FROM daily_quotas WHERE user_id = ?", user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove_company_quota(&self, empresa: &str) -> StorageResult<bool> {
+        let empresa = empresa.trim();
+        let result = sqlx::query!("DELETE FROM daily_quotas WHERE empresa = ?", empresa)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_all(&self) -> StorageResult<Vec<DailyQuota>> {
+        let quotas = sqlx::query_as!(
+            DailyQuota,
+            r#"
+            SELECT id AS "id!", user_id, empresa, max_passages, created_at AS "created_at: _",
+                   updated_at AS "updated_at: _"
+            FROM daily_quotas
+            ORDER BY user_id IS NULL, user_id, empresa
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(quotas)
+    }
+
+    async fn find_effective(
+        &self,
+        user_id: i64,
+        empresa: Option<&str>,
+    ) -> StorageResult<Option<i64>> {
+        let empresa = empresa.map(str::trim);
+        let max_passages: Option<i64> = sqlx::query_scalar!(
+            r#"
+            SELECT max_passages FROM daily_quotas
+            WHERE user_id = ? OR (empresa IS NOT NULL AND empresa = ?)
+            ORDER BY user_id IS NULL
+            LIMIT 1
+            "#,
+            user_id,
+            empresa
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(max_passages)
+    }
+
+    async fn count_passages(
+        &self,
+        user_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> StorageResult<i64> {
+        // Served by idx_access_logs_user_timestamp
+        let count: i64 = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM access_logs
+            WHERE user_id = ?
+              AND timestamp >= ? AND timestamp < ?
+              AND granted = 1
+              AND direction != ?
+            "#,
+            user_id,
+            start,
+            end,
+            Direction::Exit as i32
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{AccessLog, ReaderType};
+    use crate::repositories::access_log::{AccessLogRepository, SqliteAccessLogRepository};
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_user_quota_overrides_company_quota() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteQuotaRepository::new(db.pool().clone());
+
+        assert_eq!(repo.find_effective(1, Some("Acme")).await.unwrap(), None);
+
+        repo.set_company_quota(" Acme ", 2).await.unwrap();
+        assert_eq!(repo.find_effective(1, Some("Acme")).await.unwrap(), Some(2));
+        assert_eq!(repo.find_effective(1, None).await.unwrap(), None);
+
+        repo.set_user_quota(1, 5).await.unwrap();
+        repo.set_user_quota(1, 3).await.unwrap();
+        assert_eq!(repo.find_effective(1, Some("Acme")).await.unwrap(), Some(3));
+        assert_eq!(repo.find_all().await.unwrap().len(), 2);
+
+        assert!(repo.remove_user_quota(1).await.unwrap());
+        assert!(!repo.remove_user_quota(1).await.unwrap());
+        assert_eq!(repo.find_effective(1, Some("Acme")).await.unwrap(), Some(2));
+        assert!(repo.remove_company_quota("Acme").await.unwrap());
+
+        assert!(matches!(
+            repo.set_user_quota(1, 0).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.set_company_quota(" ", 1).await,
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_count_passages_skips_exits_and_denials() {
+        let db = Database::in_memory().await.unwrap();
+        let repo = SqliteQuotaRepository::new(db.pool().clone());
+        let logs = SqliteAccessLogRepository::new(db.pool().clone());
+        let start = Utc::now() - Duration::hours(1);
+
+        for (direction, granted, minutes) in [
+            (Direction::Entry, true, 10),
+            (Direction::Undefined, true, 20),
+            (Direction::Exit, true, 30),
+            (Direction::Entry, false, 40),
+            (Direction::Entry, true, -10),
+        ] {
+            let log = AccessLog::new(
+                Some(1),
+                Some("1001".to_string()),
+                "1234567890".to_string(),
+                direction,
+                ReaderType::Rfid,
+                granted,
+                None,
+                start + Duration::minutes(minutes),
+            );
+            logs.create(&log).await.unwrap();
+        }
+
+        let end = start + Duration::hours(1);
+        assert_eq!(repo.count_passages(1, start, end).await.unwrap(), 2);
+        assert_eq!(repo.count_passages(2, start, end).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,526 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{CompiledSchedule, TimeInterval, WeeklySchedule};
+use chrono::{NaiveDate, Weekday};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+/// Repository trait for weekly access schedules
+///
+/// Schedules are validated with [`WeeklySchedule::validate()`] before being
+/// written, so a stored schedule never has overlapping intervals. Intervals
+/// and exceptions are saved together with the schedule in one transaction.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait ScheduleRepository: Send + Sync {
+    /// Create a schedule with its intervals and exceptions
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the schedule is invalid.
+    async fn create(&self, schedule: &WeeklySchedule) -> StorageResult<i64>;
+
+    /// Find a schedule by its ID
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<WeeklySchedule>>;
+
+    /// Find a schedule by its unique name
+    async fn find_by_name(&self, name: &str) -> StorageResult<Option<WeeklySchedule>>;
+
+    /// Get all schedules, ordered by name
+    async fn find_all(&self) -> StorageResult<Vec<WeeklySchedule>>;
+
+    /// Find a schedule and compile it for lookups
+    async fn find_compiled(&self, id: i64) -> StorageResult<Option<CompiledSchedule>>;
+
+    /// Replace the name, intervals and exceptions of a schedule
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the schedule is invalid and `NotFound` if it
+    /// does not exist.
+    async fn update(&self, schedule: &WeeklySchedule) -> StorageResult<()>;
+
+    /// Delete a schedule with its intervals and exceptions
+    ///
+    /// Users the schedule was assigned to are no longer restricted.
+    async fn delete(&self, id: i64) -> StorageResult<()>;
+
+    /// Restrict a user to a schedule, replacing any previous one
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the schedule does not exist.
+    async fn assign_user(&self, user_id: i64, schedule_id: i64) -> StorageResult<()>;
+
+    /// Lift the schedule of a user; returns `false` if there was none
+    async fn unassign_user(&self, user_id: i64) -> StorageResult<bool>;
+
+    /// Find the schedule of a user and compile it, `None` if unrestricted
+    async fn find_compiled_for_user(&self, user_id: i64)
+    -> StorageResult<Option<CompiledSchedule>>;
+}
+
+/// SQLite implementation of ScheduleRepository
+pub struct SqliteScheduleRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteScheduleRepository {
+    /// Create a new SQLite schedule repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn not_found(id: i64) -> StorageError {
+        StorageError::NotFound {
+            entity_type: "Schedule".to_string(),
+            field: "id".to_string(),
+            value: id.to_string(),
+        }
+    }
+
+    fn interval(start_minute: i64, end_minute: i64) -> StorageResult<TimeInterval> {
+        let bound = |minute: i64| {
+            u16::try_from(minute).map_err(|_| {
+                StorageError::Internal(format!("Invalid schedule minute {} in database", minute))
+            })
+        };
+        TimeInterval::new(bound(start_minute)?, bound(end_minute)?)
+    }
+
+    /// Load intervals and exceptions for schedule rows
+    async fn load(&self, rows: Vec<(i64, String)>) -> StorageResult<Vec<WeeklySchedule>> {
+        let mut schedules = Vec::with_capacity(rows.len());
+
+        for (id, name) in rows {
+            let mut schedule = WeeklySchedule::new(name);
+            schedule.id = id;
+
+            let intervals = sqlx::query!(
+                r#"
+                SELECT weekday, start_minute, end_minute
+                FROM schedule_intervals
+                WHERE schedule_id = ?
+                ORDER BY weekday, start_minute
+                "#,
+                id
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            for row in intervals {
+                let weekday = u8::try_from(row.weekday - 1)
+                    .ok()
+                    .and_then(|day| Weekday::try_from(day).ok())
+                    .ok_or_else(|| {
+                        StorageError::Internal(format!(
+                            "Invalid weekday {} in database",
+                            row.weekday
+                        ))
+                    })?;
+                schedule = schedule
+                    .with_interval(weekday, Self::interval(row.start_minute, row.end_minute)?);
+            }
+
+            let exceptions = sqlx::query!(
+                r#"
+                SELECT e.date AS "date: NaiveDate", i.start_minute AS "start_minute?",
+                       i.end_minute AS "end_minute?"
+                FROM schedule_exceptions e
+                LEFT JOIN schedule_exception_intervals i ON i.exception_id = e.id
+                WHERE e.schedule_id = ?
+                ORDER BY e.date, i.start_minute
+                "#,
+                id
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            for row in exceptions {
+                let intervals = schedule.exceptions.entry(row.date).or_default();
+                if let (Some(start_minute), Some(end_minute)) = (row.start_minute, row.end_minute) {
+                    intervals.push(Self::interval(start_minute, end_minute)?);
+                }
+            }
+
+            schedules.push(schedule);
+        }
+
+        Ok(schedules)
+    }
+
+    /// Insert the intervals and exceptions of a schedule
+    pub(crate) async fn insert_children(
+        tx: &mut Transaction<'_, Sqlite>,
+        schedule_id: i64,
+        schedule: &WeeklySchedule,
+    ) -> StorageResult<()> {
+        for (day, intervals) in schedule.days.iter().enumerate() {
+            let weekday = day as i64 + 1;
+            for interval in intervals {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO schedule_intervals (schedule_id, weekday, start_minute, end_minute)
+                    VALUES (?, ?, ?, ?)
+                    "#,
+                    schedule_id,
+                    weekday,
+                    interval.start_minute,
+                    interval.end_minute
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+
+        for (date, intervals) in &schedule.exceptions {
+            let exception_id = sqlx::query!(
+                "INSERT INTO schedule_exceptions (schedule_id, date) VALUES (?, ?)",
+                schedule_id,
+                date
+            )
+            .execute(&mut **tx)
+            .await?
+            .last_insert_rowid();
+
+            for interval in intervals {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO schedule_exception_intervals
+                        (exception_id, start_minute, end_minute)
+                    VALUES (?, ?, ?)
+                    "#,
+                    exception_id,
+                    interval.start_minute,
+                    interval.end_minute
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ScheduleRepository for SqliteScheduleRepository {
+    async fn create(&self, schedule: &WeeklySchedule) -> StorageResult<i64> {
+        schedule.validate()?;
+
+        let mut tx = self.pool.begin().await?;
+        let id = sqlx::query!("INSERT INTO schedules (name) VALUES (?)", schedule.name)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+        Self::insert_children(&mut tx, id, schedule).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, id: i64) -> StorageResult<Option<WeeklySchedule>> {
+        let rows = sqlx::query!("SELECT id, name FROM schedules WHERE id = ?", id)
+            .fetch_all(&self.pool)
+            .await?;
+        let rows = rows.into_iter().map(|row| (row.id, row.name)).collect();
+
+        Ok(self.load(rows).await?.pop())
+    }
+
+    async fn find_by_name(&self, name: &str) -> StorageResult<Option<WeeklySchedule>> {
+        let rows = sqlx::query!(
+            r#"SELECT id AS "id!", name FROM schedules WHERE name = ?"#,
+            name
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let rows = rows.into_iter().map(|row| (row.id, row.name)).collect();
+
+        Ok(self.load(rows).await?.pop())
+    }
+
+    async fn find_all(&self) -> StorageResult<Vec<WeeklySchedule>> {
+        let rows = sqlx::query!(r#"SELECT id AS "id!", name FROM schedules ORDER BY name"#)
+            .fetch_all(&self.pool)
+            .await?;
+        let rows = rows.into_iter().map(|row| (row.id, row.name)).collect();
+
+        self.load(rows).await
+    }
+
+    async fn find_compiled(&self, id: i64) -> StorageResult<Option<CompiledSchedule>> {
+        self.find_by_id(id)
+            .await?
+            .map(|schedule| schedule.compile())
+            .transpose()
+    }
+
+    async fn update(&self, schedule: &WeeklySchedule) -> StorageResult<()> {
+        schedule.validate()?;
+
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "UPDATE schedules SET name = ? WHERE id = ?",
+            schedule.name,
+            schedule.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(schedule.id));
+        }
+
+        sqlx::query!(
+            "DELETE FROM schedule_intervals WHERE schedule_id = ?",
+            schedule.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM schedule_exceptions WHERE schedule_id = ?",
+            schedule.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        Self::insert_children(&mut tx, schedule.id, schedule).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: i64) -> StorageResult<()> {
+        let result = sqlx::query!("DELETE FROM schedules WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found(id));
+        }
+
+        Ok(())
+    }
+
+    async fn assign_user(&self, user_id: i64, schedule_id: i64) -> StorageResult<()> {
+        let exists = sqlx::query_scalar!("SELECT id FROM schedules WHERE id = ?", schedule_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(Self::not_found(schedule_id));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_schedules (user_id, schedule_id) VALUES (?, ?)
+            ON CONFLICT (user_id) DO UPDATE SET schedule_id = excluded.schedule_id
+            "#,
+            user_id,
+            schedule_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unassign_user(&self, user_id: i64) -> StorageResult<bool> {
+        let result = sqlx::query!("DELETE FROM user_schedules WHERE user_id = ?", user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_compiled_for_user(
+        &self,
+        user_id: i64,
+    ) -> StorageResult<Option<CompiledSchedule>> {
+        let schedule_id: Option<i64> = sqlx::query_scalar!(
+            "SELECT schedule_id FROM user_schedules WHERE user_id = ?",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match schedule_id {
+            Some(id) => self.find_compiled(id).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use chrono::NaiveDateTime;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    fn interval(s: &str) -> TimeInterval {
+        TimeInterval::parse(s).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 12, day).unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        date(day).and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn night_shift() -> WeeklySchedule {
+        WeeklySchedule::new("Turno noturno")
+            .with_interval_on(
+                &[Weekday::Mon, Weekday::Tue, Weekday::Wed],
+                interval("23:00-02:00"),
+            )
+            .with_interval(Weekday::Sat, interval("08:00-12:00"))
+            .with_interval(Weekday::Sat, interval("13:00-17:00"))
+            .with_exception(date(24), vec![])
+            .with_exception(date(27), vec![interval("09:00-11:00")])
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_roundtrip() {
+        let db = setup_test_db().await;
+        let repo = SqliteScheduleRepository::new(db.pool().clone());
+        let schedule = night_shift();
+
+        let id = repo.create(&schedule).await.unwrap();
+        let found = repo.find_by_id(id).await.unwrap().unwrap();
+
+        assert_eq!(found.id, id);
+        assert_eq!(found.days, schedule.days);
+        assert_eq!(found.exceptions, schedule.exceptions);
+        assert_eq!(
+            repo.find_by_name("Turno noturno")
+                .await
+                .unwrap()
+                .unwrap()
+                .id,
+            id
+        );
+        assert!(repo.find_by_id(id + 1).await.unwrap().is_none());
+
+        // 2025-12-22 is a Monday; the 24th (Wednesday) is closed
+        let compiled = repo.find_compiled(id).await.unwrap().unwrap();
+        assert!(compiled.is_allowed(at(23, 1, 30)));
+        assert!(compiled.is_allowed(at(24, 1, 30)));
+        assert!(!compiled.is_allowed(at(24, 23, 30)));
+        assert!(!compiled.is_allowed(at(25, 1, 30)));
+        assert!(compiled.is_allowed(at(27, 10, 0)));
+        assert!(!compiled.is_allowed(at(27, 12, 30)));
+    }
+
+    #[tokio::test]
+    async fn test_update_replaces_intervals() {
+        let db = setup_test_db().await;
+        let repo = SqliteScheduleRepository::new(db.pool().clone());
+        let id = repo.create(&night_shift()).await.unwrap();
+
+        let mut schedule =
+            WeeklySchedule::new("Comercial").with_interval(Weekday::Mon, interval("08:00-18:00"));
+        schedule.id = id;
+        repo.update(&schedule).await.unwrap();
+
+        let found = repo.find_by_id(id).await.unwrap().unwrap();
+        assert_eq!(found, schedule);
+        assert!(found.exceptions.is_empty());
+
+        schedule.id = id + 1;
+        assert!(matches!(
+            repo.update(&schedule).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_schedule_not_stored() {
+        let db = setup_test_db().await;
+        let repo = SqliteScheduleRepository::new(db.pool().clone());
+
+        let overlapping = WeeklySchedule::new("Sobreposto")
+            .with_interval(Weekday::Fri, interval("22:00-04:00"))
+            .with_interval(Weekday::Sat, interval("03:00-05:00"));
+        assert!(matches!(
+            repo.create(&overlapping).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(repo.find_all().await.unwrap().is_empty());
+
+        // An invalid update leaves the stored schedule untouched
+        let id = repo.create(&night_shift()).await.unwrap();
+        let mut invalid = overlapping;
+        invalid.id = id;
+        assert!(repo.update(&invalid).await.is_err());
+        assert_eq!(
+            repo.find_by_id(id).await.unwrap().unwrap().days,
+            night_shift().days
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_all_and_delete() {
+        let db = setup_test_db().await;
+        let repo = SqliteScheduleRepository::new(db.pool().clone());
+        let night = repo.create(&night_shift()).await.unwrap();
+        repo.create(&WeeklySchedule::new("Administrativo"))
+            .await
+            .unwrap();
+
+        let names: Vec<_> = repo
+            .find_all()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["Administrativo", "Turno noturno"]);
+
+        repo.delete(night).await.unwrap();
+        assert!(repo.find_by_id(night).await.unwrap().is_none());
+        assert!(matches!(
+            repo.delete(night).await,
+            Err(StorageError::NotFound { .. })
+        ));
+
+        let orphans = sqlx::query_scalar!("SELECT COUNT(*) FROM schedule_exceptions")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(orphans, 0);
+    }
+
+    #[tokio::test]
+    async fn test_user_schedule_assignment() {
+        let db = setup_test_db().await;
+        let repo = SqliteScheduleRepository::new(db.pool().clone());
+        let night = repo.create(&night_shift()).await.unwrap();
+
+        assert!(repo.find_compiled_for_user(1).await.unwrap().is_none());
+        assert!(matches!(
+            repo.assign_user(1, night + 1).await,
+            Err(StorageError::NotFound { .. })
+        ));
+
+        repo.assign_user(1, night).await.unwrap();
+        let compiled = repo.find_compiled_for_user(1).await.unwrap().unwrap();
+        assert!(compiled.is_allowed(at(17, 23, 30)));
+        assert!(!compiled.is_allowed(at(17, 12, 0)));
+
+        assert!(repo.unassign_user(1).await.unwrap());
+        assert!(!repo.unassign_user(1).await.unwrap());
+
+        // Deleting the schedule lifts the restriction
+        repo.assign_user(1, night).await.unwrap();
+        repo.delete(night).await.unwrap();
+        assert!(repo.find_compiled_for_user(1).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,565 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::{Site, Zone, ZoneOccupancy};
+use chrono::Utc;
+use sqlx::SqlitePool;
+use turnkey_core::DeviceId;
+use turnkey_network::DeviceGroup;
+
+/// Repository trait for the site/zone/device hierarchy
+///
+/// Sites contain zones and zones contain devices. The repository resolves
+/// a zone or site to its devices, either as raw IDs or as a
+/// [`DeviceGroup`] ready for `TcpServer::broadcast()`, and reports
+/// occupancy per zone.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait SiteRepository: Send + Sync {
+    /// Create a new site
+    async fn create_site(&self, site: &Site) -> StorageResult<i64>;
+
+    /// Find a site by its ID
+    async fn find_site_by_id(&self, id: i64) -> StorageResult<Option<Site>>;
+
+    /// Find a site by its unique name
+    async fn find_site_by_name(&self, name: &str) -> StorageResult<Option<Site>>;
+
+    /// Get all sites, ordered by name
+    async fn find_all_sites(&self) -> StorageResult<Vec<Site>>;
+
+    /// Delete a site and its zones
+    ///
+    /// Devices in the deleted zones become unassigned. Fails if an access
+    /// exception is still restricted to one of the zones.
+    async fn delete_site(&self, id: i64) -> StorageResult<()>;
+
+    /// Create a new zone
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the site does not exist.
+    async fn create_zone(&self, zone: &Zone) -> StorageResult<i64>;
+
+    /// Find a zone by its ID
+    async fn find_zone_by_id(&self, id: i64) -> StorageResult<Option<Zone>>;
+
+    /// Get the zones of a site, ordered by name
+    async fn find_zones_by_site(&self, site_id: i64) -> StorageResult<Vec<Zone>>;
+
+    /// Delete a zone
+    ///
+    /// Devices in the zone become unassigned. Fails if an access exception
+    /// is still restricted to the zone.
+    async fn delete_zone(&self, id: i64) -> StorageResult<()>;
+
+    /// Move a device into a zone (or out of any zone with `None`)
+    ///
+    /// The device is created with default settings if it is not known yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if the device ID is out of range and `NotFound`
+    /// if the zone does not exist.
+    async fn assign_device(&self, device_id: i64, zone_id: Option<i64>) -> StorageResult<()>;
+
+    /// List the devices in a zone, ordered by device ID
+    async fn find_devices_in_zone(&self, zone_id: i64) -> StorageResult<Vec<i64>>;
+
+    /// List the devices in every zone of a site, ordered by device ID
+    async fn find_devices_in_site(&self, site_id: i64) -> StorageResult<Vec<i64>>;
+
+    /// Resolve a zone to a broadcast group named after the zone
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the zone does not exist.
+    async fn zone_group(&self, zone_id: i64) -> StorageResult<DeviceGroup>;
+
+    /// Resolve a site to a broadcast group named after the site
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the site does not exist.
+    async fn site_group(&self, site_id: i64) -> StorageResult<DeviceGroup>;
+
+    /// Count credentials currently inside each zone of a site
+    ///
+    /// Every zone of the site is listed, including empty ones, ordered by
+    /// zone name.
+    async fn occupancy_by_zone(&self, site_id: i64) -> StorageResult<Vec<ZoneOccupancy>>;
+}
+
+/// SQLite implementation of SiteRepository
+pub struct SqliteSiteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteSiteRepository {
+    /// Create a new SQLite site repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn not_found(entity_type: &str, id: i64) -> StorageError {
+        StorageError::NotFound {
+            entity_type: entity_type.to_string(),
+            field: "id".to_string(),
+            value: id.to_string(),
+        }
+    }
+
+    fn to_group(name: String, devices: Vec<i64>) -> StorageResult<DeviceGroup> {
+        let devices = devices
+            .into_iter()
+            .map(|device_id| {
+                u8::try_from(device_id)
+                    .ok()
+                    .and_then(|id| DeviceId::new(id).ok())
+                    .ok_or_else(|| {
+                        StorageError::Internal(format!("Invalid device ID {} in zone", device_id))
+                    })
+            })
+            .collect::<StorageResult<Vec<_>>>()?;
+
+        Ok(DeviceGroup::new(name, devices))
+    }
+}
+
+impl SiteRepository for SqliteSiteRepository {
+    async fn create_site(&self, site: &Site) -> StorageResult<i64> {
+        let result = sqlx::query!(
+            "INSERT INTO sites (name, description) VALUES (?, ?)",
+            site.name,
+            site.description
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn find_site_by_id(&self, id: i64) -> StorageResult<Option<Site>> {
+        let site = sqlx::query_as!(
+            Site,
+            r#"
+            SELECT id AS "id!", name, description,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM sites
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(site)
+    }
+
+    async fn find_site_by_name(&self, name: &str) -> StorageResult<Option<Site>> {
+        let site = sqlx::query_as!(
+            Site,
+            r#"
+            SELECT id AS "id!", name, description,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM sites
+            WHERE name = ?
+            "#,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(site)
+    }
+
+    async fn find_all_sites(&self) -> StorageResult<Vec<Site>> {
+        let sites = sqlx::query_as!(
+            Site,
+            r#"
+            SELECT id AS "id!", name, description,
+                   created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM sites
+            ORDER BY name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sites)
+    }
+
+    async fn delete_site(&self, id: i64) -> StorageResult<()> {
+        let result = sqlx::query!("DELETE FROM sites WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found("Site", id));
+        }
+
+        Ok(())
+    }
+
+    async fn create_zone(&self, zone: &Zone) -> StorageResult<i64> {
+        if self.find_site_by_id(zone.site_id).await?.is_none() {
+            return Err(Self::not_found("Site", zone.site_id));
+        }
+
+        let result = sqlx::query!(
+            "INSERT INTO zones (site_id, name, description) VALUES (?, ?, ?)",
+            zone.site_id,
+            zone.name,
+            zone.description
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn find_zone_by_id(&self, id: i64) -> StorageResult<Option<Zone>> {
+        let zone = sqlx::query_as!(
+            Zone,
+            r#"
+            SELECT id AS "id!", site_id, name, description, created_at AS "created_at: _",
+                   updated_at AS "updated_at: _"
+            FROM zones
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(zone)
+    }
+
+    async fn find_zones_by_site(&self, site_id: i64) -> StorageResult<Vec<Zone>> {
+        let zones = sqlx::query_as!(
+            Zone,
+            r#"
+            SELECT id AS "id!", site_id, name, description, created_at AS "created_at: _",
+                   updated_at AS "updated_at: _"
+            FROM zones
+            WHERE site_id = ?
+            ORDER BY name
+            "#,
+            site_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(zones)
+    }
+
+    async fn delete_zone(&self, id: i64) -> StorageResult<()> {
+        let result = sqlx::query!("DELETE FROM zones WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::not_found("Zone", id));
+        }
+
+        Ok(())
+    }
+
+    async fn assign_device(&self, device_id: i64, zone_id: Option<i64>) -> StorageResult<()> {
+        if !(1..=99).contains(&device_id) {
+            return Err(StorageError::Validation(format!(
+                "Device ID must be 1-99, got {}",
+                device_id
+            )));
+        }
+        if let Some(zone_id) = zone_id
+            && self.find_zone_by_id(zone_id).await?.is_none()
+        {
+            return Err(Self::not_found("Zone", zone_id));
+        }
+
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO devices (device_id, zone_id, created_at, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (device_id) DO UPDATE SET
+                zone_id = excluded.zone_id,
+                updated_at = excluded.updated_at
+            "#,
+            device_id,
+            zone_id,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_devices_in_zone(&self, zone_id: i64) -> StorageResult<Vec<i64>> {
+        let devices = sqlx::query_scalar!(
+            r#"SELECT device_id AS "device_id!" FROM devices WHERE zone_id = ? ORDER BY device_id"#,
+            zone_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    async fn find_devices_in_site(&self, site_id: i64) -> StorageResult<Vec<i64>> {
+        let devices = sqlx::query_scalar!(
+            r#"
+            SELECT d.device_id AS "device_id!"
+            FROM devices d
+            JOIN zones z ON z.id = d.zone_id
+            WHERE z.site_id = ?
+            ORDER BY d.device_id
+            "#,
+            site_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    async fn zone_group(&self, zone_id: i64) -> StorageResult<DeviceGroup> {
+        let zone = self
+            .find_zone_by_id(zone_id)
+            .await?
+            .ok_or_else(|| Self::not_found("Zone", zone_id))?;
+        let devices = self.find_devices_in_zone(zone_id).await?;
+
+        Self::to_group(zone.name, devices)
+    }
+
+    async fn site_group(&self, site_id: i64) -> StorageResult<DeviceGroup> {
+        let site = self
+            .find_site_by_id(site_id)
+            .await?
+            .ok_or_else(|| Self::not_found("Site", site_id))?;
+        let devices = self.find_devices_in_site(site_id).await?;
+
+        Self::to_group(site.name, devices)
+    }
+
+    async fn occupancy_by_zone(&self, site_id: i64) -> StorageResult<Vec<ZoneOccupancy>> {
+        // The zone of a credential is the zone of the device that granted
+        // its last passage; access_state only says whether that was an entry.
+        let occupancy = sqlx::query_as!(
+            ZoneOccupancy,
+            r#"
+            WITH last_grant AS (
+                SELECT s.card_number,
+                       (SELECT l.device_id FROM access_logs l
+                        WHERE l.card_number = s.card_number AND l.granted = 1
+                        ORDER BY l.id DESC LIMIT 1) AS device_id
+                FROM access_state s
+                WHERE s.inside = 1
+            )
+            SELECT z.id AS "zone_id!", z.name AS zone_name, COUNT(g.card_number) AS "inside!: i64"
+            FROM zones z
+            LEFT JOIN devices d ON d.zone_id = z.id
+            LEFT JOIN last_grant g ON g.device_id = d.device_id
+            WHERE z.site_id = ?
+            GROUP BY z.id, z.name
+            ORDER BY z.name
+            "#,
+            site_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(occupancy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{AccessLog, Direction, ReaderType};
+    use crate::repositories::{AccessLogRepository, SqliteAccessLogRepository};
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn create_building(repo: &SqliteSiteRepository) -> (i64, i64, i64) {
+        let site_id = repo.create_site(&Site::new("Sede")).await.unwrap();
+        let lobby = repo
+            .create_zone(&Zone::new(site_id, "Lobby"))
+            .await
+            .unwrap();
+        let garage = repo
+            .create_zone(&Zone::new(site_id, "Garagem"))
+            .await
+            .unwrap();
+        (site_id, lobby, garage)
+    }
+
+    fn granted(card_number: &str, direction: Direction, device_id: i64) -> AccessLog {
+        let mut log = AccessLog::new(
+            None,
+            None,
+            card_number.to_string(),
+            direction,
+            ReaderType::Rfid,
+            true,
+            None,
+            Utc::now(),
+        );
+        log.device_id = Some(device_id);
+        log
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_sites_and_zones() {
+        let db = setup_test_db().await;
+        let repo = SqliteSiteRepository::new(db.pool().clone());
+
+        let (site_id, _, _) = create_building(&repo).await;
+
+        let site = repo.find_site_by_name("Sede").await.unwrap().unwrap();
+        assert_eq!(site.id, site_id);
+        assert_eq!(repo.find_all_sites().await.unwrap().len(), 1);
+
+        let zones = repo.find_zones_by_site(site_id).await.unwrap();
+        let names: Vec<_> = zones.iter().map(|zone| zone.name.as_str()).collect();
+        assert_eq!(names, vec!["Garagem", "Lobby"]);
+
+        // Zone names are unique per site, and zones need an existing site
+        assert!(
+            repo.create_zone(&Zone::new(site_id, "Lobby"))
+                .await
+                .is_err()
+        );
+        assert!(matches!(
+            repo.create_zone(&Zone::new(999, "Lobby")).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assign_devices_and_build_groups() {
+        let db = setup_test_db().await;
+        let repo = SqliteSiteRepository::new(db.pool().clone());
+        let (site_id, lobby, garage) = create_building(&repo).await;
+
+        repo.assign_device(3, Some(lobby)).await.unwrap();
+        repo.assign_device(1, Some(lobby)).await.unwrap();
+        repo.assign_device(7, Some(garage)).await.unwrap();
+        repo.assign_device(9, None).await.unwrap();
+
+        assert_eq!(repo.find_devices_in_zone(lobby).await.unwrap(), vec![1, 3]);
+        assert_eq!(
+            repo.find_devices_in_site(site_id).await.unwrap(),
+            vec![1, 3, 7]
+        );
+
+        let group = repo.zone_group(lobby).await.unwrap();
+        assert_eq!(group.name(), "Lobby");
+        assert_eq!(
+            group.devices(),
+            &[DeviceId::new(1).unwrap(), DeviceId::new(3).unwrap()]
+        );
+        assert_eq!(repo.site_group(site_id).await.unwrap().devices().len(), 3);
+
+        // Moving a device out of its zone
+        repo.assign_device(3, None).await.unwrap();
+        assert_eq!(repo.find_devices_in_zone(lobby).await.unwrap(), vec![1]);
+
+        assert!(matches!(
+            repo.assign_device(100, Some(lobby)).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            repo.assign_device(2, Some(999)).await,
+            Err(StorageError::NotFound { .. })
+        ));
+        assert!(matches!(
+            repo.zone_group(999).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_delete_site_unassigns_devices() {
+        let db = setup_test_db().await;
+        let repo = SqliteSiteRepository::new(db.pool().clone());
+        let (site_id, lobby, _) = create_building(&repo).await;
+        repo.assign_device(4, Some(lobby)).await.unwrap();
+
+        repo.delete_site(site_id).await.unwrap();
+
+        assert!(repo.find_zone_by_id(lobby).await.unwrap().is_none());
+        let zone_id: Option<i64> =
+            sqlx::query_scalar!("SELECT zone_id FROM devices WHERE device_id = 4")
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(zone_id, None);
+        assert!(matches!(
+            repo.delete_site(site_id).await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_occupancy_by_zone() {
+        let db = setup_test_db().await;
+        let repo = SqliteSiteRepository::new(db.pool().clone());
+        let logs = SqliteAccessLogRepository::new(db.pool().clone());
+        let (site_id, lobby, garage) = create_building(&repo).await;
+        repo.assign_device(1, Some(lobby)).await.unwrap();
+        repo.assign_device(2, Some(garage)).await.unwrap();
+
+        logs.create(&granted("ZONE0001", Direction::Entry, 1))
+            .await
+            .unwrap();
+        logs.create(&granted("ZONE0002", Direction::Entry, 1))
+            .await
+            .unwrap();
+        // Entered through the lobby, then came back in through the garage
+        logs.create(&granted("ZONE0003", Direction::Entry, 1))
+            .await
+            .unwrap();
+        logs.create(&granted("ZONE0003", Direction::Exit, 1))
+            .await
+            .unwrap();
+        logs.create(&granted("ZONE0003", Direction::Entry, 2))
+            .await
+            .unwrap();
+        // Left the building
+        logs.create(&granted("ZONE0004", Direction::Entry, 2))
+            .await
+            .unwrap();
+        logs.create(&granted("ZONE0004", Direction::Exit, 2))
+            .await
+            .unwrap();
+
+        let occupancy = repo.occupancy_by_zone(site_id).await.unwrap();
+        assert_eq!(
+            occupancy,
+            vec![
+                ZoneOccupancy {
+                    zone_id: garage,
+                    zone_name: "Garagem".to_string(),
+                    inside: 1,
+                },
+                ZoneOccupancy {
+                    zone_id: lobby,
+                    zone_name: "Lobby".to_string(),
+                    inside: 2,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+#![allow(async_fn_in_trait)]
+
+use crate::error::{StorageError, StorageResult};
+use crate::models::SyncState;
+use chrono::{DateTime, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// Repository trait for the local data freshness record
+///
+/// Whatever loads data into the local database (site bundle import, server
+/// push) records its outcome here; offline validation reads it to decide
+/// whether the data is too old to trust.
+///
+/// # Implementation Note
+///
+/// This trait uses native async trait methods (Edition 2024 feature),
+/// eliminating the need for the async-trait crate while maintaining
+/// full async/await support in trait methods.
+pub trait SyncStateRepository: Send + Sync {
+    /// Get the current sync state
+    async fn get(&self) -> StorageResult<SyncState>;
+
+    /// Record a successful sync from `source` at `at`
+    ///
+    /// Clears the error of any earlier failed attempt.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if `source` is empty.
+    async fn record_success(&self, source: &str, at: DateTime<Utc>) -> StorageResult<()>;
+
+    /// Record a failed sync attempt at `at`
+    ///
+    /// The time of the last successful sync is kept, so the data keeps
+    /// ageing until a sync succeeds.
+    async fn record_failure(&self, error: &str, at: DateTime<Utc>) -> StorageResult<()>;
+}
+
+/// SQLite implementation of SyncStateRepository
+pub struct SqliteSyncStateRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteSyncStateRepository {
+    /// Create a new SQLite sync state repository
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Record a successful sync on an open connection
+///
+/// Lets imports mark the data fresh in the same transaction that writes it.
+pub(crate) async fn mark_synced(
+    conn: &mut SqliteConnection,
+    source: &str,
+    at: DateTime<Utc>,
+) -> StorageResult<()> {
+    if source.trim().is_empty() {
+        return Err(StorageError::Validation(
+            "Sync source is required".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE sync_state
+        SET last_success_at = ?, last_source = ?, last_attempt_at = ?, last_error = NULL
+        WHERE id = 1
+        "#,
+        at,
+        source,
+        at
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+impl SyncStateRepository for SqliteSyncStateRepository {
+    async fn get(&self) -> StorageResult<SyncState> {
+        let state = sqlx::query_as!(
+            SyncState,
+            r#"
+            SELECT last_success_at AS "last_success_at: _", last_source,
+                   last_attempt_at AS "last_attempt_at: _", last_error
+            FROM sync_state
+            WHERE id = 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(state.unwrap_or_default())
+    }
+
+    async fn record_success(&self, source: &str, at: DateTime<Utc>) -> StorageResult<()> {
+        let mut conn = self.pool.acquire().await?;
+        mark_synced(&mut conn, source, at).await
+    }
+
+    async fn record_failure(&self, error: &str, at: DateTime<Utc>) -> StorageResult<()> {
+        sqlx::query!(
+            r#"
+            UPDATE sync_state
+            SET last_attempt_at = ?, last_error = ?
+            WHERE id = 1
+            "#,
+            at,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use chrono::Duration;
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_new_database_was_never_synced() {
+        let db = setup_test_db().await;
+        let repo = SqliteSyncStateRepository::new(db.pool().clone());
+
+        let state = repo.get().await.unwrap();
+        assert_eq!(state, SyncState::default());
+        assert!(state.is_stale(Duration::days(3), Utc::now()));
+    }
+
+    #[tokio::test]
+    async fn test_failure_keeps_last_success() {
+        let db = setup_test_db().await;
+        let repo = SqliteSyncStateRepository::new(db.pool().clone());
+        let synced_at = Utc::now() - Duration::days(2);
+        let failed_at = Utc::now();
+
+        repo.record_success("bundle", synced_at).await.unwrap();
+        repo.record_failure("server unreachable", failed_at)
+            .await
+            .unwrap();
+
+        let state = repo.get().await.unwrap();
+        assert_eq!(state.last_success_at, Some(synced_at));
+        assert_eq!(state.last_source.as_deref(), Some("bundle"));
+        assert_eq!(state.last_attempt_at, Some(failed_at));
+        assert_eq!(state.last_error.as_deref(), Some("server unreachable"));
+
+        repo.record_success("server", failed_at).await.unwrap();
+        let state = repo.get().await.unwrap();
+        assert_eq!(state.last_success_at, Some(failed_at));
+        assert_eq!(state.last_error, None);
+    }
+
+    #[tokio::test]
+    async fn test_record_success_requires_source() {
+        let db = setup_test_db().await;
+        let repo = SqliteSyncStateRepository::new(db.pool().clone());
+
+        let result = repo.record_success(" ", Utc::now()).await;
+        assert!(matches!(result, Err(StorageError::Validation(_))));
+    }
+}
@@ -2,13 +2,33 @@
 
 use crate::error::{StorageError, StorageResult};
 use crate::models::User;
+use crate::pagination::{Page, PageRequest};
+use crate::pin::{Argon2PinHasher, PinDigestKey, PinHasher};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use turnkey_core::secrets::names;
+
+/// Refusal of a code that another user already has
+const CODE_TAKEN: &str = "Keypad code is already assigned to another user";
 
 /// Repository trait for User entity operations
 ///
 /// This trait defines the contract for user data access, enabling
 /// testability through mock implementations and separation of concerns.
 ///
+/// Keypad codes never leave the repository in plaintext: `create`,
+/// `update` and `set_code` hash them, and lookups compare against the
+/// stored hash. Plaintext codes left by older databases are replaced by a
+/// hash the first time they are used successfully (see [`crate::pin`]).
+///
+/// A code identifies its user on its own (PIN-only keypad access), so the
+/// same three methods refuse a code already assigned to another user. Codes
+/// are found through their indexed digest (see [`crate::pin`]), never by
+/// verifying every stored hash.
+///
 /// # Implementation Note
 ///
 /// This trait uses native async trait methods (Edition 2024 feature),
@@ -21,16 +41,46 @@ pub trait UserRepository: Send + Sync {
     /// Find a user by their ID
     async fn find_by_id(&self, id: i64) -> StorageResult<Option<User>>;
 
-    /// Find a user by their PIN code
+    /// Find a keypad user by their PIN code
+    ///
+    /// Looks the code up by its digest, or by value for legacy plaintext
+    /// codes. Hashed codes stored before digests existed are not found
+    /// until [`verify_code`](Self::verify_code) or
+    /// [`set_code`](Self::set_code) gives them one.
+    ///
+    /// A code no user has takes as long as a wrong one.
     async fn find_by_code(&self, code: &str) -> StorageResult<Option<User>>;
 
+    /// Check a keypad user's PIN code
+    ///
+    /// Returns `false` for unknown matriculas and users without keypad access,
+    /// after as long as a wrong code takes, so the answer does not tell them
+    /// apart.
+    async fn verify_code(&self, matricula: &str, code: &str) -> StorageResult<bool>;
+
+    /// Replace a user's PIN code, storing it hashed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if another user already has this code, and
+    /// `NotFound` if no user has this ID.
+    async fn set_code(&self, id: i64, code: &str) -> StorageResult<()>;
+
     /// Get all active users
     async fn find_all_active(&self) -> StorageResult<Vec<User>>;
 
     /// Create a new user
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if another user already has the user's code.
     async fn create(&self, user: &User) -> StorageResult<i64>;
 
     /// Update an existing user
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` if another user already has the user's code.
     async fn update(&self, user: &User) -> StorageResult<()>;
 
     /// Delete a user by ID
@@ -38,33 +88,189 @@ pub trait UserRepository: Send + Sync {
 
     /// Check if a matricula already exists
     async fn exists_by_matricula(&self, matricula: &str) -> StorageResult<bool>;
+
+    /// Fetch one page of users ordered by ID (keyset pagination)
+    async fn find_page(&self, page: PageRequest) -> StorageResult<Page<User>>;
+
+    /// Stream all users ordered by ID without loading them into memory
+    fn stream_all(&self) -> BoxStream<'_, StorageResult<User>>;
 }
 
 /// SQLite implementation of UserRepository
 pub struct SqliteUserRepository {
     pool: SqlitePool,
+    pin_hasher: Arc<dyn PinHasher>,
+    digest_key: Option<PinDigestKey>,
+    decoy_hash: Arc<OnceCell<String>>,
 }
 
 impl SqliteUserRepository {
     /// Create a new SQLite user repository
+    ///
+    /// Codes are hashed with [`Argon2PinHasher`] at default cost.
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            pin_hasher: Arc::new(Argon2PinHasher::default()),
+            digest_key: None,
+            decoy_hash: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Use a different PIN hashing strategy or cost
+    pub fn with_pin_hasher(mut self, pin_hasher: Arc<dyn PinHasher>) -> Self {
+        self.pin_hasher = pin_hasher;
+        self
+    }
+
+    /// Key the code digests are computed with
+    ///
+    /// Needed by everything that digests a plaintext code: creating or
+    /// changing a code, finding a user by code and giving a code its first
+    /// digest once it verifies. Without it these fail with
+    /// `Configuration`. See [`pin`](crate::pin#lookup).
+    pub fn with_digest_key(mut self, key: PinDigestKey) -> Self {
+        self.digest_key = Some(key);
+        self
+    }
+
+    /// Digest of a code, looked up in the `codigo_digest` index
+    fn digest(&self, code: &str) -> StorageResult<String> {
+        let key = self.digest_key.as_ref().ok_or_else(|| {
+            StorageError::Configuration(format!(
+                "Keypad codes need the PIN digest key (secret {})",
+                names::PIN_DIGEST_KEY
+            ))
+        })?;
+        Ok(key.digest(code))
+    }
+
+    /// Verify `code` against a hash no user has, taking as long as a real check
+    async fn verify_decoy(&self, code: &str) -> StorageResult<()> {
+        let decoy = self
+            .decoy_hash
+            .get_or_try_init(|| crate::pin::hash_blocking(self.pin_hasher.clone(), ""))
+            .await?;
+        crate::pin::verify_blocking(self.pin_hasher.clone(), code, decoy).await;
+        Ok(())
+    }
+
+    /// Hash a code for storage, leaving values that are already hashed
+    ///
+    /// Returns the value to store and, for a plaintext code, its digest.
+    async fn protect_code(
+        &self,
+        code: Option<&str>,
+    ) -> StorageResult<(Option<String>, Option<String>)> {
+        match code {
+            Some(code) if !crate::pin::is_hashed(code) => {
+                let hashed = crate::pin::hash_blocking(self.pin_hasher.clone(), code).await?;
+                Ok((Some(hashed), Some(self.digest(code)?)))
+            }
+            other => Ok((other.map(str::to_string), None)),
+        }
+    }
+
+    /// Refuse a plaintext code that a user other than `owner` has in plaintext
+    ///
+    /// Legacy plaintext codes have no digest yet; hashed codes are kept
+    /// unique by the `codigo_digest` index instead (see [`code_conflict`]).
+    async fn ensure_code_free(&self, code: Option<&str>, owner: Option<i64>) -> StorageResult<()> {
+        let Some(code) = code.filter(|code| !crate::pin::is_hashed(code)) else {
+            return Ok(());
+        };
+
+        let taken = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM users WHERE codigo = ? AND (? IS NULL OR id != ?)
+            ) AS "taken: bool"
+            "#,
+            code,
+            owner,
+            owner
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if taken {
+            return Err(StorageError::Validation(CODE_TAKEN.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Check a code against a user's stored value, migrating it on success
+    ///
+    /// A legacy or outdated value is rehashed, and a code without a digest
+    /// (`digested` false) gets one. The stored value is replaced only if it
+    /// is still the one that was verified, so a concurrent `set_code` is
+    /// never overwritten.
+    async fn check_code(&self, user: &mut User, code: &str, digested: bool) -> StorageResult<bool> {
+        let Some(stored) = user.codigo.clone() else {
+            self.verify_decoy(code).await?;
+            return Ok(false);
+        };
+        if !crate::pin::verify_blocking(self.pin_hasher.clone(), code, &stored).await {
+            return Ok(false);
+        }
+
+        let rehash = self.pin_hasher.needs_rehash(&stored);
+        if rehash || !digested {
+            let codigo = if rehash {
+                crate::pin::hash_blocking(self.pin_hasher.clone(), code).await?
+            } else {
+                stored.clone()
+            };
+            let digest = self.digest(code)?;
+            let result = sqlx::query!(
+                "UPDATE users SET codigo = ?, codigo_digest = ? WHERE id = ? AND codigo = ?",
+                codigo,
+                digest,
+                user.id,
+                stored
+            )
+            .execute(&self.pool)
+            .await;
+            match result.map_err(code_conflict) {
+                Ok(_) => user.codigo = Some(codigo),
+                // Another user got the same code first; this one stays as stored
+                Err(StorageError::Validation(_)) => {
+                    tracing::warn!(user_id = user.id, "keypad code shared with another user");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Turn a violation of the `codigo_digest` index into a `Validation` error
+fn code_conflict(error: sqlx::Error) -> StorageError {
+    match &error {
+        sqlx::Error::Database(db)
+            if db.is_unique_violation() && db.message().contains("codigo_digest") =>
+        {
+            StorageError::Validation(CODE_TAKEN.to_string())
+        }
+        _ => error.into(),
     }
 }
 
 impl UserRepository for SqliteUserRepository {
     async fn find_by_matricula(&self, matricula: &str) -> StorageResult<Option<User>> {
-        let user = sqlx::query_as::<_, User>(
+        let user = sqlx::query_as!(
+            User,
             r#"
-            SELECT id, pis, nome, matricula, cpf,
-                   validade_inicio, validade_fim, ativo,
-                   allow_card, allow_bio, allow_keypad, codigo,
-                   created_at, updated_at
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM users
             WHERE matricula = ?
             "#,
+            matricula
         )
-        .bind(matricula)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -72,17 +278,17 @@ impl UserRepository for SqliteUserRepository {
     }
 
     async fn find_by_id(&self, id: i64) -> StorageResult<Option<User>> {
-        let user = sqlx::query_as::<_, User>(
+        let user = sqlx::query_as!(
+            User,
             r#"
-            SELECT id, pis, nome, matricula, cpf,
-                   validade_inicio, validade_fim, ativo,
-                   allow_card, allow_bio, allow_keypad, codigo,
-                   created_at, updated_at
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM users
             WHERE id = ?
             "#,
+            id
         )
-        .bind(id)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -90,34 +296,105 @@ impl UserRepository for SqliteUserRepository {
     }
 
     async fn find_by_code(&self, code: &str) -> StorageResult<Option<User>> {
-        let user = sqlx::query_as::<_, User>(
+        let digest = self.digest(code)?;
+        let user = sqlx::query_as!(
+            User,
             r#"
-            SELECT id, pis, nome, matricula, cpf,
-                   validade_inicio, validade_fim, ativo,
-                   allow_card, allow_bio, allow_keypad, codigo,
-                   created_at, updated_at
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM users
-            WHERE codigo = ? AND allow_keypad = 1
+            WHERE codigo_digest = ? AND allow_keypad = 1
             "#,
+            digest
         )
-        .bind(code)
         .fetch_optional(&self.pool)
         .await?;
+        if let Some(mut user) = user {
+            let verified = self.check_code(&mut user, code, true).await?;
+            return Ok(verified.then_some(user));
+        }
 
-        Ok(user)
+        // Legacy plaintext codes have no digest yet
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM users
+            WHERE codigo = ? AND codigo_digest IS NULL AND allow_keypad = 1
+            "#,
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        match user {
+            Some(mut user) => {
+                let verified = self.check_code(&mut user, code, false).await?;
+                Ok(verified.then_some(user))
+            }
+            None => {
+                self.verify_decoy(code).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn verify_code(&self, matricula: &str, code: &str) -> StorageResult<bool> {
+        match self.find_by_matricula(matricula).await? {
+            Some(mut user) if user.allow_keypad => {
+                let digested = sqlx::query_scalar!(
+                    r#"SELECT codigo_digest IS NOT NULL AS "digested: bool" FROM users WHERE id = ?"#,
+                    user.id
+                )
+                .fetch_one(&self.pool)
+                .await?;
+                self.check_code(&mut user, code, digested).await
+            }
+            _ => {
+                self.verify_decoy(code).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    async fn set_code(&self, id: i64, code: &str) -> StorageResult<()> {
+        self.ensure_code_free(Some(code), Some(id)).await?;
+        let hashed = crate::pin::hash_blocking(self.pin_hasher.clone(), code).await?;
+        let digest = self.digest(code)?;
+        let result = sqlx::query!(
+            "UPDATE users SET codigo = ?, codigo_digest = ? WHERE id = ?",
+            hashed,
+            digest,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(code_conflict)?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound {
+                entity_type: "User".to_string(),
+                field: "id".to_string(),
+                value: id.to_string(),
+            });
+        }
+
+        Ok(())
     }
 
     async fn find_all_active(&self) -> StorageResult<Vec<User>> {
-        let users = sqlx::query_as::<_, User>(
+        let users = sqlx::query_as!(
+            User,
             r#"
-            SELECT id, pis, nome, matricula, cpf,
-                   validade_inicio, validade_fim, ativo,
-                   allow_card, allow_bio, allow_keypad, codigo,
-                   created_at, updated_at
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
             FROM users
             WHERE ativo = 1
             ORDER BY nome
-            "#,
+            "#
         )
         .fetch_all(&self.pool)
         .await?;
@@ -126,58 +403,74 @@ impl UserRepository for SqliteUserRepository {
     }
 
     async fn create(&self, user: &User) -> StorageResult<i64> {
-        let result = sqlx::query(
+        self.ensure_code_free(user.codigo.as_deref(), None).await?;
+        let (codigo, digest) = self.protect_code(user.codigo.as_deref()).await?;
+        let result = sqlx::query!(
             r#"
             INSERT INTO users (
                 pis, nome, matricula, cpf,
                 validade_inicio, validade_fim, ativo,
-                allow_card, allow_bio, allow_keypad, codigo
+                allow_card, allow_bio, allow_keypad, codigo, codigo_digest, empresa
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
+            user.pis,
+            user.nome,
+            user.matricula,
+            user.cpf,
+            user.validade_inicio,
+            user.validade_fim,
+            user.ativo,
+            user.allow_card,
+            user.allow_bio,
+            user.allow_keypad,
+            codigo,
+            digest,
+            user.empresa
         )
-        .bind(&user.pis)
-        .bind(&user.nome)
-        .bind(&user.matricula)
-        .bind(&user.cpf)
-        .bind(user.validade_inicio)
-        .bind(user.validade_fim)
-        .bind(user.ativo)
-        .bind(user.allow_card)
-        .bind(user.allow_bio)
-        .bind(user.allow_keypad)
-        .bind(&user.codigo)
         .execute(&self.pool)
-        .await?;
+        .await
+        .map_err(code_conflict)?;
 
         Ok(result.last_insert_rowid())
     }
 
     async fn update(&self, user: &User) -> StorageResult<()> {
-        let result = sqlx::query(
+        self.ensure_code_free(user.codigo.as_deref(), Some(user.id))
+            .await?;
+        let (codigo, digest) = self.protect_code(user.codigo.as_deref()).await?;
+        let result = sqlx::query!(
             r#"
             UPDATE users
             SET pis = ?, nome = ?, matricula = ?, cpf = ?,
                 validade_inicio = ?, validade_fim = ?, ativo = ?,
                 allow_card = ?, allow_bio = ?, allow_keypad = ?,
-                codigo = ?, updated_at = datetime('now')
+                codigo = ?, empresa = ?, updated_at = datetime('now'),
+                -- An unchanged code keeps its digest; an already hashed one has none
+                codigo_digest = CASE WHEN ? IS NOT NULL THEN ?
+                                     WHEN codigo IS ? THEN codigo_digest END
             WHERE id = ?
             "#,
+            user.pis,
+            user.nome,
+            user.matricula,
+            user.cpf,
+            user.validade_inicio,
+            user.validade_fim,
+            user.ativo,
+            user.allow_card,
+            user.allow_bio,
+            user.allow_keypad,
+            codigo,
+            user.empresa,
+            digest,
+            digest,
+            codigo,
+            user.id
         )
-        .bind(&user.pis)
-        .bind(&user.nome)
-        .bind(&user.matricula)
-        .bind(&user.cpf)
-        .bind(user.validade_inicio)
-        .bind(user.validade_fim)
-        .bind(user.ativo)
-        .bind(user.allow_card)
-        .bind(user.allow_bio)
-        .bind(user.allow_keypad)
-        .bind(&user.codigo)
-        .bind(user.id)
         .execute(&self.pool)
-        .await?;
+        .await
+        .map_err(code_conflict)?;
 
         if result.rows_affected() == 0 {
             return Err(StorageError::NotFound {
@@ -191,8 +484,7 @@ impl UserRepository for SqliteUserRepository {
     }
 
     async fn delete(&self, id: i64) -> StorageResult<()> {
-        let result = sqlx::query("DELETE FROM users WHERE id = ?")
-            .bind(id)
+        let result = sqlx::query!("DELETE FROM users WHERE id = ?", id)
             .execute(&self.pool)
             .await?;
 
@@ -208,12 +500,50 @@ impl UserRepository for SqliteUserRepository {
     }
 
     async fn exists_by_matricula(&self, matricula: &str) -> StorageResult<bool> {
-        let result: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE matricula = ?")
-            .bind(matricula)
-            .fetch_one(&self.pool)
-            .await?;
+        let count =
+            sqlx::query_scalar!("SELECT COUNT(*) FROM users WHERE matricula = ?", matricula)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count > 0)
+    }
+
+    async fn find_page(&self, page: PageRequest) -> StorageResult<Page<User>> {
+        let after_id = page.id_lower_bound();
+        let rows = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM users
+            WHERE id > ?
+            ORDER BY id
+            LIMIT ?
+            "#,
+            after_id,
+            page.page_size
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
-        Ok(result.0 > 0)
+        Ok(Page::from_rows(rows, page, |row| row.id))
+    }
+
+    fn stream_all(&self) -> BoxStream<'_, StorageResult<User>> {
+        sqlx::query_as!(
+            User,
+            r#"
+            SELECT id AS "id!", pis, nome, matricula, cpf, validade_inicio AS "validade_inicio: _",
+                   validade_fim AS "validade_fim: _", ativo, allow_card, allow_bio, allow_keypad,
+                   codigo, empresa, created_at AS "created_at: _", updated_at AS "updated_at: _"
+            FROM users
+            ORDER BY id
+            "#
+        )
+        .fetch(&self.pool)
+        .map_err(StorageError::from)
+        .boxed()
     }
 }
 
@@ -227,6 +557,11 @@ mod tests {
         Database::in_memory().await.unwrap()
     }
 
+    fn test_repo(db: &Database) -> SqliteUserRepository {
+        let key = PinDigestKey::new([7; 32].to_vec().into()).unwrap();
+        SqliteUserRepository::new(db.pool().clone()).with_digest_key(key)
+    }
+
     fn create_test_user(matricula: &str) -> User {
         User {
             id: 0,
@@ -240,7 +575,9 @@ mod tests {
             allow_card: true,
             allow_bio: false,
             allow_keypad: true,
-            codigo: Some("1234".to_string()),
+            // Codes are unique, so derive one from the matricula
+            codigo: Some(format!("PIN-{matricula}")),
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -249,7 +586,7 @@ mod tests {
     #[tokio::test]
     async fn test_create_and_find_user() {
         let db = setup_test_db().await;
-        let repo = SqliteUserRepository::new(db.pool().clone());
+        let repo = test_repo(&db);
 
         let user = create_test_user("EMP001");
         let id = repo.create(&user).await.unwrap();
@@ -263,7 +600,7 @@ mod tests {
     #[tokio::test]
     async fn test_find_by_id() {
         let db = setup_test_db().await;
-        let repo = SqliteUserRepository::new(db.pool().clone());
+        let repo = test_repo(&db);
 
         let user = create_test_user("EMP002");
         let id = repo.create(&user).await.unwrap();
@@ -276,7 +613,7 @@ mod tests {
     #[tokio::test]
     async fn test_find_by_code() {
         let db = setup_test_db().await;
-        let repo = SqliteUserRepository::new(db.pool().clone());
+        let repo = test_repo(&db);
 
         // Create user with unique code to avoid collision with seed data
         let mut user = create_test_user("EMP003");
@@ -289,9 +626,137 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_find_all_active() {
+    async fn test_codes_need_digest_key() {
         let db = setup_test_db().await;
+        test_repo(&db)
+            .create(&create_test_user("EMP004"))
+            .await
+            .unwrap();
+
+        // No key, no lookup and no new code; codes are not digested another way
         let repo = SqliteUserRepository::new(db.pool().clone());
+        assert!(matches!(
+            repo.find_by_code("PIN-EMP004").await,
+            Err(StorageError::Configuration(_))
+        ));
+        assert!(matches!(
+            repo.create(&create_test_user("EMP005")).await,
+            Err(StorageError::Configuration(_))
+        ));
+
+        // A different key finds nothing
+        let other = PinDigestKey::new([8; 32].to_vec().into()).unwrap();
+        let repo = SqliteUserRepository::new(db.pool().clone()).with_digest_key(other);
+        assert!(repo.find_by_code("PIN-EMP004").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_codes_are_stored_hashed() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
+
+        let id = repo.create(&create_test_user("EMP010")).await.unwrap();
+        let stored = repo.find_by_id(id).await.unwrap().unwrap().codigo.unwrap();
+        assert!(crate::pin::is_hashed(&stored));
+        assert!(repo.verify_code("EMP010", "PIN-EMP010").await.unwrap());
+        assert!(!repo.verify_code("EMP010", "4321").await.unwrap());
+
+        repo.set_code(id, "8765").await.unwrap();
+        assert!(repo.verify_code("EMP010", "8765").await.unwrap());
+        assert!(!repo.verify_code("EMP010", "PIN-EMP010").await.unwrap());
+        assert!(matches!(
+            repo.set_code(-1, "1357").await,
+            Err(StorageError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_codes_must_be_unique() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
+
+        let first = repo.create(&create_test_user("EMP011")).await.unwrap();
+
+        // Seed user 1002 holds 5678 in plaintext, EMP011 a hashed code
+        let mut taken = create_test_user("EMP012");
+        taken.codigo = Some("5678".to_string());
+        assert!(matches!(
+            repo.create(&taken).await,
+            Err(StorageError::Validation(_))
+        ));
+        taken.codigo = Some("PIN-EMP011".to_string());
+        assert!(matches!(
+            repo.create(&taken).await,
+            Err(StorageError::Validation(_))
+        ));
+
+        let second = repo.create(&create_test_user("EMP012")).await.unwrap();
+        assert!(matches!(
+            repo.set_code(second, "PIN-EMP011").await,
+            Err(StorageError::Validation(_))
+        ));
+
+        // A user may keep or reassign their own code
+        repo.set_code(first, "PIN-EMP011").await.unwrap();
+        let mut own = repo.find_by_id(second).await.unwrap().unwrap();
+        own.codigo = Some("PIN-EMP012".to_string());
+        repo.update(&own).await.unwrap();
+        assert!(repo.verify_code("EMP012", "PIN-EMP012").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_code_migrated_on_use() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
+
+        // Seed data predates hashing
+        let legacy = repo.find_by_matricula("1002").await.unwrap().unwrap();
+        assert_eq!(legacy.codigo.as_deref(), Some("5678"));
+
+        assert!(!repo.verify_code("1002", "0000").await.unwrap());
+        let unchanged = repo.find_by_matricula("1002").await.unwrap().unwrap();
+        assert_eq!(unchanged.codigo.as_deref(), Some("5678"));
+
+        let found = repo.find_by_code("5678").await.unwrap().unwrap();
+        assert_eq!(found.matricula, "1002");
+        assert!(crate::pin::is_hashed(found.codigo.as_deref().unwrap()));
+
+        let migrated = repo.find_by_matricula("1002").await.unwrap().unwrap();
+        assert_eq!(migrated.codigo, found.codigo);
+        assert!(repo.verify_code("1002", "5678").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_code_digest_kept_and_backfilled() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
+
+        let id = repo.create(&create_test_user("EMP013")).await.unwrap();
+        let mut user = repo.find_by_id(id).await.unwrap().unwrap();
+        user.nome = "Renamed".to_string();
+        repo.update(&user).await.unwrap();
+        assert_eq!(
+            repo.find_by_code("PIN-EMP013").await.unwrap().unwrap().id,
+            id
+        );
+
+        // A hashed code without a digest is found by PIN once verified by matricula
+        sqlx::query!("UPDATE users SET codigo_digest = NULL WHERE id = ?", id)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(repo.find_by_code("PIN-EMP013").await.unwrap().is_none());
+        assert!(repo.verify_code("EMP013", "PIN-EMP013").await.unwrap());
+        assert_eq!(
+            repo.find_by_code("PIN-EMP013").await.unwrap().unwrap().id,
+            id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_all_active() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
 
         repo.create(&create_test_user("EMP004")).await.unwrap();
         repo.create(&create_test_user("EMP005")).await.unwrap();
@@ -303,7 +768,7 @@ mod tests {
     #[tokio::test]
     async fn test_update_user() {
         let db = setup_test_db().await;
-        let repo = SqliteUserRepository::new(db.pool().clone());
+        let repo = test_repo(&db);
 
         let user = create_test_user("EMP006");
         let id = repo.create(&user).await.unwrap();
@@ -320,7 +785,7 @@ mod tests {
     #[tokio::test]
     async fn test_delete_user() {
         let db = setup_test_db().await;
-        let repo = SqliteUserRepository::new(db.pool().clone());
+        let repo = test_repo(&db);
 
         let user = create_test_user("EMP007");
         let id = repo.create(&user).await.unwrap();
@@ -334,7 +799,7 @@ mod tests {
     #[tokio::test]
     async fn test_exists_by_matricula() {
         let db = setup_test_db().await;
-        let repo = SqliteUserRepository::new(db.pool().clone());
+        let repo = test_repo(&db);
 
         let user = create_test_user("EMP008");
         repo.create(&user).await.unwrap();
@@ -342,4 +807,49 @@ mod tests {
         assert!(repo.exists_by_matricula("EMP008").await.unwrap());
         assert!(!repo.exists_by_matricula("EMP999").await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_find_page_traverses_all_users() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
+
+        for i in 0..5 {
+            repo.create(&create_test_user(&format!("PAGE{i:03}")))
+                .await
+                .unwrap();
+        }
+
+        let mut ids = Vec::new();
+        let mut request = Some(PageRequest::first(2));
+        while let Some(page_request) = request {
+            let page = repo.find_page(page_request).await.unwrap();
+            assert!(page.items.len() <= 2);
+            ids.extend(page.items.iter().map(|u| u.id));
+            request = page.next_page();
+        }
+
+        let total = sqlx::query_scalar!("SELECT COUNT(*) FROM users")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+
+        assert_eq!(ids.len() as i64, total);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_stream_all_users() {
+        let db = setup_test_db().await;
+        let repo = test_repo(&db);
+
+        repo.create(&create_test_user("STREAM01")).await.unwrap();
+
+        let users: Vec<User> = repo.stream_all().try_collect().await.unwrap();
+        let page = repo.find_page(PageRequest::first(1000)).await.unwrap();
+
+        let streamed_ids: Vec<i64> = users.iter().map(|u| u.id).collect();
+        let paged_ids: Vec<i64> = page.items.iter().map(|u| u.id).collect();
+        assert_eq!(streamed_ids, paged_ids);
+        assert!(users.iter().any(|u| u.matricula == "STREAM01"));
+    }
 }
@@ -0,0 +1,379 @@
+//! Retention of access logs
+//!
+//! A device or server that runs for years keeps every passage in
+//! `access_logs`. [`RetentionManager`] deletes the logs older than the
+//! [`RetentionPolicy`]'s maximum age, a batch at a time so validations
+//! waiting for the database are not held up by one long delete.
+//!
+//! With the `archive` feature the logs can be archived first
+//! ([`with_archive_dir()`](RetentionManager::with_archive_dir)): every day
+//! older than the maximum age is written as gzip-compressed JSON Lines by
+//! the [`LogArchiver`](crate::archive::LogArchiver), and only logs recorded
+//! in an archive segment are deleted. Archiving works in whole UTC days, so
+//! a log is then deleted once its whole day is older than the maximum age.
+//!
+//! Logs referenced by an access override are kept with it. Deleting logs
+//! does not shrink the database file; SQLite reuses the freed pages.
+//!
+//! # Scheduling
+//!
+//! [`run()`](RetentionManager::run) prunes on a fixed interval until its
+//! token is cancelled, and is meant to be spawned next to the other
+//! long-running tasks.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use turnkey_network::CancellationToken;
+//! use turnkey_storage::retention::{RetentionManager, RetentionPolicy};
+//!
+//! # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+//! let manager = RetentionManager::new(pool, RetentionPolicy::days(90));
+//!
+//! // Once, now
+//! let report = manager.prune(chrono::Utc::now()).await?;
+//! println!("{} logs deleted", report.deleted);
+//!
+//! // Every hour in the background
+//! let stop = CancellationToken::new();
+//! tokio::spawn(manager.run(Duration::from_secs(3600), stop.clone()));
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "archive")]
+use crate::archive::{ArchiveConfig, FileStore, LogArchiver};
+use crate::error::StorageResult;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tracing::{info, warn};
+use turnkey_network::CancellationToken;
+
+/// Default maximum age of access logs, in days
+pub const DEFAULT_RETENTION_DAYS: i64 = 365;
+
+/// Default number of logs deleted per statement
+pub const DEFAULT_PRUNE_BATCH: usize = 1000;
+
+/// Default time between two runs of [`RetentionManager::run`] (1 hour)
+pub const DEFAULT_RETENTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// How long access logs are kept
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use turnkey_storage::retention::RetentionPolicy;
+///
+/// let policy = RetentionPolicy::days(30);
+/// let now = Utc.with_ymd_and_hms(2025, 5, 31, 12, 0, 0).unwrap();
+/// assert_eq!(policy.cutoff(now), Utc.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap());
+/// assert_eq!(policy.max_age, Duration::days(30));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Logs with an older event time are deleted
+    pub max_age: Duration,
+
+    /// Logs deleted per statement (default: [`DEFAULT_PRUNE_BATCH`])
+    pub batch_size: usize,
+}
+
+impl RetentionPolicy {
+    /// Keep logs for `max_age` (negative ages count as zero)
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age: max_age.max(Duration::zero()),
+            batch_size: DEFAULT_PRUNE_BATCH,
+        }
+    }
+
+    /// Keep logs for a number of days
+    pub fn days(days: i64) -> Self {
+        Self::new(Duration::days(days))
+    }
+
+    /// Set the number of logs deleted per statement (at least 1)
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Event time before which logs are deleted at `now`
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - self.max_age
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self::days(DEFAULT_RETENTION_DAYS)
+    }
+}
+
+/// Outcome of [`RetentionManager::prune`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneReport {
+    /// Event time before which logs were deleted
+    pub cutoff: DateTime<Utc>,
+
+    /// Logs written to the archive by this run
+    pub archived: u64,
+
+    /// Logs deleted
+    pub deleted: u64,
+
+    /// Logs older than the cutoff still stored (referenced by an override,
+    /// or not archived yet)
+    pub kept: u64,
+}
+
+/// Deletes access logs older than a [`RetentionPolicy`]
+///
+/// See the [module documentation](self).
+pub struct RetentionManager {
+    pool: SqlitePool,
+    policy: RetentionPolicy,
+    #[cfg(feature = "archive")]
+    archiver: Option<LogArchiver<FileStore>>,
+}
+
+impl RetentionManager {
+    /// Create a manager deleting logs without archiving them
+    pub fn new(pool: SqlitePool, policy: RetentionPolicy) -> Self {
+        Self {
+            pool,
+            policy,
+            #[cfg(feature = "archive")]
+            archiver: None,
+        }
+    }
+
+    /// Archive logs under `dir` before deleting them
+    ///
+    /// The archive uses the [`FileStore`] layout with the default
+    /// [`ArchiveConfig`], and can be restored a day at a time with
+    /// [`archiver()`](Self::archiver).
+    #[cfg(feature = "archive")]
+    pub fn with_archive_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.archiver = Some(LogArchiver::new(
+            self.pool.clone(),
+            FileStore::new(dir),
+            ArchiveConfig::new(),
+        ));
+        self
+    }
+
+    /// Archiver the logs are written to before deletion, if any
+    #[cfg(feature = "archive")]
+    pub fn archiver(&self) -> Option<&LogArchiver<FileStore>> {
+        self.archiver.as_ref()
+    }
+
+    /// Retention policy applied
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Delete the logs older than the policy allows at `now`
+    ///
+    /// # Errors
+    ///
+    /// Returns the first archive or database error. Batches deleted before
+    /// it stay deleted; with an archive, nothing is deleted if archiving
+    /// fails.
+    pub async fn prune(&self, now: DateTime<Utc>) -> StorageResult<PruneReport> {
+        let mut report = PruneReport {
+            cutoff: self.policy.cutoff(now),
+            ..PruneReport::default()
+        };
+        let archived_only = self.archive(&mut report).await?;
+
+        let batch_size = self.policy.batch_size as i64;
+        loop {
+            let deleted = sqlx::query!(
+                r#"
+                DELETE FROM access_logs WHERE id IN (
+                    SELECT l.id FROM access_logs l
+                    WHERE l.timestamp < ?1
+                      AND NOT EXISTS (
+                          SELECT 1 FROM access_overrides o
+                          WHERE o.denied_log_id = l.id OR o.granted_log_id = l.id)
+                      AND (NOT ?2 OR l.id <= COALESCE(
+                          (SELECT MAX(s.last_id) FROM log_archive_segments s
+                           WHERE s.day = substr(l.timestamp, 1, 10)),
+                          0))
+                    ORDER BY l.id
+                    LIMIT ?3
+                )
+                "#,
+                report.cutoff,
+                archived_only,
+                batch_size
+            )
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+            report.deleted += deleted;
+            if deleted < self.policy.batch_size as u64 {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+
+        let kept: i64 = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM access_logs WHERE timestamp < ?",
+            report.cutoff
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        report.kept = kept as u64;
+
+        Ok(report)
+    }
+
+    /// Archive the days before the cutoff, rounding the cutoff down to a
+    /// whole day; returns whether only archived logs may be deleted
+    #[cfg(feature = "archive")]
+    async fn archive(&self, report: &mut PruneReport) -> StorageResult<bool> {
+        let Some(archiver) = &self.archiver else {
+            return Ok(false);
+        };
+        let day = report.cutoff.date_naive();
+        report.archived = archiver.archive(day).await?.rows;
+        report.cutoff = day.and_time(chrono::NaiveTime::MIN).and_utc();
+        Ok(true)
+    }
+
+    #[cfg(not(feature = "archive"))]
+    async fn archive(&self, _report: &mut PruneReport) -> StorageResult<bool> {
+        Ok(false)
+    }
+
+    /// Prune every `interval`, starting now, until `stop` is cancelled
+    ///
+    /// Failed runs are logged and retried on the next tick. A run in
+    /// progress when `stop` fires is completed first.
+    pub async fn run(self, interval: std::time::Duration, stop: CancellationToken) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                _ = ticker.tick() => match self.prune(Utc::now()).await {
+                    Ok(report) if report.deleted > 0 || report.archived > 0 => info!(
+                        cutoff = %report.cutoff,
+                        archived = report.archived,
+                        deleted = report.deleted,
+                        kept = report.kept,
+                        "Old access logs pruned"
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!(error = %e, "Failed to prune access logs"),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{AccessLog, Direction, ReaderType};
+    use crate::repositories::{AccessLogRepository, SqliteAccessLogRepository};
+    use chrono::TimeZone;
+
+    /// Replace the seeded logs with one log a day from May 1 to May 10
+    async fn seed_logs(db: &Database) -> Vec<i64> {
+        sqlx::query!("DELETE FROM access_logs")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let mut ids = Vec::new();
+        for day in 1..=10 {
+            let log = AccessLog::new(
+                None,
+                None,
+                "00000000000022823433".to_string(),
+                Direction::Entry,
+                ReaderType::Rfid,
+                false,
+                None,
+                Utc.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap(),
+            );
+            ids.push(repo.create(&log).await.unwrap());
+        }
+        ids
+    }
+
+    async fn remaining(db: &Database) -> i64 {
+        sqlx::query_scalar!("SELECT COUNT(*) FROM access_logs")
+            .fetch_one(db.pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_prune_in_batches_keeps_overridden_logs() {
+        let db = Database::in_memory().await.unwrap();
+        let ids = seed_logs(&db).await;
+        sqlx::query!(
+            "INSERT INTO access_overrides (denied_log_id, granted_log_id, operator_id, reason, created_at) \
+             VALUES (?, ?, 'op', 'Visitante autorizado', datetime('now'))",
+            ids[0],
+            ids[9]
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        // Logs of May 1-5 are older than 5 days on May 10 at 18:00
+        let manager =
+            RetentionManager::new(db.pool().clone(), RetentionPolicy::days(5).batch_size(2));
+        let now = Utc.with_ymd_and_hms(2025, 5, 10, 18, 0, 0).unwrap();
+        let report = manager.prune(now).await.unwrap();
+        assert_eq!((report.deleted, report.kept, report.archived), (4, 1, 0));
+        assert_eq!(remaining(&db).await, 6);
+
+        let report = manager.prune(now).await.unwrap();
+        assert_eq!((report.deleted, report.kept), (0, 1));
+    }
+
+    #[cfg(feature = "archive")]
+    #[tokio::test]
+    async fn test_prune_archives_whole_days_first() {
+        let db = Database::in_memory().await.unwrap();
+        seed_logs(&db).await;
+        let dir = tempfile::tempdir().unwrap();
+        let manager = RetentionManager::new(db.pool().clone(), RetentionPolicy::days(5))
+            .with_archive_dir(dir.path());
+
+        // The cutoff (May 5, 18:00) is rounded down to May 5: the day of
+        // May 5 is not complete yet
+        let now = Utc.with_ymd_and_hms(2025, 5, 10, 18, 0, 0).unwrap();
+        let report = manager.prune(now).await.unwrap();
+        assert_eq!(
+            report.cutoff,
+            Utc.with_ymd_and_hms(2025, 5, 5, 0, 0, 0).unwrap()
+        );
+        assert_eq!((report.archived, report.deleted, report.kept), (4, 4, 0));
+        assert!(
+            dir.path()
+                .join("access-logs/2025/05/01/segment-0001.jsonl.gz")
+                .is_file()
+        );
+
+        // Deleted logs can be restored from the archive
+        let archiver = manager.archiver().unwrap();
+        let day = chrono::NaiveDate::from_ymd_opt(2025, 5, 3).unwrap();
+        assert_eq!(archiver.restore_day(day).await.unwrap().inserted, 1);
+        assert_eq!(remaining(&db).await, 7);
+    }
+}
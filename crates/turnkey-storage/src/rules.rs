@@ -0,0 +1,662 @@
+//! Declarative JSON documents for the validation rule set
+//!
+//! The rules the [`OfflineValidator`](crate::OfflineValidator) enforces on
+//! top of credentials (the anti-passback window, weekly schedules and
+//! daily quotas) can be exported as one [`RuleSet`] document for review,
+//! kept in version control, and imported into another installation.
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "anti_passback": { "window_seconds": 300 },
+//!   "schedules": [
+//!     {
+//!       "name": "Turno noturno",
+//!       "weekly": { "monday": ["22:00-06:00"], "friday": ["22:00-06:00"] },
+//!       "exceptions": { "2025-12-25": [] }
+//!     }
+//!   ],
+//!   "quotas": [
+//!     { "scope": "company", "empresa": "Academia", "max_passages": 2 },
+//!     { "scope": "user", "matricula": "1002", "max_passages": 4 }
+//!   ]
+//! }
+//! ```
+//!
+//! Rules use natural keys (schedule names, matriculas, company names), like
+//! [`bundle`](crate::bundle) records, so a document does not depend on the
+//! database IDs of the installation it came from.
+//!
+//! # Activation
+//!
+//! [`RuleSet::check()`] lists every conflict in a document without touching
+//! the database: malformed intervals, overlapping schedule intervals,
+//! duplicate schedules or quotas, and settings this build cannot honour.
+//! [`RuleSet::apply()`] runs the same check and refuses the whole document
+//! if anything is flagged, then writes all rules in one transaction.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_storage::bundle::ImportMode;
+//! use turnkey_storage::rules::RuleSet;
+//! use turnkey_storage::{Database, DatabaseConfig};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let source = Database::new(DatabaseConfig::new("site-a.db")).await?;
+//! let json = RuleSet::collect(source.pool()).await?.to_json()?;
+//! std::fs::write("rules.json", &json)?;
+//!
+//! // After review
+//! let rules = RuleSet::from_json(&std::fs::read_to_string("rules.json")?)?;
+//! for conflict in rules.check() {
+//!     eprintln!("{}", conflict);
+//! }
+//! let target = Database::new(DatabaseConfig::new("site-b.db")).await?;
+//! rules.apply(target.pool(), ImportMode::Replace).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use chrono::{NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::bundle::ImportMode;
+use crate::error::{StorageError, StorageResult};
+use crate::models::{DEFAULT_ANTI_PASSBACK_WINDOW_SECS, TimeInterval, WeeklySchedule};
+use crate::repositories::{ScheduleRepository, SqliteScheduleRepository};
+
+/// Document layout version written by this build
+pub const RULES_FORMAT_VERSION: u32 = 1;
+
+/// Weekday keys of [`ScheduleRule::weekly`], Monday first
+const WEEKDAY_NAMES: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Complete rule set of an installation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleSet {
+    /// Document layout version (see [`RULES_FORMAT_VERSION`])
+    pub format_version: u32,
+
+    /// Anti-passback settings
+    pub anti_passback: AntiPassbackRule,
+
+    /// Weekly access schedules, by name
+    #[serde(default)]
+    pub schedules: Vec<ScheduleRule>,
+
+    /// Daily passage quotas
+    #[serde(default)]
+    pub quotas: Vec<QuotaRule>,
+}
+
+/// Anti-passback settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AntiPassbackRule {
+    /// Seconds during which a repeated direction is denied
+    pub window_seconds: i64,
+}
+
+impl Default for AntiPassbackRule {
+    fn default() -> Self {
+        Self {
+            window_seconds: DEFAULT_ANTI_PASSBACK_WINDOW_SECS,
+        }
+    }
+}
+
+/// Weekly schedule written with readable intervals
+///
+/// `weekly` maps lowercase English weekday names to `HH:MM-HH:MM`
+/// intervals; `exceptions` maps dates to the intervals replacing that
+/// day's (an empty list closes the day). See [`WeeklySchedule`] for how
+/// overnight intervals and exceptions combine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    /// Unique schedule name
+    pub name: String,
+
+    /// Intervals starting on each weekday
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub weekly: BTreeMap<String, Vec<String>>,
+
+    /// Intervals replacing the weekly ones on specific dates
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exceptions: BTreeMap<NaiveDate, Vec<String>>,
+}
+
+impl ScheduleRule {
+    /// Describe a stored schedule
+    pub fn from_schedule(schedule: &WeeklySchedule) -> Self {
+        let weekly = WEEKDAY_NAMES
+            .iter()
+            .zip(&schedule.days)
+            .filter(|(_, intervals)| !intervals.is_empty())
+            .map(|(name, intervals)| (name.to_string(), format_intervals(intervals)))
+            .collect();
+        let exceptions = schedule
+            .exceptions
+            .iter()
+            .map(|(date, intervals)| (*date, format_intervals(intervals)))
+            .collect();
+
+        Self {
+            name: schedule.name.clone(),
+            weekly,
+            exceptions,
+        }
+    }
+
+    /// Build the schedule described by the rule
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` for an unknown weekday, a malformed interval or
+    /// overlapping intervals.
+    pub fn to_schedule(&self) -> StorageResult<WeeklySchedule> {
+        let mut schedule = WeeklySchedule::new(&self.name);
+        for (day, intervals) in &self.weekly {
+            let index = WEEKDAY_NAMES
+                .iter()
+                .position(|name| name == day)
+                .ok_or_else(|| {
+                    StorageError::Validation(format!(
+                        "Unknown weekday '{}': expected one of {}",
+                        day,
+                        WEEKDAY_NAMES.join(", ")
+                    ))
+                })?;
+            let weekday = Weekday::try_from(index as u8).expect("index is 0-6");
+            for interval in intervals {
+                schedule = schedule.with_interval(weekday, TimeInterval::parse(interval)?);
+            }
+        }
+        for (date, intervals) in &self.exceptions {
+            let intervals = intervals
+                .iter()
+                .map(|interval| TimeInterval::parse(interval))
+                .collect::<StorageResult<Vec<_>>>()?;
+            schedule = schedule.with_exception(*date, intervals);
+        }
+
+        schedule.validate()?;
+        Ok(schedule)
+    }
+}
+
+/// Daily passage quota for one user or for each user of a company
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum QuotaRule {
+    /// Quota of one user, winning over their company's
+    User {
+        matricula: String,
+        max_passages: i64,
+    },
+    /// Quota of every user of a company
+    Company { empresa: String, max_passages: i64 },
+}
+
+impl QuotaRule {
+    /// Passages allowed per day
+    pub fn max_passages(&self) -> i64 {
+        match self {
+            QuotaRule::User { max_passages, .. } | QuotaRule::Company { max_passages, .. } => {
+                *max_passages
+            }
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            QuotaRule::User { matricula, .. } => format!("quota for user '{}'", matricula),
+            QuotaRule::Company { empresa, .. } => format!("quota for company '{}'", empresa),
+        }
+    }
+}
+
+/// Problem preventing a rule set from being activated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleConflict {
+    /// Rule the problem was found in
+    pub rule: String,
+
+    /// What is wrong with it
+    pub reason: String,
+}
+
+impl RuleConflict {
+    fn new(rule: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            rule: rule.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for RuleConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.reason)
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            format_version: RULES_FORMAT_VERSION,
+            anti_passback: AntiPassbackRule::default(),
+            schedules: Vec::new(),
+            quotas: Vec::new(),
+        }
+    }
+}
+
+impl RuleSet {
+    /// Read the rule set configured in a database
+    pub async fn collect(pool: &SqlitePool) -> StorageResult<Self> {
+        let schedules = SqliteScheduleRepository::new(pool.clone())
+            .find_all()
+            .await?
+            .iter()
+            .map(ScheduleRule::from_schedule)
+            .collect();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT u.matricula AS "matricula?", q.empresa, q.max_passages
+            FROM daily_quotas q
+            LEFT JOIN users u ON u.id = q.user_id
+            ORDER BY q.empresa IS NULL, q.empresa, u.matricula
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+        let quotas = rows
+            .into_iter()
+            .filter_map(|row| match (row.matricula, row.empresa) {
+                (_, Some(empresa)) => Some(QuotaRule::Company {
+                    empresa,
+                    max_passages: row.max_passages,
+                }),
+                (Some(matricula), None) => Some(QuotaRule::User {
+                    matricula,
+                    max_passages: row.max_passages,
+                }),
+                (None, None) => None,
+            })
+            .collect();
+
+        Ok(Self {
+            schedules,
+            quotas,
+            ..Self::default()
+        })
+    }
+
+    /// Serialize the rule set as pretty-printed JSON
+    pub fn to_json(&self) -> StorageResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| StorageError::Internal(format!("Failed to serialize rules: {}", e)))
+    }
+
+    /// Parse a rule set document
+    ///
+    /// Only the document structure is checked here; use
+    /// [`check()`](Self::check) for the rules themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` for malformed JSON or a different format
+    /// version.
+    pub fn from_json(json: &str) -> StorageResult<Self> {
+        let rules: Self = serde_json::from_str(json)
+            .map_err(|e| StorageError::Validation(format!("Invalid rules document: {}", e)))?;
+        if rules.format_version != RULES_FORMAT_VERSION {
+            return Err(StorageError::Validation(format!(
+                "Unsupported rules format version {} (expected {})",
+                rules.format_version, RULES_FORMAT_VERSION
+            )));
+        }
+        Ok(rules)
+    }
+
+    /// List every conflict that prevents activating the rule set
+    ///
+    /// An empty list means [`apply()`](Self::apply) will accept the
+    /// document, unless it refers to users missing from the database.
+    pub fn check(&self) -> Vec<RuleConflict> {
+        let mut conflicts = Vec::new();
+
+        if self.anti_passback.window_seconds != DEFAULT_ANTI_PASSBACK_WINDOW_SECS {
+            conflicts.push(RuleConflict::new(
+                "anti_passback",
+                format!(
+                    "window of {} seconds cannot be applied from a rules document, \
+                     devices use {} seconds unless given an AntiPassbackPolicy",
+                    self.anti_passback.window_seconds, DEFAULT_ANTI_PASSBACK_WINDOW_SECS
+                ),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for rule in &self.schedules {
+            let label = format!("schedule '{}'", rule.name);
+            if !names.insert(rule.name.as_str()) {
+                conflicts.push(RuleConflict::new(&label, "defined more than once"));
+            }
+            if let Err(e) = rule.to_schedule() {
+                conflicts.push(RuleConflict::new(&label, e.to_string()));
+            }
+        }
+
+        let mut scopes = HashSet::new();
+        for rule in &self.quotas {
+            let label = rule.label();
+            let scope = match rule {
+                QuotaRule::User { matricula, .. } => (true, matricula.trim()),
+                QuotaRule::Company { empresa, .. } => (false, empresa.trim()),
+            };
+            if !scopes.insert(scope) {
+                conflicts.push(RuleConflict::new(&label, "defined more than once"));
+            }
+            if scope.1.is_empty() {
+                conflicts.push(RuleConflict::new(&label, "has an empty key"));
+            }
+            if rule.max_passages() < 1 {
+                conflicts.push(RuleConflict::new(
+                    &label,
+                    format!("must allow at least 1 passage, got {}", rule.max_passages()),
+                ));
+            }
+        }
+
+        conflicts
+    }
+
+    /// Activate the rule set in a database
+    ///
+    /// In [`ImportMode::Replace`] schedules and quotas missing from the
+    /// document are deleted; in [`ImportMode::Merge`] they are kept.
+    /// Schedules are matched by name and keep their database ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Validation` listing the conflicts if [`check()`](Self::check)
+    /// finds any, and `NotFound` for a user quota whose matricula does not
+    /// exist. Nothing is written on error.
+    pub async fn apply(&self, pool: &SqlitePool, mode: ImportMode) -> StorageResult<()> {
+        let conflicts = self.check();
+        if !conflicts.is_empty() {
+            let list: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+            return Err(StorageError::Validation(format!(
+                "Rule set has {} conflict(s): {}",
+                conflicts.len(),
+                list.join("; ")
+            )));
+        }
+
+        let mut tx = pool.begin().await?;
+
+        if mode == ImportMode::Replace {
+            let names: Vec<&str> = self.schedules.iter().map(|s| s.name.as_str()).collect();
+            let existing = sqlx::query!(r#"SELECT id AS "id!", name FROM schedules"#)
+                .fetch_all(&mut *tx)
+                .await?;
+            for schedule in existing {
+                if !names.contains(&schedule.name.as_str()) {
+                    sqlx::query!("DELETE FROM schedules WHERE id = ?", schedule.id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+            sqlx::query!("DELETE FROM daily_quotas")
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for rule in &self.schedules {
+            let schedule = rule.to_schedule()?;
+            let id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO schedules (name) VALUES (?)
+                ON CONFLICT (name) DO UPDATE SET updated_at = datetime('now')
+                RETURNING id AS "id!"
+                "#,
+                schedule.name
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query!("DELETE FROM schedule_intervals WHERE schedule_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("DELETE FROM schedule_exceptions WHERE schedule_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+            SqliteScheduleRepository::insert_children(&mut tx, id, &schedule).await?;
+        }
+
+        for rule in &self.quotas {
+            match rule {
+                QuotaRule::User {
+                    matricula,
+                    max_passages,
+                } => {
+                    let matricula_key = matricula.trim();
+                    let user_id = sqlx::query_scalar!(
+                        r#"SELECT id AS "id!" FROM users WHERE matricula = ?"#,
+                        matricula_key
+                    )
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                    let Some(user_id) = user_id else {
+                        return Err(StorageError::NotFound {
+                            entity_type: "User".to_string(),
+                            field: "matricula".to_string(),
+                            value: matricula.clone(),
+                        });
+                    };
+                    sqlx::query!(
+                        r#"
+                        INSERT INTO daily_quotas (user_id, max_passages) VALUES (?, ?)
+                        ON CONFLICT (user_id) DO UPDATE SET
+                            max_passages = excluded.max_passages,
+                            updated_at = datetime('now')
+                        "#,
+                        user_id,
+                        max_passages
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                QuotaRule::Company {
+                    empresa,
+                    max_passages,
+                } => {
+                    let empresa = empresa.trim();
+                    sqlx::query!(
+                        r#"
+                        INSERT INTO daily_quotas (empresa, max_passages) VALUES (?, ?)
+                        ON CONFLICT (empresa) DO UPDATE SET
+                            max_passages = excluded.max_passages,
+                            updated_at = datetime('now')
+                        "#,
+                        empresa,
+                        max_passages
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+fn format_intervals(intervals: &[TimeInterval]) -> Vec<String> {
+    intervals.iter().map(ToString::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::repositories::{QuotaRepository, SqliteQuotaRepository};
+
+    const DOCUMENT: &str = r#"{
+        "format_version": 1,
+        "anti_passback": { "window_seconds": 300 },
+        "schedules": [
+            {
+                "name": "Turno noturno",
+                "weekly": { "monday": ["22:00-06:00"], "friday": ["22:00-06:00"] },
+                "exceptions": { "2025-12-25": [] }
+            },
+            { "name": "Comercial", "weekly": { "tuesday": ["08:00-12:00", "13:00-18:00"] } }
+        ],
+        "quotas": [
+            { "scope": "company", "empresa": "Academia", "max_passages": 2 },
+            { "scope": "user", "matricula": "1002", "max_passages": 4 }
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn test_import_then_export_roundtrip() {
+        let db = Database::in_memory().await.unwrap();
+        let rules = RuleSet::from_json(DOCUMENT).unwrap();
+        assert!(rules.check().is_empty());
+
+        rules.apply(db.pool(), ImportMode::Replace).await.unwrap();
+
+        let exported = RuleSet::collect(db.pool()).await.unwrap();
+        assert_eq!(exported.anti_passback, rules.anti_passback);
+        assert_eq!(exported.quotas, rules.quotas);
+        // Exported sorted by name
+        let mut expected = rules.schedules.clone();
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(exported.schedules, expected);
+
+        let reparsed = RuleSet::from_json(&exported.to_json().unwrap()).unwrap();
+        assert_eq!(reparsed, exported);
+    }
+
+    #[tokio::test]
+    async fn test_replace_and_merge_modes() {
+        let db = Database::in_memory().await.unwrap();
+        let schedules = SqliteScheduleRepository::new(db.pool().clone());
+        let quotas = SqliteQuotaRepository::new(db.pool().clone());
+        schedules
+            .create(&WeeklySchedule::new("Antigo"))
+            .await
+            .unwrap();
+        quotas.set_company_quota("Refeitorio", 1).await.unwrap();
+
+        let rules = RuleSet::from_json(DOCUMENT).unwrap();
+        rules.apply(db.pool(), ImportMode::Merge).await.unwrap();
+        assert!(schedules.find_by_name("Antigo").await.unwrap().is_some());
+        assert_eq!(quotas.find_all().await.unwrap().len(), 3);
+
+        let kept_id = schedules
+            .find_by_name("Comercial")
+            .await
+            .unwrap()
+            .unwrap()
+            .id;
+        rules.apply(db.pool(), ImportMode::Replace).await.unwrap();
+        assert!(schedules.find_by_name("Antigo").await.unwrap().is_none());
+        assert_eq!(quotas.find_all().await.unwrap().len(), 2);
+        let comercial = schedules.find_by_name("Comercial").await.unwrap().unwrap();
+        assert_eq!(comercial.id, kept_id);
+        assert_eq!(comercial.intervals(Weekday::Tue).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_block_activation() {
+        let db = Database::in_memory().await.unwrap();
+        let mut rules = RuleSet::from_json(DOCUMENT).unwrap();
+        rules.anti_passback.window_seconds = 60;
+        rules.schedules.push(ScheduleRule {
+            name: "Comercial".to_string(),
+            weekly: BTreeMap::from([(
+                "tuesday".to_string(),
+                vec!["08:00-12:00".to_string(), "11:00-14:00".to_string()],
+            )]),
+            exceptions: BTreeMap::new(),
+        });
+        rules.schedules.push(ScheduleRule {
+            name: "Feriado".to_string(),
+            weekly: BTreeMap::from([("segunda".to_string(), vec!["08:00-12:00".to_string()])]),
+            exceptions: BTreeMap::new(),
+        });
+        rules.quotas.push(QuotaRule::Company {
+            empresa: " Academia ".to_string(),
+            max_passages: 0,
+        });
+
+        let conflicts = rules.check();
+        let rules_flagged: Vec<&str> = conflicts.iter().map(|c| c.rule.as_str()).collect();
+        assert_eq!(
+            rules_flagged,
+            vec![
+                "anti_passback",
+                "schedule 'Comercial'",
+                "schedule 'Comercial'",
+                "schedule 'Feriado'",
+                "quota for company ' Academia '",
+                "quota for company ' Academia '",
+            ]
+        );
+        assert!(conflicts[2].reason.contains("overlaps"));
+
+        assert!(matches!(
+            rules.apply(db.pool(), ImportMode::Merge).await,
+            Err(StorageError::Validation(_))
+        ));
+        assert!(
+            RuleSet::collect(db.pool())
+                .await
+                .unwrap()
+                .schedules
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_user_rolls_back() {
+        let db = Database::in_memory().await.unwrap();
+        let mut rules = RuleSet::from_json(DOCUMENT).unwrap();
+        rules.quotas.push(QuotaRule::User {
+            matricula: "NOBODY".to_string(),
+            max_passages: 1,
+        });
+
+        assert!(matches!(
+            rules.apply(db.pool(), ImportMode::Replace).await,
+            Err(StorageError::NotFound { .. })
+        ));
+        let current = RuleSet::collect(db.pool()).await.unwrap();
+        assert!(current.schedules.is_empty());
+        assert!(current.quotas.is_empty());
+
+        assert!(
+            RuleSet::from_json(
+                r#"{"format_version": 2, "anti_passback": {"window_seconds": 300}}"#
+            )
+            .is_err()
+        );
+    }
+}
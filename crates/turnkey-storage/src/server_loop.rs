@@ -0,0 +1,276 @@
+//! Answering device access requests on the server.
+//!
+//! [`ServerValidationLoop`] receives the access requests devices send to a
+//! [`TcpServer`], validates them with an [`OfflineValidator`] over the
+//! server's database and sends the decision back in the format
+//! [`OnlineValidator`](crate::OnlineValidator) expects (`seconds]message`).
+//!
+//! # Cancellation
+//!
+//! Each request is validated on its own task, tied to the connection it
+//! arrived on through [`TcpServer::cancellation_token()`]. When the device
+//! disconnects mid-validation, the validation stops at its next query
+//! instead of running to the end for nobody, and records nothing: no access
+//! log is written for a passage the device never heard about. See
+//! [`OfflineValidator::validate_cancellable()`] for the exact guarantees.
+//!
+//! Other requests keep being received and answered while a validation runs.
+//!
+//! # Pushed Events
+//!
+//! `ER` event chunks a device pushes, such as the replay of what it decided
+//! while offline (see [`sync`](crate::sync)), are stored in the central log
+//! by an [`EventReceiver`], which skips events whose NSR it already has.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use turnkey_network::{CancellationToken, TcpServer, TcpServerConfig};
+//! use turnkey_storage::server_loop::ServerValidationLoop;
+//! use turnkey_storage::{Database, DatabaseConfig};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let server = TcpServer::bind(TcpServerConfig::default()).await?;
+//!
+//! let stop = CancellationToken::new();
+//! let mut validation = ServerValidationLoop::new(server, db.pool().clone());
+//! validation.run(stop.clone()).await;
+//!
+//! let stats = validation.stats();
+//! println!("{} answered, {} cancelled", stats.responses, stats.cancelled);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{StorageError, StorageResult};
+use crate::sync::EventReceiver;
+use crate::validator::OfflineValidator;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use turnkey_core::DeviceId;
+use turnkey_network::{CancellationToken, TcpServer};
+use turnkey_protocol::commands::access::AccessResponse;
+use turnkey_protocol::commands::events::EventChunk;
+use turnkey_protocol::{CommandCode, FieldData, Message, MessageBuilder};
+
+/// Adjusts the validator built for each request
+type ValidatorSetup = Arc<dyn Fn(OfflineValidator) -> OfflineValidator + Send + Sync>;
+
+/// Request counters of a [`ServerValidationLoop`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerLoopStats {
+    /// Access requests received
+    pub requests: u64,
+
+    /// Decisions sent back to the device
+    pub responses: u64,
+
+    /// Validations stopped because the device disconnected
+    pub cancelled: u64,
+
+    /// Malformed requests, validation failures, failed replies and pushed
+    /// events that could not be stored
+    pub errors: u64,
+
+    /// Pushed events written to the central log
+    pub events: u64,
+
+    /// Pushed events skipped as already received
+    pub duplicate_events: u64,
+}
+
+/// Validates device access requests until stopped
+///
+/// See the [module documentation](self).
+pub struct ServerValidationLoop {
+    server: TcpServer,
+    pool: SqlitePool,
+    receiver: EventReceiver,
+    setup: Option<ValidatorSetup>,
+    in_flight: JoinSet<(DeviceId, StorageResult<AccessResponse>)>,
+    stats: ServerLoopStats,
+}
+
+impl std::fmt::Debug for ServerValidationLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerValidationLoop")
+            .field("in_flight", &self.in_flight.len())
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ServerValidationLoop {
+    /// Answer requests arriving at `server` from the database in `pool`
+    pub fn new(server: TcpServer, pool: SqlitePool) -> Self {
+        Self {
+            server,
+            receiver: EventReceiver::new(pool.clone()),
+            pool,
+            setup: None,
+            in_flight: JoinSet::new(),
+            stats: ServerLoopStats::default(),
+        }
+    }
+
+    /// Configure the validator of every request
+    ///
+    /// `setup` receives an [`OfflineValidator`] already tagged with the
+    /// requesting device's ID, and can add a clock, an outbox, a card
+    /// filter and so on.
+    pub fn with_validator_setup(
+        mut self,
+        setup: impl Fn(OfflineValidator) -> OfflineValidator + Send + Sync + 'static,
+    ) -> Self {
+        self.setup = Some(Arc::new(setup));
+        self
+    }
+
+    /// The server the requests arrive at
+    pub fn server(&self) -> &TcpServer {
+        &self.server
+    }
+
+    /// Counters since the loop was created
+    pub fn stats(&self) -> ServerLoopStats {
+        self.stats
+    }
+
+    /// Receive, validate and answer requests until `stop` is cancelled
+    ///
+    /// Validations already running when `stop` fires are completed and
+    /// answered before returning. The loop can be run again afterwards.
+    pub async fn run(&mut self, stop: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                Some(joined) = self.in_flight.join_next() => self.finish(joined).await,
+                received = self.server.recv_any() => match received {
+                    Ok((device_id, message)) if EventChunk::is_chunk(&message) => {
+                        self.receive_events(device_id, &message).await;
+                    }
+                    Ok((device_id, message)) => self.start(device_id, &message),
+                    Err(e) => {
+                        tracing::debug!(error = %e, "receive failed");
+                    }
+                },
+            }
+        }
+
+        while let Some(joined) = self.in_flight.join_next().await {
+            self.finish(joined).await;
+        }
+    }
+
+    /// Start validating an access request on its own task
+    fn start(&mut self, device_id: DeviceId, message: &Message) {
+        if message.command != CommandCode::AccessRequest {
+            return;
+        }
+        self.stats.requests += 1;
+
+        let request = match message.as_access_request() {
+            Ok(request) => request,
+            Err(e) => {
+                self.stats.errors += 1;
+                tracing::warn!(%device_id, error = %e, "malformed access request");
+                return;
+            }
+        };
+        // Gone between receiving the request and getting here
+        let Some(cancel) = self.server.cancellation_token(device_id) else {
+            self.stats.cancelled += 1;
+            return;
+        };
+
+        let mut validator = OfflineValidator::new(self.pool.clone()).with_device_id(device_id);
+        if let Some(setup) = &self.setup {
+            validator = setup(validator);
+        }
+        self.in_flight.spawn(async move {
+            let result = validator.validate_cancellable(&request, cancel).await;
+            (device_id, result)
+        });
+    }
+
+    /// Store the events of a pushed `ER` chunk
+    async fn receive_events(&mut self, device_id: DeviceId, message: &Message) {
+        match self.receiver.receive(device_id, message).await {
+            Ok(report) => {
+                self.stats.events += report.inserted;
+                self.stats.duplicate_events += report.duplicates;
+                tracing::debug!(
+                    %device_id,
+                    inserted = report.inserted,
+                    duplicates = report.duplicates,
+                    last_nsr = report.last_nsr,
+                    "device events received"
+                );
+            }
+            Err(e) => {
+                self.stats.errors += 1;
+                tracing::warn!(%device_id, error = %e, "pushed events not stored");
+            }
+        }
+    }
+
+    /// Send the outcome of a finished validation
+    async fn finish(
+        &mut self,
+        joined: Result<(DeviceId, StorageResult<AccessResponse>), tokio::task::JoinError>,
+    ) {
+        let (device_id, result) = match joined {
+            Ok(finished) => finished,
+            Err(e) => {
+                self.stats.errors += 1;
+                tracing::error!(error = %e, "validation task failed");
+                return;
+            }
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(StorageError::Cancelled) => {
+                self.stats.cancelled += 1;
+                tracing::debug!(%device_id, "validation cancelled, device disconnected");
+                return;
+            }
+            Err(e) => {
+                self.stats.errors += 1;
+                tracing::warn!(%device_id, error = %e, "validation failed");
+                return;
+            }
+        };
+
+        let sent = match response_message(device_id, &response) {
+            Ok(reply) => self
+                .server
+                .send(device_id, reply)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match sent {
+            Ok(()) => self.stats.responses += 1,
+            Err(e) => {
+                self.stats.errors += 1;
+                tracing::warn!(%device_id, error = %e, "reply failed");
+            }
+        }
+    }
+}
+
+/// Encode a decision the way `OnlineValidator` expects it: `seconds]message`
+fn response_message(device_id: DeviceId, response: &AccessResponse) -> StorageResult<Message> {
+    let build = || -> turnkey_core::Result<Message> {
+        let command = CommandCode::parse(response.decision().command_code())?;
+        MessageBuilder::new(device_id, command)
+            .field(FieldData::new(response.timeout_seconds().to_string())?)
+            .field(FieldData::new(response.display_message().to_string())?)
+            .build()
+    };
+    build().map_err(|e| StorageError::ProtocolError(e.to_string()))
+}
@@ -0,0 +1,436 @@
+//! Offline event buffering and replay to the server.
+//!
+//! While a device cannot reach the server, the accesses it decides on its
+//! own exist only in its local database. With an [`Outbox`] set on its
+//! [`OfflineValidator`](crate::OfflineValidator) (see
+//! [`with_outbox()`](crate::OfflineValidator::with_outbox)), every access
+//! log is queued as an `ER` event in the same transaction, numbered with
+//! the device's NSR. A [`SyncEngine`] keeps the queue while the link is
+//! down and replays it in NSR order once the link is back.
+//!
+//! On the server, an [`EventReceiver`] stores pushed events in the central
+//! log and remembers the highest NSR stored for each device. Events at or
+//! below it were already received, by an earlier replay or a send whose
+//! confirmation was lost, and are skipped. This makes the at-least-once
+//! delivery of the outbox safe to repeat.
+//!
+//! # Usage Pattern
+//!
+//! ```no_run
+//! use turnkey_core::DeviceId;
+//! use turnkey_network::{TcpClient, TcpClientConfig};
+//! use turnkey_storage::outbox::Outbox;
+//! use turnkey_storage::sync::SyncEngine;
+//! use turnkey_storage::{Database, DatabaseConfig, OfflineValidator};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Database::new(DatabaseConfig::new("turnkey.db")).await?;
+//! let validator = OfflineValidator::new(db.pool().clone())
+//!     .with_device_id(DeviceId::new(15)?)
+//!     .with_outbox(Outbox::new(db.pool().clone()));
+//!
+//! let mut sync = SyncEngine::new(db.pool().clone());
+//! let mut client = TcpClient::new(TcpClientConfig::default());
+//!
+//! // Link restored: send everything buffered while offline
+//! client.connect().await?;
+//! let report = sync.reconnected(&mut client).await?;
+//! println!("{} events replayed", report.sent);
+//!
+//! // Then, periodically, the events queued since
+//! sync.sync(&mut client).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::StorageResult;
+use crate::log_pull::{LogPuller, protocol_error};
+use crate::outbox::{DispatchReport, NotificationSink, Outbox, OutboxDispatcher};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::time::Instant;
+use turnkey_core::DeviceId;
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::events::EventChunk;
+
+/// Shortest wait between two [`SyncEngine::sync()`] rounds unless configured otherwise
+pub const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Buffers events while the server is unreachable and replays them on reconnection
+///
+/// The engine starts disconnected: nothing is sent before the first
+/// [`reconnected()`](Self::reconnected). See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct SyncEngine {
+    dispatcher: OutboxDispatcher,
+    interval: Duration,
+    online: bool,
+    next_sync: Instant,
+}
+
+impl SyncEngine {
+    /// Create an engine for the outbox on the given database pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            dispatcher: OutboxDispatcher::new(pool),
+            interval: DEFAULT_SYNC_INTERVAL,
+            online: false,
+            next_sync: Instant::now(),
+        }
+    }
+
+    /// Set how many events are read from the outbox at a time
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.dispatcher = self.dispatcher.with_batch_size(batch_size);
+        self
+    }
+
+    /// Set the retry waits of events that fail to send while connected
+    ///
+    /// See [`OutboxDispatcher::with_retry_delay()`].
+    pub fn with_retry_delay(mut self, initial: Duration, max: Duration) -> Self {
+        self.dispatcher = self.dispatcher.with_retry_delay(initial, max);
+        self
+    }
+
+    /// Set the shortest wait between two [`sync()`](Self::sync) rounds
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The outbox the events are sent from
+    pub fn outbox(&self) -> &Outbox {
+        self.dispatcher.outbox()
+    }
+
+    /// Whether events are being sent, rather than buffered
+    pub fn is_online(&self) -> bool {
+        self.online
+    }
+
+    /// The link to the server is down: buffer events until [`reconnected()`](Self::reconnected)
+    pub fn disconnected(&mut self) {
+        if self.online {
+            tracing::info!("server unreachable, buffering events");
+        }
+        self.online = false;
+    }
+
+    /// The link to the server is back: replay every buffered event now
+    ///
+    /// Retry waits of earlier failures are cancelled and the outbox is sent
+    /// in NSR order until it is empty or a send fails, which puts the
+    /// engine back to buffering. Sent messages are purged.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a database operation fails.
+    pub async fn reconnected<S: NotificationSink>(
+        &mut self,
+        sink: &mut S,
+    ) -> StorageResult<DispatchReport> {
+        self.online = true;
+        let pending = self.outbox().retry_now().await?;
+        if pending > 0 {
+            tracing::info!(pending, "replaying buffered events");
+        }
+        self.drain(sink).await
+    }
+
+    /// Send the events queued since the last round
+    ///
+    /// Does nothing while buffering, or if the last round was less than
+    /// the [interval](Self::with_interval) ago. A failed send puts the
+    /// engine back to buffering; the event stays queued for the next
+    /// [`reconnected()`](Self::reconnected).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a database operation fails.
+    pub async fn sync<S: NotificationSink>(
+        &mut self,
+        sink: &mut S,
+    ) -> StorageResult<DispatchReport> {
+        if !self.online || Instant::now() < self.next_sync {
+            return Ok(DispatchReport::default());
+        }
+        self.drain(sink).await
+    }
+
+    async fn drain<S: NotificationSink>(&mut self, sink: &mut S) -> StorageResult<DispatchReport> {
+        self.next_sync = Instant::now() + self.interval;
+        let mut total = DispatchReport::default();
+        loop {
+            let report = self.dispatcher.dispatch(sink).await?;
+            total.sent += report.sent;
+            total.failed += report.failed;
+            if report.failed > 0 {
+                self.disconnected();
+                break;
+            }
+            if report.sent == 0 {
+                break;
+            }
+        }
+
+        if total.sent > 0 {
+            self.outbox().purge_sent(Utc::now()).await?;
+        }
+        Ok(total)
+    }
+}
+
+/// Outcome of one [`EventReceiver::receive()`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiveReport {
+    /// Events in the message
+    pub events: u64,
+
+    /// Events written to the central log
+    pub inserted: u64,
+
+    /// Events skipped: NSR already received, or the same log already stored
+    pub duplicates: u64,
+
+    /// Highest NSR received from the device so far
+    pub last_nsr: i64,
+}
+
+/// Stores the events devices push to the server, once per NSR
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct EventReceiver {
+    pool: SqlitePool,
+    puller: LogPuller,
+}
+
+impl EventReceiver {
+    /// Create a receiver writing to the central log on the given pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            puller: LogPuller::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Highest NSR received from `device_id` (0 if none)
+    pub async fn last_nsr(&self, device_id: DeviceId) -> StorageResult<i64> {
+        let device = i64::from(device_id.as_u8());
+        let last = sqlx::query_scalar!(
+            "SELECT last_nsr FROM event_receipts WHERE device_id = ?",
+            device
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(last.unwrap_or(0))
+    }
+
+    /// Store the events of an `ER` chunk pushed by `device_id`
+    ///
+    /// Events with an NSR at or below [`last_nsr()`](Self::last_nsr) are
+    /// skipped; the others are written like pulled events (see
+    /// [`LogPuller::store()`]), keeping their NSR, and move the last NSR
+    /// forward. A gap in the
+    /// numbering is logged but accepted, since the device may have purged
+    /// events it could not deliver.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if the message is not an event chunk, and
+    /// error if a database operation fails.
+    pub async fn receive(
+        &self,
+        device_id: DeviceId,
+        message: &Message,
+    ) -> StorageResult<ReceiveReport> {
+        let chunk = EventChunk::parse(message).map_err(protocol_error)?;
+        let last_nsr = self.last_nsr(device_id).await?;
+        let mut report = ReceiveReport {
+            events: chunk.events.len() as u64,
+            last_nsr,
+            ..Default::default()
+        };
+
+        let mut events: Vec<_> = chunk
+            .events
+            .into_iter()
+            .filter(|event| event.id > last_nsr)
+            .collect();
+        events.sort_by_key(|event| event.id);
+        report.duplicates = report.events - events.len() as u64;
+        let (Some(first), Some(last)) = (events.first(), events.last()) else {
+            return Ok(report);
+        };
+        if first.id > last_nsr + 1 {
+            tracing::warn!(
+                %device_id,
+                expected = last_nsr + 1,
+                received = first.id,
+                "gap in device event numbering"
+            );
+        }
+        report.last_nsr = last.id;
+
+        let stored = self.puller.store_numbered(device_id, &events).await?;
+        report.inserted = stored.inserted;
+        report.duplicates += stored.duplicates;
+
+        let device = i64::from(device_id.as_u8());
+        sqlx::query!(
+            r#"
+            INSERT INTO event_receipts (device_id, last_nsr, received_at)
+            VALUES (?, ?, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            ON CONFLICT(device_id) DO UPDATE
+            SET last_nsr = MAX(last_nsr, excluded.last_nsr),
+                received_at = excluded.received_at
+            "#,
+            device,
+            report.last_nsr
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::error::StorageError;
+    use crate::models::{AccessLog, Direction, ReaderType};
+    use crate::repositories::{AccessLogRepository, SqliteAccessLogRepository};
+    use crate::transaction;
+    use tokio::sync::mpsc;
+
+    fn device() -> DeviceId {
+        DeviceId::new(15).unwrap()
+    }
+
+    async fn enqueue_access(db: &Database, card_number: &str) -> i64 {
+        let log = AccessLog::new(
+            None,
+            None,
+            card_number.to_string(),
+            Direction::Entry,
+            ReaderType::Rfid,
+            true,
+            Some("Acesso liberado".to_string()),
+            Utc::now(),
+        );
+        let mut tx = db.pool().begin().await.unwrap();
+        let log_id = transaction::create_access_log(&mut tx, &log).await.unwrap();
+        let nsr = Outbox::enqueue_access(&mut tx, device(), log_id, &log)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        nsr
+    }
+
+    /// Sink of a server that is down
+    struct Unreachable;
+
+    impl NotificationSink for Unreachable {
+        async fn send(&mut self, _message: Message) -> StorageResult<()> {
+            Err(StorageError::NetworkError("Connection refused".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buffers_while_offline_and_replays_on_reconnect() {
+        let db = Database::in_memory().await.unwrap();
+        let mut sync = SyncEngine::new(db.pool().clone())
+            .with_batch_size(2)
+            .with_interval(Duration::ZERO)
+            .with_retry_delay(Duration::from_secs(60), Duration::from_secs(60));
+        let (mut sender, mut receiver) = mpsc::channel(8);
+
+        assert_eq!(enqueue_access(&db, "00000000000000000001").await, 1);
+        assert_eq!(
+            sync.sync(&mut sender).await.unwrap(),
+            DispatchReport::default()
+        );
+
+        // A failed replay goes back to buffering, with a long retry wait
+        let report = sync.reconnected(&mut Unreachable).await.unwrap();
+        assert_eq!(report, DispatchReport { sent: 0, failed: 1 });
+        assert!(!sync.is_online());
+        for card in ["00000000000000000002", "00000000000000000003"] {
+            enqueue_access(&db, card).await;
+        }
+
+        // Reconnecting cancels the wait and sends everything in NSR order
+        let report = sync.reconnected(&mut sender).await.unwrap();
+        assert_eq!(report, DispatchReport { sent: 3, failed: 0 });
+        let nsrs: Vec<i64> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|message| EventChunk::parse(&message).unwrap().events[0].id)
+            .collect();
+        assert_eq!(nsrs, vec![1, 2, 3]);
+        assert_eq!(sync.outbox().pending_count().await.unwrap(), 0);
+
+        // Purged messages do not give their numbers back
+        assert_eq!(enqueue_access(&db, "00000000000000000004").await, 4);
+        let report = sync.sync(&mut sender).await.unwrap();
+        assert_eq!(report.sent, 1);
+    }
+
+    #[tokio::test]
+    async fn test_receiver_skips_events_already_received() {
+        let device_db = Database::in_memory().await.unwrap();
+        let server_db = Database::in_memory().await.unwrap();
+        for card in ["00000000000000000001", "00000000000000000002"] {
+            enqueue_access(&device_db, card).await;
+        }
+        let pending = Outbox::new(device_db.pool().clone())
+            .pending(10)
+            .await
+            .unwrap();
+        let receiver = EventReceiver::new(server_db.pool().clone());
+        assert_eq!(receiver.last_nsr(device()).await.unwrap(), 0);
+
+        let report = receiver
+            .receive(device(), &pending[0].message)
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ReceiveReport {
+                events: 1,
+                inserted: 1,
+                duplicates: 0,
+                last_nsr: 1,
+            }
+        );
+
+        // Replayed after a lost confirmation
+        let report = receiver
+            .receive(device(), &pending[0].message)
+            .await
+            .unwrap();
+        assert_eq!((report.inserted, report.duplicates), (0, 1));
+
+        let report = receiver
+            .receive(device(), &pending[1].message)
+            .await
+            .unwrap();
+        assert_eq!((report.inserted, report.last_nsr), (1, 2));
+        assert_eq!(receiver.last_nsr(device()).await.unwrap(), 2);
+
+        // The central log keeps the device's numbering
+        let logs = SqliteAccessLogRepository::new(server_db.pool().clone())
+            .find_by_nsr_range(15, 1, 2)
+            .await
+            .unwrap();
+        let nsrs: Vec<_> = logs.iter().map(|log| log.nsr).collect();
+        assert_eq!(nsrs, [Some(1), Some(2)]);
+
+        let other = DeviceId::new(16).unwrap();
+        assert_eq!(receiver.last_nsr(other).await.unwrap(), 0);
+    }
+}
@@ -39,6 +39,7 @@
 //! #     allow_bio: false,
 //! #     allow_keypad: false,
 //! #     codigo: None,
+//! #     empresa: None,
 //! #     created_at: Utc::now(),
 //! #     updated_at: Utc::now(),
 //! # };
@@ -74,7 +75,12 @@
 
 use crate::error::StorageResult;
 use crate::models::{AccessLog, Card, User};
+use crate::outbox::Outbox;
+use crate::pin::Argon2PinHasher;
+use crate::repositories::access_log::{insert_unless_passback, passback_applies};
+use chrono::{DateTime, Utc};
 use sqlx::{Sqlite, Transaction};
+use std::sync::Arc;
 
 /// Create a new user within a transaction
 ///
@@ -87,34 +93,48 @@ use sqlx::{Sqlite, Transaction};
 ///
 /// Returns the auto-generated user ID on success
 ///
+/// A plaintext `codigo` is stored hashed with [`Argon2PinHasher`] at default
+/// cost. It gets no lookup digest here, since the digest key is not at hand:
+/// like imported codes, it is digested the first time
+/// [`UserRepository::verify_code`](crate::UserRepository::verify_code)
+/// accepts it.
+///
 /// # Errors
 ///
 /// Returns error if:
-/// - Unique constraint violation (duplicate matricula)
+/// - Unique constraint violation (duplicate matricula or keypad code)
 /// - Database constraints violated
 /// - Transaction is already committed or rolled back
 pub async fn create_user(tx: &mut Transaction<'_, Sqlite>, user: &User) -> StorageResult<i64> {
-    let result = sqlx::query(
+    let codigo = match user.codigo.as_deref() {
+        Some(code) if !crate::pin::is_hashed(code) => {
+            let hasher = Arc::new(Argon2PinHasher::default());
+            Some(crate::pin::hash_blocking(hasher, code).await?)
+        }
+        other => other.map(str::to_string),
+    };
+    let result = sqlx::query!(
         r#"
         INSERT INTO users (
             pis, nome, matricula, cpf,
             validade_inicio, validade_fim, ativo,
-            allow_card, allow_bio, allow_keypad, codigo
+            allow_card, allow_bio, allow_keypad, codigo, empresa
         )
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
+        user.pis,
+        user.nome,
+        user.matricula,
+        user.cpf,
+        user.validade_inicio,
+        user.validade_fim,
+        user.ativo,
+        user.allow_card,
+        user.allow_bio,
+        user.allow_keypad,
+        codigo,
+        user.empresa
     )
-    .bind(&user.pis)
-    .bind(&user.nome)
-    .bind(&user.matricula)
-    .bind(&user.cpf)
-    .bind(user.validade_inicio)
-    .bind(user.validade_fim)
-    .bind(user.ativo)
-    .bind(user.allow_card)
-    .bind(user.allow_bio)
-    .bind(user.allow_keypad)
-    .bind(&user.codigo)
     .execute(&mut **tx)
     .await?;
 
@@ -140,7 +160,7 @@ pub async fn create_user(tx: &mut Transaction<'_, Sqlite>, user: &User) -> Stora
 /// - Dual-key consistency check fails
 /// - Transaction is already committed or rolled back
 pub async fn create_card(tx: &mut Transaction<'_, Sqlite>, card: &Card) -> StorageResult<i64> {
-    let result = sqlx::query(
+    let result = sqlx::query!(
         r#"
         INSERT INTO cards (
             numero_cartao, matricula, user_id,
@@ -148,13 +168,13 @@ pub async fn create_card(tx: &mut Transaction<'_, Sqlite>, card: &Card) -> Stora
         )
         VALUES (?, ?, ?, ?, ?, ?)
         "#,
+        card.numero_cartao,
+        card.matricula,
+        card.user_id,
+        card.validade_inicio,
+        card.validade_fim,
+        card.ativo
     )
-    .bind(&card.numero_cartao)
-    .bind(&card.matricula)
-    .bind(card.user_id)
-    .bind(card.validade_inicio)
-    .bind(card.validade_fim)
-    .bind(card.ativo)
     .execute(&mut **tx)
     .await?;
 
@@ -163,6 +183,9 @@ pub async fn create_card(tx: &mut Transaction<'_, Sqlite>, card: &Card) -> Stora
 
 /// Create a new access log entry within a transaction
 ///
+/// A log with a device and no NSR is numbered like
+/// [`AccessLogRepository::create`](crate::AccessLogRepository::create) does.
+///
 /// # Arguments
 ///
 /// * `tx` - Mutable reference to an active SQLite transaction
@@ -181,27 +204,71 @@ pub async fn create_access_log(
     tx: &mut Transaction<'_, Sqlite>,
     log: &AccessLog,
 ) -> StorageResult<i64> {
-    let result = sqlx::query(
+    let result = sqlx::query!(
         r#"
         INSERT INTO access_logs (
             user_id, matricula, card_number, direction,
-            reader_type, granted, display_message, timestamp
+            reader_type, granted, display_message, timestamp,
+            device_id, device_timestamp, correlation_id, nsr
         )
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
+        log.user_id,
+        log.matricula,
+        log.card_number,
+        log.direction,
+        log.reader_type,
+        log.granted,
+        log.display_message,
+        log.timestamp,
+        log.device_id,
+        log.device_timestamp,
+        log.correlation_id,
+        log.nsr
     )
-    .bind(log.user_id)
-    .bind(&log.matricula)
-    .bind(&log.card_number)
-    .bind(log.direction)
-    .bind(log.reader_type)
-    .bind(log.granted)
-    .bind(&log.display_message)
-    .bind(log.timestamp)
     .execute(&mut **tx)
     .await?;
+    let id = result.last_insert_rowid();
+    Outbox::number_log(tx, id).await?;
 
-    Ok(result.last_insert_rowid())
+    Ok(id)
+}
+
+/// Create an access log entry within a transaction unless anti-passback
+/// blocks it
+///
+/// Runs the same check as
+/// [`AccessLogRepository::create_unless_passback`](crate::AccessLogRepository::create_unless_passback),
+/// for writes that must commit together with the log.
+///
+/// # Arguments
+///
+/// * `tx` - Mutable reference to an active SQLite transaction
+/// * `log` - AccessLog entity to create
+/// * `window_start` - Oldest grant in the same direction that blocks this one
+///
+/// # Returns
+///
+/// Returns the auto-generated log ID, or `None` if anti-passback blocked it
+///
+/// # Errors
+///
+/// Returns error if:
+/// - Foreign key constraint violation (invalid user_id or matricula)
+/// - Transaction is already committed or rolled back
+pub async fn create_access_log_unless_passback(
+    tx: &mut Transaction<'_, Sqlite>,
+    log: &AccessLog,
+    window_start: DateTime<Utc>,
+) -> StorageResult<Option<i64>> {
+    if !passback_applies(log) {
+        return create_access_log(tx, log).await.map(Some);
+    }
+    let id = insert_unless_passback(&mut **tx, log, window_start).await?;
+    if let Some(id) = id {
+        Outbox::number_log(tx, id).await?;
+    }
+    Ok(id)
 }
 
 #[cfg(test)]
@@ -232,6 +299,7 @@ mod tests {
             allow_bio: false,
             allow_keypad: false,
             codigo: None,
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -242,11 +310,10 @@ mod tests {
         tx.commit().await.unwrap();
 
         // Verify user was persisted
-        let found: Option<(i64,)> =
-            sqlx::query_as("SELECT id FROM users WHERE matricula = 'TX001'")
-                .fetch_optional(db.pool())
-                .await
-                .unwrap();
+        let found = sqlx::query_scalar!("SELECT id FROM users WHERE matricula = 'TX001'")
+            .fetch_optional(db.pool())
+            .await
+            .unwrap();
 
         assert!(found.is_some());
     }
@@ -269,6 +336,7 @@ mod tests {
             allow_bio: false,
             allow_keypad: false,
             codigo: None,
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -279,11 +347,10 @@ mod tests {
         tx.rollback().await.unwrap();
 
         // Verify user was not persisted
-        let found: Option<(i64,)> =
-            sqlx::query_as("SELECT id FROM users WHERE matricula = 'TX002'")
-                .fetch_optional(db.pool())
-                .await
-                .unwrap();
+        let found = sqlx::query_scalar!("SELECT id FROM users WHERE matricula = 'TX002'")
+            .fetch_optional(db.pool())
+            .await
+            .unwrap();
 
         assert!(found.is_none());
     }
@@ -306,6 +373,7 @@ mod tests {
             allow_bio: false,
             allow_keypad: false,
             codigo: None,
+            empresa: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -330,14 +398,13 @@ mod tests {
         tx.commit().await.unwrap();
 
         // Verify both entities persisted
-        let user_found: Option<(i64,)> =
-            sqlx::query_as("SELECT id FROM users WHERE matricula = 'TX003'")
-                .fetch_optional(db.pool())
-                .await
-                .unwrap();
+        let user_found = sqlx::query_scalar!("SELECT id FROM users WHERE matricula = 'TX003'")
+            .fetch_optional(db.pool())
+            .await
+            .unwrap();
 
-        let card_found: Option<(i64,)> =
-            sqlx::query_as("SELECT id FROM cards WHERE numero_cartao = 'TX003CARD'")
+        let card_found =
+            sqlx::query_scalar!("SELECT id FROM cards WHERE numero_cartao = 'TX003CARD'")
                 .fetch_optional(db.pool())
                 .await
                 .unwrap();
@@ -1,17 +1,41 @@
+use crate::card_filter::CardFilter;
+use crate::cluster::{ClusterNode, DecisionClaim};
+use crate::coercion::CoercionDetector;
 use crate::error::{StorageError, StorageResult};
+use crate::events::EventBus;
 use crate::messages::DisplayMessages;
-use crate::models::{AccessLog, Card, Direction, ReaderType, TemporalValidity};
+use crate::models::{
+    AccessLog, AccessMethod, AntiPassbackPolicy, BiometricTemplate, Card, ClockDriftAlert,
+    Direction, HolidayPolicy, KeypadLockoutPolicy, PendingCard, PinLockoutEvent, PinLockoutSubject,
+    QuotaDay, ReaderType, StaleDataWarning, TemporalValidity, User,
+};
+use crate::outbox::Outbox;
+use crate::pin::PinDigestKey;
 use crate::repositories::{
-    AccessLogRepository, CardRepository, SqliteAccessLogRepository, SqliteCardRepository,
-    SqliteUserRepository, UserRepository,
+    AccessExceptionRepository, AccessLogRepository, AntiPassbackRepository,
+    BiometricTemplateRepository, CardRepository, DeviceRepository, EnrollmentSessionRepository,
+    HolidayRepository, KeypadLockoutRepository, PendingCardRepository, QuotaRepository,
+    ScheduleRepository, SqliteAccessExceptionRepository, SqliteAccessLogRepository,
+    SqliteAntiPassbackRepository, SqliteBiometricTemplateRepository, SqliteCardRepository,
+    SqliteDeviceRepository, SqliteEnrollmentSessionRepository, SqliteHolidayRepository,
+    SqliteKeypadLockoutRepository, SqlitePendingCardRepository, SqliteQuotaRepository,
+    SqliteScheduleRepository, SqliteSyncStateRepository, SqliteUserRepository, SyncStateRepository,
+    UserRepository,
 };
-use chrono::Utc;
+use crate::transaction;
+use chrono::{DateTime, FixedOffset, Utc};
 use sqlx::SqlitePool;
-use std::time::Duration;
-use turnkey_core::DeviceId;
-use turnkey_network::TcpClient;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use turnkey_core::constants::{MAX_CARD_LENGTH, MIN_CARD_LENGTH};
+use turnkey_core::{DeviceId, SharedClock, SystemClock};
+use turnkey_network::{CancellationToken, SharedTcpClient, TcpClient};
 use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
-use turnkey_protocol::{CommandCode, FieldData, Message, MessageBuilder};
+use turnkey_protocol::{CommandCode, FieldData, Message, MessageBuilder, MessageSigner};
 
 /// Trait for access validation implementations
 ///
@@ -105,7 +129,7 @@ pub trait AccessValidator {
 ///         OnlineValidatorConfig::default()
 ///     )))
 /// } else {
-///     Validator::Offline(OfflineValidator::new(pool))
+///     Validator::Offline(Box::new(OfflineValidator::new(pool)))
 /// };
 ///
 /// // Use the same interface regardless of validator type
@@ -125,7 +149,7 @@ pub enum Validator {
     Online(Box<OnlineValidator>),
 
     /// Offline validator using local database
-    Offline(OfflineValidator),
+    Offline(Box<OfflineValidator>),
 }
 
 impl Validator {
@@ -156,7 +180,7 @@ impl Validator {
     /// use turnkey_core::{AccessDirection, HenryTimestamp, ReaderType};
     ///
     /// # async fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
-    /// let mut validator = Validator::Offline(OfflineValidator::new(pool));
+    /// let mut validator = Validator::Offline(Box::new(OfflineValidator::new(pool)));
     ///
     /// let timestamp = HenryTimestamp::parse("10/05/2025 12:46:06")?;
     /// let request = AccessRequest::new(
@@ -179,24 +203,57 @@ impl Validator {
     }
 }
 
-/// Anti-passback window in seconds
-///
-/// Prevents security violations where users attempt to:
-/// - Enter twice without exiting (tailgating)
-/// - Exit twice without entering (reverse tailgating)
-///
-/// # How it works
-///
-/// When a user requests access, the system checks their last granted access:
-/// - If last was Entry and current is Entry → DENY (must exit first)
-/// - If last was Exit and current is Exit → DENY (must enter first)
-/// - If more than 300 seconds elapsed → ALLOW (window expired)
+/// Matches the finger on the scanner against one enrolled template
 ///
-/// # Configuration
+/// Implemented over the device's biometric scanner, which compares the live
+/// finger with a stored template (1:1). Used by
+/// [`OfflineValidator::verify_biometric`] for the second factor of card +
+/// biometric devices.
+#[allow(async_fn_in_trait)]
+pub trait TemplateMatcher {
+    /// Whether the presented finger matches `template`
+    async fn matches(&mut self, template: &[u8]) -> StorageResult<bool>;
+}
+
+/// Card accepted on a card + biometric device, waiting for the fingerprint
 ///
-/// Default: 300 seconds (5 minutes)
-/// Future: Should be configurable per-device or per-user
-const ANTI_PASSBACK_WINDOW_SECS: i64 = 300;
+/// Returned by [`OfflineValidator::validate_card`] with the card owner's
+/// enrolled templates, already loaded so the verification does not hit the
+/// database while the person waits at the reader.
+#[derive(Debug, Clone)]
+pub struct PendingBiometric {
+    card_number: String,
+    user_id: i64,
+    matricula: String,
+    templates: Vec<BiometricTemplate>,
+}
+
+impl PendingBiometric {
+    /// Normalized number of the accepted card
+    pub fn card_number(&self) -> &str {
+        &self.card_number
+    }
+
+    /// Matricula of the card owner
+    pub fn matricula(&self) -> &str {
+        &self.matricula
+    }
+
+    /// Templates the fingerprint is verified against, in finger order
+    pub fn templates(&self) -> &[BiometricTemplate] {
+        &self.templates
+    }
+}
+
+/// Outcome of the card step of a passage
+#[derive(Debug, Clone)]
+pub enum CardVerification {
+    /// Final decision: the card was refused, or no second factor is needed
+    Complete(AccessResponse),
+
+    /// Card accepted, the owner's fingerprint must be verified next
+    AwaitingBiometric(PendingBiometric),
+}
 
 /// Offline validator for access control requests
 ///
@@ -215,17 +272,90 @@ const ANTI_PASSBACK_WINDOW_SECS: i64 = 300;
 /// 5. **User Lookup**: Query `users` table by `card.matricula`
 /// 6. **User Active**: Deny if `user.ativo = false` → `USER_INACTIVE`
 /// 7. **User Validity**: Deny if outside validity period → `USER_EXPIRED`
-/// 8. **Access Method**: Deny if user lacks permission → `CARD_ACCESS_DENIED`
-/// 9. **Anti-Passback**: Deny if entry-after-entry or exit-after-exit → `ANTI_PASSBACK`
-/// 10. **Grant**: All checks passed → `ACCESS_GRANTED`
-/// 11. **Logging**: Record attempt (granted or denied) to `access_logs`
+/// 8. **Access Method**: Deny if the device has the reader disabled →
+///    `DEVICE_CARD_DISABLED`, or the user lacks permission → `CARD_ACCESS_DENIED`
+/// 9. **Schedule**: Deny on holidays if the site closes for them (see
+///    [`with_holiday_policy`]) → `HOLIDAY_CLOSED`, or if the user has a
+///    weekly schedule that does not cover the site local time (see
+///    [`with_schedule_offset`]) → `OUTSIDE_SCHEDULE`
+/// 10. **Daily Quota**: Deny entries once the user's passages today reach the
+///     user or company quota → `DAILY_QUOTA_EXCEEDED`
+/// 11. **Anti-Passback**: Deny if entry-after-entry or exit-after-exit within the
+///     window (see [`with_anti_passback_policy`]) → `ANTI_PASSBACK`
+/// 12. **Grant**: All checks passed → `ACCESS_GRANTED`
+/// 13. **Logging**: Record attempt (granted or denied) to `access_logs`
+///
+/// On card + biometric devices (see
+/// [`VerificationMode`](crate::models::VerificationMode)), only card reads
+/// are accepted at step 8 → `CARD_REQUIRED`, and a card that passes step 10
+/// waits for the owner's fingerprint before step 11 (see
+/// [`validate_card`]). The fingerprint step denies with its own messages:
+/// `BIOMETRIC_NOT_ENROLLED`, `BIOMETRIC_MISMATCH` or `BIOMETRIC_TIMEOUT`.
+/// Access exceptions still grant on the card alone, since event and visitor
+/// groups have no enrolled fingerprints.
+///
+/// Steps 2, 4, 7 and 9 are overridden by an open access exception (event
+/// or visitor window) covering the card or user on this device; the
+/// passage is then granted with `ACCESS_GRANTED_EXCEPTION`. The other
+/// checks still apply: inactive cards and users, the access methods of
+/// step 8, the daily quota and anti-passback deny as usual. An unregistered
+/// card has no user, so only the device's access methods apply to it.
+///
+/// In learning mode (see [`with_learning_mode`]), step 2 also records the
+/// unknown card in `pending_cards` and denies with `CARD_PENDING` so the
+/// operator can assign it to a user later.
+///
+/// While the device has an open enrollment session (see
+/// [`enrollment`](crate::enrollment)), card reads skip validation entirely:
+/// an unknown card is captured for the session's user and denied with
+/// `CARD_ENROLLED`, a registered one is denied with
+/// `CARD_ALREADY_REGISTERED` and the session stays open.
+///
+/// Keypad requests carry a typed code instead of a card number, which
+/// replaces steps 1-4. A code of the form `<matricula>*<PIN>` (see
+/// [`KEYPAD_MATRICULA_SEPARATOR`]) names the user, whose PIN is then
+/// checked in constant time. Any other code alone identifies the user
+/// (PIN-only access, e.g. for contractors without a card). An unknown
+/// matricula, a user without keypad access, a wrong PIN or a code matching
+/// no keypad user are all denied with `PIN_INVALID` and counted as wrong
+/// codes, so the keypad does not tell which matriculas exist. Wrong codes
+/// count against the device, and also against the user when the matricula
+/// names one.
+/// After too many (see [`with_keypad_lockout`]) the keypad is refused with
+/// `PIN_RETRY_IN` ("Tente novamente em X min") for a cooldown that grows
+/// with each further lockout. A user whose own PIN is locked is refused
+/// the same way on every device. A correct code clears both counts, and
+/// every new lockout is published as a [`PinLockoutEvent`] (see
+/// [`with_pin_lockout_events`]). The code itself is never logged.
+///
+/// With a coercion detector shared (see [`with_coercion_detector`]), card
+/// denials are counted per card; a card denied too often in a short time
+/// raises an alarm and, if configured, is denied with `CARD_LOCKED` before
+/// step 1 until its lockout ends.
+///
+/// With a staleness threshold set (see [`with_stale_data_threshold`]), every
+/// request validated while the last successful sync is older than the
+/// threshold logs a warning and raises a [`StaleDataWarning`]. Devices
+/// flagged with `deny_when_stale` then deny before step 1 → `DATA_STALE`;
+/// other devices carry on with the data they have.
 ///
 /// # Security Features
 ///
-/// - **Anti-Passback**: Prevents tailgating and double-entry (5-minute window)
+/// - **Anti-Passback**: Prevents tailgating and double-entry (5-minute window).
+///   The final check is repeated atomically with the grant log insert, so
+///   validators for different devices sharing one database cannot both grant
+///   the same user
 /// - **Temporal Validation**: Cards and users have independent validity periods
-/// - **Method Permissions**: Users can restrict access to card/bio/keypad
+/// - **Access Exceptions**: Event/visitor groups can pass during a time window
+/// - **Method Permissions**: Users and devices can restrict access to card/bio/keypad
+/// - **Daily Quotas**: Per-user or per-company caps on passages per day, reset
+///   at a configurable day boundary (see [`with_quota_day`]). Devices with
+///   `show_balance` set append the entries left today to grant messages
 /// - **Audit Trail**: All access attempts logged with timestamp and reason
+/// - **Clock Reconciliation**: The device-reported timestamp is stored next to
+///   the server receive time, with optional alerts when they drift apart
+/// - **Stale Data**: Optional warnings when the local data has not been synced
+///   for too long, and deny-by-default on selected high-security devices
 ///
 /// # Examples
 ///
@@ -254,10 +384,51 @@ const ANTI_PASSBACK_WINDOW_SECS: i64 = 300;
 /// # Ok(())
 /// # }
 /// ```
+///
+/// [`with_learning_mode`]: Self::with_learning_mode
+/// [`validate_card`]: Self::validate_card
+/// [`with_stale_data_threshold`]: Self::with_stale_data_threshold
+/// [`with_quota_day`]: Self::with_quota_day
+/// [`with_keypad_lockout`]: Self::with_keypad_lockout
+/// [`with_anti_passback_policy`]: Self::with_anti_passback_policy
+/// [`with_schedule_offset`]: Self::with_schedule_offset
+/// [`with_holiday_policy`]: Self::with_holiday_policy
+/// [`with_pin_lockout_events`]: Self::with_pin_lockout_events
+/// [`with_coercion_detector`]: Self::with_coercion_detector
 pub struct OfflineValidator {
     user_repo: SqliteUserRepository,
     card_repo: SqliteCardRepository,
     log_repo: SqliteAccessLogRepository,
+    exception_repo: SqliteAccessExceptionRepository,
+    pending_repo: SqlitePendingCardRepository,
+    device_repo: SqliteDeviceRepository,
+    enrollment_repo: SqliteEnrollmentSessionRepository,
+    sync_repo: SqliteSyncStateRepository,
+    quota_repo: SqliteQuotaRepository,
+    schedule_repo: SqliteScheduleRepository,
+    holiday_repo: SqliteHolidayRepository,
+    template_repo: SqliteBiometricTemplateRepository,
+    lockout_repo: SqliteKeypadLockoutRepository,
+    anti_passback_repo: SqliteAntiPassbackRepository,
+    pool: SqlitePool,
+    device_id: Option<DeviceId>,
+    drift_alerts: Option<(chrono::Duration, mpsc::Sender<ClockDriftAlert>)>,
+    enrollment_events: Option<mpsc::Sender<PendingCard>>,
+    stale_threshold: Option<chrono::Duration>,
+    stale_warnings: Option<mpsc::Sender<StaleDataWarning>>,
+    quota_day: QuotaDay,
+    schedule_offset: FixedOffset,
+    holiday_policy: HolidayPolicy,
+    keypad_lockout: KeypadLockoutPolicy,
+    anti_passback: AntiPassbackPolicy,
+    pin_lockout_events: Option<EventBus<PinLockoutEvent>>,
+    cluster: Option<Arc<ClusterNode>>,
+    outbox: Option<Outbox>,
+    card_filter: Option<Arc<CardFilter>>,
+    coercion: Option<Arc<CoercionDetector>>,
+    learning_mode: bool,
+    log_time_nanos: AtomicU64,
+    clock: SharedClock,
 }
 
 impl std::fmt::Debug for OfflineValidator {
@@ -272,54 +443,501 @@ impl OfflineValidator {
         Self {
             user_repo: SqliteUserRepository::new(pool.clone()),
             card_repo: SqliteCardRepository::new(pool.clone()),
-            log_repo: SqliteAccessLogRepository::new(pool),
+            log_repo: SqliteAccessLogRepository::new(pool.clone()),
+            exception_repo: SqliteAccessExceptionRepository::new(pool.clone()),
+            pending_repo: SqlitePendingCardRepository::new(pool.clone()),
+            device_repo: SqliteDeviceRepository::new(pool.clone()),
+            enrollment_repo: SqliteEnrollmentSessionRepository::new(pool.clone()),
+            sync_repo: SqliteSyncStateRepository::new(pool.clone()),
+            quota_repo: SqliteQuotaRepository::new(pool.clone()),
+            schedule_repo: SqliteScheduleRepository::new(pool.clone()),
+            holiday_repo: SqliteHolidayRepository::new(pool.clone()),
+            template_repo: SqliteBiometricTemplateRepository::new(pool.clone()),
+            lockout_repo: SqliteKeypadLockoutRepository::new(pool.clone()),
+            anti_passback_repo: SqliteAntiPassbackRepository::new(pool.clone()),
+            pool,
+            device_id: None,
+            drift_alerts: None,
+            enrollment_events: None,
+            stale_threshold: None,
+            stale_warnings: None,
+            quota_day: QuotaDay::default(),
+            schedule_offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+            holiday_policy: HolidayPolicy::default(),
+            keypad_lockout: KeypadLockoutPolicy::default(),
+            anti_passback: AntiPassbackPolicy::default(),
+            pin_lockout_events: None,
+            cluster: None,
+            outbox: None,
+            card_filter: None,
+            coercion: None,
+            learning_mode: false,
+            log_time_nanos: AtomicU64::new(0),
+            clock: SystemClock::shared(),
         }
     }
 
-    /// Validate an access request against the local database
+    /// Time spent writing access logs since the previous call
     ///
-    /// Executes the complete 9-step offline validation flow and returns
-    /// an access response (grant or deny).
+    /// Validation includes logging the decision; latency tracking calls this
+    /// after each validation to tell the two stages apart.
+    pub fn take_log_time(&self) -> Duration {
+        Duration::from_nanos(self.log_time_nanos.swap(0, Ordering::Relaxed))
+    }
+
+    /// Tag access logs with the device this validator serves
     ///
-    /// This is the internal implementation. The public API is available
-    /// through the [`AccessValidator`] trait implementation.
+    /// Required for per-device clock drift reports and alerts, and for the
+    /// device's access method settings to be enforced. Logs are also
+    /// numbered with the device's NSR (see
+    /// [`Outbox::number_access`](crate::outbox::Outbox::number_access)).
+    pub fn with_device_id(mut self, device_id: DeviceId) -> Self {
+        self.device_id = Some(device_id);
+        self
+    }
+
+    /// Send a [`ClockDriftAlert`] when the request timestamp drifts past `threshold`
     ///
-    /// # Arguments
+    /// Alerts are only raised when a device ID is set via [`with_device_id`].
+    /// Delivery is best-effort: if the channel is full or closed the alert is
+    /// dropped so validation is never blocked by a slow consumer.
     ///
-    /// * `request` - The access request to validate
+    /// # Examples
     ///
-    /// # Returns
+    /// ```no_run
+    /// use turnkey_storage::OfflineValidator;
+    /// use turnkey_storage::models::DEFAULT_CLOCK_DRIFT_THRESHOLD_SECS;
+    /// use turnkey_core::DeviceId;
+    /// use tokio::sync::mpsc;
     ///
-    /// Returns `Ok(AccessResponse)` with grant or deny decision.
+    /// # fn example(pool: sqlx::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    /// let (alert_tx, mut alert_rx) = mpsc::channel(16);
+    ///
+    /// let validator = OfflineValidator::new(pool)
+    ///     .with_device_id(DeviceId::new(15)?)
+    ///     .with_clock_drift_alerts(
+    ///         chrono::Duration::seconds(DEFAULT_CLOCK_DRIFT_THRESHOLD_SECS),
+    ///         alert_tx,
+    ///     );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`with_device_id`]: Self::with_device_id
+    pub fn with_clock_drift_alerts(
+        mut self,
+        threshold: chrono::Duration,
+        alerts: mpsc::Sender<ClockDriftAlert>,
+    ) -> Self {
+        self.drift_alerts = Some((threshold, alerts));
+        self
+    }
+
+    /// Record unknown card reads in `pending_cards` for later assignment
+    ///
+    /// Meant for commissioning a new reader: the operator taps badges, then
+    /// assigns each pending card to a user through
+    /// [`PendingCardRepository::assign`]. Unknown cards are still denied.
+    pub fn with_learning_mode(mut self, enabled: bool) -> Self {
+        self.learning_mode = enabled;
+        self
+    }
+
+    /// Send every card captured by an enrollment session to `events`
+    ///
+    /// Lets the caller confirm the capture to the server with
+    /// [`enrollment::confirmation`](crate::enrollment::confirmation).
+    /// Delivery is best-effort, as for clock drift alerts.
+    pub fn with_enrollment_events(mut self, events: mpsc::Sender<PendingCard>) -> Self {
+        self.enrollment_events = Some(events);
+        self
+    }
+
+    /// Treat the local data as stale once the last sync is older than `threshold`
+    ///
+    /// Stale decisions are logged as warnings, and devices flagged with
+    /// `deny_when_stale` (see [`DeviceRepository::set_deny_when_stale`]) deny
+    /// every request with `DATA_STALE` until the next successful sync. A
+    /// database that was never synced is stale.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use turnkey_storage::OfflineValidator;
+    /// use turnkey_storage::models::DEFAULT_STALE_DATA_THRESHOLD_SECS;
+    /// use tokio::sync::mpsc;
+    ///
+    /// # fn example(pool: sqlx::SqlitePool) {
+    /// let (warning_tx, mut warning_rx) = mpsc::channel(16);
+    ///
+    /// let validator = OfflineValidator::new(pool)
+    ///     .with_stale_data_threshold(chrono::Duration::seconds(
+    ///         DEFAULT_STALE_DATA_THRESHOLD_SECS,
+    ///     ))
+    ///     .with_stale_data_warnings(warning_tx);
+    /// # }
+    /// ```
+    pub fn with_stale_data_threshold(mut self, threshold: chrono::Duration) -> Self {
+        self.stale_threshold = Some(threshold);
+        self
+    }
+
+    /// Send a [`StaleDataWarning`] for every decision taken on stale data
+    ///
+    /// Only raised once a threshold is set via [`with_stale_data_threshold`].
+    /// Delivery is best-effort, as for clock drift alerts.
+    ///
+    /// [`with_stale_data_threshold`]: Self::with_stale_data_threshold
+    pub fn with_stale_data_warnings(mut self, warnings: mpsc::Sender<StaleDataWarning>) -> Self {
+        self.stale_warnings = Some(warnings);
+        self
+    }
+
+    /// Set the day boundary at which daily quotas reset (default midnight UTC)
+    ///
+    /// Quotas themselves are managed through
+    /// [`QuotaRepository`](crate::repositories::QuotaRepository); users
+    /// without one are never limited.
+    pub fn with_quota_day(mut self, day: QuotaDay) -> Self {
+        self.quota_day = day;
+        self
+    }
+
+    /// Set the UTC offset of the site local time schedules are written in
+    ///
+    /// Defaults to UTC. Holiday dates are read as site local dates too.
+    ///
+    /// Schedules are assigned to users through
+    /// [`ScheduleRepository::assign_user`]; users without one are never
+    /// restricted.
+    pub fn with_schedule_offset(mut self, offset: FixedOffset) -> Self {
+        self.schedule_offset = offset;
+        self
+    }
+
+    /// Set whether the site closes on holidays (default: open)
+    ///
+    /// Holidays and the users exempt from closures are managed through
+    /// [`HolidayRepository`]. A user whose schedule has an exception for
+    /// the holiday follows the exception instead of being turned away.
+    pub fn with_holiday_policy(mut self, policy: HolidayPolicy) -> Self {
+        self.holiday_policy = policy;
+        self
+    }
+
+    /// Set how many wrong keypad codes lock a device's keypad, and for how long
+    ///
+    /// Defaults to 5 wrong codes and a 5-minute cooldown, doubling with
+    /// each further lockout up to an hour. The same policy applies to the
+    /// PIN of each user. Device lockouts require a device ID set via
+    /// [`with_device_id`](Self::with_device_id).
+    pub fn with_keypad_lockout(mut self, policy: KeypadLockoutPolicy) -> Self {
+        self.keypad_lockout = policy;
+        self
+    }
+
+    /// Set how long a repeated direction is denied on this device
+    ///
+    /// Defaults to 5 minutes. Users with a window of their own (see
+    /// [`AntiPassbackRepository`]) get that window instead, unless the
+    /// policy ignores user overrides.
+    pub fn with_anti_passback_policy(mut self, policy: AntiPassbackPolicy) -> Self {
+        self.anti_passback = policy;
+        self
+    }
+
+    /// Publish a [`PinLockoutEvent`] whenever a keypad or a user's PIN gets locked
+    pub fn with_pin_lockout_events(mut self, bus: EventBus<PinLockoutEvent>) -> Self {
+        self.pin_lockout_events = Some(bus);
+        self
+    }
+
+    /// Key of the keypad code digests
+    ///
+    /// Needed to identify keypad users by code alone, and to accept codes
+    /// that have no digest yet. Without it, validating those entries fails
+    /// with `Configuration`. See [`PinDigestKey`].
+    pub fn with_pin_digest_key(mut self, key: PinDigestKey) -> Self {
+        self.user_repo = self.user_repo.with_digest_key(key);
+        self
+    }
+
+    /// Share decisions with the other servers of a cluster
+    ///
+    /// Requests carrying a correlation ID are decided once across the
+    /// cluster: a retry, on this node or another, gets the recorded answer
+    /// instead of a second validation and access log entry. Requires a
+    /// device ID set via [`with_device_id`](Self::with_device_id). See
+    /// [`cluster`](crate::cluster) for the details.
+    pub fn with_cluster(mut self, node: ClusterNode) -> Self {
+        self.cluster = Some(Arc::new(node));
+        self
+    }
+
+    /// Queue a notification to the server with every access log
+    ///
+    /// Each log and its `ER` event commit in one transaction, and an
+    /// [`OutboxDispatcher`](crate::outbox::OutboxDispatcher) sends them, so
+    /// a crash between the two cannot lose an access. Requires a device ID
+    /// set via [`with_device_id`](Self::with_device_id); without one nothing
+    /// is queued. See [`outbox`](crate::outbox) for the delivery guarantees.
+    pub fn with_outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Read the current time from `clock` instead of the system clock
+    ///
+    /// Every time-dependent check uses it: validity periods, exceptions,
+    /// the anti-passback window, quotas, keypad lockouts, enrollment
+    /// sessions, data freshness, and the timestamp of the access log.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Skip the card query for cards the filter knows are not registered
+    ///
+    /// The filter is shared with whatever keeps it up to date. It reads
+    /// the change outbox before ruling a card out, so cards imported since
+    /// its last update are still found; see
+    /// [`card_filter`](crate::card_filter#keeping-up-with-changes).
+    pub fn with_card_filter(mut self, filter: Arc<CardFilter>) -> Self {
+        self.card_filter = Some(filter);
+        self
+    }
+
+    /// Count card denials for repeated-denial alarms and refuse locked cards
+    ///
+    /// Every card denial is passed to `detector`, which publishes the
+    /// alarms it raises. While the detector holds a lockout for a card, the
+    /// card is denied with `CARD_LOCKED` without being looked up. See
+    /// [`coercion`](crate::coercion).
+    pub fn with_coercion_detector(mut self, detector: Arc<CoercionDetector>) -> Self {
+        self.coercion = Some(detector);
+        self
+    }
+
+    /// Validate a request on behalf of a requester that may go away
+    ///
+    /// Runs [`validate()`](AccessValidator::validate) until `cancel` fires,
+    /// typically the [`TcpServer::cancellation_token()`] of the device's
+    /// connection. Once cancelled, pending lookups are abandoned and nothing
+    /// more is written: no access log, pending card or keypad failure. A log
+    /// write that already started is completed, so a passage is either
+    /// recorded with its decision or not at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Cancelled` if the token fired before the decision was
+    /// recorded; otherwise as [`validate()`](AccessValidator::validate).
+    ///
+    /// [`TcpServer::cancellation_token()`]: turnkey_network::TcpServer::cancellation_token
+    pub async fn validate_cancellable(
+        &mut self,
+        request: &AccessRequest,
+        cancel: CancellationToken,
+    ) -> StorageResult<AccessResponse> {
+        if cancel.is_cancelled() {
+            return Err(StorageError::Cancelled);
+        }
+        CANCEL.scope(cancel, self.validate(request)).await
+    }
+
+    /// Validate the card of a passage that may need a fingerprint as well
+    ///
+    /// Runs the same checks as [`validate()`](AccessValidator::validate).
+    /// On a card + biometric device (see
+    /// [`VerificationMode`](crate::models::VerificationMode)), a card that
+    /// passes them does not grant yet: the result carries the owner's
+    /// enrolled templates, and the caller finishes the passage with
+    /// [`verify_biometric()`](Self::verify_biometric) or
+    /// [`biometric_timeout()`](Self::biometric_timeout). Everywhere else
+    /// the decision is final.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if database operations fail.
+    pub async fn validate_card(&self, request: &AccessRequest) -> StorageResult<CardVerification> {
+        self.check_request(request)
+            .instrument(validation_span("offline", request))
+            .await
+    }
+
+    /// Verify the card owner's fingerprint after [`validate_card()`](Self::validate_card)
+    ///
+    /// `request` is the card request that produced `pending`. The owner's
+    /// templates are tried in finger order; the first match grants the
+    /// passage, still subject to anti-passback. No match denies with
+    /// `BIOMETRIC_MISMATCH`, so the display tells a wrong finger apart
+    /// from a refused card.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if database operations fail or the matcher fails.
+    pub async fn verify_biometric<M: TemplateMatcher>(
+        &self,
+        request: &AccessRequest,
+        pending: &PendingBiometric,
+        matcher: &mut M,
+    ) -> StorageResult<AccessResponse> {
+        async {
+            for template in &pending.templates {
+                if matcher.matches(&template.template_data).await? {
+                    return self
+                        .grant_passage(
+                            pending.user_id,
+                            &pending.matricula,
+                            &pending.card_number,
+                            request,
+                            false,
+                        )
+                        .await;
+                }
+            }
+
+            self.deny_with_log(
+                Some(pending.user_id),
+                Some(&pending.matricula),
+                &pending.card_number,
+                request,
+                DisplayMessages::BIOMETRIC_MISMATCH,
+            )
+            .await
+        }
+        .instrument(validation_span("offline", request))
+        .await
+    }
+
+    /// Deny a passage whose fingerprint was not presented in time
+    ///
+    /// Call when the turnstile leaves `AwaitingBiometric` on timeout, so the
+    /// abandoned attempt is logged with `BIOMETRIC_TIMEOUT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error only if database logging fails.
+    pub async fn biometric_timeout(
+        &self,
+        request: &AccessRequest,
+        pending: &PendingBiometric,
+    ) -> StorageResult<AccessResponse> {
+        self.deny_with_log(
+            Some(pending.user_id),
+            Some(&pending.matricula),
+            &pending.card_number,
+            request,
+            DisplayMessages::BIOMETRIC_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Validate an access request against the local database
+    ///
+    /// Single-step form of [`check_request`](Self::check_request): a card
+    /// that still needs a fingerprint is denied with `BIOMETRIC_REQUIRED`,
+    /// since the second factor cannot be collected here.
+    ///
+    /// This is the internal implementation. The public API is available
+    /// through the [`AccessValidator`] trait implementation.
     ///
     /// # Errors
     ///
     /// Returns error if database operations fail. Note that validation
     /// failures (e.g., card not found) return `Ok(deny_response)`, not errors.
     async fn validate_internal(&self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        match self.check_request(request).await? {
+            CardVerification::Complete(response) => Ok(response),
+            CardVerification::AwaitingBiometric(pending) => {
+                self.deny_with_log(
+                    Some(pending.user_id),
+                    Some(&pending.matricula),
+                    &pending.card_number,
+                    request,
+                    DisplayMessages::BIOMETRIC_REQUIRED,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Run the offline validation flow up to the decision
+    ///
+    /// Executes the validation steps described on [`OfflineValidator`] and
+    /// returns the decision, or the pending second factor on card +
+    /// biometric devices.
+    async fn check_request(&self, request: &AccessRequest) -> StorageResult<CardVerification> {
+        if request.reader_type().is_keypad() {
+            return self.check_keypad(request).await;
+        }
+
         let card_number = Card::normalize_card_number(request.card_number());
 
+        // Enrollment mode: the read registers a card instead of opening the gate
+        if let Some(response) = self.capture_enrollment(&card_number, request).await? {
+            return Ok(CardVerification::Complete(response));
+        }
+
+        // Stale data: warn, and deny outright on high-security devices
+        if let Some(response) = self.check_data_freshness(&card_number, request).await? {
+            return Ok(CardVerification::Complete(response));
+        }
+
+        // Repeated denials: the card is refused until its lockout ends
+        if let Some(detector) = &self.coercion
+            && detector
+                .locked_until(&card_number, self.clock.now())
+                .is_some()
+        {
+            return self
+                .deny_with_log(
+                    None,
+                    None,
+                    &card_number,
+                    request,
+                    DisplayMessages::CARD_LOCKED,
+                )
+                .await
+                .map(CardVerification::Complete);
+        }
+
         // Step 1: Lookup card by number
-        let card = self.card_repo.find_by_number(&card_number).await?;
+        let card = self.find_card(&card_number).await?;
 
         // Step 2: Check if card exists
+        // Unregistered cards may still belong to an event/visitor group
         let card = match card {
             Some(c) => c,
             None => {
+                if let Some(response) = self
+                    .grant_unregistered_by_exception(&card_number, request)
+                    .await?
+                {
+                    return Ok(CardVerification::Complete(response));
+                }
+
+                let message = if self.record_pending_card(request).await? {
+                    DisplayMessages::CARD_PENDING
+                } else {
+                    DisplayMessages::CARD_NOT_FOUND
+                };
+
                 return self
-                    .deny_with_log(
-                        None,
-                        None,
-                        &card_number,
-                        request,
-                        DisplayMessages::CARD_NOT_FOUND,
-                    )
-                    .await;
+                    .deny_with_log(None, None, &card_number, request, message)
+                    .await
+                    .map(CardVerification::Complete);
             }
         };
 
         // Step 3: Check if card is active and valid
-        if !card.is_valid() {
+        // Inactive (blocked) cards are never overridden by exceptions
+        let card_valid = card.is_valid_at(self.clock.now());
+        let excepted = !card_valid
+            && card.ativo
+            && self
+                .has_exception(Some(&card.matricula), &card_number)
+                .await?;
+        if !card_valid && !excepted {
             let message = if !card.ativo {
                 DisplayMessages::CARD_INACTIVE
             } else {
@@ -334,11 +952,12 @@ impl OfflineValidator {
                     request,
                     message,
                 )
-                .await;
+                .await
+                .map(CardVerification::Complete);
         }
 
         // Step 4: Lookup user by matricula
-        let user = self.user_repo.find_by_matricula(&card.matricula).await?;
+        let user = interruptible(self.user_repo.find_by_matricula(&card.matricula)).await?;
 
         let user = match user {
             Some(u) => u,
@@ -351,952 +970,3296 @@ impl OfflineValidator {
                         request,
                         DisplayMessages::USER_NOT_FOUND,
                     )
-                    .await;
+                    .await
+                    .map(CardVerification::Complete);
             }
         };
 
-        // Step 5: Check if user is active and valid
-        if !user.is_valid() {
-            let message = if !user.ativo {
-                DisplayMessages::USER_INACTIVE
-            } else {
-                DisplayMessages::USER_EXPIRED
-            };
+        self.check_user(user, card_number, request, excepted).await
+    }
 
-            return self
-                .deny_with_log(
-                    Some(user.id),
-                    Some(&user.matricula),
-                    &card_number,
-                    request,
-                    message,
-                )
-                .await;
+    /// Keypad entry: PIN alone, or matricula and PIN
+    ///
+    /// Takes the place of steps 1-4 of the card flow. Wrong codes count
+    /// against the device's keypad lockout, and against the user when the
+    /// matricula names one; while the device, or the user the code belongs
+    /// to, is locked, codes are refused without being checked.
+    async fn check_keypad(&self, request: &AccessRequest) -> StorageResult<CardVerification> {
+        let card_number = KEYPAD_CARD_NUMBER.to_string();
+
+        if let Some(response) = self.check_data_freshness(&card_number, request).await? {
+            return Ok(CardVerification::Complete(response));
         }
 
-        // Step 6: Check access method permission
-        // For RFID readers, check allow_card permission
-        if request.is_rfid() && !user.allow_card {
+        let now = self.clock.now();
+        if let Some(device_id) = self.device_id
+            && let Some(lockout) = interruptible(self.lockout_repo.find(device_id)).await?
+            && let Some(remaining) = lockout.remaining(now)
+        {
             return self
                 .deny_with_log(
-                    Some(user.id),
-                    Some(&user.matricula),
+                    None,
+                    None,
                     &card_number,
                     request,
-                    DisplayMessages::CARD_ACCESS_DENIED,
+                    &DisplayMessages::retry_in(remaining),
                 )
-                .await;
+                .await
+                .map(CardVerification::Complete);
         }
 
-        // For biometric readers, check allow_bio permission
-        if request.is_biometric() && !user.allow_bio {
+        // With a matricula the code is checked against that user only;
+        // without one the code alone has to identify a keypad user. Either
+        // way the code is hashed once, known user or not, so the time taken
+        // does not tell a real matricula from an unknown one.
+        let entry = request.card_number();
+        let (user, verified) = match entry.split_once(KEYPAD_MATRICULA_SEPARATOR) {
+            Some((matricula, code)) => {
+                // Users without keypad access fail here like a wrong PIN
+                let verified = interruptible(self.user_repo.verify_code(matricula, code)).await?;
+                let user = interruptible(self.user_repo.find_by_matricula(matricula)).await?;
+                (user, verified)
+            }
+            None => {
+                let user = interruptible(self.user_repo.find_by_code(entry)).await?;
+                let verified = user.is_some();
+                (user, verified)
+            }
+        };
+        let user = match user {
+            Some(user) if verified => user,
+            user => {
+                ensure_active()?;
+                let user_id = user.as_ref().map(|user| user.id);
+                let message = self.record_pin_failure(user_id, now).await?;
+                return self
+                    .deny_with_log(
+                        user_id,
+                        user.as_ref().map(|user| user.matricula.as_str()),
+                        &card_number,
+                        request,
+                        &message,
+                    )
+                    .await
+                    .map(CardVerification::Complete);
+            }
+        };
+
+        // A locked PIN is reported only to whoever knows it, so a wrong
+        // code answers the same for a locked user as for an unknown one
+        if let Some(lockout) = interruptible(self.lockout_repo.find_user(user.id)).await?
+            && let Some(remaining) = lockout.remaining(now)
+        {
             return self
                 .deny_with_log(
                     Some(user.id),
                     Some(&user.matricula),
                     &card_number,
                     request,
-                    DisplayMessages::BIO_ACCESS_DENIED,
+                    &DisplayMessages::retry_in(remaining),
                 )
-                .await;
+                .await
+                .map(CardVerification::Complete);
         }
 
-        // Step 7: Anti-passback validation
-        // Check if user's last access direction conflicts with current request
-        let last_access = self
-            .log_repo
-            .find_by_user_id(user.id, 1)
-            .await?
-            .first()
-            .cloned();
+        ensure_active()?;
+        self.reset_pin_failures(Some(user.id)).await?;
 
-        if let Some(last_log) = last_access {
-            // Only check granted accesses for anti-passback
-            if last_log.granted {
-                let is_entry_after_entry =
-                    last_log.direction == Direction::Entry as i32 && request.is_entry();
+        self.check_user(user, card_number, request, false).await
+    }
 
-                let is_exit_after_exit =
-                    last_log.direction == Direction::Exit as i32 && request.is_exit();
+    /// Count a wrong keypad code against the device and, if known, the user
+    ///
+    /// Publishes an event for each lockout this code starts. Returns the
+    /// message to deny with: `PIN_INVALID`, or the time left if the code
+    /// locked the keypad. A locked user PIN is not mentioned, since that
+    /// would confirm the matricula exists.
+    async fn record_pin_failure(
+        &self,
+        user_id: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> StorageResult<String> {
+        let policy = &self.keypad_lockout;
+        // The wrong code at exactly the limit is the one that locked;
+        // codes typed during a lockout keep counting past it
+        let locking_attempt = i64::from(policy.max_attempts.max(1));
+        let mut locks = Vec::new();
+
+        if let Some(device_id) = self.device_id {
+            let lockout = self
+                .lockout_repo
+                .record_failure(device_id, policy, now)
+                .await?;
+            if let Some(locked_until) = lockout.locked_until.filter(|_| lockout.is_locked(now)) {
+                let subject = PinLockoutSubject::Device {
+                    device_id: lockout.device_id,
+                };
+                locks.push((
+                    subject,
+                    lockout.failed_attempts,
+                    lockout.lockouts,
+                    locked_until,
+                ));
+            }
+        }
+        if let Some(user_id) = user_id {
+            let lockout = self
+                .lockout_repo
+                .record_user_failure(user_id, policy, now)
+                .await?;
+            if let Some(locked_until) = lockout.locked_until.filter(|_| lockout.is_locked(now)) {
+                let subject = PinLockoutSubject::User { user_id };
+                locks.push((
+                    subject,
+                    lockout.failed_attempts,
+                    lockout.lockouts,
+                    locked_until,
+                ));
+            }
+        }
 
-                // Check if within anti-passback window
-                if (is_entry_after_entry || is_exit_after_exit)
-                    && !self.is_anti_passback_expired(&last_log)
-                {
-                    return self
-                        .deny_with_log(
-                            Some(user.id),
-                            Some(&user.matricula),
-                            &card_number,
-                            request,
-                            DisplayMessages::ANTI_PASSBACK,
-                        )
-                        .await;
-                }
+        for &(subject, failed_attempts, lockouts, locked_until) in &locks {
+            if failed_attempts != locking_attempt {
+                continue;
+            }
+            let event = PinLockoutEvent {
+                subject,
+                device_id: self.device_id.map(|id| i64::from(id.as_u8())),
+                failed_attempts,
+                lockouts,
+                locked_at: now,
+                locked_until,
+            };
+            tracing::warn!(
+                subject = ?event.subject,
+                lockouts = event.lockouts,
+                locked_until = %event.locked_until,
+                "Keypad locked after repeated wrong codes"
+            );
+            if let Some(bus) = &self.pin_lockout_events {
+                bus.publish(event).await;
             }
         }
 
-        // Step 8: All validations passed - grant access
-        // Step 9: Log the successful access
-        self.log_access_granted(
-            user.id,
-            &user.matricula,
-            &card_number,
-            request,
-            DisplayMessages::ACCESS_GRANTED,
+        Ok(
+            match locks
+                .iter()
+                .filter(|(subject, ..)| matches!(subject, PinLockoutSubject::Device { .. }))
+                .map(|&(.., locked_until)| locked_until)
+                .max()
+            {
+                Some(locked_until) => DisplayMessages::retry_in(locked_until - now),
+                None => DisplayMessages::PIN_INVALID.to_string(),
+            },
         )
-        .await?;
+    }
 
-        // Step 9: Return grant response based on direction
-        let response = if request.is_entry() {
-            AccessResponse::grant_entry(DisplayMessages::ACCESS_GRANTED.to_string())
-        } else if request.is_exit() {
-            AccessResponse::grant_exit(DisplayMessages::ACCESS_GRANTED.to_string())
-        } else {
-            // Undefined direction - grant both
-            AccessResponse::grant_both(DisplayMessages::ACCESS_GRANTED.to_string())
-        };
-
-        Ok(response)
+    /// Clear the wrong codes of the device and, if known, the user
+    async fn reset_pin_failures(&self, user_id: Option<i64>) -> StorageResult<()> {
+        if let Some(device_id) = self.device_id {
+            self.lockout_repo.reset(device_id).await?;
+        }
+        if let Some(user_id) = user_id {
+            self.lockout_repo.reset_user(user_id).await?;
+        }
+        Ok(())
     }
 
-    /// Log a granted access attempt
-    async fn log_access_granted(
+    /// Steps 5 onwards: checks on the identified user, up to the decision
+    ///
+    /// `excepted` is set when an access exception already excused the card
+    /// from its validity period; it also excuses the user's validity and
+    /// schedule, but no other check.
+    async fn check_user(
         &self,
-        user_id: i64,
-        matricula: &str,
-        card_number: &str,
+        user: User,
+        card_number: String,
         request: &AccessRequest,
-        message: &str,
-    ) -> StorageResult<()> {
-        let direction = self.map_direction(request.direction());
-        let reader_type = self.map_reader_type(request.reader_type());
+        mut excepted: bool,
+    ) -> StorageResult<CardVerification> {
+        // Step 5: Check if user is active and valid
+        // Inactive users are never overridden by exceptions
+        let user_valid = user.is_valid_at(self.clock.now());
+        if !user_valid && user.ativo && !excepted {
+            excepted = self
+                .has_exception(Some(&user.matricula), &card_number)
+                .await?;
+        }
+        if !(user_valid || user.ativo && excepted) {
+            let message = if !user.ativo {
+                DisplayMessages::USER_INACTIVE
+            } else {
+                DisplayMessages::USER_EXPIRED
+            };
 
-        let log = AccessLog::new(
-            Some(user_id),
-            Some(matricula.to_string()),
-            card_number.to_string(),
-            direction,
-            reader_type,
-            true, // granted
-            Some(message.to_string()),
-            Utc::now(),
-        );
+            return self
+                .deny_with_log(
+                    Some(user.id),
+                    Some(&user.matricula),
+                    &card_number,
+                    request,
+                    message,
+                )
+                .await
+                .map(CardVerification::Complete);
+        }
 
-        self.log_repo.create(&log).await?;
-        Ok(())
-    }
+        // Step 6: Check access method permission (device, then user)
+        let method = AccessMethod::from_reader_type(request.reader_type());
+        let device = match self.device_id {
+            Some(id) => interruptible(self.device_repo.find_by_id(i64::from(id.as_u8()))).await?,
+            None => None,
+        };
 
-    /// Log a denied access attempt
-    async fn log_access_denied(
-        &self,
-        user_id: Option<i64>,
-        matricula: Option<&str>,
-        card_number: &str,
-        request: &AccessRequest,
-        message: &str,
-    ) -> StorageResult<()> {
-        let direction = self.map_direction(request.direction());
-        let reader_type = self.map_reader_type(request.reader_type());
+        if let Some(message) = method.denial(device.as_ref(), &user) {
+            return self
+                .deny_with_log(
+                    Some(user.id),
+                    Some(&user.matricula),
+                    &card_number,
+                    request,
+                    message,
+                )
+                .await
+                .map(CardVerification::Complete);
+        }
 
-        let log = AccessLog::new(
-            user_id,
-            matricula.map(|s| s.to_string()),
-            card_number.to_string(),
-            direction,
-            reader_type,
-            false, // denied
-            Some(message.to_string()),
-            Utc::now(),
-        );
+        // Card + biometric devices only open for a card confirmed by a finger
+        let mode = device
+            .as_ref()
+            .map(|device| device.verification_mode)
+            .unwrap_or_default();
+        if mode.requires_biometric() && method != AccessMethod::Card {
+            return self
+                .deny_with_log(
+                    Some(user.id),
+                    Some(&user.matricula),
+                    &card_number,
+                    request,
+                    DisplayMessages::CARD_REQUIRED,
+                )
+                .await
+                .map(CardVerification::Complete);
+        }
 
-        self.log_repo.create(&log).await?;
-        Ok(())
+        // Holidays and weekly schedule, in site local time
+        if let Some(message) = interruptible(self.calendar_denial(user.id)).await? {
+            if !excepted {
+                excepted = self
+                    .has_exception(Some(&user.matricula), &card_number)
+                    .await?;
+            }
+            if !excepted {
+                return self
+                    .deny_with_log(
+                        Some(user.id),
+                        Some(&user.matricula),
+                        &card_number,
+                        request,
+                        message,
+                    )
+                    .await
+                    .map(CardVerification::Complete);
+            }
+        }
+
+        // Daily quota: only passages into the area use it up
+        if !request.is_exit()
+            && interruptible(self.quota_remaining(&user))
+                .await?
+                .is_some_and(|left| left <= 0)
+        {
+            return self
+                .deny_with_log(
+                    Some(user.id),
+                    Some(&user.matricula),
+                    &card_number,
+                    request,
+                    DisplayMessages::DAILY_QUOTA_EXCEEDED,
+                )
+                .await
+                .map(CardVerification::Complete);
+        }
+
+        // Card factor passed: the owner still has to confirm with a finger.
+        // Users without templates are turned away before they try one
+        if mode.requires_biometric() && !excepted {
+            let templates =
+                interruptible(self.template_repo.find_by_matricula(&user.matricula)).await?;
+            if templates.is_empty() {
+                return self
+                    .deny_with_log(
+                        Some(user.id),
+                        Some(&user.matricula),
+                        &card_number,
+                        request,
+                        DisplayMessages::BIOMETRIC_NOT_ENROLLED,
+                    )
+                    .await
+                    .map(CardVerification::Complete);
+            }
+
+            return Ok(CardVerification::AwaitingBiometric(PendingBiometric {
+                card_number,
+                user_id: user.id,
+                matricula: user.matricula,
+                templates,
+            }));
+        }
+
+        self.grant_passage(user.id, &user.matricula, &card_number, request, excepted)
+            .await
+            .map(CardVerification::Complete)
     }
 
-    /// Helper method to log denied access and return deny response
-    ///
-    /// This method encapsulates the common pattern of logging a denied access
-    /// attempt and returning the corresponding deny response. It ensures
-    /// consistent error handling throughout the validation flow.
-    ///
-    /// # Arguments
-    ///
-    /// * `user_id` - Optional user ID (None if user not found)
-    /// * `matricula` - Optional user matricula (None if user not found)
-    /// * `card_number` - Card number that was presented
-    /// * `request` - The access request being validated
-    /// * `message` - The deny reason message
-    ///
-    /// # Returns
-    ///
-    /// Returns `Ok(AccessResponse::deny)` with the provided message.
-    ///
-    /// # Errors
+    /// Look up a card, skipping the query when the card filter rules it out
+    async fn find_card(&self, card_number: &str) -> StorageResult<Option<Card>> {
+        let Some(filter) = &self.card_filter else {
+            return interruptible(self.card_repo.find_by_number(card_number)).await;
+        };
+        if interruptible(filter.rules_out(card_number)).await? {
+            return Ok(None);
+        }
+
+        let card = interruptible(self.card_repo.find_by_number(card_number)).await?;
+        if card.is_none() {
+            filter.record_false_positive();
+        }
+        Ok(card)
+    }
+
+    /// Grant a passage that passed every check but anti-passback
     ///
-    /// Returns error only if database logging fails.
-    async fn deny_with_log(
+    /// `by_exception` grants with `ACCESS_GRANTED_EXCEPTION`; anti-passback
+    /// applies all the same.
+    async fn grant_passage(
         &self,
-        user_id: Option<i64>,
-        matricula: Option<&str>,
+        user_id: i64,
+        matricula: &str,
         card_number: &str,
         request: &AccessRequest,
-        message: &str,
+        by_exception: bool,
     ) -> StorageResult<AccessResponse> {
-        self.log_access_denied(user_id, matricula, card_number, request, message)
-            .await?;
-        Ok(AccessResponse::deny(message.to_string()))
+        // Step 7: Anti-passback validation
+        // Step 8: All validations passed - grant access
+        // Step 9: Log the successful access
+        // The anti-passback check runs inside the insert of the grant log, so
+        // validators of other devices cannot grant the same user in between
+        let granted = if by_exception {
+            DisplayMessages::ACCESS_GRANTED_EXCEPTION
+        } else {
+            DisplayMessages::ACCESS_GRANTED
+        };
+        let log = self.new_log(
+            Some(user_id),
+            Some(matricula),
+            card_number,
+            request,
+            true,
+            granted,
+        );
+        let window_start = self
+            .anti_passback_window(user_id)
+            .await?
+            .map(|window| self.clock.now() - window);
+        if self
+            .timed_log(self.write_log(&log, window_start))
+            .await?
+            .is_none()
+        {
+            return self
+                .deny_with_log(
+                    Some(user_id),
+                    Some(matricula),
+                    card_number,
+                    request,
+                    DisplayMessages::ANTI_PASSBACK,
+                )
+                .await;
+        }
+
+        // Step 9: Return grant response based on direction
+        let message = if by_exception {
+            granted.to_string()
+        } else {
+            self.grant_message(user_id).await?
+        };
+        Ok(Self::grant_response(request, &message))
     }
 
-    /// Map turnkey_core::AccessDirection to storage Direction
-    fn map_direction(&self, dir: turnkey_core::AccessDirection) -> Direction {
-        match dir {
-            turnkey_core::AccessDirection::Undefined => Direction::Undefined,
-            turnkey_core::AccessDirection::Entry => Direction::Entry,
-            turnkey_core::AccessDirection::Exit => Direction::Exit,
+    /// Anti-passback window of a user on this device, `None` if disabled
+    async fn anti_passback_window(&self, user_id: i64) -> StorageResult<Option<chrono::Duration>> {
+        let user_window = if self.anti_passback.user_overrides {
+            interruptible(self.anti_passback_repo.find_user_window(user_id)).await?
+        } else {
+            None
+        };
+        Ok(self.anti_passback.window_for(user_window))
+    }
+
+    /// Grant message, with the remaining daily quota where the device shows it
+    ///
+    /// Runs after the grant is logged, so the balance already accounts for
+    /// this passage.
+    async fn grant_message(&self, user_id: i64) -> StorageResult<String> {
+        let show_balance = match self.device_id {
+            Some(id) => self
+                .device_repo
+                .find_by_id(i64::from(id.as_u8()))
+                .await?
+                .is_some_and(|device| device.show_balance),
+            None => false,
+        };
+        if !show_balance {
+            return Ok(DisplayMessages::ACCESS_GRANTED.to_string());
         }
+
+        let balance = match self.user_repo.find_by_id(user_id).await? {
+            Some(user) => self.quota_remaining(&user).await?,
+            None => None,
+        };
+        Ok(match balance {
+            Some(left) => DisplayMessages::with_balance(DisplayMessages::ACCESS_GRANTED, left),
+            // Users without a quota have no balance to show
+            None => DisplayMessages::ACCESS_GRANTED.to_string(),
+        })
     }
 
-    /// Map turnkey_core::ReaderType to storage ReaderType
-    fn map_reader_type(&self, reader: turnkey_core::ReaderType) -> ReaderType {
-        match reader {
-            turnkey_core::ReaderType::Rfid => ReaderType::Rfid,
-            turnkey_core::ReaderType::Biometric => ReaderType::Biometric,
+    /// Denial message if the calendar does not allow the user to pass now
+    async fn calendar_denial(&self, user_id: i64) -> StorageResult<Option<&'static str>> {
+        let local = self
+            .clock
+            .now()
+            .with_timezone(&self.schedule_offset)
+            .naive_local();
+        let schedule = self.schedule_repo.find_compiled_for_user(user_id).await?;
+
+        if self.holiday_policy == HolidayPolicy::Closed
+            && !schedule
+                .as_ref()
+                .is_some_and(|schedule| schedule.has_exception(local.date()))
+            && self
+                .holiday_repo
+                .find_by_date(local.date())
+                .await?
+                .is_some()
+            && !self.holiday_repo.is_exempt(user_id).await?
+        {
+            return Ok(Some(DisplayMessages::HOLIDAY_CLOSED));
+        }
+
+        match schedule {
+            Some(schedule) if !schedule.is_allowed(local) => {
+                Ok(Some(DisplayMessages::OUTSIDE_SCHEDULE))
+            }
+            _ => Ok(None),
         }
     }
 
-    /// Check if anti-passback window has expired
-    ///
-    /// Returns true if enough time has passed since the last access event,
-    /// allowing the user to access again in the same direction.
-    ///
-    /// # Arguments
-    ///
-    /// * `last_log` - The most recent access log entry for the user
-    ///
-    /// # Returns
+    /// Entries left in the user's daily quota, `None` without a quota
     ///
-    /// Returns true if the anti-passback window has expired (enough time has passed),
-    /// false if the user is still within the anti-passback window.
-    fn is_anti_passback_expired(&self, last_log: &AccessLog) -> bool {
-        let elapsed = Utc::now().signed_duration_since(last_log.timestamp);
-        elapsed.num_seconds() > ANTI_PASSBACK_WINDOW_SECS
-    }
-}
+    /// Zero or less means the quota is used up.
+    async fn quota_remaining(&self, user: &User) -> StorageResult<Option<i64>> {
+        let Some(max_passages) = self
+            .quota_repo
+            .find_effective(user.id, user.empresa.as_deref())
+            .await?
+        else {
+            return Ok(None);
+        };
 
-/// Implement AccessValidator trait for OfflineValidator
-impl AccessValidator for OfflineValidator {
-    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
-        // Delegate to internal implementation
-        // Note: internal method uses &self, trait requires &mut self for consistency
-        self.validate_internal(request).await
+        let (start, end) = self.quota_day.window(self.clock.now());
+        let passages = self.quota_repo.count_passages(user.id, start, end).await?;
+        Ok(Some(max_passages - passages))
     }
-}
 
-/// Configuration for online validator
-///
-/// Controls retry behavior and fallback strategy for network-based validation.
-///
-/// # Examples
-///
-/// ```
-/// use turnkey_storage::OnlineValidatorConfig;
-/// use std::time::Duration;
-///
-/// // Default configuration
-/// let config = OnlineValidatorConfig::default();
-/// assert_eq!(config.max_retries, 2);
-/// assert_eq!(config.retry_delay, Duration::from_millis(500));
-/// assert!(!config.fallback_to_offline);
-///
-/// // Custom configuration
-/// let config = OnlineValidatorConfig {
-///     max_retries: 1,
-///     retry_delay: Duration::from_millis(1000),
-///     fallback_to_offline: true,
-/// };
-/// ```
-#[derive(Debug, Clone)]
-pub struct OnlineValidatorConfig {
-    /// Maximum retry attempts on network failure (default: 2)
-    ///
-    /// Total attempts will be `1 + max_retries`. For example:
-    /// - max_retries = 0: Try once, no retries
-    /// - max_retries = 2: Try 3 times total (1 initial + 2 retries)
-    pub max_retries: usize,
+    /// Validate once per passage across the cluster, if one is set
+    async fn validate_clustered(&self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        let (Some(cluster), Some(device_id), Some(correlation_id)) =
+            (&self.cluster, self.device_id, request.correlation_id())
+        else {
+            return self.validate_internal(request).await;
+        };
 
-    /// Delay between retry attempts (default: 500ms)
-    ///
-    /// Fixed delay between retries. Simple and predictable for
-    /// development/testing environments.
-    pub retry_delay: Duration,
+        let takeover = match cluster.claim_decision(device_id, correlation_id).await? {
+            DecisionClaim::Decided(response) => return Ok(response),
+            DecisionClaim::InFlight => {
+                return Ok(AccessResponse::deny(
+                    DisplayMessages::DECISION_IN_PROGRESS.to_string(),
+                ));
+            }
+            DecisionClaim::Claimed { takeover } => takeover,
+        };
 
-    /// Enable fallback to offline mode on failure (default: false)
-    ///
-    /// If true, validator will attempt offline validation after
-    /// all network retries are exhausted.
-    pub fallback_to_offline: bool,
-}
+        // A node that died mid-decision may have logged the passage already
+        let logged = if takeover {
+            self.logged_response(device_id, request).await?
+        } else {
+            None
+        };
+        let result = match logged {
+            Some(response) => Ok(response),
+            None => self.validate_internal(request).await,
+        };
 
-impl Default for OnlineValidatorConfig {
-    fn default() -> Self {
-        Self {
-            max_retries: 2,
-            retry_delay: Duration::from_millis(500),
-            fallback_to_offline: false,
+        match result {
+            Ok(response) => {
+                cluster
+                    .complete_decision(device_id, correlation_id, &response)
+                    .await?;
+                Ok(response)
+            }
+            Err(e) => {
+                if let Err(abandon) = cluster.abandon_decision(device_id, correlation_id).await {
+                    tracing::warn!(error = %abandon, "Failed to abandon decision claim");
+                }
+                Err(e)
+            }
         }
     }
-}
 
-/// Online validator for access control requests
-///
-/// Validates access requests by sending them to a remote validation server
-/// via TCP and waiting for responses. This validator is used when the
-/// turnstile emulator operates in ONLINE mode.
-///
-/// # Validation Flow
-///
-/// 1. Connect to server (if not already connected)
-/// 2. Convert AccessRequest → Henry protocol Message
-/// 3. Send message to server
-/// 4. Receive response (with timeout)
-/// 5. Convert response Message → AccessResponse
-/// 6. On failure: retry up to max_retries times
-/// 7. If all retries fail and fallback enabled: use OfflineValidator
-///
-/// # Retry Logic
-///
-/// Simple fixed-delay retry strategy suitable for emulator use:
-/// - Configurable max retry attempts (default: 2)
-/// - Fixed delay between retries (default: 500ms)
-/// - Clear error messages on persistent failure
-///
-/// # Examples
-///
-/// ## Basic Online Validation
-///
-/// ```ignore
-/// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig, AccessValidator};
-/// use turnkey_network::{TcpClient, TcpClientConfig};
-/// use turnkey_core::DeviceId;
-/// use std::time::Duration;
-///
-/// // Create TCP client
-/// let client_config = TcpClientConfig {
-///     server_addr: "192.168.0.100:3000".parse()?,
-///     timeout: Duration::from_millis(3000),
-/// };
-/// let tcp_client = TcpClient::new(client_config);
-///
-/// // Create online validator
-/// let device_id = DeviceId::new(15)?;
-/// let mut validator = OnlineValidator::new(
-///     tcp_client,
-///     device_id,
-///     OnlineValidatorConfig::default()
-/// );
-///
-/// let response = validator.validate(&request).await?;
-/// ```
-///
-/// ## Hybrid Mode with Offline Fallback
-///
-/// ```ignore
-/// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig, OfflineValidator, AccessValidator};
-/// use turnkey_network::{TcpClient, TcpClientConfig};
-/// use turnkey_core::DeviceId;
-///
-/// let config = OnlineValidatorConfig {
-///     max_retries: 2,
-///     fallback_to_offline: true,
-///     ..Default::default()
-/// };
-///
-/// let offline = OfflineValidator::new(pool);
-/// let mut validator = OnlineValidator::with_fallback(
-///     tcp_client,
-///     device_id,
-///     config,
-///     offline
-/// );
-///
-/// // If network fails after retries, automatically falls back to offline
-/// let response = validator.validate(&request).await?;
-/// ```
-pub struct OnlineValidator {
-    tcp_client: TcpClient,
-    device_id: DeviceId,
-    config: OnlineValidatorConfig,
-    offline_fallback: Option<OfflineValidator>,
-}
+    /// Rebuild the answer of a passage from its access log entry, if any
+    async fn logged_response(
+        &self,
+        device_id: DeviceId,
+        request: &AccessRequest,
+    ) -> StorageResult<Option<AccessResponse>> {
+        let Some(correlation_id) = request.correlation_id() else {
+            return Ok(None);
+        };
+        let logs = self
+            .log_repo
+            .find_by_correlation_id(&correlation_id.to_string())
+            .await?;
+        let device = i64::from(device_id.as_u8());
+
+        Ok(logs
+            .into_iter()
+            .rfind(|log| log.device_id == Some(device))
+            .map(|log| {
+                let message = log.display_message.unwrap_or_default();
+                if log.granted {
+                    Self::grant_response(request, &message)
+                } else {
+                    AccessResponse::deny(message)
+                }
+            }))
+    }
 
-impl std::fmt::Debug for OnlineValidator {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("OnlineValidator")
-            .field("device_id", &self.device_id)
-            .field("config", &self.config)
-            .field("has_offline_fallback", &self.offline_fallback.is_some())
-            .finish_non_exhaustive()
+    /// Build a grant response matching the requested direction
+    fn grant_response(request: &AccessRequest, message: &str) -> AccessResponse {
+        if request.is_entry() {
+            AccessResponse::grant_entry(message.to_string())
+        } else if request.is_exit() {
+            AccessResponse::grant_exit(message.to_string())
+        } else {
+            // Undefined direction - grant both
+            AccessResponse::grant_both(message.to_string())
+        }
     }
-}
 
-impl OnlineValidator {
-    /// Create a new online validator without offline fallback
-    ///
-    /// # Arguments
-    ///
-    /// * `tcp_client` - TCP client for server communication
-    /// * `device_id` - Device ID to use in protocol messages
-    /// * `config` - Validator configuration
-    ///
-    /// # Examples
-    ///
-    /// ```no_run
-    /// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig};
-    /// use turnkey_network::{TcpClient, TcpClientConfig};
-    /// use turnkey_core::DeviceId;
+    /// Check for an open access exception covering the card or matricula
     ///
-    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-    /// let tcp_client = TcpClient::new(TcpClientConfig::default());
-    /// let device_id = DeviceId::new(1)?;
-    /// let validator = OnlineValidator::new(
-    ///     tcp_client,
-    ///     device_id,
-    ///     OnlineValidatorConfig::default()
-    /// );
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn new(tcp_client: TcpClient, device_id: DeviceId, config: OnlineValidatorConfig) -> Self {
-        Self {
-            tcp_client,
+    /// Looks up exceptions covering the card (or the user's matricula) on
+    /// this validator's device at the current server time.
+    async fn has_exception(
+        &self,
+        matricula: Option<&str>,
+        card_number: &str,
+    ) -> StorageResult<bool> {
+        let device_id = self.device_id.map(|id| i64::from(id.as_u8()));
+        let exceptions = interruptible(self.exception_repo.find_matching(
+            card_number,
+            matricula,
             device_id,
-            config,
-            offline_fallback: None,
-        }
+            self.clock.now(),
+        ))
+        .await?;
+
+        Ok(!exceptions.is_empty())
     }
 
-    /// Create a new online validator with offline fallback support
-    ///
-    /// If network validation fails after all retries, the offline validator
-    /// will be used as a fallback.
-    ///
-    /// # Arguments
-    ///
-    /// * `tcp_client` - TCP client for server communication
-    /// * `device_id` - Device ID to use in protocol messages
-    /// * `config` - Validator configuration (fallback_to_offline should be true)
-    /// * `offline_validator` - Offline validator for fallback
-    ///
-    /// # Examples
+    /// Grant an unregistered card through an open access exception, if one
+    /// matches
     ///
-    /// ```ignore
-    /// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig, OfflineValidator};
-    /// use turnkey_network::{TcpClient, TcpClientConfig};
-    /// use turnkey_core::DeviceId;
+    /// The card has no user, so of the remaining checks only the device's
+    /// access methods apply. Returns `None` when no exception covers the
+    /// card, so the caller denies it as unregistered.
+    async fn grant_unregistered_by_exception(
+        &self,
+        card_number: &str,
+        request: &AccessRequest,
+    ) -> StorageResult<Option<AccessResponse>> {
+        if !self.has_exception(None, card_number).await? {
+            return Ok(None);
+        }
+
+        let method = AccessMethod::from_reader_type(request.reader_type());
+        let device = match self.device_id {
+            Some(id) => interruptible(self.device_repo.find_by_id(i64::from(id.as_u8()))).await?,
+            None => None,
+        };
+        if let Some(message) = method.device_denial(device.as_ref()) {
+            return self
+                .deny_with_log(None, None, card_number, request, message)
+                .await
+                .map(Some);
+        }
+
+        self.log_access_granted(
+            None,
+            None,
+            card_number,
+            request,
+            DisplayMessages::ACCESS_GRANTED_EXCEPTION,
+        )
+        .await?;
+
+        Ok(Some(Self::grant_response(
+            request,
+            DisplayMessages::ACCESS_GRANTED_EXCEPTION,
+        )))
+    }
+
+    /// Record an unknown card read when learning mode is enabled
     ///
-    /// let config = OnlineValidatorConfig {
-    ///     fallback_to_offline: true,
-    ///     ..Default::default()
-    /// };
+    /// Returns `true` if the read was recorded. Numbers that only fit the
+    /// protocol length limits before trimming are skipped, since the
+    /// normalized form could not be registered as a card anyway.
+    async fn record_pending_card(&self, request: &AccessRequest) -> StorageResult<bool> {
+        let normalized_len = request.card_number().trim().len();
+        if !self.learning_mode || !(MIN_CARD_LENGTH..=MAX_CARD_LENGTH).contains(&normalized_len) {
+            return Ok(false);
+        }
+
+        ensure_active()?;
+        let device_id = self.device_id.map(|id| i64::from(id.as_u8()));
+        self.pending_repo
+            .record(request.card_number(), device_id, self.clock.now())
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Capture the read into the device's open enrollment session, if any
     ///
-    /// let offline = OfflineValidator::new(pool);
-    /// let validator = OnlineValidator::with_fallback(
-    ///     tcp_client,
-    ///     device_id,
-    ///     config,
-    ///     offline
-    /// );
-    /// ```
-    pub fn with_fallback(
-        tcp_client: TcpClient,
-        device_id: DeviceId,
-        config: OnlineValidatorConfig,
-        offline_validator: OfflineValidator,
-    ) -> Self {
-        Self {
-            tcp_client,
-            device_id,
-            config,
-            offline_fallback: Some(offline_validator),
+    /// Returns `None` when the validator has no device ID, the read is not a
+    /// card, or no session is open, so normal validation proceeds.
+    async fn capture_enrollment(
+        &self,
+        card_number: &str,
+        request: &AccessRequest,
+    ) -> StorageResult<Option<AccessResponse>> {
+        let Some(device_id) = self.device_id.map(|id| i64::from(id.as_u8())) else {
+            return Ok(None);
+        };
+        if request.reader_type() != turnkey_core::ReaderType::Rfid
+            || !(MIN_CARD_LENGTH..=MAX_CARD_LENGTH).contains(&card_number.len())
+        {
+            return Ok(None);
+        }
+
+        let now = self.clock.now();
+        if interruptible(self.enrollment_repo.find_open(device_id, now))
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        if let Some(card) = self.card_repo.find_by_number(card_number).await? {
+            return self
+                .deny_with_log(
+                    Some(card.user_id),
+                    Some(&card.matricula),
+                    card_number,
+                    request,
+                    DisplayMessages::CARD_ALREADY_REGISTERED,
+                )
+                .await
+                .map(Some);
+        }
+
+        ensure_active()?;
+        let Some(pending) = self
+            .enrollment_repo
+            .capture(device_id, request.card_number(), now)
+            .await?
+        else {
+            // Session expired or was cancelled since the lookup
+            return Ok(None);
+        };
+        if let Some(events) = &self.enrollment_events {
+            let _ = events.try_send(pending);
         }
+
+        self.deny_with_log(
+            None,
+            None,
+            card_number,
+            request,
+            DisplayMessages::CARD_ENROLLED,
+        )
+        .await
+        .map(Some)
     }
 
-    /// Attempt validation with retry logic
+    /// Check the age of the local data against the staleness threshold
     ///
-    /// Retries network operations up to `max_retries` times with
-    /// fixed delays. If all retries fail and fallback is enabled,
-    /// attempts offline validation.
-    async fn validate_with_retry(
-        &mut self,
+    /// Returns a deny response when the data is stale and this validator's
+    /// device is flagged with `deny_when_stale`; `None` lets validation
+    /// proceed, with a warning logged and raised if the data is stale.
+    async fn check_data_freshness(
+        &self,
+        card_number: &str,
         request: &AccessRequest,
-    ) -> StorageResult<AccessResponse> {
-        let mut attempts = 0;
-        let mut last_error = None;
+    ) -> StorageResult<Option<AccessResponse>> {
+        let Some(threshold) = self.stale_threshold else {
+            return Ok(None);
+        };
 
-        while attempts <= self.config.max_retries {
-            match self.validate_once(request).await {
-                Ok(response) => return Ok(response),
-                Err(e) => {
-                    last_error = Some(e);
-                    attempts += 1;
+        let now = self.clock.now();
+        let state = interruptible(self.sync_repo.get()).await?;
+        if !state.is_stale(threshold, now) {
+            return Ok(None);
+        }
 
-                    if attempts <= self.config.max_retries {
-                        tokio::time::sleep(self.config.retry_delay).await;
-                    }
-                }
-            }
+        let device_id = self.device_id.map(|id| i64::from(id.as_u8()));
+        let denied = match device_id {
+            Some(device_id) => self
+                .device_repo
+                .find_by_id(device_id)
+                .await?
+                .is_some_and(|device| device.deny_when_stale),
+            None => false,
+        };
+
+        let age_secs = state.age(now).map(|age| age.num_seconds());
+        tracing::warn!(
+            device_id,
+            age_secs,
+            threshold_secs = threshold.num_seconds(),
+            denied,
+            "offline decision on stale data"
+        );
+
+        if let Some(warnings) = &self.stale_warnings {
+            let _ = warnings.try_send(StaleDataWarning {
+                device_id,
+                card_number: card_number.to_string(),
+                last_success_at: state.last_success_at,
+                age_secs,
+                threshold_secs: threshold.num_seconds(),
+                denied,
+                timestamp: now,
+            });
         }
 
-        // If fallback enabled, try offline validation
-        if self.config.fallback_to_offline
-            && let Some(ref mut offline) = self.offline_fallback
-        {
-            return offline.validate(request).await;
+        if !denied {
+            return Ok(None);
         }
 
-        // Otherwise, return error
-        Err(crate::error::StorageError::ValidationFailed(
-            self.config.max_retries,
-            last_error
-                .map(|e| e.to_string())
-                .unwrap_or_else(|| "Unknown error".to_string()),
-        ))
+        self.deny_with_log(
+            None,
+            None,
+            card_number,
+            request,
+            DisplayMessages::DATA_STALE,
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Log a granted access attempt
+    async fn log_access_granted(
+        &self,
+        user_id: Option<i64>,
+        matricula: Option<&str>,
+        card_number: &str,
+        request: &AccessRequest,
+        message: &str,
+    ) -> StorageResult<()> {
+        let log = self.new_log(user_id, matricula, card_number, request, true, message);
+        self.timed_log(self.write_log(&log, None)).await?;
+        Ok(())
+    }
+
+    /// Log a denied access attempt
+    async fn log_access_denied(
+        &self,
+        user_id: Option<i64>,
+        matricula: Option<&str>,
+        card_number: &str,
+        request: &AccessRequest,
+        message: &str,
+    ) -> StorageResult<()> {
+        let log = self.new_log(user_id, matricula, card_number, request, false, message);
+        self.timed_log(self.write_log(&log, None)).await?;
+        Ok(())
+    }
+
+    /// Write an access log, with its notification when an outbox is set
+    ///
+    /// With a device ID, the log is numbered with the device's NSR in the
+    /// same transaction. With `passback_window`, the log is skipped under
+    /// the same rule as
+    /// [`create_unless_passback`](AccessLogRepository::create_unless_passback)
+    /// and `None` is returned.
+    async fn write_log(
+        &self,
+        log: &AccessLog,
+        passback_window: Option<DateTime<Utc>>,
+    ) -> StorageResult<Option<i64>> {
+        ensure_active()?;
+        let Some(device_id) = self.device_id else {
+            return match passback_window {
+                Some(window_start) => {
+                    self.log_repo
+                        .create_unless_passback(log, window_start)
+                        .await
+                }
+                None => self.log_repo.create(log).await.map(Some),
+            };
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let log_id = match passback_window {
+            Some(window_start) => {
+                transaction::create_access_log_unless_passback(&mut tx, log, window_start).await?
+            }
+            None => Some(transaction::create_access_log(&mut tx, log).await?),
+        };
+        if let Some(log_id) = log_id {
+            match self.outbox {
+                Some(_) => Outbox::enqueue_access(&mut tx, device_id, log_id, log).await?,
+                None => Outbox::number_access(&mut tx, device_id, log_id).await?,
+            };
+            tx.commit().await?;
+        }
+        Ok(log_id)
+    }
+
+    /// Run a log write, adding its duration to [`take_log_time`](Self::take_log_time)
+    async fn timed_log<T>(&self, write: impl Future<Output = T>) -> T {
+        let started = Instant::now();
+        let result = write.await;
+        let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.log_time_nanos.fetch_add(nanos, Ordering::Relaxed);
+        result
+    }
+
+    /// Build an access log entry for this request
+    fn new_log(
+        &self,
+        user_id: Option<i64>,
+        matricula: Option<&str>,
+        card_number: &str,
+        request: &AccessRequest,
+        granted: bool,
+        message: &str,
+    ) -> AccessLog {
+        let log = AccessLog::new(
+            user_id,
+            matricula.map(|s| s.to_string()),
+            card_number.to_string(),
+            self.map_direction(request.direction()),
+            self.map_reader_type(request.reader_type()),
+            granted,
+            Some(message.to_string()),
+            self.clock.now(),
+        );
+        let log = match request.correlation_id() {
+            Some(correlation_id) => log.with_correlation_id(correlation_id),
+            None => log,
+        };
+        self.attach_device_clock(log, request)
+    }
+
+    /// Helper method to log denied access and return deny response
+    ///
+    /// This method encapsulates the common pattern of logging a denied access
+    /// attempt and returning the corresponding deny response. It ensures
+    /// consistent error handling throughout the validation flow.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Optional user ID (None if user not found)
+    /// * `matricula` - Optional user matricula (None if user not found)
+    /// * `card_number` - Card number that was presented
+    /// * `request` - The access request being validated
+    /// * `message` - The deny reason message
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(AccessResponse::deny)` with the provided message.
+    ///
+    /// # Errors
+    ///
+    /// Returns error only if database logging fails.
+    async fn deny_with_log(
+        &self,
+        user_id: Option<i64>,
+        matricula: Option<&str>,
+        card_number: &str,
+        request: &AccessRequest,
+        message: &str,
+    ) -> StorageResult<AccessResponse> {
+        self.log_access_denied(user_id, matricula, card_number, request, message)
+            .await?;
+
+        if let Some(detector) = &self.coercion
+            && !request.reader_type().is_keypad()
+            && let Some(alarm) = detector.observe_denial(
+                card_number,
+                self.device_id.map(|id| i64::from(id.as_u8())),
+                self.clock.now(),
+            )
+        {
+            detector.publish(alarm).await;
+        }
+
+        Ok(AccessResponse::deny(message.to_string()))
+    }
+
+    /// Record the device-reported timestamp on a log entry
+    ///
+    /// Raises a drift alert when enabled and the device clock is further
+    /// from the server clock than the configured threshold.
+    fn attach_device_clock(&self, mut log: AccessLog, request: &AccessRequest) -> AccessLog {
+        let device_timestamp = request.timestamp().inner().with_timezone(&Utc);
+        log.device_timestamp = Some(device_timestamp);
+
+        if let Some(device_id) = self.device_id {
+            let device_id = i64::from(device_id.as_u8());
+            log.device_id = Some(device_id);
+
+            if let Some((threshold, alerts)) = &self.drift_alerts
+                && let Some(alert) =
+                    ClockDriftAlert::check(device_id, device_timestamp, log.timestamp, *threshold)
+            {
+                let _ = alerts.try_send(alert);
+            }
+        }
+
+        log
+    }
+
+    /// Map turnkey_core::AccessDirection to storage Direction
+    fn map_direction(&self, dir: turnkey_core::AccessDirection) -> Direction {
+        match dir {
+            turnkey_core::AccessDirection::Undefined => Direction::Undefined,
+            turnkey_core::AccessDirection::Entry => Direction::Entry,
+            turnkey_core::AccessDirection::Exit => Direction::Exit,
+        }
+    }
+
+    /// Map turnkey_core::ReaderType to storage ReaderType
+    fn map_reader_type(&self, reader: turnkey_core::ReaderType) -> ReaderType {
+        match reader {
+            turnkey_core::ReaderType::Rfid => ReaderType::Rfid,
+            turnkey_core::ReaderType::Keypad => ReaderType::Keypad,
+            turnkey_core::ReaderType::Biometric => ReaderType::Biometric,
+        }
+    }
+}
+
+/// Card number recorded in access logs for keypad entries, in place of the code
+const KEYPAD_CARD_NUMBER: &str = "TECLADO";
+
+/// Key typed between the matricula and the PIN of a keypad entry
+///
+/// A keypad request whose code reads `<matricula>*<PIN>` is checked
+/// against that user's PIN only; a code without it is a PIN-only entry.
+pub const KEYPAD_MATRICULA_SEPARATOR: char = '*';
+
+tokio::task_local! {
+    /// Cancellation of the validation running on this task
+    ///
+    /// Only set inside [`OfflineValidator::validate_cancellable`].
+    static CANCEL: CancellationToken;
+}
+
+/// Fail with `Cancelled` once the current validation was cancelled
+///
+/// Checked before every write, so a cancelled validation records nothing.
+fn ensure_active() -> StorageResult<()> {
+    match CANCEL.try_with(CancellationToken::is_cancelled) {
+        Ok(true) => Err(StorageError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// Run a read query, abandoning it if the current validation is cancelled
+async fn interruptible<T>(query: impl Future<Output = StorageResult<T>>) -> StorageResult<T> {
+    let Ok(cancel) = CANCEL.try_with(CancellationToken::clone) else {
+        return query.await;
+    };
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(StorageError::Cancelled),
+        result = query => result,
+    }
+}
+
+/// Implement AccessValidator trait for OfflineValidator
+impl AccessValidator for OfflineValidator {
+    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        // Delegate to internal implementation
+        // Note: internal method uses &self, trait requires &mut self for consistency
+        self.validate_clustered(request)
+            .instrument(validation_span("offline", request))
+            .await
+    }
+}
+
+/// Span covering one validation, tagged with the passage's correlation ID
+pub(crate) fn validation_span(mode: &'static str, request: &AccessRequest) -> tracing::Span {
+    tracing::debug_span!(
+        "validate",
+        mode,
+        correlation_id = request.correlation_id().map(tracing::field::display),
+    )
+}
+
+/// Configuration for online validator
+///
+/// Controls retry behavior and fallback strategy for network-based validation.
+///
+/// # Examples
+///
+/// ```
+/// use turnkey_storage::OnlineValidatorConfig;
+/// use std::time::Duration;
+///
+/// // Default configuration
+/// let config = OnlineValidatorConfig::default();
+/// assert_eq!(config.max_retries, 2);
+/// assert_eq!(config.retry_delay, Duration::from_millis(500));
+/// assert!(!config.fallback_to_offline);
+///
+/// // Custom configuration
+/// let config = OnlineValidatorConfig {
+///     max_retries: 1,
+///     retry_delay: Duration::from_millis(1000),
+///     fallback_to_offline: true,
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct OnlineValidatorConfig {
+    /// Maximum retry attempts on network failure (default: 2)
+    ///
+    /// Total attempts will be `1 + max_retries`. For example:
+    /// - max_retries = 0: Try once, no retries
+    /// - max_retries = 2: Try 3 times total (1 initial + 2 retries)
+    pub max_retries: usize,
+
+    /// Delay between retry attempts (default: 500ms)
+    ///
+    /// Fixed delay between retries. Simple and predictable for
+    /// development/testing environments.
+    pub retry_delay: Duration,
+
+    /// Enable fallback to offline mode on failure (default: false)
+    ///
+    /// If true, validator will attempt offline validation after
+    /// all network retries are exhausted.
+    pub fallback_to_offline: bool,
+}
+
+impl Default for OnlineValidatorConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            retry_delay: Duration::from_millis(500),
+            fallback_to_offline: false,
+        }
+    }
+}
+
+/// Online validator for access control requests
+///
+/// Validates access requests by sending them to a remote validation server
+/// via TCP and waiting for responses. This validator is used when the
+/// turnstile emulator operates in ONLINE mode.
+///
+/// # Validation Flow
+///
+/// 1. Connect to server (if not already connected)
+/// 2. Convert AccessRequest → Henry protocol Message
+/// 3. Send message to server
+/// 4. Receive response (with timeout)
+/// 5. Convert response Message → AccessResponse
+/// 6. On failure: retry up to max_retries times
+/// 7. If all retries fail and fallback enabled: use OfflineValidator
+///
+/// # Retry Logic
+///
+/// Simple fixed-delay retry strategy suitable for emulator use:
+/// - Configurable max retry attempts (default: 2)
+/// - Fixed delay between retries (default: 500ms)
+/// - Clear error messages on persistent failure
+///
+/// # Examples
+///
+/// ## Basic Online Validation
+///
+/// ```ignore
+/// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig, AccessValidator};
+/// use turnkey_network::{TcpClient, TcpClientConfig};
+/// use turnkey_core::DeviceId;
+/// use std::time::Duration;
+///
+/// // Create TCP client
+/// let client_config = TcpClientConfig {
+///     server_addr: "192.168.0.100:3000".parse()?,
+///     timeout: Duration::from_millis(3000),
+/// };
+/// let tcp_client = TcpClient::new(client_config);
+///
+/// // Create online validator
+/// let device_id = DeviceId::new(15)?;
+/// let mut validator = OnlineValidator::new(
+///     tcp_client,
+///     device_id,
+///     OnlineValidatorConfig::default()
+/// );
+///
+/// let response = validator.validate(&request).await?;
+/// ```
+///
+/// ## Hybrid Mode with Offline Fallback
+///
+/// ```ignore
+/// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig, OfflineValidator, AccessValidator};
+/// use turnkey_network::{TcpClient, TcpClientConfig};
+/// use turnkey_core::DeviceId;
+///
+/// let config = OnlineValidatorConfig {
+///     max_retries: 2,
+///     fallback_to_offline: true,
+///     ..Default::default()
+/// };
+///
+/// let offline = OfflineValidator::new(pool);
+/// let mut validator = OnlineValidator::with_fallback(
+///     tcp_client,
+///     device_id,
+///     config,
+///     offline
+/// );
+///
+/// // If network fails after retries, automatically falls back to offline
+/// let response = validator.validate(&request).await?;
+/// ```
+pub struct OnlineValidator {
+    link: ServerLink,
+    device_id: DeviceId,
+    config: OnlineValidatorConfig,
+    offline_fallback: Option<OfflineValidator>,
+    signer: Option<MessageSigner>,
+}
+
+/// Connection an [`OnlineValidator`] talks to the server through
+enum ServerLink {
+    /// Connection owned by this validator
+    Dedicated(Box<TcpClient>),
+
+    /// Connection shared with the other readers of the device
+    Shared(SharedTcpClient),
+}
+
+impl ServerLink {
+    /// Send a request and receive the server's response
+    async fn exchange(&mut self, message: Message) -> StorageResult<Message> {
+        match self {
+            Self::Dedicated(tcp_client) => {
+                if !tcp_client.is_connected() {
+                    tcp_client.connect().await.map_err(|e| {
+                        StorageError::NetworkError(format!("Connection failed: {}", e))
+                    })?;
+                }
+
+                tcp_client
+                    .send(message)
+                    .await
+                    .map_err(|e| StorageError::NetworkError(format!("Send failed: {}", e)))?;
+
+                // Receive with the timeout from TcpClient
+                tcp_client
+                    .recv()
+                    .await
+                    .map_err(|e| StorageError::NetworkError(format!("Receive failed: {}", e)))
+            }
+            Self::Shared(shared) => shared.request(message).await.map_err(|e| {
+                StorageError::NetworkError(format!("Shared connection request failed: {}", e))
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for OnlineValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnlineValidator")
+            .field("device_id", &self.device_id)
+            .field(
+                "shared_connection",
+                &matches!(self.link, ServerLink::Shared(_)),
+            )
+            .field("config", &self.config)
+            .field("has_offline_fallback", &self.offline_fallback.is_some())
+            .field("signs_requests", &self.signer.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl OnlineValidator {
+    /// Create a new online validator without offline fallback
+    ///
+    /// # Arguments
+    ///
+    /// * `tcp_client` - TCP client for server communication
+    /// * `device_id` - Device ID to use in protocol messages
+    /// * `config` - Validator configuration
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig};
+    /// use turnkey_network::{TcpClient, TcpClientConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let tcp_client = TcpClient::new(TcpClientConfig::default());
+    /// let device_id = DeviceId::new(1)?;
+    /// let validator = OnlineValidator::new(
+    ///     tcp_client,
+    ///     device_id,
+    ///     OnlineValidatorConfig::default()
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(tcp_client: TcpClient, device_id: DeviceId, config: OnlineValidatorConfig) -> Self {
+        Self {
+            link: ServerLink::Dedicated(Box::new(tcp_client)),
+            device_id,
+            config,
+            offline_fallback: None,
+            signer: None,
+        }
+    }
+
+    /// Create a new online validator with offline fallback support
+    ///
+    /// If network validation fails after all retries, the offline validator
+    /// will be used as a fallback.
+    ///
+    /// # Arguments
+    ///
+    /// * `tcp_client` - TCP client for server communication
+    /// * `device_id` - Device ID to use in protocol messages
+    /// * `config` - Validator configuration (fallback_to_offline should be true)
+    /// * `offline_validator` - Offline validator for fallback
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig, OfflineValidator};
+    /// use turnkey_network::{TcpClient, TcpClientConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// let config = OnlineValidatorConfig {
+    ///     fallback_to_offline: true,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let offline = OfflineValidator::new(pool);
+    /// let validator = OnlineValidator::with_fallback(
+    ///     tcp_client,
+    ///     device_id,
+    ///     config,
+    ///     offline
+    /// );
+    /// ```
+    pub fn with_fallback(
+        tcp_client: TcpClient,
+        device_id: DeviceId,
+        config: OnlineValidatorConfig,
+        offline_validator: OfflineValidator,
+    ) -> Self {
+        Self {
+            link: ServerLink::Dedicated(Box::new(tcp_client)),
+            device_id,
+            config,
+            offline_fallback: Some(offline_validator),
+            signer: None,
+        }
+    }
+
+    /// Create an online validator on a connection shared with other readers
+    ///
+    /// Use one validator per logical reader (e.g. the entry and exit readers
+    /// of a turnstile) with clones of the same [`SharedTcpClient`], so the
+    /// device keeps a single connection to the server. Requests from the
+    /// readers are sent one exchange at a time, in arrival order.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig};
+    /// use turnkey_network::{SharedTcpClient, TcpClientConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let connection = SharedTcpClient::new(TcpClientConfig::default());
+    /// let device_id = DeviceId::new(15)?;
+    ///
+    /// let entry = OnlineValidator::shared(
+    ///     connection.clone(),
+    ///     device_id,
+    ///     OnlineValidatorConfig::default(),
+    /// );
+    /// let exit = OnlineValidator::shared(connection, device_id, OnlineValidatorConfig::default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shared(
+        connection: SharedTcpClient,
+        device_id: DeviceId,
+        config: OnlineValidatorConfig,
+    ) -> Self {
+        Self {
+            link: ServerLink::Shared(connection),
+            device_id,
+            config,
+            offline_fallback: None,
+            signer: None,
+        }
+    }
+
+    /// Fall back to an offline validator when the server cannot be reached
+    ///
+    /// Only used when `config.fallback_to_offline` is set. Equivalent to
+    /// [`with_fallback()`](Self::with_fallback) for validators built with
+    /// [`shared()`](Self::shared).
+    pub fn with_offline_fallback(mut self, offline_validator: OfflineValidator) -> Self {
+        self.offline_fallback = Some(offline_validator);
+        self
+    }
+
+    /// Sign outbound access requests with the device's shared secret
+    ///
+    /// Every request carries an HMAC-SHA256 signature field (see
+    /// `turnkey_protocol::signing`). The server must hold the same key for
+    /// this device ID, otherwise the connection is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use turnkey_storage::{OnlineValidator, OnlineValidatorConfig};
+    /// use turnkey_network::{TcpClient, TcpClientConfig};
+    /// use turnkey_core::DeviceId;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let validator = OnlineValidator::new(
+    ///     TcpClient::new(TcpClientConfig::default()),
+    ///     DeviceId::new(15)?,
+    ///     OnlineValidatorConfig::default(),
+    /// )
+    /// .with_signing_key(b"0123456789abcdef".to_vec());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_signing_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.signer = Some(MessageSigner::new(key));
+        self
+    }
+
+    /// Attempt validation with retry logic
+    ///
+    /// Retries network operations up to `max_retries` times with
+    /// fixed delays. If all retries fail and fallback is enabled,
+    /// attempts offline validation.
+    async fn validate_with_retry(
+        &mut self,
+        request: &AccessRequest,
+    ) -> StorageResult<AccessResponse> {
+        let mut attempts = 0;
+        let mut last_error = None;
+
+        while attempts <= self.config.max_retries {
+            match self.validate_once(request).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    last_error = Some(e);
+                    attempts += 1;
+
+                    if attempts <= self.config.max_retries {
+                        tokio::time::sleep(self.config.retry_delay).await;
+                    }
+                }
+            }
+        }
+
+        // If fallback enabled, try offline validation
+        if self.config.fallback_to_offline
+            && let Some(ref mut offline) = self.offline_fallback
+        {
+            return offline.validate(request).await;
+        }
+
+        // Otherwise, return error
+        Err(crate::error::StorageError::ValidationFailed(
+            self.config.max_retries,
+            last_error
+                .map(|e| e.to_string())
+                .unwrap_or_else(|| "Unknown error".to_string()),
+        ))
+    }
+
+    /// Single validation attempt without retry
+    ///
+    /// Performs one complete validation cycle:
+    /// 1. Connect if not connected
+    /// 2. Convert request to message
+    /// 3. Send message
+    /// 4. Receive response
+    /// 5. Convert message to response
+    async fn validate_once(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        // Step 2: Convert AccessRequest → Message (signed if a key is set)
+        let message = self.outbound_message(request)?;
+
+        // Steps 1, 3 and 4: Connect if needed, send, receive
+        let response_msg = self.link.exchange(message).await?;
+
+        // Step 5: Convert Message → AccessResponse
+        Self::message_to_response(&response_msg)
+    }
+
+    /// Build the request message, signing it when a key is configured
+    fn outbound_message(&self, request: &AccessRequest) -> StorageResult<Message> {
+        let mut message = Self::request_to_message(request, self.device_id)?;
+        if let Some(signer) = &self.signer {
+            signer
+                .sign(&mut message)
+                .map_err(|e| StorageError::ProtocolError(format!("Signing failed: {}", e)))?;
+        }
+        Ok(message)
+    }
+
+    /// Create a FieldData with improved error messages
+    ///
+    /// Helper method to create FieldData with context-specific error messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to convert to FieldData
+    /// * `context` - Description of the field for error messages
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError` if the value contains reserved delimiters
+    fn field_data(value: impl ToString, context: &str) -> StorageResult<FieldData> {
+        FieldData::new(value.to_string())
+            .map_err(|e| StorageError::ProtocolError(format!("{}: {}", context, e)))
+    }
+
+    /// Convert AccessRequest to Henry protocol Message
+    ///
+    /// Builds a Henry protocol access request message (command 000+0)
+    /// with the following fields:
+    /// - Field 1: Card number
+    /// - Field 2: Timestamp
+    /// - Field 3: Direction (0=undefined, 1=entry, 2=exit)
+    /// - Field 4: Reader type (0=RFID, 1=biometric, 3=keypad)
+    /// - Field 5: Correlation ID, only when the request carries one
+    fn request_to_message(request: &AccessRequest, device_id: DeviceId) -> StorageResult<Message> {
+        let direction_value = match request.direction() {
+            turnkey_core::AccessDirection::Undefined => "0",
+            turnkey_core::AccessDirection::Entry => "1",
+            turnkey_core::AccessDirection::Exit => "2",
+        };
+
+        let reader_value = match request.reader_type() {
+            turnkey_core::ReaderType::Rfid => "0",
+            turnkey_core::ReaderType::Keypad => "3",
+            turnkey_core::ReaderType::Biometric => "1",
+        };
+
+        let mut builder = MessageBuilder::new(device_id, CommandCode::AccessRequest)
+            .field(Self::field_data(
+                request.card_number(),
+                "Invalid card number",
+            )?)
+            .field(Self::field_data(request.timestamp(), "Invalid timestamp")?)
+            .field(Self::field_data(direction_value, "Invalid direction")?)
+            .field(Self::field_data(reader_value, "Invalid reader type")?);
+        if let Some(correlation_id) = request.correlation_id() {
+            builder = builder.field(Self::field_data(correlation_id, "Invalid correlation ID")?);
+        }
+
+        builder
+            .build()
+            .map_err(|e| StorageError::ProtocolError(format!("Failed to build message: {}", e)))
+    }
+
+    /// Convert Henry protocol Message to AccessResponse
+    ///
+    /// Parses the response message and creates an appropriate
+    /// AccessResponse (grant, deny or message only).
+    ///
+    /// Expected response format:
+    /// - Grant entry: 00+5]seconds]message
+    /// - Grant exit: 00+6]seconds]message
+    /// - Grant both: 00+1]seconds]message
+    /// - Deny: 00+30]seconds]message
+    /// - Message only: 00+0]seconds]message
+    fn message_to_response(message: &Message) -> StorageResult<AccessResponse> {
+        // Check command code to determine grant/deny
+        let is_grant = matches!(
+            message.command,
+            CommandCode::GrantBoth | CommandCode::GrantEntry | CommandCode::GrantExit
+        );
+
+        let is_deny = matches!(message.command, CommandCode::DenyAccess);
+
+        // Extract display message from fields
+        // Typical format: field[0]=seconds, field[1]=message
+        let display_message = if message.fields.len() >= 2 {
+            message.fields[1].to_string()
+        } else if message.fields.len() == 1 {
+            message.fields[0].to_string()
+        } else {
+            "Unknown".to_string()
+        };
+
+        if is_grant {
+            match message.command {
+                CommandCode::GrantEntry => Ok(AccessResponse::grant_entry(display_message)),
+                CommandCode::GrantExit => Ok(AccessResponse::grant_exit(display_message)),
+                CommandCode::GrantBoth => Ok(AccessResponse::grant_both(display_message)),
+                _ => Ok(AccessResponse::deny(display_message)),
+            }
+        } else if is_deny {
+            Ok(AccessResponse::deny(display_message))
+        } else if message.command == CommandCode::DisplayOnly {
+            Ok(AccessResponse::display_only(display_message))
+        } else {
+            Err(crate::error::StorageError::ProtocolError(format!(
+                "Unexpected command code in response: {:?}",
+                message.command
+            )))
+        }
+    }
+}
+
+/// Implement AccessValidator trait for OnlineValidator
+impl AccessValidator for OnlineValidator {
+    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
+        self.validate_with_retry(request)
+            .instrument(validation_span("online", request))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Database;
+    use crate::models::{AccessException, User, VerificationMode};
+    use chrono::Duration;
+    use turnkey_core::{AccessDirection, HenryTimestamp};
+
+    async fn setup_test_db() -> Database {
+        Database::in_memory().await.unwrap()
+    }
+
+    async fn create_test_user(db: &Database, matricula: &str) -> i64 {
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Test User".to_string(),
+            matricula: matricula.to_string(),
+            cpf: None,
+            validade_inicio: Some(Utc::now() - Duration::days(1)),
+            validade_fim: Some(Utc::now() + Duration::days(30)),
+            ativo: true,
+            allow_card: true,
+            allow_bio: false,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let repo = SqliteUserRepository::new(db.pool().clone());
+        repo.create(&user).await.unwrap()
+    }
+
+    async fn create_test_card(db: &Database, numero: &str, matricula: &str, user_id: i64) {
+        let card = Card {
+            id: 0,
+            numero_cartao: numero.to_string(),
+            matricula: matricula.to_string(),
+            user_id,
+            validade_inicio: Some(Utc::now() - Duration::days(1)),
+            validade_fim: Some(Utc::now() + Duration::days(30)),
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let repo = SqliteCardRepository::new(db.pool().clone());
+        repo.create(&card).await.unwrap();
+    }
+
+    fn create_access_request(card_number: &str, direction: AccessDirection) -> AccessRequest {
+        let timestamp = HenryTimestamp::parse("10/05/2025 12:46:06").unwrap();
+        AccessRequest::new(
+            card_number.to_string(),
+            timestamp,
+            direction,
+            turnkey_core::ReaderType::Rfid,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_validate_grant_entry() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP001").await;
+        create_test_card(&db, "1234567890", "EMP001", user_id).await;
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("1234567890", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_grant());
+        assert_eq!(response.display_message(), "Acesso liberado");
+    }
+
+    #[tokio::test]
+    async fn test_validate_grant_exit() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP002").await;
+        create_test_card(&db, "2222222222", "EMP002", user_id).await;
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("2222222222", AccessDirection::Exit);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_grant());
+    }
+
+    #[tokio::test]
+    async fn test_validate_card_not_found() {
+        let db = setup_test_db().await;
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("9999999999", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), "Cartao nao cadastrado");
+    }
+
+    #[tokio::test]
+    async fn test_validate_learning_mode_records_unknown_card() {
+        let db = setup_test_db().await;
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(5).unwrap())
+            .with_learning_mode(true);
+        let request = create_access_request("c0ffee1234", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), DisplayMessages::CARD_PENDING);
+
+        let pending = SqlitePendingCardRepository::new(db.pool().clone())
+            .find_by_card_number("C0FFEE1234")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pending.raw_value, "c0ffee1234");
+        assert_eq!(pending.device_id, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_validate_without_learning_mode_skips_pending() {
+        let db = setup_test_db().await;
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("c0ffee1234", AccessDirection::Entry);
+
+        validator.validate(&request).await.unwrap();
+
+        let pending = SqlitePendingCardRepository::new(db.pool().clone())
+            .find_all()
+            .await
+            .unwrap();
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_validate_enrollment_captures_next_card() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP050").await;
+        create_test_card(&db, "5050505050", "EMP050", user_id).await;
+        SqliteEnrollmentSessionRepository::new(db.pool().clone())
+            .start(5, "EMP050", Utc::now() + Duration::minutes(2))
+            .await
+            .unwrap();
+
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(5).unwrap())
+            .with_enrollment_events(event_tx);
+
+        // A registered card does not end the session
+        let response = validator
+            .validate(&create_access_request("5050505050", AccessDirection::Entry))
+            .await
+            .unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::CARD_ALREADY_REGISTERED
+        );
+
+        let response = validator
+            .validate(&create_access_request("ab50ab50", AccessDirection::Entry))
+            .await
+            .unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), DisplayMessages::CARD_ENROLLED);
+
+        let pending = event_rx.try_recv().unwrap();
+        assert_eq!(pending.card_number, "AB50AB50");
+        assert_eq!(pending.matricula.as_deref(), Some("EMP050"));
+
+        // Session is closed: the next read of the same card is validated normally
+        let response = validator
+            .validate(&create_access_request("ab50ab50", AccessDirection::Entry))
+            .await
+            .unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::CARD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_validate_card_inactive() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP003").await;
+
+        // Create inactive card
+        let card = Card {
+            id: 0,
+            numero_cartao: "3333333333".to_string(),
+            matricula: "EMP003".to_string(),
+            user_id,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: false, // Inactive
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let repo = SqliteCardRepository::new(db.pool().clone());
+        repo.create(&card).await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("3333333333", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), "Cartao inativo");
+    }
+
+    #[tokio::test]
+    async fn test_validate_card_expired() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP004").await;
+
+        // Create expired card
+        let card = Card {
+            id: 0,
+            numero_cartao: "4444444444".to_string(),
+            matricula: "EMP004".to_string(),
+            user_id,
+            validade_inicio: Some(Utc::now() - Duration::days(60)),
+            validade_fim: Some(Utc::now() - Duration::days(1)), // Expired
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let repo = SqliteCardRepository::new(db.pool().clone());
+        repo.create(&card).await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("4444444444", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            "Cartao fora do periodo de validade"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock_moves_through_anti_passback_window() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP040").await;
+        create_test_card(&db, "4040404040", "EMP040", user_id).await;
+
+        let clock = Arc::new(turnkey_core::MockClock::new(Utc::now()));
+        let mut validator = OfflineValidator::new(db.pool().clone()).with_clock(clock.clone());
+        let request = create_access_request("4040404040", AccessDirection::Entry);
+
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+        clock.advance(std::time::Duration::from_secs(
+            crate::models::DEFAULT_ANTI_PASSBACK_WINDOW_SECS as u64 - 1,
+        ));
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::ANTI_PASSBACK);
+
+        clock.advance(std::time::Duration::from_secs(2));
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+    }
+
+    #[tokio::test]
+    async fn test_anti_passback_window_per_device_and_per_user() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP042").await;
+        create_test_card(&db, "4242424242", "EMP042", user_id).await;
+
+        let clock = Arc::new(turnkey_core::MockClock::new(Utc::now()));
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_clock(clock.clone())
+            .with_anti_passback_policy(AntiPassbackPolicy::new(Duration::seconds(30)));
+        let request = create_access_request("4242424242", AccessDirection::Entry);
+
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::ANTI_PASSBACK);
+        clock.advance(std::time::Duration::from_secs(31));
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+
+        // The user's own window replaces the device's
+        let repo = SqliteAntiPassbackRepository::new(db.pool().clone());
+        repo.set_user_window(user_id, 0).await.unwrap();
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+
+        let mut strict = validator.with_anti_passback_policy(
+            AntiPassbackPolicy::new(Duration::seconds(30)).user_overrides(false),
+        );
+        let response = strict.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::ANTI_PASSBACK);
+
+        // A disabled device still honours a user's window
+        repo.set_user_window(user_id, 60).await.unwrap();
+        let mut open = strict.with_anti_passback_policy(AntiPassbackPolicy::disabled());
+        let response = open.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::ANTI_PASSBACK);
+        repo.remove_user_window(user_id).await.unwrap();
+        assert!(open.validate(&request).await.unwrap().is_grant());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_denies_outside_local_hours() {
+        use crate::models::{TimeInterval, WeeklySchedule};
+        use chrono::{Datelike, TimeZone, Weekday};
+
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP043").await;
+        create_test_card(&db, "4343434343", "EMP043", user_id).await;
+        let schedules = SqliteScheduleRepository::new(db.pool().clone());
+        let office = WeeklySchedule::new("Comercial").with_interval_on(
+            &[
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            TimeInterval::parse("08:00-18:00").unwrap(),
+        );
+        let schedule_id = schedules.create(&office).await.unwrap();
+        schedules.assign_user(user_id, schedule_id).await.unwrap();
+
+        // Next Tuesday at 20:00 UTC, 17:00 in São Paulo
+        let today = Utc::now().date_naive();
+        let days = 7 - (i64::from(today.weekday().num_days_from_monday()) + 6) % 7;
+        let tuesday = today + Duration::days(days);
+        let clock = Arc::new(turnkey_core::MockClock::new(
+            Utc.from_utc_datetime(&tuesday.and_hms_opt(20, 0, 0).unwrap()),
+        ));
+        let mut validator = OfflineValidator::new(db.pool().clone()).with_clock(clock.clone());
+        let request = create_access_request("4343434343", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::OUTSIDE_SCHEDULE
+        );
+
+        let mut validator =
+            validator.with_schedule_offset(FixedOffset::west_opt(3 * 3600).unwrap());
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+
+        schedules.unassign_user(user_id).await.unwrap();
+        clock.advance(std::time::Duration::from_secs(6 * 3600));
+        let exit = create_access_request("4343434343", AccessDirection::Exit);
+        assert!(validator.validate(&exit).await.unwrap().is_grant());
+    }
+
+    #[tokio::test]
+    async fn test_holiday_closure_spares_exempt_users_and_exceptions() {
+        use crate::models::{Holiday, TimeInterval, WeeklySchedule};
+        use chrono::TimeZone;
+
+        let db = setup_test_db().await;
+        let guard = create_test_user(&db, "EMP044").await;
+        create_test_card(&db, "4444000044", "EMP044", guard).await;
+        let shift = create_test_user(&db, "EMP045").await;
+        create_test_card(&db, "4545454545", "EMP045", shift).await;
+        let clerk = create_test_user(&db, "EMP046").await;
+        create_test_card(&db, "4646464646", "EMP046", clerk).await;
+
+        let today = Utc::now().date_naive();
+        let clock = Arc::new(turnkey_core::MockClock::new(
+            Utc.from_utc_datetime(&today.and_hms_opt(12, 0, 0).unwrap()),
+        ));
+        let holidays = SqliteHolidayRepository::new(db.pool().clone());
+        holidays
+            .import(&[Holiday::new(today, "Feriado municipal")])
+            .await
+            .unwrap();
+        holidays.set_exempt(guard, true).await.unwrap();
+
+        let schedules = SqliteScheduleRepository::new(db.pool().clone());
+        let holiday_shift = WeeklySchedule::new("Plantao")
+            .with_exception(today, vec![TimeInterval::parse("08:00-14:00").unwrap()]);
+        let schedule_id = schedules.create(&holiday_shift).await.unwrap();
+        schedules.assign_user(shift, schedule_id).await.unwrap();
+
+        let entry = |card: &str| create_access_request(card, AccessDirection::Entry);
+        let mut open = OfflineValidator::new(db.pool().clone()).with_clock(clock.clone());
+        assert!(
+            open.validate(&entry("4646464646"))
+                .await
+                .unwrap()
+                .is_grant()
+        );
+
+        let mut closed = OfflineValidator::new(db.pool().clone())
+            .with_clock(clock.clone())
+            .with_holiday_policy(HolidayPolicy::Closed);
+        let response = closed.validate(&entry("4646464646")).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::HOLIDAY_CLOSED);
+        assert!(
+            closed
+                .validate(&entry("4444000044"))
+                .await
+                .unwrap()
+                .is_grant()
+        );
+        assert!(
+            closed
+                .validate(&entry("4545454545"))
+                .await
+                .unwrap()
+                .is_grant()
+        );
+
+        holidays.delete(today).await.unwrap();
+        let exit = create_access_request("4646464646", AccessDirection::Exit);
+        assert!(closed.validate(&exit).await.unwrap().is_grant());
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock_decides_card_validity() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP041").await;
+        create_test_card(&db, "4141414141", "EMP041", user_id).await;
+
+        // The card ends in 30 days; the user in 30 days as well
+        let clock = Arc::new(turnkey_core::MockClock::new(
+            Utc::now() + Duration::days(31),
+        ));
+        let mut validator = OfflineValidator::new(db.pool().clone()).with_clock(clock.clone());
+        let request = create_access_request("4141414141", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(
+            response.display_message(),
+            "Cartao fora do periodo de validade"
+        );
+
+        clock.set(Utc::now());
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+    }
+
+    /// Clock that cancels a validation the first time it is read
+    #[derive(Debug)]
+    struct CancellingClock(CancellationToken);
+
+    impl turnkey_core::Clock for CancellingClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0.cancel();
+            Utc::now()
+        }
+
+        fn instant(&self) -> Instant {
+            Instant::now()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_validation_writes_no_log() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP042").await;
+        create_test_card(&db, "4242424242", "EMP042", user_id).await;
+        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
+
+        // Cancelled after the card lookup, while the validation is under way
+        let cancel = CancellationToken::new();
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_clock(Arc::new(CancellingClock(cancel.clone())));
+        let request = create_access_request("4242424242", AccessDirection::Entry);
+
+        let result = validator.validate_cancellable(&request, cancel).await;
+        assert!(matches!(result, Err(StorageError::Cancelled)));
+        assert!(
+            log_repo
+                .find_by_card_number("4242424242", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // The cancellation does not outlive the call
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+        assert_eq!(
+            log_repo
+                .find_by_card_number("4242424242", 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validation_cancelled_up_front_records_nothing() {
+        let db = setup_test_db().await;
+        let mut validator = OfflineValidator::new(db.pool().clone()).with_learning_mode(true);
+        let request = create_access_request("4343434343", AccessDirection::Entry);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = validator.validate_cancellable(&request, cancel).await;
+        assert!(matches!(result, Err(StorageError::Cancelled)));
+
+        let pending = SqlitePendingCardRepository::new(db.pool().clone())
+            .find_all()
+            .await
+            .unwrap();
+        assert!(pending.is_empty());
+    }
+
+    async fn create_open_exception(db: &Database) -> (SqliteAccessExceptionRepository, i64) {
+        let repo = SqliteAccessExceptionRepository::new(db.pool().clone());
+        let exception = AccessException::new(
+            "Evento",
+            Utc::now() - Duration::hours(1),
+            Utc::now() + Duration::hours(1),
+        );
+        let id = repo.create(&exception).await.unwrap();
+        (repo, id)
+    }
+
+    #[tokio::test]
+    async fn test_validate_exception_grants_unregistered_card() {
+        let db = setup_test_db().await;
+        let (exceptions, id) = create_open_exception(&db).await;
+        exceptions.add_card(id, "7070707070").await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("7070707070", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_grant());
+        assert_eq!(response.display_message(), "Acesso liberado - evento");
+
+        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let logs = log_repo
+            .find_by_card_number("7070707070", 10)
+            .await
+            .unwrap();
+        assert!(logs[0].granted);
+        assert_eq!(logs[0].user_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_exception_overrides_expired_card() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP020").await;
+
+        let card = Card {
+            id: 0,
+            numero_cartao: "2020202020".to_string(),
+            matricula: "EMP020".to_string(),
+            user_id,
+            validade_inicio: Some(Utc::now() - Duration::days(60)),
+            validade_fim: Some(Utc::now() - Duration::days(1)), // Expired
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteCardRepository::new(db.pool().clone())
+            .create(&card)
+            .await
+            .unwrap();
+
+        let (exceptions, id) = create_open_exception(&db).await;
+        exceptions.add_user(id, "EMP020").await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("2020202020", AccessDirection::Exit);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_grant());
+        assert_eq!(response.display_message(), "Acesso liberado - evento");
+    }
+
+    #[tokio::test]
+    async fn test_validate_exception_keeps_anti_passback_and_quota() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP022").await;
+        let card = Card {
+            id: 0,
+            numero_cartao: "2222222220".to_string(),
+            matricula: "EMP022".to_string(),
+            user_id,
+            validade_inicio: Some(Utc::now() - Duration::days(60)),
+            validade_fim: Some(Utc::now() - Duration::days(1)), // Expired
+            ativo: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteCardRepository::new(db.pool().clone())
+            .create(&card)
+            .await
+            .unwrap();
+        SqliteQuotaRepository::new(db.pool().clone())
+            .set_user_quota(user_id, 2)
+            .await
+            .unwrap();
+
+        let (exceptions, id) = create_open_exception(&db).await;
+        exceptions.add_user(id, "EMP022").await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let entry = create_access_request("2222222220", AccessDirection::Entry);
+        let exit = create_access_request("2222222220", AccessDirection::Exit);
+
+        let response = validator.validate(&entry).await.unwrap();
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::ACCESS_GRANTED_EXCEPTION
+        );
+
+        // The exception excuses the expired card, not a second entry
+        let response = validator.validate(&entry).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), DisplayMessages::ANTI_PASSBACK);
+
+        // Nor an entry beyond the daily quota
+        assert!(validator.validate(&exit).await.unwrap().is_grant());
+        assert!(validator.validate(&entry).await.unwrap().is_grant());
+        assert!(validator.validate(&exit).await.unwrap().is_grant());
+        let response = validator.validate(&entry).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::DAILY_QUOTA_EXCEEDED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_exception_overrides_schedule() {
+        use crate::models::WeeklySchedule;
+
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP023").await;
+        create_test_card(&db, "2323232320", "EMP023", user_id).await;
+        let schedules = SqliteScheduleRepository::new(db.pool().clone());
+        let closed = schedules
+            .create(&WeeklySchedule::new("Fechado"))
+            .await
+            .unwrap();
+        schedules.assign_user(user_id, closed).await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("2323232320", AccessDirection::Entry);
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::OUTSIDE_SCHEDULE
+        );
+
+        let (exceptions, id) = create_open_exception(&db).await;
+        exceptions.add_card(id, "2323232320").await.unwrap();
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_grant());
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::ACCESS_GRANTED_EXCEPTION
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_exception_does_not_override_inactive_card() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP021").await;
+
+        let card = Card {
+            id: 0,
+            numero_cartao: "2121212121".to_string(),
+            matricula: "EMP021".to_string(),
+            user_id,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        SqliteCardRepository::new(db.pool().clone())
+            .create(&card)
+            .await
+            .unwrap();
+
+        let (exceptions, id) = create_open_exception(&db).await;
+        exceptions.add_card(id, "2121212121").await.unwrap();
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("2121212121", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), "Cartao inativo");
+    }
+
+    #[tokio::test]
+    async fn test_validate_exception_restricted_to_other_device() {
+        let db = setup_test_db().await;
+        let (exceptions, id) = create_open_exception(&db).await;
+        exceptions.add_card(id, "7171717171").await.unwrap();
+        exceptions.add_device(id, 2).await.unwrap();
+
+        let mut validator =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(1).unwrap());
+        let request = create_access_request("7171717171", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), "Cartao nao cadastrado");
+    }
+
+    #[tokio::test]
+    async fn test_validate_user_inactive() {
+        let db = setup_test_db().await;
+
+        // Create inactive user
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "Inactive User".to_string(),
+            matricula: "EMP005".to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: false, // Inactive
+            allow_card: true,
+            allow_bio: false,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let user_repo = SqliteUserRepository::new(db.pool().clone());
+        let user_id = user_repo.create(&user).await.unwrap();
+
+        create_test_card(&db, "5555555555", "EMP005", user_id).await;
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("5555555555", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(response.display_message(), "Usuario inativo");
+    }
+
+    #[tokio::test]
+    async fn test_validate_user_card_not_allowed() {
+        let db = setup_test_db().await;
+
+        // Create user with card access disabled
+        let user = User {
+            id: 0,
+            pis: None,
+            nome: "No Card Access".to_string(),
+            matricula: "EMP006".to_string(),
+            cpf: None,
+            validade_inicio: None,
+            validade_fim: None,
+            ativo: true,
+            allow_card: false, // Card access not allowed
+            allow_bio: true,
+            allow_keypad: false,
+            codigo: None,
+            empresa: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let user_repo = SqliteUserRepository::new(db.pool().clone());
+        let user_id = user_repo.create(&user).await.unwrap();
+
+        create_test_card(&db, "6666666666", "EMP006", user_id).await;
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("6666666666", AccessDirection::Entry);
+
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            "Acesso por cartao nao permitido"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_device_card_reader_disabled() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP020").await;
+        create_test_card(&db, "2020202020", "EMP020", user_id).await;
+
+        let devices = SqliteDeviceRepository::new(db.pool().clone());
+        devices
+            .set_access_methods(15, false, true, false)
+            .await
+            .unwrap();
+
+        let request = create_access_request("2020202020", AccessDirection::Entry);
+
+        // Same user and card are denied at the gate without a card reader
+        let mut gate =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(15).unwrap());
+        let response = gate.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::DEVICE_CARD_DISABLED
+        );
+
+        // ...and granted on a device without restrictions
+        let mut lobby =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(16).unwrap());
+        assert!(lobby.validate(&request).await.unwrap().is_grant());
+    }
+
+    /// Scanner stand-in whose finger matches one template byte for byte
+    struct FixedFinger(Vec<u8>);
+
+    impl TemplateMatcher for FixedFinger {
+        async fn matches(&mut self, template: &[u8]) -> StorageResult<bool> {
+            Ok(template == self.0.as_slice())
+        }
+    }
+
+    /// Card + biometric device 15 and a user with one enrolled finger
+    async fn setup_card_plus_biometric(db: &Database, matricula: &str, card: &str) {
+        let user_id = create_test_user(db, matricula).await;
+        create_test_card(db, card, matricula, user_id).await;
+        sqlx::query!(
+            "UPDATE users SET allow_bio = 1 WHERE matricula = ?",
+            matricula
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+        SqliteBiometricTemplateRepository::new(db.pool().clone())
+            .save(matricula, 1, &[7u8; 600])
+            .await
+            .unwrap();
+        SqliteDeviceRepository::new(db.pool().clone())
+            .set_verification_mode(15, VerificationMode::CardPlusBiometric)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_card_plus_biometric_grants_after_matching_finger() {
+        let db = setup_test_db().await;
+        setup_card_plus_biometric(&db, "EMP030", "3030303030").await;
+
+        let mut validator =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(15).unwrap());
+        let request = create_access_request("3030303030", AccessDirection::Entry);
+
+        // The card alone never opens the door
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::BIOMETRIC_REQUIRED
+        );
+
+        let CardVerification::AwaitingBiometric(pending) =
+            validator.validate_card(&request).await.unwrap()
+        else {
+            panic!("card should wait for the fingerprint");
+        };
+        assert_eq!(pending.matricula(), "EMP030");
+        assert_eq!(pending.templates().len(), 1);
+
+        let response = validator
+            .verify_biometric(&request, &pending, &mut FixedFinger(vec![8u8; 600]))
+            .await
+            .unwrap();
+        assert!(response.is_deny());
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::BIOMETRIC_MISMATCH
+        );
+
+        let response = validator
+            .verify_biometric(&request, &pending, &mut FixedFinger(vec![7u8; 600]))
+            .await
+            .unwrap();
+        assert!(response.is_grant());
+
+        let logs = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number("3030303030", 10)
+            .await
+            .unwrap();
+        assert_eq!(logs.iter().filter(|log| log.granted).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_card_plus_biometric_denials_name_the_factor() {
+        let db = setup_test_db().await;
+        setup_card_plus_biometric(&db, "EMP031", "3131313131").await;
+        let user_id = create_test_user(&db, "EMP032").await;
+        create_test_card(&db, "3232323232", "EMP032", user_id).await;
+
+        let validator =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(15).unwrap());
+        let message = |verification: CardVerification| match verification {
+            CardVerification::Complete(response) => response.display_message().to_string(),
+            CardVerification::AwaitingBiometric(_) => panic!("expected a final decision"),
+        };
+
+        // Card factor
+        let unknown = create_access_request("9999999999", AccessDirection::Entry);
+        assert_eq!(
+            message(validator.validate_card(&unknown).await.unwrap()),
+            DisplayMessages::CARD_NOT_FOUND
+        );
+
+        // Fingerprint factor: nothing enrolled for this card's owner
+        let unenrolled = create_access_request("3232323232", AccessDirection::Entry);
+        assert_eq!(
+            message(validator.validate_card(&unenrolled).await.unwrap()),
+            DisplayMessages::BIOMETRIC_NOT_ENROLLED
+        );
+
+        // A finger alone is not enough
+        let finger_only = AccessRequest::new(
+            "3131313131".to_string(),
+            HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            AccessDirection::Entry,
+            turnkey_core::ReaderType::Biometric,
+        )
+        .unwrap();
+        assert_eq!(
+            message(validator.validate_card(&finger_only).await.unwrap()),
+            DisplayMessages::CARD_REQUIRED
+        );
+
+        // Card accepted, finger never presented
+        let request = create_access_request("3131313131", AccessDirection::Entry);
+        let CardVerification::AwaitingBiometric(pending) =
+            validator.validate_card(&request).await.unwrap()
+        else {
+            panic!("card should wait for the fingerprint");
+        };
+        let response = validator
+            .biometric_timeout(&request, &pending)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.display_message(),
+            DisplayMessages::BIOMETRIC_TIMEOUT
+        );
+
+        let logs = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number("3131313131", 10)
+            .await
+            .unwrap();
+        assert!(logs.iter().all(|log| !log.granted));
+        assert!(
+            logs.iter()
+                .any(|log| log.display_message.as_deref()
+                    == Some(DisplayMessages::BIOMETRIC_TIMEOUT))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_logs_granted_access() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP007").await;
+        create_test_card(&db, "7777777777", "EMP007", user_id).await;
+
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("7777777777", AccessDirection::Entry);
+
+        validator.validate(&request).await.unwrap();
+
+        // Verify log was created
+        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let logs = log_repo
+            .find_by_card_number("7777777777", 10)
+            .await
+            .unwrap();
+
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].granted);
+        assert_eq!(logs[0].display_message.as_deref(), Some("Acesso liberado"));
     }
 
-    /// Single validation attempt without retry
-    ///
-    /// Performs one complete validation cycle:
-    /// 1. Connect if not connected
-    /// 2. Convert request to message
-    /// 3. Send message
-    /// 4. Receive response
-    /// 5. Convert message to response
-    async fn validate_once(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
-        // Step 1: Connect if not connected
-        if !self.tcp_client.is_connected() {
-            self.tcp_client.connect().await.map_err(|e| {
-                crate::error::StorageError::NetworkError(format!("Connection failed: {}", e))
-            })?;
-        }
+    #[tokio::test]
+    async fn test_validate_logs_denied_access() {
+        let db = setup_test_db().await;
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        let request = create_access_request("8888888888", AccessDirection::Entry);
 
-        // Step 2: Convert AccessRequest → Message
-        let message = Self::request_to_message(request, self.device_id)?;
+        validator.validate(&request).await.unwrap();
 
-        // Step 3: Send request
-        self.tcp_client
-            .send(message)
+        // Verify log was created
+        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let logs = log_repo
+            .find_by_card_number("8888888888", 10)
             .await
-            .map_err(|e| crate::error::StorageError::NetworkError(format!("Send failed: {}", e)))?;
-
-        // Step 4: Receive response (with timeout from TcpClient)
-        let response_msg = self.tcp_client.recv().await.map_err(|e| {
-            crate::error::StorageError::NetworkError(format!("Receive failed: {}", e))
-        })?;
+            .unwrap();
 
-        // Step 5: Convert Message → AccessResponse
-        Self::message_to_response(&response_msg)
+        assert_eq!(logs.len(), 1);
+        assert!(!logs[0].granted);
+        assert_eq!(
+            logs[0].display_message.as_deref(),
+            Some("Cartao nao cadastrado")
+        );
     }
 
-    /// Create a FieldData with improved error messages
-    ///
-    /// Helper method to create FieldData with context-specific error messages.
-    ///
-    /// # Arguments
-    ///
-    /// * `value` - The value to convert to FieldData
-    /// * `context` - Description of the field for error messages
-    ///
-    /// # Errors
-    ///
-    /// Returns `ProtocolError` if the value contains reserved delimiters
-    fn field_data(value: impl ToString, context: &str) -> StorageResult<FieldData> {
-        FieldData::new(value.to_string())
-            .map_err(|e| StorageError::ProtocolError(format!("{}: {}", context, e)))
-    }
+    #[tokio::test]
+    async fn test_card_filter_skips_unknown_cards() {
+        use crate::card_filter::{CardFilter, CardFilterConfig};
 
-    /// Convert AccessRequest to Henry protocol Message
-    ///
-    /// Builds a Henry protocol access request message (command 000+0)
-    /// with the following fields:
-    /// - Field 1: Card number
-    /// - Field 2: Timestamp
-    /// - Field 3: Direction (0=undefined, 1=entry, 2=exit)
-    /// - Field 4: Reader type (0=RFID, 1=biometric)
-    fn request_to_message(request: &AccessRequest, device_id: DeviceId) -> StorageResult<Message> {
-        let direction_value = match request.direction() {
-            turnkey_core::AccessDirection::Undefined => "0",
-            turnkey_core::AccessDirection::Entry => "1",
-            turnkey_core::AccessDirection::Exit => "2",
-        };
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP018").await;
+        create_test_card(&db, "1818181818", "EMP018", user_id).await;
 
-        let reader_value = match request.reader_type() {
-            turnkey_core::ReaderType::Rfid => "0",
-            turnkey_core::ReaderType::Biometric => "1",
-        };
+        let filter = Arc::new(
+            CardFilter::build(db.pool().clone(), CardFilterConfig::default())
+                .await
+                .unwrap(),
+        );
+        let mut validator =
+            OfflineValidator::new(db.pool().clone()).with_card_filter(filter.clone());
 
-        MessageBuilder::new(device_id, CommandCode::AccessRequest)
-            .field(Self::field_data(
-                request.card_number(),
-                "Invalid card number",
-            )?)
-            .field(Self::field_data(request.timestamp(), "Invalid timestamp")?)
-            .field(Self::field_data(direction_value, "Invalid direction")?)
-            .field(Self::field_data(reader_value, "Invalid reader type")?)
-            .build()
-            .map_err(|e| StorageError::ProtocolError(format!("Failed to build message: {}", e)))
+        let request = create_access_request("8181818181", AccessDirection::Entry);
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::CARD_NOT_FOUND);
+
+        let request = create_access_request("1818181818", AccessDirection::Entry);
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+
+        let metrics = filter.metrics();
+        assert_eq!(metrics.lookups, 2);
+        assert_eq!(metrics.rejected, 1);
+        assert_eq!(metrics.false_positives, 0);
     }
 
-    /// Convert Henry protocol Message to AccessResponse
-    ///
-    /// Parses the response message and creates an appropriate
-    /// AccessResponse (grant or deny).
-    ///
-    /// Expected response format:
-    /// - Grant entry: 00+5]seconds]message
-    /// - Grant exit: 00+6]seconds]message
-    /// - Grant both: 00+1]seconds]message
-    /// - Deny: 00+30]seconds]message
-    fn message_to_response(message: &Message) -> StorageResult<AccessResponse> {
-        // Check command code to determine grant/deny
-        let is_grant = matches!(
-            message.command,
-            CommandCode::GrantBoth | CommandCode::GrantEntry | CommandCode::GrantExit
+    #[tokio::test]
+    async fn test_card_filter_finds_cards_imported_from_bundle() {
+        use crate::bundle::{ExportOptions, ImportMode, export_bundle, import_bundle};
+        use crate::card_filter::{CardFilter, CardFilterConfig};
+
+        let source = setup_test_db().await;
+        let user_id = create_test_user(&source, "EMP044").await;
+        create_test_card(&source, "4444444440", "EMP044", user_id).await;
+        let mut archive = Vec::new();
+        export_bundle(source.pool(), &mut archive, &ExportOptions::default())
+            .await
+            .unwrap();
+
+        let db = setup_test_db().await;
+        let filter = Arc::new(
+            CardFilter::build(db.pool().clone(), CardFilterConfig::default())
+                .await
+                .unwrap(),
         );
+        let mut validator =
+            OfflineValidator::new(db.pool().clone()).with_card_filter(filter.clone());
+        let request = create_access_request("4444444440", AccessDirection::Entry);
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::CARD_NOT_FOUND);
 
-        let is_deny = matches!(message.command, CommandCode::DenyAccess);
+        // Imported without the filter knowing, and still found
+        import_bundle(db.pool(), archive.as_slice(), ImportMode::Merge)
+            .await
+            .unwrap();
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+        assert_eq!(filter.metrics().rejected, 1);
+    }
 
-        // Extract display message from fields
-        // Typical format: field[0]=seconds, field[1]=message
-        let display_message = if message.fields.len() >= 2 {
-            message.fields[1].to_string()
-        } else if message.fields.len() == 1 {
-            message.fields[0].to_string()
-        } else {
-            "Unknown".to_string()
-        };
+    #[tokio::test]
+    async fn test_coercion_detector_alarms_and_locks_card() {
+        use crate::coercion::{CoercionConfig, CoercionDetector};
+        use crate::events::{EventBus, SubscriptionOptions};
 
-        if is_grant {
-            match message.command {
-                CommandCode::GrantEntry => Ok(AccessResponse::grant_entry(display_message)),
-                CommandCode::GrantExit => Ok(AccessResponse::grant_exit(display_message)),
-                CommandCode::GrantBoth => Ok(AccessResponse::grant_both(display_message)),
-                _ => Ok(AccessResponse::deny(display_message)),
-            }
-        } else if is_deny {
-            Ok(AccessResponse::deny(display_message))
-        } else {
-            Err(crate::error::StorageError::ProtocolError(format!(
-                "Unexpected command code in response: {:?}",
-                message.command
-            )))
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP019").await;
+        create_test_card(&db, "1919191919", "EMP019", user_id).await;
+
+        let alarms = EventBus::new(4);
+        let mut desk = alarms.subscribe(SubscriptionOptions::new("desk")).await;
+        let config = CoercionConfig::new(3, std::time::Duration::from_secs(60))
+            .lockout(Some(std::time::Duration::from_secs(300)));
+        let detector = Arc::new(CoercionDetector::new(config).with_alarm_bus(alarms));
+        let clock = Arc::new(turnkey_core::MockClock::new(Utc::now()));
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(15).unwrap())
+            .with_clock(clock.clone())
+            .with_coercion_detector(detector.clone());
+
+        // An inactive card is denied on every request
+        sqlx::query!("UPDATE cards SET ativo = 0 WHERE numero_cartao = '1919191919'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let request = create_access_request("1919191919", AccessDirection::Entry);
+        for _ in 0..3 {
+            let response = validator.validate(&request).await.unwrap();
+            assert_eq!(response.display_message(), DisplayMessages::CARD_INACTIVE);
         }
-    }
-}
 
-/// Implement AccessValidator trait for OnlineValidator
-impl AccessValidator for OnlineValidator {
-    async fn validate(&mut self, request: &AccessRequest) -> StorageResult<AccessResponse> {
-        self.validate_with_retry(request).await
-    }
-}
+        let alarm = desk.try_recv().expect("third denial raises an alarm");
+        assert_eq!(alarm.card_number, "1919191919");
+        assert_eq!(alarm.device_id, Some(15));
+        assert_eq!(alarm.denials, 3);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::connection::Database;
-    use crate::models::User;
-    use chrono::Duration;
-    use turnkey_core::{AccessDirection, HenryTimestamp};
+        // Locked even once the card is active again, until the lockout ends
+        sqlx::query!("UPDATE cards SET ativo = 1 WHERE numero_cartao = '1919191919'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let response = validator.validate(&request).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::CARD_LOCKED);
+        assert!(desk.try_recv().is_none());
 
-    async fn setup_test_db() -> Database {
-        Database::in_memory().await.unwrap()
+        clock.advance(std::time::Duration::from_secs(301));
+        assert!(validator.validate(&request).await.unwrap().is_grant());
     }
 
-    async fn create_test_user(db: &Database, matricula: &str) -> i64 {
-        let user = User {
-            id: 0,
-            pis: None,
-            nome: "Test User".to_string(),
-            matricula: matricula.to_string(),
-            cpf: None,
-            validade_inicio: Some(Utc::now() - Duration::days(1)),
-            validade_fim: Some(Utc::now() + Duration::days(30)),
-            ativo: true,
-            allow_card: true,
-            allow_bio: false,
-            allow_keypad: false,
-            codigo: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+    #[tokio::test]
+    async fn test_validate_queues_notification_with_log() {
+        use turnkey_protocol::commands::events::EventChunk;
 
-        let repo = SqliteUserRepository::new(db.pool().clone());
-        repo.create(&user).await.unwrap()
-    }
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP017").await;
+        create_test_card(&db, "1717171717", "EMP017", user_id).await;
 
-    async fn create_test_card(db: &Database, numero: &str, matricula: &str, user_id: i64) {
-        let card = Card {
-            id: 0,
-            numero_cartao: numero.to_string(),
-            matricula: matricula.to_string(),
-            user_id,
-            validade_inicio: Some(Utc::now() - Duration::days(1)),
-            validade_fim: Some(Utc::now() + Duration::days(30)),
-            ativo: true,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+        let outbox = Outbox::new(db.pool().clone());
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(15).unwrap())
+            .with_outbox(outbox.clone());
+        let request = create_access_request("1717171717", AccessDirection::Entry);
 
-        let repo = SqliteCardRepository::new(db.pool().clone());
-        repo.create(&card).await.unwrap();
-    }
+        // Granted, then denied by anti-passback
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+        assert!(!validator.validate(&request).await.unwrap().is_grant());
 
-    fn create_access_request(card_number: &str, direction: AccessDirection) -> AccessRequest {
-        let timestamp = HenryTimestamp::parse("10/05/2025 12:46:06").unwrap();
-        AccessRequest::new(
-            card_number.to_string(),
-            timestamp,
-            direction,
-            turnkey_core::ReaderType::Rfid,
-        )
-        .unwrap()
+        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let mut logs = log_repo
+            .find_by_card_number("1717171717", 10)
+            .await
+            .unwrap();
+        logs.sort_by_key(|log| log.id);
+
+        let pending = outbox.pending(10).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        for ((entry, log), nsr) in pending.iter().zip(&logs).zip(1..) {
+            assert_eq!(entry.access_log_id, Some(log.id));
+            assert_eq!(entry.nsr, nsr);
+            assert_eq!(log.nsr, Some(nsr));
+            let chunk = EventChunk::parse(&entry.message).unwrap();
+            assert_eq!(entry.message.device_id.as_u8(), 15);
+            assert_eq!(chunk.events[0].id, nsr);
+            assert_eq!(chunk.events[0].granted, log.granted);
+        }
     }
 
     #[tokio::test]
-    async fn test_validate_grant_entry() {
+    async fn test_validate_records_device_clock() {
         let db = setup_test_db().await;
-        let user_id = create_test_user(&db, "EMP001").await;
-        create_test_card(&db, "1234567890", "EMP001", user_id).await;
+        let user_id = create_test_user(&db, "EMP010").await;
+        create_test_card(&db, "1010101010", "EMP010", user_id).await;
 
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("1234567890", AccessDirection::Entry);
+        let mut validator =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(15).unwrap());
+        let request = create_access_request("1010101010", AccessDirection::Entry);
 
-        let response = validator.validate(&request).await.unwrap();
-        assert!(response.is_grant());
-        assert_eq!(response.display_message(), "Acesso liberado");
+        validator.validate(&request).await.unwrap();
+
+        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
+        let logs = log_repo
+            .find_by_card_number("1010101010", 10)
+            .await
+            .unwrap();
+
+        let expected = request.timestamp().inner().with_timezone(&Utc);
+        assert_eq!(logs[0].device_id, Some(15));
+        assert_eq!(logs[0].nsr, Some(1));
+        assert_eq!(logs[0].device_timestamp, Some(expected));
+        assert!(logs[0].timestamp > expected);
     }
 
     #[tokio::test]
-    async fn test_validate_grant_exit() {
+    async fn test_validate_records_correlation_id() {
         let db = setup_test_db().await;
-        let user_id = create_test_user(&db, "EMP002").await;
-        create_test_card(&db, "2222222222", "EMP002", user_id).await;
+        let user_id = create_test_user(&db, "EMP012").await;
+        create_test_card(&db, "1212121212", "EMP012", user_id).await;
 
         let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("2222222222", AccessDirection::Exit);
+        let correlation_id = turnkey_core::CorrelationId::new();
+        let request = create_access_request("1212121212", AccessDirection::Entry)
+            .with_correlation_id(correlation_id);
 
         let response = validator.validate(&request).await.unwrap();
         assert!(response.is_grant());
+
+        let logs = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_correlation_id(&correlation_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].card_number, "1212121212");
     }
 
     #[tokio::test]
-    async fn test_validate_card_not_found() {
+    async fn test_validate_raises_clock_drift_alert() {
         let db = setup_test_db().await;
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("9999999999", AccessDirection::Entry);
+        let (alert_tx, mut alert_rx) = mpsc::channel(4);
 
-        let response = validator.validate(&request).await.unwrap();
-        assert!(response.is_deny());
-        assert_eq!(response.display_message(), "Cartao nao cadastrado");
-    }
+        // Request timestamp is fixed in 2025, far behind the server clock
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(15).unwrap())
+            .with_clock_drift_alerts(Duration::seconds(60), alert_tx);
+        let request = create_access_request("1111111111", AccessDirection::Entry);
 
-    #[tokio::test]
-    async fn test_validate_card_inactive() {
-        let db = setup_test_db().await;
-        let user_id = create_test_user(&db, "EMP003").await;
+        validator.validate(&request).await.unwrap();
 
-        // Create inactive card
-        let card = Card {
-            id: 0,
-            numero_cartao: "3333333333".to_string(),
-            matricula: "EMP003".to_string(),
-            user_id,
-            validade_inicio: None,
-            validade_fim: None,
-            ativo: false, // Inactive
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+        let alert = alert_rx.try_recv().unwrap();
+        assert_eq!(alert.device_id, 15);
+        assert!(alert.drift() < -Duration::seconds(60));
+        assert_eq!(alert.threshold_ms, 60_000);
+
+        // A device in sync does not raise an alert
+        let in_sync = AccessRequest::new(
+            "1111111111".to_string(),
+            HenryTimestamp::now(),
+            AccessDirection::Entry,
+            turnkey_core::ReaderType::Rfid,
+        )
+        .unwrap();
+        validator.validate(&in_sync).await.unwrap();
+        assert!(alert_rx.try_recv().is_err());
+    }
 
-        let repo = SqliteCardRepository::new(db.pool().clone());
-        repo.create(&card).await.unwrap();
+    #[tokio::test]
+    async fn test_validate_normalizes_card_number() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP009").await;
+        create_test_card(&db, "ABCDEF1234", "EMP009", user_id).await;
 
         let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("3333333333", AccessDirection::Entry);
+        // Request with lowercase card number
+        let request = create_access_request("abcdef1234", AccessDirection::Entry);
 
         let response = validator.validate(&request).await.unwrap();
-        assert!(response.is_deny());
-        assert_eq!(response.display_message(), "Cartao inativo");
+        assert!(response.is_grant());
     }
 
     #[tokio::test]
-    async fn test_validate_card_expired() {
+    async fn test_validate_warns_on_stale_data() {
         let db = setup_test_db().await;
-        let user_id = create_test_user(&db, "EMP004").await;
+        let user_id = create_test_user(&db, "EMP022").await;
+        create_test_card(&db, "2222222222", "EMP022", user_id).await;
+        let sync = SqliteSyncStateRepository::new(db.pool().clone());
+        sync.record_success("bundle", Utc::now() - Duration::days(5))
+            .await
+            .unwrap();
+        let (warning_tx, mut warning_rx) = mpsc::channel(4);
 
-        // Create expired card
-        let card = Card {
-            id: 0,
-            numero_cartao: "4444444444".to_string(),
-            matricula: "EMP004".to_string(),
-            user_id,
-            validade_inicio: Some(Utc::now() - Duration::days(60)),
-            validade_fim: Some(Utc::now() - Duration::days(1)), // Expired
-            ativo: true,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(15).unwrap())
+            .with_stale_data_threshold(Duration::days(3))
+            .with_stale_data_warnings(warning_tx);
+        let request = create_access_request("2222222222", AccessDirection::Entry);
 
-        let repo = SqliteCardRepository::new(db.pool().clone());
-        repo.create(&card).await.unwrap();
+        // Devices not flagged keep validating on the data they have
+        let response = validator.validate(&request).await.unwrap();
+        assert!(response.is_grant());
+
+        let warning = warning_rx.try_recv().unwrap();
+        assert_eq!(warning.device_id, Some(15));
+        assert_eq!(warning.card_number, "2222222222");
+        assert!(warning.age().unwrap() >= Duration::days(5));
+        assert_eq!(warning.threshold_secs, Duration::days(3).num_seconds());
+        assert!(!warning.denied);
+
+        // Fresh data raises no warning
+        sync.record_success("server", Utc::now()).await.unwrap();
+        let exit = create_access_request("2222222222", AccessDirection::Exit);
+        assert!(validator.validate(&exit).await.unwrap().is_grant());
+        assert!(warning_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_enforces_daily_quota() {
+        let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP024").await;
+        create_test_card(&db, "2424242424", "EMP024", user_id).await;
+        let quotas = SqliteQuotaRepository::new(db.pool().clone());
+        quotas.set_user_quota(user_id, 2).await.unwrap();
 
         let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("4444444444", AccessDirection::Entry);
+        let entry = create_access_request("2424242424", AccessDirection::Entry);
+        let exit = create_access_request("2424242424", AccessDirection::Exit);
 
-        let response = validator.validate(&request).await.unwrap();
+        // Exits do not use up the quota
+        for request in [&entry, &exit, &entry, &exit] {
+            assert!(validator.validate(request).await.unwrap().is_grant());
+        }
+
+        let response = validator.validate(&entry).await.unwrap();
         assert!(response.is_deny());
         assert_eq!(
             response.display_message(),
-            "Cartao fora do periodo de validade"
+            DisplayMessages::DAILY_QUOTA_EXCEEDED
         );
+
+        // The user quota wins over a larger company quota
+        sqlx::query!(
+            "UPDATE users SET empresa = 'Academia' WHERE id = ?",
+            user_id
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+        quotas.set_company_quota("Academia", 3).await.unwrap();
+        assert!(validator.validate(&entry).await.unwrap().is_deny());
+
+        quotas.remove_user_quota(user_id).await.unwrap();
+        assert!(validator.validate(&entry).await.unwrap().is_grant());
+        assert!(validator.validate(&exit).await.unwrap().is_grant());
+        assert!(validator.validate(&entry).await.unwrap().is_deny());
+
+        // A day boundary after the earlier passages resets the count
+        let fresh_day = QuotaDay::new(Utc::now().time(), chrono::FixedOffset::east_opt(0).unwrap());
+        let mut next_day = OfflineValidator::new(db.pool().clone()).with_quota_day(fresh_day);
+        assert!(next_day.validate(&entry).await.unwrap().is_grant());
     }
 
     #[tokio::test]
-    async fn test_validate_user_inactive() {
+    async fn test_grant_shows_balance_on_enabled_device() {
         let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP033").await;
+        create_test_card(&db, "3333333333", "EMP033", user_id).await;
+        SqliteQuotaRepository::new(db.pool().clone())
+            .set_user_quota(user_id, 5)
+            .await
+            .unwrap();
+        let entry = create_access_request("3333333333", AccessDirection::Entry);
+        let exit = create_access_request("3333333333", AccessDirection::Exit);
 
-        // Create inactive user
-        let user = User {
-            id: 0,
-            pis: None,
-            nome: "Inactive User".to_string(),
-            matricula: "EMP005".to_string(),
-            cpf: None,
-            validade_inicio: None,
-            validade_fim: None,
-            ativo: false, // Inactive
-            allow_card: true,
-            allow_bio: false,
-            allow_keypad: false,
-            codigo: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+        // Balance display is off by default
+        let mut validator =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(15).unwrap());
+        let response = validator.validate(&entry).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::ACCESS_GRANTED);
 
-        let user_repo = SqliteUserRepository::new(db.pool().clone());
-        let user_id = user_repo.create(&user).await.unwrap();
+        SqliteDeviceRepository::new(db.pool().clone())
+            .set_show_balance(15, true)
+            .await
+            .unwrap();
 
-        create_test_card(&db, "5555555555", "EMP005", user_id).await;
+        // Exits leave the balance untouched
+        let response = validator.validate(&exit).await.unwrap();
+        assert_eq!(response.display_message(), "Acesso liberado Saldo: 4");
+        let response = validator.validate(&entry).await.unwrap();
+        assert_eq!(response.display_message(), "Acesso liberado Saldo: 3");
 
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("5555555555", AccessDirection::Entry);
+        // Users without a quota see the plain message
+        SqliteQuotaRepository::new(db.pool().clone())
+            .remove_user_quota(user_id)
+            .await
+            .unwrap();
+        let response = validator.validate(&exit).await.unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::ACCESS_GRANTED);
+    }
 
-        let response = validator.validate(&request).await.unwrap();
-        assert!(response.is_deny());
-        assert_eq!(response.display_message(), "Usuario inativo");
+    /// A contractor: keypad code, no card
+    async fn create_contractor(db: &Database, matricula: &str, code: &str) -> i64 {
+        let repo = SqliteUserRepository::new(db.pool().clone()).with_digest_key(pin_digest_key());
+        let id = create_test_user(db, matricula).await;
+        let mut user = repo.find_by_id(id).await.unwrap().unwrap();
+        user.allow_card = false;
+        user.allow_keypad = true;
+        user.codigo = Some(code.to_string());
+        repo.update(&user).await.unwrap();
+        id
+    }
+
+    fn pin_digest_key() -> PinDigestKey {
+        PinDigestKey::new([7; 32].to_vec().into()).unwrap()
+    }
+
+    fn keypad_validator(db: &Database, device_id: u8) -> OfflineValidator {
+        OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(device_id).unwrap())
+            .with_pin_digest_key(pin_digest_key())
+    }
+
+    fn create_keypad_request(code: &str) -> AccessRequest {
+        let timestamp = HenryTimestamp::parse("10/05/2025 12:46:06").unwrap();
+        AccessRequest::new(
+            code.to_string(),
+            timestamp,
+            AccessDirection::Entry,
+            turnkey_core::ReaderType::Keypad,
+        )
+        .unwrap()
     }
 
     #[tokio::test]
-    async fn test_validate_user_card_not_allowed() {
+    async fn test_log_time_measured_per_validation() {
         let db = setup_test_db().await;
+        let user_id = create_test_user(&db, "EMP036").await;
+        create_test_card(&db, "3636363636", "EMP036", user_id).await;
 
-        // Create user with card access disabled
-        let user = User {
-            id: 0,
-            pis: None,
-            nome: "No Card Access".to_string(),
-            matricula: "EMP006".to_string(),
-            cpf: None,
-            validade_inicio: None,
-            validade_fim: None,
-            ativo: true,
-            allow_card: false, // Card access not allowed
-            allow_bio: true,
-            allow_keypad: false,
-            codigo: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        };
+        let mut validator = OfflineValidator::new(db.pool().clone());
+        assert_eq!(validator.take_log_time(), std::time::Duration::ZERO);
+
+        let started = std::time::Instant::now();
+        let request = create_access_request("3636363636", AccessDirection::Entry);
+        assert!(validator.validate(&request).await.unwrap().is_grant());
+        let elapsed = started.elapsed();
+
+        let log_time = validator.take_log_time();
+        assert!(log_time > std::time::Duration::ZERO);
+        assert!(log_time <= elapsed);
+        assert_eq!(validator.take_log_time(), std::time::Duration::ZERO);
+
+        // Denials are logged as well
+        let unknown = create_access_request("9090909090", AccessDirection::Entry);
+        assert!(!validator.validate(&unknown).await.unwrap().is_grant());
+        assert!(validator.take_log_time() > std::time::Duration::ZERO);
+    }
 
-        let user_repo = SqliteUserRepository::new(db.pool().clone());
-        let user_id = user_repo.create(&user).await.unwrap();
+    #[tokio::test]
+    async fn test_keypad_code_identifies_user() {
+        let db = setup_test_db().await;
+        let user_id = create_contractor(&db, "EMP034", "482916").await;
 
-        create_test_card(&db, "6666666666", "EMP006", user_id).await;
+        let mut validator = keypad_validator(&db, 15);
+        let response = validator
+            .validate(&create_keypad_request("482916"))
+            .await
+            .unwrap();
+        assert!(response.is_grant());
 
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("6666666666", AccessDirection::Entry);
+        // The code itself never reaches the access log
+        let logs = SqliteAccessLogRepository::new(db.pool().clone());
+        assert!(
+            logs.find_by_card_number("482916", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let logged = logs
+            .find_by_card_number(KEYPAD_CARD_NUMBER, 10)
+            .await
+            .unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].user_id, Some(user_id));
+    }
 
-        let response = validator.validate(&request).await.unwrap();
-        assert!(response.is_deny());
+    #[tokio::test]
+    async fn test_keypad_matricula_and_pin() {
+        let db = setup_test_db().await;
+        let user_id = create_contractor(&db, "EMP039", "528417").await;
+        create_contractor(&db, "EMP040", "306195").await;
+        let card_only = create_test_user(&db, "EMP041").await;
+
+        let mut validator = keypad_validator(&db, 15);
+        let right = create_keypad_request("EMP039*528417");
+        assert!(validator.validate(&right).await.unwrap().is_grant());
+
+        // Another user's PIN does not open for this matricula
+        for entry in ["EMP039*306195", "EMP039*000000", "EMP099*528417"] {
+            let response = validator
+                .validate(&create_keypad_request(entry))
+                .await
+                .unwrap();
+            assert_eq!(response.display_message(), DisplayMessages::PIN_INVALID);
+        }
+
+        // A user without keypad access looks like a wrong PIN
+        let response = validator
+            .validate(&create_keypad_request("EMP041*528417"))
+            .await
+            .unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::PIN_INVALID);
+
+        // Attempts on a known matricula are logged against the user
+        let logged = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number(KEYPAD_CARD_NUMBER, 10)
+            .await
+            .unwrap();
+        let mut users: Vec<_> = logged.iter().map(|log| log.user_id).collect();
+        users.sort();
         assert_eq!(
-            response.display_message(),
-            "Acesso por cartao nao permitido"
+            users,
+            [
+                None,
+                Some(user_id),
+                Some(user_id),
+                Some(user_id),
+                Some(card_only)
+            ]
         );
     }
 
     #[tokio::test]
-    async fn test_validate_logs_granted_access() {
+    async fn test_keypad_unknown_matricula_and_no_keypad_count_as_wrong_codes() {
         let db = setup_test_db().await;
-        let user_id = create_test_user(&db, "EMP007").await;
-        create_test_card(&db, "7777777777", "EMP007", user_id).await;
+        let card_only = create_test_user(&db, "EMP043").await;
+        let policy = KeypadLockoutPolicy::new(2, Duration::minutes(5));
 
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("7777777777", AccessDirection::Entry);
+        let mut validator = keypad_validator(&db, 15).with_keypad_lockout(policy);
+        let response = validator
+            .validate(&create_keypad_request("EMP098*123456"))
+            .await
+            .unwrap();
+        assert_eq!(response.display_message(), DisplayMessages::PIN_INVALID);
+        let response = validator
+            .validate(&create_keypad_request("EMP043*123456"))
+            .await
+            .unwrap();
+        assert_eq!(response.display_message(), "Tente novamente em 5 min");
 
-        validator.validate(&request).await.unwrap();
+        let lockouts = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        let user = lockouts.find_user(card_only).await.unwrap().unwrap();
+        assert_eq!(user.failed_attempts, 1);
+    }
 
-        // Verify log was created
-        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
-        let logs = log_repo
-            .find_by_card_number("7777777777", 10)
+    #[tokio::test]
+    async fn test_keypad_wrong_pin_locks_user_on_every_device() {
+        let db = setup_test_db().await;
+        let user_id = create_contractor(&db, "EMP042", "861530").await;
+        let policy = KeypadLockoutPolicy::new(2, Duration::minutes(5));
+        let lockouts = SqliteKeypadLockoutRepository::new(db.pool().clone());
+
+        let mut validator = keypad_validator(&db, 15).with_keypad_lockout(policy);
+        let wrong = create_keypad_request("EMP042*111111");
+        validator.validate(&wrong).await.unwrap();
+        let response = validator.validate(&wrong).await.unwrap();
+        assert_eq!(response.display_message(), "Tente novamente em 5 min");
+        assert!(lockouts.find_user(user_id).await.unwrap().is_some());
+
+        // The user's PIN stays locked on a device that saw no wrong code
+        let mut other = keypad_validator(&db, 16).with_keypad_lockout(policy);
+        let response = other
+            .validate(&create_keypad_request("EMP042*861530"))
             .await
             .unwrap();
+        assert!(!response.is_grant());
+        assert_eq!(response.display_message(), "Tente novamente em 5 min");
+    }
 
-        assert_eq!(logs.len(), 1);
-        assert!(logs[0].granted);
-        assert_eq!(logs[0].display_message.as_deref(), Some("Acesso liberado"));
+    #[tokio::test]
+    async fn test_keypad_locks_after_wrong_codes() {
+        let db = setup_test_db().await;
+        create_contractor(&db, "EMP035", "739264").await;
+        let policy = KeypadLockoutPolicy::new(3, Duration::minutes(10));
+
+        let mut validator = keypad_validator(&db, 15).with_keypad_lockout(policy);
+        let wrong = create_keypad_request("111111");
+
+        for _ in 0..2 {
+            let response = validator.validate(&wrong).await.unwrap();
+            assert!(!response.is_grant());
+            assert_eq!(response.display_message(), DisplayMessages::PIN_INVALID);
+        }
+        let response = validator.validate(&wrong).await.unwrap();
+        assert_eq!(response.display_message(), "Tente novamente em 10 min");
+
+        // Even the right code is refused while the keypad is locked
+        let right = create_keypad_request("739264");
+        let response = validator.validate(&right).await.unwrap();
+        assert!(!response.is_grant());
+        assert_eq!(response.display_message(), "Tente novamente em 10 min");
+
+        // The lockout belongs to the device, not to the user
+        let mut other = keypad_validator(&db, 16).with_keypad_lockout(policy);
+        assert!(other.validate(&right).await.unwrap().is_grant());
     }
 
     #[tokio::test]
-    async fn test_validate_logs_denied_access() {
+    async fn test_keypad_lockouts_escalate_and_raise_events() {
+        use crate::events::{EventBus, SubscriptionOptions};
+
         let db = setup_test_db().await;
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        let request = create_access_request("8888888888", AccessDirection::Entry);
+        create_contractor(&db, "EMP037", "615283").await;
+        let clock = Arc::new(turnkey_core::MockClock::new(Utc::now()));
+        let events = EventBus::new(4);
+        let mut security = events.subscribe(SubscriptionOptions::new("audit")).await;
+
+        let mut validator = keypad_validator(&db, 15)
+            .with_keypad_lockout(KeypadLockoutPolicy::new(2, Duration::minutes(5)))
+            .with_pin_lockout_events(events)
+            .with_clock(clock.clone());
+        let wrong = create_keypad_request("111111");
+
+        // 5 minutes, then 10: each lockout publishes one event
+        for (lockouts, message) in [
+            (1, "Tente novamente em 5 min"),
+            (2, "Tente novamente em 10 min"),
+        ] {
+            validator.validate(&wrong).await.unwrap();
+            let response = validator.validate(&wrong).await.unwrap();
+            assert_eq!(response.display_message(), message);
+            assert_eq!(
+                validator.validate(&wrong).await.unwrap().display_message(),
+                message
+            );
+
+            let event = security.try_recv().unwrap();
+            assert_eq!(event.subject, PinLockoutSubject::Device { device_id: 15 });
+            assert_eq!((event.failed_attempts, event.lockouts), (2, lockouts));
+            assert!(security.try_recv().is_none());
+
+            clock.advance((event.locked_until - event.locked_at).to_std().unwrap());
+        }
 
-        validator.validate(&request).await.unwrap();
+        // The right code clears the escalation
+        assert!(
+            validator
+                .validate(&create_keypad_request("615283"))
+                .await
+                .unwrap()
+                .is_grant()
+        );
+        validator.validate(&wrong).await.unwrap();
+        let response = validator.validate(&wrong).await.unwrap();
+        assert_eq!(response.display_message(), "Tente novamente em 5 min");
+    }
 
-        // Verify log was created
-        let log_repo = SqliteAccessLogRepository::new(db.pool().clone());
-        let logs = log_repo
-            .find_by_card_number("8888888888", 10)
+    #[tokio::test]
+    async fn test_keypad_refuses_user_with_locked_pin() {
+        let db = setup_test_db().await;
+        let user_id = create_contractor(&db, "EMP038", "904172").await;
+        let clock = Arc::new(turnkey_core::MockClock::new(Utc::now()));
+        let policy = KeypadLockoutPolicy::new(1, Duration::minutes(3));
+        let lockouts = SqliteKeypadLockoutRepository::new(db.pool().clone());
+        lockouts
+            .record_user_failure(user_id, &policy, turnkey_core::Clock::now(&*clock))
             .await
             .unwrap();
 
-        assert_eq!(logs.len(), 1);
-        assert!(!logs[0].granted);
-        assert_eq!(
-            logs[0].display_message.as_deref(),
-            Some("Cartao nao cadastrado")
-        );
+        // Locked on every device, even with the right code
+        let right = create_keypad_request("EMP038*904172");
+        for device in [15, 16] {
+            let mut validator = OfflineValidator::new(db.pool().clone())
+                .with_device_id(DeviceId::new(device).unwrap())
+                .with_keypad_lockout(policy)
+                .with_clock(clock.clone());
+            let response = validator.validate(&right).await.unwrap();
+            assert_eq!(response.display_message(), "Tente novamente em 3 min");
+        }
+
+        // A wrong code answers as for an unknown matricula
+        let mut validator = keypad_validator(&db, 17).with_clock(clock.clone());
+        for entry in ["EMP038*111111", "EMP099*111111"] {
+            let response = validator
+                .validate(&create_keypad_request(entry))
+                .await
+                .unwrap();
+            assert_eq!(response.display_message(), DisplayMessages::PIN_INVALID);
+        }
+
+        clock.advance(std::time::Duration::from_secs(3 * 60));
+        let mut validator = keypad_validator(&db, 15).with_clock(clock.clone());
+        assert!(validator.validate(&right).await.unwrap().is_grant());
+        assert!(lockouts.find_user(user_id).await.unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn test_validate_normalizes_card_number() {
+    async fn test_validate_denies_stale_data_on_flagged_device() {
         let db = setup_test_db().await;
-        let user_id = create_test_user(&db, "EMP009").await;
-        create_test_card(&db, "ABCDEF1234", "EMP009", user_id).await;
+        let user_id = create_test_user(&db, "EMP023").await;
+        create_test_card(&db, "2323232323", "EMP023", user_id).await;
+        SqliteDeviceRepository::new(db.pool().clone())
+            .set_deny_when_stale(15, true)
+            .await
+            .unwrap();
+        let (warning_tx, mut warning_rx) = mpsc::channel(4);
 
-        let mut validator = OfflineValidator::new(db.pool().clone());
-        // Request with lowercase card number
-        let request = create_access_request("abcdef1234", AccessDirection::Entry);
+        // Never synced counts as stale
+        let mut validator = OfflineValidator::new(db.pool().clone())
+            .with_device_id(DeviceId::new(15).unwrap())
+            .with_stale_data_threshold(Duration::days(3))
+            .with_stale_data_warnings(warning_tx);
+        let request = create_access_request("2323232323", AccessDirection::Entry);
 
         let response = validator.validate(&request).await.unwrap();
-        assert!(response.is_grant());
+        assert!(!response.is_grant());
+        assert_eq!(response.display_message(), DisplayMessages::DATA_STALE);
+
+        let warning = warning_rx.try_recv().unwrap();
+        assert!(warning.denied);
+        assert_eq!(warning.age_secs, None);
+
+        let logs = SqliteAccessLogRepository::new(db.pool().clone())
+            .find_by_card_number("2323232323", 10)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert!(!logs[0].granted);
+
+        // Without a threshold the flag has no effect
+        let mut unchecked =
+            OfflineValidator::new(db.pool().clone()).with_device_id(DeviceId::new(15).unwrap());
+        assert!(unchecked.validate(&request).await.unwrap().is_grant());
     }
 
     // OnlineValidator tests
-    use turnkey_network::TcpClientConfig;
+    use turnkey_network::{SharedTcpClient, TcpClientConfig};
 
     #[test]
     fn test_online_validator_config_default() {
@@ -1338,6 +4301,53 @@ mod tests {
         assert!(validator.config.fallback_to_offline);
     }
 
+    #[tokio::test]
+    async fn test_shared_validators_fall_back_offline() {
+        let db = setup_test_db().await;
+
+        // Closed local port: every exchange fails
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let connection = SharedTcpClient::new(TcpClientConfig {
+            server_addr,
+            timeout: std::time::Duration::from_millis(200),
+        });
+        let device_id = DeviceId::new(15).unwrap();
+        let config = OnlineValidatorConfig {
+            max_retries: 0,
+            fallback_to_offline: true,
+            ..Default::default()
+        };
+        let mut entry = OnlineValidator::shared(connection.clone(), device_id, config.clone())
+            .with_offline_fallback(OfflineValidator::new(db.pool().clone()));
+        let mut exit = OnlineValidator::shared(connection.clone(), device_id, config)
+            .with_offline_fallback(OfflineValidator::new(db.pool().clone()));
+        assert_eq!(connection.handle_count(), 3);
+
+        let request = |direction| {
+            AccessRequest::new(
+                "99999999".to_string(),
+                HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+                direction,
+                turnkey_core::ReaderType::Rfid,
+            )
+            .unwrap()
+        };
+        let (entry_request, exit_request) = (
+            request(AccessDirection::Entry),
+            request(AccessDirection::Exit),
+        );
+        let (entry_response, exit_response) =
+            tokio::join!(entry.validate(&entry_request), exit.validate(&exit_request));
+
+        // Unknown card: denied by the offline fallback, not a network error
+        assert!(!entry_response.unwrap().is_grant());
+        assert!(!exit_response.unwrap().is_grant());
+        assert!(!connection.is_connected().await);
+    }
+
     #[test]
     fn test_request_to_message_entry() {
         let timestamp = HenryTimestamp::parse("10/05/2025 12:46:06").unwrap();
@@ -1361,6 +4371,52 @@ mod tests {
         assert_eq!(message.fields[3].as_str(), "0"); // RFID
     }
 
+    #[test]
+    fn test_outbound_message_signed_with_key() {
+        let request = AccessRequest::new(
+            "1234567890".to_string(),
+            HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            AccessDirection::Entry,
+            turnkey_core::ReaderType::Rfid,
+        )
+        .unwrap();
+        let device_id = DeviceId::new(15).unwrap();
+        let tcp_client = TcpClient::new(TcpClientConfig::default());
+
+        let unsigned =
+            OnlineValidator::new(tcp_client, device_id, OnlineValidatorConfig::default());
+        let message = unsigned.outbound_message(&request).unwrap();
+        assert!(!MessageSigner::is_signed(&message));
+
+        let tcp_client = TcpClient::new(TcpClientConfig::default());
+        let signed = OnlineValidator::new(tcp_client, device_id, OnlineValidatorConfig::default())
+            .with_signing_key(b"0123456789abcdef".to_vec());
+        let mut message = signed.outbound_message(&request).unwrap();
+        assert_eq!(message.fields.len(), 5);
+
+        let signer = MessageSigner::new(b"0123456789abcdef".to_vec());
+        signer.verify_and_strip(&mut message).unwrap();
+        assert_eq!(message.fields[0].as_str(), "1234567890");
+    }
+
+    #[test]
+    fn test_request_to_message_carries_correlation_id() {
+        let correlation_id = turnkey_core::CorrelationId::new();
+        let request = AccessRequest::new(
+            "1234567890".to_string(),
+            HenryTimestamp::parse("10/05/2025 12:46:06").unwrap(),
+            AccessDirection::Entry,
+            turnkey_core::ReaderType::Rfid,
+        )
+        .unwrap()
+        .with_correlation_id(correlation_id);
+
+        let message =
+            OnlineValidator::request_to_message(&request, DeviceId::new(15).unwrap()).unwrap();
+        assert_eq!(message.fields.len(), 5);
+        assert_eq!(message.correlation_id(), Some(correlation_id));
+    }
+
     #[test]
     fn test_request_to_message_exit() {
         let timestamp = HenryTimestamp::parse("10/05/2025 12:46:06").unwrap();
@@ -1425,6 +4481,22 @@ mod tests {
         assert_eq!(response.display_message(), "Acesso negado");
     }
 
+    #[test]
+    fn test_message_to_response_display_only() {
+        let device_id = DeviceId::new(15).unwrap();
+        let message = MessageBuilder::new(device_id, CommandCode::DisplayOnly)
+            .field(FieldData::new("5".to_string()).unwrap())
+            .field(FieldData::new("Dirija-se a recepcao".to_string()).unwrap())
+            .build()
+            .unwrap();
+
+        let response = OnlineValidator::message_to_response(&message).unwrap();
+
+        assert!(response.is_display_only());
+        assert!(!response.is_grant());
+        assert_eq!(response.display_message(), "Dirija-se a recepcao");
+    }
+
     #[test]
     fn test_message_to_response_invalid_command() {
         let device_id = DeviceId::new(15).unwrap();
@@ -0,0 +1,129 @@
+//! Concurrency stress tests for anti-passback
+//!
+//! The same card is presented on several devices at once. Each device has
+//! its own validator sharing one database, so every request passes the
+//! read-only anti-passback check before any of them has logged its grant.
+//! Exactly one request per burst may be granted.
+//!
+//! Run with: cargo test --package turnkey-storage --test anti_passback_concurrency
+
+use std::sync::Arc;
+use tokio::sync::Barrier;
+use turnkey_core::{AccessDirection, DeviceId, HenryTimestamp, ReaderType};
+use turnkey_protocol::commands::access::AccessRequest;
+use turnkey_storage::connection::{Database, DatabaseConfig};
+use turnkey_storage::{AccessValidator, DisplayMessages, OfflineValidator};
+
+const DEVICES: u8 = 8;
+const ROUNDS: usize = 20;
+
+async fn create_credential(db: &Database, round: usize) -> (i64, String) {
+    let matricula = format!("RACE{:03}", round);
+    let card_number = format!("RACECARD{:04}", round);
+
+    let user_id = sqlx::query("INSERT INTO users (nome, matricula) VALUES ('Race', ?)")
+        .bind(&matricula)
+        .execute(db.pool())
+        .await
+        .unwrap()
+        .last_insert_rowid();
+
+    sqlx::query("INSERT INTO cards (numero_cartao, matricula, user_id) VALUES (?, ?, ?)")
+        .bind(&card_number)
+        .bind(&matricula)
+        .bind(user_id)
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+    (user_id, card_number)
+}
+
+/// Present `card_number` on every device at once; returns the number of grants
+async fn burst(db: &Database, card_number: &str, direction: AccessDirection) -> usize {
+    let barrier = Arc::new(Barrier::new(DEVICES as usize));
+
+    let handles: Vec<_> = (1..=DEVICES)
+        .map(|device| {
+            let pool = db.pool().clone();
+            let barrier = barrier.clone();
+            let card_number = card_number.to_string();
+
+            tokio::spawn(async move {
+                let mut validator =
+                    OfflineValidator::new(pool).with_device_id(DeviceId::new(device).unwrap());
+                let request = AccessRequest::new(
+                    card_number,
+                    HenryTimestamp::now(),
+                    direction,
+                    ReaderType::Rfid,
+                )
+                .unwrap();
+
+                barrier.wait().await;
+                validator.validate(&request).await.unwrap()
+            })
+        })
+        .collect();
+
+    let mut granted = 0;
+    for response in futures::future::join_all(handles).await {
+        let response = response.unwrap();
+        if response.is_grant() {
+            granted += 1;
+        } else {
+            assert_eq!(response.display_message(), DisplayMessages::ANTI_PASSBACK);
+        }
+    }
+    granted
+}
+
+async fn granted_logs(db: &Database, user_id: i64) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM access_logs WHERE user_id = ? AND granted = 1")
+        .bind(user_id)
+        .fetch_one(db.pool())
+        .await
+        .unwrap()
+}
+
+async fn run_rounds(db: &Database) {
+    for round in 0..ROUNDS {
+        let (user_id, card_number) = create_credential(db, round).await;
+
+        assert_eq!(
+            burst(db, &card_number, AccessDirection::Entry).await,
+            1,
+            "round {}: simultaneous entries",
+            round
+        );
+        assert_eq!(
+            burst(db, &card_number, AccessDirection::Exit).await,
+            1,
+            "round {}: simultaneous exits",
+            round
+        );
+        assert_eq!(granted_logs(db, user_id).await, 2, "round {}", round);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_simultaneous_requests_single_connection() {
+    // One connection: requests interleave at every await point
+    let db = Database::in_memory().await.unwrap();
+    run_rounds(&db).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_simultaneous_requests_connection_pool() {
+    // Separate connections: requests really run in parallel against the file
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("race.db");
+    let db = Database::new(
+        DatabaseConfig::new(path.to_string_lossy().to_string()).max_connections(DEVICES as u32),
+    )
+    .await
+    .unwrap();
+
+    run_rounds(&db).await;
+    db.close().await;
+}
@@ -0,0 +1,177 @@
+//! Integration test for archiving access logs to S3-compatible storage
+//!
+//! A minimal S3 stand-in stores uploaded objects in memory and checks that
+//! every request is signed and carries the SHA-256 of its body. Logs are
+//! archived, deleted from the database and restored from the bucket.
+//!
+//! Run with: cargo test --package turnkey-storage --features s3 --test archive
+
+#![cfg(feature = "s3")]
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use turnkey_storage::archive::{ArchiveConfig, LogArchiver, S3Config, S3Store};
+use turnkey_storage::connection::Database;
+use turnkey_storage::models::{AccessLog, Direction, ReaderType};
+use turnkey_storage::{AccessLogRepository, SqliteAccessLogRepository};
+
+const LOGS: i64 = 12;
+
+type Bucket = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// Serve `PUT` and `GET` of objects by path until the test ends
+///
+/// Requests without a valid signature header or with a payload hash that
+/// does not match the body are answered with 403.
+async fn fake_s3() -> (String, Bucket) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let bucket: Bucket = Arc::default();
+
+    let objects = bucket.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let objects = objects.clone();
+            tokio::spawn(async move { serve(stream, objects).await });
+        }
+    });
+    (url, bucket)
+}
+
+async fn serve(mut stream: TcpStream, objects: Bucket) {
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 8192];
+    let (head, body) = loop {
+        let n = stream.read(&mut buf).await.unwrap();
+        raw.extend_from_slice(&buf[..n]);
+        if let Some(end) = raw.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&raw[..end]).to_string();
+            let length = header(&head, "content-length")
+                .map(|v| v.parse::<usize>().unwrap())
+                .unwrap_or(0);
+            if raw.len() >= end + 4 + length {
+                break (head, raw[end + 4..end + 4 + length].to_vec());
+            }
+        }
+        if n == 0 {
+            return;
+        }
+    };
+
+    let mut request_line = head.lines().next().unwrap().split(' ');
+    let (method, path) = (request_line.next().unwrap(), request_line.next().unwrap());
+    let signed = header(&head, "authorization").is_some_and(|v| v.starts_with("AWS4-HMAC-SHA256 "))
+        && header(&head, "x-amz-content-sha256") == Some(hex::encode(Sha256::digest(&body)));
+
+    let (status, reply) = match method {
+        _ if !signed => ("403 Forbidden", Vec::new()),
+        "PUT" => {
+            objects.lock().unwrap().insert(path.to_string(), body);
+            ("200 OK", Vec::new())
+        }
+        "GET" => match objects.lock().unwrap().get(path) {
+            Some(object) => ("200 OK", object.clone()),
+            None => ("404 Not Found", Vec::new()),
+        },
+        _ => ("405 Method Not Allowed", Vec::new()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        status,
+        reply.len()
+    );
+    stream.write_all(response.as_bytes()).await.unwrap();
+    stream.write_all(&reply).await.unwrap();
+}
+
+fn header(head: &str, name: &str) -> Option<String> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+async fn count_logs(db: &Database) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM access_logs")
+        .fetch_one(db.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_archive_and_restore_through_s3() {
+    let db = Database::in_memory().await.unwrap();
+    sqlx::query("DELETE FROM access_logs")
+        .execute(db.pool())
+        .await
+        .unwrap();
+    let start = Utc.with_ymd_and_hms(2025, 5, 10, 8, 0, 0).unwrap();
+    let logs: Vec<_> = (0..LOGS)
+        .map(|i| {
+            AccessLog::new(
+                Some(2),
+                Some("1002".to_string()),
+                "00000000000022823433".to_string(),
+                Direction::Entry,
+                ReaderType::Rfid,
+                true,
+                Some("Acesso liberado".to_string()),
+                start + chrono::Duration::minutes(i),
+            )
+        })
+        .collect();
+    SqliteAccessLogRepository::new(db.pool().clone())
+        .create_many(&logs)
+        .await
+        .unwrap();
+
+    let (endpoint, bucket) = fake_s3().await;
+    let store = S3Store::new(S3Config::new(endpoint, "turnkey", "access", "secret")).unwrap();
+    let archiver = LogArchiver::new(
+        db.pool().clone(),
+        store,
+        ArchiveConfig::new().prefix("site-a").segment_rows(5),
+    );
+
+    let day = NaiveDate::from_ymd_opt(2025, 5, 10).unwrap();
+    let report = archiver.archive(day.succ_opt().unwrap()).await.unwrap();
+    assert_eq!(report.days, [day]);
+    assert_eq!((report.segments, report.rows), (3, LOGS as u64));
+
+    let mut keys: Vec<_> = bucket.lock().unwrap().keys().cloned().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        [
+            "/turnkey/site-a/2025/05/10/manifest.json",
+            "/turnkey/site-a/2025/05/10/segment-0001.jsonl.gz",
+            "/turnkey/site-a/2025/05/10/segment-0002.jsonl.gz",
+            "/turnkey/site-a/2025/05/10/segment-0003.jsonl.gz",
+        ]
+    );
+
+    sqlx::query("DELETE FROM access_logs")
+        .execute(db.pool())
+        .await
+        .unwrap();
+    let restored = archiver.restore_day(day).await.unwrap();
+    assert_eq!(restored.inserted, LOGS as u64);
+    assert_eq!(count_logs(&db).await, LOGS);
+
+    let user_logs: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM access_logs WHERE user_id = 2 AND granted = 1")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+    assert_eq!(user_logs, LOGS);
+
+    // A day that was never archived
+    let missing = day.pred_opt().unwrap();
+    assert!(archiver.manifest(missing).await.unwrap().is_none());
+}
@@ -0,0 +1,186 @@
+//! Integration tests for cluster mode
+//!
+//! Two validation servers run in-process against one database file, each
+//! through its own pool, as two server processes on one host would.
+//!
+//! Run with: cargo test --package turnkey-storage --test cluster
+
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::time::timeout;
+use turnkey_core::{AccessDirection, CorrelationId, DeviceId, HenryTimestamp, ReaderType};
+use turnkey_network::{TcpClient, TcpClientConfig, TcpServer, TcpServerConfig};
+use turnkey_protocol::commands::access::{AccessDecision, AccessRequest};
+use turnkey_protocol::{CommandCode, MessageBuilder};
+use turnkey_storage::cluster::ClusterNode;
+use turnkey_storage::connection::{Database, DatabaseConfig};
+use turnkey_storage::{AccessValidator, OfflineValidator};
+
+/// Open the shared database file as one node would
+async fn open_node(dir: &TempDir, node_id: &str) -> (Database, ClusterNode) {
+    let path = dir.path().join("cluster.db");
+    let db = Database::new(DatabaseConfig::new(path.to_str().unwrap()))
+        .await
+        .unwrap();
+    let node = ClusterNode::new(db.pool().clone(), node_id).unwrap();
+    (db, node)
+}
+
+async fn bind_server(port: u16, node: &ClusterNode) -> TcpServer {
+    let mut server = TcpServer::bind(TcpServerConfig {
+        bind_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+    server.set_device_affinity(Some(Arc::new(node.clone())));
+    server
+}
+
+/// Connect as `device_id` and send a status query
+async fn connect_device(port: u16, device_id: DeviceId) -> TcpClient {
+    let mut client = TcpClient::new(TcpClientConfig {
+        server_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        timeout: Duration::from_millis(1000),
+    });
+    client.connect().await.unwrap();
+    let message = MessageBuilder::new(device_id, CommandCode::QueryStatus)
+        .build()
+        .unwrap();
+    client.send(message).await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn test_device_served_by_one_server_at_a_time() {
+    let dir = TempDir::new().unwrap();
+    let (_db_a, node_a) = open_node(&dir, "server-a").await;
+    let (_db_b, node_b) = open_node(&dir, "server-b").await;
+    let mut server_a = bind_server(13029, &node_a).await;
+    let mut server_b = bind_server(13030, &node_b).await;
+    let device_id = DeviceId::new(15).unwrap();
+
+    let _client_a = connect_device(13029, device_id).await;
+    let (accepted, _) = timeout(Duration::from_secs(5), server_a.accept())
+        .await
+        .expect("Server A accept timeout")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+    assert_eq!(
+        node_b
+            .device_owner(device_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .node_id,
+        "server-a"
+    );
+
+    // Server B refuses the device while A holds it
+    let mut refused = connect_device(13030, device_id).await;
+    let attempt = timeout(Duration::from_millis(500), server_b.accept()).await;
+    assert!(attempt.is_err(), "server B accepted a device held by A");
+    assert!(refused.recv().await.is_err());
+    assert!(!server_b.is_connected(device_id));
+
+    // Once A lets go, the device fails over to B
+    server_a.disconnect(device_id).await.unwrap();
+    assert!(node_b.device_owner(device_id).await.unwrap().is_none());
+
+    let _client_b = connect_device(13030, device_id).await;
+    let (accepted, _) = timeout(Duration::from_secs(5), server_b.accept())
+        .await
+        .expect("Server B accept timeout")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+    assert_eq!(
+        node_a
+            .device_owner(device_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .node_id,
+        "server-b"
+    );
+}
+
+#[tokio::test]
+async fn test_crashed_server_lease_lapses() {
+    let dir = TempDir::new().unwrap();
+    let (_db_a, node_a) = open_node(&dir, "server-a").await;
+    let (_db_b, node_b) = open_node(&dir, "server-b").await;
+    let node_a = node_a.with_lease_ttl(Duration::from_millis(300));
+    let mut server_b = bind_server(13031, &node_b).await;
+    let device_id = DeviceId::new(16).unwrap();
+
+    // Server A took the device and died without releasing it
+    assert!(node_a.acquire_device(device_id).await.unwrap());
+
+    let mut refused = connect_device(13031, device_id).await;
+    assert!(
+        timeout(Duration::from_millis(200), server_b.accept())
+            .await
+            .is_err()
+    );
+    assert!(refused.recv().await.is_err());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let _client = connect_device(13031, device_id).await;
+    let (accepted, _) = timeout(Duration::from_secs(5), server_b.accept())
+        .await
+        .expect("Server B accept timeout")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+}
+
+#[tokio::test]
+async fn test_retried_request_decided_once_across_servers() {
+    let dir = TempDir::new().unwrap();
+    let (db_a, node_a) = open_node(&dir, "server-a").await;
+    let (db_b, node_b) = open_node(&dir, "server-b").await;
+    let device_id = DeviceId::new(17).unwrap();
+
+    let user_id = sqlx::query("INSERT INTO users (nome, matricula) VALUES ('Cluster', 'CLU001')")
+        .execute(db_a.pool())
+        .await
+        .unwrap()
+        .last_insert_rowid();
+    sqlx::query("INSERT INTO cards (numero_cartao, matricula, user_id) VALUES (?, ?, ?)")
+        .bind("CLUSTERCARD01")
+        .bind("CLU001")
+        .bind(user_id)
+        .execute(db_a.pool())
+        .await
+        .unwrap();
+
+    let request = AccessRequest::new(
+        "CLUSTERCARD01".to_string(),
+        HenryTimestamp::now(),
+        AccessDirection::Entry,
+        ReaderType::Rfid,
+    )
+    .unwrap()
+    .with_correlation_id(CorrelationId::new());
+
+    let mut validator_a = OfflineValidator::new(db_a.pool().clone())
+        .with_device_id(device_id)
+        .with_cluster(node_a);
+    let first = validator_a.validate(&request).await.unwrap();
+    assert_eq!(first.decision(), AccessDecision::GrantEntry);
+
+    // The device missed the answer and resends the entry to server B. A
+    // second validation would be denied by anti-passback.
+    let mut validator_b = OfflineValidator::new(db_b.pool().clone())
+        .with_device_id(device_id)
+        .with_cluster(node_b);
+    let retried = validator_b.validate(&request).await.unwrap();
+    assert_eq!(retried, first);
+
+    let logged: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM access_logs WHERE card_number = ?")
+        .bind("CLUSTERCARD01")
+        .fetch_one(db_b.pool())
+        .await
+        .unwrap();
+    assert_eq!(logged, 1);
+}
@@ -0,0 +1,109 @@
+//! Integration test for sending profile configuration to devices over TCP
+//!
+//! A device connects to the server, which resolves the profile assigned to
+//! it and sends every setting as a `SendConfig` (EC) message.
+//!
+//! Run with: cargo test --package turnkey-storage --test config_sync
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::timeout;
+use turnkey_core::DeviceId;
+use turnkey_network::{TcpClient, TcpClientConfig, TcpServer, TcpServerConfig};
+use turnkey_protocol::{CommandCode, MessageBuilder};
+use turnkey_storage::config_sync;
+use turnkey_storage::connection::Database;
+use turnkey_storage::models::{ConfigProfile, ConfigSettings, FeedbackPattern};
+use turnkey_storage::repositories::{ConfigProfileRepository, SqliteConfigProfileRepository};
+
+#[tokio::test]
+async fn test_sync_profile_sends_resolved_config() {
+    let db = Database::in_memory().await.unwrap();
+    let profiles = SqliteConfigProfileRepository::new(db.pool().clone());
+    let base = profiles
+        .create(&ConfigProfile::new("Base").with_settings(ConfigSettings {
+            volume: Some(2),
+            feedback: Some(FeedbackPattern::Beep),
+            ..ConfigSettings::default()
+        }))
+        .await
+        .unwrap();
+    let gate = profiles
+        .create(
+            &ConfigProfile::new("Portaria")
+                .with_parent(base)
+                .with_settings(ConfigSettings {
+                    entry_message: Some("Bem vindo a portaria".to_string()),
+                    ..ConfigSettings::default()
+                }),
+        )
+        .await
+        .unwrap();
+    profiles.assign_device(15, Some(gate)).await.unwrap();
+    profiles.assign_device(16, Some(base)).await.unwrap();
+    profiles
+        .set_overrides(
+            15,
+            &ConfigSettings {
+                volume: Some(9),
+                ..ConfigSettings::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let mut server = TcpServer::bind(TcpServerConfig {
+        bind_addr: "127.0.0.1:0".parse().unwrap(),
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+    let server_addr = server.local_addr().unwrap();
+
+    // Device 15 connects and collects the configuration it is sent
+    let device_id = DeviceId::new(15).unwrap();
+    let device = tokio::spawn(async move {
+        let mut client = TcpClient::new(TcpClientConfig {
+            server_addr,
+            timeout: Duration::from_millis(1000),
+        });
+        client.connect().await.unwrap();
+        let hello = MessageBuilder::new(device_id, CommandCode::QueryStatus)
+            .build()
+            .unwrap();
+        client.send(hello).await.unwrap();
+
+        let mut settings = HashMap::new();
+        while settings.len() < 7 {
+            let message = client.recv().await.unwrap();
+            assert_eq!(message.command, CommandCode::SendConfig);
+            settings.insert(
+                message.fields[1].as_str().to_string(),
+                message.fields[2].as_str().to_string(),
+            );
+        }
+        settings
+    });
+
+    let (accepted, _) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("accept timeout")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+
+    // Changing the base profile reaches devices of its child profiles too
+    let report = config_sync::sync_profile(&profiles, &mut server, base)
+        .await
+        .unwrap();
+    assert_eq!(report.sent, [device_id]);
+    assert_eq!(report.not_connected, [DeviceId::new(16).unwrap()]);
+
+    let settings = timeout(Duration::from_secs(5), device)
+        .await
+        .expect("device timeout")
+        .unwrap();
+    assert_eq!(settings["VOLUME"], "9");
+    assert_eq!(settings["SINAL"], "S");
+    assert_eq!(settings["MSG_ENTRADA"], "Bem vindo a portaria");
+    assert_eq!(settings["TIPO_VALIDA"], "O");
+}
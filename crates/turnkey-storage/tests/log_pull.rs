@@ -0,0 +1,140 @@
+//! Integration test for pulling device events over TCP
+//!
+//! A device with its own database answers `ER` queries through a TCP client
+//! while the server pulls its events into the central database.
+//!
+//! Run with: cargo test --package turnkey-storage --test log_pull
+
+use chrono::{TimeZone, Utc};
+use std::time::Duration;
+use tokio::time::timeout;
+use turnkey_core::DeviceId;
+use turnkey_network::{TcpClient, TcpClientConfig, TcpServer, TcpServerConfig};
+use turnkey_protocol::commands::events::{EventCursor, GetEvents};
+use turnkey_protocol::{CommandCode, MessageBuilder};
+use turnkey_storage::connection::Database;
+use turnkey_storage::log_pull::{DeviceLogSource, LogPuller};
+use turnkey_storage::models::{AccessLog, Direction, ReaderType};
+use turnkey_storage::{AccessLogRepository, SqliteAccessLogRepository};
+
+const EVENTS: i64 = 23;
+
+/// Seeded database without the seeded access logs
+async fn database_without_logs() -> Database {
+    let db = Database::in_memory().await.unwrap();
+    sqlx::query("DELETE FROM access_logs")
+        .execute(db.pool())
+        .await
+        .unwrap();
+    db
+}
+
+async fn device_database() -> Database {
+    let db = database_without_logs().await;
+    let start = Utc.with_ymd_and_hms(2025, 5, 10, 8, 0, 0).unwrap();
+    let logs: Vec<_> = (0..EVENTS)
+        .map(|i| {
+            AccessLog::new(
+                None,
+                None,
+                "00000000000022823433".to_string(),
+                if i % 2 == 0 {
+                    Direction::Entry
+                } else {
+                    Direction::Exit
+                },
+                ReaderType::Rfid,
+                true,
+                Some("Acesso liberado".to_string()),
+                start + chrono::Duration::seconds(i * 30),
+            )
+        })
+        .collect();
+    SqliteAccessLogRepository::new(db.pool().clone())
+        .create_many(&logs)
+        .await
+        .unwrap();
+    db
+}
+
+#[tokio::test]
+async fn test_pull_events_from_device() {
+    let device_id = DeviceId::new(15).unwrap();
+    let mut server = TcpServer::bind(TcpServerConfig {
+        bind_addr: "127.0.0.1:13032".parse().unwrap(),
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+
+    // Device: answer every event query from the local log
+    let device_db = device_database().await;
+    let source = DeviceLogSource::new(device_db.pool().clone()).with_events_per_chunk(4);
+    let device = tokio::spawn(async move {
+        let mut client = TcpClient::new(TcpClientConfig {
+            server_addr: "127.0.0.1:13032".parse().unwrap(),
+            timeout: Duration::from_millis(1000),
+        });
+        client.connect().await.unwrap();
+        let hello = MessageBuilder::new(device_id, CommandCode::QueryStatus)
+            .build()
+            .unwrap();
+        client.send(hello).await.unwrap();
+        while let Ok(message) = client.recv().await {
+            if GetEvents::is_query(&message) {
+                for chunk in source.answer_message(&message).await.unwrap() {
+                    client.send(chunk).await.unwrap();
+                }
+            }
+        }
+    });
+
+    let (accepted, _) = timeout(Duration::from_secs(5), server.accept())
+        .await
+        .expect("accept timeout")
+        .unwrap();
+    assert_eq!(accepted, device_id);
+
+    let last_id: i64 = sqlx::query_scalar("SELECT MAX(id) FROM access_logs")
+        .fetch_one(device_db.pool())
+        .await
+        .unwrap();
+    let central = database_without_logs().await;
+    let puller = LogPuller::new(central.pool().clone()).with_page_size(10);
+
+    let report = puller
+        .pull(&mut server, device_id, EventCursor::start())
+        .await
+        .unwrap();
+    assert_eq!(report.queries, 3);
+    assert_eq!(report.events, EVENTS as u64);
+    assert_eq!(report.inserted, EVENTS as u64);
+    assert_eq!(report.cursor.after_id, last_id);
+    assert!(report.deferred.is_empty());
+
+    // Nothing new after the cursor
+    let report = puller
+        .pull(&mut server, device_id, report.cursor)
+        .await
+        .unwrap();
+    assert_eq!(report.queries, 1);
+    assert_eq!(report.events, 0);
+
+    // Starting over only finds duplicates
+    let report = puller
+        .pull(&mut server, device_id, EventCursor::start())
+        .await
+        .unwrap();
+    assert_eq!(report.inserted, 0);
+    assert_eq!(report.duplicates, EVENTS as u64);
+
+    let stored = SqliteAccessLogRepository::new(central.pool().clone())
+        .find_by_card_number("00000000000022823433", 100)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), EVENTS as usize);
+    assert!(stored.iter().all(|log| log.device_id == Some(15)));
+
+    server.disconnect(device_id).await.unwrap();
+    let _ = timeout(Duration::from_secs(5), device).await;
+}
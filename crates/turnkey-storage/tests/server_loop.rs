@@ -0,0 +1,208 @@
+//! Integration test for the server validation loop
+//!
+//! Devices connect over TCP and send access requests; the loop answers them
+//! from the server database and drops the work of devices that hang up.
+//! Events devices replay after an offline period are stored once per NSR.
+//!
+//! Run with: cargo test --package turnkey-storage --test server_loop
+
+use chrono::Utc;
+use std::time::Duration;
+use tokio::time::timeout;
+use turnkey_core::{AccessDirection, DeviceId, HenryTimestamp, ReaderType};
+use turnkey_network::{CancellationToken, TcpClient, TcpClientConfig, TcpServer, TcpServerConfig};
+use turnkey_protocol::commands::access::AccessRequest;
+use turnkey_protocol::{CommandCode, FieldData, MessageBuilder};
+use turnkey_storage::connection::Database;
+use turnkey_storage::models::ReaderType as LogReaderType;
+use turnkey_storage::outbox::Outbox;
+use turnkey_storage::server_loop::{ServerLoopStats, ServerValidationLoop};
+use turnkey_storage::sync::{EventReceiver, SyncEngine};
+use turnkey_storage::{
+    AccessLog, AccessLogRepository, AccessValidator, Direction, OnlineValidator,
+    OnlineValidatorConfig, SqliteAccessLogRepository, transaction,
+};
+
+/// Seeded card of user 1002
+const CARD: &str = "00000000000022823433";
+
+fn request() -> AccessRequest {
+    AccessRequest::new(
+        CARD.to_string(),
+        HenryTimestamp::now(),
+        AccessDirection::Entry,
+        ReaderType::Rfid,
+    )
+    .unwrap()
+}
+
+fn client_config(port: u16) -> TcpClientConfig {
+    TcpClientConfig {
+        server_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        timeout: Duration::from_millis(1000),
+    }
+}
+
+/// Run the loop on `port` until the returned token is cancelled
+async fn start_loop(
+    port: u16,
+    db: &Database,
+) -> (
+    CancellationToken,
+    tokio::task::JoinHandle<ServerValidationLoop>,
+) {
+    let server = TcpServer::bind(TcpServerConfig {
+        bind_addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        max_connections: 10,
+    })
+    .await
+    .unwrap();
+    let mut validation = ServerValidationLoop::new(server, db.pool().clone());
+    let stop = CancellationToken::new();
+    let task = tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            validation.run(stop).await;
+            validation
+        }
+    });
+    (stop, task)
+}
+
+async fn card_logs(db: &Database) -> usize {
+    SqliteAccessLogRepository::new(db.pool().clone())
+        .find_by_card_number(CARD, 100)
+        .await
+        .unwrap()
+        .len()
+}
+
+#[tokio::test]
+async fn test_requests_answered_from_server_database() {
+    let db = Database::in_memory().await.unwrap();
+    let logs_before = card_logs(&db).await;
+    let (stop, task) = start_loop(13038, &db).await;
+
+    let mut online = OnlineValidator::new(
+        TcpClient::new(client_config(13038)),
+        DeviceId::new(15).unwrap(),
+        OnlineValidatorConfig::default(),
+    );
+    let response = timeout(Duration::from_secs(5), online.validate(&request()))
+        .await
+        .expect("no answer")
+        .unwrap();
+    assert!(response.is_grant());
+
+    stop.cancel();
+    let validation = task.await.unwrap();
+    assert_eq!(
+        validation.stats(),
+        ServerLoopStats {
+            requests: 1,
+            responses: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(card_logs(&db).await, logs_before + 1);
+}
+
+#[tokio::test]
+async fn test_disconnect_mid_validation_cancels_without_log() {
+    let db = Database::in_memory().await.unwrap();
+    let logs_before = card_logs(&db).await;
+    let (stop, task) = start_loop(13039, &db).await;
+
+    // The in-memory pool has a single connection: holding it stalls the
+    // validation at its first query
+    let held = db.pool().acquire().await.unwrap();
+
+    let device_id = DeviceId::new(15).unwrap();
+    let mut client = TcpClient::new(client_config(13039));
+    client.connect().await.unwrap();
+    let mut builder = MessageBuilder::new(device_id, CommandCode::AccessRequest);
+    for field in request().to_fields() {
+        builder = builder.field(FieldData::new(field).unwrap());
+    }
+    client.send(builder.build().unwrap()).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    client.close().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    drop(held);
+
+    stop.cancel();
+    let validation = timeout(Duration::from_secs(5), task)
+        .await
+        .expect("loop did not stop")
+        .unwrap();
+    assert_eq!(
+        validation.stats(),
+        ServerLoopStats {
+            requests: 1,
+            cancelled: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(card_logs(&db).await, logs_before);
+}
+
+#[tokio::test]
+async fn test_replayed_events_stored_once() {
+    let server_db = Database::in_memory().await.unwrap();
+    let logs_before = card_logs(&server_db).await;
+    let (stop, task) = start_loop(13042, &server_db).await;
+
+    // Two accesses decided by the device while offline
+    let device_id = DeviceId::new(15).unwrap();
+    let device_db = Database::in_memory().await.unwrap();
+    for hours_ago in [2, 1] {
+        let log = AccessLog::new(
+            Some(2),
+            Some("1002".to_string()),
+            CARD.to_string(),
+            Direction::Entry,
+            LogReaderType::Rfid,
+            true,
+            Some("Acesso liberado".to_string()),
+            Utc::now() - chrono::Duration::hours(hours_ago),
+        );
+        let mut tx = device_db.pool().begin().await.unwrap();
+        let log_id = transaction::create_access_log(&mut tx, &log).await.unwrap();
+        Outbox::enqueue_access(&mut tx, device_id, log_id, &log)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+    let mut sync = SyncEngine::new(device_db.pool().clone());
+
+    // The first event already went out once, but was never marked sent
+    let mut client = TcpClient::new(client_config(13042));
+    client.connect().await.unwrap();
+    let first = sync.outbox().pending(1).await.unwrap().remove(0);
+    client.send(first.message).await.unwrap();
+
+    let report = sync.reconnected(&mut client).await.unwrap();
+    assert_eq!((report.sent, report.failed), (2, 0));
+
+    let receiver = EventReceiver::new(server_db.pool().clone());
+    timeout(Duration::from_secs(5), async {
+        while receiver.last_nsr(device_id).await.unwrap() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("events not received");
+
+    stop.cancel();
+    let validation = task.await.unwrap();
+    assert_eq!(
+        validation.stats(),
+        ServerLoopStats {
+            events: 2,
+            duplicate_events: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(card_logs(&server_db).await, logs_before + 2);
+}
@@ -0,0 +1,91 @@
+//! Single entry point to the Turnkey crates
+//!
+//! An emulator binary usually needs types from half a dozen `turnkey-*`
+//! crates. This crate re-exports them under one name so downstream code has
+//! one dependency and one import path per type.
+//!
+//! # Layout
+//!
+//! - Crate root: everything from `turnkey-core` ([`DeviceId`],
+//!   [`CardNumber`], [`HenryTimestamp`], [`Clock`], ...)
+//! - [`protocol`]: Henry message format and commands (feature `protocol`)
+//! - [`hardware`]: Peripheral traits and mock devices (feature `hardware`)
+//! - [`network`]: TCP client and server (feature `network`)
+//! - [`storage`]: Database, repositories and validators (feature `storage`)
+//! - [`emulator`]: Device state machine and display (feature `emulator`)
+//! - [`prelude`]: The types most programs need, in one glob import
+//!
+//! All features are enabled by default. Features pull in what they depend
+//! on, so `default-features = false, features = ["emulator"]` also enables
+//! `protocol` and `hardware`.
+//!
+//! # Turnstile state
+//!
+//! [`TurnstileState`](protocol::commands::turnstile::TurnstileState) is
+//! defined once, in `turnkey-protocol`. `turnkey_emulator::TurnstileState`
+//! is the same type re-exported; the prelude uses the protocol path.
+//!
+//! # Examples
+//!
+//! ```
+//! use turnkey::prelude::*;
+//!
+//! let device_id = DeviceId::new(15).unwrap();
+//! let display = VirtualDisplay::new(2, 40, "DIGITE SEU CODIGO".to_string());
+//! let emulator = EmulatorCore::new(display);
+//!
+//! assert_eq!(device_id.as_u8(), 15);
+//! assert_eq!(emulator.state(), TurnstileState::Idle);
+//! ```
+
+pub use turnkey_core::*;
+
+#[cfg(feature = "emulator")]
+pub use turnkey_emulator as emulator;
+#[cfg(feature = "hardware")]
+pub use turnkey_hardware as hardware;
+#[cfg(feature = "network")]
+pub use turnkey_network as network;
+#[cfg(feature = "protocol")]
+pub use turnkey_protocol as protocol;
+#[cfg(feature = "storage")]
+pub use turnkey_storage as storage;
+
+/// Commonly used types from every enabled subsystem
+///
+/// ```
+/// use turnkey::prelude::*;
+/// ```
+pub mod prelude {
+    pub use turnkey_core::{
+        AccessDirection, CardNumber, Clock, DeviceId, HenryTimestamp, MockClock, ReaderType,
+        SharedClock, SystemClock, Timeout, ValidationMode,
+    };
+
+    #[cfg(feature = "protocol")]
+    pub use turnkey_protocol::commands::access::{AccessRequest, AccessResponse};
+    #[cfg(feature = "protocol")]
+    pub use turnkey_protocol::commands::turnstile::TurnstileState;
+    #[cfg(feature = "protocol")]
+    pub use turnkey_protocol::{
+        CommandCode, FieldData, HenryCodec, Message, MessageBuilder, MessageType,
+    };
+
+    #[cfg(feature = "hardware")]
+    pub use turnkey_hardware::{
+        BiometricDevice, CardData, HardwareError, KeypadDevice, KeypadInput, RexDevice, RfidDevice,
+        SensorDevice,
+    };
+
+    #[cfg(feature = "network")]
+    pub use turnkey_network::{TcpClient, TcpClientConfig, TcpServer, TcpServerConfig};
+
+    #[cfg(feature = "storage")]
+    pub use turnkey_storage::{
+        AccessValidator, Database, DatabaseConfig, OfflineValidator, OnlineValidator,
+        OnlineValidatorConfig, OperatingMode, StorageError, ValidationModeController, Validator,
+    };
+
+    #[cfg(feature = "emulator")]
+    pub use turnkey_emulator::{EmulatorCore, StateMachine, VirtualDisplay};
+}
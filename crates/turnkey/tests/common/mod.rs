@@ -0,0 +1,401 @@
+//! Common test utilities for cross-crate scenario tests.
+//!
+//! The message helpers of `turnkey-protocol`'s integration tests (the
+//! `create_*` and `assert_*_complete` functions) are shared here unchanged.
+//! On top of them, this module adds a fourth tier:
+//!
+//! 4. **Scenario Helpers** ([`Scenario`]) - Run a full emulated passage
+//!    against an in-memory database and assert its combined outcome
+//!
+//! A scenario drives an [`EmulatorCore`] through the access flow, validates
+//! the request with an [`OfflineValidator`] over the seeded database, and
+//! records everything the passage produced: the protocol messages exchanged,
+//! the state transitions and the access log rows written. One call then
+//! checks all three.
+//!
+//! # Usage Examples
+//!
+//! ```ignore
+//! use crate::common::{Passage, Scenario};
+//! use turnkey_core::AccessDirection;
+//!
+//! let mut scenario = Scenario::new(15).await;
+//! let outcome = scenario
+//!     .present_card(SEEDED_CARD, AccessDirection::Entry, Passage::Through)
+//!     .await;
+//!
+//! // Messages, transitions and the access log row in one call
+//! outcome.assert_grant_flow(15, SEEDED_CARD, AccessDirection::Entry);
+//! ```
+//!
+//! # Test Data
+//!
+//! [`Database::in_memory()`] applies the seed migration, so the cards of the
+//! seeded users ([`SEEDED_CARD`], belonging to user `1002`) are valid
+//! without any setup.
+
+#![allow(dead_code)]
+
+#[path = "../../../turnkey-protocol/tests/common/mod.rs"]
+mod messages;
+
+pub use messages::*;
+
+use turnkey_core::{AccessDirection, DeviceId, ReaderType};
+use turnkey_emulator::{EmulatorCore, StateTransition, TurnstileState};
+use turnkey_hardware::SensorEvent;
+use turnkey_protocol::Message;
+use turnkey_protocol::commands::access::{AccessDecision, AccessResponse};
+use turnkey_storage::models::AccessLog;
+use turnkey_storage::{
+    AccessLogRepository, AccessValidator, Database, OfflineValidator, SqliteAccessLogRepository,
+};
+
+/// Seeded card of user `1002`
+pub const SEEDED_CARD: &str = "00000000000022823433";
+
+/// What the user does once the turnstile is released
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Passage {
+    /// Turns the arm (`000+81`)
+    Through,
+
+    /// Walks away until the release expires (`000+82`)
+    Abandoned,
+}
+
+/// One emulated device validating against an in-memory database
+pub struct Scenario {
+    device_id: u8,
+    db: Database,
+    emulator: EmulatorCore,
+    validator: OfflineValidator,
+}
+
+impl Scenario {
+    /// Idle device `device_id` on a fresh seeded database
+    ///
+    /// # Panics
+    ///
+    /// Panics if the device ID is invalid or the database cannot be created.
+    pub async fn new(device_id: u8) -> Self {
+        let db = Database::in_memory()
+            .await
+            .expect("Scenario: failed to create in-memory database");
+        let validator = OfflineValidator::new(db.pool().clone()).with_device_id(
+            DeviceId::new(device_id).expect("Scenario: invalid device_id (must be 1-99)"),
+        );
+
+        Self {
+            device_id,
+            db,
+            emulator: EmulatorCore::default(),
+            validator,
+        }
+    }
+
+    /// Replace the validator, to test a differently configured flow
+    ///
+    /// The validator must use this scenario's [`database()`](Self::database).
+    pub fn with_validator(mut self, validator: OfflineValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// The database the requests are validated against
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// The emulated device
+    pub fn emulator(&self) -> &EmulatorCore {
+        &self.emulator
+    }
+
+    /// Present a card on the RFID reader and play the passage to the end
+    ///
+    /// The device ends back in `Idle`. `passage` only matters when access
+    /// is granted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the emulator rejects a transition of the flow or the
+    /// validation fails with a database error.
+    pub async fn present_card(
+        &mut self,
+        card_number: &str,
+        direction: AccessDirection,
+        passage: Passage,
+    ) -> ScenarioOutcome {
+        let logs_before = self.card_logs(card_number).await;
+        let mut messages = Vec::new();
+        let mut transitions = Vec::new();
+
+        // Card read: the device asks the server
+        let request_msg =
+            create_access_request(self.device_id, card_number, direction, ReaderType::Rfid);
+        transitions.push(self.transition(TurnstileState::Reading));
+        transitions.push(self.transition(TurnstileState::Validating));
+
+        let request = parse_access_request(&request_msg);
+        messages.push(request_msg);
+        let response = self
+            .validator
+            .validate(&request)
+            .await
+            .expect("Scenario: validation failed");
+        messages.push(create_access_response(
+            self.device_id,
+            response.decision(),
+            response.timeout_seconds(),
+            response.display_message(),
+        ));
+        transitions.push(
+            self.emulator
+                .apply_response(&response)
+                .expect("Scenario: emulator rejected the response"),
+        );
+
+        if response.is_grant() {
+            transitions.push(self.transition(TurnstileState::WaitingRotation));
+            messages.push(create_turnstile_status(
+                self.device_id,
+                TurnstileState::WaitingRotation,
+                None,
+                AccessDirection::Undefined,
+                ReaderType::Rfid,
+            ));
+
+            let end = match passage {
+                Passage::Through => {
+                    transitions.extend(
+                        self.emulator
+                            .handle_sensor_event(SensorEvent::ArmRotated(direction))
+                            .expect("Scenario: emulator rejected the rotation"),
+                    );
+                    TurnstileState::RotationCompleted
+                }
+                Passage::Abandoned => {
+                    transitions.push(self.transition(TurnstileState::RotationTimeout));
+                    TurnstileState::RotationTimeout
+                }
+            };
+            messages.push(create_turnstile_status(
+                self.device_id,
+                end,
+                Some(card_number),
+                direction,
+                ReaderType::Rfid,
+            ));
+        }
+        transitions.push(self.transition(TurnstileState::Idle));
+
+        let logs_after = self.card_logs(card_number).await;
+        let new_logs = logs_after
+            .into_iter()
+            .filter(|log| !logs_before.iter().any(|old| old.id == log.id))
+            .collect();
+
+        ScenarioOutcome {
+            response,
+            messages,
+            transitions,
+            new_logs,
+        }
+    }
+
+    fn transition(&mut self, state: TurnstileState) -> StateTransition {
+        self.emulator
+            .transition_to(state)
+            .unwrap_or_else(|e| panic!("Scenario: transition to {:?} rejected: {}", state, e))
+    }
+
+    async fn card_logs(&self, card_number: &str) -> Vec<AccessLog> {
+        SqliteAccessLogRepository::new(self.db.pool().clone())
+            .find_by_card_number(card_number, 1000)
+            .await
+            .expect("Scenario: failed to read access logs")
+    }
+}
+
+/// Everything one emulated passage produced
+#[derive(Debug)]
+pub struct ScenarioOutcome {
+    /// Decision returned by the validator
+    pub response: AccessResponse,
+
+    /// Protocol messages in the order they were exchanged
+    pub messages: Vec<Message>,
+
+    /// State machine transitions, from leaving `Idle` to returning to it
+    pub transitions: Vec<StateTransition>,
+
+    /// Access log rows written during the passage
+    pub new_logs: Vec<AccessLog>,
+}
+
+impl ScenarioOutcome {
+    /// States visited, in order, excluding the initial `Idle`
+    pub fn states(&self) -> Vec<TurnstileState> {
+        self.transitions.iter().map(|t| t.to).collect()
+    }
+
+    /// Assert the states visited during the passage
+    ///
+    /// # Panics
+    ///
+    /// Panics if the states differ from `expected`.
+    pub fn assert_states(&self, expected: &[TurnstileState]) {
+        assert_eq!(self.states(), expected, "Scenario: state sequence mismatch");
+    }
+
+    /// Assert that exactly one access log row was written, with this outcome
+    ///
+    /// # Panics
+    ///
+    /// Panics if no row or several rows were written, or if the row does
+    /// not match.
+    pub fn assert_logged(
+        &self,
+        device_id: u8,
+        direction: AccessDirection,
+        granted: bool,
+        message: &str,
+    ) {
+        assert_eq!(
+            self.new_logs.len(),
+            1,
+            "Scenario: expected one access log row, found {:?}",
+            self.new_logs
+        );
+        let log = &self.new_logs[0];
+        assert_eq!(log.granted, granted, "Access log: granted mismatch");
+        assert_eq!(
+            log.display_message.as_deref(),
+            Some(message),
+            "Access log: message mismatch"
+        );
+        assert_eq!(
+            log.direction,
+            i32::from(direction.to_u8()),
+            "Access log: direction mismatch"
+        );
+        assert_eq!(
+            log.device_id,
+            Some(i64::from(device_id)),
+            "Access log: device mismatch"
+        );
+    }
+
+    /// Assert that the passage wrote nothing to the access log
+    ///
+    /// # Panics
+    ///
+    /// Panics if any row was written.
+    pub fn assert_not_logged(&self) {
+        assert!(
+            self.new_logs.is_empty(),
+            "Scenario: unexpected access log rows {:?}",
+            self.new_logs
+        );
+    }
+
+    /// Assert a complete granted passage
+    ///
+    /// Checks the four messages of the grant flow (request → grant →
+    /// `000+80` → `000+81`), every state from `Reading` back to `Idle`, and
+    /// a single granted access log row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any part of the outcome differs.
+    pub fn assert_grant_flow(&self, device_id: u8, card_number: &str, direction: AccessDirection) {
+        assert_eq!(self.messages.len(), 4, "Scenario: grant flow message count");
+        assert_access_request_complete(
+            &self.messages[0],
+            device_id,
+            card_number,
+            direction,
+            ReaderType::Rfid,
+        );
+        assert_access_response_complete(
+            &self.messages[1],
+            device_id,
+            grant_decision(direction),
+            self.response.timeout_seconds(),
+            self.response.display_message(),
+        );
+        assert_turnstile_status_complete(
+            &self.messages[2],
+            device_id,
+            TurnstileState::WaitingRotation,
+            None,
+        );
+        assert_turnstile_status_complete(
+            &self.messages[3],
+            device_id,
+            TurnstileState::RotationCompleted,
+            Some(card_number),
+        );
+
+        self.assert_states(&[
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Granted,
+            TurnstileState::WaitingRotation,
+            TurnstileState::RotationInProgress,
+            TurnstileState::RotationCompleted,
+            TurnstileState::Idle,
+        ]);
+        self.assert_logged(device_id, direction, true, self.response.display_message());
+    }
+
+    /// Assert a complete denied passage with `message` on the display
+    ///
+    /// Checks the request and the denial, the states from `Reading` through
+    /// `Denied` back to `Idle`, and a single denied access log row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any part of the outcome differs.
+    pub fn assert_deny_flow(
+        &self,
+        device_id: u8,
+        card_number: &str,
+        direction: AccessDirection,
+        message: &str,
+    ) {
+        assert_eq!(self.messages.len(), 2, "Scenario: deny flow message count");
+        assert_access_request_complete(
+            &self.messages[0],
+            device_id,
+            card_number,
+            direction,
+            ReaderType::Rfid,
+        );
+        assert_access_response_complete(
+            &self.messages[1],
+            device_id,
+            AccessDecision::Deny,
+            self.response.timeout_seconds(),
+            message,
+        );
+
+        self.assert_states(&[
+            TurnstileState::Reading,
+            TurnstileState::Validating,
+            TurnstileState::Denied,
+            TurnstileState::Idle,
+        ]);
+        self.assert_logged(device_id, direction, false, message);
+    }
+}
+
+/// Grant decision the server sends for a request in `direction`
+fn grant_decision(direction: AccessDirection) -> AccessDecision {
+    match direction {
+        AccessDirection::Entry => AccessDecision::GrantEntry,
+        AccessDirection::Exit => AccessDecision::GrantExit,
+        AccessDirection::Undefined => AccessDecision::GrantBoth,
+    }
+}
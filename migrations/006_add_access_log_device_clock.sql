@@ -0,0 +1,10 @@
+-- Migration: Record device clock alongside server clock in access_logs
+-- Turnstile clocks drift over time. `timestamp` keeps the server receive time
+-- (used for ordering and anti-passback), while `device_timestamp` stores the
+-- time reported by the device in the protocol message so drift can be tracked.
+
+ALTER TABLE access_logs ADD COLUMN device_id INTEGER;           -- Henry device ID (1-99), NULL if unknown
+ALTER TABLE access_logs ADD COLUMN device_timestamp TEXT;       -- ISO8601: time reported by the device clock
+
+-- Drift reports aggregate per device over recent events
+CREATE INDEX idx_access_logs_device_timestamp ON access_logs(device_id, timestamp DESC);
@@ -0,0 +1,65 @@
+-- Migration: Create access_exceptions tables
+-- Temporary access windows for events and visitor groups.
+-- A group of cards and/or users may pass only between starts_at and ends_at,
+-- optionally restricted to specific devices, even when their regular
+-- validity period would deny access.
+
+CREATE TABLE IF NOT EXISTS access_exceptions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Event or group description (e.g., "Feira de Tecnologia 2025")
+    name TEXT NOT NULL,
+
+    -- Access window (ISO8601 format)
+    starts_at TEXT NOT NULL,            -- ISO8601: window opens
+    ends_at TEXT NOT NULL,              -- ISO8601: window closes
+
+    -- Status
+    active BOOLEAN NOT NULL DEFAULT 1,  -- 1=active, 0=disabled
+
+    -- Metadata
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    -- Constraints
+    CHECK (LENGTH(name) >= 1 AND LENGTH(name) <= 100),
+    CHECK (ends_at > starts_at)
+);
+
+-- Group members: either a card number or a user matricula per row.
+-- No foreign keys: visitor cards are usually not registered in `cards`.
+CREATE TABLE IF NOT EXISTS access_exception_members (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    exception_id INTEGER NOT NULL,
+    card_number TEXT,                   -- Normalized (uppercase) card number
+    matricula TEXT,                     -- User matricula
+
+    CHECK ((card_number IS NULL) != (matricula IS NULL)),
+    CHECK (card_number IS NULL OR (LENGTH(card_number) >= 3 AND LENGTH(card_number) <= 20)),
+    UNIQUE (exception_id, card_number),
+    UNIQUE (exception_id, matricula),
+    FOREIGN KEY (exception_id) REFERENCES access_exceptions(id) ON DELETE CASCADE
+);
+
+-- Devices where the exception applies. No rows = all devices.
+CREATE TABLE IF NOT EXISTS access_exception_devices (
+    exception_id INTEGER NOT NULL,
+    device_id INTEGER NOT NULL,         -- Henry device ID (1-99)
+
+    CHECK (device_id >= 1 AND device_id <= 99),
+    PRIMARY KEY (exception_id, device_id),
+    FOREIGN KEY (exception_id) REFERENCES access_exceptions(id) ON DELETE CASCADE
+);
+
+-- Indices for validation lookups
+CREATE INDEX idx_access_exceptions_window ON access_exceptions(active, starts_at, ends_at);
+CREATE INDEX idx_access_exception_members_card ON access_exception_members(card_number);
+CREATE INDEX idx_access_exception_members_matricula ON access_exception_members(matricula);
+
+-- Trigger to update updated_at timestamp
+CREATE TRIGGER update_access_exceptions_timestamp
+AFTER UPDATE ON access_exceptions
+FOR EACH ROW
+BEGIN
+    UPDATE access_exceptions SET updated_at = datetime('now') WHERE id = NEW.id;
+END;
@@ -0,0 +1,30 @@
+-- Migration: Create pending_cards table
+-- Learning mode: card reads that match no registered card are recorded here
+-- so an operator can tap a badge on a new reader and then assign it to a
+-- user. One row per normalized card number; repeated reads bump read_count.
+
+CREATE TABLE IF NOT EXISTS pending_cards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Card number as reported by the reader (untrimmed, original case)
+    raw_value TEXT NOT NULL,
+
+    -- Normalized card number (trimmed, uppercase), same form as cards.numero_cartao
+    card_number TEXT NOT NULL UNIQUE,
+
+    -- Device where the card was last read (NULL when unknown)
+    device_id INTEGER,                  -- Henry device ID (1-99)
+
+    -- Read statistics
+    read_count INTEGER NOT NULL DEFAULT 1,
+    first_seen_at TEXT NOT NULL,        -- ISO8601: first unknown read
+    last_seen_at TEXT NOT NULL,         -- ISO8601: most recent unknown read
+
+    -- Constraints
+    CHECK (LENGTH(card_number) >= 3 AND LENGTH(card_number) <= 20),
+    CHECK (device_id IS NULL OR (device_id >= 1 AND device_id <= 99)),
+    CHECK (read_count >= 1)
+);
+
+-- Index for listing the most recent reads first
+CREATE INDEX idx_pending_cards_last_seen ON pending_cards(last_seen_at DESC);
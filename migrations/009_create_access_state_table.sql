@@ -0,0 +1,95 @@
+-- Migration: Create access_state table
+-- Derived per-credential state (counters, occupancy, last access) kept up to
+-- date from access_logs by trigger. Everything here can be recomputed from
+-- access_logs with the replay module (see turnkey_storage::replay).
+
+CREATE TABLE IF NOT EXISTS access_state (
+    -- Credential the state belongs to (same value as access_logs.card_number)
+    card_number TEXT PRIMARY KEY,
+
+    -- Most recent identified user for this credential (NULL if never identified)
+    user_id INTEGER,
+
+    -- Counters
+    granted_count INTEGER NOT NULL DEFAULT 0,
+    denied_count INTEGER NOT NULL DEFAULT 0,
+
+    -- Last access (any outcome) and last granted passage (ISO8601 format)
+    last_access_at TEXT NOT NULL,
+    last_granted_at TEXT,
+    last_direction INTEGER,             -- Direction of the last granted access (0/1/2)
+
+    -- Occupancy: last granted access was an entry
+    inside BOOLEAN NOT NULL DEFAULT 0,
+
+    -- Highest access_logs.id folded into this row
+    last_log_id INTEGER NOT NULL,
+
+    CHECK (granted_count >= 0 AND denied_count >= 0),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_access_state_inside ON access_state(inside) WHERE inside = 1;
+CREATE INDEX idx_access_state_user_id ON access_state(user_id);
+
+-- Backfill from logs written before this migration
+INSERT INTO access_state (
+    card_number, user_id, granted_count, denied_count,
+    last_access_at, last_granted_at, last_direction, inside, last_log_id
+)
+SELECT
+    l.card_number,
+    (SELECT u.user_id FROM access_logs u
+     WHERE u.card_number = l.card_number AND u.user_id IS NOT NULL
+     ORDER BY u.id DESC LIMIT 1),
+    SUM(CASE WHEN l.granted THEN 1 ELSE 0 END),
+    SUM(CASE WHEN l.granted THEN 0 ELSE 1 END),
+    (SELECT a.timestamp FROM access_logs a
+     WHERE a.card_number = l.card_number
+     ORDER BY a.id DESC LIMIT 1),
+    (SELECT g.timestamp FROM access_logs g
+     WHERE g.card_number = l.card_number AND g.granted
+     ORDER BY g.id DESC LIMIT 1),
+    (SELECT g.direction FROM access_logs g
+     WHERE g.card_number = l.card_number AND g.granted
+     ORDER BY g.id DESC LIMIT 1),
+    COALESCE((SELECT g.direction = 1 FROM access_logs g
+              WHERE g.card_number = l.card_number AND g.granted
+              ORDER BY g.id DESC LIMIT 1), 0),
+    MAX(l.id)
+FROM access_logs l
+GROUP BY l.card_number;
+
+-- Fold every new log into the derived state
+CREATE TRIGGER update_access_state_on_log
+AFTER INSERT ON access_logs
+FOR EACH ROW
+BEGIN
+    INSERT INTO access_state (
+        card_number, user_id, granted_count, denied_count,
+        last_access_at, last_granted_at, last_direction, inside, last_log_id
+    )
+    VALUES (
+        NEW.card_number,
+        NEW.user_id,
+        CASE WHEN NEW.granted THEN 1 ELSE 0 END,
+        CASE WHEN NEW.granted THEN 0 ELSE 1 END,
+        NEW.timestamp,
+        CASE WHEN NEW.granted THEN NEW.timestamp END,
+        CASE WHEN NEW.granted THEN NEW.direction END,
+        CASE WHEN NEW.granted AND NEW.direction = 1 THEN 1 ELSE 0 END,
+        NEW.id
+    )
+    ON CONFLICT (card_number) DO UPDATE SET
+        user_id = COALESCE(excluded.user_id, access_state.user_id),
+        granted_count = access_state.granted_count + excluded.granted_count,
+        denied_count = access_state.denied_count + excluded.denied_count,
+        last_access_at = excluded.last_access_at,
+        last_granted_at = CASE WHEN NEW.granted THEN excluded.last_granted_at
+                               ELSE access_state.last_granted_at END,
+        last_direction = CASE WHEN NEW.granted THEN excluded.last_direction
+                              ELSE access_state.last_direction END,
+        inside = CASE WHEN NEW.granted THEN excluded.inside
+                      ELSE access_state.inside END,
+        last_log_id = excluded.last_log_id;
+END;
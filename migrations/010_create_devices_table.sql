@@ -0,0 +1,24 @@
+-- Migration: Create devices table
+-- Per-device settings kept by the server. Starts with the HMAC shared secret
+-- used to authenticate messages from a turnstile (see turnkey_protocol::signing).
+-- Devices without a row behave as before: unsigned messages are accepted.
+
+CREATE TABLE IF NOT EXISTS devices (
+    -- Henry device ID (1-99)
+    device_id INTEGER PRIMARY KEY,
+
+    -- HMAC-SHA256 shared secret (NULL when the device does not sign)
+    hmac_key BLOB,
+
+    -- Reject unsigned connections from this device
+    signing_required BOOLEAN NOT NULL DEFAULT 0,
+
+    -- Audit
+    created_at TEXT NOT NULL,           -- ISO8601
+    updated_at TEXT NOT NULL,           -- ISO8601
+
+    -- Constraints
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (hmac_key IS NULL OR LENGTH(hmac_key) >= 16),
+    CHECK (signing_required = 0 OR hmac_key IS NOT NULL)
+);
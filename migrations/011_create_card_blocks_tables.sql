@@ -0,0 +1,45 @@
+-- Migration: Bulk card block/unblock audit
+-- Security blocks or unblocks many cards at once (e.g., every card of a
+-- terminated contractor company). Each operation is recorded as a batch with
+-- a reason code and the operator who ran it, plus one row per affected card.
+
+-- Employer/contractor company of a user, used to select cards in bulk
+ALTER TABLE users ADD COLUMN empresa TEXT;      -- Company name (max 100 chars)
+
+CREATE INDEX idx_users_empresa ON users(empresa) WHERE empresa IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS card_block_batches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Operation
+    action TEXT NOT NULL,               -- 'BLOCK' or 'UNBLOCK'
+    reason_code TEXT NOT NULL,          -- e.g., 'TERMINATED', 'LOST'
+    selector TEXT NOT NULL,             -- Description of the targeted cards
+
+    -- Attribution
+    operator TEXT NOT NULL,             -- Operator login or name
+    card_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,           -- ISO8601
+
+    -- Constraints
+    CHECK (action IN ('BLOCK', 'UNBLOCK')),
+    CHECK (LENGTH(reason_code) >= 1 AND LENGTH(reason_code) <= 30),
+    CHECK (LENGTH(operator) >= 1 AND LENGTH(operator) <= 100),
+    CHECK (card_count >= 0)
+);
+
+-- Cards changed by a batch. card_id is kept NULL-able so history survives
+-- card deletion; numero_cartao and matricula are copied for that reason.
+CREATE TABLE IF NOT EXISTS card_block_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    batch_id INTEGER NOT NULL,
+    card_id INTEGER,
+    numero_cartao TEXT NOT NULL,
+    matricula TEXT NOT NULL,
+
+    FOREIGN KEY (batch_id) REFERENCES card_block_batches(id) ON DELETE CASCADE,
+    FOREIGN KEY (card_id) REFERENCES cards(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_card_block_events_batch ON card_block_events(batch_id);
+CREATE INDEX idx_card_block_events_card ON card_block_events(card_id);
@@ -0,0 +1,9 @@
+-- Migration: Per-device access methods
+-- Devices may lack a reader or forbid a method at a given entrance (e.g. no
+-- PIN at the perimeter gate). These flags are combined with the user's
+-- allow_card/allow_bio/allow_keypad during validation. Existing devices keep
+-- every method enabled, as do devices without a row.
+
+ALTER TABLE devices ADD COLUMN allow_card BOOLEAN NOT NULL DEFAULT 1;
+ALTER TABLE devices ADD COLUMN allow_bio BOOLEAN NOT NULL DEFAULT 1;
+ALTER TABLE devices ADD COLUMN allow_keypad BOOLEAN NOT NULL DEFAULT 1;
@@ -0,0 +1,64 @@
+-- Migration: Site/zone hierarchy
+-- Devices are grouped into zones (a building, a floor, a parking lot) and
+-- zones into sites, so reports, access rules and commands can target "all
+-- devices in Building A" instead of listing device IDs. A device belongs to
+-- at most one zone; devices without a zone are not part of any site.
+
+CREATE TABLE IF NOT EXISTS sites (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    CHECK (LENGTH(name) >= 1 AND LENGTH(name) <= 100)
+);
+
+CREATE TABLE IF NOT EXISTS zones (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    site_id INTEGER NOT NULL,
+
+    name TEXT NOT NULL,                 -- Unique within the site
+    description TEXT,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    CHECK (LENGTH(name) >= 1 AND LENGTH(name) <= 100),
+    UNIQUE (site_id, name),
+    FOREIGN KEY (site_id) REFERENCES sites(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_zones_site_id ON zones(site_id);
+
+ALTER TABLE devices ADD COLUMN zone_id INTEGER REFERENCES zones(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_devices_zone_id ON devices(zone_id);
+
+-- Access exceptions may be restricted to whole zones in addition to single
+-- devices; an exception with neither applies everywhere. Deleting a zone
+-- still referenced here fails instead of silently widening the exception.
+CREATE TABLE IF NOT EXISTS access_exception_zones (
+    exception_id INTEGER NOT NULL,
+    zone_id INTEGER NOT NULL,
+
+    PRIMARY KEY (exception_id, zone_id),
+    FOREIGN KEY (exception_id) REFERENCES access_exceptions(id) ON DELETE CASCADE,
+    FOREIGN KEY (zone_id) REFERENCES zones(id)
+);
+
+CREATE TRIGGER update_sites_timestamp
+AFTER UPDATE ON sites
+FOR EACH ROW
+BEGIN
+    UPDATE sites SET updated_at = datetime('now') WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_zones_timestamp
+AFTER UPDATE ON zones
+FOR EACH ROW
+BEGIN
+    UPDATE zones SET updated_at = datetime('now') WHERE id = NEW.id;
+END;
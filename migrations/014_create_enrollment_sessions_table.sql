@@ -0,0 +1,20 @@
+-- Migration: Card enrollment via reader
+-- An administrator puts a device into enrollment mode for a given user; the
+-- next card read on that device is captured into pending_cards bound to the
+-- user instead of being validated. At most one open session per device.
+
+ALTER TABLE pending_cards ADD COLUMN matricula TEXT REFERENCES users(matricula) ON DELETE SET NULL;
+
+CREATE TABLE IF NOT EXISTS enrollment_sessions (
+    device_id INTEGER PRIMARY KEY,      -- Henry device ID (1-99)
+
+    -- User the captured card will be registered for
+    matricula TEXT NOT NULL,
+
+    started_at TEXT NOT NULL,           -- ISO8601
+    expires_at TEXT NOT NULL,           -- ISO8601: session ignored afterwards
+
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (expires_at > started_at),
+    FOREIGN KEY (matricula) REFERENCES users(matricula) ON DELETE CASCADE
+);
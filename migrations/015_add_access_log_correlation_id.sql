@@ -0,0 +1,10 @@
+-- Migration: Correlation ID for tracing one passage across components
+-- Devices generate a UUID when a credential is read and send it with the
+-- access request. Storing it lets the access log be joined with device and
+-- server logs for the same passage. NULL for devices that do not send one
+-- (real Henry equipment) and for logs written before this migration.
+
+ALTER TABLE access_logs ADD COLUMN correlation_id TEXT;         -- Hyphenated UUID, NULL if not provided
+
+CREATE INDEX idx_access_logs_correlation_id ON access_logs(correlation_id)
+    WHERE correlation_id IS NOT NULL;
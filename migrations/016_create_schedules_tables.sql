@@ -0,0 +1,68 @@
+-- Migration: Weekly access schedules
+-- A schedule lists the time intervals in which access is allowed on each
+-- weekday, in site local time. Intervals whose end is not after their start
+-- cross midnight (23:00-02:00) and belong to the day they start on.
+-- Exceptions replace the intervals starting on a given date; an exception
+-- without intervals closes the day. Overlap checks and the lookup bitmaps
+-- live in the model (WeeklySchedule::compile), not in the database.
+
+CREATE TABLE IF NOT EXISTS schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    name TEXT NOT NULL UNIQUE,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    CHECK (LENGTH(name) >= 1 AND LENGTH(name) <= 100)
+);
+
+CREATE TABLE IF NOT EXISTS schedule_intervals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schedule_id INTEGER NOT NULL,
+
+    weekday INTEGER NOT NULL,           -- ISO weekday: 1=Monday .. 7=Sunday
+    start_minute INTEGER NOT NULL,      -- Minutes since midnight, 0-1439
+    end_minute INTEGER NOT NULL,        -- 1-1440; before start_minute if overnight
+
+    CHECK (weekday BETWEEN 1 AND 7),
+    CHECK (start_minute BETWEEN 0 AND 1439),
+    CHECK (end_minute BETWEEN 1 AND 1440),
+    CHECK (start_minute != end_minute),
+    FOREIGN KEY (schedule_id) REFERENCES schedules(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_schedule_intervals_schedule_id ON schedule_intervals(schedule_id);
+
+CREATE TABLE IF NOT EXISTS schedule_exceptions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    schedule_id INTEGER NOT NULL,
+
+    date TEXT NOT NULL,                 -- YYYY-MM-DD, site local date
+
+    UNIQUE (schedule_id, date),
+    FOREIGN KEY (schedule_id) REFERENCES schedules(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS schedule_exception_intervals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    exception_id INTEGER NOT NULL,
+
+    start_minute INTEGER NOT NULL,
+    end_minute INTEGER NOT NULL,
+
+    CHECK (start_minute BETWEEN 0 AND 1439),
+    CHECK (end_minute BETWEEN 1 AND 1440),
+    CHECK (start_minute != end_minute),
+    FOREIGN KEY (exception_id) REFERENCES schedule_exceptions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_schedule_exception_intervals_exception_id
+    ON schedule_exception_intervals(exception_id);
+
+CREATE TRIGGER update_schedules_timestamp
+AFTER UPDATE ON schedules
+FOR EACH ROW
+BEGIN
+    UPDATE schedules SET updated_at = datetime('now') WHERE id = NEW.id;
+END;
@@ -0,0 +1,27 @@
+-- Migration: Operator overrides of denied accesses
+-- A guard may let a denied person through anyway. The grant is written to
+-- access_logs like any other passage (so occupancy and reports see it), and
+-- this table links it to the denial it overrides, with who decided and why.
+
+CREATE TABLE IF NOT EXISTS access_overrides (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Linked access logs
+    denied_log_id INTEGER NOT NULL UNIQUE,  -- Denial being overridden (at most one override each)
+    granted_log_id INTEGER NOT NULL UNIQUE, -- Granted entry written for the override
+
+    -- Attribution
+    operator_id TEXT NOT NULL,          -- Guard login or badge
+    reason TEXT NOT NULL,               -- Free text justification
+    device_id INTEGER,                  -- Device released (NULL if the denial had none)
+    created_at TEXT NOT NULL,           -- ISO8601
+
+    -- Constraints
+    CHECK (LENGTH(operator_id) >= 1 AND LENGTH(operator_id) <= 20),
+    CHECK (LENGTH(reason) >= 1 AND LENGTH(reason) <= 100),
+    FOREIGN KEY (denied_log_id) REFERENCES access_logs(id),
+    FOREIGN KEY (granted_log_id) REFERENCES access_logs(id)
+);
+
+CREATE INDEX idx_access_overrides_operator ON access_overrides(operator_id, created_at DESC);
+CREATE INDEX idx_access_overrides_created_at ON access_overrides(created_at DESC);
@@ -0,0 +1,20 @@
+-- Migration: Local data freshness
+-- Offline validation trusts whatever the local database holds. After days
+-- without a sync, revoked cards may still be granted, so the time of the
+-- last successful sync is kept here (a single row) and compared against a
+-- staleness threshold at validation time. Devices at sensitive entrances
+-- can be set to deny every request while the data is stale.
+
+CREATE TABLE IF NOT EXISTS sync_state (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+
+    last_success_at TEXT,               -- ISO8601, NULL if never synced
+    last_source TEXT,                   -- What delivered the data (e.g. "bundle")
+    last_attempt_at TEXT,               -- ISO8601, successful or not
+    last_error TEXT                     -- Error of the last failed attempt (NULL after a success)
+);
+
+INSERT OR IGNORE INTO sync_state (id) VALUES (1);
+
+-- Deny offline requests on this device while the local data is stale
+ALTER TABLE devices ADD COLUMN deny_when_stale BOOLEAN NOT NULL DEFAULT 0;
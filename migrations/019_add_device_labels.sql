@@ -0,0 +1,19 @@
+-- Migration: Device names, locations and tags
+-- Device IDs 1-99 mean little to whoever reads a log or report. Operators
+-- can give each device a name and location and tag it ("garage",
+-- "high-security"); these are shown next to the ID wherever a device is
+-- mentioned. The protocol keeps using the numeric ID.
+
+ALTER TABLE devices ADD COLUMN name TEXT CHECK (name IS NULL OR LENGTH(name) BETWEEN 1 AND 40);
+ALTER TABLE devices ADD COLUMN location TEXT CHECK (location IS NULL OR LENGTH(location) BETWEEN 1 AND 40);
+
+CREATE TABLE IF NOT EXISTS device_tags (
+    device_id INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+
+    CHECK (LENGTH(tag) >= 1 AND LENGTH(tag) <= 40),
+    PRIMARY KEY (device_id, tag),
+    FOREIGN KEY (device_id) REFERENCES devices(device_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_device_tags_tag ON device_tags(tag);
@@ -0,0 +1,25 @@
+-- Migration: Daily passage quotas
+-- Gyms and cafeterias cap how many times a person may pass per day. A quota
+-- applies to one user or to every user of a company (users.empresa); a user
+-- quota wins over the company one. Passages are counted from access_logs,
+-- so there is no counter to reset: the day boundary is applied when the
+-- count is taken (see QuotaDay).
+
+CREATE TABLE IF NOT EXISTS daily_quotas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Scope: exactly one is set
+    user_id INTEGER UNIQUE,
+    empresa TEXT UNIQUE,
+
+    max_passages INTEGER NOT NULL,      -- Granted passages allowed per day
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    -- Constraints
+    CHECK ((user_id IS NULL) != (empresa IS NULL)),
+    CHECK (empresa IS NULL OR (LENGTH(empresa) >= 1 AND LENGTH(empresa) <= 100)),
+    CHECK (max_passages >= 1),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
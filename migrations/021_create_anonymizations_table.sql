@@ -0,0 +1,28 @@
+-- Migration: Audit of access history anonymization
+-- Erasure requests (LGPD/GDPR) replace a person's identifying fields in the
+-- access history with pseudonyms while keeping every row, so passage counts
+-- and reports stay correct. Each run is recorded here. The subject is stored
+-- only as its pseudonym: keeping the matricula would undo the erasure.
+
+CREATE TABLE IF NOT EXISTS anonymizations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Subject and attribution
+    subject TEXT NOT NULL,              -- Pseudonym that replaced the matricula
+    requested_by TEXT NOT NULL,         -- Operator who ran the job
+    reason TEXT NOT NULL,               -- e.g., erasure request reference
+
+    -- Rows rewritten per table
+    credentials INTEGER NOT NULL DEFAULT 0,
+    access_logs INTEGER NOT NULL DEFAULT 0,
+    access_states INTEGER NOT NULL DEFAULT 0,
+    card_block_events INTEGER NOT NULL DEFAULT 0,
+
+    performed_at TEXT NOT NULL,         -- ISO8601
+
+    -- Constraints
+    CHECK (LENGTH(requested_by) >= 1 AND LENGTH(requested_by) <= 100),
+    CHECK (LENGTH(reason) >= 1 AND LENGTH(reason) <= 200)
+);
+
+CREATE INDEX idx_anonymizations_performed_at ON anonymizations(performed_at DESC);
@@ -0,0 +1,7 @@
+-- Migration: Per-device verification mode
+-- High-security doors require the card followed by the fingerprint of the
+-- card's owner. 'card_plus_biometric' devices only grant once both factors
+-- are verified; every existing device keeps single-credential access.
+
+ALTER TABLE devices ADD COLUMN verification_mode TEXT NOT NULL DEFAULT 'single_factor'
+    CHECK (verification_mode IN ('single_factor', 'card_plus_biometric'));
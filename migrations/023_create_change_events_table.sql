@@ -0,0 +1,100 @@
+-- Migration: Change data capture outbox
+-- Caches and device sync have to learn when users, cards or fingerprint
+-- templates change, whichever code path wrote them (repositories, imports,
+-- bundles). Triggers append every change to this outbox in the writing
+-- transaction, so an event exists exactly when its change committed.
+-- Consumers read past their own cursor and advance it once an event is
+-- handled, which gives at-least-once delivery across restarts.
+
+CREATE TABLE IF NOT EXISTS change_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    entity TEXT NOT NULL,               -- 'user', 'card' or 'biometric_template'
+    entity_key TEXT NOT NULL,           -- matricula, card number or matricula:posicao
+    matricula TEXT,                     -- Owning user, for cards and templates
+    operation TEXT NOT NULL,            -- 'insert', 'update' or 'delete'
+    changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+
+    CHECK (entity IN ('user', 'card', 'biometric_template')),
+    CHECK (operation IN ('insert', 'update', 'delete'))
+);
+
+CREATE INDEX idx_change_events_changed_at ON change_events(changed_at);
+
+-- Last event each consumer has handled
+CREATE TABLE IF NOT EXISTS change_cursors (
+    consumer TEXT PRIMARY KEY,
+    last_event_id INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+
+    CHECK (LENGTH(consumer) >= 1 AND LENGTH(consumer) <= 64)
+);
+
+CREATE TRIGGER capture_user_insert AFTER INSERT ON users
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('user', NEW.matricula, NEW.matricula, 'insert');
+END;
+
+-- Column lists leave out updated_at, so the timestamp triggers' own updates
+-- are not captured a second time. A renamed key is an update of the new key
+-- and a delete of the old one.
+CREATE TRIGGER capture_user_update
+AFTER UPDATE OF pis, nome, matricula, cpf, validade_inicio, validade_fim, ativo,
+                allow_card, allow_bio, allow_keypad, codigo, empresa
+ON users
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    SELECT 'user', OLD.matricula, OLD.matricula, 'delete'
+    WHERE OLD.matricula != NEW.matricula;
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('user', NEW.matricula, NEW.matricula, 'update');
+END;
+
+CREATE TRIGGER capture_user_delete AFTER DELETE ON users
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('user', OLD.matricula, OLD.matricula, 'delete');
+END;
+
+CREATE TRIGGER capture_card_insert AFTER INSERT ON cards
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('card', NEW.numero_cartao, NEW.matricula, 'insert');
+END;
+
+CREATE TRIGGER capture_card_update
+AFTER UPDATE OF numero_cartao, matricula, user_id, validade_inicio, validade_fim, ativo
+ON cards
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    SELECT 'card', OLD.numero_cartao, OLD.matricula, 'delete'
+    WHERE OLD.numero_cartao != NEW.numero_cartao;
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('card', NEW.numero_cartao, NEW.matricula, 'update');
+END;
+
+CREATE TRIGGER capture_card_delete AFTER DELETE ON cards
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('card', OLD.numero_cartao, OLD.matricula, 'delete');
+END;
+
+CREATE TRIGGER capture_template_insert AFTER INSERT ON biometric_templates
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('biometric_template', NEW.matricula || ':' || NEW.posicao, NEW.matricula, 'insert');
+END;
+
+CREATE TRIGGER capture_template_update AFTER UPDATE OF template_data, matricula, posicao
+ON biometric_templates
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('biometric_template', NEW.matricula || ':' || NEW.posicao, NEW.matricula, 'update');
+END;
+
+CREATE TRIGGER capture_template_delete AFTER DELETE ON biometric_templates
+BEGIN
+    INSERT INTO change_events (entity, entity_key, matricula, operation)
+    VALUES ('biometric_template', OLD.matricula || ':' || OLD.posicao, OLD.matricula, 'delete');
+END;
@@ -0,0 +1,6 @@
+-- Migration: Per-device balance display
+-- Devices at quota-limited doors can show the passages left today next to
+-- the grant message ("Saldo: 12"). Off by default, since the extra text
+-- replaces part of the greeting on small displays.
+
+ALTER TABLE devices ADD COLUMN show_balance INTEGER NOT NULL DEFAULT 0;
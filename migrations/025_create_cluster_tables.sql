@@ -0,0 +1,37 @@
+-- Migration: Cluster mode
+-- Several validation servers may share one database. Each device is leased
+-- to one server at a time; a lease that is not renewed lapses, so a dead
+-- server's devices move to the survivors. Decisions are recorded per
+-- device and correlation ID, so a request retried against another server
+-- gets the original answer instead of a second validation and log entry.
+-- Statements stick to SQL that behaves the same on PostgreSQL.
+
+CREATE TABLE IF NOT EXISTS device_leases (
+    device_id INTEGER PRIMARY KEY,
+    node_id TEXT NOT NULL,              -- Server holding the device
+    acquired_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (LENGTH(node_id) >= 1 AND LENGTH(node_id) <= 64)
+);
+
+CREATE INDEX idx_device_leases_node_id ON device_leases(node_id);
+
+CREATE TABLE IF NOT EXISTS access_decisions (
+    device_id INTEGER NOT NULL,
+    correlation_id TEXT NOT NULL,       -- Hyphenated UUID of the passage
+    node_id TEXT NOT NULL,              -- Server that claimed the decision
+    claimed_at TEXT NOT NULL,
+
+    -- NULL until the claiming server has decided
+    decision TEXT,                      -- 'grant_both', 'grant_entry', 'grant_exit' or 'deny'
+    timeout_seconds INTEGER,
+    display_message TEXT,
+    decided_at TEXT,
+
+    PRIMARY KEY (device_id, correlation_id),
+    CHECK (decision IS NULL OR decision IN ('grant_both', 'grant_entry', 'grant_exit', 'deny'))
+);
+
+CREATE INDEX idx_access_decisions_claimed_at ON access_decisions(claimed_at);
@@ -0,0 +1,168 @@
+-- Migration: PIN-only keypad access
+-- Contractors get a numeric code and no card; the code typed on the keypad
+-- identifies the user by itself, so two users must never share one. Codes
+-- are stored salted and hashed, which defeats a plain UNIQUE check between
+-- different users: the repository compares a new code against every stored
+-- one when it is assigned. The index below still rejects two identical
+-- stored values (e.g. legacy plaintext codes).
+--
+-- A device where wrong codes keep being typed is locked for a cooldown,
+-- recorded here so the lockout survives restarts.
+--
+-- Keypad passages are logged with reader_type 3. SQLite cannot change a
+-- CHECK constraint in place, so access_logs is rebuilt. Migrations run with
+-- foreign keys on, and access_overrides references access_logs, so it is
+-- rebuilt alongside: both new tables are filled, the old ones dropped child
+-- first, and the new ones renamed into place. Views, the access_state
+-- trigger and all indices are recreated unchanged.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_codigo_unique ON users(codigo) WHERE codigo IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS keypad_lockouts (
+    device_id INTEGER PRIMARY KEY,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,  -- Wrong codes since the last success or lockout
+    last_failed_at TEXT,
+    locked_until TEXT,                           -- Keypad refused until this time
+
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (failed_attempts >= 0)
+);
+
+DROP VIEW recent_denied_accesses;
+DROP VIEW daily_access_stats;
+
+CREATE TABLE access_logs_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    matricula TEXT,
+    card_number TEXT NOT NULL,
+    direction INTEGER NOT NULL,         -- 0=Undefined, 1=Entry, 2=Exit
+    reader_type INTEGER NOT NULL,       -- 1=RFID, 3=Keypad, 5=Biometric
+    granted BOOLEAN NOT NULL,
+    display_message TEXT,
+    timestamp TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    device_id INTEGER,
+    device_timestamp TEXT,
+    correlation_id TEXT,
+
+    CHECK (direction >= 0 AND direction <= 2),
+    CHECK (reader_type IN (1, 3, 5)),
+    CHECK (LENGTH(card_number) >= 3 AND LENGTH(card_number) <= 20),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (matricula) REFERENCES users(matricula) ON DELETE SET NULL
+);
+
+INSERT INTO access_logs_new (
+    id, user_id, matricula, card_number, direction, reader_type, granted,
+    display_message, timestamp, created_at, device_id, device_timestamp, correlation_id
+)
+SELECT
+    id, user_id, matricula, card_number, direction, reader_type, granted,
+    display_message, timestamp, created_at, device_id, device_timestamp, correlation_id
+FROM access_logs;
+
+CREATE TABLE access_overrides_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    denied_log_id INTEGER NOT NULL UNIQUE,
+    granted_log_id INTEGER NOT NULL UNIQUE,
+    operator_id TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    device_id INTEGER,
+    created_at TEXT NOT NULL,
+
+    CHECK (LENGTH(operator_id) >= 1 AND LENGTH(operator_id) <= 20),
+    CHECK (LENGTH(reason) >= 1 AND LENGTH(reason) <= 100),
+    FOREIGN KEY (denied_log_id) REFERENCES access_logs_new(id),
+    FOREIGN KEY (granted_log_id) REFERENCES access_logs_new(id)
+);
+
+INSERT INTO access_overrides_new (
+    id, denied_log_id, granted_log_id, operator_id, reason, device_id, created_at
+)
+SELECT id, denied_log_id, granted_log_id, operator_id, reason, device_id, created_at
+FROM access_overrides;
+
+DROP TABLE access_overrides;
+DROP TABLE access_logs;
+ALTER TABLE access_logs_new RENAME TO access_logs;
+ALTER TABLE access_overrides_new RENAME TO access_overrides;
+
+CREATE INDEX idx_access_logs_timestamp ON access_logs(timestamp DESC);
+CREATE INDEX idx_access_logs_user_id ON access_logs(user_id);
+CREATE INDEX idx_access_logs_matricula ON access_logs(matricula);
+CREATE INDEX idx_access_logs_card_number ON access_logs(card_number);
+CREATE INDEX idx_access_logs_granted ON access_logs(granted);
+CREATE INDEX idx_access_logs_direction ON access_logs(direction);
+CREATE INDEX idx_access_logs_reader_type ON access_logs(reader_type);
+CREATE INDEX idx_access_logs_user_timestamp ON access_logs(user_id, timestamp DESC);
+CREATE INDEX idx_access_logs_granted_timestamp ON access_logs(granted, timestamp DESC);
+CREATE INDEX idx_access_logs_card_timestamp ON access_logs(card_number, timestamp DESC);
+CREATE INDEX idx_access_logs_device_timestamp ON access_logs(device_id, timestamp DESC);
+CREATE INDEX idx_access_logs_correlation_id ON access_logs(correlation_id)
+    WHERE correlation_id IS NOT NULL;
+CREATE INDEX idx_access_overrides_operator ON access_overrides(operator_id, created_at DESC);
+CREATE INDEX idx_access_overrides_created_at ON access_overrides(created_at DESC);
+
+-- View for recent denied accesses (security monitoring)
+CREATE VIEW recent_denied_accesses AS
+SELECT
+    al.id,
+    al.card_number,
+    al.matricula,
+    u.nome as user_name,
+    al.direction,
+    al.reader_type,
+    al.display_message,
+    al.timestamp
+FROM access_logs al
+LEFT JOIN users u ON al.user_id = u.id
+WHERE al.granted = 0
+ORDER BY al.timestamp DESC
+LIMIT 100;
+
+-- View for daily access statistics
+CREATE VIEW daily_access_stats AS
+SELECT
+    DATE(timestamp) as date,
+    COUNT(*) as total_attempts,
+    SUM(CASE WHEN granted = 1 THEN 1 ELSE 0 END) as granted_count,
+    SUM(CASE WHEN granted = 0 THEN 1 ELSE 0 END) as denied_count,
+    SUM(CASE WHEN direction = 1 THEN 1 ELSE 0 END) as entry_count,
+    SUM(CASE WHEN direction = 2 THEN 1 ELSE 0 END) as exit_count
+FROM access_logs
+GROUP BY DATE(timestamp)
+ORDER BY date DESC;
+
+CREATE TRIGGER update_access_state_on_log
+AFTER INSERT ON access_logs
+FOR EACH ROW
+BEGIN
+    INSERT INTO access_state (
+        card_number, user_id, granted_count, denied_count,
+        last_access_at, last_granted_at, last_direction, inside, last_log_id
+    )
+    VALUES (
+        NEW.card_number,
+        NEW.user_id,
+        CASE WHEN NEW.granted THEN 1 ELSE 0 END,
+        CASE WHEN NEW.granted THEN 0 ELSE 1 END,
+        NEW.timestamp,
+        CASE WHEN NEW.granted THEN NEW.timestamp END,
+        CASE WHEN NEW.granted THEN NEW.direction END,
+        CASE WHEN NEW.granted AND NEW.direction = 1 THEN 1 ELSE 0 END,
+        NEW.id
+    )
+    ON CONFLICT (card_number) DO UPDATE SET
+        user_id = COALESCE(excluded.user_id, access_state.user_id),
+        granted_count = access_state.granted_count + excluded.granted_count,
+        denied_count = access_state.denied_count + excluded.denied_count,
+        last_access_at = excluded.last_access_at,
+        last_granted_at = CASE WHEN NEW.granted THEN excluded.last_granted_at
+                               ELSE access_state.last_granted_at END,
+        last_direction = CASE WHEN NEW.granted THEN excluded.last_direction
+                              ELSE access_state.last_direction END,
+        inside = CASE WHEN NEW.granted THEN excluded.inside
+                      ELSE access_state.inside END,
+        last_log_id = excluded.last_log_id;
+END;
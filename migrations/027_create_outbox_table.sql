@@ -0,0 +1,29 @@
+-- Migration: Transactional outbox for protocol notifications
+-- A notification the server must receive about an access (the event that
+-- was just logged) is written in the same transaction as its access log,
+-- so a crash between logging and sending cannot lose it. A dispatcher
+-- sends pending rows in order and marks them sent, retrying failures with
+-- backoff, which gives at-least-once delivery across restarts.
+
+CREATE TABLE IF NOT EXISTS outbox_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    device_id INTEGER NOT NULL,         -- Device the message is sent as
+    message TEXT NOT NULL,              -- Protocol message, without framing
+    access_log_id INTEGER,              -- Access log the notification is about
+
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TEXT NOT NULL,      -- Not sent before this time
+    sent_at TEXT,                       -- NULL until delivered
+
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+
+    FOREIGN KEY (access_log_id) REFERENCES access_logs(id) ON DELETE SET NULL,
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (attempts >= 0)
+);
+
+-- Pending messages in sending order
+CREATE INDEX idx_outbox_messages_pending ON outbox_messages(id)
+    WHERE sent_at IS NULL;
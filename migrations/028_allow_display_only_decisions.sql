@@ -0,0 +1,37 @@
+-- Migration: Message-only decisions in the cluster ledger
+-- A server may answer with a message and keep the turnstile locked
+-- (00+0, e.g. "Dirija-se a recepcao"). Retries must get that answer back
+-- like any other, so the recorded decision accepts 'display_only'.
+--
+-- SQLite cannot change a CHECK constraint in place, so access_decisions is
+-- rebuilt. No table references it; its index is recreated unchanged.
+
+CREATE TABLE access_decisions_new (
+    device_id INTEGER NOT NULL,
+    correlation_id TEXT NOT NULL,       -- Hyphenated UUID of the passage
+    node_id TEXT NOT NULL,              -- Server that claimed the decision
+    claimed_at TEXT NOT NULL,
+
+    -- NULL until the claiming server has decided
+    decision TEXT,                      -- 'grant_both', 'grant_entry', 'grant_exit', 'deny' or 'display_only'
+    timeout_seconds INTEGER,
+    display_message TEXT,
+    decided_at TEXT,
+
+    PRIMARY KEY (device_id, correlation_id),
+    CHECK (decision IS NULL OR decision IN ('grant_both', 'grant_entry', 'grant_exit', 'deny', 'display_only'))
+);
+
+INSERT INTO access_decisions_new (
+    device_id, correlation_id, node_id, claimed_at,
+    decision, timeout_seconds, display_message, decided_at
+)
+SELECT
+    device_id, correlation_id, node_id, claimed_at,
+    decision, timeout_seconds, display_message, decided_at
+FROM access_decisions;
+
+DROP TABLE access_decisions;
+ALTER TABLE access_decisions_new RENAME TO access_decisions;
+
+CREATE INDEX idx_access_decisions_claimed_at ON access_decisions(claimed_at);
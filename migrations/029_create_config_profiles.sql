@@ -0,0 +1,70 @@
+-- Migration: Device configuration profiles
+-- A profile holds device settings (timeouts, display messages, validation
+-- mode, feedback) shared by many devices. Profiles may inherit from a
+-- parent profile, and each device may override single settings on top of
+-- its profile. Every setting column is nullable: NULL means "inherit".
+-- Resolution order, last wins: built-in defaults, the parent chain from the
+-- root down, the assigned profile, the device overrides (see
+-- ResolvedConfig). Cycles in the parent chain are rejected by the
+-- repository.
+
+CREATE TABLE IF NOT EXISTS config_profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    name TEXT NOT NULL UNIQUE,
+    parent_id INTEGER,                  -- Profile inherited from (NULL = defaults)
+
+    -- Settings (NULL = inherit)
+    online_timeout_ms INTEGER,          -- TIMEOUT_ON, 500-10000
+    validation_mode TEXT,               -- TIPO_VALIDA: 'O', 'F', 'A' or 'S'
+    entry_message TEXT,                 -- MSG_ENTRADA
+    exit_message TEXT,                  -- MSG_SAIDA
+    message_secs INTEGER,               -- TEMPO_MSG, 0-255
+    volume INTEGER,                     -- VOLUME, 0-9
+    feedback TEXT,                      -- SINAL
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    CHECK (LENGTH(name) >= 1 AND LENGTH(name) <= 100),
+    CHECK (parent_id IS NULL OR parent_id != id),
+    CHECK (online_timeout_ms IS NULL OR online_timeout_ms BETWEEN 500 AND 10000),
+    CHECK (validation_mode IS NULL OR validation_mode IN ('O', 'F', 'A', 'S')),
+    CHECK (entry_message IS NULL OR (LENGTH(entry_message) >= 1 AND LENGTH(entry_message) <= 40)),
+    CHECK (exit_message IS NULL OR (LENGTH(exit_message) >= 1 AND LENGTH(exit_message) <= 40)),
+    CHECK (message_secs IS NULL OR message_secs BETWEEN 0 AND 255),
+    CHECK (volume IS NULL OR volume BETWEEN 0 AND 9),
+    CHECK (feedback IS NULL OR feedback IN ('silent', 'beep', 'light', 'beep_and_light')),
+    FOREIGN KEY (parent_id) REFERENCES config_profiles(id) ON DELETE RESTRICT
+);
+
+CREATE INDEX idx_config_profiles_parent_id ON config_profiles(parent_id);
+
+-- Profile assignment and overrides of one device
+CREATE TABLE IF NOT EXISTS device_configs (
+    device_id INTEGER PRIMARY KEY,
+    profile_id INTEGER,                 -- Assigned profile (NULL = defaults)
+
+    -- Overrides (NULL = take the profile value)
+    online_timeout_ms INTEGER,
+    validation_mode TEXT,
+    entry_message TEXT,
+    exit_message TEXT,
+    message_secs INTEGER,
+    volume INTEGER,
+    feedback TEXT,
+
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    CHECK (online_timeout_ms IS NULL OR online_timeout_ms BETWEEN 500 AND 10000),
+    CHECK (validation_mode IS NULL OR validation_mode IN ('O', 'F', 'A', 'S')),
+    CHECK (entry_message IS NULL OR (LENGTH(entry_message) >= 1 AND LENGTH(entry_message) <= 40)),
+    CHECK (exit_message IS NULL OR (LENGTH(exit_message) >= 1 AND LENGTH(exit_message) <= 40)),
+    CHECK (message_secs IS NULL OR message_secs BETWEEN 0 AND 255),
+    CHECK (volume IS NULL OR volume BETWEEN 0 AND 9),
+    CHECK (feedback IS NULL OR feedback IN ('silent', 'beep', 'light', 'beep_and_light')),
+    FOREIGN KEY (device_id) REFERENCES devices(device_id) ON DELETE CASCADE,
+    FOREIGN KEY (profile_id) REFERENCES config_profiles(id) ON DELETE RESTRICT
+);
+
+CREATE INDEX idx_device_configs_profile_id ON device_configs(profile_id);
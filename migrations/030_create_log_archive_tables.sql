@@ -0,0 +1,37 @@
+-- Migration: Access log archive progress
+-- The archiver (feature "s3") uploads the access logs of each UTC day as
+-- compressed segments plus a manifest. Each uploaded segment is recorded
+-- here before the next one is built, so an interrupted run resumes after
+-- the last segment stored. Logs of a day that arrive after it was archived
+-- (late device pulls) have higher IDs and go into new segments.
+
+CREATE TABLE IF NOT EXISTS log_archive_segments (
+    day TEXT NOT NULL,                  -- YYYY-MM-DD, UTC
+    seq INTEGER NOT NULL,               -- 1-based segment number within the day
+
+    object_key TEXT NOT NULL,
+    rows INTEGER NOT NULL,
+    first_id INTEGER NOT NULL,          -- Lowest access_logs.id in the segment
+    last_id INTEGER NOT NULL,           -- Highest access_logs.id in the segment
+    bytes INTEGER NOT NULL,             -- Compressed size
+    sha256 TEXT NOT NULL,               -- Hex SHA-256 of the compressed object
+
+    uploaded_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    PRIMARY KEY (day, seq),
+    CHECK (seq >= 1),
+    CHECK (rows >= 1),
+    CHECK (first_id <= last_id),
+    CHECK (LENGTH(sha256) = 64)
+);
+
+-- Manifest last uploaded for a day, to tell which days need a new one
+CREATE TABLE IF NOT EXISTS log_archive_days (
+    day TEXT PRIMARY KEY,
+
+    manifest_key TEXT NOT NULL,
+    segments INTEGER NOT NULL,          -- Segments listed in the manifest
+    rows INTEGER NOT NULL,
+
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
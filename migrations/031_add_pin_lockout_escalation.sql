@@ -0,0 +1,22 @@
+-- Migration: Escalating PIN lockouts, per device and per user
+-- Each lockout of the same device or user lasts longer than the previous
+-- one (see KeypadLockoutPolicy), so `lockouts` counts the lockouts since
+-- the last correct code. It is forgotten after a quiet period.
+--
+-- Wrong PINs typed for a known user (matricula + PIN) also count against
+-- that user on every device, so moving to another turnstile does not
+-- restart the guessing.
+
+ALTER TABLE keypad_lockouts ADD COLUMN lockouts INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS user_pin_lockouts (
+    user_id INTEGER PRIMARY KEY,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,  -- Wrong PINs since the last success or lockout
+    lockouts INTEGER NOT NULL DEFAULT 0,         -- Lockouts since the last success
+    last_failed_at TEXT,
+    locked_until TEXT,                           -- PIN refused until this time
+
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (failed_attempts >= 0),
+    CHECK (lockouts >= 0)
+);
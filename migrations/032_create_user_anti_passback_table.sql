@@ -0,0 +1,16 @@
+-- Migration: Per-user anti-passback windows
+-- Each device applies its own anti-passback window (see AntiPassbackPolicy).
+-- A row here replaces that window for one user on every device: a longer
+-- one for someone known to lend their badge, a shorter one, or none at all
+-- (window_seconds = 0) for security staff doing rounds.
+
+CREATE TABLE IF NOT EXISTS user_anti_passback (
+    user_id INTEGER PRIMARY KEY,
+    window_seconds INTEGER NOT NULL,   -- Seconds a repeated direction is denied (0 disables)
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (window_seconds >= 0)
+);
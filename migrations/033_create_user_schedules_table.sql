@@ -0,0 +1,17 @@
+-- Migration: Weekly schedules assigned to users
+-- A user with a schedule is only let through in its intervals, in site
+-- local time (see OfflineValidator::with_schedule_offset). Users without a
+-- row are not restricted. Deleting a schedule lifts the restriction of its
+-- users rather than locking them out.
+
+CREATE TABLE IF NOT EXISTS user_schedules (
+    user_id INTEGER PRIMARY KEY,
+    schedule_id INTEGER NOT NULL,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (schedule_id) REFERENCES schedules(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_user_schedules_schedule_id ON user_schedules(schedule_id);
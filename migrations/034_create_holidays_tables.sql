@@ -0,0 +1,24 @@
+-- Migration: Holiday calendar
+-- Holidays are site local dates on which the validator may close the site
+-- (see HolidayPolicy). Users in holiday_exemptions, such as security and
+-- maintenance staff, are still let through; so are users whose schedule
+-- has an exception for the date, which is how a holiday shift is planned.
+
+CREATE TABLE IF NOT EXISTS holidays (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    date TEXT NOT NULL UNIQUE,          -- YYYY-MM-DD, site local date
+    description TEXT NOT NULL,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    CHECK (LENGTH(description) >= 1 AND LENGTH(description) <= 100)
+);
+
+CREATE TABLE IF NOT EXISTS holiday_exemptions (
+    user_id INTEGER PRIMARY KEY,
+
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
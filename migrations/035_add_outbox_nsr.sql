@@ -0,0 +1,43 @@
+-- Migration: NSR numbering of queued events
+-- Every message a device queues for the server gets an NSR (Numero
+-- Sequencial de Registro): a per-device number that increases by one with
+-- each message and is never reused, even after sent messages are purged.
+-- Access events carry it as their event ID, so a server receiving a replay
+-- after an offline period can skip the events it already stored.
+
+ALTER TABLE outbox_messages ADD COLUMN nsr INTEGER NOT NULL DEFAULT 0;
+
+-- Number the messages already queued in queue order
+UPDATE outbox_messages
+SET nsr = (
+    SELECT COUNT(*)
+    FROM outbox_messages AS earlier
+    WHERE earlier.device_id = outbox_messages.device_id
+      AND earlier.id <= outbox_messages.id
+);
+
+CREATE UNIQUE INDEX idx_outbox_messages_nsr ON outbox_messages(device_id, nsr);
+
+-- Last NSR assigned by each device (device side)
+CREATE TABLE IF NOT EXISTS outbox_sequences (
+    device_id INTEGER PRIMARY KEY,
+
+    last_nsr INTEGER NOT NULL DEFAULT 0,
+
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (last_nsr >= 0)
+);
+
+INSERT INTO outbox_sequences (device_id, last_nsr)
+SELECT device_id, MAX(nsr) FROM outbox_messages GROUP BY device_id;
+
+-- Last NSR stored from each device's pushed events (server side)
+CREATE TABLE IF NOT EXISTS event_receipts (
+    device_id INTEGER PRIMARY KEY,
+
+    last_nsr INTEGER NOT NULL DEFAULT 0,
+    received_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+
+    CHECK (device_id >= 1 AND device_id <= 99),
+    CHECK (last_nsr >= 0)
+);
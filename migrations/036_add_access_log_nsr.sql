@@ -0,0 +1,22 @@
+-- Migration: NSR (Numero Sequencial de Registro) of access logs
+-- Henry equipment numbers every event it records, and servers collect
+-- events by NSR range and spot missing ones by the gaps. A device numbers
+-- the access logs it writes from its NSR counter in outbox_sequences, the
+-- same counter that numbers its queued events, so an access carries one
+-- NSR in the log, in its ER event and in its turnstile notifications.
+-- Logs a server stores from pushed events keep the device's NSR.
+
+ALTER TABLE access_logs ADD COLUMN nsr INTEGER;                 -- NULL if the log was never numbered
+
+-- Logs already queued keep the NSR of their event
+UPDATE access_logs
+SET nsr = (
+    SELECT outbox_messages.nsr
+    FROM outbox_messages
+    WHERE outbox_messages.access_log_id = access_logs.id
+      AND outbox_messages.device_id = access_logs.device_id
+)
+WHERE id IN (SELECT access_log_id FROM outbox_messages);
+
+CREATE UNIQUE INDEX idx_access_logs_nsr ON access_logs(device_id, nsr)
+    WHERE nsr IS NOT NULL;
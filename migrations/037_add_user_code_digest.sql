@@ -0,0 +1,16 @@
+-- Migration: indexed lookup of keypad codes
+-- Codes are stored as salted argon2 hashes, which cannot be searched: a
+-- PIN-only entry had to verify the typed code against every keypad user,
+-- and assigning a code against every user with one. Each code now also
+-- gets a deterministic digest (HMAC-SHA256 keyed with the site's PIN
+-- digest key, a secret kept outside the database), looked up through a
+-- UNIQUE index that also keeps two users from sharing a code. The argon2
+-- hash still confirms the code of the one matching row.
+--
+-- Hashed codes written before this migration have no digest; they get one
+-- the next time they are verified.
+
+ALTER TABLE users ADD COLUMN codigo_digest TEXT;                -- HMAC of the plaintext code
+
+CREATE UNIQUE INDEX idx_users_codigo_digest ON users(codigo_digest)
+    WHERE codigo_digest IS NOT NULL;
@@ -0,0 +1,49 @@
+#!/usr/bin/env bash
+# Regenerate the sqlx offline query metadata in .sqlx/
+#
+# The query!/query_as! macros check every statement against the schema at
+# compile time. Builds read the schema from the committed .sqlx/ directory,
+# so run this after adding a migration or changing a checked query:
+#
+#   make sqlx-prepare   # rewrite .sqlx/
+#   make sqlx-check     # fail if .sqlx/ is out of date
+#
+# Builds a scratch database from migrations/ with the sqlite3 shell and
+# lets the macros write the metadata while compiling against it, which is
+# what `cargo sqlx prepare` does.
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+check=false
+if [[ "${1:-}" == "--check" ]]; then
+    check=true
+fi
+
+scratch="$(mktemp -d)"
+trap 'rm -rf "$scratch"' EXIT
+
+db="$scratch/turnkey.db"
+for migration in migrations/*.sql; do
+    sqlite3 "$db" < "$migration" > /dev/null
+done
+
+out="$scratch/sqlx"
+mkdir "$out"
+
+# Force the macros to expand again
+touch crates/turnkey-storage/src/lib.rs
+DATABASE_URL="sqlite:$db" SQLX_OFFLINE=false SQLX_OFFLINE_DIR="$out" \
+    cargo check -p turnkey-storage --all-targets --all-features
+
+if $check; then
+    if ! diff -r -q .sqlx "$out"; then
+        echo "error: .sqlx/ is out of date, run 'make sqlx-prepare'" >&2
+        exit 1
+    fi
+    echo ".sqlx/ is up to date ($(ls "$out" | wc -l) queries)"
+else
+    rm -rf .sqlx
+    cp -r "$out" .sqlx
+    echo "Query metadata written to .sqlx/ ($(ls .sqlx | wc -l) queries)"
+fi